@@ -1,10 +1,23 @@
 // Agent Commands - Tauri IPC handlers for AI agent tool execution
 
-use crate::services::agent_executor::{AgentExecutor, ToolResult};
+use crate::services::agent_executor::{
+    AgentExecutor, ChangeKind, PendingChange, ToolExecution, ToolResult,
+};
+use crate::services::agent_guard::TOOL_CALL_GUARD;
+use crate::services::agent_memory::{AgentMemoryStore, Memory};
+use crate::services::change_staging::{PendingChangeReview, PendingChangeStore};
+use crate::services::custom_tools::CustomToolRegistry;
+use crate::services::document_chunks::resolve_content;
+use crate::services::execution_journal::{
+    ExecutionJournal, ExecutionRecord, FileChange, FileOperation,
+};
+use crate::services::tool_audit_log::{ExecutionHistoryQuery, ToolAuditLog};
+use crate::AppState;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::path::PathBuf;
-use tracing::debug;
+use std::path::{Path, PathBuf};
+use tauri::State;
+use tracing::{debug, warn};
 
 // ============================================================================
 // Command Input Types
@@ -16,6 +29,32 @@ pub struct ExecuteToolRequest {
     pub workspace_root: String,
     pub tool_name: String,
     pub arguments: Value,
+    /// Stage edits and deletions for approval instead of applying them
+    #[serde(default)]
+    pub require_confirmation: bool,
+    /// The user has allowed the agent to fetch web pages
+    #[serde(default)]
+    pub allow_web_fetch: bool,
+    /// Conversation the agent run belongs to, for the audit log
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+    /// Agent turn the call belongs to; tool-call limits apply per turn
+    #[serde(default)]
+    pub turn_id: Option<String>,
+}
+
+impl ExecuteToolRequest {
+    /// Key the call is budgeted under
+    fn turn_key(&self) -> String {
+        match &self.turn_id {
+            Some(turn_id) => format!("turn:{}", turn_id),
+            None => format!(
+                "workspace:{}:{}",
+                self.workspace_root,
+                self.conversation_id.as_deref().unwrap_or("")
+            ),
+        }
+    }
 }
 
 // ============================================================================
@@ -24,13 +63,27 @@ pub struct ExecuteToolRequest {
 
 /// Execute a single tool
 #[tauri::command]
-pub async fn agent_execute_tool(request: ExecuteToolRequest) -> Result<ToolResult, String> {
+pub async fn agent_execute_tool(
+    request: ExecuteToolRequest,
+    state: State<'_, AppState>,
+) -> Result<ToolResult, String> {
     debug!(
         "agent_execute_tool: {} in {}",
         request.tool_name, request.workspace_root
     );
 
-    let executor = AgentExecutor::new(PathBuf::from(&request.workspace_root));
+    if let Err(violation) =
+        TOOL_CALL_GUARD.check(&request.turn_key(), &request.tool_name, &request.arguments)
+    {
+        warn!("Refusing {}: {}", request.tool_name, violation);
+        return Err(violation.to_string());
+    }
+
+    let executor = AgentExecutor::new(PathBuf::from(&request.workspace_root))
+        .with_confirmation(request.require_confirmation)
+        .with_web_fetch(request.allow_web_fetch)
+        .with_plugins(state.plugins.clone())
+        .with_conversation(request.conversation_id);
     let result = executor
         .execute_tool(&request.tool_name, request.arguments)
         .await;
@@ -38,44 +91,257 @@ pub async fn agent_execute_tool(request: ExecuteToolRequest) -> Result<ToolResul
     Ok(result)
 }
 
-/// List available tools
+/// Reset the tool-call budget of a finished agent turn
+#[tauri::command]
+pub fn agent_end_turn(turn_id: String) {
+    TOOL_CALL_GUARD.end_turn(&format!("turn:{}", turn_id));
+}
+
+/// List changes staged by agent tools that are waiting for confirmation
+#[tauri::command]
+pub fn agent_list_pending_changes(
+    workspace_root: String,
+) -> Result<Vec<PendingChangeReview>, String> {
+    PendingChangeStore::new(Path::new(&workspace_root)).list()
+}
+
+/// Apply a staged change
+#[tauri::command]
+pub async fn agent_approve_change(
+    workspace_root: String,
+    change_id: String,
+    state: State<'_, AppState>,
+) -> Result<PendingChange, String> {
+    debug!("agent_approve_change: {}", change_id);
+    let root = Path::new(&workspace_root);
+    let applied = PendingChangeStore::new(root).approve(&change_id)?;
+
+    if let (ChangeKind::Edit, Some(attribution)) =
+        (&applied.change.kind, &applied.change.attribution)
+    {
+        let before = applied.previous_content.as_deref().and_then(|content| {
+            let mut document: Value = serde_json::from_str(content).ok()?;
+            resolve_content(&root.join(&applied.change.path), &mut document);
+            Some(document)
+        });
+        let manager = state
+            .workspace_registry
+            .write()
+            .await
+            .get_or_create(&workspace_root)
+            .await;
+        let checkpointed = match manager {
+            Ok(manager) => manager
+                .checkpoint_ai_edit(&applied.change.path, before.as_ref(), attribution)
+                .await
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        if let Err(e) = checkpointed {
+            warn!("Failed to checkpoint approved change {}: {}", change_id, e);
+        }
+    }
+
+    let change = FileChange {
+        path: applied.change.path.clone(),
+        operation: match applied.change.kind {
+            ChangeKind::Edit => FileOperation::Modify,
+            ChangeKind::Delete => FileOperation::Delete,
+        },
+        previous_path: None,
+        old_content: applied.previous_content,
+        new_content: applied.written_content,
+    };
+    if let Err(e) = ExecutionJournal::new(root)
+        .record("agent_approve_change", vec![change])
+        .await
+    {
+        warn!("Failed to journal approved change {}: {}", change_id, e);
+    }
+
+    Ok(applied.change)
+}
+
+/// Discard a staged change
+#[tauri::command]
+pub fn agent_reject_change(
+    workspace_root: String,
+    change_id: String,
+) -> Result<PendingChange, String> {
+    debug!("agent_reject_change: {}", change_id);
+    PendingChangeStore::new(Path::new(&workspace_root)).reject(&change_id)
+}
+
+/// List agent tool executions that changed files, newest first
+#[tauri::command]
+pub fn agent_list_executions(workspace_root: String) -> Result<Vec<ExecutionRecord>, String> {
+    ExecutionJournal::new(Path::new(&workspace_root)).list()
+}
+
+/// Revert the file changes made by an agent tool execution
+#[tauri::command]
+pub async fn agent_undo_execution(
+    workspace_root: String,
+    execution_id: String,
+    force: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<ExecutionRecord, String> {
+    debug!("agent_undo_execution: {}", execution_id);
+    let manager = state
+        .workspace_registry
+        .write()
+        .await
+        .get_or_create(&workspace_root)
+        .await
+        .map_err(|e| e.to_string())?;
+    manager.init().await.map_err(|e| e.to_string())?;
+
+    ExecutionJournal::new(Path::new(&workspace_root))
+        .undo(&execution_id, force.unwrap_or(false), &manager)
+        .await
+}
+
+/// Query the workspace's log of agent tool executions, newest first
+#[tauri::command]
+pub fn agent_get_execution_history(
+    workspace_root: String,
+    query: Option<ExecutionHistoryQuery>,
+) -> Result<Vec<ToolExecution>, String> {
+    ToolAuditLog::new(Path::new(&workspace_root)).query(&query.unwrap_or_default())
+}
+
+/// List the notes the agent has saved for a workspace
 #[tauri::command]
-pub fn agent_list_tools() -> Vec<ToolInfo> {
+pub fn agent_list_memories(workspace_root: String) -> Result<Vec<Memory>, String> {
+    AgentMemoryStore::new(Path::new(&workspace_root)).list()
+}
+
+/// Delete one of the agent's saved notes
+#[tauri::command]
+pub fn agent_forget_memory(workspace_root: String, key: String) -> Result<bool, String> {
+    AgentMemoryStore::new(Path::new(&workspace_root)).forget(&key)
+}
+
+/// List available tools: the built-in ones, enabled custom tools, then tools
+/// from the workspace's enabled plugins
+#[tauri::command]
+pub fn agent_list_tools(
+    workspace_root: Option<String>,
+    state: State<'_, AppState>,
+) -> Vec<ToolInfo> {
+    let mut tools = builtin_tools();
+    match CustomToolRegistry::default().list() {
+        Ok(custom) => tools.extend(custom.into_iter().filter(|t| t.enabled).map(|t| ToolInfo {
+            name: t.name,
+            description: t.description,
+            is_destructive: t.is_destructive,
+            parameters: Some(t.parameters),
+        })),
+        Err(e) => warn!("Failed to load custom tools: {}", e),
+    }
+    if let Some(workspace_root) = workspace_root {
+        tools.extend(
+            state
+                .plugins
+                .tools(&workspace_root)
+                .into_iter()
+                .map(|t| ToolInfo {
+                    name: t.name,
+                    description: t.description,
+                    is_destructive: t.is_destructive,
+                    parameters: Some(t.parameters),
+                }),
+        );
+    }
+    tools
+}
+
+fn builtin_tools() -> Vec<ToolInfo> {
     vec![
         ToolInfo {
             name: "list_documents".to_string(),
             description: "List all documents and folders in a directory".to_string(),
             is_destructive: false,
+            parameters: None,
         },
         ToolInfo {
             name: "read_document".to_string(),
             description: "Read the full content of a document".to_string(),
             is_destructive: false,
+            parameters: None,
         },
         ToolInfo {
             name: "create_document".to_string(),
             description: "Create a new document with the specified content".to_string(),
             is_destructive: false,
+            parameters: None,
         },
         ToolInfo {
             name: "edit_document".to_string(),
             description: "Edit an existing document".to_string(),
             is_destructive: false,
+            parameters: None,
         },
         ToolInfo {
             name: "move_document".to_string(),
             description: "Move or rename a document".to_string(),
             is_destructive: false,
+            parameters: None,
         },
         ToolInfo {
             name: "delete_document".to_string(),
             description: "Delete a document (moves to trash)".to_string(),
             is_destructive: true,
+            parameters: None,
         },
         ToolInfo {
             name: "search_documents".to_string(),
             description: "Search for documents containing specific text".to_string(),
             is_destructive: false,
+            parameters: None,
+        },
+        ToolInfo {
+            name: "search_replace".to_string(),
+            description: "Find and replace text across documents (staged for review)".to_string(),
+            is_destructive: false,
+            parameters: None,
+        },
+        ToolInfo {
+            name: "create_folder".to_string(),
+            description: "Create a new folder".to_string(),
+            is_destructive: false,
+            parameters: None,
+        },
+        ToolInfo {
+            name: "rename_document".to_string(),
+            description: "Rename a document or folder without moving it".to_string(),
+            is_destructive: false,
+            parameters: None,
+        },
+        ToolInfo {
+            name: "duplicate_document".to_string(),
+            description: "Make a copy of a document in the same folder".to_string(),
+            is_destructive: false,
+            parameters: None,
+        },
+        ToolInfo {
+            name: "fetch_url".to_string(),
+            description: "Fetch a web page as readable text (requires web access)".to_string(),
+            is_destructive: false,
+            parameters: None,
+        },
+        ToolInfo {
+            name: "remember".to_string(),
+            description: "Save a note about the user's preferences for later conversations"
+                .to_string(),
+            is_destructive: false,
+            parameters: None,
+        },
+        ToolInfo {
+            name: "recall".to_string(),
+            description: "Look up notes saved with remember".to_string(),
+            is_destructive: false,
+            parameters: None,
         },
     ]
 }
@@ -86,4 +352,8 @@ pub struct ToolInfo {
     pub name: String,
     pub description: String,
     pub is_destructive: bool,
+    /// JSON schema for the arguments of custom tools; the frontend defines
+    /// the built-in tools' schemas itself
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<Value>,
 }
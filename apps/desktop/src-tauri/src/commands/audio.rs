@@ -0,0 +1,191 @@
+// Audio commands - Attach audio recordings and transcribe them
+
+use crate::services::audio_manager::{self, AudioManager};
+use crate::services::audio_transcription::{
+    self, TranscribeOptions, Transcript, TranscriptionProgress,
+};
+use crate::services::rag_indexer::RAG_INDEXER;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter};
+use tracing::error;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioUploadResult {
+    #[serde(rename = "refId")]
+    pub ref_id: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+impl From<Result<String, String>> for AudioUploadResult {
+    fn from(result: Result<String, String>) -> Self {
+        match result {
+            Ok(ref_id) => Self {
+                ref_id,
+                success: true,
+                error: None,
+            },
+            Err(e) => Self {
+                ref_id: String::new(),
+                success: false,
+                error: Some(e),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscriptionProgressEvent {
+    pub ref_id: String,
+    #[serde(flatten)]
+    pub progress: TranscriptionProgress,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscriptionResult {
+    /// Workspace-relative path of the transcript document
+    pub transcript_path: String,
+    pub transcript: Transcript,
+}
+
+/// Save an audio recording from a data URL, e.g. a memo recorded in the app
+#[tauri::command]
+pub async fn workspace_save_audio(
+    workspace_root: String,
+    data_url: String,
+) -> Result<AudioUploadResult, String> {
+    let manager = AudioManager::new(Path::new(&workspace_root));
+    let result = manager.store_audio(&data_url).await;
+    Ok(result.map_err(|e| e.to_string()).into())
+}
+
+/// Copy an audio file from disk into the workspace
+#[tauri::command]
+pub async fn workspace_import_audio(
+    workspace_root: String,
+    file_path: String,
+) -> Result<AudioUploadResult, String> {
+    let manager = AudioManager::new(Path::new(&workspace_root));
+    let result = manager.import_audio_file(Path::new(&file_path)).await;
+    Ok(result.map_err(|e| e.to_string()).into())
+}
+
+/// Get an audio recording as a data URL
+#[tauri::command]
+pub async fn workspace_get_audio(workspace_root: String, ref_id: String) -> Result<String, String> {
+    let manager = AudioManager::new(Path::new(&workspace_root));
+    manager
+        .get_audio_data_url(&ref_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Delete an audio recording
+#[tauri::command]
+pub async fn workspace_delete_audio(workspace_root: String, ref_id: String) -> Result<(), String> {
+    let manager = AudioManager::new(Path::new(&workspace_root));
+    manager.delete(&ref_id).await.map_err(|e| e.to_string())
+}
+
+/// List all audio recordings in the workspace
+#[tauri::command]
+pub async fn workspace_list_audio(workspace_root: String) -> Result<Vec<String>, String> {
+    let manager = AudioManager::new(Path::new(&workspace_root));
+    manager.list_audio().await.map_err(|e| e.to_string())
+}
+
+/// Whether a file can be attached as audio
+#[tauri::command]
+pub fn audio_is_supported(file_path: String) -> bool {
+    audio_manager::is_supported(Path::new(&file_path))
+}
+
+/// Transcribe a recording into a Markdown document next to
+/// `document_path` (the document it's attached to, relative to the
+/// workspace) or at the workspace root. Progress is emitted as
+/// "audio:transcription:progress" events.
+#[tauri::command]
+pub async fn audio_transcribe(
+    app: AppHandle,
+    workspace_root: String,
+    ref_id: String,
+    document_path: Option<String>,
+    title: Option<String>,
+    options: Option<TranscribeOptions>,
+    auth_token: Option<String>,
+) -> Result<TranscriptionResult, String> {
+    let root = PathBuf::from(&workspace_root);
+    let audio_path = AudioManager::new(&root)
+        .audio_path(&ref_id)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let event_ref = ref_id.clone();
+    let transcript = audio_transcription::transcribe(
+        &audio_path,
+        &options.unwrap_or_default(),
+        auth_token.as_deref(),
+        move |progress| {
+            let event = TranscriptionProgressEvent {
+                ref_id: event_ref.clone(),
+                progress,
+            };
+            if let Err(e) = app.emit("audio:transcription:progress", &event) {
+                error!("Failed to emit transcription progress: {}", e);
+            }
+        },
+    )
+    .await?;
+
+    let document = document_path.as_deref().map(Path::new);
+    let source_name = document
+        .and_then(|d| d.file_stem())
+        .map(|s| s.to_string_lossy().to_string());
+    let title = title
+        .filter(|t| !t.trim().is_empty())
+        .or_else(|| source_name.clone())
+        .unwrap_or_else(|| "Recording".to_string());
+
+    let folder = document
+        .and_then(Path::parent)
+        .map(|p| root.join(p))
+        .unwrap_or_else(|| root.clone());
+    let transcript_path = unique_path(&folder, &format!("{} (transcript)", title), "md");
+    let markdown = audio_transcription::transcript_markdown(
+        &title,
+        &ref_id,
+        source_name.as_deref(),
+        &transcript,
+    );
+    tokio::fs::create_dir_all(&folder)
+        .await
+        .map_err(|e| format!("Failed to create folder: {}", e))?;
+    tokio::fs::write(&transcript_path, markdown)
+        .await
+        .map_err(|e| format!("Failed to write transcript: {}", e))?;
+    RAG_INDEXER.file_changed(transcript_path.clone(), false);
+
+    let relative = transcript_path
+        .strip_prefix(&root)
+        .unwrap_or(&transcript_path)
+        .to_string_lossy()
+        .replace('\\', "/");
+    Ok(TranscriptionResult {
+        transcript_path: relative,
+        transcript,
+    })
+}
+
+/// `folder/stem.ext`, or `folder/stem 2.ext` etc. if that exists
+fn unique_path(folder: &Path, stem: &str, extension: &str) -> PathBuf {
+    let mut path = folder.join(format!("{}.{}", stem, extension));
+    let mut n = 2;
+    while path.exists() {
+        path = folder.join(format!("{} {}.{}", stem, n, extension));
+        n += 1;
+    }
+    path
+}
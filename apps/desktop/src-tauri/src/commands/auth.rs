@@ -1,14 +1,22 @@
 // Auth Commands - Tauri IPC handlers for authentication
 
 use crate::services::auth_service::{
-    CheckoutSession, PortalSession, Price, Quota, Subscription, User, AUTH_SERVICE,
+    Account, CheckoutSession, PortalSession, Price, Quota, Subscription, User, AUTH_SERVICE,
 };
+use crate::services::deep_link::AUTH_CALLBACK_URL;
 use serde::Serialize;
-use std::io::{BufRead, BufReader, Write};
-use std::net::TcpListener;
-use tauri::{AppHandle, Emitter, Manager};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
 use tracing::{debug, error, info};
 
+/// Longest the token refresher sleeps between checks. Sleep doesn't advance
+/// while the machine is suspended, so this bounds how late a refresh can be
+/// after waking up.
+const REFRESH_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Wait before retrying a refresh that failed for network reasons
+const REFRESH_RETRY_DELAY: Duration = Duration::from_secs(15);
+
 // ============================================================================
 // Event Types
 // ============================================================================
@@ -20,6 +28,67 @@ pub struct AuthStateChangedEvent {
     pub user: Option<User>,
 }
 
+// ============================================================================
+// Background Refresh
+// ============================================================================
+
+/// Refresh the access token shortly before it expires, so the first request
+/// after the app sits idle doesn't wait on a refresh. While signed in from
+/// the offline cache, retries until the server can re-validate the session.
+/// Emits "auth:state-changed" with the refreshed user, or as unauthenticated
+/// when the session can no longer be refreshed.
+pub fn start_token_refresh_worker<R: Runtime>(app: AppHandle<R>) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let wait = match AUTH_SERVICE.seconds_until_refresh() {
+                _ if AUTH_SERVICE.is_offline() => REFRESH_RETRY_DELAY,
+                Some(secs) if secs > 0 => Duration::from_secs(secs as u64),
+                Some(_) => Duration::ZERO,
+                None => REFRESH_CHECK_INTERVAL,
+            };
+            tokio::time::sleep(wait.min(REFRESH_CHECK_INTERVAL)).await;
+
+            if !AUTH_SERVICE.is_authenticated() {
+                continue;
+            }
+            let result = if AUTH_SERVICE.is_offline() {
+                debug!("Re-validating offline session");
+                AUTH_SERVICE.revalidate().await
+            } else if AUTH_SERVICE
+                .seconds_until_refresh()
+                .is_some_and(|secs| secs <= 0)
+            {
+                debug!("Refreshing access token before expiry");
+                AUTH_SERVICE.refresh_access_token().await
+            } else {
+                continue;
+            };
+
+            let event = match result {
+                Ok(response) => AuthStateChangedEvent {
+                    state: "authenticated".to_string(),
+                    user: Some(response.user),
+                },
+                Err(e) if e.code == "NETWORK_ERROR" && AUTH_SERVICE.is_authenticated() => {
+                    debug!("Token refresh failed, will retry: {}", e);
+                    continue;
+                }
+                Err(e) => {
+                    info!("Session could not be refreshed: {}", e);
+                    AuthStateChangedEvent {
+                        state: "unauthenticated".to_string(),
+                        user: None,
+                    }
+                }
+            };
+
+            if let Err(e) = app.emit("auth:state-changed", &event) {
+                error!("Failed to emit auth state changed event: {}", e);
+            }
+        }
+    });
+}
+
 // ============================================================================
 // Commands
 // ============================================================================
@@ -72,166 +141,49 @@ pub async fn auth_logout() -> Result<(), String> {
     AUTH_SERVICE.logout().await.map_err(|e| e.to_string())
 }
 
-/// Start Google OAuth flow with local callback server
+/// Start Google OAuth flow in the browser. The browser is sent back to
+/// midlight://auth/callback, which the deep link handler completes.
 #[tauri::command]
-pub async fn auth_login_with_google(app: AppHandle) -> Result<(), String> {
+pub async fn auth_login_with_google() -> Result<(), String> {
     debug!("auth_login_with_google command");
 
-    // Start a TCP listener on a random available port
-    let listener = TcpListener::bind("127.0.0.1:0").map_err(|e| {
-        error!("Failed to bind TCP listener: {}", e);
-        format!("Failed to start OAuth callback server: {}", e)
-    })?;
-
-    let port = listener
-        .local_addr()
-        .map_err(|e| {
-            error!("Failed to get local address: {}", e);
-            format!("Failed to get callback port: {}", e)
-        })?
-        .port();
-
-    info!("OAuth callback server listening on port {}", port);
-
-    // Build OAuth URL with callback port
-    let url = AUTH_SERVICE.get_oauth_url(Some(port));
-
-    // Open browser
+    let url = AUTH_SERVICE
+        .begin_oauth_login(AUTH_CALLBACK_URL)
+        .map_err(|e| e.to_string())?;
     if let Err(e) = open::that(&url) {
         error!("Failed to open OAuth URL: {}", e);
         return Err(format!("Failed to open browser: {}", e));
     }
 
-    // Handle callback in background
-    tauri::async_runtime::spawn(async move {
-        // Wait for a connection (with timeout)
-        listener.set_nonblocking(false).ok();
-
-        match listener.accept() {
-            Ok((mut stream, _)) => {
-                // Read the HTTP request
-                let mut reader = BufReader::new(&stream);
-                let mut request_line = String::new();
-
-                if reader.read_line(&mut request_line).is_err() {
-                    error!("Failed to read OAuth callback request");
-                    return;
-                }
-
-                debug!("OAuth callback request: {}", request_line.trim());
-
-                // Parse the code from the request URL
-                // Format: GET /auth/callback?code=XXX HTTP/1.1
-                let code = request_line
-                    .split_whitespace()
-                    .nth(1) // Get the path
-                    .and_then(|path| {
-                        path.split('?')
-                            .nth(1) // Get query string
-                            .and_then(|query| {
-                                query
-                                    .split('&')
-                                    .find(|param| param.starts_with("code="))
-                                    .map(|param| param.trim_start_matches("code=").to_string())
-                            })
-                    });
-
-                // Send response to browser
-                let response_body = if code.is_some() {
-                    r#"<!DOCTYPE html>
-<html>
-<head>
-    <title>Sign In Successful</title>
-    <style>
-        body { font-family: -apple-system, BlinkMacSystemFont, sans-serif; display: flex; justify-content: center; align-items: center; height: 100vh; margin: 0; background: #f5f5f5; }
-        .container { text-align: center; padding: 40px; background: white; border-radius: 12px; box-shadow: 0 2px 8px rgba(0,0,0,0.1); }
-        h1 { color: #333; margin-bottom: 8px; }
-        p { color: #666; }
-    </style>
-</head>
-<body>
-    <div class="container">
-        <h1>Sign In Successful!</h1>
-        <p>You can close this window and return to Midlight.</p>
-    </div>
-</body>
-</html>"#
-                } else {
-                    r#"<!DOCTYPE html>
-<html>
-<head>
-    <title>Sign In Failed</title>
-    <style>
-        body { font-family: -apple-system, BlinkMacSystemFont, sans-serif; display: flex; justify-content: center; align-items: center; height: 100vh; margin: 0; background: #f5f5f5; }
-        .container { text-align: center; padding: 40px; background: white; border-radius: 12px; box-shadow: 0 2px 8px rgba(0,0,0,0.1); }
-        h1 { color: #c00; margin-bottom: 8px; }
-        p { color: #666; }
-    </style>
-</head>
-<body>
-    <div class="container">
-        <h1>Sign In Failed</h1>
-        <p>Something went wrong. Please try again.</p>
-    </div>
-</body>
-</html>"#
-                };
-
-                let response = format!(
-                    "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
-                    response_body.len(),
-                    response_body
-                );
-
-                if let Err(e) = stream.write_all(response.as_bytes()) {
-                    error!("Failed to send OAuth callback response: {}", e);
-                }
-
-                // Exchange code for tokens
-                if let Some(code) = code {
-                    match AUTH_SERVICE.exchange_oauth_code(&code).await {
-                        Ok(response) => {
-                            info!("OAuth exchange successful");
-
-                            // Emit auth state changed event
-                            let event = AuthStateChangedEvent {
-                                state: "authenticated".to_string(),
-                                user: Some(response.user),
-                            };
-
-                            if let Err(e) = app.emit("auth:state-changed", &event) {
-                                error!("Failed to emit auth state changed event: {}", e);
-                            }
-
-                            // Bring the app window to focus
-                            if let Some(window) = app.get_webview_window("main") {
-                                let _ = window.set_focus();
-                            }
-                        }
-                        Err(e) => {
-                            error!("OAuth code exchange failed: {}", e);
-                        }
-                    }
-                }
-            }
-            Err(e) => {
-                error!("Failed to accept OAuth callback connection: {}", e);
-            }
-        }
-    });
-
     Ok(())
 }
 
 /// Handle OAuth callback (called when deep link received)
 #[tauri::command]
-pub async fn auth_handle_oauth_callback(app: AppHandle, code: String) -> Result<User, String> {
+pub async fn auth_handle_oauth_callback(
+    app: AppHandle,
+    code: String,
+    state: Option<String>,
+) -> Result<User, String> {
     debug!("auth_handle_oauth_callback command");
+    complete_oauth(&app, &code, state.as_deref()).await
+}
 
+/// Exchange an OAuth code, tell the frontend, and bring the app forward.
+/// The callback's `state` has to match the sign-in this app started.
+pub(crate) async fn complete_oauth<R: Runtime>(
+    app: &AppHandle<R>,
+    code: &str,
+    state: Option<&str>,
+) -> Result<User, String> {
+    AUTH_SERVICE
+        .verify_oauth_state(state)
+        .map_err(|e| e.to_string())?;
     let response = AUTH_SERVICE
-        .exchange_oauth_code(&code)
+        .exchange_oauth_code(code)
         .await
         .map_err(|e| e.to_string())?;
+    info!("OAuth exchange successful");
 
     // Emit auth state changed event
     let event = AuthStateChangedEvent {
@@ -243,9 +195,38 @@ pub async fn auth_handle_oauth_callback(app: AppHandle, code: String) -> Result<
         error!("Failed to emit auth state changed event: {}", e);
     }
 
+    // Bring the app window to focus
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.set_focus();
+    }
+
     Ok(response.user)
 }
 
+/// List signed-in accounts
+#[tauri::command]
+pub async fn auth_list_accounts() -> Result<Vec<Account>, String> {
+    debug!("auth_list_accounts command");
+    Ok(AUTH_SERVICE.list_accounts())
+}
+
+/// Make another signed-in account the active one
+#[tauri::command]
+pub async fn auth_switch_account(app: AppHandle, account_id: i64) -> Result<User, String> {
+    debug!("auth_switch_account command: {}", account_id);
+
+    let result = AUTH_SERVICE.switch_account(account_id).await;
+    let event = AuthStateChangedEvent {
+        state: AUTH_SERVICE.get_auth_state().to_string(),
+        user: AUTH_SERVICE.get_user(),
+    };
+    if let Err(e) = app.emit("auth:state-changed", &event) {
+        error!("Failed to emit auth state changed event: {}", e);
+    }
+
+    result.map_err(|e| e.to_string())
+}
+
 /// Get current user
 #[tauri::command]
 pub async fn auth_get_user() -> Result<Option<User>, String> {
@@ -279,6 +260,13 @@ pub async fn auth_is_authenticated() -> bool {
     AUTH_SERVICE.is_authenticated()
 }
 
+/// Check if the user is signed in from the offline cache
+#[tauri::command]
+pub async fn auth_is_offline() -> bool {
+    debug!("auth_is_offline command");
+    AUTH_SERVICE.is_offline()
+}
+
 /// Get current auth state
 #[tauri::command]
 pub async fn auth_get_state() -> String {
@@ -0,0 +1,208 @@
+// Automation commands - Run workspace automation rules on events, edit the
+// rules, enable them on this machine and trace what an event would do
+
+use crate::commands::fs::move_path;
+use crate::services::automations::{
+    create_from_template, evaluate, move_destination, send_webhook, AutomationAction,
+    AutomationEvent, AutomationRule, AutomationStore, RuleTrace,
+};
+use crate::services::web_fetch;
+use chrono::Local;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Runtime};
+use tracing::{debug, info, warn};
+
+/// Outcome of one rule that fired, sent to the frontend as "automations:ran"
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutomationRun {
+    pub workspace_root: String,
+    pub rule_id: String,
+    pub event: AutomationEvent,
+    pub actions_run: usize,
+    pub error: Option<String>,
+}
+
+/// Run the workspace's rules for some events in the background, if the user
+/// has enabled them. Each rule runs its actions in order and stops at the
+/// first one that fails.
+pub fn run_automations<R: Runtime>(
+    app: &AppHandle<R>,
+    workspace_root: &str,
+    events: Vec<AutomationEvent>,
+) {
+    let store = AutomationStore::new(Path::new(workspace_root));
+    if events.is_empty() || !store.is_configured() {
+        return;
+    }
+
+    let app = app.clone();
+    let workspace_root = workspace_root.to_string();
+    tauri::async_runtime::spawn(async move {
+        let rules = match store.load_enabled() {
+            Ok(Some(rules)) => rules,
+            Ok(None) => {
+                debug!("Automations in {} aren't enabled", workspace_root);
+                return;
+            }
+            Err(e) => {
+                warn!("Skipping automations: {}", e);
+                return;
+            }
+        };
+
+        let today = Local::now().date_naive();
+        for event in events {
+            for trace in evaluate(&rules, &event, today) {
+                if !trace.matched {
+                    continue;
+                }
+                info!("Automation {} fired for {}", trace.rule_id, event.path());
+                let run = run_rule(&app, &workspace_root, &trace, &event).await;
+                let _ = app.emit("automations:ran", &run);
+            }
+        }
+    });
+}
+
+async fn run_rule<R: Runtime>(
+    app: &AppHandle<R>,
+    workspace_root: &str,
+    trace: &RuleTrace,
+    event: &AutomationEvent,
+) -> AutomationRun {
+    let mut run = AutomationRun {
+        workspace_root: workspace_root.to_string(),
+        rule_id: trace.rule_id.clone(),
+        event: event.clone(),
+        actions_run: 0,
+        error: None,
+    };
+    // A move changes where later actions find the document
+    let mut document = event.path().to_string();
+
+    for action in &trace.actions {
+        let result = match action {
+            AutomationAction::Webhook { url, headers } => match web_fetch::client() {
+                Ok(client) => {
+                    send_webhook(&client, url, headers, &trace.rule_id, workspace_root, event).await
+                }
+                Err(e) => Err(e),
+            },
+            AutomationAction::Move { to } => {
+                move_document(app, workspace_root, &document, to).map(|moved| document = moved)
+            }
+            AutomationAction::Template { template, path } => create_from_template(
+                Path::new(workspace_root),
+                template,
+                path,
+                Local::now().date_naive(),
+            )
+            .map(|_| ()),
+        };
+
+        if let Err(e) = result {
+            warn!("Automation {} failed: {}", trace.rule_id, e);
+            run.error = Some(e);
+            break;
+        }
+        run.actions_run += 1;
+    }
+    run
+}
+
+/// Move a document into a folder, returning its new relative path
+fn move_document<R: Runtime>(
+    app: &AppHandle<R>,
+    workspace_root: &str,
+    document: &str,
+    folder: &str,
+) -> Result<String, String> {
+    let destination = move_destination(document, folder)?;
+    if destination == document {
+        return Ok(destination);
+    }
+
+    let root = Path::new(workspace_root);
+    let target = root.join(&destination);
+    if target.exists() {
+        return Err(format!("{} already exists", destination));
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create folder: {}", e))?;
+    }
+    move_path(
+        app,
+        &root.join(document).to_string_lossy(),
+        &target.to_string_lossy(),
+        true,
+    )?;
+    Ok(destination)
+}
+
+/// Fire import_finished for an import into `dest`, if it's inside a workspace
+pub fn import_finished<R: Runtime>(app: &AppHandle<R>, dest: &Path, documents: usize) {
+    let root: Option<PathBuf> = dest
+        .ancestors()
+        .find(|dir| dir.join(".midlight").is_dir())
+        .map(Path::to_path_buf);
+    let Some(root) = root else {
+        return;
+    };
+
+    let folder = dest
+        .strip_prefix(&root)
+        .map(|p| p.to_string_lossy().replace('\\', "/"))
+        .unwrap_or_default();
+    run_automations(
+        app,
+        &root.to_string_lossy(),
+        vec![AutomationEvent::ImportFinished { folder, documents }],
+    );
+}
+
+/// Show which rules an event would fire and what their actions would do,
+/// without running anything
+#[tauri::command]
+pub fn automations_dry_run(
+    workspace_root: String,
+    event: AutomationEvent,
+) -> Result<Vec<RuleTrace>, String> {
+    let rules = AutomationStore::new(Path::new(&workspace_root)).load()?;
+    Ok(evaluate(&rules, &event, Local::now().date_naive()))
+}
+
+/// Get the workspace's automation rules
+#[tauri::command]
+pub fn automations_get_rules(workspace_root: String) -> Result<Vec<AutomationRule>, String> {
+    AutomationStore::new(Path::new(&workspace_root)).load()
+}
+
+/// Replace the workspace's automation rules
+#[tauri::command]
+pub fn automations_save_rules(
+    workspace_root: String,
+    rules: Vec<AutomationRule>,
+) -> Result<(), String> {
+    AutomationStore::new(Path::new(&workspace_root)).save(rules)
+}
+
+/// Whether the workspace's current rules are enabled on this machine
+#[tauri::command]
+pub fn automations_is_enabled(workspace_root: String) -> Result<bool, String> {
+    AutomationStore::new(Path::new(&workspace_root)).is_enabled()
+}
+
+/// Let the workspace's rules, as they are now, run on this machine
+#[tauri::command]
+pub fn automations_enable(workspace_root: String) -> Result<(), String> {
+    AutomationStore::new(Path::new(&workspace_root)).enable()
+}
+
+/// Stop the workspace's rules from running on this machine
+#[tauri::command]
+pub fn automations_disable(workspace_root: String) -> Result<(), String> {
+    AutomationStore::new(Path::new(&workspace_root)).disable()
+}
@@ -0,0 +1,113 @@
+// Citation commands - Search the workspace's references, cite them and
+// render citations for export
+
+use crate::services::citations::{
+    citation_marker, citation_settings_path, cited_keys, format_citation, lookup, render_citations,
+    search, CitationSettings, CitationSummary, Reference,
+};
+use crate::services::docx_export::TiptapDocument;
+use crate::traits::ReqwestHttpClient;
+use serde::Serialize;
+use std::path::Path;
+
+/// A citation to insert into a document
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CitationInsert {
+    /// Marker to put in the document, e.g. `[@smith2020, p. 12]`
+    pub marker: String,
+    /// How the citation reads in the workspace's style
+    pub preview: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenderedDocument {
+    pub content: TiptapDocument,
+    pub summary: CitationSummary,
+}
+
+/// Search the workspace's references
+#[tauri::command]
+pub async fn citation_search(
+    workspace_root: String,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<Reference>, String> {
+    let root = Path::new(&workspace_root);
+    let settings = CitationSettings::load(&citation_settings_path(root));
+    search(&ReqwestHttpClient::new(), root, &settings, &query, limit).await
+}
+
+/// Build the marker citing some references, checking they exist
+#[tauri::command]
+pub async fn citation_insert(
+    workspace_root: String,
+    keys: Vec<String>,
+    locator: Option<String>,
+) -> Result<CitationInsert, String> {
+    if keys.is_empty() {
+        return Err("Choose at least one reference".to_string());
+    }
+    let root = Path::new(&workspace_root);
+    let settings = CitationSettings::load(&citation_settings_path(root));
+    let references = lookup(&ReqwestHttpClient::new(), root, &settings, &keys).await?;
+
+    let mut cited = Vec::new();
+    for (i, key) in keys.iter().enumerate() {
+        let reference = references
+            .get(key)
+            .ok_or_else(|| format!("Unknown citation key: {}", key))?;
+        let locator = locator.as_deref().filter(|_| i == keys.len() - 1);
+        cited.push((reference, locator, i + 1));
+    }
+
+    Ok(CitationInsert {
+        marker: citation_marker(&keys, locator.as_deref()),
+        preview: format_citation(settings.style, &cited),
+    })
+}
+
+/// Replace a document's citation markers and add its bibliography, for
+/// exports rendered by the frontend such as PDF
+#[tauri::command]
+pub async fn citation_render_document(
+    workspace_root: String,
+    content: TiptapDocument,
+) -> Result<RenderedDocument, String> {
+    let mut content = content;
+    let summary = render_document_citations(Path::new(&workspace_root), &mut content).await?;
+    Ok(RenderedDocument { content, summary })
+}
+
+/// Render the citations of a document being exported
+pub async fn render_document_citations(
+    workspace_root: &Path,
+    content: &mut TiptapDocument,
+) -> Result<CitationSummary, String> {
+    let keys = cited_keys(content);
+    let settings_path = citation_settings_path(workspace_root);
+    if keys.is_empty() || !settings_path.exists() {
+        return Ok(CitationSummary::default());
+    }
+
+    let settings = CitationSettings::load(&settings_path);
+    let references = lookup(&ReqwestHttpClient::new(), workspace_root, &settings, &keys).await?;
+    Ok(render_citations(content, &references, settings.style))
+}
+
+/// Get the workspace's citation settings
+#[tauri::command]
+pub fn citation_get_settings(workspace_root: String) -> CitationSettings {
+    CitationSettings::load(&citation_settings_path(Path::new(&workspace_root)))
+}
+
+/// Save the workspace's citation settings
+#[tauri::command]
+pub fn citation_set_settings(
+    workspace_root: String,
+    settings: CitationSettings,
+) -> Result<(), String> {
+    settings.validate()?;
+    settings.save(&citation_settings_path(Path::new(&workspace_root)))
+}
@@ -0,0 +1,55 @@
+// Clipper commands - File web selections into the workspace and configure
+// where they go
+
+use crate::services::web_clipper::{
+    clip, clipper_settings_path, ClipRequest, ClipResult, ClipperSettings,
+};
+use crate::services::web_fetch;
+use crate::AppState;
+use std::path::Path;
+use tauri::State;
+use tracing::info;
+
+/// Save an HTML selection from a web page as a document in the clippings folder
+#[tauri::command]
+pub async fn clip_ingest(
+    workspace_root: String,
+    url: String,
+    title: Option<String>,
+    html: String,
+    state: State<'_, AppState>,
+) -> Result<ClipResult, String> {
+    info!("Clipping {}", url);
+    let manager = state
+        .workspace_registry
+        .write()
+        .await
+        .get_or_create(&workspace_root)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let client = web_fetch::client()?;
+    let settings = ClipperSettings::load(&clipper_settings_path());
+    let request = ClipRequest { url, title, html };
+    clip(
+        &client,
+        &manager,
+        Path::new(&workspace_root),
+        &settings,
+        &request,
+    )
+    .await
+}
+
+/// Get the clipper settings
+#[tauri::command]
+pub fn clipper_get_settings() -> ClipperSettings {
+    ClipperSettings::load(&clipper_settings_path())
+}
+
+/// Save the clipper settings
+#[tauri::command]
+pub fn clipper_set_settings(settings: ClipperSettings) -> Result<(), String> {
+    settings.validate()?;
+    settings.save(&clipper_settings_path())
+}
@@ -0,0 +1,49 @@
+// Context profile commands - IPC handlers for per-document AI context
+
+use crate::services::context_profiles::{
+    ContextProfile, ContextProfileStore, ProfileEntry, ResolvedProfile,
+};
+use std::path::Path;
+use tracing::debug;
+
+/// List all context profiles in a workspace
+#[tauri::command]
+pub fn context_profile_list(workspace_root: String) -> Result<Vec<ProfileEntry>, String> {
+    ContextProfileStore::new(Path::new(&workspace_root)).list()
+}
+
+/// Get the profile attached to a document or folder ("" for the workspace)
+#[tauri::command]
+pub fn context_profile_get(
+    workspace_root: String,
+    path: String,
+) -> Result<Option<ContextProfile>, String> {
+    ContextProfileStore::new(Path::new(&workspace_root)).get(&path)
+}
+
+/// Attach a profile to a document or folder, replacing any existing one
+#[tauri::command]
+pub fn context_profile_set(
+    workspace_root: String,
+    path: String,
+    profile: ContextProfile,
+) -> Result<(), String> {
+    debug!("Setting context profile for: {:?}", path);
+    ContextProfileStore::new(Path::new(&workspace_root)).set(&path, profile)
+}
+
+/// Remove the profile attached to a document or folder
+#[tauri::command]
+pub fn context_profile_remove(workspace_root: String, path: String) -> Result<bool, String> {
+    debug!("Removing context profile for: {:?}", path);
+    ContextProfileStore::new(Path::new(&workspace_root)).remove(&path)
+}
+
+/// Preview the merged profile that llm_chat* will apply for a document
+#[tauri::command]
+pub fn context_profile_resolve(
+    workspace_root: String,
+    document_path: String,
+) -> Result<ResolvedProfile, String> {
+    ContextProfileStore::new(Path::new(&workspace_root)).resolve(&document_path)
+}
@@ -0,0 +1,106 @@
+// Conversation commands - IPC handlers for persisted chat history
+
+use crate::services::conversation_store::{
+    Conversation, ConversationStore, ConversationSummary, SaveConversationInput,
+};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{debug, info};
+
+/// State for conversation stores (one per workspace)
+pub struct ConversationState {
+    stores: RwLock<HashMap<String, Arc<ConversationStore>>>,
+}
+
+impl ConversationState {
+    pub fn new() -> Self {
+        Self {
+            stores: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub async fn get_or_create(&self, workspace_root: &str) -> Arc<ConversationStore> {
+        if let Some(store) = self.stores.read().await.get(workspace_root) {
+            return store.clone();
+        }
+
+        let mut stores = self.stores.write().await;
+        stores
+            .entry(workspace_root.to_string())
+            .or_insert_with(|| Arc::new(ConversationStore::new(PathBuf::from(workspace_root))))
+            .clone()
+    }
+}
+
+impl Default for ConversationState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// List conversations in a workspace, most recent first.
+/// Pass `linked_document` to only list chats attached to that document.
+#[tauri::command]
+pub async fn conversation_list(
+    state: tauri::State<'_, ConversationState>,
+    workspace_root: String,
+    linked_document: Option<String>,
+) -> Result<Vec<ConversationSummary>, String> {
+    let store = state.get_or_create(&workspace_root).await;
+    store.list(linked_document.as_deref()).await
+}
+
+/// Load a conversation with its messages
+#[tauri::command]
+pub async fn conversation_load(
+    state: tauri::State<'_, ConversationState>,
+    workspace_root: String,
+    id: String,
+) -> Result<Conversation, String> {
+    debug!("Loading conversation: {}", id);
+
+    let store = state.get_or_create(&workspace_root).await;
+    store.load(&id).await
+}
+
+/// Create or update a conversation
+#[tauri::command]
+pub async fn conversation_save(
+    state: tauri::State<'_, ConversationState>,
+    workspace_root: String,
+    conversation: SaveConversationInput,
+) -> Result<Conversation, String> {
+    let store = state.get_or_create(&workspace_root).await;
+    store.save(conversation).await
+}
+
+/// Rename a conversation
+#[tauri::command]
+pub async fn conversation_rename(
+    state: tauri::State<'_, ConversationState>,
+    workspace_root: String,
+    id: String,
+    title: String,
+) -> Result<ConversationSummary, String> {
+    let store = state.get_or_create(&workspace_root).await;
+    store.rename(&id, &title).await
+}
+
+/// Delete a conversation
+#[tauri::command]
+pub async fn conversation_delete(
+    state: tauri::State<'_, ConversationState>,
+    workspace_root: String,
+    id: String,
+) -> Result<(), String> {
+    info!("Deleting conversation: {}", id);
+
+    let store = state.get_or_create(&workspace_root).await;
+    store.delete(&id).await
+}
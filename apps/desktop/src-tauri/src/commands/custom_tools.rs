@@ -0,0 +1,24 @@
+// Custom tool commands - IPC handlers for managing user-defined agent tools
+
+use crate::services::agent_executor::BUILTIN_TOOLS;
+use crate::services::custom_tools::{CustomTool, CustomToolRegistry, SaveCustomToolInput};
+use tracing::debug;
+
+/// List the user's custom tools
+#[tauri::command]
+pub fn custom_tool_list() -> Result<Vec<CustomTool>, String> {
+    CustomToolRegistry::default().list()
+}
+
+/// Create or update a custom tool
+#[tauri::command]
+pub fn custom_tool_save(tool: SaveCustomToolInput) -> Result<CustomTool, String> {
+    debug!("Saving custom tool: {}", tool.name);
+    CustomToolRegistry::default().save(tool, BUILTIN_TOOLS)
+}
+
+/// Delete a custom tool
+#[tauri::command]
+pub fn custom_tool_delete(id: String) -> Result<bool, String> {
+    CustomToolRegistry::default().delete(&id)
+}
@@ -0,0 +1,102 @@
+// Deep Link Commands - Route midlight:// links to sign-in and the editor
+//
+// Links that open a document are held until the frontend asks for them, since
+// the link the app was launched with arrives before anything is listening.
+// After that they're delivered as "deep-link:open" events.
+
+use crate::commands::auth::complete_oauth;
+use crate::services::deep_link::{self, DeepLink};
+use crate::services::workspace_paths::find_workspace_root;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::{App, AppHandle, Emitter, Manager, Runtime};
+use tauri_plugin_deep_link::DeepLinkExt;
+use tracing::{debug, error, warn};
+use url::Url;
+
+lazy_static! {
+    static ref PENDING_OPENS: Mutex<Vec<OpenDocumentEvent>> = Mutex::new(Vec::new());
+}
+
+/// Set once the frontend has collected the pending links
+static FRONTEND_READY: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenDocumentEvent {
+    pub path: String,
+    /// The workspace containing the document, if it's in one
+    pub workspace_root: Option<String>,
+}
+
+/// Start handling deep links, including any the app was launched with
+pub fn setup_deep_links<R: Runtime>(app: &App<R>) -> Result<(), Box<dyn std::error::Error>> {
+    // Installed builds register the scheme at install time; Linux and
+    // Windows dev builds have to do it at runtime
+    #[cfg(any(target_os = "linux", all(debug_assertions, windows)))]
+    app.deep_link().register_all()?;
+
+    let handle = app.handle().clone();
+    app.deep_link()
+        .on_open_url(move |event| handle_urls(&handle, event.urls()));
+
+    if let Some(urls) = app.deep_link().get_current()? {
+        handle_urls(app.handle(), urls);
+    }
+    Ok(())
+}
+
+/// Handle links opened while the app is running or used to launch it
+pub fn handle_urls<R: Runtime>(app: &AppHandle<R>, urls: Vec<Url>) {
+    for url in urls {
+        debug!(
+            "Deep link: {}://{}",
+            url.scheme(),
+            url.host_str().unwrap_or("")
+        );
+        match deep_link::parse(&url) {
+            Ok(DeepLink::AuthCallback { code, state }) => {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = complete_oauth(&app, &code, state.as_deref()).await {
+                        error!("OAuth code exchange failed: {}", e);
+                    }
+                });
+            }
+            Ok(DeepLink::Open { path }) => {
+                let event = OpenDocumentEvent {
+                    workspace_root: find_workspace_root(&path)
+                        .map(|root| root.to_string_lossy().to_string()),
+                    path: path.to_string_lossy().to_string(),
+                };
+                open_document(app, event);
+            }
+            Err(e) => warn!("Ignoring deep link: {}", e),
+        }
+    }
+}
+
+pub(crate) fn open_document<R: Runtime>(app: &AppHandle<R>, event: OpenDocumentEvent) {
+    if !FRONTEND_READY.load(Ordering::SeqCst) {
+        PENDING_OPENS.lock().unwrap().push(event);
+        return;
+    }
+    if let Err(e) = app.emit("deep-link:open", &event) {
+        error!("Failed to emit deep link open event: {}", e);
+    }
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.set_focus();
+    }
+}
+
+/// Documents opened by links before the frontend was ready. Later links are
+/// sent as "deep-link:open" events.
+#[tauri::command]
+pub async fn deep_link_take_pending() -> Vec<OpenDocumentEvent> {
+    debug!("deep_link_take_pending command");
+    let mut pending = PENDING_OPENS.lock().unwrap();
+    FRONTEND_READY.store(true, Ordering::SeqCst);
+    std::mem::take(&mut *pending)
+}
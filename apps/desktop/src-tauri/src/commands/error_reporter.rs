@@ -1,8 +1,15 @@
 // Error Reporter commands - IPC handlers for error reporting
 
-use crate::services::error_reporter::{ErrorCategory, ErrorReporter};
+use crate::services::crash_reports::{PanicReport, CRASH_REPORTS};
+use crate::services::diagnostics;
+use crate::services::error_reporter::{
+    BreadcrumbOutcome, ErrorCategory, ErrorReporter, ReportEnvironment, WorkspaceSize,
+};
+use crate::services::request_queue::REQUEST_QUEUE;
+use crate::services::session_marker::{CrashInfo, SESSION};
 use serde::Serialize;
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tauri::Runtime;
 
@@ -18,7 +25,7 @@ pub struct ErrorReporterState {
 impl ErrorReporterState {
     pub fn new(app_version: &str) -> Self {
         Self {
-            reporter: Arc::new(ErrorReporter::new(app_version)),
+            reporter: Arc::new(ErrorReporter::new(app_version).with_outbox(&REQUEST_QUEUE)),
         }
     }
 }
@@ -51,9 +58,41 @@ pub async fn error_reporter_set_enabled<R: Runtime>(
     enabled: bool,
 ) -> Result<(), String> {
     state.reporter.set_enabled(enabled);
+
+    // Reporting is opt-in, so a crash found at startup waits until now
+    if enabled {
+        if let Some(crash) = SESSION.take_unreported_crash() {
+            report_crash(&state.reporter, &crash).await;
+        }
+    }
     Ok(())
 }
 
+async fn report_crash(reporter: &ErrorReporter, crash: &CrashInfo) {
+    let mut context = HashMap::new();
+    context.insert(
+        "previous_version".to_string(),
+        crash.session.app_version.clone(),
+    );
+    if let Some(excerpt) = &crash.log_excerpt {
+        // Reports keep the start of long values, so send the end of the log
+        let start = excerpt.len().saturating_sub(900);
+        let start = (start..excerpt.len())
+            .find(|&i| excerpt.is_char_boundary(i))
+            .unwrap_or(excerpt.len());
+        context.insert("log_excerpt".to_string(), excerpt[start..].to_string());
+    }
+
+    reporter
+        .report(
+            ErrorCategory::Crash,
+            "unclean_exit",
+            "Previous session ended without a clean shutdown",
+            Some(context),
+        )
+        .await;
+}
+
 /// Get error reporting status
 #[tauri::command]
 pub async fn error_reporter_get_status<R: Runtime>(
@@ -84,6 +123,7 @@ pub async fn error_reporter_report<R: Runtime>(
         "llm" => ErrorCategory::Llm,
         "auth" => ErrorCategory::Auth,
         "recovery" => ErrorCategory::Recovery,
+        "crash" => ErrorCategory::Crash,
         _ => ErrorCategory::Unknown,
     };
 
@@ -94,3 +134,93 @@ pub async fn error_reporter_report<R: Runtime>(
 
     Ok(())
 }
+
+/// Record a command the frontend ran, for context in later reports. Only the
+/// command name, duration and outcome are kept.
+#[tauri::command]
+pub async fn error_reporter_add_breadcrumb(
+    state: tauri::State<'_, ErrorReporterState>,
+    command: String,
+    duration_ms: u64,
+    outcome: BreadcrumbOutcome,
+) -> Result<(), String> {
+    state
+        .reporter
+        .add_breadcrumb(&command, duration_ms, outcome);
+    Ok(())
+}
+
+/// Set the workspace and feature flags described in later reports. The
+/// workspace is reduced to file counts and sizes.
+#[tauri::command]
+pub async fn error_reporter_set_context(
+    state: tauri::State<'_, ErrorReporterState>,
+    workspace_root: Option<String>,
+    feature_flags: Option<HashMap<String, bool>>,
+) -> Result<(), String> {
+    let workspace = match workspace_root {
+        Some(root) => {
+            let stats = tokio::task::spawn_blocking(move || {
+                diagnostics::workspace_stats(&PathBuf::from(root))
+            })
+            .await
+            .map_err(|e| format!("Task join error: {}", e))?;
+            Some(WorkspaceSize {
+                documents: stats.documents,
+                other_files: stats.other_files,
+                folders: stats.folders,
+                total_bytes: stats.total_bytes,
+            })
+        }
+        None => None,
+    };
+
+    state.reporter.set_environment(ReportEnvironment {
+        workspace,
+        feature_flags: feature_flags.unwrap_or_default().into_iter().collect(),
+    });
+    Ok(())
+}
+
+/// Backend panics from earlier sessions, for the user to send or dismiss
+#[tauri::command]
+pub async fn error_reporter_get_panics() -> Result<Vec<PanicReport>, String> {
+    Ok(CRASH_REPORTS.previous())
+}
+
+/// Send a saved panic through the error reporter
+#[tauri::command]
+pub async fn error_reporter_submit_panic(
+    state: tauri::State<'_, ErrorReporterState>,
+    id: String,
+) -> Result<(), String> {
+    if !state.reporter.is_enabled() {
+        return Err("Error reporting is disabled".to_string());
+    }
+    let panic = CRASH_REPORTS
+        .take(&id)
+        .ok_or_else(|| format!("No such crash report: {}", id))?;
+
+    let mut context = HashMap::new();
+    context.insert("previous_version".to_string(), panic.app_version.clone());
+    if let Some(location) = &panic.location {
+        context.insert("location".to_string(), location.clone());
+    }
+    if let Some(thread) = &panic.thread {
+        context.insert("thread".to_string(), thread.clone());
+    }
+    context.insert("backtrace".to_string(), panic.app_frames().join("\n"));
+
+    state
+        .reporter
+        .report(ErrorCategory::Crash, "panic", &panic.message, Some(context))
+        .await;
+    Ok(())
+}
+
+/// Delete a saved panic without sending it
+#[tauri::command]
+pub async fn error_reporter_dismiss_panic(id: String) -> Result<(), String> {
+    CRASH_REPORTS.take(&id);
+    Ok(())
+}
@@ -1,9 +1,20 @@
 // Export commands for Tauri
-// Handles DOCX export operations
+// Handles DOCX export operations, including citations and bibliographies,
+// print layout for PDF export, TextBundle/TextPack export, and exporting a
+// workspace as an Obsidian vault
 
+use crate::commands::citations::render_document_citations;
+use crate::commands::notifications::notify_operation;
+use crate::commands::operations::start_operation;
 use crate::services::docx_export::{tiptap_to_docx, TiptapDocument};
+use crate::services::export_filter::ExportFilter;
+use crate::services::notifications::Operation;
+use crate::services::obsidian_export::{export_vault, ObsidianExportResult};
+use crate::services::operations::OperationKind;
+use crate::services::print_layout::{render_print_html, PrintLayoutOptions};
+use crate::services::textbundle::{bundle_from_tiptap, write_bundle};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tauri::{AppHandle, Emitter, Runtime};
 use tauri_plugin_dialog::DialogExt;
 use tokio::sync::oneshot;
@@ -33,6 +44,9 @@ pub async fn export_select_save_path<R: Runtime>(
     let (extension, filter_name) = match file_type.as_str() {
         "docx" => ("docx", "Word Document"),
         "pdf" => ("pdf", "PDF Document"),
+        "textbundle" => ("textbundle", "TextBundle"),
+        "textpack" => ("textpack", "TextPack"),
+        "midlightpkg" => ("midlightpkg", "Midlight Workspace Archive"),
         _ => return Err(format!("Unsupported file type: {}", file_type)),
     };
 
@@ -54,13 +68,28 @@ pub async fn export_select_save_path<R: Runtime>(
     }
 }
 
-/// Exports the document to DOCX format
+/// Exports the document to DOCX format. With a workspace, its citation
+/// markers are rendered and a bibliography added.
 #[tauri::command]
 pub async fn export_to_docx<R: Runtime>(
     app: AppHandle<R>,
     content: TiptapDocument,
     output_path: String,
+    workspace_root: Option<String>,
 ) -> Result<ExportResult, String> {
+    let mut content = content;
+    if let Some(root) = workspace_root.as_deref() {
+        if let Err(e) = render_document_citations(Path::new(root), &mut content).await {
+            let error = format!("Failed to render citations: {}", e);
+            notify_operation(&app, Operation::Export, Err(error.clone()));
+            return Ok(ExportResult {
+                success: false,
+                path: None,
+                error: Some(error),
+            });
+        }
+    }
+
     let app_handle = app.clone();
 
     // Run export in a blocking task to avoid blocking the async runtime
@@ -83,7 +112,17 @@ pub async fn export_to_docx<R: Runtime>(
                     .map_err(|e| format!("Failed to create directory: {}", e))?;
             }
 
-            std::fs::write(&path, &bytes).map_err(|e| format!("Failed to write file: {}", e))?;
+            let written =
+                std::fs::write(&path, &bytes).map_err(|e| format!("Failed to write file: {}", e));
+            notify_operation(
+                &app,
+                Operation::Export,
+                written.clone().map(|_| {
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    format!("Saved {}", name)
+                }),
+            );
+            written?;
 
             Ok(ExportResult {
                 success: true,
@@ -91,10 +130,109 @@ pub async fn export_to_docx<R: Runtime>(
                 error: None,
             })
         }
-        Err(e) => Ok(ExportResult {
+        Err(e) => {
+            notify_operation(&app, Operation::Export, Err(e.clone()));
+            Ok(ExportResult {
+                success: false,
+                path: None,
+                error: Some(e),
+            })
+        }
+    }
+}
+
+/// Lays the document out for PDF export: returns HTML, with running
+/// headers and footers, a table of contents and page breaks as `options`
+/// ask, for the frontend to print. With a workspace, its citation markers
+/// are rendered and a bibliography added.
+#[tauri::command]
+pub async fn export_print_layout(
+    content: TiptapDocument,
+    options: Option<PrintLayoutOptions>,
+    workspace_root: Option<String>,
+) -> Result<String, String> {
+    let mut content = content;
+    if let Some(root) = workspace_root.as_deref() {
+        render_document_citations(Path::new(root), &mut content)
+            .await
+            .map_err(|e| format!("Failed to render citations: {}", e))?;
+    }
+    Ok(render_print_html(&content, &options.unwrap_or_default()))
+}
+
+/// Exports the document as a TextBundle, or a TextPack if the output path
+/// ends in .textpack. Stored images are copied into the bundle's assets.
+#[tauri::command]
+pub async fn export_to_textbundle<R: Runtime>(
+    app: AppHandle<R>,
+    content: serde_json::Value,
+    output_path: String,
+    workspace_root: Option<String>,
+) -> Result<ExportResult, String> {
+    let path = PathBuf::from(&output_path);
+    let result = tokio::task::spawn_blocking(move || {
+        let bundle = bundle_from_tiptap(&content, workspace_root.as_deref().map(Path::new));
+        write_bundle(&bundle, &path).map(|_| path)
+    })
+    .await
+    .map_err(|e| format!("Task failed: {}", e))?;
+
+    notify_operation(
+        &app,
+        Operation::Export,
+        result.clone().map(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            format!("Saved {}", name)
+        }),
+    );
+    Ok(match result {
+        Ok(_) => ExportResult {
+            success: true,
+            path: Some(output_path),
+            error: None,
+        },
+        Err(e) => ExportResult {
             success: false,
             path: None,
             error: Some(e),
-        }),
+        },
+    })
+}
+
+/// Exports the workspace as an Obsidian vault in `output_path`, a new or
+/// empty folder: all of it, or the part `filter` selects. Runs as a
+/// cancellable operation.
+#[tauri::command]
+pub async fn export_to_obsidian<R: Runtime>(
+    app: AppHandle<R>,
+    workspace_root: String,
+    output_path: String,
+    filter: Option<ExportFilter>,
+) -> Result<ObsidianExportResult, String> {
+    let operation = start_operation(&app, OperationKind::Export, "Exporting to Obsidian");
+    let (operation, result) = tokio::task::spawn_blocking(move || {
+        let result = export_vault(
+            Path::new(&workspace_root),
+            Path::new(&output_path),
+            &filter.unwrap_or_default(),
+            &operation,
+        );
+        (operation, result)
+    })
+    .await
+    .map_err(|e| format!("Task failed: {}", e))?;
+
+    operation.finish(&result);
+    if operation.is_cancelled() {
+        return result;
     }
+    notify_operation(
+        &app,
+        Operation::Export,
+        result
+            .as_ref()
+            .map(|r| format!("Exported {} documents to Obsidian", r.documents))
+            .map_err(Clone::clone),
+    );
+    result
 }
@@ -0,0 +1,91 @@
+// External editor commands - Round-trip documents through another editor
+
+use crate::services::external_editor::{self, EventSink, ExternalEditSession};
+use crate::services::workspace_paths::{document_key, find_workspace_root};
+use crate::AppState;
+use std::path::Path;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, State};
+
+/// Open a .midlight document in an external editor as Markdown. Saves made
+/// there are written back to the document and reported as
+/// "external-editor:changed" events.
+///
+/// `editor` is a command line such as "code --wait" or "gvim -f"; the temp
+/// file's path is appended. If the command blocks until editing is done, the
+/// session closes when it exits. Without one, the system's default app for
+/// Markdown files is used and the session stays open until closed.
+#[tauri::command]
+pub async fn open_in_external_editor(
+    app: AppHandle,
+    path: String,
+    editor: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ExternalEditSession, String> {
+    let path = Path::new(&path);
+    let root = find_workspace_root(path).ok_or("Document is not in a workspace")?;
+    let relative = path
+        .strip_prefix(&root)
+        .map_err(|_| "Document is not in a workspace")?;
+    let workspace_root = root.to_string_lossy().to_string();
+
+    let manager = state
+        .workspace_registry
+        .write()
+        .await
+        .get_or_create(&workspace_root)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let on_event: EventSink = Arc::new(move |event| {
+        let _ = app.emit("external-editor:changed", &event);
+    });
+    let session = external_editor::start(
+        manager,
+        &root,
+        &document_key(&relative.to_string_lossy()),
+        on_event,
+    )
+    .await?;
+
+    let launched = match editor.as_deref().map(str::trim).filter(|e| !e.is_empty()) {
+        Some(editor) => launch(editor, &session),
+        None => open::that(&session.temp_path).map_err(|e| e.to_string()),
+    };
+    if let Err(e) = launched {
+        external_editor::close(&session.id);
+        return Err(format!("Failed to open external editor: {}", e));
+    }
+
+    Ok(session)
+}
+
+/// Stop syncing an external editing session
+#[tauri::command]
+pub fn close_external_editor(session_id: String) -> bool {
+    external_editor::close(&session_id)
+}
+
+#[tauri::command]
+pub fn list_external_editor_sessions() -> Vec<ExternalEditSession> {
+    external_editor::list()
+}
+
+/// Run an editor command on the session's temp file, closing the session
+/// when it exits
+fn launch(editor: &str, session: &ExternalEditSession) -> Result<(), String> {
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or_default();
+    let mut child = tokio::process::Command::new(program)
+        .args(parts)
+        .arg(&session.temp_path)
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    let session_id = session.id.clone();
+    tokio::spawn(async move {
+        let _ = child.wait().await;
+        external_editor::close(&session_id);
+    });
+    Ok(())
+}
@@ -1,25 +1,44 @@
 // File watcher commands - IPC handlers for file watching
 
-use crate::services::file_watcher::FileWatcher;
+use crate::commands::fs::refresh_tree_cache;
+use crate::commands::pdf::spawn_pdf_update;
+use crate::services::file_watcher::{
+    EventEmitter, FileChangeEvent, FileWatcher, FileWatcherConfig, SubscriptionEvent,
+    Subscriptions, TauriEmitter, WatcherSettings,
+};
+use crate::services::pdf_extractor;
+use crate::services::rag_indexer::RAG_INDEXER;
+use crate::services::workspace_paths::find_workspace_root;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tauri::Runtime;
+use tauri::{Emitter, Runtime};
 use tokio::sync::RwLock;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 /// Registry of file watchers (one per workspace)
 pub struct FileWatcherRegistry {
     watchers: HashMap<String, Arc<RwLock<FileWatcher>>>,
+    /// Path prefix subscriptions for each workspace's watcher
+    subscriptions: HashMap<String, Arc<Subscriptions>>,
 }
 
 impl FileWatcherRegistry {
     pub fn new() -> Self {
         Self {
             watchers: HashMap::new(),
+            subscriptions: HashMap::new(),
         }
     }
 
+    /// Subscriptions for a workspace, kept across watcher restarts
+    pub fn subscriptions(&mut self, workspace_root: &str) -> Arc<Subscriptions> {
+        self.subscriptions
+            .entry(workspace_root.to_string())
+            .or_default()
+            .clone()
+    }
+
     pub fn get(&self, workspace_root: &str) -> Option<Arc<RwLock<FileWatcher>>> {
         self.watchers.get(workspace_root).cloned()
     }
@@ -30,6 +49,7 @@ impl FileWatcherRegistry {
     }
 
     pub fn remove(&mut self, workspace_root: &str) -> Option<Arc<RwLock<FileWatcher>>> {
+        self.subscriptions.remove(workspace_root);
         self.watchers.remove(workspace_root)
     }
 }
@@ -59,34 +79,149 @@ impl Default for FileWatcherState {
     }
 }
 
+/// Sends file changes to the frontend and queues them for background search
+/// indexing
+struct IndexingEmitter<R: Runtime> {
+    inner: TauriEmitter<R>,
+    app: tauri::AppHandle<R>,
+    workspace_root: PathBuf,
+    subscriptions: Arc<Subscriptions>,
+}
+
+impl<R: Runtime> EventEmitter for IndexingEmitter<R> {
+    fn emit_file_change(&self, event: &FileChangeEvent) -> Result<(), String> {
+        let path = self.workspace_root.join(&event.file_key);
+        let deleted = event.change_type == "delete";
+        if pdf_extractor::is_pdf(&path) {
+            spawn_pdf_update(self.workspace_root.clone(), path.clone(), deleted);
+        }
+        refresh_tree_cache(&path);
+        RAG_INDEXER.file_changed(path, deleted);
+
+        let subscription_ids = self.subscriptions.matching(&event.file_key);
+        if !subscription_ids.is_empty() {
+            let event = SubscriptionEvent {
+                subscription_ids,
+                change: event.clone(),
+            };
+            if let Err(e) = self.app.emit("file-watcher:subscription", &event) {
+                warn!("Failed to emit subscription event: {}", e);
+            }
+        }
+        self.inner.emit_file_change(event)
+    }
+}
+
 // ============================================================================
 // Tauri Commands
 // ============================================================================
 
-/// Start watching a workspace for file changes
+/// Start watching a workspace for file changes. A folder inside a workspace
+/// starts (or reuses) the watcher for the whole workspace; use
+/// `file_watcher_subscribe` for changes under one folder.
 #[tauri::command]
 pub async fn file_watcher_start<R: Runtime>(
     app: tauri::AppHandle<R>,
     state: tauri::State<'_, FileWatcherState>,
     workspace_root: String,
 ) -> Result<(), String> {
+    let workspace_root = resolve_workspace_root(&workspace_root);
     info!("Starting file watcher for: {}", workspace_root);
 
     let mut registry = state.registry.write().await;
+    ensure_started(app, &mut registry, &workspace_root)
+}
+
+/// Subscribe to changes at or under `path_prefix` (absolute, or relative to
+/// `workspace_root`), starting the workspace's watcher if needed. Matching
+/// changes are sent as "file-watcher:subscription" events carrying the ids
+/// of the subscriptions they match. Returns the subscription id.
+#[tauri::command]
+pub async fn file_watcher_subscribe<R: Runtime>(
+    app: tauri::AppHandle<R>,
+    state: tauri::State<'_, FileWatcherState>,
+    workspace_root: String,
+    path_prefix: String,
+) -> Result<String, String> {
+    let root = resolve_workspace_root(&workspace_root);
+    let prefix = Path::new(&workspace_root).join(&path_prefix);
+    let relative = prefix
+        .strip_prefix(&root)
+        .map_err(|_| format!("{} is not in the workspace", prefix.display()))?;
+
+    let mut registry = state.registry.write().await;
+    ensure_started(app, &mut registry, &root)?;
+    Ok(registry
+        .subscriptions(&root)
+        .subscribe(&relative.to_string_lossy()))
+}
+
+/// Remove a subscription; the workspace's watcher keeps running
+#[tauri::command]
+pub async fn file_watcher_unsubscribe(
+    state: tauri::State<'_, FileWatcherState>,
+    workspace_root: String,
+    subscription_id: String,
+) -> Result<bool, String> {
+    let root = resolve_workspace_root(&workspace_root);
+    let mut registry = state.registry.write().await;
+    Ok(registry.subscriptions(&root).unsubscribe(&subscription_id))
+}
 
-    // Check if already watching
-    if registry.get(&workspace_root).is_some() {
+/// The workspace containing a folder, so nested folders share one watcher
+fn resolve_workspace_root(path: &str) -> String {
+    let path = Path::new(path);
+    if path.join(".midlight").is_dir() {
+        return path.to_string_lossy().to_string();
+    }
+    find_workspace_root(path)
+        .unwrap_or_else(|| path.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
+
+fn ensure_started<R: Runtime>(
+    app: tauri::AppHandle<R>,
+    registry: &mut FileWatcherRegistry,
+    workspace_root: &str,
+) -> Result<(), String> {
+    if registry.get(workspace_root).is_some() {
         debug!("File watcher already running for: {}", workspace_root);
         return Ok(());
     }
 
-    // Create and start watcher
-    let mut watcher = FileWatcher::new(PathBuf::from(&workspace_root), None);
-    watcher.start(app)?;
+    let subscriptions = registry.subscriptions(workspace_root);
+    let watcher = start_watcher(app, workspace_root, subscriptions)?;
+    registry.insert(workspace_root.to_string(), watcher);
+    Ok(())
+}
 
-    registry.insert(workspace_root, watcher);
+/// Create and start a watcher with the workspace's settings
+fn start_watcher<R: Runtime>(
+    app: tauri::AppHandle<R>,
+    workspace_root: &str,
+    subscriptions: Arc<Subscriptions>,
+) -> Result<FileWatcher, String> {
+    let root = PathBuf::from(workspace_root);
+    let settings = WatcherSettings::load(&root).unwrap_or_else(|e| {
+        warn!("Using default watcher settings: {}", e);
+        WatcherSettings::default()
+    });
 
-    Ok(())
+    let mut watcher = FileWatcher::new(
+        root.clone(),
+        Some(FileWatcherConfig::with_settings(&settings)),
+    );
+    if let Some(interval) = settings.poll_interval(&root) {
+        watcher = watcher.with_polling(interval);
+    }
+    watcher.start_with_emitter(Arc::new(IndexingEmitter {
+        inner: TauriEmitter::new(app.clone()),
+        app,
+        workspace_root: root,
+        subscriptions,
+    }))?;
+    Ok(watcher)
 }
 
 /// Stop watching a workspace
@@ -147,3 +282,31 @@ pub async fn file_watcher_clear_saving<R: Runtime>(
 
     Ok(())
 }
+
+/// A workspace's watcher settings: extra ignore patterns, debounce delay and
+/// polling
+#[tauri::command]
+pub fn file_watcher_get_settings(workspace_root: String) -> Result<WatcherSettings, String> {
+    WatcherSettings::load(Path::new(&workspace_root))
+}
+
+/// Save a workspace's watcher settings, restarting its watcher if running
+#[tauri::command]
+pub async fn file_watcher_set_settings<R: Runtime>(
+    app: tauri::AppHandle<R>,
+    state: tauri::State<'_, FileWatcherState>,
+    workspace_root: String,
+    settings: WatcherSettings,
+) -> Result<(), String> {
+    settings.save(Path::new(&workspace_root))?;
+
+    let mut registry = state.registry.write().await;
+    if let Some(watcher) = registry.get(&workspace_root) {
+        watcher.write().await.stop();
+        let subscriptions = registry.subscriptions(&workspace_root);
+        let watcher = start_watcher(app, &workspace_root, subscriptions)?;
+        registry.insert(workspace_root, watcher);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,50 @@
+// Flashcard commands - Review sessions for cards written in documents
+
+use crate::services::flashcards::{
+    workspace_cards, CardSchedule, Flashcard, FlashcardStore, ReviewSession,
+};
+use chrono::Local;
+use std::path::{Path, PathBuf};
+
+/// Cards written in the workspace's documents, optionally limited by a
+/// path glob
+#[tauri::command]
+pub async fn flashcards_list(
+    workspace_root: String,
+    documents: Option<String>,
+) -> Result<Vec<Flashcard>, String> {
+    let root = PathBuf::from(workspace_root);
+    tokio::task::spawn_blocking(move || workspace_cards(&root, documents.as_deref()))
+        .await
+        .map_err(|e| format!("Task failed: {}", e))?
+}
+
+/// Start a review session: cards due today, then new cards up to a limit
+#[tauri::command]
+pub async fn flashcards_due(
+    workspace_root: String,
+    documents: Option<String>,
+    new_limit: Option<usize>,
+) -> Result<ReviewSession, String> {
+    let root = PathBuf::from(workspace_root);
+    tokio::task::spawn_blocking(move || {
+        let cards = workspace_cards(&root, documents.as_deref())?;
+        FlashcardStore::new(&root).session(cards, Local::now().date_naive(), new_limit)
+    })
+    .await
+    .map_err(|e| format!("Task failed: {}", e))?
+}
+
+/// Grade an answer from 0 (forgotten) to 5 (perfect) and schedule the card
+#[tauri::command]
+pub fn flashcards_review(
+    workspace_root: String,
+    card_id: String,
+    grade: u8,
+) -> Result<CardSchedule, String> {
+    FlashcardStore::new(Path::new(&workspace_root)).record_review(
+        &card_id,
+        grade,
+        Local::now().date_naive(),
+    )
+}
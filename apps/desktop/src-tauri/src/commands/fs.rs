@@ -5,7 +5,25 @@ use dirs;
 use serde::{Deserialize, Serialize};
 use serde_json;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Runtime};
+
+use crate::commands::rag;
+use crate::services::atomic_write::write_atomic;
+use crate::services::delete_impact::{DeleteImpact, DeletePlanner};
+use crate::services::dir_listing::{self, ListedEntry};
+use crate::services::document_chunks::resolve_content;
+use crate::services::document_lock;
+use crate::services::document_stats::{self, DocumentStats};
+use crate::services::file_stream::{self, ChunkEncoding, FileChunk};
+use crate::services::image_refs;
+use crate::services::link_rewrite::{LinkRewrite, LinkRewriter};
+use crate::services::pinned_documents;
+use crate::services::rag_indexer::RAG_INDEXER;
+use crate::services::trash_manager::{TrashItem, TrashManager};
+use crate::services::tree_cache::{CachedEntry, TreeCache};
+use crate::services::vector_store::{FileFilter, IndexedFile};
+use crate::services::workspace_paths::{document_key, find_workspace_root};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileNode {
@@ -15,6 +33,9 @@ pub struct FileNode {
     #[serde(rename = "type")]
     pub node_type: String, // "file" or "directory"
     pub category: Option<String>,
+    /// Visible children of a directory, when listed with child counts
+    #[serde(rename = "childCount", skip_serializing_if = "Option::is_none")]
+    pub child_count: Option<usize>,
 }
 
 fn generate_id() -> String {
@@ -39,7 +60,7 @@ fn categorize_file(name: &str) -> String {
 }
 
 /// Check if a file should be shown in the file tree
-fn should_show_file(name: &str) -> bool {
+pub(crate) fn should_show_file(name: &str) -> bool {
     // Hide hidden files
     if name.starts_with('.') {
         return false;
@@ -78,6 +99,57 @@ pub async fn get_default_workspace() -> Result<String, String> {
     Ok(workspace_path.to_string_lossy().to_string())
 }
 
+/// Bring a workspace's tree cache up to date with the disk in the
+/// background, emitting `fs:tree-cache-updated` if anything changed
+pub(crate) fn spawn_tree_reconcile<R: Runtime>(app: AppHandle<R>, workspace_root: PathBuf) {
+    tauri::async_runtime::spawn_blocking(move || {
+        let stats =
+            TreeCache::open(&workspace_root).and_then(|mut c| c.reconcile(should_show_file));
+        match stats {
+            Ok(stats) if stats.changed() => {
+                let _ = app.emit(
+                    "fs:tree-cache-updated",
+                    serde_json::json!({
+                        "workspaceRoot": workspace_root.to_string_lossy(),
+                        "stats": stats,
+                    }),
+                );
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Failed to reconcile tree cache: {}", e),
+        }
+    });
+}
+
+/// Update the tree cache for a path that was created, modified or deleted
+pub(crate) fn refresh_tree_cache(path: &Path) {
+    let Some(root) = find_workspace_root(path) else {
+        return;
+    };
+    if let Err(e) = TreeCache::open(&root).and_then(|mut c| c.refresh(path, should_show_file)) {
+        tracing::warn!("Failed to update tree cache: {}", e);
+    }
+}
+
+/// List a workspace's files from the tree cache, without walking the disk.
+/// Returns the whole tree, or the children of `parent` (relative to the
+/// workspace root). The cache is reconciled with the disk in the background.
+#[tauri::command]
+pub async fn fs_cached_tree<R: Runtime>(
+    app: AppHandle<R>,
+    workspace_root: String,
+    parent: Option<String>,
+) -> Result<Vec<CachedEntry>, String> {
+    let root = PathBuf::from(&workspace_root);
+    let cache = TreeCache::open(&root)?;
+    let entries = match parent {
+        Some(parent) => cache.children(&document_key(&parent))?,
+        None => cache.entries()?,
+    };
+    spawn_tree_reconcile(app, root);
+    Ok(entries)
+}
+
 #[tauri::command]
 pub async fn read_dir(path: String) -> Result<Vec<FileNode>, String> {
     let path = Path::new(&path);
@@ -111,6 +183,7 @@ pub async fn read_dir(path: String) -> Result<Vec<FileNode>, String> {
             path: file_path.to_string_lossy().to_string(),
             node_type: if is_dir { "directory" } else { "file" }.to_string(),
             category,
+            child_count: None,
         });
     }
 
@@ -128,19 +201,154 @@ pub async fn read_dir(path: String) -> Result<Vec<FileNode>, String> {
     Ok(entries)
 }
 
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadDirOptions {
+    /// `nextCursor` from the previous page
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+    /// Count the visible children of each directory
+    #[serde(default)]
+    pub child_counts: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirPage {
+    pub entries: Vec<FileNode>,
+    pub next_cursor: Option<String>,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DirStreamBatch {
+    stream_id: String,
+    entries: Vec<FileNode>,
+    done: bool,
+}
+
+fn listed_to_node(entry: ListedEntry) -> FileNode {
+    FileNode {
+        id: generate_id(),
+        category: (!entry.is_dir).then(|| categorize_file(&entry.name)),
+        path: entry.path.to_string_lossy().to_string(),
+        node_type: if entry.is_dir { "directory" } else { "file" }.to_string(),
+        name: entry.name,
+        child_count: entry.child_count,
+    }
+}
+
+/// List a folder's direct children a page at a time, for folders too large
+/// to send at once
+#[tauri::command]
+pub async fn read_dir_page(
+    path: String,
+    options: Option<ReadDirOptions>,
+) -> Result<DirPage, String> {
+    let options = options.unwrap_or_default();
+    let dir = PathBuf::from(&path);
+    if !dir.exists() {
+        return Err(format!("Directory does not exist: {}", dir.display()));
+    }
+
+    let page = tokio::task::spawn_blocking(move || {
+        dir_listing::list_page(
+            &dir,
+            should_show_file,
+            options.cursor.as_deref(),
+            options.limit.unwrap_or(dir_listing::DEFAULT_PAGE_SIZE),
+            options.child_counts,
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+
+    Ok(DirPage {
+        entries: page.entries.into_iter().map(listed_to_node).collect(),
+        next_cursor: page.next_cursor,
+        total: page.total,
+    })
+}
+
+/// List a whole tree, streaming it as "fs:read-dir:batch" events tagged
+/// with `stream_id`; the last event has `done` set. Returns the number of
+/// entries listed.
+#[tauri::command]
+pub async fn read_dir_recursive(
+    app: AppHandle,
+    path: String,
+    stream_id: String,
+    batch_size: Option<usize>,
+) -> Result<usize, String> {
+    let dir = PathBuf::from(&path);
+    if !dir.is_dir() {
+        return Err(format!("Directory does not exist: {}", dir.display()));
+    }
+
+    let emit = move |entries: Vec<FileNode>, done: bool| {
+        let batch = DirStreamBatch {
+            stream_id: stream_id.clone(),
+            entries,
+            done,
+        };
+        app.emit("fs:read-dir:batch", &batch).is_ok()
+    };
+
+    tokio::task::spawn_blocking(move || {
+        let total = dir_listing::walk_batches(
+            &dir,
+            should_show_file,
+            batch_size.unwrap_or(dir_listing::DEFAULT_PAGE_SIZE),
+            |batch| emit(batch.into_iter().map(listed_to_node).collect(), false),
+        );
+        emit(Vec::new(), true);
+        total
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn read_file(path: String) -> Result<String, String> {
     fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))
 }
 
+/// Read part of a file, for files too large to send in one message. Call
+/// again from `nextOffset` until `done`.
+#[tauri::command]
+pub async fn read_file_chunk(
+    path: String,
+    offset: Option<u64>,
+    length: Option<usize>,
+    encoding: Option<ChunkEncoding>,
+) -> Result<FileChunk, String> {
+    file_stream::read_chunk(
+        Path::new(&path),
+        offset.unwrap_or(0),
+        length,
+        encoding.unwrap_or_default(),
+    )
+}
+
 #[tauri::command]
 pub async fn write_file(path: String, content: String) -> Result<(), String> {
-    // Ensure parent directory exists
-    if let Some(parent) = Path::new(&path).parent() {
-        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
-    }
+    document_lock::ensure_unlocked(Path::new(&path), &path)?;
+    // Parent directories are created as needed
+    write_atomic(Path::new(&path), content).map_err(|e| format!("Failed to write file: {}", e))
+}
 
-    fs::write(&path, content).map_err(|e| format!("Failed to write file: {}", e))
+/// Lock a .midlight document so saves and the agent can't change it, or
+/// unlock it
+#[tauri::command]
+pub async fn document_set_locked(path: String, locked: bool) -> Result<(), String> {
+    document_lock::set_locked(Path::new(&path), locked)
+}
+
+#[tauri::command]
+pub async fn document_is_locked(path: String) -> bool {
+    document_lock::is_locked(Path::new(&path))
 }
 
 #[tauri::command]
@@ -148,15 +356,45 @@ pub async fn delete_file(path: String) -> Result<(), String> {
     let path = Path::new(&path);
 
     if path.is_dir() {
-        fs::remove_dir_all(path).map_err(|e| e.to_string())
+        fs::remove_dir_all(path).map_err(|e| e.to_string())?;
     } else {
-        fs::remove_file(path).map_err(|e| e.to_string())
+        fs::remove_file(path).map_err(|e| e.to_string())?;
     }
+
+    RAG_INDEXER.path_removed(path.to_path_buf());
+    image_refs::path_removed(path);
+    pinned_documents::path_removed(path);
+    Ok(())
 }
 
+/// Rename a file or folder. Links to it from other documents, and relative
+/// links inside it, are rewritten unless `update_links` is false.
 #[tauri::command]
-pub async fn rename_file(old_path: String, new_path: String) -> Result<(), String> {
-    fs::rename(&old_path, &new_path).map_err(|e| e.to_string())?;
+pub async fn rename_file(
+    app: AppHandle,
+    old_path: String,
+    new_path: String,
+    update_links: Option<bool>,
+) -> Result<(), String> {
+    move_path(&app, &old_path, &new_path, update_links.unwrap_or(true))
+}
+
+/// Move a file or folder and everything tracked about it, optionally
+/// rewriting links to it
+pub(crate) fn move_path<R: Runtime>(
+    app: &AppHandle<R>,
+    old_path: &str,
+    new_path: &str,
+    update_links: bool,
+) -> Result<(), String> {
+    let rewrites = match update_links {
+        true => plan_link_rewrites(Path::new(old_path), Path::new(new_path)),
+        false => None,
+    };
+    fs::rename(old_path, new_path).map_err(|e| e.to_string())?;
+    RAG_INDEXER.path_moved(PathBuf::from(old_path), PathBuf::from(new_path));
+    image_refs::path_moved(Path::new(old_path), Path::new(new_path));
+    pinned_documents::path_moved(Path::new(old_path), Path::new(new_path));
 
     // Also rename sidecar if exists
     let old_sidecar = format!("{}.sidecar.json", old_path);
@@ -165,14 +403,90 @@ pub async fn rename_file(old_path: String, new_path: String) -> Result<(), Strin
         let _ = fs::rename(&old_sidecar, &new_sidecar);
     }
 
+    if let Some(rewrites) = rewrites {
+        apply_link_rewrites(app, rewrites);
+    }
+
     Ok(())
 }
 
+/// Documents whose links would be rewritten by renaming or moving
+/// `old_path` to `new_path`, for confirming the move first
+#[tauri::command]
+pub async fn fs_preview_link_rewrites(
+    old_path: String,
+    new_path: String,
+) -> Result<Vec<LinkRewrite>, String> {
+    let old_path = Path::new(&old_path);
+    match find_workspace_root(old_path) {
+        Some(root) => LinkRewriter::new(&root).plan(old_path, Path::new(&new_path)),
+        None => Ok(Vec::new()),
+    }
+}
+
 #[tauri::command]
 pub async fn file_exists(path: String) -> Result<bool, String> {
     Ok(Path::new(&path).exists())
 }
 
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileMetadata {
+    pub size: u64,
+    pub is_dir: bool,
+    /// RFC 3339; not every filesystem records creation time
+    pub created: Option<String>,
+    pub modified: Option<String>,
+    /// Word and reading-time counts, for documents
+    pub stats: Option<DocumentStats>,
+}
+
+/// Size, timestamps and, for .midlight, Markdown and text files, word counts
+#[tauri::command]
+pub async fn fs_get_metadata(path: String) -> Result<FileMetadata, String> {
+    let path = PathBuf::from(path);
+    let metadata = fs::metadata(&path).map_err(|e| format!("Failed to read metadata: {}", e))?;
+    let timestamp = |time: std::io::Result<std::time::SystemTime>| {
+        time.ok()
+            .map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339())
+    };
+
+    let mut created = timestamp(metadata.created());
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let stats = match ext.as_str() {
+        _ if metadata.is_dir() => None,
+        "midlight" => {
+            let content =
+                fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+            let mut document: serde_json::Value = serde_json::from_str(&content)
+                .map_err(|e| format!("Failed to parse document: {}", e))?;
+            resolve_content(&path, &mut document);
+            if created.is_none() {
+                created = document["meta"]["created"].as_str().map(String::from);
+            }
+            Some(document_stats::midlight_stats(&document))
+        }
+        "md" | "txt" => {
+            let content =
+                fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+            Some(document_stats::text_stats(&content))
+        }
+        _ => None,
+    };
+
+    Ok(FileMetadata {
+        size: metadata.len(),
+        is_dir: metadata.is_dir(),
+        created,
+        modified: timestamp(metadata.modified()),
+        stats,
+    })
+}
+
 #[tauri::command]
 pub async fn create_folder(path: String) -> Result<(), String> {
     fs::create_dir_all(&path).map_err(|e| e.to_string())
@@ -222,6 +536,7 @@ pub async fn create_midlight_file(parent_path: String, name: String) -> Result<F
         path: file_path.to_string_lossy().to_string(),
         node_type: "file".to_string(),
         category: Some("midlight".to_string()),
+        child_count: None,
     })
 }
 
@@ -243,6 +558,7 @@ pub async fn create_new_folder(parent_path: String, name: String) -> Result<File
         path: folder_path.to_string_lossy().to_string(),
         node_type: "directory".to_string(),
         category: None,
+        child_count: None,
     })
 }
 
@@ -328,24 +644,83 @@ pub async fn file_duplicate(path: String) -> Result<DuplicateResult, String> {
     })
 }
 
-/// Move file/folder to OS trash instead of permanent delete
+/// What moving a file or folder in a workspace to the trash would break:
+/// links to it, pending agent changes and search index entries. None outside
+/// a workspace.
+#[tauri::command]
+pub async fn fs_delete_impact(
+    app: AppHandle,
+    path: String,
+) -> Result<Option<DeleteImpact>, String> {
+    let src = Path::new(&path);
+    let Some(workspace_root) = find_workspace_root(src) else {
+        return Ok(None);
+    };
+    let mut impact = DeletePlanner::new(&workspace_root).impact(src)?;
+    impact.indexed_files = indexed_files(&app, &path)
+        .await
+        .into_iter()
+        .map(|file| file.file_path)
+        .collect();
+    Ok(Some(impact))
+}
+
+/// Move file/folder to the workspace trash, where it can be restored from,
+/// or to the OS trash for files outside a workspace. Returns the trash item
+/// when it went to the workspace trash. With `clean_up`, links to it are
+/// removed, pending agent changes to it rejected and its search index
+/// entries dropped along with it (see `fs_delete_impact`).
 #[tauri::command]
-pub async fn file_trash(path: String) -> Result<(), String> {
+pub async fn file_trash(
+    app: AppHandle,
+    path: String,
+    clean_up: Option<bool>,
+) -> Result<Option<TrashItem>, String> {
     let src = Path::new(&path);
 
     if !src.exists() {
         return Err(format!("Path does not exist: {}", path));
     }
 
-    // Also trash sidecar if exists (for files)
-    if src.is_file() {
-        let sidecar = format!("{}.sidecar.json", path);
-        if Path::new(&sidecar).exists() {
-            let _ = trash::delete(&sidecar);
+    let item = if let Some(workspace_root) = find_workspace_root(src) {
+        if clean_up.unwrap_or(false) {
+            let indexed = indexed_files(&app, &path).await;
+            let (item, impact) = DeletePlanner::new(&workspace_root).trash(src)?;
+            if !impact.inbound_links.is_empty() {
+                let paths: Vec<String> = impact
+                    .inbound_links
+                    .iter()
+                    .map(|rewrite| {
+                        workspace_root
+                            .join(&rewrite.path)
+                            .to_string_lossy()
+                            .to_string()
+                    })
+                    .collect();
+                let _ = app.emit("fs:links-rewritten", &paths);
+            }
+            remove_from_index(&app, indexed).await;
+            Some(item)
+        } else {
+            Some(TrashManager::new(&workspace_root).trash(src)?)
         }
-    }
+    } else {
+        // Also trash sidecar if exists (for files)
+        if src.is_file() {
+            let sidecar = format!("{}.sidecar.json", path);
+            if Path::new(&sidecar).exists() {
+                let _ = trash::delete(&sidecar);
+            }
+        }
+
+        trash::delete(&path).map_err(|e| e.to_string())?;
+        None
+    };
 
-    trash::delete(&path).map_err(|e| e.to_string())
+    RAG_INDEXER.path_removed(PathBuf::from(&path));
+    image_refs::path_removed(src);
+    pinned_documents::path_removed(src);
+    Ok(item)
 }
 
 /// Reveal file/folder in the OS file manager
@@ -453,11 +828,14 @@ pub async fn file_copy_to(
     Ok(BatchOperationResult { succeeded, failed })
 }
 
-/// Move multiple files/folders to a destination directory
+/// Move multiple files/folders to a destination directory, rewriting links
+/// to them unless `update_links` is false
 #[tauri::command]
 pub async fn file_move_to(
+    app: AppHandle,
     source_paths: Vec<String>,
     dest_dir: String,
+    update_links: Option<bool>,
 ) -> Result<BatchOperationResult, String> {
     let dest = Path::new(&dest_dir);
 
@@ -510,6 +888,11 @@ pub async fn file_move_to(
             dest_path
         };
 
+        let rewrites = match update_links.unwrap_or(true) {
+            true => plan_link_rewrites(src, &final_dest),
+            false => None,
+        };
+
         // Try rename first (same filesystem), fall back to copy+delete
         let result = fs::rename(&src_path, &final_dest)
             .or_else(|_| {
@@ -537,7 +920,15 @@ pub async fn file_move_to(
             });
 
         match result {
-            Ok(()) => succeeded.push(final_dest.to_string_lossy().to_string()),
+            Ok(()) => {
+                RAG_INDEXER.path_moved(PathBuf::from(&src_path), final_dest.clone());
+                image_refs::path_moved(Path::new(&src_path), &final_dest);
+                pinned_documents::path_moved(Path::new(&src_path), &final_dest);
+                if let Some(rewrites) = rewrites {
+                    apply_link_rewrites(&app, rewrites);
+                }
+                succeeded.push(final_dest.to_string_lossy().to_string())
+            }
             Err(e) => failed.push((src_path, e.to_string())),
         }
     }
@@ -547,6 +938,82 @@ pub async fn file_move_to(
 
 // ============== HELPER FUNCTIONS ==============
 
+/// Plan link rewrites for a move within a workspace. Failing to plan
+/// doesn't stop the move.
+fn plan_link_rewrites(from: &Path, to: &Path) -> Option<(LinkRewriter, Vec<LinkRewrite>)> {
+    let root = find_workspace_root(from)?;
+    if !to.starts_with(&root) {
+        return None;
+    }
+    let rewriter = LinkRewriter::new(&root);
+    match rewriter.plan(from, to) {
+        Ok(rewrites) if !rewrites.is_empty() => Some((rewriter, rewrites)),
+        Ok(_) => None,
+        Err(e) => {
+            tracing::warn!("Failed to plan link updates for {}: {}", from.display(), e);
+            None
+        }
+    }
+}
+
+/// Indexed files at or under a path, or none if the index isn't available
+async fn indexed_files(app: &AppHandle, path: &str) -> Vec<IndexedFile> {
+    let filter = FileFilter {
+        path_prefix: Some(path.to_string()),
+        ..Default::default()
+    };
+    let result = match rag::get_service(app).await {
+        Ok(service) => service.find_files(&filter).await.map_err(|e| e.to_string()),
+        Err(e) => Err(e),
+    };
+    let files = result.unwrap_or_else(|e| {
+        tracing::warn!("Failed to list indexed files under {}: {}", path, e);
+        Vec::new()
+    });
+    // The filter matches by string prefix, which includes "notes2" for "notes"
+    files
+        .into_iter()
+        .filter(|file| Path::new(&file.file_path).starts_with(path))
+        .collect()
+}
+
+/// Drop deleted files from the search index now rather than when the
+/// indexer next runs
+async fn remove_from_index(app: &AppHandle, files: Vec<IndexedFile>) {
+    if files.is_empty() {
+        return;
+    }
+    let Ok(service) = rag::get_service(app).await else {
+        return;
+    };
+    for file in files {
+        if let Err(e) = service
+            .remove_file(&file.project_path, &file.file_path)
+            .await
+        {
+            tracing::warn!("Failed to remove {} from the index: {}", file.file_path, e);
+        }
+    }
+}
+
+/// Write planned link rewrites, telling the frontend which documents changed
+/// so open editors can reload them
+fn apply_link_rewrites<R: Runtime>(
+    app: &AppHandle<R>,
+    (rewriter, rewrites): (LinkRewriter, Vec<LinkRewrite>),
+) {
+    match rewriter.apply(&rewrites) {
+        Ok(paths) => {
+            let paths: Vec<String> = paths
+                .iter()
+                .map(|p| p.to_string_lossy().to_string())
+                .collect();
+            let _ = app.emit("fs:links-rewritten", &paths);
+        }
+        Err(e) => tracing::warn!("Failed to update links: {}", e),
+    }
+}
+
 /// Recursively copy a directory
 fn copy_dir_recursive(src: &Path, dest: &Path) -> Result<(), String> {
     fs::create_dir_all(dest).map_err(|e| e.to_string())?;
@@ -1,8 +1,23 @@
 // Image commands - Upload, retrieve, and manage images
 
-use crate::services::image_manager::ImageManager;
+use crate::services::file_stream::{self, ChunkEncoding, FileChunk};
+use crate::services::image_manager::{mime_for_extension, ImageManager};
+use crate::services::image_optimizer::{self, OptimizeOptions, ThumbnailSize};
+use crate::services::image_refs::ImageRefStore;
+use crate::services::rag_indexer::RAG_INDEXER;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tracing::warn;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageChunk {
+    pub mime_type: String,
+    #[serde(flatten)]
+    pub chunk: FileChunk,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImageUploadResult {
@@ -10,9 +25,15 @@ pub struct ImageUploadResult {
     pub ref_id: String,
     pub success: bool,
     pub error: Option<String>,
+    /// Size of the stored image, when it was scaled on the way in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
 }
 
-/// Save an image to the workspace
+/// Save an image to the workspace. Thumbnails are generated and text in the
+/// image is recognized in the background, then added to the search index.
 #[tauri::command]
 pub async fn workspace_save_image(
     workspace_root: String,
@@ -26,19 +47,101 @@ pub async fn workspace_save_image(
         .store_image(&data_url, original_name.as_deref())
         .await
     {
-        Ok(ref_id) => Ok(ImageUploadResult {
-            ref_id,
-            success: true,
-            error: None,
+        Ok(ref_id) => {
+            spawn_post_processing(PathBuf::from(&workspace_root), ref_id.clone());
+            Ok(ImageUploadResult {
+                ref_id,
+                success: true,
+                error: None,
+                width: None,
+                height: None,
+            })
+        }
+        Err(e) => Ok(ImageUploadResult {
+            ref_id: String::new(),
+            success: false,
+            error: Some(e.to_string()),
+            width: None,
+            height: None,
         }),
+    }
+}
+
+/// Save the image on the clipboard to the workspace, scaled down and
+/// re-encoded according to `options`
+#[tauri::command]
+pub async fn workspace_save_image_from_clipboard(
+    app: AppHandle,
+    workspace_root: String,
+    options: Option<OptimizeOptions>,
+) -> Result<ImageUploadResult, String> {
+    let (rgba, width, height) = match app.clipboard().read_image() {
+        Ok(image) => (image.rgba().to_vec(), image.width(), image.height()),
+        Err(e) => {
+            return Ok(ImageUploadResult {
+                ref_id: String::new(),
+                success: false,
+                error: Some(format!("No image on the clipboard: {}", e)),
+                width: None,
+                height: None,
+            })
+        }
+    };
+
+    let options = options.unwrap_or_default();
+    let encoded = tokio::task::spawn_blocking(move || {
+        image_optimizer::optimize_rgba(rgba, width, height, &options)
+    })
+    .await
+    .map_err(|e| format!("Failed to process image: {}", e))??;
+
+    let manager = ImageManager::new(Path::new(&workspace_root));
+    manager.init().await.map_err(|e| e.to_string())?;
+
+    match manager
+        .store_image_bytes(&encoded.bytes, encoded.mime_type)
+        .await
+    {
+        Ok(ref_id) => {
+            spawn_post_processing(PathBuf::from(&workspace_root), ref_id.clone());
+            Ok(ImageUploadResult {
+                ref_id,
+                success: true,
+                error: None,
+                width: Some(encoded.width),
+                height: Some(encoded.height),
+            })
+        }
         Err(e) => Ok(ImageUploadResult {
             ref_id: String::new(),
             success: false,
             error: Some(e.to_string()),
+            width: None,
+            height: None,
         }),
     }
 }
 
+/// Get a thumbnail as a data URL. `path` is an image reference
+/// ("midlight://img-...") or the path of an image file in the workspace.
+#[tauri::command]
+pub async fn workspace_get_image_thumbnail(
+    workspace_root: String,
+    path: String,
+    size: Option<ThumbnailSize>,
+) -> Result<String, String> {
+    let manager = ImageManager::new(Path::new(&workspace_root));
+    let size = size.unwrap_or_default();
+
+    if path.starts_with("midlight://img-") {
+        manager.get_thumbnail_data_url(&path, size).await
+    } else {
+        let file_path = Path::new(&workspace_root).join(&path);
+        manager.get_file_thumbnail_data_url(&file_path, size).await
+    }
+    .map_err(|e| e.to_string())
+}
+
 /// Get an image as a data URL
 #[tauri::command]
 pub async fn workspace_get_image(workspace_root: String, ref_id: String) -> Result<String, String> {
@@ -49,6 +152,37 @@ pub async fn workspace_get_image(workspace_root: String, ref_id: String) -> Resu
         .map_err(|e| e.to_string())
 }
 
+/// Read part of an image as base64, so large images are fetched a chunk at
+/// a time rather than as one data URL. Call again from `nextOffset` until
+/// `done`.
+#[tauri::command]
+pub async fn workspace_read_image_chunk(
+    workspace_root: String,
+    ref_id: String,
+    offset: Option<u64>,
+    length: Option<usize>,
+) -> Result<ImageChunk, String> {
+    let manager = ImageManager::new(Path::new(&workspace_root));
+    let path = manager
+        .image_path(&ref_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let chunk = tokio::task::spawn_blocking(move || {
+        file_stream::read_chunk(&path, offset.unwrap_or(0), length, ChunkEncoding::Base64)
+    })
+    .await
+    .map_err(|e| format!("Task failed: {}", e))??;
+
+    Ok(ImageChunk {
+        mime_type: mime_for_extension(&extension).to_string(),
+        chunk,
+    })
+}
+
 /// Check if an image exists
 #[tauri::command]
 pub async fn workspace_image_exists(
@@ -59,16 +193,91 @@ pub async fn workspace_image_exists(
     Ok(manager.exists(&ref_id).await)
 }
 
-/// Delete an image
+/// Delete an image. Images still used by a saved document are kept.
 #[tauri::command]
 pub async fn workspace_delete_image(workspace_root: String, ref_id: String) -> Result<(), String> {
+    let hash = ref_id.strip_prefix("midlight://img-").unwrap_or(&ref_id);
+    let references = ImageRefStore::new(Path::new(&workspace_root)).reference_count(hash)?;
+    if references > 0 {
+        return Err(format!(
+            "Image is still used by {} document{}",
+            references,
+            if references == 1 { "" } else { "s" }
+        ));
+    }
+
     let manager = ImageManager::new(Path::new(&workspace_root));
     manager.delete(&ref_id).await.map_err(|e| e.to_string())
 }
 
+/// Number of saved documents that use an image
+#[tauri::command]
+pub async fn workspace_image_reference_count(
+    workspace_root: String,
+    ref_id: String,
+) -> Result<usize, String> {
+    let hash = ref_id.strip_prefix("midlight://img-").unwrap_or(&ref_id);
+    ImageRefStore::new(Path::new(&workspace_root)).reference_count(hash)
+}
+
 /// List all images in the workspace
 #[tauri::command]
 pub async fn workspace_list_images(workspace_root: String) -> Result<Vec<String>, String> {
     let manager = ImageManager::new(Path::new(&workspace_root));
     manager.list_images().await.map_err(|e| e.to_string())
 }
+
+/// Get the text recognized in an image, if any
+#[tauri::command]
+pub async fn workspace_get_image_text(
+    workspace_root: String,
+    ref_id: String,
+) -> Result<Option<String>, String> {
+    let manager = ImageManager::new(Path::new(&workspace_root));
+    manager
+        .get_image_text(&ref_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Recognize the text in an image now, e.g. for images added before OCR was
+/// available, and return it
+#[tauri::command]
+pub async fn workspace_extract_image_text(
+    workspace_root: String,
+    ref_id: String,
+) -> Result<Option<String>, String> {
+    let manager = ImageManager::new(Path::new(&workspace_root));
+    match manager
+        .extract_text(&ref_id)
+        .await
+        .map_err(|e| e.to_string())?
+    {
+        Some(text_path) => {
+            RAG_INDEXER.file_changed(text_path, false);
+            manager
+                .get_image_text(&ref_id)
+                .await
+                .map_err(|e| e.to_string())
+        }
+        None => Ok(None),
+    }
+}
+
+fn spawn_post_processing(workspace_root: PathBuf, ref_id: String) {
+    tauri::async_runtime::spawn(async move {
+        let manager = ImageManager::new(&workspace_root);
+        if let Err(e) = manager.generate_thumbnails(&ref_id).await {
+            warn!("Failed to generate thumbnails for {}: {}", ref_id, e);
+        }
+
+        if manager.text_path(&ref_id).exists() {
+            return;
+        }
+        match manager.extract_text(&ref_id).await {
+            Ok(Some(text_path)) => RAG_INDEXER.file_changed(text_path, false),
+            Ok(None) => {}
+            Err(e) => warn!("Failed to recognize text in {}: {}", ref_id, e),
+        }
+    });
+}
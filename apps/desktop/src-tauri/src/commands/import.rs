@@ -1,19 +1,34 @@
 // Import commands - IPC handlers for import/export operations
 
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
-use tauri::{AppHandle, Emitter, Runtime};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Runtime, State};
 use tokio::sync::oneshot;
 
+use crate::commands::automations::import_finished;
+use crate::commands::notifications::notify_operation;
+use crate::commands::operations::start_operation;
 use crate::services::docx_import::{analyze_docx, import_docx, DocxAnalysis, DocxImportResult};
+use crate::services::error::ImportError;
+use crate::services::image_manager::ImageManager;
+use crate::services::import_assets::fetch_remote_images;
+use crate::services::import_manifest::{
+    list_manifests, repeat_import, write_manifest, ImportManifest, ImportManifestInfo,
+    ImportSettings,
+};
 use crate::services::import_service::{
-    analyze_notion_export, analyze_obsidian_vault, detect_source_type, import_notion_export,
-    import_obsidian_vault, CancellationToken, ImportAnalysis, ImportOptions, ImportProgress,
-    ImportResult, ImportSourceType, NotionImportOptions,
+    analyze_generic_folder, analyze_notion_export, analyze_obsidian_vault, detect_source_type,
+    import_generic_folder, import_notion_export, import_obsidian_vault, ImportAnalysis,
+    ImportOptions, ImportProgress, ImportResult, ImportSourceType, NotionImportOptions,
+    ProgressCallback,
 };
-
-/// Global cancellation token for active import
-static ACTIVE_IMPORT_CANCEL: Mutex<Option<Arc<CancellationToken>>> = Mutex::new(None);
+use crate::services::notifications::Operation;
+use crate::services::operations::{OperationHandle, OperationKind};
+use crate::services::textbundle::{
+    bundle_title, bundle_to_tiptap, read_bundle, TextBundleImportResult,
+};
+use crate::AppState;
 
 /// Select a folder for import using native dialog
 #[tauri::command]
@@ -66,6 +81,17 @@ pub async fn import_analyze_notion(export_path: String) -> Result<ImportAnalysis
         .map_err(|e| e.to_string())
 }
 
+/// Analyze a generic folder of Markdown files
+#[tauri::command]
+pub async fn import_analyze_generic(folder_path: String) -> Result<ImportAnalysis, String> {
+    let path = PathBuf::from(&folder_path);
+
+    tokio::task::spawn_blocking(move || analyze_generic_folder(&path))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+        .map_err(|e| e.to_string())
+}
+
 /// Import an Obsidian vault
 #[tauri::command]
 pub async fn import_obsidian<R: Runtime>(
@@ -83,40 +109,35 @@ pub async fn import_obsidian<R: Runtime>(
 
     let dest = PathBuf::from(&dest_path);
 
-    // Create cancellation token
-    let cancel_token = CancellationToken::new();
-
-    // Store cancel token globally
-    {
-        let mut active = ACTIVE_IMPORT_CANCEL.lock().unwrap();
-        *active = Some(cancel_token.clone());
-    }
-
-    // Create progress callback that emits events
-    let app_handle = app.clone();
-    let progress_callback = Box::new(move |progress: ImportProgress| {
-        let _ = app_handle.emit("import-progress", &progress);
-    });
+    // Track the import as a cancellable operation
+    let (operation, progress_callback) = start_import(&app, "Importing Obsidian vault");
+    let cancel_token = operation.cancel_token();
 
     // Run import in blocking task
     let result = tokio::task::spawn_blocking(move || {
-        import_obsidian_vault(
+        let mut result = import_obsidian_vault(
             &analysis,
             &dest,
             &options,
             Some(progress_callback),
             Some(cancel_token),
-        )
+        )?;
+        if options.fetch_remote_images {
+            fetch_remote_images(&dest, &mut result);
+        }
+        write_manifest(
+            &analysis,
+            &dest,
+            ImportSettings::Obsidian(options),
+            &mut result,
+        );
+        Ok(result)
     })
     .await
     .map_err(|e| format!("Task join error: {}", e))?;
 
-    // Clear cancellation token
-    {
-        let mut active = ACTIVE_IMPORT_CANCEL.lock().unwrap();
-        *active = None;
-    }
-
+    operation.finish(&result);
+    notify_import_finished(&app, &dest_path, &result);
     result.map_err(|e| e.to_string())
 }
 
@@ -137,49 +158,159 @@ pub async fn import_notion<R: Runtime>(
 
     let dest = PathBuf::from(&dest_path);
 
-    // Create cancellation token
-    let cancel_token = CancellationToken::new();
+    // Track the import as a cancellable operation
+    let (operation, progress_callback) = start_import(&app, "Importing Notion export");
+    let cancel_token = operation.cancel_token();
 
-    // Store cancel token globally
-    {
-        let mut active = ACTIVE_IMPORT_CANCEL.lock().unwrap();
-        *active = Some(cancel_token.clone());
-    }
+    // Run import
+    let result = tokio::task::spawn_blocking(move || {
+        let mut result = import_notion_export(
+            &analysis,
+            &dest,
+            &options,
+            Some(progress_callback),
+            Some(cancel_token),
+        )?;
+        if options.base.fetch_remote_images {
+            fetch_remote_images(&dest, &mut result);
+        }
+        write_manifest(
+            &analysis,
+            &dest,
+            ImportSettings::Notion(options),
+            &mut result,
+        );
+        Ok(result)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?;
 
-    // Create progress callback
-    let app_handle = app.clone();
-    let progress_callback = Box::new(move |progress: ImportProgress| {
-        let _ = app_handle.emit("import-progress", &progress);
-    });
+    operation.finish(&result);
+    notify_import_finished(&app, &dest_path, &result);
+    result.map_err(|e| e.to_string())
+}
+
+/// Import a generic folder of Markdown files
+#[tauri::command]
+pub async fn import_generic<R: Runtime>(
+    app: AppHandle<R>,
+    analysis_json: String,
+    dest_path: String,
+    options_json: String,
+) -> Result<ImportResult, String> {
+    let analysis: ImportAnalysis =
+        serde_json::from_str(&analysis_json).map_err(|e| format!("Invalid analysis: {}", e))?;
+
+    let options: ImportOptions =
+        serde_json::from_str(&options_json).map_err(|e| format!("Invalid options: {}", e))?;
+
+    let dest = PathBuf::from(&dest_path);
+
+    let (operation, progress_callback) = start_import(&app, "Importing folder");
+    let cancel_token = operation.cancel_token();
 
-    // Run import
     let result = tokio::task::spawn_blocking(move || {
-        import_notion_export(
+        let mut result = import_generic_folder(
             &analysis,
             &dest,
             &options,
             Some(progress_callback),
             Some(cancel_token),
-        )
+        )?;
+        if options.fetch_remote_images {
+            fetch_remote_images(&dest, &mut result);
+        }
+        write_manifest(
+            &analysis,
+            &dest,
+            ImportSettings::Generic(options),
+            &mut result,
+        );
+        Ok(result)
     })
     .await
     .map_err(|e| format!("Task join error: {}", e))?;
 
-    // Clear cancellation token
-    {
-        let mut active = ACTIVE_IMPORT_CANCEL.lock().unwrap();
-        *active = None;
-    }
+    operation.finish(&result);
+    notify_import_finished(&app, &dest_path, &result);
+    result.map_err(|e| e.to_string())
+}
+
+/// Re-run an earlier import from its manifest, importing only the source
+/// files that are new or changed since
+#[tauri::command]
+pub async fn import_repeat<R: Runtime>(
+    app: AppHandle<R>,
+    manifest_path: String,
+) -> Result<ImportResult, String> {
+    let path = PathBuf::from(&manifest_path);
+    let dest_path = ImportManifest::load(&path)
+        .map_err(|e| e.to_string())?
+        .dest_path;
+
+    let (operation, progress_callback) = start_import(&app, "Repeating import");
+    let cancel_token = operation.cancel_token();
+
+    let result = tokio::task::spawn_blocking(move || {
+        repeat_import(&path, Some(progress_callback), Some(cancel_token))
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?;
 
+    operation.finish(&result);
+    notify_import_finished(&app, &dest_path, &result);
     result.map_err(|e| e.to_string())
 }
 
-/// Cancel an active import
+/// Manifests of earlier imports into a folder, most recent first
 #[tauri::command]
-pub async fn import_cancel() -> Result<(), String> {
-    let active = ACTIVE_IMPORT_CANCEL.lock().unwrap();
-    if let Some(token) = active.as_ref() {
-        token.cancel();
+pub async fn import_list_manifests(dest_path: String) -> Result<Vec<ImportManifestInfo>, String> {
+    list_manifests(Path::new(&dest_path)).map_err(|e| e.to_string())
+}
+
+/// Register an import as an operation, with a progress callback that reports
+/// to it as well as through "import-progress" events
+fn start_import<R: Runtime>(
+    app: &AppHandle<R>,
+    label: &str,
+) -> (Arc<OperationHandle>, ProgressCallback) {
+    let operation = Arc::new(start_operation(app, OperationKind::Import, label));
+    let reporter = operation.clone();
+    let app_handle = app.clone();
+    let progress_callback = Box::new(move |progress: ImportProgress| {
+        reporter.progress(
+            progress.phase.as_str(),
+            progress.current,
+            progress.total,
+            Some(progress.current_file.clone()),
+        );
+        let _ = app_handle.emit("import-progress", &progress);
+    });
+    (operation, progress_callback)
+}
+
+/// Tell the user and the workspace's automations that an import finished,
+/// unless it was cancelled
+fn notify_import_finished<R: Runtime>(
+    app: &AppHandle<R>,
+    dest_path: &str,
+    result: &Result<ImportResult, ImportError>,
+) {
+    let outcome = match result {
+        Err(ImportError::Cancelled) => return,
+        Ok(result) => {
+            import_finished(app, Path::new(dest_path), result.files_imported);
+            Ok(format!("Imported {} files", result.files_imported))
+        }
+        Err(e) => Err(e.to_string()),
+    };
+    notify_operation(app, Operation::Import, outcome);
+}
+
+/// Cancel active imports
+#[tauri::command]
+pub async fn import_cancel(state: State<'_, AppState>) -> Result<(), String> {
+    if state.operations.cancel_kind(OperationKind::Import) > 0 {
         Ok(())
     } else {
         Err("No active import to cancel".into())
@@ -308,3 +439,42 @@ fn get_image_extension(content_type: &str) -> &str {
         _ => "png",
     }
 }
+
+// ============================================================================
+// TextBundle Import Commands
+// ============================================================================
+
+/// Import a .textbundle folder or .textpack archive, storing its images in
+/// the workspace. Returns the document for the frontend to save.
+#[tauri::command]
+pub async fn import_textbundle(
+    file_path: String,
+    workspace_root: String,
+) -> Result<TextBundleImportResult, String> {
+    let path = PathBuf::from(&file_path);
+    let bundle = {
+        let path = path.clone();
+        tokio::task::spawn_blocking(move || read_bundle(&path))
+            .await
+            .map_err(|e| format!("Task join error: {}", e))??
+    };
+
+    let images = ImageManager::new(Path::new(&workspace_root));
+    images.init().await.map_err(|e| e.to_string())?;
+    let mut stored = HashMap::new();
+    for asset in bundle.assets.iter().filter(|a| a.is_image()) {
+        let reference = images
+            .store_image_bytes(&asset.data, asset.mime_type())
+            .await
+            .map_err(|e| format!("Failed to save image: {}", e))?;
+        stored.insert(asset.path.clone(), reference);
+    }
+
+    let (tiptap_json, missing_assets) = bundle_to_tiptap(&bundle, &stored);
+    Ok(TextBundleImportResult {
+        title: bundle_title(&tiptap_json, &path),
+        tiptap_json,
+        image_count: stored.len(),
+        missing_assets,
+    })
+}
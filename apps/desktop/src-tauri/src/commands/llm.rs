@@ -1,13 +1,22 @@
 // LLM Commands - Tauri IPC handlers for LLM functionality
 
+use crate::services::context_profiles::ContextProfileStore;
+use crate::services::generation_params::{self, ModelCapabilities};
+use crate::services::llm_cache::CacheStats;
+use crate::services::llm_routing::RoutingPolicy;
 use crate::services::llm_service::{
-    AvailableModels, ChatMessage, ChatRequest, ChatResponse, ChatWithToolsRequest, LLMError,
-    LLMStatus, QuotaInfo, StreamChunk, ToolDefinition, LLM_SERVICE,
+    routing_policy_path, AvailableModels, ChatMessage, ChatRequest, ChatResponse,
+    ChatWithToolsRequest, LLMError, LLMStatus, QuotaInfo, StreamChunk, ToolDefinition, LLM_SERVICE,
 };
+use crate::services::quota_tracker::{quota_alerts_path, QuotaAlertSettings, TrackedQuota};
+use crate::services::request_queue::{is_retryable_llm_error, QueuedPayload, REQUEST_QUEUE};
+use crate::services::structured_output::{self, StructuredResponse};
+use crate::services::token_counter::{self, EstimateRequest, TokenEstimate};
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 use tauri::{AppHandle, Emitter};
 use tokio::sync::mpsc;
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
 
 // ============================================================================
 // Command Input Types
@@ -24,9 +33,23 @@ pub struct ChatOptions {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_tokens: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub json_mode: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub request_type: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub web_search_enabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bypass_cache: Option<bool>,
+    /// Workspace and document the request originates from; used to apply
+    /// the document's context profile
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace_root: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document_path: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,6 +62,18 @@ pub struct ChatWithToolsOptions {
     pub tool_choice: Option<serde_json::Value>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructuredChatOptions {
+    #[serde(flatten)]
+    pub base: ChatOptions,
+    /// JSON schema the response must match
+    pub schema: serde_json::Value,
+    /// How many times the model may be asked to fix invalid output
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_repair_attempts: Option<u32>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct StreamOptions {
@@ -85,20 +120,90 @@ pub struct StreamErrorEvent {
 // ============================================================================
 
 /// Emit session expired event when AUTH_REQUIRED error occurs
-fn emit_session_expired_if_auth_error(app: &AppHandle, error: &LLMError) {
+pub(crate) fn emit_session_expired_if_auth_error(app: &AppHandle, error: &LLMError) {
     if error.code == "AUTH_REQUIRED" {
         debug!("Emitting auth:session-expired event due to AUTH_REQUIRED error");
         let _ = app.emit("auth:session-expired", ());
     }
 }
 
+/// Send quota changes to the frontend as "llm:quota-updated", plus
+/// "llm:quota-threshold" when usage crosses an alert threshold
+pub fn start_quota_events(app: AppHandle) {
+    LLM_SERVICE.set_quota_listener(std::sync::Arc::new(move |event| {
+        if event.threshold.is_some() {
+            if let Err(e) = app.emit("llm:quota-threshold", &event) {
+                error!("Failed to emit quota threshold event: {}", e);
+            }
+        }
+        let _ = app.emit("llm:quota-updated", &event);
+    }));
+}
+
+/// Merge the context profile of the originating document into the request.
+/// Profile model settings override the request; temperature only fills in
+/// when the request doesn't set one.
+fn apply_context_profile(options: &mut ChatOptions) {
+    let (Some(workspace_root), Some(document_path)) =
+        (&options.workspace_root, &options.document_path)
+    else {
+        return;
+    };
+
+    match ContextProfileStore::new(Path::new(workspace_root)).resolve(document_path) {
+        Ok(profile) => {
+            profile.apply_to_messages(&mut options.messages);
+            if let Some(provider) = profile.provider {
+                options.provider = provider;
+            }
+            if let Some(model) = profile.model {
+                options.model = model;
+            }
+            if options.temperature.is_none() {
+                options.temperature = profile.temperature;
+            }
+        }
+        Err(e) => warn!(
+            "Failed to resolve context profile for {}: {}",
+            document_path, e
+        ),
+    }
+}
+
+/// Queue a request that failed transiently so the outbox worker can retry it.
+/// Returns a QUEUED error carrying the queue id, or the original error if the
+/// failure isn't retryable or the outbox is full.
+fn queue_if_retryable(payload: QueuedPayload, error: LLMError) -> LLMError {
+    if !is_retryable_llm_error(&error.code) {
+        return error;
+    }
+
+    match REQUEST_QUEUE.enqueue(payload, &error.to_string()) {
+        Ok(queue_id) => LLMError {
+            code: "QUEUED".to_string(),
+            message: format!("Request queued for retry: {}", error.message),
+            details: Some(serde_json::json!({
+                "queueId": queue_id,
+                "reason": error.code,
+            })),
+        },
+        Err(e) => {
+            warn!("Failed to queue LLM request: {}", e);
+            error
+        }
+    }
+}
+
 /// Send a chat message (non-streaming)
+/// Transient failures are queued; the result arrives later via 'queue:delivered'
 #[tauri::command]
 pub async fn llm_chat(
     app: AppHandle,
-    options: ChatOptions,
+    mut options: ChatOptions,
     auth_token: Option<String>,
 ) -> Result<ChatResponse, String> {
+    apply_context_profile(&mut options);
+
     debug!(
         "llm_chat: provider={}, model={}, has_token={}",
         options.provider,
@@ -112,9 +217,16 @@ pub async fn llm_chat(
         messages: options.messages,
         temperature: options.temperature,
         max_tokens: options.max_tokens,
+        top_p: options.top_p,
+        stop: options.stop,
+        json_mode: options.json_mode,
         stream: Some(false),
         request_type: options.request_type,
         web_search_enabled: options.web_search_enabled,
+        bypass_cache: options.bypass_cache.unwrap_or(false),
+    };
+    let payload = QueuedPayload::LlmChat {
+        request: request.clone(),
     };
 
     LLM_SERVICE
@@ -122,7 +234,7 @@ pub async fn llm_chat(
         .await
         .map_err(|e| {
             emit_session_expired_if_auth_error(&app, &e);
-            e.to_string()
+            queue_if_retryable(payload, e).to_string()
         })
 }
 
@@ -132,9 +244,11 @@ pub async fn llm_chat(
 #[tauri::command]
 pub async fn llm_chat_stream(
     app: AppHandle,
-    options: StreamOptions,
+    mut options: StreamOptions,
     auth_token: Option<String>,
 ) -> Result<(), String> {
+    apply_context_profile(&mut options.base);
+
     let stream_id = options.stream_id.clone();
     debug!(
         "llm_chat_stream: provider={}, model={}, stream_id={}, has_token={}",
@@ -150,9 +264,13 @@ pub async fn llm_chat_stream(
         messages: options.base.messages,
         temperature: options.base.temperature,
         max_tokens: options.base.max_tokens,
+        top_p: options.base.top_p,
+        stop: options.base.stop,
+        json_mode: options.base.json_mode,
         stream: Some(true),
         request_type: options.base.request_type,
         web_search_enabled: options.base.web_search_enabled,
+        bypass_cache: options.base.bypass_cache.unwrap_or(false),
     };
 
     // Create channel for stream chunks
@@ -203,12 +321,15 @@ pub async fn llm_chat_stream(
 }
 
 /// Send a chat message with tools (non-streaming)
+/// Transient failures are queued; the result arrives later via 'queue:delivered'
 #[tauri::command]
 pub async fn llm_chat_with_tools(
     app: AppHandle,
-    options: ChatWithToolsOptions,
+    mut options: ChatWithToolsOptions,
     auth_token: Option<String>,
 ) -> Result<ChatResponse, String> {
+    apply_context_profile(&mut options.base);
+
     debug!(
         "llm_chat_with_tools: provider={}, model={}, tools={}",
         options.base.provider,
@@ -223,20 +344,27 @@ pub async fn llm_chat_with_tools(
             messages: options.base.messages,
             temperature: options.base.temperature,
             max_tokens: options.base.max_tokens,
+            top_p: options.base.top_p,
+            stop: options.base.stop,
+            json_mode: options.base.json_mode,
             stream: Some(false),
             request_type: options.base.request_type,
             web_search_enabled: options.base.web_search_enabled,
+            bypass_cache: options.base.bypass_cache.unwrap_or(false),
         },
         tools: options.tools,
         tool_choice: options.tool_choice,
     };
+    let payload = QueuedPayload::LlmChatWithTools {
+        request: request.clone(),
+    };
 
     LLM_SERVICE
         .chat_with_tools(request, auth_token.as_deref())
         .await
         .map_err(|e| {
             emit_session_expired_if_auth_error(&app, &e);
-            e.to_string()
+            queue_if_retryable(payload, e).to_string()
         })
 }
 
@@ -244,9 +372,11 @@ pub async fn llm_chat_with_tools(
 #[tauri::command]
 pub async fn llm_chat_with_tools_stream(
     app: AppHandle,
-    options: StreamWithToolsOptions,
+    mut options: StreamWithToolsOptions,
     auth_token: Option<String>,
 ) -> Result<(), String> {
+    apply_context_profile(&mut options.base.base);
+
     let stream_id = options.stream_id.clone();
     debug!(
         "llm_chat_with_tools_stream: provider={}, model={}, tools={}, stream_id={}",
@@ -263,9 +393,13 @@ pub async fn llm_chat_with_tools_stream(
             messages: options.base.base.messages,
             temperature: options.base.base.temperature,
             max_tokens: options.base.base.max_tokens,
+            top_p: options.base.base.top_p,
+            stop: options.base.base.stop,
+            json_mode: options.base.base.json_mode,
             stream: Some(true),
             request_type: options.base.base.request_type,
             web_search_enabled: options.base.base.web_search_enabled,
+            bypass_cache: options.base.base.bypass_cache.unwrap_or(false),
         },
         tools: options.base.tools,
         tool_choice: options.base.tool_choice,
@@ -318,6 +452,56 @@ pub async fn llm_chat_with_tools_stream(
     }
 }
 
+/// Send a chat message that must be answered with JSON matching a schema
+/// Fails with VALIDATION_FAILED, listing each mismatch, if the output can't
+/// be repaired
+#[tauri::command]
+pub async fn llm_chat_structured(
+    app: AppHandle,
+    mut options: StructuredChatOptions,
+    auth_token: Option<String>,
+) -> Result<StructuredResponse, String> {
+    apply_context_profile(&mut options.base);
+
+    debug!(
+        "llm_chat_structured: provider={}, model={}, has_token={}",
+        options.base.provider,
+        options.base.model,
+        auth_token.is_some()
+    );
+
+    let base = options.base;
+    let request = ChatRequest {
+        provider: base.provider,
+        model: base.model,
+        messages: base.messages,
+        temperature: base.temperature,
+        max_tokens: base.max_tokens,
+        top_p: base.top_p,
+        stop: base.stop,
+        json_mode: Some(true),
+        stream: Some(false),
+        request_type: base.request_type,
+        web_search_enabled: base.web_search_enabled,
+        bypass_cache: base.bypass_cache.unwrap_or(false),
+    };
+
+    LLM_SERVICE
+        .chat_structured(
+            request,
+            &options.schema,
+            options
+                .max_repair_attempts
+                .unwrap_or(structured_output::DEFAULT_REPAIR_ATTEMPTS),
+            auth_token.as_deref(),
+        )
+        .await
+        .map_err(|e| {
+            emit_session_expired_if_auth_error(&app, &e);
+            e.to_string()
+        })
+}
+
 /// Get available models
 #[tauri::command]
 pub async fn llm_get_models(auth_token: Option<String>) -> Result<AvailableModels, String> {
@@ -350,3 +534,77 @@ pub async fn llm_get_status(auth_token: Option<String>) -> Result<LLMStatus, Str
         .await
         .map_err(|e| e.to_string())
 }
+
+/// Get response cache statistics
+#[tauri::command]
+pub fn llm_get_cache_stats() -> CacheStats {
+    LLM_SERVICE.cache_stats()
+}
+
+/// Clear the response cache
+#[tauri::command]
+pub fn llm_clear_cache() {
+    debug!("llm_clear_cache");
+    LLM_SERVICE.clear_cache();
+}
+
+/// Estimate token counts and cost per model before sending a request
+/// Tokenizes locally, so it works offline and doesn't consume quota
+#[tauri::command]
+pub async fn llm_estimate(options: EstimateRequest) -> Result<TokenEstimate, String> {
+    debug!(
+        "llm_estimate: models={:?}, messages={}, context={}",
+        options.models,
+        options.messages.len(),
+        options.context.len()
+    );
+
+    tokio::task::spawn_blocking(move || token_counter::estimate(&options))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))
+}
+
+/// Get the generation parameters a model accepts, so the UI can hide
+/// unsupported controls
+#[tauri::command]
+pub fn llm_get_model_capabilities(model: String) -> ModelCapabilities {
+    generation_params::capabilities_for_model(&model)
+}
+
+/// Get the provider routing and failover policy
+#[tauri::command]
+pub fn llm_get_routing_policy() -> RoutingPolicy {
+    LLM_SERVICE.routing_policy()
+}
+
+/// Validate, save and apply a provider routing and failover policy
+#[tauri::command]
+pub fn llm_set_routing_policy(policy: RoutingPolicy) -> Result<(), String> {
+    debug!("llm_set_routing_policy: {} rules", policy.rules.len());
+    policy.validate()?;
+    policy.save(&routing_policy_path())?;
+    LLM_SERVICE.set_routing_policy(policy);
+    Ok(())
+}
+
+/// Get the locally tracked quota, kept current from LLM responses
+#[tauri::command]
+pub fn llm_get_tracked_quota() -> Option<TrackedQuota> {
+    LLM_SERVICE.quota_tracker().current()
+}
+
+/// Get the quota usage alert thresholds
+#[tauri::command]
+pub fn llm_get_quota_alerts() -> QuotaAlertSettings {
+    LLM_SERVICE.quota_tracker().settings()
+}
+
+/// Validate, save and apply quota usage alert thresholds
+#[tauri::command]
+pub fn llm_set_quota_alerts(settings: QuotaAlertSettings) -> Result<(), String> {
+    debug!("llm_set_quota_alerts: {:?}", settings.thresholds);
+    settings.validate()?;
+    settings.save(&quota_alerts_path())?;
+    LLM_SERVICE.quota_tracker().set_settings(settings);
+    Ok(())
+}
@@ -0,0 +1,109 @@
+// Local API commands - Turn the local HTTP server on and off
+//
+// The server is started at launch when it's enabled in settings. Documents
+// appended to through it are announced with a "local-api:document-changed"
+// event so an open editor can reload them.
+
+use crate::services::local_api::{
+    generate_token, local_api_settings_path, ApiContext, LocalApiServer, LocalApiSettings,
+};
+use crate::AppState;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Mutex;
+use tracing::{error, info};
+
+lazy_static! {
+    /// Token clients must send, new every launch
+    static ref TOKEN: String = generate_token();
+    static ref SERVER: Mutex<Option<LocalApiServer>> = Mutex::new(None);
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalApiStatus {
+    pub running: bool,
+    pub port: Option<u16>,
+    /// Token for this session, for pasting into other tools
+    pub token: String,
+}
+
+/// Start the server if it's enabled
+pub fn start_local_api(app: &AppHandle) {
+    let settings = LocalApiSettings::load(&local_api_settings_path());
+    if !settings.enabled {
+        return;
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = apply_settings(&app, &settings).await {
+            error!("{}", e);
+        }
+    });
+}
+
+/// Stop the running server, then start it again if enabled
+async fn apply_settings(app: &AppHandle, settings: &LocalApiSettings) -> Result<(), String> {
+    let mut server = SERVER.lock().await;
+    if let Some(running) = server.take() {
+        running.stop().await;
+    }
+    if settings.enabled {
+        *server = Some(LocalApiServer::start(api_context(app, settings.port)).await?);
+    }
+    Ok(())
+}
+
+fn api_context(app: &AppHandle, port: u16) -> ApiContext {
+    let emitter = app.clone();
+    ApiContext {
+        token: TOKEN.clone(),
+        port,
+        registry: app.state::<AppState>().workspace_registry.clone(),
+        on_append: Arc::new(move |event| {
+            if let Err(e) = emitter.emit("local-api:document-changed", &event) {
+                error!("Failed to emit local API event: {}", e);
+            }
+        }),
+    }
+}
+
+async fn status() -> LocalApiStatus {
+    let port = SERVER.lock().await.as_ref().map(LocalApiServer::port);
+    LocalApiStatus {
+        running: port.is_some(),
+        port,
+        token: TOKEN.clone(),
+    }
+}
+
+/// Get the local API settings
+#[tauri::command]
+pub fn local_api_get_settings() -> LocalApiSettings {
+    LocalApiSettings::load(&local_api_settings_path())
+}
+
+/// Save local API settings and start or stop the server to match
+#[tauri::command]
+pub async fn local_api_set_settings(
+    app: AppHandle,
+    settings: LocalApiSettings,
+) -> Result<LocalApiStatus, String> {
+    info!(
+        "Saving local API settings (enabled: {}, port: {})",
+        settings.enabled, settings.port
+    );
+    settings.validate()?;
+    settings.save(&local_api_settings_path())?;
+    apply_settings(&app, &settings).await?;
+    Ok(status().await)
+}
+
+/// Whether the server is running, and the token it expects
+#[tauri::command]
+pub async fn local_api_get_status() -> LocalApiStatus {
+    status().await
+}
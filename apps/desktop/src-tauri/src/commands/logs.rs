@@ -0,0 +1,21 @@
+// Log commands - Browse the app's log files
+
+use crate::services::log_files::{LogFileInfo, DEFAULT_TAIL_LINES, LOGS};
+use tracing::debug;
+
+/// List log files, newest first
+#[tauri::command]
+pub async fn logs_list() -> Result<Vec<LogFileInfo>, String> {
+    debug!("logs_list command");
+    Ok(LOGS.list())
+}
+
+/// Read the end of a log file (200 lines unless asked for more)
+#[tauri::command]
+pub async fn logs_read_tail(name: String, lines: Option<usize>) -> Result<String, String> {
+    debug!("logs_read_tail command: {}", name);
+
+    tokio::task::spawn_blocking(move || LOGS.read_tail(&name, lines.unwrap_or(DEFAULT_TAIL_LINES)))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
@@ -0,0 +1,28 @@
+// Markdown commands - Convert between Markdown and Tiptap documents with the
+// same converter the agent, import/export and search indexing use
+
+use crate::services::markdown_convert::{markdown_to_tiptap, tiptap_to_markdown, MarkdownOptions};
+use serde_json::Value;
+
+/// Convert Markdown to a Tiptap `doc`
+#[tauri::command]
+pub fn convert_markdown_to_document(markdown: String, options: Option<MarkdownOptions>) -> Value {
+    markdown_to_tiptap(&markdown, options.unwrap_or_default())
+}
+
+/// Convert a Tiptap document to Markdown. Accepts the `doc` node or a whole
+/// .midlight document.
+#[tauri::command]
+pub fn convert_document_to_markdown(
+    document: Value,
+    options: Option<MarkdownOptions>,
+) -> Result<String, String> {
+    let doc = match document.get("content") {
+        Some(content) if content.is_object() => content,
+        _ => &document,
+    };
+    if doc.get("type").and_then(Value::as_str) != Some("doc") {
+        return Err("Not a Tiptap document".to_string());
+    }
+    Ok(tiptap_to_markdown(doc, options.unwrap_or_default()))
+}
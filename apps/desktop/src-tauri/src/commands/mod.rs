@@ -1,17 +1,50 @@
 // Tauri commands - IPC handlers for frontend
 
 pub mod agent;
+pub mod audio;
 pub mod auth;
+pub mod automations;
+pub mod citations;
+pub mod clipper;
+pub mod context_profiles;
+pub mod conversations;
+pub mod custom_tools;
+pub mod deep_link;
 pub mod error_reporter;
 pub mod export;
+pub mod external_editor;
 pub mod file_watcher;
+pub mod flashcards;
 pub mod fs;
 pub mod images;
 pub mod import;
 pub mod llm;
+pub mod local_api;
+pub mod logs;
+pub mod markdown;
+pub mod network;
+pub mod notifications;
+pub mod operations;
+pub mod outline;
+pub mod pdf;
+pub mod periodic_notes;
+pub mod pinned_documents;
+pub mod plugins;
+pub mod power;
+pub mod prompt_templates;
+pub mod queue;
 pub mod rag;
+pub mod readability;
 pub mod recovery;
+pub mod saved_searches;
+pub mod session;
+pub mod spellcheck;
+pub mod support;
 pub mod system;
+pub mod tables;
+pub mod tasks;
+pub mod trash;
 pub mod updates;
 pub mod versions;
 pub mod workspace;
+pub mod writing_stats;
@@ -0,0 +1,20 @@
+// Network commands - proxy and custom certificate settings
+
+use crate::services::network_config::{self, NetworkSettings};
+use tracing::info;
+
+/// Get the saved network settings
+#[tauri::command]
+pub fn network_get_settings() -> NetworkSettings {
+    network_config::current_settings()
+}
+
+/// Validate and save network settings. Takes effect after restart.
+#[tauri::command]
+pub fn network_set_settings(settings: NetworkSettings) -> Result<(), String> {
+    info!(
+        "Saving network settings (proxy: {})",
+        settings.proxy_url.is_some()
+    );
+    network_config::update_settings(settings)
+}
@@ -0,0 +1,55 @@
+// Notification commands - Settings and the helper other commands notify through
+
+use crate::services::notifications::{
+    notification_settings_path, notification_text, NotificationSettings, Operation,
+};
+use lazy_static::lazy_static;
+use std::sync::RwLock;
+use tauri::{AppHandle, Manager, Runtime};
+use tauri_plugin_notification::NotificationExt;
+use tracing::{debug, warn};
+
+lazy_static! {
+    static ref SETTINGS: RwLock<NotificationSettings> =
+        RwLock::new(NotificationSettings::load(&notification_settings_path()));
+}
+
+/// Show a system notification for a finished operation, unless notifications
+/// are off or the user is looking at the app
+pub fn notify_operation<R: Runtime>(
+    app: &AppHandle<R>,
+    operation: Operation,
+    result: Result<String, String>,
+) {
+    if !SETTINGS.read().unwrap().enabled {
+        return;
+    }
+    let focused = app
+        .get_webview_window("main")
+        .and_then(|window| window.is_focused().ok())
+        .unwrap_or(false);
+    if focused {
+        return;
+    }
+
+    let (title, body) = notification_text(operation, &result);
+    debug!("Notifying: {}", title);
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        warn!("Failed to show notification: {}", e);
+    }
+}
+
+/// Get notification settings
+#[tauri::command]
+pub fn notifications_get_settings() -> NotificationSettings {
+    SETTINGS.read().unwrap().clone()
+}
+
+/// Save and apply notification settings
+#[tauri::command]
+pub fn notifications_set_settings(settings: NotificationSettings) -> Result<(), String> {
+    debug!("notifications_set_settings: enabled={}", settings.enabled);
+    settings.save(&notification_settings_path())?;
+    *SETTINGS.write().unwrap() = settings;
+    Ok(())
+}
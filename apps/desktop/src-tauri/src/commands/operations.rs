@@ -0,0 +1,43 @@
+// Operation commands - Following and cancelling long-running work
+//
+// Imports, exports and indexing started from other commands report through
+// "operation:progress" events; these commands list and cancel them.
+
+use crate::services::operations::{OperationHandle, OperationKind, OperationProgress};
+use crate::AppState;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+use tracing::warn;
+
+/// Register an operation whose updates are emitted as "operation:progress"
+pub fn start_operation<R: Runtime>(
+    app: &AppHandle<R>,
+    kind: OperationKind,
+    label: impl Into<String>,
+) -> OperationHandle {
+    let emitter = app.clone();
+    app.state::<AppState>().operations.start(
+        kind,
+        label,
+        Box::new(move |progress: &OperationProgress| {
+            if let Err(e) = emitter.emit("operation:progress", progress) {
+                warn!("Failed to emit operation progress: {}", e);
+            }
+        }),
+    )
+}
+
+/// Cancel a running operation by id
+#[tauri::command]
+pub fn operation_cancel(state: State<'_, AppState>, id: u64) -> Result<(), String> {
+    if state.operations.cancel(id) {
+        Ok(())
+    } else {
+        Err(format!("No running operation {}", id))
+    }
+}
+
+/// Operations currently running, for a progress UI opened after they started
+#[tauri::command]
+pub fn operation_list(state: State<'_, AppState>) -> Vec<OperationProgress> {
+    state.operations.list()
+}
@@ -0,0 +1,36 @@
+// Outline commands - Heading trees and anchor lookup for documents
+
+use crate::services::document_chunks::resolve_content;
+use crate::services::document_outline::{document_outline, find_heading, OutlineHeading};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/// Read and parse a .midlight document
+pub(crate) fn read_document(path: &str) -> Result<Value, String> {
+    if !path.ends_with(".midlight") {
+        return Err(format!("Not a .midlight document: {}", path));
+    }
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read document: {}", e))?;
+    let mut document =
+        serde_json::from_str(&content).map_err(|e| format!("Failed to parse document: {}", e))?;
+    resolve_content(Path::new(path), &mut document);
+    Ok(document)
+}
+
+/// The heading tree of a document, with anchor ids and positions
+#[tauri::command]
+pub fn document_get_outline(path: String) -> Result<Vec<OutlineHeading>, String> {
+    Ok(document_outline(&read_document(&path)?))
+}
+
+/// Where the heading an `#anchor` link points to is, or None if the
+/// document has no such heading
+#[tauri::command]
+pub fn document_goto_heading(
+    path: String,
+    anchor: String,
+) -> Result<Option<OutlineHeading>, String> {
+    Ok(find_heading(&read_document(&path)?, &anchor))
+}
@@ -0,0 +1,63 @@
+// PDF commands - Text extraction and page previews for PDF attachments
+
+use crate::services::image_optimizer::ThumbnailSize;
+use crate::services::pdf_extractor::{PdfExtractor, PdfText};
+use crate::services::rag_indexer::RAG_INDEXER;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Get the text of each page of a PDF, extracting it if needed. `path` is
+/// relative to the workspace. Returns `None` when Poppler isn't installed.
+#[tauri::command]
+pub async fn pdf_extract_text(
+    workspace_root: String,
+    path: String,
+) -> Result<Option<PdfText>, String> {
+    let extractor = PdfExtractor::new(Path::new(&workspace_root));
+    let text = extractor
+        .extract_text(&Path::new(&workspace_root).join(&path))
+        .await?;
+    if let Some(text) = &text {
+        RAG_INDEXER.file_changed(extractor.text_path(&text.hash), false);
+    }
+    Ok(text)
+}
+
+/// Get a thumbnail of a PDF page (numbered from 1) as a data URL. Returns
+/// `None` when Poppler isn't installed.
+#[tauri::command]
+pub async fn pdf_get_page_thumbnail(
+    workspace_root: String,
+    path: String,
+    page: u32,
+    size: Option<ThumbnailSize>,
+) -> Result<Option<String>, String> {
+    let extractor = PdfExtractor::new(Path::new(&workspace_root));
+    extractor
+        .page_thumbnail_data_url(
+            &Path::new(&workspace_root).join(&path),
+            page,
+            size.unwrap_or_default(),
+        )
+        .await
+}
+
+/// Keep the search index in step with a PDF that was added, changed or
+/// deleted in the workspace
+pub(crate) fn spawn_pdf_update(workspace_root: PathBuf, pdf_path: PathBuf, deleted: bool) {
+    tauri::async_runtime::spawn(async move {
+        let extractor = PdfExtractor::new(&workspace_root);
+        for text_path in extractor.forget(&pdf_path).await {
+            RAG_INDEXER.file_changed(text_path, true);
+        }
+        if deleted {
+            return;
+        }
+
+        match extractor.extract_text(&pdf_path).await {
+            Ok(Some(text)) => RAG_INDEXER.file_changed(extractor.text_path(&text.hash), false),
+            Ok(None) => {}
+            Err(e) => warn!("Failed to extract text from {}: {}", pdf_path.display(), e),
+        }
+    });
+}
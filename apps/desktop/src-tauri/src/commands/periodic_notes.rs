@@ -0,0 +1,56 @@
+// Periodic note commands - IPC handlers for daily, weekly and monthly notes
+
+use crate::services::periodic_notes::{Period, PeriodicNote, PeriodicNoteSettings, PeriodicNotes};
+use chrono::NaiveDate;
+use std::path::Path;
+use tracing::debug;
+
+/// Parse a YYYY-MM-DD date, defaulting to today in local time
+fn parse_date(date: Option<String>) -> Result<NaiveDate, String> {
+    match date {
+        Some(date) => NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+            .map_err(|e| format!("Invalid date {}: {}", date, e)),
+        None => Ok(chrono::Local::now().date_naive()),
+    }
+}
+
+/// Open (creating from the template if needed) the daily note for a date
+#[tauri::command]
+pub fn workspace_open_daily_note(
+    workspace_root: String,
+    date: Option<String>,
+) -> Result<PeriodicNote, String> {
+    debug!("Opening daily note for: {:?}", date);
+    PeriodicNotes::new(Path::new(&workspace_root)).open(Period::Daily, parse_date(date)?)
+}
+
+/// Open the daily, weekly or monthly note for a date. `offset` moves that
+/// many periods back or forward, e.g. -1 for the previous week.
+#[tauri::command]
+pub fn workspace_open_periodic_note(
+    workspace_root: String,
+    period: Period,
+    date: Option<String>,
+    offset: Option<i32>,
+) -> Result<PeriodicNote, String> {
+    let date = parse_date(date)?;
+    let date = period
+        .shift(date, offset.unwrap_or(0))
+        .ok_or_else(|| "Date out of range".to_string())?;
+    debug!("Opening {:?} note for: {}", period, date);
+    PeriodicNotes::new(Path::new(&workspace_root)).open(period, date)
+}
+
+/// Get the folders, naming schemes and templates for periodic notes
+#[tauri::command]
+pub fn periodic_notes_get_settings(workspace_root: String) -> Result<PeriodicNoteSettings, String> {
+    PeriodicNotes::new(Path::new(&workspace_root)).settings()
+}
+
+#[tauri::command]
+pub fn periodic_notes_set_settings(
+    workspace_root: String,
+    settings: PeriodicNoteSettings,
+) -> Result<(), String> {
+    PeriodicNotes::new(Path::new(&workspace_root)).set_settings(settings)
+}
@@ -0,0 +1,34 @@
+// Pinned document commands - Favorites stored in workspace metadata
+
+use crate::services::pinned_documents::{PinStore, PinnedDocument};
+use std::path::Path;
+
+/// List pinned files and folders in their pinned order
+#[tauri::command]
+pub fn workspace_list_pinned(workspace_root: String) -> Result<Vec<PinnedDocument>, String> {
+    PinStore::new(Path::new(&workspace_root)).list()
+}
+
+/// Pin a file or folder, at `position` or at the end, returning all pins
+#[tauri::command]
+pub fn workspace_pin_document(
+    workspace_root: String,
+    path: String,
+    position: Option<usize>,
+) -> Result<Vec<PinnedDocument>, String> {
+    PinStore::new(Path::new(&workspace_root)).pin(&path, position)
+}
+
+#[tauri::command]
+pub fn workspace_unpin_document(workspace_root: String, path: String) -> Result<bool, String> {
+    PinStore::new(Path::new(&workspace_root)).unpin(&path)
+}
+
+/// Reorder pins after a drag and drop, returning all pins
+#[tauri::command]
+pub fn workspace_reorder_pinned(
+    workspace_root: String,
+    paths: Vec<String>,
+) -> Result<Vec<PinnedDocument>, String> {
+    PinStore::new(Path::new(&workspace_root)).reorder(&paths)
+}
@@ -0,0 +1,148 @@
+// Plugin commands - List, enable and disable workspace plugins, and import
+// files with their converters
+//
+// Listing and enabling compile plugin modules, which can take a moment, so
+// those run on a blocking thread.
+
+use crate::services::local_api::document_path;
+use crate::services::plugins::{Permission, PluginInfo, PluginRuntime};
+use crate::AppState;
+use std::path::Path;
+use std::sync::Arc;
+use tauri::State;
+use tracing::info;
+
+/// Run a runtime operation that may compile modules off the async threads,
+/// then list the workspace's plugins
+async fn with_runtime(
+    plugins: Arc<PluginRuntime>,
+    workspace_root: String,
+    operation: impl FnOnce(&PluginRuntime, &str) -> Result<(), String> + Send + 'static,
+) -> Result<Vec<PluginInfo>, String> {
+    tokio::task::spawn_blocking(move || {
+        operation(&plugins, &workspace_root)?;
+        Ok(plugins.list(&workspace_root))
+    })
+    .await
+    .map_err(|e| format!("Plugin operation failed: {}", e))?
+}
+
+/// List the plugins in the workspace's plugins folder
+#[tauri::command]
+pub async fn plugins_list(
+    workspace_root: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<PluginInfo>, String> {
+    with_runtime(state.plugins.clone(), workspace_root, |_, _| Ok(())).await
+}
+
+/// Enable a plugin with the permissions the user allowed when prompted
+#[tauri::command]
+pub async fn plugins_enable(
+    workspace_root: String,
+    plugin_id: String,
+    permissions: Vec<Permission>,
+    state: State<'_, AppState>,
+) -> Result<Vec<PluginInfo>, String> {
+    info!("Enabling plugin {} with {:?}", plugin_id, permissions);
+    with_runtime(
+        state.plugins.clone(),
+        workspace_root,
+        move |plugins, root| plugins.enable(root, &plugin_id, &permissions),
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn plugins_disable(
+    workspace_root: String,
+    plugin_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<PluginInfo>, String> {
+    with_runtime(
+        state.plugins.clone(),
+        workspace_root,
+        move |plugins, root| plugins.disable(root, &plugin_id),
+    )
+    .await
+}
+
+/// Load the workspace's enabled plugins again after they changed on disk
+#[tauri::command]
+pub async fn plugins_reload(
+    workspace_root: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<PluginInfo>, String> {
+    with_runtime(state.plugins.clone(), workspace_root, |plugins, root| {
+        plugins.reload(root);
+        Ok(())
+    })
+    .await
+}
+
+/// File extensions the workspace's enabled plugins can import
+#[tauri::command]
+pub async fn plugins_import_extensions(
+    workspace_root: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    let plugins = state.plugins.clone();
+    tokio::task::spawn_blocking(move || plugins.import_extensions(&workspace_root))
+        .await
+        .map_err(|e| format!("Plugin operation failed: {}", e))
+}
+
+/// Convert a file with the plugin that imports its extension and save it as a
+/// document in `folder` (the workspace root when empty). Returns the path of
+/// the new document.
+#[tauri::command]
+pub async fn plugins_import_file(
+    workspace_root: String,
+    source_path: String,
+    folder: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let source = Path::new(&source_path);
+    let extension = source
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_ascii_lowercase())
+        .ok_or_else(|| format!("File has no extension: {}", source_path))?;
+    let stem = source
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Imported".to_string());
+    let name = match folder.as_deref().map(str::trim).filter(|f| !f.is_empty()) {
+        Some(folder) => format!("{}/{}", folder.trim_end_matches('/'), stem),
+        None => stem,
+    };
+    let path = document_path(&name)?;
+    if Path::new(&workspace_root).join(&path).exists() {
+        return Err(format!("A document named {} already exists", path));
+    }
+
+    let data = tokio::fs::read(source)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", source_path, e))?;
+    let markdown = state
+        .plugins
+        .convert_import(&workspace_root, &extension, data)
+        .await?;
+
+    let manager = state
+        .workspace_registry
+        .write()
+        .await
+        .get_or_create(&workspace_root)
+        .await
+        .map_err(|e| e.to_string())?;
+    let result = manager
+        .import_markdown(&path, &markdown, "import")
+        .await
+        .map_err(|e| e.to_string())?;
+    if !result.success {
+        return Err(result.error.unwrap_or_default());
+    }
+
+    info!("Imported {} as {}", source_path, path);
+    Ok(path)
+}
@@ -0,0 +1,34 @@
+// Power commands - Why background work is paused, and the settings for it
+
+use crate::services::power_state::{
+    power_settings_path, BackgroundWorkStatus, PowerSettings, POWER_MONITOR,
+};
+use crate::tray::refresh_tray;
+use tauri::AppHandle;
+use tracing::info;
+
+/// Whether background indexing, sync and update downloads are paused for
+/// battery saver or a metered connection
+#[tauri::command]
+pub fn power_get_status() -> BackgroundWorkStatus {
+    POWER_MONITOR.status()
+}
+
+/// Get the power settings
+#[tauri::command]
+pub fn power_get_settings() -> PowerSettings {
+    POWER_MONITOR.settings()
+}
+
+/// Save and apply power settings
+#[tauri::command]
+pub fn power_set_settings(app: AppHandle, settings: PowerSettings) -> Result<(), String> {
+    info!(
+        "Saving power settings (battery saver: {}, metered: {})",
+        settings.pause_on_battery_saver, settings.pause_on_metered
+    );
+    settings.save(&power_settings_path())?;
+    POWER_MONITOR.set_settings(settings);
+    refresh_tray(&app);
+    Ok(())
+}
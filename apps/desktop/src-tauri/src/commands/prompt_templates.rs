@@ -0,0 +1,124 @@
+// Prompt template commands - IPC handlers for reusable AI actions
+
+use crate::commands::llm::{llm_chat, ChatOptions};
+use crate::services::llm_service::{ChatMessage, ChatResponse};
+use crate::services::prompt_templates::{
+    self, ExpandedPrompt, PromptTemplate, PromptTemplateStore, SavePromptTemplateInput,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use tauri::AppHandle;
+use tracing::debug;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunTemplateOptions {
+    pub template_id: String,
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+    pub provider: String,
+    pub model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// Document the action is run from; supplies {{title}} and its context profile
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document_path: Option<String>,
+}
+
+/// List prompt templates in a workspace
+#[tauri::command]
+pub fn prompt_template_list(workspace_root: String) -> Result<Vec<PromptTemplate>, String> {
+    PromptTemplateStore::new(Path::new(&workspace_root)).list()
+}
+
+/// Create or update a prompt template
+#[tauri::command]
+pub fn prompt_template_save(
+    workspace_root: String,
+    template: SavePromptTemplateInput,
+) -> Result<PromptTemplate, String> {
+    debug!("Saving prompt template: {}", template.name);
+    PromptTemplateStore::new(Path::new(&workspace_root)).save(template)
+}
+
+/// Delete a prompt template
+#[tauri::command]
+pub fn prompt_template_delete(workspace_root: String, id: String) -> Result<bool, String> {
+    PromptTemplateStore::new(Path::new(&workspace_root)).delete(&id)
+}
+
+/// List the variables a template expects, so the UI can prompt for them
+#[tauri::command]
+pub fn prompt_template_variables(template: String) -> Vec<String> {
+    prompt_templates::placeholders(&template)
+}
+
+/// Expand a template without sending it (for previews)
+#[tauri::command]
+pub fn prompt_template_expand(
+    workspace_root: String,
+    id: String,
+    variables: HashMap<String, String>,
+    document_path: Option<String>,
+) -> Result<ExpandedPrompt, String> {
+    let template = PromptTemplateStore::new(Path::new(&workspace_root)).get(&id)?;
+    template.expand(&prompt_templates::with_builtins(
+        variables,
+        document_path.as_deref(),
+    ))
+}
+
+/// Expand a template and send it to the LLM
+#[tauri::command]
+pub async fn prompt_template_run(
+    app: AppHandle,
+    workspace_root: String,
+    options: RunTemplateOptions,
+    auth_token: Option<String>,
+) -> Result<ChatResponse, String> {
+    let template =
+        PromptTemplateStore::new(Path::new(&workspace_root)).get(&options.template_id)?;
+    let expanded = template.expand(&prompt_templates::with_builtins(
+        options.variables,
+        options.document_path.as_deref(),
+    ))?;
+
+    debug!("Running prompt template: {}", template.name);
+
+    let mut messages = Vec::new();
+    if let Some(system_prompt) = expanded.system_prompt {
+        messages.push(message("system", system_prompt));
+    }
+    messages.push(message("user", expanded.prompt));
+
+    let chat_options = ChatOptions {
+        provider: options.provider,
+        model: options.model,
+        messages,
+        temperature: options.temperature,
+        max_tokens: options.max_tokens,
+        top_p: None,
+        stop: None,
+        json_mode: None,
+        request_type: None,
+        web_search_enabled: None,
+        bypass_cache: None,
+        workspace_root: Some(workspace_root),
+        document_path: options.document_path,
+    };
+
+    llm_chat(app, chat_options, auth_token).await
+}
+
+fn message(role: &str, content: String) -> ChatMessage {
+    ChatMessage {
+        role: role.to_string(),
+        content,
+        name: None,
+        tool_call_id: None,
+        tool_calls: None,
+    }
+}
@@ -0,0 +1,122 @@
+// Queue commands - IPC handlers and background worker for the request outbox
+
+use crate::commands::error_reporter::ErrorReporterState;
+use crate::commands::notifications::notify_operation;
+use crate::services::auth_service::AUTH_SERVICE;
+use crate::services::llm_service::{LLMError, LLM_SERVICE};
+use crate::services::notifications::Operation;
+use crate::services::power_state::POWER_MONITOR;
+use crate::services::request_queue::{
+    is_retryable_llm_error, Delivery, QueueEvent, QueueStatus, QueuedPayload, QueuedRequest,
+    REQUEST_QUEUE,
+};
+use crate::tray::refresh_tray;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+use tracing::{debug, error};
+
+/// How often the worker checks the outbox for due requests
+const WORKER_INTERVAL: Duration = Duration::from_secs(5);
+
+// ============================================================================
+// Background Worker
+// ============================================================================
+
+/// Start the outbox worker. Emits 'queue:delivered' or 'queue:failed' with a
+/// QueueEvent payload whenever a queued request leaves the queue. Nothing is
+/// sent while background work is paused for battery saver or a metered
+/// connection.
+pub fn start_queue_worker<R: Runtime>(app: AppHandle<R>) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(WORKER_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            if POWER_MONITOR.is_paused() {
+                refresh_tray(&app);
+                continue;
+            }
+
+            let events = REQUEST_QUEUE.process_due(|item| deliver(&app, item)).await;
+            notify_synced(&app, &events);
+            refresh_tray(&app);
+
+            for event in events {
+                let name = if event.delivered {
+                    "queue:delivered"
+                } else {
+                    "queue:failed"
+                };
+                if let Err(e) = app.emit(name, &event) {
+                    error!("Failed to emit queue event: {}", e);
+                }
+            }
+        }
+    });
+}
+
+/// Tell the user when messages they queued while offline have gone out.
+/// Error reports are sent quietly.
+fn notify_synced<R: Runtime>(app: &AppHandle<R>, events: &[QueueEvent]) {
+    let user_requests = events.iter().filter(|event| event.kind != "error_report");
+    let (sent, failed) = user_requests.fold((0, 0), |(sent, failed), event| {
+        if event.delivered {
+            (sent + 1, failed)
+        } else {
+            (sent, failed + 1)
+        }
+    });
+
+    let outcome = match (sent, failed) {
+        (0, 0) => return,
+        (sent, 0) => Ok(format!("Sent {} queued request(s)", sent)),
+        (sent, failed) => Err(format!(
+            "{} queued request(s) could not be sent ({} sent)",
+            failed, sent
+        )),
+    };
+    notify_operation(app, Operation::Sync, outcome);
+}
+
+/// Replay a queued request
+async fn deliver<R: Runtime>(app: &AppHandle<R>, item: QueuedRequest) -> Delivery {
+    debug!(
+        "Retrying queued {} request {} (attempt {})",
+        item.payload.kind(),
+        item.id,
+        item.attempts + 1
+    );
+
+    match item.payload {
+        QueuedPayload::LlmChat { request } => {
+            let token = AUTH_SERVICE.get_access_token().await;
+            llm_delivery(LLM_SERVICE.chat(request, token.as_deref()).await)
+        }
+        QueuedPayload::LlmChatWithTools { request } => {
+            let token = AUTH_SERVICE.get_access_token().await;
+            llm_delivery(LLM_SERVICE.chat_with_tools(request, token.as_deref()).await)
+        }
+        QueuedPayload::ErrorReport { report } => {
+            let state = app.state::<ErrorReporterState>();
+            state.reporter.deliver(&report).await
+        }
+    }
+}
+
+fn llm_delivery<T: serde::Serialize>(result: Result<T, LLMError>) -> Delivery {
+    match result {
+        Ok(response) => Delivery::Delivered(serde_json::to_value(response).ok()),
+        Err(e) if is_retryable_llm_error(&e.code) => Delivery::Retry(e.to_string()),
+        Err(e) => Delivery::Failed(e.to_string()),
+    }
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Get the requests waiting in the outbox
+#[tauri::command]
+pub fn queue_status() -> QueueStatus {
+    REQUEST_QUEUE.status()
+}
@@ -2,12 +2,63 @@
 //
 // Exposes the RAG service functionality to the frontend via IPC.
 
-use crate::services::rag_service::{RAGService, SearchOptions};
+use crate::commands::llm::{
+    emit_session_expired_if_auth_error, StreamCompleteEvent, StreamErrorEvent, StreamEvent,
+};
+use crate::commands::notifications::notify_operation;
+use crate::commands::operations::start_operation;
+use crate::services::llm_service::{ChatRequest, StreamChunk, LLM_SERVICE};
+use crate::services::notifications::Operation;
+use crate::services::operations::OperationKind;
+use crate::services::rag_answer::{self, AnswerSource, AskResponse};
+use crate::services::rag_indexer::{IndexFreshness, RAG_INDEXER};
+use crate::services::rag_service::{RAGService, RelatedDocument, SearchOptions};
 use crate::services::vector_store::{IndexStatus, SearchResult};
-use tauri::AppHandle;
-use tauri::Manager;
+use crate::tray::refresh_tray;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::mpsc;
 use tokio::sync::OnceCell;
-use tracing::{debug, info};
+use tracing::{debug, error, info, warn};
+
+/// Chunks retrieved for a question unless the caller asks for more or fewer
+const DEFAULT_ASK_TOP_K: u32 = 8;
+
+/// Related documents returned unless the caller asks for more or fewer
+const DEFAULT_RELATED_LIMIT: u32 = 5;
+
+// ============================================================================
+// Command Input Types
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AskOptions {
+    pub question: String,
+    pub stream_id: String,
+    pub provider: String,
+    pub model: String,
+    /// Number of chunks to retrieve
+    #[serde(default)]
+    pub top_k: Option<u32>,
+    #[serde(default)]
+    pub min_score: Option<f32>,
+    /// Only search these projects
+    #[serde(default)]
+    pub project_paths: Option<Vec<String>>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+}
+
+/// Sources retrieved for a question, sent before the answer starts streaming
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AskSourcesEvent {
+    pub stream_id: String,
+    pub sources: Vec<AnswerSource>,
+}
 
 // ============================================================================
 // Singleton Service
@@ -16,9 +67,9 @@ use tracing::{debug, info};
 /// Global RAG service instance - initialized lazily on first use
 static RAG_SERVICE: OnceCell<RAGService> = OnceCell::const_new();
 
-/// Get or initialize the RAG service
-async fn get_service(app: &AppHandle) -> Result<&'static RAGService, String> {
-    RAG_SERVICE
+/// Get or initialize the RAG service, starting the background indexer
+pub(crate) async fn get_service(app: &AppHandle) -> Result<&'static RAGService, String> {
+    let service = RAG_SERVICE
         .get_or_try_init(|| async {
             let app_data = app
                 .path()
@@ -35,7 +86,10 @@ async fn get_service(app: &AppHandle) -> Result<&'static RAGService, String> {
 
             RAGService::new(db_path)
         })
-        .await
+        .await?;
+
+    RAG_INDEXER.start(service);
+    Ok(service)
 }
 
 // ============================================================================
@@ -54,14 +108,30 @@ pub async fn rag_index_project(
 
     let service = get_service(&app).await?;
 
-    service
-        .index_project(&project_path, &auth_token, force.unwrap_or(false))
+    let operation = start_operation(&app, OperationKind::Indexing, "Indexing workspace");
+    let result = service
+        .index_project(&project_path, &auth_token, force.unwrap_or(false), &operation)
         .await
-        .map_err(|e| e.message)
+        .map_err(|e| e.message);
+    operation.finish(&result);
+    if !operation.is_cancelled() {
+        notify_indexed(&app, &result);
+    }
+    result
+}
+
+/// Tell the user indexing finished, if they've moved on to something else
+fn notify_indexed(app: &AppHandle, result: &Result<IndexStatus, String>) {
+    let outcome = result
+        .as_ref()
+        .map(|status| format!("Indexed {} documents", status.indexed_documents))
+        .map_err(Clone::clone);
+    notify_operation(app, Operation::Indexing, outcome);
 }
 
 /// Search for relevant document chunks
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn rag_search(
     app: AppHandle,
     query: String,
@@ -69,6 +139,9 @@ pub async fn rag_search(
     top_k: Option<u32>,
     min_score: Option<f32>,
     project_paths: Option<Vec<String>>,
+    hybrid: Option<bool>,
+    vector_weight: Option<f32>,
+    rerank: Option<bool>,
 ) -> Result<Vec<SearchResult>, String> {
     debug!("rag_search: {}", query);
 
@@ -78,6 +151,9 @@ pub async fn rag_search(
         top_k,
         min_score,
         project_paths,
+        hybrid,
+        vector_weight,
+        rerank,
     };
 
     service
@@ -130,6 +206,202 @@ pub async fn rag_index_file(
         .map_err(|e| e.message)
 }
 
+/// Get index status for projects, including changes still waiting to be
+/// picked up by the background indexer
+#[tauri::command]
+pub async fn rag_index_status(
+    app: AppHandle,
+    project_path: Option<String>,
+) -> Result<Vec<IndexFreshness>, String> {
+    debug!("rag_index_status: {:?}", project_path);
+
+    let service = get_service(&app).await?;
+
+    let statuses = service
+        .get_status(project_path.as_deref())
+        .await
+        .map_err(|e| e.message)?;
+    Ok(RAG_INDEXER.freshness(statuses))
+}
+
+/// Re-index a project in the background. Only changed files are processed
+/// unless `full` is set, which rebuilds the index from scratch.
+#[tauri::command]
+pub async fn rag_reindex(
+    app: AppHandle,
+    project_path: String,
+    auth_token: String,
+    full: Option<bool>,
+) -> Result<(), String> {
+    debug!("rag_reindex: {} (full: {:?})", project_path, full);
+
+    let service = get_service(&app).await?;
+
+    // The reindex covers anything still queued for this project
+    RAG_INDEXER.discard(&project_path);
+
+    let operation = start_operation(&app, OperationKind::Indexing, "Re-indexing workspace");
+    tauri::async_runtime::spawn(async move {
+        let result = service
+            .index_project(&project_path, &auth_token, full.unwrap_or(false), &operation)
+            .await
+            .map_err(|e| e.to_string());
+        operation.finish(&result);
+        if let Err(e) = &result {
+            warn!("Background reindex of {} failed: {}", project_path, e);
+        }
+        if !operation.is_cancelled() {
+            notify_indexed(&app, &result);
+        }
+        RAG_INDEXER.record(&project_path, result.map(|_| ()));
+    });
+
+    Ok(())
+}
+
+/// Pause or resume background indexing of changed files. Explicit index and
+/// reindex requests still run.
+#[tauri::command]
+pub fn rag_set_indexing_paused(app: AppHandle, paused: bool) {
+    debug!("rag_set_indexing_paused: {}", paused);
+    RAG_INDEXER.set_paused(paused);
+    refresh_tray(&app);
+}
+
+/// Documents semantically similar to a file, for a "Related notes" list
+#[tauri::command]
+pub async fn rag_related_documents(
+    app: AppHandle,
+    path: String,
+    limit: Option<u32>,
+    min_score: Option<f32>,
+) -> Result<Vec<RelatedDocument>, String> {
+    debug!("rag_related_documents: {}", path);
+
+    let service = get_service(&app).await?;
+
+    service
+        .related_documents(
+            &path,
+            limit.unwrap_or(DEFAULT_RELATED_LIMIT) as usize,
+            min_score,
+        )
+        .await
+        .map_err(|e| e.message)
+}
+
+/// Answer a question from the user's indexed notes ("Ask my notes")
+/// Emits 'rag:ask:sources' with the retrieved sources, then streams the
+/// answer like llm_chat_stream ('llm:stream', 'llm:stream:complete' or
+/// 'llm:stream:error'). Nothing is streamed when no notes match.
+#[tauri::command]
+pub async fn rag_ask(
+    app: AppHandle,
+    options: AskOptions,
+    auth_token: String,
+) -> Result<AskResponse, String> {
+    debug!("rag_ask: {} (stream_id={})", options.question, options.stream_id);
+
+    if options.question.trim().is_empty() {
+        return Err("Question cannot be empty".to_string());
+    }
+
+    let service = get_service(&app).await?;
+
+    let search = SearchOptions {
+        top_k: Some(options.top_k.unwrap_or(DEFAULT_ASK_TOP_K)),
+        min_score: options.min_score.or(SearchOptions::default().min_score),
+        project_paths: options.project_paths.clone(),
+        hybrid: Some(true),
+        vector_weight: None,
+        rerank: Some(true),
+    };
+    let results = service
+        .search(&options.question, &auth_token, Some(search))
+        .await
+        .map_err(|e| e.message)?;
+
+    let sources = rag_answer::select_sources(&results);
+    let event = AskSourcesEvent {
+        stream_id: options.stream_id.clone(),
+        sources: sources.clone(),
+    };
+    if let Err(e) = app.emit("rag:ask:sources", &event) {
+        error!("Failed to emit ask sources event: {}", e);
+    }
+
+    if sources.is_empty() {
+        return Ok(AskResponse {
+            answer: rag_answer::NO_SOURCES_ANSWER.to_string(),
+            sources,
+            cited: Vec::new(),
+        });
+    }
+
+    let request = ChatRequest {
+        provider: options.provider,
+        model: options.model,
+        messages: rag_answer::build_messages(&options.question, &sources),
+        temperature: options.temperature,
+        max_tokens: options.max_tokens,
+        top_p: None,
+        stop: None,
+        json_mode: None,
+        stream: Some(true),
+        request_type: Some("rag-ask".to_string()),
+        web_search_enabled: Some(false),
+        bypass_cache: false,
+    };
+
+    let stream_id = options.stream_id;
+    let (tx, mut rx) = mpsc::channel::<StreamChunk>(100);
+
+    let app_clone = app.clone();
+    let stream_id_clone = stream_id.clone();
+    tokio::spawn(async move {
+        while let Some(chunk) = rx.recv().await {
+            let event = StreamEvent {
+                stream_id: stream_id_clone.clone(),
+                chunk,
+            };
+            if let Err(e) = app_clone.emit("llm:stream", &event) {
+                error!("Failed to emit stream event: {}", e);
+            }
+        }
+    });
+
+    match LLM_SERVICE.chat_stream(request, Some(&auth_token), tx).await {
+        Ok(response) => {
+            let answer = response.content.clone();
+            let event = StreamCompleteEvent {
+                stream_id,
+                response,
+            };
+            if let Err(e) = app.emit("llm:stream:complete", &event) {
+                error!("Failed to emit stream complete event: {}", e);
+            }
+
+            let cited = rag_answer::cited_sources(&answer, &sources);
+            Ok(AskResponse {
+                answer,
+                sources,
+                cited,
+            })
+        }
+        Err(error) => {
+            emit_session_expired_if_auth_error(&app, &error);
+            let event = StreamErrorEvent {
+                stream_id,
+                error: error.clone(),
+            };
+            if let Err(e) = app.emit("llm:stream:error", &event) {
+                error!("Failed to emit stream error event: {}", e);
+            }
+            Err(error.to_string())
+        }
+    }
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -0,0 +1,23 @@
+// Readability commands - Style analysis with passages for the editor to
+// highlight
+
+use crate::commands::outline::read_document;
+use crate::services::readability::{analyze_document, StyleReport};
+use serde_json::Value;
+
+/// Readability scores, passive voice, adverbs and sentence lengths of a
+/// document. `content` is the editor's current Tiptap content, so positions
+/// match unsaved edits; without it the saved document is analysed.
+#[tauri::command]
+pub async fn document_analyze_style(
+    path: String,
+    content: Option<Value>,
+) -> Result<StyleReport, String> {
+    let document = match content {
+        Some(content) => content,
+        None => read_document(&path)?,
+    };
+    tokio::task::spawn_blocking(move || analyze_document(&document))
+        .await
+        .map_err(|e| format!("Analysis failed: {}", e))
+}
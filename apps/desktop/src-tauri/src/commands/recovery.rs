@@ -1,6 +1,9 @@
 // Recovery commands - IPC handlers for crash recovery
 
-use crate::services::recovery_manager::{RecoveryFile, RecoveryManager};
+use crate::services::recovery_manager::{
+    DraftSnapshot, RecoveryDiff, RecoveryFile, RecoveryManager, RecoverySettings,
+};
+use crate::services::session_marker::SESSION;
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -26,6 +29,7 @@ impl RecoveryManagerRegistry {
         }
 
         let manager = Arc::new(RecoveryManager::new(PathBuf::from(workspace_root)));
+        SESSION.add_workspace(workspace_root);
 
         // Initialize the recovery directory
         if let Err(e) = manager.init().await {
@@ -145,6 +149,23 @@ pub async fn recovery_get_content<R: Runtime>(
     manager.get_recovery_content(&file_key).await
 }
 
+/// Diff the document on disk against its recovery content, with `context`
+/// unchanged lines (default 3) around each change
+#[tauri::command]
+pub async fn recovery_get_diff(
+    state: tauri::State<'_, RecoveryState>,
+    workspace_root: String,
+    file_key: String,
+    context: Option<usize>,
+) -> Result<Option<RecoveryDiff>, String> {
+    debug!("Getting recovery diff for: {}", file_key);
+
+    let mut registry = state.registry.write().await;
+    let manager = registry.get_or_create(&workspace_root).await;
+
+    manager.diff_recovery(&file_key, context.unwrap_or(3)).await
+}
+
 /// Discard recovery for a specific file
 #[tauri::command]
 pub async fn recovery_discard<R: Runtime>(
@@ -192,3 +213,86 @@ pub async fn recovery_has_unique_content<R: Runtime>(
         .has_unique_recovery(&file_key, &current_content)
         .await
 }
+
+/// Get a workspace's autosave and WAL settings
+#[tauri::command]
+pub async fn recovery_get_settings(
+    state: tauri::State<'_, RecoveryState>,
+    workspace_root: String,
+) -> Result<RecoverySettings, String> {
+    let mut registry = state.registry.write().await;
+    let manager = registry.get_or_create(&workspace_root).await;
+
+    Ok(manager.settings())
+}
+
+/// Save a workspace's autosave and WAL settings
+#[tauri::command]
+pub async fn recovery_set_settings(
+    state: tauri::State<'_, RecoveryState>,
+    workspace_root: String,
+    settings: RecoverySettings,
+) -> Result<(), String> {
+    info!("Updating recovery settings for: {}", workspace_root);
+
+    let mut registry = state.registry.write().await;
+    let manager = registry.get_or_create(&workspace_root).await;
+
+    manager.set_settings(settings)
+}
+
+/// Get the previous WALs kept for a file by rotation, newest first
+#[tauri::command]
+pub async fn recovery_get_previous(
+    state: tauri::State<'_, RecoveryState>,
+    workspace_root: String,
+    file_key: String,
+) -> Result<Vec<RecoveryFile>, String> {
+    let mut registry = state.registry.write().await;
+    let manager = registry.get_or_create(&workspace_root).await;
+
+    manager.get_previous_wals(&file_key).await
+}
+
+/// Note an edit for draft snapshots. Call on edits, throttled to about once
+/// a second; a snapshot is taken every few seconds of active typing.
+/// Returns the new snapshot's id, if one was taken.
+#[tauri::command]
+pub async fn recovery_record_edit(
+    state: tauri::State<'_, RecoveryState>,
+    workspace_root: String,
+    file_key: String,
+    content: String,
+) -> Result<Option<String>, String> {
+    let mut registry = state.registry.write().await;
+    let manager = registry.get_or_create(&workspace_root).await;
+
+    manager.record_edit(&file_key, &content).await
+}
+
+/// List a file's draft snapshots, newest first
+#[tauri::command]
+pub async fn recovery_list_snapshots(
+    state: tauri::State<'_, RecoveryState>,
+    workspace_root: String,
+    file_key: String,
+) -> Result<Vec<DraftSnapshot>, String> {
+    let mut registry = state.registry.write().await;
+    let manager = registry.get_or_create(&workspace_root).await;
+
+    manager.list_snapshots(&file_key).await
+}
+
+/// Get the content of a draft snapshot
+#[tauri::command]
+pub async fn recovery_get_snapshot(
+    state: tauri::State<'_, RecoveryState>,
+    workspace_root: String,
+    file_key: String,
+    snapshot_id: String,
+) -> Result<String, String> {
+    let mut registry = state.registry.write().await;
+    let manager = registry.get_or_create(&workspace_root).await;
+
+    manager.get_snapshot(&file_key, &snapshot_id).await
+}
@@ -0,0 +1,82 @@
+// Saved search commands - IPC handlers for saved searches and smart folders
+
+use crate::commands::rag::get_service;
+use crate::services::saved_searches::{
+    self, SavedSearch, SavedSearchStore, SearchQuery, SmartFolderEntry,
+};
+use chrono::Utc;
+use std::path::Path;
+use tauri::AppHandle;
+use tracing::debug;
+
+/// List the saved searches of a workspace
+#[tauri::command]
+pub fn saved_search_list(workspace_root: String) -> Result<Vec<SavedSearch>, String> {
+    SavedSearchStore::new(Path::new(&workspace_root)).list()
+}
+
+#[tauri::command]
+pub fn saved_search_create(
+    workspace_root: String,
+    name: String,
+    query: SearchQuery,
+) -> Result<SavedSearch, String> {
+    debug!("Creating saved search: {}", name);
+    SavedSearchStore::new(Path::new(&workspace_root)).create(&name, query)
+}
+
+/// Rename a saved search and/or replace its query
+#[tauri::command]
+pub fn saved_search_update(
+    workspace_root: String,
+    id: String,
+    name: Option<String>,
+    query: Option<SearchQuery>,
+) -> Result<SavedSearch, String> {
+    SavedSearchStore::new(Path::new(&workspace_root)).update(&id, name.as_deref(), query)
+}
+
+#[tauri::command]
+pub fn saved_search_delete(workspace_root: String, id: String) -> Result<bool, String> {
+    debug!("Deleting saved search: {}", id);
+    SavedSearchStore::new(Path::new(&workspace_root)).delete(&id)
+}
+
+/// List the documents in a saved search's smart folder, most recently
+/// modified first
+#[tauri::command]
+pub async fn saved_search_evaluate(
+    app: AppHandle,
+    workspace_root: String,
+    id: String,
+    limit: Option<usize>,
+) -> Result<Vec<SmartFolderEntry>, String> {
+    let search = SavedSearchStore::new(Path::new(&workspace_root)).get(&id)?;
+    evaluate(&app, &workspace_root, &search.query, limit).await
+}
+
+/// Run a query without saving it, e.g. to preview a smart folder
+#[tauri::command]
+pub async fn saved_search_run_query(
+    app: AppHandle,
+    workspace_root: String,
+    query: SearchQuery,
+    limit: Option<usize>,
+) -> Result<Vec<SmartFolderEntry>, String> {
+    evaluate(&app, &workspace_root, &query, limit).await
+}
+
+async fn evaluate(
+    app: &AppHandle,
+    workspace_root: &str,
+    query: &SearchQuery,
+    limit: Option<usize>,
+) -> Result<Vec<SmartFolderEntry>, String> {
+    let root = Path::new(workspace_root);
+    let service = get_service(app).await?;
+    let files = service
+        .find_files(&query.to_filter(root, Utc::now()))
+        .await
+        .map_err(|e| e.message)?;
+    saved_searches::to_entries(root, query, files, limit)
+}
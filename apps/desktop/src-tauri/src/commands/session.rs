@@ -0,0 +1,49 @@
+// Session commands - IPC handlers for crash detection
+
+use crate::commands::recovery::RecoveryState;
+use crate::services::recovery_manager::RecoveryFile;
+use crate::services::session_marker::{CrashInfo, SESSION};
+use serde::Serialize;
+use std::path::Path;
+use tracing::warn;
+
+/// A crash in the previous session and the unsaved work it left behind
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReport {
+    #[serde(flatten)]
+    pub crash: CrashInfo,
+    pub recoverable: Vec<RecoveryFile>,
+}
+
+/// The previous session's crash, if it didn't exit cleanly, with recovery
+/// files from the workspaces it had open
+#[tauri::command]
+pub async fn session_get_last_crash(
+    state: tauri::State<'_, RecoveryState>,
+) -> Result<Option<CrashReport>, String> {
+    let Some(crash) = SESSION.last_crash() else {
+        return Ok(None);
+    };
+
+    let mut recoverable = Vec::new();
+    let mut registry = state.registry.write().await;
+    for workspace_root in &crash.session.workspaces {
+        if !Path::new(workspace_root).exists() {
+            continue;
+        }
+        let manager = registry.get_or_create(workspace_root).await;
+        match manager.check_for_recovery().await {
+            Ok(files) => recoverable.extend(files),
+            Err(e) => warn!("Failed to check recovery in {}: {}", workspace_root, e),
+        }
+    }
+
+    Ok(Some(CrashReport { crash, recoverable }))
+}
+
+/// Stop offering the previous session's crash
+#[tauri::command]
+pub fn session_dismiss_crash() {
+    SESSION.dismiss_crash();
+}
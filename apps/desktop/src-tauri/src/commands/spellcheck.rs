@@ -0,0 +1,46 @@
+// Spellcheck commands - Offline spelling with Hunspell dictionaries and a
+// per-workspace user dictionary
+
+use crate::commands::outline::read_document;
+use crate::services::spellcheck::{
+    add_user_word, available_languages, dictionary_dirs, load_dictionary,
+    spellcheck_document as check_document, user_words, Misspelling,
+};
+use serde_json::Value;
+use std::path::PathBuf;
+
+/// Misspelled words in a document with suggestions. `content` is the
+/// editor's current Tiptap content, so positions match unsaved edits;
+/// without it the saved document is checked. `language` defaults to en_US.
+#[tauri::command]
+pub async fn spellcheck_document(
+    workspace_root: String,
+    path: String,
+    content: Option<Value>,
+    language: Option<String>,
+) -> Result<Vec<Misspelling>, String> {
+    let document = match content {
+        Some(content) => content,
+        None => read_document(&path)?,
+    };
+    tokio::task::spawn_blocking(move || {
+        let language = language.unwrap_or_else(|| "en_US".to_string());
+        let dictionary = load_dictionary(&dictionary_dirs(), &language)?;
+        let words = user_words(&PathBuf::from(&workspace_root));
+        Ok(check_document(&document, &dictionary, &words))
+    })
+    .await
+    .map_err(|e| format!("Spellcheck failed: {}", e))?
+}
+
+/// Add a word to the workspace's dictionary so it's no longer flagged
+#[tauri::command]
+pub fn spellcheck_add_word(workspace_root: String, word: String) -> Result<(), String> {
+    add_user_word(&PathBuf::from(workspace_root), &word)
+}
+
+/// Languages with a dictionary installed
+#[tauri::command]
+pub fn spellcheck_languages() -> Vec<String> {
+    available_languages(&dictionary_dirs())
+}
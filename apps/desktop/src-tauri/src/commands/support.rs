@@ -0,0 +1,49 @@
+// Support commands - Diagnostics for support tickets
+
+use crate::commands::error_reporter::ErrorReporterState;
+use crate::services::app_dirs::app_data_dir;
+use crate::services::diagnostics::{self, DiagnosticsSources, DiagnosticsSummary};
+use crate::services::request_queue::REQUEST_QUEUE;
+use crate::services::session_marker::SESSION;
+use std::path::PathBuf;
+use tracing::{debug, info};
+
+/// Write a diagnostics zip (logs, versions, workspace stats, queued error
+/// reports and settings) for the user to attach to a support ticket. Covers
+/// the workspaces opened this session plus any passed in.
+#[tauri::command]
+pub async fn support_export_diagnostics(
+    state: tauri::State<'_, ErrorReporterState>,
+    destination: String,
+    workspace_roots: Option<Vec<String>>,
+) -> Result<DiagnosticsSummary, String> {
+    debug!("support_export_diagnostics: {}", destination);
+
+    let mut workspaces = SESSION.workspaces();
+    for root in workspace_roots.unwrap_or_default() {
+        if !workspaces.contains(&root) {
+            workspaces.push(root);
+        }
+    }
+
+    let sources = DiagnosticsSources {
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        app_data_dir: app_data_dir(),
+        workspaces: workspaces.into_iter().map(PathBuf::from).collect(),
+        error_reporting_enabled: state.reporter.is_enabled(),
+        queued_reports: REQUEST_QUEUE.error_reports(),
+    };
+
+    let summary = tokio::task::spawn_blocking(move || {
+        diagnostics::write_bundle(&PathBuf::from(destination), &sources)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))??;
+
+    info!(
+        "Exported diagnostics bundle ({} files, {} bytes)",
+        summary.files.len(),
+        summary.bytes
+    );
+    Ok(summary)
+}
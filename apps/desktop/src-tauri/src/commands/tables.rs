@@ -0,0 +1,14 @@
+// Table commands - Paging through table documents
+
+use crate::services::table_document::{query_table, TablePage, TableQuery, TABLE_EXTENSION};
+use std::path::Path;
+
+/// One page of a .midtable document's rows, sorted and filtered
+#[tauri::command]
+pub fn table_query(path: String, query: Option<TableQuery>) -> Result<TablePage, String> {
+    let path = Path::new(&path);
+    if path.extension().and_then(|e| e.to_str()) != Some(TABLE_EXTENSION) {
+        return Err(format!("Not a table document: {}", path.display()));
+    }
+    query_table(path, &query.unwrap_or_default())
+}
@@ -0,0 +1,14 @@
+// Task commands - Query the workspace's checkbox items for the task board
+
+use crate::services::task_index::{Task, TaskIndex, TaskQuery};
+use std::path::PathBuf;
+
+/// Tasks in the workspace matching a query. Only documents changed since
+/// the last query are read.
+#[tauri::command]
+pub async fn tasks_query(workspace_root: String, query: TaskQuery) -> Result<Vec<Task>, String> {
+    let root = PathBuf::from(workspace_root);
+    tokio::task::spawn_blocking(move || TaskIndex::new(&root).query(&query))
+        .await
+        .map_err(|e| format!("Task failed: {}", e))?
+}
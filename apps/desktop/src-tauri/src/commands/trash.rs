@@ -0,0 +1,35 @@
+// Trash commands - List, restore and empty the workspace trash
+
+use crate::services::image_refs;
+use crate::services::rag_indexer::RAG_INDEXER;
+use crate::services::trash_manager::{TrashItem, TrashManager};
+use std::path::Path;
+
+/// List trashed items, most recent first
+#[tauri::command]
+pub async fn trash_list(workspace_root: String) -> Result<Vec<TrashItem>, String> {
+    TrashManager::new(Path::new(&workspace_root)).list()
+}
+
+/// Restore a trashed item to where it was, returning its restored path
+#[tauri::command]
+pub async fn trash_restore(workspace_root: String, id: String) -> Result<String, String> {
+    let restored = TrashManager::new(Path::new(&workspace_root)).restore(&id)?;
+
+    RAG_INDEXER.path_added(&restored);
+    image_refs::path_added(&restored);
+    Ok(restored.to_string_lossy().to_string())
+}
+
+/// Permanently delete one trashed item
+#[tauri::command]
+pub async fn trash_delete(workspace_root: String, id: String) -> Result<(), String> {
+    TrashManager::new(Path::new(&workspace_root)).delete(&id)
+}
+
+/// Permanently delete everything in the trash, returning how many items
+/// were removed
+#[tauri::command]
+pub async fn trash_empty(workspace_root: String) -> Result<usize, String> {
+    TrashManager::new(Path::new(&workspace_root)).empty()
+}
@@ -1,8 +1,36 @@
 // Update commands - check for and install app updates
+//
+// Updates are either downloaded and installed in one go, or (with background
+// downloads on) downloaded quietly and held until the app quits, when
+// install_pending_on_quit installs them.
 
+use lazy_static::lazy_static;
 use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
 use tauri::{AppHandle, Emitter};
-use tauri_plugin_updater::UpdaterExt;
+use tauri_plugin_updater::{Update, Updater, UpdaterExt};
+use tracing::{error, info, warn};
+
+use crate::services::network_config;
+use crate::services::power_state::POWER_MONITOR;
+use crate::services::updates::{
+    update_packages_dir, update_settings_path, Throttle, UpdatePackages, UpdateSettings,
+};
+
+lazy_static! {
+    /// An update downloaded in the background, waiting to be installed
+    static ref PENDING_UPDATE: Mutex<Option<PendingUpdate>> = Mutex::new(None);
+}
+
+/// Set while a background download is running
+static DOWNLOADING: AtomicBool = AtomicBool::new(false);
+
+struct PendingUpdate {
+    update: Update,
+    bytes: Vec<u8>,
+}
 
 #[derive(Debug, Serialize, Clone)]
 pub struct UpdateInfo {
@@ -18,34 +46,75 @@ pub struct UpdateProgress {
     pub total: Option<u64>,
 }
 
+/// Build the updater for the saved channel
+fn build_updater(app: &AppHandle) -> Result<Updater, String> {
+    build_channel_updater(app, &UpdateSettings::load(&update_settings_path()), false)
+}
+
+/// Build the updater for a channel with the configured proxy, skipping
+/// versions rolled back from unless `any_release` is set. The updater has its
+/// own HTTP client, so custom root certificates don't apply to it.
+fn build_channel_updater(
+    app: &AppHandle,
+    settings: &UpdateSettings,
+    any_release: bool,
+) -> Result<Updater, String> {
+    let manifest = settings
+        .channel
+        .manifest_url()
+        .parse()
+        .map_err(|e| format!("Invalid update manifest URL: {}", e))?;
+    let skipped = settings.skipped_versions.clone();
+    let mut builder = app
+        .updater_builder()
+        .endpoints(vec![manifest])
+        .map_err(|e| e.to_string())?
+        .version_comparator(move |current, release| {
+            any_release
+                || (release.version > current && !skipped.contains(&release.version.to_string()))
+        });
+    if let Some(proxy) = network_config::updater_proxy() {
+        builder = builder.proxy(proxy);
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
 /// Check if an update is available
 #[tauri::command]
 pub async fn check_for_updates(app: AppHandle) -> Result<Option<UpdateInfo>, String> {
-    let updater = app.updater().map_err(|e| e.to_string())?;
+    let updater = build_updater(&app)?;
 
     match updater.check().await {
-        Ok(Some(update)) => {
-            let info = UpdateInfo {
-                version: update.version.clone(),
-                current_version: update.current_version.clone(),
-                body: update.body.clone(),
-                date: update.date.map(|d| d.to_string()),
-            };
-            Ok(Some(info))
-        }
+        Ok(Some(update)) => Ok(Some(update_info(&update))),
         Ok(None) => Ok(None),
         Err(e) => Err(format!("Failed to check for updates: {}", e)),
     }
 }
 
+fn update_info(update: &Update) -> UpdateInfo {
+    UpdateInfo {
+        version: update.version.clone(),
+        current_version: update.current_version.clone(),
+        body: update.body.clone(),
+        date: update.date.as_ref().map(|d| d.to_string()),
+    }
+}
+
 /// Download and install an available update
-/// This will download the update and prepare it for installation on next restart
+/// This will download the update and prepare it for installation on next restart.
+/// With background downloads on, it only starts the download and the update
+/// is installed when the app quits.
 #[tauri::command]
 pub async fn download_and_install_update(
     app: AppHandle,
     window: tauri::Window,
 ) -> Result<(), String> {
-    let updater = app.updater().map_err(|e| e.to_string())?;
+    let settings = UpdateSettings::load(&update_settings_path());
+    if settings.background_download {
+        return start_background_download(&app, &settings).await.map(|_| ());
+    }
+
+    let updater = build_updater(&app)?;
 
     let update = updater
         .check()
@@ -56,8 +125,8 @@ pub async fn download_and_install_update(
     // Download with progress reporting
     let mut downloaded: u64 = 0;
 
-    update
-        .download_and_install(
+    let bytes = update
+        .download(
             |chunk_length, content_length| {
                 downloaded += chunk_length as u64;
                 let progress = UpdateProgress {
@@ -73,11 +142,190 @@ pub async fn download_and_install_update(
             },
         )
         .await
-        .map_err(|e| format!("Failed to download/install update: {}", e))?;
+        .map_err(|e| format!("Failed to download update: {}", e))?;
+
+    // Kept so this version can be rolled back to from the next one
+    if let Err(e) = UpdatePackages::new(&update_packages_dir()).save(&update.version, &bytes) {
+        warn!("Failed to keep update package for rollback: {}", e);
+    }
+
+    update
+        .install(bytes)
+        .map_err(|e| format!("Failed to install update: {}", e))?;
 
     Ok(())
 }
 
+/// Start downloading an available update in the background, to be installed
+/// on quit. Emits "update-download-progress" while downloading, then
+/// "update-downloaded" with the UpdateInfo or "update-download-failed" with
+/// the error. Returns the update being downloaded, if there is one.
+#[tauri::command]
+pub async fn download_update_in_background(app: AppHandle) -> Result<Option<UpdateInfo>, String> {
+    let settings = UpdateSettings::load(&update_settings_path());
+    start_background_download(&app, &settings).await
+}
+
+async fn start_background_download(
+    app: &AppHandle,
+    settings: &UpdateSettings,
+) -> Result<Option<UpdateInfo>, String> {
+    if let Some(pending) = PENDING_UPDATE.lock().unwrap().as_ref() {
+        return Ok(Some(update_info(&pending.update)));
+    }
+    if let Some(reason) = POWER_MONITOR.pause_reasons().first() {
+        return Err(format!("Background downloads are paused: {}", reason));
+    }
+    if DOWNLOADING.swap(true, Ordering::SeqCst) {
+        return Err("An update is already downloading".to_string());
+    }
+
+    let update = match build_updater(app)?.check().await {
+        Ok(Some(update)) => update,
+        Ok(None) => {
+            DOWNLOADING.store(false, Ordering::SeqCst);
+            return Ok(None);
+        }
+        Err(e) => {
+            DOWNLOADING.store(false, Ordering::SeqCst);
+            return Err(format!("Failed to check for updates: {}", e));
+        }
+    };
+    let info = update_info(&update);
+    info!("Downloading update {} in the background", info.version);
+
+    // The bandwidth limit pauses between chunks, so the download gets a
+    // thread of its own rather than holding up a runtime worker
+    let app = app.clone();
+    let limit = settings.bandwidth_limit_kbps;
+    tauri::async_runtime::spawn_blocking(move || {
+        let result = download_throttled(&app, &update, limit);
+        DOWNLOADING.store(false, Ordering::SeqCst);
+
+        match result {
+            Ok(bytes) => {
+                if let Err(e) =
+                    UpdatePackages::new(&update_packages_dir()).save(&update.version, &bytes)
+                {
+                    warn!("Failed to keep update package for rollback: {}", e);
+                }
+                let info = update_info(&update);
+                *PENDING_UPDATE.lock().unwrap() = Some(PendingUpdate { update, bytes });
+                info!("Update {} downloaded, will install on quit", info.version);
+                let _ = app.emit("update-downloaded", &info);
+            }
+            Err(e) => {
+                error!("{}", e);
+                let _ = app.emit("update-download-failed", &e);
+            }
+        }
+    });
+
+    Ok(Some(info))
+}
+
+fn download_throttled(
+    app: &AppHandle,
+    update: &Update,
+    limit_kbps: Option<u32>,
+) -> Result<Vec<u8>, String> {
+    let mut throttle = Throttle::new(limit_kbps);
+    let started = Instant::now();
+    let mut downloaded: u64 = 0;
+
+    tauri::async_runtime::block_on(update.download(
+        |chunk_length, content_length| {
+            downloaded += chunk_length as u64;
+            let progress = UpdateProgress {
+                downloaded,
+                total: content_length,
+            };
+            let _ = app.emit("update-download-progress", &progress);
+            std::thread::sleep(throttle.pause_after(chunk_length, started.elapsed()));
+        },
+        || {},
+    ))
+    .map_err(|e| format!("Failed to download update: {}", e))
+}
+
+/// The update downloaded in the background, if it's waiting to be installed
+#[tauri::command]
+pub fn get_pending_update() -> Option<UpdateInfo> {
+    PENDING_UPDATE
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|pending| update_info(&pending.update))
+}
+
+/// Install the update downloaded in the background now instead of on quit.
+/// Takes effect on restart. Returns false if there was nothing to install.
+#[tauri::command]
+pub fn install_pending_update() -> Result<bool, String> {
+    let Some(pending) = PENDING_UPDATE.lock().unwrap().take() else {
+        return Ok(false);
+    };
+    pending
+        .update
+        .install(pending.bytes)
+        .map_err(|e| format!("Failed to install update: {}", e))?;
+    Ok(true)
+}
+
+/// Install an update downloaded in the background as the app exits
+pub fn install_pending_on_quit() {
+    match install_pending_update() {
+        Ok(true) => info!("Installed pending update on quit"),
+        Ok(false) => {}
+        Err(e) => error!("{}", e),
+    }
+}
+
+/// Get the update channel, background download and skipped versions
+#[tauri::command]
+pub fn get_update_settings() -> UpdateSettings {
+    UpdateSettings::load(&update_settings_path())
+}
+
+/// Validate and save the update settings
+#[tauri::command]
+pub fn set_update_settings(settings: UpdateSettings) -> Result<(), String> {
+    settings.validate()?;
+    settings.save(&update_settings_path())
+}
+
+/// Reinstall the update downloaded before the current version, and skip the
+/// current version from now on. Takes effect on restart. Returns the version
+/// rolled back to.
+#[tauri::command]
+pub async fn rollback_update(app: AppHandle) -> Result<String, String> {
+    let current_version = env!("CARGO_PKG_VERSION");
+    let packages = UpdatePackages::new(&update_packages_dir());
+    let target = packages
+        .rollback_target(current_version)
+        .ok_or_else(|| "No earlier version was downloaded to roll back to".to_string())?;
+    let bytes = packages.read(&target)?;
+
+    // Installing needs an update from the manifest to say how; any release
+    // will do, since the package is what gets installed
+    let mut settings = UpdateSettings::load(&update_settings_path());
+    let installer = build_channel_updater(&app, &settings, true)?
+        .check()
+        .await
+        .map_err(|e| format!("Failed to check for updates: {}", e))?
+        .ok_or_else(|| "No release information available for this platform".to_string())?;
+    installer
+        .install(bytes)
+        .map_err(|e| format!("Failed to install version {}: {}", target.version, e))?;
+
+    if !settings.is_skipped(current_version) {
+        settings.skipped_versions.push(current_version.to_string());
+        settings.save(&update_settings_path())?;
+    }
+    info!("Rolled back from {} to {}", current_version, target.version);
+    Ok(target.version)
+}
+
 /// Get the current app version
 #[tauri::command]
 pub fn get_current_version() -> String {
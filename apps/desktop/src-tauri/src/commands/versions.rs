@@ -1,6 +1,9 @@
 // Version history commands
 
-use crate::services::checkpoint_manager::Checkpoint;
+use crate::services::change_staging::DiffHunk;
+use crate::services::checkpoint_manager::{
+    editing_sessions, Checkpoint, DocumentVariant, EditingSession, DEFAULT_SESSION_GAP_MINUTES,
+};
 use crate::AppState;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -16,6 +19,18 @@ pub struct DiffResult {
     pub change_count: u32,
 }
 
+/// What merging a variant into its document would change
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VariantMergePreview {
+    pub insertions: usize,
+    pub deletions: usize,
+    pub hunks: Vec<DiffHunk>,
+    /// The document was edited after the variant was created; merging
+    /// replaces those edits too
+    pub document_changed: bool,
+}
+
 #[tauri::command]
 pub async fn get_checkpoints(
     workspace_root: String,
@@ -34,6 +49,32 @@ pub async fn get_checkpoints(
     }
 }
 
+/// A document's checkpoints grouped into editing sessions, split wherever
+/// more than `gap_minutes` passed between checkpoints
+#[tauri::command]
+pub async fn versions_get_timeline(
+    workspace_root: String,
+    file_path: String,
+    gap_minutes: Option<i64>,
+    state: State<'_, AppState>,
+) -> Result<Vec<EditingSession>, String> {
+    let registry = state.workspace_registry.read().await;
+
+    if let Some(manager) = registry.get(&workspace_root) {
+        let checkpoints = manager
+            .get_checkpoints(&file_path)
+            .await
+            .map_err(|e| e.to_string())?;
+        let gap = gap_minutes.unwrap_or(DEFAULT_SESSION_GAP_MINUTES).max(1);
+        Ok(editing_sessions(
+            &checkpoints,
+            chrono::Duration::minutes(gap),
+        ))
+    } else {
+        Ok(vec![])
+    }
+}
+
 #[tauri::command]
 pub async fn restore_checkpoint(
     workspace_root: String,
@@ -93,3 +134,139 @@ pub async fn compare_checkpoints(
         Err("Workspace not initialized".to_string())
     }
 }
+
+/// Start a named variant of a document, to edit apart from it
+#[tauri::command]
+pub async fn create_variant(
+    workspace_root: String,
+    file_path: String,
+    name: String,
+    state: State<'_, AppState>,
+) -> Result<DocumentVariant, String> {
+    let registry = state.workspace_registry.read().await;
+
+    if let Some(manager) = registry.get(&workspace_root) {
+        manager
+            .create_variant(&file_path, &name)
+            .await
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Workspace not initialized".to_string())
+    }
+}
+
+#[tauri::command]
+pub async fn get_variants(
+    workspace_root: String,
+    file_path: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<DocumentVariant>, String> {
+    let registry = state.workspace_registry.read().await;
+
+    if let Some(manager) = registry.get(&workspace_root) {
+        manager
+            .get_variants(&file_path)
+            .await
+            .map_err(|e| e.to_string())
+    } else {
+        Ok(vec![])
+    }
+}
+
+#[tauri::command]
+pub async fn load_variant(
+    workspace_root: String,
+    file_path: String,
+    name: String,
+    state: State<'_, AppState>,
+) -> Result<Value, String> {
+    let registry = state.workspace_registry.read().await;
+
+    if let Some(manager) = registry.get(&workspace_root) {
+        manager
+            .load_variant(&file_path, &name)
+            .await
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Workspace not initialized".to_string())
+    }
+}
+
+#[tauri::command]
+pub async fn save_variant(
+    workspace_root: String,
+    file_path: String,
+    name: String,
+    json: Value,
+    state: State<'_, AppState>,
+) -> Result<DocumentVariant, String> {
+    let registry = state.workspace_registry.read().await;
+
+    if let Some(manager) = registry.get(&workspace_root) {
+        manager
+            .save_variant(&file_path, &name, json)
+            .await
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Workspace not initialized".to_string())
+    }
+}
+
+#[tauri::command]
+pub async fn preview_variant_merge(
+    workspace_root: String,
+    file_path: String,
+    name: String,
+    context: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<VariantMergePreview, String> {
+    let registry = state.workspace_registry.read().await;
+
+    if let Some(manager) = registry.get(&workspace_root) {
+        manager
+            .preview_variant_merge(&file_path, &name, context.unwrap_or(3))
+            .await
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Workspace not initialized".to_string())
+    }
+}
+
+/// Replace a document with one of its variants, bookmarking it first
+#[tauri::command]
+pub async fn merge_variant(
+    workspace_root: String,
+    file_path: String,
+    name: String,
+    state: State<'_, AppState>,
+) -> Result<SaveResult, String> {
+    let registry = state.workspace_registry.read().await;
+
+    if let Some(manager) = registry.get(&workspace_root) {
+        manager
+            .merge_variant(&file_path, &name)
+            .await
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Workspace not initialized".to_string())
+    }
+}
+
+#[tauri::command]
+pub async fn discard_variant(
+    workspace_root: String,
+    file_path: String,
+    name: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let registry = state.workspace_registry.read().await;
+
+    if let Some(manager) = registry.get(&workspace_root) {
+        manager
+            .discard_variant(&file_path, &name)
+            .await
+            .map_err(|e| e.to_string())
+    } else {
+        Err("Workspace not initialized".to_string())
+    }
+}
@@ -1,11 +1,27 @@
 // Workspace commands - Document loading, saving, and versioning
 
-use crate::services::checkpoint_manager::Checkpoint;
+use crate::commands::automations::run_automations;
+use crate::commands::fs::spawn_tree_reconcile;
+use crate::commands::operations::start_operation;
+use crate::services::automations::{AutomationEvent, AutomationStore};
+use crate::services::checkpoint_manager::{Checkpoint, CheckpointAttribution};
+use crate::services::document_chunks::{self, SectionEntry};
+use crate::services::export_filter::ExportFilter;
+use crate::services::find_replace::{FindReplace, FindReplaceOptions, FindReplaceResult};
+use crate::services::link_graph::LinkGraph;
+use crate::services::operations::OperationKind;
+use crate::services::rag_service::document_tags;
+use crate::services::recent_documents::RECENT_DOCUMENTS;
+use crate::services::workspace_archive::{self, ArchiveContents, ArchiveManifest};
 use crate::services::workspace_manager::ProjectInfo;
+use crate::tray::refresh_tray;
 use crate::AppState;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use tauri::State;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+use tauri::{AppHandle, State};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoadedDocument {
@@ -27,6 +43,7 @@ pub struct SaveResult {
 
 #[tauri::command]
 pub async fn workspace_init(
+    app: AppHandle,
     workspace_root: String,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
@@ -37,18 +54,24 @@ pub async fn workspace_init(
         .map_err(|e| e.to_string())?
         .init()
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+
+    // The file tree is listed from the cache; catch it up with changes made
+    // while the app was closed
+    spawn_tree_reconcile(app, workspace_root.into());
+    Ok(())
 }
 
 #[tauri::command]
 pub async fn workspace_load_document(
+    app: AppHandle,
     workspace_root: String,
     file_path: String,
     state: State<'_, AppState>,
 ) -> Result<LoadedDocument, String> {
     let registry = state.workspace_registry.read().await;
 
-    if let Some(manager) = registry.get(&workspace_root) {
+    let loaded = if let Some(manager) = registry.get(&workspace_root) {
         manager
             .load_document(&file_path)
             .await
@@ -66,29 +89,133 @@ pub async fn workspace_load_document(
             .load_document(&file_path)
             .await
             .map_err(|e| e.to_string())
-    }
+    }?;
+
+    RECENT_DOCUMENTS.record(&workspace_root, &file_path);
+    refresh_tray(&app);
+    Ok(loaded)
 }
 
 #[tauri::command]
 pub async fn workspace_save_document(
+    app: AppHandle,
     workspace_root: String,
     file_path: String,
     json: Value,
     trigger: String,
+    attribution: Option<CheckpointAttribution>,
     state: State<'_, AppState>,
 ) -> Result<SaveResult, String> {
     let registry = state.workspace_registry.read().await;
+    let Some(manager) = registry.get(&workspace_root) else {
+        return Err("Workspace not initialized".to_string());
+    };
 
-    if let Some(manager) = registry.get(&workspace_root) {
-        manager
-            .save_document(&file_path, json, &trigger)
-            .await
-            .map_err(|e| e.to_string())
-    } else {
-        Err("Workspace not initialized".to_string())
+    // Tags the document had before, to tell which ones this save adds
+    let automated = AutomationStore::new(Path::new(&workspace_root)).is_configured();
+    let old_tags = match automated {
+        true => saved_tags(&Path::new(&workspace_root).join(&file_path)),
+        false => Vec::new(),
+    };
+    let new_tags = match automated {
+        true => document_tags(&json.to_string()),
+        false => Vec::new(),
+    };
+
+    // An AI edit's save sets its own checkpoint trigger
+    let result = match attribution {
+        Some(attribution) => manager.save_ai_edit(&file_path, json, &attribution).await,
+        None => manager.save_document(&file_path, json, &trigger).await,
+    }
+    .map_err(|e| e.to_string())?;
+
+    if automated && result.success {
+        let mut events = vec![AutomationEvent::DocumentSaved {
+            path: file_path.clone(),
+        }];
+        events.extend(
+            new_tags
+                .into_iter()
+                .filter(|tag| !old_tags.contains(tag))
+                .map(|tag| AutomationEvent::TagAdded {
+                    path: file_path.clone(),
+                    tag,
+                }),
+        );
+        run_automations(&app, &workspace_root, events);
+    }
+    Ok(result)
+}
+
+/// Tags of a document as it is on disk
+fn saved_tags(path: &Path) -> Vec<String> {
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    match serde_json::from_str::<Value>(&content) {
+        Ok(mut document) if document_chunks::manifest(&document).is_some() => {
+            document_chunks::resolve_content(path, &mut document);
+            document_tags(&document.to_string())
+        }
+        _ => document_tags(&content),
     }
 }
 
+/// Sections of a long document, to load it a section at a time
+#[tauri::command]
+pub async fn workspace_document_sections(
+    workspace_root: String,
+    file_path: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<SectionEntry>, String> {
+    let registry = state.workspace_registry.read().await;
+    let Some(manager) = registry.get(&workspace_root) else {
+        return Err("Workspace not initialized".to_string());
+    };
+    manager
+        .document_sections(&file_path)
+        .map_err(|e| e.to_string())
+}
+
+/// The blocks of one section of a document
+#[tauri::command]
+pub async fn workspace_load_section(
+    workspace_root: String,
+    file_path: String,
+    index: usize,
+    state: State<'_, AppState>,
+) -> Result<Vec<Value>, String> {
+    let registry = state.workspace_registry.read().await;
+    let Some(manager) = registry.get(&workspace_root) else {
+        return Err("Workspace not initialized".to_string());
+    };
+    manager
+        .load_section(&file_path, index)
+        .map_err(|e| e.to_string())
+}
+
+/// Save one section of a document. Fails if the section changed since it
+/// was loaded (its hash is no longer `base_hash`).
+#[tauri::command]
+pub async fn workspace_save_section(
+    workspace_root: String,
+    file_path: String,
+    index: usize,
+    base_hash: String,
+    blocks: Vec<Value>,
+    trigger: String,
+    state: State<'_, AppState>,
+) -> Result<SaveResult, String> {
+    let registry = state.workspace_registry.read().await;
+    let Some(manager) = registry.get(&workspace_root) else {
+        return Err("Workspace not initialized".to_string());
+    };
+    manager
+        .save_section(&file_path, index, &base_hash, blocks, &trigger)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn workspace_get_checkpoints(
     workspace_root: String,
@@ -202,3 +329,133 @@ pub async fn workspace_is_project(
         Ok(false)
     }
 }
+
+/// Documents and the links between them, for the graph view
+#[tauri::command]
+pub async fn workspace_get_link_graph(
+    workspace_root: String,
+    state: State<'_, AppState>,
+) -> Result<LinkGraph, String> {
+    let registry = state.workspace_registry.read().await;
+
+    if let Some(manager) = registry.get(&workspace_root) {
+        manager.link_graph().map_err(|e| e.to_string())
+    } else {
+        Err("Workspace not initialized".to_string())
+    }
+}
+
+/// Workspace-relative paths of the documents linking to a document
+#[tauri::command]
+pub async fn workspace_get_backlinks(
+    workspace_root: String,
+    file_path: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, String> {
+    let registry = state.workspace_registry.read().await;
+
+    if let Some(manager) = registry.get(&workspace_root) {
+        manager.backlinks(&file_path).map_err(|e| e.to_string())
+    } else {
+        Err("Workspace not initialized".to_string())
+    }
+}
+
+/// Tags in the workspace, each with the workspace-relative paths of the
+/// documents that have it
+#[tauri::command]
+pub async fn workspace_get_tags(
+    workspace_root: String,
+    state: State<'_, AppState>,
+) -> Result<BTreeMap<String, Vec<String>>, String> {
+    let registry = state.workspace_registry.read().await;
+
+    if let Some(manager) = registry.get(&workspace_root) {
+        manager.tags().map_err(|e| e.to_string())
+    } else {
+        Err("Workspace not initialized".to_string())
+    }
+}
+
+/// Pack a workspace into a `.midlightpkg` archive, for moving it to another
+/// machine or keeping a backup. Images, checkpoints and settings are
+/// included and indexes left out unless `contents` says otherwise; `filter`
+/// narrows the archive to some of the documents.
+#[tauri::command]
+pub async fn workspace_export_archive(
+    app: AppHandle,
+    workspace_root: String,
+    output_path: String,
+    contents: Option<ArchiveContents>,
+    filter: Option<ExportFilter>,
+) -> Result<ArchiveManifest, String> {
+    let operation = start_operation(&app, OperationKind::Export, "Exporting workspace");
+    let (operation, result) = tokio::task::spawn_blocking(move || {
+        let result = workspace_archive::export_archive(
+            Path::new(&workspace_root),
+            Path::new(&output_path),
+            contents.unwrap_or_default(),
+            &filter.unwrap_or_default(),
+            &operation,
+        );
+        (operation, result)
+    })
+    .await
+    .map_err(|e| format!("Export failed: {}", e))?;
+    operation.finish(&result);
+    result
+}
+
+/// Unpack a `.midlightpkg` archive into `target_path`, a new or empty
+/// folder, which can then be opened as a workspace. Files that aren't
+/// archives, or are from a newer version, are refused before anything is
+/// written.
+#[tauri::command]
+pub async fn workspace_import_archive(
+    app: AppHandle,
+    archive_path: String,
+    target_path: String,
+) -> Result<ArchiveManifest, String> {
+    let archive = archive_path.clone();
+    tokio::task::spawn_blocking(move || workspace_archive::read_manifest(Path::new(&archive)))
+        .await
+        .map_err(|e| format!("Import failed: {}", e))??;
+
+    let operation = start_operation(&app, OperationKind::Import, "Importing workspace");
+    let (operation, result) = tokio::task::spawn_blocking(move || {
+        let result = workspace_archive::import_archive(
+            Path::new(&archive_path),
+            Path::new(&target_path),
+            &operation,
+        );
+        (operation, result)
+    })
+    .await
+    .map_err(|e| format!("Import failed: {}", e))?;
+    operation.finish(&result);
+    result
+}
+
+/// Find, and unless `dryRun` is set replace, text across the workspace's
+/// documents. Every changed document is checkpointed first.
+#[tauri::command]
+pub async fn workspace_find_replace(
+    workspace_root: String,
+    options: FindReplaceOptions,
+    state: State<'_, AppState>,
+) -> Result<FindReplaceResult, String> {
+    let find_replace = Arc::new(FindReplace::new(Path::new(&workspace_root), &options)?);
+    let scanner = find_replace.clone();
+    let documents = tokio::task::spawn_blocking(move || scanner.scan())
+        .await
+        .map_err(|e| e.to_string())?;
+    if options.dry_run {
+        return Ok(FindReplaceResult::preview(documents));
+    }
+
+    let manager = state.workspace_registry.read().await.get(&workspace_root);
+    match manager {
+        Some(manager) => Ok(find_replace.apply(documents, &manager).await),
+        None => Err("Workspace not initialized".to_string()),
+    }
+}
@@ -0,0 +1,42 @@
+// Writing stats commands - Words written per day, goals and streaks
+
+use crate::services::writing_stats::{DayStats, Goals, WritingGoal, WritingProgress, WritingStats};
+use chrono::{Duration, Local};
+use std::path::Path;
+
+/// Today's writing, goal progress and streaks for a document, or for the
+/// whole project if no document is given
+#[tauri::command]
+pub fn writing_stats_progress(workspace_root: String, document: Option<String>) -> WritingProgress {
+    WritingStats::new(Path::new(&workspace_root))
+        .progress(document.as_deref(), Local::now().date_naive())
+}
+
+/// Words added and removed on each of the last `days` days (30 by default),
+/// oldest first
+#[tauri::command]
+pub fn writing_stats_history(
+    workspace_root: String,
+    document: Option<String>,
+    days: Option<u32>,
+) -> Vec<DayStats> {
+    let today = Local::now().date_naive();
+    let from = today - Duration::days(i64::from(days.unwrap_or(30).max(1)) - 1);
+    WritingStats::new(Path::new(&workspace_root)).history(document.as_deref(), from, today)
+}
+
+#[tauri::command]
+pub fn writing_stats_get_goals(workspace_root: String) -> Goals {
+    WritingStats::new(Path::new(&workspace_root)).goals()
+}
+
+/// Set the goal of a document, or of the project if no document is given.
+/// A goal of None clears it.
+#[tauri::command]
+pub fn writing_stats_set_goal(
+    workspace_root: String,
+    document: Option<String>,
+    goal: Option<WritingGoal>,
+) -> Result<(), String> {
+    WritingStats::new(Path::new(&workspace_root)).set_goal(document.as_deref(), goal)
+}
@@ -5,34 +5,46 @@
 #![allow(clippy::bind_instead_of_map)]
 
 mod commands;
-#[cfg(target_os = "macos")]
 mod menu;
 mod services;
 pub mod traits;
+mod tray;
 
 #[cfg(test)]
 mod test_utils;
 
 use std::sync::Arc;
-use tauri::menu::{MenuBuilder, MenuItemBuilder};
-use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
 use tauri::Manager;
 use tokio::sync::RwLock;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
 
+use commands::conversations::ConversationState;
 use commands::error_reporter::ErrorReporterState;
 use commands::file_watcher::FileWatcherState;
 use commands::recovery::RecoveryState;
+use services::crash_reports::CRASH_REPORTS;
+use services::log_files::LOGS;
+use services::operations::OperationManager;
+use services::plugins::PluginRuntime;
+use services::power_state::POWER_MONITOR;
+use services::session_marker::SESSION;
 use services::workspace_manager::WorkspaceManagerRegistry;
 
 /// Application state shared across all commands
 pub struct AppState {
     pub workspace_registry: Arc<RwLock<WorkspaceManagerRegistry>>,
+    pub plugins: Arc<PluginRuntime>,
+    pub operations: Arc<OperationManager>,
 }
 
 impl AppState {
     pub fn new() -> Self {
+        let workspace_registry = Arc::new(RwLock::new(WorkspaceManagerRegistry::new()));
         Self {
-            workspace_registry: Arc::new(RwLock::new(WorkspaceManagerRegistry::new())),
+            plugins: Arc::new(PluginRuntime::new(workspace_registry.clone())),
+            workspace_registry,
+            operations: Arc::new(OperationManager::new()),
         }
     }
 }
@@ -45,41 +57,88 @@ impl Default for AppState {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    // Initialize logging
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive("midlight=debug".parse().unwrap()),
+    // Initialize logging: everything to stdout, info and above to rolling
+    // log files, plus warnings and errors to the session log so a crash
+    // leaves an excerpt behind
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer().with_filter(
+                tracing_subscriber::EnvFilter::from_default_env()
+                    .add_directive("midlight=debug".parse().unwrap()),
+            ),
+        )
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(|| LOGS.writer())
+                .with_filter(LevelFilter::INFO),
+        )
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(|| SESSION.log_writer())
+                .with_filter(LevelFilter::WARN),
         )
         .init();
 
     tracing::info!("Starting Midlight desktop app");
+    CRASH_REPORTS.install_panic_hook(env!("CARGO_PKG_VERSION"));
+    if let Some(crash) = SESSION.begin(env!("CARGO_PKG_VERSION")) {
+        tracing::warn!(
+            "Midlight did not exit cleanly last time (session started {})",
+            crash.session.started_at
+        );
+    }
 
     tauri::Builder::default()
+        // Must come first: a second launch (e.g. from a midlight:// link)
+        // hands its link to this instance and exits
+        .plugin(tauri_plugin_single_instance::init(|app, _argv, _cwd| {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.show();
+                let _ = window.set_focus();
+            }
+        }))
+        .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_store::Builder::new().build())
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(tauri_plugin_notification::init())
         .manage(AppState::new())
         .manage(RecoveryState::new())
         .manage(FileWatcherState::new())
         .manage(ErrorReporterState::default())
+        .manage(ConversationState::new())
         .invoke_handler(tauri::generate_handler![
             // File system commands
             commands::fs::get_default_workspace,
             commands::fs::read_dir,
+            commands::fs::fs_cached_tree,
+            commands::fs::read_dir_page,
+            commands::fs::read_dir_recursive,
             commands::fs::read_file,
+            commands::fs::read_file_chunk,
             commands::fs::write_file,
+            commands::fs::document_set_locked,
+            commands::fs::document_is_locked,
             commands::fs::delete_file,
             commands::fs::rename_file,
+            commands::fs::fs_preview_link_rewrites,
             commands::fs::file_exists,
+            commands::fs::fs_get_metadata,
             commands::fs::create_folder,
             commands::fs::create_midlight_file,
             commands::fs::create_new_folder,
             commands::fs::file_duplicate,
             commands::fs::file_trash,
+            commands::fs::fs_delete_impact,
+            commands::trash::trash_list,
+            commands::trash::trash_restore,
+            commands::trash::trash_delete,
+            commands::trash::trash_empty,
             commands::fs::file_reveal,
             commands::fs::file_copy_to,
             commands::fs::file_move_to,
@@ -87,6 +146,9 @@ pub fn run() {
             commands::workspace::workspace_init,
             commands::workspace::workspace_load_document,
             commands::workspace::workspace_save_document,
+            commands::workspace::workspace_document_sections,
+            commands::workspace::workspace_load_section,
+            commands::workspace::workspace_save_section,
             commands::workspace::workspace_get_checkpoints,
             commands::workspace::workspace_restore_checkpoint,
             commands::workspace::workspace_create_bookmark,
@@ -94,28 +156,115 @@ pub fn run() {
             commands::workspace::workspace_invalidate_project_cache,
             commands::workspace::workspace_refresh_projects,
             commands::workspace::workspace_is_project,
+            commands::workspace::workspace_get_link_graph,
+            commands::workspace::workspace_get_backlinks,
+            commands::workspace::workspace_get_tags,
+            commands::workspace::workspace_export_archive,
+            commands::workspace::workspace_import_archive,
+            commands::workspace::workspace_find_replace,
+            // Pinned document commands
+            commands::pinned_documents::workspace_list_pinned,
+            commands::pinned_documents::workspace_pin_document,
+            commands::pinned_documents::workspace_unpin_document,
+            commands::pinned_documents::workspace_reorder_pinned,
+            // External editor commands
+            commands::external_editor::open_in_external_editor,
+            commands::external_editor::close_external_editor,
+            commands::external_editor::list_external_editor_sessions,
+            // Periodic note commands
+            commands::periodic_notes::workspace_open_daily_note,
+            commands::periodic_notes::workspace_open_periodic_note,
+            commands::periodic_notes::periodic_notes_get_settings,
+            commands::periodic_notes::periodic_notes_set_settings,
             // Version commands
             commands::versions::get_checkpoints,
+            commands::versions::versions_get_timeline,
             commands::versions::restore_checkpoint,
             commands::versions::create_bookmark,
             commands::versions::compare_checkpoints,
+            commands::versions::create_variant,
+            commands::versions::get_variants,
+            commands::versions::load_variant,
+            commands::versions::save_variant,
+            commands::versions::preview_variant_merge,
+            commands::versions::merge_variant,
+            commands::versions::discard_variant,
             // Image commands
             commands::images::workspace_save_image,
+            commands::images::workspace_save_image_from_clipboard,
             commands::images::workspace_get_image,
+            commands::images::workspace_read_image_chunk,
+            commands::images::workspace_get_image_thumbnail,
             commands::images::workspace_image_exists,
             commands::images::workspace_delete_image,
+            commands::images::workspace_image_reference_count,
             commands::images::workspace_list_images,
+            commands::images::workspace_get_image_text,
+            commands::images::workspace_extract_image_text,
+            // Audio commands
+            commands::audio::workspace_save_audio,
+            commands::audio::workspace_import_audio,
+            commands::audio::workspace_get_audio,
+            commands::audio::workspace_delete_audio,
+            commands::audio::workspace_list_audio,
+            commands::audio::audio_is_supported,
+            commands::audio::audio_transcribe,
+            // PDF commands
+            commands::pdf::pdf_extract_text,
+            commands::pdf::pdf_get_page_thumbnail,
             // LLM commands
             commands::llm::llm_chat,
             commands::llm::llm_chat_stream,
             commands::llm::llm_chat_with_tools,
             commands::llm::llm_chat_with_tools_stream,
+            commands::llm::llm_chat_structured,
             commands::llm::llm_get_models,
             commands::llm::llm_get_quota,
             commands::llm::llm_get_status,
+            commands::llm::llm_estimate,
+            commands::llm::llm_get_model_capabilities,
+            commands::llm::llm_get_routing_policy,
+            commands::llm::llm_set_routing_policy,
+            commands::llm::llm_get_tracked_quota,
+            commands::llm::llm_get_quota_alerts,
+            commands::llm::llm_set_quota_alerts,
+            commands::llm::llm_get_cache_stats,
+            commands::llm::llm_clear_cache,
+            // Conversation commands
+            commands::conversations::conversation_list,
+            commands::conversations::conversation_load,
+            commands::conversations::conversation_save,
+            commands::conversations::conversation_rename,
+            commands::conversations::conversation_delete,
+            // Context profile commands
+            commands::context_profiles::context_profile_list,
+            commands::context_profiles::context_profile_get,
+            commands::context_profiles::context_profile_set,
+            commands::context_profiles::context_profile_remove,
+            commands::context_profiles::context_profile_resolve,
+            // Prompt template commands
+            commands::prompt_templates::prompt_template_list,
+            commands::prompt_templates::prompt_template_save,
+            commands::prompt_templates::prompt_template_delete,
+            commands::prompt_templates::prompt_template_variables,
+            commands::prompt_templates::prompt_template_expand,
+            commands::prompt_templates::prompt_template_run,
             // Agent commands
             commands::agent::agent_execute_tool,
+            commands::agent::agent_end_turn,
             commands::agent::agent_list_tools,
+            commands::agent::agent_list_pending_changes,
+            commands::agent::agent_approve_change,
+            commands::agent::agent_reject_change,
+            commands::agent::agent_list_executions,
+            commands::agent::agent_undo_execution,
+            commands::agent::agent_get_execution_history,
+            commands::agent::agent_list_memories,
+            commands::agent::agent_forget_memory,
+            // Custom tool commands
+            commands::custom_tools::custom_tool_list,
+            commands::custom_tools::custom_tool_save,
+            commands::custom_tools::custom_tool_delete,
             // Auth commands
             commands::auth::auth_init,
             commands::auth::auth_signup,
@@ -124,9 +273,12 @@ pub fn run() {
             commands::auth::auth_login_with_google,
             commands::auth::auth_handle_oauth_callback,
             commands::auth::auth_get_user,
+            commands::auth::auth_list_accounts,
+            commands::auth::auth_switch_account,
             commands::auth::auth_get_subscription,
             commands::auth::auth_get_quota,
             commands::auth::auth_is_authenticated,
+            commands::auth::auth_is_offline,
             commands::auth::auth_get_state,
             commands::auth::auth_get_access_token,
             commands::auth::auth_forgot_password,
@@ -136,40 +288,143 @@ pub fn run() {
             commands::auth::subscription_get_prices,
             commands::auth::subscription_create_checkout,
             commands::auth::subscription_create_portal,
+            // Deep link commands
+            commands::deep_link::deep_link_take_pending,
             // Import commands
             commands::import::import_select_folder,
             commands::import::import_detect_source_type,
             commands::import::import_analyze_obsidian,
             commands::import::import_analyze_notion,
+            commands::import::import_analyze_generic,
             commands::import::import_obsidian,
             commands::import::import_notion,
+            commands::import::import_generic,
+            commands::import::import_repeat,
+            commands::import::import_list_manifests,
             commands::import::import_cancel,
+            // Operation commands
+            commands::operations::operation_cancel,
+            commands::operations::operation_list,
+            // Table document commands
+            commands::tables::table_query,
             // DOCX import commands
             commands::import::import_select_docx_file,
             commands::import::import_analyze_docx,
             commands::import::import_docx_file,
+            // TextBundle import commands
+            commands::import::import_textbundle,
             // Export commands
             commands::import::export_pdf,
             commands::export::export_select_save_path,
             commands::export::export_to_docx,
+            commands::export::export_print_layout,
+            commands::export::export_to_textbundle,
+            commands::export::export_to_obsidian,
             // Recovery commands
             commands::recovery::recovery_check,
             commands::recovery::recovery_write_wal,
             commands::recovery::recovery_clear_wal,
             commands::recovery::recovery_has_recovery,
             commands::recovery::recovery_get_content,
+            commands::recovery::recovery_get_diff,
             commands::recovery::recovery_discard,
             commands::recovery::recovery_discard_all,
             commands::recovery::recovery_has_unique_content,
+            commands::recovery::recovery_get_settings,
+            commands::recovery::recovery_set_settings,
+            commands::recovery::recovery_get_previous,
+            commands::recovery::recovery_record_edit,
+            commands::recovery::recovery_list_snapshots,
+            commands::recovery::recovery_get_snapshot,
             // File watcher commands
             commands::file_watcher::file_watcher_start,
             commands::file_watcher::file_watcher_stop,
             commands::file_watcher::file_watcher_mark_saving,
             commands::file_watcher::file_watcher_clear_saving,
+            commands::file_watcher::file_watcher_subscribe,
+            commands::file_watcher::file_watcher_unsubscribe,
+            commands::file_watcher::file_watcher_get_settings,
+            commands::file_watcher::file_watcher_set_settings,
             // Error reporter commands
             commands::error_reporter::error_reporter_set_enabled,
             commands::error_reporter::error_reporter_get_status,
             commands::error_reporter::error_reporter_report,
+            commands::error_reporter::error_reporter_add_breadcrumb,
+            commands::error_reporter::error_reporter_set_context,
+            commands::error_reporter::error_reporter_get_panics,
+            commands::error_reporter::error_reporter_submit_panic,
+            commands::error_reporter::error_reporter_dismiss_panic,
+            // Session commands
+            commands::session::session_get_last_crash,
+            commands::session::session_dismiss_crash,
+            // Support commands
+            commands::support::support_export_diagnostics,
+            // Notification commands
+            commands::notifications::notifications_get_settings,
+            commands::notifications::notifications_set_settings,
+            // Citation commands
+            commands::citations::citation_search,
+            commands::citations::citation_insert,
+            commands::citations::citation_render_document,
+            commands::citations::citation_get_settings,
+            commands::citations::citation_set_settings,
+            // Clipper commands
+            commands::clipper::clip_ingest,
+            commands::clipper::clipper_get_settings,
+            commands::clipper::clipper_set_settings,
+            // Local API commands
+            commands::local_api::local_api_get_settings,
+            commands::local_api::local_api_set_settings,
+            commands::local_api::local_api_get_status,
+            // Plugin commands
+            commands::plugins::plugins_list,
+            commands::plugins::plugins_enable,
+            commands::plugins::plugins_disable,
+            commands::plugins::plugins_reload,
+            commands::plugins::plugins_import_extensions,
+            commands::plugins::plugins_import_file,
+            // Markdown commands
+            commands::markdown::convert_markdown_to_document,
+            commands::markdown::convert_document_to_markdown,
+            // Outline commands
+            commands::outline::document_get_outline,
+            commands::outline::document_goto_heading,
+            // Readability commands
+            commands::readability::document_analyze_style,
+            // Spellcheck commands
+            commands::spellcheck::spellcheck_document,
+            commands::spellcheck::spellcheck_add_word,
+            commands::spellcheck::spellcheck_languages,
+            // Flashcard commands
+            commands::flashcards::flashcards_list,
+            commands::flashcards::flashcards_due,
+            commands::flashcards::flashcards_review,
+            // Task commands
+            commands::tasks::tasks_query,
+            // Writing stats commands
+            commands::writing_stats::writing_stats_progress,
+            commands::writing_stats::writing_stats_history,
+            commands::writing_stats::writing_stats_get_goals,
+            commands::writing_stats::writing_stats_set_goal,
+            // Automation commands
+            commands::automations::automations_dry_run,
+            commands::automations::automations_get_rules,
+            commands::automations::automations_save_rules,
+            commands::automations::automations_is_enabled,
+            commands::automations::automations_enable,
+            commands::automations::automations_disable,
+            // Power commands
+            commands::power::power_get_status,
+            commands::power::power_get_settings,
+            commands::power::power_set_settings,
+            // Log commands
+            commands::logs::logs_list,
+            commands::logs::logs_read_tail,
+            // Queue commands
+            commands::queue::queue_status,
+            // Network commands
+            commands::network::network_get_settings,
+            commands::network::network_set_settings,
             // System commands
             commands::system::show_in_folder,
             commands::system::open_external,
@@ -179,12 +434,30 @@ pub fn run() {
             commands::updates::check_for_updates,
             commands::updates::download_and_install_update,
             commands::updates::get_current_version,
+            commands::updates::get_update_settings,
+            commands::updates::set_update_settings,
+            commands::updates::rollback_update,
+            commands::updates::download_update_in_background,
+            commands::updates::get_pending_update,
+            commands::updates::install_pending_update,
             // RAG commands
             commands::rag::rag_index_project,
             commands::rag::rag_search,
             commands::rag::rag_get_status,
             commands::rag::rag_delete_index,
             commands::rag::rag_index_file,
+            commands::rag::rag_index_status,
+            commands::rag::rag_reindex,
+            commands::rag::rag_ask,
+            commands::rag::rag_related_documents,
+            commands::rag::rag_set_indexing_paused,
+            // Saved search commands
+            commands::saved_searches::saved_search_list,
+            commands::saved_searches::saved_search_create,
+            commands::saved_searches::saved_search_update,
+            commands::saved_searches::saved_search_delete,
+            commands::saved_searches::saved_search_evaluate,
+            commands::saved_searches::saved_search_run_query,
         ])
         .setup(|app| {
             #[cfg(debug_assertions)]
@@ -200,61 +473,45 @@ pub fn run() {
                     // Force the window to have a shadow and proper title bar settings
                     let _ = window.set_shadow(true);
                 }
-
-                // Set up native macOS menu
-                let menu = menu::create_menu(app.handle())?;
-                app.set_menu(menu)?;
             }
 
+            // Set up the native menu bar
+            let menu = menu::create_menu(app.handle())?;
+            app.set_menu(menu)?;
+
             // Set up system tray icon
-            let show_item = MenuItemBuilder::with_id("show", "Show Midlight").build(app)?;
-            let quit_item = MenuItemBuilder::with_id("quit", "Quit").build(app)?;
+            tray::create_tray(app)?;
 
-            let tray_menu = MenuBuilder::new(app)
-                .item(&show_item)
-                .separator()
-                .item(&quit_item)
-                .build()?;
+            // Pause background work in battery saver and on metered connections
+            POWER_MONITOR.start();
 
-            let _tray = TrayIconBuilder::new()
-                .icon(app.default_window_icon().unwrap().clone())
-                .icon_as_template(true)
-                .menu(&tray_menu)
-                .show_menu_on_left_click(false)
-                .on_menu_event(|app, event| match event.id().as_ref() {
-                    "show" => {
-                        if let Some(window) = app.get_webview_window("main") {
-                            let _ = window.show();
-                            let _ = window.set_focus();
-                        }
-                    }
-                    "quit" => {
-                        app.exit(0);
-                    }
-                    _ => {}
-                })
-                .on_tray_icon_event(|tray, event| {
-                    if let TrayIconEvent::Click {
-                        button: MouseButton::Left,
-                        button_state: MouseButtonState::Up,
-                        ..
-                    } = event
-                    {
-                        let app = tray.app_handle();
-                        if let Some(window) = app.get_webview_window("main") {
-                            let _ = window.show();
-                            let _ = window.set_focus();
-                        }
-                    }
-                })
-                .build(app)?;
+            // Retry requests queued while offline
+            commands::queue::start_queue_worker(app.handle().clone());
+
+            // Push quota changes from LLM responses to the frontend
+            commands::llm::start_quota_events(app.handle().clone());
+
+            // Refresh the access token ahead of expiry
+            commands::auth::start_token_refresh_worker(app.handle().clone());
+
+            // Serve the local HTTP API if it's turned on
+            commands::local_api::start_local_api(app.handle());
+
+            // Handle midlight:// links
+            commands::deep_link::setup_deep_links(app)?;
 
             Ok(())
         })
-        .on_menu_event(|_app, _event| {
-            #[cfg(target_os = "macos")]
-            menu::handle_menu_event(_app, _event.id().as_ref());
+        .on_menu_event(|app, event| {
+            menu::handle_menu_event(app, event.id().as_ref());
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|_app, event| {
+            if let tauri::RunEvent::Exit = event {
+                SESSION.end();
+                // May not return: installers can replace the running app
+                commands::updates::install_pending_on_quit();
+            }
+        });
 }
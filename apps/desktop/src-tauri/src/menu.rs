@@ -1,39 +1,100 @@
-// Native macOS menu implementation
-// This provides the standard macOS menu bar for the application
+// Native application menu
+// macOS gets the standard menu bar with an app menu; Windows and Linux get a
+// File/Edit/View/Window/Help menu in the window with the same items and
+// shortcuts, with settings, updates and quit moved to where those platforms
+// expect them.
 
 use tauri::{
-    menu::{Menu, MenuBuilder, MenuItemBuilder, PredefinedMenuItem, SubmenuBuilder},
-    AppHandle, Emitter, Runtime, Wry,
+    menu::{Menu, MenuBuilder, MenuItemBuilder, PredefinedMenuItem, Submenu, SubmenuBuilder},
+    AppHandle, Emitter, Manager, Runtime, Wry,
 };
 
-/// Create the native macOS menu bar
+/// Themes offered in View > Theme, as (menu label, theme id)
+const THEMES: &[(&str, &str)] = &[
+    ("Light", "light"),
+    ("Dark", "dark"),
+    ("Midnight", "midnight"),
+    ("Sepia", "sepia"),
+    ("Forest", "forest"),
+    ("Cyberpunk", "cyberpunk"),
+    ("Coffee", "coffee"),
+];
+
+/// Menu id prefix for theme items; the rest of the id is the theme
+const THEME_ID_PREFIX: &str = "theme_";
+
+/// Create the native menu bar
 pub fn create_menu(app: &AppHandle<Wry>) -> Result<Menu<Wry>, tauri::Error> {
-    // App menu (Midlight)
-    let app_menu = SubmenuBuilder::new(app, "Midlight")
-        .item(&PredefinedMenuItem::about(
-            app,
-            Some("About Midlight"),
-            None,
-        )?)
-        .separator()
-        .item(&MenuItemBuilder::with_id("check_for_updates", "Check for Updates...").build(app)?)
-        .separator()
-        .item(
-            &MenuItemBuilder::with_id("settings", "Settings...")
-                .accelerator("CmdOrCtrl+,")
-                .build(app)?,
-        )
-        .separator()
-        .item(&PredefinedMenuItem::services(app, None)?)
-        .separator()
-        .item(&PredefinedMenuItem::hide(app, None)?)
-        .item(&PredefinedMenuItem::hide_others(app, None)?)
-        .item(&PredefinedMenuItem::show_all(app, None)?)
-        .separator()
-        .item(&PredefinedMenuItem::quit(app, None)?)
-        .build()?;
+    let mut menu = MenuBuilder::new(app);
+
+    #[cfg(target_os = "macos")]
+    {
+        // App menu (Midlight)
+        let app_menu = SubmenuBuilder::new(app, "Midlight")
+            .item(&PredefinedMenuItem::about(
+                app,
+                Some("About Midlight"),
+                None,
+            )?)
+            .separator()
+            .item(&check_for_updates_item(app)?)
+            .separator()
+            .item(&settings_item(app)?)
+            .separator()
+            .item(&PredefinedMenuItem::services(app, None)?)
+            .separator()
+            .item(&PredefinedMenuItem::hide(app, None)?)
+            .item(&PredefinedMenuItem::hide_others(app, None)?)
+            .item(&PredefinedMenuItem::show_all(app, None)?)
+            .separator()
+            .item(&PredefinedMenuItem::quit(app, None)?)
+            .build()?;
+        menu = menu.item(&app_menu);
+    }
+
+    menu = menu
+        .item(&file_menu(app)?)
+        .item(&edit_menu(app)?)
+        .item(&view_menu(app)?);
 
-    // File menu
+    #[cfg(target_os = "macos")]
+    {
+        // Window menu
+        let window_menu = SubmenuBuilder::new(app, "Window")
+            .item(&PredefinedMenuItem::minimize(app, None)?)
+            .item(&PredefinedMenuItem::maximize(app, None)?)
+            .separator()
+            .item(&PredefinedMenuItem::close_window(app, None)?)
+            .build()?;
+        menu = menu.item(&window_menu);
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        // The predefined minimize item only works on macOS and Windows
+        let window_menu = SubmenuBuilder::new(app, "Window")
+            .item(&MenuItemBuilder::with_id("minimize", "Minimize").build(app)?)
+            .build()?;
+        menu = menu.item(&window_menu);
+    }
+
+    // Build the complete menu bar
+    menu.item(&help_menu(app)?).build()
+}
+
+fn settings_item(app: &AppHandle<Wry>) -> Result<tauri::menu::MenuItem<Wry>, tauri::Error> {
+    MenuItemBuilder::with_id("settings", "Settings...")
+        .accelerator("CmdOrCtrl+,")
+        .build(app)
+}
+
+fn check_for_updates_item(
+    app: &AppHandle<Wry>,
+) -> Result<tauri::menu::MenuItem<Wry>, tauri::Error> {
+    MenuItemBuilder::with_id("check_for_updates", "Check for Updates...").build(app)
+}
+
+fn file_menu(app: &AppHandle<Wry>) -> Result<Submenu<Wry>, tauri::Error> {
     let file_menu = SubmenuBuilder::new(app, "File")
         .item(
             &MenuItemBuilder::with_id("new_document", "New Document")
@@ -46,6 +107,8 @@ pub fn create_menu(app: &AppHandle<Wry>) -> Result<Menu<Wry>, tauri::Error> {
                 .accelerator("CmdOrCtrl+O")
                 .build(app)?,
         )
+        .item(&MenuItemBuilder::with_id("import_obsidian", "Import Obsidian Vault...").build(app)?)
+        .item(&MenuItemBuilder::with_id("import_notion", "Import Notion Export...").build(app)?)
         .item(&MenuItemBuilder::with_id("import_docx", "Import Word Document...").build(app)?)
         .separator()
         .item(
@@ -61,13 +124,46 @@ pub fn create_menu(app: &AppHandle<Wry>) -> Result<Menu<Wry>, tauri::Error> {
             &MenuItemBuilder::with_id("close_tab", "Close Tab")
                 .accelerator("CmdOrCtrl+W")
                 .build(app)?,
-        )
-        .build()?;
+        );
 
-    // Edit menu
-    let edit_menu = SubmenuBuilder::new(app, "Edit")
+    // Settings and quit live in the app menu on macOS
+    #[cfg(not(target_os = "macos"))]
+    let file_menu = file_menu
+        .separator()
+        .item(&settings_item(app)?)
+        .separator()
+        .item(&quit_item(app)?);
+
+    file_menu.build()
+}
+
+#[cfg(target_os = "windows")]
+fn quit_item(app: &AppHandle<Wry>) -> Result<tauri::menu::MenuItem<Wry>, tauri::Error> {
+    MenuItemBuilder::with_id("quit_app", "Exit").build(app)
+}
+
+#[cfg(all(not(target_os = "macos"), not(target_os = "windows")))]
+fn quit_item(app: &AppHandle<Wry>) -> Result<tauri::menu::MenuItem<Wry>, tauri::Error> {
+    MenuItemBuilder::with_id("quit_app", "Quit")
+        .accelerator("CmdOrCtrl+Q")
+        .build(app)
+}
+
+fn edit_menu(app: &AppHandle<Wry>) -> Result<Submenu<Wry>, tauri::Error> {
+    let edit_menu = SubmenuBuilder::new(app, "Edit");
+
+    // Undo and redo are only built in on macOS. Elsewhere the editor does them;
+    // no accelerator, so Ctrl+Z still reaches the editor directly.
+    #[cfg(target_os = "macos")]
+    let edit_menu = edit_menu
         .item(&PredefinedMenuItem::undo(app, None)?)
-        .item(&PredefinedMenuItem::redo(app, None)?)
+        .item(&PredefinedMenuItem::redo(app, None)?);
+    #[cfg(not(target_os = "macos"))]
+    let edit_menu = edit_menu
+        .item(&MenuItemBuilder::with_id("undo", "Undo").build(app)?)
+        .item(&MenuItemBuilder::with_id("redo", "Redo").build(app)?);
+
+    edit_menu
         .separator()
         .item(&PredefinedMenuItem::cut(app, None)?)
         .item(&PredefinedMenuItem::copy(app, None)?)
@@ -79,9 +175,10 @@ pub fn create_menu(app: &AppHandle<Wry>) -> Result<Menu<Wry>, tauri::Error> {
                 .accelerator("CmdOrCtrl+F")
                 .build(app)?,
         )
-        .build()?;
+        .build()
+}
 
-    // View menu
+fn view_menu(app: &AppHandle<Wry>) -> Result<Submenu<Wry>, tauri::Error> {
     let view_menu = SubmenuBuilder::new(app, "View")
         .item(
             &MenuItemBuilder::with_id("toggle_ai_panel", "Toggle AI Panel")
@@ -94,36 +191,68 @@ pub fn create_menu(app: &AppHandle<Wry>) -> Result<Menu<Wry>, tauri::Error> {
                 .build(app)?,
         )
         .separator()
-        .item(&PredefinedMenuItem::fullscreen(app, None)?)
-        .build()?;
+        .item(&theme_menu(app)?)
+        .separator()
+        .item(
+            &MenuItemBuilder::with_id("reload", "Reload")
+                .accelerator("CmdOrCtrl+R")
+                .build(app)?,
+        );
+
+    #[cfg(target_os = "macos")]
+    let view_menu = view_menu.item(&PredefinedMenuItem::fullscreen(app, None)?);
+    #[cfg(not(target_os = "macos"))]
+    let view_menu = view_menu.item(
+        &MenuItemBuilder::with_id("toggle_fullscreen", "Toggle Full Screen")
+            .accelerator("F11")
+            .build(app)?,
+    );
+
+    view_menu.build()
+}
+
+fn theme_menu(app: &AppHandle<Wry>) -> Result<Submenu<Wry>, tauri::Error> {
+    let mut theme_menu = SubmenuBuilder::new(app, "Theme");
+    for (label, theme) in THEMES {
+        theme_menu = theme_menu.item(
+            &MenuItemBuilder::with_id(format!("{}{}", THEME_ID_PREFIX, theme), *label)
+                .build(app)?,
+        );
+    }
 
-    // Window menu
-    let window_menu = SubmenuBuilder::new(app, "Window")
-        .item(&PredefinedMenuItem::minimize(app, None)?)
-        .item(&PredefinedMenuItem::maximize(app, None)?)
+    theme_menu
         .separator()
-        .item(&PredefinedMenuItem::close_window(app, None)?)
-        .build()?;
+        .item(&MenuItemBuilder::with_id(format!("{}system", THEME_ID_PREFIX), "System").build(app)?)
+        .build()
+}
 
-    // Help menu
+fn help_menu(app: &AppHandle<Wry>) -> Result<Submenu<Wry>, tauri::Error> {
     let help_menu = SubmenuBuilder::new(app, "Help")
         .item(&MenuItemBuilder::with_id("documentation", "Documentation").build(app)?)
-        .item(&MenuItemBuilder::with_id("report_issue", "Report an Issue").build(app)?)
-        .build()?;
+        .item(&MenuItemBuilder::with_id("report_issue", "Report an Issue").build(app)?);
 
-    // Build the complete menu bar
-    MenuBuilder::new(app)
-        .item(&app_menu)
-        .item(&file_menu)
-        .item(&edit_menu)
-        .item(&view_menu)
-        .item(&window_menu)
-        .item(&help_menu)
-        .build()
+    // Updates and About live in the app menu on macOS
+    #[cfg(not(target_os = "macos"))]
+    let help_menu = help_menu
+        .separator()
+        .item(&check_for_updates_item(app)?)
+        .separator()
+        .item(&PredefinedMenuItem::about(
+            app,
+            Some("About Midlight"),
+            None,
+        )?);
+
+    help_menu.build()
 }
 
 /// Handle menu events by emitting to the frontend
 pub fn handle_menu_event<R: Runtime>(app: &AppHandle<R>, event_id: &str) {
+    if let Some(theme) = event_id.strip_prefix(THEME_ID_PREFIX) {
+        let _ = app.emit("menu:set-theme", theme);
+        return;
+    }
+
     // Map menu IDs to frontend events
     let frontend_event = match event_id {
         // App menu
@@ -133,6 +262,8 @@ pub fn handle_menu_event<R: Runtime>(app: &AppHandle<R>, event_id: &str) {
         // File menu
         "new_document" => Some("menu:new-document"),
         "open_workspace" => Some("menu:open-workspace"),
+        "import_obsidian" => Some("menu:import-obsidian"),
+        "import_notion" => Some("menu:import-notion"),
         "import_docx" => Some("menu:import-docx"),
         "save" => Some("menu:save"),
         "export_docx" => Some("menu:export-docx"),
@@ -140,16 +271,38 @@ pub fn handle_menu_event<R: Runtime>(app: &AppHandle<R>, event_id: &str) {
         "close_tab" => Some("menu:close-tab"),
 
         // Edit menu
+        "undo" => Some("menu:undo"),
+        "redo" => Some("menu:redo"),
         "find" => Some("menu:find"),
 
         // View menu
         "toggle_ai_panel" => Some("menu:toggle-ai-panel"),
         "toggle_versions_panel" => Some("menu:toggle-versions-panel"),
+        "reload" => Some("menu:reload"),
 
         // Help menu
         "documentation" => Some("menu:documentation"),
         "report_issue" => Some("menu:report-issue"),
 
+        // Handled here rather than by the frontend
+        "toggle_fullscreen" => {
+            if let Some(window) = app.get_webview_window("main") {
+                let fullscreen = window.is_fullscreen().unwrap_or(false);
+                let _ = window.set_fullscreen(!fullscreen);
+            }
+            None
+        }
+        "minimize" => {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.minimize();
+            }
+            None
+        }
+        "quit_app" => {
+            app.exit(0);
+            None
+        }
+
         // Predefined menu items are handled by Tauri automatically
         _ => None,
     };
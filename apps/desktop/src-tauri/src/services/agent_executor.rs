@@ -1,8 +1,23 @@
 // Agent Executor - Handles tool execution for AI agent
 
+use crate::services::agent_memory::AgentMemoryStore;
+use crate::services::change_staging::{content_hash, PendingChangeStore};
+use crate::services::checkpoint_manager::CheckpointAttribution;
+use crate::services::custom_tools::{self, CustomToolRegistry};
+use crate::services::document_chunks::resolve_content;
+use crate::services::document_lock::{ensure_unlocked, is_locked_document};
+use crate::services::execution_journal::{ExecutionJournal, FileChange, FileOperation};
+use crate::services::import_security::{is_path_safe, sanitize_filename, sanitize_relative_path};
+use crate::services::markdown_convert::{self, MarkdownOptions};
+use crate::services::path_glob::PathGlob;
+use crate::services::plugins::PluginRuntime;
+use crate::services::tool_audit_log::{self, ToolAuditLog};
+use crate::services::web_fetch;
+use regex::{NoExpand, Regex, RegexBuilder};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio::fs;
 use tracing::{debug, info, warn};
 use uuid::Uuid;
@@ -11,7 +26,6 @@ use uuid::Uuid;
 // Tool Execution Types
 // ============================================================================
 
-#[allow(dead_code)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ToolExecution {
@@ -22,6 +36,14 @@ pub struct ToolExecution {
     pub result: Option<ToolResult>,
     pub started_at: String,
     pub completed_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    /// Conversation whose agent run called the tool
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conversation_id: Option<String>,
+    /// Workspace paths named in the arguments or result
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
 }
 
 #[allow(dead_code)]
@@ -62,35 +84,247 @@ pub struct SearchMatch {
     pub line: Option<u32>,
 }
 
-#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    #[default]
+    Edit,
+    Delete,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PendingChange {
     pub change_id: String,
     pub path: String,
+    #[serde(default)]
+    pub kind: ChangeKind,
     pub original_content: String,
     pub new_content: String,
     pub description: Option<String>,
     pub created_at: String,
+    /// Full document to write when an edit is approved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub staged_document: Option<Value>,
+    /// Hash of the file the edit was based on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_hash: Option<String>,
+    /// What made the change, credited on the checkpoints taken when it's applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attribution: Option<CheckpointAttribution>,
+}
+
+/// One document's share of a staged search-and-replace
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StagedReplacement {
+    #[serde(flatten)]
+    pub change: PendingChange,
+    pub replacements: usize,
+    pub original_tiptap_json: Value,
+    pub staged_tiptap_json: Value,
 }
 
+/// Maximum number of documents a single search-and-replace may change
+const MAX_REPLACE_DOCUMENTS: usize = 200;
+
+/// Names of the tools implemented by the executor itself
+pub const BUILTIN_TOOLS: &[&str] = &[
+    "list_documents",
+    "read_document",
+    "create_document",
+    "edit_document",
+    "move_document",
+    "delete_document",
+    "search_documents",
+    "search_replace",
+    "create_folder",
+    "rename_document",
+    "duplicate_document",
+    "fetch_url",
+    "remember",
+    "recall",
+];
+
 // ============================================================================
 // Agent Executor
 // ============================================================================
 
 pub struct AgentExecutor {
     workspace_root: PathBuf,
+    require_confirmation: bool,
+    allow_web_fetch: bool,
+    custom_tools: CustomToolRegistry,
+    plugins: Option<Arc<PluginRuntime>>,
+    conversation_id: Option<String>,
 }
 
 impl AgentExecutor {
     pub fn new(workspace_root: PathBuf) -> Self {
-        Self { workspace_root }
+        Self {
+            workspace_root,
+            require_confirmation: false,
+            allow_web_fetch: false,
+            custom_tools: CustomToolRegistry::default(),
+            plugins: None,
+            conversation_id: None,
+        }
+    }
+
+    /// Conversation to credit executions to in the audit log
+    pub fn with_conversation(mut self, conversation_id: Option<String>) -> Self {
+        self.conversation_id = conversation_id;
+        self
+    }
+
+    /// Credit for a change a tool stages
+    fn attribution(&self, tool: &str, change_id: &str) -> CheckpointAttribution {
+        CheckpointAttribution {
+            conversation_id: self.conversation_id.clone(),
+            tool: Some(tool.to_string()),
+            change_id: Some(change_id.to_string()),
+        }
+    }
+
+    /// Look up user-defined tools in this registry instead of the user's own
+    #[cfg(test)]
+    pub fn with_custom_tools(mut self, custom_tools: CustomToolRegistry) -> Self {
+        self.custom_tools = custom_tools;
+        self
+    }
+
+    /// Also run tools added by the workspace's enabled plugins
+    pub fn with_plugins(mut self, plugins: Arc<PluginRuntime>) -> Self {
+        self.plugins = Some(plugins);
+        self
+    }
+
+    /// Let the fetch_url tool download web pages. Off unless the user has
+    /// granted the agent web access.
+    pub fn with_web_fetch(mut self, allow_web_fetch: bool) -> Self {
+        self.allow_web_fetch = allow_web_fetch;
+        self
+    }
+
+    /// Stage edits and deletions as pending changes for the user to approve
+    /// instead of returning them for the frontend to apply or deleting directly
+    pub fn with_confirmation(mut self, require_confirmation: bool) -> Self {
+        self.require_confirmation = require_confirmation;
+        self
+    }
+
+    /// Status reported for staged edits: queued for confirmation, or
+    /// completed and left to the frontend to apply
+    fn staged_status(&self) -> ToolExecutionStatus {
+        if self.require_confirmation {
+            ToolExecutionStatus::RequiresConfirmation
+        } else {
+            ToolExecutionStatus::Completed
+        }
+    }
+
+    /// Save a pending change to the workspace's confirmation queue
+    fn stage_change(&self, change: PendingChange) -> Result<(), String> {
+        PendingChangeStore::new(&self.workspace_root).stage(change)
+    }
+
+    /// Record a tool's file changes in the execution journal so it can be
+    /// undone, and add the execution id to the result
+    async fn journal(
+        &self,
+        tool_name: &str,
+        changes: Vec<FileChange>,
+        mut result: ToolResult,
+    ) -> ToolResult {
+        match ExecutionJournal::new(&self.workspace_root)
+            .record(tool_name, changes)
+            .await
+        {
+            Ok(record) => {
+                if let Some(Value::Object(data)) = result.data.as_mut() {
+                    data.insert("executionId".to_string(), json!(record.id));
+                }
+            }
+            // The change has already been made; failing the tool would be misleading
+            Err(e) => warn!("Failed to journal {} execution: {}", tool_name, e),
+        }
+        result
+    }
+
+    /// Resolve a workspace-relative path argument. Rejects `..`, absolute
+    /// paths and anything that resolves (through symlinks) outside the
+    /// workspace root. Returns the cleaned relative path and the full path.
+    fn resolve_path(&self, path: &str) -> Result<(String, PathBuf), String> {
+        let relative =
+            sanitize_relative_path(path.trim_start_matches('/')).map_err(|e| e.to_string())?;
+        let full_path = self.workspace_root.join(&relative);
+        if !is_path_safe(&full_path, &self.workspace_root) {
+            return Err(format!("Path is outside the workspace: {}", path));
+        }
+        let relative = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        Ok((relative, full_path))
     }
 
     /// Execute a tool by name with the given arguments
     pub async fn execute_tool(&self, tool_name: &str, arguments: Value) -> ToolResult {
         info!("Executing tool: {} with args: {:?}", tool_name, arguments);
 
+        let started_at = chrono::Utc::now();
+        let timer = std::time::Instant::now();
+        let result = self.dispatch(tool_name, arguments.clone()).await;
+        let duration_ms = timer.elapsed().as_millis() as u64;
+
+        self.audit(tool_name, arguments, &result, started_at, duration_ms);
+        result
+    }
+
+    /// Record an execution in the workspace's audit log
+    fn audit(
+        &self,
+        tool_name: &str,
+        arguments: Value,
+        result: &ToolResult,
+        started_at: chrono::DateTime<chrono::Utc>,
+        duration_ms: u64,
+    ) {
+        let status = if !result.success {
+            ToolExecutionStatus::Failed
+        } else {
+            result
+                .data
+                .as_ref()
+                .and_then(|d| d.get("status"))
+                .and_then(|s| serde_json::from_value(s.clone()).ok())
+                .unwrap_or(ToolExecutionStatus::Completed)
+        };
+
+        let execution = ToolExecution {
+            id: Uuid::new_v4().to_string(),
+            tool_name: tool_name.to_string(),
+            paths: tool_audit_log::affected_paths(&arguments, result),
+            arguments: tool_audit_log::summarize_value(&arguments),
+            status,
+            result: Some(ToolResult {
+                success: result.success,
+                data: result.data.as_ref().map(tool_audit_log::summarize_value),
+                error: result.error.clone(),
+            }),
+            started_at: started_at.to_rfc3339(),
+            completed_at: Some(chrono::Utc::now().to_rfc3339()),
+            duration_ms: Some(duration_ms),
+            conversation_id: self.conversation_id.clone(),
+        };
+
+        if let Err(e) = ToolAuditLog::new(&self.workspace_root).append(&execution) {
+            warn!("Failed to record {} in audit log: {}", tool_name, e);
+        }
+    }
+
+    async fn dispatch(&self, tool_name: &str, arguments: Value) -> ToolResult {
         match tool_name {
             "list_documents" => self.list_documents(arguments).await,
             "read_document" => self.read_document(arguments).await,
@@ -99,10 +333,78 @@ impl AgentExecutor {
             "move_document" => self.move_document(arguments).await,
             "delete_document" => self.delete_document(arguments).await,
             "search_documents" => self.search_documents(arguments).await,
-            _ => ToolResult {
+            "search_replace" => self.search_replace(arguments).await,
+            "create_folder" => self.create_folder(arguments).await,
+            "rename_document" => self.rename_document(arguments).await,
+            "duplicate_document" => self.duplicate_document(arguments).await,
+            "fetch_url" => self.fetch_url(arguments).await,
+            "remember" => self.remember(arguments),
+            "recall" => self.recall(arguments),
+            _ => self.run_custom_tool(tool_name, arguments).await,
+        }
+    }
+
+    /// Run a user-defined tool from the custom tool registry, or one added
+    /// by a plugin
+    async fn run_custom_tool(&self, tool_name: &str, arguments: Value) -> ToolResult {
+        let tool = match self.custom_tools.find(tool_name) {
+            Ok(Some(tool)) => tool,
+            Ok(None) if self.plugins.is_some() => {
+                return self.run_plugin_tool(tool_name, arguments).await
+            }
+            Ok(None) => {
+                return ToolResult {
+                    success: false,
+                    data: None,
+                    error: Some(format!("Unknown tool: {}", tool_name)),
+                }
+            }
+            Err(e) => {
+                return ToolResult {
+                    success: false,
+                    data: None,
+                    error: Some(e),
+                }
+            }
+        };
+
+        match custom_tools::run_tool(&tool, &arguments, &self.workspace_root).await {
+            Ok(output) => ToolResult {
+                success: true,
+                data: Some(json!(output)),
+                error: None,
+            },
+            Err(e) => ToolResult {
+                success: false,
+                data: None,
+                error: Some(e),
+            },
+        }
+    }
+
+    async fn run_plugin_tool(&self, tool_name: &str, arguments: Value) -> ToolResult {
+        let Some(plugins) = &self.plugins else {
+            return ToolResult {
                 success: false,
                 data: None,
                 error: Some(format!("Unknown tool: {}", tool_name)),
+            };
+        };
+
+        let workspace_root = self.workspace_root.to_string_lossy();
+        match plugins
+            .call_tool(&workspace_root, tool_name, &arguments)
+            .await
+        {
+            Ok(output) => ToolResult {
+                success: true,
+                data: Some(json!({ "output": output })),
+                error: None,
+            },
+            Err(e) => ToolResult {
+                success: false,
+                data: None,
+                error: Some(e),
             },
         }
     }
@@ -208,7 +510,8 @@ impl AgentExecutor {
             Ok(content) => {
                 // Parse the Midlight JSON format
                 match serde_json::from_str::<Value>(&content) {
-                    Ok(doc) => {
+                    Ok(mut doc) => {
+                        resolve_content(&file_path, &mut doc);
                         // Extract text content from Tiptap JSON
                         // Convert to markdown so AI sees formatting (headings, bold, etc.)
                         let markdown_content =
@@ -311,7 +614,8 @@ impl AgentExecutor {
             "content": tiptap_content,
         });
 
-        match fs::write(&file_path, serde_json::to_string_pretty(&doc).unwrap()).await {
+        let serialized = serde_json::to_string_pretty(&doc).unwrap();
+        match fs::write(&file_path, &serialized).await {
             Ok(_) => {
                 let relative_path = file_path
                     .strip_prefix(&self.workspace_root)
@@ -319,14 +623,22 @@ impl AgentExecutor {
                     .to_string_lossy()
                     .to_string();
 
-                ToolResult {
+                let result = ToolResult {
                     success: true,
                     data: Some(json!({
                         "path": relative_path,
                         "name": file_path.file_name().unwrap().to_string_lossy(),
                     })),
                     error: None,
-                }
+                };
+                let change = FileChange {
+                    path: relative_path,
+                    operation: FileOperation::Create,
+                    previous_path: None,
+                    old_content: None,
+                    new_content: Some(serialized),
+                };
+                self.journal("create_document", vec![change], result).await
             }
             Err(e) => ToolResult {
                 success: false,
@@ -365,6 +677,14 @@ impl AgentExecutor {
         let file_path = self.workspace_root.join(path.trim_start_matches('/'));
         debug!("Editing document (staging): {:?}", file_path);
 
+        if let Err(e) = ensure_unlocked(&file_path, path) {
+            return ToolResult {
+                success: false,
+                data: None,
+                error: Some(e),
+            };
+        }
+
         // Read existing content
         let original_content = match fs::read_to_string(&file_path).await {
             Ok(content) => content,
@@ -378,7 +698,7 @@ impl AgentExecutor {
         };
 
         // Parse existing document
-        let original_doc: Value = match serde_json::from_str(&original_content) {
+        let mut original_doc: Value = match serde_json::from_str(&original_content) {
             Ok(d) => d,
             Err(e) => {
                 return ToolResult {
@@ -389,6 +709,8 @@ impl AgentExecutor {
             }
         };
 
+        resolve_content(&file_path, &mut original_doc);
+
         // Extract original text for diff display
         let original_text =
             self.extract_text_from_tiptap(original_doc.get("content").unwrap_or(&Value::Null));
@@ -414,6 +736,28 @@ impl AgentExecutor {
             .cloned()
             .unwrap_or(json!({"type": "doc", "content": []}));
 
+        if self.require_confirmation {
+            let change = PendingChange {
+                change_id: change_id.clone(),
+                path: path.trim_start_matches('/').to_string(),
+                kind: ChangeKind::Edit,
+                original_content: self.tiptap_to_markdown(&original_tiptap_content),
+                new_content: new_content.to_string(),
+                description: description.map(String::from),
+                created_at: chrono::Utc::now().to_rfc3339(),
+                staged_document: Some(staged_doc.clone()),
+                base_hash: Some(content_hash(&original_content)),
+                attribution: Some(self.attribution("edit_document", &change_id)),
+            };
+            if let Err(e) = self.stage_change(change) {
+                return ToolResult {
+                    success: false,
+                    data: None,
+                    error: Some(e),
+                };
+            }
+        }
+
         // Return staged content WITHOUT writing to disk
         // Frontend will display diff and write on accept
         ToolResult {
@@ -427,6 +771,7 @@ impl AgentExecutor {
                 "originalTiptapJson": original_tiptap_content,
                 "stagedTiptapJson": staged_tiptap_content,
                 "requiresAcceptance": true,
+                "status": self.staged_status(),
             })),
             error: None,
         }
@@ -494,14 +839,24 @@ impl AgentExecutor {
         }
 
         match fs::rename(&old_file_path, &new_file_path).await {
-            Ok(_) => ToolResult {
-                success: true,
-                data: Some(json!({
-                    "oldPath": old_path,
-                    "newPath": new_path,
-                })),
-                error: None,
-            },
+            Ok(_) => {
+                let result = ToolResult {
+                    success: true,
+                    data: Some(json!({
+                        "oldPath": old_path,
+                        "newPath": new_path,
+                    })),
+                    error: None,
+                };
+                let change = FileChange {
+                    path: new_path.to_string(),
+                    operation: FileOperation::Move,
+                    previous_path: Some(old_path.to_string()),
+                    old_content: None,
+                    new_content: None,
+                };
+                self.journal("move_document", vec![change], result).await
+            }
             Err(e) => ToolResult {
                 success: false,
                 data: None,
@@ -533,16 +888,88 @@ impl AgentExecutor {
                 error: Some(format!("Document not found: {}", path)),
             };
         }
+        if let Err(e) = ensure_unlocked(&file_path, path) {
+            return ToolResult {
+                success: false,
+                data: None,
+                error: Some(e),
+            };
+        }
+
+        if self.require_confirmation {
+            let original_content = match fs::read_to_string(&file_path).await {
+                Ok(content) => serde_json::from_str::<Value>(&content)
+                    .ok()
+                    .and_then(|mut doc| {
+                        resolve_content(&file_path, &mut doc);
+                        doc.get("content").map(|c| self.tiptap_to_markdown(c))
+                    })
+                    .unwrap_or(content),
+                Err(_) => String::new(),
+            };
+            let change_id = Uuid::new_v4().to_string();
+            let change = PendingChange {
+                change_id: change_id.clone(),
+                path: path.trim_start_matches('/').to_string(),
+                kind: ChangeKind::Delete,
+                original_content,
+                new_content: String::new(),
+                description: args
+                    .get("description")
+                    .and_then(|v| v.as_str())
+                    .map(String::from),
+                created_at: chrono::Utc::now().to_rfc3339(),
+                staged_document: None,
+                base_hash: None,
+                attribution: None,
+            };
+
+            return match self.stage_change(change) {
+                Ok(()) => ToolResult {
+                    success: true,
+                    data: Some(json!({
+                        "path": path,
+                        "changeId": change_id,
+                        "requiresAcceptance": true,
+                        "status": ToolExecutionStatus::RequiresConfirmation,
+                    })),
+                    error: None,
+                },
+                Err(e) => ToolResult {
+                    success: false,
+                    data: None,
+                    error: Some(e),
+                },
+            };
+        }
+
+        // Keep the content so the deletion can be undone from the journal
+        let old_content = fs::read_to_string(&file_path).await.ok();
 
         // Use trash crate to move to trash instead of permanent delete
         match trash::delete(&file_path) {
-            Ok(_) => ToolResult {
-                success: true,
-                data: Some(json!({
-                    "path": path,
-                })),
-                error: None,
-            },
+            Ok(_) => {
+                let result = ToolResult {
+                    success: true,
+                    data: Some(json!({
+                        "path": path,
+                    })),
+                    error: None,
+                };
+                match old_content {
+                    Some(content) => {
+                        let change = FileChange {
+                            path: path.to_string(),
+                            operation: FileOperation::Delete,
+                            previous_path: None,
+                            old_content: Some(content),
+                            new_content: None,
+                        };
+                        self.journal("delete_document", vec![change], result).await
+                    }
+                    None => result,
+                }
+            }
             Err(e) => ToolResult {
                 success: false,
                 data: None,
@@ -551,73 +978,474 @@ impl AgentExecutor {
         }
     }
 
-    /// Search documents for content
-    async fn search_documents(&self, args: Value) -> ToolResult {
-        let query = match args.get("query").and_then(|v| v.as_str()) {
-            Some(q) => q,
+    /// Create a folder (and any missing parents)
+    async fn create_folder(&self, args: Value) -> ToolResult {
+        let path = match args.get("path").and_then(|v| v.as_str()) {
+            Some(p) => p,
             None => {
                 return ToolResult {
                     success: false,
                     data: None,
-                    error: Some("Missing required parameter: query".to_string()),
+                    error: Some("Missing required parameter: path".to_string()),
                 }
             }
         };
 
-        let _file_pattern = args.get("filePattern").and_then(|v| v.as_str());
-        let query_lower = query.to_lowercase();
-
-        debug!("Searching for: {}", query);
-
-        let mut matches: Vec<SearchMatch> = Vec::new();
+        let (relative_path, folder_path) = match self.resolve_path(path) {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                return ToolResult {
+                    success: false,
+                    data: None,
+                    error: Some(e),
+                }
+            }
+        };
+        debug!("Creating folder: {:?}", folder_path);
 
-        // Recursively search files
-        if let Err(e) = self
-            .search_directory(&self.workspace_root, &query_lower, &mut matches)
-            .await
-        {
-            warn!("Search error: {}", e);
+        if folder_path.exists() {
+            return ToolResult {
+                success: false,
+                data: None,
+                error: Some(format!("Folder already exists: {}", path)),
+            };
         }
 
-        ToolResult {
-            success: true,
-            data: Some(json!({ "matches": matches })),
-            error: None,
+        match fs::create_dir_all(&folder_path).await {
+            Ok(_) => {
+                let result = ToolResult {
+                    success: true,
+                    data: Some(json!({
+                        "path": relative_path,
+                        "name": folder_path.file_name().unwrap().to_string_lossy(),
+                    })),
+                    error: None,
+                };
+                let change = FileChange {
+                    path: relative_path,
+                    operation: FileOperation::Create,
+                    previous_path: None,
+                    old_content: None,
+                    new_content: None,
+                };
+                self.journal("create_folder", vec![change], result).await
+            }
+            Err(e) => ToolResult {
+                success: false,
+                data: None,
+                error: Some(format!("Failed to create folder: {}", e)),
+            },
         }
     }
 
-    /// Recursively search a directory for matching content
-    async fn search_directory(
-        &self,
-        dir: &PathBuf,
-        query: &str,
-        matches: &mut Vec<SearchMatch>,
-    ) -> Result<(), std::io::Error> {
-        let mut entries = fs::read_dir(dir).await?;
-
-        while let Some(entry) = entries.next_entry().await? {
-            let path = entry.path();
-            let file_name = entry.file_name().to_string_lossy().to_string();
+    /// Rename a document or folder in place
+    async fn rename_document(&self, args: Value) -> ToolResult {
+        let path = match args.get("path").and_then(|v| v.as_str()) {
+            Some(p) => p,
+            None => {
+                return ToolResult {
+                    success: false,
+                    data: None,
+                    error: Some("Missing required parameter: path".to_string()),
+                }
+            }
+        };
 
-            // Skip hidden files
-            if file_name.starts_with('.') {
-                continue;
+        let new_name = match args.get("newName").and_then(|v| v.as_str()) {
+            Some(n) => n,
+            None => {
+                return ToolResult {
+                    success: false,
+                    data: None,
+                    error: Some("Missing required parameter: newName".to_string()),
+                }
             }
+        };
 
-            if path.is_dir() {
-                // Recurse into subdirectories
-                Box::pin(self.search_directory(&path, query, matches)).await?;
-            } else if file_name.ends_with(".midlight") {
-                // Search in file content
-                if let Ok(content) = fs::read_to_string(&path).await {
-                    if let Ok(doc) = serde_json::from_str::<Value>(&content) {
-                        let text = self
-                            .extract_text_from_tiptap(doc.get("content").unwrap_or(&Value::Null));
+        if new_name.contains(['/', '\\']) {
+            return ToolResult {
+                success: false,
+                data: None,
+                error: Some(
+                    "newName must be a name, not a path (use move_document to move)".to_string(),
+                ),
+            };
+        }
 
-                        if text.to_lowercase().contains(query) {
-                            let relative_path = path
-                                .strip_prefix(&self.workspace_root)
-                                .unwrap_or(path.as_path())
+        let (old_relative, old_file_path) = match self.resolve_path(path) {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                return ToolResult {
+                    success: false,
+                    data: None,
+                    error: Some(e),
+                }
+            }
+        };
+
+        if !old_file_path.exists() {
+            return ToolResult {
+                success: false,
+                data: None,
+                error: Some(format!("Document not found: {}", path)),
+            };
+        }
+
+        let mut file_name = match sanitize_filename(new_name) {
+            Ok(name) => name,
+            Err(e) => {
+                return ToolResult {
+                    success: false,
+                    data: None,
+                    error: Some(e.to_string()),
+                }
+            }
+        };
+        // Keep documents as documents when the agent leaves off the extension
+        if old_relative.ends_with(".midlight") && !file_name.ends_with(".midlight") {
+            file_name.push_str(".midlight");
+        }
+
+        let new_relative = match old_relative.rsplit_once('/') {
+            Some((parent, _)) => format!("{}/{}", parent, file_name),
+            None => file_name.clone(),
+        };
+        let new_file_path = old_file_path.with_file_name(&file_name);
+        debug!("Renaming {:?} -> {:?}", old_file_path, new_file_path);
+
+        if new_file_path.exists() {
+            return ToolResult {
+                success: false,
+                data: None,
+                error: Some(format!("Destination already exists: {}", new_relative)),
+            };
+        }
+
+        match fs::rename(&old_file_path, &new_file_path).await {
+            Ok(_) => {
+                // Carry the sidecar along, like the file tree's rename
+                let mut old_sidecar = old_file_path.into_os_string();
+                old_sidecar.push(".sidecar.json");
+                let mut new_sidecar = new_file_path.into_os_string();
+                new_sidecar.push(".sidecar.json");
+                if Path::new(&old_sidecar).exists() {
+                    let _ = fs::rename(&old_sidecar, &new_sidecar).await;
+                }
+
+                let result = ToolResult {
+                    success: true,
+                    data: Some(json!({
+                        "oldPath": old_relative,
+                        "newPath": new_relative,
+                        "name": file_name,
+                    })),
+                    error: None,
+                };
+                let change = FileChange {
+                    path: new_relative,
+                    operation: FileOperation::Move,
+                    previous_path: Some(old_relative),
+                    old_content: None,
+                    new_content: None,
+                };
+                self.journal("rename_document", vec![change], result).await
+            }
+            Err(e) => ToolResult {
+                success: false,
+                data: None,
+                error: Some(format!("Failed to rename document: {}", e)),
+            },
+        }
+    }
+
+    /// Copy a document next to the original with a "-Copy" suffix
+    async fn duplicate_document(&self, args: Value) -> ToolResult {
+        let path = match args.get("path").and_then(|v| v.as_str()) {
+            Some(p) => p,
+            None => {
+                return ToolResult {
+                    success: false,
+                    data: None,
+                    error: Some("Missing required parameter: path".to_string()),
+                }
+            }
+        };
+
+        let (relative_path, file_path) = match self.resolve_path(path) {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                return ToolResult {
+                    success: false,
+                    data: None,
+                    error: Some(e),
+                }
+            }
+        };
+
+        if !file_path.is_file() {
+            return ToolResult {
+                success: false,
+                data: None,
+                error: Some(format!("Document not found: {}", path)),
+            };
+        }
+
+        let content = match fs::read_to_string(&file_path).await {
+            Ok(content) => content,
+            Err(e) => {
+                return ToolResult {
+                    success: false,
+                    data: None,
+                    error: Some(format!("Failed to read document: {}", e)),
+                }
+            }
+        };
+
+        // "notes.midlight" -> "notes-Copy.midlight", "notes-Copy 2.midlight", ...
+        let stem = file_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let ext = file_path
+            .extension()
+            .map(|e| format!(".{}", e.to_string_lossy()))
+            .unwrap_or_default();
+        let Some(copy_name) = (1..=1000)
+            .map(|n| match n {
+                1 => format!("{}-Copy{}", stem, ext),
+                n => format!("{}-Copy {}{}", stem, n, ext),
+            })
+            .find(|name| !file_path.with_file_name(name).exists())
+        else {
+            return ToolResult {
+                success: false,
+                data: None,
+                error: Some("Too many copies exist".to_string()),
+            };
+        };
+
+        let copy_path = file_path.with_file_name(&copy_name);
+        let copy_relative = match relative_path.rsplit_once('/') {
+            Some((parent, _)) => format!("{}/{}", parent, copy_name),
+            None => copy_name.clone(),
+        };
+        debug!("Duplicating {:?} -> {:?}", file_path, copy_path);
+
+        match fs::write(&copy_path, &content).await {
+            Ok(_) => {
+                let result = ToolResult {
+                    success: true,
+                    data: Some(json!({
+                        "path": relative_path,
+                        "newPath": copy_relative,
+                        "name": copy_name,
+                    })),
+                    error: None,
+                };
+                let change = FileChange {
+                    path: copy_relative,
+                    operation: FileOperation::Create,
+                    previous_path: None,
+                    old_content: None,
+                    new_content: Some(content),
+                };
+                self.journal("duplicate_document", vec![change], result)
+                    .await
+            }
+            Err(e) => ToolResult {
+                success: false,
+                data: None,
+                error: Some(format!("Failed to duplicate document: {}", e)),
+            },
+        }
+    }
+
+    /// Download a web page and return its readable text
+    async fn fetch_url(&self, args: Value) -> ToolResult {
+        if !self.allow_web_fetch {
+            return ToolResult {
+                success: false,
+                data: None,
+                error: Some(
+                    "Web access is turned off. Ask the user to allow fetching URLs.".to_string(),
+                ),
+            };
+        }
+
+        let url = match args.get("url").and_then(|v| v.as_str()) {
+            Some(u) => u,
+            None => {
+                return ToolResult {
+                    success: false,
+                    data: None,
+                    error: Some("Missing required parameter: url".to_string()),
+                }
+            }
+        };
+
+        let max_chars = args
+            .get("maxChars")
+            .and_then(|v| v.as_u64())
+            .map(|n| n as usize)
+            .unwrap_or(web_fetch::DEFAULT_MAX_CHARS);
+
+        debug!("Fetching URL: {}", url);
+        let result = match web_fetch::client() {
+            Ok(client) => web_fetch::fetch_page(&client, url, max_chars).await,
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(page) => ToolResult {
+                success: true,
+                data: Some(json!(page)),
+                error: None,
+            },
+            Err(e) => ToolResult {
+                success: false,
+                data: None,
+                error: Some(e),
+            },
+        }
+    }
+
+    /// Save a note about the workspace or the user's preferences for later
+    /// conversations
+    fn remember(&self, args: Value) -> ToolResult {
+        let (key, value) = match (
+            args.get("key").and_then(|v| v.as_str()),
+            args.get("value").and_then(|v| v.as_str()),
+        ) {
+            (Some(k), Some(v)) => (k, v),
+            (None, _) => {
+                return ToolResult {
+                    success: false,
+                    data: None,
+                    error: Some("Missing required parameter: key".to_string()),
+                }
+            }
+            (_, None) => {
+                return ToolResult {
+                    success: false,
+                    data: None,
+                    error: Some("Missing required parameter: value".to_string()),
+                }
+            }
+        };
+
+        debug!("Remembering: {}", key);
+        match AgentMemoryStore::new(&self.workspace_root).remember(key, value) {
+            Ok(memory) => ToolResult {
+                success: true,
+                data: Some(json!(memory)),
+                error: None,
+            },
+            Err(e) => ToolResult {
+                success: false,
+                data: None,
+                error: Some(e),
+            },
+        }
+    }
+
+    /// Look up saved notes: one key, notes matching a query, or all of them
+    fn recall(&self, args: Value) -> ToolResult {
+        let store = AgentMemoryStore::new(&self.workspace_root);
+        let result = if let Some(key) = args.get("key").and_then(|v| v.as_str()) {
+            store
+                .get(key)
+                .map(|memory| memory.into_iter().collect::<Vec<_>>())
+        } else if let Some(query) = args.get("query").and_then(|v| v.as_str()) {
+            store.search(query)
+        } else {
+            store.list()
+        };
+
+        match result {
+            Ok(memories) => ToolResult {
+                success: true,
+                data: Some(json!({
+                    "memories": memories,
+                    "count": memories.len(),
+                })),
+                error: None,
+            },
+            Err(e) => ToolResult {
+                success: false,
+                data: None,
+                error: Some(e),
+            },
+        }
+    }
+
+    /// Search documents for content
+    async fn search_documents(&self, args: Value) -> ToolResult {
+        let query = match args.get("query").and_then(|v| v.as_str()) {
+            Some(q) => q,
+            None => {
+                return ToolResult {
+                    success: false,
+                    data: None,
+                    error: Some("Missing required parameter: query".to_string()),
+                }
+            }
+        };
+
+        let _file_pattern = args.get("filePattern").and_then(|v| v.as_str());
+        let query_lower = query.to_lowercase();
+
+        debug!("Searching for: {}", query);
+
+        let mut matches: Vec<SearchMatch> = Vec::new();
+
+        // Recursively search files
+        if let Err(e) = self
+            .search_directory(&self.workspace_root, &query_lower, &mut matches)
+            .await
+        {
+            warn!("Search error: {}", e);
+        }
+
+        ToolResult {
+            success: true,
+            data: Some(json!({ "matches": matches })),
+            error: None,
+        }
+    }
+
+    /// Recursively search a directory for matching content
+    async fn search_directory(
+        &self,
+        dir: &PathBuf,
+        query: &str,
+        matches: &mut Vec<SearchMatch>,
+    ) -> Result<(), std::io::Error> {
+        let mut entries = fs::read_dir(dir).await?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let file_name = entry.file_name().to_string_lossy().to_string();
+
+            // Skip hidden files
+            if file_name.starts_with('.') {
+                continue;
+            }
+
+            if path.is_dir() {
+                // Recurse into subdirectories
+                Box::pin(self.search_directory(&path, query, matches)).await?;
+            } else if file_name.ends_with(".midlight") {
+                // Search in file content
+                if let Ok(content) = fs::read_to_string(&path).await {
+                    if let Ok(mut doc) = serde_json::from_str::<Value>(&content) {
+                        resolve_content(&path, &mut doc);
+                        let text = self
+                            .extract_text_from_tiptap(doc.get("content").unwrap_or(&Value::Null));
+
+                        if text.to_lowercase().contains(query) {
+                            let relative_path = path
+                                .strip_prefix(&self.workspace_root)
+                                .unwrap_or(path.as_path())
                                 .to_string_lossy()
                                 .to_string();
 
@@ -659,500 +1487,273 @@ impl AgentExecutor {
         }
     }
 
-    /// Extract plain text from Tiptap JSON
-    /// Convert Tiptap JSON to markdown (preserves formatting for AI to see and edit)
-    fn tiptap_to_markdown(&self, node: &Value) -> String {
-        let mut text = String::new();
-
-        if let Some(node_type) = node.get("type").and_then(|t| t.as_str()) {
-            match node_type {
-                "text" => {
-                    if let Some(t) = node.get("text").and_then(|t| t.as_str()) {
-                        // Check for marks (bold, italic, code)
-                        let marks = node.get("marks").and_then(|m| m.as_array());
-                        let mut formatted = t.to_string();
-
-                        if let Some(marks) = marks {
-                            let has_bold = marks
-                                .iter()
-                                .any(|m| m.get("type").and_then(|t| t.as_str()) == Some("bold"));
-                            let has_italic = marks
-                                .iter()
-                                .any(|m| m.get("type").and_then(|t| t.as_str()) == Some("italic"));
-                            let has_code = marks
-                                .iter()
-                                .any(|m| m.get("type").and_then(|t| t.as_str()) == Some("code"));
-
-                            if has_code {
-                                formatted = format!("`{}`", formatted);
-                            } else if has_bold && has_italic {
-                                formatted = format!("***{}***", formatted);
-                            } else if has_bold {
-                                formatted = format!("**{}**", formatted);
-                            } else if has_italic {
-                                formatted = format!("*{}*", formatted);
-                            }
-                        }
-
-                        text.push_str(&formatted);
-                    }
-                }
-                "heading" => {
-                    let level = node
-                        .get("attrs")
-                        .and_then(|a| a.get("level"))
-                        .and_then(|l| l.as_u64())
-                        .unwrap_or(1) as usize;
-                    let prefix = "#".repeat(level);
-
-                    text.push_str(&prefix);
-                    text.push(' ');
-
-                    if let Some(content) = node.get("content").and_then(|c| c.as_array()) {
-                        for child in content {
-                            text.push_str(&self.tiptap_to_markdown(child));
-                        }
-                    }
-                    text.push('\n');
-                }
-                "paragraph" => {
-                    if let Some(content) = node.get("content").and_then(|c| c.as_array()) {
-                        for child in content {
-                            text.push_str(&self.tiptap_to_markdown(child));
-                        }
-                    }
-                    text.push('\n');
-                }
-                "bulletList" => {
-                    if let Some(content) = node.get("content").and_then(|c| c.as_array()) {
-                        for child in content {
-                            text.push_str("- ");
-                            // Extract text from listItem -> paragraph -> text
-                            if let Some(item_content) =
-                                child.get("content").and_then(|c| c.as_array())
-                            {
-                                for para in item_content {
-                                    if let Some(para_content) =
-                                        para.get("content").and_then(|c| c.as_array())
-                                    {
-                                        for text_node in para_content {
-                                            text.push_str(&self.tiptap_to_markdown(text_node));
-                                        }
-                                    }
-                                }
-                            }
-                            text.push('\n');
-                        }
-                    }
-                }
-                "orderedList" => {
-                    if let Some(content) = node.get("content").and_then(|c| c.as_array()) {
-                        for (idx, child) in content.iter().enumerate() {
-                            text.push_str(&format!("{}. ", idx + 1));
-                            // Extract text from listItem -> paragraph -> text
-                            if let Some(item_content) =
-                                child.get("content").and_then(|c| c.as_array())
-                            {
-                                for para in item_content {
-                                    if let Some(para_content) =
-                                        para.get("content").and_then(|c| c.as_array())
-                                    {
-                                        for text_node in para_content {
-                                            text.push_str(&self.tiptap_to_markdown(text_node));
-                                        }
-                                    }
-                                }
-                            }
-                            text.push('\n');
-                        }
-                    }
-                }
-                "blockquote" => {
-                    if let Some(content) = node.get("content").and_then(|c| c.as_array()) {
-                        for child in content {
-                            text.push_str("> ");
-                            if let Some(para_content) =
-                                child.get("content").and_then(|c| c.as_array())
-                            {
-                                for text_node in para_content {
-                                    text.push_str(&self.tiptap_to_markdown(text_node));
-                                }
-                            }
-                            text.push('\n');
-                        }
-                    }
-                }
-                "horizontalRule" => {
-                    text.push_str("---\n");
-                }
-                "doc" => {
-                    if let Some(content) = node.get("content").and_then(|c| c.as_array()) {
-                        for child in content {
-                            text.push_str(&self.tiptap_to_markdown(child));
-                        }
-                    }
-                }
-                _ => {
-                    // Handle unknown node types by extracting any content
-                    if let Some(content) = node.get("content").and_then(|c| c.as_array()) {
-                        for child in content {
-                            text.push_str(&self.tiptap_to_markdown(child));
-                        }
-                    }
+    /// Search and replace across documents (stages a change set for review -
+    /// does NOT write to disk). Matches are found within individual text runs,
+    /// so text split across formatting marks isn't matched.
+    async fn search_replace(&self, args: Value) -> ToolResult {
+        let pattern = match args.get("pattern").and_then(|v| v.as_str()) {
+            Some(p) if !p.is_empty() => p,
+            _ => {
+                return ToolResult {
+                    success: false,
+                    data: None,
+                    error: Some("Missing required parameter: pattern".to_string()),
                 }
             }
-        }
-
-        text
-    }
-
-    /// Extract plain text from Tiptap (for search/diff - no markdown)
-    fn extract_text_from_tiptap(&self, node: &Value) -> String {
-        let mut text = String::new();
+        };
 
-        if let Some(node_type) = node.get("type").and_then(|t| t.as_str()) {
-            match node_type {
-                "text" => {
-                    if let Some(t) = node.get("text").and_then(|t| t.as_str()) {
-                        text.push_str(t);
-                    }
-                }
-                "paragraph" | "heading" => {
-                    if let Some(content) = node.get("content").and_then(|c| c.as_array()) {
-                        for child in content {
-                            text.push_str(&self.extract_text_from_tiptap(child));
-                        }
-                    }
-                    text.push('\n');
-                }
-                "bulletList" | "orderedList" => {
-                    if let Some(content) = node.get("content").and_then(|c| c.as_array()) {
-                        for child in content {
-                            text.push_str("- ");
-                            text.push_str(&self.extract_text_from_tiptap(child));
-                        }
-                    }
-                }
-                "listItem" => {
-                    if let Some(content) = node.get("content").and_then(|c| c.as_array()) {
-                        for child in content {
-                            text.push_str(&self.extract_text_from_tiptap(child));
-                        }
-                    }
-                }
-                "doc" => {
-                    if let Some(content) = node.get("content").and_then(|c| c.as_array()) {
-                        for child in content {
-                            text.push_str(&self.extract_text_from_tiptap(child));
-                        }
-                    }
-                }
-                _ => {
-                    if let Some(content) = node.get("content").and_then(|c| c.as_array()) {
-                        for child in content {
-                            text.push_str(&self.extract_text_from_tiptap(child));
-                        }
-                    }
+        let replacement = match args.get("replacement").and_then(|v| v.as_str()) {
+            Some(r) => r,
+            None => {
+                return ToolResult {
+                    success: false,
+                    data: None,
+                    error: Some("Missing required parameter: replacement".to_string()),
                 }
             }
-        }
+        };
 
-        text
-    }
+        let is_regex = args
+            .get("isRegex")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let case_sensitive = args
+            .get("caseSensitive")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+        let description = args.get("description").and_then(|v| v.as_str());
 
-    /// Convert markdown to Tiptap JSON (simplified)
-    fn markdown_to_tiptap(&self, markdown: &str) -> Value {
-        let mut content: Vec<Value> = Vec::new();
-        let lines: Vec<&str> = markdown.lines().collect();
-        let mut i = 0;
-
-        while i < lines.len() {
-            let line = lines[i];
-
-            // Headings
-            if line.starts_with("# ") {
-                content.push(json!({
-                    "type": "heading",
-                    "attrs": { "level": 1 },
-                    "content": self.parse_inline_formatting(&line[2..])
-                }));
-            } else if line.starts_with("## ") {
-                content.push(json!({
-                    "type": "heading",
-                    "attrs": { "level": 2 },
-                    "content": self.parse_inline_formatting(&line[3..])
-                }));
-            } else if line.starts_with("### ") {
-                content.push(json!({
-                    "type": "heading",
-                    "attrs": { "level": 3 },
-                    "content": self.parse_inline_formatting(&line[4..])
-                }));
-            } else if line.starts_with("#### ") {
-                content.push(json!({
-                    "type": "heading",
-                    "attrs": { "level": 4 },
-                    "content": self.parse_inline_formatting(&line[5..])
-                }));
-            } else if line.starts_with("##### ") {
-                content.push(json!({
-                    "type": "heading",
-                    "attrs": { "level": 5 },
-                    "content": self.parse_inline_formatting(&line[6..])
-                }));
-            } else if line.starts_with("###### ") {
-                content.push(json!({
-                    "type": "heading",
-                    "attrs": { "level": 6 },
-                    "content": self.parse_inline_formatting(&line[7..])
-                }));
-            }
-            // Horizontal rule
-            else if line.trim() == "---" || line.trim() == "***" || line.trim() == "___" {
-                content.push(json!({
-                    "type": "horizontalRule"
-                }));
-            }
-            // Blockquote
-            else if line.starts_with("> ") {
-                content.push(json!({
-                    "type": "blockquote",
-                    "content": [{
-                        "type": "paragraph",
-                        "content": self.parse_inline_formatting(&line[2..])
-                    }]
-                }));
-            }
-            // Unordered list item
-            else if line.starts_with("- ") || line.starts_with("* ") {
-                let mut list_items: Vec<Value> = Vec::new();
-                while i < lines.len() && (lines[i].starts_with("- ") || lines[i].starts_with("* "))
-                {
-                    let item_text = &lines[i][2..];
-                    list_items.push(json!({
-                        "type": "listItem",
-                        "content": [{
-                            "type": "paragraph",
-                            "content": self.parse_inline_formatting(item_text)
-                        }]
-                    }));
-                    i += 1;
+        let source = if is_regex {
+            pattern.to_string()
+        } else {
+            regex::escape(pattern)
+        };
+        let regex = match RegexBuilder::new(&source)
+            .case_insensitive(!case_sensitive)
+            .build()
+        {
+            Ok(r) => r,
+            Err(e) => {
+                return ToolResult {
+                    success: false,
+                    data: None,
+                    error: Some(format!("Invalid pattern: {}", e)),
                 }
-                content.push(json!({
-                    "type": "bulletList",
-                    "content": list_items
-                }));
-                continue; // Skip the i += 1 at the end
             }
-            // Ordered list item
-            else if line
-                .chars()
-                .next()
-                .map(|c| c.is_ascii_digit())
-                .unwrap_or(false)
-                && line.contains(". ")
-            {
-                let mut list_items: Vec<Value> = Vec::new();
-                while i < lines.len() {
-                    let current = lines[i];
-                    if let Some(dot_pos) = current.find(". ") {
-                        if current[..dot_pos].chars().all(|c| c.is_ascii_digit()) {
-                            let item_text = &current[dot_pos + 2..];
-                            list_items.push(json!({
-                                "type": "listItem",
-                                "content": [{
-                                    "type": "paragraph",
-                                    "content": self.parse_inline_formatting(item_text)
-                                }]
-                            }));
-                            i += 1;
-                            continue;
-                        }
+        };
+
+        let glob = match args.get("pathGlob").and_then(|v| v.as_str()) {
+            Some(g) => match PathGlob::new(g) {
+                Ok(glob) => Some(glob),
+                Err(e) => {
+                    return ToolResult {
+                        success: false,
+                        data: None,
+                        error: Some(e),
                     }
-                    break;
                 }
-                content.push(json!({
-                    "type": "orderedList",
-                    "content": list_items
-                }));
-                continue; // Skip the i += 1 at the end
+            },
+            None => None,
+        };
+
+        debug!("Search and replace: {} (regex: {})", pattern, is_regex);
+
+        let mut documents = Vec::new();
+        if let Err(e) = self
+            .collect_documents(&self.workspace_root, &mut documents)
+            .await
+        {
+            warn!("Search error: {}", e);
+        }
+        documents.sort();
+
+        let change_set_id = Uuid::new_v4().to_string();
+        let created_at = chrono::Utc::now().to_rfc3339();
+        let mut changes: Vec<StagedReplacement> = Vec::new();
+        let mut locked = Vec::new();
+        let mut truncated = false;
+
+        for file_path in documents {
+            let relative_path = file_path
+                .strip_prefix(&self.workspace_root)
+                .unwrap_or(file_path.as_path())
+                .to_string_lossy()
+                .replace('\\', "/");
+            if glob.as_ref().is_some_and(|g| !g.is_match(&relative_path)) {
+                continue;
             }
-            // Empty line
-            else if line.is_empty() {
-                // Skip empty lines
+
+            let Ok(content) = fs::read_to_string(&file_path).await else {
+                continue;
+            };
+            let Ok(mut doc) = serde_json::from_str::<Value>(&content) else {
+                continue;
+            };
+            resolve_content(&file_path, &mut doc);
+
+            let original_tiptap = doc
+                .get("content")
+                .cloned()
+                .unwrap_or(json!({"type": "doc", "content": []}));
+            let mut staged_tiptap = original_tiptap.clone();
+            let count = replace_in_tiptap(&mut staged_tiptap, &regex, replacement, is_regex);
+            if count == 0 {
+                continue;
             }
-            // Regular paragraph
-            else {
-                let inline_content = self.parse_inline_formatting(line);
-                if !inline_content.is_empty() {
-                    content.push(json!({
-                        "type": "paragraph",
-                        "content": inline_content
-                    }));
-                }
+            if is_locked_document(&doc) {
+                locked.push(relative_path);
+                continue;
             }
 
-            i += 1;
+            if changes.len() == MAX_REPLACE_DOCUMENTS {
+                truncated = true;
+                break;
+            }
+
+            let (staged_document, base_hash) = if self.require_confirmation {
+                let mut staged_doc = doc.clone();
+                staged_doc["content"] = staged_tiptap.clone();
+                staged_doc["meta"]["modified"] =
+                    json!(chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string());
+                (Some(staged_doc), Some(content_hash(&content)))
+            } else {
+                (None, None)
+            };
+
+            let change_id = Uuid::new_v4().to_string();
+            changes.push(StagedReplacement {
+                change: PendingChange {
+                    attribution: Some(self.attribution("search_replace", &change_id)),
+                    change_id,
+                    path: relative_path,
+                    kind: ChangeKind::Edit,
+                    original_content: self.extract_text_from_tiptap(&original_tiptap),
+                    new_content: self.extract_text_from_tiptap(&staged_tiptap),
+                    description: description.map(String::from),
+                    created_at: created_at.clone(),
+                    staged_document,
+                    base_hash,
+                },
+                replacements: count,
+                original_tiptap_json: original_tiptap,
+                staged_tiptap_json: staged_tiptap,
+            });
         }
 
-        if content.is_empty() {
-            content.push(json!({
-                "type": "paragraph",
-                "content": []
-            }));
+        let total_replacements: usize = changes.iter().map(|c| c.replacements).sum();
+
+        if self.require_confirmation {
+            for staged in &changes {
+                if let Err(e) = self.stage_change(staged.change.clone()) {
+                    return ToolResult {
+                        success: false,
+                        data: None,
+                        error: Some(e),
+                    };
+                }
+            }
         }
 
-        json!({
-            "type": "doc",
-            "content": content
-        })
+        // Return the change set WITHOUT writing to disk
+        // Frontend will display diffs and write accepted documents
+        ToolResult {
+            success: true,
+            data: Some(json!({
+                "changeSetId": change_set_id,
+                "changes": changes,
+                "documentsChanged": changes.len(),
+                "totalReplacements": total_replacements,
+                "truncated": truncated,
+                "lockedDocuments": locked,
+                "requiresAcceptance": !changes.is_empty(),
+                "status": self.staged_status(),
+            })),
+            error: None,
+        }
     }
 
-    /// Parse inline markdown formatting (bold, italic, code, etc.)
-    fn parse_inline_formatting(&self, text: &str) -> Vec<Value> {
-        let mut result: Vec<Value> = Vec::new();
-        let chars: Vec<char> = text.chars().collect();
-        let mut i = 0;
-        let mut current_text = String::new();
-
-        while i < chars.len() {
-            // Check for inline code (backticks)
-            if chars[i] == '`' {
-                // Flush current text
-                if !current_text.is_empty() {
-                    result.push(json!({ "type": "text", "text": current_text }));
-                    current_text = String::new();
-                }
+    /// Recursively collect .midlight documents, skipping hidden entries
+    async fn collect_documents(
+        &self,
+        dir: &Path,
+        documents: &mut Vec<PathBuf>,
+    ) -> Result<(), std::io::Error> {
+        let mut entries = fs::read_dir(dir).await?;
 
-                // Find closing backtick
-                let start = i + 1;
-                i += 1;
-                while i < chars.len() && chars[i] != '`' {
-                    i += 1;
-                }
-                if i < chars.len() {
-                    let code_text: String = chars[start..i].iter().collect();
-                    result.push(json!({
-                        "type": "text",
-                        "text": code_text,
-                        "marks": [{ "type": "code" }]
-                    }));
-                    i += 1;
-                }
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let file_name = entry.file_name().to_string_lossy().to_string();
+
+            if file_name.starts_with('.') {
                 continue;
             }
 
-            // Check for bold+italic (*** or ___)
-            if i + 2 < chars.len()
-                && ((chars[i] == '*' && chars[i + 1] == '*' && chars[i + 2] == '*')
-                    || (chars[i] == '_' && chars[i + 1] == '_' && chars[i + 2] == '_'))
-            {
-                let marker = chars[i];
-                // Flush current text
-                if !current_text.is_empty() {
-                    result.push(json!({ "type": "text", "text": current_text }));
-                    current_text = String::new();
-                }
-
-                // Find closing markers
-                let start = i + 3;
-                i += 3;
-                while i + 2 < chars.len()
-                    && !(chars[i] == marker && chars[i + 1] == marker && chars[i + 2] == marker)
-                {
-                    i += 1;
-                }
-                if i + 2 < chars.len() {
-                    let bold_italic_text: String = chars[start..i].iter().collect();
-                    result.push(json!({
-                        "type": "text",
-                        "text": bold_italic_text,
-                        "marks": [{ "type": "bold" }, { "type": "italic" }]
-                    }));
-                    i += 3;
-                }
-                continue;
+            if path.is_dir() {
+                Box::pin(self.collect_documents(&path, documents)).await?;
+            } else if file_name.ends_with(".midlight") {
+                documents.push(path);
             }
+        }
 
-            // Check for bold (** or __)
-            if i + 1 < chars.len()
-                && ((chars[i] == '*' && chars[i + 1] == '*')
-                    || (chars[i] == '_' && chars[i + 1] == '_'))
-            {
-                let marker = chars[i];
-                // Flush current text
-                if !current_text.is_empty() {
-                    result.push(json!({ "type": "text", "text": current_text }));
-                    current_text = String::new();
-                }
+        Ok(())
+    }
 
-                // Find closing markers
-                let start = i + 2;
-                i += 2;
-                while i + 1 < chars.len() && !(chars[i] == marker && chars[i + 1] == marker) {
-                    i += 1;
-                }
-                if i + 1 < chars.len() {
-                    let bold_text: String = chars[start..i].iter().collect();
-                    result.push(json!({
-                        "type": "text",
-                        "text": bold_text,
-                        "marks": [{ "type": "bold" }]
-                    }));
-                    i += 2;
-                }
-                continue;
-            }
+    /// Convert Tiptap JSON to markdown (preserves formatting for AI to see and edit)
+    fn tiptap_to_markdown(&self, node: &Value) -> String {
+        markdown_convert::tiptap_to_markdown(node, MarkdownOptions::default())
+    }
 
-            // Check for italic (* or _) - but not at word boundaries for _
-            if (chars[i] == '*') || (chars[i] == '_' && (i == 0 || !chars[i - 1].is_alphanumeric()))
-            {
-                let marker = chars[i];
-                let next_char = if i + 1 < chars.len() {
-                    Some(chars[i + 1])
-                } else {
-                    None
-                };
+    /// Extract plain text from Tiptap (for search/diff - no markdown)
+    fn extract_text_from_tiptap(&self, node: &Value) -> String {
+        markdown_convert::extract_text_from_tiptap(node)
+    }
 
-                // Make sure it's not ** or __ (bold)
-                if next_char != Some(marker) {
-                    // Flush current text
-                    if !current_text.is_empty() {
-                        result.push(json!({ "type": "text", "text": current_text }));
-                        current_text = String::new();
-                    }
+    /// Convert markdown written by the AI to Tiptap JSON
+    fn markdown_to_tiptap(&self, markdown: &str) -> Value {
+        markdown_convert::markdown_to_tiptap(markdown, MarkdownOptions::default())
+    }
 
-                    // Find closing marker
-                    let start = i + 1;
-                    i += 1;
-                    while i < chars.len() && chars[i] != marker {
-                        i += 1;
-                    }
-                    if i < chars.len() {
-                        let italic_text: String = chars[start..i].iter().collect();
-                        result.push(json!({
-                            "type": "text",
-                            "text": italic_text,
-                            "marks": [{ "type": "italic" }]
-                        }));
-                        i += 1;
-                    }
-                    continue;
-                }
-            }
+    /// Parse inline markdown formatting (bold, italic, code, etc.)
+    #[cfg(test)]
+    fn parse_inline_formatting(&self, text: &str) -> Vec<Value> {
+        markdown_convert::parse_inline_formatting(text)
+    }
+}
 
-            // Regular character
-            current_text.push(chars[i]);
-            i += 1;
+/// Replace matches in every text node of a Tiptap tree, returning the number
+/// of replacements. Text nodes left empty are removed.
+fn replace_in_tiptap(node: &mut Value, regex: &Regex, replacement: &str, expand: bool) -> usize {
+    let mut count = 0;
+
+    if node.get("type").and_then(|t| t.as_str()) == Some("text") {
+        if let Some(text) = node.get("text").and_then(|t| t.as_str()) {
+            let matches = regex.find_iter(text).count();
+            if matches > 0 {
+                let replaced = if expand {
+                    regex.replace_all(text, replacement).to_string()
+                } else {
+                    regex.replace_all(text, NoExpand(replacement)).to_string()
+                };
+                node["text"] = json!(replaced);
+                count += matches;
+            }
         }
+    }
 
-        // Flush remaining text
-        if !current_text.is_empty() {
-            result.push(json!({ "type": "text", "text": current_text }));
+    if let Some(children) = node.get_mut("content").and_then(|c| c.as_array_mut()) {
+        for child in children.iter_mut() {
+            count += replace_in_tiptap(child, regex, replacement, expand);
         }
-
-        result
+        children.retain(|child| {
+            child.get("type").and_then(|t| t.as_str()) != Some("text")
+                || child
+                    .get("text")
+                    .and_then(|t| t.as_str())
+                    .is_some_and(|t| !t.is_empty())
+        });
     }
+
+    count
 }
 
 #[cfg(test)]
@@ -1166,7 +1767,8 @@ mod tests {
 
     fn create_test_executor() -> (TempDir, AgentExecutor) {
         let temp = TempDir::new().unwrap();
-        let executor = AgentExecutor::new(temp.path().to_path_buf());
+        let registry = CustomToolRegistry::new(&temp.path().join(".midlight/custom-tools.json"));
+        let executor = AgentExecutor::new(temp.path().to_path_buf()).with_custom_tools(registry);
         (temp, executor)
     }
 
@@ -1204,6 +1806,60 @@ mod tests {
         assert!(result.error.unwrap().contains("Unknown tool"));
     }
 
+    #[tokio::test]
+    async fn test_executions_are_audited() {
+        let (temp, executor) = create_test_executor();
+        let executor = executor.with_conversation(Some("conv-1".to_string()));
+
+        executor
+            .execute_tool("create_document", json!({"path": "notes/a"}))
+            .await;
+        executor
+            .execute_tool("read_document", json!({"path": "missing.midlight"}))
+            .await;
+
+        let history = ToolAuditLog::new(temp.path())
+            .query(&Default::default())
+            .unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].tool_name, "read_document");
+        assert_eq!(history[0].status, ToolExecutionStatus::Failed);
+        assert_eq!(history[1].status, ToolExecutionStatus::Completed);
+        assert_eq!(history[1].conversation_id.as_deref(), Some("conv-1"));
+        assert!(history[1].paths.contains(&"notes/a.midlight".to_string()));
+        assert!(history[1].duration_ms.is_some());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_custom_tool() {
+        let temp = TempDir::new().unwrap();
+        let registry_path = temp.path().join(".midlight/custom-tools.json");
+        CustomToolRegistry::new(&registry_path)
+            .save(
+                custom_tools::SaveCustomToolInput {
+                    id: None,
+                    name: "shout".to_string(),
+                    description: "Upper-case the input".to_string(),
+                    parameters: json!({"type": "object"}),
+                    runner: custom_tools::ToolRunner::Command {
+                        command: "tr a-z A-Z".to_string(),
+                    },
+                    timeout_secs: None,
+                    is_destructive: false,
+                    enabled: true,
+                },
+                BUILTIN_TOOLS,
+            )
+            .unwrap();
+        let executor = AgentExecutor::new(temp.path().to_path_buf())
+            .with_custom_tools(CustomToolRegistry::new(&registry_path));
+
+        let result = executor.execute_tool("shout", json!({"a": "b"})).await;
+        assert!(result.success);
+        assert_eq!(result.data.unwrap()["output"], json!({"A": "B"}));
+    }
+
     // ============================================
     // list_documents tests
     // ============================================
@@ -1390,6 +2046,168 @@ mod tests {
         assert_eq!(doc["version"], 1);
     }
 
+    #[tokio::test]
+    async fn test_mutating_tools_are_journaled() {
+        let (temp, executor) = create_test_executor();
+
+        let created = executor
+            .execute_tool("create_document", json!({"path": "a", "content": "Hi"}))
+            .await;
+        let moved = executor
+            .execute_tool(
+                "move_document",
+                json!({"oldPath": "a.midlight", "newPath": "b.midlight"}),
+            )
+            .await;
+        let listed = executor
+            .execute_tool("list_documents", json!({"path": "/"}))
+            .await;
+
+        let executions = ExecutionJournal::new(temp.path()).list().unwrap();
+        assert_eq!(executions.len(), 2);
+        assert_eq!(executions[0].tool_name, "move_document");
+        assert_eq!(moved.data.unwrap()["executionId"], executions[0].id);
+        assert_eq!(created.data.unwrap()["executionId"], executions[1].id);
+        assert!(listed.data.unwrap().get("executionId").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_url_requires_permission() {
+        let (_temp, executor) = create_test_executor();
+        let result = executor
+            .execute_tool("fetch_url", json!({"url": "https://example.com"}))
+            .await;
+        assert!(result.error.unwrap().contains("Web access is turned off"));
+
+        let (_temp, executor) = create_test_executor();
+        let executor = executor.with_web_fetch(true);
+        let result = executor
+            .execute_tool("fetch_url", json!({"url": "http://127.0.0.1:8080"}))
+            .await;
+        assert!(result.error.unwrap().contains("not allowed"));
+    }
+
+    #[tokio::test]
+    async fn test_create_folder() {
+        let (temp, executor) = create_test_executor();
+
+        let result = executor
+            .execute_tool("create_folder", json!({"path": "/projects/alpha"}))
+            .await;
+        assert!(result.success);
+        assert_eq!(result.data.unwrap()["path"], "projects/alpha");
+        assert!(temp.path().join("projects/alpha").is_dir());
+
+        let result = executor
+            .execute_tool("create_folder", json!({"path": "projects/alpha"}))
+            .await;
+        assert!(result.error.unwrap().contains("already exists"));
+    }
+
+    #[tokio::test]
+    async fn test_remember_and_recall() {
+        let (_temp, executor) = create_test_executor();
+
+        let result = executor
+            .execute_tool(
+                "remember",
+                json!({"key": "spelling", "value": "Always write in UK English"}),
+            )
+            .await;
+        assert!(result.success);
+
+        let result = executor
+            .execute_tool("recall", json!({"key": "Spelling"}))
+            .await;
+        let data = result.data.unwrap();
+        assert_eq!(data["count"], 1);
+        assert_eq!(data["memories"][0]["value"], "Always write in UK English");
+
+        let result = executor
+            .execute_tool("recall", json!({"query": "french"}))
+            .await;
+        assert_eq!(result.data.unwrap()["count"], 0);
+
+        let result = executor
+            .execute_tool("remember", json!({"key": "tone"}))
+            .await;
+        assert!(result.error.unwrap().contains("value"));
+    }
+
+    #[tokio::test]
+    async fn test_new_tools_reject_paths_outside_workspace() {
+        let (temp, executor) = create_test_executor();
+        let outside = temp.path().join("..").join("escaped");
+
+        for (tool, args) in [
+            ("create_folder", json!({"path": "../escaped"})),
+            ("create_folder", json!({"path": "notes/../../escaped"})),
+            (
+                "rename_document",
+                json!({"path": "../a.midlight", "newName": "b"}),
+            ),
+            ("duplicate_document", json!({"path": "../../etc/passwd"})),
+        ] {
+            let result = executor.execute_tool(tool, args).await;
+            assert!(!result.success, "{} should fail", tool);
+        }
+        assert!(!outside.exists());
+
+        let result = executor
+            .execute_tool("rename_document", json!({"path": "a", "newName": "x/y"}))
+            .await;
+        assert!(result.error.unwrap().contains("not a path"));
+    }
+
+    #[tokio::test]
+    async fn test_rename_document_keeps_extension() {
+        let (temp, executor) = create_test_executor();
+        std::fs::create_dir(temp.path().join("notes")).unwrap();
+        std::fs::write(
+            temp.path().join("notes/draft.midlight"),
+            create_midlight_doc("Draft"),
+        )
+        .unwrap();
+
+        let result = executor
+            .execute_tool(
+                "rename_document",
+                json!({"path": "/notes/draft.midlight", "newName": "final"}),
+            )
+            .await;
+        assert!(result.success);
+        let data = result.data.unwrap();
+        assert_eq!(data["newPath"], "notes/final.midlight");
+        assert!(data.get("executionId").is_some());
+        assert!(temp.path().join("notes/final.midlight").exists());
+        assert!(!temp.path().join("notes/draft.midlight").exists());
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_document() {
+        let (temp, executor) = create_test_executor();
+        std::fs::write(temp.path().join("doc.midlight"), create_midlight_doc("Hi")).unwrap();
+
+        let first = executor
+            .execute_tool("duplicate_document", json!({"path": "doc.midlight"}))
+            .await;
+        let second = executor
+            .execute_tool("duplicate_document", json!({"path": "doc.midlight"}))
+            .await;
+
+        assert_eq!(first.data.unwrap()["newPath"], "doc-Copy.midlight");
+        assert_eq!(second.data.unwrap()["newPath"], "doc-Copy 2.midlight");
+        assert_eq!(
+            std::fs::read_to_string(temp.path().join("doc-Copy 2.midlight")).unwrap(),
+            create_midlight_doc("Hi")
+        );
+
+        let result = executor
+            .execute_tool("duplicate_document", json!({"path": "missing.midlight"}))
+            .await;
+        assert!(!result.success);
+    }
+
     #[tokio::test]
     async fn test_create_document_already_exists() {
         let (temp, executor) = create_test_executor();
@@ -1512,6 +2330,41 @@ mod tests {
         assert!(!result.success);
     }
 
+    #[tokio::test]
+    async fn test_locked_document_is_not_edited() {
+        let (temp, executor) = create_test_executor();
+        let path = temp.path().join("final.midlight");
+        std::fs::write(&path, create_midlight_doc("Finished chapter")).unwrap();
+        crate::services::document_lock::set_locked(&path, true).unwrap();
+
+        let result = executor
+            .execute_tool(
+                "edit_document",
+                json!({ "path": "final.midlight", "content": "Rewritten" }),
+            )
+            .await;
+        assert_eq!(
+            result.error.as_deref(),
+            Some("Document is locked: final.midlight")
+        );
+
+        let result = executor
+            .execute_tool(
+                "search_replace",
+                json!({ "pattern": "Finished", "replacement": "Draft" }),
+            )
+            .await;
+        let data = result.data.unwrap();
+        assert_eq!(data["documentsChanged"], 0);
+        assert_eq!(data["lockedDocuments"], json!(["final.midlight"]));
+
+        let result = executor
+            .execute_tool("delete_document", json!({ "path": "final.midlight" }))
+            .await;
+        assert!(!result.success);
+        assert!(path.exists());
+    }
+
     #[tokio::test]
     async fn test_edit_document_missing_content() {
         let (temp, executor) = create_test_executor();
@@ -2429,6 +3282,234 @@ mod tests {
         assert!(result.success);
     }
 
+    // ============================================
+    // Search and replace tests
+    // ============================================
+
+    #[tokio::test]
+    async fn test_search_replace_stages_change_set() {
+        let (temp, executor) = create_test_executor();
+        let notes = temp.path().join("notes");
+        std::fs::create_dir(&notes).unwrap();
+
+        let original = create_midlight_doc("Acme Corp and acme corp");
+        std::fs::write(temp.path().join("a.midlight"), &original).unwrap();
+        std::fs::write(
+            notes.join("b.midlight"),
+            create_midlight_doc("Acme Corp again"),
+        )
+        .unwrap();
+        std::fs::write(temp.path().join("c.midlight"), create_midlight_doc("none")).unwrap();
+
+        let result = executor
+            .execute_tool(
+                "search_replace",
+                json!({ "pattern": "Acme Corp", "replacement": "Acme Inc" }),
+            )
+            .await;
+
+        assert!(result.success);
+        let data = result.data.unwrap();
+        assert_eq!(data["documentsChanged"], 2);
+        assert_eq!(data["totalReplacements"], 2);
+        assert_eq!(data["requiresAcceptance"], true);
+
+        let changes = data["changes"].as_array().unwrap();
+        assert_eq!(changes[0]["path"], "a.midlight");
+        assert_eq!(changes[0]["newContent"], "Acme Inc and acme corp\n");
+        assert_eq!(
+            changes[0]["stagedTiptapJson"]["content"][0]["content"][0]["text"],
+            "Acme Inc and acme corp"
+        );
+        assert_eq!(changes[1]["path"], "notes/b.midlight");
+
+        // Nothing is written to disk
+        assert_eq!(
+            std::fs::read_to_string(temp.path().join("a.midlight")).unwrap(),
+            original
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_replace_regex_case_insensitive_and_glob() {
+        let (temp, executor) = create_test_executor();
+        let notes = temp.path().join("notes");
+        std::fs::create_dir(&notes).unwrap();
+
+        std::fs::write(
+            temp.path().join("root.midlight"),
+            create_midlight_doc("Due 2024-01-15"),
+        )
+        .unwrap();
+        std::fs::write(
+            notes.join("todo.midlight"),
+            create_midlight_doc("DUE 2024-03-02"),
+        )
+        .unwrap();
+
+        let result = executor
+            .execute_tool(
+                "search_replace",
+                json!({
+                    "pattern": r"due (\d{4})-(\d{2})-(\d{2})",
+                    "replacement": "Due $3/$2/$1",
+                    "isRegex": true,
+                    "caseSensitive": false,
+                    "pathGlob": "notes/**"
+                }),
+            )
+            .await;
+
+        let data = result.data.unwrap();
+        let changes = data["changes"].as_array().unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0]["path"], "notes/todo.midlight");
+        assert_eq!(changes[0]["newContent"], "Due 02/03/2024\n");
+    }
+
+    #[tokio::test]
+    async fn test_search_replace_literal_replacement_and_empty_text() {
+        let (temp, executor) = create_test_executor();
+        std::fs::write(
+            temp.path().join("doc.midlight"),
+            create_midlight_doc("price: $5"),
+        )
+        .unwrap();
+        std::fs::write(temp.path().join("gone.midlight"), create_midlight_doc("x")).unwrap();
+
+        let result = executor
+            .execute_tool(
+                "search_replace",
+                json!({ "pattern": "$5", "replacement": "$1 off" }),
+            )
+            .await;
+        let data = result.data.unwrap();
+        assert_eq!(data["changes"][0]["newContent"], "price: $1 off\n");
+
+        let result = executor
+            .execute_tool(
+                "search_replace",
+                json!({ "pattern": "x", "replacement": "", "pathGlob": "gone.midlight" }),
+            )
+            .await;
+        let data = result.data.unwrap();
+        let paragraph = &data["changes"][0]["stagedTiptapJson"]["content"][0];
+        assert_eq!(paragraph["content"].as_array().unwrap().len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_search_replace_invalid_arguments() {
+        let (_temp, executor) = create_test_executor();
+
+        let result = executor
+            .execute_tool("search_replace", json!({ "replacement": "x" }))
+            .await;
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("pattern"));
+
+        let result = executor
+            .execute_tool("search_replace", json!({ "pattern": "x" }))
+            .await;
+        assert!(result.error.unwrap().contains("replacement"));
+
+        let result = executor
+            .execute_tool(
+                "search_replace",
+                json!({ "pattern": "(", "replacement": "", "isRegex": true }),
+            )
+            .await;
+        assert!(result.error.unwrap().contains("Invalid pattern"));
+    }
+
+    // ============================================
+    // Confirmation mode tests
+    // ============================================
+
+    #[tokio::test]
+    async fn test_edit_document_confirmation_mode_stages_change() {
+        let (temp, executor) = create_test_executor();
+        let executor = executor.with_confirmation(true);
+        let original = create_midlight_doc("Original content");
+        std::fs::write(temp.path().join("doc.midlight"), &original).unwrap();
+
+        let result = executor
+            .execute_tool(
+                "edit_document",
+                json!({ "path": "doc.midlight", "content": "Updated content" }),
+            )
+            .await;
+
+        let data = result.data.unwrap();
+        assert_eq!(data["status"], "requires_confirmation");
+
+        let store = PendingChangeStore::new(temp.path());
+        let pending = store.list().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].change.change_id, data["changeId"]);
+        assert!(!pending[0].diff.is_empty());
+        assert_eq!(
+            std::fs::read_to_string(temp.path().join("doc.midlight")).unwrap(),
+            original
+        );
+
+        store.approve(data["changeId"].as_str().unwrap()).unwrap();
+        let written = std::fs::read_to_string(temp.path().join("doc.midlight")).unwrap();
+        assert!(written.contains("Updated content"));
+    }
+
+    #[tokio::test]
+    async fn test_delete_document_confirmation_mode_stages_change() {
+        let (temp, executor) = create_test_executor();
+        let executor = executor.with_confirmation(true);
+        let file_path = temp.path().join("doc.midlight");
+        std::fs::write(&file_path, create_midlight_doc("Keep me")).unwrap();
+
+        let result = executor
+            .execute_tool("delete_document", json!({ "path": "doc.midlight" }))
+            .await;
+
+        assert!(result.success);
+        let data = result.data.unwrap();
+        assert_eq!(data["status"], "requires_confirmation");
+        assert!(file_path.exists());
+
+        let store = PendingChangeStore::new(temp.path());
+        let pending = store.list().unwrap();
+        assert_eq!(pending[0].change.kind, ChangeKind::Delete);
+        assert!(pending[0].change.original_content.contains("Keep me"));
+
+        store.reject(data["changeId"].as_str().unwrap()).unwrap();
+        assert!(file_path.exists());
+        assert!(store.list().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_replace_confirmation_mode_stages_each_document() {
+        let (temp, executor) = create_test_executor();
+        let executor = executor.with_confirmation(true);
+        std::fs::write(temp.path().join("a.midlight"), create_midlight_doc("old")).unwrap();
+        std::fs::write(temp.path().join("b.midlight"), create_midlight_doc("old")).unwrap();
+
+        let result = executor
+            .execute_tool(
+                "search_replace",
+                json!({ "pattern": "old", "replacement": "new" }),
+            )
+            .await;
+        assert_eq!(result.data.unwrap()["status"], "requires_confirmation");
+
+        let store = PendingChangeStore::new(temp.path());
+        let pending = store.list().unwrap();
+        assert_eq!(pending.len(), 2);
+
+        store.approve(&pending[1].change.change_id).unwrap();
+        let written = std::fs::read_to_string(temp.path().join("b.midlight")).unwrap();
+        assert!(written.contains("\"new\""));
+        assert!(std::fs::read_to_string(temp.path().join("a.midlight"))
+            .unwrap()
+            .contains("\"old\""));
+    }
+
     // ============================================
     // Tiptap to Markdown edge cases
     // ============================================
@@ -2923,6 +4004,9 @@ mod tests {
             }),
             started_at: "2024-01-01T00:00:00Z".to_string(),
             completed_at: Some("2024-01-01T00:00:01Z".to_string()),
+            duration_ms: Some(1000),
+            conversation_id: None,
+            paths: vec!["test.midlight".to_string()],
         };
 
         let json = serde_json::to_string(&exec).unwrap();
@@ -2935,10 +4019,14 @@ mod tests {
         let change = PendingChange {
             change_id: "abc123".to_string(),
             path: "doc.midlight".to_string(),
+            kind: ChangeKind::Edit,
             original_content: "old".to_string(),
             new_content: "new".to_string(),
             description: Some("Made changes".to_string()),
             created_at: "2024-01-01T00:00:00Z".to_string(),
+            staged_document: None,
+            base_hash: None,
+            attribution: None,
         };
 
         let json = serde_json::to_string(&change).unwrap();
@@ -2960,6 +4048,9 @@ mod tests {
             result: None,
             started_at: "now".to_string(),
             completed_at: None,
+            duration_ms: None,
+            conversation_id: None,
+            paths: Vec::new(),
         };
 
         let debug = format!("{:?}", exec);
@@ -3009,10 +4100,14 @@ mod tests {
         let change = PendingChange {
             change_id: "123".to_string(),
             path: "test".to_string(),
+            kind: ChangeKind::Delete,
             original_content: "old".to_string(),
             new_content: "new".to_string(),
             description: None,
             created_at: "now".to_string(),
+            staged_document: None,
+            base_hash: None,
+            attribution: None,
         };
 
         let debug = format!("{:?}", change);
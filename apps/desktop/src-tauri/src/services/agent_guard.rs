@@ -0,0 +1,242 @@
+// Agent Guard - Limits on how many tools one agent turn may call
+//
+// The model drives the tool-calling loop, so a confused model can keep
+// calling tools forever. Every call is checked against its turn's budget
+// before it runs: a turn may make at most `max_calls_per_turn` calls, and the
+// same tool with the same arguments may only be called `max_identical_calls`
+// times. Calls are grouped by the turn id the frontend sends; callers that
+// don't send one are grouped per workspace and conversation, and the count
+// starts over after the turn has been idle for `idle_reset`.
+//
+// Violations come back as "CODE: message" strings like LLM errors, with the
+// codes TOOL_BUDGET_EXCEEDED and TOOL_LOOP_DETECTED.
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ToolCallLimits {
+    pub max_calls_per_turn: u32,
+    pub max_identical_calls: u32,
+    pub idle_reset: Duration,
+}
+
+impl Default for ToolCallLimits {
+    fn default() -> Self {
+        Self {
+            max_calls_per_turn: 25,
+            max_identical_calls: 3,
+            idle_reset: Duration::from_secs(120),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GuardViolation {
+    BudgetExceeded { limit: u32 },
+    LoopDetected { tool_name: String, repeats: u32 },
+}
+
+impl GuardViolation {
+    pub fn code(&self) -> &'static str {
+        match self {
+            GuardViolation::BudgetExceeded { .. } => "TOOL_BUDGET_EXCEEDED",
+            GuardViolation::LoopDetected { .. } => "TOOL_LOOP_DETECTED",
+        }
+    }
+}
+
+impl std::fmt::Display for GuardViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GuardViolation::BudgetExceeded { limit } => write!(
+                f,
+                "{}: The agent reached the limit of {} tool calls for this turn",
+                self.code(),
+                limit
+            ),
+            GuardViolation::LoopDetected { tool_name, repeats } => write!(
+                f,
+                "{}: The agent called {} with the same arguments {} times",
+                self.code(),
+                tool_name,
+                repeats
+            ),
+        }
+    }
+}
+
+struct TurnState {
+    calls: u32,
+    identical: HashMap<String, u32>,
+    last_call: Instant,
+}
+
+// ============================================================================
+// Guard
+// ============================================================================
+
+pub struct ToolCallGuard {
+    limits: ToolCallLimits,
+    turns: Mutex<HashMap<String, TurnState>>,
+}
+
+impl ToolCallGuard {
+    pub fn new(limits: ToolCallLimits) -> Self {
+        Self {
+            limits,
+            turns: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Count a call against its turn, or refuse it if the turn is over budget
+    /// or stuck repeating itself. Refused calls are not counted.
+    pub fn check(
+        &self,
+        turn_key: &str,
+        tool_name: &str,
+        arguments: &Value,
+    ) -> Result<(), GuardViolation> {
+        self.check_at(turn_key, tool_name, arguments, Instant::now())
+    }
+
+    fn check_at(
+        &self,
+        turn_key: &str,
+        tool_name: &str,
+        arguments: &Value,
+        now: Instant,
+    ) -> Result<(), GuardViolation> {
+        let mut turns = self.turns.lock().unwrap();
+        let idle_reset = self.limits.idle_reset;
+        turns.retain(|_, turn| now.duration_since(turn.last_call) < idle_reset);
+
+        let turn = turns
+            .entry(turn_key.to_string())
+            .or_insert_with(|| TurnState {
+                calls: 0,
+                identical: HashMap::new(),
+                last_call: now,
+            });
+        turn.last_call = now;
+
+        if turn.calls >= self.limits.max_calls_per_turn {
+            return Err(GuardViolation::BudgetExceeded {
+                limit: self.limits.max_calls_per_turn,
+            });
+        }
+
+        // serde_json objects serialize with sorted keys, so equal arguments
+        // always give the same signature
+        let signature = format!("{}:{}", tool_name, arguments);
+        let repeats = turn.identical.get(&signature).copied().unwrap_or(0);
+        if repeats >= self.limits.max_identical_calls {
+            return Err(GuardViolation::LoopDetected {
+                tool_name: tool_name.to_string(),
+                repeats: repeats + 1,
+            });
+        }
+
+        turn.calls += 1;
+        turn.identical.insert(signature, repeats + 1);
+        Ok(())
+    }
+
+    /// Forget a turn's counts once the agent has finished it
+    pub fn end_turn(&self, turn_key: &str) {
+        self.turns.lock().unwrap().remove(turn_key);
+    }
+}
+
+lazy_static::lazy_static! {
+    pub static ref TOOL_CALL_GUARD: ToolCallGuard = ToolCallGuard::new(ToolCallLimits::default());
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn guard() -> ToolCallGuard {
+        ToolCallGuard::new(ToolCallLimits {
+            max_calls_per_turn: 5,
+            max_identical_calls: 2,
+            idle_reset: Duration::from_secs(60),
+        })
+    }
+
+    #[test]
+    fn test_budget_per_turn() {
+        let guard = guard();
+        for i in 0..5 {
+            guard
+                .check("turn-1", "read_document", &json!({"path": i}))
+                .unwrap();
+        }
+        let err = guard
+            .check("turn-1", "read_document", &json!({"path": 9}))
+            .unwrap_err();
+        assert_eq!(err, GuardViolation::BudgetExceeded { limit: 5 });
+        assert!(err.to_string().starts_with("TOOL_BUDGET_EXCEEDED: "));
+
+        // Other turns have their own budget, and ending a turn resets it
+        guard.check("turn-2", "list_documents", &json!({})).unwrap();
+        guard.end_turn("turn-1");
+        guard.check("turn-1", "list_documents", &json!({})).unwrap();
+    }
+
+    #[test]
+    fn test_repeated_identical_calls() {
+        let guard = guard();
+        let args = json!({"path": "a.midlight", "limit": 1});
+        let reordered = json!({"limit": 1, "path": "a.midlight"});
+
+        guard.check("turn", "read_document", &args).unwrap();
+        guard.check("turn", "read_document", &reordered).unwrap();
+        let err = guard.check("turn", "read_document", &args).unwrap_err();
+        assert_eq!(
+            err,
+            GuardViolation::LoopDetected {
+                tool_name: "read_document".to_string(),
+                repeats: 3
+            }
+        );
+
+        // Different arguments are still allowed
+        guard
+            .check("turn", "read_document", &json!({"path": "b.midlight"}))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_idle_turns_reset() {
+        let guard = guard();
+        let start = Instant::now();
+        let args = json!({});
+
+        guard
+            .check_at("turn", "list_documents", &args, start)
+            .unwrap();
+        guard
+            .check_at("turn", "list_documents", &args, start)
+            .unwrap();
+        assert!(guard
+            .check_at("turn", "list_documents", &args, start)
+            .is_err());
+
+        let later = start + Duration::from_secs(61);
+        guard
+            .check_at("turn", "list_documents", &args, later)
+            .unwrap();
+    }
+}
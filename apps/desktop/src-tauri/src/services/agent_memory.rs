@@ -0,0 +1,271 @@
+// Agent Memory - Notes the agent keeps about a workspace between conversations
+//
+// The remember and recall tools let the agent save things like the user's
+// preferences ("always write in UK English") under a short key and look them
+// up again in a later conversation, instead of every prompt carrying them.
+// Keys are case-insensitive; saving an existing key replaces its value.
+//
+// Memories are stored at: .midlight/agent-memory.json
+// Format:
+// {
+//   "version": 1,
+//   "memories": {
+//     "spelling": { "value": "Use UK English", "createdAt": "...", "updatedAt": "..." }
+//   }
+// }
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MEMORY_VERSION: u32 = 1;
+
+/// Longest key the agent may save under
+pub const MAX_KEY_CHARS: usize = 100;
+/// Longest value the agent may save
+pub const MAX_VALUE_CHARS: usize = 2_000;
+/// Most memories a workspace may hold
+pub const MAX_MEMORIES: usize = 200;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryEntry {
+    pub value: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// A memory together with its key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Memory {
+    pub key: String,
+    #[serde(flatten)]
+    pub entry: MemoryEntry,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct MemoryFile {
+    version: u32,
+    #[serde(default)]
+    memories: BTreeMap<String, MemoryEntry>,
+}
+
+// ============================================================================
+// Memory Store
+// ============================================================================
+
+/// Reads and writes the agent's memories for a single workspace
+pub struct AgentMemoryStore {
+    memory_path: PathBuf,
+}
+
+impl AgentMemoryStore {
+    pub fn new(workspace_root: &Path) -> Self {
+        Self {
+            memory_path: workspace_root.join(".midlight").join("agent-memory.json"),
+        }
+    }
+
+    /// List all memories, ordered by key
+    pub fn list(&self) -> Result<Vec<Memory>, String> {
+        Ok(self
+            .read()?
+            .memories
+            .into_iter()
+            .map(|(key, entry)| Memory { key, entry })
+            .collect())
+    }
+
+    /// Look up a single memory by key
+    pub fn get(&self, key: &str) -> Result<Option<Memory>, String> {
+        let key = Self::key(key)?;
+        Ok(self
+            .read()?
+            .memories
+            .remove(&key)
+            .map(|entry| Memory { key, entry }))
+    }
+
+    /// Memories whose key or value contains the query, case-insensitively
+    pub fn search(&self, query: &str) -> Result<Vec<Memory>, String> {
+        let query = query.trim().to_lowercase();
+        Ok(self
+            .list()?
+            .into_iter()
+            .filter(|m| m.key.contains(&query) || m.entry.value.to_lowercase().contains(&query))
+            .collect())
+    }
+
+    /// Save a memory, replacing any existing value under the same key
+    pub fn remember(&self, key: &str, value: &str) -> Result<Memory, String> {
+        let key = Self::key(key)?;
+        let value = value.trim();
+        if value.is_empty() {
+            return Err("Memory value cannot be empty".to_string());
+        }
+        if value.chars().count() > MAX_VALUE_CHARS {
+            return Err(format!(
+                "Memory value is too long (max {} characters)",
+                MAX_VALUE_CHARS
+            ));
+        }
+
+        let mut file = self.read()?;
+        if !file.memories.contains_key(&key) && file.memories.len() >= MAX_MEMORIES {
+            return Err(format!(
+                "Memory is full ({} entries). Forget something first.",
+                MAX_MEMORIES
+            ));
+        }
+
+        let now = Utc::now().to_rfc3339();
+        let created_at = file
+            .memories
+            .get(&key)
+            .map(|existing| existing.created_at.clone())
+            .unwrap_or_else(|| now.clone());
+        let entry = MemoryEntry {
+            value: value.to_string(),
+            created_at,
+            updated_at: now,
+        };
+
+        file.version = MEMORY_VERSION;
+        file.memories.insert(key.clone(), entry.clone());
+        self.write(&file)?;
+        Ok(Memory { key, entry })
+    }
+
+    /// Delete a memory. Returns false if there was none.
+    pub fn forget(&self, key: &str) -> Result<bool, String> {
+        let key = Self::key(key)?;
+        let mut file = self.read()?;
+        let removed = file.memories.remove(&key).is_some();
+        if removed {
+            self.write(&file)?;
+        }
+        Ok(removed)
+    }
+
+    fn key(key: &str) -> Result<String, String> {
+        let key = key.trim().to_lowercase();
+        if key.is_empty() {
+            return Err("Memory key cannot be empty".to_string());
+        }
+        if key.chars().count() > MAX_KEY_CHARS {
+            return Err(format!(
+                "Memory key is too long (max {} characters)",
+                MAX_KEY_CHARS
+            ));
+        }
+        Ok(key)
+    }
+
+    fn read(&self) -> Result<MemoryFile, String> {
+        if !self.memory_path.exists() {
+            return Ok(MemoryFile {
+                version: MEMORY_VERSION,
+                memories: BTreeMap::new(),
+            });
+        }
+
+        let content = fs::read_to_string(&self.memory_path)
+            .map_err(|e| format!("Failed to read agent memory: {}", e))?;
+        serde_json::from_str(&content).map_err(|e| format!("Failed to parse agent memory: {}", e))
+    }
+
+    fn write(&self, file: &MemoryFile) -> Result<(), String> {
+        if let Some(parent) = self.memory_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create .midlight directory: {}", e))?;
+        }
+
+        let json = serde_json::to_string_pretty(file)
+            .map_err(|e| format!("Failed to serialize agent memory: {}", e))?;
+
+        let temp_path = self.memory_path.with_extension("json.tmp");
+        fs::write(&temp_path, json).map_err(|e| format!("Failed to write agent memory: {}", e))?;
+        fs::rename(&temp_path, &self.memory_path)
+            .map_err(|e| format!("Failed to write agent memory: {}", e))
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_remember_and_recall() {
+        let temp = TempDir::new().unwrap();
+        let store = AgentMemoryStore::new(temp.path());
+
+        assert!(store.get("spelling").unwrap().is_none());
+
+        let saved = store.remember(" Spelling ", "Use UK English").unwrap();
+        assert_eq!(saved.key, "spelling");
+
+        let recalled = store.get("SPELLING").unwrap().unwrap();
+        assert_eq!(recalled.entry.value, "Use UK English");
+
+        // Replacing keeps the original creation time
+        let updated = store.remember("spelling", "Use US English").unwrap();
+        assert_eq!(updated.entry.created_at, recalled.entry.created_at);
+        assert_eq!(store.list().unwrap().len(), 1);
+
+        // Persisted across store instances
+        let reopened = AgentMemoryStore::new(temp.path());
+        assert_eq!(
+            reopened.get("spelling").unwrap().unwrap().entry.value,
+            "Use US English"
+        );
+    }
+
+    #[test]
+    fn test_search_and_forget() {
+        let temp = TempDir::new().unwrap();
+        let store = AgentMemoryStore::new(temp.path());
+        store.remember("spelling", "Use UK English").unwrap();
+        store.remember("tone", "Friendly but concise").unwrap();
+
+        let found = store.search("english").unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].key, "spelling");
+        assert_eq!(store.search("TONE").unwrap().len(), 1);
+
+        assert!(store.forget("tone").unwrap());
+        assert!(!store.forget("tone").unwrap());
+        assert_eq!(store.list().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_rejects_invalid_entries() {
+        let temp = TempDir::new().unwrap();
+        let store = AgentMemoryStore::new(temp.path());
+
+        assert!(store.remember("  ", "value").is_err());
+        assert!(store.remember("key", "   ").is_err());
+        assert!(store
+            .remember("key", &"x".repeat(MAX_VALUE_CHARS + 1))
+            .is_err());
+        assert!(store.remember(&"k".repeat(MAX_KEY_CHARS + 1), "v").is_err());
+
+        for i in 0..MAX_MEMORIES {
+            store.remember(&format!("key-{}", i), "value").unwrap();
+        }
+        assert!(store.remember("one-more", "value").is_err());
+        // Existing keys can still be updated when full
+        store.remember("key-0", "changed").unwrap();
+    }
+}
@@ -0,0 +1,17 @@
+// App Dirs - Where the app keeps per-user data
+//
+// Settings, queues, keys and logs that belong to the user rather than to a
+// workspace live in the platform's data directory, in a folder named after
+// the app's bundle identifier.
+
+use std::path::PathBuf;
+
+/// Bundle identifier, and the name of the app's data folder
+pub const APP_IDENTIFIER: &str = "com.midlight.app";
+
+/// The app's per-user data directory
+pub fn app_data_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(APP_IDENTIFIER)
+}
@@ -0,0 +1,192 @@
+// Atomic Write - Crash-safe file replacement
+//
+// A plain write truncates the file first, so a crash or power loss part way
+// through leaves a truncated document. Instead the new contents go to a
+// temporary file in the same directory, which is flushed to disk and then
+// renamed over the original. A rename within a directory replaces the file
+// in one step, so readers see either the old or the new contents. On Unix
+// the directory is synced too, so the rename itself survives a crash.
+//
+// Keys and tokens are written owner-only from the start: the temporary file
+// is created with those permissions, so the secret is never readable by
+// others, even briefly.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Suffix of in-progress temporary files. It contains ".midlight", which the
+/// file watcher ignores, so saves don't show up as external changes.
+const TEMP_SUFFIX: &str = ".midlight-tmp";
+
+/// Replace the contents of a file atomically and durably, creating it and
+/// its parent directories if needed. An existing file's permissions are kept.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    replace(path, contents.as_ref(), false)
+}
+
+/// Like [`write_atomic`], but the file ends up readable and writable by its
+/// owner only
+pub fn write_private(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    replace(path, contents.as_ref(), true)
+}
+
+/// Create an owner-only file that doesn't exist yet, with all of its
+/// contents at once. Fails with `AlreadyExists`, leaving the file alone, if
+/// something else created it first.
+pub fn create_private(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let parent = parent_dir(path);
+    fs::create_dir_all(parent)?;
+
+    let temp_path = temp_path_for(path);
+    // Linking, unlike renaming, never replaces an existing file
+    let result = write_and_sync(&temp_path, path, contents.as_ref(), true)
+        .and_then(|_| fs::hard_link(&temp_path, path));
+    let _ = fs::remove_file(&temp_path);
+    result?;
+
+    sync_dir(parent)
+}
+
+fn replace(path: &Path, contents: &[u8], private: bool) -> io::Result<()> {
+    let parent = parent_dir(path);
+    fs::create_dir_all(parent)?;
+
+    let temp_path = temp_path_for(path);
+    let result = write_and_sync(&temp_path, path, contents, private)
+        .and_then(|_| fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result?;
+
+    sync_dir(parent)
+}
+
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+fn write_and_sync(
+    temp_path: &Path,
+    target: &Path,
+    contents: &[u8],
+    private: bool,
+) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(temp_path)?;
+    file.write_all(contents)?;
+    if !private {
+        if let Ok(metadata) = fs::metadata(target) {
+            file.set_permissions(metadata.permissions())?;
+        }
+    }
+    file.sync_all()
+}
+
+/// Hidden temporary file next to the target, unique to this write
+fn temp_path_for(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    path.with_file_name(format!(".{}.{}{}", name, &id[..8], TEMP_SUFFIX))
+}
+
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    fs::File::open(dir)?.sync_all()
+}
+
+/// Windows can't open directories as files; NTFS journals the rename
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_write_atomic_replaces_contents() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("notes").join("a.midlight");
+
+        write_atomic(&path, "first").unwrap();
+        write_atomic(&path, "second").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+
+        // No temporary files are left behind
+        let entries: Vec<_> = fs::read_dir(path.parent().unwrap()).unwrap().collect();
+        assert_eq!(entries.len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("private.md");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+
+        write_atomic(&path, "new").unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_private_files_are_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("secret.key");
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+        create_private(&path, "first").unwrap();
+        assert_eq!(mode(&path), 0o600);
+
+        // Creating never replaces an existing file
+        let err = create_private(&path, "second").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first");
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        write_private(&path, "third").unwrap();
+        assert_eq!(mode(&path), 0o600);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "third");
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_failed_write_leaves_original() {
+        let temp = TempDir::new().unwrap();
+        // The target is a directory, so the rename fails
+        let path = temp.path().join("folder");
+        fs::create_dir(&path).unwrap();
+        fs::write(path.join("inside.md"), "kept").unwrap();
+
+        assert!(write_atomic(&path, "contents").is_err());
+        assert_eq!(fs::read_to_string(path.join("inside.md")).unwrap(), "kept");
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,240 @@
+// Audio manager - Content-addressable storage for audio attachments
+//
+// Voice memos and other recordings attached to documents are stored under
+// .midlight/audio, named by the hash of their contents like images, and
+// referenced from documents as "midlight://audio-{hash}".
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::error::{MidlightError, Result};
+use crate::traits::{FileSystem, TokioFileSystem};
+
+const REF_PREFIX: &str = "midlight://audio-";
+
+/// Audio formats that can be attached, by extension
+const SUPPORTED_EXTENSIONS: &[&str] = &["mp3", "m4a", "aac", "wav", "ogg", "oga", "webm", "flac"];
+
+/// Manages audio storage for a workspace
+pub struct AudioManager<F: FileSystem = TokioFileSystem> {
+    audio_dir: PathBuf,
+    fs: Arc<F>,
+}
+
+impl AudioManager<TokioFileSystem> {
+    pub fn new(workspace_root: &Path) -> Self {
+        Self {
+            audio_dir: workspace_root.join(".midlight").join("audio"),
+            fs: Arc::new(TokioFileSystem::new()),
+        }
+    }
+}
+
+impl<F: FileSystem> AudioManager<F> {
+    /// Create a new AudioManager with custom dependencies (for testing)
+    #[allow(dead_code)]
+    pub fn with_fs(workspace_root: &Path, fs: Arc<F>) -> Self {
+        Self {
+            audio_dir: workspace_root.join(".midlight").join("audio"),
+            fs,
+        }
+    }
+
+    /// Store audio from a data URL, returns the audio reference ID
+    /// Format: "midlight://audio-{hash}"
+    pub async fn store_audio(&self, data_url: &str) -> Result<String> {
+        let (header, base64_data) = data_url
+            .split_once(',')
+            .ok_or_else(|| MidlightError::InvalidInput("Invalid data URL format".to_string()))?;
+
+        let mime_type = header
+            .strip_prefix("data:")
+            .and_then(|s| s.split(';').next())
+            .unwrap_or_default();
+        let extension = extension_for_mime(mime_type).ok_or_else(|| {
+            MidlightError::InvalidInput(format!("Unsupported audio type: {}", mime_type))
+        })?;
+
+        let audio_data = BASE64
+            .decode(base64_data)
+            .map_err(|e| MidlightError::InvalidInput(format!("Invalid base64: {}", e)))?;
+
+        self.store_audio_bytes(&audio_data, extension).await
+    }
+
+    /// Copy an audio file from disk into the workspace, returns the audio
+    /// reference ID. Recordings can be large, so this avoids sending them
+    /// through a data URL.
+    pub async fn import_audio_file(&self, source: &Path) -> Result<String> {
+        let extension = extension_of(source);
+        if !SUPPORTED_EXTENSIONS.contains(&extension.as_str()) {
+            return Err(MidlightError::InvalidInput(format!(
+                "Unsupported audio file: {}",
+                source.display()
+            )));
+        }
+
+        let audio_data = self.fs.read(source).await?;
+        self.store_audio_bytes(&audio_data, &extension).await
+    }
+
+    async fn store_audio_bytes(&self, audio_data: &[u8], extension: &str) -> Result<String> {
+        let mut hasher = Sha256::new();
+        hasher.update(audio_data);
+        let hash = format!("{:x}", hasher.finalize());
+        let short_hash = &hash[..16];
+
+        let filename = format!("{}.{}", short_hash, extension);
+        let file_path = self.audio_dir.join(&filename);
+
+        // Only write if doesn't exist (deduplication)
+        if !self.fs.exists(&file_path).await {
+            self.fs.create_dir_all(&self.audio_dir).await?;
+            self.fs.write_bytes(&file_path, audio_data).await?;
+            tracing::debug!(
+                "Stored new audio: {} ({} bytes)",
+                filename,
+                audio_data.len()
+            );
+        }
+
+        Ok(format!("{}{}", REF_PREFIX, short_hash))
+    }
+
+    /// Path of the audio file for a reference
+    pub async fn audio_path(&self, ref_id: &str) -> Result<PathBuf> {
+        let hash = ref_id.strip_prefix(REF_PREFIX).unwrap_or(ref_id);
+        self.find_audio_by_hash(hash).await
+    }
+
+    /// Get audio as a data URL
+    pub async fn get_audio_data_url(&self, ref_id: &str) -> Result<String> {
+        let path = self.audio_path(ref_id).await?;
+        let data = self.fs.read(&path).await?;
+        let mime_type = mime_for_extension(&extension_of(&path));
+        Ok(format!("data:{};base64,{}", mime_type, BASE64.encode(data)))
+    }
+
+    /// Delete an audio attachment
+    pub async fn delete(&self, ref_id: &str) -> Result<()> {
+        let path = self.audio_path(ref_id).await?;
+        self.fs.remove_file(&path).await?;
+        tracing::debug!("Deleted audio: {}", path.display());
+        Ok(())
+    }
+
+    /// List all audio attachments
+    pub async fn list_audio(&self) -> Result<Vec<String>> {
+        let mut audio = Vec::new();
+
+        if self.fs.exists(&self.audio_dir).await {
+            for path in self.fs.read_dir(&self.audio_dir).await? {
+                if self.fs.is_file(&path).await {
+                    if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                        audio.push(format!("{}{}", REF_PREFIX, stem));
+                    }
+                }
+            }
+        }
+
+        Ok(audio)
+    }
+
+    async fn find_audio_by_hash(&self, hash: &str) -> Result<PathBuf> {
+        let not_found = || MidlightError::NotFound(format!("Audio not found: {}", hash));
+        if hash.is_empty() || !self.fs.exists(&self.audio_dir).await {
+            return Err(not_found());
+        }
+
+        self.fs
+            .read_dir(&self.audio_dir)
+            .await?
+            .into_iter()
+            .find(|path| {
+                path.file_stem()
+                    .and_then(|s| s.to_str())
+                    .is_some_and(|stem| stem.starts_with(hash))
+            })
+            .ok_or_else(not_found)
+    }
+}
+
+/// Whether a file is an audio format that can be attached
+pub fn is_supported(path: &Path) -> bool {
+    SUPPORTED_EXTENSIONS.contains(&extension_of(path).as_str())
+}
+
+fn extension_of(path: &Path) -> String {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default()
+}
+
+fn extension_for_mime(mime_type: &str) -> Option<&'static str> {
+    match mime_type {
+        "audio/mpeg" | "audio/mp3" => Some("mp3"),
+        "audio/mp4" | "audio/x-m4a" | "audio/m4a" => Some("m4a"),
+        "audio/aac" => Some("aac"),
+        "audio/wav" | "audio/x-wav" | "audio/wave" => Some("wav"),
+        "audio/ogg" => Some("ogg"),
+        "audio/webm" => Some("webm"),
+        "audio/flac" | "audio/x-flac" => Some("flac"),
+        _ => None,
+    }
+}
+
+pub fn mime_for_extension(extension: &str) -> &'static str {
+    match extension {
+        "mp3" => "audio/mpeg",
+        "m4a" => "audio/mp4",
+        "aac" => "audio/aac",
+        "wav" => "audio/wav",
+        "ogg" | "oga" => "audio/ogg",
+        "webm" => "audio/webm",
+        "flac" => "audio/flac",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::file_system::MockFileSystem;
+
+    #[tokio::test]
+    async fn test_store_and_read_audio() {
+        let fs = Arc::new(MockFileSystem::new());
+        let manager = AudioManager::with_fs(Path::new("/workspace"), fs.clone());
+
+        let data_url = format!("data:audio/webm;base64,{}", BASE64.encode(b"memo"));
+        let ref_id = manager.store_audio(&data_url).await.unwrap();
+        assert!(ref_id.starts_with(REF_PREFIX));
+
+        // Same content is stored once
+        assert_eq!(manager.store_audio(&data_url).await.unwrap(), ref_id);
+        assert_eq!(manager.list_audio().await.unwrap(), vec![ref_id.clone()]);
+
+        let path = manager.audio_path(&ref_id).await.unwrap();
+        assert_eq!(path.extension().unwrap(), "webm");
+        assert_eq!(manager.get_audio_data_url(&ref_id).await.unwrap(), data_url);
+
+        manager.delete(&ref_id).await.unwrap();
+        assert!(manager.audio_path(&ref_id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_unsupported_audio() {
+        let fs = Arc::new(MockFileSystem::new().with_file("/recordings/notes.txt", "text"));
+        let manager = AudioManager::with_fs(Path::new("/workspace"), fs);
+
+        let data_url = format!("data:image/png;base64,{}", BASE64.encode(b"png"));
+        assert!(manager.store_audio(&data_url).await.is_err());
+        assert!(manager
+            .import_audio_file(Path::new("/recordings/notes.txt"))
+            .await
+            .is_err());
+    }
+}
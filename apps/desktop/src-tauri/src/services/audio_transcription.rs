@@ -0,0 +1,626 @@
+// Audio Transcription - Turns audio attachments into text
+//
+// Two backends are supported:
+// - Local (the default): the whisper.cpp CLI, taken from MIDLIGHT_WHISPER and falling back
+//   to `whisper-cli` on PATH, with the model file from MIDLIGHT_WHISPER_MODEL
+//   or the request. Formats whisper.cpp can't read are converted to 16 kHz
+//   WAV with ffmpeg first. Nothing leaves the machine.
+// - Api: the midlight.ai transcription endpoint, for users without a local
+//   model. Recordings are uploaded whole, up to MAX_UPLOAD_BYTES, so it is
+//   only used when the request asks for it; a missing local model is an
+//   error, never a reason to upload.
+//
+// Progress is reported through a callback as the recording moves through
+// the stages, with percentages from whisper.cpp where it prints them.
+
+use crate::services::network_config::configure_client;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use tracing::debug;
+
+const DEFAULT_BASE_URL: &str = "https://midlight.ai";
+
+/// Environment variable that overrides the whisper.cpp executable
+pub const WHISPER_ENV: &str = "MIDLIGHT_WHISPER";
+
+/// Environment variable naming the whisper.cpp model file
+pub const WHISPER_MODEL_ENV: &str = "MIDLIGHT_WHISPER_MODEL";
+
+/// Largest recording sent to the transcription API
+pub const MAX_UPLOAD_BYTES: u64 = 25 * 1024 * 1024;
+
+/// Transcription taking longer than this is abandoned
+const TRANSCRIPTION_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// Formats whisper.cpp reads directly; others go through ffmpeg
+const WHISPER_EXTENSIONS: &[&str] = &["wav", "mp3", "flac", "ogg"];
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscriptionBackend {
+    #[default]
+    Local,
+    /// Upload the recording to midlight.ai
+    Api,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscribeOptions {
+    #[serde(default)]
+    pub backend: TranscriptionBackend,
+    /// Spoken language as an ISO 639-1 code; detected when unset
+    #[serde(default)]
+    pub language: Option<String>,
+    /// whisper.cpp model file for local transcription
+    #[serde(default)]
+    pub model_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscriptSegment {
+    /// Seconds from the start of the recording
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Transcript {
+    pub text: String,
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub segments: Vec<TranscriptSegment>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscriptionStage {
+    Converting,
+    Uploading,
+    Transcribing,
+    Complete,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscriptionProgress {
+    pub stage: TranscriptionStage,
+    /// Percent of the current stage, when known
+    pub percent: Option<u8>,
+}
+
+impl TranscriptionProgress {
+    fn stage(stage: TranscriptionStage) -> Self {
+        Self {
+            stage,
+            percent: None,
+        }
+    }
+}
+
+// ============================================================================
+// Transcription
+// ============================================================================
+
+/// Transcribe an audio file, reporting progress as it goes
+pub async fn transcribe(
+    audio_path: &Path,
+    options: &TranscribeOptions,
+    auth_token: Option<&str>,
+    on_progress: impl Fn(TranscriptionProgress) + Send + Sync,
+) -> Result<Transcript, String> {
+    let local_model = options
+        .model_path
+        .clone()
+        .or_else(|| std::env::var(WHISPER_MODEL_ENV).ok())
+        .map(PathBuf::from);
+
+    let transcript = match options.backend {
+        TranscriptionBackend::Local => {
+            let model = local_model.ok_or_else(|| {
+                format!(
+                    "No whisper model configured; set {}, choose a model file or transcribe with Midlight",
+                    WHISPER_MODEL_ENV
+                )
+            })?;
+            transcribe_local(audio_path, &model, options, &on_progress).await?
+        }
+        TranscriptionBackend::Api => {
+            let auth_token =
+                auth_token.ok_or_else(|| "Sign in to transcribe with Midlight".to_string())?;
+            TranscriptionClient::new(None)
+                .transcribe(audio_path, options, auth_token, &on_progress)
+                .await?
+        }
+    };
+
+    on_progress(TranscriptionProgress::stage(TranscriptionStage::Complete));
+    Ok(transcript)
+}
+
+async fn transcribe_local(
+    audio_path: &Path,
+    model: &Path,
+    options: &TranscribeOptions,
+    on_progress: &(impl Fn(TranscriptionProgress) + Send + Sync),
+) -> Result<Transcript, String> {
+    let work_dir = WorkDir::create()?;
+
+    let extension = audio_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    let input = if WHISPER_EXTENSIONS.contains(&extension.as_str()) {
+        audio_path.to_path_buf()
+    } else {
+        on_progress(TranscriptionProgress::stage(TranscriptionStage::Converting));
+        convert_to_wav(audio_path, &work_dir.0.join("input.wav")).await?
+    };
+
+    let program = std::env::var(WHISPER_ENV).unwrap_or_else(|_| "whisper-cli".to_string());
+    let output_prefix = work_dir.0.join("transcript");
+    let mut command = Command::new(&program);
+    command
+        .arg("--model")
+        .arg(model)
+        .arg("--file")
+        .arg(&input)
+        .arg("--output-json")
+        .arg("--output-file")
+        .arg(&output_prefix)
+        .arg("--print-progress")
+        .arg("--language")
+        .arg(options.language.as_deref().unwrap_or("auto"))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let mut child = command.spawn().map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => format!(
+            "{} is not installed; install whisper.cpp or set {}",
+            program, WHISPER_ENV
+        ),
+        _ => format!("Failed to start transcription: {}", e),
+    })?;
+
+    on_progress(TranscriptionProgress {
+        stage: TranscriptionStage::Transcribing,
+        percent: Some(0),
+    });
+
+    // whisper.cpp reports progress on stderr; keep the rest for errors
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let run = async {
+        let mut lines = BufReader::new(stderr).lines();
+        let mut errors = Vec::new();
+        while let Ok(Some(line)) = lines.next_line().await {
+            match parse_whisper_progress(&line) {
+                Some(percent) => on_progress(TranscriptionProgress {
+                    stage: TranscriptionStage::Transcribing,
+                    percent: Some(percent),
+                }),
+                None => errors.push(line),
+            }
+        }
+        let status = child.wait().await;
+        (status, errors)
+    };
+
+    let (status, errors) = tokio::time::timeout(TRANSCRIPTION_TIMEOUT, run)
+        .await
+        .map_err(|_| "Transcription timed out".to_string())?;
+    let status = status.map_err(|e| format!("Failed to run transcription: {}", e))?;
+    if !status.success() {
+        let tail: Vec<&str> = errors
+            .iter()
+            .rev()
+            .take(3)
+            .rev()
+            .map(String::as_str)
+            .collect();
+        return Err(format!("Transcription failed: {}", tail.join(" ").trim()));
+    }
+
+    let json = tokio::fs::read_to_string(output_prefix.with_extension("json"))
+        .await
+        .map_err(|e| format!("Failed to read transcription: {}", e))?;
+    parse_whisper_json(&json)
+}
+
+/// Scratch directory for converted audio and whisper.cpp output, removed
+/// when dropped
+struct WorkDir(PathBuf);
+
+impl WorkDir {
+    fn create() -> Result<Self, String> {
+        let path = std::env::temp_dir().join(format!("midlight-whisper-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&path)
+            .map_err(|e| format!("Failed to create temp directory: {}", e))?;
+        Ok(Self(path))
+    }
+}
+
+impl Drop for WorkDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+async fn convert_to_wav(input: &Path, output: &Path) -> Result<PathBuf, String> {
+    let status = Command::new("ffmpeg")
+        .args(["-hide_banner", "-loglevel", "error", "-y", "-i"])
+        .arg(input)
+        .args(["-ar", "16000", "-ac", "1"])
+        .arg(output)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .status()
+        .await
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => {
+                "ffmpeg is needed to transcribe this format locally".to_string()
+            }
+            _ => format!("Failed to convert audio: {}", e),
+        })?;
+
+    if !status.success() {
+        return Err("Failed to convert audio for transcription".to_string());
+    }
+    Ok(output.to_path_buf())
+}
+
+/// Percent from a whisper.cpp progress line, e.g.
+/// "whisper_print_progress_callback: progress =  45%"
+fn parse_whisper_progress(line: &str) -> Option<u8> {
+    let (_, rest) = line.split_once("progress =")?;
+    rest.trim()
+        .strip_suffix('%')?
+        .trim()
+        .parse::<u8>()
+        .ok()
+        .map(|p| p.min(100))
+}
+
+/// Transcript from whisper.cpp's JSON output
+fn parse_whisper_json(json: &str) -> Result<Transcript, String> {
+    #[derive(Deserialize)]
+    struct Output {
+        #[serde(default)]
+        result: Option<OutputResult>,
+        transcription: Vec<OutputSegment>,
+    }
+    #[derive(Deserialize)]
+    struct OutputResult {
+        language: Option<String>,
+    }
+    #[derive(Deserialize)]
+    struct OutputSegment {
+        /// Milliseconds
+        offsets: Offsets,
+        text: String,
+    }
+    #[derive(Deserialize)]
+    struct Offsets {
+        from: u64,
+        to: u64,
+    }
+
+    let output: Output =
+        serde_json::from_str(json).map_err(|e| format!("Failed to parse transcription: {}", e))?;
+
+    let segments: Vec<TranscriptSegment> = output
+        .transcription
+        .into_iter()
+        .map(|s| TranscriptSegment {
+            start: s.offsets.from as f64 / 1000.0,
+            end: s.offsets.to as f64 / 1000.0,
+            text: s.text.trim().to_string(),
+        })
+        .filter(|s| !s.text.is_empty())
+        .collect();
+
+    Ok(Transcript {
+        text: segments
+            .iter()
+            .map(|s| s.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" "),
+        language: output.result.and_then(|r| r.language),
+        segments,
+    })
+}
+
+// ============================================================================
+// API Client
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TranscribeRequest<'a> {
+    audio: String,
+    mime_type: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    language: Option<&'a str>,
+}
+
+struct TranscriptionClient {
+    client: Client,
+    base_url: String,
+}
+
+impl TranscriptionClient {
+    fn new(base_url: Option<String>) -> Self {
+        let mut default_headers = reqwest::header::HeaderMap::new();
+        default_headers.insert(
+            reqwest::header::HeaderName::from_static("x-client-type"),
+            reqwest::header::HeaderValue::from_static("desktop"),
+        );
+
+        let client = configure_client(Client::builder())
+            .default_headers(default_headers)
+            .timeout(Duration::from_secs(15 * 60))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            client,
+            base_url: base_url.unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
+        }
+    }
+
+    async fn transcribe(
+        &self,
+        audio_path: &Path,
+        options: &TranscribeOptions,
+        auth_token: &str,
+        on_progress: &(impl Fn(TranscriptionProgress) + Send + Sync),
+    ) -> Result<Transcript, String> {
+        let size = tokio::fs::metadata(audio_path)
+            .await
+            .map_err(|e| format!("Failed to read audio: {}", e))?
+            .len();
+        if size > MAX_UPLOAD_BYTES {
+            return Err(format!(
+                "Recording is too large to upload ({} MB, limit {} MB); use local transcription",
+                size / (1024 * 1024),
+                MAX_UPLOAD_BYTES / (1024 * 1024)
+            ));
+        }
+
+        on_progress(TranscriptionProgress::stage(TranscriptionStage::Uploading));
+        let audio = tokio::fs::read(audio_path)
+            .await
+            .map_err(|e| format!("Failed to read audio: {}", e))?;
+        let extension = audio_path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase())
+            .unwrap_or_default();
+        let request = TranscribeRequest {
+            audio: BASE64.encode(audio),
+            mime_type: super::audio_manager::mime_for_extension(&extension),
+            language: options.language.as_deref(),
+        };
+
+        let url = format!("{}/api/llm/transcribe", self.base_url);
+        debug!("Uploading {} bytes for transcription", size);
+        let pending = self
+            .client
+            .post(&url)
+            .bearer_auth(auth_token)
+            .json(&request)
+            .send();
+        on_progress(TranscriptionProgress::stage(
+            TranscriptionStage::Transcribing,
+        ));
+
+        let response = pending
+            .await
+            .map_err(|e| format!("Transcription request failed: {}", e))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_body: Option<serde_json::Value> = response.json().await.ok();
+            let message = error_body
+                .as_ref()
+                .and_then(|b| b.get("error"))
+                .and_then(|m| m.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("HTTP {}", status));
+            return Err(format!("Transcription failed: {}", message));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse transcription: {}", e))
+    }
+}
+
+// ============================================================================
+// Transcript Documents
+// ============================================================================
+
+/// Markdown document for a transcript, linking back to the recording and
+/// the document it is attached to
+pub fn transcript_markdown(
+    title: &str,
+    audio_ref: &str,
+    source_document: Option<&str>,
+    transcript: &Transcript,
+) -> String {
+    let mut markdown = format!(
+        "# {} (transcript)\n\nRecording: [{}]({})\n",
+        title, title, audio_ref
+    );
+    if let Some(source) = source_document {
+        markdown.push_str(&format!("Attached to: [[{}]]\n", source));
+    }
+    markdown.push('\n');
+
+    if transcript.segments.is_empty() {
+        markdown.push_str(transcript.text.trim());
+        markdown.push('\n');
+    } else {
+        for segment in &transcript.segments {
+            markdown.push_str(&format!(
+                "**{}** {}\n\n",
+                format_timestamp(segment.start),
+                segment.text
+            ));
+        }
+    }
+    markdown
+}
+
+/// "m:ss", or "h:mm:ss" for recordings over an hour
+fn format_timestamp(seconds: f64) -> String {
+    let total = seconds.max(0.0) as u64;
+    let (hours, minutes, secs) = (total / 3600, (total % 3600) / 60, total % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, secs)
+    } else {
+        format!("{}:{:02}", minutes, secs)
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_parse_whisper_output() {
+        assert_eq!(
+            parse_whisper_progress("whisper_print_progress_callback: progress =  45%"),
+            Some(45)
+        );
+        assert_eq!(
+            parse_whisper_progress("whisper_init_from_file: loading model"),
+            None
+        );
+
+        let json = r#"{
+            "result": { "language": "en" },
+            "transcription": [
+                { "offsets": { "from": 0, "to": 2400 }, "text": " Remember the milk." },
+                { "offsets": { "from": 2400, "to": 2600 }, "text": " " },
+                { "offsets": { "from": 61000, "to": 64000 }, "text": " And the eggs." }
+            ]
+        }"#;
+        let transcript = parse_whisper_json(json).unwrap();
+        assert_eq!(transcript.text, "Remember the milk. And the eggs.");
+        assert_eq!(transcript.language.as_deref(), Some("en"));
+        assert_eq!(transcript.segments.len(), 2);
+        assert_eq!(transcript.segments[1].start, 61.0);
+    }
+
+    #[test]
+    fn test_transcript_markdown() {
+        let transcript = Transcript {
+            text: "Remember the milk. And the eggs.".to_string(),
+            language: None,
+            segments: vec![
+                TranscriptSegment {
+                    start: 0.0,
+                    end: 2.4,
+                    text: "Remember the milk.".to_string(),
+                },
+                TranscriptSegment {
+                    start: 3725.0,
+                    end: 3728.0,
+                    text: "And the eggs.".to_string(),
+                },
+            ],
+        };
+        let markdown = transcript_markdown(
+            "Memo",
+            "midlight://audio-0123456789abcdef",
+            Some("Groceries"),
+            &transcript,
+        );
+        assert_eq!(
+            markdown,
+            "# Memo (transcript)\n\nRecording: [Memo](midlight://audio-0123456789abcdef)\n\
+             Attached to: [[Groceries]]\n\n**0:00** Remember the milk.\n\n\
+             **1:02:05** And the eggs.\n\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_api_transcription() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/llm/transcribe"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "text": "Hello there",
+                "language": "en",
+                "segments": [{ "start": 0.0, "end": 1.5, "text": "Hello there" }]
+            })))
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let audio_path = dir.path().join("memo.m4a");
+        std::fs::write(&audio_path, b"audio").unwrap();
+
+        let stages = Mutex::new(Vec::new());
+        let transcript = TranscriptionClient::new(Some(server.uri()))
+            .transcribe(&audio_path, &TranscribeOptions::default(), "token", &|p| {
+                stages.lock().unwrap().push(p.stage)
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(transcript.text, "Hello there");
+        assert_eq!(transcript.segments.len(), 1);
+        assert_eq!(
+            *stages.lock().unwrap(),
+            vec![
+                TranscriptionStage::Uploading,
+                TranscriptionStage::Transcribing
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_default_backend_never_uploads() {
+        let options: TranscribeOptions = serde_json::from_str("{}").unwrap();
+        assert_eq!(options.backend, TranscriptionBackend::Local);
+
+        // Without a local model the default fails instead of falling back
+        // to the API, even when signed in
+        if std::env::var_os(WHISPER_MODEL_ENV).is_none() {
+            let dir = tempfile::tempdir().unwrap();
+            let audio_path = dir.path().join("memo.wav");
+            std::fs::write(&audio_path, b"audio").unwrap();
+            let err = transcribe(&audio_path, &options, Some("token"), |_| {})
+                .await
+                .unwrap_err();
+            assert!(err.contains("No whisper model configured"));
+        }
+    }
+}
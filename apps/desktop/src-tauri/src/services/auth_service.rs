@@ -1,18 +1,52 @@
 // Auth Service - Authentication with midlight.ai backend
-
+//
+// Several accounts can be signed in at once, with one active. The active
+// account's refresh cookie lives in cookies.json as before; switching saves
+// it to accounts/{id}.cookies.json and loads the other account's jar. Access
+// tokens stay in memory, cached per account so switching back is instant.
+//
+// Accounts are listed in: {app data}/accounts.json
+//
+// The last-known user, subscription and quota are cached in offline.json,
+// signed with a local key (offline.key) and valid for a grace period. When
+// the server can't be reached, the cache keeps the user signed in instead of
+// flipping to unauthenticated; the session is re-validated once it's back.
+// The signature is an integrity check only: it catches a corrupt or
+// hand-edited cache, but the key sits next to it, readable by the user like
+// the refresh cookie, so it proves nothing to the server and grants nothing.
+
+use crate::services::app_dirs::app_data_dir;
+use crate::services::atomic_write::{create_private, write_atomic, write_private};
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
 use cookie_store::CookieStore;
 use reqwest::Client;
 use reqwest_cookie_store::CookieStoreMutex;
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::io::BufReader;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
 use tracing::{debug, info, warn};
 
+use crate::services::network_config::configure_client;
 use crate::traits::{RealTimeProvider, TimeProvider};
 
 const DEFAULT_BASE_URL: &str = "https://midlight.ai";
 const TOKEN_REFRESH_BUFFER_SECS: i64 = 60; // Refresh 60 seconds before expiry
+/// The background refresher renews tokens this long before expiry, ahead of
+/// the lazy refresh in get_access_token
+const PROACTIVE_REFRESH_SECS: i64 = 120;
+/// Length of the offline cache signing key, in bytes
+const OFFLINE_KEY_LEN: usize = 32;
+/// How long the cached session keeps the user signed in while offline
+const OFFLINE_GRACE_SECS: i64 = 7 * 24 * 60 * 60;
+/// How long a browser sign-in may take before its callback is refused
+const OAUTH_LOGIN_TIMEOUT_SECS: i64 = 10 * 60;
 
 // ============================================================================
 // Types
@@ -151,6 +185,48 @@ impl std::fmt::Display for AuthError {
 
 impl std::error::Error for AuthError {}
 
+/// A signed-in account
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Account {
+    pub user: User,
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountsFile {
+    active: Option<i64>,
+    #[serde(default)]
+    accounts: Vec<User>,
+}
+
+/// An account's access token, kept while another account is active
+#[derive(Debug, Clone)]
+struct CachedSession {
+    access_token: String,
+    expiry: i64,
+    user: User,
+}
+
+/// What's known about the signed-in user, for when the server is unreachable
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OfflineCache {
+    pub user: User,
+    pub subscription: Option<Subscription>,
+    pub quota: Option<Quota>,
+    pub cached_at: i64,
+    pub expires_at: i64,
+}
+
+/// offline.json: the cache as a JSON string and its HMAC-SHA256, in base64
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SignedOfflineCache {
+    payload: String,
+    signature: String,
+}
+
 // ============================================================================
 // Auth Service
 // ============================================================================
@@ -166,6 +242,19 @@ pub struct AuthService<T: TimeProvider = RealTimeProvider> {
     token_expiry: RwLock<Option<i64>>, // Unix timestamp
     user: RwLock<Option<User>>,
     auth_state: RwLock<AuthState>,
+    /// Sessions of inactive accounts, by account id
+    inactive_sessions: RwLock<HashMap<i64, CachedSession>>,
+    /// Signed in from the offline cache, waiting to re-validate
+    offline: RwLock<bool>,
+    /// Browser sign-in waiting for its callback
+    pending_login: RwLock<Option<PendingLogin>>,
+}
+
+/// A browser sign-in that was started from this app
+struct PendingLogin {
+    /// Nonce the callback has to return
+    state: String,
+    started_at: i64,
 }
 
 /// Type alias for production use
@@ -196,7 +285,7 @@ impl<T: TimeProvider> AuthService<T> {
             reqwest::header::HeaderValue::from_static("desktop"),
         );
 
-        let client = Client::builder()
+        let client = configure_client(Client::builder())
             .cookie_provider(cookie_store.clone())
             .default_headers(default_headers)
             .timeout(std::time::Duration::from_secs(30))
@@ -213,6 +302,9 @@ impl<T: TimeProvider> AuthService<T> {
             token_expiry: RwLock::new(None),
             user: RwLock::new(None),
             auth_state: RwLock::new(AuthState::Initializing),
+            inactive_sessions: RwLock::new(HashMap::new()),
+            offline: RwLock::new(false),
+            pending_login: RwLock::new(None),
         }
     }
 
@@ -236,14 +328,20 @@ impl<T: TimeProvider> AuthService<T> {
             token_expiry: RwLock::new(None),
             user: RwLock::new(None),
             auth_state: RwLock::new(AuthState::Initializing),
+            inactive_sessions: RwLock::new(HashMap::new()),
+            offline: RwLock::new(false),
+            pending_login: RwLock::new(None),
         }
     }
 
-    #[allow(deprecated)]
     fn load_cookie_store(app_data_dir: &Path) -> CookieStore {
-        let cookie_path = app_data_dir.join("cookies.json");
+        Self::read_cookie_store(&app_data_dir.join("cookies.json"))
+    }
+
+    #[allow(deprecated)]
+    fn read_cookie_store(cookie_path: &Path) -> CookieStore {
         if cookie_path.exists() {
-            match std::fs::File::open(&cookie_path) {
+            match std::fs::File::open(cookie_path) {
                 Ok(file) => {
                     let reader = BufReader::new(file);
                     match CookieStore::load_json(reader) {
@@ -264,10 +362,12 @@ impl<T: TimeProvider> AuthService<T> {
         CookieStore::default()
     }
 
-    #[allow(deprecated)]
     pub fn save_cookies(&self) -> Result<(), AuthError> {
-        let cookie_path = self.app_data_dir.join("cookies.json");
+        self.write_cookie_store(&self.app_data_dir.join("cookies.json"))
+    }
 
+    #[allow(deprecated)]
+    fn write_cookie_store(&self, cookie_path: &Path) -> Result<(), AuthError> {
         // Ensure directory exists
         if let Some(parent) = cookie_path.parent() {
             std::fs::create_dir_all(parent).map_err(|e| AuthError {
@@ -276,7 +376,7 @@ impl<T: TimeProvider> AuthService<T> {
             })?;
         }
 
-        let file = std::fs::File::create(&cookie_path).map_err(|e| AuthError {
+        let file = std::fs::File::create(cookie_path).map_err(|e| AuthError {
             code: "STORAGE_ERROR".to_string(),
             message: format!("Failed to create cookie file: {}", e),
         })?;
@@ -342,6 +442,14 @@ impl<T: TimeProvider> AuthService<T> {
     // Public API
     // ========================================================================
 
+    /// Seconds until the access token should be refreshed in the background
+    /// (zero or less when it's due), or None when there is no token
+    pub fn seconds_until_refresh(&self) -> Option<i64> {
+        let expiry = (*self.token_expiry.read().unwrap())?;
+        let now = self.time_provider.unix_timestamp();
+        Some(expiry - PROACTIVE_REFRESH_SECS - now)
+    }
+
     /// Initialize auth service - attempt silent refresh from stored cookie
     pub async fn init(&self) -> Result<AuthState, AuthError> {
         debug!("Initializing auth service");
@@ -350,11 +458,26 @@ impl<T: TimeProvider> AuthService<T> {
         match self.refresh_access_token_internal(false).await {
             Ok(response) => {
                 self.set_tokens(&response.access_token, response.expires_in);
+                self.remember_account(&response.user);
                 *self.user.write().unwrap() = Some(response.user);
                 self.set_auth_state(AuthState::Authenticated);
                 info!("Auth initialized - user authenticated via refresh");
                 Ok(AuthState::Authenticated)
             }
+            Err(e) if e.code == "NETWORK_ERROR" => match self.read_offline_cache() {
+                Some(cache) => {
+                    *self.user.write().unwrap() = Some(cache.user);
+                    *self.offline.write().unwrap() = true;
+                    self.set_auth_state(AuthState::Authenticated);
+                    info!("Auth initialized offline from cached session");
+                    Ok(AuthState::Authenticated)
+                }
+                None => {
+                    self.set_auth_state(AuthState::Unauthenticated);
+                    debug!("Auth initialized - offline with no cached session");
+                    Ok(AuthState::Unauthenticated)
+                }
+            },
             Err(_) => {
                 self.set_auth_state(AuthState::Unauthenticated);
                 debug!("Auth initialized - no valid session");
@@ -363,6 +486,27 @@ impl<T: TimeProvider> AuthService<T> {
         }
     }
 
+    /// Whether the user is signed in from the offline cache
+    pub fn is_offline(&self) -> bool {
+        *self.offline.read().unwrap()
+    }
+
+    /// Try to confirm an offline session with the server. A network error
+    /// keeps the session while the cache is valid; once it expires, or the
+    /// server rejects the session, the user is signed out.
+    pub async fn revalidate(&self) -> Result<AuthResponse, AuthError> {
+        match self.refresh_access_token().await {
+            Err(e) if e.code == "NETWORK_ERROR" && self.read_offline_cache().is_none() => {
+                info!("Offline grace period has ended");
+                self.clear_tokens();
+                self.set_auth_state(AuthState::Unauthenticated);
+                *self.offline.write().unwrap() = false;
+                Err(e)
+            }
+            result => result,
+        }
+    }
+
     /// Email/password signup
     pub async fn signup(
         &self,
@@ -371,6 +515,7 @@ impl<T: TimeProvider> AuthService<T> {
         display_name: Option<&str>,
     ) -> Result<AuthResponse, AuthError> {
         let url = format!("{}/api/auth/signup", self.base_url);
+        self.stash_active_account();
 
         let request = SignupRequest {
             email: email.to_string(),
@@ -405,6 +550,7 @@ impl<T: TimeProvider> AuthService<T> {
 
         // Save cookies (refresh token)
         self.save_cookies()?;
+        self.remember_account(&auth_response.user);
 
         info!("User signed up: {}", email);
         Ok(auth_response)
@@ -413,6 +559,7 @@ impl<T: TimeProvider> AuthService<T> {
     /// Email/password login
     pub async fn login(&self, email: &str, password: &str) -> Result<AuthResponse, AuthError> {
         let url = format!("{}/api/auth/login", self.base_url);
+        self.stash_active_account();
 
         let request = LoginRequest {
             email: email.to_string(),
@@ -446,6 +593,7 @@ impl<T: TimeProvider> AuthService<T> {
 
         // Save cookies (refresh token)
         self.save_cookies()?;
+        self.remember_account(&auth_response.user);
 
         info!("User logged in: {}", email);
         Ok(auth_response)
@@ -462,6 +610,9 @@ impl<T: TimeProvider> AuthService<T> {
         self.clear_tokens();
         self.clear_cookies()?;
         self.set_auth_state(AuthState::Unauthenticated);
+        self.forget_active_account();
+        self.clear_offline_cache();
+        *self.offline.write().unwrap() = false;
 
         info!("User logged out");
         Ok(())
@@ -506,9 +657,14 @@ impl<T: TimeProvider> AuthService<T> {
     ) -> Result<AuthResponse, AuthError> {
         let url = format!("{}/api/auth/refresh", self.base_url);
 
-        let response = self.client.post(&url).send().await.map_err(|e| AuthError {
-            code: "NETWORK_ERROR".to_string(),
-            message: e.to_string(),
+        let response = self.client.post(&url).send().await.map_err(|e| {
+            if self.is_authenticated() {
+                *self.offline.write().unwrap() = true;
+            }
+            AuthError {
+                code: "NETWORK_ERROR".to_string(),
+                message: e.to_string(),
+            }
         })?;
 
         if !response.status().is_success() {
@@ -518,6 +674,7 @@ impl<T: TimeProvider> AuthService<T> {
                 // Session expired - clear state
                 self.clear_tokens();
                 self.set_auth_state(AuthState::Unauthenticated);
+                self.clear_offline_cache();
             }
 
             return Err(error);
@@ -531,6 +688,11 @@ impl<T: TimeProvider> AuthService<T> {
         // Update tokens
         self.set_tokens(&auth_response.access_token, auth_response.expires_in);
         *self.user.write().unwrap() = Some(auth_response.user.clone());
+        if std::mem::take(&mut *self.offline.write().unwrap()) {
+            self.set_auth_state(AuthState::Authenticated);
+            info!("Back online, session re-validated");
+        }
+        self.update_offline_cache(&auth_response.user, |_| {});
 
         debug!("Access token refreshed");
         Ok(auth_response)
@@ -539,6 +701,7 @@ impl<T: TimeProvider> AuthService<T> {
     /// Exchange OAuth code for tokens
     pub async fn exchange_oauth_code(&self, code: &str) -> Result<AuthResponse, AuthError> {
         let url = format!("{}/api/auth/exchange", self.base_url);
+        self.stash_active_account();
 
         let request = ExchangeCodeRequest {
             code: code.to_string(),
@@ -571,20 +734,338 @@ impl<T: TimeProvider> AuthService<T> {
 
         // Save cookies (refresh token)
         self.save_cookies()?;
+        self.remember_account(&auth_response.user);
 
         info!("OAuth exchange successful");
         Ok(auth_response)
     }
 
-    /// Build OAuth URL for browser
-    pub fn get_oauth_url(&self, callback_port: Option<u16>) -> String {
-        let mut url = format!("{}/api/auth/google?desktop=true", self.base_url);
+    // ========================================================================
+    // Accounts
+    // ========================================================================
+
+    /// Signed-in accounts, with the active one marked
+    pub fn list_accounts(&self) -> Vec<Account> {
+        let file = self.read_accounts();
+        file.accounts
+            .into_iter()
+            .map(|user| Account {
+                active: file.active == Some(user.id),
+                user,
+            })
+            .collect()
+    }
+
+    /// Make another signed-in account the active one. Uses its cached access
+    /// token if still valid, otherwise refreshes with its stored cookie.
+    pub async fn switch_account(&self, account_id: i64) -> Result<User, AuthError> {
+        let mut file = self.read_accounts();
+        if !file.accounts.iter().any(|u| u.id == account_id) {
+            return Err(AuthError {
+                code: "NOT_FOUND".to_string(),
+                message: format!("No signed-in account with id {}", account_id),
+            });
+        }
+        if file.active == Some(account_id) && self.is_authenticated() {
+            if let Some(user) = self.get_user() {
+                return Ok(user);
+            }
+        }
 
+        self.stash_active_account();
+        let jar = Self::read_cookie_store(&self.account_cookie_path(account_id));
+        *self.cookie_store.lock().unwrap() = jar;
+        self.save_cookies()?;
+        file.active = Some(account_id);
+        self.write_accounts(&file)?;
+
+        let cached = self.inactive_sessions.write().unwrap().remove(&account_id);
+        match cached {
+            Some(session)
+                if self.time_provider.unix_timestamp()
+                    < session.expiry - TOKEN_REFRESH_BUFFER_SECS =>
+            {
+                *self.access_token.write().unwrap() = Some(session.access_token);
+                *self.token_expiry.write().unwrap() = Some(session.expiry);
+                *self.user.write().unwrap() = Some(session.user);
+            }
+            _ => {
+                self.clear_tokens();
+                self.refresh_access_token().await?;
+            }
+        }
+        self.set_auth_state(AuthState::Authenticated);
+
+        let user = self.get_user().ok_or_else(|| AuthError {
+            code: "AUTH_REQUIRED".to_string(),
+            message: "Account has no session".to_string(),
+        })?;
+        self.update_offline_cache(&user, |_| {});
+        info!("Switched to account {}", user.email);
+        Ok(user)
+    }
+
+    fn accounts_path(&self) -> PathBuf {
+        self.app_data_dir.join("accounts.json")
+    }
+
+    fn account_cookie_path(&self, account_id: i64) -> PathBuf {
+        self.app_data_dir
+            .join("accounts")
+            .join(format!("{}.cookies.json", account_id))
+    }
+
+    fn read_accounts(&self) -> AccountsFile {
+        std::fs::read_to_string(self.accounts_path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn write_accounts(&self, file: &AccountsFile) -> Result<(), AuthError> {
+        let storage_error = |e: String| AuthError {
+            code: "STORAGE_ERROR".to_string(),
+            message: e,
+        };
+        let json = serde_json::to_string_pretty(file)
+            .map_err(|e| storage_error(format!("Failed to serialize accounts: {}", e)))?;
+        write_atomic(&self.accounts_path(), json)
+            .map_err(|e| storage_error(format!("Failed to save accounts: {}", e)))
+    }
+
+    /// Record a newly signed-in user as the active account
+    fn remember_account(&self, user: &User) {
+        self.update_offline_cache(user, |_| {});
+        let mut file = self.read_accounts();
+        file.accounts.retain(|u| u.id != user.id);
+        file.accounts.push(user.clone());
+        file.active = Some(user.id);
+        if let Err(e) = self.write_accounts(&file) {
+            warn!("Failed to record account: {}", e);
+        }
+    }
+
+    /// Put the active account's cookies and token aside before another
+    /// account signs in or becomes active
+    fn stash_active_account(&self) {
+        let Some(active) = self.read_accounts().active else {
+            return;
+        };
+        if let Err(e) = self.write_cookie_store(&self.account_cookie_path(active)) {
+            warn!("Failed to save account cookies: {}", e);
+        }
+
+        let token = self.access_token.read().unwrap().clone();
+        let expiry = *self.token_expiry.read().unwrap();
+        let user = self.get_user();
+        if let (Some(access_token), Some(expiry), Some(user)) = (token, expiry, user) {
+            if user.id == active {
+                self.inactive_sessions.write().unwrap().insert(
+                    active,
+                    CachedSession {
+                        access_token,
+                        expiry,
+                        user,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Remove the active account after it signs out
+    fn forget_active_account(&self) {
+        let mut file = self.read_accounts();
+        let Some(active) = file.active.take() else {
+            return;
+        };
+        file.accounts.retain(|u| u.id != active);
+        let _ = std::fs::remove_file(self.account_cookie_path(active));
+        self.inactive_sessions.write().unwrap().remove(&active);
+        if let Err(e) = self.write_accounts(&file) {
+            warn!("Failed to update accounts: {}", e);
+        }
+    }
+
+    // ========================================================================
+    // Offline Cache
+    // ========================================================================
+
+    /// The cached session, if its signature checks out, it belongs to the
+    /// active account and the grace period hasn't run out
+    pub fn read_offline_cache(&self) -> Option<OfflineCache> {
+        let cache = self.read_signed_offline_cache()?;
+        if self.time_provider.unix_timestamp() >= cache.expires_at {
+            return None;
+        }
+        match self.read_accounts().active {
+            Some(active) if active != cache.user.id => None,
+            _ => Some(cache),
+        }
+    }
+
+    fn read_signed_offline_cache(&self) -> Option<OfflineCache> {
+        let content = std::fs::read_to_string(self.app_data_dir.join("offline.json")).ok()?;
+        let signed: SignedOfflineCache = serde_json::from_str(&content).ok()?;
+        let signature = STANDARD.decode(&signed.signature).ok()?;
+        let key = self.offline_key().ok()?;
+        if hmac::verify(&key, signed.payload.as_bytes(), &signature).is_err() {
+            warn!("Ignoring offline cache with a bad signature");
+            return None;
+        }
+        serde_json::from_str(&signed.payload).ok()
+    }
+
+    /// Cache the user, with `update` filling in the subscription or quota.
+    /// Anything cached for a different user is dropped.
+    fn update_offline_cache(&self, user: &User, update: impl FnOnce(&mut OfflineCache)) {
+        let now = self.time_provider.unix_timestamp();
+        let mut cache = self
+            .read_signed_offline_cache()
+            .filter(|cache| cache.user.id == user.id)
+            .unwrap_or_else(|| OfflineCache {
+                user: user.clone(),
+                subscription: None,
+                quota: None,
+                cached_at: now,
+                expires_at: now,
+            });
+        cache.user = user.clone();
+        update(&mut cache);
+        cache.cached_at = now;
+        cache.expires_at = now + OFFLINE_GRACE_SECS;
+
+        if let Err(e) = self.write_offline_cache(&cache) {
+            warn!("Failed to cache session for offline use: {}", e);
+        }
+    }
+
+    fn write_offline_cache(&self, cache: &OfflineCache) -> Result<(), String> {
+        let key = self.offline_key()?;
+        let payload = serde_json::to_string(cache)
+            .map_err(|e| format!("Failed to serialize offline cache: {}", e))?;
+        let signed = SignedOfflineCache {
+            signature: STANDARD.encode(hmac::sign(&key, payload.as_bytes())),
+            payload,
+        };
+        let json = serde_json::to_string(&signed)
+            .map_err(|e| format!("Failed to serialize offline cache: {}", e))?;
+        write_atomic(&self.app_data_dir.join("offline.json"), json)
+            .map_err(|e| format!("Failed to write offline cache: {}", e))
+    }
+
+    fn clear_offline_cache(&self) {
+        let _ = std::fs::remove_file(self.app_data_dir.join("offline.json"));
+    }
+
+    /// The key offline.json is signed with, created owner-only on first use.
+    /// A key file of the wrong length is replaced, which invalidates the
+    /// cache until the next sign-in refreshes it.
+    fn offline_key(&self) -> Result<hmac::Key, String> {
+        let path = self.app_data_dir.join("offline.key");
+        match std::fs::read(&path) {
+            Ok(bytes) if bytes.len() == OFFLINE_KEY_LEN => {
+                return Ok(hmac::Key::new(hmac::HMAC_SHA256, &bytes))
+            }
+            Ok(_) => warn!("Replacing offline cache key of the wrong length"),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("Failed to read offline cache key: {}", e)),
+        }
+
+        let mut bytes = [0u8; OFFLINE_KEY_LEN];
+        SystemRandom::new()
+            .fill(&mut bytes)
+            .map_err(|_| "Failed to generate offline cache key".to_string())?;
+        let created = if path.exists() {
+            write_private(&path, bytes)
+        } else {
+            create_private(&path, bytes)
+        };
+        match created {
+            Ok(()) => Ok(hmac::Key::new(hmac::HMAC_SHA256, &bytes)),
+            // Another thread created it first; use theirs
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => self.offline_key(),
+            Err(e) => Err(format!("Failed to write offline cache key: {}", e)),
+        }
+    }
+
+    /// Serve a request from the offline cache when it failed for lack of a
+    /// connection, or because the offline session has no access token.
+    /// Without a cached value it fails as a network error, not a sign-out.
+    fn offline_fallback<V>(
+        &self,
+        error: AuthError,
+        pick: impl FnOnce(OfflineCache) -> Option<V>,
+    ) -> Result<V, AuthError> {
+        if error.code != "NETWORK_ERROR" && !self.is_offline() {
+            return Err(error);
+        }
+        self.read_offline_cache()
+            .and_then(pick)
+            .ok_or_else(|| AuthError {
+                code: "NETWORK_ERROR".to_string(),
+                message: format!("Offline with nothing cached ({})", error.message),
+            })
+    }
+
+    /// Build OAuth URL for browser. The server sends the browser back to
+    /// `redirect_uri` with the code and `state`.
+    pub fn get_oauth_url(
+        &self,
+        redirect_uri: &str,
+        state: &str,
+        callback_port: Option<u16>,
+    ) -> String {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        query
+            .append_pair("desktop", "true")
+            .append_pair("redirect_uri", redirect_uri)
+            .append_pair("state", state);
         if let Some(port) = callback_port {
-            url.push_str(&format!("&callback_port={}", port));
+            query.append_pair("callback_port", &port.to_string());
         }
 
-        url
+        format!("{}/api/auth/google?{}", self.base_url, query.finish())
+    }
+
+    /// Start a browser sign-in, returning the URL to open. A fresh `state`
+    /// nonce is remembered, and only a callback carrying it is accepted.
+    pub fn begin_oauth_login(&self, redirect_uri: &str) -> Result<String, AuthError> {
+        let mut bytes = [0u8; 32];
+        SystemRandom::new()
+            .fill(&mut bytes)
+            .map_err(|_| AuthError {
+                code: "OAUTH_STATE".to_string(),
+                message: "Failed to start sign-in".to_string(),
+            })?;
+        let state = URL_SAFE_NO_PAD.encode(bytes);
+        *self.pending_login.write().unwrap() = Some(PendingLogin {
+            state: state.clone(),
+            started_at: self.time_provider.unix_timestamp(),
+        });
+        Ok(self.get_oauth_url(redirect_uri, &state, None))
+    }
+
+    /// Check a sign-in callback's `state` against the sign-in in progress.
+    /// A matching callback uses the sign-in up; anything else is rejected.
+    pub fn verify_oauth_state(&self, state: Option<&str>) -> Result<(), AuthError> {
+        let rejected = |message: &str| AuthError {
+            code: "OAUTH_STATE".to_string(),
+            message: message.to_string(),
+        };
+
+        let mut pending = self.pending_login.write().unwrap();
+        let Some(login) = pending.as_ref() else {
+            return Err(rejected("No sign-in is in progress"));
+        };
+        if self.time_provider.unix_timestamp() - login.started_at > OAUTH_LOGIN_TIMEOUT_SECS {
+            *pending = None;
+            return Err(rejected("Sign-in took too long; please try again"));
+        }
+        if state != Some(login.state.as_str()) {
+            return Err(rejected("Sign-in response doesn't match this sign-in"));
+        }
+        *pending = None;
+        Ok(())
     }
 
     /// Get current user
@@ -592,8 +1073,22 @@ impl<T: TimeProvider> AuthService<T> {
         self.user.read().unwrap().clone()
     }
 
-    /// Get subscription info
+    /// Get subscription info, falling back to the cached subscription while
+    /// the server is unreachable
     pub async fn get_subscription(&self) -> Result<Subscription, AuthError> {
+        match self.fetch_subscription().await {
+            Ok(subscription) => {
+                if let Some(user) = self.get_user() {
+                    let cached = subscription.clone();
+                    self.update_offline_cache(&user, |cache| cache.subscription = Some(cached));
+                }
+                Ok(subscription)
+            }
+            Err(e) => self.offline_fallback(e, |cache| cache.subscription),
+        }
+    }
+
+    async fn fetch_subscription(&self) -> Result<Subscription, AuthError> {
         let url = format!("{}/api/user/subscription", self.base_url);
 
         let token = self.get_access_token().await.ok_or_else(|| AuthError {
@@ -624,8 +1119,22 @@ impl<T: TimeProvider> AuthService<T> {
         Ok(wrapper.subscription)
     }
 
-    /// Get quota info
+    /// Get quota info, falling back to the cached quota while the server is
+    /// unreachable
     pub async fn get_quota(&self) -> Result<Quota, AuthError> {
+        match self.fetch_quota().await {
+            Ok(quota) => {
+                if let Some(user) = self.get_user() {
+                    let cached = quota.clone();
+                    self.update_offline_cache(&user, |cache| cache.quota = Some(cached));
+                }
+                Ok(quota)
+            }
+            Err(e) => self.offline_fallback(e, |cache| cache.quota),
+        }
+    }
+
+    async fn fetch_quota(&self) -> Result<Quota, AuthError> {
         let url = format!("{}/api/user/usage", self.base_url);
 
         let token = self.get_access_token().await.ok_or_else(|| AuthError {
@@ -895,12 +1404,7 @@ impl<T: TimeProvider> AuthService<T> {
 
 lazy_static::lazy_static! {
     pub static ref AUTH_SERVICE: AuthService<RealTimeProvider> = {
-        // Get app data directory
-        let app_data_dir = dirs::data_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("com.midlight.app");
-
-        AuthService::new(app_data_dir, None)
+        AuthService::new(app_data_dir(), None)
     };
 }
 
@@ -913,7 +1417,7 @@ mod tests {
     use super::*;
     use crate::traits::time::MockTimeProvider;
     use tempfile::tempdir;
-    use wiremock::matchers::{method, path};
+    use wiremock::matchers::{body_partial_json, method, path};
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
     fn create_test_service(
@@ -1038,6 +1542,29 @@ mod tests {
         assert!(service.is_token_expired());
     }
 
+    #[tokio::test]
+    async fn test_seconds_until_refresh() {
+        let time_provider = Arc::new(MockTimeProvider::from_timestamp(1704067200));
+        let temp = tempdir().unwrap();
+        let service = AuthService::with_time_provider(
+            temp.path().to_path_buf(),
+            Some("https://mock.test".to_string()),
+            time_provider.clone(),
+        );
+        assert_eq!(service.seconds_until_refresh(), None);
+
+        service.set_tokens("test_token", 3600);
+        assert_eq!(service.seconds_until_refresh(), Some(3480));
+
+        // Due before the lazy refresh would kick in
+        time_provider.advance_secs(3480);
+        assert_eq!(service.seconds_until_refresh(), Some(0));
+        assert!(!service.is_token_expired());
+
+        service.clear_tokens();
+        assert_eq!(service.seconds_until_refresh(), None);
+    }
+
     #[tokio::test]
     async fn test_logout_clears_state() {
         let mock_server = MockServer::start().await;
@@ -1067,6 +1594,175 @@ mod tests {
         assert!(service.get_user().is_none());
     }
 
+    #[tokio::test]
+    async fn test_multiple_accounts() {
+        let mock_server = MockServer::start().await;
+        let auth_response = |id: i64, email: &str| {
+            serde_json::json!({
+                "user": { "id": id, "email": email, "displayName": null, "avatarUrl": null },
+                "accessToken": format!("token_{}", id),
+                "expiresIn": 3600
+            })
+        };
+        for (id, email) in [(1, "personal@example.com"), (2, "work@example.com")] {
+            Mock::given(method("POST"))
+                .and(path("/api/auth/login"))
+                .and(body_partial_json(serde_json::json!({ "email": email })))
+                .respond_with(ResponseTemplate::new(200).set_body_json(auth_response(id, email)))
+                .mount(&mock_server)
+                .await;
+        }
+        Mock::given(method("POST"))
+            .and(path("/api/auth/refresh"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(auth_response(2, "work@example.com")),
+            )
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/auth/logout"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+
+        let temp = tempdir().unwrap();
+        let time_provider = Arc::new(MockTimeProvider::from_timestamp(1704067200));
+        let service = AuthService::with_client_for_testing(
+            temp.path().to_path_buf(),
+            mock_server.uri(),
+            Client::new(),
+            time_provider.clone(),
+        );
+
+        service
+            .login("personal@example.com", "password")
+            .await
+            .unwrap();
+        service.login("work@example.com", "password").await.unwrap();
+        let accounts = service.list_accounts();
+        assert_eq!(accounts.len(), 2);
+        assert!(accounts[1].active && accounts[1].user.id == 2);
+
+        // Switching back uses the cached token, without a refresh
+        let user = service.switch_account(1).await.unwrap();
+        assert_eq!(user.email, "personal@example.com");
+        assert_eq!(service.get_access_token().await.as_deref(), Some("token_1"));
+        assert!(service.list_accounts()[0].active);
+
+        // Once the cached token has expired, switching refreshes it
+        time_provider.advance_secs(4000);
+        let user = service.switch_account(2).await.unwrap();
+        assert_eq!(user.id, 2);
+        assert!(service.is_authenticated());
+
+        let err = service.switch_account(99).await.unwrap_err();
+        assert_eq!(err.code, "NOT_FOUND");
+
+        // Signing out removes only the active account
+        service.logout().await.unwrap();
+        let accounts = service.list_accounts();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].user.id, 1);
+        assert!(!accounts[0].active);
+    }
+
+    #[test]
+    fn test_offline_key_replaces_truncated_file() {
+        let temp = tempdir().unwrap();
+        let service = AuthService::with_client_for_testing(
+            temp.path().to_path_buf(),
+            "http://127.0.0.1:1".to_string(),
+            Client::new(),
+            Arc::new(MockTimeProvider::from_timestamp(1704067200)),
+        );
+        let key_path = temp.path().join("offline.key");
+
+        // Left behind by a crash while the key was being written
+        std::fs::write(&key_path, b"").unwrap();
+        let key = service.offline_key().unwrap();
+        let bytes = std::fs::read(&key_path).unwrap();
+        assert_eq!(bytes.len(), OFFLINE_KEY_LEN);
+
+        // The replacement is kept from then on
+        let signature = hmac::sign(&key, b"payload");
+        let again = service.offline_key().unwrap();
+        assert!(hmac::verify(&again, b"payload", signature.as_ref()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_offline_grace() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/auth/login"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock_auth_response()))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/user/subscription"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "subscription": { "tier": "pro", "status": "active" }
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let temp = tempdir().unwrap();
+        let time_provider = Arc::new(MockTimeProvider::from_timestamp(1704067200));
+        let online = AuthService::with_client_for_testing(
+            temp.path().to_path_buf(),
+            mock_server.uri(),
+            Client::new(),
+            time_provider.clone(),
+        );
+        online.login("test@example.com", "password").await.unwrap();
+        online.get_subscription().await.unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let key = std::fs::metadata(temp.path().join("offline.key")).unwrap();
+            assert_eq!(key.permissions().mode() & 0o777, 0o600);
+        }
+
+        // Next launch can't reach the server, but stays signed in
+        let offline_client = || {
+            Client::builder()
+                .timeout(std::time::Duration::from_millis(100))
+                .build()
+                .unwrap()
+        };
+        let service = AuthService::with_client_for_testing(
+            temp.path().to_path_buf(),
+            "http://127.0.0.1:1".to_string(),
+            offline_client(),
+            time_provider.clone(),
+        );
+        assert_eq!(service.init().await.unwrap(), AuthState::Authenticated);
+        assert!(service.is_offline());
+        assert_eq!(service.get_user().unwrap().email, "test@example.com");
+        assert_eq!(service.get_subscription().await.unwrap().tier, "pro");
+        assert_eq!(service.get_quota().await.unwrap_err().code, "NETWORK_ERROR");
+
+        // A tampered cache isn't trusted
+        let cache_path = temp.path().join("offline.json");
+        let content = std::fs::read_to_string(&cache_path).unwrap();
+        std::fs::write(&cache_path, content.replace("pro", "max")).unwrap();
+        let tampered = AuthService::with_client_for_testing(
+            temp.path().to_path_buf(),
+            "http://127.0.0.1:1".to_string(),
+            offline_client(),
+            time_provider.clone(),
+        );
+        assert_eq!(tampered.init().await.unwrap(), AuthState::Unauthenticated);
+        std::fs::write(&cache_path, content).unwrap();
+
+        // Still offline when the grace period runs out: signed out
+        time_provider.advance_secs(OFFLINE_GRACE_SECS as u64 - 1);
+        assert!(service.revalidate().await.is_err());
+        assert!(service.is_authenticated());
+        time_provider.advance_secs(1);
+        assert!(service.revalidate().await.is_err());
+        assert!(!service.is_authenticated());
+    }
+
     #[tokio::test]
     async fn test_get_oauth_url() {
         let time_provider = Arc::new(MockTimeProvider::from_timestamp(1704067200));
@@ -1078,14 +1774,52 @@ mod tests {
             time_provider,
         );
 
-        let url = service.get_oauth_url(None);
-        assert_eq!(url, "https://midlight.ai/api/auth/google?desktop=true");
-
-        let url_with_port = service.get_oauth_url(Some(8080));
+        let url = service.get_oauth_url("midlight://auth/callback", "n0nce", None);
         assert_eq!(
-            url_with_port,
-            "https://midlight.ai/api/auth/google?desktop=true&callback_port=8080"
+            url,
+            "https://midlight.ai/api/auth/google?desktop=true\
+             &redirect_uri=midlight%3A%2F%2Fauth%2Fcallback&state=n0nce"
         );
+
+        let url_with_port = service.get_oauth_url("midlight://auth/callback", "n0nce", Some(8080));
+        assert!(url_with_port.ends_with("&state=n0nce&callback_port=8080"));
+    }
+
+    #[tokio::test]
+    async fn test_oauth_state() {
+        let time_provider = Arc::new(MockTimeProvider::from_timestamp(1704067200));
+        let temp = tempdir().unwrap();
+        let service = AuthService::with_time_provider(
+            temp.path().to_path_buf(),
+            Some("https://midlight.ai".to_string()),
+            time_provider.clone(),
+        );
+
+        // Callbacks with no sign-in in progress are refused
+        assert!(service.verify_oauth_state(Some("anything")).is_err());
+
+        let url = service
+            .begin_oauth_login("midlight://auth/callback")
+            .unwrap();
+        let state = url.split("state=").nth(1).unwrap().to_string();
+        assert!(state.len() >= 32);
+        assert!(service.verify_oauth_state(None).is_err());
+        assert!(service.verify_oauth_state(Some("forged")).is_err());
+        assert!(service.verify_oauth_state(Some(&state)).is_ok());
+        // Each sign-in is good for one callback
+        assert!(service.verify_oauth_state(Some(&state)).is_err());
+
+        // A new sign-in replaces the old nonce, and expires
+        service
+            .begin_oauth_login("midlight://auth/callback")
+            .unwrap();
+        assert!(service.verify_oauth_state(Some(&state)).is_err());
+        let url = service
+            .begin_oauth_login("midlight://auth/callback")
+            .unwrap();
+        let state = url.split("state=").nth(1).unwrap().to_string();
+        time_provider.advance_secs(OAUTH_LOGIN_TIMEOUT_SECS as u64 + 1);
+        assert!(service.verify_oauth_state(Some(&state)).is_err());
     }
 
     #[tokio::test]
@@ -2428,7 +3162,7 @@ mod tests {
         let service = AuthService::new(temp.path().to_path_buf(), None);
 
         // The service uses DEFAULT_BASE_URL when None is passed
-        let oauth_url = service.get_oauth_url(None);
+        let oauth_url = service.get_oauth_url("midlight://auth/callback", "state", None);
         assert!(oauth_url.starts_with("https://midlight.ai"));
     }
 
@@ -0,0 +1,889 @@
+// Automations - Rules that act on workspace events
+//
+// A workspace can define rules that run actions when something happens in
+// it: a document is saved, a tag is added to a document, or an import
+// finishes. Conditions narrow a rule to documents matching a path glob (see
+// path_glob) or to one tag. Rules are evaluated here; the commands layer
+// feeds in events and carries out the actions, and a dry run shows which
+// rules an event would fire and what their actions would do.
+//
+// Rules are stored at: .midlight/automations.json
+// Format:
+// {
+//   "version": 1,
+//   "rules": [
+//     {
+//       "id": "archive-done",
+//       "name": "Archive finished projects",
+//       "enabled": true,
+//       "trigger": { "event": "tag_added", "tag": "done", "path": "Projects/**" },
+//       "actions": [
+//         { "type": "move", "to": "Archive" },
+//         { "type": "webhook", "url": "https://example.com/hooks/done" },
+//         { "type": "template", "template": "Templates/Retro.midlight", "path": "Retros/{{name}}" }
+//       ]
+//     }
+//   ]
+// }
+//
+// Actions:
+//   webhook   POST the event as JSON to a URL, with optional extra headers
+//   move      move the document into a folder, keeping its name
+//   template  create a document from a template document (see periodic_notes)
+//
+// Action fields can use {{path}}, {{name}} (file name without extension),
+// {{folder}}, {{tag}} and {{date}} (YYYY-MM-DD). For import_finished events
+// the path is the folder imported into.
+//
+// Rules do nothing until the user enables them on this machine. Like plugin
+// grants, the approval is kept in the user's app data, keyed by the
+// workspace's canonical path, and records a hash of the rules file. A
+// workspace someone else shared, or rules changed outside the app, stay off
+// until enabled again. Saving rules from the app keeps an existing approval.
+//
+// Approvals are stored at: <app data>/automation-approvals.json
+
+use crate::services::app_dirs::app_data_dir;
+use crate::services::atomic_write::write_atomic;
+use crate::services::path_glob::PathGlob;
+use crate::services::periodic_notes::{create_new, document_from_template};
+use crate::traits::http_client::HttpClient;
+use chrono::{NaiveDate, Utc};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use tracing::warn;
+
+const RULES_VERSION: u32 = 1;
+
+lazy_static! {
+    static ref VARIABLE: Regex = Regex::new(r"\{\{\s*(\w+)\s*\}\}").unwrap();
+}
+
+const VARIABLES: &[&str] = &["path", "name", "folder", "tag", "date"];
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    DocumentSaved,
+    TagAdded,
+    ImportFinished,
+}
+
+impl std::fmt::Display for EventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EventKind::DocumentSaved => write!(f, "document_saved"),
+            EventKind::TagAdded => write!(f, "tag_added"),
+            EventKind::ImportFinished => write!(f, "import_finished"),
+        }
+    }
+}
+
+/// Something that happened in a workspace. Paths are relative to its root.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AutomationEvent {
+    DocumentSaved { path: String },
+    TagAdded { path: String, tag: String },
+    ImportFinished { folder: String, documents: usize },
+}
+
+impl AutomationEvent {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            AutomationEvent::DocumentSaved { .. } => EventKind::DocumentSaved,
+            AutomationEvent::TagAdded { .. } => EventKind::TagAdded,
+            AutomationEvent::ImportFinished { .. } => EventKind::ImportFinished,
+        }
+    }
+
+    /// The document, or for imports the folder imported into
+    pub fn path(&self) -> &str {
+        match self {
+            AutomationEvent::DocumentSaved { path } | AutomationEvent::TagAdded { path, .. } => {
+                path
+            }
+            AutomationEvent::ImportFinished { folder, .. } => folder,
+        }
+    }
+
+    fn tag(&self) -> Option<&str> {
+        match self {
+            AutomationEvent::TagAdded { tag, .. } => Some(tag),
+            _ => None,
+        }
+    }
+
+    fn variables(&self, date: NaiveDate) -> HashMap<&'static str, String> {
+        let path = Path::new(self.path());
+        let name = match self.kind() {
+            EventKind::ImportFinished => path.file_name(),
+            _ => path.file_stem(),
+        };
+        let folder = match self.kind() {
+            EventKind::ImportFinished => Some(self.path().to_string()),
+            _ => path
+                .parent()
+                .map(|parent| parent.to_string_lossy().replace('\\', "/")),
+        };
+
+        HashMap::from([
+            ("path", self.path().to_string()),
+            (
+                "name",
+                name.map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+            ),
+            ("folder", folder.unwrap_or_default()),
+            ("tag", self.tag().unwrap_or_default().to_string()),
+            ("date", date.format("%Y-%m-%d").to_string()),
+        ])
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutomationTrigger {
+    pub event: EventKind,
+    /// Glob the document path must match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Tag that must have been added, for tag_added rules
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AutomationAction {
+    Webhook {
+        url: String,
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        headers: HashMap<String, String>,
+    },
+    /// Move the document into a folder
+    Move { to: String },
+    /// Create a document at `path` from a template document
+    Template { template: String, path: String },
+}
+
+impl AutomationAction {
+    /// The action with variables in its fields filled in
+    fn expand(&self, variables: &HashMap<&'static str, String>) -> Self {
+        let fill = |text: &str| {
+            VARIABLE
+                .replace_all(text, |caps: &Captures| {
+                    variables.get(&caps[1]).cloned().unwrap_or_default()
+                })
+                .into_owned()
+        };
+        match self {
+            AutomationAction::Webhook { url, headers } => AutomationAction::Webhook {
+                url: fill(url),
+                headers: headers.iter().map(|(k, v)| (k.clone(), fill(v))).collect(),
+            },
+            AutomationAction::Move { to } => AutomationAction::Move { to: fill(to) },
+            AutomationAction::Template { template, path } => AutomationAction::Template {
+                template: fill(template),
+                path: fill(path),
+            },
+        }
+    }
+
+    fn fields(&self) -> Vec<&str> {
+        match self {
+            AutomationAction::Webhook { url, headers } => std::iter::once(url.as_str())
+                .chain(headers.values().map(String::as_str))
+                .collect(),
+            AutomationAction::Move { to } => vec![to],
+            AutomationAction::Template { template, path } => vec![template, path],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutomationRule {
+    pub id: String,
+    pub name: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub trigger: AutomationTrigger,
+    pub actions: Vec<AutomationAction>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// What a rule would do for an event
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleTrace {
+    pub rule_id: String,
+    pub name: String,
+    pub matched: bool,
+    /// Why the rule doesn't fire
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// The rule's actions with variables filled in, when it fires
+    pub actions: Vec<AutomationAction>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RulesFile {
+    version: u32,
+    rules: Vec<AutomationRule>,
+}
+
+// ============================================================================
+// Rule Store
+// ============================================================================
+
+/// Path of the user's automation approvals
+pub fn automation_approvals_path() -> PathBuf {
+    app_data_dir().join("automation-approvals.json")
+}
+
+/// Hash of the approved rules file, by canonical workspace path
+type ApprovalsFile = HashMap<String, String>;
+
+pub struct AutomationStore {
+    rules_path: PathBuf,
+    approvals_path: PathBuf,
+    /// The workspace's canonical path
+    workspace: String,
+}
+
+impl AutomationStore {
+    pub fn new(workspace_root: &Path) -> Self {
+        let workspace = workspace_root
+            .canonicalize()
+            .unwrap_or_else(|_| workspace_root.to_path_buf());
+        Self {
+            rules_path: workspace_root.join(".midlight").join("automations.json"),
+            approvals_path: automation_approvals_path(),
+            workspace: workspace.to_string_lossy().to_string(),
+        }
+    }
+
+    /// Keep approvals in a different file
+    #[cfg(test)]
+    pub fn with_approvals_path(mut self, approvals_path: &Path) -> Self {
+        self.approvals_path = approvals_path.to_path_buf();
+        self
+    }
+
+    /// Whether the workspace has a rules file at all
+    pub fn is_configured(&self) -> bool {
+        self.rules_path.exists()
+    }
+
+    pub fn load(&self) -> Result<Vec<AutomationRule>, String> {
+        match self.read_rules()? {
+            Some(content) => parse_rules(&content),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// The rules, if the user has enabled them as they are now on this
+    /// machine. Nothing should run otherwise.
+    pub fn load_enabled(&self) -> Result<Option<Vec<AutomationRule>>, String> {
+        match self.read_rules()? {
+            Some(content) if self.is_approved(&content) => parse_rules(&content).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    pub fn save(&self, rules: Vec<AutomationRule>) -> Result<(), String> {
+        validate_rules(&rules)?;
+        let json = serde_json::to_string_pretty(&RulesFile {
+            version: RULES_VERSION,
+            rules,
+        })
+        .map_err(|e| format!("Failed to serialize automations: {}", e))?;
+
+        let was_enabled = self.is_enabled()?;
+        write_atomic(&self.rules_path, json)
+            .map_err(|e| format!("Failed to write automations: {}", e))?;
+        if was_enabled {
+            self.enable()?;
+        }
+        Ok(())
+    }
+
+    /// Whether the current rules are enabled on this machine
+    pub fn is_enabled(&self) -> Result<bool, String> {
+        Ok(self
+            .read_rules()?
+            .is_some_and(|content| self.is_approved(&content)))
+    }
+
+    /// Let the workspace's rules, as they are now, run on this machine
+    pub fn enable(&self) -> Result<(), String> {
+        let content = self
+            .read_rules()?
+            .ok_or("The workspace has no automation rules")?;
+        parse_rules(&content)?;
+
+        let mut approvals = self.read_approvals();
+        approvals.insert(self.workspace.clone(), rules_hash(&content));
+        self.write_approvals(&approvals)
+    }
+
+    pub fn disable(&self) -> Result<(), String> {
+        let mut approvals = self.read_approvals();
+        if approvals.remove(&self.workspace).is_some() {
+            self.write_approvals(&approvals)?;
+        }
+        Ok(())
+    }
+
+    fn read_rules(&self) -> Result<Option<Vec<u8>>, String> {
+        if !self.rules_path.exists() {
+            return Ok(None);
+        }
+        fs::read(&self.rules_path)
+            .map(Some)
+            .map_err(|e| format!("Failed to read automations: {}", e))
+    }
+
+    fn is_approved(&self, content: &[u8]) -> bool {
+        self.read_approvals().get(&self.workspace) == Some(&rules_hash(content))
+    }
+
+    fn read_approvals(&self) -> ApprovalsFile {
+        if !self.approvals_path.exists() {
+            return ApprovalsFile::new();
+        }
+
+        fs::read_to_string(&self.approvals_path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| {
+                warn!("Failed to load automation approvals: {}", e);
+                ApprovalsFile::new()
+            })
+    }
+
+    fn write_approvals(&self, approvals: &ApprovalsFile) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(approvals)
+            .map_err(|e| format!("Failed to serialize automation approvals: {}", e))?;
+        write_atomic(&self.approvals_path, json)
+            .map_err(|e| format!("Failed to save automation approvals: {}", e))
+    }
+}
+
+fn parse_rules(content: &[u8]) -> Result<Vec<AutomationRule>, String> {
+    let file: RulesFile = serde_json::from_slice(content)
+        .map_err(|e| format!("Failed to parse automations: {}", e))?;
+    validate_rules(&file.rules)?;
+    Ok(file.rules)
+}
+
+fn rules_hash(content: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(content);
+    format!("{:x}", hasher.finalize())
+}
+
+fn validate_rules(rules: &[AutomationRule]) -> Result<(), String> {
+    let mut ids = HashSet::new();
+    for rule in rules {
+        if rule.id.trim().is_empty() {
+            return Err(format!("Rule '{}' has no id", rule.name));
+        }
+        if !ids.insert(rule.id.as_str()) {
+            return Err(format!("Duplicate rule id: {}", rule.id));
+        }
+        if rule.actions.is_empty() {
+            return Err(format!("Rule '{}' has no actions", rule.id));
+        }
+        if let Some(glob) = &rule.trigger.path {
+            PathGlob::new(glob).map_err(|e| format!("Rule '{}': {}", rule.id, e))?;
+        }
+        if rule.trigger.tag.is_some() && rule.trigger.event != EventKind::TagAdded {
+            return Err(format!(
+                "Rule '{}': only tag_added rules can have a tag",
+                rule.id
+            ));
+        }
+
+        for action in &rule.actions {
+            for field in action.fields() {
+                for caps in VARIABLE.captures_iter(field) {
+                    if !VARIABLES.contains(&&caps[1]) {
+                        return Err(format!("Rule '{}': unknown variable {}", rule.id, &caps[0]));
+                    }
+                }
+            }
+            match action {
+                AutomationAction::Webhook { url, .. } if !is_http_url(url) => {
+                    return Err(format!(
+                        "Rule '{}': webhook URL must start with http:// or https://",
+                        rule.id
+                    ));
+                }
+                AutomationAction::Move { .. } | AutomationAction::Template { .. }
+                    if rule.trigger.event == EventKind::ImportFinished =>
+                {
+                    return Err(format!(
+                        "Rule '{}': import_finished rules can only call webhooks",
+                        rule.id
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+fn is_http_url(url: &str) -> bool {
+    let url = url.trim().to_ascii_lowercase();
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+// ============================================================================
+// Evaluation
+// ============================================================================
+
+/// Check every rule against an event
+pub fn evaluate(
+    rules: &[AutomationRule],
+    event: &AutomationEvent,
+    date: NaiveDate,
+) -> Vec<RuleTrace> {
+    let variables = event.variables(date);
+    rules
+        .iter()
+        .map(|rule| {
+            let reason = mismatch(rule, event);
+            let actions = match reason {
+                None => rule
+                    .actions
+                    .iter()
+                    .map(|action| action.expand(&variables))
+                    .collect(),
+                Some(_) => Vec::new(),
+            };
+            RuleTrace {
+                rule_id: rule.id.clone(),
+                name: rule.name.clone(),
+                matched: reason.is_none(),
+                reason,
+                actions,
+            }
+        })
+        .collect()
+}
+
+/// Why a rule doesn't fire for an event, or None if it does
+fn mismatch(rule: &AutomationRule, event: &AutomationEvent) -> Option<String> {
+    if !rule.enabled {
+        return Some("Rule is disabled".to_string());
+    }
+    if rule.trigger.event != event.kind() {
+        return Some(format!(
+            "Waits for {}, not {}",
+            rule.trigger.event,
+            event.kind()
+        ));
+    }
+    if let Some(glob) = &rule.trigger.path {
+        let matches = PathGlob::new(glob)
+            .map(|glob| glob.is_match(event.path()))
+            .unwrap_or(false);
+        if !matches {
+            return Some(format!("{} doesn't match {}", event.path(), glob));
+        }
+    }
+    if let Some(tag) = &rule.trigger.tag {
+        let wanted = tag.trim_start_matches('#');
+        if !event
+            .tag()
+            .is_some_and(|added| added.eq_ignore_ascii_case(wanted))
+        {
+            return Some(format!("Tag added isn't #{}", wanted));
+        }
+    }
+    None
+}
+
+// ============================================================================
+// Actions
+// ============================================================================
+
+/// POST the event to a webhook
+pub async fn send_webhook<C: HttpClient>(
+    client: &C,
+    url: &str,
+    headers: &HashMap<String, String>,
+    rule_id: &str,
+    workspace_root: &str,
+    event: &AutomationEvent,
+) -> Result<(), String> {
+    let payload = json!({
+        "rule": rule_id,
+        "workspace": workspace_root,
+        "event": event,
+        "firedAt": Utc::now().to_rfc3339(),
+    });
+    let response = client
+        .post_json_with_headers(url, &payload, headers)
+        .await
+        .map_err(|e| format!("Failed to call webhook: {}", e))?;
+    if response.is_success() {
+        Ok(())
+    } else {
+        Err(format!("Webhook returned HTTP {}", response.status))
+    }
+}
+
+/// Where a move action puts a document, relative to the workspace root
+pub fn move_destination(document: &str, folder: &str) -> Result<String, String> {
+    let folder = workspace_relative(folder)?;
+    let name = Path::new(document)
+        .file_name()
+        .ok_or_else(|| format!("Not a document: {}", document))?;
+    Ok(Path::new(&folder)
+        .join(name)
+        .to_string_lossy()
+        .replace('\\', "/"))
+}
+
+/// Create a document from a template, returning its path relative to the
+/// workspace root. Existing documents are never overwritten.
+pub fn create_from_template(
+    workspace_root: &Path,
+    template: &str,
+    path: &str,
+    date: NaiveDate,
+) -> Result<String, String> {
+    let mut relative = workspace_relative(path)?;
+    if !relative.ends_with(".midlight") {
+        relative.push_str(".midlight");
+    }
+    let title = Path::new(&relative)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let document = document_from_template(workspace_root, template, &title, date)?;
+    if create_new(&workspace_root.join(&relative), &document)? {
+        Ok(relative)
+    } else {
+        Err(format!("{} already exists", relative))
+    }
+}
+
+/// A path inside the workspace, without `..` or hidden folders
+fn workspace_relative(path: &str) -> Result<String, String> {
+    let path = path.trim().replace('\\', "/");
+    let path = path.trim_matches('/');
+    let inside = !path.is_empty()
+        && Path::new(path)
+            .components()
+            .all(|component| match component {
+                Component::Normal(name) => !name.to_string_lossy().starts_with('.'),
+                _ => false,
+            });
+    if inside {
+        Ok(path.to_string())
+    } else {
+        Err(format!("Path must be inside the workspace: {}", path))
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::http_client::HttpResponse;
+    use crate::traits::MockHttpClient;
+    use tempfile::TempDir;
+
+    fn date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, 14).unwrap()
+    }
+
+    fn rule(
+        id: &str,
+        trigger: AutomationTrigger,
+        actions: Vec<AutomationAction>,
+    ) -> AutomationRule {
+        AutomationRule {
+            id: id.to_string(),
+            name: id.to_string(),
+            enabled: true,
+            trigger,
+            actions,
+        }
+    }
+
+    fn archive_rule() -> AutomationRule {
+        rule(
+            "archive",
+            AutomationTrigger {
+                event: EventKind::TagAdded,
+                path: Some("Projects/**".to_string()),
+                tag: Some("#done".to_string()),
+            },
+            vec![
+                AutomationAction::Move {
+                    to: "Archive/{{date}}".to_string(),
+                },
+                AutomationAction::Template {
+                    template: "Templates/Retro.midlight".to_string(),
+                    path: "Retros/{{name}}".to_string(),
+                },
+            ],
+        )
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let webhook = rule(
+            "notify",
+            AutomationTrigger {
+                event: EventKind::DocumentSaved,
+                path: None,
+                tag: None,
+            },
+            vec![AutomationAction::Webhook {
+                url: "https://example.com/saved?doc={{path}}".to_string(),
+                headers: HashMap::new(),
+            }],
+        );
+        let rules = vec![archive_rule(), webhook];
+
+        let event = AutomationEvent::TagAdded {
+            path: "Projects/Alpha/Plan.midlight".to_string(),
+            tag: "Done".to_string(),
+        };
+        let traces = evaluate(&rules, &event, date());
+        assert!(traces[0].matched);
+        assert_eq!(
+            traces[0].actions,
+            vec![
+                AutomationAction::Move {
+                    to: "Archive/2026-03-14".to_string()
+                },
+                AutomationAction::Template {
+                    template: "Templates/Retro.midlight".to_string(),
+                    path: "Retros/Plan".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            traces[1].reason.as_deref(),
+            Some("Waits for document_saved, not tag_added")
+        );
+
+        let other_tag = AutomationEvent::TagAdded {
+            path: "Projects/Alpha/Plan.midlight".to_string(),
+            tag: "draft".to_string(),
+        };
+        assert_eq!(
+            evaluate(&rules, &other_tag, date())[0].reason.as_deref(),
+            Some("Tag added isn't #done")
+        );
+
+        let outside = AutomationEvent::TagAdded {
+            path: "Notes/Plan.midlight".to_string(),
+            tag: "done".to_string(),
+        };
+        assert!(!evaluate(&rules, &outside, date())[0].matched);
+
+        let saved = AutomationEvent::DocumentSaved {
+            path: "Notes/a b.midlight".to_string(),
+        };
+        let traces = evaluate(&rules, &saved, date());
+        assert!(traces[1].matched);
+        assert_eq!(
+            traces[1].actions,
+            vec![AutomationAction::Webhook {
+                url: "https://example.com/saved?doc=Notes/a b.midlight".to_string(),
+                headers: HashMap::new(),
+            }]
+        );
+
+        let mut disabled = rules.clone();
+        disabled[1].enabled = false;
+        assert_eq!(
+            evaluate(&disabled, &saved, date())[1].reason.as_deref(),
+            Some("Rule is disabled")
+        );
+    }
+
+    #[test]
+    fn test_store_validation() {
+        let temp = TempDir::new().unwrap();
+        let store = AutomationStore::new(temp.path())
+            .with_approvals_path(&temp.path().join("approvals.json"));
+        assert!(!store.is_configured());
+        assert!(store.load().unwrap().is_empty());
+
+        store.save(vec![archive_rule()]).unwrap();
+        assert!(store.is_configured());
+        assert_eq!(store.load().unwrap(), vec![archive_rule()]);
+
+        let invalid = |change: fn(&mut AutomationRule)| {
+            let mut rule = archive_rule();
+            change(&mut rule);
+            store.save(vec![rule]).is_err()
+        };
+        assert!(invalid(|r| r.actions.clear()));
+        assert!(invalid(|r| r.trigger.event = EventKind::DocumentSaved));
+        assert!(invalid(|r| r.actions[0] = AutomationAction::Move {
+            to: "{{title}}".to_string()
+        }));
+        assert!(invalid(|r| r.actions[0] = AutomationAction::Webhook {
+            url: "file:///etc/passwd".to_string(),
+            headers: HashMap::new(),
+        }));
+        assert!(store
+            .save(vec![archive_rule(), archive_rule()])
+            .unwrap_err()
+            .contains("Duplicate"));
+    }
+
+    #[test]
+    fn test_rules_run_only_once_enabled() {
+        let workspace = TempDir::new().unwrap();
+        let app_data = TempDir::new().unwrap();
+        let approvals = app_data.path().join("automation-approvals.json");
+        let store = AutomationStore::new(workspace.path()).with_approvals_path(&approvals);
+        assert!(store.enable().is_err());
+
+        // Rules that arrive with the workspace don't run
+        store.save(vec![archive_rule()]).unwrap();
+        assert!(!store.is_enabled().unwrap());
+        assert_eq!(store.load_enabled().unwrap(), None);
+
+        store.enable().unwrap();
+        assert_eq!(store.load_enabled().unwrap(), Some(vec![archive_rule()]));
+        // Approvals stay with the user, not the workspace
+        assert!(approvals.exists());
+        assert!(!fs::read_dir(workspace.path().join(".midlight"))
+            .unwrap()
+            .any(|entry| entry.unwrap().file_name() != "automations.json"));
+
+        // Saving from the app keeps the approval
+        let mut renamed = archive_rule();
+        renamed.name = "Archive".to_string();
+        store.save(vec![renamed.clone()]).unwrap();
+        assert_eq!(store.load_enabled().unwrap(), Some(vec![renamed]));
+
+        // Changing the file on disk resets it
+        let rules_path = workspace.path().join(".midlight").join("automations.json");
+        let edited = fs::read_to_string(&rules_path)
+            .unwrap()
+            .replace("Archive", "Elsewhere");
+        fs::write(&rules_path, edited).unwrap();
+        assert!(!store.is_enabled().unwrap());
+        assert_eq!(store.load_enabled().unwrap(), None);
+
+        store.enable().unwrap();
+        store.disable().unwrap();
+        assert_eq!(store.load_enabled().unwrap(), None);
+    }
+
+    #[test]
+    fn test_move_and_template_actions() {
+        assert_eq!(
+            move_destination("Projects/Plan.midlight", "/Archive/2026/").unwrap(),
+            "Archive/2026/Plan.midlight"
+        );
+        assert!(move_destination("Plan.midlight", "../outside").is_err());
+
+        let temp = TempDir::new().unwrap();
+        let template = serde_json::json!({
+            "version": 1,
+            "content": {
+                "type": "doc",
+                "content": [{ "type": "paragraph", "content": [{ "type": "text", "text": "Retro for {{title}} on {{date}}" }] }]
+            }
+        });
+        fs::create_dir_all(temp.path().join("Templates")).unwrap();
+        fs::write(
+            temp.path().join("Templates/Retro.midlight"),
+            template.to_string(),
+        )
+        .unwrap();
+
+        let path = create_from_template(
+            temp.path(),
+            "Templates/Retro.midlight",
+            "Retros/Plan",
+            date(),
+        )
+        .unwrap();
+        assert_eq!(path, "Retros/Plan.midlight");
+        let content = fs::read_to_string(temp.path().join(&path)).unwrap();
+        assert!(content.contains("Retro for Plan on 2026-03-14"));
+
+        assert!(create_from_template(
+            temp.path(),
+            "Templates/Retro.midlight",
+            "Retros/Plan",
+            date()
+        )
+        .unwrap_err()
+        .contains("already exists"));
+    }
+
+    #[tokio::test]
+    async fn test_send_webhook() {
+        let client = MockHttpClient::new()
+            .queue_response(HttpResponse::new(204, ""))
+            .queue_response(HttpResponse::new(500, "boom"));
+        let event = AutomationEvent::ImportFinished {
+            folder: "Imported".to_string(),
+            documents: 12,
+        };
+        let headers = HashMap::from([("X-Token".to_string(), "secret".to_string())]);
+
+        send_webhook(
+            &client,
+            "https://example.com/hook",
+            &headers,
+            "imports",
+            "/ws",
+            &event,
+        )
+        .await
+        .unwrap();
+        let request = client.last_request().unwrap();
+        assert_eq!(request.headers.get("X-Token").unwrap(), "secret");
+        let body: serde_json::Value = serde_json::from_str(&request.body.unwrap()).unwrap();
+        assert_eq!(body["rule"], "imports");
+        assert_eq!(body["event"]["event"], "import_finished");
+        assert_eq!(body["event"]["documents"], 12);
+
+        let error = send_webhook(
+            &client,
+            "https://example.com/hook",
+            &headers,
+            "imports",
+            "/ws",
+            &event,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error, "Webhook returned HTTP 500");
+    }
+}
@@ -0,0 +1,503 @@
+// Change Staging - Agent changes awaiting user confirmation
+//
+// When confirmation mode is on, agent tools that modify documents stage a
+// PendingChange here instead of touching the file. The user reviews a line
+// diff of each change and approves (the change is applied) or rejects it
+// (the change is discarded). Edits record a hash of the file they were based
+// on and refuse to apply if the document changed in the meantime.
+//
+// Storage: <workspace>/.midlight/pending-changes.json
+
+use crate::services::agent_executor::{ChangeKind, PendingChange};
+use crate::services::document_lock::ensure_unlocked;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use xxhash_rust::xxh64::xxh64;
+
+const PENDING_CHANGES_VERSION: u32 = 1;
+
+/// Above this many line comparisons the diff falls back to remove-all/add-all
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PendingChangesFile {
+    version: u32,
+    changes: Vec<PendingChange>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffOp {
+    Equal,
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffLine {
+    pub op: DiffOp,
+    pub text: String,
+}
+
+/// A run of changed lines with surrounding unchanged lines. Line numbers are
+/// 1-based.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffHunk {
+    pub old_start: usize,
+    pub new_start: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+/// A pending change with its diff, for review
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingChangeReview {
+    #[serde(flatten)]
+    pub change: PendingChange,
+    pub diff: Vec<DiffLine>,
+}
+
+/// An approved change and the file content it replaced
+#[derive(Debug, Clone)]
+pub struct AppliedChange {
+    pub change: PendingChange,
+    pub previous_content: Option<String>,
+    pub written_content: Option<String>,
+}
+
+/// Whether a pending change's path is a workspace-relative path or under it
+pub(crate) fn is_at_or_under(change_path: &str, path: &str) -> bool {
+    let change_path = change_path.trim_start_matches('/');
+    let path = path.trim_start_matches('/').trim_end_matches('/');
+    change_path == path || change_path.starts_with(&format!("{}/", path))
+}
+
+/// Hash of a document's content, used to detect edits made after staging
+pub fn content_hash(content: &str) -> String {
+    format!("{:016x}", xxh64(content.as_bytes(), 0))
+}
+
+// ============================================================================
+// Store
+// ============================================================================
+
+pub struct PendingChangeStore {
+    workspace_root: PathBuf,
+    changes_path: PathBuf,
+}
+
+impl PendingChangeStore {
+    pub fn new(workspace_root: &Path) -> Self {
+        Self {
+            workspace_root: workspace_root.to_path_buf(),
+            changes_path: workspace_root
+                .join(".midlight")
+                .join("pending-changes.json"),
+        }
+    }
+
+    /// List pending changes, oldest first, with diffs
+    pub fn list(&self) -> Result<Vec<PendingChangeReview>, String> {
+        Ok(self
+            .read()?
+            .changes
+            .into_iter()
+            .map(|change| {
+                let diff = line_diff(&change.original_content, &change.new_content);
+                PendingChangeReview { change, diff }
+            })
+            .collect())
+    }
+
+    /// Stage a change. A newer change to the same path replaces the older one.
+    pub fn stage(&self, change: PendingChange) -> Result<(), String> {
+        let mut file = self.read()?;
+        file.version = PENDING_CHANGES_VERSION;
+        file.changes.retain(|c| c.path != change.path);
+        file.changes.push(change);
+        self.write(&file)
+    }
+
+    /// Apply a pending change to the workspace and remove it from the queue
+    pub fn approve(&self, change_id: &str) -> Result<AppliedChange, String> {
+        let mut file = self.read()?;
+        let index = Self::position(&file, change_id)?;
+        let change = file.changes[index].clone();
+
+        let target = self
+            .workspace_root
+            .join(change.path.trim_start_matches('/'));
+        ensure_unlocked(&target, &change.path)?;
+        let previous_content = fs::read_to_string(&target).ok();
+        let written_content = match change.kind {
+            ChangeKind::Edit => {
+                let document = change
+                    .staged_document
+                    .as_ref()
+                    .ok_or_else(|| "Pending change has no staged document".to_string())?;
+
+                if let Some(ref base_hash) = change.base_hash {
+                    let current = previous_content
+                        .as_ref()
+                        .ok_or_else(|| format!("Failed to read document: {}", change.path))?;
+                    if content_hash(current) != *base_hash {
+                        return Err(format!(
+                            "{} was modified after the change was staged",
+                            change.path
+                        ));
+                    }
+                }
+
+                let json = serde_json::to_string_pretty(document)
+                    .map_err(|e| format!("Failed to serialize document: {}", e))?;
+                let temp_path = target.with_extension("midlight.tmp");
+                fs::write(&temp_path, &json)
+                    .map_err(|e| format!("Failed to write document: {}", e))?;
+                fs::rename(&temp_path, &target)
+                    .map_err(|e| format!("Failed to write document: {}", e))?;
+                Some(json)
+            }
+            ChangeKind::Delete => {
+                if target.exists() {
+                    trash::delete(&target)
+                        .map_err(|e| format!("Failed to delete document: {}", e))?;
+                }
+                None
+            }
+        };
+
+        file.changes.remove(index);
+        self.write(&file)?;
+        Ok(AppliedChange {
+            change,
+            previous_content,
+            written_content,
+        })
+    }
+
+    /// Discard the pending changes to a workspace-relative path or anything
+    /// under it, e.g. because it's being deleted
+    pub fn reject_under(&self, path: &str) -> Result<Vec<PendingChange>, String> {
+        let mut file = self.read()?;
+        let (rejected, kept): (Vec<_>, Vec<_>) = file
+            .changes
+            .into_iter()
+            .partition(|change| is_at_or_under(&change.path, path));
+        if !rejected.is_empty() {
+            file.changes = kept;
+            self.write(&file)?;
+        }
+        Ok(rejected)
+    }
+
+    /// Discard a pending change without applying it
+    pub fn reject(&self, change_id: &str) -> Result<PendingChange, String> {
+        let mut file = self.read()?;
+        let index = Self::position(&file, change_id)?;
+        let change = file.changes.remove(index);
+        self.write(&file)?;
+        Ok(change)
+    }
+
+    fn position(file: &PendingChangesFile, change_id: &str) -> Result<usize, String> {
+        file.changes
+            .iter()
+            .position(|c| c.change_id == change_id)
+            .ok_or_else(|| format!("Pending change not found: {}", change_id))
+    }
+
+    fn read(&self) -> Result<PendingChangesFile, String> {
+        if !self.changes_path.exists() {
+            return Ok(PendingChangesFile {
+                version: PENDING_CHANGES_VERSION,
+                changes: Vec::new(),
+            });
+        }
+
+        let content = fs::read_to_string(&self.changes_path)
+            .map_err(|e| format!("Failed to read pending changes: {}", e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse pending changes: {}", e))
+    }
+
+    fn write(&self, file: &PendingChangesFile) -> Result<(), String> {
+        if let Some(parent) = self.changes_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create .midlight directory: {}", e))?;
+        }
+
+        let json = serde_json::to_string_pretty(file)
+            .map_err(|e| format!("Failed to serialize pending changes: {}", e))?;
+
+        let temp_path = self.changes_path.with_extension("json.tmp");
+        fs::write(&temp_path, json)
+            .map_err(|e| format!("Failed to write pending changes: {}", e))?;
+        fs::rename(&temp_path, &self.changes_path)
+            .map_err(|e| format!("Failed to write pending changes: {}", e))
+    }
+}
+
+// ============================================================================
+// Diff
+// ============================================================================
+
+/// Line diff between two texts (longest common subsequence)
+pub fn line_diff(original: &str, new: &str) -> Vec<DiffLine> {
+    let old_lines: Vec<&str> = original.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+
+    let prefix = old_lines
+        .iter()
+        .zip(&new_lines)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old_lines[prefix..]
+        .iter()
+        .rev()
+        .zip(new_lines[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let old_mid = &old_lines[prefix..old_lines.len() - suffix];
+    let new_mid = &new_lines[prefix..new_lines.len() - suffix];
+
+    let line = |op, text: &str| DiffLine {
+        op,
+        text: text.to_string(),
+    };
+
+    let mut diff: Vec<DiffLine> = old_lines[..prefix]
+        .iter()
+        .map(|l| line(DiffOp::Equal, l))
+        .collect();
+
+    if old_mid.len().saturating_mul(new_mid.len()) > MAX_DIFF_CELLS {
+        diff.extend(old_mid.iter().map(|l| line(DiffOp::Removed, l)));
+        diff.extend(new_mid.iter().map(|l| line(DiffOp::Added, l)));
+    } else {
+        // lcs[i][j] = length of the LCS of old_mid[i..] and new_mid[j..]
+        let (n, m) = (old_mid.len(), new_mid.len());
+        let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i][j] = if old_mid[i] == new_mid[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        while i < n && j < m {
+            if old_mid[i] == new_mid[j] {
+                diff.push(line(DiffOp::Equal, old_mid[i]));
+                i += 1;
+                j += 1;
+            } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+                diff.push(line(DiffOp::Removed, old_mid[i]));
+                i += 1;
+            } else {
+                diff.push(line(DiffOp::Added, new_mid[j]));
+                j += 1;
+            }
+        }
+        diff.extend(old_mid[i..].iter().map(|l| line(DiffOp::Removed, l)));
+        diff.extend(new_mid[j..].iter().map(|l| line(DiffOp::Added, l)));
+    }
+
+    diff.extend(
+        old_lines[old_lines.len() - suffix..]
+            .iter()
+            .map(|l| line(DiffOp::Equal, l)),
+    );
+    diff
+}
+
+/// Group a line diff into hunks of changes with up to `context` unchanged
+/// lines on either side. Changes closer than twice the context share a hunk.
+pub fn diff_hunks(diff: &[DiffLine], context: usize) -> Vec<DiffHunk> {
+    // Line numbers in the old and new text before each diff line
+    let mut positions = Vec::with_capacity(diff.len());
+    let (mut old_line, mut new_line) = (1, 1);
+    for line in diff {
+        positions.push((old_line, new_line));
+        match line.op {
+            DiffOp::Equal => {
+                old_line += 1;
+                new_line += 1;
+            }
+            DiffOp::Removed => old_line += 1,
+            DiffOp::Added => new_line += 1,
+        }
+    }
+
+    let changed: Vec<usize> = diff
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| line.op != DiffOp::Equal)
+        .map(|(i, _)| i)
+        .collect();
+
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for i in changed {
+        let start = i.saturating_sub(context);
+        let end = (i + context + 1).min(diff.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    ranges
+        .into_iter()
+        .map(|(start, end)| DiffHunk {
+            old_start: positions[start].0,
+            new_start: positions[start].1,
+            lines: diff[start..end].to_vec(),
+        })
+        .collect()
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn edit(path: &str, base: &str, document: serde_json::Value) -> PendingChange {
+        PendingChange {
+            change_id: format!("change-{}", path),
+            path: path.to_string(),
+            kind: ChangeKind::Edit,
+            original_content: "old line".to_string(),
+            new_content: "new line".to_string(),
+            description: None,
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+            staged_document: Some(document),
+            base_hash: Some(content_hash(base)),
+            attribution: None,
+        }
+    }
+
+    #[test]
+    fn test_line_diff() {
+        let diff = line_diff("a\nb\nc\nd", "a\nc\nx\nd");
+        let ops: Vec<(DiffOp, &str)> = diff.iter().map(|l| (l.op, l.text.as_str())).collect();
+        assert_eq!(
+            ops,
+            vec![
+                (DiffOp::Equal, "a"),
+                (DiffOp::Removed, "b"),
+                (DiffOp::Equal, "c"),
+                (DiffOp::Added, "x"),
+                (DiffOp::Equal, "d"),
+            ]
+        );
+
+        let diff = line_diff("gone", "");
+        assert_eq!(
+            diff,
+            vec![DiffLine {
+                op: DiffOp::Removed,
+                text: "gone".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_diff_hunks() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12";
+        let new = "1\nTWO\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n13";
+        let hunks = diff_hunks(&line_diff(old, new), 2);
+        assert_eq!(hunks.len(), 2);
+        assert_eq!((hunks[0].old_start, hunks[0].new_start), (1, 1));
+        let texts: Vec<&str> = hunks[0].lines.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(texts, vec!["1", "2", "TWO", "3", "4"]);
+        assert_eq!((hunks[1].old_start, hunks[1].new_start), (11, 11));
+        assert_eq!(hunks[1].lines.last().unwrap().op, DiffOp::Added);
+
+        // Nearby changes share a hunk
+        assert_eq!(
+            diff_hunks(&line_diff("a\nb\nc\nd", "x\nb\nc\ny"), 1).len(),
+            1
+        );
+        assert!(diff_hunks(&line_diff("same", "same"), 3).is_empty());
+    }
+
+    #[test]
+    fn test_stage_list_and_reject() {
+        let temp = TempDir::new().unwrap();
+        let store = PendingChangeStore::new(temp.path());
+        assert!(store.list().unwrap().is_empty());
+
+        store.stage(edit("a.midlight", "", json!({}))).unwrap();
+        store.stage(edit("b.midlight", "", json!({}))).unwrap();
+        // Restaging a path replaces the older change
+        let mut newer = edit("a.midlight", "", json!({}));
+        newer.change_id = "newer".to_string();
+        store.stage(newer).unwrap();
+
+        let pending = store.list().unwrap();
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[1].change.change_id, "newer");
+        assert_eq!(pending[1].diff.len(), 2);
+
+        store.reject("newer").unwrap();
+        assert_eq!(store.list().unwrap().len(), 1);
+        assert!(store.reject("newer").is_err());
+    }
+
+    #[test]
+    fn test_approve_edit_writes_document() {
+        let temp = TempDir::new().unwrap();
+        let store = PendingChangeStore::new(temp.path());
+        let doc_path = temp.path().join("doc.midlight");
+        std::fs::write(&doc_path, "{\"content\": \"old\"}").unwrap();
+
+        store
+            .stage(edit(
+                "doc.midlight",
+                "{\"content\": \"old\"}",
+                json!({"content": "new"}),
+            ))
+            .unwrap();
+        let applied = store.approve("change-doc.midlight").unwrap();
+        assert_eq!(
+            applied.previous_content.as_deref(),
+            Some("{\"content\": \"old\"}")
+        );
+
+        let written: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&doc_path).unwrap()).unwrap();
+        assert_eq!(written, json!({"content": "new"}));
+        assert!(store.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_approve_edit_conflict() {
+        let temp = TempDir::new().unwrap();
+        let store = PendingChangeStore::new(temp.path());
+        std::fs::write(temp.path().join("doc.midlight"), "edited by user").unwrap();
+
+        store
+            .stage(edit("doc.midlight", "original", json!({"content": "new"})))
+            .unwrap();
+        let err = store.approve("change-doc.midlight").unwrap_err();
+        assert!(err.contains("modified after the change was staged"));
+
+        // The change stays pending so the user can reject it
+        assert_eq!(store.list().unwrap().len(), 1);
+    }
+}
@@ -26,6 +26,23 @@ pub struct Checkpoint {
     pub description: Option<String>,
     pub stats: CheckpointStats,
     pub trigger: String,
+    /// What made the change, for checkpoints around AI edits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attribution: Option<CheckpointAttribution>,
+}
+
+/// The AI conversation and tool behind a change to a document
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointAttribution {
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+    /// Agent tool that made the change, e.g. "edit_document"
+    #[serde(default)]
+    pub tool: Option<String>,
+    /// The agent change that was applied
+    #[serde(default)]
+    pub change_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,8 +62,44 @@ pub struct CheckpointHistory {
     #[serde(rename = "headId")]
     pub head_id: Option<String>,
     pub checkpoints: Vec<Checkpoint>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<DocumentVariant>,
+}
+
+/// A named copy of a document that's edited on its own, then merged back
+/// into the document or discarded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentVariant {
+    pub name: String,
+    /// The document's head checkpoint when the variant was created
+    #[serde(rename = "baseCheckpointId")]
+    pub base_checkpoint_id: Option<String>,
+    /// The document's content when the variant was created
+    #[serde(rename = "baseHash")]
+    pub base_hash: String,
+    #[serde(rename = "contentHash")]
+    pub content_hash: String,
+    pub created: String,
+    pub modified: String,
+}
+
+/// Checkpoints taken while a document was being worked on, with no long
+/// break between them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditingSession {
+    pub started: String,
+    pub ended: String,
+    pub duration_seconds: i64,
+    /// Words at the end of the session less words at its start
+    pub word_delta: i64,
+    pub word_count: u32,
+    pub checkpoints: Vec<Checkpoint>,
 }
 
+/// Break between checkpoints that starts a new editing session
+pub const DEFAULT_SESSION_GAP_MINUTES: i64 = 30;
+
 #[derive(Debug, Clone)]
 pub struct CheckpointConfig {
     pub min_interval_seconds: u64,
@@ -145,6 +198,7 @@ impl<O: ObjectStoreOps, T: TimeProvider> CheckpointManager<O, T> {
                     file_key: key.clone(),
                     head_id: None,
                     checkpoints: vec![],
+                    variants: vec![],
                 }
             };
 
@@ -171,6 +225,51 @@ impl<O: ObjectStoreOps, T: TimeProvider> CheckpointManager<O, T> {
         trigger: &str,
         label: Option<&str>,
         description: Option<&str>,
+    ) -> Result<Checkpoint> {
+        self.add_checkpoint(
+            file_path,
+            markdown,
+            sidecar,
+            trigger,
+            label,
+            description,
+            None,
+        )
+        .await
+    }
+
+    /// Create a checkpoint credited to an AI edit. Unlike automatic
+    /// checkpoints these are always created, however recent the last one.
+    pub async fn create_attributed_checkpoint(
+        &mut self,
+        file_path: &str,
+        markdown: &str,
+        sidecar: &str,
+        trigger: &str,
+        attribution: &CheckpointAttribution,
+    ) -> Result<Checkpoint> {
+        self.add_checkpoint(
+            file_path,
+            markdown,
+            sidecar,
+            trigger,
+            None,
+            None,
+            Some(attribution.clone()),
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn add_checkpoint(
+        &mut self,
+        file_path: &str,
+        markdown: &str,
+        sidecar: &str,
+        trigger: &str,
+        label: Option<&str>,
+        description: Option<&str>,
+        attribution: Option<CheckpointAttribution>,
     ) -> Result<Checkpoint> {
         // Store content in object store
         let content_hash = self.object_store.write(markdown).await?;
@@ -187,6 +286,7 @@ impl<O: ObjectStoreOps, T: TimeProvider> CheckpointManager<O, T> {
         // Check if we should create a checkpoint
         let now = self.time_provider.now_utc();
         if trigger != "bookmark"
+            && attribution.is_none()
             && !Self::should_create_checkpoint(&self.config, &history, markdown, now)
         {
             // Return the head checkpoint if exists
@@ -232,6 +332,7 @@ impl<O: ObjectStoreOps, T: TimeProvider> CheckpointManager<O, T> {
                 change_size,
             },
             trigger: trigger.to_string(),
+            attribution,
         };
 
         // Add to history
@@ -410,6 +511,170 @@ impl<O: ObjectStoreOps, T: TimeProvider> CheckpointManager<O, T> {
 
         Ok((additions, deletions))
     }
+
+    // ============================================
+    // Variants
+    // ============================================
+
+    /// Change a file's history and save it
+    async fn update_history<R>(
+        &mut self,
+        file_path: &str,
+        change: impl FnOnce(&mut CheckpointHistory) -> Result<R>,
+    ) -> Result<R> {
+        self.load_history(file_path).await?;
+        let key = Self::path_to_key(file_path);
+        let mut history = self.histories.remove(&key).unwrap();
+
+        let result = change(&mut history);
+        let saved = match &result {
+            Ok(_) => self.save_history(file_path, &history).await,
+            Err(_) => Ok(()),
+        };
+        self.histories.insert(key, history);
+        saved?;
+        result
+    }
+
+    /// Start a named variant of a document from its current content
+    pub async fn create_variant(
+        &mut self,
+        file_path: &str,
+        name: &str,
+        content: &str,
+    ) -> Result<DocumentVariant> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(MidlightError::InvalidInput(
+                "Variant name cannot be empty".to_string(),
+            ));
+        }
+
+        let hash = self.object_store.write(content).await?;
+        let now = self.time_provider.now_utc().to_rfc3339();
+        self.update_history(file_path, |history| {
+            if history.variants.iter().any(|v| v.name == name) {
+                return Err(MidlightError::InvalidInput(format!(
+                    "A variant named \"{}\" already exists",
+                    name
+                )));
+            }
+            let variant = DocumentVariant {
+                name: name.to_string(),
+                base_checkpoint_id: history.head_id.clone(),
+                base_hash: hash.clone(),
+                content_hash: hash,
+                created: now.clone(),
+                modified: now,
+            };
+            history.variants.push(variant.clone());
+            Ok(variant)
+        })
+        .await
+    }
+
+    /// A document's variants, oldest first
+    pub async fn get_variants(&mut self, file_path: &str) -> Result<Vec<DocumentVariant>> {
+        Ok(self.load_history(file_path).await?.variants.clone())
+    }
+
+    pub async fn get_variant(&mut self, file_path: &str, name: &str) -> Result<DocumentVariant> {
+        self.get_variants(file_path)
+            .await?
+            .into_iter()
+            .find(|v| v.name == name)
+            .ok_or_else(|| MidlightError::NotFound(format!("Variant \"{}\"", name)))
+    }
+
+    /// Replace a variant's content with an edited version
+    pub async fn update_variant(
+        &mut self,
+        file_path: &str,
+        name: &str,
+        content: &str,
+    ) -> Result<DocumentVariant> {
+        let hash = self.object_store.write(content).await?;
+        let now = self.time_provider.now_utc().to_rfc3339();
+        self.update_history(file_path, |history| {
+            let variant = history
+                .variants
+                .iter_mut()
+                .find(|v| v.name == name)
+                .ok_or_else(|| MidlightError::NotFound(format!("Variant \"{}\"", name)))?;
+            variant.content_hash = hash;
+            variant.modified = now;
+            Ok(variant.clone())
+        })
+        .await
+    }
+
+    /// Drop a variant, returning it. Its content stays in the object store
+    /// until it's collected.
+    pub async fn remove_variant(&mut self, file_path: &str, name: &str) -> Result<DocumentVariant> {
+        self.update_history(file_path, |history| {
+            let index = history
+                .variants
+                .iter()
+                .position(|v| v.name == name)
+                .ok_or_else(|| MidlightError::NotFound(format!("Variant \"{}\"", name)))?;
+            Ok(history.variants.remove(index))
+        })
+        .await
+    }
+
+    /// The content a variant has now and the content it started from
+    pub async fn get_variant_content(&self, variant: &DocumentVariant) -> Result<(String, String)> {
+        let content = self.object_store.read(&variant.content_hash).await?;
+        let base = self.object_store.read(&variant.base_hash).await?;
+        Ok((content, base))
+    }
+}
+
+/// Group checkpoints into editing sessions, oldest first. A checkpoint
+/// more than `gap` after the one before it starts a new session.
+pub fn editing_sessions(checkpoints: &[Checkpoint], gap: Duration) -> Vec<EditingSession> {
+    let mut timed: Vec<(DateTime<Utc>, &Checkpoint)> = checkpoints
+        .iter()
+        .filter_map(|cp| {
+            let time = DateTime::parse_from_rfc3339(&cp.timestamp).ok()?;
+            Some((time.with_timezone(&Utc), cp))
+        })
+        .collect();
+    timed.sort_by_key(|(time, _)| *time);
+
+    let mut sessions: Vec<EditingSession> = Vec::new();
+    let mut last_time: Option<DateTime<Utc>> = None;
+    let mut started = None;
+    // A document's first checkpoint starts from nothing
+    let mut words_before = match timed.first() {
+        Some((_, cp)) if cp.parent_id.is_some() => cp.stats.word_count,
+        _ => 0,
+    };
+    for (time, cp) in timed {
+        if !last_time.is_some_and(|last| time - last <= gap) {
+            if let Some(session) = sessions.last() {
+                words_before = session.word_count;
+            }
+            started = Some(time);
+            sessions.push(EditingSession {
+                started: cp.timestamp.clone(),
+                ended: cp.timestamp.clone(),
+                duration_seconds: 0,
+                word_delta: 0,
+                word_count: words_before,
+                checkpoints: Vec::new(),
+            });
+        }
+        if let Some(session) = sessions.last_mut() {
+            session.ended = cp.timestamp.clone();
+            session.duration_seconds = (time - started.unwrap_or(time)).num_seconds();
+            session.word_count = cp.stats.word_count;
+            session.word_delta = cp.stats.word_count as i64 - words_before as i64;
+            session.checkpoints.push(cp.clone());
+        }
+        last_time = Some(time);
+    }
+    sessions
 }
 
 #[cfg(test)]
@@ -744,6 +1009,7 @@ mod tests {
                 change_size: 50,
             },
             trigger: "manual".to_string(),
+            attribution: None,
         };
 
         let json = serde_json::to_string(&checkpoint).unwrap();
@@ -781,6 +1047,7 @@ mod tests {
             file_key: "test_md".to_string(),
             head_id: Some("cp-123".to_string()),
             checkpoints: vec![],
+            variants: vec![],
         };
 
         let json = serde_json::to_string(&history).unwrap();
@@ -1230,4 +1497,137 @@ mod tests {
         // char_count is bytes, not unicode chars
         assert_eq!(checkpoint.stats.char_count, content.len() as u32);
     }
+
+    #[tokio::test]
+    async fn test_attributed_checkpoints_are_always_created() {
+        let (_temp, mut manager) = create_test_manager();
+        manager.init().await.unwrap();
+        let attribution = CheckpointAttribution {
+            conversation_id: Some("conv-1".to_string()),
+            tool: Some("edit_document".to_string()),
+            change_id: None,
+        };
+
+        manager
+            .create_checkpoint("test.md", "Draft", "{}", "manual", None, None)
+            .await
+            .unwrap();
+        // Too soon and too small a change for an automatic checkpoint
+        let skipped = manager
+            .create_checkpoint("test.md", "Draft!", "{}", "interval", None, None)
+            .await
+            .unwrap();
+        assert!(skipped.attribution.is_none());
+
+        let before = manager
+            .create_attributed_checkpoint("test.md", "Draft", "{}", "before_ai_edit", &attribution)
+            .await
+            .unwrap();
+        let after = manager
+            .create_attributed_checkpoint("test.md", "Draft.", "{}", "ai_edit", &attribution)
+            .await
+            .unwrap();
+        assert_eq!(after.parent_id, Some(before.id));
+
+        let checkpoints = manager.get_checkpoints("test.md").await.unwrap();
+        assert_eq!(checkpoints.len(), 3);
+        assert_eq!(checkpoints[2].attribution, Some(attribution));
+        let json = serde_json::to_string(&checkpoints[2]).unwrap();
+        assert!(json.contains("\"conversationId\":\"conv-1\""));
+    }
+
+    #[test]
+    fn test_editing_sessions() {
+        let checkpoint = |id: &str, timestamp: &str, words: u32, parent: Option<&str>| Checkpoint {
+            id: id.to_string(),
+            content_hash: String::new(),
+            sidecar_hash: String::new(),
+            timestamp: timestamp.to_string(),
+            parent_id: parent.map(String::from),
+            checkpoint_type: "auto".to_string(),
+            label: None,
+            description: None,
+            stats: CheckpointStats {
+                word_count: words,
+                char_count: words * 5,
+                change_size: 0,
+            },
+            trigger: "interval".to_string(),
+            attribution: None,
+        };
+        let checkpoints = vec![
+            checkpoint("cp-1", "2024-01-01T09:00:00Z", 100, None),
+            checkpoint("cp-2", "2024-01-01T09:20:00Z", 250, Some("cp-1")),
+            checkpoint("cp-3", "2024-01-01T09:45:00Z", 300, Some("cp-2")),
+            checkpoint("cp-4", "2024-01-01T14:00:00Z", 280, Some("cp-3")),
+        ];
+
+        let sessions = editing_sessions(&checkpoints, Duration::minutes(30));
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].checkpoints.len(), 3);
+        assert_eq!(sessions[0].duration_seconds, 45 * 60);
+        assert_eq!(sessions[0].word_delta, 300);
+        assert_eq!(sessions[1].started, "2024-01-01T14:00:00Z");
+        assert_eq!(sessions[1].duration_seconds, 0);
+        assert_eq!(sessions[1].word_delta, -20);
+
+        // Without the document's first checkpoint, history starts where it's kept
+        let sessions = editing_sessions(&checkpoints[1..], Duration::minutes(10));
+        assert_eq!(sessions.len(), 3);
+        assert_eq!(sessions[0].word_delta, 0);
+        assert_eq!(sessions[1].word_delta, 50);
+    }
+
+    #[tokio::test]
+    async fn test_variant_lifecycle() {
+        let (_temp, mut manager) = create_test_manager();
+        manager.init().await.unwrap();
+        let head = manager
+            .create_checkpoint("story.midlight", "The end.", "{}", "manual", None, None)
+            .await
+            .unwrap();
+
+        let variant = manager
+            .create_variant("story.midlight", " Happier ending ", "The end.")
+            .await
+            .unwrap();
+        assert_eq!(variant.name, "Happier ending");
+        assert_eq!(variant.base_checkpoint_id, Some(head.id));
+        assert!(manager
+            .create_variant("story.midlight", "Happier ending", "The end.")
+            .await
+            .is_err());
+
+        let updated = manager
+            .update_variant("story.midlight", "Happier ending", "They lived.")
+            .await
+            .unwrap();
+        let (content, base) = manager.get_variant_content(&updated).await.unwrap();
+        assert_eq!(content, "They lived.");
+        assert_eq!(base, "The end.");
+
+        // Variants don't show up as checkpoints
+        assert_eq!(
+            manager
+                .get_checkpoints("story.midlight")
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        manager
+            .remove_variant("story.midlight", "Happier ending")
+            .await
+            .unwrap();
+        assert!(manager
+            .get_variants("story.midlight")
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(manager
+            .get_variant("story.midlight", "Happier ending")
+            .await
+            .is_err());
+    }
 }
@@ -0,0 +1,1392 @@
+// Citations - References from BibTeX or Zotero, cited in documents
+//
+// A workspace takes its references either from a BibTeX file or from a
+// running Zotero with the Better BibTeX add-on, which serves a JSON-RPC
+// endpoint on localhost. Documents cite references with Pandoc-style markers
+// in their text: `[@smith2020]`, `[@smith2020, p. 12]` or
+// `[@smith2020; @doe2019]`. Markers stay as written while editing; exports
+// replace them with citations in the workspace's style and add a
+// bibliography at the end of the document.
+//
+// Styles are built-in renderings of common CSL styles (APA 7th edition,
+// Chicago author-date, IEEE and MLA 9th edition), covering the fields BibTeX
+// and CSL-JSON items usually carry.
+//
+// Settings: .midlight/citations.json in the workspace.
+
+use crate::services::atomic_write::write_atomic;
+use crate::services::docx_export::{TiptapDocument, TiptapMark, TiptapNode};
+use crate::traits::http_client::HttpClient;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+use unicode_normalization::UnicodeNormalization;
+
+/// Better BibTeX's JSON-RPC endpoint in a running Zotero
+pub const DEFAULT_ZOTERO_URL: &str = "http://127.0.0.1:23119/better-bibtex/json-rpc";
+
+/// Search results returned when no limit is given
+const DEFAULT_SEARCH_LIMIT: usize = 20;
+
+/// Authors listed in a bibliography entry before "et al."
+const MAX_LISTED_AUTHORS: usize = 20;
+
+lazy_static! {
+    static ref MARKER: Regex = Regex::new(r"\[([^\[\]]*@[^\[\]]*)\]").unwrap();
+    static ref CITE: Regex = Regex::new(r"^@([\w:.#$%&+?<>~/-]+)\s*(?:,\s*(.+))?$").unwrap();
+    static ref ACCENT: Regex = Regex::new(r#"\\(["'`^~=.])\s*\{?\s*([A-Za-z])\s*\}?"#).unwrap();
+    static ref COMMAND: Regex = Regex::new(r"\\([A-Za-z]+)\s*").unwrap();
+    static ref SPACES: Regex = Regex::new(r"\s+").unwrap();
+}
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CitationSource {
+    #[default]
+    Bibtex,
+    Zotero,
+}
+
+/// Citation styles, named by their CSL style ids
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum CitationStyle {
+    #[default]
+    #[serde(rename = "apa")]
+    Apa,
+    #[serde(rename = "chicago-author-date")]
+    ChicagoAuthorDate,
+    #[serde(rename = "ieee")]
+    Ieee,
+    #[serde(rename = "modern-language-association")]
+    Mla,
+}
+
+impl CitationStyle {
+    fn bibliography_title(self) -> &'static str {
+        match self {
+            CitationStyle::Mla => "Works Cited",
+            _ => "References",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CitationSettings {
+    #[serde(default)]
+    pub source: CitationSource,
+    /// BibTeX file, absolute or relative to the workspace root
+    #[serde(default)]
+    pub bibtex_path: Option<String>,
+    #[serde(default = "default_zotero_url")]
+    pub zotero_url: String,
+    #[serde(default)]
+    pub style: CitationStyle,
+}
+
+fn default_zotero_url() -> String {
+    DEFAULT_ZOTERO_URL.to_string()
+}
+
+impl Default for CitationSettings {
+    fn default() -> Self {
+        Self {
+            source: CitationSource::default(),
+            bibtex_path: None,
+            zotero_url: default_zotero_url(),
+            style: CitationStyle::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Author {
+    pub family: String,
+    /// None for organisations and other names that aren't split
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub given: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reference {
+    pub key: String,
+    /// Entry type, e.g. article or book
+    pub kind: String,
+    pub title: String,
+    pub authors: Vec<Author>,
+    pub year: Option<String>,
+    /// Journal, book or proceedings the work appeared in
+    pub container: Option<String>,
+    pub publisher: Option<String>,
+    pub volume: Option<String>,
+    pub issue: Option<String>,
+    pub pages: Option<String>,
+    pub doi: Option<String>,
+    pub url: Option<String>,
+}
+
+/// One reference cited by a marker, with an optional locator such as "p. 12"
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cite {
+    pub key: String,
+    pub locator: Option<String>,
+}
+
+/// What rendering a document's citations did
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CitationSummary {
+    /// References in the bibliography
+    pub cited: usize,
+    /// Keys cited but not found in the library
+    pub missing: Vec<String>,
+}
+
+/// A run of bibliography text, italic or not
+#[derive(Debug, Clone, PartialEq)]
+struct Span {
+    text: String,
+    italic: bool,
+}
+
+// ============================================================================
+// Settings
+// ============================================================================
+
+pub fn citation_settings_path(workspace_root: &Path) -> PathBuf {
+    workspace_root.join(".midlight").join("citations.json")
+}
+
+impl CitationSettings {
+    pub fn load(path: &Path) -> Self {
+        let Ok(content) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            warn!("Ignoring invalid citation settings: {}", e);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize citation settings: {}", e))?;
+        write_atomic(path, json).map_err(|e| format!("Failed to save citation settings: {}", e))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        match self.source {
+            CitationSource::Bibtex => {
+                if self
+                    .bibtex_path
+                    .as_deref()
+                    .map_or(true, |p| p.trim().is_empty())
+                {
+                    return Err("Choose a BibTeX file".to_string());
+                }
+            }
+            CitationSource::Zotero => {
+                let url = url::Url::parse(&self.zotero_url)
+                    .map_err(|e| format!("Invalid Zotero address: {}", e))?;
+                if !matches!(url.scheme(), "http" | "https") {
+                    return Err("Zotero address must use http or https".to_string());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn bibtex_file(&self, workspace_root: &Path) -> Result<PathBuf, String> {
+        let path = self
+            .bibtex_path
+            .as_deref()
+            .filter(|p| !p.trim().is_empty())
+            .ok_or("No BibTeX file configured")?;
+        Ok(workspace_root.join(path))
+    }
+}
+
+// ============================================================================
+// Library
+// ============================================================================
+
+/// References matching every word of a query, by key, title, author or year
+pub async fn search<C: HttpClient>(
+    client: &C,
+    workspace_root: &Path,
+    settings: &CitationSettings,
+    query: &str,
+    limit: Option<usize>,
+) -> Result<Vec<Reference>, String> {
+    let limit = limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+    let references = match settings.source {
+        CitationSource::Bibtex => read_bibtex(workspace_root, settings)?,
+        CitationSource::Zotero => {
+            let items = zotero_call(client, settings, "item.search", json!([query])).await?;
+            items
+                .as_array()
+                .map(|items| items.iter().filter_map(from_csl_json).collect())
+                .unwrap_or_default()
+        }
+    };
+
+    let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    Ok(references
+        .into_iter()
+        .filter(|reference| {
+            let haystack = search_text(reference);
+            words.iter().all(|word| haystack.contains(word))
+        })
+        .take(limit)
+        .collect())
+}
+
+/// The references with these keys; keys not in the library are left out
+pub async fn lookup<C: HttpClient>(
+    client: &C,
+    workspace_root: &Path,
+    settings: &CitationSettings,
+    keys: &[String],
+) -> Result<HashMap<String, Reference>, String> {
+    if keys.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let references = match settings.source {
+        CitationSource::Bibtex => read_bibtex(workspace_root, settings)?,
+        CitationSource::Zotero => {
+            let result = zotero_call(
+                client,
+                settings,
+                "item.export",
+                json!([keys, "Better BibTeX"]),
+            )
+            .await?;
+            // Older versions of Better BibTeX answer [status, type, text]
+            let text = match &result {
+                Value::String(text) => text.as_str(),
+                Value::Array(parts) => parts.last().and_then(Value::as_str).unwrap_or(""),
+                _ => "",
+            };
+            parse_bibtex(text)
+        }
+    };
+
+    let wanted: HashSet<&str> = keys.iter().map(String::as_str).collect();
+    Ok(references
+        .into_iter()
+        .filter(|r| wanted.contains(r.key.as_str()))
+        .map(|r| (r.key.clone(), r))
+        .collect())
+}
+
+fn read_bibtex(
+    workspace_root: &Path,
+    settings: &CitationSettings,
+) -> Result<Vec<Reference>, String> {
+    let path = settings.bibtex_file(workspace_root)?;
+    let text = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(parse_bibtex(&text))
+}
+
+async fn zotero_call<C: HttpClient>(
+    client: &C,
+    settings: &CitationSettings,
+    method: &str,
+    params: Value,
+) -> Result<Value, String> {
+    let request = json!({ "jsonrpc": "2.0", "method": method, "params": params, "id": 1 });
+    let response = client
+        .post_json(&settings.zotero_url, &request)
+        .await
+        .map_err(|e| {
+            format!(
+                "Failed to reach Zotero (is it running with Better BibTeX?): {}",
+                e
+            )
+        })?;
+    if !response.is_success() {
+        return Err(format!("Zotero returned HTTP {}", response.status));
+    }
+
+    let mut body: Value = response
+        .json()
+        .map_err(|e| format!("Failed to parse Zotero response: {}", e))?;
+    if let Some(message) = body.pointer("/error/message").and_then(Value::as_str) {
+        return Err(format!("Zotero: {}", message));
+    }
+    Ok(body
+        .get_mut("result")
+        .map(Value::take)
+        .unwrap_or(Value::Null))
+}
+
+fn search_text(reference: &Reference) -> String {
+    let mut text = format!("{} {}", reference.key, reference.title);
+    for author in &reference.authors {
+        text.push(' ');
+        text.push_str(&author.family);
+        if let Some(given) = &author.given {
+            text.push(' ');
+            text.push_str(given);
+        }
+    }
+    if let Some(year) = &reference.year {
+        text.push(' ');
+        text.push_str(year);
+    }
+    text.to_lowercase()
+}
+
+/// A reference from a CSL-JSON item, as Zotero returns them
+fn from_csl_json(item: &Value) -> Option<Reference> {
+    let text = |field: &str| match item.get(field) {
+        Some(Value::String(s)) if !s.is_empty() => Some(s.clone()),
+        Some(Value::Number(n)) => Some(n.to_string()),
+        _ => None,
+    };
+    let key = ["citation-key", "citationKey", "citekey"]
+        .iter()
+        .find_map(|field| text(field))?;
+
+    let authors = item
+        .get("author")
+        .and_then(Value::as_array)
+        .map(|names| {
+            names
+                .iter()
+                .filter_map(|name| {
+                    let part = |f: &str| name.get(f).and_then(Value::as_str).map(str::to_string);
+                    match part("family") {
+                        Some(family) => Some(Author {
+                            family,
+                            given: part("given"),
+                        }),
+                        None => part("literal").map(|family| Author {
+                            family,
+                            given: None,
+                        }),
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let year = item
+        .pointer("/issued/date-parts/0/0")
+        .map(|y| y.to_string().trim_matches('"').to_string());
+
+    Some(Reference {
+        key,
+        kind: text("type").unwrap_or_else(|| "document".to_string()),
+        title: text("title").unwrap_or_default(),
+        authors,
+        year,
+        container: text("container-title"),
+        publisher: text("publisher"),
+        volume: text("volume"),
+        issue: text("issue"),
+        pages: text("page"),
+        doi: text("DOI"),
+        url: text("URL"),
+    })
+}
+
+// ============================================================================
+// BibTeX
+// ============================================================================
+
+/// Parse the entries of a BibTeX file. An entry ends at the first field
+/// that can't be parsed.
+pub fn parse_bibtex(input: &str) -> Vec<Reference> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut strings: HashMap<String, String> = HashMap::new();
+    let mut references = Vec::new();
+    let mut pos = 0;
+
+    while let Some(at) = chars[pos..].iter().position(|&c| c == '@') {
+        pos += at + 1;
+        let kind = take_while(&chars, &mut pos, |c| c.is_alphanumeric()).to_lowercase();
+        skip_whitespace(&chars, &mut pos);
+        let close = match chars.get(pos) {
+            Some('{') => '}',
+            Some('(') => ')',
+            _ => continue,
+        };
+        pos += 1;
+
+        match kind.as_str() {
+            "comment" | "preamble" => {
+                skip_group(&chars, &mut pos, close);
+            }
+            "string" => {
+                if let Some((name, value)) = parse_field(&chars, &mut pos, &strings) {
+                    strings.insert(name, value);
+                }
+                skip_group(&chars, &mut pos, close);
+            }
+            _ => {
+                let key = take_while(&chars, &mut pos, |c| c != ',' && c != close)
+                    .trim()
+                    .to_string();
+                let mut fields = HashMap::new();
+                while chars.get(pos) == Some(&',') {
+                    pos += 1;
+                    match parse_field(&chars, &mut pos, &strings) {
+                        Some((name, value)) => {
+                            fields.insert(name, value);
+                        }
+                        None => break,
+                    }
+                }
+                skip_group(&chars, &mut pos, close);
+                if !key.is_empty() {
+                    references.push(from_bibtex(key, kind, fields));
+                }
+            }
+        }
+    }
+    references
+}
+
+fn take_while(chars: &[char], pos: &mut usize, keep: impl Fn(char) -> bool) -> String {
+    let start = *pos;
+    while *pos < chars.len() && keep(chars[*pos]) {
+        *pos += 1;
+    }
+    chars[start..*pos].iter().collect()
+}
+
+fn skip_whitespace(chars: &[char], pos: &mut usize) {
+    take_while(chars, pos, char::is_whitespace);
+}
+
+/// Move past the end of the current entry
+fn skip_group(chars: &[char], pos: &mut usize, close: char) {
+    let mut depth = 0;
+    while let Some(&c) = chars.get(*pos) {
+        *pos += 1;
+        match c {
+            '{' => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            c if c == close && depth == 0 => return,
+            _ => {}
+        }
+    }
+}
+
+/// `name = value # value ...`, leaving pos on the following `,` or close
+fn parse_field(
+    chars: &[char],
+    pos: &mut usize,
+    strings: &HashMap<String, String>,
+) -> Option<(String, String)> {
+    skip_whitespace(chars, pos);
+    let name = take_while(chars, pos, |c| c.is_alphanumeric() || "_-:.".contains(c));
+    skip_whitespace(chars, pos);
+    if name.is_empty() || chars.get(*pos) != Some(&'=') {
+        return None;
+    }
+    *pos += 1;
+
+    let mut value = String::new();
+    loop {
+        skip_whitespace(chars, pos);
+        match chars.get(*pos)? {
+            '{' => {
+                *pos += 1;
+                value.push_str(&take_delimited(chars, pos, '}'));
+            }
+            '"' => {
+                *pos += 1;
+                value.push_str(&take_delimited(chars, pos, '"'));
+            }
+            _ => {
+                let word = take_while(chars, pos, |c| c.is_alphanumeric() || "_-:.".contains(c));
+                if word.is_empty() {
+                    return None;
+                }
+                let known = strings.get(&word.to_lowercase());
+                value.push_str(known.map(String::as_str).unwrap_or(&word));
+            }
+        }
+        skip_whitespace(chars, pos);
+        if chars.get(*pos) == Some(&'#') {
+            *pos += 1;
+        } else {
+            break;
+        }
+    }
+    Some((name.to_lowercase(), value))
+}
+
+/// Text up to `end` at brace depth zero, keeping inner braces
+fn take_delimited(chars: &[char], pos: &mut usize, end: char) -> String {
+    let mut depth = 0;
+    let mut value = String::new();
+    while let Some(&c) = chars.get(*pos) {
+        *pos += 1;
+        match c {
+            c if c == end && depth == 0 => break,
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => {}
+        }
+        value.push(c);
+    }
+    value
+}
+
+fn from_bibtex(key: String, kind: String, mut fields: HashMap<String, String>) -> Reference {
+    let authors = ["author", "editor"]
+        .iter()
+        .find_map(|field| fields.get(*field))
+        .map(|raw| parse_names(raw))
+        .unwrap_or_default();
+    let mut take = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| fields.remove(*name))
+            .map(|value| clean_latex(&value))
+            .filter(|value| !value.is_empty())
+    };
+
+    Reference {
+        key,
+        title: take(&["title"]).unwrap_or_default(),
+        authors,
+        year: take(&["year"]).or_else(|| take(&["date"]).map(|d| d.chars().take(4).collect())),
+        container: take(&["journal", "journaltitle", "booktitle"]),
+        publisher: take(&["publisher", "institution", "school", "organization"]),
+        volume: take(&["volume"]),
+        issue: take(&["number", "issue"]),
+        pages: take(&["pages"]),
+        doi: take(&["doi"]),
+        url: take(&["url"]),
+        kind,
+    }
+}
+
+/// Split a BibTeX name list on `and`, keeping braced names whole
+fn parse_names(raw: &str) -> Vec<Author> {
+    let mut names = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+    for word in raw.split_whitespace() {
+        if depth == 0 && word.eq_ignore_ascii_case("and") {
+            names.push(std::mem::take(&mut current));
+            continue;
+        }
+        depth += word.matches('{').count() as i32 - word.matches('}').count() as i32;
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    names.push(current);
+
+    names
+        .iter()
+        .map(|name| name.trim())
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            // {World Health Organization} is one name, not a given and family name
+            let inner = name.strip_prefix('{').and_then(|n| n.strip_suffix('}'));
+            if let Some(literal) = inner.filter(|n| !n.contains('{') && !n.contains('}')) {
+                return Author {
+                    family: clean_latex(literal),
+                    given: None,
+                };
+            }
+            let parts: Vec<&str> = name.splitn(3, ',').map(str::trim).collect();
+            let (family, given) = match parts.as_slice() {
+                [family, given] | [family, _, given] => (family.to_string(), given.to_string()),
+                _ => match name.rsplit_once(' ') {
+                    Some((given, family)) => (family.to_string(), given.to_string()),
+                    None => (name.to_string(), String::new()),
+                },
+            };
+            let given = clean_latex(&given);
+            Author {
+                family: clean_latex(&family),
+                given: (!given.is_empty()).then_some(given),
+            }
+        })
+        .collect()
+}
+
+/// Plain text from a BibTeX value: accents resolved, braces and commands dropped
+pub fn clean_latex(value: &str) -> String {
+    let text = ACCENT.replace_all(value, |caps: &Captures| {
+        let mark = match &caps[1] {
+            "\"" => '\u{308}',
+            "'" => '\u{301}',
+            "`" => '\u{300}',
+            "^" => '\u{302}',
+            "~" => '\u{303}',
+            "=" => '\u{304}',
+            _ => '\u{307}',
+        };
+        format!("{}{}", &caps[2], mark)
+    });
+    let text = text
+        .replace("\\&", "&")
+        .replace("\\%", "%")
+        .replace("\\$", "$")
+        .replace("\\_", "_")
+        .replace("\\#", "#")
+        .replace("---", "\u{2014}")
+        .replace("--", "\u{2013}")
+        .replace('~', "\u{a0}");
+    let text = COMMAND.replace_all(&text, |caps: &Captures| match &caps[1] {
+        "ss" => "ß".to_string(),
+        "o" => "ø".to_string(),
+        "O" => "Ø".to_string(),
+        "aa" => "å".to_string(),
+        "ae" => "æ".to_string(),
+        "l" => "ł".to_string(),
+        _ => String::new(),
+    });
+    let text: String = text.chars().filter(|c| *c != '{' && *c != '}').collect();
+    SPACES.replace_all(text.trim(), " ").nfc().collect()
+}
+
+// ============================================================================
+// Citations
+// ============================================================================
+
+/// The marker citing some keys, with a locator for the last one
+pub fn citation_marker(keys: &[String], locator: Option<&str>) -> String {
+    let mut parts: Vec<String> = keys.iter().map(|key| format!("@{}", key)).collect();
+    if let (Some(last), Some(locator)) = (parts.last_mut(), locator.map(str::trim)) {
+        if !locator.is_empty() {
+            last.push_str(", ");
+            last.push_str(locator);
+        }
+    }
+    format!("[{}]", parts.join("; "))
+}
+
+/// The references a marker cites, or None if it isn't a citation
+fn parse_marker(inner: &str) -> Option<Vec<Cite>> {
+    inner
+        .split(';')
+        .map(|part| {
+            let caps = CITE.captures(part.trim())?;
+            Some(Cite {
+                key: caps[1].to_string(),
+                locator: caps.get(2).map(|l| l.as_str().trim().to_string()),
+            })
+        })
+        .collect()
+}
+
+/// Keys cited in a document, in the order they're first cited
+pub fn cited_keys(document: &TiptapDocument) -> Vec<String> {
+    let mut keys = Vec::new();
+    visit_text(&document.content, &mut |text| {
+        for caps in MARKER.captures_iter(text) {
+            for cite in parse_marker(&caps[1]).unwrap_or_default() {
+                if !keys.contains(&cite.key) {
+                    keys.push(cite.key);
+                }
+            }
+        }
+    });
+    keys
+}
+
+fn visit_text(nodes: &[TiptapNode], visit: &mut impl FnMut(&str)) {
+    for node in nodes {
+        if let Some(text) = &node.text {
+            visit(text);
+        }
+        visit_text(&node.content, visit);
+    }
+}
+
+fn visit_text_mut(nodes: &mut [TiptapNode], visit: &mut impl FnMut(&mut String)) {
+    for node in nodes {
+        if let Some(text) = node.text.as_mut() {
+            visit(text);
+        }
+        visit_text_mut(&mut node.content, visit);
+    }
+}
+
+/// Replace citation markers with citations in a style and add a bibliography.
+/// Markers citing unknown keys are left as they are.
+pub fn render_citations(
+    document: &mut TiptapDocument,
+    references: &HashMap<String, Reference>,
+    style: CitationStyle,
+) -> CitationSummary {
+    let keys = cited_keys(document);
+    let (cited, missing): (Vec<String>, Vec<String>) = keys
+        .into_iter()
+        .partition(|key| references.contains_key(key));
+    if cited.is_empty() {
+        return CitationSummary { cited: 0, missing };
+    }
+
+    let numbers: HashMap<&str, usize> = cited
+        .iter()
+        .enumerate()
+        .map(|(i, key)| (key.as_str(), i + 1))
+        .collect();
+    visit_text_mut(&mut document.content, &mut |text| {
+        let replaced = MARKER.replace_all(text, |caps: &Captures| {
+            let cites = parse_marker(&caps[1])
+                .filter(|cites| cites.iter().all(|c| references.contains_key(&c.key)));
+            match cites {
+                Some(cites) => {
+                    let cited: Vec<(&Reference, Option<&str>, usize)> = cites
+                        .iter()
+                        .map(|c| {
+                            (
+                                &references[&c.key],
+                                c.locator.as_deref(),
+                                numbers[c.key.as_str()],
+                            )
+                        })
+                        .collect();
+                    format_citation(style, &cited)
+                }
+                None => caps[0].to_string(),
+            }
+        });
+        if let std::borrow::Cow::Owned(replaced) = replaced {
+            *text = replaced;
+        }
+    });
+
+    let mut entries: Vec<(&Reference, usize)> = cited
+        .iter()
+        .map(|key| (&references[key], numbers[key.as_str()]))
+        .collect();
+    if style != CitationStyle::Ieee {
+        entries.sort_by_key(|(reference, _)| sort_key(reference));
+    }
+
+    document.content.push(TiptapNode {
+        node_type: "heading".to_string(),
+        content: vec![text_node(style.bibliography_title(), false)],
+        text: None,
+        marks: Vec::new(),
+        attrs: Some(json!({ "level": 2 })),
+    });
+    for (reference, number) in entries {
+        document.content.push(TiptapNode {
+            node_type: "paragraph".to_string(),
+            content: format_entry(style, reference, number)
+                .into_iter()
+                .map(|span| text_node(&span.text, span.italic))
+                .collect(),
+            text: None,
+            marks: Vec::new(),
+            attrs: None,
+        });
+    }
+
+    CitationSummary {
+        cited: cited.len(),
+        missing,
+    }
+}
+
+fn text_node(text: &str, italic: bool) -> TiptapNode {
+    TiptapNode {
+        node_type: "text".to_string(),
+        content: Vec::new(),
+        text: Some(text.to_string()),
+        marks: match italic {
+            true => vec![TiptapMark {
+                mark_type: "italic".to_string(),
+                attrs: None,
+            }],
+            false => Vec::new(),
+        },
+        attrs: None,
+    }
+}
+
+fn sort_key(reference: &Reference) -> (String, String, String) {
+    let first = reference
+        .authors
+        .first()
+        .map(|a| a.family.clone())
+        .unwrap_or_else(|| reference.title.clone());
+    (
+        first.to_lowercase(),
+        reference.year.clone().unwrap_or_default(),
+        reference.title.to_lowercase(),
+    )
+}
+
+// ============================================================================
+// Styles
+// ============================================================================
+
+/// An in-text citation of one or more references, each with its locator and
+/// its number in the bibliography
+pub fn format_citation(
+    style: CitationStyle,
+    cited: &[(&Reference, Option<&str>, usize)],
+) -> String {
+    let parts: Vec<String> = cited
+        .iter()
+        .map(|(reference, locator, number)| {
+            let year = reference.year.as_deref().unwrap_or("n.d.");
+            let locator = locator.filter(|l| !l.is_empty());
+            match style {
+                CitationStyle::Apa => {
+                    let names = short_names(reference, " & ", 2);
+                    match locator {
+                        Some(l) => format!("{}, {}, {}", names, year, l),
+                        None => format!("{}, {}", names, year),
+                    }
+                }
+                CitationStyle::ChicagoAuthorDate => {
+                    let names = short_names(reference, " and ", 3);
+                    match locator {
+                        Some(l) => format!("{} {}, {}", names, year, l),
+                        None => format!("{} {}", names, year),
+                    }
+                }
+                CitationStyle::Mla => {
+                    let names = short_names(reference, " and ", 2);
+                    match locator {
+                        Some(l) => format!("{} {}", names, l),
+                        None => names,
+                    }
+                }
+                CitationStyle::Ieee => match locator {
+                    Some(l) => format!("[{}, {}]", number, l),
+                    None => format!("[{}]", number),
+                },
+            }
+        })
+        .collect();
+
+    match style {
+        CitationStyle::Ieee => parts.join(", "),
+        _ => format!("({})", parts.join("; ")),
+    }
+}
+
+/// Family names for an in-text citation, with "et al." past `max` authors
+fn short_names(reference: &Reference, and: &str, max: usize) -> String {
+    let families: Vec<&str> = reference
+        .authors
+        .iter()
+        .map(|a| a.family.as_str())
+        .collect();
+    match families.as_slice() {
+        [] => format!("\u{201c}{}\u{201d}", reference.title),
+        [one] => one.to_string(),
+        [first, second] => format!("{}{}{}", first, and, second),
+        _ if families.len() > max => format!("{} et al.", families[0]),
+        _ => {
+            let (last, rest) = families.split_last().unwrap();
+            format!("{},{}{}", rest.join(", "), and, last)
+        }
+    }
+}
+
+fn initials(given: &str) -> String {
+    given
+        .split_whitespace()
+        .map(|part| {
+            part.split('-')
+                .filter_map(|p| p.chars().next())
+                .map(|c| format!("{}.", c))
+                .collect::<Vec<_>>()
+                .join("-")
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn is_book(reference: &Reference) -> bool {
+    matches!(reference.kind.as_str(), "book" | "mvbook") || reference.container.is_none()
+}
+
+/// Builds a bibliography entry from text and italic spans
+#[derive(Default)]
+struct Entry {
+    spans: Vec<Span>,
+}
+
+impl Entry {
+    fn text(&mut self, text: impl Into<String>) -> &mut Self {
+        let text = text.into();
+        match self.spans.last_mut() {
+            Some(last) if !last.italic => last.text.push_str(&text),
+            _ => self.spans.push(Span {
+                text,
+                italic: false,
+            }),
+        }
+        self
+    }
+
+    fn italic(&mut self, text: impl Into<String>) -> &mut Self {
+        self.spans.push(Span {
+            text: text.into(),
+            italic: true,
+        });
+        self
+    }
+
+    fn finish(mut self) -> Vec<Span> {
+        if let Some(last) = self.spans.last_mut() {
+            let trimmed = last.text.trim_end().len();
+            last.text.truncate(trimmed);
+        }
+        self.spans
+    }
+}
+
+/// End a sentence unless the text already ends with punctuation
+fn sentence(text: &str) -> String {
+    match text.ends_with(['.', '?', '!']) {
+        true => text.to_string(),
+        false => format!("{}.", text),
+    }
+}
+
+fn link(reference: &Reference) -> Option<String> {
+    match (&reference.doi, &reference.url) {
+        (Some(doi), _) if doi.starts_with("http") => Some(doi.clone()),
+        (Some(doi), _) => Some(format!("https://doi.org/{}", doi)),
+        (None, Some(url)) => Some(url.clone()),
+        _ => None,
+    }
+}
+
+fn format_entry(style: CitationStyle, reference: &Reference, number: usize) -> Vec<Span> {
+    let mut entry = Entry::default();
+    let book = is_book(reference);
+    let year = reference.year.as_deref().unwrap_or("n.d.");
+    let title = reference.title.as_str();
+
+    match style {
+        CitationStyle::Apa => {
+            let names: Vec<String> = reference
+                .authors
+                .iter()
+                .take(MAX_LISTED_AUTHORS)
+                .map(|a| match &a.given {
+                    Some(given) => format!("{}, {}", a.family, initials(given)),
+                    None => a.family.clone(),
+                })
+                .collect();
+            if !names.is_empty() {
+                entry.text(format!(
+                    "{} ",
+                    sentence(&join_names(&names, ", & ", ", & "))
+                ));
+            }
+            entry.text(format!("({}). ", year));
+            if book {
+                entry.italic(sentence(title));
+                if let Some(publisher) = &reference.publisher {
+                    entry.text(format!(" {}", sentence(publisher)));
+                }
+            } else {
+                entry.text(format!("{} ", sentence(title)));
+                if let Some(container) = &reference.container {
+                    entry.italic(container.clone());
+                }
+                if let Some(volume) = &reference.volume {
+                    entry.text(", ").italic(volume.clone());
+                }
+                if let Some(issue) = &reference.issue {
+                    entry.text(format!("({})", issue));
+                }
+                if let Some(pages) = &reference.pages {
+                    entry.text(format!(", {}", pages));
+                }
+                entry.text(".");
+            }
+            if let Some(link) = link(reference) {
+                entry.text(format!(" {}", link));
+            }
+        }
+        CitationStyle::ChicagoAuthorDate | CitationStyle::Mla => {
+            let names: Vec<String> = reference
+                .authors
+                .iter()
+                .enumerate()
+                .map(|(i, a)| match (&a.given, i) {
+                    (Some(given), 0) => format!("{}, {}", a.family, given),
+                    (Some(given), _) => format!("{} {}", given, a.family),
+                    (None, _) => a.family.clone(),
+                })
+                .collect();
+            let names = match (style, names.len()) {
+                (CitationStyle::Mla, n) if n > 2 => format!("{}, et al", names[0]),
+                (_, n) if n > MAX_LISTED_AUTHORS => format!("{}, et al", names[0]),
+                _ => join_names(&names, ", and ", " and "),
+            };
+            if !names.is_empty() {
+                entry.text(format!("{} ", sentence(&names)));
+            }
+            if style == CitationStyle::ChicagoAuthorDate {
+                entry.text(format!("{}. ", year));
+            }
+
+            if book {
+                entry.italic(sentence(title));
+                match (style, &reference.publisher) {
+                    (CitationStyle::Mla, Some(p)) => entry.text(format!(" {}, {}.", p, year)),
+                    (CitationStyle::Mla, None) => entry.text(format!(" {}.", year)),
+                    (_, Some(p)) => entry.text(format!(" {}", sentence(p))),
+                    (_, None) => &mut entry,
+                };
+            } else {
+                entry.text(format!("\u{201c}{}\u{201d} ", sentence(title)));
+                if let Some(container) = &reference.container {
+                    entry.italic(container.clone());
+                }
+                if style == CitationStyle::Mla {
+                    let mut details = Vec::new();
+                    if let Some(volume) = &reference.volume {
+                        details.push(format!("vol. {}", volume));
+                    }
+                    if let Some(issue) = &reference.issue {
+                        details.push(format!("no. {}", issue));
+                    }
+                    details.push(year.to_string());
+                    if let Some(pages) = &reference.pages {
+                        details.push(format!("pp. {}", pages));
+                    }
+                    entry.text(format!(", {}.", details.join(", ")));
+                } else {
+                    if let Some(volume) = &reference.volume {
+                        entry.text(format!(" {}", volume));
+                    }
+                    if let Some(issue) = &reference.issue {
+                        entry.text(format!(" ({})", issue));
+                    }
+                    if let Some(pages) = &reference.pages {
+                        entry.text(format!(": {}", pages));
+                    }
+                    entry.text(".");
+                }
+            }
+            if let Some(link) = link(reference) {
+                entry.text(format!(" {}.", link));
+            }
+        }
+        CitationStyle::Ieee => {
+            let names: Vec<String> = reference
+                .authors
+                .iter()
+                .map(|a| match &a.given {
+                    Some(given) => format!("{} {}", initials(given), a.family),
+                    None => a.family.clone(),
+                })
+                .collect();
+            let names = match names.len() {
+                n if n > 6 => format!("{} et al.", names[0]),
+                _ => join_names(&names, ", and ", " and "),
+            };
+            entry.text(format!("[{}] ", number));
+            if !names.is_empty() {
+                entry.text(format!("{}, ", names));
+            }
+
+            if book {
+                entry.italic(title.to_string());
+                match &reference.publisher {
+                    Some(p) => entry.text(format!(". {}, {}.", p, year)),
+                    None => entry.text(format!(", {}.", year)),
+                };
+            } else {
+                entry.text(format!("\u{201c}{},\u{201d} ", title));
+                if let Some(container) = &reference.container {
+                    entry.italic(container.clone());
+                }
+                if let Some(volume) = &reference.volume {
+                    entry.text(format!(", vol. {}", volume));
+                }
+                if let Some(issue) = &reference.issue {
+                    entry.text(format!(", no. {}", issue));
+                }
+                if let Some(pages) = &reference.pages {
+                    entry.text(format!(", pp. {}", pages));
+                }
+                entry.text(format!(", {}.", year));
+            }
+            if let Some(doi) = &reference.doi {
+                entry.text(format!(" doi: {}.", doi));
+            } else if let Some(url) = &reference.url {
+                entry.text(format!(" [Online]. Available: {}", url));
+            }
+        }
+    }
+    entry.finish()
+}
+
+/// Join names with commas, using `last` before the final name (or `pair`
+/// when there are only two)
+fn join_names(names: &[String], last: &str, pair: &str) -> String {
+    match names {
+        [] => String::new(),
+        [one] => one.clone(),
+        [first, second] => format!("{}{}{}", first, pair, second),
+        _ => {
+            let (final_name, rest) = names.split_last().unwrap();
+            format!("{}{}{}", rest.join(", "), last, final_name)
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::MockHttpClient;
+    use tempfile::TempDir;
+
+    const BIBTEX: &str = r#"
+        @string{ jml = "Journal of Machine Learning" }
+        @comment{ exported from a reference manager }
+
+        @article{smith2020,
+          author  = {Smith, John and Alice M. Doe},
+          title   = {{Deep} Learning for {G\"{o}del} Numbers},
+          journal = jml # " Research",
+          year    = 2020,
+          volume  = {12},
+          number  = {3},
+          pages   = {45--67},
+          doi     = {10.1000/xyz123},
+        }
+
+        @book{who2019,
+          author    = {{World Health Organization}},
+          title     = {Global Report},
+          publisher = {WHO Press},
+          year      = {2019}
+        }
+
+        @misc{broken, title = }
+    "#;
+
+    fn library() -> HashMap<String, Reference> {
+        parse_bibtex(BIBTEX)
+            .into_iter()
+            .map(|r| (r.key.clone(), r))
+            .collect()
+    }
+
+    fn paragraph(text: &str) -> TiptapNode {
+        TiptapNode {
+            node_type: "paragraph".to_string(),
+            content: vec![text_node(text, false)],
+            text: None,
+            marks: Vec::new(),
+            attrs: None,
+        }
+    }
+
+    fn plain(node: &TiptapNode) -> String {
+        node.content
+            .iter()
+            .filter_map(|n| n.text.as_deref())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_bibtex() {
+        let references = parse_bibtex(BIBTEX);
+        let keys: Vec<&str> = references.iter().map(|r| r.key.as_str()).collect();
+        assert_eq!(keys, vec!["smith2020", "who2019", "broken"]);
+
+        let smith = &references[0];
+        assert_eq!(smith.title, "Deep Learning for Gödel Numbers");
+        assert_eq!(
+            smith.container.as_deref(),
+            Some("Journal of Machine Learning Research")
+        );
+        assert_eq!(smith.year.as_deref(), Some("2020"));
+        assert_eq!(smith.pages.as_deref(), Some("45\u{2013}67"));
+        assert_eq!(
+            smith.authors,
+            vec![
+                Author {
+                    family: "Smith".to_string(),
+                    given: Some("John".to_string())
+                },
+                Author {
+                    family: "Doe".to_string(),
+                    given: Some("Alice M.".to_string())
+                },
+            ]
+        );
+
+        let who = &references[1];
+        assert_eq!(who.authors[0].family, "World Health Organization");
+        assert_eq!(who.authors[0].given, None);
+        assert_eq!(who.publisher.as_deref(), Some("WHO Press"));
+    }
+
+    #[test]
+    fn test_styles() {
+        let library = library();
+        let smith = &library["smith2020"];
+        let who = &library["who2019"];
+
+        let cite = |style| format_citation(style, &[(smith, Some("p. 50"), 1), (who, None, 2)]);
+        assert_eq!(
+            cite(CitationStyle::Apa),
+            "(Smith & Doe, 2020, p. 50; World Health Organization, 2019)"
+        );
+        assert_eq!(
+            cite(CitationStyle::ChicagoAuthorDate),
+            "(Smith and Doe 2020, p. 50; World Health Organization 2019)"
+        );
+        assert_eq!(
+            cite(CitationStyle::Mla),
+            "(Smith and Doe p. 50; World Health Organization)"
+        );
+        assert_eq!(cite(CitationStyle::Ieee), "[1, p. 50], [2]");
+
+        let entry = |style, reference| -> String {
+            format_entry(style, reference, 1)
+                .iter()
+                .map(|span| match span.italic {
+                    true => format!("*{}*", span.text),
+                    false => span.text.clone(),
+                })
+                .collect()
+        };
+        assert_eq!(
+            entry(CitationStyle::Apa, smith),
+            "Smith, J., & Doe, A. M. (2020). Deep Learning for Gödel Numbers. \
+             *Journal of Machine Learning Research*, *12*(3), 45\u{2013}67. \
+             https://doi.org/10.1000/xyz123"
+        );
+        assert_eq!(
+            entry(CitationStyle::Apa, who),
+            "World Health Organization. (2019). *Global Report.* WHO Press."
+        );
+        assert_eq!(
+            entry(CitationStyle::Ieee, smith),
+            "[1] J. Smith and A. M. Doe, \u{201c}Deep Learning for Gödel Numbers,\u{201d} \
+             *Journal of Machine Learning Research*, vol. 12, no. 3, pp. 45\u{2013}67, 2020. \
+             doi: 10.1000/xyz123."
+        );
+        assert_eq!(
+            entry(CitationStyle::Mla, who),
+            "World Health Organization. *Global Report.* WHO Press, 2019."
+        );
+    }
+
+    #[test]
+    fn test_render_citations() {
+        let mut document = TiptapDocument {
+            doc_type: "doc".to_string(),
+            content: vec![
+                paragraph("As shown [@who2019; @smith2020, p. 50], and again [@smith2020]."),
+                paragraph("Unknown [@nobody] and email [me@example.com] stay."),
+            ],
+        };
+        assert_eq!(
+            cited_keys(&document),
+            vec!["who2019", "smith2020", "nobody"]
+        );
+
+        let summary = render_citations(&mut document, &library(), CitationStyle::Ieee);
+        assert_eq!(summary.cited, 2);
+        assert_eq!(summary.missing, vec!["nobody"]);
+        assert_eq!(
+            plain(&document.content[0]),
+            "As shown [1], [2, p. 50], and again [2]."
+        );
+        assert_eq!(
+            plain(&document.content[1]),
+            "Unknown [@nobody] and email [me@example.com] stay."
+        );
+        assert_eq!(document.content[2].node_type, "heading");
+        assert!(plain(&document.content[3]).starts_with("[1] World Health Organization"));
+        assert!(plain(&document.content[4]).starts_with("[2] J. Smith"));
+
+        assert_eq!(
+            citation_marker(&["a".to_string(), "b".to_string()], Some("p. 4")),
+            "[@a; @b, p. 4]"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_library_sources() {
+        let temp = TempDir::new().unwrap();
+        fs::write(temp.path().join("refs.bib"), BIBTEX).unwrap();
+        let client = MockHttpClient::new();
+        let settings = CitationSettings {
+            bibtex_path: Some("refs.bib".to_string()),
+            ..Default::default()
+        };
+        settings.validate().unwrap();
+
+        let found = search(&client, temp.path(), &settings, "doe 2020", None)
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].key, "smith2020");
+
+        let zotero = CitationSettings {
+            source: CitationSource::Zotero,
+            ..Default::default()
+        };
+        let client = MockHttpClient::new()
+            .queue_json_response(
+                200,
+                &json!({ "jsonrpc": "2.0", "id": 1, "result": [{
+                    "citationKey": "lee2021",
+                    "type": "article-journal",
+                    "title": "Sparse Models",
+                    "author": [{ "family": "Lee", "given": "Min" }],
+                    "issued": { "date-parts": [[2021, 5]] },
+                    "container-title": "AI Review"
+                }]}),
+            )
+            .queue_json_response(
+                200,
+                &json!({ "jsonrpc": "2.0", "id": 1, "result": [200, "text/plain", BIBTEX] }),
+            );
+
+        let found = search(&client, temp.path(), &zotero, "sparse", None)
+            .await
+            .unwrap();
+        assert_eq!(found[0].key, "lee2021");
+        assert_eq!(found[0].year.as_deref(), Some("2021"));
+        assert_eq!(found[0].authors[0].family, "Lee");
+
+        let keys = vec!["who2019".to_string(), "missing".to_string()];
+        let references = lookup(&client, temp.path(), &zotero, &keys).await.unwrap();
+        assert_eq!(references.len(), 1);
+        assert!(references.contains_key("who2019"));
+
+        let request = client.last_request().unwrap();
+        assert_eq!(request.url, DEFAULT_ZOTERO_URL);
+        let body: Value = serde_json::from_str(&request.body.unwrap()).unwrap();
+        assert_eq!(body["method"], "item.export");
+        assert_eq!(body["params"][0], json!(keys));
+    }
+}
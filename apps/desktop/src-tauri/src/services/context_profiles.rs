@@ -0,0 +1,478 @@
+// Context Profiles - Per-document and per-folder AI instructions
+//
+// A profile attaches a system prompt, style guide, glossary and preferred
+// model settings to a document, a folder, or the whole workspace. When a chat
+// request is made from a document, profiles are resolved from the workspace
+// root down to the document itself, with more specific profiles taking
+// precedence, and merged into the request.
+//
+// Profiles are stored at: .midlight/context-profiles.json
+// Format:
+// {
+//   "version": 1,
+//   "profiles": {
+//     "": { "systemPrompt": "..." },
+//     "research": { "styleGuide": "...", "glossary": [...] },
+//     "research/paper.midlight": { "model": "gpt-4o", "temperature": 0.2 }
+//   }
+// }
+
+use crate::services::llm_service::ChatMessage;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+const PROFILES_VERSION: u32 = 1;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GlossaryEntry {
+    pub term: String,
+    pub definition: String,
+}
+
+/// AI context attached to a document or folder
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextProfile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style_guide: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub glossary: Vec<GlossaryEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+}
+
+/// A profile together with the path it is attached to ("" is the workspace)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileEntry {
+    pub path: String,
+    pub profile: ContextProfile,
+}
+
+/// The effective context for a document after merging all applicable profiles
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedProfile {
+    /// Paths of the profiles that contributed, least specific first
+    pub sources: Vec<String>,
+    pub system_prompt: Option<String>,
+    pub style_guide: Option<String>,
+    pub glossary: Vec<GlossaryEntry>,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ProfilesFile {
+    version: u32,
+    #[serde(default)]
+    profiles: BTreeMap<String, ContextProfile>,
+}
+
+// ============================================================================
+// Resolution
+// ============================================================================
+
+impl ResolvedProfile {
+    /// Merge a more specific profile on top of this one. Prompts and style
+    /// guides accumulate, glossary terms are overridden case-insensitively,
+    /// and model settings are replaced.
+    fn merge(&mut self, path: &str, profile: &ContextProfile) {
+        self.sources.push(path.to_string());
+
+        append_section(&mut self.system_prompt, profile.system_prompt.as_deref());
+        append_section(&mut self.style_guide, profile.style_guide.as_deref());
+
+        for entry in &profile.glossary {
+            match self
+                .glossary
+                .iter_mut()
+                .find(|e| e.term.eq_ignore_ascii_case(&entry.term))
+            {
+                Some(existing) => *existing = entry.clone(),
+                None => self.glossary.push(entry.clone()),
+            }
+        }
+
+        if profile.provider.is_some() {
+            self.provider = profile.provider.clone();
+        }
+        if profile.model.is_some() {
+            self.model = profile.model.clone();
+        }
+        if profile.temperature.is_some() {
+            self.temperature = profile.temperature;
+        }
+    }
+
+    /// Render the prompt text this profile contributes to the system message
+    pub fn system_text(&self) -> Option<String> {
+        let mut sections = Vec::new();
+
+        if let Some(prompt) = &self.system_prompt {
+            sections.push(prompt.clone());
+        }
+        if let Some(style) = &self.style_guide {
+            sections.push(format!("Style guide:\n{}", style));
+        }
+        if !self.glossary.is_empty() {
+            let terms: Vec<String> = self
+                .glossary
+                .iter()
+                .map(|e| format!("- {}: {}", e.term, e.definition))
+                .collect();
+            sections.push(format!("Glossary:\n{}", terms.join("\n")));
+        }
+
+        if sections.is_empty() {
+            None
+        } else {
+            Some(sections.join("\n\n"))
+        }
+    }
+
+    /// Merge the profile text into a conversation, extending an existing
+    /// leading system message or inserting a new one
+    pub fn apply_to_messages(&self, messages: &mut Vec<ChatMessage>) {
+        let Some(text) = self.system_text() else {
+            return;
+        };
+
+        match messages.first_mut() {
+            Some(first) if first.role == "system" => {
+                first.content = format!("{}\n\n{}", first.content, text);
+            }
+            _ => messages.insert(
+                0,
+                ChatMessage {
+                    role: "system".to_string(),
+                    content: text,
+                    name: None,
+                    tool_call_id: None,
+                    tool_calls: None,
+                },
+            ),
+        }
+    }
+}
+
+fn append_section(target: &mut Option<String>, text: Option<&str>) {
+    let Some(text) = text.map(str::trim).filter(|t| !t.is_empty()) else {
+        return;
+    };
+    *target = Some(match target.take() {
+        Some(existing) => format!("{}\n\n{}", existing, text),
+        None => text.to_string(),
+    });
+}
+
+/// Normalize a workspace-relative path into a profile key.
+/// Returns None for paths that escape the workspace.
+pub fn normalize_key(path: &str) -> Option<String> {
+    let path = path.replace('\\', "/");
+    let mut parts = Vec::new();
+
+    for component in Path::new(&path).components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+            Component::CurDir | Component::RootDir => {}
+            Component::ParentDir | Component::Prefix(_) => return None,
+        }
+    }
+
+    Some(parts.join("/"))
+}
+
+// ============================================================================
+// Profile Store
+// ============================================================================
+
+/// Reads and writes the context profiles of a single workspace
+pub struct ContextProfileStore {
+    profiles_path: PathBuf,
+}
+
+impl ContextProfileStore {
+    pub fn new(workspace_root: &Path) -> Self {
+        Self {
+            profiles_path: workspace_root
+                .join(".midlight")
+                .join("context-profiles.json"),
+        }
+    }
+
+    /// List all profiles in the workspace
+    pub fn list(&self) -> Result<Vec<ProfileEntry>, String> {
+        Ok(self
+            .read()?
+            .profiles
+            .into_iter()
+            .map(|(path, profile)| ProfileEntry { path, profile })
+            .collect())
+    }
+
+    /// Get the profile attached to exactly this path
+    pub fn get(&self, path: &str) -> Result<Option<ContextProfile>, String> {
+        let key = Self::key(path)?;
+        Ok(self.read()?.profiles.remove(&key))
+    }
+
+    /// Attach a profile to a document or folder, replacing any existing one
+    pub fn set(&self, path: &str, profile: ContextProfile) -> Result<(), String> {
+        let key = Self::key(path)?;
+        let mut file = self.read()?;
+        file.version = PROFILES_VERSION;
+        file.profiles.insert(key, profile);
+        self.write(&file)
+    }
+
+    /// Detach the profile from a path. Returns false if there was none.
+    pub fn remove(&self, path: &str) -> Result<bool, String> {
+        let key = Self::key(path)?;
+        let mut file = self.read()?;
+        let removed = file.profiles.remove(&key).is_some();
+        if removed {
+            self.write(&file)?;
+        }
+        Ok(removed)
+    }
+
+    /// Resolve the effective profile for a document by merging the workspace,
+    /// each ancestor folder, and the document's own profile in that order
+    pub fn resolve(&self, document_path: &str) -> Result<ResolvedProfile, String> {
+        let key = Self::key(document_path)?;
+        let file = self.read()?;
+        let mut resolved = ResolvedProfile::default();
+
+        let mut candidates = vec![String::new()];
+        let mut prefix = String::new();
+        for part in key.split('/').filter(|p| !p.is_empty()) {
+            if !prefix.is_empty() {
+                prefix.push('/');
+            }
+            prefix.push_str(part);
+            candidates.push(prefix.clone());
+        }
+
+        for candidate in candidates {
+            if let Some(profile) = file.profiles.get(&candidate) {
+                resolved.merge(&candidate, profile);
+            }
+        }
+
+        Ok(resolved)
+    }
+
+    fn key(path: &str) -> Result<String, String> {
+        normalize_key(path).ok_or_else(|| format!("Invalid profile path: {}", path))
+    }
+
+    fn read(&self) -> Result<ProfilesFile, String> {
+        if !self.profiles_path.exists() {
+            return Ok(ProfilesFile {
+                version: PROFILES_VERSION,
+                profiles: BTreeMap::new(),
+            });
+        }
+
+        let content = fs::read_to_string(&self.profiles_path)
+            .map_err(|e| format!("Failed to read context profiles: {}", e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse context profiles: {}", e))
+    }
+
+    fn write(&self, file: &ProfilesFile) -> Result<(), String> {
+        if let Some(parent) = self.profiles_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create .midlight directory: {}", e))?;
+        }
+
+        let json = serde_json::to_string_pretty(file)
+            .map_err(|e| format!("Failed to serialize context profiles: {}", e))?;
+
+        let temp_path = self.profiles_path.with_extension("json.tmp");
+        fs::write(&temp_path, json)
+            .map_err(|e| format!("Failed to write context profiles: {}", e))?;
+        fs::rename(&temp_path, &self.profiles_path)
+            .map_err(|e| format!("Failed to write context profiles: {}", e))
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn message(role: &str, content: &str) -> ChatMessage {
+        ChatMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            name: None,
+            tool_call_id: None,
+            tool_calls: None,
+        }
+    }
+
+    fn glossary(term: &str, definition: &str) -> GlossaryEntry {
+        GlossaryEntry {
+            term: term.to_string(),
+            definition: definition.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_normalize_key() {
+        assert_eq!(normalize_key("").unwrap(), "");
+        assert_eq!(normalize_key("/").unwrap(), "");
+        assert_eq!(normalize_key("./notes/").unwrap(), "notes");
+        assert_eq!(
+            normalize_key("notes\\a.midlight").unwrap(),
+            "notes/a.midlight"
+        );
+        assert!(normalize_key("../outside").is_none());
+        assert!(normalize_key("notes/../../x").is_none());
+    }
+
+    #[test]
+    fn test_set_get_remove() {
+        let temp = TempDir::new().unwrap();
+        let store = ContextProfileStore::new(temp.path());
+
+        assert!(store.get("notes").unwrap().is_none());
+
+        let profile = ContextProfile {
+            system_prompt: Some("Be concise".to_string()),
+            ..Default::default()
+        };
+        store.set("notes/", profile.clone()).unwrap();
+
+        assert_eq!(store.get("./notes").unwrap(), Some(profile));
+        assert_eq!(store.list().unwrap().len(), 1);
+
+        assert!(store.remove("notes").unwrap());
+        assert!(!store.remove("notes").unwrap());
+        assert!(store.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_resolve_merges_from_workspace_to_document() {
+        let temp = TempDir::new().unwrap();
+        let store = ContextProfileStore::new(temp.path());
+
+        store
+            .set(
+                "",
+                ContextProfile {
+                    system_prompt: Some("You help write documents.".to_string()),
+                    glossary: vec![glossary("ML", "Midlight")],
+                    model: Some("gpt-4o-mini".to_string()),
+                    temperature: Some(0.7),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        store
+            .set(
+                "research",
+                ContextProfile {
+                    style_guide: Some("Use APA citations.".to_string()),
+                    glossary: vec![glossary("ml", "Machine learning")],
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        store
+            .set(
+                "research/paper.midlight",
+                ContextProfile {
+                    system_prompt: Some("This is a conference paper.".to_string()),
+                    temperature: Some(0.2),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        // Sibling folders with a shared prefix must not match
+        store
+            .set(
+                "research-old",
+                ContextProfile {
+                    system_prompt: Some("Unrelated".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        let resolved = store.resolve("research/paper.midlight").unwrap();
+        assert_eq!(
+            resolved.sources,
+            vec!["", "research", "research/paper.midlight"]
+        );
+        assert_eq!(
+            resolved.system_prompt.as_deref(),
+            Some("You help write documents.\n\nThis is a conference paper.")
+        );
+        assert_eq!(resolved.style_guide.as_deref(), Some("Use APA citations."));
+        assert_eq!(resolved.glossary, vec![glossary("ml", "Machine learning")]);
+        assert_eq!(resolved.model.as_deref(), Some("gpt-4o-mini"));
+        assert_eq!(resolved.temperature, Some(0.2));
+    }
+
+    #[test]
+    fn test_resolve_without_profiles() {
+        let temp = TempDir::new().unwrap();
+        let store = ContextProfileStore::new(temp.path());
+
+        let resolved = store.resolve("notes/a.midlight").unwrap();
+        assert!(resolved.sources.is_empty());
+        assert!(resolved.system_text().is_none());
+    }
+
+    #[test]
+    fn test_apply_to_messages() {
+        let resolved = ResolvedProfile {
+            system_prompt: Some("Be formal.".to_string()),
+            style_guide: Some("British spelling.".to_string()),
+            glossary: vec![glossary("WAL", "Write-ahead log")],
+            ..Default::default()
+        };
+
+        // Inserts a system message when there is none
+        let mut messages = vec![message("user", "Hi")];
+        resolved.apply_to_messages(&mut messages);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].role, "system");
+        assert_eq!(
+            messages[0].content,
+            "Be formal.\n\nStyle guide:\nBritish spelling.\n\nGlossary:\n- WAL: Write-ahead log"
+        );
+
+        // Extends an existing system message
+        let mut messages = vec![message("system", "Base prompt"), message("user", "Hi")];
+        resolved.apply_to_messages(&mut messages);
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].content.starts_with("Base prompt\n\nBe formal."));
+
+        // Empty profiles leave messages untouched
+        let mut messages = vec![message("user", "Hi")];
+        ResolvedProfile::default().apply_to_messages(&mut messages);
+        assert_eq!(messages.len(), 1);
+    }
+}
@@ -0,0 +1,552 @@
+// Context Window - Fit long conversations into a model's context window
+//
+// When a conversation (including any document context carried in its
+// messages) is larger than the model's context window, the oldest turns are
+// replaced with a summary instead of letting the provider reject the request.
+// Leading system messages and the most recent turns are always kept.
+//
+// Summaries are cached per conversation and extended incrementally: when the
+// conversation grows, only the turns that newly fell out of the window are
+// folded into the existing summary.
+
+use crate::services::llm_service::ChatMessage;
+use crate::services::token_counter::{
+    count_message_tokens, count_tokens, encoding_for_model, Encoding, DEFAULT_MAX_OUTPUT_TOKENS,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use xxhash_rust::xxh64::xxh64;
+
+/// Context window assumed for models not in the table
+pub const DEFAULT_CONTEXT_WINDOW: u32 = 32_000;
+
+/// Output budget for generated summaries
+pub const SUMMARY_MAX_TOKENS: u32 = 1024;
+
+/// Tokens reserved for the summary prompt and the summary message wrapper
+const SUMMARY_OVERHEAD_TOKENS: u32 = 256;
+
+/// Maximum number of conversations with a cached summary
+const MAX_CACHED_SUMMARIES: usize = 100;
+
+/// Known context windows as (model prefix, tokens).
+/// Lookups use the longest matching prefix.
+const MODEL_CONTEXT_WINDOWS: &[(&str, u32)] = &[
+    // OpenAI
+    ("gpt-5", 400_000),
+    ("gpt-4.1", 1_047_576),
+    ("gpt-4o", 128_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4", 8_192),
+    ("o1", 200_000),
+    ("o3", 200_000),
+    ("o4", 200_000),
+    // Anthropic
+    ("claude", 200_000),
+    // Google
+    ("gemini", 1_048_576),
+];
+
+const SUMMARY_SYSTEM_PROMPT: &str = "You condense chat histories. Summarize the conversation \
+below so it can replace the original messages as context for the assistant. Preserve facts, \
+decisions, names, document details, user preferences and open questions. Write in the third \
+person, be concise, and do not add commentary.";
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// How a request was shortened to fit the context window
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextTruncation {
+    pub context_window: u32,
+    pub original_tokens: u32,
+    pub final_tokens: u32,
+    /// Older messages replaced by the summary
+    pub summarized_messages: usize,
+    /// Older messages dropped without being summarized, either because the
+    /// summary request failed or they didn't fit in the summarizer's window
+    pub dropped_messages: usize,
+    /// Whether the summary came from the per-conversation cache
+    pub summary_cached: bool,
+}
+
+/// Which messages to keep verbatim and which to summarize
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextPlan {
+    pub context_window: u32,
+    pub original_tokens: u32,
+    /// Number of leading system messages, always kept
+    pub system_end: usize,
+    /// Index of the first message kept verbatim; the messages in
+    /// `system_end..keep_from` are summarized
+    pub keep_from: usize,
+}
+
+// ============================================================================
+// Planning
+// ============================================================================
+
+/// Look up a model's context window by longest matching prefix
+pub fn context_window_for_model(model: &str) -> u32 {
+    let model = model.to_lowercase();
+    MODEL_CONTEXT_WINDOWS
+        .iter()
+        .filter(|(prefix, _)| model.starts_with(prefix))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|&(_, window)| window)
+        .unwrap_or(DEFAULT_CONTEXT_WINDOW)
+}
+
+/// Decide how to fit `messages` into the model's window. Returns None when
+/// the request already fits, or when summarizing wouldn't help (nothing but
+/// system messages and the latest turn).
+///
+/// `extra_tokens` accounts for request content outside the messages, such as
+/// tool definitions.
+pub fn plan(
+    model: &str,
+    messages: &[ChatMessage],
+    max_tokens: Option<u32>,
+    extra_tokens: u32,
+) -> Option<ContextPlan> {
+    let encoding = encoding_for_model(model);
+    let context_window = context_window_for_model(model);
+    let budget = context_window
+        .saturating_sub(max_tokens.unwrap_or(DEFAULT_MAX_OUTPUT_TOKENS))
+        .saturating_sub(extra_tokens);
+
+    let original_tokens = count_message_tokens(encoding, messages);
+    if original_tokens <= budget {
+        return None;
+    }
+
+    let system_end = messages.iter().take_while(|m| m.role == "system").count();
+    let available = budget
+        .saturating_sub(count_message_tokens(encoding, &messages[..system_end]))
+        .saturating_sub(SUMMARY_MAX_TOKENS + SUMMARY_OVERHEAD_TOKENS);
+
+    // Keep as many recent messages as fit; the latest is kept regardless
+    let mut used = 0;
+    let mut keep_from = messages.len();
+    for i in (system_end..messages.len()).rev() {
+        let tokens = count_message_tokens(encoding, &messages[i..=i]);
+        if used + tokens > available && keep_from < messages.len() {
+            break;
+        }
+        used += tokens;
+        keep_from = i;
+    }
+
+    // Tool results can't be separated from the assistant message that called them
+    while keep_from < messages.len() && messages[keep_from].role == "tool" {
+        keep_from += 1;
+    }
+
+    if keep_from <= system_end || keep_from >= messages.len() {
+        return None;
+    }
+
+    Some(ContextPlan {
+        context_window,
+        original_tokens,
+        system_end,
+        keep_from,
+    })
+}
+
+/// Build the shortened message list: system messages, the summary (if any),
+/// then the kept recent messages
+pub fn apply(
+    messages: &[ChatMessage],
+    plan: &ContextPlan,
+    summary: Option<&str>,
+) -> Vec<ChatMessage> {
+    let mut result = messages[..plan.system_end].to_vec();
+
+    if let Some(summary) = summary {
+        result.push(ChatMessage {
+            role: "system".to_string(),
+            content: format!("Summary of the earlier conversation:\n{}", summary),
+            name: None,
+            tool_call_id: None,
+            tool_calls: None,
+        });
+    }
+
+    result.extend_from_slice(&messages[plan.keep_from..]);
+    result
+}
+
+// ============================================================================
+// Summarization
+// ============================================================================
+
+/// Render messages as a plain-text transcript for the summarizer, keeping the
+/// most recent messages that fit within `max_tokens`. Returns the transcript
+/// and the number of oldest messages that didn't fit.
+pub fn transcript(
+    encoding: Encoding,
+    messages: &[ChatMessage],
+    max_tokens: u32,
+) -> (String, usize) {
+    let mut lines = Vec::new();
+    let mut used = 0;
+
+    for message in messages.iter().rev() {
+        let mut line = format!("{}: {}", capitalize(&message.role), message.content);
+        if let Some(ref tool_calls) = message.tool_calls {
+            for call in tool_calls {
+                line.push_str(&format!("\n[called {} with {}]", call.name, call.arguments));
+            }
+        }
+
+        let tokens = count_tokens(encoding, &line);
+        if used + tokens > max_tokens {
+            break;
+        }
+        used += tokens;
+        lines.push(line);
+    }
+
+    let dropped = messages.len() - lines.len();
+    lines.reverse();
+    (lines.join("\n\n"), dropped)
+}
+
+fn capitalize(role: &str) -> String {
+    let mut chars = role.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Messages for a summarization request, folding new turns into the previous
+/// summary when there is one
+pub fn summary_messages(previous: Option<&str>, transcript: &str) -> Vec<ChatMessage> {
+    let content = match previous {
+        Some(previous) => format!(
+            "Existing summary:\n{}\n\nNew messages to fold into the summary:\n{}",
+            previous, transcript
+        ),
+        None => format!("Conversation:\n{}", transcript),
+    };
+
+    vec![
+        ChatMessage {
+            role: "system".to_string(),
+            content: SUMMARY_SYSTEM_PROMPT.to_string(),
+            name: None,
+            tool_call_id: None,
+            tool_calls: None,
+        },
+        ChatMessage {
+            role: "user".to_string(),
+            content,
+            name: None,
+            tool_call_id: None,
+            tool_calls: None,
+        },
+    ]
+}
+
+/// Token budget for the transcript sent to the summarizer
+pub fn summary_input_budget(model: &str, previous: Option<&str>) -> u32 {
+    let previous_tokens = previous
+        .map(|p| count_tokens(encoding_for_model(model), p))
+        .unwrap_or(0);
+    context_window_for_model(model)
+        .saturating_sub(SUMMARY_MAX_TOKENS + SUMMARY_OVERHEAD_TOKENS)
+        .saturating_sub(previous_tokens)
+}
+
+// ============================================================================
+// Summary Cache
+// ============================================================================
+
+struct CachedSummary {
+    /// Number of history messages the summary covers
+    covered: usize,
+    /// Hash of those messages, to detect edited or different histories
+    prefix_hash: u64,
+    summary: String,
+    last_used: u64,
+}
+
+/// Summaries of older turns, one per conversation.
+///
+/// A conversation is identified by its first non-system message, which stays
+/// the same as the conversation grows.
+#[derive(Default)]
+pub struct SummaryCache {
+    entries: HashMap<u64, CachedSummary>,
+    clock: u64,
+}
+
+impl SummaryCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Find the cached summary for a history (the messages to be summarized).
+    /// Returns how many leading messages it covers and the summary.
+    pub fn lookup(&mut self, history: &[ChatMessage]) -> Option<(usize, String)> {
+        let key = conversation_key(history)?;
+        self.clock += 1;
+        let clock = self.clock;
+
+        let entry = self.entries.get_mut(&key)?;
+        if entry.covered > history.len()
+            || hash_messages(&history[..entry.covered]) != entry.prefix_hash
+        {
+            return None;
+        }
+
+        entry.last_used = clock;
+        Some((entry.covered, entry.summary.clone()))
+    }
+
+    /// Store the summary covering an entire history
+    pub fn store(&mut self, history: &[ChatMessage], summary: String) {
+        let Some(key) = conversation_key(history) else {
+            return;
+        };
+        self.clock += 1;
+
+        self.entries.insert(
+            key,
+            CachedSummary {
+                covered: history.len(),
+                prefix_hash: hash_messages(history),
+                summary,
+                last_used: self.clock,
+            },
+        );
+
+        while self.entries.len() > MAX_CACHED_SUMMARIES {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| *key);
+            match oldest {
+                Some(key) => self.entries.remove(&key),
+                None => break,
+            };
+        }
+    }
+}
+
+fn conversation_key(history: &[ChatMessage]) -> Option<u64> {
+    let first = history.first()?;
+    Some(xxh64(
+        format!("{}\n{}", first.role, first.content).as_bytes(),
+        0,
+    ))
+}
+
+fn hash_messages(messages: &[ChatMessage]) -> u64 {
+    xxh64(&serde_json::to_vec(messages).unwrap_or_default(), 0)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::llm_service::ToolCall;
+
+    fn message(role: &str, content: &str) -> ChatMessage {
+        ChatMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            name: None,
+            tool_call_id: None,
+            tool_calls: None,
+        }
+    }
+
+    /// A message of roughly `tokens` tokens
+    fn long_message(role: &str, tokens: usize) -> ChatMessage {
+        message(role, &"hello ".repeat(tokens))
+    }
+
+    #[test]
+    fn test_context_window_for_model() {
+        assert_eq!(context_window_for_model("gpt-4o-mini"), 128_000);
+        assert_eq!(context_window_for_model("gpt-4"), 8_192);
+        assert_eq!(context_window_for_model("claude-sonnet-4"), 200_000);
+        assert_eq!(
+            context_window_for_model("mystery-model"),
+            DEFAULT_CONTEXT_WINDOW
+        );
+    }
+
+    #[test]
+    fn test_plan_none_when_it_fits() {
+        let messages = vec![message("system", "Be helpful"), message("user", "Hi")];
+        assert!(plan("gpt-4", &messages, None, 0).is_none());
+    }
+
+    #[test]
+    fn test_plan_keeps_system_and_recent_messages() {
+        // gpt-4: 8192 window - 1024 output = ~7k budget
+        let messages = vec![
+            message("system", "Be helpful"),
+            long_message("user", 3000),
+            long_message("assistant", 3000),
+            long_message("user", 2000),
+            long_message("assistant", 1000),
+            message("user", "And now?"),
+        ];
+
+        let plan = plan("gpt-4", &messages, Some(1024), 0).unwrap();
+        assert_eq!(plan.context_window, 8_192);
+        assert_eq!(plan.system_end, 1);
+        assert_eq!(plan.keep_from, 3);
+        assert!(plan.original_tokens > 8_192);
+    }
+
+    #[test]
+    fn test_plan_counts_extra_tokens() {
+        let messages = vec![long_message("user", 3000), message("user", "Hi")];
+        assert!(plan("gpt-4", &messages, Some(1024), 0).is_none());
+        assert!(plan("gpt-4", &messages, Some(1024), 5000).is_some());
+    }
+
+    #[test]
+    fn test_plan_does_not_orphan_tool_results() {
+        let mut call = message("assistant", "");
+        call.tool_calls = Some(vec![ToolCall {
+            id: "call_1".to_string(),
+            name: "read_document".to_string(),
+            arguments: serde_json::json!({}),
+        }]);
+        let mut first_result = long_message("tool", 4000);
+        first_result.tool_call_id = Some("call_1".to_string());
+        let mut second_result = long_message("tool", 1000);
+        second_result.tool_call_id = Some("call_2".to_string());
+
+        let messages = vec![
+            long_message("user", 2000),
+            call,
+            first_result,
+            second_result,
+            long_message("assistant", 1500),
+            message("user", "Thanks"),
+        ];
+
+        // The window boundary falls between the two tool results
+        let plan = plan("gpt-4", &messages, Some(1024), 0).unwrap();
+        assert_eq!(plan.keep_from, 4);
+    }
+
+    #[test]
+    fn test_plan_none_when_latest_message_alone_is_too_big() {
+        let messages = vec![message("system", "Be helpful"), long_message("user", 9000)];
+        assert!(plan("gpt-4", &messages, None, 0).is_none());
+    }
+
+    #[test]
+    fn test_apply() {
+        let messages = vec![
+            message("system", "Be helpful"),
+            message("user", "old"),
+            message("assistant", "old reply"),
+            message("user", "new"),
+        ];
+        let plan = ContextPlan {
+            context_window: 8_192,
+            original_tokens: 10_000,
+            system_end: 1,
+            keep_from: 3,
+        };
+
+        let applied = apply(&messages, &plan, Some("They talked."));
+        assert_eq!(applied.len(), 3);
+        assert_eq!(applied[0].content, "Be helpful");
+        assert_eq!(applied[1].role, "system");
+        assert!(applied[1].content.ends_with("They talked."));
+        assert_eq!(applied[2].content, "new");
+
+        let dropped = apply(&messages, &plan, None);
+        assert_eq!(dropped.len(), 2);
+    }
+
+    #[test]
+    fn test_transcript_keeps_most_recent() {
+        let encoding = encoding_for_model("gpt-4");
+        let messages = vec![
+            long_message("user", 500),
+            message("assistant", "Short answer"),
+            message("user", "Follow-up"),
+        ];
+
+        let (text, dropped) = transcript(encoding, &messages, 100);
+        assert_eq!(dropped, 1);
+        assert_eq!(text, "Assistant: Short answer\n\nUser: Follow-up");
+
+        let (_, dropped) = transcript(encoding, &messages, 10_000);
+        assert_eq!(dropped, 0);
+    }
+
+    #[test]
+    fn test_summary_messages() {
+        let fresh = summary_messages(None, "User: Hi");
+        assert_eq!(fresh[0].role, "system");
+        assert_eq!(fresh[1].content, "Conversation:\nUser: Hi");
+
+        let incremental = summary_messages(Some("Earlier stuff"), "User: Hi");
+        assert!(incremental[1]
+            .content
+            .starts_with("Existing summary:\nEarlier stuff"));
+    }
+
+    #[test]
+    fn test_summary_cache_incremental_lookup() {
+        let mut cache = SummaryCache::new();
+        let history = vec![
+            message("user", "first"),
+            message("assistant", "reply"),
+            message("user", "second"),
+        ];
+
+        assert!(cache.lookup(&history).is_none());
+        cache.store(&history[..2], "summary of two".to_string());
+
+        // Same conversation, grown by one message
+        let (covered, summary) = cache.lookup(&history).unwrap();
+        assert_eq!(covered, 2);
+        assert_eq!(summary, "summary of two");
+
+        // Edited history invalidates the summary
+        let mut edited = history.clone();
+        edited[1].content = "different reply".to_string();
+        assert!(cache.lookup(&edited).is_none());
+
+        // Shorter history than the summary covers
+        assert!(cache.lookup(&history[..1]).is_none());
+    }
+
+    #[test]
+    fn test_summary_cache_evicts_least_recently_used() {
+        let mut cache = SummaryCache::new();
+        for i in 0..MAX_CACHED_SUMMARIES + 5 {
+            let history = vec![message("user", &format!("conversation {}", i))];
+            cache.store(&history, format!("summary {}", i));
+        }
+
+        assert_eq!(cache.entries.len(), MAX_CACHED_SUMMARIES);
+        assert!(cache.lookup(&[message("user", "conversation 0")]).is_none());
+        assert!(cache
+            .lookup(&[message(
+                "user",
+                &format!("conversation {}", MAX_CACHED_SUMMARIES + 4)
+            )])
+            .is_some());
+    }
+}
@@ -0,0 +1,488 @@
+// Conversation Store - Persistent chat threads per workspace
+//
+// Each conversation is stored as its own JSON file so a corrupt or partially
+// written thread can't take the rest of the history down with it.
+//
+// Files are stored at: .midlight/conversations/{id}.json
+// Format:
+// {
+//   "version": 1,
+//   "id": "5f0c...",
+//   "title": "Outline for chapter 3",
+//   "provider": "anthropic",
+//   "model": "claude-sonnet-4",
+//   "linkedDocument": "drafts/chapter-3.midlight",
+//   "messages": [{ "role": "user", "content": "..." }, ...],
+//   "createdAt": "2025-01-08T12:34:56Z",
+//   "updatedAt": "2025-01-08T12:40:02Z"
+// }
+
+use crate::services::llm_service::ChatMessage;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tokio::fs;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+const CONVERSATION_VERSION: u32 = 1;
+
+/// Maximum length of a title derived from the first user message
+const AUTO_TITLE_MAX_CHARS: usize = 60;
+
+/// A chat thread as stored on disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Conversation {
+    pub version: u32,
+    pub id: String,
+    pub title: String,
+    pub provider: String,
+    pub model: String,
+    /// Workspace-relative path of the document the chat is attached to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub linked_document: Option<String>,
+    pub messages: Vec<ChatMessage>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Conversation sent from the frontend to be saved
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SaveConversationInput {
+    /// Omit to create a new conversation
+    pub id: Option<String>,
+    /// Omit to keep the existing title, or derive one from the first message
+    pub title: Option<String>,
+    pub provider: String,
+    pub model: String,
+    pub linked_document: Option<String>,
+    pub messages: Vec<ChatMessage>,
+}
+
+/// Lightweight listing entry (no messages)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversationSummary {
+    pub id: String,
+    pub title: String,
+    pub provider: String,
+    pub model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub linked_document: Option<String>,
+    pub message_count: usize,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<&Conversation> for ConversationSummary {
+    fn from(conversation: &Conversation) -> Self {
+        Self {
+            id: conversation.id.clone(),
+            title: conversation.title.clone(),
+            provider: conversation.provider.clone(),
+            model: conversation.model.clone(),
+            linked_document: conversation.linked_document.clone(),
+            message_count: conversation.messages.len(),
+            created_at: conversation.created_at,
+            updated_at: conversation.updated_at,
+        }
+    }
+}
+
+// ============================================================================
+// Conversation Store
+// ============================================================================
+
+/// Stores chat conversations for a single workspace
+pub struct ConversationStore {
+    conversations_dir: PathBuf,
+}
+
+impl ConversationStore {
+    /// Create a new ConversationStore for the given workspace
+    pub fn new(workspace_root: PathBuf) -> Self {
+        Self {
+            conversations_dir: workspace_root.join(".midlight").join("conversations"),
+        }
+    }
+
+    /// Initialize the conversations directory
+    pub async fn init(&self) -> Result<(), String> {
+        fs::create_dir_all(&self.conversations_dir)
+            .await
+            .map_err(|e| format!("Failed to create conversations directory: {}", e))?;
+
+        debug!(
+            "Conversation store initialized at {:?}",
+            self.conversations_dir
+        );
+        Ok(())
+    }
+
+    /// List all conversations, most recently updated first.
+    /// Optionally restricted to those linked to a document.
+    pub async fn list(
+        &self,
+        linked_document: Option<&str>,
+    ) -> Result<Vec<ConversationSummary>, String> {
+        let mut summaries = Vec::new();
+
+        if !self.conversations_dir.exists() {
+            return Ok(summaries);
+        }
+
+        let mut entries = fs::read_dir(&self.conversations_dir)
+            .await
+            .map_err(|e| format!("Failed to read conversations directory: {}", e))?;
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+
+            match Self::read_file(&path).await {
+                Ok(conversation) => {
+                    if linked_document.is_some()
+                        && conversation.linked_document.as_deref() != linked_document
+                    {
+                        continue;
+                    }
+                    summaries.push(ConversationSummary::from(&conversation));
+                }
+                Err(e) => warn!("Skipping unreadable conversation {:?}: {}", path, e),
+            }
+        }
+
+        summaries.sort_by_key(|s| std::cmp::Reverse(s.updated_at));
+        Ok(summaries)
+    }
+
+    /// Load a conversation with all of its messages
+    pub async fn load(&self, id: &str) -> Result<Conversation, String> {
+        let path = self.conversation_path(id)?;
+        if !path.exists() {
+            return Err(format!("Conversation not found: {}", id));
+        }
+        Self::read_file(&path).await
+    }
+
+    /// Create or update a conversation
+    pub async fn save(&self, input: SaveConversationInput) -> Result<Conversation, String> {
+        let now = Utc::now();
+
+        let existing = match &input.id {
+            Some(id) => {
+                let path = self.conversation_path(id)?;
+                if path.exists() {
+                    Some(Self::read_file(&path).await?)
+                } else {
+                    None
+                }
+            }
+            None => None,
+        };
+
+        let title = input
+            .title
+            .filter(|t| !t.trim().is_empty())
+            .or_else(|| existing.as_ref().map(|c| c.title.clone()))
+            .unwrap_or_else(|| derive_title(&input.messages));
+
+        let conversation = Conversation {
+            version: CONVERSATION_VERSION,
+            id: input.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            title,
+            provider: input.provider,
+            model: input.model,
+            linked_document: input.linked_document,
+            messages: input.messages,
+            created_at: existing.map(|c| c.created_at).unwrap_or(now),
+            updated_at: now,
+        };
+
+        self.write_file(&conversation).await?;
+        debug!("Saved conversation {}", conversation.id);
+        Ok(conversation)
+    }
+
+    /// Rename a conversation
+    pub async fn rename(&self, id: &str, title: &str) -> Result<ConversationSummary, String> {
+        let title = title.trim();
+        if title.is_empty() {
+            return Err("Conversation title cannot be empty".to_string());
+        }
+
+        let mut conversation = self.load(id).await?;
+        conversation.title = title.to_string();
+        conversation.updated_at = Utc::now();
+
+        self.write_file(&conversation).await?;
+        Ok(ConversationSummary::from(&conversation))
+    }
+
+    /// Delete a conversation. Deleting a missing conversation is not an error.
+    pub async fn delete(&self, id: &str) -> Result<(), String> {
+        let path = self.conversation_path(id)?;
+        if path.exists() {
+            fs::remove_file(&path)
+                .await
+                .map_err(|e| format!("Failed to delete conversation: {}", e))?;
+            debug!("Deleted conversation {}", id);
+        }
+        Ok(())
+    }
+
+    // ========================================================================
+    // Helpers
+    // ========================================================================
+
+    /// Resolve the file for a conversation id, rejecting ids that could
+    /// escape the conversations directory
+    fn conversation_path(&self, id: &str) -> Result<PathBuf, String> {
+        let valid = !id.is_empty()
+            && id.len() <= 64
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(format!("Invalid conversation id: {}", id));
+        }
+        Ok(self.conversations_dir.join(format!("{}.json", id)))
+    }
+
+    async fn read_file(path: &PathBuf) -> Result<Conversation, String> {
+        let content = fs::read_to_string(path)
+            .await
+            .map_err(|e| format!("Failed to read conversation: {}", e))?;
+        serde_json::from_str(&content).map_err(|e| format!("Failed to parse conversation: {}", e))
+    }
+
+    async fn write_file(&self, conversation: &Conversation) -> Result<(), String> {
+        self.init().await?;
+
+        let path = self.conversation_path(&conversation.id)?;
+        let json = serde_json::to_string_pretty(conversation)
+            .map_err(|e| format!("Failed to serialize conversation: {}", e))?;
+
+        // Write atomically (write to temp, then rename)
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, &json)
+            .await
+            .map_err(|e| format!("Failed to write conversation temp file: {}", e))?;
+        fs::rename(&temp_path, &path)
+            .await
+            .map_err(|e| format!("Failed to rename conversation file: {}", e))
+    }
+}
+
+/// Derive a title from the first user message
+fn derive_title(messages: &[ChatMessage]) -> String {
+    let first = messages
+        .iter()
+        .find(|m| m.role == "user")
+        .map(|m| m.content.split_whitespace().collect::<Vec<_>>().join(" "))
+        .unwrap_or_default();
+
+    if first.is_empty() {
+        return "New conversation".to_string();
+    }
+
+    if first.chars().count() <= AUTO_TITLE_MAX_CHARS {
+        first
+    } else {
+        let truncated: String = first.chars().take(AUTO_TITLE_MAX_CHARS).collect();
+        format!("{}…", truncated.trim_end())
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::llm_service::ToolCall;
+    use tempfile::TempDir;
+
+    fn message(role: &str, content: &str) -> ChatMessage {
+        ChatMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            name: None,
+            tool_call_id: None,
+            tool_calls: None,
+        }
+    }
+
+    fn input(id: Option<&str>, messages: Vec<ChatMessage>) -> SaveConversationInput {
+        SaveConversationInput {
+            id: id.map(String::from),
+            title: None,
+            provider: "openai".to_string(),
+            model: "gpt-4o".to_string(),
+            linked_document: None,
+            messages,
+        }
+    }
+
+    async fn store() -> (ConversationStore, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let store = ConversationStore::new(temp_dir.path().to_path_buf());
+        store.init().await.unwrap();
+        (store, temp_dir)
+    }
+
+    #[tokio::test]
+    async fn test_save_and_load_round_trip() {
+        let (store, _temp) = store().await;
+
+        let mut assistant = message("assistant", "");
+        assistant.tool_calls = Some(vec![ToolCall {
+            id: "call_1".to_string(),
+            name: "read_document".to_string(),
+            arguments: serde_json::json!({ "path": "notes.midlight" }),
+        }]);
+        let mut tool_result = message("tool", "{\"content\":\"...\"}");
+        tool_result.tool_call_id = Some("call_1".to_string());
+
+        let saved = store
+            .save(input(
+                None,
+                vec![message("user", "Read my notes"), assistant, tool_result],
+            ))
+            .await
+            .unwrap();
+
+        let loaded = store.load(&saved.id).await.unwrap();
+        assert_eq!(loaded.title, "Read my notes");
+        assert_eq!(loaded.model, "gpt-4o");
+        assert_eq!(loaded.messages.len(), 3);
+        assert_eq!(
+            loaded.messages[1].tool_calls.as_ref().unwrap()[0].id,
+            "call_1"
+        );
+        assert_eq!(loaded.messages[2].tool_call_id.as_deref(), Some("call_1"));
+    }
+
+    #[tokio::test]
+    async fn test_update_keeps_created_at_and_title() {
+        let (store, _temp) = store().await;
+
+        let first = store
+            .save(input(None, vec![message("user", "Hello")]))
+            .await
+            .unwrap();
+        store.rename(&first.id, "Greetings").await.unwrap();
+
+        let updated = store
+            .save(input(
+                Some(&first.id),
+                vec![message("user", "Hello"), message("assistant", "Hi!")],
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(updated.id, first.id);
+        assert_eq!(updated.created_at, first.created_at);
+        assert_eq!(updated.title, "Greetings");
+        assert_eq!(updated.messages.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_list_sorted_and_filtered_by_document() {
+        let (store, _temp) = store().await;
+
+        let mut linked = input(None, vec![message("user", "About chapter 1")]);
+        linked.linked_document = Some("chapter-1.midlight".to_string());
+        let linked = store.save(linked).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        let newer = store
+            .save(input(None, vec![message("user", "Unrelated")]))
+            .await
+            .unwrap();
+
+        let all = store.list(None).await.unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].id, newer.id);
+        assert_eq!(all[0].message_count, 1);
+
+        let filtered = store.list(Some("chapter-1.midlight")).await.unwrap();
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].id, linked.id);
+    }
+
+    #[tokio::test]
+    async fn test_list_skips_corrupt_files() {
+        let (store, temp) = store().await;
+
+        store
+            .save(input(None, vec![message("user", "Valid")]))
+            .await
+            .unwrap();
+        std::fs::write(
+            temp.path().join(".midlight/conversations/broken.json"),
+            "{ not json",
+        )
+        .unwrap();
+
+        assert_eq!(store.list(None).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_rename_and_delete() {
+        let (store, _temp) = store().await;
+
+        let saved = store
+            .save(input(None, vec![message("user", "Hello")]))
+            .await
+            .unwrap();
+
+        assert!(store.rename(&saved.id, "   ").await.is_err());
+        let renamed = store.rename(&saved.id, "  Renamed ").await.unwrap();
+        assert_eq!(renamed.title, "Renamed");
+
+        store.delete(&saved.id).await.unwrap();
+        assert!(store.load(&saved.id).await.is_err());
+        assert!(store.list(None).await.unwrap().is_empty());
+
+        // Deleting again is a no-op
+        store.delete(&saved.id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rejects_path_traversal_ids() {
+        let (store, _temp) = store().await;
+
+        assert!(store.load("../../etc/passwd").await.is_err());
+        assert!(store.delete("../outside").await.is_err());
+        assert!(store
+            .save(input(Some("a/b"), vec![message("user", "x")]))
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_derive_title() {
+        assert_eq!(derive_title(&[]), "New conversation");
+        assert_eq!(
+            derive_title(&[
+                message("system", "You are helpful"),
+                message("user", "  Fix   my\nintro  ")
+            ]),
+            "Fix my intro"
+        );
+
+        let long = "word ".repeat(30);
+        let title = derive_title(&[message("user", &long)]);
+        assert!(title.ends_with('…'));
+        assert!(title.chars().count() <= AUTO_TITLE_MAX_CHARS + 1);
+    }
+}
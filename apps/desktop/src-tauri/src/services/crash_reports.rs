@@ -0,0 +1,255 @@
+// Crash Reports - Backend panics saved to disk and offered on the next launch
+//
+// A panic inside an async command is swallowed by the runtime, and one on the
+// main thread takes the process down before anything reaches the user or the
+// error reporter. The panic hook writes the message, location and backtrace
+// to a file first, then hands over to the default hook. Files left by earlier
+// sessions are loaded at startup so the user can send them through the error
+// reporter or dismiss them.
+//
+// Stored at: {app data}/com.midlight.app/crashes/
+
+use crate::services::app_dirs::app_data_dir;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::warn;
+use uuid::Uuid;
+
+/// Backtrace frames from our own code kept when a panic is sent
+const MAX_REPORTED_FRAMES: usize = 12;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PanicReport {
+    pub id: String,
+    pub app_version: String,
+    pub occurred_at: DateTime<Utc>,
+    pub thread: Option<String>,
+    pub message: String,
+    /// file:line:column of the panic
+    pub location: Option<String>,
+    pub backtrace: String,
+}
+
+impl PanicReport {
+    /// Frames from the app's own code, innermost first
+    pub fn app_frames(&self) -> Vec<String> {
+        self.backtrace
+            .lines()
+            .filter_map(|line| {
+                let (index, symbol) = line.trim().split_once(": ")?;
+                index.parse::<usize>().ok()?;
+                symbol.starts_with("midlight").then(|| symbol.to_string())
+            })
+            .take(MAX_REPORTED_FRAMES)
+            .collect()
+    }
+}
+
+// ============================================================================
+// Crash Reports
+// ============================================================================
+
+pub struct CrashReports {
+    dir: PathBuf,
+    /// Panics from earlier sessions not yet sent or dismissed
+    previous: Mutex<Vec<PanicReport>>,
+}
+
+impl CrashReports {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            previous: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Load panics left by earlier sessions, then start saving new ones
+    pub fn install_panic_hook(&'static self, app_version: &str) {
+        *self.previous.lock().unwrap() = self.load_all();
+
+        let app_version = app_version.to_string();
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            // No tracing here: the panic may have happened inside a log writer
+            let report = self.record(
+                &app_version,
+                panic_message(info.payload()),
+                info.location()
+                    .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column())),
+                std::backtrace::Backtrace::force_capture().to_string(),
+            );
+            if let Err(e) = report {
+                eprintln!("{}", e);
+            }
+            default_hook(info);
+        }));
+    }
+
+    /// Panics from earlier sessions, oldest first
+    pub fn previous(&self) -> Vec<PanicReport> {
+        self.previous.lock().unwrap().clone()
+    }
+
+    /// Remove a panic from the list and delete its file
+    pub fn take(&self, id: &str) -> Option<PanicReport> {
+        let mut previous = self.previous.lock().unwrap();
+        let index = previous.iter().position(|r| r.id == id)?;
+        let report = previous.remove(index);
+        let _ = fs::remove_file(self.path_for(&report));
+        Some(report)
+    }
+
+    fn record(
+        &self,
+        app_version: &str,
+        message: String,
+        location: Option<String>,
+        backtrace: String,
+    ) -> Result<PanicReport, String> {
+        let report = PanicReport {
+            id: Uuid::new_v4().to_string(),
+            app_version: app_version.to_string(),
+            occurred_at: Utc::now(),
+            thread: std::thread::current().name().map(str::to_string),
+            message,
+            location,
+            backtrace,
+        };
+
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create crash report directory: {}", e))?;
+        let json = serde_json::to_string_pretty(&report)
+            .map_err(|e| format!("Failed to serialize crash report: {}", e))?;
+        fs::write(self.path_for(&report), json)
+            .map_err(|e| format!("Failed to write crash report: {}", e))?;
+        Ok(report)
+    }
+
+    fn load_all(&self) -> Vec<PanicReport> {
+        let mut reports: Vec<PanicReport> = fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| {
+                let content = fs::read_to_string(&path).ok()?;
+                match serde_json::from_str(&content) {
+                    Ok(report) => Some(report),
+                    Err(e) => {
+                        warn!("Removing unreadable crash report {:?}: {}", path, e);
+                        let _ = fs::remove_file(&path);
+                        None
+                    }
+                }
+            })
+            .collect();
+        reports.sort_by_key(|r| r.occurred_at);
+        reports
+    }
+
+    fn path_for(&self, report: &PanicReport) -> PathBuf {
+        self.dir.join(format!(
+            "panic-{}-{}.json",
+            report.occurred_at.format("%Y%m%d-%H%M%S"),
+            report.id
+        ))
+    }
+}
+
+/// The message passed to panic!, when it's a string
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<dyn Any>".to_string()
+    }
+}
+
+// ============================================================================
+// Global Singleton
+// ============================================================================
+
+lazy_static::lazy_static! {
+    pub static ref CRASH_REPORTS: CrashReports = CrashReports::new(
+        &app_data_dir()
+            .join("crashes"),
+    );
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const BACKTRACE: &str = "   0: std::backtrace::Backtrace::force_capture
+             at /rustc/library/std/src/backtrace.rs:312:9
+   1: midlight_lib::services::crash_reports::CrashReports::install_panic_hook
+   2: core::ops::function::Fn::call
+   3: midlight_lib::services::document_service::DocumentService::load
+             at ./src/services/document_service.rs:120:13
+   4: tokio::runtime::task::harness::Harness<T,S>::poll";
+
+    #[test]
+    fn test_record_and_take() {
+        let temp = TempDir::new().unwrap();
+        let reports = CrashReports::new(temp.path());
+
+        let first = reports
+            .record(
+                "1.2.3",
+                "index out of bounds".to_string(),
+                Some("src/services/document_service.rs:120:13".to_string()),
+                BACKTRACE.to_string(),
+            )
+            .unwrap();
+        reports
+            .record("1.2.3", "second".to_string(), None, String::new())
+            .unwrap();
+        std::fs::write(temp.path().join("garbage.json"), "not json").unwrap();
+
+        // A new session picks up what the last one left
+        let next = CrashReports::new(temp.path());
+        *next.previous.lock().unwrap() = next.load_all();
+        let previous = next.previous();
+        assert_eq!(previous.len(), 2);
+        assert_eq!(previous[0], first);
+        assert!(!temp.path().join("garbage.json").exists());
+
+        assert_eq!(
+            first.app_frames(),
+            vec![
+                "midlight_lib::services::crash_reports::CrashReports::install_panic_hook",
+                "midlight_lib::services::document_service::DocumentService::load",
+            ]
+        );
+
+        assert_eq!(next.take(&first.id), Some(first.clone()));
+        assert_eq!(next.take(&first.id), None);
+        assert_eq!(next.previous().len(), 1);
+        assert_eq!(next.load_all().len(), 1);
+    }
+
+    #[test]
+    fn test_panic_message() {
+        let payload = std::panic::catch_unwind(|| panic!("boom {}", 42)).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "boom 42");
+        let payload = std::panic::catch_unwind(|| panic!("static")).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "static");
+    }
+}
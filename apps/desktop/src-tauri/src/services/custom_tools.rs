@@ -0,0 +1,845 @@
+// Custom Tools - User-defined agent tools backed by local programs
+//
+// A custom tool pairs a name, description and JSON schema for its arguments
+// (all shown to the model) with a runner: either a shell command or a
+// WebAssembly module. The agent's arguments are validated against the schema
+// and passed to the runner as JSON; what it returns is the tool's result
+// (parsed as JSON when possible).
+//
+// Shell commands are not sandboxed. They run as the user, with the user's
+// files and network, just as if typed into a terminal; the arguments arrive
+// on stdin and the result is whatever is printed to stdout. The app only
+// clears the environment, runs them from the workspace, kills them (and
+// anything they started) after the timeout and caps captured output, which is why tools ask before each run
+// unless told otherwise.
+//
+// WebAssembly modules are isolated. They run in the embedded wasmtime with
+// the plugin ABI (see plugins.rs): the arguments are passed to
+// `midlight_tool` and its result is the output. The only host calls are
+// `log` and `error` - no WASI, so no files, network, clock or environment -
+// memory is capped, and the timeout interrupts the module.
+//
+// The registry is per-user rather than per-workspace so that opening someone
+// else's workspace can never add commands for the agent to run.
+//
+// Storage: <app data>/custom-tools.json
+
+use crate::services::app_dirs::app_data_dir;
+use crate::services::atomic_write::write_atomic;
+use crate::services::plugins::{
+    read_memory, read_string, write_guest, MAX_MEMORY_BYTES, MAX_TRANSFER_BYTES,
+};
+use crate::services::structured_output;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, Command};
+use tracing::debug;
+use uuid::Uuid;
+use wasmtime::{
+    Caller, Config, Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder, Trap,
+};
+
+const CUSTOM_TOOLS_VERSION: u32 = 1;
+
+pub const DEFAULT_TIMEOUT_SECS: u64 = 30;
+pub const MAX_TIMEOUT_SECS: u64 = 300;
+
+/// Captured stdout beyond this is discarded and the process is stopped
+pub const MAX_OUTPUT_BYTES: usize = 256 * 1024;
+const MAX_STDERR_BYTES: usize = 16 * 1024;
+
+/// Variables passed through from the app's environment; everything else is cleared
+const INHERITED_ENV_VARS: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USERPROFILE",
+    "LANG",
+    "TMPDIR",
+    "TEMP",
+    "TMP",
+    "SYSTEMROOT",
+];
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ToolRunner {
+    /// Run through the system shell (`sh -c` / `cmd /C`), unsandboxed
+    Command { command: String },
+    /// Run a WebAssembly module built for the plugin ABI, isolated
+    Wasm { module: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomTool {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    /// JSON schema for the tool's arguments
+    pub parameters: Value,
+    pub runner: ToolRunner,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    /// Ask the user before each run
+    #[serde(default = "default_true")]
+    pub is_destructive: bool,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Tool definition sent from the frontend to be saved
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SaveCustomToolInput {
+    /// Omit to create a new tool
+    pub id: Option<String>,
+    pub name: String,
+    pub description: String,
+    pub parameters: Value,
+    pub runner: ToolRunner,
+    pub timeout_secs: Option<u64>,
+    #[serde(default = "default_true")]
+    pub is_destructive: bool,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+/// What a tool run produced
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomToolOutput {
+    /// Parsed JSON if stdout was JSON, otherwise the text
+    pub output: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub stderr: String,
+    pub truncated: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CustomToolsFile {
+    version: u32,
+    #[serde(default)]
+    tools: Vec<CustomTool>,
+}
+
+// ============================================================================
+// Registry
+// ============================================================================
+
+/// Path of the user's custom tool registry
+pub fn registry_path() -> PathBuf {
+    app_data_dir().join("custom-tools.json")
+}
+
+pub struct CustomToolRegistry {
+    tools_path: PathBuf,
+}
+
+impl Default for CustomToolRegistry {
+    fn default() -> Self {
+        Self::new(&registry_path())
+    }
+}
+
+impl CustomToolRegistry {
+    pub fn new(tools_path: &Path) -> Self {
+        Self {
+            tools_path: tools_path.to_path_buf(),
+        }
+    }
+
+    /// List all tools, sorted by name
+    pub fn list(&self) -> Result<Vec<CustomTool>, String> {
+        let mut tools = self.read()?.tools;
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(tools)
+    }
+
+    /// Find an enabled tool by the name the model calls it with
+    pub fn find(&self, name: &str) -> Result<Option<CustomTool>, String> {
+        Ok(self
+            .read()?
+            .tools
+            .into_iter()
+            .find(|t| t.enabled && t.name == name))
+    }
+
+    /// Create or update a tool. `reserved` are names that are already taken
+    /// by built-in tools.
+    pub fn save(
+        &self,
+        input: SaveCustomToolInput,
+        reserved: &[&str],
+    ) -> Result<CustomTool, String> {
+        let name = input.name.trim();
+        validate_name(name)?;
+        if reserved.contains(&name) {
+            return Err(format!("'{}' is the name of a built-in tool", name));
+        }
+        if input.description.trim().is_empty() {
+            return Err("Tool description cannot be empty".to_string());
+        }
+        structured_output::check_schema(&input.parameters)?;
+        validate_runner(&input.runner)?;
+        if let Some(timeout) = input.timeout_secs {
+            if timeout == 0 || timeout > MAX_TIMEOUT_SECS {
+                return Err(format!(
+                    "Timeout must be between 1 and {} seconds",
+                    MAX_TIMEOUT_SECS
+                ));
+            }
+        }
+
+        let mut file = self.read()?;
+        let existing = input
+            .id
+            .as_ref()
+            .and_then(|id| file.tools.iter().position(|t| &t.id == id));
+        if file
+            .tools
+            .iter()
+            .enumerate()
+            .any(|(i, t)| t.name == name && Some(i) != existing)
+        {
+            return Err(format!("A custom tool named '{}' already exists", name));
+        }
+
+        let now = Utc::now();
+        let tool = CustomTool {
+            id: input.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            name: name.to_string(),
+            description: input.description.trim().to_string(),
+            parameters: input.parameters,
+            runner: input.runner,
+            timeout_secs: input.timeout_secs,
+            is_destructive: input.is_destructive,
+            enabled: input.enabled,
+            created_at: existing.map(|i| file.tools[i].created_at).unwrap_or(now),
+            updated_at: now,
+        };
+
+        match existing {
+            Some(index) => file.tools[index] = tool.clone(),
+            None => file.tools.push(tool.clone()),
+        }
+
+        file.version = CUSTOM_TOOLS_VERSION;
+        self.write(&file)?;
+        Ok(tool)
+    }
+
+    /// Delete a tool. Returns false if it didn't exist.
+    pub fn delete(&self, id: &str) -> Result<bool, String> {
+        let mut file = self.read()?;
+        let before = file.tools.len();
+        file.tools.retain(|t| t.id != id);
+
+        if file.tools.len() == before {
+            return Ok(false);
+        }
+        self.write(&file)?;
+        Ok(true)
+    }
+
+    fn read(&self) -> Result<CustomToolsFile, String> {
+        if !self.tools_path.exists() {
+            return Ok(CustomToolsFile {
+                version: CUSTOM_TOOLS_VERSION,
+                tools: Vec::new(),
+            });
+        }
+
+        let content = fs::read_to_string(&self.tools_path)
+            .map_err(|e| format!("Failed to read custom tools: {}", e))?;
+        serde_json::from_str(&content).map_err(|e| format!("Failed to parse custom tools: {}", e))
+    }
+
+    fn write(&self, file: &CustomToolsFile) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(file)
+            .map_err(|e| format!("Failed to serialize custom tools: {}", e))?;
+        write_atomic(&self.tools_path, json)
+            .map_err(|e| format!("Failed to write custom tools: {}", e))
+    }
+}
+
+/// Tool names follow the built-in style: lowercase snake_case
+pub(crate) fn validate_name(name: &str) -> Result<(), String> {
+    let valid = (1..=64).contains(&name.len())
+        && name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid tool name '{}': use lowercase letters, digits and underscores",
+            name
+        ))
+    }
+}
+
+fn validate_runner(runner: &ToolRunner) -> Result<(), String> {
+    match runner {
+        ToolRunner::Command { command } if command.trim().is_empty() => {
+            Err("Tool command cannot be empty".to_string())
+        }
+        ToolRunner::Wasm { module } => {
+            let path = Path::new(module);
+            if !path.is_absolute() {
+                return Err("WASM module path must be absolute".to_string());
+            }
+            if !path.is_file() {
+                return Err(format!("WASM module not found: {}", module));
+            }
+            Ok(())
+        }
+        ToolRunner::Command { .. } => Ok(()),
+    }
+}
+
+// ============================================================================
+// Execution
+// ============================================================================
+
+/// Validate the arguments and run the tool from the workspace root
+pub async fn run_tool(
+    tool: &CustomTool,
+    arguments: &Value,
+    workspace_root: &Path,
+) -> Result<CustomToolOutput, String> {
+    let issues = structured_output::validate(&tool.parameters, arguments);
+    if !issues.is_empty() {
+        let details: Vec<String> = issues.iter().map(|i| i.to_string()).collect();
+        return Err(format!(
+            "Invalid arguments for {}: {}",
+            tool.name,
+            details.join("; ")
+        ));
+    }
+
+    let timeout = Duration::from_secs(
+        tool.timeout_secs
+            .unwrap_or(DEFAULT_TIMEOUT_SECS)
+            .min(MAX_TIMEOUT_SECS),
+    );
+    let input = serde_json::to_vec(arguments)
+        .map_err(|e| format!("Failed to serialize arguments: {}", e))?;
+
+    match &tool.runner {
+        ToolRunner::Command { command } => {
+            run_command(tool, command, workspace_root, input, timeout).await
+        }
+        ToolRunner::Wasm { module } => run_wasm(tool, module, input, timeout).await,
+    }
+}
+
+async fn run_command(
+    tool: &CustomTool,
+    command: &str,
+    workspace_root: &Path,
+    input: Vec<u8>,
+    timeout: Duration,
+) -> Result<CustomToolOutput, String> {
+    let mut command = shell_command(command);
+    command
+        .current_dir(workspace_root)
+        .env_clear()
+        .envs(
+            INHERITED_ENV_VARS
+                .iter()
+                .filter_map(|name| std::env::var_os(name).map(|value| (name.to_string(), value))),
+        )
+        .env("MIDLIGHT_TOOL_NAME", &tool.name)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    run_capped(command, input, timeout).await
+}
+
+fn shell_command(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        // Its own process group, so `kill_tree` reaches whatever it starts
+        #[cfg(unix)]
+        cmd.process_group(0);
+        cmd
+    }
+}
+
+/// Kill the tool along with any processes it started
+fn kill_tree(child: &mut Child) {
+    if let Some(pid) = child.id() {
+        // SAFETY: killpg has no memory-safety preconditions; the group is the
+        // child's, which can't be reused while the child is unreaped
+        #[cfg(unix)]
+        unsafe {
+            libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+        }
+        #[cfg(windows)]
+        {
+            let _ = std::process::Command::new("taskkill")
+                .args(["/T", "/F", "/PID", &pid.to_string()])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status();
+        }
+    }
+    let _ = child.start_kill();
+}
+
+async fn run_capped(
+    mut command: Command,
+    input: Vec<u8>,
+    timeout: Duration,
+) -> Result<CustomToolOutput, String> {
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Failed to start tool: {}", e))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        tokio::spawn(async move {
+            // The tool may exit without reading its input
+            let _ = stdin.write_all(&input).await;
+        });
+    }
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+
+    let run = async {
+        let ((stdout, truncated), (stderr, _)) = tokio::join!(
+            read_capped(stdout, MAX_OUTPUT_BYTES),
+            read_capped(stderr, MAX_STDERR_BYTES)
+        );
+        if truncated {
+            // Stop a tool that keeps writing past the cap
+            kill_tree(&mut child);
+        }
+        (stdout, truncated, stderr, child.wait().await)
+    };
+
+    let (stdout, truncated, stderr, status) = match tokio::time::timeout(timeout, run).await {
+        Ok(result) => result,
+        Err(_) => {
+            kill_tree(&mut child);
+            let _ = child.wait().await;
+            return Err(format!(
+                "Tool timed out after {} seconds",
+                timeout.as_secs()
+            ));
+        }
+    };
+    let status = status.map_err(|e| format!("Failed to run tool: {}", e))?;
+
+    let stderr = String::from_utf8_lossy(&stderr).trim().to_string();
+    if !status.success() && !truncated {
+        let code = status
+            .code()
+            .map_or_else(|| "a signal".to_string(), |c| format!("code {}", c));
+        return Err(if stderr.is_empty() {
+            format!("Tool exited with {}", code)
+        } else {
+            format!("Tool exited with {}: {}", code, stderr)
+        });
+    }
+
+    let text = String::from_utf8_lossy(&stdout).trim().to_string();
+    let output = if truncated {
+        Value::String(text)
+    } else {
+        serde_json::from_str(&text).unwrap_or(Value::String(text))
+    };
+
+    Ok(CustomToolOutput {
+        output,
+        exit_code: status.code(),
+        stderr,
+        truncated,
+    })
+}
+
+/// What a WASM tool's instance can reach
+struct WasmToolState {
+    limits: StoreLimits,
+    /// Message from the module's last `error` call
+    error: Option<String>,
+}
+
+/// Call a module's `midlight_tool` in a fresh instance on a blocking thread,
+/// interrupting it once the timeout passes
+async fn run_wasm(
+    tool: &CustomTool,
+    module: &str,
+    input: Vec<u8>,
+    timeout: Duration,
+) -> Result<CustomToolOutput, String> {
+    if input.len() > MAX_TRANSFER_BYTES {
+        return Err(format!("Input is too large ({} bytes)", input.len()));
+    }
+
+    let mut config = Config::new();
+    config.epoch_interruption(true);
+    let engine = Engine::new(&config).map_err(|e| format!("Failed to start tool: {}", e))?;
+
+    let deadline = {
+        let engine = engine.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(timeout).await;
+            engine.increment_epoch();
+        })
+    };
+    let name = tool.name.clone();
+    let module = PathBuf::from(module);
+    let result =
+        tokio::task::spawn_blocking(move || call_wasm(&engine, &module, &name, &input)).await;
+    deadline.abort();
+
+    let stdout = match result.map_err(|e| format!("Failed to run tool: {}", e))? {
+        Ok(output) => output,
+        Err(Some(e)) => return Err(e),
+        Err(None) => {
+            return Err(format!(
+                "Tool timed out after {} seconds",
+                timeout.as_secs()
+            ))
+        }
+    };
+
+    let truncated = stdout.len() > MAX_OUTPUT_BYTES;
+    let text = String::from_utf8_lossy(&stdout[..stdout.len().min(MAX_OUTPUT_BYTES)])
+        .trim()
+        .to_string();
+    let output = if truncated {
+        Value::String(text)
+    } else {
+        serde_json::from_str(&text).unwrap_or(Value::String(text))
+    };
+
+    Ok(CustomToolOutput {
+        output,
+        exit_code: None,
+        stderr: String::new(),
+        truncated,
+    })
+}
+
+/// Run the module; `Err(None)` means it was interrupted by the timeout
+fn call_wasm(
+    engine: &Engine,
+    module: &Path,
+    name: &str,
+    input: &[u8],
+) -> Result<Vec<u8>, Option<String>> {
+    let failed = |e: wasmtime::Error| Some(format!("Failed to start tool: {}", e));
+    let module = Module::from_file(engine, module).map_err(failed)?;
+
+    let mut store = Store::new(
+        engine,
+        WasmToolState {
+            limits: StoreLimitsBuilder::new()
+                .memory_size(MAX_MEMORY_BYTES)
+                .build(),
+            error: None,
+        },
+    );
+    store.limiter(|state| &mut state.limits);
+    store.set_epoch_deadline(1);
+
+    let mut linker = Linker::new(engine);
+    linker
+        .func_wrap(
+            "midlight",
+            "log",
+            |mut caller: Caller<'_, WasmToolState>, ptr: i32, len: i32| {
+                if let Ok(message) = read_string(&mut caller, ptr, len) {
+                    debug!("[custom tool] {}", message);
+                }
+            },
+        )
+        .map_err(failed)?;
+    linker
+        .func_wrap(
+            "midlight",
+            "error",
+            |mut caller: Caller<'_, WasmToolState>, ptr: i32, len: i32| {
+                if let Ok(message) = read_string(&mut caller, ptr, len) {
+                    caller.data_mut().error = Some(message);
+                }
+            },
+        )
+        .map_err(failed)?;
+
+    let instance = linker.instantiate(&mut store, &module).map_err(failed)?;
+    let entry = instance
+        .get_typed_func::<(i32, i32, i32, i32), i64>(&mut store, "midlight_tool")
+        .map_err(|_| Some("Tool module doesn't export midlight_tool".to_string()))?;
+
+    let result =
+        write_guest(&mut store, &instance, name.as_bytes()).and_then(|(name_ptr, name_len)| {
+            let (input_ptr, input_len) = write_guest(&mut store, &instance, input)?;
+            entry.call(&mut store, (name_ptr, name_len, input_ptr, input_len))
+        });
+    let result = match result {
+        Ok(result) => result,
+        Err(e) if e.downcast_ref::<Trap>() == Some(&Trap::Interrupt) => return Err(None),
+        Err(e) => return Err(Some(format!("Tool failed: {}", e))),
+    };
+
+    if result < 0 {
+        return Err(Some(match &store.data().error {
+            Some(message) => format!("Tool failed: {}", message),
+            None => "Tool failed".to_string(),
+        }));
+    }
+
+    let memory = instance
+        .get_memory(&mut store, "memory")
+        .ok_or_else(|| Some("Tool module doesn't export memory".to_string()))?;
+    read_memory(&memory, &store, result).map_err(|e| Some(format!("Tool {}", e)))
+}
+
+/// Read up to `cap` bytes; the flag reports whether there was more
+async fn read_capped<R: AsyncRead + Unpin>(reader: Option<R>, cap: usize) -> (Vec<u8>, bool) {
+    let mut buf = Vec::new();
+    if let Some(reader) = reader {
+        let _ = reader.take(cap as u64 + 1).read_to_end(&mut buf).await;
+    }
+    let truncated = buf.len() > cap;
+    buf.truncate(cap);
+    (buf, truncated)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn input(name: &str, command: &str) -> SaveCustomToolInput {
+        SaveCustomToolInput {
+            id: None,
+            name: name.to_string(),
+            description: "Test tool".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {"text": {"type": "string"}},
+                "required": ["text"]
+            }),
+            runner: ToolRunner::Command {
+                command: command.to_string(),
+            },
+            timeout_secs: None,
+            is_destructive: true,
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_save_validates_tools() {
+        let temp = TempDir::new().unwrap();
+        let registry = CustomToolRegistry::new(&temp.path().join("custom-tools.json"));
+
+        assert!(registry.save(input("Bad Name", "true"), &[]).is_err());
+        assert!(registry
+            .save(input("read_document", "true"), &["read_document"])
+            .is_err());
+        assert!(registry.save(input("empty", "  "), &[]).is_err());
+        let mut relative_wasm = input("wasm_tool", "");
+        relative_wasm.runner = ToolRunner::Wasm {
+            module: "tool.wasm".to_string(),
+        };
+        assert!(registry.save(relative_wasm, &[]).is_err());
+
+        let saved = registry.save(input("word_count", "wc -w"), &[]).unwrap();
+        assert!(registry.save(input("word_count", "wc -c"), &[]).is_err());
+
+        // Updating keeps the id and creation time
+        let mut update = input("word_count", "wc -c");
+        update.id = Some(saved.id.clone());
+        let updated = registry.save(update, &[]).unwrap();
+        assert_eq!(updated.created_at, saved.created_at);
+        assert_eq!(registry.list().unwrap().len(), 1);
+
+        assert!(registry.find("word_count").unwrap().is_some());
+        assert!(registry.delete(&saved.id).unwrap());
+        assert!(registry.find("word_count").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_runner_serialization() {
+        let runner: ToolRunner =
+            serde_json::from_value(json!({"type": "command", "command": "echo hi"})).unwrap();
+        assert_eq!(
+            runner,
+            ToolRunner::Command {
+                command: "echo hi".to_string()
+            }
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_tool_passes_arguments_on_stdin() {
+        let temp = TempDir::new().unwrap();
+        let registry = CustomToolRegistry::new(&temp.path().join("custom-tools.json"));
+        let tool = registry.save(input("echo_args", "cat"), &[]).unwrap();
+
+        let result = run_tool(&tool, &json!({"text": "hello"}), temp.path())
+            .await
+            .unwrap();
+        assert_eq!(result.output, json!({"text": "hello"}));
+        assert_eq!(result.exit_code, Some(0));
+        assert!(!result.truncated);
+
+        let err = run_tool(&tool, &json!({"text": 5}), temp.path())
+            .await
+            .unwrap_err();
+        assert!(err.contains("Invalid arguments"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_tool_limits() {
+        let temp = TempDir::new().unwrap();
+        let registry = CustomToolRegistry::new(&temp.path().join("custom-tools.json"));
+        let args = json!({"text": "x"});
+
+        // Runs in the workspace with a cleared environment
+        std::env::set_var("MIDLIGHT_TEST_SECRET", "secret");
+        let tool = registry
+            .save(
+                input("env_check", "pwd; echo \"${MIDLIGHT_TEST_SECRET:-unset}\""),
+                &[],
+            )
+            .unwrap();
+        let result = run_tool(&tool, &args, temp.path()).await.unwrap();
+        let output = result.output.as_str().unwrap().to_string();
+        assert!(output.ends_with("unset"));
+        assert!(output.starts_with(&temp.path().canonicalize().unwrap().to_string_lossy()[..]));
+
+        let mut slow = input("slow", "sleep 5");
+        slow.timeout_secs = Some(1);
+        let tool = registry.save(slow, &[]).unwrap();
+        let err = run_tool(&tool, &args, temp.path()).await.unwrap_err();
+        assert!(err.contains("timed out"));
+
+        // The timeout also kills what the shell started
+        let mut spawner = input("spawner", "sleep 30 & echo $! > child.pid; wait");
+        spawner.timeout_secs = Some(1);
+        let tool = registry.save(spawner, &[]).unwrap();
+        let err = run_tool(&tool, &args, temp.path()).await.unwrap_err();
+        assert!(err.contains("timed out"));
+        let pid = std::fs::read_to_string(temp.path().join("child.pid")).unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let state = std::process::Command::new("ps")
+            .args(["-o", "stat=", "-p", pid.trim()])
+            .output()
+            .unwrap();
+        let state = String::from_utf8_lossy(&state.stdout);
+        assert!(state.trim().is_empty() || state.starts_with('Z'));
+
+        let tool = registry.save(input("noisy", "yes"), &[]).unwrap();
+        let result = run_tool(&tool, &args, temp.path()).await.unwrap();
+        assert!(result.truncated);
+        assert!(result.output.as_str().unwrap().len() <= MAX_OUTPUT_BYTES);
+
+        let tool = registry
+            .save(input("failing", "echo broken >&2; exit 3"), &[])
+            .unwrap();
+        let err = run_tool(&tool, &args, temp.path()).await.unwrap_err();
+        assert_eq!(err, "Tool exited with code 3: broken");
+    }
+
+    /// Echoes its input unless the tool is named `loop` (never returns) or
+    /// `fail` (reports an error)
+    const TOOL_MODULE: &str = r##"
+        (module
+          (import "midlight" "error" (func $error (param i32 i32)))
+          (memory (export "memory") 1)
+          (global $next (mut i32) (i32.const 1024))
+          (data (i32.const 16) "bad input")
+
+          (func (export "midlight_alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $next))
+            (global.set $next (i32.add (global.get $next) (local.get $len)))
+            (local.get $ptr))
+
+          (func (export "midlight_tool")
+            (param $name i32) (param $name_len i32) (param $input i32) (param $input_len i32)
+            (result i64)
+            (local $first i32)
+            (local.set $first (i32.load8_u (local.get $name)))
+            (if (i32.eq (local.get $first) (i32.const 108))
+              (then (loop $spin (br $spin))))
+            (if (i32.eq (local.get $first) (i32.const 102))
+              (then
+                (call $error (i32.const 16) (i32.const 9))
+                (return (i64.const -1))))
+            (i64.or
+              (i64.shl (i64.extend_i32_u (local.get $input)) (i64.const 32))
+              (i64.extend_i32_u (local.get $input_len)))))
+    "##;
+
+    #[tokio::test]
+    async fn test_run_wasm_tool() {
+        let temp = TempDir::new().unwrap();
+        let registry = CustomToolRegistry::new(&temp.path().join("custom-tools.json"));
+        let module = temp.path().join("tool.wasm");
+        fs::write(&module, wat::parse_str(TOOL_MODULE).unwrap()).unwrap();
+        let wasm_tool = |name: &str| {
+            let mut tool = input(name, "");
+            tool.runner = ToolRunner::Wasm {
+                module: module.to_string_lossy().to_string(),
+            };
+            tool.timeout_secs = Some(1);
+            registry.save(tool, &[]).unwrap()
+        };
+        let args = json!({"text": "hello"});
+
+        let result = run_tool(&wasm_tool("echo"), &args, temp.path())
+            .await
+            .unwrap();
+        assert_eq!(result.output, args);
+        assert!(!result.truncated);
+
+        let err = run_tool(&wasm_tool("fail"), &args, temp.path())
+            .await
+            .unwrap_err();
+        assert_eq!(err, "Tool failed: bad input");
+
+        let err = run_tool(&wasm_tool("loop"), &args, temp.path())
+            .await
+            .unwrap_err();
+        assert!(err.contains("timed out"));
+    }
+}
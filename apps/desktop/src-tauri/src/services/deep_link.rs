@@ -0,0 +1,131 @@
+// Deep Links - midlight:// URLs from the browser and other apps
+//
+// Two kinds of link are understood:
+//
+//   midlight://auth/callback?code=...&state=...
+//                                       finishes Google sign-in; the browser
+//                                       is sent here after the OAuth flow
+//   midlight://open?path=...            opens a document from another app
+//
+// Links come from outside the app, so the path to open must be absolute and
+// name an existing document (.midlight or .md), and a sign-in callback is only accepted when its
+// state matches a sign-in this app started. Anything else is rejected rather
+// than guessed at.
+
+use std::path::PathBuf;
+use url::Url;
+
+pub const SCHEME: &str = "midlight";
+
+/// Where the browser is sent back to after signing in
+pub const AUTH_CALLBACK_URL: &str = "midlight://auth/callback";
+
+/// File extensions an open link may name
+const DOCUMENT_EXTENSIONS: &[&str] = &["midlight", "md"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeepLink {
+    AuthCallback { code: String, state: Option<String> },
+    Open { path: PathBuf },
+}
+
+/// Parse a midlight:// URL
+pub fn parse(url: &Url) -> Result<DeepLink, String> {
+    if url.scheme() != SCHEME {
+        return Err(format!("Not a {}:// link: {}", SCHEME, url));
+    }
+    let query = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    match (url.host_str(), url.path().trim_end_matches('/')) {
+        (Some("auth"), "/callback") => {
+            let code = query("code").ok_or("Sign-in link has no code")?;
+            Ok(DeepLink::AuthCallback {
+                code,
+                state: query("state"),
+            })
+        }
+        (Some("open"), "") => {
+            let path = PathBuf::from(query("path").ok_or("Open link has no path")?);
+            if !path.is_absolute() {
+                return Err(format!(
+                    "Open link path is not absolute: {}",
+                    path.display()
+                ));
+            }
+            let is_document = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| {
+                    DOCUMENT_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str())
+                });
+            if !is_document {
+                return Err(format!("Open link is not a document: {}", path.display()));
+            }
+            if !path.is_file() {
+                return Err(format!("No such document: {}", path.display()));
+            }
+            Ok(DeepLink::Open { path })
+        }
+        _ => Err(format!("Unknown link: {}", url)),
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn parse_str(url: &str) -> Result<DeepLink, String> {
+        parse(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse_str("midlight://auth/callback?code=abc123&state=x"),
+            Ok(DeepLink::AuthCallback {
+                code: "abc123".to_string(),
+                state: Some("x".to_string()),
+            })
+        );
+        assert!(parse(&Url::parse(AUTH_CALLBACK_URL).unwrap()).is_err());
+        assert!(parse_str("midlight://auth/callback?code=").is_err());
+
+        let temp = TempDir::new().unwrap();
+        let doc = temp.path().join("My Notes.midlight");
+        std::fs::write(&doc, "{}").unwrap();
+        let mut url = Url::parse("midlight://open").unwrap();
+        url.query_pairs_mut()
+            .append_pair("path", &doc.to_string_lossy());
+        assert_eq!(parse(&url), Ok(DeepLink::Open { path: doc }));
+
+        let markdown = temp.path().join("notes.md");
+        std::fs::write(&markdown, "# Notes").unwrap();
+        let mut url = Url::parse("midlight://open").unwrap();
+        url.query_pairs_mut()
+            .append_pair("path", &markdown.to_string_lossy());
+        assert_eq!(parse(&url), Ok(DeepLink::Open { path: markdown }));
+
+        // Existing files that aren't documents are refused
+        let secrets = temp.path().join("passwd");
+        std::fs::write(&secrets, "root:x:0:0").unwrap();
+        let mut url = Url::parse("midlight://open").unwrap();
+        url.query_pairs_mut()
+            .append_pair("path", &secrets.to_string_lossy());
+        assert!(parse(&url).is_err());
+
+        assert!(parse_str("midlight://open?path=notes.midlight").is_err());
+        assert!(parse_str("midlight://open?path=/no/such/file.midlight").is_err());
+        assert!(parse_str("midlight://settings").is_err());
+        assert!(parse_str("https://auth/callback?code=abc").is_err());
+    }
+}
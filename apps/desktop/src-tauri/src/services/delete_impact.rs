@@ -0,0 +1,228 @@
+// Delete Impact - What breaks when a document or folder is deleted
+//
+// Before deleting, the frontend asks what the delete affects: links into the
+// path from other documents, agent changes to it still awaiting review, and
+// (filled in by the caller, which owns the search index) indexed files.
+// Confirming trashes the path and cleans these up together: inbound links
+// become plain text and pending changes are rejected. If any step fails the
+// earlier ones are undone, so the workspace ends up either fully cleaned or
+// as it was.
+
+use crate::services::agent_executor::{ChangeKind, PendingChange};
+use crate::services::atomic_write::write_atomic;
+use crate::services::change_staging::{is_at_or_under, PendingChangeStore};
+use crate::services::link_rewrite::{LinkRewrite, LinkRewriter};
+use crate::services::trash_manager::{TrashItem, TrashManager};
+use crate::services::workspace_paths::document_key;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteImpact {
+    /// Workspace-relative path being deleted
+    pub path: String,
+    /// Documents at or under the path
+    pub documents: Vec<String>,
+    /// Documents elsewhere whose links into the path will be removed
+    pub inbound_links: Vec<LinkRewrite>,
+    /// Agent changes to the path awaiting review, which will be rejected
+    pub pending_changes: Vec<PendingReference>,
+    /// Files whose search index entries will be removed, by absolute path
+    pub indexed_files: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingReference {
+    pub change_id: String,
+    pub path: String,
+    pub kind: ChangeKind,
+    pub description: Option<String>,
+}
+
+impl From<&PendingChange> for PendingReference {
+    fn from(change: &PendingChange) -> Self {
+        Self {
+            change_id: change.change_id.clone(),
+            path: change.path.clone(),
+            kind: change.kind,
+            description: change.description.clone(),
+        }
+    }
+}
+
+// ============================================================================
+// Delete Planner
+// ============================================================================
+
+pub struct DeletePlanner {
+    workspace_root: PathBuf,
+}
+
+impl DeletePlanner {
+    pub fn new(workspace_root: &Path) -> Self {
+        Self {
+            workspace_root: workspace_root.to_path_buf(),
+        }
+    }
+
+    /// What deleting `path` would affect, apart from the search index
+    pub fn impact(&self, path: &Path) -> Result<DeleteImpact, String> {
+        let key = self.key(path)?;
+        let inbound_links = LinkRewriter::new(&self.workspace_root).plan_unlink(path)?;
+        let pending_changes = PendingChangeStore::new(&self.workspace_root)
+            .list()?
+            .iter()
+            .filter(|review| is_at_or_under(&review.change.path, &key))
+            .map(|review| PendingReference::from(&review.change))
+            .collect();
+
+        Ok(DeleteImpact {
+            documents: self.documents(path),
+            path: key,
+            inbound_links,
+            pending_changes,
+            indexed_files: Vec::new(),
+        })
+    }
+
+    /// Move `path` to the trash, remove links to it and reject pending
+    /// changes to it. Returns the trash item and what was cleaned up.
+    pub fn trash(&self, path: &Path) -> Result<(TrashItem, DeleteImpact), String> {
+        let impact = self.impact(path)?;
+        let rewriter = LinkRewriter::new(&self.workspace_root);
+
+        // Linking documents as they are now, to put back on failure
+        let originals: Vec<(PathBuf, String)> = impact
+            .inbound_links
+            .iter()
+            .map(|rewrite| {
+                let document = self.workspace_root.join(&rewrite.path);
+                fs::read_to_string(&document)
+                    .map(|content| (document, content))
+                    .map_err(|e| format!("Failed to read {}: {}", rewrite.path, e))
+            })
+            .collect::<Result<_, _>>()?;
+
+        let trash = TrashManager::new(&self.workspace_root);
+        let item = trash.trash(path)?;
+        let cleaned = rewriter
+            .apply(&impact.inbound_links)
+            .and_then(|_| PendingChangeStore::new(&self.workspace_root).reject_under(&impact.path));
+
+        if let Err(e) = cleaned {
+            for (document, content) in &originals {
+                if let Err(e) = write_atomic(document, content) {
+                    tracing::warn!("Failed to restore {}: {}", document.display(), e);
+                }
+            }
+            if let Err(e) = trash.restore(&item.id) {
+                tracing::warn!("Failed to restore {} from the trash: {}", impact.path, e);
+            }
+            return Err(format!(
+                "Failed to clean up after deleting {}: {}",
+                impact.path, e
+            ));
+        }
+        Ok((item, impact))
+    }
+
+    /// Documents at or under a path, workspace-relative
+    fn documents(&self, path: &Path) -> Vec<String> {
+        WalkDir::new(path)
+            .into_iter()
+            .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter_map(|e| {
+                let relative = e.path().strip_prefix(&self.workspace_root).ok()?;
+                let key = document_key(&relative.to_string_lossy());
+                (key.ends_with(".midlight") || key.ends_with(".md")).then_some(key)
+            })
+            .collect()
+    }
+
+    fn key(&self, path: &Path) -> Result<String, String> {
+        let relative = path
+            .strip_prefix(&self.workspace_root)
+            .map_err(|_| format!("{} is not in the workspace", path.display()))?;
+        Ok(document_key(&relative.to_string_lossy()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn pending(path: &str) -> PendingChange {
+        PendingChange {
+            change_id: format!("change-{}", path),
+            path: path.to_string(),
+            kind: ChangeKind::Edit,
+            original_content: "old".to_string(),
+            new_content: "new".to_string(),
+            description: Some("Tidy up".to_string()),
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            staged_document: Some(json!({ "content": {} })),
+            base_hash: None,
+            attribution: None,
+        }
+    }
+
+    #[test]
+    fn test_trash_with_cleanup() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join(".midlight")).unwrap();
+        fs::create_dir_all(root.join("drafts")).unwrap();
+        fs::write(
+            root.join("drafts/chapter.md"),
+            "# Chapter\n\nSee [[outline]]",
+        )
+        .unwrap();
+        fs::write(root.join("drafts/outline.md"), "- one").unwrap();
+        fs::write(root.join("index.md"), "[Chapter one](drafts/chapter.md)").unwrap();
+        let store = PendingChangeStore::new(root);
+        store.stage(pending("drafts/chapter.md")).unwrap();
+        store.stage(pending("index.md")).unwrap();
+
+        let planner = DeletePlanner::new(root);
+        let impact = planner.impact(&root.join("drafts")).unwrap();
+        assert_eq!(impact.path, "drafts");
+        let mut documents = impact.documents.clone();
+        documents.sort();
+        assert_eq!(documents, vec!["drafts/chapter.md", "drafts/outline.md"]);
+        let linking: Vec<_> = impact
+            .inbound_links
+            .iter()
+            .map(|r| r.path.as_str())
+            .collect();
+        assert_eq!(linking, vec!["index.md"]);
+        assert_eq!(impact.pending_changes.len(), 1);
+        assert_eq!(impact.pending_changes[0].path, "drafts/chapter.md");
+
+        let (item, _) = planner.trash(&root.join("drafts")).unwrap();
+        assert_eq!(item.original_path, "drafts");
+        assert!(!root.join("drafts").exists());
+        assert_eq!(
+            fs::read_to_string(root.join("index.md")).unwrap(),
+            "Chapter one"
+        );
+        let remaining: Vec<_> = store
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|r| r.change.path)
+            .collect();
+        assert_eq!(remaining, vec!["index.md"]);
+    }
+}
@@ -0,0 +1,254 @@
+// Directory Listing - Paged and streamed folder contents for the file tree
+//
+// Folders with thousands of entries are listed a page at a time. Entries are
+// ordered directories first, then by case-insensitive name, and a page's
+// cursor is the sort key of its last entry, so paging stays consistent when
+// entries are added or removed between requests. Whole trees are walked in
+// batches handed to a callback, which the caller streams to the UI.
+
+use std::cmp::Ordering;
+use std::io;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Entries per page when the caller doesn't say
+pub const DEFAULT_PAGE_SIZE: usize = 200;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListedEntry {
+    pub name: String,
+    pub path: PathBuf,
+    pub is_dir: bool,
+    /// Number of visible children, for directories when asked for
+    pub child_count: Option<usize>,
+}
+
+impl ListedEntry {
+    /// Position in the listing order, also used as the page cursor
+    fn sort_key(&self) -> String {
+        format!(
+            "{}:{}\u{0}{}",
+            if self.is_dir { 0 } else { 1 },
+            self.name.to_lowercase(),
+            self.name
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ListPage {
+    pub entries: Vec<ListedEntry>,
+    /// Pass back to get the next page; `None` on the last page
+    pub next_cursor: Option<String>,
+    /// Visible entries in the whole folder
+    pub total: usize,
+}
+
+// ============================================================================
+// Listing
+// ============================================================================
+
+/// Directories first, then by case-insensitive name
+pub fn compare_entries(a_is_dir: bool, a_name: &str, b_is_dir: bool, b_name: &str) -> Ordering {
+    b_is_dir
+        .cmp(&a_is_dir)
+        .then_with(|| a_name.to_lowercase().cmp(&b_name.to_lowercase()))
+        .then_with(|| a_name.cmp(b_name))
+}
+
+/// One page of a folder's direct children that pass `show`, starting after
+/// `cursor`
+pub fn list_page(
+    dir: &Path,
+    show: impl Fn(&str) -> bool,
+    cursor: Option<&str>,
+    limit: usize,
+    child_counts: bool,
+) -> io::Result<ListPage> {
+    let mut entries = children(dir, &show)?;
+    entries.sort_by(|a, b| compare_entries(a.is_dir, &a.name, b.is_dir, &b.name));
+    let total = entries.len();
+
+    let start = match cursor {
+        Some(cursor) => entries.partition_point(|e| e.sort_key().as_str() <= cursor),
+        None => 0,
+    };
+    let end = (start + limit.max(1)).min(total);
+    let mut page: Vec<ListedEntry> = entries.drain(start..end).collect();
+
+    if child_counts {
+        for entry in page.iter_mut().filter(|e| e.is_dir) {
+            entry.child_count = children(&entry.path, &show).ok().map(|c| c.len());
+        }
+    }
+
+    let next_cursor = if end < total {
+        page.last().map(ListedEntry::sort_key)
+    } else {
+        None
+    };
+    Ok(ListPage {
+        entries: page,
+        next_cursor,
+        total,
+    })
+}
+
+/// Walk a whole tree, skipping entries (and folders) that don't pass `show`,
+/// handing entries to `on_batch` in batches. Folders come before their
+/// contents. Stops early when `on_batch` returns false. Returns the number
+/// of entries delivered.
+pub fn walk_batches(
+    dir: &Path,
+    show: impl Fn(&str) -> bool,
+    batch_size: usize,
+    mut on_batch: impl FnMut(Vec<ListedEntry>) -> bool,
+) -> usize {
+    let batch_size = batch_size.max(1);
+    let mut batch = Vec::with_capacity(batch_size);
+    let mut delivered = 0;
+
+    let walker = WalkDir::new(dir)
+        .min_depth(1)
+        .sort_by(|a, b| {
+            compare_entries(
+                a.file_type().is_dir(),
+                &a.file_name().to_string_lossy(),
+                b.file_type().is_dir(),
+                &b.file_name().to_string_lossy(),
+            )
+        })
+        .into_iter()
+        .filter_entry(|e| show(&e.file_name().to_string_lossy()));
+
+    for entry in walker.filter_map(|e| e.ok()) {
+        batch.push(ListedEntry {
+            name: entry.file_name().to_string_lossy().to_string(),
+            path: entry.path().to_path_buf(),
+            is_dir: entry.file_type().is_dir(),
+            child_count: None,
+        });
+
+        if batch.len() == batch_size {
+            delivered += batch.len();
+            if !on_batch(std::mem::replace(
+                &mut batch,
+                Vec::with_capacity(batch_size),
+            )) {
+                return delivered;
+            }
+        }
+    }
+
+    if !batch.is_empty() {
+        delivered += batch.len();
+        on_batch(batch);
+    }
+    delivered
+}
+
+fn children(dir: &Path, show: &impl Fn(&str) -> bool) -> io::Result<Vec<ListedEntry>> {
+    let mut entries = Vec::new();
+    for entry in std::fs::read_dir(dir)?.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !show(&name) {
+            continue;
+        }
+        // Follows symlinks, like Path::is_dir
+        let path = entry.path();
+        entries.push(ListedEntry {
+            is_dir: path.is_dir(),
+            name,
+            path,
+            child_count: None,
+        });
+    }
+    Ok(entries)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn visible(name: &str) -> bool {
+        !name.starts_with('.')
+    }
+
+    fn names(entries: &[ListedEntry]) -> Vec<&str> {
+        entries.iter().map(|e| e.name.as_str()).collect()
+    }
+
+    fn setup() -> TempDir {
+        let temp = TempDir::new().unwrap();
+        for dir in ["b-folder", "A-folder", "A-folder/nested", ".hidden"] {
+            fs::create_dir_all(temp.path().join(dir)).unwrap();
+        }
+        for file in [
+            "c.md",
+            "a.md",
+            "B.md",
+            ".secret",
+            "A-folder/one.md",
+            "A-folder/.two",
+            "A-folder/nested/deep.md",
+            ".hidden/x.md",
+        ] {
+            fs::write(temp.path().join(file), "").unwrap();
+        }
+        temp
+    }
+
+    #[test]
+    fn test_list_pages() {
+        let temp = setup();
+
+        let first = list_page(temp.path(), visible, None, 3, true).unwrap();
+        assert_eq!(first.total, 5);
+        assert_eq!(names(&first.entries), vec!["A-folder", "b-folder", "a.md"]);
+        assert_eq!(first.entries[0].child_count, Some(2));
+        assert_eq!(first.entries[1].child_count, Some(0));
+        assert_eq!(first.entries[2].child_count, None);
+
+        // A file added before the cursor doesn't shift the next page
+        fs::write(temp.path().join("0.md"), "").unwrap();
+        let cursor = first.next_cursor.unwrap();
+        let second = list_page(temp.path(), visible, Some(&cursor), 3, false).unwrap();
+        assert_eq!(names(&second.entries), vec!["B.md", "c.md"]);
+        assert!(second.next_cursor.is_none());
+        assert_eq!(second.entries[0].child_count, None);
+    }
+
+    #[test]
+    fn test_walk_batches() {
+        let temp = setup();
+
+        let mut batches = Vec::new();
+        let delivered = walk_batches(temp.path(), visible, 3, |batch| {
+            batches.push(names(&batch).join(","));
+            true
+        });
+        assert_eq!(delivered, 8);
+        assert_eq!(
+            batches,
+            vec![
+                "A-folder,nested,deep.md",
+                "one.md,b-folder,a.md",
+                "B.md,c.md"
+            ]
+        );
+
+        // Stopping after the first batch
+        let delivered = walk_batches(temp.path(), visible, 3, |_| false);
+        assert_eq!(delivered, 3);
+    }
+}
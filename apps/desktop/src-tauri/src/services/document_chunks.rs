@@ -0,0 +1,290 @@
+// Document Chunks - Section-by-section storage for very long documents
+//
+// A document's content is normally stored whole in its .midlight file, so
+// every save rewrites and every load parses all of it. Once the content
+// passes CHUNK_THRESHOLD it's split into sections at top-level headings (or
+// every MAX_SECTION_BLOCKS blocks) and each section is stored in the object
+// store. The .midlight file keeps a manifest under "chunks" in place of
+// "content". Sections are content-addressed, so a save writes only the
+// sections that changed, and the editor can load sections one at a time.
+//
+// A document with both "content" and "chunks" was last written by something
+// that doesn't know about chunks (an agent edit, find and replace); its
+// "content" is current and the manifest is ignored until the next save.
+//
+// Storage: .midlight/objects (shared with checkpoints)
+// Format: "chunks": { "version": 1, "sections": [{ "hash", "title", "blocks", "words" }] }
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::Path;
+
+use super::markdown_convert::{extract_text_content, extract_text_from_tiptap};
+use super::object_store::ObjectStore;
+use super::workspace_paths::find_workspace_root;
+
+/// Serialized content size past which a document is stored in sections
+pub const CHUNK_THRESHOLD: usize = 1024 * 1024;
+
+/// Most top-level blocks in one section, for long stretches without headings
+const MAX_SECTION_BLOCKS: usize = 400;
+
+/// Deepest heading that starts a new section
+const SECTION_HEADING_LEVEL: u64 = 2;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChunkManifest {
+    pub version: u32,
+    pub sections: Vec<SectionEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SectionEntry {
+    /// Object store hash of the section's blocks
+    pub hash: String,
+    /// Text of the heading the section starts with
+    pub title: Option<String>,
+    /// Number of top-level blocks
+    pub blocks: usize,
+    pub words: usize,
+}
+
+/// Sections split from a document, before they're stored
+pub struct Sections {
+    pub manifest: ChunkManifest,
+    /// Serialized blocks of each section, in manifest order
+    serialized: Vec<String>,
+}
+
+impl Sections {
+    /// Split a Tiptap `doc` into sections
+    pub fn split(content: &Value, store: &ObjectStore) -> Self {
+        let blocks = content
+            .get("content")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+
+        let mut groups: Vec<&[Value]> = Vec::new();
+        let mut start = 0;
+        for (i, block) in blocks.iter().enumerate() {
+            if i > start && (starts_section(block) || i - start >= MAX_SECTION_BLOCKS) {
+                groups.push(&blocks[start..i]);
+                start = i;
+            }
+        }
+        if start < blocks.len() {
+            groups.push(&blocks[start..]);
+        }
+
+        let mut sections = Vec::new();
+        let mut serialized = Vec::new();
+        for group in groups {
+            let text = Value::Array(group.to_vec()).to_string();
+            sections.push(SectionEntry {
+                hash: store.hash(&text),
+                title: group
+                    .first()
+                    .filter(|block| starts_section(block))
+                    .map(|block| extract_text_content(block).trim().to_string()),
+                blocks: group.len(),
+                words: group
+                    .iter()
+                    .map(|block| extract_text_from_tiptap(block).split_whitespace().count())
+                    .sum(),
+            });
+            serialized.push(text);
+        }
+
+        Self {
+            manifest: ChunkManifest {
+                version: 1,
+                sections,
+            },
+            serialized,
+        }
+    }
+
+    /// The blocks of one section
+    pub fn blocks(&self, index: usize) -> Option<Vec<Value>> {
+        serde_json::from_str(self.serialized.get(index)?).ok()
+    }
+
+    /// Total serialized size of the sections
+    pub fn size(&self) -> usize {
+        self.serialized.iter().map(String::len).sum()
+    }
+
+    /// Whether the document is long enough to store in sections
+    pub fn should_chunk(&self) -> bool {
+        self.size() >= CHUNK_THRESHOLD
+    }
+
+    /// Write sections not already in the store. Returns how many were
+    /// written.
+    pub fn store(&self, store: &ObjectStore) -> Result<usize, String> {
+        let mut written = 0;
+        for (entry, text) in self.manifest.sections.iter().zip(&self.serialized) {
+            if store.exists_now(&entry.hash) {
+                continue;
+            }
+            store
+                .write_now(text)
+                .map_err(|e| format!("Failed to store section: {}", e))?;
+            written += 1;
+        }
+        Ok(written)
+    }
+}
+
+fn starts_section(block: &Value) -> bool {
+    block.get("type").and_then(Value::as_str) == Some("heading")
+        && block
+            .get("attrs")
+            .and_then(|a| a.get("level"))
+            .and_then(Value::as_u64)
+            .map_or(true, |level| level <= SECTION_HEADING_LEVEL)
+}
+
+// ============================================================================
+// Reading
+// ============================================================================
+
+/// The manifest of a chunked .midlight document, if it's stored in sections
+pub fn manifest(document: &Value) -> Option<ChunkManifest> {
+    if document.get("content").is_some() {
+        return None;
+    }
+    serde_json::from_value(document.get("chunks")?.clone()).ok()
+}
+
+/// The blocks of one section
+pub fn load_section(store: &ObjectStore, entry: &SectionEntry) -> Result<Vec<Value>, String> {
+    let text = store
+        .read_now(&entry.hash)
+        .map_err(|e| format!("Failed to read section: {}", e))?;
+    serde_json::from_str(&text).map_err(|e| format!("Failed to parse section: {}", e))
+}
+
+/// A chunked document's content, put back together
+pub fn load_content(store: &ObjectStore, manifest: &ChunkManifest) -> Result<Value, String> {
+    let mut blocks = Vec::new();
+    for entry in &manifest.sections {
+        blocks.extend(load_section(store, entry)?);
+    }
+    if blocks.is_empty() {
+        blocks.push(json!({ "type": "paragraph" }));
+    }
+    Ok(json!({ "type": "doc", "content": blocks }))
+}
+
+/// Fill in "content" of a .midlight document read from `path` if it's
+/// stored in sections, so code reading documents from disk doesn't need to
+/// know about chunking. Leaves the document as it is on failure.
+pub fn resolve_content(path: &Path, document: &mut Value) {
+    let Some(manifest) = manifest(document) else {
+        return;
+    };
+    let Some(root) = find_workspace_root(path) else {
+        return;
+    };
+    match load_content(&ObjectStore::new(&root), &manifest) {
+        Ok(content) => {
+            document["content"] = content;
+        }
+        Err(e) => tracing::warn!("Failed to load {}: {}", path.display(), e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn heading(level: u64, text: &str) -> Value {
+        json!({ "type": "heading", "attrs": { "level": level },
+                "content": [{ "type": "text", "text": text }] })
+    }
+
+    fn paragraph(text: &str) -> Value {
+        json!({ "type": "paragraph", "content": [{ "type": "text", "text": text }] })
+    }
+
+    #[test]
+    fn test_split_store_and_load() {
+        let temp = TempDir::new().unwrap();
+        let store = ObjectStore::new(temp.path());
+        let doc = json!({ "type": "doc", "content": [
+            paragraph("Epigraph"),
+            heading(1, "Part one"),
+            paragraph("It was a dark night"),
+            heading(3, "An aside"),
+            paragraph("Not a new section"),
+            heading(2, "Chapter two"),
+            paragraph("Morning came")
+        ]});
+
+        let sections = Sections::split(&doc, &store);
+        let manifest = &sections.manifest;
+        let titles: Vec<_> = manifest
+            .sections
+            .iter()
+            .map(|s| s.title.as_deref())
+            .collect();
+        assert_eq!(titles, vec![None, Some("Part one"), Some("Chapter two")]);
+        assert_eq!(manifest.sections[1].blocks, 4);
+        assert_eq!(manifest.sections[1].words, 13);
+        assert!(!sections.should_chunk());
+
+        assert_eq!(sections.store(&store).unwrap(), 3);
+        assert_eq!(load_content(&store, manifest).unwrap(), doc);
+
+        // Only the edited section is written again
+        let mut edited = doc.clone();
+        edited["content"][6] = paragraph("Evening came");
+        let resplit = Sections::split(&edited, &store);
+        assert_eq!(resplit.store(&store).unwrap(), 1);
+        assert_eq!(resplit.manifest.sections[1], manifest.sections[1]);
+        assert_eq!(
+            load_section(&store, &resplit.manifest.sections[2]).unwrap()[1],
+            paragraph("Evening came")
+        );
+    }
+
+    #[test]
+    fn test_resolve_content_of_chunked_document() {
+        let temp = TempDir::new().unwrap();
+        let store = ObjectStore::new(temp.path());
+        std::fs::create_dir_all(temp.path().join(".midlight")).unwrap();
+        let doc = json!({ "type": "doc", "content": [
+            heading(1, "Part one"),
+            paragraph("It was a dark night"),
+            heading(2, "Chapter two"),
+            paragraph("Morning came")
+        ]});
+        let sections = Sections::split(&doc, &store);
+        sections.store(&store).unwrap();
+
+        let path = temp.path().join("novel.midlight");
+        let mut document = json!({
+            "version": 1,
+            "meta": {},
+            "chunks": sections.manifest
+        });
+        resolve_content(&path, &mut document);
+
+        assert_eq!(document["content"], doc);
+        let words: usize = sections.manifest.sections.iter().map(|s| s.words).sum();
+        assert_eq!(
+            crate::services::document_stats::midlight_stats(&document).words,
+            words
+        );
+        assert_eq!(words, 11);
+    }
+}
@@ -0,0 +1,112 @@
+// Document Lock - Read-only documents
+//
+// A locked document has `"locked": true` in its meta. Editor saves, raw
+// file writes, workspace find and replace and the agent's editing tools
+// leave it alone until it's unlocked. Moving, renaming or copying the file
+// keeps the lock, since it travels with the document.
+
+use crate::services::atomic_write::write_atomic;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/// Whether a .midlight document's JSON is locked
+pub fn is_locked_document(document: &Value) -> bool {
+    document
+        .get("meta")
+        .and_then(|m| m.get("locked"))
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+/// Whether the document at `path` is locked. Files that can't be read or
+/// aren't .midlight documents never are.
+pub fn is_locked(path: &Path) -> bool {
+    if path.extension().map_or(true, |ext| ext != "midlight") {
+        return false;
+    }
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+        .is_some_and(|document| is_locked_document(&document))
+}
+
+/// An error naming `display_path` if the document at `path` is locked
+pub fn ensure_unlocked(path: &Path, display_path: &str) -> Result<(), String> {
+    if is_locked(path) {
+        return Err(format!("Document is locked: {}", display_path));
+    }
+    Ok(())
+}
+
+/// Lock or unlock a .midlight document. Its modified time is left as is, as
+/// the content doesn't change.
+pub fn set_locked(path: &Path, locked: bool) -> Result<(), String> {
+    if path.extension().map_or(true, |ext| ext != "midlight") {
+        return Err("Only .midlight documents can be locked".to_string());
+    }
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read document: {}", e))?;
+    let mut document: Value =
+        serde_json::from_str(&content).map_err(|e| format!("Failed to parse document: {}", e))?;
+    if is_locked_document(&document) == locked {
+        return Ok(());
+    }
+
+    let Some(root) = document.as_object_mut() else {
+        return Err("Failed to parse document: not an object".to_string());
+    };
+    let meta = root
+        .entry("meta")
+        .or_insert_with(|| Value::Object(Default::default()));
+    let Some(meta) = meta.as_object_mut() else {
+        return Err("Failed to parse document: meta is not an object".to_string());
+    };
+    if locked {
+        meta.insert("locked".to_string(), Value::Bool(true));
+    } else {
+        meta.remove("locked");
+    }
+
+    let json = serde_json::to_string_pretty(&document)
+        .map_err(|e| format!("Failed to serialize document: {}", e))?;
+    write_atomic(path, json).map_err(|e| format!("Failed to write document: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_set_locked() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("chapter.midlight");
+        let document = json!({
+            "version": 1,
+            "meta": { "created": "2026-01-01T00:00:00Z", "modified": "2026-01-02T00:00:00Z" },
+            "content": { "type": "doc", "content": [] }
+        });
+        fs::write(&path, document.to_string()).unwrap();
+        assert!(ensure_unlocked(&path, "chapter.midlight").is_ok());
+
+        set_locked(&path, true).unwrap();
+        assert!(is_locked(&path));
+        assert_eq!(
+            ensure_unlocked(&path, "chapter.midlight").unwrap_err(),
+            "Document is locked: chapter.midlight"
+        );
+        let saved: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved["meta"]["modified"], "2026-01-02T00:00:00Z");
+
+        set_locked(&path, false).unwrap();
+        assert!(!is_locked(&path));
+        let saved: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(saved["meta"].get("locked").is_none());
+
+        fs::write(temp.path().join("notes.md"), "# Notes").unwrap();
+        assert!(set_locked(&temp.path().join("notes.md"), true).is_err());
+        assert!(!is_locked(&temp.path().join("missing.midlight")));
+    }
+}
@@ -0,0 +1,284 @@
+// Document Outline - The heading tree of a .midlight document
+//
+// Headings get anchor ids for `#anchor` links: the heading's own `id`
+// attribute if the editor gave it one, otherwise a slug of its text
+// ("Next Steps" -> "next-steps"), with "-1", "-2"... added to repeats in
+// document order. Positions are ProseMirror positions (UTF-16 offsets, with
+// each node boundary counting one), so the editor can scroll to or select a
+// heading without parsing the document itself.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Nodes without content that still take up a position
+const LEAF_NODES: &[&str] = &["image", "hardBreak", "horizontalRule", "mention"];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutlineHeading {
+    /// Anchor id, unique within the document
+    pub id: String,
+    pub text: String,
+    pub level: u8,
+    /// ProseMirror position just before the heading
+    pub pos: usize,
+    /// Index of the top-level block containing the heading
+    pub block: usize,
+    /// Position where the heading's section ends: the next heading of the
+    /// same or a higher level, or the end of the document
+    pub end: usize,
+    pub children: Vec<OutlineHeading>,
+}
+
+/// Headings of a .midlight document's JSON (the whole file, or just its
+/// Tiptap content), nested by level
+pub fn document_outline(document: &Value) -> Vec<OutlineHeading> {
+    nest(flat_headings(document))
+}
+
+/// The heading an anchor points to. Anchors match a heading's id, or its
+/// text as written in wiki links (`[[Doc#Next Steps]]`), and may start with '#'.
+pub fn find_heading(document: &Value, anchor: &str) -> Option<OutlineHeading> {
+    let anchor = anchor.trim().trim_start_matches('#');
+    let headings = flat_headings(document);
+    let slug = slugify(anchor);
+    headings
+        .iter()
+        .find(|h| h.id == anchor)
+        .or_else(|| {
+            headings
+                .iter()
+                .find(|h| h.text.eq_ignore_ascii_case(anchor))
+        })
+        .or_else(|| headings.iter().find(|h| !slug.is_empty() && h.id == slug))
+        .cloned()
+}
+
+/// GitHub-style slug: lowercase, punctuation dropped, spaces as hyphens
+pub fn slugify(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            c if c.is_whitespace() => Some('-'),
+            _ => None,
+        })
+        .collect()
+}
+
+fn nest(headings: Vec<OutlineHeading>) -> Vec<OutlineHeading> {
+    let mut roots: Vec<OutlineHeading> = Vec::new();
+    // Headings whose children are still being collected, outermost first
+    let mut open: Vec<OutlineHeading> = Vec::new();
+
+    for heading in headings {
+        while open
+            .last()
+            .is_some_and(|parent| parent.level >= heading.level)
+        {
+            close(&mut open, &mut roots);
+        }
+        open.push(heading);
+    }
+    while !open.is_empty() {
+        close(&mut open, &mut roots);
+    }
+    roots
+}
+
+fn close(open: &mut Vec<OutlineHeading>, roots: &mut Vec<OutlineHeading>) {
+    let Some(heading) = open.pop() else {
+        return;
+    };
+    match open.last_mut() {
+        Some(parent) => parent.children.push(heading),
+        None => roots.push(heading),
+    }
+}
+
+/// Headings in document order, with positions and section ends
+fn flat_headings(document: &Value) -> Vec<OutlineHeading> {
+    let content = document
+        .get("content")
+        .filter(|c| c.is_object())
+        .unwrap_or(document);
+    let blocks = content
+        .get("content")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+
+    let mut headings = Vec::new();
+    let mut pos = 0;
+    for (block, node) in blocks.iter().enumerate() {
+        collect_headings(node, pos, block, &mut headings);
+        pos += node_size(node);
+    }
+    let document_end = pos;
+
+    // Section ends, then unique ids
+    for i in 0..headings.len() {
+        let level = headings[i].level;
+        headings[i].end = headings[i + 1..]
+            .iter()
+            .find(|h| h.level <= level)
+            .map_or(document_end, |h| h.pos);
+    }
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for heading in &mut headings {
+        let base = heading.id.clone();
+        let count = seen.entry(base.clone()).or_insert(0);
+        if *count > 0 {
+            heading.id = format!("{}-{}", base, count);
+        }
+        *count += 1;
+    }
+    headings
+}
+
+fn collect_headings(node: &Value, pos: usize, block: usize, headings: &mut Vec<OutlineHeading>) {
+    if node.get("type").and_then(Value::as_str) == Some("heading") {
+        let text = node_text(node);
+        let level = node
+            .pointer("/attrs/level")
+            .and_then(Value::as_u64)
+            .unwrap_or(1)
+            .clamp(1, 6) as u8;
+        let id = node
+            .pointer("/attrs/id")
+            .and_then(Value::as_str)
+            .filter(|id| !id.is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| slugify(&text));
+        headings.push(OutlineHeading {
+            id,
+            text,
+            level,
+            pos,
+            block,
+            end: 0,
+            children: Vec::new(),
+        });
+        return;
+    }
+
+    // Headings nested in blockquotes, callouts and the like; a node's
+    // content starts one position after its opening boundary
+    let mut child_pos = pos + 1;
+    for child in node
+        .get("content")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        collect_headings(child, child_pos, block, headings);
+        child_pos += node_size(child);
+    }
+}
+
+/// Size of a node in ProseMirror positions
+fn node_size(node: &Value) -> usize {
+    if let Some(text) = node.get("text").and_then(Value::as_str) {
+        return text.encode_utf16().count();
+    }
+    let node_type = node.get("type").and_then(Value::as_str).unwrap_or("");
+    if LEAF_NODES.contains(&node_type) {
+        return 1;
+    }
+    let content: usize = node
+        .get("content")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(node_size)
+        .sum();
+    content + 2
+}
+
+fn node_text(node: &Value) -> String {
+    let mut text = String::new();
+    if let Some(t) = node.get("text").and_then(Value::as_str) {
+        text.push_str(t);
+    }
+    for child in node
+        .get("content")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        text.push_str(&node_text(child));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn heading(level: u8, text: &str) -> Value {
+        json!({ "type": "heading", "attrs": { "level": level }, "content": [{ "type": "text", "text": text }] })
+    }
+
+    fn paragraph(text: &str) -> Value {
+        json!({ "type": "paragraph", "content": [{ "type": "text", "text": text }] })
+    }
+
+    fn document() -> Value {
+        json!({
+            "version": 1,
+            "content": { "type": "doc", "content": [
+                heading(1, "Plan"),                // 0..6
+                paragraph("Intro 😀"),             // 6..16
+                heading(2, "Next Steps!"),         // 16..29
+                heading(3, "Notes"),               // 29..36
+                { "type": "horizontalRule" },      // 36..37
+                heading(2, "Notes"),               // 37..44
+                { "type": "blockquote", "content": [heading(3, "Quoted")] }, // 44..54
+                heading(1, "Appendix")             // 54..64
+            ]}
+        })
+    }
+
+    #[test]
+    fn test_outline() {
+        let outline = document_outline(&document());
+        assert_eq!(outline.len(), 2);
+
+        let plan = &outline[0];
+        assert_eq!((plan.id.as_str(), plan.pos, plan.end), ("plan", 0, 54));
+        let ids: Vec<&str> = plan.children.iter().map(|h| h.id.as_str()).collect();
+        assert_eq!(ids, vec!["next-steps", "notes-1"]);
+
+        let next = &plan.children[0];
+        assert_eq!((next.pos, next.end, next.block), (16, 37, 2));
+        assert_eq!(next.children[0].id, "notes");
+        assert_eq!(next.children[0].end, 37);
+
+        let quoted = &plan.children[1].children[0];
+        assert_eq!(
+            (quoted.text.as_str(), quoted.pos, quoted.block),
+            ("Quoted", 45, 6)
+        );
+
+        assert_eq!((outline[1].pos, outline[1].end), (54, 64));
+    }
+
+    #[test]
+    fn test_find_heading() {
+        let document = document();
+        assert_eq!(find_heading(&document, "#next-steps").unwrap().pos, 16);
+        assert_eq!(find_heading(&document, "Next Steps!").unwrap().pos, 16);
+        assert_eq!(find_heading(&document, "notes-1").unwrap().level, 2);
+        assert_eq!(find_heading(&document, "notes").unwrap().level, 3);
+        assert!(find_heading(&document, "missing").is_none());
+
+        let with_id = json!({ "type": "doc", "content": [
+            { "type": "heading", "attrs": { "level": 2, "id": "h-42" }, "content": [{ "type": "text", "text": "Title" }] }
+        ]});
+        assert_eq!(document_outline(&with_id)[0].id, "h-42");
+        assert_eq!(find_heading(&with_id, "title").unwrap().id, "h-42");
+    }
+}
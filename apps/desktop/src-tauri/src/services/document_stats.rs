@@ -0,0 +1,165 @@
+// Document Stats - Word, character and reading-time counts for documents
+//
+// Counts are taken from the text of a .midlight document's Tiptap content,
+// with block nodes (paragraphs, headings, list items...) separated so words
+// on either side of a block boundary aren't joined. Markdown and plain text
+// files are counted as they are.
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// Average silent reading speed, in words per minute
+pub const READING_WORDS_PER_MINUTE: usize = 238;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentStats {
+    pub words: usize,
+    /// Characters including spaces, excluding line breaks between blocks
+    pub characters: usize,
+    pub characters_no_spaces: usize,
+    pub paragraphs: usize,
+    /// Whole minutes, rounded up; zero for an empty document
+    pub reading_minutes: usize,
+}
+
+/// Stats for a .midlight document's JSON (the whole file, or just its
+/// Tiptap content)
+pub fn midlight_stats(document: &Value) -> DocumentStats {
+    stats_for_blocks(&midlight_blocks(document))
+}
+
+/// The text of each block in a .midlight document's JSON, in order
+pub fn midlight_blocks(document: &Value) -> Vec<String> {
+    // A bare Tiptap doc's "content" is its array of blocks
+    let content = document
+        .get("content")
+        .filter(|c| c.is_object())
+        .unwrap_or(document);
+    let mut blocks = Vec::new();
+    collect_blocks(content, &mut String::new(), &mut blocks);
+    blocks
+}
+
+/// Stats for plain text or Markdown, with paragraphs separated by blank lines
+pub fn text_stats(text: &str) -> DocumentStats {
+    let blocks: Vec<String> = text
+        .split("\n\n")
+        .map(|block| block.trim().to_string())
+        .collect();
+    stats_for_blocks(&blocks)
+}
+
+fn stats_for_blocks(blocks: &[String]) -> DocumentStats {
+    let mut stats = DocumentStats::default();
+    for block in blocks.iter().filter(|b| !b.trim().is_empty()) {
+        stats.paragraphs += 1;
+        stats.words += block.split_whitespace().count();
+        for c in block.chars().filter(|c| *c != '\n') {
+            stats.characters += 1;
+            if !c.is_whitespace() {
+                stats.characters_no_spaces += 1;
+            }
+        }
+    }
+    stats.reading_minutes = (stats.words + READING_WORDS_PER_MINUTE - 1) / READING_WORDS_PER_MINUTE;
+    stats
+}
+
+/// Gather the text of each textblock (a node whose children are inline)
+fn collect_blocks(node: &Value, current: &mut String, blocks: &mut Vec<String>) {
+    match node.get("type").and_then(|t| t.as_str()) {
+        Some("text") => {
+            if let Some(text) = node.get("text").and_then(|t| t.as_str()) {
+                current.push_str(text);
+            }
+            return;
+        }
+        Some("hardBreak") => {
+            current.push(' ');
+            return;
+        }
+        _ => {}
+    }
+
+    let Some(children) = node.get("content").and_then(|c| c.as_array()) else {
+        return;
+    };
+    let is_textblock = children.iter().any(|c| {
+        matches!(
+            c.get("type").and_then(|t| t.as_str()),
+            Some("text" | "hardBreak")
+        )
+    });
+
+    if is_textblock {
+        let mut text = String::new();
+        for child in children {
+            collect_blocks(child, &mut text, blocks);
+        }
+        blocks.push(text);
+    } else {
+        for child in children {
+            collect_blocks(child, current, blocks);
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_midlight_stats() {
+        let document = json!({
+            "version": 1,
+            "content": {
+                "type": "doc",
+                "content": [
+                    { "type": "heading", "content": [{ "type": "text", "text": "Title" }] },
+                    { "type": "paragraph", "content": [
+                        { "type": "text", "text": "Hello " },
+                        { "type": "text", "text": "world", "marks": [{ "type": "bold" }] },
+                        { "type": "hardBreak" },
+                        { "type": "text", "text": "again." }
+                    ] },
+                    { "type": "paragraph" },
+                    { "type": "bulletList", "content": [
+                        { "type": "listItem", "content": [
+                            { "type": "paragraph", "content": [{ "type": "text", "text": "one" }] }
+                        ] },
+                        { "type": "listItem", "content": [
+                            { "type": "paragraph", "content": [{ "type": "text", "text": "two" }] }
+                        ] }
+                    ] }
+                ]
+            }
+        });
+
+        let stats = midlight_stats(&document);
+        assert_eq!(stats.words, 6);
+        assert_eq!(stats.paragraphs, 4);
+        assert_eq!(
+            stats.characters,
+            "Title".len() + "Hello world again.".len() + 6
+        );
+        assert_eq!(stats.characters_no_spaces, 5 + 16 + 6);
+        assert_eq!(stats.reading_minutes, 1);
+    }
+
+    #[test]
+    fn test_text_stats() {
+        assert_eq!(text_stats("").reading_minutes, 0);
+
+        let long = "word ".repeat(READING_WORDS_PER_MINUTE + 1);
+        let stats = text_stats(&format!("# Heading\n\n{}\n\n\n", long));
+        assert_eq!(stats.words, READING_WORDS_PER_MINUTE + 3);
+        assert_eq!(stats.paragraphs, 2);
+        assert_eq!(stats.reading_minutes, 2);
+    }
+}
@@ -3,6 +3,7 @@
 // Calls the midlight.ai embedding endpoint to generate vector embeddings
 // for document chunks.
 
+use crate::services::network_config::configure_client;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
@@ -58,7 +59,7 @@ impl EmbeddingService {
             reqwest::header::HeaderValue::from_static("desktop"),
         );
 
-        let client = Client::builder()
+        let client = configure_client(Client::builder())
             .default_headers(default_headers)
             .timeout(std::time::Duration::from_secs(120))
             .build()
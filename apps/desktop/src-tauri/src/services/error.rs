@@ -1,5 +1,6 @@
 // Error types for Midlight services
 
+use super::import_security::YamlError;
 use crate::traits::object_store::ObjectStoreError;
 use thiserror::Error;
 
@@ -17,6 +18,9 @@ pub enum MidlightError {
     #[error("Document not found: {0}")]
     DocumentNotFound(String),
 
+    #[error("Document is locked: {0}")]
+    DocumentLocked(String),
+
     #[error("Checkpoint not found: {0}")]
     CheckpointNotFound(String),
 
@@ -55,8 +59,11 @@ pub enum ImportError {
     #[error("File too large: {0}")]
     FileTooLarge(String),
 
+    #[error("Import source too large: {0}")]
+    SourceTooLarge(String),
+
     #[error("YAML parsing error: {0}")]
-    YamlParse(String),
+    YamlParse(YamlError),
 
     #[error("CSV parsing error: {0}")]
     CsvParse(String),
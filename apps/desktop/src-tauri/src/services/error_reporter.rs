@@ -4,12 +4,17 @@
 // - Opt-in only (disabled by default)
 // - Aggressive PII sanitization (file paths, emails, IPs, etc.)
 // - Rate limiting (max 50 reports per session)
-// - Fire-and-forget (no retry on failure)
+// - Fire-and-forget; transient failures are retried via the request queue
+// - Breadcrumbs: the last few commands run (name, duration, outcome - never
+//   arguments or results) and the workspace size and feature flags in use
 
+use crate::services::network_config::configure_client;
+use crate::services::request_queue::{is_retryable_status, Delivery, QueuedPayload, RequestQueue};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Mutex;
 use tracing::{debug, info, warn};
 use uuid::Uuid;
 
@@ -18,7 +23,7 @@ use uuid::Uuid;
 // ============================================================================
 
 /// Error report sent to the server
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ErrorReport {
     pub schema_version: u32,
     pub category: String,
@@ -33,6 +38,45 @@ pub struct ErrorReport {
     pub context: Option<HashMap<String, String>>,
     pub timestamp: String,
     pub session_id: String,
+    /// Commands run before the error, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub breadcrumbs: Vec<Breadcrumb>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<ReportEnvironment>,
+}
+
+/// A command run before an error
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Breadcrumb {
+    pub command: String,
+    pub duration_ms: u64,
+    pub outcome: BreadcrumbOutcome,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreadcrumbOutcome {
+    Ok,
+    Error,
+}
+
+/// What the app was working with when an error happened
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReportEnvironment {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<WorkspaceSize>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub feature_flags: BTreeMap<String, bool>,
+}
+
+/// Size of the open workspace, as counts only
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WorkspaceSize {
+    pub documents: usize,
+    pub other_files: usize,
+    pub folders: usize,
+    pub total_bytes: u64,
 }
 
 /// Error categories for grouping
@@ -46,6 +90,7 @@ pub enum ErrorCategory {
     Llm,
     Auth,
     Recovery,
+    Crash,
     Unknown,
 }
 
@@ -59,6 +104,7 @@ impl std::fmt::Display for ErrorCategory {
             ErrorCategory::Llm => write!(f, "llm"),
             ErrorCategory::Auth => write!(f, "auth"),
             ErrorCategory::Recovery => write!(f, "recovery"),
+            ErrorCategory::Crash => write!(f, "crash"),
             ErrorCategory::Unknown => write!(f, "unknown"),
         }
     }
@@ -141,6 +187,12 @@ pub struct ErrorReporter {
     client: reqwest::Client,
     /// App version
     app_version: String,
+    /// Outbox for reports that failed to send (None disables retries)
+    outbox: Option<&'static RequestQueue>,
+    /// Most recent commands, oldest first
+    breadcrumbs: Mutex<VecDeque<Breadcrumb>>,
+    /// Attached to every report
+    environment: Mutex<ReportEnvironment>,
 }
 
 impl ErrorReporter {
@@ -150,6 +202,12 @@ impl ErrorReporter {
     /// API endpoint for error reports
     const DEFAULT_ENDPOINT: &'static str = "https://midlight.ai/api/error-report";
 
+    /// Breadcrumbs kept for the next report
+    const MAX_BREADCRUMBS: usize = 30;
+
+    /// Longest command name kept in a breadcrumb
+    const MAX_COMMAND_LEN: usize = 64;
+
     /// Create a new error reporter
     pub fn new(app_version: &str) -> Self {
         Self {
@@ -158,8 +216,13 @@ impl ErrorReporter {
             reports_this_session: AtomicU32::new(0),
             max_reports_per_session: Self::DEFAULT_MAX_REPORTS,
             endpoint: Self::DEFAULT_ENDPOINT.to_string(),
-            client: reqwest::Client::new(),
+            client: configure_client(reqwest::Client::builder())
+                .build()
+                .unwrap_or_default(),
             app_version: app_version.to_string(),
+            outbox: None,
+            breadcrumbs: Mutex::new(VecDeque::new()),
+            environment: Mutex::new(ReportEnvironment::default()),
         }
     }
 
@@ -177,6 +240,9 @@ impl ErrorReporter {
                 .build()
                 .unwrap(),
             app_version: app_version.to_string(),
+            outbox: None,
+            breadcrumbs: Mutex::new(VecDeque::new()),
+            environment: Mutex::new(ReportEnvironment::default()),
         }
     }
 
@@ -194,9 +260,18 @@ impl ErrorReporter {
                 .build()
                 .unwrap(),
             app_version: app_version.to_string(),
+            outbox: None,
+            breadcrumbs: Mutex::new(VecDeque::new()),
+            environment: Mutex::new(ReportEnvironment::default()),
         }
     }
 
+    /// Queue reports that fail transiently for retry instead of dropping them
+    pub fn with_outbox(mut self, outbox: &'static RequestQueue) -> Self {
+        self.outbox = Some(outbox);
+        self
+    }
+
     /// Enable or disable error reporting
     pub fn set_enabled(&self, enabled: bool) {
         self.enabled.store(enabled, Ordering::SeqCst);
@@ -204,6 +279,13 @@ impl ErrorReporter {
             info!("Error reporting enabled");
         } else {
             info!("Error reporting disabled");
+            // Opting out also withdraws reports still waiting to be retried
+            if let Some(outbox) = self.outbox {
+                let discarded = outbox.discard_error_reports();
+                if discarded > 0 {
+                    info!("Discarded {} queued error reports", discarded);
+                }
+            }
         }
     }
 
@@ -223,6 +305,67 @@ impl ErrorReporter {
         &self.session_id
     }
 
+    /// Record a command run, for context in later reports. Only the name is
+    /// kept; anything after it is dropped.
+    pub fn add_breadcrumb(&self, command: &str, duration_ms: u64, outcome: BreadcrumbOutcome) {
+        let command: String = command
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | ':' | '.'))
+            .take(Self::MAX_COMMAND_LEN)
+            .collect();
+        if command.is_empty() {
+            return;
+        }
+
+        let mut breadcrumbs = self.breadcrumbs.lock().unwrap();
+        if breadcrumbs.len() == Self::MAX_BREADCRUMBS {
+            breadcrumbs.pop_front();
+        }
+        breadcrumbs.push_back(Breadcrumb {
+            command,
+            duration_ms,
+            outcome,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        });
+    }
+
+    /// Recorded breadcrumbs, oldest first
+    pub fn breadcrumbs(&self) -> Vec<Breadcrumb> {
+        self.breadcrumbs.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Replace the environment attached to reports
+    pub fn set_environment(&self, environment: ReportEnvironment) {
+        *self.environment.lock().unwrap() = environment;
+    }
+
+    /// Build a report with sanitization, breadcrumbs and environment
+    fn build_report(
+        &self,
+        category: ErrorCategory,
+        error_type: &str,
+        message: &str,
+        context: Option<HashMap<String, String>>,
+    ) -> ErrorReport {
+        let environment = self.environment.lock().unwrap().clone();
+        ErrorReport {
+            schema_version: 1,
+            category: category.to_string(),
+            error_type: error_type.to_string(),
+            message: sanitize_message(message),
+            sanitized: true,
+            app_version: self.app_version.clone(),
+            platform: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            os_version: get_os_version(),
+            context: context.map(|c| sanitize_context(&c)),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            session_id: self.session_id.clone(),
+            breadcrumbs: self.breadcrumbs(),
+            environment: (environment != ReportEnvironment::default()).then_some(environment),
+        }
+    }
+
     /// Report an error (fire-and-forget)
     pub async fn report(
         &self,
@@ -248,42 +391,31 @@ impl ErrorReporter {
             return;
         }
 
-        // Build report with sanitization
-        let report = ErrorReport {
-            schema_version: 1,
-            category: category.to_string(),
-            error_type: error_type.to_string(),
-            message: sanitize_message(message),
-            sanitized: true,
-            app_version: self.app_version.clone(),
-            platform: std::env::consts::OS.to_string(),
-            arch: std::env::consts::ARCH.to_string(),
-            os_version: get_os_version(),
-            context: context.map(|c| sanitize_context(&c)),
-            timestamp: chrono::Utc::now().to_rfc3339(),
-            session_id: self.session_id.clone(),
-        };
+        let report = self.build_report(category, error_type, message, context);
 
         // Send report (fire-and-forget)
         let endpoint = self.endpoint.clone();
         let client = self.client.clone();
+        let outbox = self.outbox;
 
         tokio::spawn(async move {
-            match client.post(&endpoint).json(&report).send().await {
-                Ok(response) => {
-                    if response.status().is_success() {
-                        debug!("Error report sent successfully");
-                    } else {
-                        debug!("Error report failed with status: {}", response.status());
-                    }
-                }
-                Err(e) => {
-                    debug!("Error report failed: {}", e);
+            if let Delivery::Retry(error) = post_report(&client, &endpoint, &report).await {
+                if let Some(outbox) = outbox {
+                    let _ = outbox.enqueue(QueuedPayload::ErrorReport { report }, &error);
                 }
             }
         });
     }
 
+    /// Deliver a previously queued report, dropping it if reporting has since
+    /// been turned off
+    pub async fn deliver(&self, report: &ErrorReport) -> Delivery {
+        if !self.is_enabled() {
+            return Delivery::Failed("Error reporting is disabled".to_string());
+        }
+        post_report(&self.client, &self.endpoint, report).await
+    }
+
     /// Report an error and wait for the result (for testing)
     #[cfg(test)]
     pub async fn report_sync(
@@ -305,21 +437,7 @@ impl ErrorReporter {
             return None;
         }
 
-        // Build report with sanitization
-        let report = ErrorReport {
-            schema_version: 1,
-            category: category.to_string(),
-            error_type: error_type.to_string(),
-            message: sanitize_message(message),
-            sanitized: true,
-            app_version: self.app_version.clone(),
-            platform: std::env::consts::OS.to_string(),
-            arch: std::env::consts::ARCH.to_string(),
-            os_version: get_os_version(),
-            context: context.map(|c| sanitize_context(&c)),
-            timestamp: chrono::Utc::now().to_rfc3339(),
-            session_id: self.session_id.clone(),
-        };
+        let report = self.build_report(category, error_type, message, context);
 
         match self.client.post(&self.endpoint).json(&report).send().await {
             Ok(response) => Some(response.status()),
@@ -339,7 +457,7 @@ impl Default for ErrorReporter {
 // ============================================================================
 
 /// Get OS version string
-fn get_os_version() -> String {
+pub fn get_os_version() -> String {
     #[cfg(target_os = "macos")]
     {
         use std::process::Command;
@@ -383,6 +501,29 @@ fn get_os_version() -> String {
     }
 }
 
+/// Post a report, classifying failures as retryable or permanent
+async fn post_report(client: &reqwest::Client, endpoint: &str, report: &ErrorReport) -> Delivery {
+    match client.post(endpoint).json(report).send().await {
+        Ok(response) => {
+            let status = response.status();
+            if status.is_success() {
+                debug!("Error report sent successfully");
+                Delivery::Delivered(None)
+            } else if is_retryable_status(status.as_u16()) {
+                debug!("Error report failed with status: {}", status);
+                Delivery::Retry(format!("HTTP {}", status))
+            } else {
+                debug!("Error report failed with status: {}", status);
+                Delivery::Failed(format!("HTTP {}", status))
+            }
+        }
+        Err(e) => {
+            debug!("Error report failed: {}", e);
+            Delivery::Retry(e.to_string())
+        }
+    }
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -627,6 +768,7 @@ mod tests {
         assert_eq!(ErrorCategory::Llm.to_string(), "llm");
         assert_eq!(ErrorCategory::Auth.to_string(), "auth");
         assert_eq!(ErrorCategory::Recovery.to_string(), "recovery");
+        assert_eq!(ErrorCategory::Crash.to_string(), "crash");
         assert_eq!(ErrorCategory::Unknown.to_string(), "unknown");
     }
 
@@ -747,6 +889,8 @@ mod tests {
             context: None,
             timestamp: "2024-01-01T00:00:00Z".to_string(),
             session_id: "test-session".to_string(),
+            breadcrumbs: Vec::new(),
+            environment: None,
         };
 
         let json = serde_json::to_string(&report).unwrap();
@@ -776,12 +920,56 @@ mod tests {
             context: Some(context),
             timestamp: "2024-01-01T00:00:00Z".to_string(),
             session_id: "test-session".to_string(),
+            breadcrumbs: Vec::new(),
+            environment: None,
         };
 
         let json = serde_json::to_string(&report).unwrap();
         assert!(json.contains("\"context\":{\"key\":\"value\"}"));
     }
 
+    #[test]
+    fn test_report_breadcrumbs_and_environment() {
+        let reporter = ErrorReporter::new("1.0.0");
+        let report = reporter.build_report(ErrorCategory::Editor, "Test", "test", None);
+        assert!(report.breadcrumbs.is_empty());
+        assert!(report.environment.is_none());
+
+        for i in 0..40 {
+            reporter.add_breadcrumb(&format!("command_{}", i), i, BreadcrumbOutcome::Ok);
+        }
+        reporter.add_breadcrumb(
+            "workspace_save /Users/john/secret.md",
+            12,
+            BreadcrumbOutcome::Error,
+        );
+        reporter.add_breadcrumb("   ", 1, BreadcrumbOutcome::Ok);
+
+        let mut feature_flags = BTreeMap::new();
+        feature_flags.insert("rag".to_string(), true);
+        reporter.set_environment(ReportEnvironment {
+            workspace: Some(WorkspaceSize {
+                documents: 12,
+                total_bytes: 4096,
+                ..Default::default()
+            }),
+            feature_flags,
+        });
+
+        let report = reporter.build_report(ErrorCategory::Editor, "Test", "test", None);
+        assert_eq!(report.breadcrumbs.len(), ErrorReporter::MAX_BREADCRUMBS);
+        assert_eq!(report.breadcrumbs[0].command, "command_11");
+        let last = report.breadcrumbs.last().unwrap();
+        assert_eq!(last.command, "workspace_save");
+        assert_eq!(last.outcome, BreadcrumbOutcome::Error);
+
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(!json.contains("john"));
+        assert!(json.contains("\"outcome\":\"error\""));
+        assert!(json.contains("\"feature_flags\":{\"rag\":true}"));
+        assert!(json.contains("\"documents\":12"));
+    }
+
     #[tokio::test]
     async fn test_report_server_error() {
         let mock_server = MockServer::start().await;
@@ -806,6 +994,61 @@ mod tests {
         assert_eq!(result, Some(reqwest::StatusCode::INTERNAL_SERVER_ERROR));
     }
 
+    #[tokio::test]
+    async fn test_deliver_classifies_failures() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/error-report"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/error-report"))
+            .respond_with(ResponseTemplate::new(400))
+            .mount(&mock_server)
+            .await;
+
+        let reporter = ErrorReporter::with_endpoint(
+            "1.0.0",
+            format!("{}/api/error-report", mock_server.uri()),
+        );
+        let report = ErrorReport {
+            schema_version: 1,
+            category: "llm".to_string(),
+            error_type: "NetworkError".to_string(),
+            message: "offline".to_string(),
+            sanitized: true,
+            app_version: "1.0.0".to_string(),
+            platform: "linux".to_string(),
+            arch: "x86_64".to_string(),
+            os_version: "Linux".to_string(),
+            context: None,
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+            session_id: "test-session".to_string(),
+            breadcrumbs: Vec::new(),
+            environment: None,
+        };
+
+        // Reports queued before the user opted out are dropped unsent
+        assert!(matches!(
+            reporter.deliver(&report).await,
+            Delivery::Failed(_)
+        ));
+        assert!(mock_server.received_requests().await.unwrap().is_empty());
+
+        reporter.set_enabled(true);
+        assert!(matches!(
+            reporter.deliver(&report).await,
+            Delivery::Retry(_)
+        ));
+        assert!(matches!(
+            reporter.deliver(&report).await,
+            Delivery::Failed(_)
+        ));
+    }
+
     #[test]
     fn test_sanitize_empty_context() {
         let context: HashMap<String, String> = HashMap::new();
@@ -0,0 +1,462 @@
+// Execution Journal - Undo log for agent tool executions
+//
+// Every agent tool execution that changes files on disk is recorded with the
+// paths it touched and the file content before and after. Content lives in the
+// workspace object store alongside checkpoint content, so the journal itself
+// only holds hashes. Undoing an execution bookmarks the current state of each
+// affected document in its version history, then puts the files back the way
+// they were before the tool ran.
+//
+// Storage: <workspace>/.midlight/agent-executions.json
+
+use crate::services::atomic_write::write_atomic;
+use crate::services::object_store::ObjectStore;
+use crate::services::workspace_manager::WorkspaceManager;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+const JOURNAL_VERSION: u32 = 1;
+
+/// Oldest executions are dropped beyond this many
+const MAX_JOURNAL_ENTRIES: usize = 500;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileOperation {
+    Create,
+    Modify,
+    Move,
+    Delete,
+}
+
+/// A file change to record, with full content
+#[derive(Debug, Clone)]
+pub struct FileChange {
+    pub path: String,
+    pub operation: FileOperation,
+    /// Where a moved file came from
+    pub previous_path: Option<String>,
+    pub old_content: Option<String>,
+    pub new_content: Option<String>,
+}
+
+/// A recorded file change; content is referenced by object store hash
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedChange {
+    pub path: String,
+    pub operation: FileOperation,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionRecord {
+    pub id: String,
+    pub tool_name: String,
+    pub executed_at: String,
+    pub changes: Vec<RecordedChange>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub undone_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct JournalFile {
+    version: u32,
+    executions: Vec<ExecutionRecord>,
+}
+
+// ============================================================================
+// Journal
+// ============================================================================
+
+pub struct ExecutionJournal {
+    workspace_root: PathBuf,
+    journal_path: PathBuf,
+    objects: ObjectStore,
+}
+
+impl ExecutionJournal {
+    pub fn new(workspace_root: &Path) -> Self {
+        Self {
+            workspace_root: workspace_root.to_path_buf(),
+            journal_path: workspace_root
+                .join(".midlight")
+                .join("agent-executions.json"),
+            objects: ObjectStore::new(workspace_root),
+        }
+    }
+
+    /// Record a tool execution and return its entry
+    pub async fn record(
+        &self,
+        tool_name: &str,
+        changes: Vec<FileChange>,
+    ) -> Result<ExecutionRecord, String> {
+        let mut recorded = Vec::with_capacity(changes.len());
+        for change in changes {
+            recorded.push(RecordedChange {
+                path: normalize(&change.path),
+                operation: change.operation,
+                previous_path: change.previous_path.as_deref().map(normalize),
+                old_hash: self.store(change.old_content.as_deref()).await?,
+                new_hash: self.store(change.new_content.as_deref()).await?,
+            });
+        }
+
+        let record = ExecutionRecord {
+            id: Uuid::new_v4().to_string(),
+            tool_name: tool_name.to_string(),
+            executed_at: chrono::Utc::now().to_rfc3339(),
+            changes: recorded,
+            undone_at: None,
+        };
+
+        let mut file = self.read()?;
+        file.version = JOURNAL_VERSION;
+        file.executions.push(record.clone());
+        if file.executions.len() > MAX_JOURNAL_ENTRIES {
+            let excess = file.executions.len() - MAX_JOURNAL_ENTRIES;
+            file.executions.drain(..excess);
+        }
+        self.write(&file)?;
+
+        Ok(record)
+    }
+
+    /// List recorded executions, newest first
+    pub fn list(&self) -> Result<Vec<ExecutionRecord>, String> {
+        let mut executions = self.read()?.executions;
+        executions.reverse();
+        Ok(executions)
+    }
+
+    /// Revert an execution. Each affected document that still exists is
+    /// bookmarked first so the undo can itself be rolled back from version
+    /// history. Unless `force` is set, the undo is refused if any affected
+    /// file has changed since the execution.
+    pub async fn undo(
+        &self,
+        execution_id: &str,
+        force: bool,
+        workspace: &WorkspaceManager,
+    ) -> Result<ExecutionRecord, String> {
+        let mut file = self.read()?;
+        let index = file
+            .executions
+            .iter()
+            .position(|e| e.id == execution_id)
+            .ok_or_else(|| format!("Execution not found: {}", execution_id))?;
+        let record = file.executions[index].clone();
+
+        if record.undone_at.is_some() {
+            return Err(format!("Execution {} was already undone", execution_id));
+        }
+
+        if !force {
+            let changed = self.changed_since(&record)?;
+            if !changed.is_empty() {
+                return Err(format!(
+                    "Files changed since the agent ran: {}",
+                    changed.join(", ")
+                ));
+            }
+        }
+
+        let label = format!("Before undoing {}", record.tool_name);
+        for change in record.changes.iter().rev() {
+            workspace
+                .checkpoint_file(&change.path, &label, None)
+                .await
+                .map_err(|e| format!("Failed to create checkpoint: {}", e))?;
+            self.revert(change).await?;
+        }
+
+        file.executions[index].undone_at = Some(chrono::Utc::now().to_rfc3339());
+        let record = file.executions[index].clone();
+        self.write(&file)?;
+        Ok(record)
+    }
+
+    /// Paths whose current state differs from what the execution left behind
+    fn changed_since(&self, record: &ExecutionRecord) -> Result<Vec<String>, String> {
+        let mut changed = Vec::new();
+        for change in &record.changes {
+            let target = self.resolve(&change.path);
+            let unchanged = match change.operation {
+                FileOperation::Create | FileOperation::Modify => match &change.new_hash {
+                    Some(hash) => fs::read_to_string(&target)
+                        .map(|content| self.objects.hash(&content) == *hash)
+                        .unwrap_or(false),
+                    None => target.exists(),
+                },
+                FileOperation::Move => {
+                    target.exists()
+                        && change
+                            .previous_path
+                            .as_ref()
+                            .map_or(true, |p| !self.resolve(p).exists())
+                }
+                FileOperation::Delete => !target.exists(),
+            };
+            if !unchanged {
+                changed.push(change.path.clone());
+            }
+        }
+        Ok(changed)
+    }
+
+    async fn revert(&self, change: &RecordedChange) -> Result<(), String> {
+        let target = self.resolve(&change.path);
+        match change.operation {
+            FileOperation::Create => {
+                if target.exists() {
+                    trash::delete(&target)
+                        .map_err(|e| format!("Failed to delete {}: {}", change.path, e))?;
+                }
+            }
+            FileOperation::Modify | FileOperation::Delete => {
+                let hash = change
+                    .old_hash
+                    .as_ref()
+                    .ok_or_else(|| format!("No previous content recorded for {}", change.path))?;
+                let content = self
+                    .objects
+                    .read(hash)
+                    .await
+                    .map_err(|e| format!("Failed to read previous content: {}", e))?;
+                write_atomic(&target, &content)
+                    .map_err(|e| format!("Failed to write document: {}", e))?;
+            }
+            FileOperation::Move => {
+                let previous = change
+                    .previous_path
+                    .as_ref()
+                    .ok_or_else(|| format!("No previous path recorded for {}", change.path))?;
+                let source = self.resolve(previous);
+                if source.exists() {
+                    return Err(format!("Cannot move back: {} already exists", previous));
+                }
+                if let Some(parent) = source.parent() {
+                    fs::create_dir_all(parent)
+                        .map_err(|e| format!("Failed to create directory: {}", e))?;
+                }
+                fs::rename(&target, &source)
+                    .map_err(|e| format!("Failed to move {} back: {}", change.path, e))?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn store(&self, content: Option<&str>) -> Result<Option<String>, String> {
+        match content {
+            Some(content) => self
+                .objects
+                .write(content)
+                .await
+                .map(Some)
+                .map_err(|e| format!("Failed to store content: {}", e)),
+            None => Ok(None),
+        }
+    }
+
+    fn resolve(&self, relative_path: &str) -> PathBuf {
+        self.workspace_root.join(relative_path)
+    }
+
+    fn read(&self) -> Result<JournalFile, String> {
+        if !self.journal_path.exists() {
+            return Ok(JournalFile {
+                version: JOURNAL_VERSION,
+                executions: Vec::new(),
+            });
+        }
+
+        let content = fs::read_to_string(&self.journal_path)
+            .map_err(|e| format!("Failed to read execution journal: {}", e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse execution journal: {}", e))
+    }
+
+    fn write(&self, file: &JournalFile) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(file)
+            .map_err(|e| format!("Failed to serialize execution journal: {}", e))?;
+        write_atomic(&self.journal_path, json)
+            .map_err(|e| format!("Failed to write execution journal: {}", e))
+    }
+}
+
+/// Workspace-relative path without a leading slash
+fn normalize(path: &str) -> String {
+    path.trim_start_matches('/').to_string()
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    async fn setup() -> (TempDir, ExecutionJournal, WorkspaceManager) {
+        let temp = TempDir::new().unwrap();
+        let workspace = WorkspaceManager::new(temp.path());
+        workspace.init().await.unwrap();
+        let journal = ExecutionJournal::new(temp.path());
+        (temp, journal, workspace)
+    }
+
+    fn change(path: &str, operation: FileOperation) -> FileChange {
+        FileChange {
+            path: path.to_string(),
+            operation,
+            previous_path: None,
+            old_content: None,
+            new_content: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record_and_list() {
+        let (_temp, journal, _workspace) = setup().await;
+        assert!(journal.list().unwrap().is_empty());
+
+        journal
+            .record(
+                "create_document",
+                vec![FileChange {
+                    new_content: Some("a".to_string()),
+                    ..change("/a.midlight", FileOperation::Create)
+                }],
+            )
+            .await
+            .unwrap();
+        journal.record("delete_document", vec![]).await.unwrap();
+
+        let executions = journal.list().unwrap();
+        assert_eq!(executions.len(), 2);
+        assert_eq!(executions[0].tool_name, "delete_document");
+        assert_eq!(executions[1].changes[0].path, "a.midlight");
+        assert!(executions[1].changes[0].new_hash.is_some());
+        assert!(executions[1].changes[0].old_hash.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_undo_modify_restores_content_and_bookmarks() {
+        let (temp, journal, workspace) = setup().await;
+        let doc = temp.path().join("doc.midlight");
+        std::fs::write(&doc, "after").unwrap();
+
+        let record = journal
+            .record(
+                "agent_approve_change",
+                vec![FileChange {
+                    old_content: Some("before".to_string()),
+                    new_content: Some("after".to_string()),
+                    ..change("doc.midlight", FileOperation::Modify)
+                }],
+            )
+            .await
+            .unwrap();
+
+        let undone = journal.undo(&record.id, false, &workspace).await.unwrap();
+        assert!(undone.undone_at.is_some());
+        assert_eq!(std::fs::read_to_string(&doc).unwrap(), "before");
+
+        let checkpoints = workspace.get_checkpoints("doc.midlight").await.unwrap();
+        assert_eq!(checkpoints.len(), 1);
+        assert_eq!(
+            checkpoints[0].label.as_deref(),
+            Some("Before undoing agent_approve_change")
+        );
+
+        let err = journal
+            .undo(&record.id, false, &workspace)
+            .await
+            .unwrap_err();
+        assert!(err.contains("already undone"));
+    }
+
+    #[tokio::test]
+    async fn test_undo_refuses_when_file_changed() {
+        let (temp, journal, workspace) = setup().await;
+        let doc = temp.path().join("doc.midlight");
+        std::fs::write(&doc, "edited by user").unwrap();
+
+        let record = journal
+            .record(
+                "agent_approve_change",
+                vec![FileChange {
+                    old_content: Some("before".to_string()),
+                    new_content: Some("after".to_string()),
+                    ..change("doc.midlight", FileOperation::Modify)
+                }],
+            )
+            .await
+            .unwrap();
+
+        let err = journal
+            .undo(&record.id, false, &workspace)
+            .await
+            .unwrap_err();
+        assert!(err.contains("doc.midlight"));
+        assert_eq!(std::fs::read_to_string(&doc).unwrap(), "edited by user");
+
+        journal.undo(&record.id, true, &workspace).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&doc).unwrap(), "before");
+    }
+
+    #[tokio::test]
+    async fn test_undo_move_and_delete() {
+        let (temp, journal, workspace) = setup().await;
+        std::fs::create_dir_all(temp.path().join("archive")).unwrap();
+        std::fs::write(temp.path().join("archive/moved.midlight"), "moved").unwrap();
+
+        let record = journal
+            .record(
+                "move_document",
+                vec![
+                    FileChange {
+                        previous_path: Some("notes/moved.midlight".to_string()),
+                        ..change("archive/moved.midlight", FileOperation::Move)
+                    },
+                    FileChange {
+                        old_content: Some("deleted".to_string()),
+                        ..change("gone.midlight", FileOperation::Delete)
+                    },
+                ],
+            )
+            .await
+            .unwrap();
+
+        journal.undo(&record.id, false, &workspace).await.unwrap();
+        assert!(!temp.path().join("archive/moved.midlight").exists());
+        assert_eq!(
+            std::fs::read_to_string(temp.path().join("notes/moved.midlight")).unwrap(),
+            "moved"
+        );
+        assert_eq!(
+            std::fs::read_to_string(temp.path().join("gone.midlight")).unwrap(),
+            "deleted"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_undo_unknown_execution() {
+        let (_temp, journal, workspace) = setup().await;
+        assert!(journal.undo("missing", false, &workspace).await.is_err());
+    }
+}
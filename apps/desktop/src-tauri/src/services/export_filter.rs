@@ -0,0 +1,275 @@
+// Export Filter - Which parts of a workspace an export takes
+//
+// Workspace exports (Obsidian vaults and .midlightpkg archives) can be
+// narrowed to some folders or tags, leave out documents marked private, and
+// carry only the assets the exported documents use, so exporting one
+// project doesn't bring along notes from the rest of the workspace.
+//
+// A document is private when its properties (front matter, for Markdown)
+// have `private: true` or it's tagged #private. Assets are worked out from
+// the same links the link graph records, plus embedded images: workspace
+// files an exported document links to or embeds, and stored images it
+// references.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+use super::document_chunks::resolve_content;
+use super::image_refs::extract_refs;
+use super::link_graph::{
+    is_document, markdown_links, midlight_links, midlight_tags, resolve_href, MARKDOWN_LINK,
+};
+use super::rag_service::document_tags;
+use super::workspace_paths::document_key;
+
+/// Tag that marks a document private
+const PRIVATE_TAG: &str = "private";
+
+/// What a workspace export includes. The default exports everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExportFilter {
+    /// Workspace-relative folders to export; empty for all of them
+    pub folders: Vec<String>,
+    /// Export only documents with one of these tags; empty for any
+    pub tags: Vec<String>,
+    pub exclude_private: bool,
+    /// Only export files and images the exported documents use
+    pub referenced_assets_only: bool,
+}
+
+/// The files an export takes
+#[derive(Debug, Clone, Default)]
+pub struct ExportSelection {
+    /// Documents and other files, by workspace-relative path
+    pub files: BTreeSet<String>,
+    /// Hashes of the stored images the documents use, or None for all of them
+    pub images: Option<BTreeSet<String>>,
+    /// Documents the filter left out
+    pub excluded_documents: usize,
+}
+
+impl ExportSelection {
+    /// Whether a stored image, by the file name it's stored under, is exported
+    pub fn includes_image(&self, file_name: &str) -> bool {
+        let hash = file_name.split(['.', '-']).next().unwrap_or(file_name);
+        self.images
+            .as_ref()
+            .map_or(true, |images| images.contains(hash))
+    }
+}
+
+/// A document's tags and what it links to
+struct DocumentInfo {
+    tags: BTreeSet<String>,
+    private: bool,
+    /// Workspace files it links to or embeds
+    links: BTreeSet<String>,
+    images: BTreeSet<String>,
+}
+
+impl ExportFilter {
+    /// Whether the filter leaves anything out
+    pub fn is_active(&self) -> bool {
+        self != &Self::default()
+    }
+
+    /// The files in the workspace the export takes. Files and folders
+    /// starting with a dot are never exported.
+    pub fn select(&self, workspace_root: &Path) -> ExportSelection {
+        let keys: Vec<String> = WalkDir::new(workspace_root)
+            .min_depth(1)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|e| !e.file_name().to_string_lossy().starts_with('.'))
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter_map(|e| {
+                let relative = e.path().strip_prefix(workspace_root).ok()?;
+                Some(document_key(&relative.to_string_lossy()))
+            })
+            .collect();
+
+        let tags: BTreeSet<String> = self
+            .tags
+            .iter()
+            .map(|tag| tag.trim().trim_start_matches('#').to_lowercase())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+
+        let mut selection = ExportSelection::default();
+        let mut links = BTreeSet::new();
+        let mut images = BTreeSet::new();
+        for key in keys.iter().filter(|key| is_document(Path::new(key))) {
+            let info = read_document(&workspace_root.join(key), key);
+            let included = self.in_folders(key)
+                && (tags.is_empty() || !info.tags.is_disjoint(&tags))
+                && !(self.exclude_private && info.private);
+            if !included {
+                selection.excluded_documents += 1;
+                continue;
+            }
+            selection.files.insert(key.clone());
+            links.extend(info.links);
+            images.extend(info.images);
+        }
+
+        for key in keys.iter().filter(|key| !is_document(Path::new(key))) {
+            let included = match self.referenced_assets_only {
+                true => links.contains(key),
+                false => self.in_folders(key),
+            };
+            if included {
+                selection.files.insert(key.clone());
+            }
+        }
+        if self.referenced_assets_only {
+            selection.images = Some(images);
+        }
+        selection
+    }
+
+    fn in_folders(&self, key: &str) -> bool {
+        self.folders.is_empty()
+            || self.folders.iter().any(|folder| {
+                let folder = document_key(folder.trim_matches(['/', '\\']));
+                folder.is_empty()
+                    || key
+                        .strip_prefix(folder.as_str())
+                        .is_some_and(|rest| rest.starts_with('/'))
+            })
+    }
+}
+
+fn read_document(path: &Path, key: &str) -> DocumentInfo {
+    let content = fs::read_to_string(path).unwrap_or_default();
+    if key.ends_with(".md") {
+        let tags: BTreeSet<String> = document_tags(&content).into_iter().collect();
+        let front_matter = content
+            .strip_prefix("---\n")
+            .and_then(|rest| rest.split_once("\n---"))
+            .and_then(|(yaml, _)| serde_yaml::from_str::<serde_yaml::Value>(yaml).ok());
+        let private = front_matter
+            .as_ref()
+            .and_then(|fm| fm.get("private"))
+            .and_then(serde_yaml::Value::as_bool)
+            .unwrap_or(false);
+        let mut links = markdown_links(key, &content);
+        // Embedded images are assets too
+        links.extend(
+            MARKDOWN_LINK
+                .captures_iter(&content)
+                .filter(|caps| &caps[1] == "!")
+                .filter_map(|caps| resolve_href(key, &caps[2])),
+        );
+        return DocumentInfo {
+            private: private || tags.contains(PRIVATE_TAG),
+            tags,
+            links,
+            images: extract_refs(&content),
+        };
+    }
+
+    let mut document = serde_json::from_str::<Value>(&content).unwrap_or(Value::Null);
+    resolve_content(path, &mut document);
+    let body = document.get("content").cloned().unwrap_or(Value::Null);
+    let tags = midlight_tags(&body);
+    let private = document.pointer("/meta/private") == Some(&Value::Bool(true));
+    let mut links = midlight_links(key, &body);
+    collect_embeds(key, &body, &mut links);
+    DocumentInfo {
+        private: private || tags.contains(PRIVATE_TAG),
+        tags,
+        links,
+        images: extract_refs(&body.to_string()),
+    }
+}
+
+/// Workspace files embedded as images in Tiptap content
+fn collect_embeds(key: &str, node: &Value, links: &mut BTreeSet<String>) {
+    if let Some(src) = node.pointer("/attrs/src").and_then(Value::as_str) {
+        links.extend(resolve_href(key, src));
+    }
+    if let Some(children) = node.get("content").and_then(Value::as_array) {
+        for child in children {
+            collect_embeds(key, child, links);
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn write(root: &Path, key: &str, content: &str) {
+        let path = root.join(key);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_select() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        let midlight = json!({
+            "version": 1,
+            "meta": { "private": true },
+            "content": { "type": "doc", "content": [{
+                "type": "paragraph",
+                "content": [{ "type": "text", "text": "Salaries" }]
+            }]}
+        });
+        write(root, "Project/plan.md", "See [notes](notes.md) and ![chart](chart.png)\n\n![logo](midlight://img-0123456789abcdef)");
+        write(root, "Project/notes.md", "---\nprivate: true\n---\nNotes");
+        write(root, "Project/budget.midlight", &midlight.to_string());
+        write(root, "Project/chart.png", "png");
+        write(root, "Project/unused.pdf", "pdf");
+        write(root, "Personal/diary.md", "Dear diary #journal");
+
+        let everything = ExportFilter::default();
+        assert!(!everything.is_active());
+        let selection = everything.select(root);
+        assert_eq!(selection.files.len(), 6);
+        assert!(selection.images.is_none());
+
+        let filter = ExportFilter {
+            folders: vec!["Project/".to_string()],
+            exclude_private: true,
+            referenced_assets_only: true,
+            ..Default::default()
+        };
+        let selection = filter.select(root);
+        assert_eq!(
+            selection
+                .files
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>(),
+            vec!["Project/chart.png", "Project/plan.md"]
+        );
+        assert_eq!(selection.excluded_documents, 3);
+        assert!(selection.includes_image("0123456789abcdef.png"));
+        assert!(selection.includes_image("0123456789abcdef-small.webp"));
+        assert!(!selection.includes_image("fedcba9876543210.png"));
+
+        let tagged = ExportFilter {
+            tags: vec!["#Journal".to_string()],
+            ..Default::default()
+        };
+        let selection = tagged.select(root);
+        assert!(selection.files.contains("Personal/diary.md"));
+        assert!(!selection.files.contains("Project/plan.md"));
+        // Only documents are chosen by tag
+        assert!(selection.files.contains("Project/unused.pdf"));
+    }
+}
@@ -0,0 +1,330 @@
+// External Editor - Edit documents in another editor and bring changes back
+//
+// A document is exported to Markdown in a temp directory for the external
+// editor to open. The directory is watched rather than the file, since many
+// editors (vim included) save by writing a new file and renaming it over the
+// old one. Every save there is converted back into the .midlight document
+// through the workspace manager, so it gets a checkpoint like any other save.
+//
+// A session ends when the frontend closes it, or when an editor started with
+// a blocking command (e.g. "code --wait") exits. Changes are synced one last
+// time before the temp directory is removed.
+
+use crate::services::workspace_manager::WorkspaceManager;
+use lazy_static::lazy_static;
+use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+/// How long to wait for an editor to finish writing before reading the file
+const SETTLE_DELAY: Duration = Duration::from_millis(250);
+
+/// Checkpoint trigger for changes made in an external editor
+const TRIGGER: &str = "external-edit";
+
+lazy_static! {
+    static ref SESSIONS: Mutex<HashMap<String, Session>> = Mutex::new(HashMap::new());
+}
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalEditSession {
+    pub id: String,
+    pub workspace_root: String,
+    /// Relative to the workspace root
+    pub document_path: String,
+    /// The Markdown file the external editor works on
+    pub temp_path: String,
+}
+
+/// Sent after a change from the external editor is saved, and when the
+/// session ends
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalEditEvent {
+    pub session_id: String,
+    pub document_path: String,
+    pub checkpoint_id: Option<String>,
+    pub closed: bool,
+    pub error: Option<String>,
+}
+
+pub type EventSink = Arc<dyn Fn(ExternalEditEvent) + Send + Sync>;
+
+struct Session {
+    info: ExternalEditSession,
+    stop: oneshot::Sender<()>,
+}
+
+// ============================================================================
+// Sessions
+// ============================================================================
+
+/// Export a document and start watching its Markdown copy. Launching the
+/// editor is left to the caller.
+pub async fn start(
+    workspace: Arc<WorkspaceManager>,
+    workspace_root: &Path,
+    document_path: &str,
+    on_event: EventSink,
+) -> Result<ExternalEditSession, String> {
+    if !document_path.ends_with(".midlight") {
+        return Err("Only .midlight documents can be edited externally".to_string());
+    }
+    if let Some(existing) = list()
+        .into_iter()
+        .find(|s| s.document_path == document_path && s.workspace_root == root_str(workspace_root))
+    {
+        return Ok(existing);
+    }
+
+    let markdown = workspace
+        .export_markdown(document_path)
+        .await
+        .map_err(|e| format!("Failed to export document: {}", e))?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let temp_dir = std::env::temp_dir().join("midlight-edit").join(&id);
+    fs::create_dir_all(&temp_dir).map_err(|e| format!("Failed to create temp directory: {}", e))?;
+    let name = Path::new(document_path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "document".to_string());
+    let temp_path = temp_dir.join(format!("{}.md", name));
+    fs::write(&temp_path, &markdown).map_err(|e| format!("Failed to write temp file: {}", e))?;
+
+    let (changes_tx, changes_rx) = mpsc::unbounded_channel();
+    let watched = temp_path.clone();
+    let mut watcher = RecommendedWatcher::new(
+        move |result: notify::Result<Event>| {
+            let Ok(event) = result else {
+                return;
+            };
+            if event.paths.iter().any(|p| p == &watched)
+                && (event.kind.is_modify() || event.kind.is_create())
+            {
+                let _ = changes_tx.send(());
+            }
+        },
+        Config::default(),
+    )
+    .map_err(|e| format!("Failed to watch temp file: {}", e))?;
+    watcher
+        .watch(&temp_dir, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Failed to watch temp file: {}", e))?;
+
+    let info = ExternalEditSession {
+        id: id.clone(),
+        workspace_root: root_str(workspace_root),
+        document_path: document_path.to_string(),
+        temp_path: temp_path.to_string_lossy().to_string(),
+    };
+    let (stop_tx, stop_rx) = oneshot::channel();
+    SESSIONS.lock().unwrap().insert(
+        id,
+        Session {
+            info: info.clone(),
+            stop: stop_tx,
+        },
+    );
+
+    let synced = SyncState {
+        workspace,
+        info: info.clone(),
+        last_markdown: markdown,
+        on_event,
+    };
+    tokio::spawn(synced.run(watcher, changes_rx, stop_rx, temp_dir));
+
+    Ok(info)
+}
+
+/// Stop a session after syncing any last changes. Returns false if there was
+/// no such session.
+pub fn close(session_id: &str) -> bool {
+    match SESSIONS.lock().unwrap().remove(session_id) {
+        Some(session) => {
+            let _ = session.stop.send(());
+            true
+        }
+        None => false,
+    }
+}
+
+pub fn list() -> Vec<ExternalEditSession> {
+    SESSIONS
+        .lock()
+        .unwrap()
+        .values()
+        .map(|s| s.info.clone())
+        .collect()
+}
+
+fn root_str(workspace_root: &Path) -> String {
+    workspace_root.to_string_lossy().to_string()
+}
+
+struct SyncState {
+    workspace: Arc<WorkspaceManager>,
+    info: ExternalEditSession,
+    last_markdown: String,
+    on_event: EventSink,
+}
+
+impl SyncState {
+    async fn run(
+        mut self,
+        watcher: RecommendedWatcher,
+        mut changes: mpsc::UnboundedReceiver<()>,
+        mut stop: oneshot::Receiver<()>,
+        temp_dir: PathBuf,
+    ) {
+        loop {
+            tokio::select! {
+                Some(()) = changes.recv() => {
+                    tokio::time::sleep(SETTLE_DELAY).await;
+                    while changes.try_recv().is_ok() {}
+                    let result = self.save_changes().await;
+                    self.report(result, false);
+                }
+                _ = &mut stop => break,
+                else => break,
+            }
+        }
+
+        drop(watcher);
+        let result = self.save_changes().await;
+        if let Err(e) = fs::remove_dir_all(&temp_dir) {
+            tracing::debug!("Failed to remove {}: {}", temp_dir.display(), e);
+        }
+        SESSIONS.lock().unwrap().remove(&self.info.id);
+        self.report(result, true);
+    }
+
+    /// Tell the frontend about a saved change, a failure, or the session
+    /// ending
+    fn report(&self, result: Result<Option<String>, String>, closed: bool) {
+        if result.as_ref().is_ok_and(|c| c.is_none()) && !closed {
+            return;
+        }
+
+        let (checkpoint_id, error) = match result {
+            Ok(checkpoint_id) => (checkpoint_id, None),
+            Err(e) => {
+                tracing::warn!("External edit of {} failed: {}", self.info.document_path, e);
+                (None, Some(e))
+            }
+        };
+        (self.on_event)(ExternalEditEvent {
+            session_id: self.info.id.clone(),
+            document_path: self.info.document_path.clone(),
+            checkpoint_id,
+            closed,
+            error,
+        });
+    }
+
+    /// Save the temp file into the document if it changed, returning the
+    /// new checkpoint
+    async fn save_changes(&mut self) -> Result<Option<String>, String> {
+        let markdown = match fs::read_to_string(&self.info.temp_path) {
+            Ok(markdown) => markdown,
+            // Mid-rename, or removed by the editor
+            Err(_) => return Ok(None),
+        };
+        if markdown == self.last_markdown {
+            return Ok(None);
+        }
+
+        let result = self
+            .workspace
+            .import_markdown(&self.info.document_path, &markdown, TRIGGER)
+            .await
+            .map_err(|e| format!("Failed to save document: {}", e))?;
+        self.last_markdown = markdown;
+        Ok(result.checkpoint_id)
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::mpsc as std_mpsc;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let temp = TempDir::new().unwrap();
+        let workspace = Arc::new(WorkspaceManager::new(temp.path()));
+        workspace.init().await.unwrap();
+        let content = json!({ "type": "doc", "content": [
+            { "type": "heading", "attrs": { "level": 1 },
+              "content": [{ "type": "text", "text": "Plan" }] }
+        ]});
+        workspace
+            .save_document("plan.midlight", content, "manual")
+            .await
+            .unwrap();
+
+        let (tx, rx) = std_mpsc::channel();
+        let sink: EventSink = Arc::new(move |event| {
+            let _ = tx.send(event);
+        });
+        let session = start(
+            workspace.clone(),
+            temp.path(),
+            "plan.midlight",
+            sink.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(fs::read_to_string(&session.temp_path).unwrap(), "# Plan");
+
+        // Starting again reuses the session
+        let again = start(workspace.clone(), temp.path(), "plan.midlight", sink)
+            .await
+            .unwrap();
+        assert_eq!(again.id, session.id);
+
+        fs::write(&session.temp_path, "# Plan\nShip it").unwrap();
+        let event = tokio::task::spawn_blocking(move || {
+            let event = rx.recv_timeout(Duration::from_secs(10)).unwrap();
+            (event, rx)
+        })
+        .await
+        .unwrap();
+        let (event, rx) = event;
+        assert!(!event.closed);
+        assert!(event.checkpoint_id.is_some());
+
+        let loaded = workspace.load_document("plan.midlight").await.unwrap();
+        assert_eq!(loaded.json["content"][1]["content"][0]["text"], "Ship it");
+
+        assert!(close(&session.id));
+        assert!(!close(&session.id));
+        let closed =
+            tokio::task::spawn_blocking(move || rx.recv_timeout(Duration::from_secs(10)).unwrap())
+                .await
+                .unwrap();
+        assert!(closed.closed);
+        assert!(!Path::new(&session.temp_path).exists());
+
+        assert!(start(workspace, temp.path(), "notes.md", Arc::new(|_| {}))
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,164 @@
+// File Stream - Reading large files a chunk at a time
+//
+// read_file sends a whole file over IPC in one message, so a large
+// attachment is held in memory several times over on both sides. Instead
+// the frontend pulls a file in chunks: it asks for the next chunk, from the
+// offset the last one ended at, only once it has handled the last one, so
+// there's never more than one chunk in flight however slow it is to consume
+// them. Reads are stateless; a chunk carries the file's size and modified
+// time so a reader can tell if the file changed under it.
+//
+// Text chunks end on a character boundary. Binary chunks are base64, sized
+// to a multiple of three bytes so the chunks concatenate into valid base64.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+/// Bytes read per chunk unless the caller asks for another size
+pub const DEFAULT_CHUNK_SIZE: usize = 256 * 1024;
+
+/// Largest chunk a caller can ask for
+const MAX_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChunkEncoding {
+    /// UTF-8 text, cut at character boundaries
+    #[default]
+    Utf8,
+    /// Raw bytes as base64
+    Base64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileChunk {
+    pub data: String,
+    /// Byte offset the chunk starts at
+    pub offset: u64,
+    /// Byte offset to ask for the next chunk from
+    pub next_offset: u64,
+    /// Size of the whole file in bytes
+    pub size: u64,
+    /// Modified time of the file, in milliseconds since the epoch
+    pub modified: Option<u64>,
+    /// Whether this is the last chunk
+    pub done: bool,
+}
+
+/// Read the chunk of a file starting at `offset`
+pub fn read_chunk(
+    path: &Path,
+    offset: u64,
+    length: Option<usize>,
+    encoding: ChunkEncoding,
+) -> Result<FileChunk, String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let metadata = file
+        .metadata()
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    let size = metadata.len();
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64);
+
+    let mut length = length
+        .unwrap_or(DEFAULT_CHUNK_SIZE)
+        .clamp(4, MAX_CHUNK_SIZE);
+    if encoding == ChunkEncoding::Base64 {
+        length -= length % 3;
+    }
+
+    let mut bytes = Vec::with_capacity(length.min(size.saturating_sub(offset) as usize));
+    file.seek(SeekFrom::Start(offset))
+        .and_then(|_| file.by_ref().take(length as u64).read_to_end(&mut bytes))
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    let at_end = offset + bytes.len() as u64 >= size;
+
+    let (data, read) = match encoding {
+        ChunkEncoding::Base64 => (BASE64.encode(&bytes), bytes.len()),
+        ChunkEncoding::Utf8 => {
+            let valid = match std::str::from_utf8(&bytes) {
+                Ok(_) => bytes.len(),
+                // A character split by the end of the chunk starts the next one
+                Err(e) if e.error_len().is_none() && !at_end => e.valid_up_to(),
+                Err(_) => {
+                    return Err(
+                        "Failed to read file: stream did not contain valid UTF-8".to_string()
+                    )
+                }
+            };
+            bytes.truncate(valid);
+            let text =
+                String::from_utf8(bytes).map_err(|e| format!("Failed to read file: {}", e))?;
+            (text, valid)
+        }
+    };
+
+    let next_offset = offset + read as u64;
+    Ok(FileChunk {
+        data,
+        offset,
+        next_offset,
+        size,
+        modified,
+        done: next_offset >= size,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn read_all(path: &Path, length: usize, encoding: ChunkEncoding) -> Vec<String> {
+        let mut chunks = Vec::new();
+        let mut offset = 0;
+        loop {
+            let chunk = read_chunk(path, offset, Some(length), encoding).unwrap();
+            offset = chunk.next_offset;
+            chunks.push(chunk.data);
+            if chunk.done {
+                return chunks;
+            }
+        }
+    }
+
+    #[test]
+    fn test_text_chunks_end_on_characters() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("notes.md");
+        let text = "Café — naïve résumé ✓ ".repeat(20);
+        std::fs::write(&path, &text).unwrap();
+
+        let chunks = read_all(&path, 7, ChunkEncoding::Utf8);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| !c.is_empty()));
+        assert_eq!(chunks.concat(), text);
+
+        std::fs::write(&path, [b'a', 0xff, b'b']).unwrap();
+        assert!(read_chunk(&path, 0, None, ChunkEncoding::Utf8).is_err());
+    }
+
+    #[test]
+    fn test_base64_chunks_concatenate() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("photo.png");
+        let bytes: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        std::fs::write(&path, &bytes).unwrap();
+
+        let chunks = read_all(&path, 100, ChunkEncoding::Base64);
+        assert_eq!(chunks.len(), 11);
+        assert_eq!(BASE64.decode(chunks.concat()).unwrap(), bytes);
+
+        let past_end = read_chunk(&path, 5000, None, ChunkEncoding::Base64).unwrap();
+        assert!(past_end.done);
+        assert!(past_end.data.is_empty());
+    }
+}
@@ -1,9 +1,14 @@
 // File Watcher - Monitors workspace files for external changes
 //
-// Uses the `notify` crate for native file system events.
+// Uses the `notify` crate for native file system events, or polling with
+// content hashes for workspaces on network filesystems, where native events
+// miss changes made from other machines.
 // Debounces events and distinguishes between app-initiated and external changes.
 
-use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use crate::services::atomic_write::write_atomic;
+use crate::services::mount_info;
+use crate::services::path_glob::PathGlob;
+use notify::{Config, Event, EventKind, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
@@ -61,6 +66,149 @@ impl Default for FileWatcherConfig {
     }
 }
 
+impl FileWatcherConfig {
+    /// The defaults with a workspace's settings applied
+    pub fn with_settings(settings: &WatcherSettings) -> Self {
+        let mut config = Self::default();
+        if let Some(debounce_ms) = settings.debounce_ms {
+            config.debounce_ms = debounce_ms;
+        }
+        for pattern in &settings.ignore {
+            if !config.ignored_patterns.contains(pattern) {
+                config.ignored_patterns.push(pattern.clone());
+            }
+        }
+        config
+    }
+}
+
+/// Compiled ignore patterns. A pattern with glob characters is matched
+/// against the workspace-relative path (see PathGlob); any other pattern
+/// ignores paths containing it.
+struct IgnoreRules {
+    substrings: Vec<String>,
+    globs: Vec<PathGlob>,
+}
+
+impl IgnoreRules {
+    fn new(patterns: &[String]) -> Self {
+        let mut rules = Self {
+            substrings: Vec::new(),
+            globs: Vec::new(),
+        };
+        for pattern in patterns {
+            if !is_glob(pattern) {
+                rules.substrings.push(pattern.clone());
+                continue;
+            }
+            match PathGlob::new(pattern) {
+                Ok(glob) => rules.globs.push(glob),
+                Err(e) => error!("Skipping watcher ignore pattern: {}", e),
+            }
+        }
+        rules
+    }
+
+    fn is_ignored(&self, relative: &Path) -> bool {
+        let path = relative.to_string_lossy().replace('\\', "/");
+        self.substrings.iter().any(|p| path.contains(p.as_str()))
+            || self.globs.iter().any(|g| g.is_match(&path))
+    }
+}
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '{'])
+}
+
+// ============================================================================
+// Workspace Settings
+// ============================================================================
+
+/// Smallest and largest debounce delays a workspace can set
+const DEBOUNCE_RANGE_MS: (u64, u64) = (50, 10_000);
+
+/// Polling interval for workspaces on network filesystems. Polling hashes
+/// file contents, so it runs much less often than native event delivery.
+pub const DEFAULT_POLL_INTERVAL_MS: u64 = 5_000;
+
+const POLL_INTERVAL_RANGE_MS: (u64, u64) = (1_000, 300_000);
+
+/// Per-workspace watcher settings, layered over the defaults.
+///
+/// Stored at: .midlight/watcher.json
+/// Format: { "debounceMs": 1000, "ignore": ["exports/**", "*.log"], "polling": true }
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatcherSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debounce_ms: Option<u64>,
+    /// Ignore patterns added to the defaults
+    #[serde(default)]
+    pub ignore: Vec<String>,
+    /// Force polling on or off; by default workspaces on network
+    /// filesystems are polled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub polling: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poll_interval_ms: Option<u64>,
+}
+
+impl WatcherSettings {
+    fn path(workspace_root: &Path) -> PathBuf {
+        workspace_root.join(".midlight").join("watcher.json")
+    }
+
+    pub fn load(workspace_root: &Path) -> Result<Self, String> {
+        let path = Self::path(workspace_root);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read watcher settings: {}", e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse watcher settings: {}", e))
+    }
+
+    /// The polling interval to use for a workspace, or None for native events
+    pub fn poll_interval(&self, workspace_root: &Path) -> Option<Duration> {
+        let polling = self
+            .polling
+            .unwrap_or_else(|| mount_info::is_network_filesystem(workspace_root));
+        polling.then(|| {
+            Duration::from_millis(self.poll_interval_ms.unwrap_or(DEFAULT_POLL_INTERVAL_MS))
+        })
+    }
+
+    pub fn save(&self, workspace_root: &Path) -> Result<(), String> {
+        if let Some(ms) = self.debounce_ms {
+            let (min, max) = DEBOUNCE_RANGE_MS;
+            if !(min..=max).contains(&ms) {
+                return Err(format!("Debounce must be between {} and {} ms", min, max));
+            }
+        }
+        if let Some(ms) = self.poll_interval_ms {
+            let (min, max) = POLL_INTERVAL_RANGE_MS;
+            if !(min..=max).contains(&ms) {
+                return Err(format!(
+                    "Poll interval must be between {} and {} ms",
+                    min, max
+                ));
+            }
+        }
+        for pattern in self.ignore.iter().filter(|p| is_glob(p)) {
+            PathGlob::new(pattern)?;
+        }
+        if self.ignore.iter().any(|p| p.trim().is_empty()) {
+            return Err("Ignore patterns cannot be empty".to_string());
+        }
+
+        let path = Self::path(workspace_root);
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize watcher settings: {}", e))?;
+        write_atomic(&path, json).map_err(|e| format!("Failed to write watcher settings: {}", e))
+    }
+}
+
 // ============================================================================
 // Event Emitter Trait (for testability)
 // ============================================================================
@@ -90,6 +238,64 @@ impl<R: Runtime> EventEmitter for TauriEmitter<R> {
     }
 }
 
+// ============================================================================
+// Subscriptions
+// ============================================================================
+
+/// A change, for the subscriptions whose prefix it falls under
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SubscriptionEvent {
+    pub subscription_ids: Vec<String>,
+    #[serde(flatten)]
+    pub change: FileChangeEvent,
+}
+
+/// Path prefixes the frontend wants changes for, so one watcher covering the
+/// whole workspace can serve every open folder
+#[derive(Debug, Default)]
+pub struct Subscriptions {
+    prefixes: Mutex<HashMap<String, String>>,
+}
+
+impl Subscriptions {
+    /// Subscribe to changes at or under a workspace-relative prefix ("" for
+    /// everything), returning the subscription id
+    pub fn subscribe(&self, prefix: &str) -> String {
+        let id = uuid::Uuid::new_v4().to_string();
+        let prefix = prefix.replace('\\', "/").trim_matches('/').to_string();
+        if let Ok(mut prefixes) = self.prefixes.lock() {
+            prefixes.insert(id.clone(), prefix);
+        }
+        id
+    }
+
+    pub fn unsubscribe(&self, id: &str) -> bool {
+        self.prefixes
+            .lock()
+            .map(|mut prefixes| prefixes.remove(id).is_some())
+            .unwrap_or(false)
+    }
+
+    /// Ids of the subscriptions covering a file, sorted
+    pub fn matching(&self, file_key: &str) -> Vec<String> {
+        let file_key = file_key.replace('\\', "/");
+        let Ok(prefixes) = self.prefixes.lock() else {
+            return Vec::new();
+        };
+        let mut ids: Vec<String> = prefixes
+            .iter()
+            .filter(|(_, prefix)| {
+                prefix.is_empty()
+                    || file_key == **prefix
+                    || file_key.starts_with(&format!("{}/", prefix))
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        ids.sort();
+        ids
+    }
+}
+
 // ============================================================================
 // File Watcher
 // ============================================================================
@@ -104,7 +310,9 @@ pub struct FileWatcher {
     /// Pending events for debouncing
     pending_events: Arc<Mutex<HashMap<PathBuf, PendingEvent>>>,
     /// Watcher handle
-    watcher: Option<RecommendedWatcher>,
+    watcher: Option<Box<dyn Watcher + Send + Sync>>,
+    /// Poll at this interval instead of using native events
+    poll_interval: Option<Duration>,
     /// Stop signal
     stop_tx: Option<Sender<()>>,
 }
@@ -119,14 +327,20 @@ impl FileWatcher {
             recent_saves: Arc::new(Mutex::new(HashMap::new())),
             pending_events: Arc::new(Mutex::new(HashMap::new())),
             watcher: None,
+            poll_interval: None,
             stop_tx: None,
         }
     }
 
-    /// Start watching the workspace (convenience method for Tauri apps)
-    pub fn start<R: Runtime>(&mut self, app: AppHandle<R>) -> Result<(), String> {
-        let emitter = Arc::new(TauriEmitter::new(app));
-        self.start_with_emitter(emitter)
+    /// Poll for changes, comparing file contents, instead of relying on
+    /// native events
+    pub fn with_polling(mut self, interval: Duration) -> Self {
+        self.poll_interval = Some(interval);
+        self
+    }
+
+    pub fn is_polling(&self) -> bool {
+        self.poll_interval.is_some()
     }
 
     /// Start watching the workspace with a custom event emitter
@@ -138,14 +352,40 @@ impl FileWatcher {
         let (tx, rx) = channel::<notify::Result<Event>>();
         let (stop_tx, stop_rx) = channel::<()>();
 
-        // Create watcher
-        let watcher = RecommendedWatcher::new(
-            move |res| {
+        // Create watcher. Ignored paths are dropped here, before they reach
+        // the channel, so busy ignored folders don't flood it.
+        let ignore = IgnoreRules::new(&self.config.ignored_patterns);
+        let root = self.workspace_root.clone();
+        let handler = move |res: notify::Result<Event>| {
+            let res = res.map(|mut event| {
+                event.paths.retain(|path| {
+                    path.strip_prefix(&root)
+                        .map_or(true, |relative| !ignore.is_ignored(relative))
+                });
+                event
+            });
+            if res.as_ref().map_or(true, |event| !event.paths.is_empty()) {
                 let _ = tx.send(res);
-            },
-            Config::default().with_poll_interval(Duration::from_millis(100)),
-        )
-        .map_err(|e| format!("Failed to create watcher: {}", e))?;
+            }
+        };
+        let watcher: Box<dyn Watcher + Send + Sync> = match self.poll_interval {
+            Some(interval) => Box::new(
+                PollWatcher::new(
+                    handler,
+                    Config::default()
+                        .with_poll_interval(interval)
+                        .with_compare_contents(true),
+                )
+                .map_err(|e| format!("Failed to create watcher: {}", e))?,
+            ),
+            None => Box::new(
+                RecommendedWatcher::new(
+                    handler,
+                    Config::default().with_poll_interval(Duration::from_millis(100)),
+                )
+                .map_err(|e| format!("Failed to create watcher: {}", e))?,
+            ),
+        };
 
         self.watcher = Some(watcher);
         self.stop_tx = Some(stop_tx);
@@ -156,7 +396,11 @@ impl FileWatcher {
                 .map_err(|e| format!("Failed to watch directory: {}", e))?;
         }
 
-        info!("File watcher started for: {:?}", self.workspace_root);
+        info!(
+            "File watcher started for: {:?}{}",
+            self.workspace_root,
+            if self.is_polling() { " (polling)" } else { "" }
+        );
 
         // Spawn event processing thread
         let workspace_root = self.workspace_root.clone();
@@ -527,6 +771,140 @@ mod tests {
         assert_eq!(config.ignored_patterns.len(), 1);
     }
 
+    #[test]
+    fn test_subscriptions() {
+        let subscriptions = Subscriptions::default();
+        let notes = subscriptions.subscribe("notes/");
+        let all = subscriptions.subscribe("");
+        let deep = subscriptions.subscribe("notes\\daily");
+
+        let mut both = vec![notes.clone(), all.clone()];
+        both.sort();
+        assert_eq!(subscriptions.matching("notes/plan.md"), both);
+        assert_eq!(
+            subscriptions.matching("notes-old/plan.md"),
+            vec![all.clone()]
+        );
+        assert_eq!(subscriptions.matching("notes/daily/x.md").len(), 3);
+
+        assert!(subscriptions.unsubscribe(&deep));
+        assert!(!subscriptions.unsubscribe(&deep));
+        assert!(subscriptions.unsubscribe(&all));
+        assert_eq!(
+            subscriptions.matching("notes-old/plan.md"),
+            Vec::<String>::new()
+        );
+        assert!(subscriptions.unsubscribe(&notes));
+        assert!(subscriptions.matching("notes/daily/x.md").is_empty());
+    }
+
+    #[test]
+    fn test_ignore_rules() {
+        let rules = IgnoreRules::new(&[
+            "node_modules".to_string(),
+            "exports/**".to_string(),
+            "*.log".to_string(),
+            "{unclosed".to_string(),
+        ]);
+        assert!(rules.is_ignored(Path::new("app/node_modules/x/index.js")));
+        assert!(rules.is_ignored(Path::new("exports/2026/report.pdf")));
+        assert!(rules.is_ignored(Path::new("notes/debug.log")));
+        assert!(!rules.is_ignored(Path::new("notes/exports.midlight")));
+        assert!(!rules.is_ignored(Path::new("notes/plan.midlight")));
+    }
+
+    #[test]
+    fn test_workspace_settings() {
+        let temp = TempDir::new().unwrap();
+        assert_eq!(
+            WatcherSettings::load(temp.path()).unwrap(),
+            WatcherSettings::default()
+        );
+
+        let settings = WatcherSettings {
+            debounce_ms: Some(1500),
+            ignore: vec!["exports/**".to_string(), ".git".to_string()],
+            ..Default::default()
+        };
+        settings.save(temp.path()).unwrap();
+        assert_eq!(WatcherSettings::load(temp.path()).unwrap(), settings);
+
+        let config = FileWatcherConfig::with_settings(&settings);
+        assert_eq!(config.debounce_ms, 1500);
+        assert!(config
+            .ignored_patterns
+            .contains(&"node_modules".to_string()));
+        assert_eq!(
+            config
+                .ignored_patterns
+                .iter()
+                .filter(|p| *p == ".git")
+                .count(),
+            1
+        );
+        assert_eq!(config.ignored_patterns.last().unwrap(), "exports/**");
+
+        let too_fast = WatcherSettings {
+            debounce_ms: Some(1),
+            ..Default::default()
+        };
+        assert!(too_fast.save(temp.path()).is_err());
+        let bad_glob = WatcherSettings {
+            ignore: vec!["{unclosed".to_string()],
+            ..Default::default()
+        };
+        assert!(bad_glob.save(temp.path()).is_err());
+    }
+
+    #[test]
+    fn test_poll_interval() {
+        let temp = TempDir::new().unwrap();
+        // A temp directory is local, so native events are used by default
+        assert_eq!(WatcherSettings::default().poll_interval(temp.path()), None);
+
+        let forced = WatcherSettings {
+            polling: Some(true),
+            poll_interval_ms: Some(2_000),
+            ..Default::default()
+        };
+        assert_eq!(
+            forced.poll_interval(temp.path()),
+            Some(Duration::from_millis(2_000))
+        );
+        let too_fast = WatcherSettings {
+            poll_interval_ms: Some(10),
+            ..Default::default()
+        };
+        assert!(too_fast.save(temp.path()).is_err());
+    }
+
+    #[test]
+    fn test_polling_watcher_detects_changes() {
+        let temp = TempDir::new().unwrap();
+        let file_path = temp.path().join("note.md");
+        std::fs::write(&file_path, "before").unwrap();
+
+        let emitter = Arc::new(MockEmitter::new());
+        let config = FileWatcherConfig {
+            debounce_ms: 50,
+            ..Default::default()
+        };
+        let mut watcher = FileWatcher::new(temp.path().to_path_buf(), Some(config))
+            .with_polling(Duration::from_millis(100));
+        assert!(watcher.is_polling());
+        watcher.start_with_emitter(emitter.clone()).unwrap();
+
+        std::thread::sleep(Duration::from_millis(300));
+        std::fs::write(&file_path, "after").unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while emitter.get_events().is_empty() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        watcher.stop();
+        assert!(emitter.get_events().iter().any(|e| e.file_key == "note.md"));
+    }
+
     // ============================================================================
     // FileWatcher Construction Tests
     // ============================================================================
@@ -0,0 +1,501 @@
+// Find and Replace - Workspace-wide search and replace across documents
+//
+// Documents are searched in parallel, each .midlight document text node by
+// text node (a match never spans formatting) and Markdown documents as plain
+// text. A dry run returns matches with surrounding context for a preview.
+// Otherwise every document that changes gets a checkpoint of its content
+// from before the replace, so any one of them can be rolled back from its
+// version history. Locked documents are skipped.
+
+use crate::services::atomic_write::write_atomic;
+use crate::services::document_chunks::resolve_content;
+use crate::services::document_lock::is_locked_document;
+use crate::services::path_glob::PathGlob;
+use crate::services::rag_indexer::RAG_INDEXER;
+use crate::services::workspace_manager::WorkspaceManager;
+use crate::services::workspace_paths::document_key;
+use rayon::prelude::*;
+use regex::{Captures, Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Characters of context kept on each side of a match in previews
+const CONTEXT_CHARS: usize = 40;
+
+/// Matches listed per document; the count covers all of them
+const MAX_MATCHES_PER_DOCUMENT: usize = 100;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FindReplaceOptions {
+    pub find: String,
+    #[serde(default)]
+    pub replace: String,
+    /// Treat `find` as a regular expression; `replace` may use $1 and ${name}
+    #[serde(default)]
+    pub regex: bool,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    #[serde(default)]
+    pub whole_word: bool,
+    /// Globs over workspace-relative paths; every document if empty
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Only report what would change
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextMatch {
+    pub before: String,
+    pub matched: String,
+    pub after: String,
+    pub replacement: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentMatches {
+    /// Relative to the workspace root
+    pub path: String,
+    pub match_count: usize,
+    pub matches: Vec<TextMatch>,
+    /// Checkpoint of the document from before the replace
+    pub checkpoint_id: Option<String>,
+    #[serde(skip)]
+    replaced: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FindReplaceResult {
+    pub documents: Vec<DocumentMatches>,
+    pub total_matches: usize,
+    pub dry_run: bool,
+    /// Documents that matched but couldn't be updated, with the error
+    pub failed: Vec<(String, String)>,
+}
+
+impl FindReplaceResult {
+    /// The result of a dry run
+    pub fn preview(documents: Vec<DocumentMatches>) -> Self {
+        Self {
+            total_matches: documents.iter().map(|d| d.match_count).sum(),
+            documents,
+            dry_run: true,
+            failed: Vec::new(),
+        }
+    }
+}
+
+// ============================================================================
+// Find and Replace
+// ============================================================================
+
+pub struct FindReplace {
+    workspace_root: PathBuf,
+    pattern: Regex,
+    replace: String,
+    expand: bool,
+    include: Vec<PathGlob>,
+    exclude: Vec<PathGlob>,
+}
+
+impl FindReplace {
+    pub fn new(workspace_root: &Path, options: &FindReplaceOptions) -> Result<Self, String> {
+        if options.find.is_empty() {
+            return Err("Search text cannot be empty".to_string());
+        }
+        let mut pattern = match options.regex {
+            true => options.find.clone(),
+            false => regex::escape(&options.find),
+        };
+        if options.whole_word {
+            pattern = format!(r"\b(?:{})\b", pattern);
+        }
+        let pattern = RegexBuilder::new(&pattern)
+            .case_insensitive(!options.case_sensitive)
+            .multi_line(true)
+            .build()
+            .map_err(|e| format!("Invalid search pattern: {}", e))?;
+        if pattern.is_match("") {
+            return Err("Search pattern matches empty text".to_string());
+        }
+
+        let globs = |patterns: &[String]| -> Result<Vec<PathGlob>, String> {
+            patterns.iter().map(|p| PathGlob::new(p)).collect()
+        };
+        Ok(Self {
+            workspace_root: workspace_root.to_path_buf(),
+            pattern,
+            replace: options.replace.clone(),
+            expand: options.regex,
+            include: globs(&options.include)?,
+            exclude: globs(&options.exclude)?,
+        })
+    }
+
+    /// Find matches in every document in scope, in parallel. Documents are
+    /// sorted by path.
+    pub fn scan(&self) -> Vec<DocumentMatches> {
+        let mut documents: Vec<DocumentMatches> = self
+            .documents()
+            .par_iter()
+            .filter_map(|key| self.scan_document(key))
+            .collect();
+        documents.sort_by(|a, b| a.path.cmp(&b.path));
+        documents
+    }
+
+    /// Checkpoint and rewrite scanned documents
+    pub async fn apply(
+        &self,
+        documents: Vec<DocumentMatches>,
+        workspace: &WorkspaceManager,
+    ) -> FindReplaceResult {
+        let total_matches = documents.iter().map(|d| d.match_count).sum();
+        let mut applied = Vec::with_capacity(documents.len());
+        let mut failed = Vec::new();
+
+        for mut doc in documents {
+            let checkpoint = workspace
+                .checkpoint_file(&doc.path, "Before find and replace", None)
+                .await;
+            let checkpoint = match checkpoint {
+                Ok(checkpoint) => checkpoint,
+                Err(e) => {
+                    failed.push((doc.path, format!("Failed to create checkpoint: {}", e)));
+                    continue;
+                }
+            };
+
+            let path = self.workspace_root.join(&doc.path);
+            if let Err(e) = write_atomic(&path, &doc.replaced) {
+                failed.push((doc.path, format!("Failed to write file: {}", e)));
+                continue;
+            }
+            RAG_INDEXER.file_changed(path, false);
+            doc.checkpoint_id = checkpoint.map(|c| c.id);
+            applied.push(doc);
+        }
+
+        FindReplaceResult {
+            documents: applied,
+            total_matches,
+            dry_run: false,
+            failed,
+        }
+    }
+
+    /// Workspace-relative paths of documents in scope
+    fn documents(&self) -> Vec<String> {
+        WalkDir::new(&self.workspace_root)
+            .min_depth(1)
+            .into_iter()
+            .filter_entry(|e| !e.file_name().to_string_lossy().starts_with('.'))
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter_map(|e| {
+                let relative = e.path().strip_prefix(&self.workspace_root).ok()?;
+                let key = document_key(&relative.to_string_lossy());
+                (key.ends_with(".midlight") || key.ends_with(".md")).then_some(key)
+            })
+            .filter(|key| self.include.is_empty() || self.include.iter().any(|g| g.is_match(key)))
+            .filter(|key| !self.exclude.iter().any(|g| g.is_match(key)))
+            .collect()
+    }
+
+    fn scan_document(&self, key: &str) -> Option<DocumentMatches> {
+        let content = fs::read_to_string(self.workspace_root.join(key)).ok()?;
+        let mut matches = Vec::new();
+        let mut match_count = 0;
+
+        let replaced = if key.ends_with(".md") {
+            self.replace_text(&content, &mut matches, &mut match_count)?
+        } else {
+            let mut doc: Value = serde_json::from_str(&content).ok()?;
+            if is_locked_document(&doc) {
+                return None;
+            }
+            resolve_content(&self.workspace_root.join(key), &mut doc);
+            let changed = doc.get_mut("content").is_some_and(|content| {
+                self.replace_in_node(content, &mut matches, &mut match_count)
+            });
+            if !changed {
+                return None;
+            }
+            if let Some(meta) = doc.get_mut("meta").and_then(|m| m.as_object_mut()) {
+                meta.insert(
+                    "modified".to_string(),
+                    Value::String(chrono::Utc::now().to_rfc3339()),
+                );
+            }
+            serde_json::to_string_pretty(&doc).ok()?
+        };
+
+        Some(DocumentMatches {
+            path: key.to_string(),
+            match_count,
+            matches,
+            checkpoint_id: None,
+            replaced,
+        })
+    }
+
+    /// Replace in the text nodes under a Tiptap node, returning whether any
+    /// matched
+    fn replace_in_node(
+        &self,
+        node: &mut Value,
+        matches: &mut Vec<TextMatch>,
+        match_count: &mut usize,
+    ) -> bool {
+        match node {
+            Value::Object(map) => {
+                let mut changed = false;
+                if let Some(Value::String(text)) = map.get_mut("text") {
+                    if let Some(replaced) = self.replace_text(text, matches, match_count) {
+                        *text = replaced;
+                        changed = true;
+                    }
+                }
+                for (key, value) in map.iter_mut() {
+                    if key != "text" {
+                        changed |= self.replace_in_node(value, matches, match_count);
+                    }
+                }
+                changed
+            }
+            Value::Array(items) => {
+                let mut changed = false;
+                for item in items {
+                    changed |= self.replace_in_node(item, matches, match_count);
+                }
+                changed
+            }
+            _ => false,
+        }
+    }
+
+    /// Replace every match in a piece of text, or None if nothing matched
+    fn replace_text(
+        &self,
+        text: &str,
+        matches: &mut Vec<TextMatch>,
+        match_count: &mut usize,
+    ) -> Option<String> {
+        let mut out = String::with_capacity(text.len());
+        let mut last = 0;
+        for caps in self.pattern.captures_iter(text) {
+            let found = caps.get(0).unwrap();
+            let replacement = self.replacement(&caps);
+            if matches.len() < MAX_MATCHES_PER_DOCUMENT {
+                matches.push(context(text, found.start(), found.end(), &replacement));
+            }
+            *match_count += 1;
+            out.push_str(&text[last..found.start()]);
+            out.push_str(&replacement);
+            last = found.end();
+        }
+        if last == 0 {
+            return None;
+        }
+        out.push_str(&text[last..]);
+        (out != text).then_some(out)
+    }
+
+    fn replacement(&self, caps: &Captures) -> String {
+        if !self.expand {
+            return self.replace.clone();
+        }
+        let mut expanded = String::new();
+        caps.expand(&self.replace, &mut expanded);
+        expanded
+    }
+}
+
+/// A match with the text around it on the same line
+fn context(text: &str, start: usize, end: usize, replacement: &str) -> TextMatch {
+    let before_start = text[..start]
+        .char_indices()
+        .rev()
+        .nth(CONTEXT_CHARS - 1)
+        .map_or(0, |(i, _)| i);
+    let after_end = text[end..]
+        .char_indices()
+        .nth(CONTEXT_CHARS)
+        .map_or(text.len(), |(i, _)| end + i);
+    let before = &text[before_start..start];
+    let after = &text[end..after_end];
+
+    TextMatch {
+        before: before.rsplit('\n').next().unwrap_or_default().to_string(),
+        matched: text[start..end].to_string(),
+        after: after.split('\n').next().unwrap_or_default().to_string(),
+        replacement: replacement.to_string(),
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn setup() -> TempDir {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join(".midlight")).unwrap();
+        fs::create_dir_all(root.join("drafts")).unwrap();
+        let doc = json!({
+            "version": 1,
+            "meta": { "created": "2026-01-01T00:00:00Z", "modified": "2026-01-01T00:00:00Z" },
+            "content": { "type": "doc", "content": [
+                { "type": "paragraph", "content": [
+                    { "type": "text", "text": "Acme Corp ships " },
+                    { "type": "text", "text": "acme", "marks": [{ "type": "bold" }] }
+                ]}
+            ]}
+        });
+        fs::write(root.join("a.midlight"), doc.to_string()).unwrap();
+        fs::write(
+            root.join("drafts/b.md"),
+            "Line one\nPrices at acme: 2024-03-01\n",
+        )
+        .unwrap();
+        fs::write(root.join("drafts/c.md"), "Acmeology is unrelated").unwrap();
+        fs::write(root.join(".midlight/notes.md"), "acme").unwrap();
+        temp
+    }
+
+    fn options(find: &str, replace: &str) -> FindReplaceOptions {
+        FindReplaceOptions {
+            find: find.to_string(),
+            replace: replace.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_scan_documents() {
+        let temp = setup();
+        let scan = FindReplace::new(temp.path(), &options("acme", "Initech"))
+            .unwrap()
+            .scan();
+        let found: Vec<_> = scan
+            .iter()
+            .map(|d| (d.path.as_str(), d.match_count))
+            .collect();
+        assert_eq!(
+            found,
+            vec![("a.midlight", 2), ("drafts/b.md", 1), ("drafts/c.md", 1)]
+        );
+
+        // Context stays on the match's line
+        assert_eq!(
+            scan[1].matches[0],
+            TextMatch {
+                before: "Prices at ".to_string(),
+                matched: "acme".to_string(),
+                after: ": 2024-03-01".to_string(),
+                replacement: "Initech".to_string(),
+            }
+        );
+
+        // Formatting is kept, and meta.modified is updated
+        let doc: Value = serde_json::from_str(&scan[0].replaced).unwrap();
+        let text = &doc["content"]["content"][0]["content"];
+        assert_eq!(text[0]["text"], "Initech Corp ships ");
+        assert_eq!(text[1]["text"], "Initech");
+        assert_eq!(text[1]["marks"][0]["type"], "bold");
+        assert_ne!(doc["meta"]["modified"], "2026-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn test_options() {
+        let temp = setup();
+        let run = |options: FindReplaceOptions| -> Vec<String> {
+            FindReplace::new(temp.path(), &options)
+                .unwrap()
+                .scan()
+                .into_iter()
+                .map(|d| d.path)
+                .collect()
+        };
+
+        let whole_word = FindReplaceOptions {
+            whole_word: true,
+            case_sensitive: true,
+            ..options("acme", "x")
+        };
+        assert_eq!(run(whole_word), vec!["a.midlight", "drafts/b.md"]);
+
+        let scoped = FindReplaceOptions {
+            include: vec!["drafts/**".to_string()],
+            exclude: vec!["c.md".to_string()],
+            ..options("acme", "x")
+        };
+        assert_eq!(run(scoped), vec!["drafts/b.md"]);
+
+        let dates = FindReplaceOptions {
+            regex: true,
+            ..options(r"(\d{4})-(\d{2})-(\d{2})", "$3/$2/$1")
+        };
+        let scan = FindReplace::new(temp.path(), &dates).unwrap().scan();
+        assert_eq!(scan[0].replaced, "Line one\nPrices at acme: 01/03/2024\n");
+
+        assert!(FindReplace::new(temp.path(), &options("", "x")).is_err());
+        let empty = FindReplaceOptions {
+            regex: true,
+            ..options("a*", "x")
+        };
+        assert!(FindReplace::new(temp.path(), &empty).is_err());
+        let invalid = FindReplaceOptions {
+            regex: true,
+            ..options("(", "x")
+        };
+        assert!(FindReplace::new(temp.path(), &invalid).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_apply_creates_checkpoints() {
+        let temp = setup();
+        let workspace = WorkspaceManager::new(temp.path());
+        workspace.init().await.unwrap();
+
+        let find_replace = FindReplace::new(temp.path(), &options("acme", "Initech")).unwrap();
+        let result = find_replace.apply(find_replace.scan(), &workspace).await;
+        assert_eq!(result.total_matches, 4);
+        assert!(result.failed.is_empty());
+        assert_eq!(
+            fs::read_to_string(temp.path().join("drafts/c.md")).unwrap(),
+            "Initechology is unrelated"
+        );
+
+        let checkpoint_id = result.documents[0].checkpoint_id.clone().unwrap();
+        let restored = workspace
+            .restore_checkpoint("a.midlight", &checkpoint_id)
+            .await
+            .unwrap();
+        assert_eq!(
+            restored["content"][0]["content"][0]["text"],
+            "Acme Corp ships "
+        );
+        assert!(find_replace.scan().is_empty());
+    }
+}
@@ -0,0 +1,566 @@
+// Flashcards - Spaced repetition review of cards written in documents
+//
+// Cards are written inline in any document:
+//   Capital of France :: Paris          a question and its answer
+//   Hund ::: dog                        reviewed both ways, as two cards
+//   The ==mitochondria== makes ATP      a cloze deletion
+//   {{c1::Ottawa}} is in {{c2::Canada}} Anki-style cloze deletions
+// In .midlight documents highlighted text is a cloze deletion too. Each
+// deletion in a line is its own card, showing the rest of the line.
+//
+// Reviews are scheduled with SM-2: each answer is graded 0-5, and the gap
+// until the card is due again grows with the card's ease while answers are
+// correct (3 or more) and starts over when they aren't. Cards are identified
+// by the text they show, so scheduling survives moving a card to another
+// document or rewording its answer.
+//
+// Scheduling is stored at: .midlight/flashcards.json
+// Format:
+// {
+//   "version": 1,
+//   "cards": {
+//     "9f3c…": { "repetitions": 2, "interval": 6, "ease": 2.5, "due": "2026-03-20", "reviewed": "2026-03-14" }
+//   }
+// }
+
+use crate::services::atomic_write::write_atomic;
+use crate::services::document_chunks::resolve_content;
+use crate::services::path_glob::PathGlob;
+use crate::services::workspace_paths::document_key;
+use chrono::{Duration, NaiveDate};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+use xxhash_rust::xxh64::xxh64;
+
+const STORE_VERSION: u32 = 1;
+
+/// New cards introduced per session when no limit is given
+const DEFAULT_NEW_CARDS: usize = 20;
+
+/// Shown in place of a cloze deletion without a hint
+const CLOZE_BLANK: &str = "[...]";
+
+const INITIAL_EASE: f64 = 2.5;
+const MIN_EASE: f64 = 1.3;
+
+lazy_static! {
+    static ref CLOZE: Regex = Regex::new(r"==([^=]+?)==|\{\{c\d+::(.+?)(?:::(.+?))?\}\}").unwrap();
+    static ref LIST_MARKER: Regex =
+        Regex::new(r"^\s*(?:[-*+]|\d+[.)])\s+(?:\[[ xX]\]\s+)?").unwrap();
+}
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CardKind {
+    Basic,
+    Reversed,
+    Cloze,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Flashcard {
+    pub id: String,
+    /// Document the card is written in, relative to the workspace root
+    pub document: String,
+    pub kind: CardKind,
+    pub front: String,
+    pub back: String,
+}
+
+/// SM-2 scheduling of one card
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CardSchedule {
+    /// Correct answers in a row
+    pub repetitions: u32,
+    /// Days until the card is due again
+    pub interval: u32,
+    pub ease: f64,
+    pub due: NaiveDate,
+    pub reviewed: NaiveDate,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DueCard {
+    #[serde(flatten)]
+    pub card: Flashcard,
+    /// None for cards never reviewed
+    pub schedule: Option<CardSchedule>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewSession {
+    /// Cards due for review first, then new cards
+    pub cards: Vec<DueCard>,
+    pub review_count: usize,
+    pub new_count: usize,
+    /// New cards left for later sessions
+    pub new_remaining: usize,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoreFile {
+    version: u32,
+    cards: HashMap<String, CardSchedule>,
+}
+
+// ============================================================================
+// Extraction
+// ============================================================================
+
+/// Cards written in a document's text lines
+pub fn extract_cards(document: &str, lines: &[String]) -> Vec<Flashcard> {
+    let mut cards = Vec::new();
+    for line in lines {
+        let line = LIST_MARKER.replace(line, "");
+        let line = line.trim();
+
+        if CLOZE.is_match(line) {
+            let deletions: Vec<_> = CLOZE.captures_iter(line).collect();
+            for hidden in 0..deletions.len() {
+                let mut front = String::new();
+                let mut back = String::new();
+                let mut last = 0;
+                for (i, caps) in deletions.iter().enumerate() {
+                    let whole = caps.get(0).unwrap();
+                    let answer = caps.get(1).or_else(|| caps.get(2)).unwrap().as_str();
+                    front.push_str(&line[last..whole.start()]);
+                    if i == hidden {
+                        match caps.get(3) {
+                            Some(hint) => front.push_str(&format!("[{}]", hint.as_str())),
+                            None => front.push_str(CLOZE_BLANK),
+                        }
+                        back = answer.to_string();
+                    } else {
+                        front.push_str(answer);
+                    }
+                    last = whole.end();
+                }
+                front.push_str(&line[last..]);
+                cards.push(card(document, CardKind::Cloze, front, back));
+            }
+        } else if let Some((question, answer)) = line.split_once(":::") {
+            let (question, answer) = (question.trim(), answer.trim());
+            if !question.is_empty() && !answer.is_empty() {
+                cards.push(card(
+                    document,
+                    CardKind::Basic,
+                    question.into(),
+                    answer.into(),
+                ));
+                cards.push(card(
+                    document,
+                    CardKind::Reversed,
+                    answer.into(),
+                    question.into(),
+                ));
+            }
+        } else if let Some((question, answer)) = line.split_once("::") {
+            let (question, answer) = (question.trim(), answer.trim());
+            if !question.is_empty() && !answer.is_empty() {
+                cards.push(card(
+                    document,
+                    CardKind::Basic,
+                    question.into(),
+                    answer.into(),
+                ));
+            }
+        }
+    }
+    cards
+}
+
+fn card(document: &str, kind: CardKind, front: String, back: String) -> Flashcard {
+    Flashcard {
+        id: format!("{:016x}", xxh64(front.as_bytes(), 0)),
+        document: document.to_string(),
+        kind,
+        front,
+        back,
+    }
+}
+
+/// Lines of a Markdown file, without fenced code blocks
+fn markdown_lines(content: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut in_fence = false;
+    for line in content.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        } else if !in_fence {
+            lines.push(line.to_string());
+        }
+    }
+    lines
+}
+
+/// Text blocks of a Tiptap document, one line each, with highlighted text
+/// written as `==cloze==`
+fn document_lines(node: &Value, lines: &mut Vec<String>) {
+    match node.get("type").and_then(Value::as_str) {
+        Some("codeBlock") => return,
+        Some("paragraph") | Some("heading") => {
+            let mut line = String::new();
+            for child in node
+                .get("content")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+            {
+                let Some(text) = child.get("text").and_then(Value::as_str) else {
+                    continue;
+                };
+                let highlighted =
+                    child
+                        .get("marks")
+                        .and_then(Value::as_array)
+                        .is_some_and(|marks| {
+                            marks
+                                .iter()
+                                .any(|m| m.get("type").and_then(Value::as_str) == Some("highlight"))
+                        });
+                match highlighted {
+                    true => line.push_str(&format!("=={}==", text)),
+                    false => line.push_str(text),
+                }
+            }
+            lines.push(line);
+            return;
+        }
+        _ => {}
+    }
+    for child in node
+        .get("content")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        document_lines(child, lines);
+    }
+}
+
+/// Cards in every document of a workspace, optionally limited by a path glob
+pub fn workspace_cards(
+    workspace_root: &Path,
+    documents: Option<&str>,
+) -> Result<Vec<Flashcard>, String> {
+    let glob = documents.map(PathGlob::new).transpose()?;
+    let mut cards = Vec::new();
+
+    let entries = WalkDir::new(workspace_root)
+        .min_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| !e.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file());
+    for entry in entries {
+        let Ok(relative) = entry.path().strip_prefix(workspace_root) else {
+            continue;
+        };
+        let key = document_key(&relative.to_string_lossy());
+        let markdown = key.ends_with(".md");
+        if !markdown && !key.ends_with(".midlight") {
+            continue;
+        }
+        if glob.as_ref().is_some_and(|g| !g.is_match(&key)) {
+            continue;
+        }
+        let Ok(content) = fs::read_to_string(entry.path()) else {
+            continue;
+        };
+
+        let lines = if markdown {
+            markdown_lines(&content)
+        } else {
+            let mut lines = Vec::new();
+            if let Ok(mut document) = serde_json::from_str::<Value>(&content) {
+                resolve_content(entry.path(), &mut document);
+                document_lines(document.get("content").unwrap_or(&Value::Null), &mut lines);
+            }
+            lines
+        };
+        cards.extend(extract_cards(&key, &lines));
+    }
+    Ok(cards)
+}
+
+// ============================================================================
+// Scheduling
+// ============================================================================
+
+/// Schedule a card after an answer graded 0 (forgotten) to 5 (perfect)
+pub fn review(schedule: Option<&CardSchedule>, grade: u8, today: NaiveDate) -> CardSchedule {
+    let grade = grade.min(5);
+    let (repetitions, interval, ease) = match schedule {
+        Some(s) => (s.repetitions, s.interval, s.ease),
+        None => (0, 0, INITIAL_EASE),
+    };
+
+    let (repetitions, interval) = if grade >= 3 {
+        let interval = match repetitions {
+            0 => 1,
+            1 => 6,
+            _ => (interval as f64 * ease).round() as u32,
+        };
+        (repetitions + 1, interval)
+    } else {
+        (0, 1)
+    };
+    let miss = f64::from(5 - grade);
+    let ease = (ease + 0.1 - miss * (0.08 + miss * 0.02)).max(MIN_EASE);
+
+    CardSchedule {
+        repetitions,
+        interval,
+        ease,
+        due: today + Duration::days(i64::from(interval)),
+        reviewed: today,
+    }
+}
+
+pub struct FlashcardStore {
+    store_path: PathBuf,
+}
+
+impl FlashcardStore {
+    pub fn new(workspace_root: &Path) -> Self {
+        Self {
+            store_path: workspace_root.join(".midlight").join("flashcards.json"),
+        }
+    }
+
+    fn load(&self) -> Result<StoreFile, String> {
+        if !self.store_path.exists() {
+            return Ok(StoreFile {
+                version: STORE_VERSION,
+                cards: HashMap::new(),
+            });
+        }
+        let content = fs::read_to_string(&self.store_path)
+            .map_err(|e| format!("Failed to read flashcards: {}", e))?;
+        serde_json::from_str(&content).map_err(|e| format!("Failed to parse flashcards: {}", e))
+    }
+
+    /// Cards due on or before `today`, oldest first, followed by up to
+    /// `new_limit` cards never reviewed
+    pub fn session(
+        &self,
+        cards: Vec<Flashcard>,
+        today: NaiveDate,
+        new_limit: Option<usize>,
+    ) -> Result<ReviewSession, String> {
+        let store = self.load()?;
+        let mut due = Vec::new();
+        let mut new = Vec::new();
+        let mut seen = std::collections::HashSet::new();
+
+        for card in cards {
+            // The same card written twice is reviewed once
+            if !seen.insert(card.id.clone()) {
+                continue;
+            }
+            match store.cards.get(&card.id) {
+                Some(schedule) if schedule.due <= today => due.push(DueCard {
+                    schedule: Some(schedule.clone()),
+                    card,
+                }),
+                Some(_) => {}
+                None => new.push(DueCard {
+                    card,
+                    schedule: None,
+                }),
+            }
+        }
+
+        due.sort_by_key(|c| c.schedule.as_ref().map(|s| s.due));
+        let new_total = new.len();
+        new.truncate(new_limit.unwrap_or(DEFAULT_NEW_CARDS));
+
+        Ok(ReviewSession {
+            review_count: due.len(),
+            new_count: new.len(),
+            new_remaining: new_total - new.len(),
+            cards: due.into_iter().chain(new).collect(),
+        })
+    }
+
+    /// Record an answer to a card and return when it's due next
+    pub fn record_review(
+        &self,
+        card_id: &str,
+        grade: u8,
+        today: NaiveDate,
+    ) -> Result<CardSchedule, String> {
+        if grade > 5 {
+            return Err(format!("Grade must be 0 to 5, not {}", grade));
+        }
+        let mut store = self.load()?;
+        let schedule = review(store.cards.get(card_id), grade, today);
+        store.cards.insert(card_id.to_string(), schedule.clone());
+        store.version = STORE_VERSION;
+
+        let json = serde_json::to_string_pretty(&store)
+            .map_err(|e| format!("Failed to serialize flashcards: {}", e))?;
+        write_atomic(&self.store_path, json)
+            .map_err(|e| format!("Failed to write flashcards: {}", e))?;
+        Ok(schedule)
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, d).unwrap()
+    }
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_extract_cards() {
+        let cards = extract_cards(
+            "Notes.md",
+            &lines(
+                "- Capital of France :: Paris\n\
+                 Hund ::: dog\n\
+                 {{c1::Ottawa}} is the capital of {{c2::Canada::country}}\n\
+                 The ==mitochondria== makes ATP\n\
+                 Just a note: nothing here",
+            ),
+        );
+        let pairs: Vec<(CardKind, &str, &str)> = cards
+            .iter()
+            .map(|c| (c.kind, c.front.as_str(), c.back.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                (CardKind::Basic, "Capital of France", "Paris"),
+                (CardKind::Basic, "Hund", "dog"),
+                (CardKind::Reversed, "dog", "Hund"),
+                (CardKind::Cloze, "[...] is the capital of Canada", "Ottawa"),
+                (
+                    CardKind::Cloze,
+                    "Ottawa is the capital of [country]",
+                    "Canada"
+                ),
+                (CardKind::Cloze, "The [...] makes ATP", "mitochondria"),
+            ]
+        );
+        assert_ne!(cards[0].id, cards[1].id);
+        assert_eq!(
+            cards[0].id,
+            extract_cards("Other.md", &lines("Capital of France :: Lyon"))[0].id
+        );
+
+        let mut from_document = Vec::new();
+        document_lines(
+            &json!({ "type": "doc", "content": [
+                { "type": "paragraph", "content": [
+                    { "type": "text", "text": "Water boils at " },
+                    { "type": "text", "text": "100 °C", "marks": [{ "type": "highlight" }] }
+                ]},
+                { "type": "codeBlock", "content": [{ "type": "text", "text": "a :: b" }] }
+            ]}),
+            &mut from_document,
+        );
+        let cards = extract_cards("Science.midlight", &from_document);
+        assert_eq!(cards.len(), 1);
+        assert_eq!(cards[0].front, "Water boils at [...]");
+        assert_eq!(cards[0].back, "100 °C");
+    }
+
+    #[test]
+    fn test_sm2() {
+        let first = review(None, 5, day(1));
+        assert_eq!(
+            (first.repetitions, first.interval, first.due),
+            (1, 1, day(2))
+        );
+        assert!((first.ease - 2.6).abs() < 1e-9);
+
+        let second = review(Some(&first), 4, day(2));
+        assert_eq!((second.repetitions, second.interval), (2, 6));
+        let third = review(Some(&second), 3, day(8));
+        assert_eq!(third.interval, 16);
+        assert!(third.ease < second.ease);
+
+        let forgotten = review(Some(&third), 1, day(24));
+        assert_eq!((forgotten.repetitions, forgotten.interval), (0, 1));
+        assert_eq!(forgotten.due, day(25));
+
+        let mut hard = first.clone();
+        for _ in 0..10 {
+            hard = review(Some(&hard), 0, day(3));
+        }
+        assert_eq!(hard.ease, MIN_EASE);
+    }
+
+    #[test]
+    fn test_review_session() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("Languages")).unwrap();
+        fs::write(
+            root.join("Languages/German.md"),
+            "Hund :: dog\nKatze :: cat\nMaus :: mouse",
+        )
+        .unwrap();
+        fs::write(root.join("Other.md"), "Hund :: dog\nPi :: 3.14").unwrap();
+
+        let cards = workspace_cards(root, Some("Languages/**")).unwrap();
+        assert_eq!(cards.len(), 3);
+        assert_eq!(workspace_cards(root, None).unwrap().len(), 5);
+
+        let store = FlashcardStore::new(root);
+        let session = store
+            .session(workspace_cards(root, None).unwrap(), day(1), Some(3))
+            .unwrap();
+        assert_eq!(
+            (
+                session.review_count,
+                session.new_count,
+                session.new_remaining
+            ),
+            (0, 3, 1)
+        );
+
+        store.record_review(&cards[0].id, 4, day(1)).unwrap();
+        store.record_review(&cards[1].id, 2, day(1)).unwrap();
+        assert!(store.record_review(&cards[2].id, 6, day(1)).is_err());
+
+        let session = store
+            .session(workspace_cards(root, None).unwrap(), day(2), None)
+            .unwrap();
+        assert_eq!(session.review_count, 2);
+        assert_eq!(session.new_count, 2);
+        assert!(session.cards[0].schedule.is_some());
+        assert!(session.cards[3].schedule.is_none());
+
+        let later = store.session(cards, day(1), None).unwrap();
+        assert_eq!(later.review_count, 0);
+    }
+}
@@ -0,0 +1,320 @@
+// Generation Parameters - Validation and per-model capability detection
+//
+// Requests may set temperature, top_p, max_tokens, stop sequences and JSON
+// mode. Values are validated against the target model before the request is
+// sent, and parameters the model doesn't accept (e.g. temperature on OpenAI
+// reasoning models) are stripped so the provider doesn't reject the request.
+
+use crate::services::llm_service::{ChatRequest, LLMError};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+/// Maximum number of stop sequences accepted by any provider
+pub const MAX_STOP_SEQUENCES: usize = 4;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Generation parameters a model accepts
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelCapabilities {
+    pub temperature: bool,
+    pub max_temperature: f32,
+    pub top_p: bool,
+    pub stop_sequences: bool,
+    pub json_mode: bool,
+    /// Upper bound for `max_tokens`, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<u32>,
+}
+
+impl ModelCapabilities {
+    const fn full(max_temperature: f32, max_output_tokens: Option<u32>) -> Self {
+        Self {
+            temperature: true,
+            max_temperature,
+            top_p: true,
+            stop_sequences: true,
+            json_mode: true,
+            max_output_tokens,
+        }
+    }
+
+    /// OpenAI reasoning models only accept the default sampling settings
+    const fn reasoning(max_output_tokens: u32, stop_sequences: bool) -> Self {
+        Self {
+            temperature: false,
+            max_temperature: 2.0,
+            top_p: false,
+            stop_sequences,
+            json_mode: true,
+            max_output_tokens: Some(max_output_tokens),
+        }
+    }
+
+    /// Anthropic models: temperature 0-1, no native JSON mode
+    const fn anthropic(max_output_tokens: u32) -> Self {
+        Self {
+            temperature: true,
+            max_temperature: 1.0,
+            top_p: true,
+            stop_sequences: true,
+            json_mode: false,
+            max_output_tokens: Some(max_output_tokens),
+        }
+    }
+}
+
+impl Default for ModelCapabilities {
+    fn default() -> Self {
+        Self::full(2.0, None)
+    }
+}
+
+/// Known model capabilities by name prefix. Lookups use the longest match.
+const MODEL_CAPABILITIES: &[(&str, ModelCapabilities)] = &[
+    // OpenAI
+    ("gpt-5", ModelCapabilities::reasoning(128_000, false)),
+    ("gpt-4.1", ModelCapabilities::full(2.0, Some(32_768))),
+    ("gpt-4o", ModelCapabilities::full(2.0, Some(16_384))),
+    ("gpt-4-turbo", ModelCapabilities::full(2.0, Some(4_096))),
+    ("gpt-4", ModelCapabilities::full(2.0, Some(8_192))),
+    ("o1", ModelCapabilities::reasoning(100_000, false)),
+    ("o3", ModelCapabilities::reasoning(100_000, false)),
+    ("o4", ModelCapabilities::reasoning(100_000, false)),
+    // Anthropic
+    ("claude-opus-4", ModelCapabilities::anthropic(32_000)),
+    ("claude-sonnet-4", ModelCapabilities::anthropic(64_000)),
+    ("claude-3-7-sonnet", ModelCapabilities::anthropic(64_000)),
+    ("claude", ModelCapabilities::anthropic(8_192)),
+    // Google
+    ("gemini-2.5", ModelCapabilities::full(2.0, Some(65_536))),
+    ("gemini", ModelCapabilities::full(2.0, Some(8_192))),
+];
+
+// ============================================================================
+// Capability Detection
+// ============================================================================
+
+/// Look up the generation parameters a model accepts
+pub fn capabilities_for_model(model: &str) -> ModelCapabilities {
+    let model = model.to_lowercase();
+    MODEL_CAPABILITIES
+        .iter()
+        .filter(|(prefix, _)| model.starts_with(prefix))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|&(_, capabilities)| capabilities)
+        .unwrap_or_default()
+}
+
+// ============================================================================
+// Validation
+// ============================================================================
+
+fn invalid(message: String) -> LLMError {
+    LLMError {
+        code: "INVALID_REQUEST".to_string(),
+        message,
+        details: None,
+    }
+}
+
+/// Validate a request's generation parameters for its model, stripping any
+/// the model doesn't support. Out-of-range values are rejected.
+pub fn normalize(request: &mut ChatRequest) -> Result<(), LLMError> {
+    let capabilities = capabilities_for_model(&request.model);
+
+    if let Some(temperature) = request.temperature {
+        if !(0.0..=capabilities.max_temperature).contains(&temperature) {
+            return Err(invalid(format!(
+                "temperature must be between 0 and {} for {}",
+                capabilities.max_temperature, request.model
+            )));
+        }
+    }
+
+    if let Some(top_p) = request.top_p {
+        if !(top_p > 0.0 && top_p <= 1.0) {
+            return Err(invalid(
+                "top_p must be greater than 0 and at most 1".to_string(),
+            ));
+        }
+    }
+
+    if let Some(max_tokens) = request.max_tokens {
+        if max_tokens == 0 {
+            return Err(invalid("max_tokens must be at least 1".to_string()));
+        }
+        if let Some(limit) = capabilities.max_output_tokens {
+            if max_tokens > limit {
+                return Err(invalid(format!(
+                    "max_tokens must be at most {} for {}",
+                    limit, request.model
+                )));
+            }
+        }
+    }
+
+    if let Some(ref stop) = request.stop {
+        if stop.len() > MAX_STOP_SEQUENCES {
+            return Err(invalid(format!(
+                "At most {} stop sequences are allowed",
+                MAX_STOP_SEQUENCES
+            )));
+        }
+        if stop.iter().any(|s| s.is_empty()) {
+            return Err(invalid("Stop sequences cannot be empty".to_string()));
+        }
+    }
+
+    let mut stripped = Vec::new();
+    if !capabilities.temperature && request.temperature.take().is_some() {
+        stripped.push("temperature");
+    }
+    if !capabilities.top_p && request.top_p.take().is_some() {
+        stripped.push("top_p");
+    }
+    if !capabilities.stop_sequences && request.stop.take().is_some() {
+        stripped.push("stop");
+    }
+    if request.stop.as_ref().is_some_and(|s| s.is_empty()) {
+        request.stop = None;
+    }
+    if !capabilities.json_mode && request.json_mode.take() == Some(true) {
+        stripped.push("json_mode");
+    }
+
+    if !stripped.is_empty() {
+        debug!(
+            "Dropped unsupported parameters for {}: {}",
+            request.model,
+            stripped.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(model: &str) -> ChatRequest {
+        ChatRequest {
+            provider: "openai".to_string(),
+            model: model.to_string(),
+            messages: vec![],
+            temperature: None,
+            max_tokens: None,
+            top_p: None,
+            stop: None,
+            json_mode: None,
+            stream: None,
+            request_type: None,
+            web_search_enabled: None,
+            bypass_cache: false,
+        }
+    }
+
+    #[test]
+    fn test_capabilities_for_model() {
+        assert!(capabilities_for_model("gpt-4o-mini").temperature);
+        assert!(!capabilities_for_model("o3-mini").temperature);
+        assert!(!capabilities_for_model("claude-sonnet-4-20250514").json_mode);
+        assert_eq!(
+            capabilities_for_model("claude-sonnet-4").max_output_tokens,
+            Some(64_000)
+        );
+        assert_eq!(
+            capabilities_for_model("claude-3-haiku").max_temperature,
+            1.0
+        );
+        assert_eq!(
+            capabilities_for_model("unknown-model"),
+            ModelCapabilities::default()
+        );
+    }
+
+    #[test]
+    fn test_valid_parameters_pass_through() {
+        let mut req = request("gpt-4o");
+        req.temperature = Some(1.5);
+        req.top_p = Some(0.9);
+        req.max_tokens = Some(1000);
+        req.stop = Some(vec!["\n\n".to_string()]);
+        req.json_mode = Some(true);
+
+        normalize(&mut req).unwrap();
+        assert_eq!(req.temperature, Some(1.5));
+        assert_eq!(req.top_p, Some(0.9));
+        assert_eq!(req.stop.as_ref().unwrap().len(), 1);
+        assert_eq!(req.json_mode, Some(true));
+    }
+
+    #[test]
+    fn test_out_of_range_values_rejected() {
+        let mut req = request("claude-3-5-sonnet");
+        req.temperature = Some(1.5);
+        assert_eq!(normalize(&mut req).unwrap_err().code, "INVALID_REQUEST");
+
+        let mut req = request("gpt-4o");
+        req.top_p = Some(0.0);
+        assert!(normalize(&mut req).is_err());
+
+        let mut req = request("gpt-4o");
+        req.max_tokens = Some(100_000);
+        assert!(normalize(&mut req).is_err());
+
+        let mut req = request("gpt-4o");
+        req.max_tokens = Some(0);
+        assert!(normalize(&mut req).is_err());
+    }
+
+    #[test]
+    fn test_stop_sequence_validation() {
+        let mut req = request("gpt-4o");
+        req.stop = Some(vec![
+            "a".into(),
+            "b".into(),
+            "c".into(),
+            "d".into(),
+            "e".into(),
+        ]);
+        assert!(normalize(&mut req).is_err());
+
+        let mut req = request("gpt-4o");
+        req.stop = Some(vec!["".into()]);
+        assert!(normalize(&mut req).is_err());
+
+        let mut req = request("gpt-4o");
+        req.stop = Some(vec![]);
+        normalize(&mut req).unwrap();
+        assert!(req.stop.is_none());
+    }
+
+    #[test]
+    fn test_unsupported_parameters_stripped() {
+        let mut req = request("o3-mini");
+        req.temperature = Some(0.5);
+        req.top_p = Some(0.9);
+        req.stop = Some(vec!["END".into()]);
+        req.json_mode = Some(true);
+
+        normalize(&mut req).unwrap();
+        assert!(req.temperature.is_none());
+        assert!(req.top_p.is_none());
+        assert!(req.stop.is_none());
+        assert_eq!(req.json_mode, Some(true));
+
+        let mut req = request("claude-sonnet-4");
+        req.json_mode = Some(true);
+        normalize(&mut req).unwrap();
+        assert!(req.json_mode.is_none());
+    }
+}
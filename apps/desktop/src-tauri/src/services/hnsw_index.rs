@@ -0,0 +1,647 @@
+// HNSW Index - Approximate nearest neighbour index for chunk embeddings
+//
+// A Hierarchical Navigable Small World graph over unit-normalized vectors,
+// so similarity is a dot product. Searching visits a few hundred nodes instead
+// of every chunk, which keeps search fast in workspaces with tens of thousands
+// of chunks. Removed chunks are tombstoned and dropped on the next compaction.
+//
+// The index is saved next to the vector database (vectors.hnsw) and read back
+// in a single pass at startup, so the graph never has to be rebuilt from the
+// stored embeddings unless it is missing or out of date. The file records the
+// generation of the database it reflects for that check. It isn't memory
+// mapped: indexing inserts and compacts in place, so the graph has to live in
+// owned, growable buffers anyway, and one sequential read of the file is cheap
+// next to rebuilding it.
+//
+// File format (little endian):
+//   magic "MLHNSW01", generation u64, dim u32, m u32, node count u32,
+//   entry point u32 (u32::MAX if empty), max level u32,
+//   then per node: id (u32 length + UTF-8), deleted u8, level u8,
+//   vector (dim x f32), and for each level 0..=level its links (u32 count + u32s)
+
+use crate::services::atomic_write::write_atomic;
+use rand::Rng;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"MLHNSW01";
+
+/// Links per node on upper levels (twice as many on level 0)
+const DEFAULT_M: usize = 16;
+
+/// Candidate list size while inserting
+const EF_CONSTRUCTION: usize = 100;
+
+/// Highest level a node can be assigned
+const MAX_LEVEL: usize = 16;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Candidate {
+    distance: f32,
+    node: u32,
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .partial_cmp(&other.distance)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| self.node.cmp(&other.node))
+    }
+}
+
+// ============================================================================
+// Index
+// ============================================================================
+
+pub struct HnswIndex {
+    dim: usize,
+    m: usize,
+    /// Generation of the vector database this index reflects
+    pub generation: u64,
+    ids: Vec<String>,
+    id_to_node: HashMap<String, u32>,
+    deleted: Vec<bool>,
+    live: usize,
+    /// Flat, unit-normalized vectors: node n occupies [n * dim, (n + 1) * dim)
+    vectors: Vec<f32>,
+    /// links[node][level] = neighbours of node on that level
+    links: Vec<Vec<Vec<u32>>>,
+    entry_point: Option<u32>,
+    max_level: usize,
+}
+
+impl HnswIndex {
+    pub fn new(dim: usize) -> Self {
+        Self {
+            dim,
+            m: DEFAULT_M,
+            generation: 0,
+            ids: Vec::new(),
+            id_to_node: HashMap::new(),
+            deleted: Vec::new(),
+            live: 0,
+            vectors: Vec::new(),
+            links: Vec::new(),
+            entry_point: None,
+            max_level: 0,
+        }
+    }
+
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Number of chunks in the index, not counting removed ones
+    pub fn len(&self) -> usize {
+        self.live
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.live == 0
+    }
+
+    /// Whether enough nodes are tombstoned that the graph should be rebuilt
+    pub fn needs_compaction(&self) -> bool {
+        self.ids.len() > 1000 && self.ids.len() - self.live > self.live
+    }
+
+    /// Add a chunk's embedding, replacing any existing entry for the id
+    pub fn insert(&mut self, id: &str, embedding: &[f32]) -> Result<(), String> {
+        if embedding.len() != self.dim {
+            return Err(format!(
+                "Embedding has {} dimensions, index expects {}",
+                embedding.len(),
+                self.dim
+            ));
+        }
+        self.remove(id);
+
+        let node = self.ids.len() as u32;
+        let level = random_level(self.m);
+        self.ids.push(id.to_string());
+        self.id_to_node.insert(id.to_string(), node);
+        self.deleted.push(false);
+        self.live += 1;
+        self.vectors.extend(normalize(embedding));
+        self.links.push(vec![Vec::new(); level + 1]);
+
+        let Some(mut entry) = self.entry_point else {
+            self.entry_point = Some(node);
+            self.max_level = level;
+            return Ok(());
+        };
+
+        let query = self.vector(node).to_vec();
+
+        // Greedy descent through the levels above the new node's top level
+        for l in (level + 1..=self.max_level).rev() {
+            entry = self.greedy_closest(&query, entry, l);
+        }
+
+        let mut entries = vec![entry];
+        for l in (0..=level.min(self.max_level)).rev() {
+            let candidates = self.search_level(&query, &entries, EF_CONSTRUCTION, l);
+            let max_links = self.max_links(l);
+            let neighbours: Vec<u32> = candidates
+                .iter()
+                .filter(|c| c.node != node)
+                .take(self.m)
+                .map(|c| c.node)
+                .collect();
+
+            self.links[node as usize][l] = neighbours.clone();
+            for &neighbour in &neighbours {
+                self.links[neighbour as usize][l].push(node);
+                if self.links[neighbour as usize][l].len() > max_links {
+                    self.prune(neighbour, l, max_links);
+                }
+            }
+
+            entries = candidates.iter().map(|c| c.node).collect();
+        }
+
+        if level > self.max_level {
+            self.max_level = level;
+            self.entry_point = Some(node);
+        }
+        Ok(())
+    }
+
+    /// Remove a chunk. Returns false if it wasn't in the index.
+    pub fn remove(&mut self, id: &str) -> bool {
+        let Some(node) = self.id_to_node.remove(id) else {
+            return false;
+        };
+        self.deleted[node as usize] = true;
+        self.live -= 1;
+        true
+    }
+
+    /// Change the id of a chunk, e.g. after its file was renamed. Returns
+    /// false if the old id wasn't in the index.
+    pub fn rename(&mut self, old_id: &str, new_id: &str) -> bool {
+        let Some(node) = self.id_to_node.remove(old_id) else {
+            return false;
+        };
+        self.remove(new_id);
+        self.ids[node as usize] = new_id.to_string();
+        self.id_to_node.insert(new_id.to_string(), node);
+        true
+    }
+
+    /// Find the chunks most similar to a query, best first, as (id, cosine
+    /// similarity) pairs. `ef` is the candidate list size; larger is more
+    /// accurate and slower.
+    pub fn search(&self, query: &[f32], k: usize, ef: usize) -> Vec<(String, f32)> {
+        let Some(mut entry) = self.entry_point else {
+            return Vec::new();
+        };
+        if query.len() != self.dim || k == 0 {
+            return Vec::new();
+        }
+
+        let query = normalize(query);
+        for l in (1..=self.max_level).rev() {
+            entry = self.greedy_closest(&query, entry, l);
+        }
+
+        self.search_level(&query, &[entry], ef.max(k), 0)
+            .into_iter()
+            .filter(|c| !self.deleted[c.node as usize])
+            .take(k)
+            .map(|c| (self.ids[c.node as usize].clone(), 1.0 - c.distance))
+            .collect()
+    }
+
+    /// Rebuild the graph from the chunks that are still live
+    pub fn compact(&mut self) {
+        let mut compacted = HnswIndex::new(self.dim);
+        compacted.generation = self.generation;
+        for (node, id) in self.ids.iter().enumerate() {
+            if !self.deleted[node] {
+                // Vectors are already normalized and of the right length
+                let _ = compacted.insert(id, self.vector(node as u32));
+            }
+        }
+        *self = compacted;
+    }
+
+    // ========================================================================
+    // Persistence
+    // ========================================================================
+
+    /// Write the index to disk, replacing any existing file atomically
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes)
+            .and_then(|_| write_atomic(path, bytes))
+            .map_err(|e| format!("Failed to write vector index: {}", e))
+    }
+
+    fn write_to(&self, out: &mut impl Write) -> std::io::Result<()> {
+        out.write_all(MAGIC)?;
+        out.write_all(&self.generation.to_le_bytes())?;
+        for value in [
+            self.dim as u32,
+            self.m as u32,
+            self.ids.len() as u32,
+            self.entry_point.unwrap_or(u32::MAX),
+            self.max_level as u32,
+        ] {
+            out.write_all(&value.to_le_bytes())?;
+        }
+
+        for (node, id) in self.ids.iter().enumerate() {
+            out.write_all(&(id.len() as u32).to_le_bytes())?;
+            out.write_all(id.as_bytes())?;
+            out.write_all(&[self.deleted[node] as u8, (self.links[node].len() - 1) as u8])?;
+            for value in self.vector(node as u32) {
+                out.write_all(&value.to_le_bytes())?;
+            }
+            for links in &self.links[node] {
+                out.write_all(&(links.len() as u32).to_le_bytes())?;
+                for link in links {
+                    out.write_all(&link.to_le_bytes())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Read an index written by `save`
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| format!("Failed to read vector index: {}", e))?;
+        Self::from_bytes(&bytes).map_err(|e| format!("Failed to parse vector index: {}", e))
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader { bytes, pos: 0 };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err("not a vector index file".to_string());
+        }
+
+        let generation = reader.u64()?;
+        let dim = reader.u32()? as usize;
+        let m = reader.u32()? as usize;
+        let count = reader.u32()? as usize;
+        let entry_point = reader.u32()?;
+        let max_level = reader.u32()? as usize;
+        if dim == 0 || m == 0 || max_level > MAX_LEVEL {
+            return Err("invalid header".to_string());
+        }
+
+        let mut index = HnswIndex::new(dim);
+        index.m = m;
+        index.generation = generation;
+        index.max_level = max_level;
+        index.entry_point = (entry_point != u32::MAX).then_some(entry_point);
+        if index.entry_point.is_some_and(|e| e as usize >= count) {
+            return Err("entry point out of range".to_string());
+        }
+
+        index
+            .vectors
+            .reserve(count.saturating_mul(dim).min(bytes.len() / 4));
+        for node in 0..count {
+            let id_len = reader.u32()? as usize;
+            let id = String::from_utf8(reader.take(id_len)?.to_vec())
+                .map_err(|_| "invalid chunk id".to_string())?;
+            let flags = reader.take(2)?;
+            let (deleted, level) = (flags[0] != 0, flags[1] as usize);
+            if level > MAX_LEVEL {
+                return Err("invalid node level".to_string());
+            }
+
+            index.vectors.extend(
+                reader
+                    .take(dim * 4)?
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+            );
+
+            let mut levels = Vec::with_capacity(level + 1);
+            for _ in 0..=level {
+                let link_count = reader.u32()? as usize;
+                let links: Vec<u32> = reader
+                    .take(link_count * 4)?
+                    .chunks_exact(4)
+                    .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                    .collect();
+                if links.iter().any(|&l| l as usize >= count) {
+                    return Err("link out of range".to_string());
+                }
+                levels.push(links);
+            }
+
+            if !deleted {
+                index.id_to_node.insert(id.clone(), node as u32);
+                index.live += 1;
+            }
+            index.ids.push(id);
+            index.deleted.push(deleted);
+            index.links.push(levels);
+        }
+
+        Ok(index)
+    }
+
+    // ========================================================================
+    // Graph Search
+    // ========================================================================
+
+    fn vector(&self, node: u32) -> &[f32] {
+        let start = node as usize * self.dim;
+        &self.vectors[start..start + self.dim]
+    }
+
+    fn distance(&self, query: &[f32], node: u32) -> f32 {
+        let dot: f32 = query
+            .iter()
+            .zip(self.vector(node))
+            .map(|(a, b)| a * b)
+            .sum();
+        1.0 - dot
+    }
+
+    fn max_links(&self, level: usize) -> usize {
+        if level == 0 {
+            self.m * 2
+        } else {
+            self.m
+        }
+    }
+
+    fn neighbours(&self, node: u32, level: usize) -> &[u32] {
+        self.links[node as usize]
+            .get(level)
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Walk to the node closest to the query on one level
+    fn greedy_closest(&self, query: &[f32], entry: u32, level: usize) -> u32 {
+        let mut current = entry;
+        let mut current_distance = self.distance(query, current);
+        loop {
+            let mut improved = false;
+            for &neighbour in self.neighbours(current, level) {
+                let distance = self.distance(query, neighbour);
+                if distance < current_distance {
+                    current = neighbour;
+                    current_distance = distance;
+                    improved = true;
+                }
+            }
+            if !improved {
+                return current;
+            }
+        }
+    }
+
+    /// Best-first search on one level, returning up to `ef` nodes sorted by
+    /// distance
+    fn search_level(
+        &self,
+        query: &[f32],
+        entries: &[u32],
+        ef: usize,
+        level: usize,
+    ) -> Vec<Candidate> {
+        let mut visited: HashSet<u32> = entries.iter().copied().collect();
+        // Min-heap of nodes to expand, max-heap of the best nodes found
+        let mut to_visit: BinaryHeap<std::cmp::Reverse<Candidate>> = BinaryHeap::new();
+        let mut found: BinaryHeap<Candidate> = BinaryHeap::new();
+
+        for &node in entries {
+            let candidate = Candidate {
+                distance: self.distance(query, node),
+                node,
+            };
+            to_visit.push(std::cmp::Reverse(candidate));
+            found.push(candidate);
+        }
+        while found.len() > ef {
+            found.pop();
+        }
+
+        while let Some(std::cmp::Reverse(current)) = to_visit.pop() {
+            if found.len() >= ef && found.peek().is_some_and(|w| current.distance > w.distance) {
+                break;
+            }
+
+            for &neighbour in self.neighbours(current.node, level) {
+                if !visited.insert(neighbour) {
+                    continue;
+                }
+                let distance = self.distance(query, neighbour);
+                if found.len() < ef || found.peek().is_some_and(|w| distance < w.distance) {
+                    let candidate = Candidate {
+                        distance,
+                        node: neighbour,
+                    };
+                    to_visit.push(std::cmp::Reverse(candidate));
+                    found.push(candidate);
+                    if found.len() > ef {
+                        found.pop();
+                    }
+                }
+            }
+        }
+
+        found.into_sorted_vec()
+    }
+
+    /// Keep only a node's closest links on a level
+    fn prune(&mut self, node: u32, level: usize, max_links: usize) {
+        let query = self.vector(node).to_vec();
+        let mut candidates: Vec<Candidate> = self.links[node as usize][level]
+            .iter()
+            .map(|&n| Candidate {
+                distance: self.distance(&query, n),
+                node: n,
+            })
+            .collect();
+        candidates.sort();
+        candidates.truncate(max_links);
+        self.links[node as usize][level] = candidates.into_iter().map(|c| c.node).collect();
+    }
+}
+
+fn random_level(m: usize) -> usize {
+    let multiplier = 1.0 / (m as f64).ln();
+    let uniform: f64 = rand::thread_rng().gen_range(f64::EPSILON..1.0);
+    ((-uniform.ln() * multiplier) as usize).min(MAX_LEVEL)
+}
+
+fn normalize(vector: &[f32]) -> Vec<f32> {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        return vector.to_vec();
+    }
+    vector.iter().map(|x| x / norm).collect()
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| "unexpected end of file".to_string())?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        let b = self.take(8)?;
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(b);
+        Ok(u64::from_le_bytes(buf))
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn random_vectors(count: usize, dim: usize) -> Vec<Vec<f32>> {
+        let mut rng = rand::thread_rng();
+        (0..count)
+            .map(|_| (0..dim).map(|_| rng.gen_range(-1.0..1.0)).collect())
+            .collect()
+    }
+
+    fn exact_top(vectors: &[Vec<f32>], query: &[f32], k: usize) -> Vec<String> {
+        let query = normalize(query);
+        let mut scored: Vec<(usize, f32)> = vectors
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (i, normalize(v).iter().zip(&query).map(|(a, b)| a * b).sum()))
+            .collect();
+        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        scored.iter().take(k).map(|(i, _)| i.to_string()).collect()
+    }
+
+    #[test]
+    fn test_search_finds_nearest_neighbours() {
+        let vectors = random_vectors(1000, 16);
+        let mut index = HnswIndex::new(16);
+        for (i, v) in vectors.iter().enumerate() {
+            index.insert(&i.to_string(), v).unwrap();
+        }
+        assert_eq!(index.len(), 1000);
+
+        // Recall of the top 10 should be near perfect on a small index
+        let mut hits = 0;
+        for query in random_vectors(20, 16) {
+            let expected = exact_top(&vectors, &query, 10);
+            let found: Vec<String> = index
+                .search(&query, 10, 100)
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect();
+            hits += found.iter().filter(|id| expected.contains(id)).count();
+        }
+        assert!(hits >= 180, "recall too low: {}/200", hits);
+
+        // An indexed vector is its own best match
+        let results = index.search(&vectors[42], 1, 50);
+        assert_eq!(results[0].0, "42");
+        assert!((results[0].1 - 1.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_remove_and_replace() {
+        let mut index = HnswIndex::new(3);
+        index.insert("a", &[1.0, 0.0, 0.0]).unwrap();
+        index.insert("b", &[0.0, 1.0, 0.0]).unwrap();
+        index.insert("c", &[0.9, 0.1, 0.0]).unwrap();
+
+        assert!(index.remove("a"));
+        assert!(!index.remove("a"));
+        let results = index.search(&[1.0, 0.0, 0.0], 1, 10);
+        assert_eq!(results[0].0, "c");
+
+        assert!(index.rename("c", "c2"));
+        assert!(!index.rename("c", "c3"));
+        assert_eq!(index.search(&[1.0, 0.0, 0.0], 1, 10)[0].0, "c2");
+
+        // Re-inserting an id replaces its vector
+        index.insert("b", &[1.0, 0.0, 0.0]).unwrap();
+        assert_eq!(index.len(), 2);
+        assert_eq!(index.search(&[1.0, 0.0, 0.0], 1, 10)[0].0, "b");
+
+        index.compact();
+        assert_eq!(index.len(), 2);
+        assert_eq!(index.search(&[0.0, 1.0, 0.0], 2, 10).len(), 2);
+
+        assert!(index.insert("d", &[1.0, 0.0]).is_err());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("vectors.hnsw");
+
+        let vectors = random_vectors(200, 8);
+        let mut index = HnswIndex::new(8);
+        index.generation = 7;
+        for (i, v) in vectors.iter().enumerate() {
+            index.insert(&i.to_string(), v).unwrap();
+        }
+        index.remove("5");
+        index.save(&path).unwrap();
+
+        let loaded = HnswIndex::load(&path).unwrap();
+        assert_eq!(loaded.generation, 7);
+        assert_eq!(loaded.len(), 199);
+        assert_eq!(loaded.dim(), 8);
+        assert_eq!(
+            loaded.search(&vectors[9], 5, 50),
+            index.search(&vectors[9], 5, 50)
+        );
+        assert!(loaded
+            .search(&vectors[5], 5, 50)
+            .iter()
+            .all(|(id, _)| id != "5"));
+
+        // Truncated or foreign files are rejected
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+        assert!(HnswIndex::load(&path).is_err());
+        fs::write(&path, b"not an index").unwrap();
+        assert!(HnswIndex::load(&path).is_err());
+    }
+}
@@ -1,4 +1,9 @@
 // Image manager - Content-addressable image storage with deduplication
+//
+// Text recognized in an image is stored as text/{hash}.txt under the images
+// directory, where the RAG index picks it up. Thumbnails are cached as
+// thumbs/{hash}-{size}.{ext}; thumbnails of other image files in the
+// workspace are keyed by the file's path, size and modification time.
 
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use sha2::{Digest, Sha256};
@@ -6,6 +11,9 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use super::error::{MidlightError, Result};
+use super::image_ocr;
+use super::image_optimizer::{self, EncodedImage, ThumbnailSize};
+use super::import_security::verify_content;
 use crate::traits::{FileSystem, TokioFileSystem};
 
 /// Manages image storage for a workspace
@@ -72,9 +80,14 @@ impl<F: FileSystem> ImageManager<F> {
             .decode(base64_data)
             .map_err(|e| MidlightError::InvalidInput(format!("Invalid base64: {}", e)))?;
 
+        self.store_image_bytes(&image_data, mime_type).await
+    }
+
+    /// Store already-decoded image bytes, returns the image reference ID
+    pub async fn store_image_bytes(&self, image_data: &[u8], mime_type: &str) -> Result<String> {
         // Calculate SHA-256 hash for deduplication
         let mut hasher = Sha256::new();
-        hasher.update(&image_data);
+        hasher.update(image_data);
         let hash = format!("{:x}", hasher.finalize());
         let short_hash = &hash[..16];
 
@@ -88,13 +101,19 @@ impl<F: FileSystem> ImageManager<F> {
             _ => "bin",
         };
 
+        // The declared type isn't trusted: a program or web page is refused
+        // whatever it claims to be, and data that doesn't look like any
+        // image can't pass as one
+        verify_content(&format!("image.{}", extension), image_data)
+            .map_err(MidlightError::InvalidInput)?;
+
         // Create filename with hash
         let filename = format!("{}.{}", short_hash, extension);
         let file_path = self.images_dir.join(&filename);
 
         // Only write if doesn't exist (deduplication)
         if !self.fs.exists(&file_path).await {
-            self.fs.write_bytes(&file_path, &image_data).await?;
+            self.fs.write_bytes(&file_path, image_data).await?;
             tracing::debug!(
                 "Stored new image: {} ({} bytes)",
                 filename,
@@ -120,24 +139,160 @@ impl<F: FileSystem> ImageManager<F> {
         let image_data = self.fs.read(&matching_file).await?;
 
         // Determine mime type from extension
-        let mime_type = matching_file
-            .extension()
-            .and_then(|e| e.to_str())
-            .map(|ext| match ext {
-                "png" => "image/png",
-                "jpg" | "jpeg" => "image/jpeg",
-                "gif" => "image/gif",
-                "webp" => "image/webp",
-                "svg" => "image/svg+xml",
-                _ => "application/octet-stream",
-            })
-            .unwrap_or("application/octet-stream");
+        let mime_type = mime_for_extension(&extension_of(&matching_file));
 
         // Encode as data URL
         let base64_data = BASE64.encode(&image_data);
         Ok(format!("data:{};base64,{}", mime_type, base64_data))
     }
 
+    /// Generate and cache thumbnails of a stored image at every size
+    pub async fn generate_thumbnails(&self, ref_id: &str) -> Result<()> {
+        let hash = ref_id.strip_prefix("midlight://img-").unwrap_or(ref_id);
+        let image_path = self.find_image_by_hash(hash).await?;
+        self.cache_thumbnails(&image_path, hash, &ThumbnailSize::ALL)
+            .await
+            .map(|_| ())
+    }
+
+    /// Get a thumbnail of a stored image as a data URL, generating it if
+    /// it isn't cached yet. SVGs are returned as they are.
+    pub async fn get_thumbnail_data_url(
+        &self,
+        ref_id: &str,
+        size: ThumbnailSize,
+    ) -> Result<String> {
+        let hash = ref_id.strip_prefix("midlight://img-").unwrap_or(ref_id);
+        let image_path = self.find_image_by_hash(hash).await?;
+        self.thumbnail_data_url(&image_path, hash, size).await
+    }
+
+    /// Get a thumbnail of any image file in the workspace as a data URL
+    pub async fn get_file_thumbnail_data_url(
+        &self,
+        image_path: &Path,
+        size: ThumbnailSize,
+    ) -> Result<String> {
+        let metadata = self.fs.metadata(image_path).await?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+
+        let mut hasher = Sha256::new();
+        hasher.update(image_path.to_string_lossy().as_bytes());
+        hasher.update(metadata.len().to_le_bytes());
+        hasher.update(modified.to_le_bytes());
+        let key = format!("file-{}", &format!("{:x}", hasher.finalize())[..16]);
+
+        self.thumbnail_data_url(image_path, &key, size).await
+    }
+
+    async fn thumbnail_data_url(
+        &self,
+        image_path: &Path,
+        key: &str,
+        size: ThumbnailSize,
+    ) -> Result<String> {
+        if extension_of(image_path) == "svg" {
+            let data = self.fs.read(image_path).await?;
+            return Ok(format!("data:image/svg+xml;base64,{}", BASE64.encode(data)));
+        }
+
+        let thumbnail_path = match self.find_thumbnail(key, size).await {
+            Some(path) => path,
+            None => self
+                .cache_thumbnails(image_path, key, &[size])
+                .await?
+                .into_iter()
+                .next()
+                .ok_or_else(|| MidlightError::Internal("No thumbnail generated".to_string()))?,
+        };
+
+        let data = self.fs.read(&thumbnail_path).await?;
+        Ok(format!(
+            "data:{};base64,{}",
+            mime_for_extension(&extension_of(&thumbnail_path)),
+            BASE64.encode(data)
+        ))
+    }
+
+    /// Decode an image once, write its thumbnails and return their paths
+    async fn cache_thumbnails(
+        &self,
+        image_path: &Path,
+        key: &str,
+        sizes: &[ThumbnailSize],
+    ) -> Result<Vec<PathBuf>> {
+        let data = self.fs.read(image_path).await?;
+        let sizes = sizes.to_vec();
+        let thumbnails: Vec<(ThumbnailSize, EncodedImage)> =
+            tokio::task::spawn_blocking(move || image_optimizer::thumbnails(&data, &sizes))
+                .await
+                .map_err(|e| MidlightError::Internal(e.to_string()))?
+                .map_err(MidlightError::InvalidInput)?;
+
+        let thumbs_dir = self.images_dir.join("thumbs");
+        self.fs.create_dir_all(&thumbs_dir).await?;
+
+        let mut paths = Vec::new();
+        for (size, thumbnail) in thumbnails {
+            let extension = if thumbnail.mime_type == "image/jpeg" {
+                "jpg"
+            } else {
+                "png"
+            };
+            let path = thumbs_dir.join(format!("{}-{}.{}", key, size.name(), extension));
+            self.fs.write_bytes(&path, &thumbnail.bytes).await?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    async fn find_thumbnail(&self, key: &str, size: ThumbnailSize) -> Option<PathBuf> {
+        let thumbs_dir = self.images_dir.join("thumbs");
+        let stem = format!("{}-{}", key, size.name());
+        self.fs
+            .read_dir(&thumbs_dir)
+            .await
+            .ok()?
+            .into_iter()
+            .find(|path| path.file_stem().and_then(|s| s.to_str()) == Some(stem.as_str()))
+    }
+
+    /// Path of the image file for a reference
+    pub async fn image_path(&self, ref_id: &str) -> Result<PathBuf> {
+        let hash = ref_id.strip_prefix("midlight://img-").unwrap_or(ref_id);
+        self.find_image_by_hash(hash).await
+    }
+
+    /// Path where the text recognized in an image is stored
+    pub fn text_path(&self, ref_id: &str) -> PathBuf {
+        let hash = ref_id.strip_prefix("midlight://img-").unwrap_or(ref_id);
+        self.images_dir.join("text").join(format!("{}.txt", hash))
+    }
+
+    /// Text recognized in an image, if any has been extracted
+    pub async fn get_image_text(&self, ref_id: &str) -> Result<Option<String>> {
+        let text_path = self.text_path(ref_id);
+        if !self.fs.exists(&text_path).await {
+            return Ok(None);
+        }
+        Ok(Some(self.fs.read_to_string(&text_path).await?))
+    }
+
+    /// Store the text recognized in an image, returning the text file's path
+    pub async fn save_image_text(&self, ref_id: &str, text: &str) -> Result<PathBuf> {
+        let text_path = self.text_path(ref_id);
+        if let Some(parent) = text_path.parent() {
+            self.fs.create_dir_all(parent).await?;
+        }
+        self.fs.write(&text_path, text).await?;
+        Ok(text_path)
+    }
+
     /// Check if an image exists
     pub async fn exists(&self, ref_id: &str) -> bool {
         let hash = ref_id.strip_prefix("midlight://img-").unwrap_or(ref_id);
@@ -149,10 +304,34 @@ impl<F: FileSystem> ImageManager<F> {
         let hash = ref_id.strip_prefix("midlight://img-").unwrap_or(ref_id);
         let file_path = self.find_image_by_hash(hash).await?;
         self.fs.remove_file(&file_path).await?;
+
+        let text_path = self.text_path(hash);
+        if self.fs.exists(&text_path).await {
+            self.fs.remove_file(&text_path).await?;
+        }
+        for size in ThumbnailSize::ALL {
+            if let Some(thumbnail) = self.find_thumbnail(hash, size).await {
+                self.fs.remove_file(&thumbnail).await?;
+            }
+        }
         tracing::debug!("Deleted image: {}", file_path.display());
         Ok(())
     }
 
+    /// Recognize the text in an image and store it. Returns the path of the
+    /// stored text, or `None` if no text was found.
+    pub async fn extract_text(&self, ref_id: &str) -> Result<Option<PathBuf>> {
+        let image_path = self.image_path(ref_id).await?;
+        let text = image_ocr::recognize(&image_path)
+            .await
+            .map_err(MidlightError::Internal)?;
+
+        match text {
+            Some(text) => Ok(Some(self.save_image_text(ref_id, &text).await?)),
+            None => Ok(None),
+        }
+    }
+
     /// List all images
     pub async fn list_images(&self) -> Result<Vec<String>> {
         let mut images = Vec::new();
@@ -196,6 +375,24 @@ impl<F: FileSystem> ImageManager<F> {
     }
 }
 
+fn extension_of(path: &Path) -> String {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default()
+}
+
+pub(crate) fn mime_for_extension(extension: &str) -> &'static str {
+    match extension {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        _ => "application/octet-stream",
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -337,6 +534,68 @@ mod tests {
         assert!(!manager.exists(&ref_id).await);
     }
 
+    #[tokio::test]
+    async fn test_image_text() {
+        let fs = Arc::new(MockFileSystem::new().with_dir("/workspace/.midlight/images"));
+        let manager = ImageManager::with_fs(Path::new("/workspace"), fs.clone());
+
+        let ref_id = manager
+            .store_image(&create_png_data_url(), None)
+            .await
+            .unwrap();
+        assert!(manager.get_image_text(&ref_id).await.unwrap().is_none());
+
+        let text_path = manager
+            .save_image_text(&ref_id, "Revenue: $4.2M")
+            .await
+            .unwrap();
+        let hash = ref_id.strip_prefix("midlight://img-").unwrap();
+        assert_eq!(
+            text_path,
+            PathBuf::from(format!("/workspace/.midlight/images/text/{}.txt", hash))
+        );
+        assert_eq!(
+            manager.get_image_text(&ref_id).await.unwrap().as_deref(),
+            Some("Revenue: $4.2M")
+        );
+
+        // The text directory isn't listed as an image, and goes with its image
+        assert_eq!(manager.list_images().await.unwrap(), vec![ref_id.clone()]);
+        manager.delete(&ref_id).await.unwrap();
+        assert!(!fs.has_file(&text_path));
+    }
+
+    #[tokio::test]
+    async fn test_thumbnails_are_cached() {
+        let fs = Arc::new(MockFileSystem::new().with_dir("/workspace/.midlight/images"));
+        let manager = ImageManager::with_fs(Path::new("/workspace"), fs.clone());
+
+        let ref_id = manager
+            .store_image(&create_png_data_url(), None)
+            .await
+            .unwrap();
+        let hash = ref_id.strip_prefix("midlight://img-").unwrap();
+        let small = format!("/workspace/.midlight/images/thumbs/{}-small.png", hash);
+
+        let data_url = manager
+            .get_thumbnail_data_url(&ref_id, ThumbnailSize::Small)
+            .await
+            .unwrap();
+        assert!(data_url.starts_with("data:image/png;base64,"));
+        assert!(fs.has_file(&small));
+
+        manager.generate_thumbnails(&ref_id).await.unwrap();
+        assert!(fs.has_file(format!(
+            "/workspace/.midlight/images/thumbs/{}-large.png",
+            hash
+        )));
+
+        // Thumbnails aren't listed as images and go with their image
+        assert_eq!(manager.list_images().await.unwrap(), vec![ref_id.clone()]);
+        manager.delete(&ref_id).await.unwrap();
+        assert!(!fs.has_file(&small));
+    }
+
     #[tokio::test]
     async fn test_list_images() {
         let fs = Arc::new(MockFileSystem::new().with_dir("/workspace/.midlight/images"));
@@ -356,6 +615,24 @@ mod tests {
         assert_eq!(images[0], ref_id);
     }
 
+    #[tokio::test]
+    async fn test_store_image_checks_content() {
+        let fs = Arc::new(MockFileSystem::new().with_dir("/workspace/.midlight/images"));
+        let manager = ImageManager::with_fs(Path::new("/workspace"), fs);
+
+        let html = BASE64.encode("<!DOCTYPE html><script>alert(1)</script>");
+        let result = manager
+            .store_image(&format!("data:image/png;base64,{}", html), None)
+            .await;
+        assert!(matches!(result, Err(MidlightError::InvalidInput(_))));
+
+        let exe = BASE64.encode(b"MZ\x90\x00");
+        let result = manager
+            .store_image(&format!("data:image/x-unknown;base64,{}", exe), None)
+            .await;
+        assert!(result.is_err());
+    }
+
     #[tokio::test]
     async fn test_mime_type_detection() {
         let fs = Arc::new(MockFileSystem::new().with_dir("/workspace/.midlight/images"));
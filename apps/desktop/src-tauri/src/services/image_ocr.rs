@@ -0,0 +1,122 @@
+// Image OCR - Extracts the text shown in images so screenshots are searchable
+//
+// Text recognition runs the Tesseract CLI, taken from MIDLIGHT_TESSERACT and
+// falling back to `tesseract` on PATH. Tesseract is optional: when it isn't
+// installed, recognition reports no text and images are stored as before.
+//
+// The recognized text is kept next to the image by the image manager, as a
+// plain text file the RAG index picks up like any other note.
+
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+use tracing::debug;
+
+/// Environment variable that overrides the Tesseract executable
+pub const TESSERACT_ENV: &str = "MIDLIGHT_TESSERACT";
+
+/// Recognition taking longer than this is abandoned
+const OCR_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Most characters of recognized text kept per image
+pub const MAX_TEXT_CHARS: usize = 50_000;
+
+/// Image formats Tesseract can read
+const SUPPORTED_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "bmp", "tif", "tiff"];
+
+/// Whether an image file is in a format OCR can read
+pub fn is_supported(image_path: &Path) -> bool {
+    image_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| SUPPORTED_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Recognize the text in an image. Returns `None` when the image has no
+/// readable text or Tesseract isn't installed.
+pub async fn recognize(image_path: &Path) -> Result<Option<String>, String> {
+    if !is_supported(image_path) {
+        return Ok(None);
+    }
+
+    let program = std::env::var(TESSERACT_ENV).unwrap_or_else(|_| "tesseract".to_string());
+    let mut command = Command::new(&program);
+    command
+        .arg(image_path)
+        .arg("stdout")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            debug!("OCR skipped, {} is not installed", program);
+            return Ok(None);
+        }
+        Err(e) => return Err(format!("Failed to start OCR: {}", e)),
+    };
+
+    let output = tokio::time::timeout(OCR_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| "OCR timed out".to_string())?
+        .map_err(|e| format!("Failed to run OCR: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "OCR failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let text = clean_text(&String::from_utf8_lossy(&output.stdout));
+    Ok(if text.is_empty() { None } else { Some(text) })
+}
+
+/// Tidy raw OCR output: trims lines, drops lines without any letters or
+/// digits (usually noise from icons and borders), collapses runs of blank
+/// lines and caps the length
+pub fn clean_text(raw: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    let mut blank = false;
+
+    for line in raw.lines().map(str::trim) {
+        if line.chars().any(char::is_alphanumeric) {
+            if blank && !lines.is_empty() {
+                lines.push("");
+            }
+            lines.push(line);
+            blank = false;
+        } else {
+            blank = true;
+        }
+    }
+
+    lines.join("\n").chars().take(MAX_TEXT_CHARS).collect()
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_text() {
+        let raw = "  Quarterly results  \n|  —  |\n\n\n\nRevenue: $4.2M\n\u{c}";
+        assert_eq!(clean_text(raw), "Quarterly results\n\nRevenue: $4.2M");
+        assert_eq!(clean_text(" \n ~~ \n"), "");
+    }
+
+    #[test]
+    fn test_supported_formats() {
+        assert!(is_supported(Path::new("/ws/.midlight/images/abc.png")));
+        assert!(is_supported(Path::new("shot.JPG")));
+        assert!(!is_supported(Path::new("diagram.svg")));
+        assert!(!is_supported(Path::new("noext")));
+    }
+}
@@ -0,0 +1,288 @@
+// Image Optimizer - Shrinks images before they are stored in a workspace
+//
+// Pasted screenshots arrive as raw RGBA bitmaps that can be many megabytes.
+// They are scaled down to fit the configured maximum dimensions and encoded
+// as PNG (lossless) or JPEG (at the configured quality). In "auto" mode,
+// images with transparency stay PNG and opaque ones use whichever encoding
+// is smaller. Images are always re-encoded from pixels, so no EXIF or other
+// metadata from the source survives.
+//
+// Thumbnails go through the same path, decoded from the stored original.
+
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::{CompressionType, FilterType as PngFilter, PngEncoder};
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageEncoder, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_QUALITY: u8 = 85;
+pub const DEFAULT_MAX_DIMENSION: u32 = 2560;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Auto,
+    Png,
+    Jpeg,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OptimizeOptions {
+    #[serde(default)]
+    pub format: OutputFormat,
+    /// JPEG quality, 1-100
+    #[serde(default)]
+    pub quality: Option<u8>,
+    #[serde(default)]
+    pub max_width: Option<u32>,
+    #[serde(default)]
+    pub max_height: Option<u32>,
+}
+
+/// Thumbnail sizes, by their longest side
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbnailSize {
+    Small,
+    #[default]
+    Medium,
+    Large,
+}
+
+impl ThumbnailSize {
+    pub const ALL: [ThumbnailSize; 3] = [Self::Small, Self::Medium, Self::Large];
+
+    pub fn pixels(self) -> u32 {
+        match self {
+            Self::Small => 96,
+            Self::Medium => 256,
+            Self::Large => 640,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Small => "small",
+            Self::Medium => "medium",
+            Self::Large => "large",
+        }
+    }
+}
+
+/// An encoded image ready to be stored
+#[derive(Debug, Clone)]
+pub struct EncodedImage {
+    pub bytes: Vec<u8>,
+    pub mime_type: &'static str,
+    pub width: u32,
+    pub height: u32,
+}
+
+// ============================================================================
+// Optimization
+// ============================================================================
+
+/// Scale down and encode an RGBA bitmap
+pub fn optimize_rgba(
+    rgba: Vec<u8>,
+    width: u32,
+    height: u32,
+    options: &OptimizeOptions,
+) -> Result<EncodedImage, String> {
+    let image = RgbaImage::from_raw(width, height, rgba)
+        .ok_or_else(|| "Image data doesn't match its dimensions".to_string())?;
+
+    let (max_width, max_height) = (
+        options.max_width.unwrap_or(DEFAULT_MAX_DIMENSION).max(1),
+        options.max_height.unwrap_or(DEFAULT_MAX_DIMENSION).max(1),
+    );
+    let (new_width, new_height) = fit_within(width, height, max_width, max_height);
+    let image = if (new_width, new_height) != (width, height) {
+        image::imageops::resize(&image, new_width, new_height, FilterType::Lanczos3)
+    } else {
+        image
+    };
+
+    let quality = options.quality.unwrap_or(DEFAULT_QUALITY).clamp(1, 100);
+    let (bytes, mime_type) = match options.format {
+        OutputFormat::Png => (encode_png(&image)?, "image/png"),
+        OutputFormat::Jpeg => (encode_jpeg(&image, quality)?, "image/jpeg"),
+        OutputFormat::Auto => {
+            let png = encode_png(&image)?;
+            if has_transparency(&image) {
+                (png, "image/png")
+            } else {
+                let jpeg = encode_jpeg(&image, quality)?;
+                if jpeg.len() < png.len() {
+                    (jpeg, "image/jpeg")
+                } else {
+                    (png, "image/png")
+                }
+            }
+        }
+    };
+
+    Ok(EncodedImage {
+        bytes,
+        mime_type,
+        width: new_width,
+        height: new_height,
+    })
+}
+
+/// Decode an image once and make a thumbnail at each size. Images already
+/// smaller than a size are re-encoded but not enlarged.
+pub fn thumbnails(
+    bytes: &[u8],
+    sizes: &[ThumbnailSize],
+) -> Result<Vec<(ThumbnailSize, EncodedImage)>, String> {
+    let image = image::load_from_memory(bytes)
+        .map_err(|e| format!("Failed to decode image: {}", e))?
+        .to_rgba8();
+    let (width, height) = image.dimensions();
+    let rgba = image.into_raw();
+
+    sizes
+        .iter()
+        .map(|&size| {
+            let options = OptimizeOptions {
+                format: OutputFormat::Auto,
+                quality: Some(80),
+                max_width: Some(size.pixels()),
+                max_height: Some(size.pixels()),
+            };
+            optimize_rgba(rgba.clone(), width, height, &options).map(|image| (size, image))
+        })
+        .collect()
+}
+
+/// Largest size with the same aspect ratio that fits within the bounds
+fn fit_within(width: u32, height: u32, max_width: u32, max_height: u32) -> (u32, u32) {
+    if width <= max_width && height <= max_height {
+        return (width, height);
+    }
+    let scale = f64::min(
+        max_width as f64 / width as f64,
+        max_height as f64 / height as f64,
+    );
+    (
+        ((width as f64 * scale).round() as u32).max(1),
+        ((height as f64 * scale).round() as u32).max(1),
+    )
+}
+
+fn has_transparency(image: &RgbaImage) -> bool {
+    image.pixels().any(|p| p.0[3] < u8::MAX)
+}
+
+fn encode_png(image: &RgbaImage) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    PngEncoder::new_with_quality(&mut bytes, CompressionType::Best, PngFilter::Adaptive)
+        .write_image(
+            image.as_raw(),
+            image.width(),
+            image.height(),
+            image::ColorType::Rgba8,
+        )
+        .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+    Ok(bytes)
+}
+
+fn encode_jpeg(image: &RgbaImage, quality: u8) -> Result<Vec<u8>, String> {
+    // JPEG has no alpha channel
+    let rgb = DynamicImage::ImageRgba8(image.clone()).to_rgb8();
+    let mut bytes = Vec::new();
+    JpegEncoder::new_with_quality(&mut bytes, quality)
+        .encode_image(&rgb)
+        .map_err(|e| format!("Failed to encode JPEG: {}", e))?;
+    Ok(bytes)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(width: u32, height: u32, alpha: u8) -> Vec<u8> {
+        (0..width * height)
+            .flat_map(|i| {
+                let (x, y) = (i % width, i / width);
+                [
+                    (x * 255 / width) as u8,
+                    (y * 255 / height) as u8,
+                    128,
+                    alpha,
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fit_within() {
+        assert_eq!(fit_within(800, 600, 2560, 2560), (800, 600));
+        assert_eq!(fit_within(4000, 2000, 2000, 2000), (2000, 1000));
+        assert_eq!(fit_within(1000, 3000, 2000, 1500), (500, 1500));
+        assert_eq!(fit_within(10000, 1, 100, 100), (100, 1));
+    }
+
+    #[test]
+    fn test_resizes_and_encodes() {
+        let options = OptimizeOptions {
+            format: OutputFormat::Png,
+            max_width: Some(32),
+            ..Default::default()
+        };
+        let encoded = optimize_rgba(gradient(64, 48, 255), 64, 48, &options).unwrap();
+        assert_eq!((encoded.width, encoded.height), (32, 24));
+        assert_eq!(encoded.mime_type, "image/png");
+        assert!(encoded.bytes.starts_with(b"\x89PNG"));
+
+        let options = OptimizeOptions {
+            format: OutputFormat::Jpeg,
+            quality: Some(60),
+            ..Default::default()
+        };
+        let encoded = optimize_rgba(gradient(64, 48, 255), 64, 48, &options).unwrap();
+        assert_eq!(encoded.mime_type, "image/jpeg");
+        assert!(encoded.bytes.starts_with(&[0xFF, 0xD8]));
+    }
+
+    #[test]
+    fn test_thumbnails() {
+        let original = optimize_rgba(
+            gradient(1200, 600, 255),
+            1200,
+            600,
+            &OptimizeOptions {
+                format: OutputFormat::Png,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let thumbs = thumbnails(&original.bytes, &ThumbnailSize::ALL).unwrap();
+        let dimensions: Vec<(u32, u32)> = thumbs.iter().map(|(_, t)| (t.width, t.height)).collect();
+        assert_eq!(dimensions, vec![(96, 48), (256, 128), (640, 320)]);
+        assert!(thumbs[2].1.bytes.len() < original.bytes.len());
+
+        assert!(thumbnails(b"not an image", &ThumbnailSize::ALL).is_err());
+    }
+
+    #[test]
+    fn test_auto_keeps_transparency() {
+        let options = OptimizeOptions::default();
+        let encoded = optimize_rgba(gradient(64, 64, 100), 64, 64, &options).unwrap();
+        assert_eq!(encoded.mime_type, "image/png");
+
+        assert!(optimize_rgba(vec![0; 10], 64, 64, &options).is_err());
+    }
+}
@@ -0,0 +1,393 @@
+// Image References - Which documents use which stored images
+//
+// Images are stored once per content hash, so the same screenshot pasted
+// into five notes is one file. Each saved document records the image hashes
+// it references; an image's reference count is the number of documents that
+// use it. When a save or delete drops an image's last reference the image
+// becomes an orphan, and orphans are removed once they have stayed
+// unreferenced for ORPHAN_GRACE_DAYS, long enough for checkpoints that still show
+// the image to be restored. Referencing an orphan again rescues it.
+//
+// References are stored at: .midlight/image-refs.json
+// Format:
+// {
+//   "version": 1,
+//   "documents": { "notes/a.midlight": ["3f2a9c01d4e5b6a7"] },
+//   "orphans": { "0b1c2d3e4f5a6b7c": "2026-01-01T00:00:00Z" }
+// }
+
+use super::workspace_paths::{document_key, find_workspace_root};
+use chrono::{DateTime, Duration, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const REFS_VERSION: u32 = 1;
+
+/// Days an unreferenced image is kept before it is deleted; matches the
+/// default checkpoint retention
+pub const ORPHAN_GRACE_DAYS: i64 = 7;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RefsFile {
+    version: u32,
+    #[serde(default)]
+    documents: BTreeMap<String, BTreeSet<String>>,
+    #[serde(default)]
+    orphans: BTreeMap<String, DateTime<Utc>>,
+}
+
+impl RefsFile {
+    fn count(&self, hash: &str) -> usize {
+        self.documents
+            .values()
+            .filter(|hashes| hashes.contains(hash))
+            .count()
+    }
+
+    /// Mark images that lost their last reference as orphans, and rescue
+    /// referenced ones
+    fn reconcile(&mut self, touched: BTreeSet<String>, now: DateTime<Utc>) {
+        for hash in touched {
+            if self.count(&hash) == 0 {
+                self.orphans.entry(hash).or_insert(now);
+            } else {
+                self.orphans.remove(&hash);
+            }
+        }
+    }
+}
+
+/// Image hashes referenced ("midlight://img-{hash}") anywhere in a document
+pub fn extract_refs(content: &str) -> BTreeSet<String> {
+    lazy_static::lazy_static! {
+        static ref IMAGE_REF: Regex = Regex::new(r"midlight://img-([0-9a-f]{8,64})").unwrap();
+    }
+
+    IMAGE_REF
+        .captures_iter(content)
+        .map(|c| c[1].to_string())
+        .collect()
+}
+
+/// Forget the references of a document or folder that was deleted or
+/// trashed outside the workspace manager
+pub fn path_removed(path: &Path) {
+    let Some((root, relative)) = workspace_relative(path) else {
+        return;
+    };
+    if let Err(e) = ImageRefStore::new(&root).remove_documents(&relative) {
+        tracing::warn!("Failed to update image references: {}", e);
+    }
+}
+
+/// Carry the references of a renamed or moved document or folder along
+pub fn path_moved(from: &Path, to: &Path) {
+    let (Some((root, from)), Some((to_root, to))) =
+        (workspace_relative(from), workspace_relative(to))
+    else {
+        return;
+    };
+    let store = ImageRefStore::new(&root);
+    let result = if root == to_root {
+        store.move_documents(&from, &to)
+    } else {
+        // Moved to another workspace, which has its own image store
+        store.remove_documents(&from)
+    };
+    if let Err(e) = result {
+        tracing::warn!("Failed to update image references: {}", e);
+    }
+}
+
+/// Record the references of documents that reappeared, e.g. restored from
+/// the trash, so their images are no longer treated as orphans
+pub fn path_added(path: &Path) {
+    let Some(root) = find_workspace_root(path) else {
+        return;
+    };
+    let store = ImageRefStore::new(&root);
+    let documents = walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "midlight"));
+
+    for document in documents {
+        let (Ok(content), Some((_, key))) = (
+            fs::read_to_string(document.path()),
+            workspace_relative(document.path()),
+        ) else {
+            continue;
+        };
+        if let Err(e) = store.update_document(&key, extract_refs(&content)) {
+            tracing::warn!("Failed to update image references: {}", e);
+        }
+    }
+}
+
+fn workspace_relative(path: &Path) -> Option<(PathBuf, String)> {
+    let root = find_workspace_root(path)?;
+    let relative = document_key(&path.strip_prefix(&root).ok()?.to_string_lossy());
+    Some((root, relative))
+}
+
+// ============================================================================
+// Reference Store
+// ============================================================================
+
+pub struct ImageRefStore {
+    refs_path: PathBuf,
+}
+
+impl ImageRefStore {
+    pub fn new(workspace_root: &Path) -> Self {
+        Self {
+            refs_path: workspace_root.join(".midlight").join("image-refs.json"),
+        }
+    }
+
+    /// Number of documents that reference an image
+    pub fn reference_count(&self, hash: &str) -> Result<usize, String> {
+        Ok(self.read()?.count(hash))
+    }
+
+    /// Record the images a document references after it was saved
+    pub fn update_document(&self, document: &str, refs: BTreeSet<String>) -> Result<(), String> {
+        self.update_document_at(document, refs, Utc::now())
+    }
+
+    fn update_document_at(
+        &self,
+        document: &str,
+        refs: BTreeSet<String>,
+        now: DateTime<Utc>,
+    ) -> Result<(), String> {
+        let mut file = self.read()?;
+        let previous = file.documents.remove(document).unwrap_or_default();
+        if previous == refs && file.orphans.keys().all(|hash| !refs.contains(hash)) {
+            if !refs.is_empty() {
+                file.documents.insert(document.to_string(), refs);
+            }
+            return Ok(());
+        }
+
+        let touched: BTreeSet<String> = previous.union(&refs).cloned().collect();
+        if !refs.is_empty() {
+            file.documents.insert(document.to_string(), refs);
+        }
+        file.reconcile(touched, now);
+        self.write(&file)
+    }
+
+    /// Drop the references of a deleted document, or of every document in a
+    /// deleted folder
+    pub fn remove_documents(&self, path: &str) -> Result<(), String> {
+        let mut file = self.read()?;
+        let removed: Vec<String> = file
+            .documents
+            .keys()
+            .filter(|document| is_at_or_under(document, path))
+            .cloned()
+            .collect();
+        if removed.is_empty() {
+            return Ok(());
+        }
+
+        let mut touched = BTreeSet::new();
+        for document in removed {
+            touched.extend(file.documents.remove(&document).unwrap_or_default());
+        }
+        file.reconcile(touched, Utc::now());
+        self.write(&file)
+    }
+
+    /// Re-key the references of a renamed or moved document or folder
+    pub fn move_documents(&self, from: &str, to: &str) -> Result<(), String> {
+        let mut file = self.read()?;
+        let moved: Vec<String> = file
+            .documents
+            .keys()
+            .filter(|document| is_at_or_under(document, from))
+            .cloned()
+            .collect();
+        if moved.is_empty() {
+            return Ok(());
+        }
+
+        for document in moved {
+            if let Some(refs) = file.documents.remove(&document) {
+                let renamed = format!("{}{}", to, &document[from.len()..]);
+                file.documents.insert(renamed, refs);
+            }
+        }
+        self.write(&file)
+    }
+
+    /// Remove and return orphans that have been unreferenced for longer
+    /// than the grace period, so their images can be deleted
+    pub fn take_expired_orphans(&self) -> Result<Vec<String>, String> {
+        self.take_expired_orphans_at(Utc::now())
+    }
+
+    fn take_expired_orphans_at(&self, now: DateTime<Utc>) -> Result<Vec<String>, String> {
+        let mut file = self.read()?;
+        let expired: Vec<String> = file
+            .orphans
+            .iter()
+            .filter(|(_, since)| now - **since >= Duration::days(ORPHAN_GRACE_DAYS))
+            .map(|(hash, _)| hash.clone())
+            .collect();
+        if expired.is_empty() {
+            return Ok(expired);
+        }
+
+        for hash in &expired {
+            file.orphans.remove(hash);
+        }
+        self.write(&file)?;
+        Ok(expired)
+    }
+
+    fn read(&self) -> Result<RefsFile, String> {
+        if !self.refs_path.exists() {
+            return Ok(RefsFile {
+                version: REFS_VERSION,
+                ..Default::default()
+            });
+        }
+
+        let content = fs::read_to_string(&self.refs_path)
+            .map_err(|e| format!("Failed to read image references: {}", e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse image references: {}", e))
+    }
+
+    fn write(&self, file: &RefsFile) -> Result<(), String> {
+        if let Some(parent) = self.refs_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create .midlight directory: {}", e))?;
+        }
+
+        let json = serde_json::to_string_pretty(file)
+            .map_err(|e| format!("Failed to serialize image references: {}", e))?;
+
+        let temp_path = self.refs_path.with_extension("json.tmp");
+        fs::write(&temp_path, json)
+            .map_err(|e| format!("Failed to write image references: {}", e))?;
+        fs::rename(&temp_path, &self.refs_path)
+            .map_err(|e| format!("Failed to write image references: {}", e))
+    }
+}
+
+fn is_at_or_under(document: &str, path: &str) -> bool {
+    document == path
+        || document
+            .strip_prefix(path)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn refs(hashes: &[&str]) -> BTreeSet<String> {
+        hashes.iter().map(|h| h.to_string()).collect()
+    }
+
+    #[test]
+    fn test_extract_refs() {
+        let content = r#"[{"src":"midlight://img-3f2a9c01d4e5b6a7"},
+            {"src":"midlight://img-3f2a9c01d4e5b6a7"},
+            {"src":"midlight://img-0b1c2d3e4f5a6b7c"},
+            {"src":"https://example.com/a.png"}]"#;
+        assert_eq!(
+            extract_refs(content),
+            refs(&["0b1c2d3e4f5a6b7c", "3f2a9c01d4e5b6a7"])
+        );
+    }
+
+    #[test]
+    fn test_counts_and_orphans() {
+        let temp = TempDir::new().unwrap();
+        let store = ImageRefStore::new(temp.path());
+        let start = Utc::now();
+
+        store
+            .update_document_at("a.midlight", refs(&["aaaa1111", "bbbb2222"]), start)
+            .unwrap();
+        store
+            .update_document_at("notes/b.midlight", refs(&["aaaa1111"]), start)
+            .unwrap();
+        assert_eq!(store.reference_count("aaaa1111").unwrap(), 2);
+
+        // Dropping the last reference to bbbb2222 orphans it
+        store
+            .update_document_at("a.midlight", refs(&["aaaa1111"]), start)
+            .unwrap();
+        assert_eq!(store.reference_count("bbbb2222").unwrap(), 0);
+        assert!(store.take_expired_orphans_at(start).unwrap().is_empty());
+
+        // Deleting a folder drops its documents' references, but aaaa1111 is
+        // still used by a.midlight
+        store.remove_documents("notes").unwrap();
+        assert_eq!(store.reference_count("aaaa1111").unwrap(), 1);
+
+        let later = start + Duration::days(ORPHAN_GRACE_DAYS);
+        assert_eq!(
+            store.take_expired_orphans_at(later).unwrap(),
+            vec!["bbbb2222".to_string()]
+        );
+        assert!(store.take_expired_orphans_at(later).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rereferenced_orphan_is_kept() {
+        let temp = TempDir::new().unwrap();
+        let store = ImageRefStore::new(temp.path());
+        let start = Utc::now();
+
+        store
+            .update_document_at("a.midlight", refs(&["aaaa1111"]), start)
+            .unwrap();
+        store
+            .update_document_at("a.midlight", refs(&[]), start)
+            .unwrap();
+        // e.g. a checkpoint with the image was restored
+        store
+            .update_document_at("a.midlight", refs(&["aaaa1111"]), start)
+            .unwrap();
+        assert!(store
+            .take_expired_orphans_at(start + Duration::days(ORPHAN_GRACE_DAYS))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_move_documents() {
+        let temp = TempDir::new().unwrap();
+        let store = ImageRefStore::new(temp.path());
+        store
+            .update_document("notes/a.midlight", refs(&["aaaa1111"]))
+            .unwrap();
+        store
+            .update_document("notes-old.midlight", refs(&["bbbb2222"]))
+            .unwrap();
+
+        store.move_documents("notes", "archive").unwrap();
+        store.remove_documents("archive/a.midlight").unwrap();
+        assert_eq!(store.reference_count("aaaa1111").unwrap(), 0);
+        // Only paths under the folder moved
+        assert_eq!(store.reference_count("bbbb2222").unwrap(), 1);
+    }
+}
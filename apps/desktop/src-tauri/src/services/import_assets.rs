@@ -0,0 +1,202 @@
+// Import Assets - Downloads remote images linked from imported documents
+//
+// Notion and HTML exports often link images hosted elsewhere, which stop
+// showing when the app is offline or the links expire. When an import asks
+// for it, each web image in the imported Markdown is downloaded into the
+// destination's image store and its link pointed at the stored copy. The
+// download is size-capped like a web clipping's; images that can't be
+// downloaded keep their remote address and are reported as warnings.
+
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use tracing::debug;
+
+use super::atomic_write::write_atomic;
+use super::image_manager::ImageManager;
+use super::import_service::{ImportResult, ImportTransform, ImportWarningInfo};
+use super::web_clipper::download_image;
+use super::web_fetch;
+use crate::traits::http_client::HttpClient;
+
+/// Images downloaded per import; the rest keep their remote address
+const MAX_REMOTE_IMAGES: usize = 500;
+
+lazy_static! {
+    /// `![alt](https://… "title")`
+    static ref REMOTE_IMAGE: Regex =
+        Regex::new(r#"!\[([^\]]*)\]\((https?://[^)\s]+)((?:\s+"[^"]*")?)\)"#).unwrap();
+}
+
+/// Download the remote images of an import's Markdown files. Runs on the
+/// import's blocking thread.
+pub fn fetch_remote_images(dest: &Path, result: &mut ImportResult) {
+    match web_fetch::client() {
+        Ok(client) => {
+            tauri::async_runtime::block_on(download_remote_images(&client, dest, result));
+        }
+        Err(e) => result.warnings.push(ImportWarningInfo {
+            file: String::new(),
+            message: format!("Remote images weren't downloaded: {}", e),
+        }),
+    }
+}
+
+/// Download the remote images linked from the Markdown files an import
+/// wrote and rewrite the links. Returns how many images were stored.
+pub async fn download_remote_images<C: HttpClient>(
+    client: &C,
+    dest: &Path,
+    result: &mut ImportResult,
+) -> usize {
+    let images = ImageManager::new(dest);
+    if let Err(e) = images.init().await {
+        result.warnings.push(ImportWarningInfo {
+            file: String::new(),
+            message: format!("Remote images weren't downloaded: {}", e),
+        });
+        return 0;
+    }
+
+    let mut stored: HashMap<String, Option<String>> = HashMap::new();
+    let mut saved = 0;
+    let mut limited = false;
+
+    for file in &mut result.imported {
+        if !file.destination.ends_with(".md") {
+            continue;
+        }
+        let path = dest.join(&file.destination);
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+
+        for image in REMOTE_IMAGE.captures_iter(&content) {
+            let url = &image[2];
+            if stored.contains_key(url) {
+                continue;
+            }
+            if stored.len() >= MAX_REMOTE_IMAGES {
+                limited = true;
+                break;
+            }
+            let reference = match download_image(client, &images, url).await {
+                Ok(reference) => {
+                    saved += 1;
+                    Some(reference)
+                }
+                Err(e) => {
+                    debug!("Keeping remote image {}: {}", url, e);
+                    result.warnings.push(ImportWarningInfo {
+                        file: file.source.clone(),
+                        message: format!("Couldn't download image {}: {}", url, e),
+                    });
+                    None
+                }
+            };
+            stored.insert(url.to_string(), reference);
+        }
+
+        let rewritten =
+            REMOTE_IMAGE.replace_all(&content, |image: &Captures| match stored.get(&image[2]) {
+                Some(Some(reference)) => format!("![{}]({}{})", &image[1], reference, &image[3]),
+                _ => image[0].to_string(),
+            });
+        if rewritten == content {
+            continue;
+        }
+        match write_atomic(&path, rewritten.as_bytes()) {
+            Ok(()) => file.transforms.push(ImportTransform::RemoteImages),
+            Err(e) => result.warnings.push(ImportWarningInfo {
+                file: file.source.clone(),
+                message: format!("Couldn't point images at their downloaded copies: {}", e),
+            }),
+        }
+    }
+
+    if limited {
+        result.warnings.push(ImportWarningInfo {
+            file: String::new(),
+            message: format!(
+                "Only the first {} remote images were downloaded",
+                MAX_REMOTE_IMAGES
+            ),
+        });
+    }
+    saved
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::import_service::{
+        analyze_generic_folder, import_generic_folder, ImportOptions,
+    };
+    use crate::traits::http_client::HttpResponse;
+    use crate::traits::MockHttpClient;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_download_remote_images() {
+        let source = TempDir::new().unwrap();
+        let dest = TempDir::new().unwrap();
+        fs::write(
+            source.path().join("a.md"),
+            "![Logo](https://example.com/logo.png \"Our logo\")\n\
+             ![Gone](https://example.com/gone.png)\n\
+             ![Local](logo.png)",
+        )
+        .unwrap();
+        fs::write(
+            source.path().join("b.md"),
+            "Again: ![Logo](https://example.com/logo.png)",
+        )
+        .unwrap();
+
+        let analysis = analyze_generic_folder(source.path()).unwrap();
+        let options = ImportOptions {
+            fetch_remote_images: true,
+            ..ImportOptions::default()
+        };
+        let mut result =
+            import_generic_folder(&analysis, dest.path(), &options, None, None).unwrap();
+
+        let client = MockHttpClient::new()
+            .queue_response(
+                HttpResponse::new(200, vec![0x89, b'P', b'N', b'G'])
+                    .with_header("Content-Type", "image/png"),
+            )
+            .queue_response(HttpResponse::new(404, "missing"));
+        let warnings = result.warnings.len();
+        let saved = download_remote_images(&client, dest.path(), &mut result).await;
+
+        assert_eq!(saved, 1);
+        assert_eq!(client.get_requests().len(), 2);
+        assert_eq!(result.warnings.len(), warnings + 1);
+        assert!(result.warnings[warnings].message.contains("gone.png"));
+
+        let a = fs::read_to_string(dest.path().join("a.md")).unwrap();
+        let reference = a
+            .lines()
+            .next()
+            .and_then(|line| line.strip_prefix("![Logo]("))
+            .and_then(|rest| rest.strip_suffix(" \"Our logo\")"))
+            .unwrap();
+        assert!(reference.starts_with("midlight://img-"));
+        assert!(a.contains("![Gone](https://example.com/gone.png)"));
+        assert!(a.contains("![Local](logo.png)"));
+        let b = fs::read_to_string(dest.path().join("b.md")).unwrap();
+        assert_eq!(b, format!("Again: ![Logo]({})", reference));
+
+        assert!(result
+            .imported
+            .iter()
+            .all(|file| file.transforms.contains(&ImportTransform::RemoteImages)));
+    }
+}
@@ -0,0 +1,442 @@
+// Import Manifests - What an import brought in, so it can be repeated
+//
+// After each import a manifest records every imported source file, where it
+// went and the conversions applied, along with the source's size and
+// modification time. Repeating the import from its manifest re-imports only
+// the files that are new or changed since, which lets a vault still in use
+// be migrated in stages. Files edited in the workspace since they were
+// imported are left alone.
+//
+// Stored in {destination}/.midlight/imports/{timestamp}.json
+// Format:
+// {
+//   "version": 1,
+//   "sourcePath": "/Users/me/Vault",
+//   "destPath": "/Users/me/Workspace/Vault",
+//   "importedAt": "2024-01-05T10:00:00+00:00",
+//   "sourceType": "obsidian",
+//   "options": { "convertWikiLinks": true, ... },
+//   "files": [{
+//     "source": "Notes/a.md", "destination": "Notes/a.md",
+//     "transforms": ["wikiLinks"], "size": 120,
+//     "modified": 1704448800000, "destinationModified": 1704448801000
+//   }]
+// }
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+use super::atomic_write::write_atomic;
+use super::error::ImportError;
+use super::import_assets::fetch_remote_images;
+use super::import_service::{
+    analyze_generic_folder, analyze_notion_export, analyze_obsidian_vault, import_generic_folder,
+    import_notion_export, import_obsidian_vault, CancellationToken, ImportAnalysis, ImportOptions,
+    ImportResult, ImportSourceType, ImportWarningInfo, ImportedFile, NotionImportOptions,
+    ProgressCallback,
+};
+
+const MANIFEST_VERSION: u32 = 1;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// The kind of import and the options it ran with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "sourceType", content = "options", rename_all = "lowercase")]
+pub enum ImportSettings {
+    Obsidian(ImportOptions),
+    Notion(NotionImportOptions),
+    Generic(ImportOptions),
+}
+
+impl ImportSettings {
+    pub fn source_type(&self) -> ImportSourceType {
+        match self {
+            ImportSettings::Obsidian(_) => ImportSourceType::Obsidian,
+            ImportSettings::Notion(_) => ImportSourceType::Notion,
+            ImportSettings::Generic(_) => ImportSourceType::Generic,
+        }
+    }
+
+    /// The options every kind of import shares
+    pub fn options(&self) -> &ImportOptions {
+        match self {
+            ImportSettings::Obsidian(options) | ImportSettings::Generic(options) => options,
+            ImportSettings::Notion(options) => &options.base,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestEntry {
+    #[serde(flatten)]
+    pub file: ImportedFile,
+    /// Size of the source file when it was imported
+    pub size: u64,
+    /// When the source file was last modified, in milliseconds
+    pub modified: Option<u64>,
+    /// When the imported file was written, in milliseconds
+    pub destination_modified: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportManifest {
+    pub version: u32,
+    pub source_path: String,
+    pub dest_path: String,
+    /// When the import last ran
+    pub imported_at: String,
+    #[serde(flatten)]
+    pub settings: ImportSettings,
+    pub files: Vec<ManifestEntry>,
+}
+
+/// A manifest as listed for the user to pick one to repeat
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportManifestInfo {
+    pub path: String,
+    pub source_type: ImportSourceType,
+    pub source_path: String,
+    pub imported_at: String,
+    pub files: usize,
+}
+
+// ============================================================================
+// Manifest
+// ============================================================================
+
+/// Where the manifests of imports into `dest` are kept
+pub fn manifest_dir(dest: &Path) -> PathBuf {
+    dest.join(".midlight").join("imports")
+}
+
+fn modified_ms(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    modified
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_millis() as u64)
+}
+
+impl ImportManifest {
+    pub fn new(source_path: &str, dest: &Path, settings: ImportSettings) -> Self {
+        Self {
+            version: MANIFEST_VERSION,
+            source_path: source_path.to_string(),
+            dest_path: dest.to_string_lossy().to_string(),
+            imported_at: Utc::now().to_rfc3339(),
+            settings,
+            files: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ImportError> {
+        let json = fs::read_to_string(path)?;
+        let manifest: Self = serde_json::from_str(&json)
+            .map_err(|e| ImportError::Other(format!("Invalid import manifest: {}", e)))?;
+        if manifest.version > MANIFEST_VERSION {
+            return Err(ImportError::Other(format!(
+                "Import manifest version {} is newer than this app supports",
+                manifest.version
+            )));
+        }
+        Ok(manifest)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ImportError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| ImportError::Other(format!("Failed to serialize manifest: {}", e)))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_atomic(path, json)?;
+        Ok(())
+    }
+
+    /// Add files that were just imported, replacing any earlier entries for
+    /// the same source files
+    pub fn record(&mut self, imported: &[ImportedFile]) {
+        let source = Path::new(&self.source_path);
+        let dest = Path::new(&self.dest_path);
+        let mut positions: HashMap<String, usize> = self
+            .files
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (entry.file.source.clone(), i))
+            .collect();
+
+        for file in imported {
+            let source_file = source.join(&file.source);
+            let entry = ManifestEntry {
+                file: file.clone(),
+                size: fs::metadata(&source_file).map(|m| m.len()).unwrap_or(0),
+                modified: modified_ms(&source_file),
+                destination_modified: modified_ms(&dest.join(&file.destination)),
+            };
+            match positions.get(&file.source) {
+                Some(&i) => self.files[i] = entry,
+                None => {
+                    positions.insert(file.source.clone(), self.files.len());
+                    self.files.push(entry);
+                }
+            }
+        }
+        self.imported_at = Utc::now().to_rfc3339();
+    }
+
+    /// The files of a fresh analysis of the source that don't need importing
+    /// again, and a warning for each changed file that's been edited in the
+    /// workspace since it was imported, which is kept rather than replaced
+    pub fn unchanged_files(
+        &self,
+        analysis: &ImportAnalysis,
+    ) -> (HashSet<String>, Vec<ImportWarningInfo>) {
+        let entries: HashMap<&str, &ManifestEntry> = self
+            .files
+            .iter()
+            .map(|entry| (entry.file.source.as_str(), entry))
+            .collect();
+        let dest = Path::new(&self.dest_path);
+        let mut unchanged = HashSet::new();
+        let mut warnings = Vec::new();
+
+        for file in &analysis.files_to_import {
+            let Some(entry) = entries.get(file.relative_path.as_str()) else {
+                continue;
+            };
+            let modified = modified_ms(Path::new(&file.source_path));
+            if entry.size == file.size && modified.is_some() && entry.modified == modified {
+                unchanged.insert(file.relative_path.clone());
+            } else if modified_ms(&dest.join(&entry.file.destination)) != entry.destination_modified
+            {
+                unchanged.insert(file.relative_path.clone());
+                warnings.push(ImportWarningInfo {
+                    file: file.relative_path.clone(),
+                    message: format!(
+                        "Changed since the last import, but {} was edited or removed in the workspace, so it wasn't replaced",
+                        entry.file.destination
+                    ),
+                });
+            }
+        }
+        (unchanged, warnings)
+    }
+}
+
+/// Write a manifest for an import that just finished next to what it
+/// imported. Failing to write one doesn't fail the import; it's reported as
+/// a warning instead.
+pub fn write_manifest(
+    analysis: &ImportAnalysis,
+    dest: &Path,
+    settings: ImportSettings,
+    result: &mut ImportResult,
+) {
+    let mut manifest = ImportManifest::new(&analysis.source_path, dest, settings);
+    manifest.record(&result.imported);
+
+    let name = format!("{}.json", Utc::now().format("%Y%m%dT%H%M%S%3f"));
+    let path = manifest_dir(dest).join(name);
+    match manifest.save(&path) {
+        Ok(()) => result.manifest_path = Some(path.to_string_lossy().to_string()),
+        Err(e) => result.warnings.push(ImportWarningInfo {
+            file: String::new(),
+            message: format!("Could not save the import manifest: {}", e),
+        }),
+    }
+}
+
+/// Manifests of imports into `dest`, most recent first
+pub fn list_manifests(dest: &Path) -> Result<Vec<ImportManifestInfo>, ImportError> {
+    let dir = manifest_dir(dest);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut manifests = Vec::new();
+    for entry in fs::read_dir(&dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        // Skip anything that isn't a readable manifest
+        let Ok(manifest) = ImportManifest::load(&path) else {
+            continue;
+        };
+        manifests.push(ImportManifestInfo {
+            path: path.to_string_lossy().to_string(),
+            source_type: manifest.settings.source_type(),
+            source_path: manifest.source_path,
+            imported_at: manifest.imported_at,
+            files: manifest.files.len(),
+        });
+    }
+    manifests.sort_by(|a, b| b.imported_at.cmp(&a.imported_at));
+    Ok(manifests)
+}
+
+// ============================================================================
+// Repeating Imports
+// ============================================================================
+
+/// Run an import again from its manifest with the same options, importing
+/// only source files that are new or changed since it last ran. The
+/// manifest is updated with what was imported.
+pub fn repeat_import(
+    manifest_path: &Path,
+    progress_callback: Option<ProgressCallback>,
+    cancel_token: Option<Arc<CancellationToken>>,
+) -> Result<ImportResult, ImportError> {
+    let mut manifest = ImportManifest::load(manifest_path)?;
+    let source = Path::new(&manifest.source_path);
+    let dest = PathBuf::from(&manifest.dest_path);
+
+    let mut analysis = match manifest.settings {
+        ImportSettings::Obsidian(_) => analyze_obsidian_vault(source)?,
+        ImportSettings::Notion(_) => analyze_notion_export(source)?,
+        ImportSettings::Generic(_) => analyze_generic_folder(source)?,
+    };
+    let (unchanged, kept) = manifest.unchanged_files(&analysis);
+    analysis.unchanged_files = unchanged;
+
+    let mut result = match &manifest.settings {
+        ImportSettings::Obsidian(options) => {
+            import_obsidian_vault(&analysis, &dest, options, progress_callback, cancel_token)?
+        }
+        ImportSettings::Notion(options) => {
+            import_notion_export(&analysis, &dest, options, progress_callback, cancel_token)?
+        }
+        ImportSettings::Generic(options) => {
+            import_generic_folder(&analysis, &dest, options, progress_callback, cancel_token)?
+        }
+    };
+    result.warnings.extend(kept);
+    if manifest.settings.options().fetch_remote_images {
+        fetch_remote_images(&dest, &mut result);
+    }
+
+    manifest.record(&result.imported);
+    match manifest.save(manifest_path) {
+        Ok(()) => result.manifest_path = Some(manifest_path.to_string_lossy().to_string()),
+        Err(e) => result.warnings.push(ImportWarningInfo {
+            file: String::new(),
+            message: format!("Could not update the import manifest: {}", e),
+        }),
+    }
+    Ok(result)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::import_service::ImportTransform;
+    use tempfile::TempDir;
+
+    fn first_import(source: &Path, dest: &Path) -> ImportResult {
+        let analysis = analyze_generic_folder(source).unwrap();
+        let options = ImportOptions::default();
+        let mut result = import_generic_folder(&analysis, dest, &options, None, None).unwrap();
+        write_manifest(
+            &analysis,
+            dest,
+            ImportSettings::Generic(options),
+            &mut result,
+        );
+        result
+    }
+
+    #[test]
+    fn test_manifest_records_import() {
+        let source = TempDir::new().unwrap();
+        let dest = TempDir::new().unwrap();
+        fs::write(source.path().join("a.md"), "See [[b]]").unwrap();
+        fs::write(source.path().join("b.md"), "Hello").unwrap();
+
+        let result = first_import(source.path(), dest.path());
+        let path = PathBuf::from(result.manifest_path.unwrap());
+        assert!(path.starts_with(manifest_dir(dest.path())));
+
+        let manifest = ImportManifest::load(&path).unwrap();
+        assert_eq!(manifest.settings.source_type(), ImportSourceType::Generic);
+        assert_eq!(manifest.files.len(), 2);
+        let a = manifest
+            .files
+            .iter()
+            .find(|e| e.file.source == "a.md")
+            .unwrap();
+        assert_eq!(a.file.destination, "a.md");
+        assert!(a.file.transforms.contains(&ImportTransform::WikiLinks));
+        assert_eq!(a.size, 9);
+        assert!(a.modified.is_some());
+
+        let listed = list_manifests(dest.path()).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].files, 2);
+    }
+
+    #[test]
+    fn test_repeat_imports_only_new_and_changed_files() {
+        let source = TempDir::new().unwrap();
+        let dest = TempDir::new().unwrap();
+        fs::write(source.path().join("a.md"), "First").unwrap();
+        fs::write(source.path().join("b.md"), "Unchanged").unwrap();
+        let result = first_import(source.path(), dest.path());
+        let manifest_path = PathBuf::from(result.manifest_path.unwrap());
+
+        fs::write(source.path().join("a.md"), "Second").unwrap();
+        fs::write(source.path().join("c.md"), "New").unwrap();
+
+        let result = repeat_import(&manifest_path, None, None).unwrap();
+        assert_eq!(result.files_imported, 2);
+        assert_eq!(
+            fs::read_to_string(dest.path().join("a.md")).unwrap(),
+            "Second"
+        );
+        assert!(dest.path().join("c.md").exists());
+
+        let manifest = ImportManifest::load(&manifest_path).unwrap();
+        assert_eq!(manifest.files.len(), 3);
+
+        // Nothing changed since, so nothing to import
+        let result = repeat_import(&manifest_path, None, None).unwrap();
+        assert_eq!(result.files_imported, 0);
+    }
+
+    #[test]
+    fn test_repeat_keeps_files_edited_in_workspace() {
+        let source = TempDir::new().unwrap();
+        let dest = TempDir::new().unwrap();
+        fs::write(source.path().join("a.md"), "Original").unwrap();
+        let result = first_import(source.path(), dest.path());
+        let manifest_path = PathBuf::from(result.manifest_path.unwrap());
+
+        fs::write(source.path().join("a.md"), "Changed in the vault").unwrap();
+        fs::write(dest.path().join("a.md"), "Edited in the workspace").unwrap();
+        // As if the edit came well after the import
+        let mut manifest = ImportManifest::load(&manifest_path).unwrap();
+        manifest.files[0].destination_modified = Some(0);
+        manifest.save(&manifest_path).unwrap();
+
+        let result = repeat_import(&manifest_path, None, None).unwrap();
+        assert_eq!(result.files_imported, 0);
+        assert_eq!(result.warnings.len(), 1);
+        assert_eq!(
+            fs::read_to_string(dest.path().join("a.md")).unwrap(),
+            "Edited in the workspace"
+        );
+    }
+}
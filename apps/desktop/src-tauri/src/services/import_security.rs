@@ -3,6 +3,7 @@
 
 use percent_encoding::percent_decode_str;
 use serde::{Deserialize, Serialize};
+use std::fs;
 use std::path::{Path, PathBuf};
 use unicode_normalization::UnicodeNormalization;
 
@@ -29,6 +30,12 @@ impl ImportConfig {
     /// Maximum YAML nesting depth
     pub const MAX_YAML_DEPTH: usize = 50;
 
+    /// Maximum alias references (`*name`) in a YAML document
+    pub const MAX_YAML_ALIASES: usize = 100;
+
+    /// Maximum values in a YAML document once aliases are expanded
+    pub const MAX_YAML_NODES: usize = 10_000;
+
     /// Parallel batch size for file processing
     pub const PARALLEL_BATCH_SIZE: usize = 10;
 
@@ -40,6 +47,12 @@ impl ImportConfig {
 
     /// Disk space buffer percentage (10%)
     pub const DISK_SPACE_BUFFER: f64 = 0.1;
+
+    /// Maximum number of files read from an import source
+    pub const MAX_SOURCE_FILES: usize = 100_000;
+
+    /// Maximum total size of the files read from an import source (5GB)
+    pub const MAX_SOURCE_SIZE: u64 = 5 * 1024 * 1024 * 1024;
 }
 
 /// Allowed file extensions for import
@@ -87,6 +100,183 @@ impl AllowedExtension {
     }
 }
 
+/// Bytes read from the start of a file to recognize its content
+pub const SNIFF_LENGTH: usize = 1024;
+
+/// What a file's first bytes show it to be
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SniffedType {
+    Png,
+    Jpeg,
+    Gif,
+    Webp,
+    Bmp,
+    Ico,
+    Svg,
+    Pdf,
+    Mp3,
+    /// MP4 and QuickTime, which share a container format
+    Mp4,
+    Wav,
+    Webm,
+    Ogg,
+    Executable,
+    Html,
+    Unknown,
+}
+
+impl SniffedType {
+    /// The type a file with this extension should contain, for extensions
+    /// whose formats have a recognizable signature
+    pub fn for_extension(extension: &str) -> Option<SniffedType> {
+        Some(match extension.to_lowercase().as_str() {
+            "png" => SniffedType::Png,
+            "jpg" | "jpeg" => SniffedType::Jpeg,
+            "gif" => SniffedType::Gif,
+            "webp" => SniffedType::Webp,
+            "bmp" => SniffedType::Bmp,
+            "ico" => SniffedType::Ico,
+            "svg" => SniffedType::Svg,
+            "pdf" => SniffedType::Pdf,
+            "mp3" => SniffedType::Mp3,
+            "mp4" | "mov" => SniffedType::Mp4,
+            "wav" => SniffedType::Wav,
+            "webm" => SniffedType::Webm,
+            "ogg" => SniffedType::Ogg,
+            _ => return None,
+        })
+    }
+
+    fn is_dangerous(&self) -> bool {
+        matches!(self, SniffedType::Executable | SniffedType::Html)
+    }
+}
+
+/// Recognize content by its magic bytes
+pub fn sniff_content(bytes: &[u8]) -> SniffedType {
+    let starts = |magic: &[u8]| bytes.starts_with(magic);
+    let at = |offset: usize, magic: &[u8]| bytes.get(offset..offset + magic.len()) == Some(magic);
+
+    if starts(b"\x89PNG") {
+        SniffedType::Png
+    } else if starts(&[0xFF, 0xD8, 0xFF]) {
+        SniffedType::Jpeg
+    } else if starts(b"GIF87a") || starts(b"GIF89a") {
+        SniffedType::Gif
+    } else if starts(b"RIFF") && at(8, b"WEBP") {
+        SniffedType::Webp
+    } else if starts(b"RIFF") && at(8, b"WAVE") {
+        SniffedType::Wav
+    } else if starts(b"BM") && bytes.len() >= 14 {
+        SniffedType::Bmp
+    } else if starts(&[0, 0, 1, 0]) {
+        SniffedType::Ico
+    } else if starts(b"ID3") || (bytes.len() >= 2 && bytes[0] == 0xFF && bytes[1] & 0xE0 == 0xE0) {
+        SniffedType::Mp3
+    } else if at(4, b"ftyp") || at(4, b"moov") || at(4, b"mdat") || at(4, b"wide") {
+        SniffedType::Mp4
+    } else if starts(&[0x1A, 0x45, 0xDF, 0xA3]) {
+        SniffedType::Webm
+    } else if starts(b"OggS") {
+        SniffedType::Ogg
+    } else if starts(b"MZ")
+        || starts(b"\x7fELF")
+        || starts(&[0xFE, 0xED, 0xFA, 0xCE])
+        || starts(&[0xFE, 0xED, 0xFA, 0xCF])
+        || starts(&[0xCE, 0xFA, 0xED, 0xFE])
+        || starts(&[0xCF, 0xFA, 0xED, 0xFE])
+        || starts(&[0xCA, 0xFE, 0xBA, 0xBE])
+        || starts(b"#!")
+    {
+        SniffedType::Executable
+    } else if bytes.windows(5).take(SNIFF_LENGTH).any(|w| w == b"%PDF-") {
+        // Some writers put junk before the header, which readers accept
+        SniffedType::Pdf
+    } else {
+        sniff_markup(bytes)
+    }
+}
+
+/// Tell SVG from HTML by the first element
+fn sniff_markup(bytes: &[u8]) -> SniffedType {
+    let text = String::from_utf8_lossy(&bytes[..bytes.len().min(SNIFF_LENGTH)]).to_lowercase();
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    if !text.starts_with('<') {
+        return SniffedType::Unknown;
+    }
+    if [
+        "<!doctype html",
+        "<html",
+        "<head",
+        "<body",
+        "<script",
+        "<iframe",
+    ]
+    .iter()
+    .any(|tag| text.starts_with(tag))
+    {
+        return SniffedType::Html;
+    }
+    // Skip the XML declaration, comments and doctype to the root element
+    let root = text
+        .match_indices('<')
+        .map(|(i, _)| &text[i..])
+        .find(|rest| !rest.starts_with("<?") && !rest.starts_with("<!"));
+    match root {
+        Some(root) if root.starts_with("<svg") => SniffedType::Svg,
+        Some(root) if root.starts_with("<html") => SniffedType::Html,
+        _ => SniffedType::Unknown,
+    }
+}
+
+/// Check that a file's content matches its extension. Executables and HTML
+/// are refused whatever they're called; other content must carry the
+/// signature of its extension's format, or of another harmless format it
+/// was misnamed from.
+pub fn verify_content(filename: &str, bytes: &[u8]) -> Result<(), String> {
+    let sniffed = sniff_content(bytes);
+    if sniffed.is_dangerous() {
+        let what = if sniffed == SniffedType::Executable {
+            "a program"
+        } else {
+            "a web page"
+        };
+        return Err(format!(
+            "Blocked {}: the file is actually {}",
+            filename, what
+        ));
+    }
+
+    let extension = Path::new(filename)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("");
+    match SniffedType::for_extension(extension) {
+        Some(_) if bytes.is_empty() => Ok(()),
+        Some(_) if sniffed == SniffedType::Unknown => Err(format!(
+            "Blocked {}: the content isn't a .{} file",
+            filename,
+            extension.to_lowercase()
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Read the start of a file and check it with `verify_content`
+pub fn verify_file_content(path: &Path) -> Result<(), String> {
+    use std::io::Read;
+
+    let filename = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut bytes = Vec::with_capacity(SNIFF_LENGTH);
+    fs::File::open(path)
+        .and_then(|file| file.take(SNIFF_LENGTH as u64).read_to_end(&mut bytes))
+        .map_err(|e| format!("Could not read {}: {}", filename, e))?;
+    verify_content(&filename, &bytes)
+}
+
 /// Windows reserved filenames that cannot be used
 const WINDOWS_RESERVED_NAMES: &[&str] = &[
     "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
@@ -308,6 +498,88 @@ pub fn is_path_safe(dest_path: &Path, base_path: &Path) -> bool {
     full_canonical.starts_with(&canonical_base)
 }
 
+/// Whether a file in an import source may be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceFileCheck {
+    Allowed,
+    /// Skipped, with the reason to report as a warning
+    Blocked(String),
+}
+
+/// Checks the files of an import source before they're read. Symlinks and
+/// junctions are only followed when they resolve to a file inside the
+/// source folder, and the source as a whole is capped in file count and
+/// size, so a folder can't pull in files from elsewhere on disk or fill the
+/// workspace's disk.
+pub struct SourceGuard {
+    root: Option<PathBuf>,
+    files: usize,
+    bytes: u64,
+}
+
+impl SourceGuard {
+    pub fn new(source_root: &Path) -> Self {
+        Self {
+            root: source_root.canonicalize().ok(),
+            files: 0,
+            bytes: 0,
+        }
+    }
+
+    /// Check the next file. Fails once the source is over the caps.
+    pub fn check(&mut self, path: &Path) -> Result<SourceFileCheck, ImportError> {
+        let Ok(link) = fs::symlink_metadata(path) else {
+            // Missing files are reported when reading them fails
+            return Ok(SourceFileCheck::Allowed);
+        };
+        // Junctions count as symlinks on Windows
+        let is_link = link.file_type().is_symlink();
+
+        let resolved = match path.canonicalize() {
+            Ok(resolved) => resolved,
+            Err(_) if is_link => {
+                return Ok(SourceFileCheck::Blocked("Skipped broken link".into()));
+            }
+            Err(_) => return Ok(SourceFileCheck::Allowed),
+        };
+        let inside = self
+            .root
+            .as_ref()
+            .is_some_and(|root| resolved.starts_with(root));
+        if !inside {
+            return Ok(SourceFileCheck::Blocked(if is_link {
+                "Skipped link to a location outside the import folder".into()
+            } else {
+                "Skipped file outside the import folder".into()
+            }));
+        }
+
+        let Ok(metadata) = fs::metadata(&resolved) else {
+            return Ok(SourceFileCheck::Allowed);
+        };
+        if metadata.is_dir() {
+            // Anything in it inside the source is imported from where it is
+            return Ok(SourceFileCheck::Blocked("Skipped link to a folder".into()));
+        }
+
+        self.files += 1;
+        self.bytes += metadata.len();
+        if self.files > ImportConfig::MAX_SOURCE_FILES {
+            return Err(ImportError::SourceTooLarge(format!(
+                "more than {} files",
+                ImportConfig::MAX_SOURCE_FILES
+            )));
+        }
+        if self.bytes > ImportConfig::MAX_SOURCE_SIZE {
+            return Err(ImportError::SourceTooLarge(format!(
+                "more than {} GB",
+                ImportConfig::MAX_SOURCE_SIZE / (1024 * 1024 * 1024)
+            )));
+        }
+        Ok(SourceFileCheck::Allowed)
+    }
+}
+
 /// Validate a path string for basic safety
 #[allow(dead_code)] // Security validation preserved for future use
 pub fn validate_path(input_path: &str) -> Result<(), ImportError> {
@@ -340,51 +612,175 @@ pub fn validate_path(input_path: &str) -> Result<(), ImportError> {
     Ok(())
 }
 
-/// Safely parse YAML content with size and depth limits
+/// What was wrong with a YAML document
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum YamlErrorKind {
+    Syntax,
+    TooLarge,
+    TooDeep,
+    TooManyAliases,
+    TooManyNodes,
+}
+
+/// A rejected YAML document, with the position of the problem when known
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct YamlError {
+    pub kind: YamlErrorKind,
+    pub message: String,
+    /// 1-based; for front matter, the line in the whole file
+    pub line: Option<usize>,
+    /// 1-based
+    pub column: Option<usize>,
+}
+
+impl YamlError {
+    fn new(kind: YamlErrorKind, message: String) -> Self {
+        Self {
+            kind,
+            message,
+            line: None,
+            column: None,
+        }
+    }
+}
+
+impl std::fmt::Display for YamlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => {
+                write!(f, "line {}, column {}: {}", line, column, self.message)
+            }
+            (Some(line), None) => write!(f, "line {}: {}", line, self.message),
+            _ => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Safely parse YAML content with size, depth and alias limits, so a small
+/// document can't expand into a huge one
 pub fn safe_parse_yaml(content: &str) -> Result<serde_yaml::Value, ImportError> {
     // Check size limit
     if content.len() > ImportConfig::MAX_YAML_SIZE {
-        return Err(ImportError::YamlParse(format!(
-            "YAML content exceeds maximum size of {} bytes",
-            ImportConfig::MAX_YAML_SIZE
+        return Err(ImportError::YamlParse(YamlError::new(
+            YamlErrorKind::TooLarge,
+            format!(
+                "YAML content exceeds maximum size of {} bytes",
+                ImportConfig::MAX_YAML_SIZE
+            ),
         )));
     }
 
-    // Parse YAML
-    let value: serde_yaml::Value =
-        serde_yaml::from_str(content).map_err(|e| ImportError::YamlParse(e.to_string()))?;
+    // Aliases are what make small documents expand, so they're limited
+    // before parsing
+    check_aliases(content).map_err(ImportError::YamlParse)?;
 
-    // Validate depth
-    fn check_depth(value: &serde_yaml::Value, current_depth: usize) -> Result<(), ImportError> {
+    // Parse YAML
+    let value: serde_yaml::Value = serde_yaml::from_str(content).map_err(|e| {
+        let location = e.location();
+        ImportError::YamlParse(YamlError {
+            kind: YamlErrorKind::Syntax,
+            // The location is reported separately
+            message: e
+                .to_string()
+                .split(" at line ")
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            line: location.as_ref().map(|l| l.line()),
+            column: location.as_ref().map(|l| l.column()),
+        })
+    })?;
+
+    // Validate depth and size once aliases are expanded
+    fn check_structure(
+        value: &serde_yaml::Value,
+        current_depth: usize,
+        nodes: &mut usize,
+    ) -> Result<(), YamlError> {
         if current_depth > ImportConfig::MAX_YAML_DEPTH {
-            return Err(ImportError::YamlParse(format!(
-                "YAML nesting exceeds maximum depth of {}",
-                ImportConfig::MAX_YAML_DEPTH
-            )));
+            return Err(YamlError::new(
+                YamlErrorKind::TooDeep,
+                format!(
+                    "YAML nesting exceeds maximum depth of {}",
+                    ImportConfig::MAX_YAML_DEPTH
+                ),
+            ));
+        }
+        *nodes += 1;
+        if *nodes > ImportConfig::MAX_YAML_NODES {
+            return Err(YamlError::new(
+                YamlErrorKind::TooManyNodes,
+                format!("YAML has more than {} values", ImportConfig::MAX_YAML_NODES),
+            ));
         }
 
         match value {
             serde_yaml::Value::Mapping(map) => {
                 for (_, v) in map {
-                    check_depth(v, current_depth + 1)?;
+                    check_structure(v, current_depth + 1, nodes)?;
                 }
             }
             serde_yaml::Value::Sequence(seq) => {
                 for v in seq {
-                    check_depth(v, current_depth + 1)?;
+                    check_structure(v, current_depth + 1, nodes)?;
                 }
             }
+            serde_yaml::Value::Tagged(tagged) => {
+                check_structure(&tagged.value, current_depth + 1, nodes)?;
+            }
             _ => {}
         }
 
         Ok(())
     }
 
-    check_depth(&value, 0)?;
+    check_structure(&value, 0, &mut 0).map_err(ImportError::YamlParse)?;
 
     Ok(value)
 }
 
+/// Count alias references (`*name`), failing at the first one over the limit.
+/// Quoted scalars and comments are skipped.
+fn check_aliases(content: &str) -> Result<(), YamlError> {
+    let mut aliases = 0;
+    for (index, line) in content.lines().enumerate() {
+        let mut quote = None;
+        let mut previous = ' ';
+        for (column, c) in line.chars().enumerate() {
+            match quote {
+                Some(q) if c == q => quote = None,
+                Some(_) => {}
+                None if (c == '"' || c == '\'') && starts_token(previous) => quote = Some(c),
+                None if c == '#' && previous.is_whitespace() => break,
+                None if c == '*' && starts_token(previous) => {
+                    aliases += 1;
+                    if aliases > ImportConfig::MAX_YAML_ALIASES {
+                        return Err(YamlError {
+                            kind: YamlErrorKind::TooManyAliases,
+                            message: format!(
+                                "YAML has more than {} aliases",
+                                ImportConfig::MAX_YAML_ALIASES
+                            ),
+                            line: Some(index + 1),
+                            column: Some(column + 1),
+                        });
+                    }
+                }
+                None => {}
+            }
+            previous = c;
+        }
+    }
+    Ok(())
+}
+
+/// Whether a token can start after `previous`
+fn starts_token(previous: char) -> bool {
+    previous.is_whitespace() || matches!(previous, '[' | '{' | ',' | ':' | '-')
+}
+
 /// Parsed front matter from a markdown file
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FrontMatter {
@@ -392,7 +788,8 @@ pub struct FrontMatter {
     pub data: serde_yaml::Value,
 }
 
-/// Safely extract and parse YAML front matter from markdown content
+/// Safely extract and parse YAML front matter from markdown content. Error
+/// positions are lines in `content`, not in the front matter.
 pub fn safe_parse_front_matter(content: &str) -> Result<Option<FrontMatter>, ImportError> {
     // Check if content starts with ---
     if !content.starts_with("---") {
@@ -428,7 +825,16 @@ pub fn safe_parse_front_matter(content: &str) -> Result<Option<FrontMatter>, Imp
         return Ok(None);
     }
 
-    let data = safe_parse_yaml(yaml_content)?;
+    // The YAML starts on the line after the opening ---, unless the line
+    // didn't end there
+    let offset = usize::from(rest.starts_with('\n'));
+    let data = safe_parse_yaml(yaml_content).map_err(|e| match e {
+        ImportError::YamlParse(mut error) => {
+            error.line = error.line.map(|line| line + offset);
+            ImportError::YamlParse(error)
+        }
+        e => e,
+    })?;
 
     Ok(Some(FrontMatter {
         raw: yaml_content.to_string(),
@@ -722,6 +1128,94 @@ mod tests {
         assert!(sanitize_relative_path("./.").is_err());
     }
 
+    // ============================================
+    // Content sniffing tests
+    // ============================================
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    #[test]
+    fn test_sniff_content() {
+        assert_eq!(sniff_content(PNG), SniffedType::Png);
+        assert_eq!(sniff_content(&[0xFF, 0xD8, 0xFF, 0xE0]), SniffedType::Jpeg);
+        assert_eq!(sniff_content(b"RIFF\0\0\0\0WEBPVP8 "), SniffedType::Webp);
+        assert_eq!(sniff_content(b"\0\0\0\x18ftypmp42"), SniffedType::Mp4);
+        assert_eq!(sniff_content(b"%PDF-1.7"), SniffedType::Pdf);
+        assert_eq!(sniff_content(b"MZ\x90\0"), SniffedType::Executable);
+        assert_eq!(sniff_content(b"\x7fELF\x02"), SniffedType::Executable);
+        assert_eq!(
+            sniff_content(b"<?xml version=\"1.0\"?>\n<!-- icon -->\n<svg xmlns=\"\">"),
+            SniffedType::Svg
+        );
+        assert_eq!(
+            sniff_content(b"\n  <!DOCTYPE HTML><html>"),
+            SniffedType::Html
+        );
+        assert_eq!(sniff_content(b"hello"), SniffedType::Unknown);
+    }
+
+    #[test]
+    fn test_verify_content() {
+        assert!(verify_content("photo.png", PNG).is_ok());
+        // A misnamed but harmless file is let through
+        assert!(verify_content("photo.jpg", PNG).is_ok());
+        assert!(verify_content("empty.png", b"").is_ok());
+        assert!(verify_content("notes.txt", b"hello").is_ok());
+
+        let blocked = verify_content("photo.png", b"MZ\x90\0").unwrap_err();
+        assert!(blocked.contains("a program"));
+        assert!(verify_content("logo.svg", b"<html><script>").is_err());
+        assert!(verify_content("report.pdf", b"<script>alert(1)</script>").is_err());
+        assert!(verify_content("photo.png", b"hello").is_err());
+        assert!(verify_content("clip.mov", b"#!/bin/sh\nrm -rf ~").is_err());
+    }
+
+    // ============================================
+    // SourceGuard tests
+    // ============================================
+
+    #[cfg(unix)]
+    #[test]
+    fn test_source_guard_links() {
+        use std::os::unix::fs::symlink;
+
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("vault");
+        fs::create_dir_all(source.join("notes")).unwrap();
+        fs::write(source.join("notes/a.md"), "# A").unwrap();
+        fs::write(temp.path().join("secret.txt"), "outside").unwrap();
+        symlink(source.join("notes/a.md"), source.join("alias.md")).unwrap();
+        symlink(temp.path().join("secret.txt"), source.join("secret.md")).unwrap();
+        symlink(source.join("notes"), source.join("linked")).unwrap();
+        symlink(source.join("gone.md"), source.join("broken.md")).unwrap();
+
+        let mut guard = SourceGuard::new(&source);
+        let check = |guard: &mut SourceGuard, name: &str| guard.check(&source.join(name)).unwrap();
+        assert_eq!(check(&mut guard, "notes/a.md"), SourceFileCheck::Allowed);
+        assert_eq!(check(&mut guard, "alias.md"), SourceFileCheck::Allowed);
+        for name in ["secret.md", "linked", "broken.md"] {
+            assert!(matches!(
+                check(&mut guard, name),
+                SourceFileCheck::Blocked(_)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_source_guard_caps_file_count() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = temp.path().join("a.md");
+        fs::write(&file, "# A").unwrap();
+
+        let mut guard = SourceGuard::new(temp.path());
+        guard.files = ImportConfig::MAX_SOURCE_FILES - 1;
+        assert!(guard.check(&file).is_ok());
+        assert!(matches!(
+            guard.check(&file),
+            Err(ImportError::SourceTooLarge(_))
+        ));
+    }
+
     // ============================================
     // is_path_safe tests
     // ============================================
@@ -843,6 +1337,41 @@ mod tests {
         assert!(result.is_null());
     }
 
+    #[test]
+    fn test_safe_parse_yaml_alias_bomb() {
+        let mut yaml = String::from("a: &a [x, x, x, x, x, x, x, x, x, x]\n");
+        for level in 1..12 {
+            let alias = format!("*l{}", level - 1).replace("*l0", "*a");
+            yaml.push_str(&format!(
+                "l{}: &l{} [{}]\n",
+                level,
+                level,
+                vec![alias; 10].join(", ")
+            ));
+        }
+        let error = match safe_parse_yaml(&yaml) {
+            Err(ImportError::YamlParse(error)) => error,
+            other => panic!("expected a YAML error, got {:?}", other),
+        };
+        assert_eq!(error.kind, YamlErrorKind::TooManyAliases);
+        assert_eq!(error.line, Some(12));
+
+        // A few aliases, and asterisks that aren't aliases, are fine
+        let yaml = "base: &base {a: 1}\nfirst: *base\nnote: \"*not an alias*\"\nglob: src/*.rs";
+        assert!(safe_parse_yaml(yaml).is_ok());
+    }
+
+    #[test]
+    fn test_safe_parse_yaml_syntax_error_position() {
+        let error = match safe_parse_yaml("title: ok\ntags: [unclosed\n") {
+            Err(ImportError::YamlParse(error)) => error,
+            other => panic!("expected a YAML error, got {:?}", other),
+        };
+        assert_eq!(error.kind, YamlErrorKind::Syntax);
+        assert!(error.line.is_some() && error.column.is_some());
+        assert!(!error.message.contains(" at line "));
+    }
+
     #[test]
     fn test_safe_parse_yaml_sequence_depth() {
         // Test that sequences also count toward depth
@@ -864,6 +1393,19 @@ mod tests {
         assert!(fm.data["title"].as_str() == Some("Hello"));
     }
 
+    #[test]
+    fn test_safe_parse_front_matter_error_line() {
+        let content = "---\ntitle: Hello\nbad: *\n---\n";
+        let yaml_line = match safe_parse_yaml("title: Hello\nbad: *\n") {
+            Err(ImportError::YamlParse(error)) => error.line.unwrap(),
+            other => panic!("expected a YAML error, got {:?}", other),
+        };
+        match safe_parse_front_matter(content) {
+            Err(ImportError::YamlParse(error)) => assert_eq!(error.line, Some(yaml_line + 1)),
+            other => panic!("expected a YAML error, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_safe_parse_front_matter_none() {
         let content = "# No front matter\n\nJust content";
@@ -1,9 +1,9 @@
-// Import service for Obsidian and Notion
+// Import service for Obsidian, Notion and generic Markdown folders
 // Provides vault/export analysis and import with content conversion
 
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -13,10 +13,13 @@ use walkdir::WalkDir;
 
 use super::error::ImportError;
 use super::import_security::{
-    safe_parse_front_matter, sanitize_csv_cell, sanitize_relative_path, AllowedExtension,
-    ImportConfig,
+    safe_parse_front_matter, sanitize_csv_cell, sanitize_relative_path, verify_file_content,
+    AllowedExtension, ImportConfig, SourceFileCheck, SourceGuard, YamlError,
 };
 use super::import_transaction::ImportTransaction;
+use super::link_graph::resolve_href;
+use super::link_rewrite::relative_path;
+use super::table_document::{TableDocument, TABLE_EXTENSION};
 
 /// Type of import source
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -49,6 +52,9 @@ pub struct ImportFileInfo {
     pub has_front_matter: bool,
     pub has_callouts: bool,
     pub has_dataview: bool,
+    /// Why the file's front matter couldn't be read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub front_matter_error: Option<YamlError>,
 }
 
 /// Warning about file access during analysis
@@ -79,6 +85,10 @@ pub struct ImportAnalysis {
     pub empty_pages: Vec<String>,
     pub files_to_import: Vec<ImportFileInfo>,
     pub access_warnings: Vec<AccessWarning>,
+    /// Files imported before and unchanged since, by relative path. They
+    /// still count when resolving links but aren't imported again.
+    #[serde(default, skip_serializing_if = "HashSet::is_empty")]
+    pub unchanged_files: HashSet<String>,
 }
 
 /// Import options
@@ -92,6 +102,10 @@ pub struct ImportOptions {
     pub preserve_folder_structure: bool,
     pub skip_empty_pages: bool,
     pub create_midlight_files: bool,
+    /// Download images linked from the web into the destination's image
+    /// store and point the links at the stored copies
+    #[serde(default)]
+    pub fetch_remote_images: bool,
 }
 
 impl Default for ImportOptions {
@@ -104,6 +118,7 @@ impl Default for ImportOptions {
             preserve_folder_structure: true,
             skip_empty_pages: true,
             create_midlight_files: true,
+            fetch_remote_images: false,
         }
     }
 }
@@ -125,6 +140,10 @@ pub struct NotionImportOptions {
     pub base: ImportOptions,
     pub remove_uuids: bool,
     pub convert_csv_to_tables: bool,
+    /// Import databases as sortable table documents rather than Markdown
+    /// tables. Only applies when `convert_csv_to_tables` is set.
+    #[serde(default)]
+    pub csv_as_table_documents: bool,
     pub untitled_handling: UntitledHandling,
 }
 
@@ -134,6 +153,7 @@ impl Default for NotionImportOptions {
             base: ImportOptions::default(),
             remove_uuids: true,
             convert_csv_to_tables: true,
+            csv_as_table_documents: false,
             untitled_handling: UntitledHandling::Number,
         }
     }
@@ -150,6 +170,18 @@ pub enum ImportPhase {
     Complete,
 }
 
+impl ImportPhase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportPhase::Analyzing => "analyzing",
+            ImportPhase::Converting => "converting",
+            ImportPhase::Copying => "copying",
+            ImportPhase::Finalizing => "finalizing",
+            ImportPhase::Complete => "complete",
+        }
+    }
+}
+
 /// Import error details
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -188,6 +220,53 @@ pub struct ImportResult {
     pub attachments_copied: usize,
     pub errors: Vec<ImportErrorInfo>,
     pub warnings: Vec<ImportWarningInfo>,
+    /// What each imported file became, for the import manifest
+    #[serde(skip)]
+    pub imported: Vec<ImportedFile>,
+    /// Where the import manifest was written
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest_path: Option<String>,
+}
+
+/// Conversion applied to a file while importing it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportTransform {
+    WikiLinks,
+    Callouts,
+    Dataview,
+    NotionIds,
+    CsvTable,
+    TableDocument,
+    RelativeLinks,
+    Footnotes,
+    Lists,
+    RemoteImages,
+}
+
+/// A source file and where it was imported to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedFile {
+    /// Relative to the import source
+    pub source: String,
+    /// Relative to the import destination, "/"-separated
+    pub destination: String,
+    pub transforms: Vec<ImportTransform>,
+}
+
+impl ImportedFile {
+    fn new(
+        file_info: &ImportFileInfo,
+        destination: &Path,
+        transforms: Vec<ImportTransform>,
+    ) -> Self {
+        Self {
+            source: file_info.relative_path.clone(),
+            destination: destination.to_string_lossy().replace('\\', "/"),
+            transforms,
+        }
+    }
 }
 
 /// Broken link found during import
@@ -246,14 +325,32 @@ pub fn analyze_obsidian_vault(vault_path: &Path) -> Result<ImportAnalysis, Impor
             vault_path
         )));
     }
+    analyze_markdown_folder(vault_path, ImportSourceType::Obsidian)
+}
+
+/// Analyze a folder of Markdown files that didn't come from a known app
+pub fn analyze_generic_folder(folder_path: &Path) -> Result<ImportAnalysis, ImportError> {
+    if !folder_path.exists() {
+        return Err(ImportError::FileNotFound(format!(
+            "Folder not found: {:?}",
+            folder_path
+        )));
+    }
+    analyze_markdown_folder(folder_path, ImportSourceType::Generic)
+}
 
+/// Walk a folder of Markdown files and attachments, skipping hidden entries
+fn analyze_markdown_folder(
+    vault_path: &Path,
+    source_type: ImportSourceType,
+) -> Result<ImportAnalysis, ImportError> {
     let wiki_link_pattern = Regex::new(r"\[\[([^\]]+)\]\]").expect("Invalid wiki link regex");
     let callout_pattern = Regex::new(r"(?m)^>\s*\[!(\w+)\]").expect("Invalid callout regex");
     let dataview_pattern =
         Regex::new(r"```(?:dataview|dataviewjs)[\s\S]*?```").expect("Invalid dataview regex");
 
     let mut analysis = ImportAnalysis {
-        source_type: ImportSourceType::Obsidian,
+        source_type,
         source_path: vault_path.to_string_lossy().to_string(),
         total_files: 0,
         markdown_files: 0,
@@ -269,9 +366,11 @@ pub fn analyze_obsidian_vault(vault_path: &Path) -> Result<ImportAnalysis, Impor
         empty_pages: Vec::new(),
         files_to_import: Vec::new(),
         access_warnings: Vec::new(),
+        unchanged_files: HashSet::new(),
     };
 
     let mut folder_set = std::collections::HashSet::new();
+    let mut guard = SourceGuard::new(vault_path);
 
     for entry in WalkDir::new(vault_path) {
         let entry = match entry {
@@ -312,9 +411,16 @@ pub fn analyze_obsidian_vault(vault_path: &Path) -> Result<ImportAnalysis, Impor
         }
 
         let relative_path = rel_path.to_string_lossy().to_string();
+        if let SourceFileCheck::Blocked(message) = guard.check(path)? {
+            analysis.access_warnings.push(AccessWarning {
+                path: relative_path,
+                message,
+            });
+            continue;
+        }
 
         let file_name = entry.file_name().to_string_lossy().to_string();
-        let metadata = match entry.metadata() {
+        let metadata = match fs::metadata(path) {
             Ok(m) => m,
             Err(err) => {
                 analysis.access_warnings.push(AccessWarning {
@@ -349,6 +455,7 @@ pub fn analyze_obsidian_vault(vault_path: &Path) -> Result<ImportAnalysis, Impor
             has_front_matter: false,
             has_callouts: false,
             has_dataview: false,
+            front_matter_error: None,
         };
 
         match file_type {
@@ -382,9 +489,15 @@ pub fn analyze_obsidian_vault(vault_path: &Path) -> Result<ImportAnalysis, Impor
                             }
 
                             // Check for front matter
-                            if safe_parse_front_matter(&content).ok().flatten().is_some() {
-                                analysis.front_matter += 1;
-                                file_info.has_front_matter = true;
+                            match safe_parse_front_matter(&content) {
+                                Ok(Some(_)) => {
+                                    analysis.front_matter += 1;
+                                    file_info.has_front_matter = true;
+                                }
+                                Err(ImportError::YamlParse(error)) => {
+                                    file_info.front_matter_error = Some(error);
+                                }
+                                _ => {}
                             }
 
                             // Count callouts
@@ -456,9 +569,11 @@ pub fn analyze_notion_export(export_path: &Path) -> Result<ImportAnalysis, Impor
         empty_pages: Vec::new(),
         files_to_import: Vec::new(),
         access_warnings: Vec::new(),
+        unchanged_files: HashSet::new(),
     };
 
     let mut folder_set = std::collections::HashSet::new();
+    let mut guard = SourceGuard::new(export_path);
 
     for entry in WalkDir::new(export_path) {
         let entry = match entry {
@@ -489,9 +604,16 @@ pub fn analyze_notion_export(export_path: &Path) -> Result<ImportAnalysis, Impor
             Ok(p) => p.to_string_lossy().to_string(),
             Err(_) => continue,
         };
+        if let SourceFileCheck::Blocked(message) = guard.check(path)? {
+            analysis.access_warnings.push(AccessWarning {
+                path: relative_path,
+                message,
+            });
+            continue;
+        }
 
         let file_name = entry.file_name().to_string_lossy().to_string();
-        let metadata = match entry.metadata() {
+        let metadata = match fs::metadata(path) {
             Ok(m) => m,
             Err(err) => {
                 analysis.access_warnings.push(AccessWarning {
@@ -532,6 +654,7 @@ pub fn analyze_notion_export(export_path: &Path) -> Result<ImportAnalysis, Impor
             has_front_matter: false,
             has_callouts: false,
             has_dataview: false,
+            front_matter_error: None,
         };
 
         match file_type {
@@ -802,6 +925,46 @@ impl Default for CancellationToken {
 /// Progress callback type
 pub type ProgressCallback = Box<dyn Fn(ImportProgress) + Send + Sync>;
 
+/// The analysis without the files that fail the source checks, and a warning
+/// for each. The analysis comes back from the frontend, so links are checked
+/// again rather than trusted. Attachments must also contain what their
+/// extension says.
+fn checked_source(
+    analysis: &ImportAnalysis,
+) -> Result<(ImportAnalysis, Vec<ImportWarningInfo>), ImportError> {
+    let mut guard = SourceGuard::new(Path::new(&analysis.source_path));
+    let mut files = Vec::with_capacity(analysis.files_to_import.len());
+    let mut warnings = Vec::new();
+    for file in &analysis.files_to_import {
+        let path = Path::new(&file.source_path);
+        let check = match guard.check(path)? {
+            SourceFileCheck::Allowed if file.file_type == ImportFileType::Attachment => {
+                match verify_file_content(path) {
+                    Ok(()) => SourceFileCheck::Allowed,
+                    // Unreadable files are reported when copying fails
+                    Err(_) if !path.exists() => SourceFileCheck::Allowed,
+                    Err(message) => SourceFileCheck::Blocked(message),
+                }
+            }
+            check => check,
+        };
+        match check {
+            SourceFileCheck::Allowed => files.push(file.clone()),
+            SourceFileCheck::Blocked(message) => warnings.push(ImportWarningInfo {
+                file: file.relative_path.clone(),
+                message,
+            }),
+        }
+    }
+    Ok((
+        ImportAnalysis {
+            files_to_import: files,
+            ..analysis.clone()
+        },
+        warnings,
+    ))
+}
+
 /// Import an Obsidian vault
 pub fn import_obsidian_vault(
     analysis: &ImportAnalysis,
@@ -811,6 +974,8 @@ pub fn import_obsidian_vault(
     cancel_token: Option<Arc<CancellationToken>>,
 ) -> Result<ImportResult, ImportError> {
     let _source_path = PathBuf::from(&analysis.source_path);
+    let (analysis, mut warnings) = checked_source(analysis)?;
+    let analysis = &analysis;
     let mut transaction = ImportTransaction::new(dest_path.to_path_buf())?;
 
     let file_map = build_file_map(&analysis.files_to_import);
@@ -820,7 +985,7 @@ pub fn import_obsidian_vault(
     let mut links_converted = 0;
     let mut attachments_copied = 0;
     let mut errors = Vec::new();
-    let mut warnings = Vec::new();
+    let mut imported = Vec::new();
 
     let mut last_progress_time = Instant::now();
 
@@ -865,6 +1030,10 @@ pub fn import_obsidian_vault(
             last_progress_time = Instant::now();
         }
 
+        if analysis.unchanged_files.contains(&file_info.relative_path) {
+            continue;
+        }
+
         // Determine destination path
         let dest_relative = if options.preserve_folder_structure {
             file_info.relative_path.clone()
@@ -905,6 +1074,7 @@ pub fn import_obsidian_vault(
                 };
 
                 let mut converted = content;
+                let mut transforms = Vec::new();
 
                 // Convert wiki links
                 if options.convert_wiki_links && file_info.has_wiki_links {
@@ -912,6 +1082,7 @@ pub fn import_obsidian_vault(
                         convert_wiki_links(&converted, &file_map, &file_info.relative_path);
                     converted = new_content;
                     links_converted += count;
+                    transforms.push(ImportTransform::WikiLinks);
 
                     for link in broken {
                         warnings.push(ImportWarningInfo {
@@ -924,11 +1095,13 @@ pub fn import_obsidian_vault(
                 // Convert callouts
                 if options.convert_callouts && file_info.has_callouts {
                     converted = convert_callouts(&converted);
+                    transforms.push(ImportTransform::Callouts);
                 }
 
                 // Remove dataview
                 if file_info.has_dataview {
                     converted = remove_dataview(&converted);
+                    transforms.push(ImportTransform::Dataview);
                 }
 
                 // Stage the file
@@ -940,6 +1113,11 @@ pub fn import_obsidian_vault(
                     continue;
                 }
 
+                imported.push(ImportedFile::new(
+                    file_info,
+                    &dest_relative_path,
+                    transforms,
+                ));
                 files_imported += 1;
             }
             ImportFileType::Attachment => {
@@ -958,6 +1136,11 @@ pub fn import_obsidian_vault(
                     continue;
                 }
 
+                imported.push(ImportedFile::new(
+                    file_info,
+                    &dest_relative_path,
+                    Vec::new(),
+                ));
                 attachments_copied += 1;
             }
             ImportFileType::Other => {
@@ -996,6 +1179,8 @@ pub fn import_obsidian_vault(
         attachments_copied,
         errors,
         warnings,
+        imported,
+        manifest_path: None,
     })
 }
 
@@ -1007,6 +1192,8 @@ pub fn import_notion_export(
     progress_callback: Option<ProgressCallback>,
     cancel_token: Option<Arc<CancellationToken>>,
 ) -> Result<ImportResult, ImportError> {
+    let (analysis, mut warnings) = checked_source(analysis)?;
+    let analysis = &analysis;
     let mut transaction = ImportTransaction::new(dest_path.to_path_buf())?;
 
     let total_files = analysis.files_to_import.len();
@@ -1015,7 +1202,7 @@ pub fn import_notion_export(
     let mut links_converted = 0;
     let mut attachments_copied = 0;
     let mut errors = Vec::new();
-    let mut warnings = Vec::new();
+    let mut imported = Vec::new();
 
     // Build filename map for link updates (UUID -> clean name)
     let mut filename_map: HashMap<String, String> = HashMap::new();
@@ -1070,6 +1257,10 @@ pub fn import_notion_export(
             last_progress_time = Instant::now();
         }
 
+        if analysis.unchanged_files.contains(&file_info.relative_path) {
+            continue;
+        }
+
         // Determine destination path
         let dest_name = if options.remove_uuids {
             filename_map
@@ -1126,6 +1317,10 @@ pub fn import_notion_export(
                 };
 
                 let mut converted = content;
+                let mut transforms = Vec::new();
+                if dest_name != file_info.name {
+                    transforms.push(ImportTransform::NotionIds);
+                }
 
                 // Update links if UUIDs are being removed
                 if options.remove_uuids && !filename_map.is_empty() {
@@ -1151,6 +1346,11 @@ pub fn import_notion_export(
                     continue;
                 }
 
+                imported.push(ImportedFile::new(
+                    file_info,
+                    &dest_relative_path,
+                    transforms,
+                ));
                 files_imported += 1;
             }
             ImportFileType::Attachment => {
@@ -1168,6 +1368,11 @@ pub fn import_notion_export(
                     continue;
                 }
 
+                imported.push(ImportedFile::new(
+                    file_info,
+                    &dest_relative_path,
+                    Vec::new(),
+                ));
                 attachments_copied += 1;
             }
             ImportFileType::Other => {
@@ -1185,10 +1390,26 @@ pub fn import_notion_export(
                         }
                     };
 
-                    match csv_to_markdown_table(&content) {
+                    let (extension, transform, converted) = if options.csv_as_table_documents {
+                        let title = Path::new(&dest_name)
+                            .file_stem()
+                            .map(|s| s.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        let table = TableDocument::from_csv(&title, &content)
+                            .and_then(|table| table.to_json());
+                        (TABLE_EXTENSION, ImportTransform::TableDocument, table)
+                    } else {
+                        let table = csv_to_markdown_table(&content).map_err(|e| e.to_string());
+                        ("md", ImportTransform::CsvTable, table)
+                    };
+
+                    match converted {
                         Ok(table) => {
-                            // Create markdown file from CSV
-                            let md_name = dest_name.replace(".csv", ".md").replace(".CSV", ".md");
+                            // Create the table's file from CSV
+                            let md_name = Path::new(&dest_name)
+                                .with_extension(extension)
+                                .to_string_lossy()
+                                .to_string();
                             let md_path = if options.base.preserve_folder_structure {
                                 let parent = Path::new(&file_info.relative_path)
                                     .parent()
@@ -1211,6 +1432,11 @@ pub fn import_notion_export(
                                         message: e.to_string(),
                                     });
                                 }
+                                let mut transforms = vec![transform];
+                                if dest_name != file_info.name {
+                                    transforms.push(ImportTransform::NotionIds);
+                                }
+                                imported.push(ImportedFile::new(file_info, &safe_path, transforms));
                                 files_imported += 1;
                             }
                         }
@@ -1254,12 +1480,458 @@ pub fn import_notion_export(
         attachments_copied,
         errors,
         warnings,
+        imported,
+        manifest_path: None,
+    })
+}
+
+// ============================================================================
+// Generic Markdown Import
+// ============================================================================
+
+/// Where each file of a generic import ends up, keyed by its lowercase
+/// source path ("/"-separated). Markdown files are renamed to .md.
+fn generic_destinations(
+    files: &[ImportFileInfo],
+    options: &ImportOptions,
+) -> HashMap<String, String> {
+    files
+        .iter()
+        .filter(|file| file.file_type != ImportFileType::Other)
+        .map(|file| {
+            let source = file.relative_path.replace('\\', "/");
+            let dest = if options.preserve_folder_structure {
+                source.clone()
+            } else {
+                file.name.clone()
+            };
+            let dest = match file.file_type {
+                ImportFileType::Markdown => Path::new(&dest)
+                    .with_extension("md")
+                    .to_string_lossy()
+                    .replace('\\', "/"),
+                _ => dest,
+            };
+            (source.to_lowercase(), dest)
+        })
+        .collect()
+}
+
+/// The destination of a link target, trying the Markdown extensions and a
+/// folder's README or index when the link leaves them out
+fn resolve_destination<'a>(
+    destinations: &'a HashMap<String, String>,
+    target: &str,
+) -> Option<&'a String> {
+    let target = target.to_lowercase();
+    let target = target.trim_end_matches('/');
+    [
+        target.to_string(),
+        format!("{}.md", target),
+        format!("{}.markdown", target),
+        format!("{}/readme.md", target),
+        format!("{}/index.md", target),
+    ]
+    .iter()
+    .find_map(|candidate| destinations.get(candidate))
+}
+
+/// Apply `convert` to the parts of Markdown outside fenced code blocks
+fn map_outside_code(content: &str, mut convert: impl FnMut(&str) -> String) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut prose = String::new();
+    let mut fence: Option<&str> = None;
+
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        match fence {
+            Some(marker) => {
+                out.push_str(line);
+                if trimmed.starts_with(marker) {
+                    fence = None;
+                }
+            }
+            None if trimmed.starts_with("```") || trimmed.starts_with("~~~") => {
+                out.push_str(&convert(&std::mem::take(&mut prose)));
+                out.push_str(line);
+                fence = Some(&trimmed[..3]);
+            }
+            None => prose.push_str(line),
+        }
+    }
+    out.push_str(&convert(&prose));
+    out
+}
+
+/// Point relative Markdown links and images at where their targets were
+/// imported. Links are resolved from `source_file` (percent-encoded,
+/// root-relative with "/", or missing the extension) and rewritten relative
+/// to `dest_file`. External links are left alone.
+///
+/// Returns (converted_content, conversion_count, broken_links)
+pub fn convert_relative_links(
+    content: &str,
+    destinations: &HashMap<String, String>,
+    source_file: &str,
+    dest_file: &str,
+) -> (String, usize, Vec<BrokenLink>) {
+    let inline_pattern =
+        Regex::new(r#"(!?\[[^\]]*\]\(\s*)(<[^>]+>|[^)\s]+)((?:\s+"[^"]*")?\s*\))"#)
+            .expect("Invalid link regex");
+    let reference_pattern = Regex::new(r"(?m)^( {0,3}\[[^\]]+\]:[ \t]*)(<[^>]+>|\S+)")
+        .expect("Invalid reference regex");
+
+    let source_file = source_file.replace('\\', "/");
+    let mut conversion_count = 0;
+    let mut broken_links = Vec::new();
+
+    let mut rewrite = |href: &str| -> Option<String> {
+        let bare = href.trim_start_matches('<').trim_end_matches('>');
+        let target = resolve_href(&source_file, bare)?;
+        let Some(dest) = resolve_destination(destinations, &target) else {
+            broken_links.push(BrokenLink {
+                original: href.to_string(),
+                file: source_file.clone(),
+            });
+            return None;
+        };
+        let suffix = bare.find(['#', '?']).map_or("", |i| &bare[i..]);
+        let rewritten = format!(
+            "{}{}",
+            relative_path(dest_file, dest).replace(' ', "%20"),
+            suffix
+        );
+        if rewritten == bare {
+            return None;
+        }
+        conversion_count += 1;
+        Some(rewritten)
+    };
+
+    let result = map_outside_code(content, |prose| {
+        let prose = inline_pattern.replace_all(prose, |caps: &regex::Captures| {
+            let href = rewrite(&caps[2]).unwrap_or_else(|| caps[2].to_string());
+            format!("{}{}{}", &caps[1], href, &caps[3])
+        });
+        reference_pattern
+            .replace_all(&prose, |caps: &regex::Captures| {
+                let href = rewrite(&caps[2]).unwrap_or_else(|| caps[2].to_string());
+                format!("{}{}", &caps[1], href)
+            })
+            .to_string()
+    });
+
+    (result, conversion_count, broken_links)
+}
+
+/// Turn GitHub-style footnotes into numbered references ("[1]") and a list
+/// of notes at the end, since documents have no footnotes. References
+/// without a definition are left as they are.
+///
+/// Returns (converted_content, footnote_count)
+pub fn convert_footnotes(content: &str) -> (String, usize) {
+    let definition_pattern =
+        Regex::new(r"^ {0,3}\[\^([^\]]+)\]:[ \t]?(.*)$").expect("Invalid footnote regex");
+    let reference_pattern = Regex::new(r"\[\^([^\]]+)\]").expect("Invalid footnote regex");
+
+    // Definitions, with their indented continuation lines
+    let mut definitions: HashMap<String, String> = HashMap::new();
+    let without_definitions = map_outside_code(content, |prose| {
+        let mut kept = String::with_capacity(prose.len());
+        let mut current: Option<String> = None;
+        for line in prose.split_inclusive('\n') {
+            let text = line.trim_end_matches(['\n', '\r']);
+            if let Some(caps) = definition_pattern.captures(text) {
+                definitions.insert(caps[1].to_string(), caps[2].trim().to_string());
+                current = Some(caps[1].to_string());
+                continue;
+            }
+            if let Some(id) = &current {
+                if text.starts_with("    ") || text.starts_with('\t') {
+                    if let Some(note) = definitions.get_mut(id) {
+                        note.push(' ');
+                        note.push_str(text.trim());
+                    }
+                    continue;
+                }
+                current = None;
+            }
+            kept.push_str(line);
+        }
+        kept
+    });
+    if definitions.is_empty() {
+        return (content.to_string(), 0);
+    }
+
+    let mut order: Vec<String> = Vec::new();
+    let mut result = map_outside_code(&without_definitions, |prose| {
+        reference_pattern
+            .replace_all(prose, |caps: &regex::Captures| {
+                let id = &caps[1];
+                if !definitions.contains_key(id) {
+                    return caps[0].to_string();
+                }
+                let number = match order.iter().position(|seen| seen == id) {
+                    Some(index) => index + 1,
+                    None => {
+                        order.push(id.to_string());
+                        order.len()
+                    }
+                };
+                format!("[{}]", number)
+            })
+            .to_string()
+    });
+
+    if !order.is_empty() {
+        result = format!("{}\n\n---\n\n", result.trim_end());
+        for (index, id) in order.iter().enumerate() {
+            result.push_str(&format!("{}. {}\n", index + 1, definitions[id]));
+        }
+    }
+    (result, order.len())
+}
+
+/// Rewrite GitHub-flavored list syntax the editor doesn't read: "+" bullets
+/// become "-" (task list checkboxes included)
+pub fn normalize_lists(content: &str) -> String {
+    let plus_bullet = Regex::new(r"(?m)^([ \t]*)\+ ").expect("Invalid list regex");
+    map_outside_code(content, |prose| {
+        plus_bullet.replace_all(prose, "$1- ").to_string()
+    })
+}
+
+/// Import a generic folder of Markdown files. Relative links and images are
+/// rewritten to where their targets were imported, wiki links are converted
+/// as for Obsidian, footnotes become numbered notes and tables are kept as
+/// Markdown. Attachments in nested folders are copied alongside.
+pub fn import_generic_folder(
+    analysis: &ImportAnalysis,
+    dest_path: &Path,
+    options: &ImportOptions,
+    progress_callback: Option<ProgressCallback>,
+    cancel_token: Option<Arc<CancellationToken>>,
+) -> Result<ImportResult, ImportError> {
+    let (analysis, mut warnings) = checked_source(analysis)?;
+    let analysis = &analysis;
+    let mut transaction = ImportTransaction::new(dest_path.to_path_buf())?;
+
+    let destinations = generic_destinations(&analysis.files_to_import, options);
+    // Wiki links become root-relative links, which the relative link pass
+    // then points at the imported file
+    let wiki_file_map: HashMap<String, String> = build_file_map(&analysis.files_to_import)
+        .into_iter()
+        .map(|(name, path)| {
+            (
+                name,
+                format!("/{}", path.replace('\\', "/").replace(' ', "%20")),
+            )
+        })
+        .collect();
+    let total_files = analysis.files_to_import.len();
+
+    let mut files_imported = 0;
+    let mut links_converted = 0;
+    let mut attachments_copied = 0;
+    let mut errors = Vec::new();
+    let mut imported = Vec::new();
+
+    let mut last_progress_time = Instant::now();
+
+    let send_progress = |phase: ImportPhase,
+                         current: usize,
+                         current_file: &str,
+                         errors: &[ImportErrorInfo],
+                         warnings: &[ImportWarningInfo]| {
+        if let Some(ref callback) = progress_callback {
+            callback(ImportProgress {
+                phase,
+                current,
+                total: total_files,
+                current_file: current_file.to_string(),
+                errors: errors.to_vec(),
+                warnings: warnings.to_vec(),
+            });
+        }
+    };
+
+    send_progress(ImportPhase::Converting, 0, "", &errors, &warnings);
+
+    for (idx, file_info) in analysis.files_to_import.iter().enumerate() {
+        if let Some(ref token) = cancel_token {
+            if token.is_cancelled() {
+                transaction.rollback()?;
+                return Err(ImportError::Cancelled);
+            }
+        }
+
+        if last_progress_time.elapsed().as_millis() >= ImportConfig::PROGRESS_THROTTLE_MS as u128 {
+            send_progress(
+                ImportPhase::Converting,
+                idx,
+                &file_info.name,
+                &errors,
+                &warnings,
+            );
+            last_progress_time = Instant::now();
+        }
+
+        if analysis.unchanged_files.contains(&file_info.relative_path) {
+            continue;
+        }
+
+        let source_relative = file_info.relative_path.replace('\\', "/");
+        let Some(dest_relative) = destinations.get(&source_relative.to_lowercase()) else {
+            continue;
+        };
+        let dest_relative_path = match sanitize_relative_path(dest_relative) {
+            Ok(p) => p,
+            Err(e) => {
+                errors.push(ImportErrorInfo {
+                    file: file_info.relative_path.clone(),
+                    message: e.to_string(),
+                });
+                continue;
+            }
+        };
+
+        match file_info.file_type {
+            ImportFileType::Markdown => {
+                if options.skip_empty_pages
+                    && analysis.empty_pages.contains(&file_info.relative_path)
+                {
+                    continue;
+                }
+
+                let mut converted = match fs::read_to_string(&file_info.source_path) {
+                    Ok(c) => c,
+                    Err(e) => {
+                        errors.push(ImportErrorInfo {
+                            file: file_info.relative_path.clone(),
+                            message: format!("Could not read file: {}", e),
+                        });
+                        continue;
+                    }
+                };
+
+                let mut broken = Vec::new();
+                let mut transforms = Vec::new();
+                if options.convert_wiki_links && file_info.has_wiki_links {
+                    // Counted when the relative link pass rewrites them
+                    let (new_content, _, wiki_broken) =
+                        convert_wiki_links(&converted, &wiki_file_map, &file_info.relative_path);
+                    converted = new_content;
+                    broken.extend(wiki_broken);
+                    transforms.push(ImportTransform::WikiLinks);
+                }
+
+                let (new_content, count, link_broken) = convert_relative_links(
+                    &converted,
+                    &destinations,
+                    &source_relative,
+                    dest_relative,
+                );
+                converted = new_content;
+                links_converted += count;
+                broken.extend(link_broken);
+                if count > 0 {
+                    transforms.push(ImportTransform::RelativeLinks);
+                }
+
+                let (new_content, footnotes) = convert_footnotes(&converted);
+                if footnotes > 0 {
+                    transforms.push(ImportTransform::Footnotes);
+                }
+                let normalized = normalize_lists(&new_content);
+                if normalized != new_content {
+                    transforms.push(ImportTransform::Lists);
+                }
+                converted = normalized;
+
+                for link in broken {
+                    warnings.push(ImportWarningInfo {
+                        file: link.file,
+                        message: format!("Broken link: {}", link.original),
+                    });
+                }
+
+                if let Err(e) = transaction.stage_file(&dest_relative_path, converted.as_bytes()) {
+                    errors.push(ImportErrorInfo {
+                        file: file_info.relative_path.clone(),
+                        message: e.to_string(),
+                    });
+                    continue;
+                }
+
+                imported.push(ImportedFile::new(
+                    file_info,
+                    &dest_relative_path,
+                    transforms,
+                ));
+                files_imported += 1;
+            }
+            ImportFileType::Attachment => {
+                if !options.copy_attachments {
+                    continue;
+                }
+
+                if let Err(e) =
+                    transaction.stage_copy(Path::new(&file_info.source_path), &dest_relative_path)
+                {
+                    errors.push(ImportErrorInfo {
+                        file: file_info.relative_path.clone(),
+                        message: e.to_string(),
+                    });
+                    continue;
+                }
+
+                imported.push(ImportedFile::new(
+                    file_info,
+                    &dest_relative_path,
+                    Vec::new(),
+                ));
+                attachments_copied += 1;
+            }
+            ImportFileType::Other => {}
+        }
+    }
+
+    if let Some(ref token) = cancel_token {
+        if token.is_cancelled() {
+            transaction.rollback()?;
+            return Err(ImportError::Cancelled);
+        }
+    }
+
+    send_progress(
+        ImportPhase::Finalizing,
+        total_files,
+        "Committing changes...",
+        &errors,
+        &warnings,
+    );
+
+    transaction.commit()?;
+
+    send_progress(ImportPhase::Complete, total_files, "", &errors, &warnings);
+
+    Ok(ImportResult {
+        success: errors.is_empty(),
+        files_imported,
+        links_converted,
+        attachments_copied,
+        errors,
+        warnings,
+        imported,
+        manifest_path: None,
     })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::services::import_security::YamlErrorKind;
     use tempfile::TempDir;
 
     // ============================================================================
@@ -1381,6 +2053,7 @@ mod tests {
             has_front_matter: false,
             has_callouts: true,
             has_dataview: false,
+            front_matter_error: None,
         };
         let json = serde_json::to_string(&info).unwrap();
         assert!(json.contains("\"sourcePath\""));
@@ -1445,6 +2118,8 @@ mod tests {
             attachments_copied: 3,
             errors: vec![],
             warnings: vec![],
+            imported: vec![],
+            manifest_path: None,
         };
         let json = serde_json::to_string(&result).unwrap();
         assert!(json.contains("\"success\":true"));
@@ -1472,6 +2147,7 @@ mod tests {
             empty_pages: vec![],
             files_to_import: vec![],
             access_warnings: vec![],
+            unchanged_files: HashSet::new(),
         };
         let json = serde_json::to_string(&analysis).unwrap();
         assert!(json.contains("\"sourceType\":\"obsidian\""));
@@ -1541,6 +2217,7 @@ mod tests {
             has_front_matter: false,
             has_callouts: false,
             has_dataview: false,
+            front_matter_error: None,
         }];
         let map = build_file_map(&files);
 
@@ -1561,6 +2238,7 @@ mod tests {
                 has_front_matter: false,
                 has_callouts: false,
                 has_dataview: false,
+                front_matter_error: None,
             },
             ImportFileInfo {
                 source_path: "/vault/note2.md".to_string(),
@@ -1572,6 +2250,7 @@ mod tests {
                 has_front_matter: false,
                 has_callouts: false,
                 has_dataview: false,
+                front_matter_error: None,
             },
         ];
         let map = build_file_map(&files);
@@ -1593,6 +2272,7 @@ mod tests {
                 has_front_matter: false,
                 has_callouts: false,
                 has_dataview: false,
+                front_matter_error: None,
             },
             ImportFileInfo {
                 source_path: "/vault/image.png".to_string(),
@@ -1604,6 +2284,7 @@ mod tests {
                 has_front_matter: false,
                 has_callouts: false,
                 has_dataview: false,
+                front_matter_error: None,
             },
         ];
         let map = build_file_map(&files);
@@ -1624,6 +2305,7 @@ mod tests {
             has_front_matter: false,
             has_callouts: false,
             has_dataview: false,
+            front_matter_error: None,
         }];
         let map = build_file_map(&files);
 
@@ -2098,6 +2780,23 @@ mod tests {
         assert!(analysis.files_to_import[0].has_front_matter);
     }
 
+    #[test]
+    fn test_analyze_reports_invalid_front_matter() {
+        let temp = TempDir::new().unwrap();
+        std::fs::write(
+            temp.path().join("note.md"),
+            "---\ntitle: Test\ntags: [a, b\n---\n# Content",
+        )
+        .unwrap();
+
+        let analysis = analyze_generic_folder(temp.path()).unwrap();
+        let file = &analysis.files_to_import[0];
+        assert!(!file.has_front_matter);
+        let error = file.front_matter_error.as_ref().unwrap();
+        assert_eq!(error.kind, YamlErrorKind::Syntax);
+        assert!(error.line.unwrap() >= 3);
+    }
+
     #[test]
     fn test_analyze_obsidian_vault_skips_hidden() {
         let temp = TempDir::new().unwrap();
@@ -2223,6 +2922,43 @@ mod tests {
         assert_eq!(analysis.empty_pages.len(), 1);
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_links_outside_source_are_not_imported() {
+        let outside = TempDir::new().unwrap();
+        let source = TempDir::new().unwrap();
+        let dest = TempDir::new().unwrap();
+        std::fs::write(outside.path().join("secret.md"), "secret").unwrap();
+        std::fs::write(source.path().join("note.md"), "# Note").unwrap();
+        std::os::unix::fs::symlink(
+            outside.path().join("secret.md"),
+            source.path().join("secret.md"),
+        )
+        .unwrap();
+
+        let analysis = analyze_generic_folder(source.path()).unwrap();
+        assert_eq!(analysis.files_to_import.len(), 1);
+        assert_eq!(analysis.access_warnings[0].path, "secret.md");
+
+        // A forged analysis is checked again on import
+        let mut forged = analysis.clone();
+        let mut secret = forged.files_to_import[0].clone();
+        secret.source_path = outside
+            .path()
+            .join("secret.md")
+            .to_string_lossy()
+            .to_string();
+        secret.relative_path = "secret.md".to_string();
+        secret.name = "secret.md".to_string();
+        forged.files_to_import.push(secret);
+        let result =
+            import_generic_folder(&forged, dest.path(), &ImportOptions::default(), None, None)
+                .unwrap();
+        assert_eq!(result.files_imported, 1);
+        assert_eq!(result.warnings[0].file, "secret.md");
+        assert!(!dest.path().join("secret.midlight").exists());
+    }
+
     // ============================================================================
     // Import Execution Tests
     // ============================================================================
@@ -3021,6 +3757,35 @@ mod tests {
         assert!(dest.path().join("Data/table.md").exists());
     }
 
+    #[test]
+    fn test_notion_import_csv_as_table_document() {
+        let source = TempDir::new().unwrap();
+        let dest = TempDir::new().unwrap();
+        std::fs::write(
+            source.path().join("Tasks.csv"),
+            "Name,Done,Estimate\nWrite,Yes,3\nEdit,No,",
+        )
+        .unwrap();
+
+        let analysis = analyze_notion_export(source.path()).unwrap();
+        let options = NotionImportOptions {
+            csv_as_table_documents: true,
+            ..Default::default()
+        };
+
+        let result = import_notion_export(&analysis, dest.path(), &options, None, None).unwrap();
+        assert!(result.success);
+        assert!(!dest.path().join("Tasks.md").exists());
+
+        let table = TableDocument::load(&dest.path().join("Tasks.midtable")).unwrap();
+        assert_eq!(table.title, "Tasks");
+        assert_eq!(table.rows.len(), 2);
+        assert_eq!(
+            table.columns[1].column_type,
+            crate::services::table_document::ColumnType::Checkbox
+        );
+    }
+
     // ============================================================================
     // Progress Callback Tests
     // ============================================================================
@@ -3149,4 +3914,105 @@ mod tests {
         assert_eq!(result.attachments_copied, 0);
         assert!(!dest.path().join("image.png").exists());
     }
+
+    // ============================================================================
+    // Generic Import Tests
+    // ============================================================================
+
+    #[test]
+    fn test_convert_relative_links() {
+        let destinations: HashMap<String, String> = [
+            ("docs/guide.markdown", "docs/guide.md"),
+            ("docs/setup/readme.md", "docs/setup/README.md"),
+            ("docs/assets/diagram.png", "docs/assets/diagram.png"),
+            ("my notes.md", "my notes.md"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        let content =
+            "[Guide](guide.markdown#install) [Setup](setup/) ![Diagram](<assets/diagram.png>)\n\
+                       [Notes](../My%20Notes.md \"title\") [Web](https://example.com) [Top](#top)\n\
+                       [missing](gone.md)\n\n[ref]: ./guide\n\n```\n[code](guide.markdown)\n```\n";
+        let (converted, count, broken) =
+            convert_relative_links(content, &destinations, "docs/index.md", "docs/index.md");
+
+        assert_eq!(
+            converted,
+            "[Guide](guide.md#install) [Setup](setup/README.md) ![Diagram](<assets/diagram.png>)\n\
+             [Notes](../my%20notes.md \"title\") [Web](https://example.com) [Top](#top)\n\
+             [missing](gone.md)\n\n[ref]: guide.md\n\n```\n[code](guide.markdown)\n```\n"
+        );
+        assert_eq!(count, 4);
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].original, "gone.md");
+    }
+
+    #[test]
+    fn test_convert_footnotes() {
+        let content = "First[^a] and second[^note].\n\n[^note]: The second\n    continued.\n[^a]: The first\n\nMore[^a] and [^none].\n";
+        let (converted, count) = convert_footnotes(content);
+        assert_eq!(count, 2);
+        assert_eq!(
+            converted,
+            "First[1] and second[2].\n\n\nMore[1] and [^none].\n\n---\n\n1. The first\n2. The second continued.\n"
+        );
+
+        assert_eq!(convert_footnotes("No notes[^x]").0, "No notes[^x]");
+    }
+
+    #[test]
+    fn test_normalize_lists() {
+        assert_eq!(
+            normalize_lists("+ [ ] Todo\n  + Nested\n1 + 1\n```\n+ code\n```\n"),
+            "- [ ] Todo\n  - Nested\n1 + 1\n```\n+ code\n```\n"
+        );
+    }
+
+    #[test]
+    fn test_import_generic_folder_flattened() {
+        let source = TempDir::new().unwrap();
+        let dest = TempDir::new().unwrap();
+        std::fs::create_dir_all(source.path().join("chapters/images")).unwrap();
+        std::fs::write(
+            source.path().join("README.markdown"),
+            "See [chapter one](chapters/one.md) and [[one|the first]].\n",
+        )
+        .unwrap();
+        std::fs::write(
+            source.path().join("chapters/one.md"),
+            "![map](images/map.png) back to [the start](../README.markdown)\n",
+        )
+        .unwrap();
+        std::fs::write(
+            source.path().join("chapters/images/map.png"),
+            b"\x89PNG\r\n\x1a\n",
+        )
+        .unwrap();
+
+        let analysis = analyze_generic_folder(source.path()).unwrap();
+        assert_eq!(analysis.source_type, ImportSourceType::Generic);
+        let options = ImportOptions {
+            preserve_folder_structure: false,
+            ..ImportOptions::default()
+        };
+
+        let result = import_generic_folder(&analysis, dest.path(), &options, None, None).unwrap();
+        assert!(result.success);
+        assert_eq!(result.files_imported, 2);
+        assert_eq!(result.attachments_copied, 1);
+        assert_eq!(result.links_converted, 4);
+        assert!(result.warnings.is_empty());
+
+        assert_eq!(
+            std::fs::read_to_string(dest.path().join("README.md")).unwrap(),
+            "See [chapter one](one.md) and [the first](one.md).\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dest.path().join("one.md")).unwrap(),
+            "![map](map.png) back to [the start](README.md)\n"
+        );
+        assert!(dest.path().join("map.png").exists());
+    }
 }
@@ -0,0 +1,554 @@
+// Link Graph - Links between documents, for graph views and backlinks
+//
+// The outgoing links of each document are recorded when the workspace manager
+// saves it, along with the file's modification time. Building the graph only
+// re-reads documents whose modification time changed since they were
+// recorded (edited outside the app, or never seen), so the frontend gets
+// nodes and edges without parsing every file.
+//
+// Links are Tiptap link marks and Markdown links with a relative target, and
+// [[wiki links]] resolved by document name. Targets are stored as written
+// (relative paths resolved against the linking document) and matched to
+// documents when the graph is built, so a link starts resolving as soon as
+// its target appears.
+//
+// Each document's #tags are recorded alongside its links.
+//
+// Links and tags are stored in the workspace metadata store
+// (.midlight/metadata.db)
+
+use crate::services::document_chunks::resolve_content;
+use crate::services::metadata_store::{self, DocumentLinks};
+use crate::services::rag_service::document_tags;
+use crate::services::workspace_paths::document_key;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use walkdir::WalkDir;
+
+/// Extensions of documents that appear in the graph
+const DOCUMENT_EXTENSIONS: &[&str] = &["midlight", "md"];
+
+lazy_static! {
+    pub(crate) static ref WIKI_LINK: Regex =
+        Regex::new(r"\[\[([^\]|#]+)(?:#[^\]|]*)?(?:\|[^\]]*)?\]\]").unwrap();
+    pub(crate) static ref MARKDOWN_LINK: Regex =
+        Regex::new(r#"(!?)\[[^\]]*\]\(<?([^)\s>]+)>?(?:\s+"[^"]*")?\)"#).unwrap();
+}
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphNode {
+    /// Workspace-relative path
+    pub id: String,
+    pub title: String,
+    /// Documents this one links to
+    pub links: usize,
+    /// Documents linking to this one
+    pub backlinks: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+}
+
+/// A link whose target isn't a document in the workspace
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnresolvedLink {
+    pub source: String,
+    pub target: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+    pub unresolved: Vec<UnresolvedLink>,
+}
+
+// ============================================================================
+// Link Extraction
+// ============================================================================
+
+/// Links in a .midlight document's Tiptap content
+pub fn midlight_links(document_key: &str, content: &Value) -> BTreeSet<String> {
+    let mut links = BTreeSet::new();
+    collect_midlight_links(document_key, content, &mut links);
+    links
+}
+
+fn collect_midlight_links(document_key: &str, node: &Value, links: &mut BTreeSet<String>) {
+    match node {
+        Value::Object(map) => {
+            if let Some(text) = map.get("text").and_then(|t| t.as_str()) {
+                links.extend(wiki_links(text));
+            }
+            let marks = map.get("marks").and_then(|m| m.as_array());
+            for mark in marks.into_iter().flatten() {
+                if mark.get("type").and_then(|t| t.as_str()) != Some("link") {
+                    continue;
+                }
+                let href = mark.pointer("/attrs/href").and_then(|h| h.as_str());
+                if let Some(target) = href.and_then(|h| resolve_href(document_key, h)) {
+                    links.insert(target);
+                }
+            }
+            for value in map.values() {
+                collect_midlight_links(document_key, value, links);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                collect_midlight_links(document_key, item, links);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Links in a Markdown document
+pub fn markdown_links(document_key: &str, markdown: &str) -> BTreeSet<String> {
+    let mut links: BTreeSet<String> = wiki_links(markdown).collect();
+    for caps in MARKDOWN_LINK.captures_iter(markdown) {
+        // Images aren't links
+        if &caps[1] == "!" {
+            continue;
+        }
+        if let Some(target) = resolve_href(document_key, &caps[2]) {
+            links.insert(target);
+        }
+    }
+    links
+}
+
+/// Wiki links, stored as "[[lowercase name]]"
+fn wiki_links(text: &str) -> impl Iterator<Item = String> + '_ {
+    WIKI_LINK
+        .captures_iter(text)
+        .map(|caps| format!("[[{}]]", caps[1].trim().to_lowercase()))
+}
+
+/// The workspace-relative path a link points at, or None for external links
+/// and links within the same document
+pub(crate) fn resolve_href(document_key: &str, href: &str) -> Option<String> {
+    let href = href.trim();
+    if href.is_empty() || href.starts_with('#') || href.contains(':') {
+        return None;
+    }
+    let path = href.split(['#', '?']).next().unwrap_or_default();
+    let path = percent_decode(path);
+
+    let mut parts: Vec<&str> = match path.strip_prefix('/') {
+        Some(_) => Vec::new(),
+        None => {
+            let mut dir: Vec<&str> = document_key.split('/').collect();
+            dir.pop();
+            dir
+        }
+    };
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                // Outside the workspace
+                parts.pop()?;
+            }
+            part => parts.push(part),
+        }
+    }
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+pub(crate) fn is_document(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| DOCUMENT_EXTENSIONS.contains(&ext))
+}
+
+/// Read a document from disk and extract its links
+/// Links and tags of a document on disk
+fn read_document(path: &Path, key: &str) -> DocumentLinks {
+    let modified = modified_millis(path).unwrap_or_default();
+    let Ok(content) = fs::read_to_string(path) else {
+        return DocumentLinks {
+            modified,
+            ..Default::default()
+        };
+    };
+    if key.ends_with(".md") {
+        return DocumentLinks {
+            modified,
+            links: markdown_links(key, &content),
+            tags: document_tags(&content).into_iter().collect(),
+        };
+    }
+    let (links, tags) = serde_json::from_str::<Value>(&content)
+        .ok()
+        .and_then(|mut doc| {
+            resolve_content(path, &mut doc);
+            let content = doc.get("content")?;
+            Some((midlight_links(key, content), midlight_tags(content)))
+        })
+        .unwrap_or_default();
+    DocumentLinks {
+        modified,
+        links,
+        tags,
+    }
+}
+
+/// Tags in a .midlight document's Tiptap content
+pub fn midlight_tags(content: &Value) -> BTreeSet<String> {
+    document_tags(&content.to_string()).into_iter().collect()
+}
+
+fn modified_millis(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_millis() as u64)
+}
+
+// ============================================================================
+// Link Graph Store
+// ============================================================================
+
+pub struct LinkGraphStore {
+    workspace_root: PathBuf,
+}
+
+impl LinkGraphStore {
+    pub fn new(workspace_root: &Path) -> Self {
+        Self {
+            workspace_root: workspace_root.to_path_buf(),
+        }
+    }
+
+    /// Record the links and tags of a document that was just saved
+    pub fn update_document(
+        &self,
+        key: &str,
+        links: BTreeSet<String>,
+        tags: BTreeSet<String>,
+    ) -> Result<(), String> {
+        let store = metadata_store::open(&self.workspace_root)?;
+        let entry = DocumentLinks {
+            modified: modified_millis(&self.workspace_root.join(key)).unwrap_or_default(),
+            links,
+            tags,
+        };
+        if store.document_links()?.get(key) == Some(&entry) {
+            return Ok(());
+        }
+        store.update_document_links(&BTreeMap::from([(key.to_string(), Some(entry))]))
+    }
+
+    /// The whole graph, bringing changed documents up to date first
+    pub fn graph(&self) -> Result<LinkGraph, String> {
+        let documents = self.refresh()?;
+        let resolver = Resolver::new(documents.keys());
+
+        let mut edges = Vec::new();
+        let mut unresolved = Vec::new();
+        let mut outgoing: HashMap<&str, usize> = HashMap::new();
+        let mut incoming: HashMap<&str, usize> = HashMap::new();
+        for (source, doc) in &documents {
+            let targets: BTreeSet<&str> = doc
+                .links
+                .iter()
+                .filter_map(|link| match resolver.resolve(link) {
+                    Some(target) => Some(target),
+                    None => {
+                        unresolved.push(UnresolvedLink {
+                            source: source.clone(),
+                            target: link.clone(),
+                        });
+                        None
+                    }
+                })
+                .filter(|target| target != source)
+                .collect();
+
+            for target in targets {
+                *outgoing.entry(source).or_default() += 1;
+                *incoming.entry(target).or_default() += 1;
+                edges.push(GraphEdge {
+                    source: source.clone(),
+                    target: target.to_string(),
+                });
+            }
+        }
+
+        let nodes = documents
+            .keys()
+            .map(|id| GraphNode {
+                title: Path::new(id)
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                links: outgoing.get(id.as_str()).copied().unwrap_or(0),
+                backlinks: incoming.get(id.as_str()).copied().unwrap_or(0),
+                id: id.clone(),
+            })
+            .collect();
+
+        Ok(LinkGraph {
+            nodes,
+            edges,
+            unresolved,
+        })
+    }
+
+    /// Documents linking to a document, by workspace-relative path
+    pub fn backlinks(&self, key: &str) -> Result<Vec<String>, String> {
+        let key = document_key(key);
+        Ok(self
+            .graph()?
+            .edges
+            .into_iter()
+            .filter(|edge| edge.target == key)
+            .map(|edge| edge.source)
+            .collect())
+    }
+
+    /// Tags in the workspace, each with the documents that have it
+    pub fn tags(&self) -> Result<BTreeMap<String, Vec<String>>, String> {
+        let mut tags: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (key, doc) in self.refresh()? {
+            for tag in doc.tags {
+                tags.entry(tag).or_default().push(key.clone());
+            }
+        }
+        Ok(tags)
+    }
+
+    /// Re-read documents that changed on disk and drop ones that are gone
+    fn refresh(&self) -> Result<BTreeMap<String, DocumentLinks>, String> {
+        let store = metadata_store::open(&self.workspace_root)?;
+        let mut recorded = store.document_links()?;
+        let mut current = BTreeMap::new();
+        let mut updates = BTreeMap::new();
+
+        let walker = WalkDir::new(&self.workspace_root)
+            .min_depth(1)
+            .into_iter()
+            .filter_entry(|e| !e.file_name().to_string_lossy().starts_with('.'));
+        for entry in walker.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if !entry.file_type().is_file() || !is_document(path) {
+                continue;
+            }
+            let Ok(relative) = path.strip_prefix(&self.workspace_root) else {
+                continue;
+            };
+            let key = document_key(&relative.to_string_lossy());
+            let modified = modified_millis(path).unwrap_or_default();
+
+            let doc = match recorded.remove(&key) {
+                Some(doc) if doc.modified == modified => doc,
+                _ => {
+                    let doc = read_document(path, &key);
+                    updates.insert(key.clone(), Some(doc.clone()));
+                    doc
+                }
+            };
+            current.insert(key, doc);
+        }
+
+        // Whatever is left was deleted
+        updates.extend(recorded.into_keys().map(|key| (key, None)));
+        if !updates.is_empty() {
+            store.update_document_links(&updates)?;
+        }
+        Ok(current)
+    }
+}
+
+/// Matches stored link targets to documents
+pub(crate) struct Resolver<'a> {
+    documents: BTreeSet<&'a str>,
+    /// Lowercase path without extension, and lowercase name, to document.
+    /// .midlight documents win over Markdown ones with the same name.
+    by_name: HashMap<String, &'a str>,
+}
+
+impl<'a> Resolver<'a> {
+    pub(crate) fn new(keys: impl Iterator<Item = &'a String>) -> Self {
+        let mut documents: Vec<&str> = keys.map(String::as_str).collect();
+        // Markdown first so .midlight entries overwrite them
+        documents.sort_by_key(|key| (key.ends_with(".midlight"), *key));
+
+        let mut by_name = HashMap::new();
+        for key in documents.iter().rev() {
+            let without_ext = key.rsplit_once('.').map_or(*key, |(stem, _)| stem);
+            let name = without_ext.rsplit('/').next().unwrap_or(without_ext);
+            by_name.entry(without_ext.to_lowercase()).or_insert(*key);
+            by_name.entry(name.to_lowercase()).or_insert(*key);
+        }
+
+        Self {
+            documents: documents.into_iter().collect(),
+            by_name,
+        }
+    }
+
+    pub(crate) fn resolve(&self, link: &str) -> Option<&'a str> {
+        if let Some(name) = link.strip_prefix("[[").and_then(|l| l.strip_suffix("]]")) {
+            let name = name
+                .strip_suffix(".midlight")
+                .or(name.strip_suffix(".md"))
+                .unwrap_or(name);
+            return self.by_name.get(name).copied();
+        }
+        if let Some(key) = self.documents.get(link) {
+            return Some(key);
+        }
+        // Extensionless links, and Markdown links to documents since converted
+        let without_ext = match link.rsplit_once('.') {
+            Some((stem, "md" | "midlight")) => stem,
+            _ => link,
+        };
+        ["midlight", "md"]
+            .iter()
+            .find_map(|ext| {
+                self.documents
+                    .get(format!("{}.{}", without_ext, ext).as_str())
+            })
+            .copied()
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn midlight(content: Value) -> String {
+        json!({ "version": 1, "content": content }).to_string()
+    }
+
+    fn link_paragraph(text: &str, href: &str) -> Value {
+        json!({ "type": "doc", "content": [{
+            "type": "paragraph",
+            "content": [{
+                "type": "text",
+                "text": text,
+                "marks": [{ "type": "link", "attrs": { "href": href } }]
+            }]
+        }]})
+    }
+
+    #[test]
+    fn test_extract_links() {
+        let content = link_paragraph("See [[Plan|the plan]]", "../other%20notes/b.midlight#top");
+        let links = midlight_links("notes/a.midlight", &content);
+        assert_eq!(
+            links.into_iter().collect::<Vec<_>>(),
+            vec!["[[plan]]", "other notes/b.midlight"]
+        );
+
+        let markdown = "[web](https://example.com) ![img](pic.png) [b](./b.md) [[Idea#h]] \
+                        [up](../../outside.md) [self](#section)";
+        let links = markdown_links("notes/a.md", markdown);
+        assert_eq!(
+            links.into_iter().collect::<Vec<_>>(),
+            vec!["[[idea]]", "notes/b.md"]
+        );
+    }
+
+    #[test]
+    fn test_graph_and_incremental_updates() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("notes")).unwrap();
+        fs::create_dir_all(root.join(".midlight")).unwrap();
+        fs::write(
+            root.join("a.midlight"),
+            midlight(link_paragraph("[[Plan]] and [[missing]]", "notes/b")),
+        )
+        .unwrap();
+        fs::write(root.join("notes/b.md"), "Back to [a](../a.midlight)").unwrap();
+        fs::write(
+            root.join("plan.midlight"),
+            midlight(json!({ "type": "doc" })),
+        )
+        .unwrap();
+        fs::write(root.join("plan.md"), "").unwrap();
+
+        let store = LinkGraphStore::new(root);
+        let graph = store.graph().unwrap();
+        assert_eq!(graph.nodes.len(), 4);
+        let edge = |s: &str, t: &str| GraphEdge {
+            source: s.to_string(),
+            target: t.to_string(),
+        };
+        assert_eq!(
+            graph.edges,
+            vec![
+                edge("a.midlight", "notes/b.md"),
+                edge("a.midlight", "plan.midlight"),
+                edge("notes/b.md", "a.midlight"),
+            ]
+        );
+        assert_eq!(graph.unresolved[0].target, "[[missing]]");
+        let a = graph.nodes.iter().find(|n| n.id == "a.midlight").unwrap();
+        assert_eq!((a.title.as_str(), a.links, a.backlinks), ("a", 2, 1));
+
+        // A save records links without the graph re-reading the file
+        fs::write(root.join("notes/b.md"), "No links now").unwrap();
+        store
+            .update_document(
+                "notes/b.md",
+                markdown_links("notes/b.md", "[p](../plan.md)"),
+                BTreeSet::new(),
+            )
+            .unwrap();
+        assert_eq!(store.backlinks("plan.md").unwrap(), vec!["notes/b.md"]);
+
+        // Deleted documents drop out
+        fs::remove_file(root.join("a.midlight")).unwrap();
+        let graph = store.graph().unwrap();
+        assert_eq!(graph.nodes.len(), 3);
+        assert!(graph.unresolved.is_empty());
+    }
+}
@@ -0,0 +1,695 @@
+// Link Rewriting - Keep internal links working when documents move
+//
+// When a document or folder is renamed or moved through the app, links that
+// point into it from other documents, and relative links inside it that point
+// elsewhere, would silently break. The link graph says which documents link
+// into the moved path, so only those and the moved documents themselves are
+// read.
+//
+// Rewrites are planned before the move, which lets the frontend preview the
+// affected documents, and written once the move succeeds. Link marks in
+// .midlight documents, Markdown links and images, and [[wiki links]] whose
+// target's name changed are updated. Fragments and the style of the original
+// link (root-relative, "./", percent-encoded, extensionless) are kept.
+//
+// Deleting a document plans the opposite: links into the deleted path are
+// removed and their text kept, so nothing points at a missing document.
+
+use crate::services::atomic_write::write_atomic;
+use crate::services::document_chunks::resolve_content;
+use crate::services::document_lock::is_locked_document;
+use crate::services::link_graph::{
+    resolve_href, LinkGraphStore, Resolver, MARKDOWN_LINK, WIKI_LINK,
+};
+use crate::services::workspace_paths::document_key;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkChange {
+    pub from: String,
+    pub to: String,
+}
+
+/// A document whose links a move changes
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkRewrite {
+    /// Workspace-relative path, after the move
+    pub path: String,
+    pub changes: Vec<LinkChange>,
+    /// Rewritten file content
+    #[serde(skip)]
+    content: String,
+}
+
+// ============================================================================
+// Link Rewriter
+// ============================================================================
+
+pub struct LinkRewriter {
+    workspace_root: PathBuf,
+}
+
+impl LinkRewriter {
+    pub fn new(workspace_root: &Path) -> Self {
+        Self {
+            workspace_root: workspace_root.to_path_buf(),
+        }
+    }
+
+    /// The documents whose links change if `from` is moved to `to`. Must run
+    /// before the move, while documents are still at their old paths.
+    pub fn plan(&self, from: &Path, to: &Path) -> Result<Vec<LinkRewrite>, String> {
+        let from = self.key(from)?;
+        let to = self.key(to)?;
+        if from == to {
+            return Ok(Vec::new());
+        }
+
+        let graph = LinkGraphStore::new(&self.workspace_root).graph()?;
+        let documents: Vec<String> = graph.nodes.into_iter().map(|n| n.id).collect();
+        let mut affected: BTreeSet<&str> = graph
+            .edges
+            .iter()
+            .filter(|edge| is_at_or_under(&edge.target, &from))
+            .map(|edge| edge.source.as_str())
+            .collect();
+        affected.extend(
+            documents
+                .iter()
+                .filter(|key| is_at_or_under(key, &from))
+                .map(String::as_str),
+        );
+
+        let mv = Move {
+            from: &from,
+            to: &to,
+            resolver: Resolver::new(documents.iter()),
+        };
+        let mut rewrites = Vec::new();
+        for key in affected {
+            let Ok(content) = fs::read_to_string(self.workspace_root.join(key)) else {
+                continue;
+            };
+            let new_key = mv.moved(key).unwrap_or_else(|| key.to_string());
+            let mut changes = Vec::new();
+            let content = if key.ends_with(".md") {
+                mv.rewrite_markdown(&content, key, &new_key, &mut changes)
+            } else {
+                let Ok(mut doc) = serde_json::from_str::<Value>(&content) else {
+                    continue;
+                };
+                resolve_content(&self.workspace_root.join(key), &mut doc);
+                if let Some(content) = doc.get_mut("content") {
+                    mv.rewrite_midlight(content, key, &new_key, &mut changes);
+                }
+                serde_json::to_string_pretty(&doc)
+                    .map_err(|e| format!("Failed to serialize document: {}", e))?
+            };
+
+            if !changes.is_empty() {
+                rewrites.push(LinkRewrite {
+                    path: new_key,
+                    changes,
+                    content,
+                });
+            }
+        }
+        Ok(rewrites)
+    }
+
+    /// The documents outside `path` whose links into it would be removed,
+    /// keeping the link text, when it's deleted. Locked documents are left
+    /// as they are.
+    pub fn plan_unlink(&self, path: &Path) -> Result<Vec<LinkRewrite>, String> {
+        let target = self.key(path)?;
+        let graph = LinkGraphStore::new(&self.workspace_root).graph()?;
+        let documents: Vec<String> = graph.nodes.into_iter().map(|n| n.id).collect();
+        let sources: BTreeSet<&str> = graph
+            .edges
+            .iter()
+            .filter(|edge| {
+                is_at_or_under(&edge.target, &target) && !is_at_or_under(&edge.source, &target)
+            })
+            .map(|edge| edge.source.as_str())
+            .collect();
+
+        let unlink = Unlink {
+            target: &target,
+            resolver: Resolver::new(documents.iter()),
+        };
+        let mut rewrites = Vec::new();
+        for key in sources {
+            let Ok(content) = fs::read_to_string(self.workspace_root.join(key)) else {
+                continue;
+            };
+            let mut changes = Vec::new();
+            let content = if key.ends_with(".md") {
+                unlink.unlink_markdown(&content, key, &mut changes)
+            } else {
+                let Ok(mut doc) = serde_json::from_str::<Value>(&content) else {
+                    continue;
+                };
+                if is_locked_document(&doc) {
+                    continue;
+                }
+                resolve_content(&self.workspace_root.join(key), &mut doc);
+                if let Some(content) = doc.get_mut("content") {
+                    unlink.unlink_midlight(content, key, &mut changes);
+                }
+                serde_json::to_string_pretty(&doc)
+                    .map_err(|e| format!("Failed to serialize document: {}", e))?
+            };
+
+            if !changes.is_empty() {
+                rewrites.push(LinkRewrite {
+                    path: key.to_string(),
+                    changes,
+                    content,
+                });
+            }
+        }
+        Ok(rewrites)
+    }
+
+    /// Write planned rewrites once the move has happened, returning the
+    /// absolute paths of the updated documents
+    pub fn apply(&self, rewrites: &[LinkRewrite]) -> Result<Vec<PathBuf>, String> {
+        let mut written = Vec::with_capacity(rewrites.len());
+        for rewrite in rewrites {
+            let path = self.workspace_root.join(&rewrite.path);
+            write_atomic(&path, &rewrite.content)
+                .map_err(|e| format!("Failed to update links in {}: {}", rewrite.path, e))?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+
+    fn key(&self, path: &Path) -> Result<String, String> {
+        let relative = if path.is_absolute() {
+            path.strip_prefix(&self.workspace_root)
+                .map_err(|_| format!("{} is not in the workspace", path.display()))?
+        } else {
+            path
+        };
+        let inside = relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)));
+        if !inside || relative.as_os_str().is_empty() {
+            return Err(format!("{} is not in the workspace", path.display()));
+        }
+        Ok(document_key(&relative.to_string_lossy()))
+    }
+}
+
+/// One planned move, with the pre-move documents for resolving links
+struct Move<'a> {
+    from: &'a str,
+    to: &'a str,
+    resolver: Resolver<'a>,
+}
+
+impl Move<'_> {
+    /// Where a path at or under the moved path ends up
+    fn moved(&self, key: &str) -> Option<String> {
+        is_at_or_under(key, self.from).then(|| format!("{}{}", self.to, &key[self.from.len()..]))
+    }
+
+    fn rewrite_midlight(
+        &self,
+        node: &mut Value,
+        old_doc: &str,
+        new_doc: &str,
+        changes: &mut Vec<LinkChange>,
+    ) {
+        match node {
+            Value::Object(map) => {
+                if let Some(Value::String(text)) = map.get_mut("text") {
+                    if let Some(rewritten) = self.rewrite_wiki_links(text, changes) {
+                        *text = rewritten;
+                    }
+                }
+                if let Some(Value::Array(marks)) = map.get_mut("marks") {
+                    for mark in marks {
+                        if mark.get("type").and_then(|t| t.as_str()) != Some("link") {
+                            continue;
+                        }
+                        if let Some(Value::String(href)) = mark.pointer_mut("/attrs/href") {
+                            if let Some(rewritten) = self.rewrite_href(href, old_doc, new_doc) {
+                                changes.push(LinkChange {
+                                    from: std::mem::replace(href, rewritten.clone()),
+                                    to: rewritten,
+                                });
+                            }
+                        }
+                    }
+                }
+                for value in map.values_mut() {
+                    self.rewrite_midlight(value, old_doc, new_doc, changes);
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.rewrite_midlight(item, old_doc, new_doc, changes);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn rewrite_markdown(
+        &self,
+        markdown: &str,
+        old_doc: &str,
+        new_doc: &str,
+        changes: &mut Vec<LinkChange>,
+    ) -> String {
+        let mut out = String::with_capacity(markdown.len());
+        let mut last = 0;
+        for caps in MARKDOWN_LINK.captures_iter(markdown) {
+            let href = caps.get(2).unwrap();
+            if let Some(rewritten) = self.rewrite_href(href.as_str(), old_doc, new_doc) {
+                out.push_str(&markdown[last..href.start()]);
+                out.push_str(&rewritten);
+                last = href.end();
+                changes.push(LinkChange {
+                    from: href.as_str().to_string(),
+                    to: rewritten,
+                });
+            }
+        }
+        out.push_str(&markdown[last..]);
+        self.rewrite_wiki_links(&out, changes).unwrap_or(out)
+    }
+
+    /// The new href for a link written in `old_doc`, now living at `new_doc`,
+    /// or None if it still points at the right place
+    fn rewrite_href(&self, href: &str, old_doc: &str, new_doc: &str) -> Option<String> {
+        let target = resolve_href(old_doc, href)?;
+        let href = href.trim();
+        let suffix = href.find(['#', '?']).map_or("", |i| &href[i..]);
+
+        let new_target = match self.moved(&target) {
+            Some(new_target) => new_target,
+            // An extensionless link to the moved document
+            None if self.resolver.resolve(&target) == Some(self.from) => {
+                strip_extension(self.to).to_string()
+            }
+            None if old_doc == new_doc => return None,
+            None => target,
+        };
+
+        let mut path = match href.strip_prefix('/') {
+            Some(_) => format!("/{}", new_target),
+            None => relative_path(new_doc, &new_target),
+        };
+        if href.starts_with("./") && !path.starts_with("../") {
+            path = format!("./{}", path);
+        }
+        if href.contains('%') {
+            path = path.replace(' ', "%20");
+        }
+
+        let rewritten = format!("{}{}", path, suffix);
+        (rewritten != href).then_some(rewritten)
+    }
+
+    /// Rename [[wiki links]] to the moved document when its name changed
+    fn rewrite_wiki_links(&self, text: &str, changes: &mut Vec<LinkChange>) -> Option<String> {
+        let mut out = String::with_capacity(text.len());
+        let mut last = 0;
+        for caps in WIKI_LINK.captures_iter(text) {
+            let name = caps.get(1).unwrap();
+            let trimmed = name.as_str().trim();
+            let Some(rewritten) = self.rewrite_wiki_name(trimmed) else {
+                continue;
+            };
+            out.push_str(&text[last..name.start()]);
+            out.push_str(&rewritten);
+            last = name.end();
+            changes.push(LinkChange {
+                from: format!("[[{}]]", trimmed),
+                to: format!("[[{}]]", rewritten),
+            });
+        }
+        if last == 0 {
+            return None;
+        }
+        out.push_str(&text[last..]);
+        Some(out)
+    }
+
+    fn rewrite_wiki_name(&self, name: &str) -> Option<String> {
+        let target = self
+            .resolver
+            .resolve(&format!("[[{}]]", name.to_lowercase()))?;
+        let new_target = self.moved(target)?;
+
+        // Keep the link's form: a bare name or a path, with or without extension
+        let written = if name.contains('/') {
+            new_target.as_str()
+        } else {
+            new_target.rsplit('/').next().unwrap_or(&new_target)
+        };
+        let rewritten = if strip_extension(name) == name {
+            strip_extension(written)
+        } else {
+            written
+        };
+        (rewritten.to_lowercase() != name.to_lowercase()).then(|| rewritten.to_string())
+    }
+}
+
+/// One planned delete, for removing links to it
+struct Unlink<'a> {
+    target: &'a str,
+    resolver: Resolver<'a>,
+}
+
+impl Unlink<'_> {
+    fn is_deleted(&self, doc: &str, href: &str) -> bool {
+        resolve_href(doc, href).is_some_and(|target| {
+            is_at_or_under(&target, self.target)
+                || self
+                    .resolver
+                    .resolve(&target)
+                    .is_some_and(|resolved| is_at_or_under(resolved, self.target))
+        })
+    }
+
+    fn unlink_midlight(&self, node: &mut Value, doc: &str, changes: &mut Vec<LinkChange>) {
+        match node {
+            Value::Object(map) => {
+                if let Some(Value::String(text)) = map.get_mut("text") {
+                    if let Some(unlinked) = self.unlink_wiki_links(text, changes) {
+                        *text = unlinked;
+                    }
+                }
+                let text = map
+                    .get("text")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string();
+                let mut unmarked = false;
+                if let Some(Value::Array(marks)) = map.get_mut("marks") {
+                    marks.retain(|mark| {
+                        let href = mark.pointer("/attrs/href").and_then(Value::as_str);
+                        match href {
+                            Some(href)
+                                if mark.get("type").and_then(Value::as_str) == Some("link")
+                                    && self.is_deleted(doc, href) =>
+                            {
+                                changes.push(LinkChange {
+                                    from: href.to_string(),
+                                    to: text.clone(),
+                                });
+                                false
+                            }
+                            _ => true,
+                        }
+                    });
+                    unmarked = marks.is_empty();
+                }
+                if unmarked {
+                    map.remove("marks");
+                }
+                for value in map.values_mut() {
+                    self.unlink_midlight(value, doc, changes);
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.unlink_midlight(item, doc, changes);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn unlink_markdown(&self, markdown: &str, doc: &str, changes: &mut Vec<LinkChange>) -> String {
+        let mut out = String::with_capacity(markdown.len());
+        let mut last = 0;
+        for caps in MARKDOWN_LINK.captures_iter(markdown) {
+            // Images aren't links
+            if &caps[1] == "!" || !self.is_deleted(doc, &caps[2]) {
+                continue;
+            }
+            let link = caps.get(0).unwrap();
+            let label = &link.as_str()[1..link.as_str().find("](").unwrap_or(1)];
+            out.push_str(&markdown[last..link.start()]);
+            out.push_str(label);
+            last = link.end();
+            changes.push(LinkChange {
+                from: caps[2].to_string(),
+                to: label.to_string(),
+            });
+        }
+        out.push_str(&markdown[last..]);
+        self.unlink_wiki_links(&out, changes).unwrap_or(out)
+    }
+
+    /// Replace [[wiki links]] to the deleted path with their alias or name
+    fn unlink_wiki_links(&self, text: &str, changes: &mut Vec<LinkChange>) -> Option<String> {
+        let mut out = String::with_capacity(text.len());
+        let mut last = 0;
+        for caps in WIKI_LINK.captures_iter(text) {
+            let name = caps[1].trim();
+            let deleted = self
+                .resolver
+                .resolve(&format!("[[{}]]", name.to_lowercase()))
+                .is_some_and(|target| is_at_or_under(target, self.target));
+            if !deleted {
+                continue;
+            }
+            let link = caps.get(0).unwrap();
+            let label = link
+                .as_str()
+                .trim_end_matches("]]")
+                .split_once('|')
+                .map_or(name, |(_, alias)| alias.trim());
+            out.push_str(&text[last..link.start()]);
+            out.push_str(label);
+            last = link.end();
+            changes.push(LinkChange {
+                from: link.as_str().to_string(),
+                to: label.to_string(),
+            });
+        }
+        if last == 0 {
+            return None;
+        }
+        out.push_str(&text[last..]);
+        Some(out)
+    }
+}
+
+fn is_at_or_under(path: &str, prefix: &str) -> bool {
+    path == prefix || path.starts_with(&format!("{}/", prefix))
+}
+
+fn strip_extension(path: &str) -> &str {
+    path.strip_suffix(".midlight")
+        .or(path.strip_suffix(".md"))
+        .unwrap_or(path)
+}
+
+/// A relative href from a document to a workspace-relative target
+pub(crate) fn relative_path(document_key: &str, target: &str) -> String {
+    let mut dir: Vec<&str> = document_key.split('/').collect();
+    dir.pop();
+    let target: Vec<&str> = target.split('/').collect();
+
+    let common = dir.iter().zip(&target).take_while(|(a, b)| a == b).count();
+    let mut parts = vec![".."; dir.len() - common];
+    parts.extend(&target[common..]);
+    parts.join("/")
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn midlight(text: &str, href: &str) -> String {
+        json!({ "version": 1, "content": { "type": "doc", "content": [{
+            "type": "paragraph",
+            "content": [{
+                "type": "text",
+                "text": text,
+                "marks": [{ "type": "link", "attrs": { "href": href } }]
+            }]
+        }]}})
+        .to_string()
+    }
+
+    fn href(root: &Path, key: &str) -> String {
+        let content = fs::read_to_string(root.join(key)).unwrap();
+        let doc: Value = serde_json::from_str(&content).unwrap();
+        doc.pointer("/content/content/0/content/0/marks/0/attrs/href")
+            .and_then(|h| h.as_str())
+            .unwrap()
+            .to_string()
+    }
+
+    fn setup() -> TempDir {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join(".midlight")).unwrap();
+        fs::create_dir_all(root.join("notes")).unwrap();
+        fs::write(
+            root.join("index.midlight"),
+            midlight("See [[Plan]]", "notes/plan.midlight#goals"),
+        )
+        .unwrap();
+        fs::write(
+            root.join("notes/plan.midlight"),
+            midlight("Back", "../index.midlight"),
+        )
+        .unwrap();
+        fs::write(
+            root.join("notes/ideas.md"),
+            "[plan](./plan) ![chart](chart.png) [home](/index.midlight) [web](https://x.y)",
+        )
+        .unwrap();
+        fs::write(root.join("other.md"), "[elsewhere](notes/ideas.md)").unwrap();
+        temp
+    }
+
+    #[test]
+    fn test_rename_document() {
+        let temp = setup();
+        let root = temp.path();
+        let rewriter = LinkRewriter::new(root);
+
+        let plan = rewriter
+            .plan(
+                Path::new("notes/plan.midlight"),
+                Path::new("notes/roadmap.midlight"),
+            )
+            .unwrap();
+        let paths: Vec<_> = plan.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(paths, vec!["index.midlight", "notes/ideas.md"]);
+        assert_eq!(
+            plan[0].changes,
+            vec![
+                LinkChange {
+                    from: "[[Plan]]".to_string(),
+                    to: "[[roadmap]]".to_string(),
+                },
+                LinkChange {
+                    from: "notes/plan.midlight#goals".to_string(),
+                    to: "notes/roadmap.midlight#goals".to_string(),
+                },
+            ]
+        );
+
+        fs::rename(
+            root.join("notes/plan.midlight"),
+            root.join("notes/roadmap.midlight"),
+        )
+        .unwrap();
+        rewriter.apply(&plan).unwrap();
+        assert_eq!(href(root, "index.midlight"), "notes/roadmap.midlight#goals");
+        assert!(fs::read_to_string(root.join("index.midlight"))
+            .unwrap()
+            .contains("See [[roadmap]]"));
+        assert!(fs::read_to_string(root.join("notes/ideas.md"))
+            .unwrap()
+            .starts_with("[plan](./roadmap) "));
+    }
+
+    #[test]
+    fn test_move_folder() {
+        let temp = setup();
+        let root = temp.path();
+        let rewriter = LinkRewriter::new(root);
+
+        let plan = rewriter
+            .plan(&root.join("notes"), &root.join("archive/old notes"))
+            .unwrap();
+        fs::create_dir_all(root.join("archive")).unwrap();
+        fs::rename(root.join("notes"), root.join("archive/old notes")).unwrap();
+        rewriter.apply(&plan).unwrap();
+
+        // Links into the folder follow it, wiki links by name still resolve
+        assert_eq!(
+            href(root, "index.midlight"),
+            "archive/old notes/plan.midlight#goals"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("other.md")).unwrap(),
+            "[elsewhere](archive/old notes/ideas.md)"
+        );
+        // Links out of the folder are recomputed, links within it are kept
+        assert_eq!(
+            href(root, "archive/old notes/plan.midlight"),
+            "../../index.midlight"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("archive/old notes/ideas.md")).unwrap(),
+            "[plan](./plan) ![chart](chart.png) [home](/index.midlight) [web](https://x.y)"
+        );
+
+        assert!(rewriter
+            .plan(&root.join("other.md"), Path::new("/elsewhere/other.md"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_unlink_deleted_document() {
+        let temp = setup();
+        let root = temp.path();
+        fs::write(
+            root.join("todo.md"),
+            "Read [[plan|the plan]] and [the list](notes/plan.midlight), then [[ideas]]",
+        )
+        .unwrap();
+        let rewriter = LinkRewriter::new(root);
+
+        let plan = rewriter
+            .plan_unlink(&root.join("notes/plan.midlight"))
+            .unwrap();
+        let paths: Vec<_> = plan.iter().map(|r| r.path.as_str()).collect();
+        // Links from inside the deleted path don't count
+        assert_eq!(paths, vec!["index.midlight", "notes/ideas.md", "todo.md"]);
+        rewriter.apply(&plan).unwrap();
+
+        let index: Value =
+            serde_json::from_str(&fs::read_to_string(root.join("index.midlight")).unwrap())
+                .unwrap();
+        let text = &index["content"]["content"][0]["content"][0];
+        assert_eq!(text["text"], "See Plan");
+        assert!(text.get("marks").is_none());
+        assert!(fs::read_to_string(root.join("notes/ideas.md"))
+            .unwrap()
+            .starts_with("plan ![chart](chart.png) [home](/index.midlight)"));
+        assert_eq!(
+            fs::read_to_string(root.join("todo.md")).unwrap(),
+            "Read the plan and the list, then [[ideas]]"
+        );
+    }
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(relative_path("a.md", "notes/b.md"), "notes/b.md");
+        assert_eq!(relative_path("notes/a.md", "notes/b.md"), "b.md");
+        assert_eq!(
+            relative_path("notes/deep/a.md", "other/b.md"),
+            "../../other/b.md"
+        );
+    }
+}
@@ -0,0 +1,411 @@
+// LLM Response Cache - Content-addressed cache for chat responses
+//
+// Keys are a SHA-256 hash of the normalized request (provider, model, messages
+// and generation parameters), so repeating an identical request returns the
+// stored response instead of consuming quota again. Entries expire after a TTL
+// and the least recently used entries are evicted to stay within size limits.
+//
+// Only deterministic requests (temperature 0) are cached. A sampled request
+// asked again, as when regenerating a reply, is expected to give a different
+// answer, so it always goes to the model.
+
+use crate::services::llm_service::{ChatRequest, ChatResponse};
+use crate::traits::{RealTimeProvider, TimeProvider};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Default time-to-live for cached responses
+pub const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Default maximum number of cached responses
+pub const DEFAULT_MAX_ENTRIES: usize = 200;
+
+/// Default maximum total size of cached responses (10MB)
+pub const DEFAULT_MAX_BYTES: usize = 10 * 1024 * 1024;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    pub ttl: Duration,
+    pub max_entries: usize,
+    pub max_bytes: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl: DEFAULT_TTL,
+            max_entries: DEFAULT_MAX_ENTRIES,
+            max_bytes: DEFAULT_MAX_BYTES,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    pub entries: usize,
+    pub bytes: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+struct CacheEntry {
+    response: ChatResponse,
+    size: usize,
+    created_at: i64,
+    last_accessed: i64,
+}
+
+// ============================================================================
+// Cache Key
+// ============================================================================
+
+/// Compute the content-addressed key for a request.
+///
+/// The `stream` flag is ignored so streaming and non-streaming requests for
+/// the same conversation share an entry.
+pub fn cache_key(request: &ChatRequest) -> String {
+    let mut normalized = request.clone();
+    normalized.stream = None;
+
+    let bytes = serde_json::to_vec(&normalized).unwrap_or_default();
+    let mut hasher = Sha256::new();
+    hasher.update(&bytes);
+    format!("{:x}", hasher.finalize())
+}
+
+// ============================================================================
+// Response Cache
+// ============================================================================
+
+pub struct ResponseCache<T: TimeProvider = RealTimeProvider> {
+    config: CacheConfig,
+    entries: HashMap<String, CacheEntry>,
+    total_bytes: usize,
+    hits: u64,
+    misses: u64,
+    time_provider: Arc<T>,
+}
+
+impl ResponseCache<RealTimeProvider> {
+    pub fn new(config: CacheConfig) -> Self {
+        Self::with_time_provider(config, Arc::new(RealTimeProvider))
+    }
+}
+
+impl Default for ResponseCache<RealTimeProvider> {
+    fn default() -> Self {
+        Self::new(CacheConfig::default())
+    }
+}
+
+impl<T: TimeProvider> ResponseCache<T> {
+    /// Create a cache with a custom time provider (for testing)
+    pub fn with_time_provider(config: CacheConfig, time_provider: Arc<T>) -> Self {
+        Self {
+            config,
+            entries: HashMap::new(),
+            total_bytes: 0,
+            hits: 0,
+            misses: 0,
+            time_provider,
+        }
+    }
+
+    /// Look up a cached response, dropping it if it has expired
+    pub fn get(&mut self, key: &str) -> Option<ChatResponse> {
+        let now = self.time_provider.unix_timestamp_millis();
+        let ttl_ms = self.config.ttl.as_millis() as i64;
+
+        let expired = match self.entries.get(key) {
+            Some(entry) => now - entry.created_at >= ttl_ms,
+            None => {
+                self.misses += 1;
+                return None;
+            }
+        };
+
+        if expired {
+            self.remove(key);
+            self.misses += 1;
+            return None;
+        }
+
+        self.hits += 1;
+        let entry = self.entries.get_mut(key)?;
+        entry.last_accessed = now;
+        Some(entry.response.clone())
+    }
+
+    /// Store a response, evicting old entries to stay within limits
+    pub fn insert(&mut self, key: String, response: ChatResponse) {
+        let size = serde_json::to_vec(&response)
+            .map(|b| b.len())
+            .unwrap_or(response.content.len());
+
+        // Responses larger than the whole cache are never stored
+        if size > self.config.max_bytes || self.config.max_entries == 0 {
+            return;
+        }
+
+        self.remove(&key);
+
+        let now = self.time_provider.unix_timestamp_millis();
+        self.entries.insert(
+            key,
+            CacheEntry {
+                response,
+                size,
+                created_at: now,
+                last_accessed: now,
+            },
+        );
+        self.total_bytes += size;
+
+        self.evict();
+    }
+
+    /// Remove all cached responses
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.total_bytes = 0;
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            entries: self.entries.len(),
+            bytes: self.total_bytes,
+            hits: self.hits,
+            misses: self.misses,
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.total_bytes -= entry.size;
+        }
+    }
+
+    /// Drop expired entries, then least recently used until within limits
+    fn evict(&mut self) {
+        let now = self.time_provider.unix_timestamp_millis();
+        let ttl_ms = self.config.ttl.as_millis() as i64;
+
+        let expired: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| now - entry.created_at >= ttl_ms)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in expired {
+            self.remove(&key);
+        }
+
+        while self.entries.len() > self.config.max_entries
+            || self.total_bytes > self.config.max_bytes
+        {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_accessed)
+                .map(|(key, _)| key.clone());
+
+            match oldest {
+                Some(key) => self.remove(&key),
+                None => break,
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::llm_service::ChatMessage;
+    use crate::traits::MockTimeProvider;
+
+    fn request(content: &str) -> ChatRequest {
+        ChatRequest {
+            provider: "openai".to_string(),
+            model: "gpt-4o-mini".to_string(),
+            messages: vec![ChatMessage {
+                role: "user".to_string(),
+                content: content.to_string(),
+                name: None,
+                tool_call_id: None,
+                tool_calls: None,
+            }],
+            temperature: None,
+            max_tokens: None,
+            top_p: None,
+            stop: None,
+            json_mode: None,
+            stream: None,
+            request_type: None,
+            web_search_enabled: None,
+            bypass_cache: false,
+        }
+    }
+
+    fn response(content: &str) -> ChatResponse {
+        ChatResponse {
+            id: "resp".to_string(),
+            content: content.to_string(),
+            finish_reason: "stop".to_string(),
+            usage: None,
+            tool_calls: None,
+            context: None,
+            routing: None,
+        }
+    }
+
+    fn cache_with(config: CacheConfig) -> (ResponseCache<MockTimeProvider>, MockTimeProvider) {
+        let time = MockTimeProvider::from_timestamp(1_700_000_000);
+        let cache = ResponseCache::with_time_provider(config, Arc::new(time.clone()));
+        (cache, time)
+    }
+
+    #[test]
+    fn test_cache_key_is_stable_and_ignores_stream() {
+        let a = request("Summarize");
+        let mut b = request("Summarize");
+        b.stream = Some(true);
+
+        assert_eq!(cache_key(&a), cache_key(&b));
+        assert_eq!(cache_key(&a).len(), 64);
+    }
+
+    #[test]
+    fn test_cache_key_differs_by_content_and_model() {
+        let a = request("Summarize");
+        let b = request("Summarize again");
+        let mut c = request("Summarize");
+        c.model = "gpt-4o".to_string();
+
+        assert_ne!(cache_key(&a), cache_key(&b));
+        assert_ne!(cache_key(&a), cache_key(&c));
+    }
+
+    #[test]
+    fn test_get_and_insert() {
+        let (mut cache, _time) = cache_with(CacheConfig::default());
+
+        assert!(cache.get("key").is_none());
+        cache.insert("key".to_string(), response("cached"));
+
+        let hit = cache.get("key").unwrap();
+        assert_eq!(hit.content, "cached");
+
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 1);
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 1);
+        assert!(stats.bytes > 0);
+    }
+
+    #[test]
+    fn test_entries_expire_after_ttl() {
+        let (mut cache, time) = cache_with(CacheConfig {
+            ttl: Duration::from_secs(60),
+            ..CacheConfig::default()
+        });
+
+        cache.insert("key".to_string(), response("cached"));
+        time.advance_secs(59);
+        assert!(cache.get("key").is_some());
+
+        time.advance_secs(1);
+        assert!(cache.get("key").is_none());
+        assert_eq!(cache.stats().entries, 0);
+        assert_eq!(cache.stats().bytes, 0);
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used_over_max_entries() {
+        let (mut cache, time) = cache_with(CacheConfig {
+            max_entries: 2,
+            ..CacheConfig::default()
+        });
+
+        cache.insert("a".to_string(), response("a"));
+        time.advance_secs(1);
+        cache.insert("b".to_string(), response("b"));
+        time.advance_secs(1);
+        // Touch "a" so "b" becomes least recently used
+        cache.get("a");
+        time.advance_secs(1);
+        cache.insert("c".to_string(), response("c"));
+
+        assert_eq!(cache.stats().entries, 2);
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("c").is_some());
+    }
+
+    #[test]
+    fn test_evicts_over_max_bytes() {
+        let size = serde_json::to_vec(&response("x")).unwrap().len();
+        let (mut cache, time) = cache_with(CacheConfig {
+            max_bytes: size * 2,
+            ..CacheConfig::default()
+        });
+
+        cache.insert("a".to_string(), response("x"));
+        time.advance_secs(1);
+        cache.insert("b".to_string(), response("y"));
+        time.advance_secs(1);
+        cache.insert("c".to_string(), response("z"));
+
+        assert_eq!(cache.stats().entries, 2);
+        assert!(cache.stats().bytes <= size * 2);
+        assert!(cache.get("a").is_none());
+    }
+
+    #[test]
+    fn test_oversized_response_not_cached() {
+        let (mut cache, _time) = cache_with(CacheConfig {
+            max_bytes: 10,
+            ..CacheConfig::default()
+        });
+
+        cache.insert("key".to_string(), response("this response is too large"));
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[test]
+    fn test_replacing_entry_updates_size() {
+        let (mut cache, _time) = cache_with(CacheConfig::default());
+
+        cache.insert("key".to_string(), response("short"));
+        cache.insert("key".to_string(), response("a much longer response"));
+
+        let expected = serde_json::to_vec(&response("a much longer response"))
+            .unwrap()
+            .len();
+        assert_eq!(cache.stats().entries, 1);
+        assert_eq!(cache.stats().bytes, expected);
+    }
+
+    #[test]
+    fn test_clear() {
+        let (mut cache, _time) = cache_with(CacheConfig::default());
+
+        cache.insert("key".to_string(), response("cached"));
+        cache.clear();
+
+        assert_eq!(cache.stats().entries, 0);
+        assert_eq!(cache.stats().bytes, 0);
+        assert!(cache.get("key").is_none());
+    }
+}
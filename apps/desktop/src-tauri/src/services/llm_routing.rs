@@ -0,0 +1,296 @@
+// LLM Routing - Provider routing rules and failover policy
+//
+// A routing policy is a list of rules matched against each chat request by
+// request type and provider. The first matching rule can redirect the request
+// to a different primary model and lists fallbacks that are tried in order
+// when an attempt fails with a transient error (network failure or timeout,
+// rate limit, quota exhausted, provider outage).
+//
+// Storage: `llm-routing.json` in the app data directory.
+
+use crate::services::atomic_write::write_atomic;
+use crate::services::llm_service::{ChatRequest, LLMError};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tracing::warn;
+
+/// Error codes that trigger failover to the next target
+const FAILOVER_CODES: &[&str] = &[
+    "NETWORK_ERROR",
+    "RATE_LIMITED",
+    "QUOTA_EXCEEDED",
+    "PROVIDER_ERROR",
+];
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RouteTarget {
+    pub provider: String,
+    pub model: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutingRule {
+    /// Only match requests with this request type
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_type: Option<String>,
+    /// Only match requests for this provider
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// Send matching requests here instead of their requested model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub primary: Option<RouteTarget>,
+    /// Tried in order when the previous target fails with a transient error
+    #[serde(default)]
+    pub fallbacks: Vec<RouteTarget>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutingPolicy {
+    #[serde(default)]
+    pub rules: Vec<RoutingRule>,
+}
+
+/// A target that failed before the request was served
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailedAttempt {
+    pub provider: String,
+    pub model: String,
+    pub code: String,
+    pub message: String,
+}
+
+/// Which provider served a response, and which were tried first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutingInfo {
+    pub provider: String,
+    pub model: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failed_attempts: Vec<FailedAttempt>,
+}
+
+impl RoutingInfo {
+    pub fn failed_over(&self) -> bool {
+        !self.failed_attempts.is_empty()
+    }
+}
+
+// ============================================================================
+// Routing
+// ============================================================================
+
+impl RoutingRule {
+    fn matches(&self, request: &ChatRequest) -> bool {
+        let type_matches = self
+            .request_type
+            .as_ref()
+            .map_or(true, |t| request.request_type.as_ref() == Some(t));
+        let provider_matches = self
+            .provider
+            .as_ref()
+            .map_or(true, |p| *p == request.provider);
+        type_matches && provider_matches
+    }
+}
+
+impl RoutingPolicy {
+    /// Load the policy from disk, falling back to no rules
+    pub fn load(path: &Path) -> Self {
+        if !path.exists() {
+            return Self::default();
+        }
+
+        std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| {
+                warn!("Failed to load routing policy, using defaults: {}", e);
+                Self::default()
+            })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize routing policy: {}", e))?;
+        write_atomic(path, json).map_err(|e| format!("Failed to save routing policy: {}", e))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for (index, rule) in self.rules.iter().enumerate() {
+            let targets = rule.primary.iter().chain(&rule.fallbacks);
+            for target in targets {
+                if target.provider.trim().is_empty() || target.model.trim().is_empty() {
+                    return Err(format!(
+                        "Routing rule {} has a target without a provider or model",
+                        index + 1
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Targets to try for a request, in order. Always contains at least the
+    /// primary target; duplicates are skipped.
+    pub fn targets(&self, request: &ChatRequest) -> Vec<RouteTarget> {
+        let requested = RouteTarget {
+            provider: request.provider.clone(),
+            model: request.model.clone(),
+        };
+
+        let Some(rule) = self.rules.iter().find(|rule| rule.matches(request)) else {
+            return vec![requested];
+        };
+
+        let mut targets = vec![rule.primary.clone().unwrap_or(requested)];
+        for fallback in &rule.fallbacks {
+            if !targets.contains(fallback) {
+                targets.push(fallback.clone());
+            }
+        }
+        targets
+    }
+}
+
+/// Whether an error is transient enough to try the next target
+pub fn should_failover(error: &LLMError) -> bool {
+    FAILOVER_CODES.contains(&error.code.as_str())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn target(provider: &str, model: &str) -> RouteTarget {
+        RouteTarget {
+            provider: provider.to_string(),
+            model: model.to_string(),
+        }
+    }
+
+    fn request(provider: &str, model: &str, request_type: Option<&str>) -> ChatRequest {
+        ChatRequest {
+            provider: provider.to_string(),
+            model: model.to_string(),
+            messages: vec![],
+            temperature: None,
+            max_tokens: None,
+            top_p: None,
+            stop: None,
+            json_mode: None,
+            stream: None,
+            request_type: request_type.map(String::from),
+            web_search_enabled: None,
+            bypass_cache: false,
+        }
+    }
+
+    fn error(code: &str) -> LLMError {
+        LLMError {
+            code: code.to_string(),
+            message: String::new(),
+            details: None,
+        }
+    }
+
+    #[test]
+    fn test_no_rules_uses_requested_target() {
+        let policy = RoutingPolicy::default();
+        assert_eq!(
+            policy.targets(&request("openai", "gpt-4o", None)),
+            vec![target("openai", "gpt-4o")]
+        );
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let policy = RoutingPolicy {
+            rules: vec![
+                RoutingRule {
+                    request_type: Some("inline-edit".to_string()),
+                    primary: Some(target("openai", "gpt-4o-mini")),
+                    ..Default::default()
+                },
+                RoutingRule {
+                    provider: Some("openai".to_string()),
+                    fallbacks: vec![
+                        target("anthropic", "claude-sonnet-4"),
+                        target("openai", "gpt-4o"),
+                    ],
+                    ..Default::default()
+                },
+            ],
+        };
+
+        assert_eq!(
+            policy.targets(&request("openai", "gpt-4o", Some("inline-edit"))),
+            vec![target("openai", "gpt-4o-mini")]
+        );
+        // Fallback identical to the primary is skipped
+        assert_eq!(
+            policy.targets(&request("openai", "gpt-4o", Some("chat"))),
+            vec![
+                target("openai", "gpt-4o"),
+                target("anthropic", "claude-sonnet-4")
+            ]
+        );
+        assert_eq!(
+            policy.targets(&request("gemini", "gemini-2.5-pro", None)),
+            vec![target("gemini", "gemini-2.5-pro")]
+        );
+    }
+
+    #[test]
+    fn test_should_failover() {
+        assert!(should_failover(&error("NETWORK_ERROR")));
+        assert!(should_failover(&error("QUOTA_EXCEEDED")));
+        assert!(should_failover(&error("RATE_LIMITED")));
+        assert!(!should_failover(&error("AUTH_REQUIRED")));
+        assert!(!should_failover(&error("INVALID_REQUEST")));
+        assert!(!should_failover(&error("CONTENT_FILTERED")));
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut policy = RoutingPolicy {
+            rules: vec![RoutingRule {
+                fallbacks: vec![target("anthropic", "claude-sonnet-4")],
+                ..Default::default()
+            }],
+        };
+        assert!(policy.validate().is_ok());
+
+        policy.rules[0].fallbacks.push(target("openai", " "));
+        assert!(policy.validate().is_err());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("llm-routing.json");
+        assert_eq!(RoutingPolicy::load(&path), RoutingPolicy::default());
+
+        let policy = RoutingPolicy {
+            rules: vec![RoutingRule {
+                provider: Some("openai".to_string()),
+                fallbacks: vec![target("anthropic", "claude-sonnet-4")],
+                ..Default::default()
+            }],
+        };
+        policy.save(&path).unwrap();
+        assert_eq!(RoutingPolicy::load(&path), policy);
+    }
+}
@@ -1,11 +1,25 @@
 // LLM Service - HTTP client for LLM API communication
 
+use crate::services::app_dirs::app_data_dir;
+use crate::services::context_window::{self, ContextTruncation, SummaryCache};
+use crate::services::generation_params;
+use crate::services::llm_cache::{cache_key, CacheStats, ResponseCache};
+use crate::services::llm_routing::{self, FailedAttempt, RoutingInfo, RoutingPolicy};
+use crate::services::network_config::configure_client;
+use crate::services::quota_tracker::{
+    quota_alerts_path, quota_from_headers, QuotaAlertSettings, QuotaEvent, QuotaListener,
+    QuotaTracker, ReportedQuota,
+};
+use crate::services::structured_output::{self, StructuredResponse};
+use crate::services::token_counter::{count_message_tokens, count_tokens, encoding_for_model};
 use futures::StreamExt;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
 use tokio::sync::mpsc;
-use tracing::{error, warn};
+use tracing::{debug, error, info, warn};
 
 const DEFAULT_BASE_URL: &str = "https://midlight.ai";
 
@@ -84,11 +98,21 @@ pub struct ChatRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_tokens: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+    /// Ask the provider to return a JSON object
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub json_mode: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub request_type: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub web_search_enabled: Option<bool>,
+    /// Skip the response cache for this request (never sent to the backend)
+    #[serde(skip)]
+    pub bypass_cache: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -111,6 +135,12 @@ pub struct ChatResponse {
     pub usage: Option<UsageInfo>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<ToolCall>>,
+    /// Set when older messages were summarized to fit the context window
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<ContextTruncation>,
+    /// Which provider and model served the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routing: Option<RoutingInfo>,
 }
 
 /// StreamChunk is the normalized chunk format sent to the frontend
@@ -144,6 +174,9 @@ struct BackendSSEChunk {
     done: Option<bool>,
     #[serde(default)]
     usage: Option<UsageInfo>,
+    /// Quota after this request, on the done chunk
+    #[serde(default)]
+    quota: Option<ReportedQuota>,
     #[serde(default)]
     error: Option<String>,
 }
@@ -230,6 +263,11 @@ impl std::error::Error for LLMError {}
 pub struct LLMService {
     client: Client,
     base_url: String,
+    cache: Mutex<ResponseCache>,
+    summaries: Mutex<SummaryCache>,
+    routing: RwLock<RoutingPolicy>,
+    quota: QuotaTracker,
+    quota_listener: RwLock<Option<QuotaListener>>,
 }
 
 impl LLMService {
@@ -241,7 +279,7 @@ impl LLMService {
             reqwest::header::HeaderValue::from_static("desktop"),
         );
 
-        let client = Client::builder()
+        let client = configure_client(Client::builder())
             .default_headers(default_headers)
             .timeout(std::time::Duration::from_secs(120))
             .build()
@@ -250,67 +288,171 @@ impl LLMService {
         Self {
             client,
             base_url: base_url.unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
+            cache: Mutex::new(ResponseCache::default()),
+            summaries: Mutex::new(SummaryCache::new()),
+            routing: RwLock::new(RoutingPolicy::default()),
+            quota: QuotaTracker::default(),
+            quota_listener: RwLock::new(None),
         }
     }
 
     /// Create a new LLMService with a custom HTTP client (for testing)
     #[cfg(test)]
     pub fn with_client(base_url: String, client: Client) -> Self {
-        Self { client, base_url }
+        Self {
+            client,
+            base_url,
+            cache: Mutex::new(ResponseCache::default()),
+            summaries: Mutex::new(SummaryCache::new()),
+            routing: RwLock::new(RoutingPolicy::default()),
+            quota: QuotaTracker::default(),
+            quota_listener: RwLock::new(None),
+        }
     }
 
     /// Send a non-streaming chat request
+    /// Identical requests at temperature 0 are served from the response cache
+    /// unless `bypass_cache` is set.
+    /// Conversations too long for the model's context window have their older
+    /// turns summarized first.
     pub async fn chat(
         &self,
-        request: ChatRequest,
+        mut request: ChatRequest,
         auth_token: Option<&str>,
     ) -> Result<ChatResponse, LLMError> {
-        let url = format!("{}/api/llm/chat", self.base_url);
+        generation_params::normalize(&mut request)?;
 
-        let mut req = self.client.post(&url).json(&request);
+        let key = self.lookup_key(&request);
+        if let Some(cached) = key.as_deref().and_then(|k| self.cached_response(k)) {
+            debug!("LLM cache hit for model {}", request.model);
+            return Ok(cached);
+        }
 
-        if let Some(token) = auth_token {
-            req = req.bearer_auth(token);
+        let response = self
+            .route(&request, |mut attempt| async move {
+                let context = self.fit_context(&mut attempt, 0, auth_token).await;
+                let mut response = self.post_chat(&attempt, auth_token).await?;
+                response.context = context;
+                Ok(response)
+            })
+            .await?;
+
+        if let Some(key) = key {
+            self.store_response(key, &response);
         }
 
-        let response = req.send().await.map_err(|e| LLMError {
-            code: "NETWORK_ERROR".to_string(),
-            message: e.to_string(),
+        Ok(response)
+    }
+
+    /// Send a chat request that must return JSON matching `schema`.
+    /// Output is repaired locally where possible; otherwise the model is shown
+    /// the validation errors and asked to fix its output, up to `max_repairs` times.
+    pub async fn chat_structured(
+        &self,
+        mut request: ChatRequest,
+        schema: &serde_json::Value,
+        max_repairs: u32,
+        auth_token: Option<&str>,
+    ) -> Result<StructuredResponse, LLMError> {
+        structured_output::check_schema(schema).map_err(|message| LLMError {
+            code: "INVALID_REQUEST".to_string(),
+            message,
             details: None,
         })?;
 
-        self.handle_response(response).await
+        structured_output::add_schema_instructions(&mut request.messages, schema);
+        request.json_mode = Some(true);
+
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let response = self.chat(request.clone(), auth_token).await?;
+
+            let issues = match structured_output::parse_output(schema, &response.content) {
+                Ok(output) => {
+                    return Ok(StructuredResponse {
+                        data: output.data,
+                        attempts,
+                        repaired: output.repaired || attempts > 1,
+                        usage: response.usage,
+                        routing: response.routing,
+                    })
+                }
+                Err(issues) => issues,
+            };
+
+            if attempts > max_repairs {
+                let summary = issues
+                    .iter()
+                    .map(|issue| issue.to_string())
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                return Err(LLMError {
+                    code: "VALIDATION_FAILED".to_string(),
+                    message: format!("Response did not match the schema: {}", summary),
+                    details: Some(serde_json::json!({
+                        "errors": issues,
+                        "output": response.content,
+                    })),
+                });
+            }
+
+            debug!(
+                "Structured output failed validation ({} issues), asking model to repair",
+                issues.len()
+            );
+            request.messages.push(ChatMessage {
+                role: "assistant".to_string(),
+                content: response.content,
+                name: None,
+                tool_call_id: None,
+                tool_calls: None,
+            });
+            request.messages.push(ChatMessage {
+                role: "user".to_string(),
+                content: structured_output::repair_prompt(&issues),
+                name: None,
+                tool_call_id: None,
+                tool_calls: None,
+            });
+        }
     }
 
     /// Send a streaming chat request, returning chunks via channel
     pub async fn chat_stream(
         &self,
-        request: ChatRequest,
+        mut request: ChatRequest,
         auth_token: Option<&str>,
         tx: mpsc::Sender<StreamChunk>,
     ) -> Result<ChatResponse, LLMError> {
-        let mut streaming_request = request.clone();
-        streaming_request.stream = Some(true);
-
-        let url = format!("{}/api/llm/chat", self.base_url);
+        generation_params::normalize(&mut request)?;
 
-        let mut req = self.client.post(&url).json(&streaming_request);
-
-        if let Some(token) = auth_token {
-            req = req.bearer_auth(token);
+        let key = self.lookup_key(&request);
+        if let Some(cached) = key.as_deref().and_then(|k| self.cached_response(k)) {
+            debug!("LLM cache hit for streaming model {}", request.model);
+            self.replay_cached_response(&cached, &tx).await;
+            return Ok(cached);
         }
 
-        let response = req.send().await.map_err(|e| LLMError {
-            code: "NETWORK_ERROR".to_string(),
-            message: e.to_string(),
-            details: None,
-        })?;
-
-        if !response.status().is_success() {
-            return Err(self.parse_error_response(response).await);
+        request.stream = Some(true);
+        let tx = &tx;
+        let response = self
+            .route(&request, |mut attempt| async move {
+                let context = self.fit_context(&mut attempt, 0, auth_token).await;
+                let url = format!("{}/api/llm/chat", self.base_url);
+                let response = self.post_stream(&url, &attempt, auth_token).await?;
+                let mut response = self.process_sse_stream(response, tx.clone()).await?;
+                response.context = context;
+                Ok(response)
+            })
+            .await?;
+
+        // Only completed streams report usage; partial or errored streams aren't cached
+        if let (Some(key), Some(_)) = (key, response.usage.as_ref()) {
+            self.store_response(key, &response);
         }
 
-        self.process_sse_stream(response, tx).await
+        Ok(response)
     }
 
     /// Send a chat request with tools (non-streaming)
@@ -319,21 +461,33 @@ impl LLMService {
         request: ChatWithToolsRequest,
         auth_token: Option<&str>,
     ) -> Result<ChatResponse, LLMError> {
-        let url = format!("{}/api/llm/chat-with-tools", self.base_url);
+        let request = &request;
+        self.route(&request.base, |base| async move {
+            let mut attempt = request.with_base(base);
+            let tool_tokens = tool_definition_tokens(&attempt);
+            let context = self
+                .fit_context(&mut attempt.base, tool_tokens, auth_token)
+                .await;
 
-        let mut req = self.client.post(&url).json(&request);
+            let url = format!("{}/api/llm/chat-with-tools", self.base_url);
 
-        if let Some(token) = auth_token {
-            req = req.bearer_auth(token);
-        }
+            let mut req = self.client.post(&url).json(&attempt);
 
-        let response = req.send().await.map_err(|e| LLMError {
-            code: "NETWORK_ERROR".to_string(),
-            message: e.to_string(),
-            details: None,
-        })?;
+            if let Some(token) = auth_token {
+                req = req.bearer_auth(token);
+            }
 
-        self.handle_response(response).await
+            let response = req.send().await.map_err(|e| LLMError {
+                code: "NETWORK_ERROR".to_string(),
+                message: e.to_string(),
+                details: None,
+            })?;
+
+            let mut response = self.handle_response(response).await?;
+            response.context = context;
+            Ok(response)
+        })
+        .await
     }
 
     /// Send a streaming chat request with tools
@@ -343,28 +497,23 @@ impl LLMService {
         auth_token: Option<&str>,
         tx: mpsc::Sender<StreamChunk>,
     ) -> Result<ChatResponse, LLMError> {
-        let mut streaming_request = request.clone();
+        let mut streaming_request = request;
         streaming_request.base.stream = Some(true);
-
-        let url = format!("{}/api/llm/chat-with-tools", self.base_url);
-
-        let mut req = self.client.post(&url).json(&streaming_request);
-
-        if let Some(token) = auth_token {
-            req = req.bearer_auth(token);
-        }
-
-        let response = req.send().await.map_err(|e| LLMError {
-            code: "NETWORK_ERROR".to_string(),
-            message: e.to_string(),
-            details: None,
-        })?;
-
-        if !response.status().is_success() {
-            return Err(self.parse_error_response(response).await);
-        }
-
-        self.process_sse_stream(response, tx).await
+        let (request, tx) = (&streaming_request, &tx);
+        self.route(&request.base, |base| async move {
+            let mut attempt = request.with_base(base);
+            let tool_tokens = tool_definition_tokens(&attempt);
+            let context = self
+                .fit_context(&mut attempt.base, tool_tokens, auth_token)
+                .await;
+
+            let url = format!("{}/api/llm/chat-with-tools", self.base_url);
+            let response = self.post_stream(&url, &attempt, auth_token).await?;
+            let mut response = self.process_sse_stream(response, tx.clone()).await?;
+            response.context = context;
+            Ok(response)
+        })
+        .await
     }
 
     /// Get available models
@@ -416,11 +565,16 @@ impl LLMService {
             return Err(self.parse_error_response(response).await);
         }
 
-        response.json().await.map_err(|e| LLMError {
+        let quota: QuotaInfo = response.json().await.map_err(|e| LLMError {
             code: "PARSE_ERROR".to_string(),
             message: e.to_string(),
             details: None,
-        })
+        })?;
+        if let Some(event) = self.quota.sync(&quota) {
+            self.notify_quota(event);
+        }
+
+        Ok(quota)
     }
 
     /// Get LLM service status
@@ -462,17 +616,340 @@ impl LLMService {
         })
     }
 
+    /// Get response cache statistics
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache.lock().unwrap().stats()
+    }
+
+    /// Remove all cached responses
+    pub fn clear_cache(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    /// Cache key for a request, or None if the request opted out of caching
+    /// or samples (an unset temperature means the provider's default, which
+    /// samples), so regenerating gets a new response
+    fn lookup_key(&self, request: &ChatRequest) -> Option<String> {
+        let deterministic = request.temperature.is_some_and(|t| t <= 0.0);
+        if request.bypass_cache || !deterministic {
+            None
+        } else {
+            Some(cache_key(request))
+        }
+    }
+
+    fn cached_response(&self, key: &str) -> Option<ChatResponse> {
+        self.cache.lock().unwrap().get(key)
+    }
+
+    fn store_response(&self, key: String, response: &ChatResponse) {
+        self.cache.lock().unwrap().insert(key, response.clone());
+    }
+
+    /// Send a cached response through the stream channel as if it had been streamed
+    async fn replay_cached_response(
+        &self,
+        response: &ChatResponse,
+        tx: &mpsc::Sender<StreamChunk>,
+    ) {
+        let chunks = [
+            ("content", Some(response.content.clone()), None, None),
+            ("usage", None, response.usage.clone(), None),
+            (
+                "done",
+                None,
+                response.usage.clone(),
+                Some(response.finish_reason.clone()),
+            ),
+        ];
+
+        for (chunk_type, content, usage, finish_reason) in chunks {
+            let _ = tx
+                .send(StreamChunk {
+                    chunk_type: chunk_type.to_string(),
+                    content,
+                    tool_call: None,
+                    error: None,
+                    usage,
+                    finish_reason,
+                    id: None,
+                })
+                .await;
+        }
+    }
+
+    /// Get the provider routing policy
+    pub fn routing_policy(&self) -> RoutingPolicy {
+        self.routing.read().unwrap().clone()
+    }
+
+    /// Replace the provider routing policy
+    pub fn set_routing_policy(&self, policy: RoutingPolicy) {
+        *self.routing.write().unwrap() = policy;
+    }
+
+    /// The running quota count
+    pub fn quota_tracker(&self) -> &QuotaTracker {
+        &self.quota
+    }
+
+    /// Set the function told about every change to the quota count
+    pub fn set_quota_listener(&self, listener: QuotaListener) {
+        *self.quota_listener.write().unwrap() = Some(listener);
+    }
+
+    /// Update the quota count after a response
+    fn track_quota(&self, reported: Option<ReportedQuota>, usage: Option<&UsageInfo>) {
+        if let Some(event) = self.quota.update(reported, usage) {
+            self.notify_quota(event);
+        }
+    }
+
+    fn notify_quota(&self, event: QuotaEvent) {
+        if let Some(threshold) = event.threshold {
+            info!("LLM quota usage passed {}%", threshold);
+        }
+        let listener = self.quota_listener.read().unwrap().clone();
+        if let Some(listener) = listener {
+            listener(event);
+        }
+    }
+
+    /// Send a request to each target of the routing policy in turn until one
+    /// succeeds. Only transient errors move on to the next target, and
+    /// streaming requests only fail over before the first chunk arrives.
+    async fn route<F, Fut>(
+        &self,
+        request: &ChatRequest,
+        mut send: F,
+    ) -> Result<ChatResponse, LLMError>
+    where
+        F: FnMut(ChatRequest) -> Fut,
+        Fut: Future<Output = Result<ChatResponse, LLMError>>,
+    {
+        let targets = self.routing.read().unwrap().targets(request);
+        let mut failed_attempts = Vec::new();
+
+        for (index, target) in targets.iter().enumerate() {
+            let mut attempt = request.clone();
+            attempt.provider = target.provider.clone();
+            attempt.model = target.model.clone();
+
+            let result = match generation_params::normalize(&mut attempt) {
+                Ok(()) => send(attempt).await,
+                Err(e) => Err(e),
+            };
+
+            match result {
+                Ok(mut response) => {
+                    let routing = RoutingInfo {
+                        provider: target.provider.clone(),
+                        model: target.model.clone(),
+                        failed_attempts,
+                    };
+                    if routing.failed_over() {
+                        info!(
+                            "Request served by fallback {}/{}",
+                            routing.provider, routing.model
+                        );
+                    }
+                    response.routing = Some(routing);
+                    return Ok(response);
+                }
+                Err(e) if index + 1 < targets.len() && llm_routing::should_failover(&e) => {
+                    warn!(
+                        "{}/{} failed ({}), trying next provider",
+                        target.provider, target.model, e
+                    );
+                    failed_attempts.push(FailedAttempt {
+                        provider: target.provider.clone(),
+                        model: target.model.clone(),
+                        code: e.code,
+                        message: e.message,
+                    });
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        unreachable!("routing always yields at least one target")
+    }
+
+    /// POST a streaming request, returning the response once the stream opens
+    async fn post_stream<T: Serialize>(
+        &self,
+        url: &str,
+        body: &T,
+        auth_token: Option<&str>,
+    ) -> Result<reqwest::Response, LLMError> {
+        let mut req = self.client.post(url).json(body);
+
+        if let Some(token) = auth_token {
+            req = req.bearer_auth(token);
+        }
+
+        let response = req.send().await.map_err(|e| LLMError {
+            code: "NETWORK_ERROR".to_string(),
+            message: e.to_string(),
+            details: None,
+        })?;
+
+        if !response.status().is_success() {
+            return Err(self.parse_error_response(response).await);
+        }
+
+        Ok(response)
+    }
+
+    /// POST a chat request to the backend without caching or context fitting
+    async fn post_chat(
+        &self,
+        request: &ChatRequest,
+        auth_token: Option<&str>,
+    ) -> Result<ChatResponse, LLMError> {
+        let url = format!("{}/api/llm/chat", self.base_url);
+
+        let mut req = self.client.post(&url).json(request);
+
+        if let Some(token) = auth_token {
+            req = req.bearer_auth(token);
+        }
+
+        let response = req.send().await.map_err(|e| LLMError {
+            code: "NETWORK_ERROR".to_string(),
+            message: e.to_string(),
+            details: None,
+        })?;
+
+        self.handle_response(response).await
+    }
+
+    /// Replace older turns with a summary when the request doesn't fit the
+    /// model's context window. Returns what was truncated, if anything.
+    /// If summarization fails the older turns are dropped instead.
+    async fn fit_context(
+        &self,
+        request: &mut ChatRequest,
+        extra_tokens: u32,
+        auth_token: Option<&str>,
+    ) -> Option<ContextTruncation> {
+        let plan = context_window::plan(
+            &request.model,
+            &request.messages,
+            request.max_tokens,
+            extra_tokens,
+        )?;
+        let history = &request.messages[plan.system_end..plan.keep_from];
+
+        let cached = self.summaries.lock().unwrap().lookup(history);
+        let (summary, summary_cached, dropped) = match cached {
+            Some((covered, summary)) if covered == history.len() => (Some(summary), true, 0),
+            cached => {
+                let (covered, previous) = match cached {
+                    Some((covered, summary)) => (covered, Some(summary)),
+                    None => (0, None),
+                };
+                match self
+                    .summarize(
+                        request,
+                        previous.as_deref(),
+                        &history[covered..],
+                        auth_token,
+                    )
+                    .await
+                {
+                    Ok((summary, dropped)) => {
+                        self.summaries
+                            .lock()
+                            .unwrap()
+                            .store(history, summary.clone());
+                        (Some(summary), false, dropped)
+                    }
+                    Err(e) => {
+                        warn!(
+                            "Failed to summarize conversation, dropping older turns: {}",
+                            e
+                        );
+                        (None, false, history.len())
+                    }
+                }
+            }
+        };
+
+        let summarized = if summary.is_some() {
+            history.len() - dropped
+        } else {
+            0
+        };
+        request.messages = context_window::apply(&request.messages, &plan, summary.as_deref());
+
+        let final_tokens =
+            count_message_tokens(encoding_for_model(&request.model), &request.messages);
+        debug!(
+            "Fitted conversation into {} token window: {} -> {} tokens ({} summarized, {} dropped)",
+            plan.context_window, plan.original_tokens, final_tokens, summarized, dropped
+        );
+
+        Some(ContextTruncation {
+            context_window: plan.context_window,
+            original_tokens: plan.original_tokens,
+            final_tokens,
+            summarized_messages: summarized,
+            dropped_messages: dropped,
+            summary_cached,
+        })
+    }
+
+    /// Summarize messages with the request's own model, folding them into a
+    /// previous summary if given. Returns the summary and how many of the
+    /// oldest messages didn't fit into the summarizer's window.
+    async fn summarize(
+        &self,
+        request: &ChatRequest,
+        previous: Option<&str>,
+        messages: &[ChatMessage],
+        auth_token: Option<&str>,
+    ) -> Result<(String, usize), LLMError> {
+        let (transcript, dropped) = context_window::transcript(
+            encoding_for_model(&request.model),
+            messages,
+            context_window::summary_input_budget(&request.model, previous),
+        );
+
+        let summary_request = ChatRequest {
+            provider: request.provider.clone(),
+            model: request.model.clone(),
+            messages: context_window::summary_messages(previous, &transcript),
+            temperature: Some(0.2),
+            max_tokens: Some(context_window::SUMMARY_MAX_TOKENS),
+            top_p: None,
+            stop: None,
+            json_mode: None,
+            stream: Some(false),
+            request_type: request.request_type.clone(),
+            web_search_enabled: None,
+            bypass_cache: true,
+        };
+
+        let response = self.post_chat(&summary_request, auth_token).await?;
+        Ok((response.content, dropped))
+    }
+
     /// Handle a successful response
     async fn handle_response(&self, response: reqwest::Response) -> Result<ChatResponse, LLMError> {
         if !response.status().is_success() {
             return Err(self.parse_error_response(response).await);
         }
 
-        response.json().await.map_err(|e| LLMError {
+        let reported = quota_from_headers(response.headers());
+        let response: ChatResponse = response.json().await.map_err(|e| LLMError {
             code: "PARSE_ERROR".to_string(),
             message: e.to_string(),
             details: None,
-        })
+        })?;
+        self.track_quota(reported, response.usage.as_ref());
+
+        Ok(response)
     }
 
     /// Parse an error response
@@ -523,6 +1000,7 @@ impl LLMService {
         response: reqwest::Response,
         tx: mpsc::Sender<StreamChunk>,
     ) -> Result<ChatResponse, LLMError> {
+        let mut reported_quota = quota_from_headers(response.headers());
         let mut stream = response.bytes_stream();
         let mut buffer = String::new();
         let mut accumulated_content = String::new();
@@ -585,6 +1063,9 @@ impl LLMService {
                                 if let Some(ref usage) = backend_chunk.usage {
                                     final_usage = Some(usage.clone());
                                 }
+                                if backend_chunk.quota.is_some() {
+                                    reported_quota = backend_chunk.quota.clone();
+                                }
                                 let _ = tx
                                     .send(StreamChunk {
                                         chunk_type: "usage".to_string(),
@@ -620,6 +1101,8 @@ impl LLMService {
             }
         }
 
+        self.track_quota(reported_quota, final_usage.as_ref());
+
         Ok(ChatResponse {
             id: response_id,
             content: accumulated_content,
@@ -630,13 +1113,42 @@ impl LLMService {
             } else {
                 Some(accumulated_tool_calls)
             },
+            context: None,
+            routing: None,
         })
     }
 }
 
+/// Tokens used by tool definitions, which count against the context window
+fn tool_definition_tokens(request: &ChatWithToolsRequest) -> u32 {
+    let json = serde_json::to_string(&request.tools).unwrap_or_default();
+    count_tokens(encoding_for_model(&request.base.model), &json)
+}
+
+impl ChatWithToolsRequest {
+    /// Copy of this request with a different base request
+    fn with_base(&self, base: ChatRequest) -> Self {
+        Self {
+            base,
+            tools: self.tools.clone(),
+            tool_choice: self.tool_choice.clone(),
+        }
+    }
+}
+
+/// Path of the saved routing policy
+pub fn routing_policy_path() -> PathBuf {
+    app_data_dir().join("llm-routing.json")
+}
+
 impl Default for LLMService {
     fn default() -> Self {
-        Self::new(None)
+        let service = Self::new(None);
+        service.set_routing_policy(RoutingPolicy::load(&routing_policy_path()));
+        service
+            .quota
+            .set_settings(QuotaAlertSettings::load(&quota_alerts_path()));
+        service
     }
 }
 
@@ -652,7 +1164,7 @@ lazy_static::lazy_static! {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use wiremock::matchers::{method, path};
+    use wiremock::matchers::{body_string_contains, method, path};
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
     fn create_test_service(base_url: &str) -> LLMService {
@@ -663,6 +1175,14 @@ mod tests {
         LLMService::with_client(base_url.to_string(), client)
     }
 
+    /// A request at temperature 0, which the response cache keeps
+    fn create_deterministic_request() -> ChatRequest {
+        ChatRequest {
+            temperature: Some(0.0),
+            ..create_chat_request()
+        }
+    }
+
     fn create_chat_request() -> ChatRequest {
         ChatRequest {
             provider: "openai".to_string(),
@@ -676,9 +1196,13 @@ mod tests {
             }],
             temperature: None,
             max_tokens: None,
+            top_p: None,
+            stop: None,
+            json_mode: None,
             stream: None,
             request_type: None,
             web_search_enabled: None,
+            bypass_cache: false,
         }
     }
 
@@ -1209,9 +1733,13 @@ mod tests {
             }],
             temperature: Some(0.7),
             max_tokens: Some(1000),
+            top_p: None,
+            stop: None,
+            json_mode: None,
             stream: Some(true),
             request_type: Some("chat".to_string()),
             web_search_enabled: Some(true),
+            bypass_cache: false,
         };
 
         let json = serde_json::to_string(&request).unwrap();
@@ -1405,9 +1933,13 @@ mod tests {
                 messages: vec![],
                 temperature: None,
                 max_tokens: None,
+                top_p: None,
+                stop: None,
+                json_mode: None,
                 stream: None,
                 request_type: None,
                 web_search_enabled: None,
+                bypass_cache: false,
             },
             tools: vec![],
             tool_choice: Some(serde_json::json!("auto")),
@@ -1419,6 +1951,583 @@ mod tests {
         assert!(json.contains("\"toolChoice\":\"auto\""));
     }
 
+    #[tokio::test]
+    async fn test_chat_rejects_invalid_parameters_before_sending() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/llm/chat"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock_chat_response()))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let service = create_test_service(&mock_server.uri());
+        let mut request = create_chat_request();
+        request.temperature = Some(5.0);
+
+        let error = service.chat(request, None).await.unwrap_err();
+        assert_eq!(error.code, "INVALID_REQUEST");
+    }
+
+    #[test]
+    fn test_generation_parameters_serialization() {
+        let mut request = create_chat_request();
+        request.top_p = Some(0.5);
+        request.stop = Some(vec!["END".to_string()]);
+        request.json_mode = Some(true);
+
+        let json = serde_json::to_string(&request).unwrap();
+        assert!(json.contains("\"topP\":0.5"));
+        assert!(json.contains("\"stop\":[\"END\"]"));
+        assert!(json.contains("\"jsonMode\":true"));
+    }
+
+    // ============================================================================
+    // Response Cache Tests
+    // ============================================================================
+
+    #[tokio::test]
+    async fn test_chat_serves_repeat_request_from_cache() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/llm/chat"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock_chat_response()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let service = create_test_service(&mock_server.uri());
+
+        let first = service
+            .chat(create_deterministic_request(), None)
+            .await
+            .unwrap();
+        let second = service
+            .chat(create_deterministic_request(), None)
+            .await
+            .unwrap();
+
+        assert_eq!(first.content, second.content);
+        assert_eq!(service.cache_stats().hits, 1);
+    }
+
+    #[tokio::test]
+    async fn test_chat_sampled_requests_not_cached() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/llm/chat"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock_chat_response()))
+            .expect(4)
+            .mount(&mock_server)
+            .await;
+
+        let service = create_test_service(&mock_server.uri());
+        // The provider's default temperature samples too
+        let mut sampled = create_chat_request();
+        for temperature in [None, Some(0.7)] {
+            sampled.temperature = temperature;
+            service.chat(sampled.clone(), None).await.unwrap();
+            service.chat(sampled.clone(), None).await.unwrap();
+        }
+
+        assert_eq!(service.cache_stats().entries, 0);
+        assert_eq!(service.cache_stats().hits, 0);
+    }
+
+    #[tokio::test]
+    async fn test_chat_bypass_cache() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/llm/chat"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock_chat_response()))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        let service = create_test_service(&mock_server.uri());
+        let mut request = create_deterministic_request();
+        request.bypass_cache = true;
+
+        service.chat(request.clone(), None).await.unwrap();
+        service.chat(request, None).await.unwrap();
+
+        assert_eq!(service.cache_stats().entries, 0);
+    }
+
+    #[tokio::test]
+    async fn test_chat_errors_not_cached() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/llm/chat"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        let service = create_test_service(&mock_server.uri());
+
+        assert!(service
+            .chat(create_deterministic_request(), None)
+            .await
+            .is_err());
+        assert!(service
+            .chat(create_deterministic_request(), None)
+            .await
+            .is_err());
+        assert_eq!(service.cache_stats().entries, 0);
+    }
+
+    #[tokio::test]
+    async fn test_chat_stream_replays_cached_response() {
+        let mock_server = MockServer::start().await;
+
+        let sse_body = "data: {\"content\":\"Hello\"}\n\ndata: {\"done\":true,\"usage\":{\"promptTokens\":10,\"completionTokens\":5,\"totalTokens\":15}}\n\ndata: [DONE]\n\n";
+
+        Mock::given(method("POST"))
+            .and(path("/api/llm/chat"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(sse_body)
+                    .insert_header("content-type", "text/event-stream"),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let service = create_test_service(&mock_server.uri());
+
+        let (tx, _rx) = mpsc::channel::<StreamChunk>(10);
+        service
+            .chat_stream(create_deterministic_request(), None, tx)
+            .await
+            .unwrap();
+
+        let (tx, mut rx) = mpsc::channel::<StreamChunk>(10);
+        let cached = service
+            .chat_stream(create_deterministic_request(), None, tx)
+            .await
+            .unwrap();
+        assert_eq!(cached.content, "Hello");
+
+        let mut chunks = vec![];
+        while let Ok(chunk) = rx.try_recv() {
+            chunks.push(chunk);
+        }
+        assert_eq!(chunks[0].chunk_type, "content");
+        assert_eq!(chunks[0].content.as_deref(), Some("Hello"));
+        assert!(chunks.iter().any(|c| c.chunk_type == "done"));
+    }
+
+    #[tokio::test]
+    async fn test_chat_stream_without_usage_not_cached() {
+        let mock_server = MockServer::start().await;
+
+        let sse_body = "data: {\"error\":\"Provider failed\"}\n\ndata: [DONE]\n\n";
+
+        Mock::given(method("POST"))
+            .and(path("/api/llm/chat"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(sse_body)
+                    .insert_header("content-type", "text/event-stream"),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let service = create_test_service(&mock_server.uri());
+
+        let (tx, _rx) = mpsc::channel::<StreamChunk>(10);
+        let _ = service
+            .chat_stream(create_deterministic_request(), None, tx)
+            .await;
+
+        assert_eq!(service.cache_stats().entries, 0);
+    }
+
+    #[tokio::test]
+    async fn test_quota_tracking() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/llm/chat"))
+            .and(body_string_contains("\"stream\":false"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(mock_chat_response())
+                    .insert_header("x-quota-used", "790")
+                    .insert_header("x-quota-limit", "1000"),
+            )
+            .mount(&mock_server)
+            .await;
+        let sse_body = "data: {\"content\":\"Hi\"}\n\ndata: {\"done\":true,\"usage\":{\"promptTokens\":10,\"completionTokens\":5,\"totalTokens\":15}}\n\ndata: [DONE]\n\n";
+        Mock::given(method("POST"))
+            .and(path("/api/llm/chat"))
+            .and(body_string_contains("\"stream\":true"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(sse_body))
+            .mount(&mock_server)
+            .await;
+
+        let service = create_test_service(&mock_server.uri());
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        service.set_quota_listener(Arc::new(move |event| sink.lock().unwrap().push(event)));
+
+        let mut request = create_chat_request();
+        request.stream = Some(false);
+        request.bypass_cache = true;
+        service.chat(request.clone(), None).await.unwrap();
+        assert_eq!(service.quota_tracker().current().unwrap().used, 790);
+
+        // The stream reports no quota, so its tokens are added to the count
+        let (tx, _rx) = mpsc::channel::<StreamChunk>(10);
+        service.chat_stream(request, None, tx).await.unwrap();
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].quota.used, 805);
+        assert_eq!(events[1].threshold, Some(80));
+    }
+
+    // ============================================================================
+    // Context Window Tests
+    // ============================================================================
+
+    /// A gpt-4 (8k window) conversation too long to send as-is
+    fn create_long_chat_request() -> ChatRequest {
+        let message = |role: &str, content: String| ChatMessage {
+            role: role.to_string(),
+            content,
+            name: None,
+            tool_call_id: None,
+            tool_calls: None,
+        };
+
+        let mut request = create_chat_request();
+        request.max_tokens = Some(1024);
+        request.bypass_cache = true;
+        request.messages = vec![
+            message("system", "You are a writing assistant.".to_string()),
+            message("user", "draft ".repeat(3000)),
+            message("assistant", "reply ".repeat(3000)),
+            message("user", "more ".repeat(2000)),
+            message("assistant", "okay ".repeat(1000)),
+            message("user", "What should I change?".to_string()),
+        ];
+        request
+    }
+
+    #[tokio::test]
+    async fn test_chat_summarizes_older_turns_to_fit_context() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/llm/chat"))
+            .and(body_string_contains("You condense chat histories"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "summary",
+                "content": "The user shared a long draft.",
+                "finishReason": "stop"
+            })))
+            .with_priority(1)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/llm/chat"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock_chat_response()))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        let service = create_test_service(&mock_server.uri());
+
+        let response = service
+            .chat(create_long_chat_request(), None)
+            .await
+            .unwrap();
+        let context = response.context.unwrap();
+        assert_eq!(context.context_window, 8_192);
+        assert_eq!(context.summarized_messages, 2);
+        assert_eq!(context.dropped_messages, 0);
+        assert!(!context.summary_cached);
+        assert!(context.final_tokens < context.original_tokens);
+
+        let requests = mock_server.received_requests().await.unwrap();
+        let sent: ChatRequest = serde_json::from_slice(&requests[1].body).unwrap();
+        assert_eq!(sent.messages.len(), 5);
+        assert_eq!(sent.messages[0].content, "You are a writing assistant.");
+        assert!(sent.messages[1]
+            .content
+            .ends_with("The user shared a long draft."));
+
+        // The summary is reused for the same conversation
+        let response = service
+            .chat(create_long_chat_request(), None)
+            .await
+            .unwrap();
+        assert!(response.context.unwrap().summary_cached);
+    }
+
+    #[tokio::test]
+    async fn test_chat_drops_older_turns_when_summary_fails() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/llm/chat"))
+            .and(body_string_contains("You condense chat histories"))
+            .respond_with(ResponseTemplate::new(500))
+            .with_priority(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/llm/chat"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock_chat_response()))
+            .mount(&mock_server)
+            .await;
+
+        let service = create_test_service(&mock_server.uri());
+
+        let response = service
+            .chat(create_long_chat_request(), None)
+            .await
+            .unwrap();
+        let context = response.context.unwrap();
+        assert_eq!(context.summarized_messages, 0);
+        assert_eq!(context.dropped_messages, 2);
+    }
+
+    #[tokio::test]
+    async fn test_chat_short_conversation_not_truncated() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/llm/chat"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock_chat_response()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let service = create_test_service(&mock_server.uri());
+        let response = service.chat(create_chat_request(), None).await.unwrap();
+
+        assert!(response.context.is_none());
+    }
+
+    fn structured_response(content: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": "msg_structured",
+            "content": content,
+            "finishReason": "stop"
+        })
+    }
+
+    fn name_schema() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": { "name": { "type": "string" } },
+            "required": ["name"]
+        })
+    }
+
+    #[tokio::test]
+    async fn test_chat_structured_repairs_locally() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/llm/chat"))
+            .and(body_string_contains("\"jsonMode\":true"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(structured_response("```json\n{\"name\": \"Ada\",}\n```")),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let service = create_test_service(&mock_server.uri());
+        let response = service
+            .chat_structured(create_chat_request(), &name_schema(), 1, None)
+            .await
+            .unwrap();
+
+        assert_eq!(response.data, serde_json::json!({"name": "Ada"}));
+        assert_eq!(response.attempts, 1);
+        assert!(response.repaired);
+    }
+
+    #[tokio::test]
+    async fn test_chat_structured_asks_model_to_fix_invalid_output() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/llm/chat"))
+            .and(body_string_contains("did not match the JSON schema"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(structured_response("{\"name\": \"Grace\"}")),
+            )
+            .with_priority(1)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/llm/chat"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(structured_response("{\"title\": 1}")),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let service = create_test_service(&mock_server.uri());
+        let response = service
+            .chat_structured(create_chat_request(), &name_schema(), 1, None)
+            .await
+            .unwrap();
+
+        assert_eq!(response.data["name"], "Grace");
+        assert_eq!(response.attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn test_chat_structured_reports_validation_errors() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/llm/chat"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(structured_response("{\"name\": []}")),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let service = create_test_service(&mock_server.uri());
+        let error = service
+            .chat_structured(create_chat_request(), &name_schema(), 0, None)
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.code, "VALIDATION_FAILED");
+        assert!(error
+            .message
+            .contains("/name: expected string, found array"));
+        let details = error.details.unwrap();
+        assert_eq!(details["errors"][0]["path"], "/name");
+        assert_eq!(details["output"], "{\"name\": []}");
+    }
+
+    fn failover_policy() -> RoutingPolicy {
+        use crate::services::llm_routing::{RouteTarget, RoutingRule};
+
+        RoutingPolicy {
+            rules: vec![RoutingRule {
+                fallbacks: vec![RouteTarget {
+                    provider: "anthropic".to_string(),
+                    model: "claude-sonnet-4".to_string(),
+                }],
+                ..Default::default()
+            }],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chat_fails_over_on_quota_error() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/llm/chat"))
+            .and(body_string_contains("\"provider\":\"openai\""))
+            .respond_with(ResponseTemplate::new(403).set_body_json(serde_json::json!({
+                "code": "QUOTA_EXCEEDED",
+                "message": "Monthly quota exceeded"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/llm/chat"))
+            .and(body_string_contains("\"provider\":\"anthropic\""))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock_chat_response()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let service = create_test_service(&mock_server.uri());
+        service.set_routing_policy(failover_policy());
+
+        let response = service.chat(create_chat_request(), None).await.unwrap();
+        let routing = response.routing.unwrap();
+        assert_eq!(routing.provider, "anthropic");
+        assert_eq!(routing.model, "claude-sonnet-4");
+        assert_eq!(routing.failed_attempts.len(), 1);
+        assert_eq!(routing.failed_attempts[0].provider, "openai");
+        assert_eq!(routing.failed_attempts[0].code, "QUOTA_EXCEEDED");
+    }
+
+    #[tokio::test]
+    async fn test_chat_does_not_fail_over_on_auth_error() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/llm/chat"))
+            .respond_with(ResponseTemplate::new(401))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let service = create_test_service(&mock_server.uri());
+        service.set_routing_policy(failover_policy());
+
+        let error = service.chat(create_chat_request(), None).await.unwrap_err();
+        assert_eq!(error.code, "AUTH_REQUIRED");
+    }
+
+    #[tokio::test]
+    async fn test_chat_returns_last_error_when_all_targets_fail() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/llm/chat"))
+            .respond_with(ResponseTemplate::new(503))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        let service = create_test_service(&mock_server.uri());
+        service.set_routing_policy(failover_policy());
+
+        let error = service.chat(create_chat_request(), None).await.unwrap_err();
+        assert_eq!(error.code, "PROVIDER_ERROR");
+    }
+
+    #[tokio::test]
+    async fn test_chat_reports_serving_provider_without_failover() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/llm/chat"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(mock_chat_response()))
+            .mount(&mock_server)
+            .await;
+
+        let service = create_test_service(&mock_server.uri());
+        let response = service.chat(create_chat_request(), None).await.unwrap();
+
+        let routing = response.routing.unwrap();
+        assert_eq!(routing.provider, "openai");
+        assert_eq!(routing.model, "gpt-4");
+        assert!(!routing.failed_over());
+    }
+
     // ============================================================================
     // Streaming Tests
     // ============================================================================
@@ -2120,6 +3229,8 @@ mod tests {
             finish_reason: "stop".to_string(),
             usage: None,
             tool_calls: None,
+            context: None,
+            routing: None,
         };
 
         let cloned = response.clone();
@@ -0,0 +1,785 @@
+// Local API - Opt-in HTTP server for other tools on this machine
+//
+// Launchers (Alfred, Raycast), browser extensions and scripts can read and
+// search documents and push clippings into the workspace without going
+// through the UI. The server is off by default, listens on 127.0.0.1 only,
+// and every request must carry the token generated for this session:
+//
+//   Authorization: Bearer <token>
+//
+// The token changes on every launch and is shown in settings. Only workspaces
+// open in the app can be reached, and only .midlight documents inside them.
+// Requests naming another host are refused, so web pages can't reach the
+// server through DNS rebinding.
+//
+// Endpoints, JSON in and out:
+//   GET  /v1/status                            app version and open workspaces
+//   GET  /v1/documents?path=notes/a.midlight   a document as Markdown
+//   GET  /v1/search?q=text&limit=20            text search across documents
+//   POST /v1/documents/append                  {"path", "markdown"}
+//   POST /v1/clip                              {"url", "title"?, "html"}
+//
+// `workspace` can be passed as a query parameter or in the request body, and
+// is required when more than one workspace is open. Clippings are saved in
+// the clippings folder, see web_clipper.
+//
+// Settings: `local_api.json` in the app data directory.
+
+use crate::services::app_dirs::app_data_dir;
+use crate::services::atomic_write::write_private;
+use crate::services::find_replace::{FindReplace, FindReplaceOptions};
+use crate::services::web_clipper::{self, clipper_settings_path, ClipRequest, ClipperSettings};
+use crate::services::web_fetch;
+use crate::services::workspace_manager::{WorkspaceManager, WorkspaceManagerRegistry};
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{self, HeaderValue};
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::{oneshot, watch, RwLock};
+use tracing::{debug, info, warn};
+
+pub const DEFAULT_PORT: u16 = 27124;
+
+/// Largest request body accepted
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+const DEFAULT_SEARCH_LIMIT: usize = 20;
+const MAX_SEARCH_LIMIT: usize = 100;
+
+/// Matches shown per document in search results
+const SNIPPETS_PER_DOCUMENT: usize = 3;
+
+/// Checkpoint trigger for documents changed through the API
+const TRIGGER: &str = "api";
+
+// ============================================================================
+// Settings
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalApiSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_port")]
+    pub port: u16,
+}
+
+fn default_port() -> u16 {
+    DEFAULT_PORT
+}
+
+impl Default for LocalApiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_PORT,
+        }
+    }
+}
+
+/// Path of the saved local API settings
+pub fn local_api_settings_path() -> PathBuf {
+    app_data_dir().join("local_api.json")
+}
+
+impl LocalApiSettings {
+    pub fn load(path: &Path) -> Self {
+        if !path.exists() {
+            return Self::default();
+        }
+
+        std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| {
+                warn!("Failed to load local API settings, using defaults: {}", e);
+                Self::default()
+            })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize local API settings: {}", e))?;
+        write_private(path, json).map_err(|e| format!("Failed to save local API settings: {}", e))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.port < 1024 {
+            return Err("Port must be 1024 or higher".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// A random token for this session
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// A document changed through the API, so an open editor can reload it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppendedEvent {
+    pub workspace_root: String,
+    /// Relative to the workspace root
+    pub path: String,
+}
+
+#[derive(Clone)]
+pub struct ApiContext {
+    pub token: String,
+    pub port: u16,
+    pub registry: Arc<RwLock<WorkspaceManagerRegistry>>,
+    pub on_append: Arc<dyn Fn(AppendedEvent) + Send + Sync>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AppendRequest {
+    workspace: Option<String>,
+    path: String,
+    markdown: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClipBody {
+    workspace: Option<String>,
+    #[serde(flatten)]
+    clip: ClipRequest,
+}
+
+#[derive(Debug, PartialEq)]
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+
+    fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, message)
+    }
+
+    fn internal(message: impl std::fmt::Display) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, message.to_string())
+    }
+}
+
+// ============================================================================
+// Server
+// ============================================================================
+
+pub struct LocalApiServer {
+    port: u16,
+    shutdown: watch::Sender<bool>,
+    /// Signalled once the listener is closed
+    closed: oneshot::Receiver<()>,
+}
+
+impl LocalApiServer {
+    /// Listen on 127.0.0.1 at the context's port
+    pub async fn start(context: ApiContext) -> Result<Self, String> {
+        let port = context.port;
+        let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, port)))
+            .await
+            .map_err(|e| format!("Failed to listen on port {}: {}", port, e))?;
+        let (shutdown, stopped) = watch::channel(false);
+        let (closed_tx, closed) = oneshot::channel();
+        info!("Local API listening on 127.0.0.1:{}", port);
+
+        let context = Arc::new(context);
+        tauri::async_runtime::spawn(async move {
+            let mut shutdown_signal = stopped.clone();
+            loop {
+                let stream = tokio::select! {
+                    _ = shutdown_signal.changed() => break,
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, _)) => stream,
+                        Err(e) => {
+                            warn!("Local API failed to accept a connection: {}", e);
+                            continue;
+                        }
+                    },
+                };
+
+                let context = context.clone();
+                let stopped = stopped.clone();
+                tauri::async_runtime::spawn(async move {
+                    let service = hyper::service::service_fn(move |request| {
+                        let context = context.clone();
+                        // Kept-alive connections outlive the listener
+                        let stopped = *stopped.borrow();
+                        async move {
+                            Ok::<_, Infallible>(match stopped {
+                                true => error_response(StatusCode::SERVICE_UNAVAILABLE, "Stopped"),
+                                false => handle(&context, request).await,
+                            })
+                        }
+                    });
+                    if let Err(e) = hyper::server::conn::http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await
+                    {
+                        debug!("Local API connection ended: {}", e);
+                    }
+                });
+            }
+            drop(listener);
+            let _ = closed_tx.send(());
+            info!("Local API stopped");
+        });
+
+        Ok(Self {
+            port,
+            shutdown,
+            closed,
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Stop listening. Returns once the port is free again.
+    pub async fn stop(self) {
+        let _ = self.shutdown.send(true);
+        let _ = self.closed.await;
+    }
+}
+
+async fn handle(context: &ApiContext, request: Request<Incoming>) -> Response<Full<Bytes>> {
+    let host = request
+        .headers()
+        .get(header::HOST)
+        .and_then(|h| h.to_str().ok());
+    if !is_allowed_host(host, context.port) {
+        return error_response(StatusCode::FORBIDDEN, "Host not allowed");
+    }
+    if request.method() == Method::OPTIONS {
+        return preflight_response();
+    }
+    let authorization = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok());
+    if !is_authorized(authorization, &context.token) {
+        return error_response(StatusCode::UNAUTHORIZED, "Missing or invalid token");
+    }
+
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let query = parse_query(request.uri().query());
+    debug!("Local API: {} {}", method, path);
+
+    let body = if method == Method::POST {
+        match Limited::new(request.into_body(), MAX_BODY_BYTES)
+            .collect()
+            .await
+        {
+            Ok(collected) => collected.to_bytes(),
+            Err(_) => {
+                return error_response(
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    "Request body is too large or could not be read",
+                )
+            }
+        }
+    } else {
+        Bytes::new()
+    };
+
+    match dispatch(context, &method, &path, &query, &body).await {
+        Ok(value) => json_response(StatusCode::OK, &value),
+        Err(e) => error_response(e.status, &e.message),
+    }
+}
+
+async fn dispatch(
+    context: &ApiContext,
+    method: &Method,
+    path: &str,
+    query: &HashMap<String, String>,
+    body: &[u8],
+) -> Result<Value, ApiError> {
+    match (method, path) {
+        (&Method::GET, "/v1/status") => {
+            let registry = context.registry.read().await;
+            Ok(json!({
+                "version": env!("CARGO_PKG_VERSION"),
+                "workspaces": registry.workspace_roots(),
+            }))
+        }
+        (&Method::GET, "/v1/documents") => {
+            let (root, manager) = open_workspace(context, query.get("workspace")).await?;
+            let path = query
+                .get("path")
+                .ok_or_else(|| ApiError::bad_request("path is required"))?;
+            let path = document_path(path).map_err(ApiError::bad_request)?;
+            if !Path::new(&root).join(&path).is_file() {
+                return Err(ApiError::not_found("Document not found"));
+            }
+
+            let markdown = manager
+                .export_markdown(&path)
+                .await
+                .map_err(ApiError::internal)?;
+            Ok(json!({ "workspace": root, "path": path, "markdown": markdown }))
+        }
+        (&Method::GET, "/v1/search") => {
+            let text = query
+                .get("q")
+                .filter(|q| !q.trim().is_empty())
+                .ok_or_else(|| ApiError::bad_request("q is required"))?;
+            let limit = query
+                .get("limit")
+                .and_then(|limit| limit.parse::<usize>().ok())
+                .unwrap_or(DEFAULT_SEARCH_LIMIT)
+                .min(MAX_SEARCH_LIMIT);
+            let (root, _) = open_workspace(context, query.get("workspace")).await?;
+
+            let options = FindReplaceOptions {
+                find: text.clone(),
+                dry_run: true,
+                ..Default::default()
+            };
+            let search =
+                FindReplace::new(Path::new(&root), &options).map_err(ApiError::bad_request)?;
+            let documents = tokio::task::spawn_blocking(move || search.scan())
+                .await
+                .map_err(ApiError::internal)?;
+
+            let results: Vec<Value> = documents
+                .into_iter()
+                .take(limit)
+                .map(|document| {
+                    let snippets: Vec<String> = document
+                        .matches
+                        .iter()
+                        .take(SNIPPETS_PER_DOCUMENT)
+                        .map(|m| format!("{}{}{}", m.before, m.matched, m.after))
+                        .collect();
+                    json!({
+                        "path": document.path,
+                        "matchCount": document.match_count,
+                        "snippets": snippets,
+                    })
+                })
+                .collect();
+            Ok(json!({ "workspace": root, "results": results }))
+        }
+        (&Method::POST, "/v1/documents/append") => {
+            let request: AppendRequest = serde_json::from_slice(body)
+                .map_err(|e| ApiError::bad_request(format!("Invalid request body: {}", e)))?;
+            if request.markdown.trim().is_empty() {
+                return Err(ApiError::bad_request("markdown is required"));
+            }
+            let (root, manager) = open_workspace(context, request.workspace.as_ref()).await?;
+            let path = document_path(&request.path).map_err(ApiError::bad_request)?;
+
+            let result = manager
+                .append_markdown(&path, &request.markdown, TRIGGER)
+                .await
+                .map_err(ApiError::internal)?;
+            if !result.success {
+                return Err(ApiError::internal(result.error.unwrap_or_default()));
+            }
+
+            (context.on_append)(AppendedEvent {
+                workspace_root: root.clone(),
+                path: path.clone(),
+            });
+            Ok(json!({
+                "workspace": root,
+                "path": path,
+                "checkpointId": result.checkpoint_id,
+            }))
+        }
+        (&Method::POST, "/v1/clip") => {
+            let request: ClipBody = serde_json::from_slice(body)
+                .map_err(|e| ApiError::bad_request(format!("Invalid request body: {}", e)))?;
+            let (root, manager) = open_workspace(context, request.workspace.as_ref()).await?;
+
+            let client = web_fetch::client().map_err(ApiError::internal)?;
+            let settings = ClipperSettings::load(&clipper_settings_path());
+            let result = web_clipper::clip(
+                &client,
+                &manager,
+                Path::new(&root),
+                &settings,
+                &request.clip,
+            )
+            .await
+            .map_err(ApiError::bad_request)?;
+
+            (context.on_append)(AppendedEvent {
+                workspace_root: root.clone(),
+                path: result.path.clone(),
+            });
+            Ok(json!({
+                "workspace": root,
+                "path": result.path,
+                "title": result.title,
+                "imagesSaved": result.images_saved,
+                "imagesFailed": result.images_failed,
+            }))
+        }
+        (
+            _,
+            "/v1/status" | "/v1/documents" | "/v1/search" | "/v1/documents/append" | "/v1/clip",
+        ) => Err(ApiError::new(
+            StatusCode::METHOD_NOT_ALLOWED,
+            "Method not allowed",
+        )),
+        _ => Err(ApiError::not_found("Not found")),
+    }
+}
+
+/// The named workspace, or the only open one when none is named
+async fn open_workspace(
+    context: &ApiContext,
+    requested: Option<&String>,
+) -> Result<(String, Arc<WorkspaceManager>), ApiError> {
+    let registry = context.registry.read().await;
+    let root = match requested {
+        Some(root) => root.clone(),
+        None => match registry.workspace_roots().as_slice() {
+            [root] => root.clone(),
+            [] => return Err(ApiError::new(StatusCode::CONFLICT, "No workspace is open")),
+            _ => {
+                return Err(ApiError::bad_request(
+                    "workspace is required when more than one workspace is open",
+                ))
+            }
+        },
+    };
+    let manager = registry
+        .get(&root)
+        .ok_or_else(|| ApiError::not_found("Workspace is not open in Midlight"))?;
+    Ok((root, manager))
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+/// A workspace-relative .midlight path, with the extension added if missing.
+/// Absolute paths, `..` and hidden folders such as .midlight are refused.
+pub fn document_path(path: &str) -> Result<String, String> {
+    let path = path.trim().replace('\\', "/");
+    let path = path.trim_start_matches("./");
+    if path.is_empty() {
+        return Err("path is required".to_string());
+    }
+
+    let inside_workspace = Path::new(path)
+        .components()
+        .all(|component| match component {
+            Component::Normal(name) => !name.to_string_lossy().starts_with('.'),
+            _ => false,
+        });
+    if !inside_workspace {
+        return Err("path must be relative to the workspace".to_string());
+    }
+
+    if path.ends_with(".midlight") {
+        Ok(path.to_string())
+    } else if Path::new(path).extension().is_none() {
+        Ok(format!("{}.midlight", path))
+    } else {
+        Err("Only .midlight documents are supported".to_string())
+    }
+}
+
+/// Whether the Authorization header carries the session token
+fn is_authorized(authorization: Option<&str>, token: &str) -> bool {
+    let Some(given) = authorization.and_then(|value| value.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    // Compare without stopping at the first difference
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Whether a Host header names this server. Clients that send none are not
+/// browsers, so they're let through.
+fn is_allowed_host(host: Option<&str>, port: u16) -> bool {
+    let Some(host) = host else {
+        return true;
+    };
+    let name = host
+        .strip_suffix(&format!(":{}", port))
+        .unwrap_or(host)
+        .to_ascii_lowercase();
+    name == "127.0.0.1" || name == "localhost"
+}
+
+fn parse_query(query: Option<&str>) -> HashMap<String, String> {
+    query
+        .map(|query| {
+            url::form_urlencoded::parse(query.as_bytes())
+                .into_owned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn json_response(status: StatusCode, value: &Value) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(value.to_string())));
+    *response.status_mut() = status;
+    let headers = response.headers_mut();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    // The token travels in a header rather than a cookie, so any origin
+    // (e.g. a browser extension) may call with it
+    headers.insert(
+        header::ACCESS_CONTROL_ALLOW_ORIGIN,
+        HeaderValue::from_static("*"),
+    );
+    response
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Full<Bytes>> {
+    json_response(status, &json!({ "error": message }))
+}
+
+fn preflight_response() -> Response<Full<Bytes>> {
+    let mut response = json_response(StatusCode::NO_CONTENT, &Value::Null);
+    *response.body_mut() = Full::new(Bytes::new());
+    let headers = response.headers_mut();
+    headers.insert(
+        header::ACCESS_CONTROL_ALLOW_METHODS,
+        HeaderValue::from_static("GET, POST, OPTIONS"),
+    );
+    headers.insert(
+        header::ACCESS_CONTROL_ALLOW_HEADERS,
+        HeaderValue::from_static("Authorization, Content-Type"),
+    );
+    response
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+
+    async fn context(root: &str) -> (ApiContext, Arc<Mutex<Vec<AppendedEvent>>>) {
+        let mut registry = WorkspaceManagerRegistry::new();
+        registry
+            .get_or_create(root)
+            .await
+            .unwrap()
+            .init()
+            .await
+            .unwrap();
+        let appended = Arc::new(Mutex::new(Vec::new()));
+        let recorded = appended.clone();
+        let context = ApiContext {
+            token: generate_token(),
+            port: DEFAULT_PORT,
+            registry: Arc::new(RwLock::new(registry)),
+            on_append: Arc::new(move |event| recorded.lock().unwrap().push(event)),
+        };
+        (context, appended)
+    }
+
+    fn query(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_append_read_and_search() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().to_string_lossy().to_string();
+        let (context, appended) = context(&root).await;
+
+        let body = br#"{"path": "Inbox", "markdown": "Clipped from the web"}"#;
+        let result = dispatch(
+            &context,
+            &Method::POST,
+            "/v1/documents/append",
+            &HashMap::new(),
+            body,
+        )
+        .await
+        .unwrap();
+        assert_eq!(result["path"], "Inbox.midlight");
+        assert_eq!(
+            appended.lock().unwrap()[0],
+            AppendedEvent {
+                workspace_root: root.clone(),
+                path: "Inbox.midlight".to_string(),
+            }
+        );
+
+        let read = dispatch(
+            &context,
+            &Method::GET,
+            "/v1/documents",
+            &query(&[("path", "Inbox.midlight")]),
+            &[],
+        )
+        .await
+        .unwrap();
+        assert!(read["markdown"]
+            .as_str()
+            .unwrap()
+            .contains("Clipped from the web"));
+
+        let search = dispatch(
+            &context,
+            &Method::GET,
+            "/v1/search",
+            &query(&[("q", "clipped"), ("workspace", &root)]),
+            &[],
+        )
+        .await
+        .unwrap();
+        assert_eq!(search["results"][0]["path"], "Inbox.midlight");
+        assert_eq!(search["results"][0]["matchCount"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_errors() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().to_string_lossy().to_string();
+        let (context, _) = context(&root).await;
+
+        let status = |result: Result<Value, ApiError>| result.unwrap_err().status;
+        let missing = dispatch(
+            &context,
+            &Method::GET,
+            "/v1/documents",
+            &query(&[("path", "nope")]),
+            &[],
+        )
+        .await;
+        assert_eq!(status(missing), StatusCode::NOT_FOUND);
+
+        let traversal = br#"{"path": "../outside", "markdown": "x"}"#;
+        let result = dispatch(
+            &context,
+            &Method::POST,
+            "/v1/documents/append",
+            &HashMap::new(),
+            traversal,
+        )
+        .await;
+        assert_eq!(status(result), StatusCode::BAD_REQUEST);
+        assert!(!temp.path().join("../outside.midlight").exists());
+
+        let other = query(&[("q", "x"), ("workspace", "/not/open")]);
+        let result = dispatch(&context, &Method::GET, "/v1/search", &other, &[]).await;
+        assert_eq!(status(result), StatusCode::NOT_FOUND);
+
+        let result = dispatch(
+            &context,
+            &Method::DELETE,
+            "/v1/documents",
+            &HashMap::new(),
+            &[],
+        )
+        .await;
+        assert_eq!(status(result), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[test]
+    fn test_document_path() {
+        assert_eq!(document_path("notes/idea").unwrap(), "notes/idea.midlight");
+        assert_eq!(document_path("./a.midlight").unwrap(), "a.midlight");
+        assert_eq!(
+            document_path("notes\\b.midlight").unwrap(),
+            "notes/b.midlight"
+        );
+        assert!(document_path("").is_err());
+        assert!(document_path("/etc/passwd").is_err());
+        assert!(document_path("notes/../../x").is_err());
+        assert!(document_path(".midlight/config").is_err());
+        assert!(document_path("notes/a.txt").is_err());
+    }
+
+    #[test]
+    fn test_token_and_host_checks() {
+        let token = generate_token();
+        assert_eq!(token.len(), 64);
+        assert_ne!(token, generate_token());
+
+        assert!(is_authorized(Some(&format!("Bearer {}", token)), &token));
+        assert!(!is_authorized(Some(&token), &token));
+        assert!(!is_authorized(Some("Bearer wrong"), &token));
+        assert!(!is_authorized(None, &token));
+
+        assert!(is_allowed_host(Some("127.0.0.1:27124"), 27124));
+        assert!(is_allowed_host(Some("localhost:27124"), 27124));
+        assert!(is_allowed_host(None, 27124));
+        assert!(!is_allowed_host(Some("evil.example:27124"), 27124));
+    }
+
+    #[test]
+    fn test_settings() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("local_api.json");
+        let settings = LocalApiSettings::load(&path);
+        assert!(!settings.enabled);
+        assert_eq!(settings.port, DEFAULT_PORT);
+
+        let settings = LocalApiSettings {
+            enabled: true,
+            port: 31000,
+        };
+        settings.save(&path).unwrap();
+        assert_eq!(LocalApiSettings::load(&path), settings);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        assert!(LocalApiSettings {
+            enabled: true,
+            port: 80
+        }
+        .validate()
+        .is_err());
+    }
+}
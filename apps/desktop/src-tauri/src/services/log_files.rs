@@ -0,0 +1,292 @@
+// Log Files - Rolling log files in the app data directory
+//
+// Everything logged at info and above is written to a file as well as
+// stdout, so there's something to look at after the fact. A new file is
+// started each day and whenever the current one reaches MAX_FILE_BYTES;
+// only the newest MAX_FILES are kept.
+//
+// Files are named by when they were started (midlight-2024-01-31_09-15-00.log)
+// so sorting by name sorts them oldest first.
+//
+// Stored at: {app data}/com.midlight.app/logs/
+
+use crate::services::app_dirs::app_data_dir;
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Start a new file once the current one is this large
+const MAX_FILE_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Older files are deleted beyond this many
+const MAX_FILES: usize = 10;
+
+/// Lines returned by read_tail when not asked for a number
+pub const DEFAULT_TAIL_LINES: usize = 200;
+
+/// Most lines read_tail returns
+const MAX_TAIL_LINES: usize = 5000;
+
+const PREFIX: &str = "midlight-";
+const EXTENSION: &str = "log";
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogFileInfo {
+    pub name: String,
+    pub size: u64,
+    pub modified_at: Option<String>,
+    /// Whether this is the file being written to
+    pub current: bool,
+}
+
+struct CurrentFile {
+    name: String,
+    date: NaiveDate,
+    file: File,
+    bytes: u64,
+}
+
+// ============================================================================
+// Rolling Log
+// ============================================================================
+
+pub struct RollingLog {
+    dir: PathBuf,
+    max_file_bytes: u64,
+    max_files: usize,
+    current: Mutex<Option<CurrentFile>>,
+}
+
+impl RollingLog {
+    pub fn new(dir: &Path) -> Self {
+        Self::with_limits(dir, MAX_FILE_BYTES, MAX_FILES)
+    }
+
+    fn with_limits(dir: &Path, max_file_bytes: u64, max_files: usize) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            max_file_bytes,
+            max_files,
+            current: Mutex::new(None),
+        }
+    }
+
+    /// Writer for a single log line
+    pub fn writer(&self) -> LogWriter<'_> {
+        LogWriter { log: self }
+    }
+
+    /// Log files, newest first
+    pub fn list(&self) -> Vec<LogFileInfo> {
+        let current = self
+            .current
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|c| c.name.clone());
+
+        self.file_names()
+            .into_iter()
+            .rev()
+            .filter_map(|name| {
+                let metadata = fs::metadata(self.dir.join(&name)).ok()?;
+                Some(LogFileInfo {
+                    current: current.as_deref() == Some(name.as_str()),
+                    size: metadata.len(),
+                    modified_at: metadata
+                        .modified()
+                        .ok()
+                        .map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339()),
+                    name,
+                })
+            })
+            .collect()
+    }
+
+    /// The last `lines` lines of a log file
+    pub fn read_tail(&self, name: &str, lines: usize) -> Result<String, String> {
+        if !self.file_names().iter().any(|n| n == name) {
+            return Err(format!("No such log file: {}", name));
+        }
+        let content =
+            fs::read(self.dir.join(name)).map_err(|e| format!("Failed to read log file: {}", e))?;
+        let content = String::from_utf8_lossy(&content);
+
+        let all: Vec<&str> = content.lines().collect();
+        let start = all.len().saturating_sub(lines.min(MAX_TAIL_LINES));
+        Ok(all[start..].join("\n"))
+    }
+
+    /// Names of the log files in the directory, oldest first
+    fn file_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+            .filter(|name| name.starts_with(PREFIX) && name.ends_with(&format!(".{}", EXTENSION)))
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn write_at(&self, buf: &[u8], now: NaiveDateTime) -> io::Result<()> {
+        let mut current = self.current.lock().unwrap();
+        let needs_new_file = current.as_ref().map_or(true, |c| {
+            c.date != now.date() || c.bytes + buf.len() as u64 > self.max_file_bytes
+        });
+        if needs_new_file {
+            *current = Some(self.start_file(now)?);
+            self.prune();
+        }
+
+        let Some(current) = current.as_mut() else {
+            return Ok(());
+        };
+        current.file.write_all(buf)?;
+        current.bytes += buf.len() as u64;
+        Ok(())
+    }
+
+    fn start_file(&self, now: NaiveDateTime) -> io::Result<CurrentFile> {
+        fs::create_dir_all(&self.dir)?;
+        let stem = format!("{}{}", PREFIX, now.format("%Y-%m-%d_%H-%M-%S"));
+
+        // A file started in the same second gets a counter, which sorts after
+        // the file without one
+        let mut name = format!("{}.{}", stem, EXTENSION);
+        let mut counter = 1;
+        while self.dir.join(&name).exists() {
+            name = format!("{}_{:02}.{}", stem, counter, EXTENSION);
+            counter += 1;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.dir.join(&name))?;
+        Ok(CurrentFile {
+            name,
+            date: now.date(),
+            file,
+            bytes: 0,
+        })
+    }
+
+    /// Delete the oldest files beyond the limit
+    fn prune(&self) {
+        let names = self.file_names();
+        let excess = names.len().saturating_sub(self.max_files);
+        for name in &names[..excess] {
+            let _ = fs::remove_file(self.dir.join(name));
+        }
+    }
+}
+
+/// Writes through to the current log file, starting a new one as needed
+pub struct LogWriter<'a> {
+    log: &'a RollingLog,
+}
+
+impl Write for LogWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.log.write_at(buf, Local::now().naive_local())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Global Singleton
+// ============================================================================
+
+lazy_static::lazy_static! {
+    pub static ref LOGS: RollingLog = RollingLog::new(
+        &app_data_dir()
+            .join("logs"),
+    );
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn at(date: &str, time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn test_rotation() {
+        let temp = TempDir::new().unwrap();
+        let log = RollingLog::with_limits(temp.path(), 20, 3);
+
+        log.write_at(b"first line\n", at("2024-01-31", "09:00:00"))
+            .unwrap();
+        log.write_at(b"second\n", at("2024-01-31", "09:00:00"))
+            .unwrap();
+        assert_eq!(log.list().len(), 1);
+
+        // Too big for the current file, same second
+        log.write_at(b"third line\n", at("2024-01-31", "09:00:00"))
+            .unwrap();
+        // New day
+        log.write_at(b"fourth\n", at("2024-02-01", "00:00:01"))
+            .unwrap();
+
+        let files = log.list();
+        assert_eq!(
+            files.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(),
+            vec![
+                "midlight-2024-02-01_00-00-01.log",
+                "midlight-2024-01-31_09-00-00_01.log",
+                "midlight-2024-01-31_09-00-00.log",
+            ]
+        );
+        assert!(files[0].current);
+        assert!(!files[1].current);
+
+        // The oldest file is removed past the limit
+        log.write_at(b"fifth\n", at("2024-02-02", "00:00:01"))
+            .unwrap();
+        let files = log.list();
+        assert_eq!(files.len(), 3);
+        assert_eq!(files[2].name, "midlight-2024-01-31_09-00-00_01.log");
+    }
+
+    #[test]
+    fn test_read_tail() {
+        let temp = TempDir::new().unwrap();
+        let log = RollingLog::new(temp.path());
+        for i in 1..=5 {
+            log.write_at(
+                format!("line {}\n", i).as_bytes(),
+                at("2024-01-31", "09:00:00"),
+            )
+            .unwrap();
+        }
+        std::fs::write(temp.path().join("notes.txt"), "not a log").unwrap();
+
+        let name = &log.list()[0].name;
+        assert_eq!(log.read_tail(name, 2).unwrap(), "line 4\nline 5");
+        assert_eq!(log.read_tail(name, 100).unwrap().lines().count(), 5);
+
+        assert!(log.read_tail("notes.txt", 10).is_err());
+        assert!(log.read_tail("../session.log", 10).is_err());
+    }
+}
@@ -0,0 +1,664 @@
+// Markdown Convert - Tiptap JSON <-> Markdown, shared by the agent, the
+// workspace manager, RAG indexing and the convert commands
+//
+// Covers what the editor's Markdown can hold: headings, paragraphs, bullet,
+// ordered and task lists (nested by indentation), blockquotes, code blocks,
+// horizontal rules, images on their own line, and bold/italic/code/strike/
+// link marks. Anything else keeps its text and loses its formatting.
+//
+// Two layouts are supported. Standard Markdown separates blocks with a blank
+// line. With `keep_blank_lines` every line is a block and blank lines are
+// empty paragraphs, which is how the workspace stores Markdown documents so
+// the editor's spacing survives a round trip.
+
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+lazy_static::lazy_static! {
+    /// A line holding only an image: ![alt](src)
+    static ref IMAGE_LINE: regex::Regex = regex::Regex::new(r"^!\[([^\]]*)\]\(([^)\s]+)\)$").unwrap();
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarkdownOptions {
+    /// One block per line, with blank lines as empty paragraphs
+    #[serde(default)]
+    pub keep_blank_lines: bool,
+}
+
+impl MarkdownOptions {
+    /// The layout of Markdown documents stored in a workspace
+    pub fn workspace() -> Self {
+        Self {
+            keep_blank_lines: true,
+        }
+    }
+}
+
+// ============================================================================
+// Markdown -> Tiptap
+// ============================================================================
+
+/// Convert Markdown to a Tiptap `doc` node
+pub fn markdown_to_tiptap(markdown: &str, options: MarkdownOptions) -> Value {
+    let lines: Vec<&str> = markdown.lines().collect();
+    let mut content = parse_blocks(&lines, options);
+    if content.is_empty() {
+        content.push(json!({ "type": "paragraph" }));
+    }
+    json!({ "type": "doc", "content": content })
+}
+
+fn parse_blocks(lines: &[&str], options: MarkdownOptions) -> Vec<Value> {
+    let mut content = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim();
+
+        // Code block, up to the closing fence or the end of the document
+        if let Some(language) = trimmed.strip_prefix("```") {
+            let start = i + 1;
+            let mut end = start;
+            while end < lines.len() && !lines[end].trim_start().starts_with("```") {
+                end += 1;
+            }
+            let code = lines[start..end].join("\n");
+            let mut block = json!({
+                "type": "codeBlock",
+                "attrs": { "language": Some(language.trim()).filter(|l| !l.is_empty()) }
+            });
+            if !code.is_empty() {
+                block["content"] = json!([{ "type": "text", "text": code }]);
+            }
+            content.push(block);
+            i = end + 1;
+            continue;
+        }
+
+        if let Some(image) = IMAGE_LINE.captures(trimmed) {
+            content.push(json!({
+                "type": "image",
+                "attrs": { "src": &image[2], "alt": &image[1], "title": "" }
+            }));
+        } else if let Some((level, text)) = heading(line) {
+            content.push(json!({
+                "type": "heading",
+                "attrs": { "level": level },
+                "content": parse_inline_formatting(text)
+            }));
+        } else if trimmed == "---" || trimmed == "***" || trimmed == "___" {
+            content.push(json!({ "type": "horizontalRule" }));
+        } else if line.starts_with('>') {
+            let mut quoted = Vec::new();
+            while i < lines.len() && lines[i].starts_with('>') {
+                let inner = &lines[i][1..];
+                quoted.push(inner.strip_prefix(' ').unwrap_or(inner));
+                i += 1;
+            }
+            let mut inner = parse_blocks(&quoted, options);
+            if inner.is_empty() {
+                inner.push(json!({ "type": "paragraph" }));
+            }
+            content.push(json!({ "type": "blockquote", "content": inner }));
+            continue;
+        } else if let Some(item) = list_item(line) {
+            let (list, next) = parse_list(lines, i, item.kind);
+            content.push(list);
+            i = next;
+            continue;
+        } else if line.trim().is_empty() {
+            if options.keep_blank_lines {
+                content.push(json!({ "type": "paragraph" }));
+            }
+        } else {
+            let inline = parse_inline_formatting(line);
+            if !inline.is_empty() {
+                content.push(json!({ "type": "paragraph", "content": inline }));
+            } else if options.keep_blank_lines {
+                content.push(json!({ "type": "paragraph" }));
+            }
+        }
+
+        i += 1;
+    }
+
+    content
+}
+
+/// `# Title` up to `###### Title`
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    line[level..].strip_prefix(' ').map(|text| (level, text))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ListKind {
+    Bullet,
+    Ordered,
+    Task,
+}
+
+struct ListItem<'a> {
+    kind: ListKind,
+    /// Width of the marker, which continuation lines are indented by
+    width: usize,
+    text: &'a str,
+    checked: bool,
+    number: u64,
+}
+
+fn list_item(line: &str) -> Option<ListItem<'_>> {
+    if let Some(rest) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) {
+        for (box_text, checked) in [("[ ] ", false), ("[x] ", true), ("[X] ", true)] {
+            if let Some(text) = rest.strip_prefix(box_text) {
+                return Some(ListItem {
+                    kind: ListKind::Task,
+                    width: 2,
+                    text,
+                    checked,
+                    number: 0,
+                });
+            }
+        }
+        return Some(ListItem {
+            kind: ListKind::Bullet,
+            width: 2,
+            text: rest,
+            checked: false,
+            number: 0,
+        });
+    }
+
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits == 0 {
+        return None;
+    }
+    let text = line[digits..].strip_prefix(". ")?;
+    Some(ListItem {
+        kind: ListKind::Ordered,
+        width: digits + 2,
+        text,
+        checked: false,
+        number: line[..digits].parse().unwrap_or(1),
+    })
+}
+
+/// A run of list items of one kind starting at `start`, with each item's
+/// indented lines parsed as its content. Returns the list and the index of
+/// the first line after it.
+fn parse_list(lines: &[&str], start: usize, kind: ListKind) -> (Value, usize) {
+    let mut items = Vec::new();
+    let mut first_number = None;
+    let mut i = start;
+
+    while i < lines.len() {
+        let Some(item) = list_item(lines[i]).filter(|item| item.kind == kind) else {
+            break;
+        };
+        first_number.get_or_insert(item.number);
+
+        let indent = " ".repeat(item.width);
+        let mut body = vec![item.text];
+        i += 1;
+        while i < lines.len() && lines[i].starts_with(' ') && !lines[i].trim().is_empty() {
+            let line = lines[i];
+            body.push(
+                line.strip_prefix(indent.as_str())
+                    .unwrap_or(line.trim_start()),
+            );
+            i += 1;
+        }
+
+        // Items always hold a paragraph, even when empty
+        let mut content = parse_blocks(&body, MarkdownOptions::default());
+        if content.first().and_then(|b| b.get("type")) != Some(&json!("paragraph")) {
+            content.insert(0, json!({ "type": "paragraph" }));
+        }
+        items.push(match kind {
+            ListKind::Task => json!({
+                "type": "taskItem",
+                "attrs": { "checked": item.checked },
+                "content": content
+            }),
+            _ => json!({ "type": "listItem", "content": content }),
+        });
+    }
+
+    let list = match kind {
+        ListKind::Bullet => json!({ "type": "bulletList", "content": items }),
+        ListKind::Task => json!({ "type": "taskList", "content": items }),
+        ListKind::Ordered => {
+            let mut list = json!({ "type": "orderedList", "content": items });
+            if let Some(start) = first_number.filter(|n| *n != 1) {
+                list["attrs"] = json!({ "start": start });
+            }
+            list
+        }
+    };
+    (list, i)
+}
+
+/// Parse inline Markdown (bold, italic, code, strike, links) into text nodes
+pub fn parse_inline_formatting(text: &str) -> Vec<Value> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut result: Vec<Value> = Vec::new();
+    let mut current = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        // Link: [text](href)
+        if c == '[' {
+            if let Some((label, href, end)) = link_at(&chars, i) {
+                flush(&mut current, &mut result);
+                result.push(text_node(
+                    &label,
+                    vec![json!({ "type": "link", "attrs": { "href": href } })],
+                ));
+                i = end;
+                continue;
+            }
+        }
+
+        let marker: Option<(&[char], Vec<&str>)> = if c == '`' {
+            Some((&['`'], vec!["code"]))
+        } else if starts_with(&chars, i, &['~', '~']) {
+            Some((&['~', '~'], vec!["strike"]))
+        } else if starts_with(&chars, i, &['*', '*', '*']) {
+            Some((&['*', '*', '*'], vec!["bold", "italic"]))
+        } else if starts_with(&chars, i, &['_', '_', '_']) {
+            Some((&['_', '_', '_'], vec!["bold", "italic"]))
+        } else if starts_with(&chars, i, &['*', '*']) {
+            Some((&['*', '*'], vec!["bold"]))
+        } else if starts_with(&chars, i, &['_', '_']) {
+            Some((&['_', '_'], vec!["bold"]))
+        } else if c == '*' || (c == '_' && (i == 0 || !chars[i - 1].is_alphanumeric())) {
+            // '_' only opens italics at the start of a word, so snake_case stays text
+            Some((if c == '*' { &['*'] } else { &['_'] }, vec!["italic"]))
+        } else {
+            None
+        };
+
+        if let Some((marker, marks)) = marker {
+            let start = i + marker.len();
+            if let Some(end) = find_closing(&chars, start, marker) {
+                flush(&mut current, &mut result);
+                let inner: String = chars[start..end].iter().collect();
+                let marks = marks.iter().map(|m| json!({ "type": m })).collect();
+                result.push(text_node(&inner, marks));
+                i = end + marker.len();
+            } else {
+                // Unclosed: the marker is just text
+                current.extend(marker);
+                i = start;
+            }
+            continue;
+        }
+
+        current.push(c);
+        i += 1;
+    }
+
+    flush(&mut current, &mut result);
+    result
+}
+
+fn starts_with(chars: &[char], at: usize, marker: &[char]) -> bool {
+    chars[at..].starts_with(marker)
+}
+
+/// Where the `marker` closing a span that starts at `from` is; spans can't be empty
+fn find_closing(chars: &[char], from: usize, marker: &[char]) -> Option<usize> {
+    (from + 1..chars.len()).find(|&i| starts_with(chars, i, marker))
+}
+
+fn link_at(chars: &[char], at: usize) -> Option<(String, String, usize)> {
+    let close = (at + 1..chars.len()).find(|&i| chars[i] == ']')?;
+    if chars.get(close + 1) != Some(&'(') || close == at + 1 || chars[at + 1] == '[' {
+        return None;
+    }
+    let end = (close + 2..chars.len()).find(|&i| chars[i] == ')')?;
+    let href: String = chars[close + 2..end].iter().collect();
+    if href.is_empty() || href.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((chars[at + 1..close].iter().collect(), href, end + 1))
+}
+
+fn text_node(text: &str, marks: Vec<Value>) -> Value {
+    let mut node = json!({ "type": "text", "text": text });
+    if !marks.is_empty() {
+        node["marks"] = Value::Array(marks);
+    }
+    node
+}
+
+fn flush(current: &mut String, result: &mut Vec<Value>) {
+    if !current.is_empty() {
+        result.push(text_node(current, Vec::new()));
+        current.clear();
+    }
+}
+
+// ============================================================================
+// Tiptap -> Markdown
+// ============================================================================
+
+/// Convert a Tiptap node (usually the `doc`) to Markdown
+pub fn tiptap_to_markdown(node: &Value, options: MarkdownOptions) -> String {
+    match node_type(node) {
+        "doc" => join_blocks(&blocks(children(node), options), options),
+        _ => join_blocks(&blocks(std::slice::from_ref(node), options), options),
+    }
+}
+
+fn node_type(node: &Value) -> &str {
+    node.get("type").and_then(Value::as_str).unwrap_or("")
+}
+
+fn children(node: &Value) -> &[Value] {
+    node.get("content")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn is_inline(node: &Value) -> bool {
+    matches!(node_type(node), "text" | "hardBreak" | "mention")
+}
+
+fn join_blocks(blocks: &[String], options: MarkdownOptions) -> String {
+    if options.keep_blank_lines {
+        blocks.join("\n")
+    } else {
+        blocks
+            .iter()
+            .filter(|b| !b.is_empty())
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+/// Markdown for each block of a node list; runs of inline nodes (as in a
+/// list item holding text directly) make one block
+fn blocks(nodes: &[Value], options: MarkdownOptions) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut inline: Vec<&Value> = Vec::new();
+
+    for node in nodes {
+        if is_inline(node) {
+            inline.push(node);
+            continue;
+        }
+        if !inline.is_empty() {
+            result.push(inline_markdown(inline.drain(..)));
+        }
+        result.push(block(node, options));
+    }
+    if !inline.is_empty() {
+        result.push(inline_markdown(inline.into_iter()));
+    }
+    result
+}
+
+fn block(node: &Value, options: MarkdownOptions) -> String {
+    let attr = |name: &str| node.get("attrs").and_then(|a| a.get(name));
+
+    match node_type(node) {
+        "heading" => {
+            let level = attr("level")
+                .and_then(Value::as_u64)
+                .unwrap_or(1)
+                .clamp(1, 6);
+            format!(
+                "{} {}",
+                "#".repeat(level as usize),
+                inline_markdown(children(node).iter())
+            )
+        }
+        "paragraph" => inline_markdown(children(node).iter()),
+        "bulletList" | "orderedList" | "taskList" => {
+            let start = attr("start").and_then(Value::as_u64).unwrap_or(1);
+            children(node)
+                .iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    let marker = match node_type(node) {
+                        "orderedList" => format!("{}. ", start + i as u64),
+                        "taskList" => {
+                            let checked = item
+                                .pointer("/attrs/checked")
+                                .and_then(Value::as_bool)
+                                .unwrap_or(false);
+                            format!("- [{}] ", if checked { 'x' } else { ' ' })
+                        }
+                        _ => "- ".to_string(),
+                    };
+                    let body = blocks(children(item), MarkdownOptions::default()).join("\n");
+                    prefix_lines(&body, &marker, &" ".repeat(marker.len()))
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        "blockquote" => {
+            let inner = join_blocks(&blocks(children(node), options), options);
+            inner
+                .split('\n')
+                .map(|line| {
+                    if line.is_empty() {
+                        ">".to_string()
+                    } else {
+                        format!("> {}", line)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        "codeBlock" => format!(
+            "```{}\n{}\n```",
+            attr("language").and_then(Value::as_str).unwrap_or(""),
+            extract_text_content(node)
+        ),
+        "horizontalRule" => "---".to_string(),
+        "image" => {
+            let text = |name: &str| attr(name).and_then(Value::as_str).unwrap_or("");
+            format!("![{}]({})", text("alt"), text("src"))
+        }
+        // Keep the text of anything else
+        _ if is_inline(node) => inline_markdown(std::iter::once(node)),
+        _ => join_blocks(&blocks(children(node), options), options),
+    }
+}
+
+/// Put `first` before the first line and `rest` before the others
+fn prefix_lines(text: &str, first: &str, rest: &str) -> String {
+    text.split('\n')
+        .enumerate()
+        .map(|(i, line)| match (i, line.is_empty()) {
+            (0, _) => format!("{}{}", first, line),
+            (_, true) => String::new(),
+            _ => format!("{}{}", rest, line),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn inline_markdown<'a>(nodes: impl Iterator<Item = &'a Value>) -> String {
+    let mut text = String::new();
+    for node in nodes {
+        match node_type(node) {
+            "text" => text.push_str(&marked_text(node)),
+            "hardBreak" => text.push('\n'),
+            _ => text.push_str(&extract_text_content(node)),
+        }
+    }
+    text
+}
+
+fn marked_text(node: &Value) -> String {
+    let text = node.get("text").and_then(Value::as_str).unwrap_or("");
+    let marks: Vec<&Map<String, Value>> = node
+        .get("marks")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object)
+        .collect();
+    let mark = |name: &str| {
+        marks
+            .iter()
+            .find(|m| m.get("type").and_then(Value::as_str) == Some(name))
+    };
+
+    let mut formatted = if mark("code").is_some() {
+        format!("`{}`", text)
+    } else {
+        let wrap = match (mark("bold").is_some(), mark("italic").is_some()) {
+            (true, true) => "***",
+            (true, false) => "**",
+            (false, true) => "*",
+            (false, false) => "",
+        };
+        format!("{}{}{}", wrap, text, wrap)
+    };
+    if mark("strike").is_some() {
+        formatted = format!("~~{}~~", formatted);
+    }
+    if let Some(href) = mark("link")
+        .and_then(|m| m.get("attrs"))
+        .and_then(|a| a.get("href"))
+        .and_then(Value::as_str)
+    {
+        formatted = format!("[{}]({})", formatted, href);
+    }
+    formatted
+}
+
+// ============================================================================
+// Plain text
+// ============================================================================
+
+/// Text of a node and its descendants, concatenated
+pub fn extract_text_content(node: &Value) -> String {
+    if let Some(text) = node.get("text").and_then(Value::as_str) {
+        return text.to_string();
+    }
+    children(node).iter().map(extract_text_content).collect()
+}
+
+/// Plain text of a document for search and diffs: one line per paragraph
+/// or heading, and list items prefixed with "- "
+pub fn extract_text_from_tiptap(node: &Value) -> String {
+    let mut text = String::new();
+
+    match node_type(node) {
+        "text" => {
+            if let Some(t) = node.get("text").and_then(Value::as_str) {
+                text.push_str(t);
+            }
+        }
+        "paragraph" | "heading" => {
+            for child in children(node) {
+                text.push_str(&extract_text_from_tiptap(child));
+            }
+            text.push('\n');
+        }
+        "bulletList" | "orderedList" | "taskList" => {
+            for child in children(node) {
+                text.push_str("- ");
+                text.push_str(&extract_text_from_tiptap(child));
+            }
+        }
+        _ => {
+            for child in children(node) {
+                text.push_str(&extract_text_from_tiptap(child));
+            }
+        }
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_standard() {
+        let markdown = "# Plan\n\nSome **bold**, *italic*, `code`, ~~gone~~ and a [link](https://example.com).\n\n\
+                        - One\n- Two\n  - Nested\n\n3. Third\n4. Fourth\n\n- [ ] Open\n- [x] Done\n\n\
+                        > Quoted\n>\n> Again\n\n```rust\nfn main() {}\n```\n\n---\n\n![Chart](midlight://img-0123456789abcdef)";
+        let doc = markdown_to_tiptap(markdown, MarkdownOptions::default());
+
+        let types: Vec<&str> = children(&doc).iter().map(node_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                "heading",
+                "paragraph",
+                "bulletList",
+                "orderedList",
+                "taskList",
+                "blockquote",
+                "codeBlock",
+                "horizontalRule",
+                "image"
+            ]
+        );
+        assert_eq!(
+            doc["content"][2]["content"][1]["content"][1]["type"],
+            "bulletList"
+        );
+        assert_eq!(doc["content"][3]["attrs"]["start"], 3);
+        assert_eq!(doc["content"][4]["content"][1]["attrs"]["checked"], true);
+        assert_eq!(doc["content"][5]["content"].as_array().unwrap().len(), 2);
+        assert_eq!(doc["content"][6]["attrs"]["language"], "rust");
+
+        assert_eq!(
+            tiptap_to_markdown(&doc, MarkdownOptions::default()),
+            markdown
+        );
+    }
+
+    #[test]
+    fn test_keep_blank_lines() {
+        let markdown = "Intro\n\n\nOutro";
+        let doc = markdown_to_tiptap(markdown, MarkdownOptions::workspace());
+        assert_eq!(doc["content"].as_array().unwrap().len(), 4);
+        assert_eq!(
+            tiptap_to_markdown(&doc, MarkdownOptions::workspace()),
+            markdown
+        );
+
+        let standard = markdown_to_tiptap(markdown, MarkdownOptions::default());
+        assert_eq!(standard["content"].as_array().unwrap().len(), 2);
+        assert_eq!(
+            tiptap_to_markdown(&standard, MarkdownOptions::default()),
+            "Intro\n\nOutro"
+        );
+    }
+
+    #[test]
+    fn test_inline_edge_cases() {
+        // Unclosed markers, snake_case and wiki links stay text
+        for text in [
+            "a `b",
+            "snake_case_name",
+            "see [[Other Doc]]",
+            "[@smith2020]",
+        ] {
+            let nodes = parse_inline_formatting(text);
+            assert_eq!(nodes.len(), 1, "{}", text);
+            assert_eq!(nodes[0]["text"], text);
+            assert!(nodes[0].get("marks").is_none());
+        }
+    }
+}
@@ -0,0 +1,777 @@
+// Metadata Store - The workspace's derived metadata in one database
+//
+// The link graph, tags, task index, pins and writing stats are kept in a
+// per-workspace SQLite database rather than a JSON file each, so an update
+// touches only the rows that changed instead of rewriting a whole file, and
+// new kinds of metadata get a table and a migration instead of another file
+// format. Services reach the database through the MetadataStore trait.
+//
+// The schema is versioned with SQLite's user_version and brought up to date
+// by MIGRATIONS when the store is opened. The JSON files the services used
+// before are imported by one of the migrations and then removed.
+//
+// Storage: .midlight/metadata.db
+// Paths are workspace-relative with "/" separators.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::{params, Connection, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use super::task_index::Task;
+use super::writing_stats::{GoalPeriod, Goals, WordCount, WritingGoal};
+
+/// How long to wait for another connection's write to finish
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// JSON files replaced by the store, imported by `import_sidecars`
+const SIDECARS: &[&str] = &[
+    "link-graph.json",
+    "tasks.json",
+    "pinned.json",
+    "writing-stats.json",
+];
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Links and tags of one document
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct DocumentLinks {
+    /// File modification time in milliseconds when they were recorded
+    pub modified: u64,
+    pub links: BTreeSet<String>,
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+}
+
+/// Tasks of one document
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct DocumentTasks {
+    /// File modification time in milliseconds when they were read
+    pub modified: u64,
+    pub tasks: Vec<Task>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pin {
+    pub path: String,
+    pub pinned_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WritingRecords {
+    #[serde(default)]
+    pub goals: Goals,
+    /// Latest length of each document, in words
+    #[serde(default)]
+    pub words: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub days: BTreeMap<NaiveDate, BTreeMap<String, WordCount>>,
+}
+
+/// Per-workspace metadata kept by the link graph, task index, pins and
+/// writing stats
+pub trait MetadataStore {
+    /// Links and tags of every recorded document
+    fn document_links(&self) -> Result<BTreeMap<String, DocumentLinks>, String>;
+
+    /// Record documents' links and tags, or forget them (None)
+    fn update_document_links(
+        &self,
+        updates: &BTreeMap<String, Option<DocumentLinks>>,
+    ) -> Result<(), String>;
+
+    /// Tasks of every indexed document
+    fn document_tasks(&self) -> Result<BTreeMap<String, DocumentTasks>, String>;
+
+    /// Record documents' tasks, or forget them (None)
+    fn update_document_tasks(
+        &self,
+        updates: &BTreeMap<String, Option<DocumentTasks>>,
+    ) -> Result<(), String>;
+
+    /// Pins, in order
+    fn pins(&self) -> Result<Vec<Pin>, String>;
+
+    /// Replace the pins
+    fn set_pins(&self, pins: &[Pin]) -> Result<(), String>;
+
+    fn writing_records(&self) -> Result<WritingRecords, String>;
+
+    /// Record a document's length, adding `count` to its tally for `date`
+    fn record_writing(
+        &self,
+        document: &str,
+        words: u64,
+        date: NaiveDate,
+        count: WordCount,
+    ) -> Result<(), String>;
+
+    /// Set or clear the goal of a document, or of the project if no
+    /// document is given
+    fn set_goal(&self, document: Option<&str>, goal: Option<WritingGoal>) -> Result<(), String>;
+}
+
+/// Open a workspace's metadata store, creating or migrating it as needed
+pub fn open(workspace_root: &Path) -> Result<Box<dyn MetadataStore>, String> {
+    Ok(Box::new(SqliteMetadataStore::open(workspace_root)?))
+}
+
+// ============================================================================
+// Migrations
+// ============================================================================
+
+type Migration = fn(&Connection, &Path) -> Result<(), String>;
+
+/// Schema changes in order; the database's user_version is the number applied
+const MIGRATIONS: &[Migration] = &[create_tables, import_sidecars];
+
+/// Version of a database that has had the sidecars imported
+const IMPORTED_VERSION: i64 = 2;
+
+fn create_tables(conn: &Connection, _workspace_root: &Path) -> Result<(), String> {
+    conn.execute_batch(
+        "CREATE TABLE link_documents (
+             path TEXT PRIMARY KEY,
+             modified INTEGER NOT NULL
+         );
+         CREATE TABLE links (
+             source TEXT NOT NULL,
+             target TEXT NOT NULL,
+             PRIMARY KEY (source, target)
+         );
+         CREATE TABLE tags (
+             path TEXT NOT NULL,
+             tag TEXT NOT NULL,
+             PRIMARY KEY (path, tag)
+         );
+         CREATE INDEX idx_tags_tag ON tags(tag);
+         CREATE TABLE task_documents (
+             path TEXT PRIMARY KEY,
+             modified INTEGER NOT NULL
+         );
+         CREATE TABLE tasks (
+             path TEXT NOT NULL,
+             idx INTEGER NOT NULL,
+             text TEXT NOT NULL,
+             done INTEGER NOT NULL,
+             due TEXT,
+             tags TEXT NOT NULL,
+             PRIMARY KEY (path, idx)
+         );
+         CREATE TABLE pins (
+             position INTEGER PRIMARY KEY,
+             path TEXT NOT NULL UNIQUE,
+             pinned_at TEXT NOT NULL
+         );
+         CREATE TABLE document_words (
+             path TEXT PRIMARY KEY,
+             words INTEGER NOT NULL
+         );
+         CREATE TABLE writing_days (
+             day TEXT NOT NULL,
+             path TEXT NOT NULL,
+             added INTEGER NOT NULL,
+             removed INTEGER NOT NULL,
+             PRIMARY KEY (day, path)
+         );
+         CREATE TABLE writing_goals (
+             path TEXT PRIMARY KEY,
+             words INTEGER NOT NULL,
+             period TEXT NOT NULL
+         );",
+    )
+    .map_err(|e| format!("Failed to create metadata tables: {}", e))
+}
+
+#[derive(Deserialize)]
+struct LegacyLinks {
+    #[serde(default)]
+    documents: BTreeMap<String, DocumentLinks>,
+}
+
+#[derive(Deserialize)]
+struct LegacyTasks {
+    #[serde(default)]
+    documents: BTreeMap<String, DocumentTasks>,
+}
+
+#[derive(Deserialize)]
+struct LegacyPins {
+    #[serde(default)]
+    pins: Vec<Pin>,
+}
+
+/// Bring in the JSON files the services kept before the store. A file that
+/// can't be parsed is skipped; its metadata is rebuilt from the documents
+/// where it can be.
+fn import_sidecars(conn: &Connection, workspace_root: &Path) -> Result<(), String> {
+    let midlight_dir = workspace_root.join(".midlight");
+    let read = |name: &str| fs::read_to_string(midlight_dir.join(name)).ok();
+
+    if let Some(legacy) =
+        read("link-graph.json").and_then(|json| serde_json::from_str::<LegacyLinks>(&json).ok())
+    {
+        for (path, links) in &legacy.documents {
+            put_links(conn, path, Some(links))?;
+        }
+    }
+    if let Some(legacy) =
+        read("tasks.json").and_then(|json| serde_json::from_str::<LegacyTasks>(&json).ok())
+    {
+        for (path, tasks) in &legacy.documents {
+            put_tasks(conn, path, Some(tasks))?;
+        }
+    }
+    if let Some(legacy) =
+        read("pinned.json").and_then(|json| serde_json::from_str::<LegacyPins>(&json).ok())
+    {
+        put_pins(conn, &legacy.pins)?;
+    }
+    if let Some(legacy) = read("writing-stats.json")
+        .and_then(|json| serde_json::from_str::<WritingRecords>(&json).ok())
+    {
+        for (path, words) in &legacy.words {
+            conn.execute(
+                "INSERT OR REPLACE INTO document_words (path, words) VALUES (?1, ?2)",
+                params![path, *words as i64],
+            )
+            .map_err(|e| format!("Failed to import writing stats: {}", e))?;
+        }
+        for (date, documents) in &legacy.days {
+            for (path, count) in documents {
+                add_words(conn, path, *date, *count)?;
+            }
+        }
+        if let Some(goal) = legacy.goals.project {
+            put_goal(conn, "", Some(goal))?;
+        }
+        for (path, goal) in &legacy.goals.documents {
+            put_goal(conn, path, Some(*goal))?;
+        }
+    }
+    Ok(())
+}
+
+// ============================================================================
+// SQLite Store
+// ============================================================================
+
+pub struct SqliteMetadataStore {
+    conn: Connection,
+}
+
+impl SqliteMetadataStore {
+    pub fn open(workspace_root: &Path) -> Result<Self, String> {
+        let midlight_dir = workspace_root.join(".midlight");
+        fs::create_dir_all(&midlight_dir)
+            .map_err(|e| format!("Failed to create .midlight directory: {}", e))?;
+        let mut conn = Connection::open(midlight_dir.join("metadata.db"))
+            .map_err(|e| format!("Failed to open metadata store: {}", e))?;
+        conn.busy_timeout(BUSY_TIMEOUT)
+            .and_then(|_| conn.execute_batch("PRAGMA journal_mode=WAL; PRAGMA synchronous=NORMAL;"))
+            .map_err(|e| format!("Failed to set pragmas: {}", e))?;
+
+        if migrate(&mut conn, workspace_root)? {
+            for name in SIDECARS {
+                let _ = fs::remove_file(midlight_dir.join(name));
+            }
+        }
+        Ok(Self { conn })
+    }
+
+    fn transaction(&self, f: impl FnOnce(&Connection) -> Result<(), String>) -> Result<(), String> {
+        self.conn
+            .execute_batch("BEGIN IMMEDIATE")
+            .map_err(|e| format!("Failed to update metadata: {}", e))?;
+        match f(&self.conn) {
+            Ok(()) => self
+                .conn
+                .execute_batch("COMMIT")
+                .map_err(|e| format!("Failed to update metadata: {}", e)),
+            Err(e) => {
+                let _ = self.conn.execute_batch("ROLLBACK");
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Apply migrations the database hasn't had. Returns whether the sidecar
+/// import was one of them.
+fn migrate(conn: &mut Connection, workspace_root: &Path) -> Result<bool, String> {
+    let current = |conn: &Connection| {
+        conn.query_row("PRAGMA user_version", [], |row| row.get::<_, i64>(0))
+            .map_err(|e| format!("Failed to read metadata version: {}", e))
+    };
+    if current(conn)? >= MIGRATIONS.len() as i64 {
+        return Ok(false);
+    }
+
+    // Another window may be migrating the same workspace; the version is
+    // read again once we hold the write lock
+    let tx = conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| format!("Failed to migrate metadata store: {}", e))?;
+    let from = current(&tx)?;
+    for (version, migration) in MIGRATIONS.iter().enumerate().skip(from as usize) {
+        migration(&tx, workspace_root)?;
+        tx.pragma_update(None, "user_version", version as i64 + 1)
+            .map_err(|e| format!("Failed to migrate metadata store: {}", e))?;
+    }
+    tx.commit()
+        .map_err(|e| format!("Failed to migrate metadata store: {}", e))?;
+
+    Ok(from < IMPORTED_VERSION)
+}
+
+impl MetadataStore for SqliteMetadataStore {
+    fn document_links(&self) -> Result<BTreeMap<String, DocumentLinks>, String> {
+        let error = |e: rusqlite::Error| format!("Failed to read link graph: {}", e);
+        let mut documents: BTreeMap<String, DocumentLinks> = BTreeMap::new();
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, modified FROM link_documents")
+            .map_err(error)?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })
+            .map_err(error)?;
+        for row in rows {
+            let (path, modified) = row.map_err(error)?;
+            documents.insert(
+                path,
+                DocumentLinks {
+                    modified: modified as u64,
+                    ..Default::default()
+                },
+            );
+        }
+
+        for (table, column) in [("links", "source, target"), ("tags", "path, tag")] {
+            let mut stmt = self
+                .conn
+                .prepare(&format!("SELECT {} FROM {}", column, table))
+                .map_err(error)?;
+            let rows = stmt
+                .query_map([], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                })
+                .map_err(error)?;
+            for row in rows {
+                let (path, value) = row.map_err(error)?;
+                if let Some(doc) = documents.get_mut(&path) {
+                    match table {
+                        "links" => doc.links.insert(value),
+                        _ => doc.tags.insert(value),
+                    };
+                }
+            }
+        }
+        Ok(documents)
+    }
+
+    fn update_document_links(
+        &self,
+        updates: &BTreeMap<String, Option<DocumentLinks>>,
+    ) -> Result<(), String> {
+        self.transaction(|conn| {
+            for (path, links) in updates {
+                put_links(conn, path, links.as_ref())?;
+            }
+            Ok(())
+        })
+    }
+
+    fn document_tasks(&self) -> Result<BTreeMap<String, DocumentTasks>, String> {
+        let error = |e: rusqlite::Error| format!("Failed to read task index: {}", e);
+        let mut documents: BTreeMap<String, DocumentTasks> = BTreeMap::new();
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, modified FROM task_documents")
+            .map_err(error)?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })
+            .map_err(error)?;
+        for row in rows {
+            let (path, modified) = row.map_err(error)?;
+            let doc = DocumentTasks {
+                modified: modified as u64,
+                tasks: Vec::new(),
+            };
+            documents.insert(path, doc);
+        }
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, idx, text, done, due, tags FROM tasks ORDER BY path, idx")
+            .map_err(error)?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(Task {
+                    document: row.get(0)?,
+                    index: row.get::<_, i64>(1)? as usize,
+                    text: row.get(2)?,
+                    done: row.get(3)?,
+                    due: row
+                        .get::<_, Option<String>>(4)?
+                        .and_then(|due| NaiveDate::parse_from_str(&due, "%Y-%m-%d").ok()),
+                    tags: serde_json::from_str(&row.get::<_, String>(5)?).unwrap_or_default(),
+                })
+            })
+            .map_err(error)?;
+        for row in rows {
+            let task = row.map_err(error)?;
+            if let Some(doc) = documents.get_mut(&task.document) {
+                doc.tasks.push(task);
+            }
+        }
+        Ok(documents)
+    }
+
+    fn update_document_tasks(
+        &self,
+        updates: &BTreeMap<String, Option<DocumentTasks>>,
+    ) -> Result<(), String> {
+        self.transaction(|conn| {
+            for (path, tasks) in updates {
+                put_tasks(conn, path, tasks.as_ref())?;
+            }
+            Ok(())
+        })
+    }
+
+    fn pins(&self) -> Result<Vec<Pin>, String> {
+        let error = |e: rusqlite::Error| format!("Failed to read pins: {}", e);
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, pinned_at FROM pins ORDER BY position")
+            .map_err(error)?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(error)?;
+        let mut pins = Vec::new();
+        for row in rows {
+            let (path, pinned_at) = row.map_err(error)?;
+            let pinned_at = DateTime::parse_from_rfc3339(&pinned_at)
+                .map_err(|e| format!("Failed to read pins: {}", e))?;
+            pins.push(Pin {
+                path,
+                pinned_at: pinned_at.with_timezone(&Utc),
+            });
+        }
+        Ok(pins)
+    }
+
+    fn set_pins(&self, pins: &[Pin]) -> Result<(), String> {
+        self.transaction(|conn| put_pins(conn, pins))
+    }
+
+    fn writing_records(&self) -> Result<WritingRecords, String> {
+        let error = |e: rusqlite::Error| format!("Failed to read writing stats: {}", e);
+        let mut records = WritingRecords::default();
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, words FROM document_words")
+            .map_err(error)?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })
+            .map_err(error)?;
+        for row in rows {
+            let (path, words) = row.map_err(error)?;
+            records.words.insert(path, words as u64);
+        }
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT day, path, added, removed FROM writing_days")
+            .map_err(error)?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    WordCount {
+                        added: row.get::<_, i64>(2)? as u64,
+                        removed: row.get::<_, i64>(3)? as u64,
+                    },
+                ))
+            })
+            .map_err(error)?;
+        for row in rows {
+            let (day, path, count) = row.map_err(error)?;
+            if let Ok(date) = NaiveDate::parse_from_str(&day, "%Y-%m-%d") {
+                records.days.entry(date).or_default().insert(path, count);
+            }
+        }
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, words, period FROM writing_goals")
+            .map_err(error)?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })
+            .map_err(error)?;
+        for row in rows {
+            let (path, words, period) = row.map_err(error)?;
+            let period = match period.as_str() {
+                "daily" => GoalPeriod::Daily,
+                _ => GoalPeriod::Total,
+            };
+            let goal = WritingGoal {
+                words: words as u64,
+                period,
+            };
+            match path.as_str() {
+                "" => records.goals.project = Some(goal),
+                _ => {
+                    records.goals.documents.insert(path, goal);
+                }
+            }
+        }
+        Ok(records)
+    }
+
+    fn record_writing(
+        &self,
+        document: &str,
+        words: u64,
+        date: NaiveDate,
+        count: WordCount,
+    ) -> Result<(), String> {
+        self.transaction(|conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO document_words (path, words) VALUES (?1, ?2)",
+                params![document, words as i64],
+            )
+            .map_err(|e| format!("Failed to write writing stats: {}", e))?;
+            if count != WordCount::default() {
+                add_words(conn, document, date, count)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn set_goal(&self, document: Option<&str>, goal: Option<WritingGoal>) -> Result<(), String> {
+        put_goal(&self.conn, document.unwrap_or(""), goal)
+    }
+}
+
+// ============================================================================
+// Writes
+// ============================================================================
+
+fn put_links(conn: &Connection, path: &str, links: Option<&DocumentLinks>) -> Result<(), String> {
+    let error = |e: rusqlite::Error| format!("Failed to write link graph: {}", e);
+    for sql in [
+        "DELETE FROM link_documents WHERE path = ?1",
+        "DELETE FROM links WHERE source = ?1",
+        "DELETE FROM tags WHERE path = ?1",
+    ] {
+        conn.execute(sql, params![path]).map_err(error)?;
+    }
+    let Some(links) = links else {
+        return Ok(());
+    };
+
+    conn.execute(
+        "INSERT INTO link_documents (path, modified) VALUES (?1, ?2)",
+        params![path, links.modified as i64],
+    )
+    .map_err(error)?;
+    for target in &links.links {
+        conn.execute(
+            "INSERT OR IGNORE INTO links (source, target) VALUES (?1, ?2)",
+            params![path, target],
+        )
+        .map_err(error)?;
+    }
+    for tag in &links.tags {
+        conn.execute(
+            "INSERT OR IGNORE INTO tags (path, tag) VALUES (?1, ?2)",
+            params![path, tag],
+        )
+        .map_err(error)?;
+    }
+    Ok(())
+}
+
+fn put_tasks(conn: &Connection, path: &str, tasks: Option<&DocumentTasks>) -> Result<(), String> {
+    let error = |e: rusqlite::Error| format!("Failed to write task index: {}", e);
+    for sql in [
+        "DELETE FROM task_documents WHERE path = ?1",
+        "DELETE FROM tasks WHERE path = ?1",
+    ] {
+        conn.execute(sql, params![path]).map_err(error)?;
+    }
+    let Some(tasks) = tasks else {
+        return Ok(());
+    };
+
+    conn.execute(
+        "INSERT INTO task_documents (path, modified) VALUES (?1, ?2)",
+        params![path, tasks.modified as i64],
+    )
+    .map_err(error)?;
+    for task in &tasks.tasks {
+        let tags = serde_json::to_string(&task.tags)
+            .map_err(|e| format!("Failed to serialize task tags: {}", e))?;
+        conn.execute(
+            "INSERT OR REPLACE INTO tasks (path, idx, text, done, due, tags)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                path,
+                task.index as i64,
+                task.text,
+                task.done,
+                task.due.map(|due| due.format("%Y-%m-%d").to_string()),
+                tags
+            ],
+        )
+        .map_err(error)?;
+    }
+    Ok(())
+}
+
+fn put_pins(conn: &Connection, pins: &[Pin]) -> Result<(), String> {
+    let error = |e: rusqlite::Error| format!("Failed to write pins: {}", e);
+    conn.execute("DELETE FROM pins", []).map_err(error)?;
+    for (position, pin) in pins.iter().enumerate() {
+        conn.execute(
+            "INSERT OR IGNORE INTO pins (position, path, pinned_at) VALUES (?1, ?2, ?3)",
+            params![position as i64, pin.path, pin.pinned_at.to_rfc3339()],
+        )
+        .map_err(error)?;
+    }
+    Ok(())
+}
+
+fn add_words(
+    conn: &Connection,
+    path: &str,
+    date: NaiveDate,
+    count: WordCount,
+) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO writing_days (day, path, added, removed) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT (day, path) DO UPDATE SET
+             added = added + excluded.added, removed = removed + excluded.removed",
+        params![
+            date.format("%Y-%m-%d").to_string(),
+            path,
+            count.added as i64,
+            count.removed as i64
+        ],
+    )
+    .map(|_| ())
+    .map_err(|e| format!("Failed to write writing stats: {}", e))
+}
+
+fn put_goal(conn: &Connection, path: &str, goal: Option<WritingGoal>) -> Result<(), String> {
+    let result = match goal {
+        Some(goal) => conn.execute(
+            "INSERT OR REPLACE INTO writing_goals (path, words, period) VALUES (?1, ?2, ?3)",
+            params![
+                path,
+                goal.words as i64,
+                match goal.period {
+                    GoalPeriod::Daily => "daily",
+                    GoalPeriod::Total => "total",
+                }
+            ],
+        ),
+        None => conn.execute("DELETE FROM writing_goals WHERE path = ?1", params![path]),
+    };
+    result
+        .map(|_| ())
+        .map_err(|e| format!("Failed to write writing goal: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_imports_sidecars_once() {
+        let temp = TempDir::new().unwrap();
+        let midlight = temp.path().join(".midlight");
+        fs::create_dir_all(&midlight).unwrap();
+        fs::write(
+            midlight.join("link-graph.json"),
+            r#"{ "version": 1, "documents": {
+                "a.midlight": { "modified": 1700000000000, "links": ["b.midlight", "[[plan]]"] }
+            } }"#,
+        )
+        .unwrap();
+        fs::write(
+            midlight.join("pinned.json"),
+            r#"{ "version": 1, "pins": [
+                { "path": "b.midlight", "pinnedAt": "2026-03-01T09:00:00Z" },
+                { "path": "a.midlight", "pinnedAt": "2026-03-02T09:00:00Z" }
+            ] }"#,
+        )
+        .unwrap();
+        fs::write(
+            midlight.join("writing-stats.json"),
+            r#"{ "version": 1, "goals": { "project": { "words": 500, "period": "daily" } },
+                 "words": { "a.midlight": 40 },
+                 "days": { "2026-03-14": { "a.midlight": { "added": 40, "removed": 2 } } } }"#,
+        )
+        .unwrap();
+        fs::write(midlight.join("tasks.json"), "not json").unwrap();
+
+        let store = open(temp.path()).unwrap();
+        assert!(SIDECARS.iter().all(|name| !midlight.join(name).exists()));
+
+        let links = store.document_links().unwrap();
+        assert_eq!(links["a.midlight"].modified, 1700000000000);
+        assert_eq!(links["a.midlight"].links.len(), 2);
+        let pins: Vec<_> = store.pins().unwrap().into_iter().map(|p| p.path).collect();
+        assert_eq!(pins, vec!["b.midlight", "a.midlight"]);
+        assert!(store.document_tasks().unwrap().is_empty());
+
+        let date = NaiveDate::from_ymd_opt(2026, 3, 14).unwrap();
+        let count = WordCount {
+            added: 5,
+            removed: 1,
+        };
+        store.record_writing("a.midlight", 44, date, count).unwrap();
+        let records = store.writing_records().unwrap();
+        assert_eq!(records.words["a.midlight"], 44);
+        assert_eq!(records.days[&date]["a.midlight"].added, 45);
+        assert_eq!(records.goals.project.unwrap().words, 500);
+
+        // Reopening doesn't run the import again
+        fs::write(
+            midlight.join("pinned.json"),
+            r#"{ "version": 1, "pins": [] }"#,
+        )
+        .unwrap();
+        let store = open(temp.path()).unwrap();
+        assert_eq!(store.pins().unwrap().len(), 2);
+        assert!(midlight.join("pinned.json").exists());
+    }
+}
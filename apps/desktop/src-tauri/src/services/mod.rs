@@ -1,21 +1,101 @@
 // Rust services for Midlight desktop
 
 pub mod agent_executor;
+pub mod agent_guard;
+pub mod agent_memory;
+pub mod app_dirs;
+pub mod atomic_write;
+pub mod audio_manager;
+pub mod audio_transcription;
 pub mod auth_service;
+pub mod automations;
+pub mod change_staging;
 pub mod checkpoint_manager;
+pub mod citations;
+pub mod context_profiles;
+pub mod context_window;
+pub mod conversation_store;
+pub mod crash_reports;
+pub mod custom_tools;
+pub mod deep_link;
+pub mod delete_impact;
+pub mod diagnostics;
+pub mod dir_listing;
+pub mod document_chunks;
+pub mod document_lock;
+pub mod document_outline;
+pub mod document_stats;
 pub mod docx_export;
 pub mod docx_import;
 pub mod embedding_service;
 pub mod error;
 pub mod error_reporter;
+pub mod execution_journal;
+pub mod export_filter;
+pub mod external_editor;
+pub mod file_stream;
 pub mod file_watcher;
+pub mod find_replace;
+pub mod flashcards;
+pub mod generation_params;
+pub mod hnsw_index;
 pub mod image_manager;
+pub mod image_ocr;
+pub mod image_optimizer;
+pub mod image_refs;
+pub mod import_assets;
+pub mod import_manifest;
 pub mod import_security;
 pub mod import_service;
 pub mod import_transaction;
+pub mod link_graph;
+pub mod link_rewrite;
+pub mod llm_cache;
+pub mod llm_routing;
 pub mod llm_service;
+pub mod local_api;
+pub mod log_files;
+pub mod markdown_convert;
+pub mod metadata_store;
+pub mod mount_info;
+pub mod network_config;
+pub mod notifications;
 pub mod object_store;
+pub mod obsidian_export;
+pub mod operations;
+pub mod path_glob;
+pub mod pdf_extractor;
+pub mod periodic_notes;
+pub mod pinned_documents;
+pub mod plugins;
+pub mod power_state;
+pub mod print_layout;
+pub mod prompt_templates;
+pub mod quota_tracker;
+pub mod rag_answer;
+pub mod rag_indexer;
 pub mod rag_service;
+pub mod readability;
+pub mod recent_documents;
 pub mod recovery_manager;
+pub mod request_queue;
+pub mod saved_searches;
+pub mod session_marker;
+pub mod spellcheck;
+pub mod structured_output;
+pub mod table_document;
+pub mod task_index;
+pub mod textbundle;
+pub mod token_counter;
+pub mod tool_audit_log;
+pub mod trash_manager;
+pub mod tree_cache;
+pub mod updates;
 pub mod vector_store;
+pub mod wal_cipher;
+pub mod web_clipper;
+pub mod web_fetch;
+pub mod workspace_archive;
 pub mod workspace_manager;
+pub mod workspace_paths;
+pub mod writing_stats;
@@ -0,0 +1,186 @@
+// Mount Info - Which kind of filesystem a path lives on
+//
+// Native change notifications (inotify, FSEvents, ReadDirectoryChangesW) miss
+// changes made by other machines on network shares, so the file watcher polls
+// workspaces on them instead. Linux reads /proc/self/mounts and macOS parses
+// the output of `mount`; on Windows only UNC paths are recognized.
+
+use std::path::Path;
+
+/// Filesystem types that are network shares or behave like them
+const NETWORK_FILESYSTEMS: &[&str] = &[
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "afpfs",
+    "webdav",
+    "davfs",
+    "fuse.sshfs",
+    "sshfs",
+    "fuse.rclone",
+    "9p",
+    "afs",
+    "ncpfs",
+];
+
+/// A mounted filesystem
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mount {
+    pub mount_point: String,
+    pub fs_type: String,
+}
+
+/// Whether a path is on a network filesystem
+pub fn is_network_filesystem(path: &Path) -> bool {
+    #[cfg(windows)]
+    {
+        let path = path.to_string_lossy();
+        (path.starts_with(r"\\") && !path.starts_with(r"\\?\")) || path.starts_with(r"\\?\UNC\")
+    }
+
+    #[cfg(not(windows))]
+    {
+        filesystem_type(path).is_some_and(|fs_type| is_network_type(&fs_type))
+    }
+}
+
+/// The type of the filesystem a path is on, e.g. "ext4" or "nfs4"
+#[cfg(not(windows))]
+pub fn filesystem_type(path: &Path) -> Option<String> {
+    let path = path.canonicalize().ok()?;
+    let mounts = mounts()?;
+    mount_for(&mounts, &path.to_string_lossy()).map(|m| m.fs_type.clone())
+}
+
+#[cfg(target_os = "linux")]
+fn mounts() -> Option<Vec<Mount>> {
+    let content = std::fs::read_to_string("/proc/self/mounts").ok()?;
+    Some(parse_proc_mounts(&content))
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn mounts() -> Option<Vec<Mount>> {
+    let output = std::process::Command::new("mount").output().ok()?;
+    Some(parse_mount_output(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Whether a filesystem type is a network filesystem
+pub fn is_network_type(fs_type: &str) -> bool {
+    NETWORK_FILESYSTEMS.contains(&fs_type.to_lowercase().as_str())
+}
+
+/// The mount with the longest mount point containing `path`
+pub fn mount_for<'a>(mounts: &'a [Mount], path: &str) -> Option<&'a Mount> {
+    mounts
+        .iter()
+        .filter(|m| {
+            let point = m.mount_point.trim_end_matches('/');
+            path == point || path.starts_with(&format!("{}/", point)) || m.mount_point == "/"
+        })
+        .max_by_key(|m| m.mount_point.len())
+}
+
+/// Parse /proc/mounts: "device mount_point fs_type options dump pass", with
+/// spaces and other special characters in paths escaped as octal
+#[cfg(any(test, target_os = "linux"))]
+pub fn parse_proc_mounts(content: &str) -> Vec<Mount> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let _device = fields.next()?;
+            let mount_point = unescape_octal(fields.next()?);
+            let fs_type = fields.next()?.to_string();
+            Some(Mount {
+                mount_point,
+                fs_type,
+            })
+        })
+        .collect()
+}
+
+/// Parse BSD/macOS `mount` output: "device on /mount/point (fs_type, options)"
+#[cfg(any(test, all(unix, not(target_os = "linux"))))]
+pub fn parse_mount_output(content: &str) -> Vec<Mount> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (_, rest) = line.split_once(" on ")?;
+            let (mount_point, options) = rest.rsplit_once(" (")?;
+            let fs_type = options.split([',', ')']).next()?.trim().to_string();
+            Some(Mount {
+                mount_point: mount_point.to_string(),
+                fs_type,
+            })
+        })
+        .collect()
+}
+
+#[cfg(any(test, target_os = "linux"))]
+fn unescape_octal(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let octal = bytes
+            .get(i + 1..i + 4)
+            .and_then(|o| std::str::from_utf8(o).ok())
+            .and_then(|o| u8::from_str_radix(o, 8).ok());
+        match (bytes[i], octal) {
+            (b'\\', Some(byte)) => {
+                out.push(byte);
+                i += 4;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc_mounts() {
+        let mounts = parse_proc_mounts(
+            "/dev/sda1 / ext4 rw,relatime 0 0\n\
+             server:/export /mnt/team\\040share nfs4 rw 0 0\n\
+             //nas/docs /mnt/nas cifs rw 0 0\n",
+        );
+        assert_eq!(mounts.len(), 3);
+        assert_eq!(mounts[1].mount_point, "/mnt/team share");
+
+        let mount = |path| mount_for(&mounts, path).map(|m| m.fs_type.as_str());
+        assert_eq!(mount("/mnt/team share/notes"), Some("nfs4"));
+        assert_eq!(mount("/mnt/nas"), Some("cifs"));
+        assert_eq!(mount("/mnt/nasty/notes"), Some("ext4"));
+        assert_eq!(mount("/home/me"), Some("ext4"));
+    }
+
+    #[test]
+    fn test_parse_mount_output() {
+        let mounts = parse_mount_output(
+            "/dev/disk3s1s1 on / (apfs, sealed, local, read-only, journaled)\n\
+             //me@nas._smb._tcp.local/Docs on /Volumes/Docs (smbfs, nodev, nosuid, mounted by me)\n",
+        );
+        assert_eq!(
+            mounts[1],
+            Mount {
+                mount_point: "/Volumes/Docs".to_string(),
+                fs_type: "smbfs".to_string(),
+            }
+        );
+        assert!(is_network_type(&mounts[1].fs_type));
+        assert!(!is_network_type(&mounts[0].fs_type));
+    }
+}
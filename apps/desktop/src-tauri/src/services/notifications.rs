@@ -0,0 +1,133 @@
+// Notifications - OS notifications when long-running operations finish
+//
+// Imports, exports, indexing and sending requests queued while offline can
+// take a while, and the user has often switched to another app in the
+// meantime. When one finishes or fails while the window is in the background,
+// a system notification says so. They can be turned off in settings.
+//
+// Storage: `notifications.json` in the app data directory.
+
+use crate::services::app_dirs::app_data_dir;
+use crate::services::atomic_write::write_atomic;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationSettings {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+        }
+    }
+}
+
+/// Operations that notify when they finish
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operation {
+    Import,
+    Export,
+    Indexing,
+    Sync,
+}
+
+/// Title and body of the notification for a finished operation: the result
+/// holds what was done, or the error
+pub fn notification_text(
+    operation: Operation,
+    result: &Result<String, String>,
+) -> (String, String) {
+    let (done, failed) = match operation {
+        Operation::Import => ("Import complete", "Import failed"),
+        Operation::Export => ("Export complete", "Export failed"),
+        Operation::Indexing => ("Indexing complete", "Indexing failed"),
+        Operation::Sync => ("Sync complete", "Sync failed"),
+    };
+    match result {
+        Ok(detail) => (done.to_string(), detail.clone()),
+        Err(error) => (failed.to_string(), error.clone()),
+    }
+}
+
+// ============================================================================
+// Settings
+// ============================================================================
+
+/// Path of the saved notification settings
+pub fn notification_settings_path() -> PathBuf {
+    app_data_dir().join("notifications.json")
+}
+
+impl NotificationSettings {
+    pub fn load(path: &Path) -> Self {
+        if !path.exists() {
+            return Self::default();
+        }
+
+        std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| {
+                warn!(
+                    "Failed to load notification settings, using defaults: {}",
+                    e
+                );
+                Self::default()
+            })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize notification settings: {}", e))?;
+        write_atomic(path, json).map_err(|e| format!("Failed to save notification settings: {}", e))
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_settings_and_text() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("notifications.json");
+        assert!(NotificationSettings::load(&path).enabled);
+
+        NotificationSettings { enabled: false }.save(&path).unwrap();
+        assert!(!NotificationSettings::load(&path).enabled);
+
+        std::fs::write(&path, "{}").unwrap();
+        assert!(NotificationSettings::load(&path).enabled);
+
+        assert_eq!(
+            notification_text(Operation::Import, &Ok("Imported 12 files".to_string())),
+            (
+                "Import complete".to_string(),
+                "Imported 12 files".to_string()
+            )
+        );
+        assert_eq!(
+            notification_text(Operation::Indexing, &Err("Not signed in".to_string())).0,
+            "Indexing failed"
+        );
+    }
+}
@@ -45,6 +45,12 @@ impl ObjectStore {
     /// Store content and return its hash
     /// If content already exists (same hash), returns hash without re-storing
     pub async fn write(&self, content: &str) -> Result<String> {
+        self.write_now(content)
+    }
+
+    /// Store content without going through the async runtime, for callers
+    /// that aren't async
+    pub fn write_now(&self, content: &str) -> Result<String> {
         let hash = self.hash(content);
         let object_path = self.get_object_path(&hash);
 
@@ -72,6 +78,11 @@ impl ObjectStore {
 
     /// Read content by hash
     pub async fn read(&self, hash: &str) -> Result<String> {
+        self.read_now(hash)
+    }
+
+    /// Read content by hash without going through the async runtime
+    pub fn read_now(&self, hash: &str) -> Result<String> {
         let object_path = self.get_object_path(hash);
 
         if !object_path.exists() {
@@ -90,12 +101,17 @@ impl ObjectStore {
     /// Check if object exists
     #[allow(dead_code)]
     pub async fn exists(&self, hash: &str) -> bool {
+        self.exists_now(hash)
+    }
+
+    /// Check if object exists without going through the async runtime
+    pub fn exists_now(&self, hash: &str) -> bool {
         self.get_object_path(hash).exists()
     }
 
     /// Get the file path for an object hash
     /// Uses git-like structure: first 2 chars as subdirectory
-    fn get_object_path(&self, hash: &str) -> PathBuf {
+    pub(crate) fn get_object_path(&self, hash: &str) -> PathBuf {
         if hash.len() < 2 {
             return self.objects_dir.join(hash);
         }
@@ -0,0 +1,464 @@
+// Obsidian Export - A workspace written out as an Obsidian vault
+//
+// The counterpart of the Obsidian importer, so a workspace can leave the app
+// as plain Markdown that Obsidian opens as it is. .midlight documents are
+// converted to Markdown with their properties as YAML front matter; links
+// between documents become [[wiki links]], resolved the same way as the link
+// graph, so they keep working once every document is a .md file. Stored
+// images are copied into attachments/ and referenced by relative path.
+// Markdown files and other files in the workspace are copied unchanged.
+//
+// A .obsidian folder with an app.json pointing new attachments at
+// attachments/ is written so the folder opens as a vault.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+use super::document_chunks::resolve_content;
+use super::export_filter::ExportFilter;
+use super::link_graph::{midlight_tags, resolve_href, Resolver};
+use super::markdown_convert::{tiptap_to_markdown, MarkdownOptions};
+use super::operations::OperationHandle;
+use super::textbundle::stored_image;
+use super::workspace_paths::document_key;
+
+/// Folder images are copied into, relative to the vault root
+const ATTACHMENTS_DIR: &str = "attachments";
+
+/// Properties of a document's meta that aren't written to front matter
+const SKIPPED_META: &[&str] = &["tags"];
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObsidianExportResult {
+    pub vault_path: String,
+    /// Documents converted from .midlight
+    pub documents: usize,
+    /// Markdown and other files copied as they are
+    pub files_copied: usize,
+    pub attachments: usize,
+    /// Links between documents written as wiki links
+    pub links_converted: usize,
+    /// Documents the export filter left out
+    pub excluded: usize,
+    pub warnings: Vec<String>,
+}
+
+/// Write the workspace at `workspace_root`, or the part of it `filter`
+/// selects, to a new or empty folder
+pub fn export_vault(
+    workspace_root: &Path,
+    vault: &Path,
+    filter: &ExportFilter,
+    operation: &OperationHandle,
+) -> Result<ObsidianExportResult, String> {
+    if !workspace_root.is_dir() {
+        return Err(format!("Workspace not found: {}", workspace_root.display()));
+    }
+    if vault.starts_with(workspace_root) {
+        return Err("Can't export the vault inside the workspace".to_string());
+    }
+    if vault.exists()
+        && fs::read_dir(vault)
+            .map_err(|e| format!("Failed to read {}: {}", vault.display(), e))?
+            .next()
+            .is_some()
+    {
+        return Err(format!("{} is not empty", vault.display()));
+    }
+
+    let selection = filter.is_active().then(|| filter.select(workspace_root));
+
+    // Every file's path in the vault, decided up front so links can point
+    // at documents not yet written. Links to documents left out stay as
+    // they are.
+    let mut outputs: BTreeMap<String, String> = BTreeMap::new();
+    let mut taken: HashMap<String, usize> = HashMap::new();
+    let walker = WalkDir::new(workspace_root)
+        .min_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| !e.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file());
+    for entry in walker {
+        let Ok(relative) = entry.path().strip_prefix(workspace_root) else {
+            continue;
+        };
+        let key = document_key(&relative.to_string_lossy());
+        if selection
+            .as_ref()
+            .is_some_and(|selection| !selection.files.contains(&key))
+        {
+            continue;
+        }
+        let output = match key.strip_suffix(".midlight") {
+            Some(stem) => format!("{}.md", stem),
+            None => key.clone(),
+        };
+        outputs.insert(key, output);
+    }
+    // .md files keep their names; a converted document that would clash
+    // with one gets a number
+    for output in outputs.values() {
+        *taken.entry(output.to_lowercase()).or_default() += 1;
+    }
+    for (key, output) in outputs.iter_mut() {
+        if key.ends_with(".midlight") && taken[&output.to_lowercase()] > 1 {
+            let stem = output.trim_end_matches(".md").to_string();
+            let mut n = 1;
+            while taken.contains_key(&format!("{} {}.md", stem, n).to_lowercase()) {
+                n += 1;
+            }
+            *output = format!("{} {}.md", stem, n);
+            taken.insert(output.to_lowercase(), 1);
+        }
+    }
+
+    let mut result = ObsidianExportResult {
+        vault_path: vault.to_string_lossy().to_string(),
+        excluded: selection.map_or(0, |selection| selection.excluded_documents),
+        ..Default::default()
+    };
+    let links = WikiLinks::new(&outputs);
+    let images_dir = workspace_root.join(".midlight").join("images");
+
+    fs::create_dir_all(vault).map_err(|e| format!("Failed to create vault: {}", e))?;
+    for (i, (key, output)) in outputs.iter().enumerate() {
+        operation.check_cancelled()?;
+        operation.progress("converting", i, outputs.len(), Some(key.clone()));
+        let source = workspace_root.join(key);
+        let target = vault.join(output);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create folder: {}", e))?;
+        }
+
+        if !key.ends_with(".midlight") {
+            fs::copy(&source, &target).map_err(|e| format!("Failed to copy {}: {}", key, e))?;
+            result.files_copied += 1;
+            continue;
+        }
+
+        let document = fs::read_to_string(&source)
+            .ok()
+            .and_then(|json| serde_json::from_str::<Value>(&json).ok());
+        let Some(mut document) = document else {
+            result
+                .warnings
+                .push(format!("Skipped {}: not a readable document", key));
+            continue;
+        };
+        resolve_content(&source, &mut document);
+        let mut content = document.get("content").cloned().unwrap_or(json!({}));
+
+        result.links_converted += links.convert(key, &mut content);
+        let mut names = HashMap::new();
+        for hash in stored_images(&content) {
+            match stored_image(&images_dir, &hash) {
+                Some((extension, data)) => {
+                    let name = format!("{}.{}", hash, extension);
+                    let attachment = vault.join(ATTACHMENTS_DIR).join(&name);
+                    if !attachment.exists() {
+                        fs::create_dir_all(vault.join(ATTACHMENTS_DIR))
+                            .and_then(|_| fs::write(&attachment, data))
+                            .map_err(|e| format!("Failed to copy image: {}", e))?;
+                        result.attachments += 1;
+                    }
+                    names.insert(hash, name);
+                }
+                None => result
+                    .warnings
+                    .push(format!("{}: missing image {}", key, hash)),
+            }
+        }
+        relink_images(&mut content, output.matches('/').count(), &names);
+
+        let markdown = format!(
+            "{}{}\n",
+            front_matter(&document, &content),
+            tiptap_to_markdown(&content, MarkdownOptions::default()).trim_end()
+        );
+        fs::write(&target, markdown).map_err(|e| format!("Failed to write {}: {}", output, e))?;
+        result.documents += 1;
+    }
+
+    write_vault_config(vault)?;
+    Ok(result)
+}
+
+// ============================================================================
+// Front matter
+// ============================================================================
+
+/// The document's meta as YAML front matter, with its tags
+fn front_matter(document: &Value, content: &Value) -> String {
+    let mut properties = serde_yaml::Mapping::new();
+    let meta = document.get("meta").and_then(Value::as_object);
+    for (name, value) in meta.into_iter().flatten() {
+        if SKIPPED_META.contains(&name.as_str()) {
+            continue;
+        }
+        let value = match value {
+            Value::String(_) | Value::Number(_) | Value::Bool(_) => value.clone(),
+            Value::Array(items) if items.iter().all(Value::is_string) => value.clone(),
+            _ => continue,
+        };
+        if let Ok(value) = serde_yaml::to_value(value) {
+            properties.insert(name.as_str().into(), value);
+        }
+    }
+
+    let tags: Vec<serde_yaml::Value> = midlight_tags(content)
+        .into_iter()
+        .map(serde_yaml::Value::String)
+        .collect();
+    if !tags.is_empty() {
+        properties.insert("tags".into(), serde_yaml::Value::Sequence(tags));
+    }
+
+    if properties.is_empty() {
+        return String::new();
+    }
+    match serde_yaml::to_string(&properties) {
+        Ok(yaml) => format!("---\n{}---\n\n", yaml),
+        Err(_) => String::new(),
+    }
+}
+
+// ============================================================================
+// Links
+// ============================================================================
+
+/// Turns link marks between documents into wiki links
+struct WikiLinks<'a> {
+    resolver: Resolver<'a>,
+    /// Vault path without .md, or just the name where it's unique
+    names: HashMap<&'a str, String>,
+}
+
+impl<'a> WikiLinks<'a> {
+    fn new(outputs: &'a BTreeMap<String, String>) -> Self {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        let markdown = || outputs.iter().filter(|(_, o)| o.ends_with(".md"));
+        for (_, output) in markdown() {
+            *counts.entry(file_stem(output).to_lowercase()).or_default() += 1;
+        }
+        let names = markdown()
+            .map(|(key, output)| {
+                let stem = file_stem(output);
+                let name = match counts[&stem.to_lowercase()] {
+                    1 => stem.to_string(),
+                    _ => output.trim_end_matches(".md").to_string(),
+                };
+                (key.as_str(), name)
+            })
+            .collect();
+        Self {
+            resolver: Resolver::new(
+                outputs
+                    .keys()
+                    .filter(|k| k.ends_with(".midlight") || k.ends_with(".md")),
+            ),
+            names,
+        }
+    }
+
+    /// Rewrite the links in a document's content, returning how many were
+    /// converted
+    fn convert(&self, key: &str, node: &mut Value) -> usize {
+        let mut converted = 0;
+        self.convert_node(key, node, &mut converted);
+        converted
+    }
+
+    fn convert_node(&self, key: &str, node: &mut Value, converted: &mut usize) {
+        if let Some(children) = node.get_mut("content").and_then(Value::as_array_mut) {
+            for child in children {
+                self.convert_node(key, child, converted);
+            }
+        }
+        let Some(href) = node
+            .get("marks")
+            .and_then(Value::as_array)
+            .and_then(|marks| marks.iter().find(|m| m["type"] == "link"))
+            .and_then(|mark| mark.pointer("/attrs/href"))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+        else {
+            return;
+        };
+        let Some(target) = resolve_href(key, &href)
+            .and_then(|target| self.resolver.resolve(&target))
+            .and_then(|target| self.names.get(target))
+        else {
+            return;
+        };
+        let label = node.get("text").and_then(Value::as_str).unwrap_or_default();
+        if label.contains(['|', '[', ']']) {
+            return;
+        }
+
+        let heading = href
+            .split_once('#')
+            .map(|(_, h)| format!("#{}", h))
+            .unwrap_or_default();
+        let text = if label.eq_ignore_ascii_case(target) && heading.is_empty() {
+            format!("[[{}]]", target)
+        } else {
+            format!("[[{}{}|{}]]", target, heading, label)
+        };
+        node["text"] = json!(text);
+        if let Some(marks) = node.get_mut("marks").and_then(Value::as_array_mut) {
+            marks.retain(|m| m["type"] != "link");
+        }
+        *converted += 1;
+    }
+}
+
+fn file_stem(path: &str) -> &str {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.strip_suffix(".md").unwrap_or(name)
+}
+
+// ============================================================================
+// Images
+// ============================================================================
+
+/// Hashes of the stored images a document shows
+fn stored_images(node: &Value) -> Vec<String> {
+    let mut hashes = Vec::new();
+    if node["type"] == "image" {
+        let src = node.pointer("/attrs/src").and_then(Value::as_str);
+        if let Some(hash) = src.and_then(|s| s.strip_prefix("midlight://img-")) {
+            hashes.push(hash.to_string());
+        }
+    }
+    for child in node
+        .get("content")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        hashes.extend(stored_images(child));
+    }
+    hashes
+}
+
+/// Point stored images at their copies in attachments/, relative to a note
+/// `depth` folders down
+fn relink_images(node: &mut Value, depth: usize, names: &HashMap<String, String>) {
+    if node["type"] == "image" {
+        let src = node.pointer("/attrs/src").and_then(Value::as_str);
+        let name = src
+            .and_then(|s| s.strip_prefix("midlight://img-"))
+            .and_then(|hash| names.get(hash));
+        if let Some(name) = name {
+            let src = format!("{}{}/{}", "../".repeat(depth), ATTACHMENTS_DIR, name);
+            node["attrs"]["src"] = json!(src);
+        }
+    }
+    if let Some(children) = node.get_mut("content").and_then(Value::as_array_mut) {
+        for child in children {
+            relink_images(child, depth, names);
+        }
+    }
+}
+
+// ============================================================================
+// Vault config
+// ============================================================================
+
+fn write_vault_config(vault: &Path) -> Result<(), String> {
+    let config_dir = vault.join(".obsidian");
+    let app = json!({
+        "attachmentFolderPath": ATTACHMENTS_DIR,
+        "newLinkFormat": "shortest",
+        "useMarkdownLinks": false,
+    });
+    fs::create_dir_all(&config_dir)
+        .and_then(|_| {
+            fs::write(
+                config_dir.join("app.json"),
+                serde_json::to_string_pretty(&app).unwrap_or_default(),
+            )
+        })
+        .map_err(|e| format!("Failed to write vault settings: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::operations::OperationKind;
+    use tempfile::TempDir;
+
+    fn write(root: &Path, key: &str, content: &str) {
+        let path = root.join(key);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn midlight(blocks: Value) -> String {
+        json!({
+            "version": 1,
+            "meta": { "created": "2026-03-01T09:00:00Z", "title": "Plan" },
+            "content": { "type": "doc", "content": blocks }
+        })
+        .to_string()
+    }
+
+    fn link(text: &str, href: &str) -> Value {
+        json!({ "type": "text", "text": text,
+                "marks": [{ "type": "link", "attrs": { "href": href } }] })
+    }
+
+    #[test]
+    fn test_export_vault() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("Notes");
+        write(
+            &root,
+            "Projects/Plan.midlight",
+            &midlight(json!([
+                { "type": "paragraph", "content": [
+                    link("Ideas", "../Ideas.midlight"),
+                    { "type": "text", "text": " and " },
+                    link("the brief", "brief.md#Scope"),
+                    { "type": "text", "text": " #launch" }
+                ]},
+                { "type": "image", "attrs": { "src": "midlight://img-3f2a" } }
+            ])),
+        );
+        write(&root, "Ideas.midlight", &midlight(json!([])));
+        write(&root, "Ideas.md", "Already Markdown");
+        write(&root, "Projects/brief.md", "# Brief");
+        write(&root, ".midlight/images/3f2a.jpg", "jpeg");
+
+        let vault = temp.path().join("Vault");
+        let operation = OperationHandle::detached(OperationKind::Export);
+        let result = export_vault(&root, &vault, &ExportFilter::default(), &operation).unwrap();
+        assert_eq!((result.documents, result.files_copied), (2, 2));
+        assert_eq!((result.attachments, result.links_converted), (1, 2));
+
+        let plan = fs::read_to_string(vault.join("Projects/Plan.md")).unwrap();
+        assert!(plan.starts_with("---\n"));
+        assert!(plan.contains("title: Plan\n"));
+        assert!(plan.contains("tags:\n- launch\n"));
+        assert!(plan.contains("[[Ideas 1|Ideas]] and [[brief#Scope|the brief]] #launch"));
+        assert!(plan.contains("![](../attachments/3f2a.jpg)"));
+
+        // The Markdown file keeps its name; the converted one is numbered
+        assert_eq!(
+            fs::read_to_string(vault.join("Ideas.md")).unwrap(),
+            "Already Markdown"
+        );
+        assert!(vault.join("Ideas 1.md").exists());
+        assert!(vault.join("attachments/3f2a.jpg").exists());
+        assert!(vault.join(".obsidian/app.json").exists());
+
+        assert!(export_vault(&root, &vault, &ExportFilter::default(), &operation).is_err());
+    }
+}
@@ -0,0 +1,341 @@
+// Operations - Registry of long-running, cancellable work
+//
+// Imports, exports and indexing register an operation when they start and
+// get back a handle. Progress reported through the handle goes out as one
+// uniform event shape whatever the subsystem, and the handle carries the
+// cancellation token the work checks between steps, so the frontend can
+// follow and cancel any of them by id.
+//
+// Operations are only tracked in memory, and only while they run.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use super::import_service::CancellationToken;
+
+/// Error returned by work that stopped because its operation was cancelled
+pub const CANCELLED: &str = "Cancelled";
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OperationKind {
+    Import,
+    Export,
+    Indexing,
+    Sync,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OperationState {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// An operation's latest progress
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationProgress {
+    pub id: u64,
+    pub kind: OperationKind,
+    /// What the operation is, e.g. "Exporting Notes"
+    pub label: String,
+    /// The subsystem's current step, e.g. "converting"
+    pub phase: String,
+    pub current: usize,
+    /// 0 until the amount of work is known
+    pub total: usize,
+    pub message: Option<String>,
+    pub state: OperationState,
+    /// Why the operation failed, once it has
+    pub error: Option<String>,
+}
+
+/// Receives every update of an operation, including its start and end
+pub type ProgressSink = Box<dyn Fn(&OperationProgress) + Send + Sync>;
+
+struct Operation {
+    progress: Mutex<OperationProgress>,
+    cancel: Arc<CancellationToken>,
+    sink: Option<ProgressSink>,
+}
+
+impl Operation {
+    fn update(&self, change: impl FnOnce(&mut OperationProgress)) {
+        let progress = {
+            let mut progress = self.progress.lock().unwrap();
+            change(&mut progress);
+            progress.clone()
+        };
+        if let Some(sink) = &self.sink {
+            sink(&progress);
+        }
+    }
+
+    fn state(&self) -> OperationState {
+        self.progress.lock().unwrap().state
+    }
+}
+
+// ============================================================================
+// Operation Manager
+// ============================================================================
+
+#[derive(Default)]
+pub struct OperationManager {
+    next_id: AtomicU64,
+    active: Mutex<HashMap<u64, Arc<Operation>>>,
+}
+
+impl OperationManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register an operation and announce it through `sink`
+    pub fn start(
+        self: &Arc<Self>,
+        kind: OperationKind,
+        label: impl Into<String>,
+        sink: ProgressSink,
+    ) -> OperationHandle {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let operation = Arc::new(Operation {
+            progress: Mutex::new(OperationProgress {
+                id,
+                kind,
+                label: label.into(),
+                phase: "starting".to_string(),
+                current: 0,
+                total: 0,
+                message: None,
+                state: OperationState::Running,
+                error: None,
+            }),
+            cancel: CancellationToken::new(),
+            sink: Some(sink),
+        });
+        self.active.lock().unwrap().insert(id, operation.clone());
+        operation.update(|_| {});
+        OperationHandle {
+            id,
+            operation,
+            manager: Some(self.clone()),
+        }
+    }
+
+    /// Ask a running operation to stop. It finishes as cancelled once the
+    /// work notices. Returns false if there's no such operation.
+    pub fn cancel(&self, id: u64) -> bool {
+        match self.active.lock().unwrap().get(&id) {
+            Some(operation) => {
+                operation.cancel.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Cancel every running operation of a kind, returning how many there were
+    pub fn cancel_kind(&self, kind: OperationKind) -> usize {
+        let active = self.active.lock().unwrap();
+        let matching = active
+            .values()
+            .filter(|o| o.progress.lock().unwrap().kind == kind);
+        let mut count = 0;
+        for operation in matching {
+            operation.cancel.cancel();
+            count += 1;
+        }
+        count
+    }
+
+    /// Operations still running, oldest first
+    pub fn list(&self) -> Vec<OperationProgress> {
+        let mut operations: Vec<_> = self
+            .active
+            .lock()
+            .unwrap()
+            .values()
+            .map(|o| o.progress.lock().unwrap().clone())
+            .collect();
+        operations.sort_by_key(|p| p.id);
+        operations
+    }
+}
+
+// ============================================================================
+// Operation Handle
+// ============================================================================
+
+/// A running operation, held by the work it tracks. Dropping the handle
+/// unregisters the operation; if it wasn't finished it ends as failed, or
+/// cancelled if it was asked to stop.
+pub struct OperationHandle {
+    id: u64,
+    operation: Arc<Operation>,
+    manager: Option<Arc<OperationManager>>,
+}
+
+impl OperationHandle {
+    /// A handle that isn't registered or reported anywhere, for running
+    /// work outside of a command
+    pub fn detached(kind: OperationKind) -> Self {
+        Self {
+            id: 0,
+            operation: Arc::new(Operation {
+                progress: Mutex::new(OperationProgress {
+                    id: 0,
+                    kind,
+                    label: String::new(),
+                    phase: String::new(),
+                    current: 0,
+                    total: 0,
+                    message: None,
+                    state: OperationState::Running,
+                    error: None,
+                }),
+                cancel: CancellationToken::new(),
+                sink: None,
+            }),
+            manager: None,
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The token to hand to work that takes one directly
+    pub fn cancel_token(&self) -> Arc<CancellationToken> {
+        self.operation.cancel.clone()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.operation.cancel.is_cancelled()
+    }
+
+    /// `Err(CANCELLED)` once the operation has been asked to stop
+    pub fn check_cancelled(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            Err(CANCELLED.to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn progress(&self, phase: &str, current: usize, total: usize, message: Option<String>) {
+        self.operation.update(|progress| {
+            progress.phase = phase.to_string();
+            progress.current = current;
+            progress.total = total;
+            progress.message = message;
+        });
+    }
+
+    /// End the operation with the outcome of its work. An error after the
+    /// operation was cancelled counts as the cancellation.
+    pub fn finish<T, E: Display>(&self, result: &Result<T, E>) {
+        let (state, error) = match result {
+            Ok(_) => (OperationState::Completed, None),
+            Err(_) if self.is_cancelled() => (OperationState::Cancelled, None),
+            Err(e) => (OperationState::Failed, Some(e.to_string())),
+        };
+        self.operation.update(|progress| {
+            progress.state = state;
+            progress.error = error;
+        });
+    }
+}
+
+impl Drop for OperationHandle {
+    fn drop(&mut self) {
+        if let Some(manager) = &self.manager {
+            manager.active.lock().unwrap().remove(&self.id);
+        }
+        if self.operation.state() == OperationState::Running {
+            let state = if self.is_cancelled() {
+                OperationState::Cancelled
+            } else {
+                OperationState::Failed
+            };
+            self.operation.update(|progress| progress.state = state);
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recorder() -> (ProgressSink, Arc<Mutex<Vec<OperationProgress>>>) {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink_events = events.clone();
+        let sink: ProgressSink =
+            Box::new(move |p: &OperationProgress| sink_events.lock().unwrap().push(p.clone()));
+        (sink, events)
+    }
+
+    #[test]
+    fn test_operation_lifecycle() {
+        let manager = Arc::new(OperationManager::new());
+        let (sink, events) = recorder();
+
+        let operation = manager.start(OperationKind::Export, "Exporting Notes", sink);
+        operation.progress("writing", 1, 4, Some("a.midlight".to_string()));
+        assert_eq!(manager.list().len(), 1);
+        assert_eq!(manager.list()[0].current, 1);
+
+        operation.finish(&Err::<(), _>("disk full"));
+        drop(operation);
+        assert!(manager.list().is_empty());
+
+        let events = events.lock().unwrap();
+        let states: Vec<_> = events.iter().map(|p| p.state).collect();
+        assert_eq!(
+            states,
+            vec![
+                OperationState::Running,
+                OperationState::Running,
+                OperationState::Failed
+            ]
+        );
+        assert_eq!(events[2].error.as_deref(), Some("disk full"));
+    }
+
+    #[test]
+    fn test_cancel() {
+        let manager = Arc::new(OperationManager::new());
+        let (sink, events) = recorder();
+        let import = manager.start(OperationKind::Import, "Importing", sink);
+        let (sink, _) = recorder();
+        let export = manager.start(OperationKind::Export, "Exporting", sink);
+
+        assert!(!manager.cancel(export.id() + 1));
+        assert_eq!(manager.cancel_kind(OperationKind::Import), 1);
+        assert!(import.check_cancelled().is_err());
+        assert!(export.check_cancelled().is_ok());
+
+        // Work that stops without finishing ends as cancelled
+        drop(import);
+        assert_eq!(
+            events.lock().unwrap().last().unwrap().state,
+            OperationState::Cancelled
+        );
+        assert_eq!(manager.list().len(), 1);
+        assert!(manager.cancel(export.id()));
+    }
+}
@@ -0,0 +1,120 @@
+// Path Glob - Glob patterns for workspace-relative paths
+//
+// Supports `*` (anything except `/`), `**` (any number of directories), `?`
+// (one character except `/`) and `{a,b}` alternatives. Like .gitignore,
+// patterns without a `/` match the file name at any depth.
+
+use regex::Regex;
+
+#[derive(Debug, Clone)]
+pub struct PathGlob {
+    regex: Regex,
+    match_name_only: bool,
+}
+
+impl PathGlob {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let pattern = pattern.trim().trim_start_matches('/');
+        if pattern.is_empty() {
+            return Err("Glob pattern cannot be empty".to_string());
+        }
+
+        let mut regex = String::from("^");
+        let mut chars = pattern.chars().peekable();
+        let mut brace_depth = 0;
+
+        while let Some(c) = chars.next() {
+            match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    if chars.peek() == Some(&'/') {
+                        chars.next();
+                        regex.push_str("(?:.*/)?");
+                    } else {
+                        regex.push_str(".*");
+                    }
+                }
+                '*' => regex.push_str("[^/]*"),
+                '?' => regex.push_str("[^/]"),
+                '{' => {
+                    brace_depth += 1;
+                    regex.push_str("(?:");
+                }
+                '}' if brace_depth > 0 => {
+                    brace_depth -= 1;
+                    regex.push(')');
+                }
+                ',' if brace_depth > 0 => regex.push('|'),
+                _ => regex.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+
+        if brace_depth > 0 {
+            return Err(format!("Unclosed '{{' in glob pattern: {}", pattern));
+        }
+        regex.push('$');
+
+        Ok(Self {
+            regex: Regex::new(&regex).map_err(|e| format!("Invalid glob pattern: {}", e))?,
+            match_name_only: !pattern.contains('/'),
+        })
+    }
+
+    /// Whether a workspace-relative path matches (either separator style)
+    pub fn is_match(&self, relative_path: &str) -> bool {
+        let path = relative_path.replace('\\', "/");
+        let path = path.trim_start_matches('/');
+
+        if self.match_name_only {
+            let name = path.rsplit('/').next().unwrap_or(path);
+            self.regex.is_match(name)
+        } else {
+            self.regex.is_match(path)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_only_patterns_match_at_any_depth() {
+        let glob = PathGlob::new("*.midlight").unwrap();
+        assert!(glob.is_match("note.midlight"));
+        assert!(glob.is_match("projects/2024/note.midlight"));
+        assert!(!glob.is_match("note.md"));
+    }
+
+    #[test]
+    fn test_path_patterns() {
+        let glob = PathGlob::new("projects/*/notes.midlight").unwrap();
+        assert!(glob.is_match("projects/alpha/notes.midlight"));
+        assert!(!glob.is_match("projects/alpha/beta/notes.midlight"));
+
+        let glob = PathGlob::new("/projects/**/*.midlight").unwrap();
+        assert!(glob.is_match("projects/a.midlight"));
+        assert!(glob.is_match("projects/x/y/a.midlight"));
+        assert!(glob.is_match("projects\\x\\a.midlight"));
+        assert!(!glob.is_match("archive/projects/a.midlight"));
+    }
+
+    #[test]
+    fn test_alternatives_and_single_chars() {
+        let glob = PathGlob::new("draft-?.{md,midlight}").unwrap();
+        assert!(glob.is_match("draft-1.md"));
+        assert!(glob.is_match("drafts/draft-2.midlight"));
+        assert!(!glob.is_match("draft-10.md"));
+        assert!(!glob.is_match("draft-1.txt"));
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        assert!(PathGlob::new("").is_err());
+        assert!(PathGlob::new("{a,b").is_err());
+        // Regex metacharacters are literal
+        assert!(PathGlob::new("notes (1).midlight")
+            .unwrap()
+            .is_match("notes (1).midlight"));
+    }
+}
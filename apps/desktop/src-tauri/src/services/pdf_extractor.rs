@@ -0,0 +1,402 @@
+// PDF Extractor - Text and page previews for PDFs in a workspace
+//
+// Extraction runs the Poppler CLI tools: pdftotext for the text of each page
+// and pdftoppm to render page thumbnails. They are looked up in
+// MIDLIGHT_POPPLER_DIR, falling back to PATH. Poppler is optional: without
+// it PDFs are stored and opened as before, just not searched or previewed.
+//
+// Results are cached by the hash of the PDF's contents, so a PDF is only
+// processed once however often it is opened, moved or copied:
+//   .midlight/pdf/{hash}/pages.json             text of each page
+//   .midlight/pdf/{hash}/page-{n}-{size}.png    page thumbnails
+//   .midlight/pdf/text/{hash}.txt               all text, for the RAG index
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+use tracing::debug;
+
+use super::image_optimizer::ThumbnailSize;
+
+/// Environment variable naming the directory with the Poppler tools
+pub const POPPLER_ENV: &str = "MIDLIGHT_POPPLER_DIR";
+
+/// Extraction or rendering taking longer than this is abandoned
+const EXTRACT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Most characters of text kept per PDF
+pub const MAX_TEXT_CHARS: usize = 1_000_000;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PdfText {
+    /// Hash of the PDF's contents, which keys the cache
+    pub hash: String,
+    pub page_count: usize,
+    /// Text of each page, in order
+    pub pages: Vec<String>,
+}
+
+/// Whether a file is a PDF, by extension
+pub fn is_pdf(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"))
+}
+
+// ============================================================================
+// Extractor
+// ============================================================================
+
+pub struct PdfExtractor {
+    workspace_root: PathBuf,
+    cache_dir: PathBuf,
+}
+
+impl PdfExtractor {
+    pub fn new(workspace_root: &Path) -> Self {
+        Self {
+            workspace_root: workspace_root.to_path_buf(),
+            cache_dir: workspace_root.join(".midlight").join("pdf"),
+        }
+    }
+
+    /// Path of the searchable text of a PDF, by content hash
+    pub fn text_path(&self, hash: &str) -> PathBuf {
+        self.cache_dir.join("text").join(format!("{}.txt", hash))
+    }
+
+    /// Text of each page of a PDF, extracted on first use. Returns `None`
+    /// when Poppler isn't installed.
+    pub async fn extract_text(&self, pdf_path: &Path) -> Result<Option<PdfText>, String> {
+        let hash = hash_file(pdf_path).await?;
+        let pages_path = self.cache_dir.join(&hash).join("pages.json");
+
+        if let Ok(cached) = tokio::fs::read_to_string(&pages_path).await {
+            if let Ok(text) = serde_json::from_str::<PdfText>(&cached) {
+                return Ok(Some(text));
+            }
+        }
+
+        let output = run_tool(
+            "pdftotext",
+            &[
+                "-enc".as_ref(),
+                "UTF-8".as_ref(),
+                pdf_path.as_os_str(),
+                "-".as_ref(),
+            ],
+        )
+        .await?;
+        let Some(output) = output else {
+            return Ok(None);
+        };
+
+        let pages = split_pages(&String::from_utf8_lossy(&output));
+        let text = PdfText {
+            hash: hash.clone(),
+            page_count: pages.len(),
+            pages,
+        };
+
+        let json = serde_json::to_string(&text)
+            .map_err(|e| format!("Failed to serialize PDF text: {}", e))?;
+        write_creating_dirs(&pages_path, json.as_bytes()).await?;
+
+        let searchable = search_text(&self.source_name(pdf_path), &text.pages);
+        write_creating_dirs(&self.text_path(&hash), searchable.as_bytes()).await?;
+
+        debug!(
+            "Extracted {} pages from {}",
+            text.page_count,
+            pdf_path.display()
+        );
+        Ok(Some(text))
+    }
+
+    /// Thumbnail of a page (1-based) as a PNG, rendered on first use. Returns
+    /// `None` when Poppler isn't installed.
+    pub async fn page_thumbnail(
+        &self,
+        pdf_path: &Path,
+        page: u32,
+        size: ThumbnailSize,
+    ) -> Result<Option<PathBuf>, String> {
+        if page == 0 {
+            return Err("Pages are numbered from 1".to_string());
+        }
+
+        let hash = hash_file(pdf_path).await?;
+        let entry_dir = self.cache_dir.join(&hash);
+        let stem = format!("page-{}-{}", page, size.name());
+        let thumbnail = entry_dir.join(format!("{}.png", stem));
+        if thumbnail.exists() {
+            return Ok(Some(thumbnail));
+        }
+
+        tokio::fs::create_dir_all(&entry_dir)
+            .await
+            .map_err(|e| format!("Failed to create PDF cache: {}", e))?;
+
+        let page = page.to_string();
+        let pixels = size.pixels().to_string();
+        let prefix = entry_dir.join(&stem);
+        let output = run_tool(
+            "pdftoppm",
+            &[
+                "-png".as_ref(),
+                "-singlefile".as_ref(),
+                "-f".as_ref(),
+                page.as_ref(),
+                "-l".as_ref(),
+                page.as_ref(),
+                "-scale-to".as_ref(),
+                pixels.as_ref(),
+                pdf_path.as_os_str(),
+                prefix.as_os_str(),
+            ],
+        )
+        .await?;
+        if output.is_none() {
+            return Ok(None);
+        }
+
+        if !thumbnail.exists() {
+            return Err(format!("Page {} could not be rendered", page));
+        }
+        Ok(Some(thumbnail))
+    }
+
+    /// Drop the cached results for a PDF that was deleted, returning the
+    /// search text files that were removed
+    pub async fn forget(&self, pdf_path: &Path) -> Vec<PathBuf> {
+        let header = format!("PDF: {}", self.source_name(pdf_path));
+        let mut removed = Vec::new();
+
+        let Ok(mut entries) = tokio::fs::read_dir(self.cache_dir.join("text")).await else {
+            return removed;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            let Ok(contents) = tokio::fs::read_to_string(&path).await else {
+                continue;
+            };
+            if contents.lines().next() != Some(header.as_str()) {
+                continue;
+            }
+            if let Some(hash) = path.file_stem() {
+                let _ = tokio::fs::remove_dir_all(self.cache_dir.join(hash)).await;
+            }
+            if tokio::fs::remove_file(&path).await.is_ok() {
+                removed.push(path);
+            }
+        }
+        removed
+    }
+
+    fn source_name(&self, pdf_path: &Path) -> String {
+        pdf_path
+            .strip_prefix(&self.workspace_root)
+            .unwrap_or(pdf_path)
+            .to_string_lossy()
+            .replace('\\', "/")
+    }
+
+    /// Thumbnail of a page as a data URL
+    pub async fn page_thumbnail_data_url(
+        &self,
+        pdf_path: &Path,
+        page: u32,
+        size: ThumbnailSize,
+    ) -> Result<Option<String>, String> {
+        let Some(path) = self.page_thumbnail(pdf_path, page, size).await? else {
+            return Ok(None);
+        };
+        let bytes = tokio::fs::read(&path)
+            .await
+            .map_err(|e| format!("Failed to read thumbnail: {}", e))?;
+        Ok(Some(format!(
+            "data:image/png;base64,{}",
+            BASE64.encode(bytes)
+        )))
+    }
+}
+
+/// Run a Poppler tool, returning its stdout, or `None` if it isn't installed
+async fn run_tool(name: &str, args: &[&std::ffi::OsStr]) -> Result<Option<Vec<u8>>, String> {
+    let program = match std::env::var_os(POPPLER_ENV) {
+        Some(dir) => Path::new(&dir).join(name),
+        None => PathBuf::from(name),
+    };
+
+    let child = Command::new(&program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn();
+    let child = match child {
+        Ok(child) => child,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            debug!("PDF processing skipped, {} is not installed", name);
+            return Ok(None);
+        }
+        Err(e) => return Err(format!("Failed to start {}: {}", name, e)),
+    };
+
+    let output = tokio::time::timeout(EXTRACT_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| format!("{} timed out", name))?
+        .map_err(|e| format!("Failed to run {}: {}", name, e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "{} failed: {}",
+            name,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(Some(output.stdout))
+}
+
+async fn hash_file(path: &Path) -> Result<String, String> {
+    let bytes = tokio::fs::read(path)
+        .await
+        .map_err(|e| format!("Failed to read PDF: {}", e))?;
+    let hash = format!("{:x}", Sha256::digest(&bytes));
+    Ok(hash[..16].to_string())
+}
+
+async fn write_creating_dirs(path: &Path, contents: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create PDF cache: {}", e))?;
+    }
+    tokio::fs::write(path, contents)
+        .await
+        .map_err(|e| format!("Failed to write PDF cache: {}", e))
+}
+
+/// Split pdftotext output, which ends each page with a form feed, into
+/// pages with trailing whitespace and runs of blank lines tidied up
+fn split_pages(raw: &str) -> Vec<String> {
+    let mut pages: Vec<&str> = raw.split('\u{c}').collect();
+    if pages.last().is_some_and(|last| last.trim().is_empty()) {
+        pages.pop();
+    }
+
+    let mut remaining = MAX_TEXT_CHARS;
+    pages
+        .into_iter()
+        .map(|page| {
+            let mut lines: Vec<&str> = Vec::new();
+            for line in page.lines().map(str::trim_end) {
+                if line.is_empty() && lines.last().map_or(true, |l| l.is_empty()) {
+                    continue;
+                }
+                lines.push(line);
+            }
+            while lines.last().is_some_and(|l| l.is_empty()) {
+                lines.pop();
+            }
+
+            let text: String = lines.join("\n").chars().take(remaining).collect();
+            remaining -= text.chars().count();
+            text
+        })
+        .collect()
+}
+
+/// Text indexed for search: where the PDF is, then its pages
+fn search_text(source: &str, pages: &[String]) -> String {
+    let mut text = format!("PDF: {}\n", source);
+    for page in pages.iter().filter(|p| !p.is_empty()) {
+        text.push('\n');
+        text.push_str(page);
+        text.push('\n');
+    }
+    text
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_split_pages() {
+        let raw = "Title   \n\n\n\nFirst page\n\u{c}\u{c}  Second\n\n\u{c}";
+        assert_eq!(
+            split_pages(raw),
+            vec!["Title\n\nFirst page", "", "  Second"]
+        );
+        assert!(split_pages("").is_empty());
+
+        let pages = vec!["One".to_string(), String::new(), "Two".to_string()];
+        assert_eq!(
+            search_text("docs/a.pdf", &pages),
+            "PDF: docs/a.pdf\n\nOne\n\nTwo\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_results_are_cached_by_hash() {
+        let temp = TempDir::new().unwrap();
+        let pdf_path = temp.path().join("report.pdf");
+        std::fs::write(&pdf_path, b"%PDF-1.4 fake").unwrap();
+
+        let extractor = PdfExtractor::new(temp.path());
+        let hash = hash_file(&pdf_path).await.unwrap();
+        let entry_dir = temp.path().join(".midlight").join("pdf").join(&hash);
+        std::fs::create_dir_all(&entry_dir).unwrap();
+
+        let cached = PdfText {
+            hash: hash.clone(),
+            page_count: 1,
+            pages: vec!["Cached text".to_string()],
+        };
+        std::fs::write(
+            entry_dir.join("pages.json"),
+            serde_json::to_string(&cached).unwrap(),
+        )
+        .unwrap();
+        std::fs::write(entry_dir.join("page-1-small.png"), b"png").unwrap();
+
+        // Served from the cache without running Poppler
+        let text = extractor.extract_text(&pdf_path).await.unwrap().unwrap();
+        assert_eq!(text.pages, vec!["Cached text"]);
+        let thumbnail = extractor
+            .page_thumbnail(&pdf_path, 1, ThumbnailSize::Small)
+            .await
+            .unwrap();
+        assert_eq!(thumbnail, Some(entry_dir.join("page-1-small.png")));
+
+        assert!(extractor
+            .page_thumbnail(&pdf_path, 0, ThumbnailSize::Small)
+            .await
+            .is_err());
+
+        let text_path = extractor.text_path(&hash);
+        std::fs::create_dir_all(text_path.parent().unwrap()).unwrap();
+        std::fs::write(&text_path, "PDF: report.pdf\n\nCached text\n").unwrap();
+        assert_eq!(extractor.forget(&pdf_path).await, vec![text_path]);
+        assert!(!entry_dir.exists());
+
+        assert!(is_pdf(Path::new("Scan.PDF")));
+        assert!(!is_pdf(Path::new("notes.md")));
+    }
+}
@@ -0,0 +1,482 @@
+// Periodic Notes - Daily, weekly and monthly notes
+//
+// Each period has a folder, a strftime naming scheme and an optional template
+// document. Opening the note for a date creates it from the template the
+// first time and returns the existing file after that. Weekly notes belong to
+// the ISO week (starting Monday) and monthly notes to the calendar month, so
+// any date in the period opens the same note.
+//
+// Settings are stored at: .midlight/periodic-notes.json
+// Format:
+// {
+//   "version": 1,
+//   "daily": { "folder": "Daily", "format": "%Y-%m-%d", "template": "Templates/Day.midlight" },
+//   "weekly": { "folder": "Weekly", "format": "%G-W%V" },
+//   "monthly": { "folder": "Monthly", "format": "%Y-%m" }
+// }
+//
+// Templates are .midlight documents. These placeholders are replaced in their
+// text: {{title}}, {{date}} (YYYY-MM-DD of the period's first day) and
+// {{date:FORMAT}} with any strftime format.
+
+use crate::services::atomic_write::write_atomic;
+use chrono::{Datelike, Duration, Months, NaiveDate, Utc};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::Write;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write as _};
+use std::path::{Component, Path, PathBuf};
+
+const SETTINGS_VERSION: u32 = 1;
+
+lazy_static! {
+    static ref PLACEHOLDER: Regex = Regex::new(r"\{\{\s*(title|date)(?::([^}]+))?\s*\}\}").unwrap();
+}
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl Period {
+    /// First day of the period containing `date`
+    pub fn start(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Daily => date,
+            Period::Weekly => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            Period::Monthly => date.with_day(1).unwrap_or(date),
+        }
+    }
+
+    /// Start of the period `steps` periods before or after the one
+    /// containing `date`
+    pub fn shift(self, date: NaiveDate, steps: i32) -> Option<NaiveDate> {
+        let start = self.start(date);
+        match self {
+            Period::Daily => start.checked_add_signed(Duration::days(steps as i64)),
+            Period::Weekly => start.checked_add_signed(Duration::weeks(steps as i64)),
+            Period::Monthly if steps >= 0 => start.checked_add_months(Months::new(steps as u32)),
+            Period::Monthly => start.checked_sub_months(Months::new(steps.unsigned_abs())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeriodConfig {
+    /// Folder relative to the workspace root
+    pub folder: String,
+    /// strftime format for the file name, without extension. May contain
+    /// "/" to file notes into subfolders, e.g. "%Y/%m/%Y-%m-%d".
+    pub format: String,
+    /// Template document relative to the workspace root
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+}
+
+impl PeriodConfig {
+    fn new(folder: &str, format: &str) -> Self {
+        Self {
+            folder: folder.to_string(),
+            format: format.to_string(),
+            template: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeriodicNoteSettings {
+    #[serde(default = "default_daily")]
+    pub daily: PeriodConfig,
+    #[serde(default = "default_weekly")]
+    pub weekly: PeriodConfig,
+    #[serde(default = "default_monthly")]
+    pub monthly: PeriodConfig,
+}
+
+fn default_daily() -> PeriodConfig {
+    PeriodConfig::new("Daily", "%Y-%m-%d")
+}
+
+fn default_weekly() -> PeriodConfig {
+    PeriodConfig::new("Weekly", "%G-W%V")
+}
+
+fn default_monthly() -> PeriodConfig {
+    PeriodConfig::new("Monthly", "%Y-%m")
+}
+
+impl Default for PeriodicNoteSettings {
+    fn default() -> Self {
+        Self {
+            daily: default_daily(),
+            weekly: default_weekly(),
+            monthly: default_monthly(),
+        }
+    }
+}
+
+impl PeriodicNoteSettings {
+    pub fn config(&self, period: Period) -> &PeriodConfig {
+        match period {
+            Period::Daily => &self.daily,
+            Period::Weekly => &self.weekly,
+            Period::Monthly => &self.monthly,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SettingsFile {
+    version: u32,
+    #[serde(flatten)]
+    settings: PeriodicNoteSettings,
+}
+
+/// A periodic note that was opened
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeriodicNote {
+    pub path: PathBuf,
+    /// Relative to the workspace root
+    pub relative_path: String,
+    pub title: String,
+    pub period: Period,
+    /// First day of the note's period
+    pub date: NaiveDate,
+    /// Whether the note was created by this call
+    pub created: bool,
+}
+
+// ============================================================================
+// Periodic Notes
+// ============================================================================
+
+pub struct PeriodicNotes {
+    workspace_root: PathBuf,
+    settings_path: PathBuf,
+}
+
+impl PeriodicNotes {
+    pub fn new(workspace_root: &Path) -> Self {
+        Self {
+            workspace_root: workspace_root.to_path_buf(),
+            settings_path: workspace_root.join(".midlight").join("periodic-notes.json"),
+        }
+    }
+
+    pub fn settings(&self) -> Result<PeriodicNoteSettings, String> {
+        if !self.settings_path.exists() {
+            return Ok(PeriodicNoteSettings::default());
+        }
+
+        let content = fs::read_to_string(&self.settings_path)
+            .map_err(|e| format!("Failed to read periodic note settings: {}", e))?;
+        let file: SettingsFile = serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse periodic note settings: {}", e))?;
+        Ok(file.settings)
+    }
+
+    pub fn set_settings(&self, settings: PeriodicNoteSettings) -> Result<(), String> {
+        for period in [Period::Daily, Period::Weekly, Period::Monthly] {
+            let config = settings.config(period);
+            relative_path(&config.folder)?;
+            format_date(Utc::now().date_naive(), &config.format)?;
+        }
+
+        let json = serde_json::to_string_pretty(&SettingsFile {
+            version: SETTINGS_VERSION,
+            settings,
+        })
+        .map_err(|e| format!("Failed to serialize periodic note settings: {}", e))?;
+
+        write_atomic(&self.settings_path, json)
+            .map_err(|e| format!("Failed to write periodic note settings: {}", e))
+    }
+
+    /// Where the note for a period starting on `start` lives, whether or
+    /// not it exists yet
+    fn note_path(&self, config: &PeriodConfig, start: NaiveDate) -> Result<PathBuf, String> {
+        let name = format_date(start, &config.format)?;
+        let relative = relative_path(&config.folder)?.join(relative_path(&name)?);
+        let mut path = self.workspace_root.join(relative);
+        let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(".midlight");
+        path.set_file_name(file_name);
+        Ok(path)
+    }
+
+    /// Open the note for the period containing `date`, creating it from the
+    /// period's template if it doesn't exist yet
+    pub fn open(&self, period: Period, date: NaiveDate) -> Result<PeriodicNote, String> {
+        let settings = self.settings()?;
+        let config = settings.config(period);
+        let start = period.start(date);
+        let path = self.note_path(config, start)?;
+        let title = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let created = if path.exists() {
+            false
+        } else {
+            let document = self.render_template(config.template.as_deref(), &title, start)?;
+            create_new(&path, &document)?
+        };
+
+        let relative_path = path
+            .strip_prefix(&self.workspace_root)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        Ok(PeriodicNote {
+            path,
+            relative_path,
+            title,
+            period,
+            date: start,
+            created,
+        })
+    }
+
+    fn render_template(
+        &self,
+        template: Option<&str>,
+        title: &str,
+        date: NaiveDate,
+    ) -> Result<Value, String> {
+        let now = Utc::now().to_rfc3339();
+        let Some(template) = template else {
+            return Ok(serde_json::json!({
+                "version": 1,
+                "meta": { "created": now, "modified": now },
+                "document": { "defaultFont": "Merriweather", "defaultFontSize": 16 },
+                "content": {
+                    "type": "doc",
+                    "content": [
+                        {
+                            "type": "heading",
+                            "attrs": { "level": 1 },
+                            "content": [{ "type": "text", "text": title }]
+                        },
+                        { "type": "paragraph" }
+                    ]
+                }
+            }));
+        };
+
+        document_from_template(&self.workspace_root, template, title, date)
+    }
+}
+
+/// A new document from a template document, with its placeholders filled in
+pub fn document_from_template(
+    workspace_root: &Path,
+    template: &str,
+    title: &str,
+    date: NaiveDate,
+) -> Result<Value, String> {
+    let template_path = workspace_root.join(relative_path(template)?);
+    if template_path.extension().and_then(|e| e.to_str()) != Some("midlight") {
+        return Err(format!(
+            "Template must be a .midlight document: {}",
+            template
+        ));
+    }
+    let content = fs::read_to_string(&template_path)
+        .map_err(|e| format!("Failed to read template {}: {}", template, e))?;
+    let mut document: Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse template {}: {}", template, e))?;
+
+    if let Some(body) = document.get_mut("content") {
+        fill_placeholders(body, title, date);
+    }
+    let now = Utc::now().to_rfc3339();
+    document["meta"] = serde_json::json!({ "created": now, "modified": now });
+    Ok(document)
+}
+
+/// Write a new document, returning false if another writer got there first
+pub(crate) fn create_new(path: &Path, document: &Value) -> Result<bool, String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create folder: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(document)
+        .map_err(|e| format!("Failed to serialize note: {}", e))?;
+
+    match OpenOptions::new().write(true).create_new(true).open(path) {
+        Ok(mut file) => {
+            file.write_all(json.as_bytes())
+                .map_err(|e| format!("Failed to write note: {}", e))?;
+            Ok(true)
+        }
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => Ok(false),
+        Err(e) => Err(format!("Failed to create note: {}", e)),
+    }
+}
+
+/// Format a date, rejecting invalid strftime formats rather than panicking
+pub fn format_date(date: NaiveDate, format: &str) -> Result<String, String> {
+    let mut formatted = String::new();
+    write!(formatted, "{}", date.format(format))
+        .map_err(|_| format!("Invalid date format: {}", format))?;
+    Ok(formatted)
+}
+
+/// A relative path that stays inside the workspace
+fn relative_path(path: &str) -> Result<PathBuf, String> {
+    let path = Path::new(path);
+    if path
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        Ok(path.to_path_buf())
+    } else {
+        Err(format!(
+            "Path must be inside the workspace: {}",
+            path.display()
+        ))
+    }
+}
+
+/// Replace placeholders in every text node
+fn fill_placeholders(node: &mut Value, title: &str, date: NaiveDate) {
+    match node {
+        Value::Object(map) => {
+            if let Some(Value::String(text)) = map.get_mut("text") {
+                let filled = PLACEHOLDER.replace_all(text, |caps: &Captures| {
+                    match (&caps[1], caps.get(2)) {
+                        ("title", _) => title.to_string(),
+                        (_, Some(format)) => format_date(date, format.as_str().trim())
+                            .unwrap_or_else(|_| caps[0].to_string()),
+                        _ => date.format("%Y-%m-%d").to_string(),
+                    }
+                });
+                *text = filled.into_owned();
+            }
+            for value in map.values_mut() {
+                fill_placeholders(value, title, date);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                fill_placeholders(item, title, date);
+            }
+        }
+        _ => {}
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_period_start_and_shift() {
+        // A Thursday
+        let day = date("2026-01-01");
+        assert_eq!(Period::Weekly.start(day), date("2025-12-29"));
+        assert_eq!(
+            Period::Monthly.start(date("2026-03-31")),
+            date("2026-03-01")
+        );
+
+        assert_eq!(Period::Daily.shift(day, -1), Some(date("2025-12-31")));
+        assert_eq!(Period::Weekly.shift(day, 1), Some(date("2026-01-05")));
+        assert_eq!(
+            Period::Monthly.shift(date("2026-03-31"), -2),
+            Some(date("2026-01-01"))
+        );
+    }
+
+    #[test]
+    fn test_open_creates_then_reopens() {
+        let temp = TempDir::new().unwrap();
+        let notes = PeriodicNotes::new(temp.path());
+
+        let note = notes.open(Period::Weekly, date("2026-01-01")).unwrap();
+        assert!(note.created);
+        assert_eq!(note.relative_path, "Weekly/2026-W01.midlight");
+        assert_eq!(note.date, date("2025-12-29"));
+
+        let document: Value =
+            serde_json::from_str(&fs::read_to_string(&note.path).unwrap()).unwrap();
+        assert_eq!(
+            document["content"]["content"][0]["content"][0]["text"],
+            "2026-W01"
+        );
+
+        let again = notes.open(Period::Weekly, date("2026-01-04")).unwrap();
+        assert!(!again.created);
+        assert_eq!(again.path, note.path);
+    }
+
+    #[test]
+    fn test_template_and_settings() {
+        let temp = TempDir::new().unwrap();
+        let template = serde_json::json!({
+            "version": 1,
+            "meta": { "created": "2020-01-01T00:00:00Z" },
+            "content": {
+                "type": "doc",
+                "content": [{
+                    "type": "paragraph",
+                    "content": [{ "type": "text", "text": "{{title}} on {{date:%A}}, {{date}}" }]
+                }]
+            }
+        });
+        fs::create_dir_all(temp.path().join("Templates")).unwrap();
+        fs::write(
+            temp.path().join("Templates/Day.midlight"),
+            template.to_string(),
+        )
+        .unwrap();
+
+        let notes = PeriodicNotes::new(temp.path());
+        let mut settings = notes.settings().unwrap();
+        settings.daily = PeriodConfig {
+            folder: "Journal".to_string(),
+            format: "%Y/%m/%d %b".to_string(),
+            template: Some("Templates/Day.midlight".to_string()),
+        };
+        notes.set_settings(settings.clone()).unwrap();
+        assert_eq!(notes.settings().unwrap(), settings);
+
+        let note = notes.open(Period::Daily, date("2026-01-02")).unwrap();
+        assert_eq!(note.relative_path, "Journal/2026/01/02 Jan.midlight");
+        let document: Value =
+            serde_json::from_str(&fs::read_to_string(&note.path).unwrap()).unwrap();
+        assert_eq!(
+            document["content"]["content"][0]["content"][0]["text"],
+            "02 Jan on Friday, 2026-01-02"
+        );
+        assert_ne!(document["meta"]["created"], "2020-01-01T00:00:00Z");
+
+        settings.daily.folder = "../outside".to_string();
+        assert!(notes.set_settings(settings.clone()).is_err());
+        settings.daily.folder = "Journal".to_string();
+        settings.daily.format = "%Q".to_string();
+        assert!(notes.set_settings(settings).is_err());
+    }
+}
@@ -0,0 +1,290 @@
+// Pinned Documents - Favorites kept in the workspace
+//
+// Pins live in the workspace's metadata rather than in the frontend, so they
+// and their order survive reinstalls and travel with the workspace when it is
+// synced. Paths are workspace-relative with forward slashes, and follow files
+// and folders that are renamed or moved through the app.
+//
+// Pins are stored in the workspace metadata store (.midlight/metadata.db)
+
+use crate::services::metadata_store::{self, Pin};
+use crate::services::workspace_paths::{document_key, find_workspace_root};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::{Component, Path, PathBuf};
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// A pinned file or folder, in pin order
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PinnedDocument {
+    /// Relative to the workspace root
+    pub path: String,
+    pub absolute_path: String,
+    pub pinned_at: DateTime<Utc>,
+    /// False if the file was removed outside the app
+    pub exists: bool,
+}
+
+// ============================================================================
+// Pin Store
+// ============================================================================
+
+pub struct PinStore {
+    workspace_root: PathBuf,
+}
+
+impl PinStore {
+    pub fn new(workspace_root: &Path) -> Self {
+        Self {
+            workspace_root: workspace_root.to_path_buf(),
+        }
+    }
+
+    pub fn list(&self) -> Result<Vec<PinnedDocument>, String> {
+        Ok(self
+            .read()?
+            .into_iter()
+            .map(|pin| {
+                let absolute = self.workspace_root.join(&pin.path);
+                PinnedDocument {
+                    exists: absolute.exists(),
+                    absolute_path: absolute.to_string_lossy().to_string(),
+                    path: pin.path,
+                    pinned_at: pin.pinned_at,
+                }
+            })
+            .collect())
+    }
+
+    /// Pin a file or folder (absolute or workspace-relative), at `position`
+    /// or at the end. Pinning something already pinned moves it.
+    pub fn pin(&self, path: &str, position: Option<usize>) -> Result<Vec<PinnedDocument>, String> {
+        let key = self.key(path)?;
+        if !self.workspace_root.join(&key).exists() {
+            return Err(format!("File not found: {}", path));
+        }
+
+        let mut pins = self.read()?;
+        let pin = match pins.iter().position(|p| p.path == key) {
+            Some(index) => pins.remove(index),
+            None => Pin {
+                path: key,
+                pinned_at: Utc::now(),
+            },
+        };
+        let index = position.unwrap_or(pins.len()).min(pins.len());
+        pins.insert(index, pin);
+        self.write(&pins)?;
+        self.list()
+    }
+
+    /// Unpin a file or folder, returning whether it was pinned
+    pub fn unpin(&self, path: &str) -> Result<bool, String> {
+        let key = self.key(path)?;
+        let mut pins = self.read()?;
+        let before = pins.len();
+        pins.retain(|p| p.path != key);
+        if pins.len() == before {
+            return Ok(false);
+        }
+        self.write(&pins)?;
+        Ok(true)
+    }
+
+    /// Put pins in the given order. Pinned paths that aren't listed keep
+    /// their relative order after the listed ones.
+    pub fn reorder(&self, paths: &[String]) -> Result<Vec<PinnedDocument>, String> {
+        let mut pins = self.read()?;
+        let mut ordered = Vec::with_capacity(pins.len());
+        for path in paths {
+            let key = self.key(path)?;
+            if let Some(index) = pins.iter().position(|p| p.path == key) {
+                ordered.push(pins.remove(index));
+            }
+        }
+        ordered.append(&mut pins);
+        self.write(&ordered)?;
+        self.list()
+    }
+
+    /// Drop pins at or under a removed path
+    pub fn remove_paths(&self, relative: &str) -> Result<(), String> {
+        let mut pins = self.read()?;
+        let before = pins.len();
+        pins.retain(|p| !is_at_or_under(&p.path, relative));
+        if pins.len() != before {
+            self.write(&pins)?;
+        }
+        Ok(())
+    }
+
+    /// Re-point pins at or under a moved path
+    pub fn move_paths(&self, from: &str, to: &str) -> Result<(), String> {
+        let mut pins = self.read()?;
+        let mut changed = false;
+        for pin in pins.iter_mut().filter(|p| is_at_or_under(&p.path, from)) {
+            pin.path = format!("{}{}", to, &pin.path[from.len()..]);
+            changed = true;
+        }
+        if changed {
+            self.write(&pins)?;
+        }
+        Ok(())
+    }
+
+    fn key(&self, path: &str) -> Result<String, String> {
+        let path = Path::new(path);
+        let relative = if path.is_absolute() {
+            path.strip_prefix(&self.workspace_root)
+                .map_err(|_| format!("{} is not in the workspace", path.display()))?
+        } else {
+            path
+        };
+        let inside = relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)));
+        if !inside || relative.as_os_str().is_empty() || relative.starts_with(".midlight") {
+            return Err(format!("{} can't be pinned", path.display()));
+        }
+        Ok(document_key(&relative.to_string_lossy()))
+    }
+
+    fn read(&self) -> Result<Vec<Pin>, String> {
+        metadata_store::open(&self.workspace_root)?.pins()
+    }
+
+    fn write(&self, pins: &[Pin]) -> Result<(), String> {
+        metadata_store::open(&self.workspace_root)?.set_pins(pins)
+    }
+}
+
+fn is_at_or_under(path: &str, prefix: &str) -> bool {
+    path == prefix || path.starts_with(&format!("{}/", prefix))
+}
+
+/// Unpin a file or folder that was deleted or trashed
+pub fn path_removed(path: &Path) {
+    let Some(root) = find_workspace_root(path) else {
+        return;
+    };
+    let Ok(relative) = path.strip_prefix(&root) else {
+        return;
+    };
+    if let Err(e) = PinStore::new(&root).remove_paths(&document_key(&relative.to_string_lossy())) {
+        tracing::warn!("Failed to update pins: {}", e);
+    }
+}
+
+/// Keep pins on a file or folder that was renamed or moved. Pins don't
+/// follow it into another workspace.
+pub fn path_moved(from: &Path, to: &Path) {
+    let Some(root) = find_workspace_root(from) else {
+        return;
+    };
+    let Ok(from_relative) = from.strip_prefix(&root) else {
+        return;
+    };
+    let from_key = document_key(&from_relative.to_string_lossy());
+    let store = PinStore::new(&root);
+
+    let result = match to.strip_prefix(&root) {
+        Ok(to_relative) if find_workspace_root(to).as_deref() == Some(root.as_path()) => {
+            store.move_paths(&from_key, &document_key(&to_relative.to_string_lossy()))
+        }
+        _ => store.remove_paths(&from_key),
+    };
+    if let Err(e) = result {
+        tracing::warn!("Failed to update pins: {}", e);
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn setup() -> TempDir {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join(".midlight")).unwrap();
+        fs::create_dir_all(temp.path().join("notes")).unwrap();
+        for file in ["a.midlight", "notes/b.midlight", "notes/c.md"] {
+            fs::write(temp.path().join(file), "").unwrap();
+        }
+        temp
+    }
+
+    fn paths(pins: &[PinnedDocument]) -> Vec<&str> {
+        pins.iter().map(|p| p.path.as_str()).collect()
+    }
+
+    #[test]
+    fn test_pin_order() {
+        let temp = setup();
+        let store = PinStore::new(temp.path());
+
+        store.pin("a.midlight", None).unwrap();
+        let absolute = temp.path().join("notes/b.midlight");
+        store.pin(&absolute.to_string_lossy(), None).unwrap();
+        let pins = store.pin("notes/c.md", Some(0)).unwrap();
+        assert_eq!(
+            paths(&pins),
+            vec!["notes/c.md", "a.midlight", "notes/b.midlight"]
+        );
+        assert!(pins[2].exists);
+        assert_eq!(pins[2].absolute_path, absolute.to_string_lossy());
+
+        // Pinning again moves rather than duplicates
+        let pins = store.pin("notes/c.md", None).unwrap();
+        assert_eq!(
+            paths(&pins),
+            vec!["a.midlight", "notes/b.midlight", "notes/c.md"]
+        );
+
+        let pins = store.reorder(&["notes/c.md".to_string()]).unwrap();
+        assert_eq!(
+            paths(&pins),
+            vec!["notes/c.md", "a.midlight", "notes/b.midlight"]
+        );
+
+        assert!(store.unpin("a.midlight").unwrap());
+        assert!(!store.unpin("a.midlight").unwrap());
+        assert!(store.pin("missing.midlight", None).is_err());
+        assert!(store.pin(".midlight/pinned.json", None).is_err());
+        assert!(store.pin("/elsewhere/x.md", None).is_err());
+        assert!(store.pin("notes/../a.midlight", None).is_err());
+    }
+
+    #[test]
+    fn test_pins_follow_moves_and_deletes() {
+        let temp = setup();
+        let store = PinStore::new(temp.path());
+        store.pin("a.midlight", None).unwrap();
+        store.pin("notes/b.midlight", None).unwrap();
+        store.pin("notes", None).unwrap();
+
+        path_moved(&temp.path().join("notes"), &temp.path().join("archive"));
+        assert_eq!(
+            paths(&store.list().unwrap()),
+            vec!["a.midlight", "archive/b.midlight", "archive"]
+        );
+
+        path_removed(&temp.path().join("archive"));
+        assert_eq!(paths(&store.list().unwrap()), vec!["a.midlight"]);
+
+        // Moving out of the workspace unpins
+        path_moved(
+            &temp.path().join("a.midlight"),
+            Path::new("/elsewhere/a.midlight"),
+        );
+        assert!(store.list().unwrap().is_empty());
+    }
+}
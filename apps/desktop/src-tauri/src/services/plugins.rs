@@ -0,0 +1,1252 @@
+// Plugins - WebAssembly extensions loaded from the workspace
+//
+// A plugin is a folder in the workspace's `plugins/` directory holding a
+// `plugin.json` manifest and a WebAssembly module. Plugins can add agent
+// tools and import converters for other file formats, and can read and write
+// documents through the host API when the user has granted them that.
+//
+// Modules run in wasmtime with nothing but the host API below: no WASI, so no
+// files, network, clock or environment. Every call gets a fresh instance, a
+// fuel budget and a memory cap, so a plugin can't keep state between calls,
+// spin forever or exhaust memory.
+//
+// Plugins are off until enabled. Enabling one shows the permissions its
+// manifest asks for; the ones the user allows are checked on every host
+// call. Grants are kept in the user's app data, keyed by the workspace's
+// canonical path, never in the workspace itself, so plugins in a workspace
+// someone else shared, or one copied or synced from elsewhere, do nothing
+// until enabled on this machine. A grant also records a hash of the manifest
+// and module it was given to; a plugin that changes afterwards stays off
+// until it is enabled again.
+//
+// ABI - strings are UTF-8 passed as (pointer, length) into the module's
+// exported `memory`. Results are an i64 with the pointer in the high 32 bits
+// and the length in the low 32; a negative result is an error.
+//
+//   Module exports:
+//     midlight_alloc(len) -> ptr                   memory for the host to write
+//     midlight_tool(name, name_len, json, json_len) -> result
+//     midlight_import(ext, ext_len, data, data_len) -> markdown
+//
+//   Host imports, module "midlight":
+//     log(ptr, len)
+//     error(ptr, len)                              message for a failed call
+//     read_document(path, path_len) -> markdown    needs documents:read
+//     write_document(path, path_len, md, md_len) -> 0 on success
+//                                                  needs documents:write
+//
+// Storage:
+//   <workspace>/plugins/<id>/plugin.json   manifest
+//   <app data>/plugin-grants.json          enabled plugins and their grants,
+//                                          by workspace
+
+use crate::services::agent_executor::BUILTIN_TOOLS;
+use crate::services::app_dirs::app_data_dir;
+use crate::services::atomic_write::write_atomic;
+use crate::services::custom_tools::validate_name;
+use crate::services::local_api::document_path;
+use crate::services::workspace_manager::WorkspaceManagerRegistry;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::runtime::Handle;
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+use wasmtime::{
+    Caller, Config, Engine, Extern, Linker, Module, Store, StoreLimits, StoreLimitsBuilder, Trap,
+};
+
+pub const PLUGINS_DIR: &str = "plugins";
+const MANIFEST_FILE: &str = "plugin.json";
+
+/// Fuel for one call; roughly the number of wasm instructions it may run
+const FUEL_PER_CALL: u64 = 2_000_000_000;
+
+/// Linear memory a plugin instance may grow to
+pub(crate) const MAX_MEMORY_BYTES: usize = 64 * 1024 * 1024;
+
+/// Largest string passed in or out of a plugin
+pub(crate) const MAX_TRANSFER_BYTES: usize = 8 * 1024 * 1024;
+
+/// Checkpoint trigger for documents written by plugins
+const TRIGGER: &str = "plugin";
+
+// Host call results
+const DENIED: i64 = -1;
+const FAILED: i64 = -2;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Permission {
+    #[serde(rename = "documents:read")]
+    ReadDocuments,
+    #[serde(rename = "documents:write")]
+    WriteDocuments,
+}
+
+impl std::fmt::Display for Permission {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Permission::ReadDocuments => write!(f, "read documents"),
+            Permission::WriteDocuments => write!(f, "write documents"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginManifest {
+    /// Must match the plugin's folder name
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    /// Module file, relative to the plugin folder
+    #[serde(default = "default_main")]
+    pub main: String,
+    #[serde(default)]
+    pub permissions: Vec<Permission>,
+    #[serde(default)]
+    pub tools: Vec<PluginTool>,
+    #[serde(default)]
+    pub importers: Vec<PluginImporter>,
+}
+
+fn default_main() -> String {
+    "plugin.wasm".to_string()
+}
+
+/// An agent tool a plugin adds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginTool {
+    pub name: String,
+    pub description: String,
+    /// JSON schema for the tool's arguments
+    #[serde(default = "default_parameters")]
+    pub parameters: Value,
+    #[serde(default)]
+    pub is_destructive: bool,
+}
+
+fn default_parameters() -> Value {
+    serde_json::json!({ "type": "object", "properties": {} })
+}
+
+/// A file format a plugin converts to Markdown on import
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginImporter {
+    pub name: String,
+    /// File extensions handled, without the dot
+    pub extensions: Vec<String>,
+}
+
+/// A plugin found in the workspace, as shown in settings
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginInfo {
+    /// Folder name
+    pub id: String,
+    /// Missing when the manifest couldn't be read
+    pub manifest: Option<PluginManifest>,
+    pub enabled: bool,
+    pub granted: Vec<Permission>,
+    /// Why the plugin can't be loaded
+    pub error: Option<String>,
+}
+
+// ============================================================================
+// Manifests
+// ============================================================================
+
+impl PluginManifest {
+    pub fn load(plugin_dir: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(plugin_dir.join(MANIFEST_FILE))
+            .map_err(|e| format!("Failed to read {}: {}", MANIFEST_FILE, e))?;
+        let manifest: Self = serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid {}: {}", MANIFEST_FILE, e))?;
+
+        let folder = plugin_dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if manifest.id != folder {
+            return Err(format!(
+                "Plugin id '{}' doesn't match its folder '{}'",
+                manifest.id, folder
+            ));
+        }
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let valid_id = !self.id.is_empty()
+            && self
+                .id
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+        if !valid_id {
+            return Err(format!(
+                "Invalid plugin id '{}': use lowercase letters, digits, '-' and '_'",
+                self.id
+            ));
+        }
+
+        let main_inside = Path::new(&self.main)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        if self.main.is_empty() || !main_inside {
+            return Err(format!(
+                "Plugin module must be inside the plugin folder: {}",
+                self.main
+            ));
+        }
+
+        for tool in &self.tools {
+            validate_name(&tool.name)?;
+            if BUILTIN_TOOLS.contains(&tool.name.as_str()) {
+                return Err(format!("Tool '{}' is a built-in tool", tool.name));
+            }
+        }
+        for importer in &self.importers {
+            if importer.extensions.is_empty() {
+                return Err(format!("Importer '{}' has no extensions", importer.name));
+            }
+        }
+        Ok(())
+    }
+
+    fn handles_extension(&self, extension: &str) -> bool {
+        self.importers.iter().any(|importer| {
+            importer.extensions.iter().any(|handled| {
+                handled
+                    .trim_start_matches('.')
+                    .eq_ignore_ascii_case(extension)
+            })
+        })
+    }
+}
+
+// ============================================================================
+// Enabled Plugins
+// ============================================================================
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PluginGrant {
+    enabled: bool,
+    granted: Vec<Permission>,
+    /// Hash of the manifest and module that were enabled
+    #[serde(default)]
+    hash: String,
+}
+
+/// Path of the user's plugin grants
+pub fn plugin_grants_path() -> PathBuf {
+    app_data_dir().join("plugin-grants.json")
+}
+
+/// Plugin grants by workspace, then by plugin id
+type GrantsFile = HashMap<String, HashMap<String, PluginGrant>>;
+
+/// Which plugins are enabled in a workspace, and what they may do
+struct PluginStateStore {
+    path: PathBuf,
+    /// The workspace's canonical path
+    workspace: String,
+}
+
+impl PluginStateStore {
+    fn new(grants_path: &Path, workspace_root: &Path) -> Self {
+        let workspace = workspace_root
+            .canonicalize()
+            .unwrap_or_else(|_| workspace_root.to_path_buf());
+        Self {
+            path: grants_path.to_path_buf(),
+            workspace: workspace.to_string_lossy().to_string(),
+        }
+    }
+
+    fn load(&self) -> HashMap<String, PluginGrant> {
+        self.read_all().remove(&self.workspace).unwrap_or_default()
+    }
+
+    fn save(&self, grants: &HashMap<String, PluginGrant>) -> Result<(), String> {
+        let mut all = self.read_all();
+        all.insert(self.workspace.clone(), grants.clone());
+        let json = serde_json::to_string_pretty(&all)
+            .map_err(|e| format!("Failed to serialize plugin settings: {}", e))?;
+        write_atomic(&self.path, json).map_err(|e| format!("Failed to save plugin settings: {}", e))
+    }
+
+    fn read_all(&self) -> GrantsFile {
+        if !self.path.exists() {
+            return GrantsFile::new();
+        }
+
+        fs::read_to_string(&self.path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| {
+                warn!("Failed to load plugin settings: {}", e);
+                GrantsFile::new()
+            })
+    }
+}
+
+// ============================================================================
+// Runtime
+// ============================================================================
+
+/// A compiled, enabled plugin
+struct LoadedPlugin {
+    manifest: PluginManifest,
+    granted: Vec<Permission>,
+    module: Module,
+    /// Hash of the manifest and module it was compiled from
+    hash: String,
+}
+
+/// What a running instance can reach
+struct HostState {
+    plugin_id: String,
+    granted: Vec<Permission>,
+    workspace_root: String,
+    registry: Arc<RwLock<WorkspaceManagerRegistry>>,
+    runtime: Handle,
+    limits: StoreLimits,
+    /// Message from the plugin's last `error` call
+    error: Option<String>,
+    /// Permission a host call was refused for
+    denied: Option<Permission>,
+}
+
+pub struct PluginRuntime {
+    engine: Engine,
+    registry: Arc<RwLock<WorkspaceManagerRegistry>>,
+    grants_path: PathBuf,
+    /// Loaded plugins by workspace root, filled on first use
+    loaded: Mutex<HashMap<String, Vec<Arc<LoadedPlugin>>>>,
+    /// Why enabled plugins failed to load, by workspace root and plugin id
+    load_errors: Mutex<HashMap<(String, String), String>>,
+}
+
+impl PluginRuntime {
+    pub fn new(registry: Arc<RwLock<WorkspaceManagerRegistry>>) -> Self {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config).expect("Failed to create the plugin engine");
+
+        Self {
+            engine,
+            registry,
+            grants_path: plugin_grants_path(),
+            loaded: Mutex::new(HashMap::new()),
+            load_errors: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Keep plugin grants in a different file
+    #[cfg(test)]
+    pub fn with_grants_path(mut self, grants_path: &Path) -> Self {
+        self.grants_path = grants_path.to_path_buf();
+        self
+    }
+
+    fn grants(&self, workspace_root: &Path) -> PluginStateStore {
+        PluginStateStore::new(&self.grants_path, workspace_root)
+    }
+
+    /// Every plugin folder in the workspace, enabled or not
+    pub fn list(&self, workspace_root: &str) -> Vec<PluginInfo> {
+        let root = Path::new(workspace_root);
+        let grants = self.grants(root).load();
+        self.ensure_loaded(workspace_root);
+        let load_errors = self.load_errors.lock().unwrap();
+
+        let mut plugins: Vec<PluginInfo> = plugin_dirs(root)
+            .into_iter()
+            .map(|dir| {
+                let id = dir
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let grant = grants.get(&id).cloned().unwrap_or_default();
+                let (manifest, error) = match PluginManifest::load(&dir) {
+                    Ok(manifest) => (Some(manifest), None),
+                    Err(e) => (None, Some(e)),
+                };
+                let error = error.or_else(|| {
+                    load_errors
+                        .get(&(workspace_root.to_string(), id.clone()))
+                        .cloned()
+                });
+                PluginInfo {
+                    id,
+                    manifest,
+                    enabled: grant.enabled,
+                    granted: grant.granted,
+                    error,
+                }
+            })
+            .collect();
+        plugins.sort_by(|a, b| a.id.cmp(&b.id));
+        plugins
+    }
+
+    /// Enable a plugin with the permissions the user allowed. Permissions the
+    /// manifest doesn't ask for are ignored.
+    pub fn enable(
+        &self,
+        workspace_root: &str,
+        plugin_id: &str,
+        permissions: &[Permission],
+    ) -> Result<(), String> {
+        let root = Path::new(workspace_root);
+        let plugin_dir = root.join(PLUGINS_DIR).join(plugin_id);
+        let manifest = PluginManifest::load(&plugin_dir)?;
+        let granted: Vec<Permission> = manifest
+            .permissions
+            .iter()
+            .filter(|permission| permissions.contains(permission))
+            .copied()
+            .collect();
+        let plugin = self.compile(&plugin_dir, manifest, granted.clone())?;
+
+        let store = self.grants(root);
+        let mut grants = store.load();
+        grants.insert(
+            plugin_id.to_string(),
+            PluginGrant {
+                enabled: true,
+                granted,
+                hash: plugin.hash.clone(),
+            },
+        );
+        store.save(&grants)?;
+
+        self.ensure_loaded(workspace_root);
+        let mut loaded = self.loaded.lock().unwrap();
+        let plugins = loaded.entry(workspace_root.to_string()).or_default();
+        plugins.retain(|loaded| loaded.manifest.id != plugin_id);
+        plugins.push(Arc::new(plugin));
+        self.load_errors
+            .lock()
+            .unwrap()
+            .remove(&(workspace_root.to_string(), plugin_id.to_string()));
+        info!("Enabled plugin {} in {}", plugin_id, workspace_root);
+        Ok(())
+    }
+
+    pub fn disable(&self, workspace_root: &str, plugin_id: &str) -> Result<(), String> {
+        let store = self.grants(Path::new(workspace_root));
+        let mut grants = store.load();
+        if let Some(grant) = grants.get_mut(plugin_id) {
+            grant.enabled = false;
+        }
+        store.save(&grants)?;
+
+        if let Some(plugins) = self.loaded.lock().unwrap().get_mut(workspace_root) {
+            plugins.retain(|loaded| loaded.manifest.id != plugin_id);
+        }
+        info!("Disabled plugin {} in {}", plugin_id, workspace_root);
+        Ok(())
+    }
+
+    /// Load the workspace's enabled plugins again, after they've changed on disk
+    pub fn reload(&self, workspace_root: &str) {
+        self.loaded.lock().unwrap().remove(workspace_root);
+        self.ensure_loaded(workspace_root);
+    }
+
+    /// Agent tools added by the workspace's enabled plugins
+    pub fn tools(&self, workspace_root: &str) -> Vec<PluginTool> {
+        self.plugins(workspace_root)
+            .iter()
+            .flat_map(|plugin| plugin.manifest.tools.clone())
+            .collect()
+    }
+
+    /// File extensions enabled plugins can import
+    pub fn import_extensions(&self, workspace_root: &str) -> Vec<String> {
+        let mut extensions: Vec<String> = self
+            .plugins(workspace_root)
+            .iter()
+            .flat_map(|plugin| plugin.manifest.importers.iter())
+            .flat_map(|importer| importer.extensions.iter())
+            .map(|extension| extension.trim_start_matches('.').to_ascii_lowercase())
+            .collect();
+        extensions.sort();
+        extensions.dedup();
+        extensions
+    }
+
+    /// Run a plugin tool with the agent's arguments. Output that is JSON is
+    /// returned parsed, anything else as a string.
+    pub async fn call_tool(
+        self: &Arc<Self>,
+        workspace_root: &str,
+        tool_name: &str,
+        arguments: &Value,
+    ) -> Result<Value, String> {
+        let plugin = self
+            .plugins(workspace_root)
+            .into_iter()
+            .find(|plugin| plugin.manifest.tools.iter().any(|t| t.name == tool_name))
+            .ok_or_else(|| format!("Unknown tool: {}", tool_name))?;
+        debug!("Running {} from plugin {}", tool_name, plugin.manifest.id);
+
+        let input = arguments.to_string();
+        let output = self
+            .run(
+                workspace_root,
+                plugin,
+                "midlight_tool",
+                tool_name.to_string(),
+                input.into_bytes(),
+            )
+            .await?;
+        let output = String::from_utf8_lossy(&output).to_string();
+        Ok(serde_json::from_str(&output).unwrap_or(Value::String(output)))
+    }
+
+    /// Convert a file to Markdown with the plugin that imports its extension
+    pub async fn convert_import(
+        self: &Arc<Self>,
+        workspace_root: &str,
+        extension: &str,
+        data: Vec<u8>,
+    ) -> Result<String, String> {
+        let plugin = self
+            .plugins(workspace_root)
+            .into_iter()
+            .find(|plugin| plugin.manifest.handles_extension(extension))
+            .ok_or_else(|| format!("No plugin imports .{} files", extension))?;
+        debug!(
+            "Converting .{} file with plugin {}",
+            extension, plugin.manifest.id
+        );
+
+        let output = self
+            .run(
+                workspace_root,
+                plugin,
+                "midlight_import",
+                extension.to_string(),
+                data,
+            )
+            .await?;
+        String::from_utf8(output).map_err(|_| "Plugin returned invalid UTF-8".to_string())
+    }
+
+    fn plugins(&self, workspace_root: &str) -> Vec<Arc<LoadedPlugin>> {
+        self.ensure_loaded(workspace_root);
+        self.loaded
+            .lock()
+            .unwrap()
+            .get(workspace_root)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Compile the workspace's enabled plugins the first time it's used
+    fn ensure_loaded(&self, workspace_root: &str) {
+        if self.loaded.lock().unwrap().contains_key(workspace_root) {
+            return;
+        }
+
+        let root = Path::new(workspace_root);
+        let mut plugins = Vec::new();
+        for (id, grant) in self.grants(root).load() {
+            if !grant.enabled {
+                continue;
+            }
+            let plugin_dir = root.join(PLUGINS_DIR).join(&id);
+            let loaded = PluginManifest::load(&plugin_dir)
+                .and_then(|manifest| self.compile(&plugin_dir, manifest, grant.granted))
+                .and_then(|plugin| match plugin.hash == grant.hash {
+                    true => Ok(plugin),
+                    false => {
+                        Err("Plugin changed since it was enabled; enable it again".to_string())
+                    }
+                });
+            let key = (workspace_root.to_string(), id.clone());
+            match loaded {
+                Ok(plugin) => {
+                    self.load_errors.lock().unwrap().remove(&key);
+                    plugins.push(Arc::new(plugin));
+                }
+                Err(e) => {
+                    warn!("Failed to load plugin {}: {}", id, e);
+                    self.load_errors.lock().unwrap().insert(key, e);
+                }
+            }
+        }
+        plugins.sort_by(|a, b| a.manifest.id.cmp(&b.manifest.id));
+
+        self.loaded
+            .lock()
+            .unwrap()
+            .entry(workspace_root.to_string())
+            .or_insert(plugins);
+    }
+
+    fn compile(
+        &self,
+        plugin_dir: &Path,
+        manifest: PluginManifest,
+        granted: Vec<Permission>,
+    ) -> Result<LoadedPlugin, String> {
+        let bytes = fs::read(plugin_dir.join(&manifest.main))
+            .map_err(|e| format!("Failed to read {}: {}", manifest.main, e))?;
+        let hash = plugin_hash(&manifest, &bytes)?;
+        let module = Module::new(&self.engine, bytes)
+            .map_err(|e| format!("Failed to compile {}: {}", manifest.main, e))?;
+        Ok(LoadedPlugin {
+            manifest,
+            granted,
+            module,
+            hash,
+        })
+    }
+
+    /// Call an entry point in a fresh instance on a blocking thread
+    async fn run(
+        self: &Arc<Self>,
+        workspace_root: &str,
+        plugin: Arc<LoadedPlugin>,
+        export: &'static str,
+        name: String,
+        input: Vec<u8>,
+    ) -> Result<Vec<u8>, String> {
+        if input.len() > MAX_TRANSFER_BYTES {
+            return Err(format!("Input is too large ({} bytes)", input.len()));
+        }
+
+        let runtime = self.clone();
+        let host = HostState {
+            plugin_id: plugin.manifest.id.clone(),
+            granted: plugin.granted.clone(),
+            workspace_root: workspace_root.to_string(),
+            registry: self.registry.clone(),
+            runtime: Handle::current(),
+            limits: StoreLimitsBuilder::new()
+                .memory_size(MAX_MEMORY_BYTES)
+                .build(),
+            error: None,
+            denied: None,
+        };
+        tokio::task::spawn_blocking(move || runtime.call(&plugin, host, export, &name, &input))
+            .await
+            .map_err(|e| format!("Plugin call failed: {}", e))?
+    }
+
+    fn call(
+        &self,
+        plugin: &LoadedPlugin,
+        host: HostState,
+        export: &str,
+        name: &str,
+        input: &[u8],
+    ) -> Result<Vec<u8>, String> {
+        let id = plugin.manifest.id.clone();
+        let mut store = Store::new(&self.engine, host);
+        store.limiter(|host| &mut host.limits);
+        store
+            .set_fuel(FUEL_PER_CALL)
+            .map_err(|e| format!("Failed to start plugin {}: {}", id, e))?;
+
+        let linker = self
+            .linker()
+            .map_err(|e| format!("Failed to start plugin {}: {}", id, e))?;
+        let instance = linker
+            .instantiate(&mut store, &plugin.module)
+            .map_err(|e| format!("Failed to start plugin {}: {}", id, e))?;
+        let entry = instance
+            .get_typed_func::<(i32, i32, i32, i32), i64>(&mut store, export)
+            .map_err(|_| format!("Plugin {} doesn't export {}", id, export))?;
+
+        let result =
+            write_guest(&mut store, &instance, name.as_bytes()).and_then(|(name_ptr, name_len)| {
+                let (input_ptr, input_len) = write_guest(&mut store, &instance, input)?;
+                entry.call(&mut store, (name_ptr, name_len, input_ptr, input_len))
+            });
+
+        let result = match result {
+            Ok(result) => result,
+            Err(e) if e.downcast_ref::<Trap>() == Some(&Trap::OutOfFuel) => {
+                return Err(format!("Plugin {} ran for too long", id))
+            }
+            Err(e) => return Err(format!("Plugin {} failed: {}", id, e)),
+        };
+
+        if result < 0 {
+            let host = store.data();
+            return Err(match (host.denied, &host.error) {
+                (Some(permission), _) => {
+                    format!("Plugin {} needs permission to {}", id, permission)
+                }
+                (None, Some(message)) => format!("Plugin {} failed: {}", id, message),
+                (None, None) => format!("Plugin {} failed", id),
+            });
+        }
+
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| format!("Plugin {} doesn't export memory", id))?;
+        read_memory(&memory, &store, result).map_err(|e| format!("Plugin {} {}", id, e))
+    }
+
+    fn linker(&self) -> wasmtime::Result<Linker<HostState>> {
+        let mut linker = Linker::new(&self.engine);
+
+        linker.func_wrap(
+            "midlight",
+            "log",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                if let Ok(message) = read_string(&mut caller, ptr, len) {
+                    debug!("[plugin {}] {}", caller.data().plugin_id, message);
+                }
+            },
+        )?;
+
+        linker.func_wrap(
+            "midlight",
+            "error",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                if let Ok(message) = read_string(&mut caller, ptr, len) {
+                    caller.data_mut().error = Some(message);
+                }
+            },
+        )?;
+
+        linker.func_wrap(
+            "midlight",
+            "read_document",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> wasmtime::Result<i64> {
+                if !allow(&mut caller, Permission::ReadDocuments) {
+                    return Ok(DENIED);
+                }
+                let Ok(path) = read_string(&mut caller, ptr, len) else {
+                    return Ok(FAILED);
+                };
+
+                let host = caller.data();
+                let markdown = host.runtime.block_on(read_document(
+                    host.registry.clone(),
+                    host.workspace_root.clone(),
+                    path,
+                ));
+                match markdown {
+                    Ok(markdown) => write_caller(&mut caller, markdown.as_bytes()),
+                    Err(e) => {
+                        caller.data_mut().error = Some(e);
+                        Ok(FAILED)
+                    }
+                }
+            },
+        )?;
+
+        linker.func_wrap(
+            "midlight",
+            "write_document",
+            |mut caller: Caller<'_, HostState>,
+             path_ptr: i32,
+             path_len: i32,
+             markdown_ptr: i32,
+             markdown_len: i32|
+             -> i32 {
+                if !allow(&mut caller, Permission::WriteDocuments) {
+                    return DENIED as i32;
+                }
+                let (Ok(path), Ok(markdown)) = (
+                    read_string(&mut caller, path_ptr, path_len),
+                    read_string(&mut caller, markdown_ptr, markdown_len),
+                ) else {
+                    return FAILED as i32;
+                };
+
+                let host = caller.data();
+                let written = host.runtime.block_on(write_document(
+                    host.registry.clone(),
+                    host.workspace_root.clone(),
+                    path,
+                    markdown,
+                ));
+                match written {
+                    Ok(()) => 0,
+                    Err(e) => {
+                        caller.data_mut().error = Some(e);
+                        FAILED as i32
+                    }
+                }
+            },
+        )?;
+
+        Ok(linker)
+    }
+}
+
+/// Check a permission for a host call, remembering refusals for the error
+fn allow(caller: &mut Caller<'_, HostState>, permission: Permission) -> bool {
+    let host = caller.data_mut();
+    if host.granted.contains(&permission) {
+        true
+    } else {
+        warn!(
+            "Plugin {} was refused permission to {}",
+            host.plugin_id, permission
+        );
+        host.denied = Some(permission);
+        false
+    }
+}
+
+async fn read_document(
+    registry: Arc<RwLock<WorkspaceManagerRegistry>>,
+    workspace_root: String,
+    path: String,
+) -> Result<String, String> {
+    let path = document_path(&path)?;
+    if !Path::new(&workspace_root).join(&path).is_file() {
+        return Err(format!("Document not found: {}", path));
+    }
+    let manager = registry
+        .write()
+        .await
+        .get_or_create(&workspace_root)
+        .await
+        .map_err(|e| e.to_string())?;
+    manager
+        .export_markdown(&path)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+async fn write_document(
+    registry: Arc<RwLock<WorkspaceManagerRegistry>>,
+    workspace_root: String,
+    path: String,
+    markdown: String,
+) -> Result<(), String> {
+    let path = document_path(&path)?;
+    let manager = registry
+        .write()
+        .await
+        .get_or_create(&workspace_root)
+        .await
+        .map_err(|e| e.to_string())?;
+    let result = manager
+        .import_markdown(&path, &markdown, TRIGGER)
+        .await
+        .map_err(|e| e.to_string())?;
+    if result.success {
+        Ok(())
+    } else {
+        Err(result.error.unwrap_or_default())
+    }
+}
+
+// ============================================================================
+// Memory
+// ============================================================================
+
+/// Hash of a plugin's manifest and module, to tell whether it changed after
+/// it was enabled
+fn plugin_hash(manifest: &PluginManifest, module: &[u8]) -> Result<String, String> {
+    let manifest = serde_json::to_vec(manifest)
+        .map_err(|e| format!("Failed to serialize {}: {}", MANIFEST_FILE, e))?;
+    let mut hasher = Sha256::new();
+    hasher.update((manifest.len() as u64).to_le_bytes());
+    hasher.update(&manifest);
+    hasher.update(module);
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn pack(ptr: i32, len: usize) -> i64 {
+    ((ptr as u32 as i64) << 32) | len as i64
+}
+
+fn unpack(packed: i64) -> (usize, usize) {
+    (
+        (packed as u64 >> 32) as usize,
+        (packed as u64 & 0xffff_ffff) as usize,
+    )
+}
+
+/// Copy bytes into memory allocated by the module's allocator
+pub(crate) fn write_guest<T>(
+    store: &mut Store<T>,
+    instance: &wasmtime::Instance,
+    bytes: &[u8],
+) -> wasmtime::Result<(i32, i32)> {
+    let alloc = instance.get_typed_func::<i32, i32>(&mut *store, "midlight_alloc")?;
+    let memory = instance
+        .get_memory(&mut *store, "memory")
+        .ok_or_else(|| wasmtime::Error::msg("module doesn't export memory"))?;
+    let ptr = alloc.call(&mut *store, bytes.len() as i32)?;
+    memory.write(&mut *store, ptr as u32 as usize, bytes)?;
+    Ok((ptr, bytes.len() as i32))
+}
+
+/// Copy bytes into the calling module's memory, returning the packed result
+fn write_caller(caller: &mut Caller<'_, HostState>, bytes: &[u8]) -> wasmtime::Result<i64> {
+    if bytes.len() > MAX_TRANSFER_BYTES {
+        caller.data_mut().error = Some(format!("Document is too large ({} bytes)", bytes.len()));
+        return Ok(FAILED);
+    }
+    let alloc = caller
+        .get_export("midlight_alloc")
+        .and_then(Extern::into_func)
+        .ok_or_else(|| wasmtime::Error::msg("module doesn't export midlight_alloc"))?
+        .typed::<i32, i32>(&*caller)?;
+    let ptr = alloc.call(&mut *caller, bytes.len() as i32)?;
+    let memory = caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmtime::Error::msg("module doesn't export memory"))?;
+    memory.write(&mut *caller, ptr as u32 as usize, bytes)?;
+    Ok(pack(ptr, bytes.len()))
+}
+
+pub(crate) fn read_string<T>(
+    caller: &mut Caller<'_, T>,
+    ptr: i32,
+    len: i32,
+) -> Result<String, String> {
+    let len = len as u32 as usize;
+    if len > MAX_TRANSFER_BYTES {
+        return Err("string is too large".to_string());
+    }
+    let memory = caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| "module doesn't export memory".to_string())?;
+    let mut buffer = vec![0; len];
+    memory
+        .read(&*caller, ptr as u32 as usize, &mut buffer)
+        .map_err(|e| e.to_string())?;
+    String::from_utf8(buffer).map_err(|_| "string is not UTF-8".to_string())
+}
+
+pub(crate) fn read_memory<T>(
+    memory: &wasmtime::Memory,
+    store: &Store<T>,
+    packed: i64,
+) -> Result<Vec<u8>, String> {
+    let (ptr, len) = unpack(packed);
+    if len > MAX_TRANSFER_BYTES {
+        return Err(format!("returned too much data ({} bytes)", len));
+    }
+    let mut buffer = vec![0; len];
+    memory
+        .read(store, ptr, &mut buffer)
+        .map_err(|_| "returned an invalid result".to_string())?;
+    Ok(buffer)
+}
+
+/// Folders in the workspace's plugins directory
+fn plugin_dirs(workspace_root: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(workspace_root.join(PLUGINS_DIR)) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect()
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Tools picked by the first letter of their name: echo returns its input,
+    /// loop never returns, write saves notes/a and reads it back, anything
+    /// else fails. The importer returns its input.
+    const TEST_MODULE: &str = r##"
+        (module
+          (import "midlight" "read_document" (func $read (param i32 i32) (result i64)))
+          (import "midlight" "write_document" (func $write (param i32 i32 i32 i32) (result i32)))
+          (import "midlight" "error" (func $error (param i32 i32)))
+          (memory (export "memory") 2)
+          (global $next (mut i32) (i32.const 1024))
+          (data (i32.const 16) "notes/a")
+          (data (i32.const 32) "# Written")
+          (data (i32.const 48) "bad input")
+
+          (func $pack (param $ptr i32) (param $len i32) (result i64)
+            (i64.or
+              (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+              (i64.extend_i32_u (local.get $len))))
+
+          (func (export "midlight_alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $next))
+            (global.set $next (i32.add (global.get $next) (local.get $len)))
+            (local.get $ptr))
+
+          (func (export "midlight_tool")
+            (param $name i32) (param $name_len i32) (param $input i32) (param $input_len i32)
+            (result i64)
+            (local $first i32)
+            (local.set $first (i32.load8_u (local.get $name)))
+            (if (i32.eq (local.get $first) (i32.const 101))
+              (then (return (call $pack (local.get $input) (local.get $input_len)))))
+            (if (i32.eq (local.get $first) (i32.const 108))
+              (then (loop $spin (br $spin))))
+            (if (i32.eq (local.get $first) (i32.const 119))
+              (then
+                (if (i32.lt_s
+                      (call $write (i32.const 16) (i32.const 7) (i32.const 32) (i32.const 9))
+                      (i32.const 0))
+                  (then (return (i64.const -1))))
+                (return (call $read (i32.const 16) (i32.const 7)))))
+            (call $error (i32.const 48) (i32.const 9))
+            (i64.const -1))
+
+          (func (export "midlight_import")
+            (param $ext i32) (param $ext_len i32) (param $data i32) (param $data_len i32)
+            (result i64)
+            (call $pack (local.get $data) (local.get $data_len))))
+    "##;
+
+    fn install_plugin(root: &Path, id: &str, permissions: &[&str]) {
+        let dir = root.join(PLUGINS_DIR).join(id);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("plugin.wasm"),
+            wat::parse_str(TEST_MODULE).unwrap(),
+        )
+        .unwrap();
+        let manifest = serde_json::json!({
+            "id": id,
+            "name": "Test plugin",
+            "version": "1.0.0",
+            "permissions": permissions,
+            "tools": [
+                { "name": "echo", "description": "Echo" },
+                { "name": "loop", "description": "Never returns" },
+                { "name": "write", "description": "Write a note" },
+                { "name": "fail", "description": "Fails" }
+            ],
+            "importers": [{ "name": "Org", "extensions": ["org"] }]
+        });
+        fs::write(dir.join(MANIFEST_FILE), manifest.to_string()).unwrap();
+    }
+
+    fn runtime(grants: &TempDir) -> Arc<PluginRuntime> {
+        let registry = Arc::new(RwLock::new(WorkspaceManagerRegistry::new()));
+        Arc::new(
+            PluginRuntime::new(registry)
+                .with_grants_path(&grants.path().join("plugin-grants.json")),
+        )
+    }
+
+    #[test]
+    fn test_manifest_validation() {
+        let temp = TempDir::new().unwrap();
+        install_plugin(temp.path(), "test", &["documents:read"]);
+        let manifest = PluginManifest::load(&temp.path().join("plugins/test")).unwrap();
+        assert_eq!(manifest.permissions, vec![Permission::ReadDocuments]);
+        assert!(manifest.handles_extension("ORG"));
+
+        let invalid = |change: fn(&mut PluginManifest)| {
+            let mut manifest = manifest.clone();
+            change(&mut manifest);
+            manifest.validate().is_err()
+        };
+        assert!(invalid(|m| m.id = "Bad Id".to_string()));
+        assert!(invalid(|m| m.main = "../escape.wasm".to_string()));
+        assert!(invalid(|m| m.tools[0].name = "read_document".to_string()));
+        assert!(invalid(|m| m.importers[0].extensions.clear()));
+
+        // The id must match the folder
+        fs::rename(
+            temp.path().join("plugins/test"),
+            temp.path().join("plugins/other"),
+        )
+        .unwrap();
+        assert!(PluginManifest::load(&temp.path().join("plugins/other")).is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_lifecycle_and_tools() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().to_string_lossy().to_string();
+        install_plugin(temp.path(), "test", &[]);
+        fs::create_dir_all(temp.path().join("plugins/broken")).unwrap();
+
+        let grants = TempDir::new().unwrap();
+        let plugins = runtime(&grants);
+        let listed = plugins.list(&root);
+        assert_eq!(listed.len(), 2);
+        assert!(listed[0].error.is_some());
+        assert!(!listed[1].enabled);
+        assert!(plugins.tools(&root).is_empty());
+
+        plugins.enable(&root, "test", &[]).unwrap();
+        assert_eq!(plugins.tools(&root).len(), 4);
+        assert_eq!(plugins.import_extensions(&root), vec!["org"]);
+
+        let output = plugins
+            .call_tool(&root, "echo", &serde_json::json!({ "text": "hi" }))
+            .await
+            .unwrap();
+        assert_eq!(output, serde_json::json!({ "text": "hi" }));
+
+        let error = plugins
+            .call_tool(&root, "fail", &Value::Null)
+            .await
+            .unwrap_err();
+        assert_eq!(error, "Plugin test failed: bad input");
+
+        let error = plugins
+            .call_tool(&root, "loop", &Value::Null)
+            .await
+            .unwrap_err();
+        assert_eq!(error, "Plugin test ran for too long");
+
+        let markdown = plugins
+            .convert_import(&root, "org", b"* Heading".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(markdown, "* Heading");
+
+        // Enabled plugins are loaded again in a new session
+        let restarted = runtime(&grants);
+        assert!(restarted.list(&root)[1].enabled);
+        assert_eq!(restarted.tools(&root).len(), 4);
+
+        restarted.disable(&root, "test").unwrap();
+        assert!(restarted.tools(&root).is_empty());
+        assert!(restarted
+            .call_tool(&root, "echo", &Value::Null)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_document_permissions() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().to_string_lossy().to_string();
+        install_plugin(temp.path(), "test", &["documents:read", "documents:write"]);
+        crate::services::workspace_manager::WorkspaceManager::new(temp.path())
+            .init()
+            .await
+            .unwrap();
+
+        let grants = TempDir::new().unwrap();
+        let plugins = runtime(&grants);
+        plugins
+            .enable(&root, "test", &[Permission::ReadDocuments])
+            .unwrap();
+        let error = plugins
+            .call_tool(&root, "write", &Value::Null)
+            .await
+            .unwrap_err();
+        assert_eq!(error, "Plugin test needs permission to write documents");
+        assert!(!temp.path().join("notes/a.midlight").exists());
+
+        plugins
+            .enable(
+                &root,
+                "test",
+                &[Permission::ReadDocuments, Permission::WriteDocuments],
+            )
+            .unwrap();
+        let output = plugins
+            .call_tool(&root, "write", &Value::Null)
+            .await
+            .unwrap();
+        assert_eq!(output, Value::String("# Written".to_string()));
+        assert!(temp.path().join("notes/a.midlight").exists());
+    }
+
+    #[test]
+    fn test_grants_stay_with_the_user() {
+        let grants = TempDir::new().unwrap();
+        let temp = TempDir::new().unwrap();
+        let original = temp.path().join("original");
+        install_plugin(&original, "test", &["documents:write"]);
+        let root = original.to_string_lossy().to_string();
+
+        let plugins = runtime(&grants);
+        plugins
+            .enable(&root, "test", &[Permission::WriteDocuments])
+            .unwrap();
+        assert!(!original.join(".midlight/plugins.json").exists());
+
+        // A copy of the workspace, even one carrying grants of its own,
+        // starts with every plugin disabled
+        let copy = temp.path().join("copy");
+        install_plugin(&copy, "test", &["documents:write"]);
+        fs::create_dir_all(copy.join(".midlight")).unwrap();
+        fs::write(
+            copy.join(".midlight/plugins.json"),
+            r#"{"test":{"enabled":true,"granted":["documents:write"]}}"#,
+        )
+        .unwrap();
+        let copy_root = copy.to_string_lossy().to_string();
+        let listed = plugins.list(&copy_root);
+        assert_eq!(listed.len(), 1);
+        assert!(!listed[0].enabled);
+        assert!(listed[0].granted.is_empty());
+        assert!(plugins.tools(&copy_root).is_empty());
+
+        // The original is still enabled, however its path is written
+        let restarted = runtime(&grants);
+        let alias = format!("{}/../original", copy_root);
+        assert!(restarted.list(&alias)[0].enabled);
+        assert_eq!(restarted.tools(&root).len(), 4);
+    }
+
+    #[test]
+    fn test_changed_plugins_need_enabling_again() {
+        let grants = TempDir::new().unwrap();
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().to_string_lossy().to_string();
+        install_plugin(temp.path(), "test", &["documents:read"]);
+        runtime(&grants)
+            .enable(&root, "test", &[Permission::ReadDocuments])
+            .unwrap();
+        assert_eq!(runtime(&grants).tools(&root).len(), 4);
+
+        // A new permission request in the manifest
+        install_plugin(temp.path(), "test", &["documents:read", "documents:write"]);
+        let plugins = runtime(&grants);
+        assert!(plugins.tools(&root).is_empty());
+        assert!(plugins.list(&root)[0]
+            .error
+            .as_deref()
+            .is_some_and(|e| e.contains("enable it again")));
+
+        plugins
+            .enable(&root, "test", &[Permission::ReadDocuments])
+            .unwrap();
+        assert_eq!(runtime(&grants).tools(&root).len(), 4);
+
+        // A different module
+        let module = temp.path().join("plugins/test/plugin.wasm");
+        let mut bytes = fs::read(&module).unwrap();
+        // An empty custom section keeps the module valid
+        bytes.extend_from_slice(&[0, 2, 1, b'x']);
+        fs::write(&module, bytes).unwrap();
+        assert!(runtime(&grants).tools(&root).is_empty());
+    }
+}
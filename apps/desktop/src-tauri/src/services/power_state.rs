@@ -0,0 +1,412 @@
+// Power State - Battery saver and metered connection detection
+//
+// Background work that uses the network or a lot of CPU waits while the
+// system is in battery saver mode or on a metered connection: re-embedding
+// changed files, sending requests queued while offline, and downloading
+// updates in the background. Work the user asks for directly still runs.
+// Either condition can be ignored in settings.
+//
+// Detection shells out on some platforms, so it runs on a blocking thread
+// once a minute rather than on every check:
+//   battery saver - `pmset -g` on macOS, power-profiles-daemon or the ACPI
+//                   platform profile on Linux, GetSystemPowerStatus on Windows
+//   metered       - NetworkManager on Linux, the connection cost on Windows.
+//                   macOS has no way to ask, so it's never considered metered.
+//
+// Settings: `power.json` in the app data directory.
+
+use crate::services::app_dirs::app_data_dir;
+use crate::services::atomic_write::write_atomic;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// How often battery saver and the connection are checked
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PowerSettings {
+    /// Pause background work while battery saver is on
+    #[serde(default = "default_true")]
+    pub pause_on_battery_saver: bool,
+    /// Pause background work on metered connections
+    #[serde(default = "default_true")]
+    pub pause_on_metered: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for PowerSettings {
+    fn default() -> Self {
+        Self {
+            pause_on_battery_saver: true,
+            pause_on_metered: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PauseReason {
+    BatterySaver,
+    MeteredConnection,
+}
+
+impl std::fmt::Display for PauseReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PauseReason::BatterySaver => write!(f, "battery saver is on"),
+            PauseReason::MeteredConnection => write!(f, "the connection is metered"),
+        }
+    }
+}
+
+/// Whether background work is paused, and why
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackgroundWorkStatus {
+    pub paused: bool,
+    pub reasons: Vec<PauseReason>,
+    pub battery_saver: bool,
+    pub metered: bool,
+    /// When the conditions were last checked; None before the first check
+    pub checked_at: Option<DateTime<Utc>>,
+}
+
+// ============================================================================
+// Settings
+// ============================================================================
+
+/// Path of the saved power settings
+pub fn power_settings_path() -> PathBuf {
+    app_data_dir().join("power.json")
+}
+
+impl PowerSettings {
+    pub fn load(path: &Path) -> Self {
+        if !path.exists() {
+            return Self::default();
+        }
+
+        std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| {
+                warn!("Failed to load power settings, using defaults: {}", e);
+                Self::default()
+            })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize power settings: {}", e))?;
+        write_atomic(path, json).map_err(|e| format!("Failed to save power settings: {}", e))
+    }
+}
+
+// ============================================================================
+// Power Monitor
+// ============================================================================
+
+pub struct PowerMonitor {
+    settings: RwLock<PowerSettings>,
+    battery_saver: AtomicBool,
+    metered: AtomicBool,
+    checked_at: Mutex<Option<DateTime<Utc>>>,
+    started: AtomicBool,
+}
+
+impl PowerMonitor {
+    pub fn new(settings: PowerSettings) -> Self {
+        Self {
+            settings: RwLock::new(settings),
+            battery_saver: AtomicBool::new(false),
+            metered: AtomicBool::new(false),
+            checked_at: Mutex::new(None),
+            started: AtomicBool::new(false),
+        }
+    }
+
+    pub fn settings(&self) -> PowerSettings {
+        self.settings.read().unwrap().clone()
+    }
+
+    pub fn set_settings(&self, settings: PowerSettings) {
+        *self.settings.write().unwrap() = settings;
+    }
+
+    /// Record the detected conditions
+    pub fn update(&self, battery_saver: bool, metered: bool) {
+        let was_paused = self.is_paused();
+        self.battery_saver.store(battery_saver, Ordering::SeqCst);
+        self.metered.store(metered, Ordering::SeqCst);
+        *self.checked_at.lock().unwrap() = Some(Utc::now());
+
+        match (was_paused, self.pause_reasons().first()) {
+            (false, Some(reason)) => info!("Pausing background work: {}", reason),
+            (true, None) => info!("Resuming background work"),
+            _ => {}
+        }
+    }
+
+    /// Why background work should wait, if it should
+    pub fn pause_reasons(&self) -> Vec<PauseReason> {
+        let settings = self.settings.read().unwrap();
+        let mut reasons = Vec::new();
+        if settings.pause_on_battery_saver && self.battery_saver.load(Ordering::SeqCst) {
+            reasons.push(PauseReason::BatterySaver);
+        }
+        if settings.pause_on_metered && self.metered.load(Ordering::SeqCst) {
+            reasons.push(PauseReason::MeteredConnection);
+        }
+        reasons
+    }
+
+    pub fn is_paused(&self) -> bool {
+        !self.pause_reasons().is_empty()
+    }
+
+    pub fn status(&self) -> BackgroundWorkStatus {
+        let reasons = self.pause_reasons();
+        BackgroundWorkStatus {
+            paused: !reasons.is_empty(),
+            reasons,
+            battery_saver: self.battery_saver.load(Ordering::SeqCst),
+            metered: self.metered.load(Ordering::SeqCst),
+            checked_at: *self.checked_at.lock().unwrap(),
+        }
+    }
+
+    /// Start checking the conditions periodically. Only the first call has
+    /// any effect.
+    pub fn start(&'static self) {
+        if self.started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        tauri::async_runtime::spawn(async move {
+            let mut interval = tokio::time::interval(CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                let detected = tokio::task::spawn_blocking(|| {
+                    (detect_battery_saver(), detect_metered_connection())
+                })
+                .await;
+                match detected {
+                    Ok((battery_saver, metered)) => self.update(battery_saver, metered),
+                    Err(e) => warn!("Failed to check power state: {}", e),
+                }
+            }
+        });
+    }
+}
+
+// ============================================================================
+// Detection
+// ============================================================================
+
+#[cfg(target_os = "macos")]
+fn detect_battery_saver() -> bool {
+    std::process::Command::new("pmset")
+        .arg("-g")
+        .output()
+        .map(|output| parse_pmset_low_power(&String::from_utf8_lossy(&output.stdout)))
+        .unwrap_or(false)
+}
+
+#[cfg(target_os = "linux")]
+fn detect_battery_saver() -> bool {
+    if let Ok(output) = std::process::Command::new("powerprofilesctl")
+        .arg("get")
+        .output()
+    {
+        if output.status.success() {
+            return is_power_saver_profile(&String::from_utf8_lossy(&output.stdout));
+        }
+    }
+    std::fs::read_to_string("/sys/firmware/acpi/platform_profile")
+        .map(|profile| is_power_saver_profile(&profile))
+        .unwrap_or(false)
+}
+
+#[cfg(windows)]
+fn detect_battery_saver() -> bool {
+    #[repr(C)]
+    #[derive(Default)]
+    struct SystemPowerStatus {
+        ac_line_status: u8,
+        battery_flag: u8,
+        battery_life_percent: u8,
+        system_status_flag: u8,
+        battery_life_time: u32,
+        battery_full_life_time: u32,
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetSystemPowerStatus(status: *mut SystemPowerStatus) -> i32;
+    }
+
+    let mut status = SystemPowerStatus::default();
+    // SAFETY: the struct matches SYSTEM_POWER_STATUS and outlives the call
+    let ok = unsafe { GetSystemPowerStatus(&mut status) } != 0;
+    // System status flag 1 means battery saver is on
+    ok && status.system_status_flag == 1
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", windows)))]
+fn detect_battery_saver() -> bool {
+    false
+}
+
+#[cfg(target_os = "linux")]
+fn detect_metered_connection() -> bool {
+    std::process::Command::new("nmcli")
+        .args(["-t", "-f", "GENERAL.METERED", "device", "show"])
+        .output()
+        .map(|output| parse_nmcli_metered(&String::from_utf8_lossy(&output.stdout)))
+        .unwrap_or(false)
+}
+
+#[cfg(windows)]
+fn detect_metered_connection() -> bool {
+    const SCRIPT: &str = "[Windows.Networking.Connectivity.NetworkInformation,\
+        Windows.Networking.Connectivity,ContentType=WindowsRuntime]::\
+        GetInternetConnectionProfile().GetConnectionCost().NetworkCostType";
+
+    std::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", SCRIPT])
+        .output()
+        .map(|output| is_metered_cost_type(&String::from_utf8_lossy(&output.stdout)))
+        .unwrap_or(false)
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn detect_metered_connection() -> bool {
+    false
+}
+
+/// Whether `pmset -g` reports Low Power Mode on
+#[cfg(any(test, target_os = "macos"))]
+pub fn parse_pmset_low_power(output: &str) -> bool {
+    output.lines().any(|line| {
+        let mut fields = line.split_whitespace();
+        fields.next() == Some("lowpowermode") && fields.next() == Some("1")
+    })
+}
+
+/// Whether a power-profiles-daemon or ACPI platform profile saves power
+#[cfg(any(test, target_os = "linux"))]
+pub fn is_power_saver_profile(profile: &str) -> bool {
+    matches!(profile.trim(), "power-saver" | "low-power" | "quiet")
+}
+
+/// Whether any device in `nmcli -t -f GENERAL.METERED device show` output is
+/// metered, including ones NetworkManager guessed are (phone hotspots)
+#[cfg(any(test, target_os = "linux"))]
+pub fn parse_nmcli_metered(output: &str) -> bool {
+    output.lines().any(|line| {
+        line.strip_prefix("GENERAL.METERED:")
+            .is_some_and(|value| value.trim().starts_with("yes"))
+    })
+}
+
+/// Whether a Windows NetworkCostType is metered
+#[cfg(any(test, windows))]
+pub fn is_metered_cost_type(cost_type: &str) -> bool {
+    matches!(cost_type.trim(), "Fixed" | "Variable")
+}
+
+// ============================================================================
+// Global Singleton
+// ============================================================================
+
+lazy_static::lazy_static! {
+    pub static ref POWER_MONITOR: PowerMonitor =
+        PowerMonitor::new(PowerSettings::load(&power_settings_path()));
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_pause_reasons_follow_settings() {
+        let monitor = PowerMonitor::new(PowerSettings::default());
+        assert!(!monitor.is_paused());
+        assert!(monitor.status().checked_at.is_none());
+
+        monitor.update(true, true);
+        assert_eq!(
+            monitor.pause_reasons(),
+            vec![PauseReason::BatterySaver, PauseReason::MeteredConnection]
+        );
+
+        monitor.set_settings(PowerSettings {
+            pause_on_battery_saver: false,
+            pause_on_metered: true,
+        });
+        let status = monitor.status();
+        assert!(status.paused);
+        assert_eq!(status.reasons, vec![PauseReason::MeteredConnection]);
+        assert!(status.battery_saver);
+        assert!(status.checked_at.is_some());
+
+        monitor.update(true, false);
+        assert!(!monitor.is_paused());
+    }
+
+    #[test]
+    fn test_settings() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("power.json");
+        assert_eq!(PowerSettings::load(&path), PowerSettings::default());
+
+        std::fs::write(&path, r#"{"pauseOnMetered": false}"#).unwrap();
+        let settings = PowerSettings::load(&path);
+        assert!(settings.pause_on_battery_saver);
+        assert!(!settings.pause_on_metered);
+
+        settings.save(&path).unwrap();
+        assert_eq!(PowerSettings::load(&path), settings);
+    }
+
+    #[test]
+    fn test_parse_detection_output() {
+        let pmset = "System-wide power settings:\nCurrently in use:\n \
+                     standby              1\n lowpowermode         1\n sleep                1\n";
+        assert!(parse_pmset_low_power(pmset));
+        assert!(!parse_pmset_low_power(
+            &pmset.replace("lowpowermode         1", "lowpowermode 0")
+        ));
+
+        assert!(is_power_saver_profile("power-saver\n"));
+        assert!(is_power_saver_profile("low-power"));
+        assert!(!is_power_saver_profile("balanced\n"));
+
+        let nmcli = "GENERAL.METERED:no\nGENERAL.METERED:unknown\n";
+        assert!(!parse_nmcli_metered(nmcli));
+        assert!(parse_nmcli_metered(
+            "GENERAL.METERED:no\nGENERAL.METERED:yes (guessed)\n"
+        ));
+
+        assert!(is_metered_cost_type("Variable\r\n"));
+        assert!(!is_metered_cost_type("Unrestricted\r\n"));
+    }
+}
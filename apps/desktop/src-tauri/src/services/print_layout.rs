@@ -0,0 +1,494 @@
+// Print Layout - Lays a document out for PDF export
+//
+// PDF export prints through the webview, so the document is rendered to a
+// standalone HTML fragment the frontend prints in place of the editor. Its
+// stylesheet sets CSS page margin boxes for the running header and footer,
+// whose templates can show the title, author, date and "page X of Y" via
+// the page counters. A table of contents can be generated from the
+// headings, each entry linking to its heading.
+//
+// Page breaks are marked in the document by a paragraph holding only
+// `\pagebreak`, `\newpage` or `<!-- pagebreak -->`. Markers never print;
+// when breaks are honored, the next block starts a new page.
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fmt::Write as _;
+
+use super::docx_export::{TiptapDocument, TiptapMark, TiptapNode};
+
+/// Paragraph texts that mark a page break
+const PAGE_BREAK_MARKERS: &[&str] = &["\\pagebreak", "\\newpage", "<!-- pagebreak -->"];
+
+/// How a document is laid out for printing
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PrintLayoutOptions {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    /// Running header template; empty for none. See [`PrintLayoutOptions::footer`].
+    pub header: String,
+    /// Running footer template; empty for none. `{title}`, `{author}`,
+    /// `{date}`, `{page}` and `{pages}` are filled in. `|` separates the
+    /// left, center and right parts: one part is centered, two go left
+    /// and right.
+    pub footer: String,
+    pub table_of_contents: bool,
+    /// Deepest heading level listed in the table of contents
+    pub toc_depth: u8,
+    pub honor_page_breaks: bool,
+}
+
+impl Default for PrintLayoutOptions {
+    fn default() -> Self {
+        Self {
+            title: None,
+            author: None,
+            header: String::new(),
+            footer: "Page {page} of {pages}".to_string(),
+            table_of_contents: false,
+            toc_depth: 3,
+            honor_page_breaks: true,
+        }
+    }
+}
+
+/// A heading listed in the table of contents
+struct TocEntry {
+    level: u8,
+    id: String,
+    text: String,
+}
+
+/// Render a document as printable HTML: a stylesheet, the table of
+/// contents if asked for, then the document.
+pub fn render_print_html(doc: &TiptapDocument, options: &PrintLayoutOptions) -> String {
+    let mut renderer = Renderer {
+        options,
+        ids: HashSet::new(),
+        toc: Vec::new(),
+    };
+    let mut body = String::new();
+    for node in &doc.content {
+        renderer.block(node, &mut body);
+    }
+
+    let mut html = String::new();
+    html.push_str("<style>");
+    html.push_str(&page_css(options));
+    html.push_str("</style>");
+
+    let toc: Vec<&TocEntry> = renderer
+        .toc
+        .iter()
+        .filter(|entry| entry.level <= options.toc_depth)
+        .collect();
+    if options.table_of_contents && !toc.is_empty() {
+        html.push_str("<nav class=\"print-toc\"><h1>Contents</h1><ol>");
+        for entry in toc {
+            let _ = write!(
+                html,
+                "<li class=\"toc-level-{}\"><a href=\"#{}\">{}</a></li>",
+                entry.level,
+                entry.id,
+                escape(&entry.text)
+            );
+        }
+        html.push_str("</ol></nav>");
+    }
+
+    html.push_str("<article class=\"print-document\">");
+    html.push_str(&body);
+    html.push_str("</article>");
+    html
+}
+
+/// The `@page` rules for the running header and footer
+fn page_css(options: &PrintLayoutOptions) -> String {
+    let mut css = String::from("@page{margin:2cm;");
+    for (edge, template) in [("top", &options.header), ("bottom", &options.footer)] {
+        let parts: Vec<&str> = template.split('|').map(str::trim).collect();
+        let boxes: &[&str] = match parts.len() {
+            1 => &["center"],
+            2 => &["left", "right"],
+            _ => &["left", "center", "right"],
+        };
+        for (position, part) in boxes.iter().zip(&parts) {
+            if let Some(content) = css_content(part, options) {
+                let _ = write!(
+                    css,
+                    "@{}-{}{{content:{};font-size:9pt;color:#555;}}",
+                    edge, position, content
+                );
+            }
+        }
+    }
+    css.push('}');
+    css.push_str(
+        ".print-toc{break-after:page;}\
+         .print-toc ol{list-style:none;padding:0;}\
+         .print-toc a{color:inherit;text-decoration:none;}\
+         .print-toc .toc-level-2{margin-left:1.5em;}\
+         .print-toc .toc-level-3{margin-left:3em;}\
+         .print-toc .toc-level-4,.print-toc .toc-level-5,.print-toc .toc-level-6{margin-left:4.5em;}\
+         .page-break{break-after:page;}\
+         .print-document h1,.print-document h2,.print-document h3{break-after:avoid;}\
+         .print-document img{max-width:100%;}",
+    );
+    css
+}
+
+/// A header or footer template as a CSS `content` value, or None when it
+/// comes out empty
+fn css_content(template: &str, options: &PrintLayoutOptions) -> Option<String> {
+    let date = Local::now().format("%B %-d, %Y").to_string();
+    let mut values = Vec::new();
+    let mut literal = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        literal.push_str(&rest[..start]);
+        let placeholder = &rest[start + 1..start + len];
+        match placeholder {
+            "page" | "pages" => {
+                if !literal.is_empty() {
+                    values.push(css_string(&literal));
+                    literal.clear();
+                }
+                values.push(format!("counter({})", placeholder));
+            }
+            "title" => literal.push_str(options.title.as_deref().unwrap_or_default()),
+            "author" => literal.push_str(options.author.as_deref().unwrap_or_default()),
+            "date" => literal.push_str(&date),
+            _ => literal.push_str(&rest[start..=start + len]),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    literal.push_str(rest);
+    if !literal.trim().is_empty() {
+        values.push(css_string(&literal));
+    }
+    match values.is_empty() {
+        true => None,
+        false => Some(values.join(" ")),
+    }
+}
+
+fn css_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '<' => quoted.push_str("\\3c "),
+            '\n' | '\r' => quoted.push(' '),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Plain text of a node and its children
+fn text_of(node: &TiptapNode) -> String {
+    match &node.text {
+        Some(text) => text.clone(),
+        None => node.content.iter().map(text_of).collect(),
+    }
+}
+
+fn attr<'a>(node: &'a TiptapNode, name: &str) -> Option<&'a Value> {
+    node.attrs.as_ref().and_then(|attrs| attrs.get(name))
+}
+
+/// URLs that are safe to print: web, data images, and relative paths
+fn safe_url(url: &str) -> bool {
+    let lower = url.trim().to_lowercase();
+    match lower.split_once(':') {
+        Some((scheme, _)) if !scheme.contains('/') => {
+            matches!(scheme, "http" | "https" | "mailto")
+                || lower.starts_with("data:image/")
+                || scheme == "midlight"
+        }
+        _ => true,
+    }
+}
+
+struct Renderer<'a> {
+    options: &'a PrintLayoutOptions,
+    ids: HashSet<String>,
+    toc: Vec<TocEntry>,
+}
+
+impl Renderer<'_> {
+    fn block(&mut self, node: &TiptapNode, out: &mut String) {
+        match node.node_type.as_str() {
+            "paragraph" => {
+                let text = text_of(node);
+                if PAGE_BREAK_MARKERS.contains(&text.trim()) {
+                    if self.options.honor_page_breaks {
+                        out.push_str("<div class=\"page-break\"></div>");
+                    }
+                    return;
+                }
+                let _ = write!(out, "<p{}>", align_style(node));
+                self.inline(&node.content, out);
+                out.push_str("</p>");
+            }
+            "heading" => {
+                let level = attr(node, "level")
+                    .and_then(Value::as_u64)
+                    .unwrap_or(1)
+                    .clamp(1, 6) as u8;
+                let text = text_of(node);
+                let id = self.heading_id(&text);
+                let _ = write!(out, "<h{} id=\"{}\"{}>", level, id, align_style(node));
+                self.inline(&node.content, out);
+                let _ = write!(out, "</h{}>", level);
+                if !text.trim().is_empty() {
+                    self.toc.push(TocEntry {
+                        level,
+                        id,
+                        text: text.trim().to_string(),
+                    });
+                }
+            }
+            "bulletList" => self.children("ul", "", node, out),
+            "orderedList" => {
+                let start = attr(node, "start").and_then(Value::as_u64).unwrap_or(1);
+                let attrs = match start {
+                    1 => String::new(),
+                    n => format!(" start=\"{}\"", n),
+                };
+                self.children("ol", &attrs, node, out);
+            }
+            "taskList" => self.children("ul", " class=\"task-list\"", node, out),
+            "listItem" => self.children("li", "", node, out),
+            "taskItem" => {
+                let checked = attr(node, "checked").and_then(Value::as_bool) == Some(true);
+                out.push_str("<li>");
+                out.push_str(if checked { "&#9745; " } else { "&#9744; " });
+                for child in &node.content {
+                    self.block(child, out);
+                }
+                out.push_str("</li>");
+            }
+            "blockquote" => self.children("blockquote", "", node, out),
+            "codeBlock" => {
+                out.push_str("<pre><code>");
+                out.push_str(&escape(&text_of(node)));
+                out.push_str("</code></pre>");
+            }
+            "horizontalRule" => out.push_str("<hr>"),
+            "pageBreak" => {
+                if self.options.honor_page_breaks {
+                    out.push_str("<div class=\"page-break\"></div>");
+                }
+            }
+            "image" => {
+                let src = attr(node, "src").and_then(Value::as_str).unwrap_or("");
+                if !src.is_empty() && safe_url(src) {
+                    let alt = attr(node, "alt").and_then(Value::as_str).unwrap_or("");
+                    let _ = write!(
+                        out,
+                        "<p{}><img src=\"{}\" alt=\"{}\"></p>",
+                        align_style(node),
+                        escape(src),
+                        escape(alt)
+                    );
+                }
+            }
+            "table" => self.children("table", "", node, out),
+            "tableRow" => self.children("tr", "", node, out),
+            "tableHeader" => self.children("th", "", node, out),
+            "tableCell" => self.children("td", "", node, out),
+            _ if node.text.is_some() => {
+                out.push_str("<p>");
+                self.inline(std::slice::from_ref(node), out);
+                out.push_str("</p>");
+            }
+            _ => {
+                for child in &node.content {
+                    self.block(child, out);
+                }
+            }
+        }
+    }
+
+    fn children(&mut self, tag: &str, attrs: &str, node: &TiptapNode, out: &mut String) {
+        let _ = write!(out, "<{}{}>", tag, attrs);
+        for child in &node.content {
+            self.block(child, out);
+        }
+        let _ = write!(out, "</{}>", tag);
+    }
+
+    fn inline(&mut self, nodes: &[TiptapNode], out: &mut String) {
+        for node in nodes {
+            match node.node_type.as_str() {
+                "text" => {
+                    let text = escape(node.text.as_deref().unwrap_or(""));
+                    out.push_str(&wrap_marks(&text, &node.marks));
+                }
+                "hardBreak" => out.push_str("<br>"),
+                "image" => self.block(node, out),
+                _ => self.inline(&node.content, out),
+            }
+        }
+    }
+
+    /// A unique anchor id for a heading
+    fn heading_id(&mut self, text: &str) -> String {
+        let slug: String = text
+            .trim()
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '-' })
+            .collect::<String>()
+            .split('-')
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("-");
+        let base = match slug.is_empty() {
+            true => "section".to_string(),
+            false => slug,
+        };
+        let mut id = base.clone();
+        let mut n = 1;
+        while !self.ids.insert(id.clone()) {
+            n += 1;
+            id = format!("{}-{}", base, n);
+        }
+        id
+    }
+}
+
+fn align_style(node: &TiptapNode) -> String {
+    match attr(node, "textAlign").and_then(Value::as_str) {
+        Some(align @ ("center" | "right" | "justify")) => {
+            format!(" style=\"text-align:{}\"", align)
+        }
+        _ => String::new(),
+    }
+}
+
+fn wrap_marks(text: &str, marks: &[TiptapMark]) -> String {
+    let mut html = text.to_string();
+    for mark in marks {
+        html = match mark.mark_type.as_str() {
+            "bold" => format!("<strong>{}</strong>", html),
+            "italic" => format!("<em>{}</em>", html),
+            "underline" => format!("<u>{}</u>", html),
+            "strike" => format!("<s>{}</s>", html),
+            "code" => format!("<code>{}</code>", html),
+            "superscript" => format!("<sup>{}</sup>", html),
+            "subscript" => format!("<sub>{}</sub>", html),
+            "highlight" => format!("<mark>{}</mark>", html),
+            "link" => {
+                let href = mark
+                    .attrs
+                    .as_ref()
+                    .and_then(|attrs| attrs.get("href"))
+                    .and_then(Value::as_str)
+                    .filter(|href| safe_url(href));
+                match href {
+                    Some(href) => format!("<a href=\"{}\">{}</a>", escape(href), html),
+                    None => html,
+                }
+            }
+            _ => html,
+        };
+    }
+    html
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_print_html() {
+        let doc: TiptapDocument = serde_json::from_value(json!({
+            "type": "doc",
+            "content": [
+                { "type": "heading", "attrs": { "level": 1 },
+                  "content": [{ "type": "text", "text": "Intro" }] },
+                { "type": "paragraph", "content": [
+                    { "type": "text", "text": "Fish & <chips>", "marks": [{ "type": "bold" }] },
+                    { "type": "text", "text": "bad", "marks": [
+                        { "type": "link", "attrs": { "href": "javascript:alert(1)" } }
+                    ]}
+                ]},
+                { "type": "paragraph", "content": [{ "type": "text", "text": " \\pagebreak " }] },
+                { "type": "heading", "attrs": { "level": 2 },
+                  "content": [{ "type": "text", "text": "Intro" }] },
+                { "type": "heading", "attrs": { "level": 4 },
+                  "content": [{ "type": "text", "text": "Details" }] }
+            ]
+        }))
+        .unwrap();
+
+        let options = PrintLayoutOptions {
+            title: Some("Report \"Q3\"".to_string()),
+            header: "{title} | {author}".to_string(),
+            table_of_contents: true,
+            ..Default::default()
+        };
+        let html = render_print_html(&doc, &options);
+
+        assert!(html.contains("@top-left{content:\"Report \\\"Q3\\\"\";"));
+        assert!(!html.contains("@top-right"));
+        assert!(html
+            .contains("@bottom-center{content:\"Page \" counter(page) \" of \" counter(pages);"));
+        assert!(html.contains("<strong>Fish &amp; &lt;chips&gt;</strong>bad</p>"));
+        assert!(!html.contains("javascript"));
+        assert!(html.contains("<div class=\"page-break\"></div>"));
+        assert!(!html.contains("pagebreak</p>"));
+
+        // Headings get unique anchors, listed down to the TOC depth
+        assert!(html.contains("<h1 id=\"intro\">"));
+        assert!(html.contains("<h2 id=\"intro-2\">"));
+        assert!(html.contains("<a href=\"#intro-2\">Intro</a>"));
+        assert!(html.contains("<h4 id=\"details\">"));
+        assert!(!html.contains("href=\"#details\""));
+
+        let plain = render_print_html(
+            &doc,
+            &PrintLayoutOptions {
+                footer: String::new(),
+                honor_page_breaks: false,
+                ..Default::default()
+            },
+        );
+        assert!(!plain.contains("print-toc\">"));
+        assert!(!plain.contains("@bottom"));
+        assert!(!plain.contains("<div class=\"page-break\">"));
+        assert!(!plain.contains("pagebreak</p>"));
+    }
+}
@@ -0,0 +1,430 @@
+// Prompt Templates - Reusable AI actions with variable substitution
+//
+// Templates contain `{{placeholder}}` variables that are expanded before the
+// prompt is sent to the LLM service. Values are substituted in a single pass,
+// so text inserted from a selection or document is never expanded again.
+//
+// Built-in variables:
+//   {{selection}} - the text currently selected in the editor
+//   {{document}}  - the full text of the current document
+//   {{title}}     - the document title (defaults to the file name)
+//   {{date}}      - today's date (YYYY-MM-DD)
+//
+// Templates are stored at: .midlight/prompt-templates.json
+
+use crate::services::atomic_write::write_atomic;
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+const TEMPLATES_VERSION: u32 = 1;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptTemplate {
+    pub id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Optional system prompt, also expanded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    /// The user prompt with `{{placeholder}}` variables
+    pub template: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Template sent from the frontend to be saved
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavePromptTemplateInput {
+    /// Omit to create a new template
+    pub id: Option<String>,
+    pub name: String,
+    pub description: Option<String>,
+    pub system_prompt: Option<String>,
+    pub template: String,
+}
+
+/// A template with its variables filled in
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpandedPrompt {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    pub prompt: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TemplatesFile {
+    version: u32,
+    #[serde(default)]
+    templates: Vec<PromptTemplate>,
+}
+
+// ============================================================================
+// Expansion
+// ============================================================================
+
+/// A variable name is made of letters, digits, `_`, `-` and `.`
+fn is_variable_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+/// A piece of a parsed template
+enum Segment<'a> {
+    Text(&'a str),
+    Variable(&'a str),
+}
+
+/// Split a template into literal text and placeholders.
+/// Malformed placeholders are treated as literal text.
+fn parse(template: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) if is_variable_name(after[..end].trim()) => {
+                segments.push(Segment::Text(&rest[..start]));
+                segments.push(Segment::Variable(after[..end].trim()));
+                rest = &after[end + 2..];
+            }
+            _ => {
+                segments.push(Segment::Text(&rest[..start + 2]));
+                rest = after;
+            }
+        }
+    }
+
+    segments.push(Segment::Text(rest));
+    segments
+}
+
+/// List the distinct variables used by a template, in order of appearance
+pub fn placeholders(template: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for segment in parse(template) {
+        if let Segment::Variable(name) = segment {
+            if !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names
+}
+
+/// Expand a template. Fails listing every variable without a value.
+pub fn expand(template: &str, variables: &HashMap<String, String>) -> Result<String, String> {
+    let mut output = String::with_capacity(template.len());
+    let mut missing: Vec<&str> = Vec::new();
+
+    for segment in parse(template) {
+        match segment {
+            Segment::Text(text) => output.push_str(text),
+            Segment::Variable(name) => match variables.get(name) {
+                Some(value) => output.push_str(value),
+                None if !missing.contains(&name) => missing.push(name),
+                None => {}
+            },
+        }
+    }
+
+    if missing.is_empty() {
+        Ok(output)
+    } else {
+        Err(format!("Missing values for: {}", missing.join(", ")))
+    }
+}
+
+/// Add built-in variables that the caller didn't provide
+pub fn with_builtins(
+    mut variables: HashMap<String, String>,
+    document_path: Option<&str>,
+) -> HashMap<String, String> {
+    variables
+        .entry("date".to_string())
+        .or_insert_with(|| Local::now().format("%Y-%m-%d").to_string());
+
+    if let Some(stem) = document_path
+        .and_then(|p| Path::new(p).file_stem())
+        .map(|s| s.to_string_lossy().to_string())
+    {
+        variables.entry("title".to_string()).or_insert(stem);
+    }
+
+    variables
+}
+
+impl PromptTemplate {
+    /// Expand both the system prompt and the user prompt
+    pub fn expand(&self, variables: &HashMap<String, String>) -> Result<ExpandedPrompt, String> {
+        Ok(ExpandedPrompt {
+            system_prompt: self
+                .system_prompt
+                .as_deref()
+                .map(|s| expand(s, variables))
+                .transpose()?,
+            prompt: expand(&self.template, variables)?,
+        })
+    }
+}
+
+// ============================================================================
+// Template Store
+// ============================================================================
+
+/// Reads and writes the prompt templates of a single workspace
+pub struct PromptTemplateStore {
+    templates_path: PathBuf,
+}
+
+impl PromptTemplateStore {
+    pub fn new(workspace_root: &Path) -> Self {
+        Self {
+            templates_path: workspace_root
+                .join(".midlight")
+                .join("prompt-templates.json"),
+        }
+    }
+
+    /// List all templates, sorted by name
+    pub fn list(&self) -> Result<Vec<PromptTemplate>, String> {
+        let mut templates = self.read()?.templates;
+        templates.sort_by_key(|t| t.name.to_lowercase());
+        Ok(templates)
+    }
+
+    pub fn get(&self, id: &str) -> Result<PromptTemplate, String> {
+        self.read()?
+            .templates
+            .into_iter()
+            .find(|t| t.id == id)
+            .ok_or_else(|| format!("Prompt template not found: {}", id))
+    }
+
+    /// Create or update a template
+    pub fn save(&self, input: SavePromptTemplateInput) -> Result<PromptTemplate, String> {
+        let name = input.name.trim();
+        if name.is_empty() {
+            return Err("Prompt template name cannot be empty".to_string());
+        }
+        if input.template.trim().is_empty() {
+            return Err("Prompt template cannot be empty".to_string());
+        }
+
+        let mut file = self.read()?;
+        let now = Utc::now();
+        let existing = input
+            .id
+            .as_ref()
+            .and_then(|id| file.templates.iter().position(|t| &t.id == id));
+
+        let template = PromptTemplate {
+            id: input.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            name: name.to_string(),
+            description: input.description.filter(|d| !d.trim().is_empty()),
+            system_prompt: input.system_prompt.filter(|s| !s.trim().is_empty()),
+            template: input.template,
+            created_at: existing
+                .map(|i| file.templates[i].created_at)
+                .unwrap_or(now),
+            updated_at: now,
+        };
+
+        match existing {
+            Some(index) => file.templates[index] = template.clone(),
+            None => file.templates.push(template.clone()),
+        }
+
+        self.write(&file)?;
+        Ok(template)
+    }
+
+    /// Delete a template. Returns false if it didn't exist.
+    pub fn delete(&self, id: &str) -> Result<bool, String> {
+        let mut file = self.read()?;
+        let before = file.templates.len();
+        file.templates.retain(|t| t.id != id);
+
+        if file.templates.len() == before {
+            return Ok(false);
+        }
+        self.write(&file)?;
+        Ok(true)
+    }
+
+    fn read(&self) -> Result<TemplatesFile, String> {
+        if !self.templates_path.exists() {
+            return Ok(TemplatesFile {
+                version: TEMPLATES_VERSION,
+                templates: Vec::new(),
+            });
+        }
+
+        let content = fs::read_to_string(&self.templates_path)
+            .map_err(|e| format!("Failed to read prompt templates: {}", e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse prompt templates: {}", e))
+    }
+
+    fn write(&self, file: &TemplatesFile) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(file)
+            .map_err(|e| format!("Failed to serialize prompt templates: {}", e))?;
+        write_atomic(&self.templates_path, json)
+            .map_err(|e| format!("Failed to write prompt templates: {}", e))
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn input(id: Option<&str>, name: &str, template: &str) -> SavePromptTemplateInput {
+        SavePromptTemplateInput {
+            id: id.map(String::from),
+            name: name.to_string(),
+            description: None,
+            system_prompt: None,
+            template: template.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_expand_substitutes_variables() {
+        let result = expand(
+            "Summarize {{ selection }} from \"{{title}}\".",
+            &vars(&[("selection", "the intro"), ("title", "Draft")]),
+        )
+        .unwrap();
+        assert_eq!(result, "Summarize the intro from \"Draft\".");
+    }
+
+    #[test]
+    fn test_expand_does_not_reexpand_values() {
+        let result = expand(
+            "Fix: {{selection}}",
+            &vars(&[("selection", "{{document}}"), ("document", "secret")]),
+        )
+        .unwrap();
+        assert_eq!(result, "Fix: {{document}}");
+    }
+
+    #[test]
+    fn test_expand_reports_missing_variables() {
+        let err = expand("{{a}} {{b}} {{a}}", &vars(&[])).unwrap_err();
+        assert_eq!(err, "Missing values for: a, b");
+    }
+
+    #[test]
+    fn test_malformed_placeholders_are_literal() {
+        let template = "Use {{ two words }} or {{unclosed and {} braces";
+        assert!(placeholders(template).is_empty());
+        assert_eq!(expand(template, &vars(&[])).unwrap(), template);
+    }
+
+    #[test]
+    fn test_placeholders_in_order_without_duplicates() {
+        assert_eq!(
+            placeholders("{{selection}} {{title}} {{ selection }} {{date}}"),
+            vec!["selection", "title", "date"]
+        );
+    }
+
+    #[test]
+    fn test_with_builtins() {
+        let variables = with_builtins(
+            vars(&[("date", "2020-01-01")]),
+            Some("notes/Weekly Review.midlight"),
+        );
+        assert_eq!(variables["date"], "2020-01-01");
+        assert_eq!(variables["title"], "Weekly Review");
+
+        let variables = with_builtins(vars(&[("title", "Custom")]), Some("a.midlight"));
+        assert_eq!(variables["title"], "Custom");
+        assert_eq!(variables["date"].len(), 10);
+    }
+
+    #[test]
+    fn test_template_expands_system_prompt() {
+        let template = PromptTemplate {
+            id: "t".to_string(),
+            name: "Translate".to_string(),
+            description: None,
+            system_prompt: Some("Translate into {{language}}.".to_string()),
+            template: "{{selection}}".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let expanded = template
+            .expand(&vars(&[("language", "French"), ("selection", "Hello")]))
+            .unwrap();
+        assert_eq!(
+            expanded.system_prompt.as_deref(),
+            Some("Translate into French.")
+        );
+        assert_eq!(expanded.prompt, "Hello");
+    }
+
+    #[test]
+    fn test_store_crud() {
+        let temp = TempDir::new().unwrap();
+        let store = PromptTemplateStore::new(temp.path());
+
+        assert!(store.save(input(None, "  ", "x")).is_err());
+        assert!(store.save(input(None, "Empty", " ")).is_err());
+
+        let shorten = store
+            .save(input(None, "Shorten", "Shorten: {{selection}}"))
+            .unwrap();
+        store
+            .save(input(None, "brainstorm", "Ideas for {{title}}"))
+            .unwrap();
+
+        let names: Vec<String> = store.list().unwrap().into_iter().map(|t| t.name).collect();
+        assert_eq!(names, vec!["brainstorm", "Shorten"]);
+
+        let updated = store
+            .save(input(
+                Some(&shorten.id),
+                "Shorten",
+                "Make shorter: {{selection}}",
+            ))
+            .unwrap();
+        assert_eq!(updated.created_at, shorten.created_at);
+        assert_eq!(
+            store.get(&shorten.id).unwrap().template,
+            "Make shorter: {{selection}}"
+        );
+        assert_eq!(store.list().unwrap().len(), 2);
+
+        assert!(store.delete(&shorten.id).unwrap());
+        assert!(!store.delete(&shorten.id).unwrap());
+        assert!(store.get(&shorten.id).is_err());
+    }
+}
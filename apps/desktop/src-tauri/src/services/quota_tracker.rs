@@ -0,0 +1,330 @@
+// Quota Tracker - Running count of LLM quota use, with soft-limit alerts
+//
+// The backend reports quota on each LLM response, in X-Quota-* headers or a
+// `quota` field on the final stream chunk. When a response doesn't say, the
+// tokens it used are added to the last known count, so the count stays close
+// between reports. Crossing an alert threshold (80% and 95% by default)
+// produces an alert once; a count that drops back, as when the quota period
+// resets, re-arms the thresholds below it.
+//
+// Storage: `quota-alerts.json` in the app data directory.
+
+use crate::services::app_dirs::app_data_dir;
+use crate::services::atomic_write::write_atomic;
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+use crate::services::llm_service::{QuotaInfo, UsageInfo};
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Usage percentages that trigger an alert
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaAlertSettings {
+    #[serde(default = "default_thresholds")]
+    pub thresholds: Vec<u8>,
+}
+
+fn default_thresholds() -> Vec<u8> {
+    vec![80, 95]
+}
+
+impl Default for QuotaAlertSettings {
+    fn default() -> Self {
+        Self {
+            thresholds: default_thresholds(),
+        }
+    }
+}
+
+/// Quota as reported by the backend for a single response
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportedQuota {
+    pub used: u32,
+    pub limit: Option<u32>,
+    #[serde(default)]
+    pub resets_at: Option<String>,
+}
+
+/// The tracked quota
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackedQuota {
+    pub used: u32,
+    pub limit: Option<u32>,
+    pub remaining: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resets_at: Option<String>,
+}
+
+impl TrackedQuota {
+    /// Share of the limit used, in percent; None without a limit
+    pub fn percent_used(&self) -> Option<f64> {
+        self.limit
+            .filter(|limit| *limit > 0)
+            .map(|limit| self.used as f64 * 100.0 / limit as f64)
+    }
+}
+
+/// Sent whenever the tracked quota changes
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaEvent {
+    pub quota: TrackedQuota,
+    /// The alert threshold just crossed, if any
+    pub threshold: Option<u8>,
+}
+
+pub type QuotaListener = Arc<dyn Fn(QuotaEvent) + Send + Sync>;
+
+// ============================================================================
+// Settings
+// ============================================================================
+
+/// Path of the saved alert settings
+pub fn quota_alerts_path() -> PathBuf {
+    app_data_dir().join("quota-alerts.json")
+}
+
+impl QuotaAlertSettings {
+    pub fn load(path: &Path) -> Self {
+        if !path.exists() {
+            return Self::default();
+        }
+
+        std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| {
+                warn!("Failed to load quota alert settings, using defaults: {}", e);
+                Self::default()
+            })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize quota alert settings: {}", e))?;
+        write_atomic(path, json).map_err(|e| format!("Failed to save quota alert settings: {}", e))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        match self.thresholds.iter().find(|t| **t == 0 || **t > 100) {
+            Some(t) => Err(format!("Alert threshold must be 1-100%, got {}", t)),
+            None => Ok(()),
+        }
+    }
+}
+
+// ============================================================================
+// Tracker
+// ============================================================================
+
+#[derive(Default)]
+struct TrackerState {
+    quota: Option<TrackedQuota>,
+    /// Highest threshold already alerted on
+    alerted: u8,
+}
+
+#[derive(Default)]
+pub struct QuotaTracker {
+    state: Mutex<TrackerState>,
+    settings: Mutex<QuotaAlertSettings>,
+}
+
+impl QuotaTracker {
+    pub fn settings(&self) -> QuotaAlertSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    pub fn set_settings(&self, settings: QuotaAlertSettings) {
+        *self.settings.lock().unwrap() = settings;
+    }
+
+    pub fn current(&self) -> Option<TrackedQuota> {
+        self.state.lock().unwrap().quota.clone()
+    }
+
+    /// Replace the count with a fresh quota from the quota endpoint
+    pub fn sync(&self, quota: &QuotaInfo) -> Option<QuotaEvent> {
+        self.update(
+            Some(ReportedQuota {
+                used: quota.used,
+                limit: quota.limit,
+                resets_at: quota.resets_at.clone(),
+            }),
+            None,
+        )
+    }
+
+    /// Update from a response: its reported quota if any, otherwise by the
+    /// tokens it used. Returns an event if anything changed.
+    pub fn update(
+        &self,
+        reported: Option<ReportedQuota>,
+        usage: Option<&UsageInfo>,
+    ) -> Option<QuotaEvent> {
+        let mut state = self.state.lock().unwrap();
+        let quota = match (reported, state.quota.take(), usage) {
+            (Some(reported), previous, _) => TrackedQuota {
+                used: reported.used,
+                limit: reported.limit,
+                remaining: None,
+                resets_at: reported
+                    .resets_at
+                    .or_else(|| previous.and_then(|p| p.resets_at)),
+            },
+            (None, Some(mut previous), Some(usage)) if usage.total_tokens > 0 => {
+                previous.used = previous.used.saturating_add(usage.total_tokens);
+                previous
+            }
+            (None, previous, _) => {
+                state.quota = previous;
+                return None;
+            }
+        };
+        let quota = TrackedQuota {
+            remaining: quota.limit.map(|limit| limit.saturating_sub(quota.used)),
+            ..quota
+        };
+
+        let level = quota.percent_used().map_or(0, |percent| {
+            self.settings
+                .lock()
+                .unwrap()
+                .thresholds
+                .iter()
+                .copied()
+                .filter(|t| percent >= *t as f64)
+                .max()
+                .unwrap_or(0)
+        });
+        let threshold = (level > state.alerted).then_some(level);
+        state.alerted = level;
+        state.quota = Some(quota.clone());
+
+        Some(QuotaEvent { quota, threshold })
+    }
+}
+
+/// Quota from X-Quota-Used / X-Quota-Limit / X-Quota-Reset response headers
+pub fn quota_from_headers(headers: &HeaderMap) -> Option<ReportedQuota> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    let used = header("x-quota-used")?.trim().parse().ok()?;
+    Some(ReportedQuota {
+        used,
+        limit: header("x-quota-limit").and_then(|v| v.trim().parse().ok()),
+        resets_at: header("x-quota-reset").map(|v| v.trim().to_string()),
+    })
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(total_tokens: u32) -> UsageInfo {
+        UsageInfo {
+            prompt_tokens: 0,
+            completion_tokens: total_tokens,
+            total_tokens,
+        }
+    }
+
+    fn reported(used: u32) -> Option<ReportedQuota> {
+        Some(ReportedQuota {
+            used,
+            limit: Some(1000),
+            resets_at: None,
+        })
+    }
+
+    #[test]
+    fn test_threshold_alerts() {
+        let tracker = QuotaTracker::default();
+
+        // Nothing to count from until the quota is known
+        assert!(tracker.update(None, Some(&usage(100))).is_none());
+
+        let event = tracker.update(reported(700), None).unwrap();
+        assert_eq!(event.threshold, None);
+        assert_eq!(event.quota.remaining, Some(300));
+
+        // Running count crosses 80%, alerting once
+        let event = tracker.update(None, Some(&usage(120))).unwrap();
+        assert_eq!(event.quota.used, 820);
+        assert_eq!(event.threshold, Some(80));
+        assert_eq!(
+            tracker.update(None, Some(&usage(10))).unwrap().threshold,
+            None
+        );
+
+        // Jumping past both thresholds reports the higher one
+        assert_eq!(
+            tracker.update(reported(990), None).unwrap().threshold,
+            Some(95)
+        );
+        assert_eq!(
+            tracker
+                .update(reported(1200), None)
+                .unwrap()
+                .quota
+                .remaining,
+            Some(0)
+        );
+
+        // A new period re-arms the thresholds
+        assert_eq!(tracker.update(reported(10), None).unwrap().threshold, None);
+        assert_eq!(
+            tracker.update(reported(850), None).unwrap().threshold,
+            Some(80)
+        );
+
+        // No limit, no alerts
+        let event = tracker
+            .update(
+                Some(ReportedQuota {
+                    used: 5000,
+                    ..Default::default()
+                }),
+                None,
+            )
+            .unwrap();
+        assert_eq!(event.threshold, None);
+        assert_eq!(event.quota.remaining, None);
+    }
+
+    #[test]
+    fn test_quota_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert!(quota_from_headers(&headers).is_none());
+
+        headers.insert("x-quota-used", "420".parse().unwrap());
+        headers.insert("x-quota-limit", "1000".parse().unwrap());
+        headers.insert("x-quota-reset", "2024-02-01T00:00:00Z".parse().unwrap());
+        assert_eq!(
+            quota_from_headers(&headers),
+            Some(ReportedQuota {
+                used: 420,
+                limit: Some(1000),
+                resets_at: Some("2024-02-01T00:00:00Z".to_string()),
+            })
+        );
+
+        let settings = QuotaAlertSettings {
+            thresholds: vec![50, 101],
+        };
+        assert!(settings.validate().is_err());
+        assert!(QuotaAlertSettings::default().validate().is_ok());
+    }
+}
@@ -0,0 +1,215 @@
+// RAG Answer - Grounded answers to questions about the user's notes
+//
+// "Ask my notes" retrieves the chunks most relevant to a question, numbers
+// them as sources and asks the model to answer only from those sources,
+// citing them inline as [1], [2], ... The sources go back to the frontend
+// together with the answer, along with which of them the answer actually
+// cited, so citations can link to the notes they came from.
+
+use crate::services::llm_service::ChatMessage;
+use crate::services::vector_store::SearchResult;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Most characters of source text put into the prompt
+pub const MAX_CONTEXT_CHARS: usize = 12_000;
+
+/// Answer given without asking the model when no notes match the question
+pub const NO_SOURCES_ANSWER: &str = "I couldn't find anything in your notes about that.";
+
+const SYSTEM_PROMPT: &str = "You answer questions using only the user's notes. \
+The notes are given as numbered sources. Cite the sources that support each \
+statement inline, like [1] or [2][3]. If the sources don't contain the answer, \
+say so instead of guessing. Do not cite sources that weren't given.";
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// A retrieved chunk the answer may cite, numbered from 1
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnswerSource {
+    pub number: u32,
+    pub project_path: String,
+    pub file_path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heading: Option<String>,
+    pub content: String,
+    pub score: f32,
+}
+
+/// Answer to a question together with the notes it was based on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AskResponse {
+    pub answer: String,
+    pub sources: Vec<AnswerSource>,
+    /// Numbers of the sources the answer cites
+    pub cited: Vec<u32>,
+}
+
+// ============================================================================
+// Prompting
+// ============================================================================
+
+/// Number search results as sources, keeping as many as fit in
+/// `MAX_CONTEXT_CHARS`. The best result is always kept, truncated if needed.
+pub fn select_sources(results: &[SearchResult]) -> Vec<AnswerSource> {
+    let mut sources = Vec::new();
+    let mut used = 0;
+
+    for result in results {
+        let chunk = &result.chunk;
+        let remaining = MAX_CONTEXT_CHARS.saturating_sub(used);
+        let length = chunk.content.chars().count();
+        let content = if length <= remaining {
+            chunk.content.clone()
+        } else if sources.is_empty() {
+            chunk.content.chars().take(remaining).collect()
+        } else {
+            break;
+        };
+        used += content.chars().count();
+
+        sources.push(AnswerSource {
+            number: sources.len() as u32 + 1,
+            project_path: chunk.project_path.clone(),
+            file_path: chunk.file_path.clone(),
+            heading: chunk.metadata.heading.clone(),
+            content,
+            score: result.score,
+        });
+    }
+
+    sources
+}
+
+/// Messages asking the model to answer a question from the given sources
+pub fn build_messages(question: &str, sources: &[AnswerSource]) -> Vec<ChatMessage> {
+    let context = sources
+        .iter()
+        .map(|source| {
+            let title = match &source.heading {
+                Some(heading) => format!("{} > {}", source.file_path, heading),
+                None => source.file_path.clone(),
+            };
+            format!("[{}] {}\n{}", source.number, title, source.content.trim())
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    vec![
+        message("system", SYSTEM_PROMPT.to_string()),
+        message(
+            "user",
+            format!("Sources:\n\n{}\n\nQuestion: {}", context, question.trim()),
+        ),
+    ]
+}
+
+/// Numbers of the sources an answer cites, in order and without repeats.
+/// Citations of sources that don't exist are ignored.
+pub fn cited_sources(answer: &str, sources: &[AnswerSource]) -> Vec<u32> {
+    lazy_static::lazy_static! {
+        static ref CITATION: Regex = Regex::new(r"\[(\d+)\]").unwrap();
+    }
+
+    let mut cited = Vec::new();
+    for capture in CITATION.captures_iter(answer) {
+        let Ok(number) = capture[1].parse::<u32>() else {
+            continue;
+        };
+        if sources.iter().any(|s| s.number == number) && !cited.contains(&number) {
+            cited.push(number);
+        }
+    }
+    cited
+}
+
+fn message(role: &str, content: String) -> ChatMessage {
+    ChatMessage {
+        role: role.to_string(),
+        content,
+        name: None,
+        tool_call_id: None,
+        tool_calls: None,
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::vector_store::{ChunkMetadata, DocumentChunk};
+
+    fn result(file_path: &str, heading: Option<&str>, content: &str, score: f32) -> SearchResult {
+        SearchResult {
+            chunk: DocumentChunk {
+                id: format!("/ws:{}:0", file_path),
+                project_path: "/ws".to_string(),
+                file_path: file_path.to_string(),
+                chunk_index: 0,
+                content: content.to_string(),
+                metadata: ChunkMetadata {
+                    heading: heading.map(str::to_string),
+                    section: None,
+                    token_estimate: 10,
+                },
+            },
+            score,
+            vector_score: None,
+            keyword_score: None,
+        }
+    }
+
+    #[test]
+    fn test_prompt_numbers_sources() {
+        let sources = select_sources(&[
+            result("trip.md", Some("Flights"), "We fly out on May 3rd.", 0.9),
+            result("budget.md", None, "Travel budget is $2000.", 0.7),
+        ]);
+        assert_eq!(sources.len(), 2);
+        assert_eq!(sources[1].number, 2);
+
+        let messages = build_messages(" When do we fly? ", &sources);
+        assert_eq!(messages[0].role, "system");
+        let prompt = &messages[1].content;
+        assert!(prompt.contains("[1] trip.md > Flights\nWe fly out on May 3rd."));
+        assert!(prompt.contains("[2] budget.md\nTravel budget is $2000."));
+        assert!(prompt.ends_with("Question: When do we fly?"));
+    }
+
+    #[test]
+    fn test_sources_fit_context_budget() {
+        let long = "x".repeat(MAX_CONTEXT_CHARS + 10);
+        let sources = select_sources(&[
+            result("a.md", None, &long, 0.9),
+            result("b.md", None, "short", 0.8),
+        ]);
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources[0].content.len(), MAX_CONTEXT_CHARS);
+
+        let half = "y".repeat(MAX_CONTEXT_CHARS / 2);
+        let sources = select_sources(&[
+            result("a.md", None, &half, 0.9),
+            result("b.md", None, &half, 0.8),
+            result("c.md", None, "over budget", 0.7),
+        ]);
+        assert_eq!(sources.len(), 2);
+    }
+
+    #[test]
+    fn test_cited_sources() {
+        let sources = select_sources(&[
+            result("a.md", None, "one", 0.9),
+            result("b.md", None, "two", 0.8),
+        ]);
+        let answer = "You fly on May 3rd [2]. The budget is fixed [1][2]. See also [7].";
+        assert_eq!(cited_sources(answer, &sources), vec![2, 1]);
+        assert!(cited_sources("No citations here.", &sources).is_empty());
+    }
+}
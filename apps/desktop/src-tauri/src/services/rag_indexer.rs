@@ -0,0 +1,540 @@
+// RAG Indexer - Keeps project search indexes up to date in the background
+//
+// Document saves and file watcher events queue the changed file. A background
+// task waits for edits to settle, then re-chunks and re-embeds each changed
+// file into every indexed project that contains it, and drops deleted files
+// from the index. Files outside indexed projects are ignored; a project has
+// to be indexed once with rag_index_project or rag_reindex before changes to
+// it are picked up.
+//
+// Deletes, renames and moves made through the app are applied right away
+// without waiting for the debounce: the affected chunks are dropped or
+// re-keyed to the new path, keeping their embeddings. Whole folders are
+// handled the same way as single files.
+//
+// Embedding needs the signed-in user's access token. While signed out,
+// changes stay queued and are retried periodically.
+//
+// Indexing can be paused from the tray, and pauses by itself in battery saver
+// mode and on metered connections (see power_state). Changes keep queueing
+// while paused and are indexed once it's resumed.
+
+use crate::services::auth_service::AUTH_SERVICE;
+use crate::services::power_state::POWER_MONITOR;
+use crate::services::rag_service::RAGService;
+use crate::services::vector_store::IndexStatus;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+// ============================================================================
+// Configuration
+// ============================================================================
+
+/// How long a file must go without changes before it is re-indexed
+const DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// How long to wait before retrying a file that could not be indexed
+const RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// How often the background task checks the queue
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Most files kept in the queue; further changes are dropped until it drains
+const MAX_QUEUED_FILES: usize = 10_000;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Index status of a project together with the background indexer's view of it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexFreshness {
+    #[serde(flatten)]
+    pub status: IndexStatus,
+    /// Changed files waiting to be re-indexed
+    pub pending_files: u32,
+    /// When the background indexer last updated this project
+    pub last_synced_at: Option<String>,
+    /// Error from the last background update, if it failed
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct QueuedFile {
+    deleted: bool,
+    ready_at: Instant,
+}
+
+/// A file or folder that was deleted or moved
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PathChange {
+    Removed(PathBuf),
+    Moved { from: PathBuf, to: PathBuf },
+}
+
+#[derive(Debug, Clone, Default)]
+struct ProjectSync {
+    last_synced_at: Option<String>,
+    last_error: Option<String>,
+}
+
+// ============================================================================
+// Indexer
+// ============================================================================
+
+pub struct RagIndexer {
+    queue: Mutex<HashMap<PathBuf, QueuedFile>>,
+    path_changes: Mutex<Vec<PathChange>>,
+    projects: Mutex<HashMap<String, ProjectSync>>,
+    started: AtomicBool,
+    paused: AtomicBool,
+}
+
+impl RagIndexer {
+    pub fn new() -> Self {
+        Self {
+            queue: Mutex::new(HashMap::new()),
+            path_changes: Mutex::new(Vec::new()),
+            projects: Mutex::new(HashMap::new()),
+            started: AtomicBool::new(false),
+            paused: AtomicBool::new(false),
+        }
+    }
+
+    /// Queue a changed or deleted file for re-indexing
+    pub fn file_changed(&self, file_path: PathBuf, deleted: bool) {
+        if !RAGService::is_indexable(&file_path) {
+            return;
+        }
+        self.enqueue(file_path, deleted, Instant::now() + DEBOUNCE);
+    }
+
+    /// Drop a deleted file or folder from the index
+    pub fn path_removed(&self, path: PathBuf) {
+        self.queue
+            .lock()
+            .unwrap()
+            .retain(|queued, _| !queued.starts_with(&path));
+        self.path_changes
+            .lock()
+            .unwrap()
+            .push(PathChange::Removed(path));
+    }
+
+    /// Queue a file or folder that reappeared, e.g. restored from the trash
+    pub fn path_added(&self, path: &Path) {
+        self.queue_tree(path);
+    }
+
+    /// Re-key a renamed or moved file or folder in the index
+    pub fn path_moved(&self, from: PathBuf, to: PathBuf) {
+        {
+            // Pending changes follow the file to its new path
+            let mut queue = self.queue.lock().unwrap();
+            let moved: Vec<PathBuf> = queue
+                .keys()
+                .filter(|queued| queued.starts_with(&from))
+                .cloned()
+                .collect();
+            for path in moved {
+                if let Some(file) = queue.remove(&path) {
+                    let relative = path.strip_prefix(&from).unwrap_or(Path::new(""));
+                    queue.insert(to.join(relative), file);
+                }
+            }
+        }
+        self.path_changes
+            .lock()
+            .unwrap()
+            .push(PathChange::Moved { from, to });
+    }
+
+    fn take_path_changes(&self) -> Vec<PathChange> {
+        std::mem::take(&mut *self.path_changes.lock().unwrap())
+    }
+
+    fn enqueue(&self, file_path: PathBuf, deleted: bool, ready_at: Instant) {
+        let mut queue = self.queue.lock().unwrap();
+        if !queue.contains_key(&file_path) && queue.len() >= MAX_QUEUED_FILES {
+            warn!(
+                "RAG index queue is full, dropping change to {:?}",
+                file_path
+            );
+            return;
+        }
+        queue.insert(file_path, QueuedFile { deleted, ready_at });
+    }
+
+    /// Remove and return the files whose changes have settled
+    fn take_ready(&self, now: Instant) -> Vec<(PathBuf, bool)> {
+        if self.is_paused() {
+            return Vec::new();
+        }
+        let mut queue = self.queue.lock().unwrap();
+        let ready: Vec<PathBuf> = queue
+            .iter()
+            .filter(|(_, file)| file.ready_at <= now)
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        ready
+            .into_iter()
+            .filter_map(|path| queue.remove(&path).map(|file| (path, file.deleted)))
+            .collect()
+    }
+
+    /// Stop or resume indexing queued changes
+    pub fn set_paused(&self, paused: bool) {
+        info!(
+            "{} background RAG indexing",
+            if paused { "Pausing" } else { "Resuming" }
+        );
+        self.paused.store(paused, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Drop queued changes under a project, e.g. before a full reindex
+    pub fn discard(&self, project_path: &str) {
+        let project = Path::new(project_path);
+        self.queue
+            .lock()
+            .unwrap()
+            .retain(|path, _| !path.starts_with(project));
+    }
+
+    /// Record the outcome of an update to a project's index
+    pub fn record(&self, project_path: &str, result: Result<(), String>) {
+        let mut projects = self.projects.lock().unwrap();
+        let sync = projects.entry(project_path.to_string()).or_default();
+        match result {
+            Ok(()) => {
+                sync.last_synced_at = Some(chrono::Utc::now().to_rfc3339());
+                sync.last_error = None;
+            }
+            Err(e) => sync.last_error = Some(e),
+        }
+    }
+
+    /// Attach queue and sync information to index statuses
+    pub fn freshness(&self, statuses: Vec<IndexStatus>) -> Vec<IndexFreshness> {
+        let queue = self.queue.lock().unwrap();
+        let projects = self.projects.lock().unwrap();
+
+        statuses
+            .into_iter()
+            .map(|status| {
+                let project = Path::new(&status.project_path);
+                let pending_files = queue.keys().filter(|p| p.starts_with(project)).count();
+                let sync = projects
+                    .get(&status.project_path)
+                    .cloned()
+                    .unwrap_or_default();
+                IndexFreshness {
+                    status,
+                    pending_files: pending_files as u32,
+                    last_synced_at: sync.last_synced_at,
+                    last_error: sync.last_error,
+                }
+            })
+            .collect()
+    }
+
+    /// Start the background task. Only the first call has any effect.
+    pub fn start(&'static self, service: &'static RAGService) {
+        if self.started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        info!("Starting background RAG indexer");
+        tauri::async_runtime::spawn(async move {
+            let mut interval = tokio::time::interval(POLL_INTERVAL);
+            loop {
+                interval.tick().await;
+                let changes = self.take_path_changes();
+                if !changes.is_empty() {
+                    self.apply_path_changes(service, changes).await;
+                }
+                // Changes stay queued while battery saver or a metered
+                // connection pauses background work
+                let ready = if POWER_MONITOR.is_paused() {
+                    Vec::new()
+                } else {
+                    self.take_ready(Instant::now())
+                };
+                if ready.is_empty() {
+                    service.persist_index().await;
+                } else {
+                    self.sync(service, ready).await;
+                }
+            }
+        });
+    }
+
+    /// Drop or re-key deleted and moved paths in every indexed project
+    async fn apply_path_changes(&self, service: &RAGService, changes: Vec<PathChange>) {
+        let statuses = match service.get_status(None).await {
+            Ok(statuses) => statuses,
+            Err(e) => {
+                warn!("Failed to load RAG index status: {}", e);
+                return;
+            }
+        };
+
+        for change in changes {
+            let from = match &change {
+                PathChange::Removed(path) => path,
+                PathChange::Moved { from, .. } => from,
+            };
+            let from_str = from.to_string_lossy().to_string();
+
+            for status in &statuses {
+                let project_path = &status.project_path;
+                let project = Path::new(project_path);
+
+                let result = if project.starts_with(from) {
+                    // The project folder itself is gone from where it was indexed
+                    service.delete_index(project_path).await
+                } else if !from.starts_with(project) {
+                    continue;
+                } else {
+                    match &change {
+                        PathChange::Moved { to, .. } if to.starts_with(project) => {
+                            let to_str = to.to_string_lossy().to_string();
+                            service.move_path(project_path, &from_str, &to_str).await
+                        }
+                        _ => service.remove_file(project_path, &from_str).await,
+                    }
+                };
+                self.record(project_path, result.map_err(|e| e.to_string()));
+            }
+
+            // Files moved in from outside a project still need indexing there
+            if let PathChange::Moved { from, to } = &change {
+                let entered = statuses.iter().any(|s| {
+                    let project = Path::new(&s.project_path);
+                    to.starts_with(project) && !from.starts_with(project)
+                });
+                if entered {
+                    self.queue_tree(to);
+                }
+            }
+        }
+    }
+
+    /// Queue every indexable file at or under a path
+    fn queue_tree(&self, path: &Path) {
+        for entry in walkdir::WalkDir::new(path)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+        {
+            self.file_changed(entry.into_path(), false);
+        }
+    }
+
+    /// Apply settled changes to every indexed project containing them
+    async fn sync(&self, service: &RAGService, files: Vec<(PathBuf, bool)>) {
+        let statuses = match service.get_status(None).await {
+            Ok(statuses) => statuses,
+            Err(e) => {
+                warn!("Failed to load RAG index status: {}", e);
+                return;
+            }
+        };
+
+        let mut token: Option<Option<String>> = None;
+
+        for (file_path, deleted) in files {
+            let file = file_path.to_string_lossy().to_string();
+            let projects: Vec<&IndexStatus> = statuses
+                .iter()
+                .filter(|s| RAGService::is_in_project(&s.project_path, &file_path))
+                .collect();
+            if projects.is_empty() {
+                continue;
+            }
+
+            if projects.iter().any(|s| s.is_indexing) {
+                // A full index is running; check the file again once it's done
+                self.enqueue(file_path, deleted, Instant::now() + RETRY_DELAY);
+                continue;
+            }
+
+            if deleted || !file_path.exists() {
+                for project in projects {
+                    let result = service
+                        .remove_file(&project.project_path, &file)
+                        .await
+                        .map_err(|e| e.to_string());
+                    self.record(&project.project_path, result);
+                }
+                continue;
+            }
+
+            if token.is_none() {
+                token = Some(AUTH_SERVICE.get_access_token().await);
+            }
+            let Some(Some(auth_token)) = token.as_ref() else {
+                for project in &projects {
+                    self.record(
+                        &project.project_path,
+                        Err("Sign in to keep the search index up to date".to_string()),
+                    );
+                }
+                self.enqueue(file_path, deleted, Instant::now() + RETRY_DELAY);
+                continue;
+            };
+
+            for project in projects {
+                // Moved files arrive here too, already re-keyed
+                if service.is_up_to_date(&project.project_path, &file).await {
+                    continue;
+                }
+                debug!("Re-indexing {} in {}", file, project.project_path);
+                let result = service
+                    .index_file(&project.project_path, &file, auth_token)
+                    .await
+                    .map_err(|e| e.to_string());
+                if result.is_err() {
+                    self.enqueue(file_path.clone(), false, Instant::now() + RETRY_DELAY);
+                }
+                self.record(&project.project_path, result);
+            }
+        }
+    }
+}
+
+impl Default for RagIndexer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+lazy_static::lazy_static! {
+    pub static ref RAG_INDEXER: RagIndexer = RagIndexer::new();
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(project_path: &str) -> IndexStatus {
+        IndexStatus {
+            project_path: project_path.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_changes_wait_for_debounce() {
+        let indexer = RagIndexer::new();
+        indexer.file_changed(PathBuf::from("/ws/notes/a.midlight"), false);
+        indexer.file_changed(PathBuf::from("/ws/notes/b.md"), false);
+        // Not an indexable file type
+        indexer.file_changed(PathBuf::from("/ws/notes/image.png"), false);
+
+        assert!(indexer.take_ready(Instant::now()).is_empty());
+
+        let mut ready = indexer.take_ready(Instant::now() + DEBOUNCE);
+        ready.sort();
+        assert_eq!(
+            ready,
+            vec![
+                (PathBuf::from("/ws/notes/a.midlight"), false),
+                (PathBuf::from("/ws/notes/b.md"), false),
+            ]
+        );
+        assert!(indexer.take_ready(Instant::now() + DEBOUNCE).is_empty());
+    }
+
+    #[test]
+    fn test_latest_change_wins() {
+        let indexer = RagIndexer::new();
+        let path = PathBuf::from("/ws/a.midlight");
+        indexer.file_changed(path.clone(), false);
+        indexer.file_changed(path.clone(), true);
+
+        let ready = indexer.take_ready(Instant::now() + DEBOUNCE);
+        assert_eq!(ready, vec![(path, true)]);
+    }
+
+    #[test]
+    fn test_paused_keeps_changes_queued() {
+        let indexer = RagIndexer::new();
+        let path = PathBuf::from("/ws/a.midlight");
+        indexer.set_paused(true);
+        indexer.file_changed(path.clone(), false);
+        assert!(indexer.take_ready(Instant::now() + DEBOUNCE).is_empty());
+
+        indexer.set_paused(false);
+        let ready = indexer.take_ready(Instant::now() + DEBOUNCE);
+        assert_eq!(ready, vec![(path, false)]);
+    }
+
+    #[test]
+    fn test_path_changes() {
+        let indexer = RagIndexer::new();
+        indexer.file_changed(PathBuf::from("/ws/notes/a.midlight"), false);
+        indexer.file_changed(PathBuf::from("/ws/drafts/b.midlight"), false);
+
+        indexer.path_moved(PathBuf::from("/ws/notes"), PathBuf::from("/ws/archive"));
+        indexer.path_removed(PathBuf::from("/ws/drafts"));
+
+        // Pending changes follow moves and are dropped with deleted folders
+        let ready = indexer.take_ready(Instant::now() + DEBOUNCE);
+        assert_eq!(
+            ready,
+            vec![(PathBuf::from("/ws/archive/a.midlight"), false)]
+        );
+
+        assert_eq!(
+            indexer.take_path_changes(),
+            vec![
+                PathChange::Moved {
+                    from: PathBuf::from("/ws/notes"),
+                    to: PathBuf::from("/ws/archive"),
+                },
+                PathChange::Removed(PathBuf::from("/ws/drafts")),
+            ]
+        );
+        assert!(indexer.take_path_changes().is_empty());
+    }
+
+    #[test]
+    fn test_freshness_and_discard() {
+        let indexer = RagIndexer::new();
+        indexer.file_changed(PathBuf::from("/ws/one/a.midlight"), false);
+        indexer.file_changed(PathBuf::from("/ws/one/b.midlight"), false);
+        indexer.file_changed(PathBuf::from("/ws/two/c.midlight"), false);
+        indexer.record("/ws/two", Err("EMBEDDING_ERROR: offline".to_string()));
+
+        let freshness = indexer.freshness(vec![status("/ws/one"), status("/ws/two")]);
+        assert_eq!(freshness[0].pending_files, 2);
+        assert!(freshness[0].last_error.is_none());
+        assert_eq!(freshness[1].pending_files, 1);
+        assert_eq!(
+            freshness[1].last_error.as_deref(),
+            Some("EMBEDDING_ERROR: offline")
+        );
+
+        indexer.discard("/ws/one");
+        indexer.record("/ws/two", Ok(()));
+        let freshness = indexer.freshness(vec![status("/ws/one"), status("/ws/two")]);
+        assert_eq!(freshness[0].pending_files, 0);
+        assert!(freshness[1].last_error.is_none());
+        assert!(freshness[1].last_synced_at.is_some());
+    }
+}
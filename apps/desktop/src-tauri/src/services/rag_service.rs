@@ -5,12 +5,22 @@
 // 2. Chunks documents into smaller pieces
 // 3. Generates embeddings via the embedding service
 // 4. Stores in vector database
-// 5. Retrieves relevant chunks for queries
+// 5. Retrieves relevant chunks for queries, combining vector similarity with
+//    keyword (BM25) matches and optionally reranking the best candidates
+//
+// Hidden directories are skipped, except for the text recognized in images
+// (.midlight/images/text) and extracted from PDFs (.midlight/pdf/text), so
+// screenshots and attachments can be found by what they contain.
 
+use crate::services::document_chunks::resolve_content;
 use crate::services::embedding_service::EmbeddingService;
-use crate::services::vector_store::{IndexStatus, SearchResult, StoredChunk, VectorStore};
+use crate::services::markdown_convert::{tiptap_to_markdown, MarkdownOptions};
+use crate::services::operations::OperationHandle;
+use crate::services::vector_store::{
+    query_terms, FileFilter, IndexStatus, IndexedFile, SearchResult, StoredChunk, VectorStore,
+};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -19,6 +29,14 @@ use tokio::sync::RwLock;
 use tracing::{debug, info, warn};
 use walkdir::WalkDir;
 
+lazy_static::lazy_static! {
+    /// "#tag" at the start of a word; must start with a letter, so headings
+    /// ("# Title"), issue numbers and URL fragments aren't tags. Inside
+    /// .midlight JSON a text node may begin with one, hence the quote.
+    static ref HASHTAG: regex::Regex =
+        regex::Regex::new(r#"(?:^|[\s("])#(\p{L}[\p{L}\p{N}_/-]*)"#).unwrap();
+}
+
 // ============================================================================
 // Configuration
 // ============================================================================
@@ -32,10 +50,34 @@ const MIN_CHUNK_SIZE: usize = 100;
 /// File extensions to index
 const INDEXABLE_EXTENSIONS: &[&str] = &["midlight", "md", "txt"];
 
+/// Directories, relative to a project, holding text taken from images and
+/// PDFs
+const DERIVED_TEXT_DIRS: &[&[&str]] = &[
+    &[".midlight", "images", "text"],
+    &[".midlight", "pdf", "text"],
+];
+
+/// Share of the hybrid score that comes from vector similarity; the rest
+/// comes from keyword relevance
+const DEFAULT_VECTOR_WEIGHT: f32 = 0.7;
+
+/// How many candidates each retriever contributes per requested result
+const CANDIDATE_MULTIPLIER: usize = 4;
+
+/// Share of the final score that comes from the reranker
+const RERANK_WEIGHT: f32 = 0.3;
+
+/// Chunks fetched per related document asked for, since several chunks of
+/// the same document usually match
+const RELATED_CHUNKS_PER_DOCUMENT: usize = 5;
+
 // ============================================================================
 // Types
 // ============================================================================
 
+/// A chunk of a file: (chunk id, content, path relative to the project)
+type FileChunk = (String, String, String);
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SearchOptions {
@@ -45,6 +87,15 @@ pub struct SearchOptions {
     pub min_score: Option<f32>,
     /// Filter by project paths
     pub project_paths: Option<Vec<String>>,
+    /// Combine keyword matches with vector similarity (default true)
+    #[serde(default)]
+    pub hybrid: Option<bool>,
+    /// Weight of vector similarity in the hybrid score (0.0 - 1.0)
+    #[serde(default)]
+    pub vector_weight: Option<f32>,
+    /// Rerank the top candidates by how closely they match the query's words
+    #[serde(default)]
+    pub rerank: Option<bool>,
 }
 
 impl Default for SearchOptions {
@@ -53,10 +104,25 @@ impl Default for SearchOptions {
             top_k: Some(5),
             min_score: Some(0.3),
             project_paths: None,
+            hybrid: Some(true),
+            vector_weight: None,
+            rerank: None,
         }
     }
 }
 
+/// A document similar to another one, represented by its best-matching chunk
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelatedDocument {
+    pub project_path: String,
+    /// Path relative to the project
+    pub file_path: String,
+    pub score: f32,
+    pub heading: Option<String>,
+    pub snippet: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RAGError {
     pub code: String,
@@ -71,6 +137,13 @@ impl std::fmt::Display for RAGError {
 
 impl std::error::Error for RAGError {}
 
+fn check_cancelled(operation: &OperationHandle) -> Result<(), RAGError> {
+    operation.check_cancelled().map_err(|message| RAGError {
+        code: "CANCELLED".to_string(),
+        message,
+    })
+}
+
 // ============================================================================
 // RAG Service
 // ============================================================================
@@ -106,6 +179,7 @@ impl RAGService {
         project_path: &str,
         auth_token: &str,
         force: bool,
+        operation: &OperationHandle,
     ) -> Result<IndexStatus, RAGError> {
         // Atomic check-and-insert to prevent race condition (TOCTOU)
         {
@@ -120,9 +194,16 @@ impl RAGService {
         }
 
         let result = self
-            .do_index_project(project_path, auth_token, force)
+            .do_index_project(project_path, auth_token, force, operation)
             .await;
 
+        // Have the HNSW index ready (and saved) before the next search
+        if result.is_ok() {
+            if let Err(e) = self.vector_store.refresh_ann().await {
+                warn!("Failed to update vector index: {}", e);
+            }
+        }
+
         // Remove from indexing set
         {
             let mut indexing = self.indexing_projects.write().await;
@@ -138,6 +219,7 @@ impl RAGService {
         project_path: &str,
         auth_token: &str,
         force: bool,
+        operation: &OperationHandle,
     ) -> Result<IndexStatus, RAGError> {
         info!(
             "Indexing project: {} (force: {})",
@@ -254,7 +336,15 @@ impl RAGService {
         let mut all_chunks: Vec<(String, String, String, i64)> = Vec::new(); // (id, content, file_path, mtime)
         let mut files_processed = 0;
 
-        for (file_path, mtime) in &files_to_index {
+        for (i, (file_path, mtime)) in files_to_index.iter().enumerate() {
+            check_cancelled(operation)?;
+            operation.progress(
+                "chunking",
+                i,
+                files_to_index.len(),
+                Some(file_path.clone()),
+            );
+
             // Delete old chunks for this file first (for re-indexing modified files)
             if indexed_files.contains_key(file_path) {
                 self.vector_store
@@ -264,7 +354,7 @@ impl RAGService {
             }
 
             match self.process_file(project_path, file_path) {
-                Ok(chunks) => {
+                Ok((chunks, tags)) => {
                     let chunk_count = chunks.len();
                     for (id, content, fp) in chunks {
                         all_chunks.push((id, content, fp, *mtime));
@@ -279,6 +369,13 @@ impl RAGService {
                     {
                         warn!("Failed to track file {}: {}", file_path, e);
                     }
+                    if let Err(e) = self
+                        .vector_store
+                        .set_file_tags(project_path, file_path, &tags)
+                        .await
+                    {
+                        warn!("Failed to record tags of {}: {}", file_path, e);
+                    }
                 }
                 Err(e) => {
                     warn!("Failed to process file {}: {}", file_path, e);
@@ -303,6 +400,8 @@ impl RAGService {
         }
 
         // Generate embeddings in batches
+        check_cancelled(operation)?;
+        operation.progress("embedding", files_processed as usize, files_to_index.len(), None);
         let texts: Vec<String> = all_chunks.iter().map(|(_, c, _, _)| c.clone()).collect();
         let embeddings = self
             .embedding_service
@@ -404,22 +503,77 @@ impl RAGService {
                 message: e.message,
             })?;
 
-        // Search vector store
-        let results = self
+        let top_k = opts.top_k.unwrap_or(5) as usize;
+
+        if !opts.hybrid.unwrap_or(true) {
+            // Search vector store
+            let results = self
+                .vector_store
+                .search(
+                    &query_embedding,
+                    top_k,
+                    opts.project_paths.as_deref(),
+                    opts.min_score,
+                )
+                .await
+                .map_err(|e| RAGError {
+                    code: "SEARCH_ERROR".to_string(),
+                    message: e,
+                })?;
+
+            debug!("Found {} results for query: {}", results.len(), query);
+            return Ok(results);
+        }
+
+        // Gather candidates from both retrievers, then fuse their scores
+        let candidates = top_k * CANDIDATE_MULTIPLIER;
+        let vector_results = self
             .vector_store
             .search(
                 &query_embedding,
-                opts.top_k.unwrap_or(5) as usize,
+                candidates,
                 opts.project_paths.as_deref(),
-                opts.min_score,
+                None,
             )
             .await
             .map_err(|e| RAGError {
                 code: "SEARCH_ERROR".to_string(),
                 message: e,
             })?;
+        let keyword_results = self
+            .vector_store
+            .keyword_search(
+                query,
+                &query_embedding,
+                candidates,
+                opts.project_paths.as_deref(),
+            )
+            .await
+            .map_err(|e| RAGError {
+                code: "SEARCH_ERROR".to_string(),
+                message: e,
+            })?;
+
+        let vector_weight = opts
+            .vector_weight
+            .unwrap_or(DEFAULT_VECTOR_WEIGHT)
+            .clamp(0.0, 1.0);
+        let mut results = fuse_results(vector_results, keyword_results, vector_weight);
+
+        if opts.rerank.unwrap_or(false) {
+            results.truncate(candidates);
+            rerank(query, &mut results);
+        }
+
+        let threshold = opts.min_score.unwrap_or(0.0);
+        results.retain(|r| r.score >= threshold);
+        results.truncate(top_k);
 
-        debug!("Found {} results for query: {}", results.len(), query);
+        debug!(
+            "Found {} hybrid results for query: {}",
+            results.len(),
+            query
+        );
         Ok(results)
     }
 
@@ -481,7 +635,7 @@ impl RAGService {
             .ok();
 
         // Process the file
-        let chunks = self.process_file(project_path, file_path).map_err(|e| RAGError {
+        let (chunks, tags) = self.process_file(project_path, file_path).map_err(|e| RAGError {
             code: "PROCESS_ERROR".to_string(),
             message: e,
         })?;
@@ -538,11 +692,152 @@ impl RAGService {
                 code: "TRACK_ERROR".to_string(),
                 message: e,
             })?;
+        self.vector_store
+            .set_file_tags(project_path, file_path, &tags)
+            .await
+            .map_err(|e| RAGError {
+                code: "TRACK_ERROR".to_string(),
+                message: e,
+            })?;
 
         info!("Indexed file {} with {} chunks", file_path, chunk_count);
         Ok(())
     }
 
+    /// Save recent changes to the on-disk vector index, at most every so often
+    pub async fn persist_index(&self) {
+        if let Err(e) = self.vector_store.persist_ann(false).await {
+            warn!("Failed to save vector index: {}", e);
+        }
+    }
+
+    /// Remove a file, or every file under a folder, from a project's index
+    /// (after it was deleted)
+    pub async fn remove_file(&self, project_path: &str, file_path: &str) -> Result<(), RAGError> {
+        self.vector_store
+            .delete_file_complete(project_path, file_path)
+            .await
+            .map_err(|e| RAGError {
+                code: "DELETE_ERROR".to_string(),
+                message: e,
+            })?;
+
+        debug!("Removed file {} from index", file_path);
+        Ok(())
+    }
+
+    /// Re-key a renamed or moved file or folder without re-embedding it
+    pub async fn move_path(
+        &self,
+        project_path: &str,
+        from_path: &str,
+        to_path: &str,
+    ) -> Result<(), RAGError> {
+        let moved = self
+            .vector_store
+            .move_path_complete(project_path, from_path, to_path)
+            .await
+            .map_err(|e| RAGError {
+                code: "MOVE_ERROR".to_string(),
+                message: e,
+            })?;
+
+        debug!("Moved {} chunks from {} to {} in index", moved, from_path, to_path);
+        Ok(())
+    }
+
+    /// Whether a file's indexed content is as new as the file on disk
+    pub async fn is_up_to_date(&self, project_path: &str, file_path: &str) -> bool {
+        let Ok(mtime) = self.get_file_mtime(file_path) else {
+            return false;
+        };
+        matches!(
+            self.vector_store.indexed_mtime(project_path, file_path).await,
+            Ok(Some(indexed)) if indexed >= mtime
+        )
+    }
+
+    /// Documents most similar to an indexed file, best first, excluding the
+    /// file itself. Similarity is measured from the average of the file's
+    /// chunk embeddings, so no embedding request is needed. Returns nothing
+    /// if the file isn't indexed yet.
+    pub async fn related_documents(
+        &self,
+        file_path: &str,
+        limit: usize,
+        min_score: Option<f32>,
+    ) -> Result<Vec<RelatedDocument>, RAGError> {
+        // The innermost indexed project containing the file
+        let statuses = self.get_status(None).await?;
+        let Some(project) = statuses
+            .iter()
+            .map(|s| s.project_path.as_str())
+            .filter(|p| Path::new(file_path).starts_with(p))
+            .max_by_key(|p| p.len())
+        else {
+            return Ok(Vec::new());
+        };
+
+        let embeddings = self
+            .vector_store
+            .file_embeddings(project, file_path)
+            .await
+            .map_err(|e| RAGError {
+                code: "SEARCH_ERROR".to_string(),
+                message: e,
+            })?;
+        let Some(centroid) = mean_embedding(&embeddings) else {
+            return Ok(Vec::new());
+        };
+
+        // Fetch enough chunks to cover the file's own and several per document
+        let candidates = embeddings.len() + limit * RELATED_CHUNKS_PER_DOCUMENT;
+        let projects = [project.to_string()];
+        let results = self
+            .vector_store
+            .search(&centroid, candidates, Some(&projects), min_score)
+            .await
+            .map_err(|e| RAGError {
+                code: "SEARCH_ERROR".to_string(),
+                message: e,
+            })?;
+
+        let relative = Path::new(file_path)
+            .strip_prefix(project)
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|_| file_path.to_string());
+        Ok(group_by_document(results, &relative, limit))
+    }
+
+    /// Indexed files matching a filter, most recently modified first
+    pub async fn find_files(&self, filter: &FileFilter) -> Result<Vec<IndexedFile>, RAGError> {
+        self.vector_store
+            .find_files(filter)
+            .await
+            .map_err(|e| RAGError {
+                code: "SEARCH_ERROR".to_string(),
+                message: e,
+            })
+    }
+
+    /// Whether a file has an extension that gets indexed
+    pub fn is_indexable(file_path: &Path) -> bool {
+        file_path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|ext| INDEXABLE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+    }
+
+    /// Whether a file would be included when indexing a project: it is an
+    /// indexable file inside the project and not under a hidden directory
+    pub fn is_in_project(project_path: &str, file_path: &Path) -> bool {
+        let Ok(relative) = file_path.strip_prefix(project_path) else {
+            return false;
+        };
+
+        Self::is_indexable(file_path) && (!is_hidden(relative) || is_derived_text(relative))
+    }
+
     // ========================================================================
     // Internal Methods
     // ========================================================================
@@ -558,12 +853,14 @@ impl RAGService {
         {
             let path = entry.path();
 
-            // Skip hidden directories and files
-            if path
-                .components()
-                .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
-            {
-                continue;
+            // Skip hidden directories and files, apart from image and PDF text
+            if is_hidden(path) {
+                let derived_text = path
+                    .strip_prefix(project_path)
+                    .is_ok_and(is_derived_text);
+                if !derived_text {
+                    continue;
+                }
             }
 
             // Skip non-files
@@ -582,17 +879,17 @@ impl RAGService {
         Ok(files)
     }
 
-    /// Process a single file into chunks
+    /// Process a single file into chunks, along with its tags
     fn process_file(
         &self,
         project_path: &str,
         file_path: &str,
-    ) -> Result<Vec<(String, String, String)>, String> {
+    ) -> Result<(Vec<FileChunk>, Vec<String>), String> {
         let content =
             std::fs::read_to_string(file_path).map_err(|e| format!("Read error: {}", e))?;
 
         if content.trim().is_empty() {
-            return Ok(vec![]);
+            return Ok((vec![], vec![]));
         }
 
         // Get relative path for storage
@@ -601,8 +898,8 @@ impl RAGService {
             .map(|p| p.to_string_lossy().to_string())
             .unwrap_or_else(|_| file_path.to_string());
 
-        // Chunk the content
-        let chunks = self.chunk_content(&content);
+        // Chunk the content, documents as Markdown rather than raw JSON
+        let chunks = self.chunk_content(&document_text(file_path, &content));
 
         // Create chunk IDs and tuples
         let result: Vec<(String, String, String)> = chunks
@@ -619,7 +916,7 @@ impl RAGService {
             relative_path,
             result.len()
         );
-        Ok(result)
+        Ok((result, document_tags(&content)))
     }
 
     /// Chunk content into smaller pieces for embedding
@@ -669,6 +966,216 @@ impl RAGService {
     }
 }
 
+// ============================================================================
+// Score Fusion
+// ============================================================================
+
+/// Merge vector and keyword results into one list ranked by a weighted score.
+///
+/// Keyword scores are normalized against the best keyword match so both
+/// signals are on a 0-1 scale. Chunks found only by keyword search still have
+/// their cosine similarity, so neither signal is missing for any result.
+fn fuse_results(
+    vector_results: Vec<SearchResult>,
+    keyword_results: Vec<SearchResult>,
+    vector_weight: f32,
+) -> Vec<SearchResult> {
+    let max_keyword = keyword_results
+        .iter()
+        .filter_map(|r| r.keyword_score)
+        .fold(0.0f32, f32::max);
+
+    let mut merged: HashMap<String, SearchResult> = HashMap::new();
+    for mut result in vector_results {
+        result.vector_score = Some(result.score);
+        merged.insert(result.chunk.id.clone(), result);
+    }
+    for result in keyword_results {
+        match merged.get_mut(&result.chunk.id) {
+            Some(existing) => existing.keyword_score = result.keyword_score,
+            None => {
+                merged.insert(result.chunk.id.clone(), result);
+            }
+        }
+    }
+
+    let mut results: Vec<SearchResult> = merged
+        .into_values()
+        .map(|mut result| {
+            let vector = result.vector_score.unwrap_or(0.0).max(0.0);
+            let keyword = match (result.keyword_score, max_keyword > 0.0) {
+                (Some(score), true) => score / max_keyword,
+                _ => 0.0,
+            };
+            result.score = vector_weight * vector + (1.0 - vector_weight) * keyword;
+            result
+        })
+        .collect();
+
+    sort_by_score(&mut results);
+    results
+}
+
+/// Rerank results by how fully and closely they contain the query's words:
+/// the share of query terms present, with a bonus for the exact phrase or a
+/// match in the heading
+fn rerank(query: &str, results: &mut [SearchResult]) {
+    let terms = query_terms(query);
+    if terms.is_empty() {
+        return;
+    }
+    let phrase = query.trim().to_lowercase();
+
+    for result in results.iter_mut() {
+        let content = result.chunk.content.to_lowercase();
+        let heading = result
+            .chunk
+            .metadata
+            .heading
+            .as_deref()
+            .unwrap_or("")
+            .to_lowercase();
+
+        let present = terms.iter().filter(|t| content.contains(t.as_str())).count();
+        let mut relevance = present as f32 / terms.len() as f32;
+        if terms.len() > 1 && content.contains(&phrase) {
+            relevance += 0.5;
+        }
+        if terms.iter().any(|t| heading.contains(t.as_str())) {
+            relevance += 0.25;
+        }
+
+        let relevance = (relevance / 1.75).min(1.0);
+        result.score = (1.0 - RERANK_WEIGHT) * result.score + RERANK_WEIGHT * relevance;
+    }
+
+    sort_by_score(results);
+}
+
+fn sort_by_score(results: &mut [SearchResult]) {
+    results.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.chunk.id.cmp(&b.chunk.id))
+    });
+}
+
+// ============================================================================
+// Paths
+// ============================================================================
+
+/// Tags of a document: #hashtags in its text, plus the `tags` of Markdown
+/// front matter. Lowercase, sorted and without duplicates.
+pub fn document_tags(content: &str) -> Vec<String> {
+    let mut tags: Vec<String> = HASHTAG
+        .captures_iter(content)
+        .map(|caps| caps[1].trim_end_matches(['/', '-']).to_lowercase())
+        .collect();
+
+    let front_matter = content
+        .strip_prefix("---\n")
+        .and_then(|rest| rest.split_once("\n---"))
+        .and_then(|(yaml, _)| serde_yaml::from_str::<serde_yaml::Value>(yaml).ok());
+    match front_matter.as_ref().and_then(|fm| fm.get("tags")) {
+        Some(serde_yaml::Value::Sequence(items)) => {
+            tags.extend(items.iter().filter_map(|t| t.as_str()).map(str::to_lowercase));
+        }
+        Some(serde_yaml::Value::String(list)) => {
+            tags.extend(list.split(',').map(|t| t.trim().to_lowercase()));
+        }
+        _ => {}
+    }
+
+    tags.retain(|t| !t.is_empty());
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// The text of a file to chunk: a .midlight document's content converted to
+/// Markdown, so chunks split on its paragraphs, or any other file as is
+fn document_text(file_path: &str, content: &str) -> String {
+    if !file_path.ends_with(".midlight") {
+        return content.to_string();
+    }
+    match serde_json::from_str::<serde_json::Value>(content) {
+        Ok(mut document) => {
+            resolve_content(Path::new(file_path), &mut document);
+            tiptap_to_markdown(
+                document.get("content").unwrap_or(&document),
+                MarkdownOptions::default(),
+            )
+        }
+        Err(_) => content.to_string(),
+    }
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.components()
+        .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+}
+
+/// Whether a path relative to a project is a text file of image or PDF text
+fn is_derived_text(relative: &Path) -> bool {
+    let components: Vec<_> = relative.components().map(|c| c.as_os_str()).collect();
+    DERIVED_TEXT_DIRS.iter().any(|dirs| {
+        components.len() == dirs.len() + 1
+            && components.iter().zip(dirs.iter()).all(|(c, dir)| c == dir)
+    }) && relative.extension().is_some_and(|ext| ext == "txt")
+}
+
+// ============================================================================
+// Related Documents
+// ============================================================================
+
+/// Average of a document's chunk embeddings
+fn mean_embedding(embeddings: &[Vec<f32>]) -> Option<Vec<f32>> {
+    let dimensions = embeddings.first()?.len();
+    let mut mean = vec![0.0f32; dimensions];
+    let mut count = 0;
+    for embedding in embeddings.iter().filter(|e| e.len() == dimensions) {
+        for (total, value) in mean.iter_mut().zip(embedding) {
+            *total += value;
+        }
+        count += 1;
+    }
+    mean.iter_mut().for_each(|total| *total /= count as f32);
+    Some(mean)
+}
+
+/// Collapse chunk results into one entry per document, scored by its best
+/// chunk, leaving out the document the search started from
+fn group_by_document(
+    results: Vec<SearchResult>,
+    exclude_file: &str,
+    limit: usize,
+) -> Vec<RelatedDocument> {
+    let mut documents: Vec<RelatedDocument> = Vec::new();
+
+    // Results arrive best first, so the first chunk of a document is its best
+    for result in results {
+        let chunk = result.chunk;
+        if chunk.file_path == exclude_file
+            || documents.iter().any(|d| d.file_path == chunk.file_path)
+        {
+            continue;
+        }
+        documents.push(RelatedDocument {
+            project_path: chunk.project_path,
+            file_path: chunk.file_path,
+            score: result.score,
+            heading: chunk.metadata.heading,
+            snippet: chunk.content.chars().take(200).collect(),
+        });
+        if documents.len() >= limit {
+            break;
+        }
+    }
+
+    documents
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -742,6 +1249,56 @@ mod tests {
         assert!(chunks.is_empty());
     }
 
+    #[test]
+    fn test_document_text() {
+        let document = r#"{"version":1,"content":{"type":"doc","content":[
+            {"type":"heading","attrs":{"level":1},"content":[{"type":"text","text":"Plan"}]},
+            {"type":"paragraph","content":[{"type":"text","text":"Ship it","marks":[{"type":"bold"}]}]}
+        ]}}"#;
+        assert_eq!(document_text("/ws/a.midlight", document), "# Plan\n\n**Ship it**");
+        assert_eq!(document_text("/ws/a.md", "# Plan"), "# Plan");
+        assert_eq!(document_text("/ws/broken.midlight", "{"), "{");
+    }
+
+    #[test]
+    fn test_is_in_project() {
+        let project = "/ws/research";
+
+        assert!(RAGService::is_in_project(
+            project,
+            Path::new("/ws/research/notes/a.midlight")
+        ));
+        assert!(RAGService::is_in_project(project, Path::new("/ws/research/b.MD")));
+        assert!(!RAGService::is_in_project(project, Path::new("/ws/research/c.png")));
+        assert!(!RAGService::is_in_project(
+            project,
+            Path::new("/ws/research/.midlight/d.midlight")
+        ));
+        assert!(!RAGService::is_in_project(project, Path::new("/ws/other/a.midlight")));
+
+        // Text recognized in images is indexed, other files in .midlight aren't
+        assert!(RAGService::is_in_project(
+            project,
+            Path::new("/ws/research/.midlight/images/text/0123abcd.txt")
+        ));
+        assert!(RAGService::is_in_project(
+            project,
+            Path::new("/ws/research/.midlight/pdf/text/0123abcd.txt")
+        ));
+        assert!(!RAGService::is_in_project(
+            project,
+            Path::new("/ws/research/.midlight/pdf/0123abcd/pages.txt")
+        ));
+        assert!(!RAGService::is_in_project(
+            project,
+            Path::new("/ws/research/.midlight/images/text/nested/a.txt")
+        ));
+        assert!(!RAGService::is_in_project(
+            project,
+            Path::new("/ws/research/.midlight/images/0123abcd.md")
+        ));
+    }
+
     #[test]
     fn test_search_options_default() {
         let opts = SearchOptions::default();
@@ -749,6 +1306,91 @@ mod tests {
         assert_eq!(opts.top_k, Some(5));
         assert_eq!(opts.min_score, Some(0.3));
         assert!(opts.project_paths.is_none());
+        assert_eq!(opts.hybrid, Some(true));
+    }
+
+    fn result(id: &str, content: &str, vector: Option<f32>, keyword: Option<f32>) -> SearchResult {
+        use crate::services::vector_store::{ChunkMetadata, DocumentChunk};
+
+        SearchResult {
+            chunk: DocumentChunk {
+                id: id.to_string(),
+                project_path: "/p".to_string(),
+                file_path: format!("{}.md", id),
+                chunk_index: 0,
+                content: content.to_string(),
+                metadata: ChunkMetadata {
+                    heading: None,
+                    section: None,
+                    token_estimate: 0,
+                },
+            },
+            score: vector.unwrap_or(0.0),
+            vector_score: vector,
+            keyword_score: keyword,
+        }
+    }
+
+    #[test]
+    fn test_group_by_document() {
+        let results = vec![
+            result("self", "the current note", Some(0.99), None),
+            result("a", "best chunk of a", Some(0.9), None),
+            result("b", "only chunk of b", Some(0.8), None),
+            result("a", "another chunk of a", Some(0.7), None),
+            result("c", "chunk of c", Some(0.6), None),
+        ];
+
+        let related = group_by_document(results.clone(), "self.md", 10);
+        let files: Vec<&str> = related.iter().map(|d| d.file_path.as_str()).collect();
+        assert_eq!(files, vec!["a.md", "b.md", "c.md"]);
+        assert_eq!(related[0].snippet, "best chunk of a");
+
+        assert_eq!(group_by_document(results, "self.md", 2).len(), 2);
+        assert_eq!(
+            mean_embedding(&[vec![1.0, 0.0], vec![0.0, 1.0]]),
+            Some(vec![0.5, 0.5])
+        );
+        assert_eq!(mean_embedding(&[]), None);
+    }
+
+    #[test]
+    fn test_fuse_results() {
+        let vector = vec![
+            result("a", "semantic match", Some(0.9), None),
+            result("b", "both", Some(0.5), None),
+        ];
+        let keyword = vec![
+            result("b", "both", Some(0.5), Some(4.0)),
+            result("c", "rare name", Some(0.1), Some(8.0)),
+        ];
+
+        let fused = fuse_results(vector, keyword, 0.5);
+        let ids: Vec<&str> = fused.iter().map(|r| r.chunk.id.as_str()).collect();
+        // c: 0.05 + 0.5, b: 0.25 + 0.25, a: 0.45
+        assert_eq!(ids, vec!["c", "b", "a"]);
+        assert!((fused[0].score - 0.55).abs() < 0.001);
+        assert_eq!(fused[1].vector_score, Some(0.5));
+        assert_eq!(fused[1].keyword_score, Some(4.0));
+
+        // Pure vector weighting keeps the vector order
+        let fused = fuse_results(
+            vec![result("a", "", Some(0.9), None)],
+            vec![result("c", "", Some(0.1), Some(8.0))],
+            1.0,
+        );
+        assert_eq!(fused[0].chunk.id, "a");
+    }
+
+    #[test]
+    fn test_rerank_prefers_exact_phrase() {
+        let mut results = vec![
+            result("a", "the whale was white and captain ahab", Some(0.6), None),
+            result("b", "captain ahab hunted the white whale", Some(0.55), None),
+        ];
+
+        rerank("white whale", &mut results);
+        assert_eq!(results[0].chunk.id, "b");
     }
 
     #[test]
@@ -760,4 +1402,15 @@ mod tests {
 
         assert_eq!(format!("{}", error), "TEST_ERROR: Something went wrong");
     }
+
+    #[test]
+    fn test_document_tags() {
+        let markdown = "---\ntitle: Plan\ntags: [Work, ideas]\n---\n# Heading\n\n\
+                        Notes for #project/alpha and #Work. See issue #42 and page#anchor.";
+        assert_eq!(document_tags(markdown), vec!["ideas", "project/alpha", "work"]);
+
+        let midlight = r##"{"content":[{"type":"text","text":"#todo soon"}]}"##;
+        assert_eq!(document_tags(midlight), vec!["todo"]);
+        assert!(document_tags("---\ntags: \n---\nnothing").is_empty());
+    }
 }
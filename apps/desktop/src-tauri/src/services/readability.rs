@@ -0,0 +1,651 @@
+// Readability - Local style analysis of a document's prose
+//
+// Scores (Flesch reading ease, Flesch-Kincaid grade, Gunning fog), passive
+// voice, adverbs and sentence lengths, computed from the text of paragraphs,
+// list items and quotes; headings and code blocks aren't prose and are left
+// out. Syllables are counted with the usual vowel-group heuristic, and
+// passive voice is a form of "to be" followed by a past participle, so both
+// are estimates for English text.
+//
+// Annotations carry ProseMirror positions (UTF-16 offsets, with each node
+// boundary counting one, as in document_outline), so the editor can
+// highlight them without parsing the document itself.
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// Sentences at least this long are flagged as long, and at least
+/// VERY_LONG_SENTENCE words as very long
+const LONG_SENTENCE: usize = 25;
+const VERY_LONG_SENTENCE: usize = 40;
+
+/// Upper bounds of the sentence-length buckets; the last bucket is open
+const LENGTH_BUCKETS: &[usize] = &[10, 20, 30, 40];
+
+const BE_FORMS: &[&str] = &[
+    "am", "is", "are", "was", "were", "be", "been", "being", "isn't", "aren't", "wasn't", "weren't",
+];
+
+/// Past participles that don't end in "-ed"
+const IRREGULAR_PARTICIPLES: &[&str] = &[
+    "begun",
+    "bitten",
+    "blown",
+    "born",
+    "bought",
+    "bound",
+    "broken",
+    "brought",
+    "built",
+    "caught",
+    "chosen",
+    "done",
+    "drawn",
+    "driven",
+    "eaten",
+    "fallen",
+    "felt",
+    "forbidden",
+    "forgiven",
+    "forgotten",
+    "found",
+    "frozen",
+    "given",
+    "gone",
+    "grown",
+    "heard",
+    "held",
+    "hidden",
+    "hit",
+    "hung",
+    "hurt",
+    "kept",
+    "known",
+    "laid",
+    "led",
+    "left",
+    "lent",
+    "lost",
+    "made",
+    "meant",
+    "met",
+    "paid",
+    "put",
+    "read",
+    "ridden",
+    "rung",
+    "said",
+    "seen",
+    "sent",
+    "set",
+    "shaken",
+    "shot",
+    "shown",
+    "shut",
+    "sold",
+    "sought",
+    "spent",
+    "spoken",
+    "spun",
+    "stolen",
+    "struck",
+    "sung",
+    "taken",
+    "taught",
+    "thought",
+    "thrown",
+    "told",
+    "torn",
+    "understood",
+    "woken",
+    "won",
+    "worn",
+    "written",
+];
+
+/// "-ed" words that after "to be" usually describe rather than report an action
+const ED_ADJECTIVES: &[&str] = &[
+    "bored",
+    "concerned",
+    "confused",
+    "excited",
+    "interested",
+    "married",
+    "pleased",
+    "prepared",
+    "related",
+    "scared",
+    "supposed",
+    "surprised",
+    "tired",
+    "used",
+    "worried",
+];
+
+/// "-ly" words that aren't adverbs
+const NOT_ADVERBS: &[&str] = &[
+    "ally", "apply", "belly", "bully", "comply", "curly", "daily", "early", "family", "fly",
+    "friendly", "holy", "italy", "jelly", "july", "likely", "lily", "lonely", "lovely", "monthly",
+    "only", "rally", "reply", "silly", "supply", "ugly", "weekly", "yearly",
+];
+
+/// Abbreviations whose full stop doesn't end a sentence
+const ABBREVIATIONS: &[&str] = &[
+    "dr", "e.g", "etc", "i.e", "jr", "mr", "mrs", "ms", "no", "prof", "sr", "st", "vs",
+];
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnotationKind {
+    PassiveVoice,
+    Adverb,
+    LongSentence,
+    VeryLongSentence,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StyleAnnotation {
+    pub kind: AnnotationKind,
+    /// ProseMirror position of the first character
+    pub from: usize,
+    /// ProseMirror position after the last character
+    pub to: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LengthBucket {
+    /// Fewest words a sentence in the bucket has
+    pub min: usize,
+    /// Most words, or None for the last bucket
+    pub max: Option<usize>,
+    pub sentences: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StyleReport {
+    pub words: usize,
+    pub sentences: usize,
+    pub syllables: usize,
+    /// Usually 0-100 (very simple text goes above), higher is easier
+    pub flesch_reading_ease: f64,
+    /// US school grade
+    pub flesch_kincaid_grade: f64,
+    /// Years of schooling needed to follow the text on a first reading
+    pub gunning_fog: f64,
+    pub average_sentence_length: f64,
+    pub sentence_lengths: Vec<LengthBucket>,
+    pub passive_voice: usize,
+    pub adverbs: usize,
+    /// Adverbs per word
+    pub adverb_density: f64,
+    /// Passages to highlight, in document order
+    pub annotations: Vec<StyleAnnotation>,
+}
+
+/// The text of a paragraph or heading with the ProseMirror position of each
+/// character. Inline code, hard breaks and other inline leaves are spaces.
+pub(crate) struct TextBlock {
+    pub chars: Vec<char>,
+    positions: Vec<usize>,
+    end: usize,
+    pub heading: bool,
+}
+
+impl TextBlock {
+    /// Position of the character at `index`, or after the last one
+    pub fn position(&self, index: usize) -> usize {
+        self.positions.get(index).copied().unwrap_or(self.end)
+    }
+}
+
+/// A word, as char indices into its block
+struct Word {
+    start: usize,
+    end: usize,
+    lower: String,
+}
+
+// ============================================================================
+// Analysis
+// ============================================================================
+
+/// Analyse a .midlight document's JSON (the whole file, or just its Tiptap
+/// content)
+pub fn analyze_document(document: &Value) -> StyleReport {
+    let mut words = 0;
+    let mut syllables = 0;
+    let mut complex = 0;
+    let mut passive_voice = 0;
+    let mut adverbs = 0;
+    let mut lengths = Vec::new();
+    let mut annotations = Vec::new();
+
+    for block in text_blocks(document).iter().filter(|b| !b.heading) {
+        for (start, end) in sentences(&block.chars) {
+            let sentence_words = words_in(&block.chars[start..end], start);
+            if sentence_words.is_empty() {
+                continue;
+            }
+            lengths.push(sentence_words.len());
+            words += sentence_words.len();
+            for word in &sentence_words {
+                let count = syllable_count(&word.lower);
+                syllables += count;
+                if count >= 3 {
+                    complex += 1;
+                }
+            }
+
+            let mut annotate = |kind, from: usize, to: usize| {
+                annotations.push(StyleAnnotation {
+                    kind,
+                    from: block.position(from),
+                    to: block.position(to),
+                    text: block.chars[from..to].iter().collect(),
+                });
+            };
+
+            if sentence_words.len() >= LONG_SENTENCE {
+                let kind = if sentence_words.len() >= VERY_LONG_SENTENCE {
+                    AnnotationKind::VeryLongSentence
+                } else {
+                    AnnotationKind::LongSentence
+                };
+                annotate(
+                    kind,
+                    sentence_words[0].start,
+                    sentence_words.last().unwrap().end,
+                );
+            }
+            for (first, last) in passive_phrases(&sentence_words) {
+                passive_voice += 1;
+                annotate(
+                    AnnotationKind::PassiveVoice,
+                    sentence_words[first].start,
+                    sentence_words[last].end,
+                );
+            }
+            for word in sentence_words.iter().filter(|w| is_adverb(&w.lower)) {
+                adverbs += 1;
+                annotate(AnnotationKind::Adverb, word.start, word.end);
+            }
+        }
+    }
+    annotations.sort_by_key(|a| (a.from, a.to));
+
+    let sentence_count = lengths.len();
+    let words_per_sentence = ratio(words, sentence_count);
+    let syllables_per_word = ratio(syllables, words);
+    let scores_apply = words > 0;
+
+    StyleReport {
+        words,
+        sentences: sentence_count,
+        syllables,
+        flesch_reading_ease: if scores_apply {
+            round(206.835 - 1.015 * words_per_sentence - 84.6 * syllables_per_word)
+        } else {
+            0.0
+        },
+        flesch_kincaid_grade: if scores_apply {
+            round(0.39 * words_per_sentence + 11.8 * syllables_per_word - 15.59)
+        } else {
+            0.0
+        },
+        gunning_fog: if scores_apply {
+            round(0.4 * (words_per_sentence + 100.0 * ratio(complex, words)))
+        } else {
+            0.0
+        },
+        average_sentence_length: round(words_per_sentence),
+        sentence_lengths: length_buckets(&lengths),
+        passive_voice,
+        adverbs,
+        adverb_density: (ratio(adverbs, words) * 1000.0).round() / 1000.0,
+        annotations,
+    }
+}
+
+fn ratio(a: usize, b: usize) -> f64 {
+    if b == 0 {
+        0.0
+    } else {
+        a as f64 / b as f64
+    }
+}
+
+fn round(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+fn length_buckets(lengths: &[usize]) -> Vec<LengthBucket> {
+    let mut min = 1;
+    let mut buckets = Vec::new();
+    for max in LENGTH_BUCKETS.iter().map(|m| Some(*m)).chain([None]) {
+        buckets.push(LengthBucket {
+            min,
+            max,
+            sentences: lengths
+                .iter()
+                .filter(|l| **l >= min && max.map_or(true, |max| **l <= max))
+                .count(),
+        });
+        min = max.unwrap_or(0) + 1;
+    }
+    buckets
+}
+
+/// Sentences of a block as char ranges. A sentence ends at '.', '!', '?' or
+/// '…' (with any closing quotes or brackets) followed by a space, or at the
+/// end of the block.
+fn sentences(chars: &[char]) -> Vec<(usize, usize)> {
+    let mut result = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+
+    while i < chars.len() {
+        if matches!(chars[i], '.' | '!' | '?' | '…') {
+            let mut end = i + 1;
+            while end < chars.len()
+                && matches!(
+                    chars[end],
+                    '.' | '!' | '?' | '"' | '\'' | '”' | '’' | ')' | ']'
+                )
+            {
+                end += 1;
+            }
+            let at_break = end == chars.len() || chars[end].is_whitespace();
+            if at_break && !(chars[i] == '.' && is_abbreviation(&chars[start..i])) {
+                result.push((start, end));
+                start = end;
+            }
+            i = end;
+            continue;
+        }
+        i += 1;
+    }
+    if chars[start..].iter().any(|c| c.is_alphanumeric()) {
+        result.push((start, chars.len()));
+    }
+    result
+}
+
+fn is_abbreviation(before: &[char]) -> bool {
+    let word: String = before
+        .iter()
+        .rev()
+        .take_while(|c| c.is_alphanumeric() || **c == '.')
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect::<String>()
+        .to_lowercase();
+    ABBREVIATIONS.contains(&word.as_str())
+        || (word.chars().count() == 1 && word.chars().all(|c| c.is_alphabetic()))
+}
+
+/// Words of a sentence, with indices offset to the block
+fn words_in(chars: &[char], offset: usize) -> Vec<Word> {
+    let mut words = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if !chars[i].is_alphanumeric() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < chars.len()
+            && (chars[i].is_alphanumeric()
+                || (matches!(chars[i], '\'' | '’' | '-')
+                    && chars.get(i + 1).is_some_and(|c| c.is_alphanumeric())))
+        {
+            i += 1;
+        }
+        let lower = chars[start..i]
+            .iter()
+            .collect::<String>()
+            .to_lowercase()
+            .replace('’', "'");
+        // Numbers aren't words for readability purposes
+        if lower.chars().any(|c| c.is_alphabetic()) {
+            words.push(Word {
+                start: start + offset,
+                end: i + offset,
+                lower,
+            });
+        }
+    }
+    words
+}
+
+/// Vowel groups, less a silent final "e" or "-ed"; at least one
+fn syllable_count(word: &str) -> usize {
+    const VOWELS: &str = "aeiouyàáâäèéêëìíîïòóôöùúûü";
+    let chars: Vec<char> = word.chars().filter(|c| c.is_alphabetic()).collect();
+    let mut count = 0;
+    let mut in_group = false;
+    for c in &chars {
+        let vowel = VOWELS.contains(*c);
+        if vowel && !in_group {
+            count += 1;
+        }
+        in_group = vowel;
+    }
+
+    let n = chars.len();
+    if count > 1 && n > 2 {
+        let before = chars[n - 2];
+        let silent_e = chars[n - 1] == 'e' && before != 'l' && !VOWELS.contains(before);
+        let silent_ed = chars[n - 2..] == ['e', 'd'] && n > 3 && !matches!(chars[n - 3], 't' | 'd');
+        if silent_e || silent_ed {
+            count -= 1;
+        }
+    }
+    count.max(1)
+}
+
+/// Passive phrases as word index ranges: a form of "to be", up to one
+/// adverb or "not", and a past participle
+fn passive_phrases(words: &[Word]) -> Vec<(usize, usize)> {
+    let mut phrases = Vec::new();
+    let mut i = 0;
+    while i < words.len() {
+        if !BE_FORMS.contains(&words[i].lower.as_str()) {
+            i += 1;
+            continue;
+        }
+        let mut j = i + 1;
+        if words
+            .get(j)
+            .is_some_and(|w| w.lower == "not" || w.lower == "never" || is_adverb(&w.lower))
+        {
+            j += 1;
+        }
+        match words.get(j) {
+            Some(word) if is_participle(&word.lower) => {
+                phrases.push((i, j));
+                i = j + 1;
+            }
+            _ => i += 1,
+        }
+    }
+    phrases
+}
+
+fn is_participle(word: &str) -> bool {
+    (word.len() > 3 && word.ends_with("ed") && !ED_ADJECTIVES.contains(&word))
+        || IRREGULAR_PARTICIPLES.contains(&word)
+}
+
+fn is_adverb(word: &str) -> bool {
+    word.len() > 4 && word.ends_with("ly") && !NOT_ADVERBS.contains(&word)
+}
+
+// ============================================================================
+// Positions
+// ============================================================================
+
+fn children(node: &Value) -> &[Value] {
+    node.get("content")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn node_type(node: &Value) -> &str {
+    node.get("type").and_then(Value::as_str).unwrap_or("")
+}
+
+/// Paragraphs and headings of a .midlight document's JSON (the whole file,
+/// or just its Tiptap content), in document order; code blocks are skipped
+pub(crate) fn text_blocks(document: &Value) -> Vec<TextBlock> {
+    let content = document
+        .get("content")
+        .filter(|c| c.is_object())
+        .unwrap_or(document);
+
+    let mut blocks = Vec::new();
+    let mut pos = 0;
+    for node in children(content) {
+        collect_blocks(node, pos, &mut blocks);
+        pos += node_size(node);
+    }
+    blocks
+}
+
+/// Textblocks under a node starting at `pos`
+fn collect_blocks(node: &Value, pos: usize, blocks: &mut Vec<TextBlock>) {
+    match node_type(node) {
+        "codeBlock" => return,
+        "paragraph" | "heading" => {
+            let mut block = TextBlock {
+                chars: Vec::new(),
+                positions: Vec::new(),
+                end: pos + 1,
+                heading: node_type(node) == "heading",
+            };
+            let mut at = pos + 1;
+            for child in children(node) {
+                match child.get("text").and_then(Value::as_str) {
+                    Some(text) => {
+                        let code = child
+                            .get("marks")
+                            .and_then(Value::as_array)
+                            .is_some_and(|marks| marks.iter().any(|m| m["type"] == "code"));
+                        for c in text.chars() {
+                            block.chars.push(if code { ' ' } else { c });
+                            block.positions.push(at);
+                            at += c.len_utf16();
+                        }
+                    }
+                    None => {
+                        // Hard breaks and other inline leaves separate words
+                        block.chars.push(' ');
+                        block.positions.push(at);
+                        at += node_size(child);
+                    }
+                }
+            }
+            block.end = at;
+            blocks.push(block);
+            return;
+        }
+        _ => {}
+    }
+
+    let mut child_pos = pos + 1;
+    for child in children(node) {
+        collect_blocks(child, child_pos, blocks);
+        child_pos += node_size(child);
+    }
+}
+
+/// Size of a node in ProseMirror positions
+fn node_size(node: &Value) -> usize {
+    if let Some(text) = node.get("text").and_then(Value::as_str) {
+        return text.encode_utf16().count();
+    }
+    if !node.get("content").is_some_and(Value::is_array)
+        && matches!(
+            node_type(node),
+            "image" | "hardBreak" | "horizontalRule" | "mention"
+        )
+    {
+        return 1;
+    }
+    children(node).iter().map(node_size).sum::<usize>() + 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn paragraph(text: &str) -> Value {
+        json!({ "type": "paragraph", "content": [{ "type": "text", "text": text }] })
+    }
+
+    #[test]
+    fn test_scores() {
+        let document = json!({ "type": "doc", "content": [
+            { "type": "heading", "attrs": { "level": 1 }, "content": [{ "type": "text", "text": "Ignored heading" }] },
+            paragraph("The cat sat on the mat. Mr. Smith was not amused! It rained."),
+            { "type": "codeBlock", "content": [{ "type": "text", "text": "let ignored = true;" }] }
+        ]});
+        let report = analyze_document(&document);
+
+        assert_eq!((report.words, report.sentences), (13, 3));
+        assert_eq!(report.syllables, 14);
+        assert_eq!(report.flesch_reading_ease, 111.3);
+        assert_eq!(report.flesch_kincaid_grade, -1.2);
+        assert_eq!(report.passive_voice, 1);
+        assert_eq!(report.sentence_lengths[0].sentences, 3);
+        assert_eq!(report.sentence_lengths[4].max, None);
+
+        assert_eq!(syllable_count("readability"), 5);
+        assert_eq!(syllable_count("table"), 2);
+        assert_eq!(syllable_count("jumped"), 1);
+        assert_eq!(syllable_count("wanted"), 2);
+    }
+
+    #[test]
+    fn test_annotations() {
+        let long = vec!["word"; 26].join(" ");
+        let document = json!({
+            "version": 1,
+            "content": { "type": "doc", "content": [
+                { "type": "horizontalRule" },
+                { "type": "blockquote", "content": [
+                    paragraph("😀 The report was quickly written by the team. We only met daily.")
+                ]},
+                paragraph(&format!("{}.", long))
+            ]}
+        });
+        let report = analyze_document(&document);
+        assert_eq!((report.passive_voice, report.adverbs), (1, 1));
+
+        // The paragraph's text starts at 3 (rule, quote and paragraph
+        // openings), and the emoji takes two positions
+        let passive = &report.annotations[0];
+        assert_eq!(passive.kind, AnnotationKind::PassiveVoice);
+        assert_eq!(passive.text, "was quickly written");
+        assert_eq!((passive.from, passive.to), (3 + 14, 3 + 33));
+        let adverb = &report.annotations[1];
+        assert_eq!(
+            (adverb.kind, adverb.text.as_str()),
+            (AnnotationKind::Adverb, "quickly")
+        );
+
+        let sentence = report.annotations.last().unwrap();
+        assert_eq!(sentence.kind, AnnotationKind::LongSentence);
+        assert_eq!(sentence.text, long);
+    }
+}
@@ -0,0 +1,169 @@
+// Recent Documents - Documents opened lately, offered in the tray menu
+//
+// Every document loaded through the workspace is moved to the front of the
+// list. The list is kept across launches so the tray can reopen a document
+// before any window has loaded a workspace. Documents that no longer exist
+// are left out when the list is read.
+//
+// Stored at: {app data}/com.midlight.app/recent_documents.json
+
+use crate::services::app_dirs::app_data_dir;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::warn;
+
+/// Documents remembered
+const MAX_RECENT_DOCUMENTS: usize = 8;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentDocument {
+    /// Absolute path of the document
+    pub path: String,
+    pub workspace_root: String,
+    pub opened_at: DateTime<Utc>,
+}
+
+impl RecentDocument {
+    /// File name without the extension
+    pub fn title(&self) -> String {
+        Path::new(&self.path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.path.clone())
+    }
+}
+
+// ============================================================================
+// Recent Documents
+// ============================================================================
+
+pub struct RecentDocuments {
+    path: PathBuf,
+    documents: Mutex<Vec<RecentDocument>>,
+}
+
+impl RecentDocuments {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            documents: Mutex::new(Self::load(path)),
+        }
+    }
+
+    /// Move a document to the front of the list
+    pub fn record(&self, workspace_root: &str, file_path: &str) {
+        let path = Path::new(workspace_root)
+            .join(file_path)
+            .to_string_lossy()
+            .to_string();
+
+        let mut documents = self.documents.lock().unwrap();
+        documents.retain(|document| document.path != path);
+        documents.insert(
+            0,
+            RecentDocument {
+                path,
+                workspace_root: workspace_root.to_string(),
+                opened_at: Utc::now(),
+            },
+        );
+        documents.truncate(MAX_RECENT_DOCUMENTS);
+
+        if let Err(e) = self.save(&documents) {
+            warn!("{}", e);
+        }
+    }
+
+    /// Recent documents that still exist, most recent first
+    pub fn list(&self) -> Vec<RecentDocument> {
+        self.documents
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|document| Path::new(&document.path).exists())
+            .cloned()
+            .collect()
+    }
+
+    fn load(path: &Path) -> Vec<RecentDocument> {
+        if !path.exists() {
+            return Vec::new();
+        }
+
+        std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| {
+                warn!("Failed to load recent documents: {}", e);
+                Vec::new()
+            })
+    }
+
+    fn save(&self, documents: &[RecentDocument]) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+        }
+
+        let json = serde_json::to_string_pretty(documents)
+            .map_err(|e| format!("Failed to serialize recent documents: {}", e))?;
+        std::fs::write(&self.path, json)
+            .map_err(|e| format!("Failed to save recent documents: {}", e))
+    }
+}
+
+// ============================================================================
+// Global Singleton
+// ============================================================================
+
+lazy_static::lazy_static! {
+    pub static ref RECENT_DOCUMENTS: RecentDocuments = RecentDocuments::new(
+        &app_data_dir()
+            .join("recent_documents.json"),
+    );
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_record_and_list() {
+        let temp = TempDir::new().unwrap();
+        let workspace = temp.path().join("ws");
+        std::fs::create_dir_all(&workspace).unwrap();
+        let root = workspace.to_string_lossy().to_string();
+        for i in 0..10 {
+            std::fs::write(workspace.join(format!("{}.midlight", i)), "{}").unwrap();
+        }
+
+        let store_path = temp.path().join("recent_documents.json");
+        let recent = RecentDocuments::new(&store_path);
+        for i in 0..10 {
+            recent.record(&root, &format!("{}.midlight", i));
+        }
+        recent.record(&root, "5.midlight");
+
+        let titles: Vec<String> = recent.list().iter().map(|d| d.title()).collect();
+        assert_eq!(titles, vec!["5", "9", "8", "7", "6", "4", "3", "2"]);
+
+        // Kept across launches, without documents deleted since
+        std::fs::remove_file(workspace.join("9.midlight")).unwrap();
+        let reloaded = RecentDocuments::new(&store_path);
+        let titles: Vec<String> = reloaded.list().iter().map(|d| d.title()).collect();
+        assert_eq!(titles, vec!["5", "8", "7", "6", "4", "3", "2"]);
+        assert_eq!(reloaded.list()[0].workspace_root, root);
+    }
+}
@@ -12,12 +12,31 @@
 //   "timestamp": "2025-01-08T12:34:56Z",
 //   "workspace_root": "/Users/..."
 // }
+//
+// With rotation enabled, the WAL being replaced is kept as
+// {hash}.wal.{n}.json (1 = newest) if it is older than the rotation interval.
+//
+// Draft snapshots are taken every few seconds of active typing, so a crash
+// between WAL writes (which wait for a pause in typing) loses little. They
+// are kept separately from checkpoints, capped per document, and removed when
+// the document is saved:
+//   .midlight/recovery/snapshots/{hash}/{unix millis}.json (WAL format)
+//
+// With WAL encryption on, "content" holds the encrypted content (see
+// wal_cipher) and "encrypted" is true.
+//
+// Settings are stored at: .midlight/recovery.json
 
+use crate::services::atomic_write::write_atomic;
+use crate::services::change_staging::{diff_hunks, line_diff, DiffHunk, DiffOp};
+use crate::services::document_stats::midlight_blocks;
+use crate::services::wal_cipher::{self, WalCipher};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
-use std::sync::Mutex;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::fs;
 use tracing::{debug, info, warn};
 use xxhash_rust::xxh64::xxh64;
@@ -36,6 +55,8 @@ pub struct WalFile {
     pub content: String,
     pub timestamp: DateTime<Utc>,
     pub workspace_root: String,
+    #[serde(default)]
+    pub encrypted: bool,
 }
 
 /// Recovery file info returned to frontend
@@ -47,18 +68,177 @@ pub struct RecoveryFile {
     pub workspace_root: String,
 }
 
+/// What restoring a WAL would change in the document on disk, compared block
+/// by block (one line per paragraph, heading, list item...)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryDiff {
+    pub file_key: String,
+    pub wal_time: DateTime<Utc>,
+    /// Lines in the WAL but not on disk
+    pub insertions: usize,
+    /// Lines on disk but not in the WAL
+    pub deletions: usize,
+    pub hunks: Vec<DiffHunk>,
+}
+
+/// A draft snapshot of a document, without its content
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DraftSnapshot {
+    pub id: String,
+    pub file_key: String,
+    pub timestamp: DateTime<Utc>,
+    pub size: u64,
+}
+
 /// State for tracking active files
 #[derive(Debug)]
 struct FileState {
     last_content_hash: u64,
 }
 
+/// Typing activity since a document's last snapshot
+#[derive(Debug)]
+struct TypingState {
+    last_edit: Instant,
+    active: Duration,
+}
+
+/// Pauses longer than this don't count as active typing
+const TYPING_PAUSE: Duration = Duration::from_secs(3);
+
+/// Most draft snapshots a document can keep
+const MAX_SNAPSHOTS: usize = 100;
+
+/// Smallest and largest autosave and WAL delays a workspace can set
+const INTERVAL_RANGE_MS: (u64, u64) = (250, 600_000);
+
+/// Most previous WALs a document can keep
+const MAX_GENERATIONS: usize = 20;
+
+/// Autosave and WAL policy for a workspace. The intervals are applied by the
+/// editor; size limits and rotation are enforced here.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RecoverySettings {
+    /// Idle time after an edit before the document is saved
+    pub autosave_interval_ms: u64,
+    /// Idle time after an edit before a WAL is written
+    pub wal_interval_ms: u64,
+    /// Largest document content written to a WAL
+    pub max_wal_bytes: u64,
+    /// Total size of previous WALs kept; the oldest are removed beyond this
+    pub max_rotated_bytes: u64,
+    /// Previous WALs kept per document (0 keeps only the latest)
+    pub wal_generations: usize,
+    /// Minimum age of a WAL before a write rotates it rather than replacing it
+    pub rotate_after_ms: u64,
+    /// Per-document overrides of `wal_generations`, keyed by file key
+    pub document_generations: HashMap<String, usize>,
+    /// Encrypt WAL content; applies to WALs written after it is turned on
+    pub encrypt_wal: bool,
+    /// Seconds of active typing between draft snapshots (0 disables them)
+    pub snapshot_interval_secs: u64,
+    /// Draft snapshots kept per document
+    pub max_snapshots: usize,
+    /// Draft snapshots older than this are removed
+    pub snapshot_max_age_mins: u64,
+}
+
+impl Default for RecoverySettings {
+    fn default() -> Self {
+        Self {
+            autosave_interval_ms: 3000,
+            wal_interval_ms: 2000,
+            max_wal_bytes: 32 * 1024 * 1024,
+            max_rotated_bytes: 128 * 1024 * 1024,
+            wal_generations: 0,
+            rotate_after_ms: 5 * 60 * 1000,
+            document_generations: HashMap::new(),
+            encrypt_wal: false,
+            snapshot_interval_secs: 10,
+            max_snapshots: 10,
+            snapshot_max_age_mins: 60,
+        }
+    }
+}
+
+impl RecoverySettings {
+    fn path(workspace_root: &Path) -> PathBuf {
+        workspace_root.join(".midlight").join("recovery.json")
+    }
+
+    pub fn load(workspace_root: &Path) -> Result<Self, String> {
+        let path = Self::path(workspace_root);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read recovery settings: {}", e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse recovery settings: {}", e))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let (min, max) = INTERVAL_RANGE_MS;
+        for (name, ms) in [
+            ("Autosave interval", self.autosave_interval_ms),
+            ("WAL interval", self.wal_interval_ms),
+        ] {
+            if !(min..=max).contains(&ms) {
+                return Err(format!("{} must be between {} and {} ms", name, min, max));
+            }
+        }
+        if self.max_wal_bytes == 0 {
+            return Err("WAL size limit must be greater than zero".to_string());
+        }
+        let generations = self
+            .document_generations
+            .values()
+            .chain(std::iter::once(&self.wal_generations));
+        if generations.into_iter().any(|&g| g > MAX_GENERATIONS) {
+            return Err(format!(
+                "At most {} previous WALs can be kept",
+                MAX_GENERATIONS
+            ));
+        }
+        if self.max_snapshots > MAX_SNAPSHOTS {
+            return Err(format!(
+                "At most {} draft snapshots can be kept",
+                MAX_SNAPSHOTS
+            ));
+        }
+        Ok(())
+    }
+
+    fn save(&self, workspace_root: &Path) -> Result<(), String> {
+        let path = Self::path(workspace_root);
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize recovery settings: {}", e))?;
+        write_atomic(&path, json).map_err(|e| format!("Failed to write recovery settings: {}", e))
+    }
+
+    /// Previous WALs to keep for a document
+    pub fn generations_for(&self, file_key: &str) -> usize {
+        self.document_generations
+            .get(file_key)
+            .copied()
+            .unwrap_or(self.wal_generations)
+    }
+}
+
 /// Recovery Manager maintains WAL files for crash recovery
 pub struct RecoveryManager {
     workspace_root: PathBuf,
     recovery_dir: PathBuf,
     /// Track content hashes to avoid redundant writes
     file_states: Mutex<HashMap<String, FileState>>,
+    settings: Mutex<RecoverySettings>,
+    /// Where WAL encryption keys are kept, outside the workspace
+    key_dir: PathBuf,
+    cipher: Mutex<Option<Arc<WalCipher>>>,
+    typing: Mutex<HashMap<String, TypingState>>,
 }
 
 impl RecoveryManager {
@@ -69,15 +249,31 @@ impl RecoveryManager {
             workspace_root,
             recovery_dir,
             file_states: Mutex::new(HashMap::new()),
+            settings: Mutex::new(RecoverySettings::default()),
+            key_dir: wal_cipher::default_key_dir(),
+            cipher: Mutex::new(None),
+            typing: Mutex::new(HashMap::new()),
         }
     }
 
-    /// Initialize the recovery directory
+    /// Keep WAL encryption keys in a different directory
+    #[cfg(test)]
+    pub fn with_key_dir(mut self, key_dir: PathBuf) -> Self {
+        self.key_dir = key_dir;
+        self
+    }
+
+    /// Initialize the recovery directory and load the workspace's settings
     pub async fn init(&self) -> Result<(), String> {
         fs::create_dir_all(&self.recovery_dir)
             .await
             .map_err(|e| format!("Failed to create recovery directory: {}", e))?;
 
+        match RecoverySettings::load(&self.workspace_root) {
+            Ok(settings) => *self.settings.lock().unwrap() = settings,
+            Err(e) => warn!("Using default recovery settings: {}", e),
+        }
+
         debug!("Recovery manager initialized at {:?}", self.recovery_dir);
         Ok(())
     }
@@ -98,28 +294,21 @@ impl RecoveryManager {
             }
         }
 
-        // Build WAL file
-        let wal = WalFile {
-            version: WAL_VERSION,
-            file_key: file_key.to_string(),
-            content: content.to_string(),
-            timestamp: Utc::now(),
-            workspace_root: self.workspace_root.to_string_lossy().to_string(),
-        };
+        let settings = self.settings();
+        if content.len() as u64 > settings.max_wal_bytes {
+            return Err(format!(
+                "Document is larger than the WAL size limit ({} bytes)",
+                settings.max_wal_bytes
+            ));
+        }
 
         let wal_path = self.get_wal_path(file_key);
-        let wal_json = serde_json::to_string_pretty(&wal)
-            .map_err(|e| format!("Failed to serialize WAL: {}", e))?;
-
-        // Write atomically (write to temp, then rename)
-        let temp_path = wal_path.with_extension("wal.tmp");
-        fs::write(&temp_path, &wal_json)
-            .await
-            .map_err(|e| format!("Failed to write WAL temp file: {}", e))?;
-
-        fs::rename(&temp_path, &wal_path)
-            .await
-            .map_err(|e| format!("Failed to rename WAL file: {}", e))?;
+        let generations = settings.generations_for(file_key);
+        if generations > 0 {
+            self.rotate(&wal_path, generations, &settings).await?;
+        }
+        self.write_wal_file(&wal_path, file_key, content, &settings)
+            .await?;
 
         // Update state
         {
@@ -154,9 +343,201 @@ impl RecoveryManager {
             debug!("WAL cleared for {}", file_key);
         }
 
+        // Previous WALs and snapshots only matter until the document is saved
+        for path in self.rotated_paths(&wal_path) {
+            fs::remove_file(&path)
+                .await
+                .map_err(|e| format!("Failed to remove WAL file: {}", e))?;
+        }
+        self.typing.lock().unwrap().remove(file_key);
+        let snapshot_dir = self.snapshot_dir(file_key);
+        if snapshot_dir.exists() {
+            fs::remove_dir_all(&snapshot_dir)
+                .await
+                .map_err(|e| format!("Failed to remove draft snapshots: {}", e))?;
+        }
+
         Ok(())
     }
 
+    // =========================================================================
+    // Draft snapshots
+    // =========================================================================
+
+    /// Note an edit to a document, taking a draft snapshot once enough active
+    /// typing has passed since the last one. Meant to be called on edits
+    /// (throttled to about once a second), independently of WAL writes.
+    /// Returns the new snapshot's id, if one was taken.
+    pub async fn record_edit(
+        &self,
+        file_key: &str,
+        content: &str,
+    ) -> Result<Option<String>, String> {
+        let settings = self.settings();
+        if settings.snapshot_interval_secs == 0 || settings.max_snapshots == 0 {
+            return Ok(None);
+        }
+
+        {
+            let mut typing = self.typing.lock().unwrap();
+            let now = Instant::now();
+            let state = typing.entry(file_key.to_string()).or_insert(TypingState {
+                last_edit: now,
+                active: Duration::ZERO,
+            });
+            let gap = now.duration_since(state.last_edit);
+            if gap <= TYPING_PAUSE {
+                state.active += gap;
+            }
+            state.last_edit = now;
+            if state.active < Duration::from_secs(settings.snapshot_interval_secs) {
+                return Ok(None);
+            }
+            state.active = Duration::ZERO;
+        }
+
+        self.take_snapshot(file_key, content, &settings)
+            .await
+            .map(Some)
+    }
+
+    /// Snapshot a document's unsaved content now, returning the snapshot's id
+    pub async fn take_snapshot(
+        &self,
+        file_key: &str,
+        content: &str,
+        settings: &RecoverySettings,
+    ) -> Result<String, String> {
+        if content.len() as u64 > settings.max_wal_bytes {
+            return Err(format!(
+                "Document is larger than the WAL size limit ({} bytes)",
+                settings.max_wal_bytes
+            ));
+        }
+
+        let dir = self.snapshot_dir(file_key);
+        fs::create_dir_all(&dir)
+            .await
+            .map_err(|e| format!("Failed to create snapshot directory: {}", e))?;
+        // Ids are timestamps, bumped past the newest so they stay unique
+        let newest = self.snapshot_ids(&dir).await?.last().copied().unwrap_or(0);
+        let id = (Utc::now().timestamp_millis() as u64).max(newest + 1);
+        self.write_wal_file(
+            &dir.join(format!("{}.json", id)),
+            file_key,
+            content,
+            settings,
+        )
+        .await?;
+        debug!("Draft snapshot {} taken for {}", id, file_key);
+
+        self.prune_snapshots(&dir, settings).await?;
+        Ok(id.to_string())
+    }
+
+    /// A document's draft snapshots, newest first
+    pub async fn list_snapshots(&self, file_key: &str) -> Result<Vec<DraftSnapshot>, String> {
+        let dir = self.snapshot_dir(file_key);
+        let mut snapshots = Vec::new();
+        for id in self.snapshot_ids(&dir).await?.into_iter().rev() {
+            let path = dir.join(format!("{}.json", id));
+            let Ok(metadata) = fs::metadata(&path).await else {
+                continue;
+            };
+            snapshots.push(DraftSnapshot {
+                id: id.to_string(),
+                file_key: file_key.to_string(),
+                timestamp: DateTime::from_timestamp_millis(id as i64).unwrap_or_default(),
+                size: metadata.len(),
+            });
+        }
+        Ok(snapshots)
+    }
+
+    /// The content of a draft snapshot
+    pub async fn get_snapshot(&self, file_key: &str, snapshot_id: &str) -> Result<String, String> {
+        let id: u64 = snapshot_id
+            .parse()
+            .map_err(|_| format!("Invalid snapshot id: {}", snapshot_id))?;
+        let path = self.snapshot_dir(file_key).join(format!("{}.json", id));
+        if !path.exists() {
+            return Err(format!("Snapshot not found: {}", snapshot_id));
+        }
+        Ok(self.read_wal_file(&path).await?.content)
+    }
+
+    fn snapshot_dir(&self, file_key: &str) -> PathBuf {
+        let hash = xxh64(file_key.as_bytes(), 0);
+        self.recovery_dir
+            .join("snapshots")
+            .join(format!("{:016x}", hash))
+    }
+
+    /// Snapshot ids in a directory, oldest first
+    async fn snapshot_ids(&self, dir: &Path) -> Result<Vec<u64>, String> {
+        let mut ids = Vec::new();
+        let Ok(mut entries) = fs::read_dir(dir).await else {
+            return Ok(ids);
+        };
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|e| format!("Failed to read directory entry: {}", e))?
+        {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if let Some(id) = name.strip_suffix(".json").and_then(|n| n.parse().ok()) {
+                ids.push(id);
+            }
+        }
+        ids.sort_unstable();
+        Ok(ids)
+    }
+
+    /// Remove snapshots beyond the per-document limit or older than the age
+    /// limit, always keeping the newest
+    async fn prune_snapshots(&self, dir: &Path, settings: &RecoverySettings) -> Result<(), String> {
+        let ids = self.snapshot_ids(dir).await?;
+        let max_age_ms = settings.snapshot_max_age_mins.saturating_mul(60_000);
+        let cutoff = (Utc::now().timestamp_millis() as u64).saturating_sub(max_age_ms);
+        let keep_from = ids.len().saturating_sub(settings.max_snapshots.max(1));
+
+        for (i, id) in ids.iter().enumerate() {
+            let newest = i + 1 == ids.len();
+            if newest || (i >= keep_from && *id >= cutoff) {
+                continue;
+            }
+            let _ = fs::remove_file(dir.join(format!("{}.json", id))).await;
+        }
+        Ok(())
+    }
+
+    pub fn settings(&self) -> RecoverySettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    /// Validate, save and apply new settings
+    pub fn set_settings(&self, settings: RecoverySettings) -> Result<(), String> {
+        settings.validate()?;
+        settings.save(&self.workspace_root)?;
+        *self.settings.lock().unwrap() = settings;
+        Ok(())
+    }
+
+    /// Previous WALs kept for a document by rotation, newest first
+    pub async fn get_previous_wals(&self, file_key: &str) -> Result<Vec<RecoveryFile>, String> {
+        let mut previous = Vec::new();
+        for path in self.rotated_paths(&self.get_wal_path(file_key)) {
+            let wal = self.read_wal_file(&path).await?;
+            previous.push(RecoveryFile {
+                file_key: wal.file_key,
+                wal_content: wal.content,
+                wal_time: wal.timestamp,
+                workspace_root: wal.workspace_root,
+            });
+        }
+        Ok(previous)
+    }
+
     /// Check for recovery files on startup
     /// Returns list of files with unsaved changes
     pub async fn check_for_recovery(&self) -> Result<Vec<RecoveryFile>, String> {
@@ -225,6 +606,37 @@ impl RecoveryManager {
         Ok(Some(wal.content))
     }
 
+    /// Diff the document on disk against its WAL, with `context` unchanged
+    /// lines around each change. None if the file has no WAL.
+    pub async fn diff_recovery(
+        &self,
+        file_key: &str,
+        context: usize,
+    ) -> Result<Option<RecoveryDiff>, String> {
+        let wal_path = self.get_wal_path(file_key);
+        if !wal_path.exists() {
+            return Ok(None);
+        }
+        let wal = self.read_wal_file(&wal_path).await?;
+
+        let disk_path = self.workspace_root.join(file_key);
+        let on_disk = match fs::read_to_string(&disk_path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(format!("Failed to read document: {}", e)),
+        };
+
+        let diff = line_diff(&document_text(&on_disk), &document_text(&wal.content));
+        let count = |op| diff.iter().filter(|l| l.op == op).count();
+        Ok(Some(RecoveryDiff {
+            file_key: wal.file_key,
+            wal_time: wal.timestamp,
+            insertions: count(DiffOp::Added),
+            deletions: count(DiffOp::Removed),
+            hunks: diff_hunks(&diff, context),
+        }))
+    }
+
     /// Discard recovery for a specific file (user chose not to recover)
     pub async fn discard_recovery(&self, file_key: &str) -> Result<(), String> {
         self.clear_wal(file_key).await
@@ -248,18 +660,29 @@ impl RecoveryManager {
             let path = entry.path();
 
             let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
-            if name.ends_with(".wal.json") || name.ends_with(".wal.tmp") {
+            if name.ends_with(".wal.json")
+                || name.ends_with(".wal.tmp")
+                || rotated_generation(name).is_some()
+            {
                 if let Err(e) = fs::remove_file(&path).await {
                     warn!("Failed to remove recovery file {:?}: {}", path, e);
                 }
             }
         }
 
+        let snapshots_dir = self.recovery_dir.join("snapshots");
+        if snapshots_dir.exists() {
+            if let Err(e) = fs::remove_dir_all(&snapshots_dir).await {
+                warn!("Failed to remove draft snapshots: {}", e);
+            }
+        }
+
         // Clear all tracked states
         {
             let mut states = self.file_states.lock().unwrap();
             states.clear();
         }
+        self.typing.lock().unwrap().clear();
 
         info!("All recovery files discarded");
         Ok(())
@@ -290,13 +713,150 @@ impl RecoveryManager {
         self.recovery_dir.join(format!("{:016x}.wal.json", hash))
     }
 
+    /// Existing rotated copies of a WAL, newest first
+    fn rotated_paths(&self, wal_path: &Path) -> Vec<PathBuf> {
+        (1..)
+            .map(|n| rotated_path(wal_path, n))
+            .take_while(|path| path.exists())
+            .collect()
+    }
+
+    /// Keep the current WAL as generation 1 if it is old enough, shifting
+    /// older generations down and dropping those beyond the limit
+    async fn rotate(
+        &self,
+        wal_path: &Path,
+        generations: usize,
+        settings: &RecoverySettings,
+    ) -> Result<(), String> {
+        let Ok(metadata) = fs::metadata(wal_path).await else {
+            return Ok(());
+        };
+        let age = metadata
+            .modified()
+            .ok()
+            .and_then(|m| m.elapsed().ok())
+            .unwrap_or_default();
+        if age.as_millis() < u128::from(settings.rotate_after_ms) {
+            return Ok(());
+        }
+
+        let existing = self.rotated_paths(wal_path);
+        for path in existing.iter().skip(generations.saturating_sub(1)) {
+            let _ = fs::remove_file(path).await;
+        }
+        for n in (1..generations.min(existing.len() + 1)).rev() {
+            fs::rename(rotated_path(wal_path, n), rotated_path(wal_path, n + 1))
+                .await
+                .map_err(|e| format!("Failed to rotate WAL file: {}", e))?;
+        }
+        fs::copy(wal_path, rotated_path(wal_path, 1))
+            .await
+            .map_err(|e| format!("Failed to rotate WAL file: {}", e))?;
+
+        self.prune_rotated(settings.max_rotated_bytes).await
+    }
+
+    /// Remove the oldest rotated WALs until they fit within `max_bytes`
+    async fn prune_rotated(&self, max_bytes: u64) -> Result<(), String> {
+        let mut rotated = Vec::new();
+        let mut entries = fs::read_dir(&self.recovery_dir)
+            .await
+            .map_err(|e| format!("Failed to read recovery directory: {}", e))?;
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|e| format!("Failed to read directory entry: {}", e))?
+        {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let Some(generation) = rotated_generation(&name) else {
+                continue;
+            };
+            if let Ok(metadata) = entry.metadata().await {
+                rotated.push((
+                    generation,
+                    metadata.modified().ok(),
+                    metadata.len(),
+                    entry.path(),
+                ));
+            }
+        }
+
+        let mut total: u64 = rotated.iter().map(|(_, _, len, _)| len).sum();
+        // Oldest generations first, then oldest files
+        rotated.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        for (_, _, len, path) in rotated {
+            if total <= max_bytes {
+                break;
+            }
+            if fs::remove_file(&path).await.is_ok() {
+                debug!("Pruned rotated WAL {:?}", path);
+                total -= len;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write content in WAL format, encrypted if the settings ask for it.
+    /// Written atomically (to a temp file, then renamed).
+    async fn write_wal_file(
+        &self,
+        path: &Path,
+        file_key: &str,
+        content: &str,
+        settings: &RecoverySettings,
+    ) -> Result<(), String> {
+        let (content, encrypted) = if settings.encrypt_wal {
+            (self.cipher()?.encrypt(content)?, true)
+        } else {
+            (content.to_string(), false)
+        };
+        let wal = WalFile {
+            version: WAL_VERSION,
+            file_key: file_key.to_string(),
+            content,
+            timestamp: Utc::now(),
+            workspace_root: self.workspace_root.to_string_lossy().to_string(),
+            encrypted,
+        };
+        let wal_json = serde_json::to_string_pretty(&wal)
+            .map_err(|e| format!("Failed to serialize WAL: {}", e))?;
+
+        let temp_path = path.with_extension("wal.tmp");
+        fs::write(&temp_path, &wal_json)
+            .await
+            .map_err(|e| format!("Failed to write WAL temp file: {}", e))?;
+        fs::rename(&temp_path, path)
+            .await
+            .map_err(|e| format!("Failed to rename WAL file: {}", e))
+    }
+
+    /// The workspace's WAL cipher, loading (or creating) its key on first use
+    fn cipher(&self) -> Result<Arc<WalCipher>, String> {
+        let mut cipher = self.cipher.lock().unwrap();
+        if let Some(cipher) = cipher.as_ref() {
+            return Ok(cipher.clone());
+        }
+        let loaded = Arc::new(WalCipher::load_or_create(
+            &self.key_dir,
+            &self.workspace_root,
+        )?);
+        *cipher = Some(loaded.clone());
+        Ok(loaded)
+    }
+
+    /// Read a WAL file, decrypting its content if needed
     async fn read_wal_file(&self, path: &PathBuf) -> Result<WalFile, String> {
         let content = fs::read_to_string(path)
             .await
             .map_err(|e| format!("Failed to read WAL file: {}", e))?;
 
-        let wal: WalFile = serde_json::from_str(&content)
+        let mut wal: WalFile = serde_json::from_str(&content)
             .map_err(|e| format!("Failed to parse WAL file: {}", e))?;
+        if wal.encrypted {
+            wal.content = self.cipher()?.decrypt(&wal.content)?;
+            wal.encrypted = false;
+        }
 
         // Version check for future compatibility
         if wal.version > WAL_VERSION {
@@ -310,6 +870,29 @@ impl RecoveryManager {
     }
 }
 
+/// Readable text of document content for diffing: Tiptap JSON (a .midlight
+/// file or the editor's content) becomes one line per block, anything else is
+/// compared as it is
+fn document_text(content: &str) -> String {
+    match serde_json::from_str::<serde_json::Value>(content) {
+        Ok(json) if json.is_object() => midlight_blocks(&json).join("\n"),
+        _ => content.to_string(),
+    }
+}
+
+/// Path of a WAL's nth previous generation: {hash}.wal.{n}.json
+fn rotated_path(wal_path: &Path, n: usize) -> PathBuf {
+    let name = wal_path.file_name().unwrap_or_default().to_string_lossy();
+    let stem = name.trim_end_matches(".json");
+    wal_path.with_file_name(format!("{}.{}.json", stem, n))
+}
+
+/// The generation number of a rotated WAL file name
+fn rotated_generation(name: &str) -> Option<usize> {
+    let (_, rest) = name.split_once(".wal.")?;
+    rest.strip_suffix(".json")?.parse().ok()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -797,6 +1380,247 @@ mod tests {
     // Clear after write cycles
     // ============================================
 
+    #[tokio::test]
+    async fn test_diff_recovery() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = RecoveryManager::new(temp_dir.path().to_path_buf());
+        manager.init().await.unwrap();
+        assert!(manager
+            .diff_recovery("doc.midlight", 3)
+            .await
+            .unwrap()
+            .is_none());
+
+        let paragraph = |text: &str| serde_json::json!({ "type": "paragraph", "content": [{ "type": "text", "text": text }] });
+        let on_disk = serde_json::json!({
+            "version": 1,
+            "content": { "type": "doc", "content": [paragraph("One"), paragraph("Two")] }
+        });
+        std::fs::write(temp_dir.path().join("doc.midlight"), on_disk.to_string()).unwrap();
+        let wal = serde_json::json!({
+            "type": "doc",
+            "content": [paragraph("One"), paragraph("Two!"), paragraph("Three")]
+        });
+        manager
+            .write_wal("doc.midlight", &wal.to_string())
+            .await
+            .unwrap();
+
+        let diff = manager
+            .diff_recovery("doc.midlight", 3)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((diff.insertions, diff.deletions), (2, 1));
+        assert_eq!(diff.hunks.len(), 1);
+        let lines: Vec<_> = diff.hunks[0]
+            .lines
+            .iter()
+            .map(|l| (l.op, l.text.as_str()))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                (DiffOp::Equal, "One"),
+                (DiffOp::Removed, "Two"),
+                (DiffOp::Added, "Two!"),
+                (DiffOp::Added, "Three"),
+            ]
+        );
+    }
+
+    // ============================================
+    // Settings and rotation
+    // ============================================
+
+    #[tokio::test]
+    async fn test_settings() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = RecoveryManager::new(temp_dir.path().to_path_buf());
+        manager.init().await.unwrap();
+        assert_eq!(manager.settings(), RecoverySettings::default());
+
+        let settings = RecoverySettings {
+            max_wal_bytes: 8,
+            ..Default::default()
+        };
+        manager.set_settings(settings.clone()).unwrap();
+        assert!(manager
+            .write_wal("file.md", "too much content")
+            .await
+            .is_err());
+        assert!(manager.write_wal("file.md", "short").await.unwrap());
+
+        // Settings persist for the workspace
+        let reloaded = RecoveryManager::new(temp_dir.path().to_path_buf());
+        reloaded.init().await.unwrap();
+        assert_eq!(reloaded.settings(), settings);
+
+        let invalid = RecoverySettings {
+            wal_interval_ms: 10,
+            ..Default::default()
+        };
+        assert!(manager.set_settings(invalid).is_err());
+        assert_eq!(manager.settings(), settings);
+    }
+
+    #[tokio::test]
+    async fn test_wal_rotation() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = RecoveryManager::new(temp_dir.path().to_path_buf());
+        manager.init().await.unwrap();
+        let mut settings = RecoverySettings {
+            wal_generations: 2,
+            rotate_after_ms: 0,
+            ..Default::default()
+        };
+        settings
+            .document_generations
+            .insert("other.md".to_string(), 0);
+        manager.set_settings(settings).unwrap();
+
+        for content in ["v1", "v2", "v3", "v4"] {
+            manager.write_wal("file.md", content).await.unwrap();
+            manager.write_wal("other.md", content).await.unwrap();
+        }
+
+        let previous = manager.get_previous_wals("file.md").await.unwrap();
+        let contents: Vec<_> = previous.iter().map(|p| p.wal_content.as_str()).collect();
+        assert_eq!(contents, vec!["v3", "v2"]);
+        assert!(manager
+            .get_previous_wals("other.md")
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Rotated WALs are not offered as separate recoveries
+        assert_eq!(manager.check_for_recovery().await.unwrap().len(), 2);
+
+        manager.clear_wal("file.md").await.unwrap();
+        assert!(manager
+            .get_previous_wals("file.md")
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_wal() {
+        let temp_dir = TempDir::new().unwrap();
+        let keys = TempDir::new().unwrap();
+        let manager = RecoveryManager::new(temp_dir.path().to_path_buf())
+            .with_key_dir(keys.path().to_path_buf());
+        manager.init().await.unwrap();
+        manager
+            .set_settings(RecoverySettings {
+                encrypt_wal: true,
+                ..Default::default()
+            })
+            .unwrap();
+
+        manager
+            .write_wal("diary.md", "private thoughts")
+            .await
+            .unwrap();
+
+        let recovery_dir = temp_dir.path().join(".midlight").join("recovery");
+        let entry = std::fs::read_dir(&recovery_dir)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        let raw = std::fs::read_to_string(entry.path()).unwrap();
+        assert!(!raw.contains("private thoughts"));
+        assert!(serde_json::from_str::<WalFile>(&raw).unwrap().encrypted);
+
+        // Decrypted when read back, including by a new manager
+        let reloaded = RecoveryManager::new(temp_dir.path().to_path_buf())
+            .with_key_dir(keys.path().to_path_buf());
+        let recoverable = reloaded.check_for_recovery().await.unwrap();
+        assert_eq!(recoverable[0].wal_content, "private thoughts");
+        assert!(!reloaded
+            .has_unique_recovery("diary.md", "private thoughts")
+            .await
+            .unwrap());
+
+        // Without the key the content can't be read
+        let keyless = RecoveryManager::new(temp_dir.path().to_path_buf())
+            .with_key_dir(temp_dir.path().join("no-keys"));
+        assert!(keyless.get_recovery_content("diary.md").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_draft_snapshots() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = RecoveryManager::new(temp_dir.path().to_path_buf());
+        manager.init().await.unwrap();
+        let settings = RecoverySettings {
+            max_snapshots: 2,
+            ..Default::default()
+        };
+        manager.set_settings(settings.clone()).unwrap();
+
+        // Edits shortly after one another don't add up to a snapshot yet
+        assert!(manager.record_edit("doc.md", "a").await.unwrap().is_none());
+        assert!(manager.record_edit("doc.md", "ab").await.unwrap().is_none());
+
+        for content in ["v1", "v2", "v3"] {
+            manager
+                .take_snapshot("doc.md", content, &settings)
+                .await
+                .unwrap();
+        }
+        let snapshots = manager.list_snapshots("doc.md").await.unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(
+            manager
+                .get_snapshot("doc.md", &snapshots[0].id)
+                .await
+                .unwrap(),
+            "v3"
+        );
+        assert_eq!(
+            manager
+                .get_snapshot("doc.md", &snapshots[1].id)
+                .await
+                .unwrap(),
+            "v2"
+        );
+
+        // Snapshots are not offered as WAL recoveries, and go on save
+        assert!(manager.check_for_recovery().await.unwrap().is_empty());
+        manager.clear_wal("doc.md").await.unwrap();
+        assert!(manager.list_snapshots("doc.md").await.unwrap().is_empty());
+        assert!(manager.get_snapshot("doc.md", "123").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rotated_size_limit() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = RecoveryManager::new(temp_dir.path().to_path_buf());
+        manager.init().await.unwrap();
+        manager
+            .set_settings(RecoverySettings {
+                wal_generations: 5,
+                rotate_after_ms: 0,
+                max_rotated_bytes: 0,
+                ..Default::default()
+            })
+            .unwrap();
+
+        manager.write_wal("file.md", "v1").await.unwrap();
+        manager.write_wal("file.md", "v2").await.unwrap();
+        assert!(manager
+            .get_previous_wals("file.md")
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            manager.get_recovery_content("file.md").await.unwrap(),
+            Some("v2".to_string())
+        );
+    }
+
     #[tokio::test]
     async fn test_write_clear_write_cycle() {
         let temp_dir = TempDir::new().unwrap();
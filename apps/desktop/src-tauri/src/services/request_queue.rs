@@ -0,0 +1,725 @@
+// Request Queue - Persistent outbox for requests that failed transiently
+//
+// Non-streaming LLM requests and error reports that fail because the network
+// is down or the API returned 5xx/429 are stored in an outbox on disk and
+// retried with exponential backoff by a background worker. Delivery is done
+// by the caller-supplied closure, so the queue only handles storage and
+// scheduling.
+//
+// Queued chat requests include the conversation in plaintext, so nothing is
+// kept longer than `max_age` (a day by default). Older entries are dropped
+// when the outbox is loaded and before each delivery pass.
+
+use crate::services::app_dirs::app_data_dir;
+use crate::services::atomic_write::write_atomic;
+use crate::services::error_reporter::ErrorReport;
+use crate::services::llm_service::{ChatRequest, ChatWithToolsRequest};
+use crate::traits::{RealTimeProvider, TimeProvider};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// Delay before the first retry
+pub const DEFAULT_BASE_DELAY: Duration = Duration::from_secs(5);
+
+/// Upper bound for the backoff delay
+pub const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(15 * 60);
+
+/// Attempts (including the original request) before giving up
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 10;
+
+/// Maximum number of queued requests
+pub const DEFAULT_MAX_QUEUE_SIZE: usize = 100;
+
+/// How long a request may wait in the outbox before it is dropped
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone)]
+pub struct QueueConfig {
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub max_attempts: u32,
+    pub max_queue_size: usize,
+    pub max_age: Duration,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            base_delay: DEFAULT_BASE_DELAY,
+            max_delay: DEFAULT_MAX_DELAY,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            max_queue_size: DEFAULT_MAX_QUEUE_SIZE,
+            max_age: DEFAULT_MAX_AGE,
+        }
+    }
+}
+
+/// The request to replay. Auth tokens are never stored; the worker fetches a
+/// fresh token at delivery time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum QueuedPayload {
+    LlmChat { request: ChatRequest },
+    LlmChatWithTools { request: ChatWithToolsRequest },
+    ErrorReport { report: ErrorReport },
+}
+
+impl QueuedPayload {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            QueuedPayload::LlmChat { .. } => "llm_chat",
+            QueuedPayload::LlmChatWithTools { .. } => "llm_chat_with_tools",
+            QueuedPayload::ErrorReport { .. } => "error_report",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedRequest {
+    pub id: String,
+    pub payload: QueuedPayload,
+    pub attempts: u32,
+    /// Unix timestamp (ms) when the request was first queued
+    pub created_at: i64,
+    /// Unix timestamp (ms) of the next delivery attempt
+    pub next_attempt_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// Result of a single delivery attempt
+#[derive(Debug, Clone)]
+pub enum Delivery {
+    /// Delivered; carries the response body if the caller needs it
+    Delivered(Option<serde_json::Value>),
+    /// Transient failure, try again later
+    Retry(String),
+    /// Permanent failure, drop the request
+    Failed(String),
+}
+
+/// Emitted when a queued request leaves the queue
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueEvent {
+    pub id: String,
+    pub kind: String,
+    pub delivered: bool,
+    pub attempts: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedItemSummary {
+    pub id: String,
+    pub kind: String,
+    pub attempts: u32,
+    pub created_at: i64,
+    pub next_attempt_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueStatus {
+    pub pending: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_attempt_at: Option<i64>,
+    pub items: Vec<QueuedItemSummary>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueError {
+    pub code: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for QueueError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for QueueError {}
+
+// ============================================================================
+// Retry Classification
+// ============================================================================
+
+/// Whether an HTTP status is worth retrying (rate limits and server errors)
+pub fn is_retryable_status(status: u16) -> bool {
+    status == 429 || (500..600).contains(&status)
+}
+
+/// Whether an LLM error code represents a transient failure
+pub fn is_retryable_llm_error(code: &str) -> bool {
+    matches!(code, "NETWORK_ERROR" | "PROVIDER_ERROR" | "RATE_LIMITED")
+}
+
+// ============================================================================
+// Request Queue
+// ============================================================================
+
+pub struct RequestQueue<T: TimeProvider = RealTimeProvider> {
+    path: PathBuf,
+    config: QueueConfig,
+    items: Mutex<Vec<QueuedRequest>>,
+    time_provider: Arc<T>,
+}
+
+impl RequestQueue<RealTimeProvider> {
+    pub fn new(path: PathBuf) -> Self {
+        Self::with_time_provider(path, QueueConfig::default(), Arc::new(RealTimeProvider))
+    }
+}
+
+impl<T: TimeProvider> RequestQueue<T> {
+    /// Create a queue with custom config and time provider, loading any
+    /// requests persisted by a previous session
+    pub fn with_time_provider(path: PathBuf, config: QueueConfig, time_provider: Arc<T>) -> Self {
+        let queue = Self {
+            items: Mutex::new(Self::load(&path)),
+            path,
+            config,
+            time_provider,
+        };
+
+        let mut items = queue.items.lock().unwrap();
+        let now = queue.time_provider.unix_timestamp_millis();
+        let loaded = items.len();
+        items.retain(|item| !queue.is_expired(item, now));
+        if items.len() < loaded {
+            info!(
+                "Dropped {} expired requests from outbox",
+                loaded - items.len()
+            );
+            queue.persist(&items);
+        }
+        if !items.is_empty() {
+            info!("Loaded {} queued requests from outbox", items.len());
+        }
+        drop(items);
+
+        queue
+    }
+
+    fn load(path: &Path) -> Vec<QueuedRequest> {
+        if !path.exists() {
+            return Vec::new();
+        }
+
+        match std::fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                warn!("Failed to parse outbox, starting empty: {}", e);
+                Vec::new()
+            }),
+            Err(e) => {
+                warn!("Failed to read outbox: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Write the queue to disk atomically so a crash can't truncate it
+    fn persist(&self, items: &[QueuedRequest]) {
+        let result = serde_json::to_string_pretty(items)
+            .map_err(std::io::Error::from)
+            .and_then(|json| write_atomic(&self.path, json));
+
+        if let Err(e) = result {
+            warn!("Failed to persist outbox: {}", e);
+        }
+    }
+
+    fn is_expired(&self, item: &QueuedRequest, now: i64) -> bool {
+        now.saturating_sub(item.created_at) > self.config.max_age.as_millis() as i64
+    }
+
+    /// Backoff delay before the given attempt number (1-based)
+    pub fn backoff_delay(&self, attempts: u32) -> Duration {
+        let exponent = attempts.saturating_sub(1).min(16);
+        let delay = self.config.base_delay.saturating_mul(1 << exponent);
+        delay.min(self.config.max_delay)
+    }
+
+    /// Queue a request that has already failed once. Returns the queue id.
+    pub fn enqueue(&self, payload: QueuedPayload, error: &str) -> Result<String, QueueError> {
+        let mut items = self.items.lock().unwrap();
+
+        if items.len() >= self.config.max_queue_size {
+            return Err(QueueError {
+                code: "QUEUE_FULL".to_string(),
+                message: format!("Outbox is full ({} requests)", self.config.max_queue_size),
+            });
+        }
+
+        let now = self.time_provider.unix_timestamp_millis();
+        let id = Uuid::new_v4().to_string();
+        let delay = self.backoff_delay(1).as_millis() as i64;
+
+        debug!("Queueing {} request {}: {}", payload.kind(), id, error);
+
+        items.push(QueuedRequest {
+            id: id.clone(),
+            payload,
+            attempts: 1,
+            created_at: now,
+            next_attempt_at: now + delay,
+            last_error: Some(error.to_string()),
+        });
+        self.persist(&items);
+
+        Ok(id)
+    }
+
+    /// Attempt delivery of every request whose retry time has passed.
+    /// Returns an event for each request that left the queue.
+    pub async fn process_due<F, Fut>(&self, deliver: F) -> Vec<QueueEvent>
+    where
+        F: Fn(QueuedRequest) -> Fut,
+        Fut: Future<Output = Delivery>,
+    {
+        let now = self.time_provider.unix_timestamp_millis();
+        let mut events = Vec::new();
+        let due: Vec<QueuedRequest> = {
+            let mut items = self.items.lock().unwrap();
+            let (expired, kept): (Vec<_>, Vec<_>) =
+                items.drain(..).partition(|item| self.is_expired(item, now));
+            *items = kept;
+            if !expired.is_empty() {
+                self.persist(&items);
+            }
+            for item in expired {
+                warn!("Dropping queued request {}: expired", item.id);
+                events.push(QueueEvent {
+                    id: item.id,
+                    kind: item.payload.kind().to_string(),
+                    delivered: false,
+                    attempts: item.attempts,
+                    response: None,
+                    error: Some("EXPIRED: request waited too long in the outbox".to_string()),
+                });
+            }
+
+            items
+                .iter()
+                .filter(|item| item.next_attempt_at <= now)
+                .cloned()
+                .collect()
+        };
+
+        if due.is_empty() {
+            return events;
+        }
+
+        let mut outcomes = Vec::with_capacity(due.len());
+        for item in due {
+            let id = item.id.clone();
+            outcomes.push((id, deliver(item).await));
+        }
+
+        let now = self.time_provider.unix_timestamp_millis();
+        let mut items = self.items.lock().unwrap();
+
+        for (id, outcome) in outcomes {
+            let Some(index) = items.iter().position(|item| item.id == id) else {
+                continue;
+            };
+            let attempts = items[index].attempts + 1;
+
+            match outcome {
+                Delivery::Delivered(response) => {
+                    let item = items.remove(index);
+                    debug!("Delivered queued request {}", id);
+                    events.push(QueueEvent {
+                        id,
+                        kind: item.payload.kind().to_string(),
+                        delivered: true,
+                        attempts,
+                        response,
+                        error: None,
+                    });
+                }
+                Delivery::Retry(error) if attempts < self.config.max_attempts => {
+                    let delay = self.backoff_delay(attempts).as_millis() as i64;
+                    let item = &mut items[index];
+                    item.attempts = attempts;
+                    item.next_attempt_at = now + delay;
+                    item.last_error = Some(error);
+                }
+                Delivery::Retry(error) | Delivery::Failed(error) => {
+                    let item = items.remove(index);
+                    warn!(
+                        "Dropping queued request {} after {} attempts: {}",
+                        id, attempts, error
+                    );
+                    events.push(QueueEvent {
+                        id,
+                        kind: item.payload.kind().to_string(),
+                        delivered: false,
+                        attempts,
+                        response: None,
+                        error: Some(error),
+                    });
+                }
+            }
+        }
+
+        self.persist(&items);
+        events
+    }
+
+    /// Error reports waiting to be sent
+    pub fn error_reports(&self) -> Vec<ErrorReport> {
+        self.items
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|item| match &item.payload {
+                QueuedPayload::ErrorReport { report } => Some(report.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Drop every queued error report. Returns how many were removed.
+    pub fn discard_error_reports(&self) -> usize {
+        let mut items = self.items.lock().unwrap();
+        let before = items.len();
+        items.retain(|item| !matches!(item.payload, QueuedPayload::ErrorReport { .. }));
+        let discarded = before - items.len();
+        if discarded > 0 {
+            self.persist(&items);
+        }
+        discarded
+    }
+
+    pub fn status(&self) -> QueueStatus {
+        let items = self.items.lock().unwrap();
+
+        QueueStatus {
+            pending: items.len(),
+            next_attempt_at: items.iter().map(|item| item.next_attempt_at).min(),
+            items: items
+                .iter()
+                .map(|item| QueuedItemSummary {
+                    id: item.id.clone(),
+                    kind: item.payload.kind().to_string(),
+                    attempts: item.attempts,
+                    created_at: item.created_at,
+                    next_attempt_at: item.next_attempt_at,
+                    last_error: item.last_error.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+// ============================================================================
+// Global Singleton
+// ============================================================================
+
+lazy_static::lazy_static! {
+    pub static ref REQUEST_QUEUE: RequestQueue<RealTimeProvider> = {
+        RequestQueue::new(app_data_dir().join("outbox.json"))
+    };
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::llm_service::ChatMessage;
+    use crate::traits::MockTimeProvider;
+    use tempfile::TempDir;
+
+    fn chat_payload() -> QueuedPayload {
+        QueuedPayload::LlmChat {
+            request: ChatRequest {
+                provider: "openai".to_string(),
+                model: "gpt-4o-mini".to_string(),
+                messages: vec![ChatMessage {
+                    role: "user".to_string(),
+                    content: "Hello".to_string(),
+                    name: None,
+                    tool_call_id: None,
+                    tool_calls: None,
+                }],
+                temperature: None,
+                max_tokens: None,
+                top_p: None,
+                stop: None,
+                json_mode: None,
+                stream: Some(false),
+                request_type: None,
+                web_search_enabled: None,
+                bypass_cache: false,
+            },
+        }
+    }
+
+    fn create_queue(
+        config: QueueConfig,
+    ) -> (TempDir, RequestQueue<MockTimeProvider>, MockTimeProvider) {
+        let temp = TempDir::new().unwrap();
+        let time = MockTimeProvider::from_timestamp(1_700_000_000);
+        let queue = RequestQueue::with_time_provider(
+            temp.path().join("outbox.json"),
+            config,
+            Arc::new(time.clone()),
+        );
+        (temp, queue, time)
+    }
+
+    #[test]
+    fn test_retry_classification() {
+        assert!(is_retryable_status(429));
+        assert!(is_retryable_status(503));
+        assert!(!is_retryable_status(400));
+        assert!(!is_retryable_status(401));
+
+        assert!(is_retryable_llm_error("NETWORK_ERROR"));
+        assert!(is_retryable_llm_error("PROVIDER_ERROR"));
+        assert!(is_retryable_llm_error("RATE_LIMITED"));
+        assert!(!is_retryable_llm_error("QUOTA_EXCEEDED"));
+        assert!(!is_retryable_llm_error("AUTH_REQUIRED"));
+    }
+
+    #[test]
+    fn test_backoff_doubles_and_caps() {
+        let (_temp, queue, _time) = create_queue(QueueConfig {
+            base_delay: Duration::from_secs(5),
+            max_delay: Duration::from_secs(60),
+            ..QueueConfig::default()
+        });
+
+        assert_eq!(queue.backoff_delay(1), Duration::from_secs(5));
+        assert_eq!(queue.backoff_delay(2), Duration::from_secs(10));
+        assert_eq!(queue.backoff_delay(3), Duration::from_secs(20));
+        assert_eq!(queue.backoff_delay(5), Duration::from_secs(60));
+        assert_eq!(queue.backoff_delay(100), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_enqueue_persists_across_reload() {
+        let (temp, queue, time) = create_queue(QueueConfig::default());
+
+        let id = queue
+            .enqueue(chat_payload(), "NETWORK_ERROR: offline")
+            .unwrap();
+        let status = queue.status();
+        assert_eq!(status.pending, 1);
+        assert_eq!(status.items[0].id, id);
+        assert_eq!(status.items[0].kind, "llm_chat");
+        assert_eq!(status.items[0].attempts, 1);
+
+        let reloaded = RequestQueue::with_time_provider(
+            temp.path().join("outbox.json"),
+            QueueConfig::default(),
+            Arc::new(time),
+        );
+        assert_eq!(reloaded.status().pending, 1);
+        assert_eq!(reloaded.status().items[0].id, id);
+    }
+
+    #[test]
+    fn test_enqueue_rejects_when_full() {
+        let (_temp, queue, _time) = create_queue(QueueConfig {
+            max_queue_size: 1,
+            ..QueueConfig::default()
+        });
+
+        assert!(queue.enqueue(chat_payload(), "offline").is_ok());
+        let err = queue.enqueue(chat_payload(), "offline").unwrap_err();
+        assert_eq!(err.code, "QUEUE_FULL");
+    }
+
+    #[test]
+    fn test_discard_error_reports_keeps_other_requests() {
+        let (temp, queue, time) = create_queue(QueueConfig::default());
+        queue.enqueue(chat_payload(), "offline").unwrap();
+        queue
+            .enqueue(
+                QueuedPayload::ErrorReport {
+                    report: ErrorReport {
+                        schema_version: 1,
+                        category: "llm".to_string(),
+                        error_type: "NetworkError".to_string(),
+                        message: "offline".to_string(),
+                        sanitized: true,
+                        app_version: "1.0.0".to_string(),
+                        platform: "linux".to_string(),
+                        arch: "x86_64".to_string(),
+                        os_version: "Linux".to_string(),
+                        context: None,
+                        timestamp: "2024-01-01T00:00:00Z".to_string(),
+                        session_id: "test-session".to_string(),
+                        breadcrumbs: Vec::new(),
+                        environment: None,
+                    },
+                },
+                "offline",
+            )
+            .unwrap();
+
+        assert_eq!(queue.discard_error_reports(), 1);
+        assert_eq!(queue.discard_error_reports(), 0);
+        assert!(queue.error_reports().is_empty());
+
+        let reloaded = RequestQueue::with_time_provider(
+            temp.path().join("outbox.json"),
+            QueueConfig::default(),
+            Arc::new(time),
+        );
+        let status = reloaded.status();
+        assert_eq!(status.pending, 1);
+        assert_eq!(status.items[0].kind, "llm_chat");
+    }
+
+    #[test]
+    fn test_corrupt_outbox_starts_empty() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("outbox.json");
+        std::fs::write(&path, "not json").unwrap();
+
+        let queue = RequestQueue::with_time_provider(
+            path,
+            QueueConfig::default(),
+            Arc::new(MockTimeProvider::new()),
+        );
+        assert_eq!(queue.status().pending, 0);
+    }
+
+    #[tokio::test]
+    async fn test_process_due_skips_requests_not_yet_due() {
+        let (_temp, queue, _time) = create_queue(QueueConfig::default());
+        queue.enqueue(chat_payload(), "offline").unwrap();
+
+        let events = queue
+            .process_due(|_| async { Delivery::Delivered(None) })
+            .await;
+
+        assert!(events.is_empty());
+        assert_eq!(queue.status().pending, 1);
+    }
+
+    #[tokio::test]
+    async fn test_process_due_delivers_and_removes() {
+        let (_temp, queue, time) = create_queue(QueueConfig::default());
+        let id = queue.enqueue(chat_payload(), "offline").unwrap();
+        time.advance_secs(5);
+
+        let events = queue
+            .process_due(|_| async { Delivery::Delivered(Some(serde_json::json!({"ok": true}))) })
+            .await;
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].id, id);
+        assert!(events[0].delivered);
+        assert_eq!(events[0].attempts, 2);
+        assert!(events[0].response.is_some());
+        assert_eq!(queue.status().pending, 0);
+    }
+
+    #[tokio::test]
+    async fn test_process_due_reschedules_with_backoff() {
+        let (_temp, queue, time) = create_queue(QueueConfig::default());
+        queue.enqueue(chat_payload(), "offline").unwrap();
+        time.advance_secs(5);
+        let now = time.unix_timestamp_millis();
+
+        let events = queue
+            .process_due(|_| async { Delivery::Retry("still offline".to_string()) })
+            .await;
+
+        assert!(events.is_empty());
+        let status = queue.status();
+        assert_eq!(status.items[0].attempts, 2);
+        assert_eq!(status.items[0].next_attempt_at, now + 10_000);
+        assert_eq!(status.items[0].last_error.as_deref(), Some("still offline"));
+    }
+
+    #[tokio::test]
+    async fn test_process_due_gives_up_after_max_attempts() {
+        let (_temp, queue, time) = create_queue(QueueConfig {
+            max_attempts: 2,
+            ..QueueConfig::default()
+        });
+        queue.enqueue(chat_payload(), "offline").unwrap();
+        time.advance_secs(5);
+
+        let events = queue
+            .process_due(|_| async { Delivery::Retry("still offline".to_string()) })
+            .await;
+
+        assert_eq!(events.len(), 1);
+        assert!(!events[0].delivered);
+        assert_eq!(events[0].error.as_deref(), Some("still offline"));
+        assert_eq!(queue.status().pending, 0);
+    }
+
+    #[tokio::test]
+    async fn test_process_due_drops_permanent_failures() {
+        let (_temp, queue, time) = create_queue(QueueConfig::default());
+        queue.enqueue(chat_payload(), "offline").unwrap();
+        time.advance_secs(5);
+
+        let events = queue
+            .process_due(|_| async { Delivery::Failed("QUOTA_EXCEEDED".to_string()) })
+            .await;
+
+        assert_eq!(events.len(), 1);
+        assert!(!events[0].delivered);
+        assert_eq!(queue.status().pending, 0);
+    }
+
+    #[tokio::test]
+    async fn test_process_due_drops_expired_requests() {
+        let (_temp, queue, time) = create_queue(QueueConfig {
+            max_age: Duration::from_secs(60),
+            ..QueueConfig::default()
+        });
+        queue.enqueue(chat_payload(), "offline").unwrap();
+        time.advance_secs(61);
+
+        let events = queue
+            .process_due(|_| async { panic!("expired requests must not be delivered") })
+            .await;
+
+        assert_eq!(events.len(), 1);
+        assert!(!events[0].delivered);
+        assert!(events[0].error.as_deref().unwrap().starts_with("EXPIRED"));
+        assert_eq!(queue.status().pending, 0);
+    }
+
+    #[test]
+    fn test_expired_requests_are_removed_on_load() {
+        let (temp, queue, time) = create_queue(QueueConfig::default());
+        queue.enqueue(chat_payload(), "offline").unwrap();
+        time.advance_secs(DEFAULT_MAX_AGE.as_secs() + 1);
+
+        let path = temp.path().join("outbox.json");
+        let reloaded =
+            RequestQueue::with_time_provider(path.clone(), QueueConfig::default(), Arc::new(time));
+        assert_eq!(reloaded.status().pending, 0);
+        assert!(!std::fs::read_to_string(path).unwrap().contains("Hello"));
+    }
+}
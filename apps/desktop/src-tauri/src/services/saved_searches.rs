@@ -0,0 +1,373 @@
+// Saved Searches - Named queries shown as smart folders
+//
+// A saved search combines full-text, tag, modification date and path
+// conditions. Evaluating one asks the RAG index which tracked files match
+// (see VectorStore::find_files), so a smart folder opens without reading the
+// workspace from disk. Only indexed documents can appear.
+//
+// Searches are stored at: .midlight/saved-searches.json
+// Format:
+// {
+//   "version": 1,
+//   "searches": [
+//     {
+//       "id": "...",
+//       "name": "Recent drafts",
+//       "query": { "tags": ["draft"], "modifiedWithinDays": 7 },
+//       "createdAt": "...",
+//       "updatedAt": "..."
+//     }
+//   ]
+// }
+
+use crate::services::atomic_write::write_atomic;
+use crate::services::path_glob::PathGlob;
+use crate::services::vector_store::{query_terms, FileFilter, IndexedFile};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SEARCHES_VERSION: u32 = 1;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Conditions a document must all meet
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchQuery {
+    /// Words that must all appear in the document
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Tags the document must all have, with or without the leading "#"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified_after: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified_before: Option<DateTime<Utc>>,
+    /// Modified in the last this many days, counted from when the search runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified_within_days: Option<u32>,
+    /// Glob over workspace-relative paths, e.g. "projects/**/*.midlight"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedSearch {
+    pub id: String,
+    pub name: String,
+    pub query: SearchQuery,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A document in a smart folder
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmartFolderEntry {
+    pub path: String,
+    /// Relative to the workspace root
+    pub relative_path: String,
+    pub name: String,
+    pub modified: DateTime<Utc>,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SearchesFile {
+    version: u32,
+    #[serde(default)]
+    searches: Vec<SavedSearch>,
+}
+
+impl SearchQuery {
+    /// The index filter for this query, as of `now`
+    pub fn to_filter(&self, workspace_root: &Path, now: DateTime<Utc>) -> FileFilter {
+        let within = self
+            .modified_within_days
+            .map(|days| now - Duration::days(days as i64));
+        let modified_after = match (self.modified_after, within) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+
+        FileFilter {
+            path_prefix: Some(workspace_root.to_string_lossy().to_string()),
+            terms: self.text.as_deref().map(query_terms).unwrap_or_default(),
+            tags: self
+                .tags
+                .iter()
+                .map(|t| t.trim().trim_start_matches('#').to_lowercase())
+                .filter(|t| !t.is_empty())
+                .collect(),
+            modified_after: modified_after.map(|t| t.timestamp()),
+            modified_before: self.modified_before.map(|t| t.timestamp()),
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if let Some(pattern) = &self.path {
+            PathGlob::new(pattern)?;
+        }
+        Ok(())
+    }
+}
+
+/// Turn the index's matches into smart folder entries, applying the parts of
+/// the query the index doesn't handle
+pub fn to_entries(
+    workspace_root: &Path,
+    query: &SearchQuery,
+    files: Vec<IndexedFile>,
+    limit: Option<usize>,
+) -> Result<Vec<SmartFolderEntry>, String> {
+    let glob = query.path.as_deref().map(PathGlob::new).transpose()?;
+
+    let entries = files.into_iter().filter_map(|file| {
+        let path = PathBuf::from(&file.file_path);
+        let relative = path
+            .strip_prefix(workspace_root)
+            .ok()?
+            .to_string_lossy()
+            .replace('\\', "/");
+        // Text taken from images and PDFs is indexed but isn't a document
+        if relative.starts_with(".midlight/") {
+            return None;
+        }
+        if glob.as_ref().is_some_and(|glob| !glob.is_match(&relative)) {
+            return None;
+        }
+
+        Some(SmartFolderEntry {
+            name: path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            path: file.file_path,
+            relative_path: relative,
+            modified: Utc
+                .timestamp_opt(file.mtime, 0)
+                .single()
+                .unwrap_or_default(),
+            tags: file.tags,
+        })
+    });
+
+    Ok(entries.take(limit.unwrap_or(usize::MAX)).collect())
+}
+
+// ============================================================================
+// Saved Search Store
+// ============================================================================
+
+pub struct SavedSearchStore {
+    searches_path: PathBuf,
+}
+
+impl SavedSearchStore {
+    pub fn new(workspace_root: &Path) -> Self {
+        Self {
+            searches_path: workspace_root.join(".midlight").join("saved-searches.json"),
+        }
+    }
+
+    /// All saved searches, in the order they were created
+    pub fn list(&self) -> Result<Vec<SavedSearch>, String> {
+        Ok(self.read()?.searches)
+    }
+
+    pub fn get(&self, id: &str) -> Result<SavedSearch, String> {
+        self.read()?
+            .searches
+            .into_iter()
+            .find(|s| s.id == id)
+            .ok_or_else(|| format!("Saved search not found: {}", id))
+    }
+
+    pub fn create(&self, name: &str, query: SearchQuery) -> Result<SavedSearch, String> {
+        let name = validate_name(name)?;
+        query.validate()?;
+
+        let now = Utc::now();
+        let search = SavedSearch {
+            id: uuid::Uuid::new_v4().to_string(),
+            name,
+            query,
+            created_at: now,
+            updated_at: now,
+        };
+
+        let mut file = self.read()?;
+        file.searches.push(search.clone());
+        self.write(&file)?;
+        Ok(search)
+    }
+
+    /// Rename a saved search and/or change its query
+    pub fn update(
+        &self,
+        id: &str,
+        name: Option<&str>,
+        query: Option<SearchQuery>,
+    ) -> Result<SavedSearch, String> {
+        let mut file = self.read()?;
+        let search = file
+            .searches
+            .iter_mut()
+            .find(|s| s.id == id)
+            .ok_or_else(|| format!("Saved search not found: {}", id))?;
+
+        if let Some(name) = name {
+            search.name = validate_name(name)?;
+        }
+        if let Some(query) = query {
+            query.validate()?;
+            search.query = query;
+        }
+        search.updated_at = Utc::now();
+
+        let search = search.clone();
+        self.write(&file)?;
+        Ok(search)
+    }
+
+    /// Delete a saved search, returning whether it existed
+    pub fn delete(&self, id: &str) -> Result<bool, String> {
+        let mut file = self.read()?;
+        let before = file.searches.len();
+        file.searches.retain(|s| s.id != id);
+        if file.searches.len() == before {
+            return Ok(false);
+        }
+        self.write(&file)?;
+        Ok(true)
+    }
+
+    fn read(&self) -> Result<SearchesFile, String> {
+        if !self.searches_path.exists() {
+            return Ok(SearchesFile {
+                version: SEARCHES_VERSION,
+                searches: Vec::new(),
+            });
+        }
+
+        let content = fs::read_to_string(&self.searches_path)
+            .map_err(|e| format!("Failed to read saved searches: {}", e))?;
+        serde_json::from_str(&content).map_err(|e| format!("Failed to parse saved searches: {}", e))
+    }
+
+    fn write(&self, file: &SearchesFile) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(file)
+            .map_err(|e| format!("Failed to serialize saved searches: {}", e))?;
+
+        write_atomic(&self.searches_path, json)
+            .map_err(|e| format!("Failed to write saved searches: {}", e))
+    }
+}
+
+fn validate_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Saved search name cannot be empty".to_string());
+    }
+    Ok(name.to_string())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_store_crud() {
+        let temp = TempDir::new().unwrap();
+        let store = SavedSearchStore::new(temp.path());
+        assert!(store.list().unwrap().is_empty());
+
+        let query = SearchQuery {
+            tags: vec!["#Draft".to_string()],
+            ..Default::default()
+        };
+        let search = store.create(" Drafts ", query.clone()).unwrap();
+        assert_eq!(search.name, "Drafts");
+        assert_eq!(store.get(&search.id).unwrap().query, query);
+
+        let bad = SearchQuery {
+            path: Some("{unclosed".to_string()),
+            ..Default::default()
+        };
+        assert!(store.update(&search.id, None, Some(bad)).is_err());
+        assert!(store.create("", SearchQuery::default()).is_err());
+
+        let renamed = store
+            .update(&search.id, Some("Work in progress"), None)
+            .unwrap();
+        assert_eq!(renamed.name, "Work in progress");
+        assert_eq!(store.list().unwrap().len(), 1);
+
+        assert!(store.delete(&search.id).unwrap());
+        assert!(!store.delete(&search.id).unwrap());
+        assert!(store.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_query_to_filter() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 10, 12, 0, 0).unwrap();
+        let query = SearchQuery {
+            text: Some("Quarterly plan, plan".to_string()),
+            tags: vec!["#Work".to_string(), " ".to_string()],
+            modified_after: Some(Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap()),
+            modified_within_days: Some(7),
+            ..Default::default()
+        };
+
+        let filter = query.to_filter(Path::new("/ws"), now);
+        assert_eq!(filter.path_prefix.as_deref(), Some("/ws"));
+        assert_eq!(filter.terms, vec!["quarterly", "plan"]);
+        assert_eq!(filter.tags, vec!["work"]);
+        // The later of the two lower bounds wins
+        assert_eq!(
+            filter.modified_after,
+            Some((now - Duration::days(7)).timestamp())
+        );
+        assert_eq!(filter.modified_before, None);
+    }
+
+    #[test]
+    fn test_to_entries() {
+        let file = |path: &str| IndexedFile {
+            project_path: "/ws".to_string(),
+            file_path: path.to_string(),
+            mtime: 1_700_000_000,
+            tags: vec![],
+        };
+        let files = vec![
+            file("/ws/notes/a.midlight"),
+            file("/ws/notes/b.md"),
+            file("/ws/.midlight/pdf/text/x.txt"),
+            file("/ws/other/c.midlight"),
+        ];
+
+        let query = SearchQuery {
+            path: Some("notes/**".to_string()),
+            ..Default::default()
+        };
+        let entries = to_entries(Path::new("/ws"), &query, files.clone(), None).unwrap();
+        let names: Vec<_> = entries.iter().map(|e| e.relative_path.as_str()).collect();
+        assert_eq!(names, vec!["notes/a.midlight", "notes/b.md"]);
+        assert_eq!(entries[0].name, "a.midlight");
+        assert_eq!(entries[0].modified.timestamp(), 1_700_000_000);
+
+        let all = to_entries(Path::new("/ws"), &SearchQuery::default(), files, Some(2)).unwrap();
+        assert_eq!(all.len(), 2);
+    }
+}
@@ -0,0 +1,261 @@
+// Session Marker - Tell a crash from a clean exit
+//
+// A marker file is written when the app starts and removed when it exits
+// cleanly, so finding one at startup means the previous session never shut
+// down: a crash, a force quit, or the machine losing power. Warnings and
+// errors logged during a session are appended to a small log beside the
+// marker, which leaves an excerpt of what happened before a crash.
+//
+// Workspaces opened during the session are recorded in the marker so their
+// recovery data can be offered after a crash without waiting for the user to
+// open them again.
+//
+// Stored at: {app data}/com.midlight.app/session.json (and session.log)
+
+use crate::services::app_dirs::app_data_dir;
+use crate::services::atomic_write::write_atomic;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::{info, warn};
+
+/// Lines of the previous session's log kept in a crash report
+const EXCERPT_LINES: usize = 40;
+
+/// Once the session log grows past this, later lines are dropped
+const MAX_LOG_BYTES: u64 = 512 * 1024;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Contents of the marker file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInfo {
+    pub started_at: DateTime<Utc>,
+    pub app_version: String,
+    pub pid: u32,
+    #[serde(default)]
+    pub workspaces: Vec<String>,
+}
+
+/// A previous session that ended without a clean shutdown
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashInfo {
+    pub session: SessionInfo,
+    /// The last warnings and errors logged before the crash
+    pub log_excerpt: Option<String>,
+    /// Whether the crash has been sent to the error reporter
+    #[serde(skip)]
+    pub reported: bool,
+}
+
+// ============================================================================
+// Session Marker
+// ============================================================================
+
+pub struct SessionMarker {
+    marker_path: PathBuf,
+    log_path: PathBuf,
+    current: Mutex<Option<SessionInfo>>,
+    last_crash: Mutex<Option<CrashInfo>>,
+}
+
+impl SessionMarker {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            marker_path: dir.join("session.json"),
+            log_path: dir.join("session.log"),
+            current: Mutex::new(None),
+            last_crash: Mutex::new(None),
+        }
+    }
+
+    /// Start a session, returning the previous one if it crashed
+    pub fn begin(&self, app_version: &str) -> Option<CrashInfo> {
+        let crash = self.read_marker().map(|session| CrashInfo {
+            session,
+            log_excerpt: self.log_excerpt(),
+            reported: false,
+        });
+        if let Some(crash) = &crash {
+            info!(
+                "Previous session (started {}) did not exit cleanly",
+                crash.session.started_at
+            );
+        }
+
+        let session = SessionInfo {
+            started_at: Utc::now(),
+            app_version: app_version.to_string(),
+            pid: std::process::id(),
+            workspaces: Vec::new(),
+        };
+        if let Err(e) = self.write_marker(&session) {
+            warn!("Failed to write session marker: {}", e);
+        }
+        let _ = fs::remove_file(&self.log_path);
+
+        *self.current.lock().unwrap() = Some(session);
+        *self.last_crash.lock().unwrap() = crash.clone();
+        crash
+    }
+
+    /// Mark the session as cleanly finished
+    pub fn end(&self) {
+        if self.current.lock().unwrap().take().is_none() {
+            return;
+        }
+        let _ = fs::remove_file(&self.marker_path);
+        let _ = fs::remove_file(&self.log_path);
+        info!("Session ended cleanly");
+    }
+
+    /// Record a workspace opened during this session
+    pub fn add_workspace(&self, workspace_root: &str) {
+        let session = {
+            let mut current = self.current.lock().unwrap();
+            let Some(session) = current.as_mut() else {
+                return;
+            };
+            if session.workspaces.iter().any(|w| w == workspace_root) {
+                return;
+            }
+            session.workspaces.push(workspace_root.to_string());
+            session.clone()
+        };
+        // Logging goes through log_writer, so not while holding the lock
+        if let Err(e) = self.write_marker(&session) {
+            warn!("Failed to update session marker: {}", e);
+        }
+    }
+
+    /// Workspaces opened during this session
+    pub fn workspaces(&self) -> Vec<String> {
+        self.current
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|session| session.workspaces.clone())
+            .unwrap_or_default()
+    }
+
+    /// The crash detected at startup, if any
+    pub fn last_crash(&self) -> Option<CrashInfo> {
+        self.last_crash.lock().unwrap().clone()
+    }
+
+    /// Forget the detected crash once the user has dealt with it
+    pub fn dismiss_crash(&self) {
+        *self.last_crash.lock().unwrap() = None;
+    }
+
+    /// The detected crash if it hasn't been reported yet, marking it reported
+    pub fn take_unreported_crash(&self) -> Option<CrashInfo> {
+        let mut last_crash = self.last_crash.lock().unwrap();
+        let crash = last_crash.as_mut().filter(|c| !c.reported)?;
+        crash.reported = true;
+        Some(crash.clone())
+    }
+
+    /// Writer for the session log; lines past the size limit are discarded
+    pub fn log_writer(&self) -> Box<dyn Write> {
+        let too_large = fs::metadata(&self.log_path).is_ok_and(|m| m.len() > MAX_LOG_BYTES);
+        if too_large || self.current.lock().unwrap().is_none() {
+            return Box::new(io::sink());
+        }
+        match OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.log_path)
+        {
+            Ok(file) => Box::new(file),
+            Err(_) => Box::new(io::sink()),
+        }
+    }
+
+    fn read_marker(&self) -> Option<SessionInfo> {
+        let content = fs::read_to_string(&self.marker_path).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(session) => Some(session),
+            Err(e) => {
+                warn!("Ignoring unreadable session marker: {}", e);
+                None
+            }
+        }
+    }
+
+    fn write_marker(&self, session: &SessionInfo) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(session)
+            .map_err(|e| format!("Failed to serialize session marker: {}", e))?;
+        write_atomic(&self.marker_path, json)
+            .map_err(|e| format!("Failed to write session marker: {}", e))
+    }
+
+    fn log_excerpt(&self) -> Option<String> {
+        let content = fs::read_to_string(&self.log_path).ok()?;
+        let lines: Vec<&str> = content.lines().collect();
+        let excerpt = lines[lines.len().saturating_sub(EXCERPT_LINES)..].join("\n");
+        (!excerpt.trim().is_empty()).then_some(excerpt)
+    }
+}
+
+// ============================================================================
+// Global Singleton
+// ============================================================================
+
+lazy_static::lazy_static! {
+    pub static ref SESSION: SessionMarker = {
+        SessionMarker::new(&app_data_dir())
+    };
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_crash_detection() {
+        let temp = TempDir::new().unwrap();
+
+        // First launch, then a clean exit
+        let marker = SessionMarker::new(temp.path());
+        assert!(marker.begin("1.0.0").is_none());
+        marker.end();
+        assert!(SessionMarker::new(temp.path()).begin("1.0.0").is_none());
+
+        // A session that never ends looks like a crash to the next one
+        let marker = SessionMarker::new(temp.path());
+        marker.begin("1.0.0");
+        marker.add_workspace("/notes");
+        marker.add_workspace("/notes");
+        writeln!(marker.log_writer(), "WARN something went wrong").unwrap();
+        drop(marker);
+
+        let marker = SessionMarker::new(temp.path());
+        let crash = marker.begin("1.1.0").unwrap();
+        assert_eq!(crash.session.app_version, "1.0.0");
+        assert_eq!(crash.session.workspaces, vec!["/notes".to_string()]);
+        assert_eq!(
+            crash.log_excerpt.as_deref(),
+            Some("WARN something went wrong")
+        );
+
+        // Reported once, and still available until dismissed
+        assert!(marker.take_unreported_crash().is_some());
+        assert!(marker.take_unreported_crash().is_none());
+        assert!(marker.last_crash().is_some());
+        marker.dismiss_crash();
+        assert!(marker.last_crash().is_none());
+    }
+}
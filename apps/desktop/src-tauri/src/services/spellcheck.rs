@@ -0,0 +1,657 @@
+// Spellcheck - Offline spelling with Hunspell dictionaries
+//
+// Dictionaries are the .dic/.aff pairs used by LibreOffice, Firefox and most
+// Linux distributions, looked up by language code ("en_US") in the app's
+// dictionaries folder, then in $DICPATH and the system's usual locations.
+// Words are checked against the dictionary's stems with its prefix and
+// suffix rules (including one prefix and one suffix together); compounding
+// rules aren't supported. Suggestions come from the dictionary's REP table
+// and single edits using its TRY characters.
+//
+// Words the user adds are kept per workspace, one per line, at:
+// .midlight/dictionary.txt
+
+use crate::services::app_dirs::app_data_dir;
+use crate::services::atomic_write::write_atomic;
+use crate::services::readability::text_blocks;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Suggestions offered for each misspelling
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Words longer than this get no suggestions, as the edits to try grow
+/// with the length
+const MAX_SUGGEST_LENGTH: usize = 30;
+
+lazy_static! {
+    /// Links and email addresses, whose parts aren't words to check
+    static ref NOT_PROSE: Regex = Regex::new(r"\b[a-zA-Z][\w+.-]*://\S+|\b[\w.+-]+@[\w-]+\.[\w.-]+").unwrap();
+    static ref DICTIONARIES: Mutex<HashMap<PathBuf, Arc<Dictionary>>> = Mutex::new(HashMap::new());
+}
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Misspelling {
+    pub word: String,
+    /// ProseMirror position of the first character
+    pub from: usize,
+    /// ProseMirror position after the last character
+    pub to: usize,
+    pub suggestions: Vec<String>,
+}
+
+type Flag = u32;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FlagMode {
+    /// One character per flag
+    Char,
+    /// Two characters per flag
+    Long,
+    /// Comma-separated numbers
+    Num,
+}
+
+#[derive(Debug)]
+struct Affix {
+    flag: Flag,
+    /// Whether it combines with affixes of the other kind
+    cross: bool,
+    strip: String,
+    add: String,
+    /// What the stem must start (prefixes) or end (suffixes) with
+    condition: Option<Regex>,
+}
+
+impl Affix {
+    fn allows(&self, stem: &str) -> bool {
+        self.condition.as_ref().map_or(true, |c| c.is_match(stem))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Dictionary {
+    words: HashMap<String, Vec<Flag>>,
+    prefixes: Vec<Affix>,
+    suffixes: Vec<Affix>,
+    try_chars: Vec<char>,
+    replacements: Vec<(String, String)>,
+    forbidden: Option<Flag>,
+    need_affix: Option<Flag>,
+    no_suggest: Option<Flag>,
+}
+
+// ============================================================================
+// Loading
+// ============================================================================
+
+/// Folders searched for dictionaries, in order
+pub fn dictionary_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![app_data_dir().join("dictionaries")];
+    if let Some(paths) = std::env::var_os("DICPATH") {
+        dirs.extend(std::env::split_paths(&paths));
+    }
+    if let Some(home) = dirs::home_dir() {
+        dirs.push(home.join("Library").join("Spelling"));
+    }
+    for dir in [
+        "/Library/Spelling",
+        "/usr/share/hunspell",
+        "/usr/share/myspell",
+        "/usr/share/myspell/dicts",
+        "/usr/local/share/hunspell",
+    ] {
+        dirs.push(PathBuf::from(dir));
+    }
+    dirs
+}
+
+/// Languages with a dictionary installed, e.g. ["de_DE", "en_US"]
+pub fn available_languages(dirs: &[PathBuf]) -> Vec<String> {
+    let mut languages = BTreeSet::new();
+    for dir in dirs {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+            if path.extension().is_some_and(|ext| ext == "dic")
+                && path.with_extension("aff").exists()
+            {
+                if let Some(stem) = path.file_stem() {
+                    languages.insert(stem.to_string_lossy().to_string());
+                }
+            }
+        }
+    }
+    languages.into_iter().collect()
+}
+
+/// The dictionary for a language, loaded once and then shared
+pub fn load_dictionary(dirs: &[PathBuf], language: &str) -> Result<Arc<Dictionary>, String> {
+    if language.is_empty() || language.contains(['/', '\\', '.']) {
+        return Err(format!("Invalid language: {}", language));
+    }
+    let dic_path = dirs
+        .iter()
+        .map(|dir| dir.join(format!("{}.dic", language)))
+        .find(|path| path.exists() && path.with_extension("aff").exists())
+        .ok_or_else(|| format!("No dictionary installed for {}", language))?;
+
+    let mut cache = DICTIONARIES.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(dictionary) = cache.get(&dic_path) {
+        return Ok(dictionary.clone());
+    }
+    let aff = fs::read(dic_path.with_extension("aff"))
+        .map_err(|e| format!("Failed to read dictionary: {}", e))?;
+    let dic = fs::read(&dic_path).map_err(|e| format!("Failed to read dictionary: {}", e))?;
+    let dictionary = Arc::new(Dictionary::from_bytes(&aff, &dic));
+    cache.insert(dic_path, dictionary.clone());
+    Ok(dictionary)
+}
+
+/// Text of a dictionary file in the encoding its .aff file declares. Only
+/// UTF-8 and ISO-8859-1 are decoded exactly; other 8-bit encodings are read
+/// as ISO-8859-1.
+fn decode(bytes: &[u8], utf8: bool) -> String {
+    if utf8 {
+        String::from_utf8_lossy(bytes).into_owned()
+    } else {
+        bytes.iter().map(|b| *b as char).collect()
+    }
+}
+
+impl Dictionary {
+    pub fn from_bytes(aff: &[u8], dic: &[u8]) -> Self {
+        let utf8 = String::from_utf8_lossy(aff).lines().any(|line| {
+            let mut fields = line.split_whitespace();
+            fields.next() == Some("SET")
+                && fields
+                    .next()
+                    .is_some_and(|set| set.eq_ignore_ascii_case("UTF-8"))
+        });
+        Self::parse(&decode(aff, utf8), &decode(dic, utf8))
+    }
+
+    pub fn parse(aff: &str, dic: &str) -> Self {
+        let mut dictionary = Dictionary::default();
+        let mut mode = FlagMode::Char;
+        let mut cross: HashMap<(bool, Flag), bool> = HashMap::new();
+
+        for line in aff.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let Some(&directive) = fields.first() else {
+                continue;
+            };
+            let flag = |mode| {
+                fields
+                    .get(1)
+                    .and_then(|f| parse_flags(f, mode).into_iter().next())
+            };
+            match directive {
+                "FLAG" => {
+                    mode = match fields.get(1).copied() {
+                        Some("long") => FlagMode::Long,
+                        Some("num") => FlagMode::Num,
+                        _ => FlagMode::Char,
+                    }
+                }
+                "TRY" => dictionary.try_chars = fields.get(1).unwrap_or(&"").chars().collect(),
+                "REP" if fields.len() >= 3 => {
+                    let unescape = |s: &str| s.trim_matches(['^', '$']).replace('_', " ");
+                    dictionary
+                        .replacements
+                        .push((unescape(fields[1]), unescape(fields[2])));
+                }
+                "FORBIDDENWORD" => dictionary.forbidden = flag(mode),
+                "NEEDAFFIX" => dictionary.need_affix = flag(mode),
+                "NOSUGGEST" => dictionary.no_suggest = flag(mode),
+                "PFX" | "SFX" if fields.len() >= 4 => {
+                    let prefix = directive == "PFX";
+                    let Some(affix_flag) = flag(mode) else {
+                        continue;
+                    };
+                    // Header: PFX flag Y|N count
+                    if fields.len() == 4
+                        && matches!(fields[2], "Y" | "N")
+                        && fields[3].parse::<usize>().is_ok()
+                    {
+                        cross.insert((prefix, affix_flag), fields[2] == "Y");
+                        continue;
+                    }
+                    let strip = if fields[2] == "0" { "" } else { fields[2] };
+                    let add = fields[3].split('/').next().unwrap_or("");
+                    let affix = Affix {
+                        flag: affix_flag,
+                        cross: cross.get(&(prefix, affix_flag)).copied().unwrap_or(false),
+                        strip: strip.to_string(),
+                        add: if add == "0" {
+                            String::new()
+                        } else {
+                            add.to_string()
+                        },
+                        condition: fields.get(4).and_then(|c| condition_regex(c, prefix)),
+                    };
+                    if prefix {
+                        dictionary.prefixes.push(affix);
+                    } else {
+                        dictionary.suffixes.push(affix);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // The first line of a .dic file is its approximate word count
+        for line in dic.lines().skip(1) {
+            let entry = line.split(['\t', ' ']).next().unwrap_or("");
+            let (word, flags) = match entry.split_once('/') {
+                Some((word, flags)) => (word, parse_flags(flags, mode)),
+                None => (entry, Vec::new()),
+            };
+            if !word.is_empty() {
+                dictionary
+                    .words
+                    .entry(word.to_string())
+                    .or_default()
+                    .extend(flags);
+            }
+        }
+        dictionary
+    }
+
+    /// Whether a word is spelled correctly. A capitalized word may be a
+    /// lowercase one starting a sentence, and a word in capitals any form.
+    pub fn check(&self, word: &str) -> bool {
+        if self.check_form(word) {
+            return true;
+        }
+        let lower = word.to_lowercase();
+        if lower == word {
+            return false;
+        }
+        let all_caps = !word.chars().any(char::is_lowercase);
+        if (all_caps || capitalize(&lower) == word) && self.check_form(&lower) {
+            return true;
+        }
+        all_caps && self.check_form(&capitalize(&lower))
+    }
+
+    /// Up to MAX_SUGGESTIONS correct words one edit (or one REP
+    /// replacement) away, most likely first
+    pub fn suggest(&self, word: &str) -> Vec<String> {
+        let chars: Vec<char> = word.chars().collect();
+        if chars.len() > MAX_SUGGEST_LENGTH {
+            return Vec::new();
+        }
+        let capitalized = chars.first().is_some_and(|c| c.is_uppercase());
+        let base = if capitalized {
+            word.to_lowercase()
+        } else {
+            word.to_string()
+        };
+        let chars: Vec<char> = base.chars().collect();
+
+        let mut candidates: Vec<String> = Vec::new();
+        for (from, to) in &self.replacements {
+            if !from.is_empty() {
+                for (i, _) in base.match_indices(from.as_str()) {
+                    candidates.push(format!("{}{}{}", &base[..i], to, &base[i + from.len()..]));
+                }
+            }
+        }
+        let join = |chars: &[char]| chars.iter().collect::<String>();
+        for i in 0..chars.len().saturating_sub(1) {
+            let mut swapped = chars.clone();
+            swapped.swap(i, i + 1);
+            candidates.push(join(&swapped));
+        }
+        for i in 0..chars.len() {
+            for c in &self.try_chars {
+                let mut replaced = chars.clone();
+                replaced[i] = *c;
+                candidates.push(join(&replaced));
+            }
+        }
+        for i in 0..chars.len() {
+            let mut removed = chars.clone();
+            removed.remove(i);
+            candidates.push(join(&removed));
+        }
+        for i in 0..=chars.len() {
+            for c in &self.try_chars {
+                let mut inserted = chars.clone();
+                inserted.insert(i, *c);
+                candidates.push(join(&inserted));
+            }
+        }
+
+        let mut seen = HashSet::new();
+        let mut suggestions: Vec<String> = candidates
+            .into_iter()
+            .filter(|c| c != &base && seen.insert(c.clone()))
+            .filter(|c| self.check(c) && !self.is_no_suggest(c))
+            .take(MAX_SUGGESTIONS)
+            .collect();
+
+        // Two words run together
+        if suggestions.len() < MAX_SUGGESTIONS {
+            for i in 1..chars.len() {
+                let (a, b) = (join(&chars[..i]), join(&chars[i..]));
+                if a.chars().count() > 1
+                    && b.chars().count() > 1
+                    && self.check(&a)
+                    && self.check(&b)
+                {
+                    suggestions.push(format!("{} {}", a, b));
+                    break;
+                }
+            }
+        }
+
+        if capitalized {
+            suggestions = suggestions.iter().map(|s| capitalize(s)).collect();
+        }
+        suggestions
+    }
+
+    fn check_form(&self, word: &str) -> bool {
+        if let Some(flags) = self.words.get(word) {
+            if self.forbidden.is_some_and(|f| flags.contains(&f)) {
+                return false;
+            }
+            if !self.need_affix.is_some_and(|f| flags.contains(&f)) {
+                return true;
+            }
+        }
+        self.check_affixed(word)
+    }
+
+    fn check_affixed(&self, word: &str) -> bool {
+        if self.suffixes.iter().any(|sfx| {
+            strip_suffix(word, sfx).is_some_and(|stem| self.has_flags(&stem, &[sfx.flag]))
+        }) {
+            return true;
+        }
+
+        for pfx in &self.prefixes {
+            let Some(stem) = strip_prefix(word, pfx) else {
+                continue;
+            };
+            if self.has_flags(&stem, &[pfx.flag]) {
+                return true;
+            }
+            if pfx.cross
+                && self.suffixes.iter().filter(|sfx| sfx.cross).any(|sfx| {
+                    strip_suffix(&stem, sfx)
+                        .is_some_and(|root| self.has_flags(&root, &[pfx.flag, sfx.flag]))
+                })
+            {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn has_flags(&self, stem: &str, required: &[Flag]) -> bool {
+        self.words.get(stem).is_some_and(|flags| {
+            required.iter().all(|f| flags.contains(f))
+                && !self.forbidden.is_some_and(|f| flags.contains(&f))
+        })
+    }
+
+    fn is_no_suggest(&self, word: &str) -> bool {
+        self.no_suggest
+            .is_some_and(|f| self.words.get(word).is_some_and(|flags| flags.contains(&f)))
+    }
+}
+
+fn strip_suffix(word: &str, sfx: &Affix) -> Option<String> {
+    let rest = word.strip_suffix(sfx.add.as_str())?;
+    if rest.is_empty() {
+        return None;
+    }
+    let stem = format!("{}{}", rest, sfx.strip);
+    sfx.allows(&stem).then_some(stem)
+}
+
+fn strip_prefix(word: &str, pfx: &Affix) -> Option<String> {
+    let rest = word.strip_prefix(pfx.add.as_str())?;
+    if rest.is_empty() {
+        return None;
+    }
+    let stem = format!("{}{}", pfx.strip, rest);
+    pfx.allows(&stem).then_some(stem)
+}
+
+fn parse_flags(flags: &str, mode: FlagMode) -> Vec<Flag> {
+    match mode {
+        FlagMode::Char => flags.chars().map(|c| c as Flag).collect(),
+        FlagMode::Long => flags
+            .chars()
+            .collect::<Vec<_>>()
+            .chunks(2)
+            .map(|pair| pair.iter().fold(0, |flag, c| (flag << 16) | *c as Flag))
+            .collect(),
+        FlagMode::Num => flags
+            .split(',')
+            .filter_map(|n| n.trim().parse().ok())
+            .collect(),
+    }
+}
+
+/// An affix condition ("[^aeiou]y", ".") as a regex anchored at the end
+/// of the stem for suffixes, or its start for prefixes
+fn condition_regex(condition: &str, prefix: bool) -> Option<Regex> {
+    if condition == "." {
+        return None;
+    }
+    let mut pattern = String::new();
+    let mut in_class = false;
+    for c in condition.chars() {
+        match c {
+            '[' => in_class = true,
+            ']' => in_class = false,
+            _ => {}
+        }
+        match c {
+            '[' | ']' | '.' => pattern.push(c),
+            '^' if in_class => pattern.push(c),
+            _ => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    let anchored = if prefix {
+        format!("^{}", pattern)
+    } else {
+        format!("{}$", pattern)
+    };
+    Regex::new(&anchored).ok()
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+// ============================================================================
+// User dictionary
+// ============================================================================
+
+pub fn user_dictionary_path(workspace_root: &Path) -> PathBuf {
+    workspace_root.join(".midlight").join("dictionary.txt")
+}
+
+pub fn user_words(workspace_root: &Path) -> BTreeSet<String> {
+    fs::read_to_string(user_dictionary_path(workspace_root))
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|w| !w.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Add a word to the workspace's dictionary
+pub fn add_user_word(workspace_root: &Path, word: &str) -> Result<(), String> {
+    let word = word.trim().replace('’', "'");
+    if word.is_empty() || word.contains(char::is_whitespace) {
+        return Err(format!("Not a single word: {}", word));
+    }
+    let mut words = user_words(workspace_root);
+    if !words.insert(word) {
+        return Ok(());
+    }
+
+    let path = user_dictionary_path(workspace_root);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create .midlight directory: {}", e))?;
+    }
+    let content: String = words.iter().map(|w| format!("{}\n", w)).collect();
+    write_atomic(&path, content).map_err(|e| format!("Failed to write dictionary: {}", e))
+}
+
+// ============================================================================
+// Documents
+// ============================================================================
+
+/// Misspelled words in a .midlight document's JSON (the whole file, or
+/// just its Tiptap content), skipping words in capitals, words with digits,
+/// #tags, @mentions, links and the user's own words
+pub fn spellcheck_document(
+    document: &Value,
+    dictionary: &Dictionary,
+    user_words: &BTreeSet<String>,
+) -> Vec<Misspelling> {
+    let mut suggestions: HashMap<String, Vec<String>> = HashMap::new();
+    let mut misspellings = Vec::new();
+
+    for block in text_blocks(document) {
+        let mut chars = block.chars.clone();
+        let text: String = chars.iter().collect();
+        for found in NOT_PROSE.find_iter(&text) {
+            let start = text[..found.start()].chars().count();
+            let end = start + found.as_str().chars().count();
+            chars[start..end].iter_mut().for_each(|c| *c = ' ');
+        }
+
+        let mut i = 0;
+        while i < chars.len() {
+            if !chars[i].is_alphanumeric() {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_alphanumeric()
+                    || (matches!(chars[i], '\'' | '’')
+                        && chars.get(i + 1).is_some_and(|c| c.is_alphabetic())))
+            {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect::<String>().replace('’', "'");
+
+            let skip = word.chars().any(|c| c.is_numeric())
+                || !word.chars().any(char::is_lowercase)
+                || (start > 0 && matches!(chars[start - 1], '#' | '@'))
+                || user_words.contains(&word)
+                || user_words.contains(&word.to_lowercase())
+                || dictionary.check(&word);
+            if skip {
+                continue;
+            }
+            misspellings.push(Misspelling {
+                suggestions: suggestions
+                    .entry(word.clone())
+                    .or_insert_with(|| dictionary.suggest(&word))
+                    .clone(),
+                word,
+                from: block.position(start),
+                to: block.position(i),
+            });
+        }
+    }
+    misspellings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    const AFF: &str = "SET UTF-8\nTRY esianrtolcdugmphbyfvkwz'\nREP 1\nREP f ph\n\
+                       FORBIDDENWORD !\n\
+                       PFX U Y 1\nPFX U 0 un .\n\
+                       SFX S Y 4\nSFX S y ies [^aeiou]y\nSFX S 0 s [aeiou]y\nSFX S 0 es [sxz]\nSFX S 0 s [^sxyz]\n\
+                       SFX D Y 2\nSFX D 0 d e\nSFX D 0 ed [^e]\n";
+    const DIC: &str = "12\nhello\nworld/S\nstory/S\nbox/S\nlock/UD\ntie/UD\nphone/S\nirregardless/!\nsay/S\nto\nand\nin\n";
+
+    fn dictionary() -> Dictionary {
+        Dictionary::parse(AFF, DIC)
+    }
+
+    #[test]
+    fn test_check() {
+        let dictionary = dictionary();
+        for word in [
+            "hello", "Hello", "HELLO", "worlds", "stories", "boxes", "unlocked", "untied", "phones",
+        ] {
+            assert!(dictionary.check(word), "{}", word);
+        }
+        for word in [
+            "helo",
+            "storys",
+            "boxs",
+            "unhello",
+            "hELLO",
+            "irregardless",
+            "locks",
+        ] {
+            assert!(!dictionary.check(word), "{}", word);
+        }
+
+        assert_eq!(dictionary.suggest("wrold"), vec!["world"]);
+        assert_eq!(dictionary.suggest("Fone")[0], "Phone");
+        assert_eq!(dictionary.suggest("helloworld"), vec!["hello world"]);
+    }
+
+    #[test]
+    fn test_spellcheck_document() {
+        let temp = TempDir::new().unwrap();
+        add_user_word(temp.path(), "Midlight").unwrap();
+        add_user_word(temp.path(), "Midlight").unwrap();
+        assert!(add_user_word(temp.path(), "two words").is_err());
+        let user = user_words(temp.path());
+        assert_eq!(user.len(), 1);
+
+        let document = json!({ "type": "doc", "content": [
+            { "type": "heading", "attrs": { "level": 1 }, "content": [{ "type": "text", "text": "Helo" }] },
+            { "type": "paragraph", "content": [
+                { "type": "text", "text": "Midlight says helo wrold to NASA, #tgas and https://exmaple.com in 2026 " },
+                { "type": "text", "text": "cde", "marks": [{ "type": "code" }] }
+            ]}
+        ]});
+        let found = spellcheck_document(&document, &dictionary(), &user);
+        let words: Vec<&str> = found.iter().map(|m| m.word.as_str()).collect();
+        assert_eq!(words, vec!["Helo", "helo", "wrold"]);
+
+        assert_eq!((found[0].from, found[0].to), (1, 5));
+        assert_eq!(found[0].suggestions[0], "Hello");
+        // The paragraph's text starts at 7, after the heading (6) and its opening
+        assert_eq!((found[1].from, found[1].to), (7 + 14, 7 + 18));
+    }
+}
@@ -0,0 +1,630 @@
+// Structured Output - JSON schema validation and repair for LLM responses
+//
+// Structured requests ask the provider for JSON and describe the expected
+// shape with a JSON schema. Responses are repaired before validation:
+// markdown fences and surrounding prose are stripped, trailing commas are
+// removed, scalar values are coerced to the schema's type ("42" -> 42), and
+// properties disallowed by `additionalProperties: false` are dropped.
+//
+// Supported schema keywords: type, enum, const, properties, required,
+// additionalProperties, items, minItems, maxItems, minLength, maxLength,
+// pattern, minimum, maximum, exclusiveMinimum, exclusiveMaximum, anyOf,
+// oneOf, allOf and local $ref (#/$defs/..., #/definitions/...).
+
+use crate::services::llm_routing::RoutingInfo;
+use crate::services::llm_service::{ChatMessage, UsageInfo};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Default number of times the model is asked to fix invalid output
+pub const DEFAULT_REPAIR_ATTEMPTS: u32 = 1;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// A place where a value doesn't match its schema
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationIssue {
+    /// JSON pointer to the offending value ("/" for the root)
+    pub path: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Parsed output that matches the schema
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedOutput {
+    pub data: Value,
+    /// Whether the raw output had to be repaired to match
+    pub repaired: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructuredResponse {
+    pub data: Value,
+    /// Number of requests sent, including repair requests
+    pub attempts: u32,
+    /// Whether the output was repaired locally or by a follow-up request
+    pub repaired: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<UsageInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub routing: Option<RoutingInfo>,
+}
+
+fn issue(path: &str, message: impl Into<String>) -> ValidationIssue {
+    ValidationIssue {
+        path: if path.is_empty() { "/" } else { path }.to_string(),
+        message: message.into(),
+    }
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"))
+}
+
+// ============================================================================
+// Prompting
+// ============================================================================
+
+/// Check that a schema is usable before sending anything
+pub fn check_schema(schema: &Value) -> Result<(), String> {
+    match schema {
+        Value::Object(_) | Value::Bool(_) => Ok(()),
+        _ => Err("Schema must be a JSON object".to_string()),
+    }
+}
+
+/// Add instructions describing the expected output to the system message
+pub fn add_schema_instructions(messages: &mut Vec<ChatMessage>, schema: &Value) {
+    let text = format!(
+        "Respond only with JSON that matches this JSON schema. Do not include any other text.\n\n{}",
+        serde_json::to_string_pretty(schema).unwrap_or_default()
+    );
+
+    match messages.first_mut() {
+        Some(first) if first.role == "system" => {
+            first.content = format!("{}\n\n{}", first.content, text);
+        }
+        _ => messages.insert(
+            0,
+            ChatMessage {
+                role: "system".to_string(),
+                content: text,
+                name: None,
+                tool_call_id: None,
+                tool_calls: None,
+            },
+        ),
+    }
+}
+
+/// Follow-up message asking the model to fix invalid output
+pub fn repair_prompt(issues: &[ValidationIssue]) -> String {
+    let list = issues
+        .iter()
+        .map(|issue| format!("- {}", issue))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "Your response did not match the JSON schema:\n{}\n\nReply with corrected JSON only.",
+        list
+    )
+}
+
+// ============================================================================
+// Parsing and Repair
+// ============================================================================
+
+/// Parse, repair and validate raw model output against a schema
+pub fn parse_output(schema: &Value, text: &str) -> Result<ParsedOutput, Vec<ValidationIssue>> {
+    let (mut data, mut repaired) = extract_json(text).map_err(|e| vec![issue("", e)])?;
+
+    repaired |= coerce(schema, schema, &mut data);
+
+    let issues = validate(schema, &data);
+    if issues.is_empty() {
+        Ok(ParsedOutput { data, repaired })
+    } else {
+        Err(issues)
+    }
+}
+
+/// Find the JSON value in model output. Returns the value and whether the
+/// text needed cleaning up to parse.
+fn extract_json(text: &str) -> Result<(Value, bool), String> {
+    let trimmed = text.trim();
+    let error = match serde_json::from_str(trimmed) {
+        Ok(value) => return Ok((value, false)),
+        Err(e) => e,
+    };
+
+    let fence = Regex::new(r"(?s)```(?:json|JSON)?\s*(.*?)```").unwrap();
+    let candidate = match fence.captures(trimmed) {
+        Some(captures) => captures.get(1).map_or("", |m| m.as_str()).trim(),
+        None => {
+            let start = trimmed.find(['{', '[']);
+            let end = trimmed.rfind(['}', ']']);
+            match (start, end) {
+                (Some(start), Some(end)) if start < end => &trimmed[start..=end],
+                _ => return Err(format!("Response is not valid JSON: {}", error)),
+            }
+        }
+    };
+
+    if let Ok(value) = serde_json::from_str(candidate) {
+        return Ok((value, true));
+    }
+
+    let trailing_comma = Regex::new(r",(\s*[}\]])").unwrap();
+    let cleaned = trailing_comma.replace_all(candidate, "$1");
+    serde_json::from_str(&cleaned)
+        .map(|value| (value, true))
+        .map_err(|e| format!("Response is not valid JSON: {}", e))
+}
+
+/// Coerce scalar values to the types the schema expects and drop
+/// disallowed properties. Returns whether anything changed.
+fn coerce(root: &Value, schema: &Value, value: &mut Value) -> bool {
+    let Some(schema) = resolve(root, schema).and_then(Value::as_object) else {
+        return false;
+    };
+    let types = schema_types(schema);
+    let mut changed = false;
+
+    if !types.is_empty() && !types.iter().any(|t| matches_type(value, t)) {
+        if let Some(coerced) = types.iter().find_map(|t| coerce_scalar(value, t)) {
+            *value = coerced;
+            changed = true;
+        }
+    }
+
+    match value {
+        Value::Object(map) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            if schema.get("additionalProperties") == Some(&Value::Bool(false)) {
+                let before = map.len();
+                map.retain(|key, _| properties.is_some_and(|p| p.contains_key(key)));
+                changed |= map.len() != before;
+            }
+            if let Some(properties) = properties {
+                for (key, property_schema) in properties {
+                    if let Some(property) = map.get_mut(key) {
+                        changed |= coerce(root, property_schema, property);
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for item in items {
+                    changed |= coerce(root, item_schema, item);
+                }
+            }
+        }
+        _ => {}
+    }
+
+    changed
+}
+
+fn coerce_scalar(value: &Value, expected: &str) -> Option<Value> {
+    match (expected, value) {
+        ("integer", Value::String(s)) => s.trim().parse::<i64>().ok().map(Value::from),
+        ("number", Value::String(s)) => s
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number),
+        ("boolean", Value::String(s)) => match s.trim().to_lowercase().as_str() {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        ("string", Value::Number(n)) => Some(Value::String(n.to_string())),
+        ("string", Value::Bool(b)) => Some(Value::String(b.to_string())),
+        _ => None,
+    }
+}
+
+// ============================================================================
+// Validation
+// ============================================================================
+
+/// Validate a value against a schema, returning every mismatch
+pub fn validate(schema: &Value, value: &Value) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    validate_at(schema, schema, value, "", &mut issues);
+    issues
+}
+
+fn validate_at(
+    root: &Value,
+    schema: &Value,
+    value: &Value,
+    path: &str,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    let schema = match resolve(root, schema) {
+        Some(Value::Bool(true)) => return,
+        Some(Value::Bool(false)) => {
+            issues.push(issue(path, "no value is allowed here"));
+            return;
+        }
+        Some(Value::Object(schema)) => schema,
+        Some(_) => return,
+        None => {
+            issues.push(issue(path, "schema reference could not be resolved"));
+            return;
+        }
+    };
+
+    let types = schema_types(schema);
+    if !types.is_empty() && !types.iter().any(|t| matches_type(value, t)) {
+        issues.push(issue(
+            path,
+            format!(
+                "expected {}, found {}",
+                types.join(" or "),
+                type_name(value)
+            ),
+        ));
+        return;
+    }
+
+    if let Some(Value::Array(allowed)) = schema.get("enum") {
+        if !allowed.contains(value) {
+            issues.push(issue(
+                path,
+                format!("must be one of {}", Value::Array(allowed.clone())),
+            ));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            issues.push(issue(path, format!("must equal {}", expected)));
+        }
+    }
+
+    match value {
+        Value::Object(map) => validate_object(root, schema, map, path, issues),
+        Value::Array(items) => {
+            check_bound(schema, "minItems", items.len(), path, issues);
+            check_bound(schema, "maxItems", items.len(), path, issues);
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    let item_path = child_path(path, &index.to_string());
+                    validate_at(root, item_schema, item, &item_path, issues);
+                }
+            }
+        }
+        Value::String(s) => {
+            let length = s.chars().count();
+            check_bound(schema, "minLength", length, path, issues);
+            check_bound(schema, "maxLength", length, path, issues);
+            if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+                match Regex::new(pattern) {
+                    Ok(re) if !re.is_match(s) => {
+                        issues.push(issue(path, format!("must match pattern {}", pattern)))
+                    }
+                    Ok(_) => {}
+                    Err(_) => issues.push(issue(path, format!("invalid pattern {}", pattern))),
+                }
+            }
+        }
+        Value::Number(n) => {
+            if let Some(n) = n.as_f64() {
+                validate_number(schema, n, path, issues);
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(Value::Array(all)) = schema.get("allOf") {
+        for sub_schema in all {
+            validate_at(root, sub_schema, value, path, issues);
+        }
+    }
+    for keyword in ["anyOf", "oneOf"] {
+        if let Some(Value::Array(options)) = schema.get(keyword) {
+            let matching = options
+                .iter()
+                .filter(|option| {
+                    let mut option_issues = Vec::new();
+                    validate_at(root, option, value, path, &mut option_issues);
+                    option_issues.is_empty()
+                })
+                .count();
+            if matching == 0 {
+                issues.push(issue(path, "does not match any of the allowed schemas"));
+            } else if keyword == "oneOf" && matching > 1 {
+                issues.push(issue(path, "matches more than one of the allowed schemas"));
+            }
+        }
+    }
+}
+
+fn validate_object(
+    root: &Value,
+    schema: &Map<String, Value>,
+    map: &Map<String, Value>,
+    path: &str,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    if let Some(Value::Array(required)) = schema.get("required") {
+        for name in required.iter().filter_map(Value::as_str) {
+            if !map.contains_key(name) {
+                issues.push(issue(
+                    &child_path(path, name),
+                    "required property is missing",
+                ));
+            }
+        }
+    }
+
+    let properties = schema.get("properties").and_then(Value::as_object);
+    for (key, property) in map {
+        let property_path = child_path(path, key);
+        match properties.and_then(|p| p.get(key)) {
+            Some(property_schema) => {
+                validate_at(root, property_schema, property, &property_path, issues)
+            }
+            None => match schema.get("additionalProperties") {
+                Some(Value::Bool(false)) => {
+                    issues.push(issue(&property_path, "unexpected property"))
+                }
+                Some(additional @ Value::Object(_)) => {
+                    validate_at(root, additional, property, &property_path, issues)
+                }
+                _ => {}
+            },
+        }
+    }
+}
+
+fn validate_number(
+    schema: &Map<String, Value>,
+    n: f64,
+    path: &str,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    let limit = |keyword: &str| schema.get(keyword).and_then(Value::as_f64);
+
+    if let Some(min) = limit("minimum").filter(|&min| n < min) {
+        issues.push(issue(path, format!("must be at least {}", min)));
+    }
+    if let Some(max) = limit("maximum").filter(|&max| n > max) {
+        issues.push(issue(path, format!("must be at most {}", max)));
+    }
+    if let Some(min) = limit("exclusiveMinimum").filter(|&min| n <= min) {
+        issues.push(issue(path, format!("must be greater than {}", min)));
+    }
+    if let Some(max) = limit("exclusiveMaximum").filter(|&max| n >= max) {
+        issues.push(issue(path, format!("must be less than {}", max)));
+    }
+}
+
+fn check_bound(
+    schema: &Map<String, Value>,
+    keyword: &str,
+    actual: usize,
+    path: &str,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    let Some(bound) = schema.get(keyword).and_then(Value::as_u64) else {
+        return;
+    };
+    let bound = bound as usize;
+    let (violated, relation) = if keyword.starts_with("min") {
+        (actual < bound, "at least")
+    } else {
+        (actual > bound, "at most")
+    };
+    if violated {
+        let unit = if keyword.ends_with("Items") {
+            "items"
+        } else {
+            "characters"
+        };
+        issues.push(issue(
+            path,
+            format!("must have {} {} {}", relation, bound, unit),
+        ));
+    }
+}
+
+/// Follow a local `$ref`, if the schema is one
+fn resolve<'a>(root: &'a Value, schema: &'a Value) -> Option<&'a Value> {
+    match schema.get("$ref").and_then(Value::as_str) {
+        Some(reference) => reference
+            .strip_prefix('#')
+            .and_then(|pointer| root.pointer(pointer)),
+        None => Some(schema),
+    }
+}
+
+fn schema_types(schema: &Map<String, Value>) -> Vec<&str> {
+    match schema.get("type") {
+        Some(Value::String(t)) => vec![t.as_str()],
+        Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn matches_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn task_schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "title": { "type": "string", "minLength": 1 },
+                "priority": { "type": "integer", "minimum": 1, "maximum": 5 },
+                "done": { "type": "boolean" },
+                "tags": { "type": "array", "items": { "type": "string" }, "maxItems": 3 },
+                "status": { "enum": ["todo", "doing", "done"] }
+            },
+            "required": ["title", "priority"],
+            "additionalProperties": false
+        })
+    }
+
+    #[test]
+    fn test_valid_output_passes_unchanged() {
+        let output = parse_output(
+            &task_schema(),
+            r#"{"title": "Write docs", "priority": 2, "tags": ["docs"]}"#,
+        )
+        .unwrap();
+        assert!(!output.repaired);
+        assert_eq!(output.data["title"], "Write docs");
+    }
+
+    #[test]
+    fn test_validation_reports_every_issue_with_path() {
+        let issues = validate(
+            &task_schema(),
+            &json!({
+                "priority": 9,
+                "tags": ["a", 2, "c", "d"],
+                "status": "blocked",
+                "extra": true
+            }),
+        );
+        let rendered: Vec<String> = issues.iter().map(|i| i.to_string()).collect();
+
+        assert!(rendered.contains(&"/title: required property is missing".to_string()));
+        assert!(rendered.contains(&"/priority: must be at most 5".to_string()));
+        assert!(rendered.contains(&"/tags: must have at most 3 items".to_string()));
+        assert!(rendered.contains(&"/tags/1: expected string, found number".to_string()));
+        assert!(rendered
+            .iter()
+            .any(|r| r.starts_with("/status: must be one of")));
+        assert!(rendered.contains(&"/extra: unexpected property".to_string()));
+    }
+
+    #[test]
+    fn test_repairs_fences_prose_and_trailing_commas() {
+        let text = "Here you go:\n```json\n{\"title\": \"A\", \"priority\": 1,}\n```";
+        let output = parse_output(&task_schema(), text).unwrap();
+        assert!(output.repaired);
+        assert_eq!(output.data, json!({"title": "A", "priority": 1}));
+
+        let text = "Sure! {\"title\": \"B\", \"priority\": 3} Hope that helps.";
+        assert_eq!(
+            parse_output(&task_schema(), text).unwrap().data["title"],
+            "B"
+        );
+    }
+
+    #[test]
+    fn test_coerces_scalars_and_drops_extra_properties() {
+        let output = parse_output(
+            &task_schema(),
+            r#"{"title": 42, "priority": "3", "done": "true", "note": "extra"}"#,
+        )
+        .unwrap();
+        assert!(output.repaired);
+        assert_eq!(
+            output.data,
+            json!({"title": "42", "priority": 3, "done": true})
+        );
+    }
+
+    #[test]
+    fn test_unparseable_output_is_a_root_issue() {
+        let issues = parse_output(&task_schema(), "I can't help with that.").unwrap_err();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].path, "/");
+        assert!(issues[0].message.contains("not valid JSON"));
+    }
+
+    #[test]
+    fn test_refs_and_combinators() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "items": { "type": "array", "items": { "$ref": "#/$defs/item" } },
+                "id": { "anyOf": [{ "type": "string" }, { "type": "integer" }] }
+            },
+            "$defs": {
+                "item": {
+                    "type": "object",
+                    "properties": { "name": { "type": "string", "pattern": "^[a-z]+$" } },
+                    "required": ["name"]
+                }
+            }
+        });
+
+        assert!(validate(&schema, &json!({"items": [{"name": "ok"}], "id": 3})).is_empty());
+
+        let issues = validate(
+            &schema,
+            &json!({"items": [{"name": "Bad1"}, {}], "id": [1]}),
+        );
+        let paths: Vec<&str> = issues.iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(paths, vec!["/id", "/items/0/name", "/items/1/name"]);
+    }
+
+    #[test]
+    fn test_schema_instructions_extend_system_message() {
+        let mut messages = vec![ChatMessage {
+            role: "user".to_string(),
+            content: "Plan my day".to_string(),
+            name: None,
+            tool_call_id: None,
+            tool_calls: None,
+        }];
+        add_schema_instructions(&mut messages, &task_schema());
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].role, "system");
+        assert!(messages[0].content.contains("\"required\""));
+
+        add_schema_instructions(&mut messages, &json!({"type": "string"}));
+        assert_eq!(messages.len(), 2);
+
+        assert!(check_schema(&json!("object")).is_err());
+    }
+}
@@ -0,0 +1,413 @@
+// Table Documents - Structured tables imported from databases
+//
+// A table document keeps a database's rows with typed columns instead of
+// flattening them into a Markdown table, so it can be sorted and searched.
+// The backend owns the rows and hands the frontend one page at a time, so
+// databases with many thousands of rows stay usable.
+//
+// Column types are inferred when a table is created from CSV: a column is
+// a number, checkbox, date or URL column if every non-empty value parses as
+// one, and text otherwise. Dates are stored as ISO 8601.
+//
+// Stored as {name}.midtable in the workspace
+// Format:
+// {
+//   "version": 1,
+//   "title": "Reading list",
+//   "columns": [{ "name": "Title", "type": "text" }, { "name": "Pages", "type": "number" }],
+//   "rows": [["Middlemarch", 880], ["Persuasion", null]]
+// }
+
+use chrono::{NaiveDate, NaiveDateTime};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use super::import_security::sanitize_csv_cell;
+
+pub const TABLE_EXTENSION: &str = "midtable";
+
+const TABLE_VERSION: u32 = 1;
+
+/// Rows returned when a query doesn't say how many
+const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// Date formats recognized in CSV values, Notion's first
+const DATE_FORMATS: &[&str] = &["%B %d, %Y", "%Y-%m-%d", "%Y/%m/%d", "%m/%d/%Y"];
+const DATE_TIME_FORMATS: &[&str] = &["%B %d, %Y %I:%M %p", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"];
+
+lazy_static! {
+    /// The most recently queried table, so paging through one doesn't
+    /// re-read the file each time
+    static ref LAST_TABLE: Mutex<Option<(PathBuf, SystemTime, Arc<TableDocument>)>> =
+        Mutex::new(None);
+}
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ColumnType {
+    Text,
+    Number,
+    Checkbox,
+    Date,
+    Url,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableColumn {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub column_type: ColumnType,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableDocument {
+    pub version: u32,
+    pub title: String,
+    pub columns: Vec<TableColumn>,
+    /// One value per column; null for empty cells
+    pub rows: Vec<Vec<Value>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableQuery {
+    /// Index of the column to sort by
+    pub sort_column: Option<usize>,
+    #[serde(default)]
+    pub descending: bool,
+    /// Only rows with a cell containing this, ignoring case
+    pub search: Option<String>,
+    #[serde(default)]
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+/// One page of a table's rows
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TablePage {
+    pub title: String,
+    pub columns: Vec<TableColumn>,
+    pub rows: Vec<Vec<Value>>,
+    /// Rows matching the query, across all pages
+    pub total: usize,
+}
+
+// ============================================================================
+// Creating
+// ============================================================================
+
+impl TableDocument {
+    /// Build a table from CSV with a header row, inferring column types
+    pub fn from_csv(title: &str, csv_content: &str) -> Result<Self, String> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(true)
+            .flexible(true)
+            .from_reader(csv_content.as_bytes());
+
+        let names: Vec<String> = reader
+            .headers()
+            .map_err(|e| format!("Failed to read CSV: {}", e))?
+            .iter()
+            .map(|name| name.trim_start_matches('\u{feff}').to_string())
+            .collect();
+        let mut cells: Vec<Vec<String>> = Vec::new();
+        for record in reader.records() {
+            let record = record.map_err(|e| format!("Failed to read CSV: {}", e))?;
+            let mut row: Vec<String> = record.iter().map(|c| c.trim().to_string()).collect();
+            row.resize(names.len(), String::new());
+            cells.push(row);
+        }
+
+        let columns: Vec<TableColumn> = names
+            .into_iter()
+            .enumerate()
+            .map(|(i, name)| TableColumn {
+                column_type: infer_type(cells.iter().map(|row| row[i].as_str())),
+                name,
+            })
+            .collect();
+        let rows = cells
+            .into_iter()
+            .map(|row| {
+                row.iter()
+                    .zip(&columns)
+                    .map(|(cell, column)| typed_value(cell, column.column_type))
+                    .collect()
+            })
+            .collect();
+
+        Ok(Self {
+            version: TABLE_VERSION,
+            title: title.to_string(),
+            columns,
+            rows,
+        })
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|e| format!("Failed to serialize table: {}", e))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let json = fs::read_to_string(path).map_err(|e| format!("Failed to read table: {}", e))?;
+        serde_json::from_str(&json).map_err(|e| format!("Failed to parse table: {}", e))
+    }
+
+    // ========================================================================
+    // Querying
+    // ========================================================================
+
+    pub fn query(&self, query: &TableQuery) -> TablePage {
+        let needle = query
+            .search
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_lowercase);
+        let mut rows: Vec<&Vec<Value>> = self
+            .rows
+            .iter()
+            .filter(|row| {
+                needle.as_ref().map_or(true, |needle| {
+                    row.iter()
+                        .any(|cell| cell_text(cell).to_lowercase().contains(needle))
+                })
+            })
+            .collect();
+
+        if let Some(column) = query.sort_column.filter(|&c| c < self.columns.len()) {
+            // Empty cells go last whichever way the column is sorted
+            rows.sort_by(|a, b| match (&a[column], &b[column]) {
+                (Value::Null, Value::Null) => Ordering::Equal,
+                (Value::Null, _) => Ordering::Greater,
+                (_, Value::Null) => Ordering::Less,
+                (a, b) if query.descending => compare(b, a),
+                (a, b) => compare(a, b),
+            });
+        }
+
+        TablePage {
+            title: self.title.clone(),
+            columns: self.columns.clone(),
+            total: rows.len(),
+            rows: rows
+                .into_iter()
+                .skip(query.offset)
+                .take(query.limit.unwrap_or(DEFAULT_PAGE_SIZE))
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+/// Query a table document, reusing the last one read if it hasn't changed
+pub fn query_table(path: &Path, query: &TableQuery) -> Result<TablePage, String> {
+    let modified = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map_err(|e| format!("Failed to read table: {}", e))?;
+
+    let mut last = LAST_TABLE.lock().unwrap();
+    let table = match last.as_ref() {
+        Some((cached, at, table)) if cached == path && *at == modified => table.clone(),
+        _ => {
+            let table = Arc::new(TableDocument::load(path)?);
+            *last = Some((path.to_path_buf(), modified, table.clone()));
+            table
+        }
+    };
+    drop(last);
+    Ok(table.query(query))
+}
+
+// ============================================================================
+// Values
+// ============================================================================
+
+fn infer_type<'a>(values: impl Iterator<Item = &'a str> + Clone) -> ColumnType {
+    let mut present = values.filter(|v| !v.is_empty()).peekable();
+    if present.peek().is_none() {
+        return ColumnType::Text;
+    }
+    [
+        ColumnType::Checkbox,
+        ColumnType::Number,
+        ColumnType::Date,
+        ColumnType::Url,
+    ]
+    .into_iter()
+    .find(|&column_type| {
+        present
+            .clone()
+            .all(|v| parse_cell(v, column_type).is_some())
+    })
+    .unwrap_or(ColumnType::Text)
+}
+
+/// A cell as the column's type, or as text if it doesn't parse
+fn typed_value(cell: &str, column_type: ColumnType) -> Value {
+    if cell.is_empty() {
+        return Value::Null;
+    }
+    parse_cell(cell, column_type).unwrap_or_else(|| Value::String(sanitize_csv_cell(cell)))
+}
+
+fn parse_cell(cell: &str, column_type: ColumnType) -> Option<Value> {
+    match column_type {
+        ColumnType::Text => None,
+        ColumnType::Checkbox => match cell.to_lowercase().as_str() {
+            "yes" | "true" => Some(Value::Bool(true)),
+            "no" | "false" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        // Leading zeros mean an identifier, like a postcode, not a number
+        ColumnType::Number
+            if cell.len() > 1 && cell.starts_with('0') && !cell.starts_with("0.") =>
+        {
+            None
+        }
+        ColumnType::Number => cell
+            .parse::<f64>()
+            .ok()
+            .filter(|n| n.is_finite())
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number),
+        ColumnType::Date => parse_date(cell).map(Value::String),
+        ColumnType::Url => (cell.starts_with("http://") || cell.starts_with("https://"))
+            .then(|| Value::String(cell.to_string())),
+    }
+}
+
+fn parse_date(cell: &str) -> Option<String> {
+    DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(cell, format).ok())
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .or_else(|| {
+            DATE_TIME_FORMATS
+                .iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(cell, format).ok())
+                .map(|time| time.format("%Y-%m-%dT%H:%M").to_string())
+        })
+}
+
+fn cell_text(cell: &Value) -> String {
+    match cell {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn compare(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a
+            .as_f64()
+            .partial_cmp(&b.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        (a, b) => cell_text(a)
+            .to_lowercase()
+            .cmp(&cell_text(b).to_lowercase()),
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const CSV: &str = "Name,Pages,Read,Finished,Link,Zip\n\
+        Middlemarch,880,Yes,\"January 5, 2024\",https://example.com/m,02134\n\
+        Persuasion,,No,2023-11-02,,10001\n\
+        =cmd,249,No,,https://example.com/e,90210\n";
+
+    #[test]
+    fn test_from_csv_infers_types() {
+        let table = TableDocument::from_csv("Books", CSV).unwrap();
+        let types: Vec<ColumnType> = table.columns.iter().map(|c| c.column_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                ColumnType::Text,
+                ColumnType::Number,
+                ColumnType::Checkbox,
+                ColumnType::Date,
+                ColumnType::Url,
+                ColumnType::Text,
+            ]
+        );
+        assert_eq!(
+            table.rows[0],
+            vec![
+                json!("Middlemarch"),
+                json!(880.0),
+                json!(true),
+                json!("2024-01-05"),
+                json!("https://example.com/m"),
+                json!("02134"),
+            ]
+        );
+        assert_eq!(table.rows[1][1], Value::Null);
+        // Text cells can't carry spreadsheet formulas
+        assert_eq!(table.rows[2][0], json!("'=cmd"));
+    }
+
+    #[test]
+    fn test_query_sorts_searches_and_pages() {
+        let table = TableDocument::from_csv("Books", CSV).unwrap();
+        let names = |page: &TablePage| -> Vec<String> {
+            page.rows.iter().map(|row| cell_text(&row[0])).collect()
+        };
+
+        let page = table.query(&TableQuery {
+            sort_column: Some(1),
+            descending: true,
+            ..Default::default()
+        });
+        assert_eq!(names(&page), vec!["Middlemarch", "'=cmd", "Persuasion"]);
+
+        let page = table.query(&TableQuery {
+            sort_column: Some(3),
+            offset: 1,
+            limit: Some(1),
+            ..Default::default()
+        });
+        assert_eq!(page.total, 3);
+        assert_eq!(names(&page), vec!["Middlemarch"]);
+
+        let page = table.query(&TableQuery {
+            search: Some("EXAMPLE.com/e".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(page.total, 1);
+    }
+
+    #[test]
+    fn test_query_table_file() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("Books.midtable");
+        let table = TableDocument::from_csv("Books", CSV).unwrap();
+        fs::write(&path, table.to_json().unwrap()).unwrap();
+
+        let page = query_table(&path, &TableQuery::default()).unwrap();
+        assert_eq!(page.title, "Books");
+        assert_eq!(page.rows.len(), 3);
+    }
+}
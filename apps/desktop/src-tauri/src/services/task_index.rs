@@ -0,0 +1,467 @@
+// Task Index - Checkbox items across a workspace's documents
+//
+// Task list items in .midlight documents and `- [ ]` / `- [x]` lines in
+// Markdown files are collected with their due dates and tags, so a task
+// board can be shown without reading every document. Each document's tasks
+// are stored with the modification time they were read at; saves from the
+// editor update a document straight away, and queries re-read only the
+// documents whose modification time changed since (edits from other apps,
+// new and deleted files).
+//
+// Due dates are written in the task's text as `due:2026-03-14`,
+// `@due(2026-03-14)` or `📅 2026-03-14`. Tags are #hashtags in the text.
+//
+// Index is stored in the workspace metadata store (.midlight/metadata.db)
+
+use crate::services::document_chunks::resolve_content;
+use crate::services::metadata_store::{self, DocumentTasks};
+use crate::services::path_glob::PathGlob;
+use crate::services::rag_service::document_tags;
+use crate::services::workspace_paths::document_key;
+use chrono::NaiveDate;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use walkdir::WalkDir;
+
+lazy_static! {
+    static ref MARKDOWN_TASK: Regex = Regex::new(r"^\s*[-*+]\s+\[([ xX])\]\s+(.*)$").unwrap();
+    static ref DUE: Regex =
+        Regex::new(r"(?:\bdue:\s*|@due\(\s*|📅\s*)(\d{4}-\d{2}-\d{2})\)?").unwrap();
+    /// Serializes read-modify-write cycles of the index
+    static ref INDEX_LOCK: Mutex<()> = Mutex::new(());
+}
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Task {
+    /// Document the task is in, relative to the workspace root
+    pub document: String,
+    /// Position of the task among the document's tasks
+    pub index: usize,
+    /// Text of the task, without its due date
+    pub text: String,
+    pub done: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    #[default]
+    All,
+    Open,
+    Done,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskQuery {
+    #[serde(default)]
+    pub status: TaskStatus,
+    /// Only tasks due on or before this date
+    #[serde(default)]
+    pub due_before: Option<NaiveDate>,
+    /// Only tasks due on or after this date
+    #[serde(default)]
+    pub due_after: Option<NaiveDate>,
+    /// Document path or glob, e.g. `Projects/**`
+    #[serde(default)]
+    pub document: Option<String>,
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+// ============================================================================
+// Index
+// ============================================================================
+
+pub struct TaskIndex {
+    workspace_root: PathBuf,
+}
+
+impl TaskIndex {
+    pub fn new(workspace_root: &Path) -> Self {
+        Self {
+            workspace_root: workspace_root.to_path_buf(),
+        }
+    }
+
+    /// Re-read one document's tasks, or drop them if it no longer exists
+    pub fn update_document(&self, relative: &str) -> Result<(), String> {
+        let _lock = INDEX_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let key = document_key(relative);
+        let tasks = self.read_document(&key);
+        metadata_store::open(&self.workspace_root)?
+            .update_document_tasks(&BTreeMap::from([(key, tasks)]))
+    }
+
+    /// Tasks matching a query, sorted by due date and then by document
+    pub fn query(&self, query: &TaskQuery) -> Result<Vec<Task>, String> {
+        let document = match &query.document {
+            Some(pattern) => Some(PathGlob::new(pattern)?),
+            None => None,
+        };
+        let tag = query
+            .tag
+            .as_deref()
+            .map(|t| t.trim_start_matches('#').to_lowercase());
+
+        let mut tasks: Vec<Task> = self
+            .refresh()?
+            .into_values()
+            .flat_map(|document| document.tasks)
+            .filter(|task| match query.status {
+                TaskStatus::All => true,
+                TaskStatus::Open => !task.done,
+                TaskStatus::Done => task.done,
+            })
+            .filter(|task| {
+                query
+                    .due_before
+                    .map_or(true, |d| task.due.is_some_and(|due| due <= d))
+            })
+            .filter(|task| {
+                query
+                    .due_after
+                    .map_or(true, |d| task.due.is_some_and(|due| due >= d))
+            })
+            .filter(|task| {
+                document
+                    .as_ref()
+                    .map_or(true, |g| g.is_match(&task.document))
+            })
+            .filter(|task| tag.as_ref().map_or(true, |t| task.tags.contains(t)))
+            .collect();
+
+        tasks.sort_by(|a, b| {
+            (a.due.is_none(), a.due, &a.document, a.index).cmp(&(
+                b.due.is_none(),
+                b.due,
+                &b.document,
+                b.index,
+            ))
+        });
+        if let Some(limit) = query.limit {
+            tasks.truncate(limit);
+        }
+        Ok(tasks)
+    }
+
+    /// Bring the index up to date with the documents on disk
+    fn refresh(&self) -> Result<BTreeMap<String, DocumentTasks>, String> {
+        let _lock = INDEX_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let store = metadata_store::open(&self.workspace_root)?;
+        let mut documents = store.document_tasks()?;
+        let on_disk = self.documents();
+        let mut updates = BTreeMap::new();
+
+        for key in documents.keys() {
+            if !on_disk.contains_key(key) {
+                updates.insert(key.clone(), None);
+            }
+        }
+        for (key, modified) in on_disk {
+            if documents.get(&key).map(|d| d.modified) != Some(modified) {
+                let tasks = self.read_document(&key);
+                updates.insert(key, tasks);
+            }
+        }
+
+        if !updates.is_empty() {
+            store.update_document_tasks(&updates)?;
+            for (key, tasks) in updates {
+                match tasks {
+                    Some(tasks) => documents.insert(key, tasks),
+                    None => documents.remove(&key),
+                };
+            }
+        }
+        Ok(documents)
+    }
+
+    /// Documents in the workspace with their modification times
+    fn documents(&self) -> HashMap<String, u64> {
+        WalkDir::new(&self.workspace_root)
+            .min_depth(1)
+            .into_iter()
+            .filter_entry(|e| !e.file_name().to_string_lossy().starts_with('.'))
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter_map(|e| {
+                let relative = e.path().strip_prefix(&self.workspace_root).ok()?;
+                let key = document_key(&relative.to_string_lossy());
+                if !key.ends_with(".midlight") && !key.ends_with(".md") {
+                    return None;
+                }
+                Some((key, modified_millis(&e.metadata().ok()?)))
+            })
+            .collect()
+    }
+
+    fn read_document(&self, key: &str) -> Option<DocumentTasks> {
+        let path = self.workspace_root.join(key);
+        let modified = modified_millis(&fs::metadata(&path).ok()?);
+        let content = fs::read_to_string(&path).ok()?;
+        let tasks = if key.ends_with(".md") {
+            markdown_tasks(key, &content)
+        } else {
+            let mut document: Value = serde_json::from_str(&content).ok()?;
+            resolve_content(&path, &mut document);
+            document_tasks(key, document.get("content").unwrap_or(&Value::Null))
+        };
+        Some(DocumentTasks { modified, tasks })
+    }
+}
+
+fn modified_millis(metadata: &fs::Metadata) -> u64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+// ============================================================================
+// Parsing
+// ============================================================================
+
+fn task(document: &str, index: usize, text: &str, done: bool) -> Task {
+    let due = DUE
+        .captures(text)
+        .and_then(|caps| NaiveDate::parse_from_str(&caps[1], "%Y-%m-%d").ok());
+    let text = match due {
+        Some(_) => DUE
+            .replace(text, "")
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" "),
+        None => text.trim().to_string(),
+    };
+    Task {
+        document: document.to_string(),
+        index,
+        tags: document_tags(&text),
+        text,
+        done,
+        due,
+    }
+}
+
+/// Tasks in a Markdown file, skipping fenced code blocks
+fn markdown_tasks(document: &str, content: &str) -> Vec<Task> {
+    let mut tasks = Vec::new();
+    let mut in_fence = false;
+    for line in content.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        if let Some(caps) = MARKDOWN_TASK.captures(line) {
+            tasks.push(task(document, tasks.len(), &caps[2], &caps[1] != " "));
+        }
+    }
+    tasks
+}
+
+/// Tasks in a Tiptap document, including nested task lists
+fn document_tasks(document: &str, content: &Value) -> Vec<Task> {
+    let mut tasks = Vec::new();
+    collect_tasks(document, content, &mut tasks);
+    tasks
+}
+
+fn collect_tasks(document: &str, node: &Value, tasks: &mut Vec<Task>) {
+    if node.get("type").and_then(Value::as_str) == Some("taskItem") {
+        let done = node
+            .pointer("/attrs/checked")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        // The item's own text is in its paragraphs; nested lists are their own tasks
+        let mut text = String::new();
+        for child in node
+            .get("content")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            if child.get("type").and_then(Value::as_str) == Some("paragraph") {
+                append_text(child, &mut text);
+            }
+        }
+        tasks.push(task(document, tasks.len(), &text, done));
+    }
+
+    for child in node
+        .get("content")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        collect_tasks(document, child, tasks);
+    }
+}
+
+fn append_text(node: &Value, text: &mut String) {
+    if let Some(t) = node.get("text").and_then(Value::as_str) {
+        text.push_str(t);
+    }
+    for child in node
+        .get("content")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        append_text(child, text);
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, day).unwrap()
+    }
+
+    fn task_item(text: &str, checked: bool, nested: Option<Value>) -> Value {
+        let mut content = vec![json!({
+            "type": "paragraph",
+            "content": [{ "type": "text", "text": text }]
+        })];
+        content.extend(nested);
+        json!({ "type": "taskItem", "attrs": { "checked": checked }, "content": content })
+    }
+
+    fn write_document(root: &Path, path: &str, items: Vec<Value>) {
+        let document = json!({
+            "version": 1,
+            "content": { "type": "doc", "content": [{ "type": "taskList", "content": items }] }
+        });
+        fs::create_dir_all(root.join(path).parent().unwrap()).unwrap();
+        fs::write(root.join(path), document.to_string()).unwrap();
+    }
+
+    #[test]
+    fn test_parse_tasks() {
+        let markdown = "# Plan\n\
+                        - [ ] Write draft due:2026-03-10 #writing\n\
+                        - [x] Book venue @due(2026-03-02)\n\
+                        ```\n- [ ] not a task\n```\n\
+                        * [ ] Call 📅 2026-03-20\n\
+                        - plain item";
+        let tasks = markdown_tasks("Plan.md", markdown);
+        assert_eq!(tasks.len(), 3);
+        assert_eq!(tasks[0].text, "Write draft #writing");
+        assert_eq!(tasks[0].due, Some(date(10)));
+        assert_eq!(tasks[0].tags, vec!["writing"]);
+        assert!(tasks[1].done);
+        assert_eq!(tasks[1].due, Some(date(2)));
+        assert_eq!(tasks[2].text, "Call");
+        assert_eq!(tasks[2].index, 2);
+
+        let nested = json!({ "type": "taskList", "content": [task_item("Child", true, None)] });
+        let content = json!({
+            "type": "doc",
+            "content": [{ "type": "taskList", "content": [task_item("Parent #home", false, Some(nested))] }]
+        });
+        let tasks = document_tasks("Home.midlight", &content);
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].text, "Parent #home");
+        assert!(!tasks[0].done);
+        assert_eq!(tasks[1].text, "Child");
+        assert!(tasks[1].done);
+    }
+
+    #[test]
+    fn test_query_and_incremental_updates() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        write_document(
+            root,
+            "Projects/Launch.midlight",
+            vec![
+                task_item("Ship it due:2026-03-12 #launch", false, None),
+                task_item("Plan due:2026-03-01", true, None),
+            ],
+        );
+        fs::write(
+            root.join("Inbox.md"),
+            "- [ ] Reply to Sam due:2026-03-05\n- [ ] Someday",
+        )
+        .unwrap();
+
+        let index = TaskIndex::new(root);
+        let all = index.query(&TaskQuery::default()).unwrap();
+        let texts: Vec<&str> = all.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec!["Plan", "Reply to Sam", "Ship it #launch", "Someday"]
+        );
+
+        let open_due = index
+            .query(&TaskQuery {
+                status: TaskStatus::Open,
+                due_before: Some(date(10)),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(open_due.len(), 1);
+        assert_eq!(open_due[0].document, "Inbox.md");
+
+        let tagged = index
+            .query(&TaskQuery {
+                document: Some("Projects/**".to_string()),
+                tag: Some("#Launch".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(tagged.len(), 1);
+
+        // A save from the editor is picked up straight away
+        write_document(
+            root,
+            "Projects/Launch.midlight",
+            vec![task_item("Ship it", true, None)],
+        );
+        index.update_document("Projects/Launch.midlight").unwrap();
+        let done = index
+            .query(&TaskQuery {
+                status: TaskStatus::Done,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(done.len(), 1);
+        assert_eq!(done[0].text, "Ship it");
+
+        // Deleted documents drop out on the next query
+        fs::remove_file(root.join("Inbox.md")).unwrap();
+        assert_eq!(index.query(&TaskQuery::default()).unwrap().len(), 1);
+        assert!(root.join(".midlight/metadata.db").exists());
+    }
+}
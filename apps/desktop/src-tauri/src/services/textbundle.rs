@@ -0,0 +1,506 @@
+// TextBundle - Import and export of .textbundle and .textpack documents
+//
+// A TextBundle is a folder holding one Markdown document (text.md), its
+// images and other files (assets/) and an info.json describing it. A
+// TextPack is the same folder zipped. Typora, iA Writer, Ulysses and Bear
+// read and write them, which makes them a good way to move a single
+// document with its images between editors.
+//
+// On import, images the text refers to under assets/ are handed back to the
+// caller to store, and their references replaced with the stored ones. On
+// export, stored images (midlight://img-...) and embedded data URLs are
+// copied into assets/ and the text refers to them there.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+use crate::services::image_manager::mime_for_extension;
+use crate::services::markdown_convert::{markdown_to_tiptap, tiptap_to_markdown, MarkdownOptions};
+
+/// Largest bundle file read on import, unpacked
+const MAX_ENTRY_SIZE: u64 = 50 * 1024 * 1024;
+
+/// Characters escaped in asset paths written to the text
+const ASSET_PATH: &AsciiSet = &CONTROLS.add(b' ').add(b'(').add(b')').add(b'<').add(b'>');
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Default)]
+pub struct TextBundle {
+    /// The Markdown text
+    pub text: String,
+    /// Files under assets/, by bundle-relative path ("assets/photo.png")
+    pub assets: Vec<BundleAsset>,
+    /// App that wrote the bundle, from info.json
+    pub creator: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BundleAsset {
+    pub path: String,
+    pub data: Vec<u8>,
+}
+
+impl BundleAsset {
+    pub fn mime_type(&self) -> &'static str {
+        let extension = Path::new(&self.path)
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        mime_for_extension(&extension)
+    }
+
+    pub fn is_image(&self) -> bool {
+        self.mime_type().starts_with("image/")
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextBundleImportResult {
+    pub tiptap_json: Value,
+    pub title: String,
+    pub image_count: usize,
+    /// Asset references in the text that aren't in the bundle
+    pub missing_assets: Vec<String>,
+}
+
+// ============================================================================
+// Reading
+// ============================================================================
+
+/// Whether a path names a zipped bundle
+pub fn is_textpack(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("textpack"))
+}
+
+/// Read a .textbundle folder or .textpack archive
+pub fn read_bundle(path: &Path) -> Result<TextBundle, String> {
+    let files = if is_textpack(path) {
+        read_archive(path)?
+    } else {
+        read_folder(path)?
+    };
+
+    let info: Value = files
+        .get("info.json")
+        .map(|data| serde_json::from_slice(data))
+        .transpose()
+        .map_err(|e| format!("Failed to parse info.json: {}", e))?
+        .unwrap_or(Value::Null);
+
+    // text.md by convention, but the extension follows the text's type
+    let text = files
+        .iter()
+        .filter(|(name, _)| !name.contains('/') && name.starts_with("text."))
+        .min_by_key(|(name, _)| !name.ends_with(".md"))
+        .map(|(_, data)| String::from_utf8_lossy(data).into_owned())
+        .ok_or_else(|| "Invalid TextBundle: no text file".to_string())?;
+
+    let mut assets: Vec<BundleAsset> = files
+        .into_iter()
+        .filter(|(name, _)| name.starts_with("assets/"))
+        .map(|(path, data)| BundleAsset { path, data })
+        .collect();
+    assets.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(TextBundle {
+        text,
+        assets,
+        creator: info
+            .get("creatorIdentifier")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+    })
+}
+
+fn read_folder(path: &Path) -> Result<HashMap<String, Vec<u8>>, String> {
+    if !path.is_dir() {
+        return Err(format!("Not a TextBundle: {}", path.display()));
+    }
+    let mut files = HashMap::new();
+    for entry in walkdir::WalkDir::new(path)
+        .follow_links(false)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let Ok(relative) = entry.path().strip_prefix(path) else {
+            continue;
+        };
+        if entry.metadata().map_or(true, |m| m.len() > MAX_ENTRY_SIZE) {
+            continue;
+        }
+        let data = fs::read(entry.path())
+            .map_err(|e| format!("Failed to read {}: {}", entry.path().display(), e))?;
+        files.insert(relative.to_string_lossy().replace('\\', "/"), data);
+    }
+    Ok(files)
+}
+
+/// Files in a TextPack, relative to the bundle folder inside it
+fn read_archive(path: &Path) -> Result<HashMap<String, Vec<u8>>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open TextPack: {}", e))?;
+    let mut archive =
+        ZipArchive::new(file).map_err(|e| format!("Failed to read TextPack: {}", e))?;
+
+    let mut entries = Vec::new();
+    for i in 0..archive.len() {
+        let mut entry = archive
+            .by_index(i)
+            .map_err(|e| format!("Failed to read TextPack: {}", e))?;
+        let Some(name) = entry.enclosed_name() else {
+            continue;
+        };
+        if entry.is_dir() || entry.size() > MAX_ENTRY_SIZE {
+            continue;
+        }
+        let name = name.to_string_lossy().replace('\\', "/");
+        let mut data = Vec::new();
+        entry
+            .by_ref()
+            .take(MAX_ENTRY_SIZE)
+            .read_to_end(&mut data)
+            .map_err(|e| format!("Failed to read {}: {}", name, e))?;
+        entries.push((name, data));
+    }
+
+    // Usually the archive holds the bundle folder, but some apps zip its
+    // contents directly. The bundle starts where info.json is.
+    let prefix = entries
+        .iter()
+        .filter_map(|(name, _)| name.strip_suffix("info.json"))
+        .filter(|prefix| prefix.is_empty() || prefix.ends_with('/'))
+        .min_by_key(|prefix| prefix.len())
+        .unwrap_or("")
+        .to_string();
+
+    Ok(entries
+        .into_iter()
+        .filter_map(|(name, data)| Some((name.strip_prefix(&prefix)?.to_string(), data)))
+        .collect())
+}
+
+// ============================================================================
+// Import
+// ============================================================================
+
+/// Convert the bundle's text to Tiptap JSON. `stored` maps asset paths to
+/// the references they were stored under. Returns the document and asset
+/// references that aren't in the bundle.
+pub fn bundle_to_tiptap(
+    bundle: &TextBundle,
+    stored: &HashMap<String, String>,
+) -> (Value, Vec<String>) {
+    let mut doc = markdown_to_tiptap(&bundle.text, MarkdownOptions::default());
+    let mut missing = Vec::new();
+    for_each_image(&mut doc, &mut |attrs| {
+        let Some(src) = attrs.get("src").and_then(Value::as_str) else {
+            return;
+        };
+        let path = asset_path(src);
+        if !path.starts_with("assets/") {
+            return;
+        }
+        match stored.get(&path) {
+            Some(reference) => {
+                attrs.insert("src".to_string(), json!(reference));
+            }
+            None if !missing.contains(&path) => missing.push(path),
+            None => {}
+        }
+    });
+    (doc, missing)
+}
+
+/// A reference in the text as a bundle-relative path
+fn asset_path(src: &str) -> String {
+    let decoded = percent_decode_str(src).decode_utf8_lossy();
+    decoded.trim_start_matches("./").to_string()
+}
+
+/// The document title: its first heading, else the bundle's name
+pub fn bundle_title(doc: &Value, path: &Path) -> String {
+    doc.get("content")
+        .and_then(Value::as_array)
+        .and_then(|blocks| {
+            blocks
+                .iter()
+                .find(|b| b.get("type").and_then(Value::as_str) == Some("heading"))
+        })
+        .map(crate::services::markdown_convert::extract_text_content)
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| {
+            path.file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_else(|| "Untitled".to_string())
+        })
+}
+
+// ============================================================================
+// Export
+// ============================================================================
+
+/// Build a bundle from a Tiptap document, copying its images into assets/.
+/// Stored images are looked up in the workspace; without one they, like
+/// remote images, stay as they are.
+pub fn bundle_from_tiptap(doc: &Value, workspace_root: Option<&Path>) -> TextBundle {
+    let images_dir = workspace_root.map(|root| root.join(".midlight").join("images"));
+    let mut doc = doc.clone();
+    let mut assets: Vec<BundleAsset> = Vec::new();
+
+    for_each_image(&mut doc, &mut |attrs| {
+        let Some(src) = attrs.get("src").and_then(Value::as_str) else {
+            return;
+        };
+        let asset = if let Some(hash) = src.strip_prefix("midlight://img-") {
+            images_dir
+                .as_deref()
+                .and_then(|dir| stored_image(dir, hash))
+                .map(|(extension, data)| BundleAsset {
+                    path: format!("assets/{}.{}", hash, extension),
+                    data,
+                })
+        } else if src.starts_with("data:") {
+            data_url_asset(src)
+        } else {
+            None
+        };
+        let Some(asset) = asset else {
+            return;
+        };
+
+        let encoded = utf8_percent_encode(&asset.path, ASSET_PATH).to_string();
+        attrs.insert("src".to_string(), json!(encoded));
+        if !assets.iter().any(|a| a.path == asset.path) {
+            assets.push(asset);
+        }
+    });
+
+    TextBundle {
+        text: tiptap_to_markdown(&doc, MarkdownOptions::default()),
+        assets,
+        creator: Some("com.midlight.app".to_string()),
+    }
+}
+
+/// Extension and bytes of a stored image
+pub(crate) fn stored_image(images_dir: &Path, hash: &str) -> Option<(String, Vec<u8>)> {
+    fs::read_dir(images_dir)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .find(|path| path.is_file() && path.file_stem().is_some_and(|s| s == hash))
+        .and_then(|path| {
+            let extension = path.extension()?.to_string_lossy().to_lowercase();
+            Some((extension, fs::read(&path).ok()?))
+        })
+}
+
+fn data_url_asset(src: &str) -> Option<BundleAsset> {
+    let (header, encoded) = src.strip_prefix("data:")?.split_once(',')?;
+    let data = BASE64.decode(encoded).ok()?;
+    let extension = match header.split(';').next()? {
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        _ => return None,
+    };
+    let hash = format!("{:x}", Sha256::digest(&data));
+    Some(BundleAsset {
+        path: format!("assets/{}.{}", &hash[..16], extension),
+        data,
+    })
+}
+
+/// Write a bundle as a folder, or zipped if the path ends in .textpack.
+/// An existing bundle at the path is replaced.
+pub fn write_bundle(bundle: &TextBundle, path: &Path) -> Result<(), String> {
+    let info = serde_json::to_vec_pretty(&json!({
+        "version": 2,
+        "type": "net.daringfireball.markdown",
+        "transient": false,
+        "creatorIdentifier": bundle.creator.as_deref().unwrap_or("com.midlight.app"),
+    }))
+    .map_err(|e| format!("Failed to serialize info.json: {}", e))?;
+
+    let mut files: Vec<(&str, &[u8])> = vec![
+        ("info.json", info.as_slice()),
+        ("text.md", bundle.text.as_bytes()),
+    ];
+    files.extend(
+        bundle
+            .assets
+            .iter()
+            .map(|a| (a.path.as_str(), a.data.as_slice())),
+    );
+
+    if is_textpack(path) {
+        write_archive(path, &files)
+    } else {
+        write_folder(path, &files)
+    }
+}
+
+fn write_folder(path: &Path, files: &[(&str, &[u8])]) -> Result<(), String> {
+    if path.is_dir() {
+        if !path.join("info.json").is_file() {
+            return Err(format!("Not a TextBundle: {}", path.display()));
+        }
+        fs::remove_dir_all(path).map_err(|e| format!("Failed to replace bundle: {}", e))?;
+    }
+    for (name, data) in files {
+        let target = path.join(name);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+        }
+        fs::write(&target, data).map_err(|e| format!("Failed to write {}: {}", name, e))?;
+    }
+    Ok(())
+}
+
+/// Zip the bundle folder, named after the archive
+fn write_archive(path: &Path, files: &[(&str, &[u8])]) -> Result<(), String> {
+    let folder = format!(
+        "{}.textbundle",
+        path.file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Untitled".to_string())
+    );
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    let file = File::create(path).map_err(|e| format!("Failed to create TextPack: {}", e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default();
+    for (name, data) in files {
+        zip.start_file(format!("{}/{}", folder, name), options)
+            .and_then(|_| zip.write_all(data).map_err(Into::into))
+            .map_err(|e| format!("Failed to write {}: {}", name, e))?;
+    }
+    zip.finish()
+        .map_err(|e| format!("Failed to write TextPack: {}", e))?;
+    Ok(())
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+/// Call `f` with the attributes of every image node
+fn for_each_image(node: &mut Value, f: &mut impl FnMut(&mut serde_json::Map<String, Value>)) {
+    if node.get("type").and_then(Value::as_str) == Some("image") {
+        if let Some(attrs) = node.get_mut("attrs").and_then(Value::as_object_mut) {
+            f(attrs);
+        }
+    }
+    if let Some(children) = node.get_mut("content").and_then(Value::as_array_mut) {
+        for child in children {
+            for_each_image(child, f);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_round_trip_with_images() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join(".midlight/images")).unwrap();
+        fs::write(root.join(".midlight/images/3f2a9c01d4e5b6a7.png"), b"png").unwrap();
+        let doc = json!({
+            "type": "doc",
+            "content": [
+                { "type": "heading", "attrs": { "level": 1 },
+                  "content": [{ "type": "text", "text": "Trip notes" }] },
+                { "type": "image",
+                  "attrs": { "src": "midlight://img-3f2a9c01d4e5b6a7", "alt": "Harbour" } },
+                { "type": "image",
+                  "attrs": { "src": "https://example.com/a.png", "alt": "Remote" } }
+            ]
+        });
+
+        for name in ["Trip.textbundle", "Trip.textpack"] {
+            let path = root.join(name);
+            write_bundle(&bundle_from_tiptap(&doc, Some(root)), &path).unwrap();
+
+            let bundle = read_bundle(&path).unwrap();
+            assert_eq!(bundle.creator.as_deref(), Some("com.midlight.app"));
+            assert!(bundle
+                .text
+                .contains("![Harbour](assets/3f2a9c01d4e5b6a7.png)"));
+            assert_eq!(bundle.assets.len(), 1);
+            assert!(bundle.assets[0].is_image());
+            assert_eq!(bundle.assets[0].data, b"png");
+
+            let stored = HashMap::from([(
+                "assets/3f2a9c01d4e5b6a7.png".to_string(),
+                "midlight://img-3f2a9c01d4e5b6a7".to_string(),
+            )]);
+            let (imported, missing) = bundle_to_tiptap(&bundle, &stored);
+            assert!(missing.is_empty());
+            assert_eq!(
+                imported["content"][1]["attrs"]["src"],
+                doc["content"][1]["attrs"]["src"]
+            );
+            assert_eq!(
+                imported["content"][2]["attrs"]["src"],
+                "https://example.com/a.png"
+            );
+            assert_eq!(bundle_title(&imported, &path), "Trip notes");
+        }
+    }
+
+    #[test]
+    fn test_read_textpack_without_folder() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("Note.textpack");
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        let options = SimpleFileOptions::default();
+        zip.start_file("info.json", options).unwrap();
+        zip.write_all(br#"{"version":2,"creatorIdentifier":"net.ia.writer"}"#)
+            .unwrap();
+        zip.start_file("text.markdown", options).unwrap();
+        zip.write_all(b"Hello\n\n![](assets/My%20Photo.jpg)\n\n![](assets/gone.png)")
+            .unwrap();
+        zip.start_file("assets/My Photo.jpg", options).unwrap();
+        zip.write_all(b"jpg").unwrap();
+        zip.finish().unwrap();
+
+        let bundle = read_bundle(&path).unwrap();
+        assert_eq!(bundle.creator.as_deref(), Some("net.ia.writer"));
+        assert_eq!(bundle.assets[0].path, "assets/My Photo.jpg");
+        assert_eq!(bundle.assets[0].mime_type(), "image/jpeg");
+
+        let stored = HashMap::from([(
+            "assets/My Photo.jpg".to_string(),
+            "midlight://img-0b1c2d3e4f5a6b7c".to_string(),
+        )]);
+        let (doc, missing) = bundle_to_tiptap(&bundle, &stored);
+        assert_eq!(missing, vec!["assets/gone.png"]);
+        assert_eq!(
+            doc["content"][1]["attrs"]["src"],
+            "midlight://img-0b1c2d3e4f5a6b7c"
+        );
+        assert_eq!(bundle_title(&doc, &path), "Note");
+    }
+}
@@ -0,0 +1,361 @@
+// Token Counter - Local token counting and cost estimation
+//
+// Tokenizes prompts with tiktoken BPE encodings so the frontend can show
+// token counts and estimated cost per model before a request is sent.
+// Anthropic and Gemini tokenizers are not public, so cl100k_base is used as
+// an approximation for those models.
+
+use crate::services::llm_service::ChatMessage;
+use serde::{Deserialize, Serialize};
+use tiktoken_rs::{cl100k_base_singleton, o200k_base_singleton};
+
+/// Tokens added per message for role and formatting markers
+const TOKENS_PER_MESSAGE: u32 = 3;
+
+/// Tokens added when a message carries a `name` field
+const TOKENS_PER_NAME: u32 = 1;
+
+/// Tokens added to prime the assistant reply
+const TOKENS_PER_REPLY: u32 = 3;
+
+/// Output budget assumed when the request doesn't set `max_tokens`
+pub const DEFAULT_MAX_OUTPUT_TOKENS: u32 = 4096;
+
+/// Quota units consumed by a single request (quota is metered per request)
+const QUOTA_UNITS_PER_REQUEST: u32 = 1;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// BPE encoding used to count tokens for a model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    Cl100kBase,
+    O200kBase,
+}
+
+/// Price per million tokens (USD) for models matching a name prefix
+#[derive(Debug, Clone, Copy)]
+pub struct ModelPricing {
+    pub prefix: &'static str,
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+const fn price(prefix: &'static str, input: f64, output: f64) -> ModelPricing {
+    ModelPricing {
+        prefix,
+        input_per_million: input,
+        output_per_million: output,
+    }
+}
+
+/// Known model prices. Lookups use the longest matching prefix.
+const MODEL_PRICING: &[ModelPricing] = &[
+    // OpenAI
+    price("gpt-5-nano", 0.05, 0.40),
+    price("gpt-5-mini", 0.25, 2.00),
+    price("gpt-5", 1.25, 10.00),
+    price("gpt-4.1-nano", 0.10, 0.40),
+    price("gpt-4.1-mini", 0.40, 1.60),
+    price("gpt-4.1", 2.00, 8.00),
+    price("gpt-4o-mini", 0.15, 0.60),
+    price("gpt-4o", 2.50, 10.00),
+    // Anthropic
+    price("claude-haiku-4", 1.00, 5.00),
+    price("claude-sonnet-4", 3.00, 15.00),
+    price("claude-opus-4-5", 5.00, 25.00),
+    price("claude-opus-4", 15.00, 75.00),
+    price("claude-3-5-haiku", 0.80, 4.00),
+    // Gemini
+    price("gemini-3-flash", 0.50, 3.00),
+    price("gemini-3-pro", 2.00, 12.00),
+    price("gemini-2.5-flash", 0.30, 2.50),
+    price("gemini-2.5-pro", 1.25, 10.00),
+];
+
+/// Input for an estimate: the messages and context that would be sent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EstimateRequest {
+    pub models: Vec<String>,
+    pub messages: Vec<ChatMessage>,
+    /// Extra context (documents, selections) that will be attached to the prompt
+    #[serde(default)]
+    pub context: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+}
+
+/// Token counts and estimated cost for a single model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelEstimate {
+    pub model: String,
+    pub encoding: Encoding,
+    pub prompt_tokens: u32,
+    pub context_tokens: u32,
+    pub input_tokens: u32,
+    pub max_output_tokens: u32,
+    /// Cost of the input alone, if the model's pricing is known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_input_cost_usd: Option<f64>,
+    /// Cost if the response uses the full output budget
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_max_cost_usd: Option<f64>,
+    pub quota_units: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenEstimate {
+    pub estimates: Vec<ModelEstimate>,
+}
+
+// ============================================================================
+// Token Counting
+// ============================================================================
+
+/// Pick the BPE encoding for a model name
+pub fn encoding_for_model(model: &str) -> Encoding {
+    let model = model.to_lowercase();
+    let uses_o200k = model.starts_with("gpt-4o")
+        || model.starts_with("gpt-4.1")
+        || model.starts_with("gpt-5")
+        || model.starts_with("o1")
+        || model.starts_with("o3")
+        || model.starts_with("o4");
+
+    if uses_o200k {
+        Encoding::O200kBase
+    } else {
+        Encoding::Cl100kBase
+    }
+}
+
+/// Count tokens in a piece of text
+pub fn count_tokens(encoding: Encoding, text: &str) -> u32 {
+    if text.is_empty() {
+        return 0;
+    }
+
+    let bpe = match encoding {
+        Encoding::Cl100kBase => cl100k_base_singleton(),
+        Encoding::O200kBase => o200k_base_singleton(),
+    };
+    let count = bpe.lock().encode_with_special_tokens(text).len();
+    count as u32
+}
+
+/// Count tokens for a list of chat messages, including per-message overhead
+pub fn count_message_tokens(encoding: Encoding, messages: &[ChatMessage]) -> u32 {
+    let mut total = 0;
+
+    for message in messages {
+        total += TOKENS_PER_MESSAGE;
+        total += count_tokens(encoding, &message.role);
+        total += count_tokens(encoding, &message.content);
+
+        if let Some(ref name) = message.name {
+            total += TOKENS_PER_NAME + count_tokens(encoding, name);
+        }
+
+        if let Some(ref tool_calls) = message.tool_calls {
+            for call in tool_calls {
+                total += count_tokens(encoding, &call.name);
+                total += count_tokens(encoding, &call.arguments.to_string());
+            }
+        }
+    }
+
+    if !messages.is_empty() {
+        total += TOKENS_PER_REPLY;
+    }
+
+    total
+}
+
+// ============================================================================
+// Cost Estimation
+// ============================================================================
+
+/// Look up pricing for a model by longest matching prefix
+pub fn pricing_for_model(model: &str) -> Option<ModelPricing> {
+    let model = model.to_lowercase();
+    MODEL_PRICING
+        .iter()
+        .filter(|pricing| model.starts_with(pricing.prefix))
+        .max_by_key(|pricing| pricing.prefix.len())
+        .copied()
+}
+
+/// Cost in USD for the given token counts
+pub fn cost_usd(pricing: &ModelPricing, input_tokens: u32, output_tokens: u32) -> f64 {
+    (input_tokens as f64 * pricing.input_per_million
+        + output_tokens as f64 * pricing.output_per_million)
+        / 1_000_000.0
+}
+
+/// Estimate token usage and cost for each requested model
+pub fn estimate(request: &EstimateRequest) -> TokenEstimate {
+    let max_output_tokens = request.max_tokens.unwrap_or(DEFAULT_MAX_OUTPUT_TOKENS);
+
+    let estimates = request
+        .models
+        .iter()
+        .map(|model| {
+            let encoding = encoding_for_model(model);
+            let prompt_tokens = count_message_tokens(encoding, &request.messages);
+            let context_tokens: u32 = request
+                .context
+                .iter()
+                .map(|text| count_tokens(encoding, text))
+                .sum();
+            let input_tokens = prompt_tokens + context_tokens;
+            let pricing = pricing_for_model(model);
+
+            ModelEstimate {
+                model: model.clone(),
+                encoding,
+                prompt_tokens,
+                context_tokens,
+                input_tokens,
+                max_output_tokens,
+                estimated_input_cost_usd: pricing.map(|p| cost_usd(&p, input_tokens, 0)),
+                estimated_max_cost_usd: pricing
+                    .map(|p| cost_usd(&p, input_tokens, max_output_tokens)),
+                quota_units: QUOTA_UNITS_PER_REQUEST,
+            }
+        })
+        .collect();
+
+    TokenEstimate { estimates }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user_message(content: &str) -> ChatMessage {
+        ChatMessage {
+            role: "user".to_string(),
+            content: content.to_string(),
+            name: None,
+            tool_call_id: None,
+            tool_calls: None,
+        }
+    }
+
+    #[test]
+    fn test_encoding_for_model() {
+        assert_eq!(encoding_for_model("gpt-4o-mini"), Encoding::O200kBase);
+        assert_eq!(encoding_for_model("gpt-5-nano"), Encoding::O200kBase);
+        assert_eq!(encoding_for_model("gpt-4"), Encoding::Cl100kBase);
+        assert_eq!(
+            encoding_for_model("claude-haiku-4-5-20251001"),
+            Encoding::Cl100kBase
+        );
+    }
+
+    #[test]
+    fn test_count_tokens() {
+        assert_eq!(count_tokens(Encoding::Cl100kBase, ""), 0);
+        assert_eq!(count_tokens(Encoding::Cl100kBase, "hello world"), 2);
+        assert_eq!(count_tokens(Encoding::O200kBase, "hello world"), 2);
+    }
+
+    #[test]
+    fn test_count_message_tokens_includes_overhead() {
+        let messages = vec![user_message("hello world")];
+        let content = count_tokens(Encoding::Cl100kBase, "hello world");
+        let role = count_tokens(Encoding::Cl100kBase, "user");
+
+        assert_eq!(
+            count_message_tokens(Encoding::Cl100kBase, &messages),
+            TOKENS_PER_MESSAGE + role + content + TOKENS_PER_REPLY
+        );
+        assert_eq!(count_message_tokens(Encoding::Cl100kBase, &[]), 0);
+    }
+
+    #[test]
+    fn test_pricing_uses_longest_prefix() {
+        assert_eq!(
+            pricing_for_model("gpt-4o-mini").unwrap().prefix,
+            "gpt-4o-mini"
+        );
+        assert_eq!(
+            pricing_for_model("gpt-4o-2024-08-06").unwrap().prefix,
+            "gpt-4o"
+        );
+        assert_eq!(
+            pricing_for_model("claude-opus-4-5-20251101")
+                .unwrap()
+                .prefix,
+            "claude-opus-4-5"
+        );
+        assert!(pricing_for_model("unknown-model").is_none());
+    }
+
+    #[test]
+    fn test_cost_usd() {
+        let pricing = ModelPricing {
+            prefix: "test",
+            input_per_million: 2.0,
+            output_per_million: 10.0,
+        };
+        let cost = cost_usd(&pricing, 500_000, 100_000);
+        assert!((cost - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_estimate_per_model() {
+        let request = EstimateRequest {
+            models: vec!["gpt-4o-mini".to_string(), "unknown-model".to_string()],
+            messages: vec![user_message("Summarize this document")],
+            context: vec!["The quick brown fox jumps over the lazy dog.".to_string()],
+            max_tokens: Some(1000),
+        };
+
+        let result = estimate(&request);
+        assert_eq!(result.estimates.len(), 2);
+
+        let known = &result.estimates[0];
+        assert!(known.prompt_tokens > 0);
+        assert!(known.context_tokens > 0);
+        assert_eq!(
+            known.input_tokens,
+            known.prompt_tokens + known.context_tokens
+        );
+        assert_eq!(known.max_output_tokens, 1000);
+        assert!(known.estimated_input_cost_usd.unwrap() > 0.0);
+        assert!(known.estimated_max_cost_usd.unwrap() > known.estimated_input_cost_usd.unwrap());
+        assert_eq!(known.quota_units, 1);
+
+        let unknown = &result.estimates[1];
+        assert!(unknown.estimated_input_cost_usd.is_none());
+        assert!(unknown.estimated_max_cost_usd.is_none());
+    }
+
+    #[test]
+    fn test_estimate_default_max_tokens() {
+        let request = EstimateRequest {
+            models: vec!["gpt-4o".to_string()],
+            messages: vec![user_message("Hi")],
+            context: vec![],
+            max_tokens: None,
+        };
+
+        let result = estimate(&request);
+        assert_eq!(
+            result.estimates[0].max_output_tokens,
+            DEFAULT_MAX_OUTPUT_TOKENS
+        );
+        assert_eq!(result.estimates[0].context_tokens, 0);
+    }
+}
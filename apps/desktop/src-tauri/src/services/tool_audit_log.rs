@@ -0,0 +1,422 @@
+// Tool Audit Log - History of every agent tool execution in a workspace
+//
+// Each execution is appended as one JSON line with its arguments, a trimmed
+// copy of the result, timing, the conversation that asked for it and the
+// documents it touched. The log is append-only; when it grows past its size
+// limit the oldest half is dropped. Lines that fail to parse (for example a
+// write cut short by a crash) are skipped when reading.
+//
+// Storage: <workspace>/.midlight/agent-audit.jsonl
+
+use crate::services::agent_executor::{ToolExecution, ToolResult};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Rewrite the log keeping the newest half once it passes this size
+const MAX_LOG_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Strings in logged arguments and results are cut to this many characters
+const MAX_LOGGED_STRING: usize = 500;
+const MAX_LOGGED_ITEMS: usize = 50;
+const MAX_LOGGED_DEPTH: usize = 6;
+
+pub const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Filters for `ToolAuditLog::query`. All set filters must match.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionHistoryQuery {
+    #[serde(default)]
+    pub tool_name: Option<String>,
+    /// Only executions that touched this document, or anything inside it if
+    /// it is a folder
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+    #[serde(default)]
+    pub success: Option<bool>,
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub until: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: Option<usize>,
+}
+
+// ============================================================================
+// Log
+// ============================================================================
+
+pub struct ToolAuditLog {
+    log_path: PathBuf,
+}
+
+impl ToolAuditLog {
+    pub fn new(workspace_root: &Path) -> Self {
+        Self {
+            log_path: workspace_root.join(".midlight").join("agent-audit.jsonl"),
+        }
+    }
+
+    /// Append an execution to the log
+    pub fn append(&self, execution: &ToolExecution) -> Result<(), String> {
+        if let Some(parent) = self.log_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create .midlight directory: {}", e))?;
+        }
+
+        let mut line = serde_json::to_string(execution)
+            .map_err(|e| format!("Failed to serialize tool execution: {}", e))?;
+        line.push('\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.log_path)
+            .map_err(|e| format!("Failed to open audit log: {}", e))?;
+        file.write_all(line.as_bytes())
+            .map_err(|e| format!("Failed to write audit log: {}", e))?;
+
+        if file.metadata().map(|m| m.len()).unwrap_or(0) > MAX_LOG_BYTES {
+            drop(file);
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    /// Executions matching the query, newest first
+    pub fn query(&self, query: &ExecutionHistoryQuery) -> Result<Vec<ToolExecution>, String> {
+        let path_filter = query
+            .path
+            .as_deref()
+            .map(normalize_path)
+            .filter(|p| !p.is_empty());
+
+        let mut executions: Vec<ToolExecution> = self
+            .read()?
+            .into_iter()
+            .filter(|e| query.tool_name.as_ref().map_or(true, |t| &e.tool_name == t))
+            .filter(|e| {
+                query
+                    .conversation_id
+                    .as_ref()
+                    .map_or(true, |c| e.conversation_id.as_ref() == Some(c))
+            })
+            .filter(|e| {
+                query.success.map_or(true, |success| {
+                    e.result.as_ref().map(|r| r.success) == Some(success)
+                })
+            })
+            .filter(|e| {
+                let started = DateTime::parse_from_rfc3339(&e.started_at)
+                    .map(|t| t.with_timezone(&Utc))
+                    .ok();
+                query
+                    .since
+                    .map_or(true, |since| started.is_some_and(|t| t >= since))
+                    && query
+                        .until
+                        .map_or(true, |until| started.is_some_and(|t| t <= until))
+            })
+            .filter(|e| {
+                path_filter.as_ref().map_or(true, |filter| {
+                    e.paths.iter().any(|p| {
+                        p == filter
+                            || p.strip_prefix(filter.as_str())
+                                .is_some_and(|rest| rest.starts_with('/'))
+                    })
+                })
+            })
+            .collect();
+
+        executions.reverse();
+        Ok(executions
+            .into_iter()
+            .skip(query.offset.unwrap_or(0))
+            .take(query.limit.unwrap_or(DEFAULT_HISTORY_LIMIT))
+            .collect())
+    }
+
+    fn read(&self) -> Result<Vec<ToolExecution>, String> {
+        if !self.log_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&self.log_path)
+            .map_err(|e| format!("Failed to read audit log: {}", e))?;
+        Ok(content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(execution) => Some(execution),
+                Err(e) => {
+                    warn!("Skipping unreadable audit log entry: {}", e);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// Drop the oldest half of the log
+    fn compact(&self) -> Result<(), String> {
+        let content = fs::read_to_string(&self.log_path)
+            .map_err(|e| format!("Failed to read audit log: {}", e))?;
+        let lines: Vec<&str> = content.lines().collect();
+        let kept = lines[lines.len() / 2..].join("\n") + "\n";
+
+        let temp_path = self.log_path.with_extension("jsonl.tmp");
+        fs::write(&temp_path, kept).map_err(|e| format!("Failed to write audit log: {}", e))?;
+        fs::rename(&temp_path, &self.log_path)
+            .map_err(|e| format!("Failed to write audit log: {}", e))
+    }
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+fn normalize_path(path: &str) -> String {
+    path.replace('\\', "/").trim_matches('/').to_string()
+}
+
+/// Workspace paths an execution refers to, from its arguments and result
+pub fn affected_paths(arguments: &Value, result: &ToolResult) -> Vec<String> {
+    const PATH_KEYS: &[&str] = &["path", "oldPath", "newPath"];
+
+    let mut paths = Vec::new();
+    let mut collect = |value: &Value| {
+        for key in PATH_KEYS {
+            if let Some(path) = value.get(key).and_then(|v| v.as_str()) {
+                let path = normalize_path(path);
+                if !path.is_empty() && !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+        }
+    };
+
+    collect(arguments);
+    if let Some(data) = &result.data {
+        collect(data);
+        if let Some(changes) = data.get("changes").and_then(|c| c.as_array()) {
+            changes.iter().for_each(&mut collect);
+        }
+    }
+    paths
+}
+
+/// Copy of a value small enough to keep in the log: long strings, arrays
+/// and deep nesting are cut short
+pub fn summarize_value(value: &Value) -> Value {
+    summarize_at(value, 0)
+}
+
+fn summarize_at(value: &Value, depth: usize) -> Value {
+    match value {
+        Value::String(s) if s.chars().count() > MAX_LOGGED_STRING => {
+            let cut: String = s.chars().take(MAX_LOGGED_STRING).collect();
+            Value::String(format!("{}…", cut))
+        }
+        Value::Array(_) | Value::Object(_) if depth >= MAX_LOGGED_DEPTH => {
+            Value::String("…".to_string())
+        }
+        Value::Array(items) => {
+            let mut summarized: Vec<Value> = items
+                .iter()
+                .take(MAX_LOGGED_ITEMS)
+                .map(|v| summarize_at(v, depth + 1))
+                .collect();
+            if items.len() > MAX_LOGGED_ITEMS {
+                summarized.push(Value::String(format!(
+                    "… {} more",
+                    items.len() - MAX_LOGGED_ITEMS
+                )));
+            }
+            Value::Array(summarized)
+        }
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), summarize_at(v, depth + 1)))
+                .collect(),
+        ),
+        _ => value.clone(),
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::agent_executor::ToolExecutionStatus;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn execution(tool: &str, started_at: &str, path: &str, success: bool) -> ToolExecution {
+        ToolExecution {
+            id: format!("{}-{}", tool, started_at),
+            tool_name: tool.to_string(),
+            arguments: json!({"path": path}),
+            status: if success {
+                ToolExecutionStatus::Completed
+            } else {
+                ToolExecutionStatus::Failed
+            },
+            result: Some(ToolResult {
+                success,
+                data: None,
+                error: None,
+            }),
+            started_at: started_at.to_string(),
+            completed_at: Some(started_at.to_string()),
+            duration_ms: Some(5),
+            conversation_id: Some("conv-1".to_string()),
+            paths: vec![path.to_string()],
+        }
+    }
+
+    #[test]
+    fn test_append_and_query() {
+        let temp = TempDir::new().unwrap();
+        let log = ToolAuditLog::new(temp.path());
+        assert!(log.query(&Default::default()).unwrap().is_empty());
+
+        log.append(&execution(
+            "read_document",
+            "2024-01-01T10:00:00Z",
+            "notes/a.midlight",
+            true,
+        ))
+        .unwrap();
+        log.append(&execution(
+            "edit_document",
+            "2024-01-02T10:00:00Z",
+            "notes/b.midlight",
+            true,
+        ))
+        .unwrap();
+        log.append(&execution(
+            "read_document",
+            "2024-01-03T10:00:00Z",
+            "other.midlight",
+            false,
+        ))
+        .unwrap();
+
+        let all = log.query(&Default::default()).unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].started_at, "2024-01-03T10:00:00Z");
+
+        let by_tool = log
+            .query(&ExecutionHistoryQuery {
+                tool_name: Some("read_document".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(by_tool.len(), 2);
+
+        let by_folder = log
+            .query(&ExecutionHistoryQuery {
+                path: Some("/notes".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(by_folder.len(), 2);
+
+        let by_date = log
+            .query(&ExecutionHistoryQuery {
+                since: Some("2024-01-02T00:00:00Z".parse().unwrap()),
+                until: Some("2024-01-02T23:59:59Z".parse().unwrap()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(by_date.len(), 1);
+        assert_eq!(by_date[0].tool_name, "edit_document");
+
+        let failed = log
+            .query(&ExecutionHistoryQuery {
+                success: Some(false),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(failed.len(), 1);
+
+        let paged = log
+            .query(&ExecutionHistoryQuery {
+                limit: Some(1),
+                offset: Some(1),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(paged[0].tool_name, "edit_document");
+    }
+
+    #[test]
+    fn test_skips_corrupt_lines() {
+        let temp = TempDir::new().unwrap();
+        let log = ToolAuditLog::new(temp.path());
+        log.append(&execution(
+            "read_document",
+            "2024-01-01T10:00:00Z",
+            "a",
+            true,
+        ))
+        .unwrap();
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(temp.path().join(".midlight/agent-audit.jsonl"))
+            .unwrap();
+        file.write_all(b"{\"id\": \"trunc").unwrap();
+
+        assert_eq!(log.query(&Default::default()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_affected_paths() {
+        let result = ToolResult {
+            success: true,
+            data: Some(json!({
+                "changes": [{"path": "a.midlight"}, {"path": "/b.midlight"}]
+            })),
+            error: None,
+        };
+        let paths = affected_paths(
+            &json!({"oldPath": "/x.midlight", "newPath": "y.midlight"}),
+            &result,
+        );
+        assert_eq!(
+            paths,
+            vec!["x.midlight", "y.midlight", "a.midlight", "b.midlight"]
+        );
+    }
+
+    #[test]
+    fn test_summarize_value() {
+        let long = "x".repeat(MAX_LOGGED_STRING + 10);
+        let summarized = summarize_value(&json!({
+            "content": long,
+            "items": (0..60).collect::<Vec<_>>(),
+        }));
+        assert_eq!(
+            summarized["content"].as_str().unwrap().chars().count(),
+            MAX_LOGGED_STRING + 1
+        );
+        let items = summarized["items"].as_array().unwrap();
+        assert_eq!(items.len(), MAX_LOGGED_ITEMS + 1);
+        assert_eq!(items[MAX_LOGGED_ITEMS], json!("… 10 more"));
+    }
+}
@@ -0,0 +1,339 @@
+// Trash Manager - App-level trash so deleted files can be restored
+//
+// Trashed files and folders are moved into the workspace's trash rather
+// than the OS trash, each in its own directory named by the item's id,
+// together with any sidecar file. Moving within the workspace is a rename,
+// so trashing and restoring are instant whatever the size. Items older than
+// RETENTION_DAYS are deleted for good the next time something is trashed.
+//
+// Trash is stored at: .midlight/trash/
+//   index.json           metadata for every item
+//   {id}/{name}          the trashed file or folder
+//   {id}/{name}.sidecar.json
+//
+// Index format:
+// {
+//   "version": 1,
+//   "items": [{ "id": "...", "name": "a.md", "originalPath": "notes/a.md", ... }]
+// }
+
+use crate::services::atomic_write::write_atomic;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const INDEX_VERSION: u32 = 1;
+
+/// Days items stay in the trash before they are deleted for good
+pub const RETENTION_DAYS: i64 = 30;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashItem {
+    pub id: String,
+    pub name: String,
+    /// Where the item was, relative to the workspace
+    pub original_path: String,
+    pub is_dir: bool,
+    /// Total size in bytes, including folder contents
+    pub size: u64,
+    pub trashed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TrashIndex {
+    version: u32,
+    #[serde(default)]
+    items: Vec<TrashItem>,
+}
+
+// ============================================================================
+// Trash Manager
+// ============================================================================
+
+pub struct TrashManager {
+    workspace_root: PathBuf,
+    trash_dir: PathBuf,
+}
+
+impl TrashManager {
+    pub fn new(workspace_root: &Path) -> Self {
+        Self {
+            workspace_root: workspace_root.to_path_buf(),
+            trash_dir: workspace_root.join(".midlight").join("trash"),
+        }
+    }
+
+    /// Move a file or folder in the workspace to the trash
+    pub fn trash(&self, path: &Path) -> Result<TrashItem, String> {
+        self.trash_at(path, Utc::now())
+    }
+
+    fn trash_at(&self, path: &Path, now: DateTime<Utc>) -> Result<TrashItem, String> {
+        let relative = path
+            .strip_prefix(&self.workspace_root)
+            .map_err(|_| format!("{} is not in the workspace", path.display()))?;
+        if relative.starts_with(".midlight") || relative.as_os_str().is_empty() {
+            return Err(format!("{} can't be moved to the trash", path.display()));
+        }
+        let name = path
+            .file_name()
+            .ok_or_else(|| "Invalid path".to_string())?
+            .to_string_lossy()
+            .to_string();
+        let metadata =
+            fs::symlink_metadata(path).map_err(|e| format!("Failed to read {}: {}", name, e))?;
+
+        let mut index = self.read()?;
+        self.purge_expired(&mut index, now);
+
+        let item = TrashItem {
+            id: uuid::Uuid::new_v4().to_string(),
+            original_path: relative.to_string_lossy().replace('\\', "/"),
+            is_dir: metadata.is_dir(),
+            size: size_of(path),
+            trashed_at: now,
+            name,
+        };
+
+        let item_dir = self.trash_dir.join(&item.id);
+        fs::create_dir_all(&item_dir).map_err(|e| format!("Failed to create trash: {}", e))?;
+        fs::rename(path, item_dir.join(&item.name))
+            .map_err(|e| format!("Failed to move {} to the trash: {}", item.name, e))?;
+
+        let sidecar = sidecar_path(path);
+        if sidecar.exists() {
+            let _ = fs::rename(&sidecar, sidecar_path(&item_dir.join(&item.name)));
+        }
+
+        index.items.push(item.clone());
+        self.write(&index)?;
+        Ok(item)
+    }
+
+    /// Items in the trash, most recently trashed first
+    pub fn list(&self) -> Result<Vec<TrashItem>, String> {
+        let mut items = self.read()?.items;
+        items.sort_by_key(|item| std::cmp::Reverse(item.trashed_at));
+        Ok(items)
+    }
+
+    /// Put an item back where it was, recreating missing folders. If
+    /// something else now has its name, it is restored alongside as
+    /// "name-Restored". Returns the restored path.
+    pub fn restore(&self, id: &str) -> Result<PathBuf, String> {
+        let mut index = self.read()?;
+        let position = index
+            .items
+            .iter()
+            .position(|item| item.id == id)
+            .ok_or_else(|| format!("Trash item not found: {}", id))?;
+        let item = &index.items[position];
+
+        let trashed = self.trash_dir.join(&item.id).join(&item.name);
+        let original = self.workspace_root.join(&item.original_path);
+        let target = available_path(&original, item.is_dir);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create folder: {}", e))?;
+        }
+        fs::rename(&trashed, &target)
+            .map_err(|e| format!("Failed to restore {}: {}", item.name, e))?;
+
+        let sidecar = sidecar_path(&trashed);
+        if sidecar.exists() {
+            let _ = fs::rename(&sidecar, sidecar_path(&target));
+        }
+        let _ = fs::remove_dir_all(self.trash_dir.join(&item.id));
+
+        index.items.remove(position);
+        self.write(&index)?;
+        Ok(target)
+    }
+
+    /// Delete one item for good
+    pub fn delete(&self, id: &str) -> Result<(), String> {
+        let mut index = self.read()?;
+        let before = index.items.len();
+        index.items.retain(|item| item.id != id);
+        if index.items.len() == before {
+            return Err(format!("Trash item not found: {}", id));
+        }
+
+        fs::remove_dir_all(self.trash_dir.join(id))
+            .map_err(|e| format!("Failed to delete trash item: {}", e))?;
+        self.write(&index)
+    }
+
+    /// Delete everything in the trash for good, returning how many items
+    /// were removed
+    pub fn empty(&self) -> Result<usize, String> {
+        let index = self.read()?;
+        for item in &index.items {
+            let _ = fs::remove_dir_all(self.trash_dir.join(&item.id));
+        }
+        self.write(&TrashIndex {
+            version: INDEX_VERSION,
+            items: Vec::new(),
+        })?;
+        Ok(index.items.len())
+    }
+
+    fn purge_expired(&self, index: &mut TrashIndex, now: DateTime<Utc>) {
+        let cutoff = now - Duration::days(RETENTION_DAYS);
+        index.items.retain(|item| {
+            if item.trashed_at >= cutoff {
+                return true;
+            }
+            let _ = fs::remove_dir_all(self.trash_dir.join(&item.id));
+            false
+        });
+    }
+
+    fn read(&self) -> Result<TrashIndex, String> {
+        let index_path = self.trash_dir.join("index.json");
+        if !index_path.exists() {
+            return Ok(TrashIndex {
+                version: INDEX_VERSION,
+                items: Vec::new(),
+            });
+        }
+
+        let content =
+            fs::read_to_string(&index_path).map_err(|e| format!("Failed to read trash: {}", e))?;
+        serde_json::from_str(&content).map_err(|e| format!("Failed to parse trash: {}", e))
+    }
+
+    fn write(&self, index: &TrashIndex) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(index)
+            .map_err(|e| format!("Failed to serialize trash: {}", e))?;
+
+        let index_path = self.trash_dir.join("index.json");
+        write_atomic(&index_path, json).map_err(|e| format!("Failed to write trash: {}", e))
+    }
+}
+
+fn sidecar_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".sidecar.json");
+    PathBuf::from(name)
+}
+
+/// The path itself if it's free, otherwise "name-Restored", "name-Restored 2"...
+fn available_path(path: &Path, is_dir: bool) -> PathBuf {
+    if !path.exists() {
+        return path.to_path_buf();
+    }
+
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let (stem, ext) = match (is_dir, path.file_stem(), path.extension()) {
+        (false, Some(stem), Some(ext)) => (
+            stem.to_string_lossy().to_string(),
+            format!(".{}", ext.to_string_lossy()),
+        ),
+        _ => (file_name, String::new()),
+    };
+
+    (1..)
+        .map(|n| {
+            let suffix = if n == 1 {
+                "-Restored".to_string()
+            } else {
+                format!("-Restored {}", n)
+            };
+            path.with_file_name(format!("{}{}{}", stem, suffix, ext))
+        })
+        .find(|candidate| !candidate.exists())
+        .expect("unbounded range")
+}
+
+fn size_of(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_trash_and_restore() {
+        let temp = TempDir::new().unwrap();
+        let notes = temp.path().join("notes");
+        fs::create_dir_all(&notes).unwrap();
+        fs::write(notes.join("a.md"), "hello").unwrap();
+        fs::write(notes.join("a.md.sidecar.json"), "{}").unwrap();
+
+        let trash = TrashManager::new(temp.path());
+        let item = trash.trash(&notes.join("a.md")).unwrap();
+        assert_eq!(item.original_path, "notes/a.md");
+        assert_eq!(item.size, 5);
+        assert!(!notes.join("a.md").exists());
+        assert!(!notes.join("a.md.sidecar.json").exists());
+        assert_eq!(trash.list().unwrap().len(), 1);
+
+        // The folder is gone and the name is taken by then
+        fs::remove_dir_all(&notes).unwrap();
+        let restored = trash.restore(&item.id).unwrap();
+        assert_eq!(restored, notes.join("a.md"));
+        assert_eq!(fs::read_to_string(&restored).unwrap(), "hello");
+        assert!(notes.join("a.md.sidecar.json").exists());
+        assert!(trash.list().unwrap().is_empty());
+
+        let item = trash.trash(&restored).unwrap();
+        fs::write(notes.join("a.md"), "new").unwrap();
+        assert_eq!(
+            trash.restore(&item.id).unwrap(),
+            notes.join("a-Restored.md")
+        );
+        assert!(trash.restore(&item.id).is_err());
+    }
+
+    #[test]
+    fn test_empty_and_expiry() {
+        let temp = TempDir::new().unwrap();
+        for name in ["a.md", "b.md", "c.md"] {
+            fs::write(temp.path().join(name), name).unwrap();
+        }
+        let folder = temp.path().join("folder");
+        fs::create_dir_all(&folder).unwrap();
+
+        let trash = TrashManager::new(temp.path());
+        let long_ago = Utc::now() - Duration::days(RETENTION_DAYS + 1);
+        let old = trash.trash_at(&temp.path().join("a.md"), long_ago).unwrap();
+        assert_eq!(trash.list().unwrap()[0].id, old.id);
+
+        // Trashing again purges items past the retention period
+        trash.trash(&temp.path().join("b.md")).unwrap();
+        assert_eq!(trash.list().unwrap().len(), 1);
+        assert!(!temp.path().join(".midlight/trash").join(&old.id).exists());
+
+        let item = trash.trash(&folder).unwrap();
+        assert!(item.is_dir);
+        assert_eq!(trash.list().unwrap()[0].id, item.id);
+
+        trash.delete(&item.id).unwrap();
+        assert_eq!(trash.empty().unwrap(), 1);
+        assert!(trash.list().unwrap().is_empty());
+
+        assert!(trash.trash(&temp.path().join(".midlight")).is_err());
+        assert!(trash.trash(Path::new("/elsewhere/c.md")).is_err());
+    }
+}
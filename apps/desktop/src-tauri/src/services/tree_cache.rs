@@ -0,0 +1,392 @@
+// Tree Cache - The workspace file tree, document titles and word counts,
+// kept between launches
+//
+// Listing a large workspace means walking the disk and reading every
+// document for its title and word count. The cache keeps all of that in
+// SQLite so the tree can be shown at once on launch. It is reconciled with
+// the disk in the background afterwards, reading only documents whose size
+// or modified time changed, and kept current by the file watcher and by
+// saves.
+//
+// Storage: .midlight/tree-cache.db
+// Paths are workspace-relative with "/" separators; the root's children
+// have the parent "".
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use walkdir::WalkDir;
+
+use super::document_chunks;
+use super::document_stats;
+use super::markdown_convert::extract_text_content;
+use super::workspace_paths::document_key;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedEntry {
+    /// Workspace-relative path
+    pub path: String,
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+    /// Modified time in milliseconds since the epoch
+    pub modified: u64,
+    /// Document title, for .midlight and .md files
+    pub title: Option<String>,
+    /// Document word count, for .midlight and .md files
+    pub word_count: Option<usize>,
+}
+
+/// Changes found by reconciling the cache with the disk
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconcileStats {
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+}
+
+impl ReconcileStats {
+    pub fn changed(&self) -> bool {
+        self.added + self.updated + self.removed > 0
+    }
+}
+
+// ============================================================================
+// Tree Cache
+// ============================================================================
+
+pub struct TreeCache {
+    workspace_root: PathBuf,
+    conn: Connection,
+}
+
+impl TreeCache {
+    pub fn open(workspace_root: &Path) -> Result<Self, String> {
+        let midlight_dir = workspace_root.join(".midlight");
+        fs::create_dir_all(&midlight_dir)
+            .map_err(|e| format!("Failed to create .midlight directory: {}", e))?;
+        let conn = Connection::open(midlight_dir.join("tree-cache.db"))
+            .map_err(|e| format!("Failed to open tree cache: {}", e))?;
+        conn.execute_batch(
+            "PRAGMA journal_mode=WAL; PRAGMA synchronous=NORMAL;
+             CREATE TABLE IF NOT EXISTS entries (
+                 path TEXT PRIMARY KEY,
+                 parent TEXT NOT NULL,
+                 name TEXT NOT NULL,
+                 is_dir INTEGER NOT NULL,
+                 size INTEGER NOT NULL,
+                 modified INTEGER NOT NULL,
+                 title TEXT,
+                 word_count INTEGER
+             );
+             CREATE INDEX IF NOT EXISTS idx_parent ON entries(parent);",
+        )
+        .map_err(|e| format!("Failed to create tree cache: {}", e))?;
+
+        Ok(Self {
+            workspace_root: workspace_root.to_path_buf(),
+            conn,
+        })
+    }
+
+    /// Every cached entry
+    pub fn entries(&self) -> Result<Vec<CachedEntry>, String> {
+        self.query("SELECT * FROM entries ORDER BY path", params![])
+    }
+
+    /// Cached entries directly inside a folder ("" for the workspace root)
+    pub fn children(&self, parent: &str) -> Result<Vec<CachedEntry>, String> {
+        self.query(
+            "SELECT * FROM entries WHERE parent = ?1 ORDER BY path",
+            params![document_key(parent)],
+        )
+    }
+
+    fn query(&self, sql: &str, params: impl rusqlite::Params) -> Result<Vec<CachedEntry>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(sql)
+            .map_err(|e| format!("Failed to read tree cache: {}", e))?;
+        let rows = stmt
+            .query_map(params, |row| {
+                Ok(CachedEntry {
+                    path: row.get("path")?,
+                    name: row.get("name")?,
+                    is_dir: row.get("is_dir")?,
+                    size: row.get::<_, i64>("size")? as u64,
+                    modified: row.get::<_, i64>("modified")? as u64,
+                    title: row.get("title")?,
+                    word_count: row.get::<_, Option<i64>>("word_count")?.map(|n| n as usize),
+                })
+            })
+            .map_err(|e| format!("Failed to read tree cache: {}", e))?;
+        rows.collect::<Result<_, _>>()
+            .map_err(|e| format!("Failed to read tree cache: {}", e))
+    }
+
+    /// Walk the workspace and bring the cache in line with it. Only
+    /// documents whose size or modified time changed are read.
+    pub fn reconcile(&mut self, visible: impl Fn(&str) -> bool) -> Result<ReconcileStats, String> {
+        let mut cached: HashMap<String, (u64, u64)> = self
+            .entries()?
+            .into_iter()
+            .map(|e| (e.path, (e.size, e.modified)))
+            .collect();
+
+        let mut stats = ReconcileStats::default();
+        let tx = self
+            .conn
+            .transaction()
+            .map_err(|e| format!("Failed to update tree cache: {}", e))?;
+        let walker = WalkDir::new(&self.workspace_root)
+            .min_depth(1)
+            .into_iter()
+            .filter_entry(|e| visible(&e.file_name().to_string_lossy()))
+            .filter_map(|e| e.ok());
+        for entry in walker {
+            let Some(scanned) = scan(&self.workspace_root, entry.path()) else {
+                continue;
+            };
+            match cached.remove(&scanned.path) {
+                Some(stamp) if stamp == (scanned.size, scanned.modified) => continue,
+                Some(_) => stats.updated += 1,
+                None => stats.added += 1,
+            }
+            let entry = with_document_info(&self.workspace_root, scanned);
+            upsert(&tx, &entry)?;
+        }
+        for path in cached.keys() {
+            tx.execute("DELETE FROM entries WHERE path = ?1", params![path])
+                .map_err(|e| format!("Failed to update tree cache: {}", e))?;
+            stats.removed += 1;
+        }
+        tx.commit()
+            .map_err(|e| format!("Failed to update tree cache: {}", e))?;
+        Ok(stats)
+    }
+
+    /// Update the cache for one changed path: a file or folder that was
+    /// created, modified or deleted. A new folder's contents are added too.
+    pub fn refresh(&mut self, path: &Path, visible: impl Fn(&str) -> bool) -> Result<(), String> {
+        let Ok(relative) = path.strip_prefix(&self.workspace_root) else {
+            return Ok(());
+        };
+        let key = document_key(&relative.to_string_lossy());
+        let hidden = relative
+            .components()
+            .any(|c| !visible(&c.as_os_str().to_string_lossy()));
+
+        let tx = self
+            .conn
+            .transaction()
+            .map_err(|e| format!("Failed to update tree cache: {}", e))?;
+        tx.execute(
+            "DELETE FROM entries WHERE path = ?1 OR path LIKE ?2 ESCAPE '\\'",
+            params![key, format!("{}/%", escape_like(&key))],
+        )
+        .map_err(|e| format!("Failed to update tree cache: {}", e))?;
+
+        if !hidden && path.exists() {
+            let walker = WalkDir::new(path)
+                .into_iter()
+                .filter_entry(|e| e.depth() == 0 || visible(&e.file_name().to_string_lossy()))
+                .filter_map(|e| e.ok());
+            for entry in walker {
+                if let Some(scanned) = scan(&self.workspace_root, entry.path()) {
+                    upsert(&tx, &with_document_info(&self.workspace_root, scanned))?;
+                }
+            }
+        }
+        tx.commit()
+            .map_err(|e| format!("Failed to update tree cache: {}", e))
+    }
+}
+
+fn upsert(conn: &Connection, entry: &CachedEntry) -> Result<(), String> {
+    let parent = entry
+        .path
+        .rsplit_once('/')
+        .map(|(parent, _)| parent)
+        .unwrap_or("");
+    conn.execute(
+        "INSERT OR REPLACE INTO entries
+             (path, parent, name, is_dir, size, modified, title, word_count)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            entry.path,
+            parent,
+            entry.name,
+            entry.is_dir,
+            entry.size as i64,
+            entry.modified as i64,
+            entry.title,
+            entry.word_count.map(|n| n as i64),
+        ],
+    )
+    .map_err(|e| format!("Failed to update tree cache: {}", e))?;
+    Ok(())
+}
+
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+// ============================================================================
+// Scanning
+// ============================================================================
+
+/// An entry as it is on disk, without document info
+fn scan(workspace_root: &Path, path: &Path) -> Option<CachedEntry> {
+    let metadata = fs::metadata(path).ok()?;
+    let relative = path.strip_prefix(workspace_root).ok()?;
+    let is_dir = metadata.is_dir();
+    Some(CachedEntry {
+        path: document_key(&relative.to_string_lossy()),
+        name: path.file_name()?.to_string_lossy().to_string(),
+        is_dir,
+        size: if is_dir { 0 } else { metadata.len() },
+        modified: metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_millis() as u64),
+        title: None,
+        word_count: None,
+    })
+}
+
+/// Fill in the title and word count of a document
+fn with_document_info(workspace_root: &Path, mut entry: CachedEntry) -> CachedEntry {
+    if entry.is_dir {
+        return entry;
+    }
+    let path = workspace_root.join(&entry.path);
+    if entry.path.ends_with(".midlight") {
+        if let Some(document) = fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str::<Value>(&s).ok())
+        {
+            let (title, words) = midlight_info(&document);
+            entry.title = title;
+            entry.word_count = Some(words);
+        }
+    } else if entry.path.ends_with(".md") {
+        if let Ok(text) = fs::read_to_string(&path) {
+            entry.title = text
+                .lines()
+                .find_map(|line| line.strip_prefix("# "))
+                .map(|title| title.trim().to_string());
+            entry.word_count = Some(document_stats::text_stats(&text).words);
+        }
+    }
+    entry
+}
+
+/// Title and word count of a .midlight document. Documents stored in
+/// sections are counted from their manifest without loading the sections.
+fn midlight_info(document: &Value) -> (Option<String>, usize) {
+    let meta_title = document
+        .get("meta")
+        .and_then(|m| m.get("title"))
+        .and_then(Value::as_str)
+        .map(str::to_string);
+
+    if let Some(manifest) = document_chunks::manifest(document) {
+        let title = meta_title.or_else(|| {
+            manifest
+                .sections
+                .iter()
+                .find_map(|section| section.title.clone())
+        });
+        return (title, manifest.sections.iter().map(|s| s.words).sum());
+    }
+
+    let title = meta_title.or_else(|| {
+        document
+            .get("content")
+            .and_then(|c| c.get("content"))
+            .and_then(Value::as_array)?
+            .iter()
+            .find(|block| block.get("type").and_then(Value::as_str) == Some("heading"))
+            .map(|heading| extract_text_content(heading).trim().to_string())
+    });
+    (title, document_stats::midlight_stats(document).words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn visible(name: &str) -> bool {
+        !name.starts_with('.')
+    }
+
+    #[test]
+    fn test_reconcile_and_refresh() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("drafts/old")).unwrap();
+        fs::write(root.join("drafts/plan.md"), "# The plan\n\nShip it soon").unwrap();
+        fs::write(
+            root.join("notes.midlight"),
+            r#"{"version":1,"meta":{},"content":{"type":"doc","content":[
+                {"type":"heading","attrs":{"level":1},"content":[{"type":"text","text":"Notes"}]},
+                {"type":"paragraph","content":[{"type":"text","text":"one two three"}]}]}}"#,
+        )
+        .unwrap();
+
+        let mut cache = TreeCache::open(root).unwrap();
+        assert!(cache.entries().unwrap().is_empty());
+        let stats = cache.reconcile(visible).unwrap();
+        assert_eq!(stats.added, 4);
+
+        let paths: Vec<_> = cache
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.path)
+            .collect();
+        assert_eq!(
+            paths,
+            vec!["drafts", "drafts/old", "drafts/plan.md", "notes.midlight"]
+        );
+        let drafts = cache.children("drafts").unwrap();
+        assert_eq!(drafts[1].title.as_deref(), Some("The plan"));
+        assert_eq!(drafts[1].word_count, Some(6));
+        let root_entries = cache.children("").unwrap();
+        assert_eq!(root_entries[1].title.as_deref(), Some("Notes"));
+        assert_eq!(root_entries[1].word_count, Some(4));
+
+        // Nothing changed on disk
+        assert!(!cache.reconcile(visible).unwrap().changed());
+
+        fs::remove_dir_all(root.join("drafts")).unwrap();
+        cache.refresh(&root.join("drafts"), visible).unwrap();
+        let paths: Vec<_> = cache
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.path)
+            .collect();
+        assert_eq!(paths, vec!["notes.midlight"]);
+
+        fs::create_dir_all(root.join("ideas")).unwrap();
+        fs::write(root.join("ideas/a.md"), "alpha").unwrap();
+        cache.refresh(&root.join("ideas"), visible).unwrap();
+        assert_eq!(cache.children("ideas").unwrap()[0].word_count, Some(1));
+    }
+}
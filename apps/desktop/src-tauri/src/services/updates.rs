@@ -0,0 +1,311 @@
+// Updates - Release channel settings and saved update packages
+//
+// Stable releases are published to releases/tauri-latest.json and betas to
+// releases/beta/tauri-latest.json; the channel chosen is kept in
+// `updates.json` in the app data directory.
+//
+// Every update package downloaded is also kept in `updates/`, so a release
+// that turns out broken can be rolled back by reinstalling the package that
+// was downloaded before it. A version rolled back from is skipped by later
+// update checks. Only the last MAX_PACKAGES packages are kept, and rolling
+// back needs the previous version to have arrived as an update too.
+//
+// With background downloads on, updates are fetched quietly (optionally under
+// a bandwidth limit) and installed when the app quits rather than
+// interrupting whatever the user is writing.
+
+use crate::services::app_dirs::app_data_dir;
+use crate::services::atomic_write::write_atomic;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::warn;
+
+/// Update packages kept for rollback
+const MAX_PACKAGES: usize = 2;
+
+const INDEX_FILE: &str = "index.json";
+
+// ============================================================================
+// Settings
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    Beta,
+}
+
+impl UpdateChannel {
+    /// Manifest the updater checks on this channel
+    pub fn manifest_url(&self) -> &'static str {
+        match self {
+            UpdateChannel::Stable => "https://midlight.ai/releases/tauri-latest.json",
+            UpdateChannel::Beta => "https://midlight.ai/releases/beta/tauri-latest.json",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateSettings {
+    #[serde(default)]
+    pub channel: UpdateChannel,
+    /// Versions rolled back from, which updates won't offer again
+    #[serde(default)]
+    pub skipped_versions: Vec<String>,
+    /// Download updates in the background and install them on quit
+    #[serde(default)]
+    pub background_download: bool,
+    /// Cap on background download speed, in KB/s
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bandwidth_limit_kbps: Option<u32>,
+}
+
+/// Path of the saved update settings
+pub fn update_settings_path() -> PathBuf {
+    app_data_dir().join("updates.json")
+}
+
+/// Directory of saved update packages
+pub fn update_packages_dir() -> PathBuf {
+    app_data_dir().join("updates")
+}
+
+impl UpdateSettings {
+    pub fn load(path: &Path) -> Self {
+        if !path.exists() {
+            return Self::default();
+        }
+
+        std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| {
+                warn!("Failed to load update settings, using defaults: {}", e);
+                Self::default()
+            })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize update settings: {}", e))?;
+        write_atomic(path, json).map_err(|e| format!("Failed to save update settings: {}", e))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.bandwidth_limit_kbps == Some(0) {
+            return Err("Bandwidth limit must be at least 1 KB/s".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn is_skipped(&self, version: &str) -> bool {
+        self.skipped_versions.iter().any(|v| v == version)
+    }
+}
+
+// ============================================================================
+// Saved Packages
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedPackage {
+    pub version: String,
+    pub file: String,
+    pub saved_at: String,
+}
+
+/// Downloaded update packages, oldest first
+pub struct UpdatePackages {
+    dir: PathBuf,
+}
+
+impl UpdatePackages {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    pub fn list(&self) -> Vec<SavedPackage> {
+        std::fs::read_to_string(self.dir.join(INDEX_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Keep a downloaded package, dropping the oldest beyond the limit
+    pub fn save(&self, version: &str, bytes: &[u8]) -> Result<(), String> {
+        let safe_version: String = version
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+'))
+            .collect();
+        if safe_version.is_empty() {
+            return Err(format!("Invalid update version: {}", version));
+        }
+
+        let file = format!("{}.pkg", safe_version);
+        write_atomic(&self.dir.join(&file), bytes)
+            .map_err(|e| format!("Failed to save update package: {}", e))?;
+
+        let mut packages = self.list();
+        packages.retain(|p| p.version != version);
+        packages.push(SavedPackage {
+            version: version.to_string(),
+            file,
+            saved_at: chrono::Utc::now().to_rfc3339(),
+        });
+        let excess = packages.len().saturating_sub(MAX_PACKAGES);
+        for old in packages.drain(..excess) {
+            let _ = std::fs::remove_file(self.dir.join(&old.file));
+        }
+        self.write_index(&packages)
+    }
+
+    /// The package downloaded before the one for `current_version`
+    pub fn rollback_target(&self, current_version: &str) -> Option<SavedPackage> {
+        let packages = self.list();
+        let current = packages.iter().position(|p| p.version == current_version)?;
+        current
+            .checked_sub(1)
+            .map(|previous| packages[previous].clone())
+    }
+
+    pub fn read(&self, package: &SavedPackage) -> Result<Vec<u8>, String> {
+        std::fs::read(self.dir.join(&package.file))
+            .map_err(|e| format!("Failed to read update package: {}", e))
+    }
+
+    fn write_index(&self, packages: &[SavedPackage]) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(packages)
+            .map_err(|e| format!("Failed to serialize update index: {}", e))?;
+        write_atomic(&self.dir.join(INDEX_FILE), json)
+            .map_err(|e| format!("Failed to save update index: {}", e))
+    }
+}
+
+// ============================================================================
+// Bandwidth Limit
+// ============================================================================
+
+/// Paces a download so its average rate stays under a limit
+pub struct Throttle {
+    bytes_per_sec: Option<u64>,
+    transferred: u64,
+}
+
+impl Throttle {
+    pub fn new(limit_kbps: Option<u32>) -> Self {
+        Self {
+            bytes_per_sec: limit_kbps.map(|kbps| kbps as u64 * 1024),
+            transferred: 0,
+        }
+    }
+
+    /// How long to wait after a chunk arrives, given the time since the
+    /// download started
+    pub fn pause_after(&mut self, chunk: usize, elapsed: Duration) -> Duration {
+        self.transferred += chunk as u64;
+        match self.bytes_per_sec {
+            Some(rate) if rate > 0 => {
+                Duration::from_secs_f64(self.transferred as f64 / rate as f64)
+                    .saturating_sub(elapsed)
+            }
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_rollback_target() {
+        let temp = TempDir::new().unwrap();
+        let packages = UpdatePackages::new(temp.path());
+        assert!(packages.rollback_target("1.0.0").is_none());
+
+        packages.save("1.0.0", b"one").unwrap();
+        assert!(packages.rollback_target("1.0.0").is_none());
+        packages.save("1.1.0", b"two").unwrap();
+        let target = packages.rollback_target("1.1.0").unwrap();
+        assert_eq!(target.version, "1.0.0");
+        assert_eq!(packages.read(&target).unwrap(), b"one");
+
+        // Only the last two are kept
+        packages.save("1.2.0-beta.1", b"three").unwrap();
+        assert_eq!(
+            packages
+                .list()
+                .iter()
+                .map(|p| p.version.as_str())
+                .collect::<Vec<_>>(),
+            vec!["1.1.0", "1.2.0-beta.1"]
+        );
+        assert!(!temp.path().join("1.0.0.pkg").exists());
+        assert!(packages.rollback_target("1.1.0").is_none());
+        assert!(packages.rollback_target("0.9.0").is_none());
+
+        assert!(packages.save("///", b"x").is_err());
+    }
+
+    #[test]
+    fn test_settings() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("updates.json");
+        assert_eq!(UpdateSettings::load(&path), UpdateSettings::default());
+
+        let settings = UpdateSettings {
+            channel: UpdateChannel::Beta,
+            skipped_versions: vec!["1.1.0".to_string()],
+            background_download: true,
+            bandwidth_limit_kbps: Some(512),
+        };
+        settings.save(&path).unwrap();
+        let loaded = UpdateSettings::load(&path);
+        assert_eq!(loaded, settings);
+        assert!(loaded.is_skipped("1.1.0"));
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .contains("\"channel\": \"beta\""));
+        assert!(UpdateChannel::Beta.manifest_url().contains("/beta/"));
+
+        assert!(settings.validate().is_ok());
+        let settings = UpdateSettings {
+            bandwidth_limit_kbps: Some(0),
+            ..settings
+        };
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_throttle() {
+        let mut unlimited = Throttle::new(None);
+        assert_eq!(
+            unlimited.pause_after(1024 * 1024, Duration::ZERO),
+            Duration::ZERO
+        );
+
+        // 100 KB/s: 50 KB should take half a second
+        let mut throttle = Throttle::new(Some(100));
+        assert_eq!(
+            throttle.pause_after(50 * 1024, Duration::from_millis(100)),
+            Duration::from_millis(400)
+        );
+        // Already slower than the limit
+        assert_eq!(
+            throttle.pause_after(50 * 1024, Duration::from_secs(2)),
+            Duration::ZERO
+        );
+    }
+}
@@ -1,12 +1,20 @@
 // Vector Store - SQLite-based vector storage with cosine similarity search
 //
 // Stores document chunks with their embeddings and provides semantic search
-// using cosine similarity.
+// using cosine similarity, plus BM25 keyword search through an FTS5 index of
+// the chunk text.
+//
+// Once a store holds enough chunks, similarity search goes through an HNSW
+// index (see hnsw_index) saved next to the database instead of scanning every
+// embedding. Each change to the chunks bumps a generation number in the
+// database; an index file from another generation is rebuilt on first use.
 
-use rusqlite::{params, Connection};
+use crate::services::hnsw_index::HnswIndex;
+use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
 use tracing::{debug, error, info, warn};
 
@@ -63,6 +71,12 @@ impl Default for IndexStatus {
 pub struct SearchResult {
     pub chunk: DocumentChunk,
     pub score: f32,
+    /// Cosine similarity to the query, when `score` combines several signals
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vector_score: Option<f32>,
+    /// BM25 relevance for chunks matched by keyword search
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keyword_score: Option<f32>,
 }
 
 /// Document chunk metadata for search results (without embedding)
@@ -85,12 +99,58 @@ pub struct ChunkMetadata {
     pub token_estimate: u32,
 }
 
+/// Conditions on indexed files, all of which must hold
+#[derive(Debug, Clone, Default)]
+pub struct FileFilter {
+    /// Only files under this absolute path
+    pub path_prefix: Option<String>,
+    /// Lowercase words that must each appear somewhere in the file
+    pub terms: Vec<String>,
+    /// Tags the file must all have
+    pub tags: Vec<String>,
+    /// Modification time bounds, in Unix seconds (inclusive, exclusive)
+    pub modified_after: Option<i64>,
+    pub modified_before: Option<i64>,
+}
+
+/// An indexed file matched by a FileFilter
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedFile {
+    pub project_path: String,
+    /// Absolute path
+    pub file_path: String,
+    pub mtime: i64,
+    pub tags: Vec<String>,
+}
+
 // ============================================================================
 // Vector Store
 // ============================================================================
 
+/// Chunk count from which similarity search uses the HNSW index
+const ANN_MIN_CHUNKS: usize = 2000;
+
+/// Minimum time between saves of the HNSW index, unless forced
+const ANN_SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
 pub struct VectorStore {
     conn: Arc<Mutex<Connection>>,
+    ann: Arc<Mutex<AnnState>>,
+    ann_path: PathBuf,
+    ann_min_chunks: usize,
+}
+
+/// In-memory HNSW index and its relation to the copy on disk
+#[derive(Default)]
+struct AnnState {
+    index: Option<HnswIndex>,
+    /// Whether the file on disk has been checked
+    loaded: bool,
+    /// The store has chunks the index doesn't reflect, so it must be rebuilt
+    stale: bool,
+    /// The index has changes not yet saved
+    dirty: bool,
+    last_saved: Option<Instant>,
 }
 
 impl VectorStore {
@@ -139,6 +199,44 @@ impl VectorStore {
         )
         .ok();
 
+        // Full-text index over chunk text for keyword search, keyed by the
+        // chunk's rowid. It is kept in sync by hand: INSERT OR REPLACE doesn't
+        // fire delete triggers.
+        let has_fts = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'chunks_fts'",
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|count| count > 0)
+            .unwrap_or(false);
+
+        conn.execute(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS chunks_fts USING fts5(content, heading)",
+            [],
+        )
+        .map_err(|e| format!("Failed to create full-text index: {}", e))?;
+
+        if !has_fts {
+            // Databases created before keyword search: index existing chunks
+            conn.execute(
+                "INSERT INTO chunks_fts (rowid, content, heading)
+                 SELECT rowid, content, heading FROM document_chunks",
+                [],
+            )
+            .map_err(|e| format!("Failed to build full-text index: {}", e))?;
+        }
+
+        // Generation counter for detecting an out-of-date HNSW index file
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS store_meta (
+                key TEXT PRIMARY KEY,
+                value INTEGER NOT NULL
+            )",
+            [],
+        )
+        .map_err(|e| format!("Failed to create store_meta table: {}", e))?;
+
         // Create indexed_files table for tracking file modification times (incremental indexing)
         conn.execute(
             "CREATE TABLE IF NOT EXISTS indexed_files (
@@ -153,10 +251,28 @@ impl VectorStore {
         )
         .map_err(|e| format!("Failed to create indexed_files table: {}", e))?;
 
+        // Tags of indexed files, keyed like indexed_files
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS file_tags (
+                project_path TEXT NOT NULL,
+                file_path TEXT NOT NULL,
+                tag TEXT NOT NULL,
+                PRIMARY KEY (project_path, file_path, tag)
+            )",
+            [],
+        )
+        .map_err(|e| format!("Failed to create file_tags table: {}", e))?;
+
+        conn.execute("CREATE INDEX IF NOT EXISTS idx_tag ON file_tags(tag)", [])
+            .ok();
+
         info!("Vector store initialized at {:?}", db_path);
 
         Ok(Self {
             conn: Arc::new(Mutex::new(conn)),
+            ann: Arc::new(Mutex::new(AnnState::default())),
+            ann_path: db_path.with_extension("hnsw"),
+            ann_min_chunks: ANN_MIN_CHUNKS,
         })
     }
 
@@ -169,6 +285,7 @@ impl VectorStore {
         let conn = self.conn.lock().await;
 
         let mut count = 0;
+        let mut replaced_ids: Vec<String> = Vec::new();
         for chunk in &chunks {
             // Convert embedding to bytes
             let embedding_bytes: Vec<u8> = chunk
@@ -177,6 +294,39 @@ impl VectorStore {
                 .flat_map(|f| f.to_le_bytes())
                 .collect();
 
+            // Rows replaced through the (project, file, index) constraint
+            // under another id need to leave the HNSW index too
+            match conn
+                .prepare_cached(
+                    "SELECT id FROM document_chunks
+                     WHERE id != ?1 AND project_path = ?2 AND file_path = ?3 AND chunk_index = ?4",
+                )
+                .and_then(|mut stmt| {
+                    stmt.query_map(
+                        params![chunk.id, chunk.project_path, chunk.file_path, chunk.chunk_index],
+                        |row| row.get::<_, String>(0),
+                    )?
+                    .collect::<Result<Vec<_>, _>>()
+                }) {
+                Ok(ids) => replaced_ids.extend(ids),
+                Err(e) => {
+                    error!("Failed to look up chunk {}: {}", chunk.id, e);
+                    continue;
+                }
+            }
+
+            // Drop the full-text entries of any rows this insert replaces
+            if let Err(e) = conn.execute(
+                "DELETE FROM chunks_fts WHERE rowid IN (
+                     SELECT rowid FROM document_chunks
+                     WHERE id = ?1 OR (project_path = ?2 AND file_path = ?3 AND chunk_index = ?4)
+                 )",
+                params![chunk.id, chunk.project_path, chunk.file_path, chunk.chunk_index],
+            ) {
+                error!("Failed to unindex chunk {}: {}", chunk.id, e);
+                continue;
+            }
+
             let result = conn.execute(
                 "INSERT OR REPLACE INTO document_chunks
                  (id, project_path, file_path, chunk_index, content, heading, embedding, created_at)
@@ -193,6 +343,13 @@ impl VectorStore {
                 ],
             );
 
+            let result = result.and_then(|_| {
+                conn.execute(
+                    "INSERT INTO chunks_fts (rowid, content, heading) VALUES (?1, ?2, ?3)",
+                    params![conn.last_insert_rowid(), chunk.content, chunk.heading],
+                )
+            });
+
             match result {
                 Ok(_) => count += 1,
                 Err(e) => error!("Failed to insert chunk {}: {}", chunk.id, e),
@@ -200,9 +357,282 @@ impl VectorStore {
         }
 
         debug!("Upserted {} chunks", count);
+
+        let mut guard = self.ann.lock().await;
+        let ann = &mut *guard;
+        self.ensure_ann_loaded(&conn, ann);
+        let generation = bump_generation(&conn)?;
+        if ann.index.is_none() && !ann.stale {
+            if let Some(first) = chunks.first() {
+                ann.index = Some(HnswIndex::new(first.embedding.len()));
+            }
+        }
+
+        let mut dimensions_changed = false;
+        if let Some(index) = ann.index.as_mut() {
+            for id in &replaced_ids {
+                index.remove(id);
+            }
+            for chunk in &chunks {
+                if index.insert(&chunk.id, &chunk.embedding).is_err() {
+                    dimensions_changed = true;
+                    break;
+                }
+            }
+            index.generation = generation;
+        }
+        if dimensions_changed {
+            // Embedding size changed (e.g. a new model): start over
+            warn!("Embedding dimensions changed, rebuilding vector index");
+            ann.index = None;
+            ann.stale = true;
+        }
+        ann.dirty = true;
+
         Ok(count)
     }
 
+    // ========================================================================
+    // HNSW Index
+    // ========================================================================
+
+    /// Read the saved HNSW index the first time it's needed. An index from
+    /// another generation, or a store with chunks but no index, is marked
+    /// stale so it gets rebuilt.
+    fn ensure_ann_loaded(&self, conn: &Connection, ann: &mut AnnState) {
+        if ann.loaded {
+            return;
+        }
+        ann.loaded = true;
+
+        let generation = current_generation(conn).unwrap_or(0);
+        if self.ann_path.exists() {
+            match HnswIndex::load(&self.ann_path) {
+                Ok(index) if index.generation == generation => {
+                    info!("Loaded vector index with {} chunks", index.len());
+                    ann.index = Some(index);
+                    return;
+                }
+                Ok(_) => info!("Vector index is out of date, it will be rebuilt"),
+                Err(e) => warn!("{}, it will be rebuilt", e),
+            }
+        }
+
+        let has_chunks = conn
+            .query_row("SELECT EXISTS(SELECT 1 FROM document_chunks)", [], |row| {
+                row.get::<_, bool>(0)
+            })
+            .unwrap_or(true);
+        ann.stale = has_chunks;
+    }
+
+    /// Build the HNSW index from every stored embedding
+    fn rebuild_ann(&self, conn: &Connection, ann: &mut AnnState) -> Result<(), String> {
+        let started = Instant::now();
+        let mut stmt = conn
+            .prepare("SELECT id, embedding FROM document_chunks")
+            .map_err(|e| format!("Failed to prepare query: {}", e))?;
+        let mut rows = stmt.query([]).map_err(|e| format!("Query failed: {}", e))?;
+
+        let mut index: Option<HnswIndex> = None;
+        while let Some(row) = rows.next().map_err(|e| format!("Row error: {}", e))? {
+            let id: String = row.get(0).map_err(|e| format!("Get id: {}", e))?;
+            let embedding_blob: Vec<u8> = row.get(1).map_err(|e| format!("Get embedding: {}", e))?;
+            let embedding: Vec<f32> = embedding_blob
+                .chunks_exact(4)
+                .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                .collect();
+
+            let index = index.get_or_insert_with(|| HnswIndex::new(embedding.len()));
+            if let Err(e) = index.insert(&id, &embedding) {
+                warn!("Skipping chunk {} in vector index: {}", id, e);
+            }
+        }
+
+        if let Some(index) = index.as_mut() {
+            index.generation = current_generation(conn)?;
+            info!(
+                "Built vector index with {} chunks in {:?}",
+                index.len(),
+                started.elapsed()
+            );
+        }
+        ann.index = index;
+        ann.stale = false;
+        ann.dirty = true;
+        Ok(())
+    }
+
+    /// Search through the HNSW index. Returns None when the store is small
+    /// enough to scan, or when a project filter leaves too few approximate
+    /// matches and an exact scan should be used instead.
+    async fn ann_search(
+        &self,
+        query_embedding: &[f32],
+        top_k: usize,
+        project_filter: Option<&[String]>,
+        min_score: Option<f32>,
+    ) -> Result<Option<Vec<SearchResult>>, String> {
+        let conn = self.conn.lock().await;
+
+        let chunk_count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM document_chunks", [], |row| row.get(0))
+            .map_err(|e| format!("Count failed: {}", e))?;
+        if (chunk_count as usize) < self.ann_min_chunks || top_k == 0 {
+            return Ok(None);
+        }
+
+        let mut ann = self.ann.lock().await;
+        self.ensure_ann_loaded(&conn, &mut ann);
+        if ann.stale {
+            self.rebuild_ann(&conn, &mut ann)?;
+        }
+        let Some(index) = ann.index.as_ref() else {
+            return Ok(None);
+        };
+        if index.dim() != query_embedding.len() {
+            return Ok(None);
+        }
+        if index.is_empty() {
+            return Ok(Some(Vec::new()));
+        }
+
+        let ef = (top_k * 10).max(100);
+        let matches = index.search(query_embedding, ef, ef);
+        drop(ann);
+        if matches.is_empty() {
+            return Ok(Some(Vec::new()));
+        }
+
+        let placeholders: Vec<&str> = matches.iter().map(|_| "?").collect();
+        let sql = format!(
+            "SELECT id, project_path, file_path, chunk_index, content, heading
+             FROM document_chunks
+             WHERE id IN ({})",
+            placeholders.join(",")
+        );
+        let mut stmt = conn
+            .prepare(&sql)
+            .map_err(|e| format!("Failed to prepare query: {}", e))?;
+        let ids: Vec<&dyn rusqlite::ToSql> = matches
+            .iter()
+            .map(|(id, _)| id as &dyn rusqlite::ToSql)
+            .collect();
+        let mut rows = stmt
+            .query(ids.as_slice())
+            .map_err(|e| format!("Query failed: {}", e))?;
+
+        let mut chunks = std::collections::HashMap::new();
+        while let Some(row) = rows.next().map_err(|e| format!("Row error: {}", e))? {
+            let content: String = row.get(4).map_err(|e| format!("Get content: {}", e))?;
+            let chunk = DocumentChunk {
+                id: row.get(0).map_err(|e| format!("Get id: {}", e))?,
+                project_path: row.get(1).map_err(|e| format!("Get project_path: {}", e))?,
+                file_path: row.get(2).map_err(|e| format!("Get file_path: {}", e))?,
+                chunk_index: row.get(3).map_err(|e| format!("Get chunk_index: {}", e))?,
+                content: content.clone(),
+                metadata: ChunkMetadata {
+                    heading: row.get(5).ok(),
+                    section: None,
+                    token_estimate: (content.len() / 4) as u32,
+                },
+            };
+            chunks.insert(chunk.id.clone(), chunk);
+        }
+
+        let threshold = min_score.unwrap_or(0.0);
+        let filter = project_filter.filter(|p| !p.is_empty());
+        let results: Vec<SearchResult> = matches
+            .into_iter()
+            .filter(|(_, score)| *score >= threshold)
+            .filter_map(|(id, score)| chunks.remove(&id).map(|chunk| (chunk, score)))
+            .filter(|(chunk, _)| filter.map_or(true, |p| p.contains(&chunk.project_path)))
+            .take(top_k)
+            .map(|(chunk, score)| SearchResult {
+                chunk,
+                score,
+                vector_score: None,
+                keyword_score: None,
+            })
+            .collect();
+
+        if filter.is_some() && results.len() < top_k {
+            return Ok(None);
+        }
+
+        debug!("HNSW search returned {} results", results.len());
+        Ok(Some(results))
+    }
+
+    /// Save the HNSW index if it has unsaved changes. Unless forced, saves
+    /// are spaced out so a burst of edits doesn't rewrite the file each time.
+    pub async fn persist_ann(&self, force: bool) -> Result<(), String> {
+        let mut ann = self.ann.lock().await;
+        if !ann.dirty {
+            return Ok(());
+        }
+        if !force && ann.last_saved.is_some_and(|t| t.elapsed() < ANN_SAVE_INTERVAL) {
+            return Ok(());
+        }
+
+        match ann.index.as_mut() {
+            Some(index) => {
+                if index.needs_compaction() {
+                    index.compact();
+                }
+                index.save(&self.ann_path)?;
+            }
+            None => {
+                if self.ann_path.exists() {
+                    std::fs::remove_file(&self.ann_path)
+                        .map_err(|e| format!("Failed to remove vector index: {}", e))?;
+                }
+            }
+        }
+
+        ann.dirty = false;
+        ann.last_saved = Some(Instant::now());
+        debug!("Saved vector index to {:?}", self.ann_path);
+        Ok(())
+    }
+
+    /// Make sure a large store has an up-to-date HNSW index, building it if
+    /// needed, and save it (after a full index of a project)
+    pub async fn refresh_ann(&self) -> Result<(), String> {
+        {
+            let conn = self.conn.lock().await;
+            let chunk_count: i64 = conn
+                .query_row("SELECT COUNT(*) FROM document_chunks", [], |row| row.get(0))
+                .map_err(|e| format!("Count failed: {}", e))?;
+
+            let mut ann = self.ann.lock().await;
+            self.ensure_ann_loaded(&conn, &mut ann);
+            if ann.stale && chunk_count as usize >= self.ann_min_chunks {
+                self.rebuild_ann(&conn, &mut ann)?;
+            }
+        }
+        self.persist_ann(true).await
+    }
+
+    /// Mirror removed chunks in the HNSW index
+    fn remove_from_ann(
+        &self,
+        conn: &Connection,
+        ann: &mut AnnState,
+        ids: &[String],
+    ) -> Result<(), String> {
+        self.ensure_ann_loaded(conn, ann);
+        let generation = bump_generation(conn)?;
+        if let Some(index) = ann.index.as_mut() {
+            for id in ids {
+                index.remove(id);
+            }
+            index.generation = generation;
+        }
+        ann.dirty = true;
+        Ok(())
+    }
+
     /// Search for chunks similar to the query embedding
     ///
     /// Uses a bounded min-heap to efficiently track top-k results without
@@ -221,6 +651,13 @@ impl VectorStore {
         // Maximum chunks to scan (prevents runaway queries on large datasets)
         const MAX_SCAN_LIMIT: usize = 10000;
 
+        if let Some(results) = self
+            .ann_search(query_embedding, top_k, project_filter, min_score)
+            .await?
+        {
+            return Ok(results);
+        }
+
         let conn = self.conn.lock().await;
 
         // Build query with optional project filter
@@ -337,6 +774,8 @@ impl VectorStore {
                     },
                 },
                 score,
+                vector_score: None,
+                keyword_score: None,
             };
 
             heap.push(ScoredResult { score, result });
@@ -356,6 +795,97 @@ impl VectorStore {
         Ok(results)
     }
 
+    /// Search chunk text for the words of a query, best BM25 matches first
+    ///
+    /// Each result's `keyword_score` holds its BM25 relevance (higher is
+    /// better), and `score`/`vector_score` its cosine similarity to
+    /// `query_embedding`, so keyword-only hits can be ranked alongside vector
+    /// results.
+    pub async fn keyword_search(
+        &self,
+        query: &str,
+        query_embedding: &[f32],
+        limit: usize,
+        project_filter: Option<&[String]>,
+    ) -> Result<Vec<SearchResult>, String> {
+        let terms = query_terms(query);
+        if terms.is_empty() || limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        // Quote each term so FTS5 operators in the query are taken literally
+        let match_expr = terms
+            .iter()
+            .map(|t| format!("\"{}\"", t))
+            .collect::<Vec<_>>()
+            .join(" OR ");
+
+        let mut values: Vec<rusqlite::types::Value> = vec![match_expr.into()];
+        let project_clause = match project_filter {
+            Some(projects) if !projects.is_empty() => {
+                values.extend(projects.iter().map(|p| p.clone().into()));
+                let placeholders: Vec<&str> = projects.iter().map(|_| "?").collect();
+                format!("AND c.project_path IN ({})", placeholders.join(","))
+            }
+            _ => String::new(),
+        };
+        values.push((limit as i64).into());
+
+        let sql = format!(
+            "SELECT c.id, c.project_path, c.file_path, c.chunk_index, c.content, c.heading, c.embedding,
+                    bm25(chunks_fts) AS rank
+             FROM chunks_fts
+             JOIN document_chunks c ON c.rowid = chunks_fts.rowid
+             WHERE chunks_fts MATCH ? {}
+             ORDER BY rank
+             LIMIT ?",
+            project_clause
+        );
+
+        let conn = self.conn.lock().await;
+        let mut stmt = conn
+            .prepare(&sql)
+            .map_err(|e| format!("Failed to prepare query: {}", e))?;
+        let mut rows = stmt
+            .query(rusqlite::params_from_iter(values))
+            .map_err(|e| format!("Query failed: {}", e))?;
+
+        let mut results = Vec::new();
+        while let Some(row) = rows.next().map_err(|e| format!("Row error: {}", e))? {
+            let embedding_blob: Vec<u8> = row.get(6).map_err(|e| format!("Get embedding: {}", e))?;
+            let embedding: Vec<f32> = embedding_blob
+                .chunks_exact(4)
+                .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                .collect();
+            let similarity = cosine_similarity(query_embedding, &embedding);
+
+            // bm25() is lower for better matches
+            let rank: f64 = row.get(7).map_err(|e| format!("Get rank: {}", e))?;
+            let content: String = row.get(4).map_err(|e| format!("Get content: {}", e))?;
+
+            results.push(SearchResult {
+                chunk: DocumentChunk {
+                    id: row.get(0).map_err(|e| format!("Get id: {}", e))?,
+                    project_path: row.get(1).map_err(|e| format!("Get project_path: {}", e))?,
+                    file_path: row.get(2).map_err(|e| format!("Get file_path: {}", e))?,
+                    chunk_index: row.get(3).map_err(|e| format!("Get chunk_index: {}", e))?,
+                    content: content.clone(),
+                    metadata: ChunkMetadata {
+                        heading: row.get(5).ok(),
+                        section: None,
+                        token_estimate: (content.len() / 4) as u32,
+                    },
+                },
+                score: similarity,
+                vector_score: Some(similarity),
+                keyword_score: Some(-rank as f32),
+            });
+        }
+
+        debug!("Keyword search found {} results for: {}", results.len(), query);
+        Ok(results)
+    }
+
     /// Get index status for projects
     pub async fn get_status(&self, project_path: Option<&str>) -> Result<Vec<IndexStatus>, String> {
         let conn = self.conn.lock().await;
@@ -487,10 +1017,24 @@ impl VectorStore {
     pub async fn delete_project_complete(&self, project_path: &str) -> Result<usize, String> {
         let conn = self.conn.lock().await;
 
+        let removed_ids = chunk_ids(
+            &conn,
+            "SELECT id FROM document_chunks WHERE project_path = ?1",
+            params![project_path],
+        )?;
+
         conn.execute("BEGIN TRANSACTION", [])
             .map_err(|e| format!("Begin transaction failed: {}", e))?;
 
         let result = (|| {
+            conn.execute(
+                "DELETE FROM chunks_fts WHERE rowid IN (
+                     SELECT rowid FROM document_chunks WHERE project_path = ?1
+                 )",
+                params![project_path],
+            )
+            .map_err(|e| format!("Delete full-text entries failed: {}", e))?;
+
             let deleted = conn
                 .execute(
                     "DELETE FROM document_chunks WHERE project_path = ?1",
@@ -504,6 +1048,12 @@ impl VectorStore {
             )
             .map_err(|e| format!("Delete tracking failed: {}", e))?;
 
+            conn.execute(
+                "DELETE FROM file_tags WHERE project_path = ?1",
+                params![project_path],
+            )
+            .map_err(|e| format!("Delete tags failed: {}", e))?;
+
             Ok::<usize, String>(deleted)
         })();
 
@@ -515,6 +1065,8 @@ impl VectorStore {
                     "Deleted {} chunks and all tracking for project {} (atomic)",
                     deleted, project_path
                 );
+                let mut ann = self.ann.lock().await;
+                self.remove_from_ann(&conn, &mut ann, &removed_ids)?;
                 Ok(deleted)
             }
             Err(e) => {
@@ -524,43 +1076,157 @@ impl VectorStore {
         }
     }
 
-    /// Delete file data atomically (chunks + tracking in single transaction)
+    /// Delete the data of a file, or of every file in a folder, atomically
+    /// (chunks + tracking in single transaction). The path may be absolute
+    /// or relative to the project.
     pub async fn delete_file_complete(
         &self,
         project_path: &str,
         file_path: &str,
     ) -> Result<usize, String> {
+        let keys = PathKeys::new(project_path, file_path);
+        if keys.relative.is_empty() {
+            return self.delete_project_complete(project_path).await;
+        }
+
         let conn = self.conn.lock().await;
 
+        let removed_ids = chunk_ids(
+            &conn,
+            &format!("SELECT id FROM document_chunks WHERE {}", CHUNK_PATH_MATCH),
+            params![project_path, keys.relative, keys.relative_prefix],
+        )?;
+
         conn.execute("BEGIN TRANSACTION", [])
             .map_err(|e| format!("Begin transaction failed: {}", e))?;
 
+        let result = delete_path_rows(&conn, project_path, &keys);
+
+        match result {
+            Ok(deleted) => {
+                conn.execute("COMMIT", [])
+                    .map_err(|e| format!("Commit failed: {}", e))?;
+                debug!(
+                    "Deleted {} chunks and tracking for {} (atomic)",
+                    deleted, file_path
+                );
+                let mut ann = self.ann.lock().await;
+                self.remove_from_ann(&conn, &mut ann, &removed_ids)?;
+                Ok(deleted)
+            }
+            Err(e) => {
+                conn.execute("ROLLBACK", []).ok();
+                Err(e)
+            }
+        }
+    }
+
+    /// Re-key the data of a moved or renamed file or folder to its new path,
+    /// keeping the embeddings (atomic). Anything already indexed at the new
+    /// path is replaced. Returns the number of chunks moved.
+    pub async fn move_path_complete(
+        &self,
+        project_path: &str,
+        from_path: &str,
+        to_path: &str,
+    ) -> Result<usize, String> {
+        let from = PathKeys::new(project_path, from_path);
+        let to = PathKeys::new(project_path, to_path);
+        if from.relative.is_empty() || to.relative.is_empty() {
+            return Err("Cannot move the project folder itself".to_string());
+        }
+
+        let conn = self.conn.lock().await;
+
+        let replaced_ids = chunk_ids(
+            &conn,
+            &format!("SELECT id FROM document_chunks WHERE {}", CHUNK_PATH_MATCH),
+            params![project_path, to.relative, to.relative_prefix],
+        )?;
+        let moved: Vec<(String, String)> = {
+            let mut stmt = conn
+                .prepare(&format!(
+                    "SELECT id, file_path FROM document_chunks WHERE {}",
+                    CHUNK_PATH_MATCH
+                ))
+                .map_err(|e| format!("Prepare failed: {}", e))?;
+            let rows = stmt
+                .query_map(
+                    params![project_path, from.relative, from.relative_prefix],
+                    |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+                )
+                .map_err(|e| format!("Query failed: {}", e))?;
+            rows.collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Row error: {}", e))?
+        };
+
+        conn.execute("BEGIN TRANSACTION", [])
+            .map_err(|e| format!("Begin transaction failed: {}", e))?;
+
+        let mut renamed_ids: Vec<(String, String)> = Vec::new();
         let result = (|| {
-            let deleted = conn
-                .execute(
-                    "DELETE FROM document_chunks WHERE project_path = ?1 AND file_path = ?2",
-                    params![project_path, file_path],
+            delete_path_rows(&conn, project_path, &to)?;
+
+            for (id, file_path) in &moved {
+                let new_file_path =
+                    format!("{}{}", to.relative, &file_path[from.relative.len()..]);
+                // Chunk ids are "<project>:<file>:<index>"
+                let old_prefix = format!("{}:{}:", project_path, file_path);
+                let new_id = match id.strip_prefix(&old_prefix) {
+                    Some(index) => format!("{}:{}:{}", project_path, new_file_path, index),
+                    None => id.clone(),
+                };
+                conn.execute(
+                    "UPDATE document_chunks SET id = ?1, file_path = ?2 WHERE id = ?3",
+                    params![new_id, new_file_path, id],
                 )
-                .map_err(|e| format!("Delete chunks failed: {}", e))?;
+                .map_err(|e| format!("Move chunk failed: {}", e))?;
+                if &new_id != id {
+                    renamed_ids.push((id.clone(), new_id));
+                }
+            }
 
             conn.execute(
-                "DELETE FROM indexed_files WHERE project_path = ?1 AND file_path = ?2",
-                params![project_path, file_path],
+                &format!(
+                    "UPDATE indexed_files SET file_path = ?4 || substr(file_path, length(?2) + 1)
+                     WHERE {}",
+                    TRACKING_PATH_MATCH
+                ),
+                params![project_path, from.absolute, from.absolute_prefix, to.absolute],
             )
-            .map_err(|e| format!("Delete tracking failed: {}", e))?;
+            .map_err(|e| format!("Move tracking failed: {}", e))?;
 
-            Ok::<usize, String>(deleted)
+            conn.execute(
+                &format!(
+                    "UPDATE file_tags SET file_path = ?4 || substr(file_path, length(?2) + 1)
+                     WHERE {}",
+                    TRACKING_PATH_MATCH
+                ),
+                params![project_path, from.absolute, from.absolute_prefix, to.absolute],
+            )
+            .map_err(|e| format!("Move tags failed: {}", e))?;
+
+            Ok::<usize, String>(moved.len())
         })();
 
         match result {
-            Ok(deleted) => {
+            Ok(count) => {
                 conn.execute("COMMIT", [])
                     .map_err(|e| format!("Commit failed: {}", e))?;
                 debug!(
-                    "Deleted {} chunks and tracking for file {} (atomic)",
-                    deleted, file_path
+                    "Moved {} chunks from {} to {} (atomic)",
+                    count, from_path, to_path
                 );
-                Ok(deleted)
+
+                let mut guard = self.ann.lock().await;
+                let ann = &mut *guard;
+                self.remove_from_ann(&conn, ann, &replaced_ids)?;
+                if let Some(index) = ann.index.as_mut() {
+                    for (old_id, new_id) in &renamed_ids {
+                        index.rename(old_id, new_id);
+                    }
+                }
+                Ok(count)
             }
             Err(e) => {
                 conn.execute("ROLLBACK", []).ok();
@@ -568,6 +1234,346 @@ impl VectorStore {
             }
         }
     }
+
+    /// Embeddings of a file's chunks, in chunk order. The path may be
+    /// absolute or relative to the project.
+    pub async fn file_embeddings(
+        &self,
+        project_path: &str,
+        file_path: &str,
+    ) -> Result<Vec<Vec<f32>>, String> {
+        let keys = PathKeys::new(project_path, file_path);
+        let conn = self.conn.lock().await;
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT embedding FROM document_chunks
+                 WHERE project_path = ?1 AND file_path = ?2
+                 ORDER BY chunk_index",
+            )
+            .map_err(|e| format!("Prepare failed: {}", e))?;
+        let rows = stmt
+            .query_map(params![project_path, keys.relative], |row| {
+                row.get::<_, Vec<u8>>(0)
+            })
+            .map_err(|e| format!("Query failed: {}", e))?;
+
+        rows.map(|blob| {
+            blob.map(|blob| {
+                blob.chunks_exact(4)
+                    .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                    .collect()
+            })
+            .map_err(|e| format!("Row error: {}", e))
+        })
+        .collect()
+    }
+
+    /// Modification time recorded when a file was last indexed
+    pub async fn indexed_mtime(
+        &self,
+        project_path: &str,
+        file_path: &str,
+    ) -> Result<Option<i64>, String> {
+        let conn = self.conn.lock().await;
+        conn.query_row(
+            "SELECT mtime FROM indexed_files WHERE project_path = ?1 AND file_path = ?2",
+            params![project_path, file_path],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Query failed: {}", e))
+    }
+
+    // ========================================================================
+    // File Queries
+    // ========================================================================
+
+    /// Replace the tags recorded for an indexed file
+    pub async fn set_file_tags(
+        &self,
+        project_path: &str,
+        file_path: &str,
+        tags: &[String],
+    ) -> Result<(), String> {
+        let conn = self.conn.lock().await;
+
+        conn.execute("BEGIN TRANSACTION", [])
+            .map_err(|e| format!("Begin transaction failed: {}", e))?;
+
+        let result = (|| {
+            conn.execute(
+                "DELETE FROM file_tags WHERE project_path = ?1 AND file_path = ?2",
+                params![project_path, file_path],
+            )
+            .map_err(|e| format!("Delete tags failed: {}", e))?;
+            for tag in tags {
+                conn.execute(
+                    "INSERT OR IGNORE INTO file_tags (project_path, file_path, tag)
+                     VALUES (?1, ?2, ?3)",
+                    params![project_path, file_path, tag.to_lowercase()],
+                )
+                .map_err(|e| format!("Insert tag failed: {}", e))?;
+            }
+            Ok::<(), String>(())
+        })();
+
+        match result {
+            Ok(()) => conn
+                .execute("COMMIT", [])
+                .map(|_| ())
+                .map_err(|e| format!("Commit failed: {}", e)),
+            Err(e) => {
+                conn.execute("ROLLBACK", []).ok();
+                Err(e)
+            }
+        }
+    }
+
+    /// Indexed files matching a filter, most recently modified first.
+    /// Answered from the tracking, tag and full-text tables without reading
+    /// any files.
+    pub async fn find_files(&self, filter: &FileFilter) -> Result<Vec<IndexedFile>, String> {
+        let conn = self.conn.lock().await;
+
+        let mut clauses: Vec<String> = Vec::new();
+        let mut values: Vec<rusqlite::types::Value> = Vec::new();
+        if let Some(prefix) = &filter.path_prefix {
+            clauses.push("substr(f.file_path, 1, length(?)) = ?".to_string());
+            values.push(prefix.clone().into());
+            values.push(prefix.clone().into());
+        }
+        if let Some(after) = filter.modified_after {
+            clauses.push("f.mtime >= ?".to_string());
+            values.push(after.into());
+        }
+        if let Some(before) = filter.modified_before {
+            clauses.push("f.mtime < ?".to_string());
+            values.push(before.into());
+        }
+        for tag in &filter.tags {
+            clauses.push(
+                "EXISTS (SELECT 1 FROM file_tags t WHERE t.project_path = f.project_path
+                         AND t.file_path = f.file_path AND t.tag = ?)"
+                    .to_string(),
+            );
+            values.push(tag.to_lowercase().into());
+        }
+        let where_clause = if clauses.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", clauses.join(" AND "))
+        };
+
+        let candidates: Vec<(String, String, i64)> = {
+            let mut stmt = conn
+                .prepare(&format!(
+                    "SELECT f.project_path, f.file_path, f.mtime FROM indexed_files f {}
+                     ORDER BY f.mtime DESC, f.file_path",
+                    where_clause
+                ))
+                .map_err(|e| format!("Prepare failed: {}", e))?;
+            let rows = stmt
+                .query_map(rusqlite::params_from_iter(values), |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+                })
+                .map_err(|e| format!("Query failed: {}", e))?;
+            rows.collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Row error: {}", e))?
+        };
+
+        // Files containing every term, as (project, path relative to project)
+        let mut with_terms: Option<std::collections::HashSet<(String, String)>> = None;
+        for term in &filter.terms {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT DISTINCT c.project_path, c.file_path
+                     FROM chunks_fts
+                     JOIN document_chunks c ON c.rowid = chunks_fts.rowid
+                     WHERE chunks_fts MATCH ?1",
+                )
+                .map_err(|e| format!("Prepare failed: {}", e))?;
+            let rows = stmt
+                .query_map(params![format!("\"{}\"", term.replace('"', ""))], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                })
+                .map_err(|e| format!("Query failed: {}", e))?;
+            let matched = rows
+                .collect::<Result<std::collections::HashSet<_>, _>>()
+                .map_err(|e| format!("Row error: {}", e))?;
+            with_terms = Some(match with_terms {
+                Some(previous) => previous.intersection(&matched).cloned().collect(),
+                None => matched,
+            });
+        }
+
+        let mut tags_stmt = conn
+            .prepare(
+                "SELECT tag FROM file_tags WHERE project_path = ?1 AND file_path = ?2
+                 ORDER BY tag",
+            )
+            .map_err(|e| format!("Prepare failed: {}", e))?;
+
+        let mut files = Vec::new();
+        for (project_path, file_path, mtime) in candidates {
+            if let Some(with_terms) = &with_terms {
+                let keys = PathKeys::new(&project_path, &file_path);
+                if !with_terms.contains(&(project_path.clone(), keys.relative)) {
+                    continue;
+                }
+            }
+            let tags = tags_stmt
+                .query_map(params![project_path, file_path], |row| row.get(0))
+                .map_err(|e| format!("Query failed: {}", e))?
+                .collect::<Result<Vec<String>, _>>()
+                .map_err(|e| format!("Row error: {}", e))?;
+            files.push(IndexedFile {
+                project_path,
+                file_path,
+                mtime,
+                tags,
+            });
+        }
+
+        Ok(files)
+    }
+}
+
+// ============================================================================
+// Paths
+// ============================================================================
+
+/// Chunks of a project at a path or under a folder (?2 path, ?3 path + separator)
+const CHUNK_PATH_MATCH: &str =
+    "project_path = ?1 AND (file_path = ?2 OR substr(file_path, 1, length(?3)) = ?3)";
+
+/// Tracked files of a project at a path or under a folder
+const TRACKING_PATH_MATCH: &str =
+    "project_path = ?1 AND (file_path = ?2 OR substr(file_path, 1, length(?3)) = ?3)";
+
+/// A path in both of the forms the store keeps: chunks are stored under
+/// paths relative to the project, tracked files under absolute paths
+struct PathKeys {
+    relative: String,
+    relative_prefix: String,
+    absolute: String,
+    absolute_prefix: String,
+}
+
+impl PathKeys {
+    fn new(project_path: &str, path: &str) -> Self {
+        let project = Path::new(project_path);
+        let (relative, absolute) = match Path::new(path).strip_prefix(project) {
+            Ok(relative) => (relative.to_string_lossy().to_string(), path.to_string()),
+            Err(_) => (
+                path.to_string(),
+                project.join(path).to_string_lossy().to_string(),
+            ),
+        };
+
+        Self {
+            relative_prefix: format!("{}{}", relative, MAIN_SEPARATOR),
+            absolute_prefix: format!("{}{}", absolute, MAIN_SEPARATOR),
+            relative,
+            absolute,
+        }
+    }
+}
+
+/// Delete the chunks, full-text entries and tracking of a path. Must run
+/// inside a transaction.
+fn delete_path_rows(
+    conn: &Connection,
+    project_path: &str,
+    keys: &PathKeys,
+) -> Result<usize, String> {
+    conn.execute(
+        &format!(
+            "DELETE FROM chunks_fts WHERE rowid IN (
+                 SELECT rowid FROM document_chunks WHERE {}
+             )",
+            CHUNK_PATH_MATCH
+        ),
+        params![project_path, keys.relative, keys.relative_prefix],
+    )
+    .map_err(|e| format!("Delete full-text entries failed: {}", e))?;
+
+    let deleted = conn
+        .execute(
+            &format!("DELETE FROM document_chunks WHERE {}", CHUNK_PATH_MATCH),
+            params![project_path, keys.relative, keys.relative_prefix],
+        )
+        .map_err(|e| format!("Delete chunks failed: {}", e))?;
+
+    conn.execute(
+        &format!("DELETE FROM indexed_files WHERE {}", TRACKING_PATH_MATCH),
+        params![project_path, keys.absolute, keys.absolute_prefix],
+    )
+    .map_err(|e| format!("Delete tracking failed: {}", e))?;
+
+    conn.execute(
+        &format!("DELETE FROM file_tags WHERE {}", TRACKING_PATH_MATCH),
+        params![project_path, keys.absolute, keys.absolute_prefix],
+    )
+    .map_err(|e| format!("Delete tags failed: {}", e))?;
+
+    Ok(deleted)
+}
+
+// ============================================================================
+// Store Generation
+// ============================================================================
+
+fn current_generation(conn: &Connection) -> Result<u64, String> {
+    conn.query_row(
+        "SELECT COALESCE(MAX(value), 0) FROM store_meta WHERE key = 'generation'",
+        [],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|g| g as u64)
+    .map_err(|e| format!("Failed to read store generation: {}", e))
+}
+
+/// Record that the stored chunks changed, returning the new generation
+fn bump_generation(conn: &Connection) -> Result<u64, String> {
+    conn.execute(
+        "INSERT INTO store_meta (key, value) VALUES ('generation', 1)
+         ON CONFLICT(key) DO UPDATE SET value = value + 1",
+        [],
+    )
+    .map_err(|e| format!("Failed to update store generation: {}", e))?;
+    current_generation(conn)
+}
+
+fn chunk_ids(conn: &Connection, sql: &str, params: &[&dyn rusqlite::ToSql]) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare(sql)
+        .map_err(|e| format!("Prepare failed: {}", e))?;
+    let ids = stmt
+        .query_map(params, |row| row.get::<_, String>(0))
+        .map_err(|e| format!("Query failed: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))?;
+    Ok(ids)
+}
+
+// ============================================================================
+// Query Terms
+// ============================================================================
+
+/// Split a query into lowercase words for keyword matching
+pub fn query_terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for term in query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+    {
+        if !terms.contains(&term) {
+            terms.push(term);
+        }
+    }
+    terms
 }
 
 // ============================================================================
@@ -687,6 +1693,226 @@ mod tests {
         assert_eq!(statuses[0].total_chunks, 1);
     }
 
+    #[tokio::test]
+    async fn test_keyword_search() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let store = VectorStore::new(db_path).unwrap();
+
+        let chunk1 = create_test_chunk("1", "Captain Ahab hunts the whale", vec![1.0, 0.0, 0.0]);
+        let chunk2 = create_test_chunk("2", "The sea was calm", vec![0.0, 1.0, 0.0]);
+        store.upsert_chunks(vec![chunk1, chunk2]).await.unwrap();
+
+        let results = store
+            .keyword_search("ahab's \"whale\" OR", &[1.0, 0.0, 0.0], 10, None)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].chunk.id, "1");
+        assert!(results[0].keyword_score.unwrap() > 0.0);
+        assert!(results[0].vector_score.unwrap() > 0.99);
+
+        // Replacing a chunk replaces its full-text entry
+        let chunk1 = create_test_chunk("1", "Ishmael tells the story", vec![1.0, 0.0, 0.0]);
+        store.upsert_chunks(vec![chunk1]).await.unwrap();
+        let results = store
+            .keyword_search("ahab", &[1.0, 0.0, 0.0], 10, None)
+            .await
+            .unwrap();
+        assert!(results.is_empty());
+
+        store
+            .delete_file_complete("/test/project", "test.md")
+            .await
+            .unwrap();
+        let results = store
+            .keyword_search("ishmael sea", &[1.0, 0.0, 0.0], 10, None)
+            .await
+            .unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_hnsw_search_and_persistence() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+
+        let mut store = VectorStore::new(db_path.clone()).unwrap();
+        store.ann_min_chunks = 0;
+        let chunks = vec![
+            create_test_chunk("1", "Hello world", vec![1.0, 0.0, 0.0]),
+            create_test_chunk("2", "Goodbye world", vec![0.0, 1.0, 0.0]),
+            create_test_chunk("3", "Hello there", vec![0.9, 0.1, 0.0]),
+        ];
+        store.upsert_chunks(chunks).await.unwrap();
+
+        let results = store.search(&[1.0, 0.0, 0.0], 2, None, None).await.unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].chunk.id, "1");
+        assert_eq!(results[1].chunk.id, "3");
+
+        store.persist_ann(true).await.unwrap();
+        assert!(dir.path().join("test.hnsw").exists());
+
+        // A reopened store uses the saved index
+        let mut reopened = VectorStore::new(db_path.clone()).unwrap();
+        reopened.ann_min_chunks = 0;
+        let results = reopened
+            .search(&[0.0, 1.0, 0.0], 1, None, None)
+            .await
+            .unwrap();
+        assert_eq!(results[0].chunk.id, "2");
+        assert!(reopened.ann.lock().await.index.is_some());
+
+        // Deleting without saving leaves the file behind; the next store
+        // notices and rebuilds instead of returning deleted chunks
+        reopened
+            .delete_file_complete("/test/project", "test.md")
+            .await
+            .unwrap();
+        let mut rebuilt = VectorStore::new(db_path).unwrap();
+        rebuilt.ann_min_chunks = 0;
+        let results = rebuilt.search(&[1.0, 0.0, 0.0], 2, None, None).await.unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delete_and_move_paths() {
+        let dir = tempdir().unwrap();
+        let store = VectorStore::new(dir.path().join("test.db")).unwrap();
+        let project = "/ws/project";
+
+        let chunk = |file: &str, index: i32, content: &str| StoredChunk {
+            id: format!("{}:{}:{}", project, file, index),
+            project_path: project.to_string(),
+            file_path: file.to_string(),
+            chunk_index: index,
+            content: content.to_string(),
+            heading: None,
+            embedding: vec![1.0, 0.0, 0.0],
+            created_at: chrono::Utc::now().to_rfc3339(),
+        };
+        let notes = format!("notes{}", MAIN_SEPARATOR);
+        store
+            .upsert_chunks(vec![
+                chunk(&format!("{}a.md", notes), 0, "alpha"),
+                chunk(&format!("{}b.md", notes), 1, "bravo"),
+                chunk("notes-old.md", 2, "charlie"),
+            ])
+            .await
+            .unwrap();
+        for file in [format!("{}a.md", notes), format!("{}b.md", notes)] {
+            let absolute = Path::new(project).join(&file).to_string_lossy().to_string();
+            store.track_indexed_file(project, &absolute, 42, 1).await.unwrap();
+        }
+
+        // Moving the folder re-keys its chunks and tracking
+        let from = Path::new(project).join("notes").to_string_lossy().to_string();
+        let to = Path::new(project).join("archive").to_string_lossy().to_string();
+        assert_eq!(store.move_path_complete(project, &from, &to).await.unwrap(), 2);
+
+        let moved_file = format!("archive{}a.md", MAIN_SEPARATOR);
+        let results = store
+            .keyword_search("alpha", &[1.0, 0.0, 0.0], 5, None)
+            .await
+            .unwrap();
+        assert_eq!(results[0].chunk.file_path, moved_file);
+        assert_eq!(results[0].chunk.id, format!("{}:{}:0", project, moved_file));
+        let moved_absolute = Path::new(project).join(&moved_file).to_string_lossy().to_string();
+        assert_eq!(
+            store.indexed_mtime(project, &moved_absolute).await.unwrap(),
+            Some(42)
+        );
+
+        // Deleting the folder by absolute path removes everything under it,
+        // but not siblings that merely share a name prefix
+        assert_eq!(store.delete_file_complete(project, &to).await.unwrap(), 2);
+        let results = store
+            .search(&[1.0, 0.0, 0.0], 10, None, None)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].chunk.file_path, "notes-old.md");
+        assert_eq!(store.get_indexed_files(project).await.unwrap().len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_find_files() {
+        let dir = tempdir().unwrap();
+        let store = VectorStore::new(dir.path().join("test.db")).unwrap();
+        let project = "/ws/project";
+
+        let files = [("a.md", "quarterly plan", 100), ("b.md", "quarterly budget", 200)];
+        let mut chunks = Vec::new();
+        for (index, (file, content, mtime)) in files.iter().enumerate() {
+            chunks.push(StoredChunk {
+                id: format!("{}:{}:0", project, file),
+                project_path: project.to_string(),
+                file_path: file.to_string(),
+                chunk_index: index as i32,
+                content: content.to_string(),
+                heading: None,
+                embedding: vec![1.0, 0.0, 0.0],
+                created_at: chrono::Utc::now().to_rfc3339(),
+            });
+            let absolute = Path::new(project).join(file).to_string_lossy().to_string();
+            store.track_indexed_file(project, &absolute, *mtime, 1).await.unwrap();
+        }
+        store.upsert_chunks(chunks).await.unwrap();
+        let a = Path::new(project).join("a.md").to_string_lossy().to_string();
+        store
+            .set_file_tags(project, &a, &["Work".to_string(), "draft".to_string()])
+            .await
+            .unwrap();
+
+        let paths = |found: Vec<IndexedFile>| -> Vec<String> {
+            found.into_iter().map(|f| f.file_path).collect()
+        };
+        let b = Path::new(project).join("b.md").to_string_lossy().to_string();
+
+        // Newest first
+        let all = store.find_files(&FileFilter::default()).await.unwrap();
+        assert_eq!(all[1].tags, vec!["draft", "work"]);
+        assert_eq!(paths(all), vec![b.clone(), a.clone()]);
+
+        let filter = FileFilter {
+            terms: vec!["quarterly".to_string(), "plan".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(paths(store.find_files(&filter).await.unwrap()), vec![a.clone()]);
+
+        let filter = FileFilter {
+            tags: vec!["work".to_string()],
+            modified_after: Some(150),
+            ..Default::default()
+        };
+        assert!(store.find_files(&filter).await.unwrap().is_empty());
+
+        let filter = FileFilter {
+            path_prefix: Some(project.to_string()),
+            modified_before: Some(150),
+            ..Default::default()
+        };
+        assert_eq!(paths(store.find_files(&filter).await.unwrap()), vec![a.clone()]);
+
+        // Tags go with the file when it is deleted
+        store.delete_file_complete(project, &a).await.unwrap();
+        let filter = FileFilter {
+            tags: vec!["draft".to_string()],
+            ..Default::default()
+        };
+        assert!(store.find_files(&filter).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_query_terms() {
+        assert_eq!(
+            query_terms("Ahab's \"white\" whale, ahab"),
+            vec!["ahab", "s", "white", "whale"]
+        );
+        assert!(query_terms("  -- ").is_empty());
+    }
+
     #[test]
     fn test_cosine_similarity() {
         // Identical vectors
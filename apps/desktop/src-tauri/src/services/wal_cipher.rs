@@ -0,0 +1,144 @@
+// WAL Cipher - Encryption for recovery files
+//
+// When a workspace opts in, WAL content is encrypted with AES-256-GCM before
+// it is written to .midlight/recovery. Each workspace gets its own random key,
+// kept in the app data directory rather than the workspace, so a synced or
+// backed-up workspace folder never carries both the drafts and their key.
+//
+// Encrypted content is base64 of: 12-byte nonce || ciphertext || tag
+//
+// Keys are stored at: {app data}/com.midlight.app/wal-keys/{hash}.key
+
+use crate::services::app_dirs::app_data_dir;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::fs;
+use std::path::{Path, PathBuf};
+use xxhash_rust::xxh64::xxh64;
+
+const KEY_LEN: usize = 32;
+
+/// Default directory for WAL keys
+pub fn default_key_dir() -> PathBuf {
+    app_data_dir().join("wal-keys")
+}
+
+pub struct WalCipher {
+    key: LessSafeKey,
+    rng: SystemRandom,
+}
+
+impl WalCipher {
+    /// Load a workspace's key, creating one if it doesn't exist yet
+    pub fn load_or_create(key_dir: &Path, workspace_root: &Path) -> Result<Self, String> {
+        let key_path = key_dir.join(format!(
+            "{:016x}.key",
+            xxh64(workspace_root.to_string_lossy().as_bytes(), 0)
+        ));
+        let rng = SystemRandom::new();
+
+        let key_bytes = match fs::read(&key_path) {
+            Ok(bytes) if bytes.len() == KEY_LEN => bytes,
+            Ok(_) => return Err("WAL key file is corrupt".to_string()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let mut bytes = vec![0u8; KEY_LEN];
+                rng.fill(&mut bytes)
+                    .map_err(|_| "Failed to generate WAL key".to_string())?;
+                write_key(&key_path, &bytes)?;
+                bytes
+            }
+            Err(e) => return Err(format!("Failed to read WAL key: {}", e)),
+        };
+
+        let key =
+            UnboundKey::new(&AES_256_GCM, &key_bytes).map_err(|_| "Invalid WAL key".to_string())?;
+        Ok(Self {
+            key: LessSafeKey::new(key),
+            rng,
+        })
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> Result<String, String> {
+        let mut nonce_bytes = [0u8; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce_bytes)
+            .map_err(|_| "Failed to generate nonce".to_string())?;
+
+        let mut data = plaintext.as_bytes().to_vec();
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce_bytes),
+                Aad::empty(),
+                &mut data,
+            )
+            .map_err(|_| "Failed to encrypt WAL".to_string())?;
+
+        let mut out = nonce_bytes.to_vec();
+        out.extend_from_slice(&data);
+        Ok(STANDARD.encode(out))
+    }
+
+    pub fn decrypt(&self, encoded: &str) -> Result<String, String> {
+        let data = STANDARD
+            .decode(encoded)
+            .map_err(|e| format!("Failed to decode WAL: {}", e))?;
+        if data.len() < NONCE_LEN {
+            return Err("Encrypted WAL is truncated".to_string());
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        let nonce =
+            Nonce::try_assume_unique_for_key(nonce).map_err(|_| "Invalid WAL nonce".to_string())?;
+
+        let mut buffer = ciphertext.to_vec();
+        let plaintext = self
+            .key
+            .open_in_place(nonce, Aad::empty(), &mut buffer)
+            .map_err(|_| "Failed to decrypt WAL (wrong key or corrupt file)".to_string())?;
+        String::from_utf8(plaintext.to_vec())
+            .map_err(|e| format!("Decrypted WAL is not text: {}", e))
+    }
+}
+
+fn write_key(path: &Path, key: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create key directory: {}", e))?;
+    }
+    fs::write(path, key).map_err(|e| format!("Failed to write WAL key: {}", e))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+            .map_err(|e| format!("Failed to restrict WAL key permissions: {}", e))?;
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_round_trip() {
+        let keys = TempDir::new().unwrap();
+        let cipher = WalCipher::load_or_create(keys.path(), Path::new("/notes")).unwrap();
+
+        let encrypted = cipher.encrypt("secret draft 🎉").unwrap();
+        assert!(!encrypted.contains("secret"));
+        assert_ne!(encrypted, cipher.encrypt("secret draft 🎉").unwrap());
+        assert_eq!(cipher.decrypt(&encrypted).unwrap(), "secret draft 🎉");
+
+        // The key persists; another workspace gets a different one
+        let reloaded = WalCipher::load_or_create(keys.path(), Path::new("/notes")).unwrap();
+        assert_eq!(reloaded.decrypt(&encrypted).unwrap(), "secret draft 🎉");
+        let other = WalCipher::load_or_create(keys.path(), Path::new("/other")).unwrap();
+        assert!(other.decrypt(&encrypted).is_err());
+        assert!(cipher.decrypt("bm9wZQ==").is_err());
+    }
+}
@@ -0,0 +1,679 @@
+// Web Clipper - Files selections from web pages as documents
+//
+// A browser extension (through the local API) or the app itself sends the URL
+// of a page and the HTML of the selection. The HTML is converted to Markdown,
+// images are downloaded into the workspace image store so the clipping still
+// works offline, and the result is saved as a new document in the clippings
+// folder with the page's address and the time it was clipped at the top.
+// Images that can't be downloaded keep their remote address.
+//
+// Settings: `clipper.json` in the app data directory.
+
+use crate::services::app_dirs::app_data_dir;
+use crate::services::atomic_write::write_atomic;
+use crate::services::image_manager::ImageManager;
+use crate::services::web_fetch::{decode_entities, validate_url, MAX_RESPONSE_BYTES};
+use crate::services::workspace_manager::WorkspaceManager;
+use crate::traits::http_client::HttpClient;
+use chrono::Utc;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use tracing::{debug, warn};
+use url::Url;
+
+/// Largest image downloaded
+const MAX_IMAGE_BYTES: usize = MAX_RESPONSE_BYTES;
+
+/// Images downloaded per clipping; the rest keep their remote address
+const MAX_IMAGES: usize = 50;
+
+/// Longest file name given to a clipping, without the extension
+const MAX_TITLE_CHARS: usize = 80;
+
+/// Checkpoint trigger for clipped documents
+const TRIGGER: &str = "clip";
+
+/// Marks where a preformatted block is put back after conversion
+const PLACEHOLDER: char = '\u{E000}';
+
+lazy_static! {
+    static ref COMMENT: Regex = Regex::new(r"(?s)<!--.*?-->").unwrap();
+    static ref SKIPPED: Vec<Regex> = [
+        "script", "style", "noscript", "template", "svg", "iframe", "form", "button", "head",
+    ]
+    .iter()
+    .map(|tag| Regex::new(&format!(r"(?is)<{0}\b[^>]*>.*?</{0}\s*>", tag)).unwrap())
+    .collect();
+    static ref PRE: Regex = Regex::new(r"(?is)<pre\b[^>]*>(.*?)</pre\s*>").unwrap();
+    static ref BLOCKQUOTE: Regex =
+        Regex::new(r"(?is)<blockquote\b[^>]*>(.*?)</blockquote\s*>").unwrap();
+    static ref IMG: Regex = Regex::new(r"(?is)<img\b([^>]*)>").unwrap();
+    static ref LINK: Regex = Regex::new(r"(?is)<a\b([^>]*)>(.*?)</a\s*>").unwrap();
+    static ref HEADING: Regex = Regex::new(r"(?is)<h([1-6])\b[^>]*>(.*?)</h[1-6]\s*>").unwrap();
+    static ref STRONG: Regex = Regex::new(r"(?is)<(?:strong|b)\b[^>]*>(.*?)</(?:strong|b)\s*>").unwrap();
+    static ref EMPHASIS: Regex = Regex::new(r"(?is)<(?:em|i)\b[^>]*>(.*?)</(?:em|i)\s*>").unwrap();
+    static ref CODE: Regex = Regex::new(r"(?is)<code\b[^>]*>(.*?)</code\s*>").unwrap();
+    static ref LIST_ITEM: Regex = Regex::new(r"(?i)<li\b[^>]*>").unwrap();
+    static ref LINE_BREAK: Regex = Regex::new(r"(?i)<br\s*/?>").unwrap();
+    static ref BLOCK: Regex = Regex::new(
+        r"(?i)</?(?:p|div|section|article|main|header|footer|aside|nav|ul|ol|dl|dt|dd|table|thead|tbody|tr|figure|figcaption|hr)\b[^>]*>"
+    )
+    .unwrap();
+    static ref CELL: Regex = Regex::new(r"(?i)</?(?:td|th)\b[^>]*>").unwrap();
+    static ref TAG: Regex = Regex::new(r"(?s)<[^>]+>").unwrap();
+    static ref SPACES: Regex = Regex::new(r"[ \t\r\f\u{a0}]+").unwrap();
+    static ref ATTRIBUTE: Regex =
+        Regex::new(r#"(?is)\b([a-z-]+)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+))"#).unwrap();
+    static ref IMAGE_LINE: Regex = Regex::new(r"^!\[([^\]]*)\]\(([^)\s]+)\)$").unwrap();
+}
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClipperSettings {
+    /// Folder clippings are saved in, relative to the workspace root
+    #[serde(default = "default_folder")]
+    pub folder: String,
+}
+
+fn default_folder() -> String {
+    "Clippings".to_string()
+}
+
+impl Default for ClipperSettings {
+    fn default() -> Self {
+        Self {
+            folder: default_folder(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClipRequest {
+    /// Address of the page the selection came from
+    pub url: String,
+    /// Page title; the first heading or the host name is used when missing
+    pub title: Option<String>,
+    /// HTML of the selection
+    pub html: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClipResult {
+    /// Relative to the workspace root
+    pub path: String,
+    pub title: String,
+    pub images_saved: usize,
+    pub images_failed: usize,
+}
+
+// ============================================================================
+// Settings
+// ============================================================================
+
+/// Path of the saved clipper settings
+pub fn clipper_settings_path() -> PathBuf {
+    app_data_dir().join("clipper.json")
+}
+
+impl ClipperSettings {
+    pub fn load(path: &Path) -> Self {
+        if !path.exists() {
+            return Self::default();
+        }
+
+        std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| {
+                warn!("Failed to load clipper settings, using defaults: {}", e);
+                Self::default()
+            })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize clipper settings: {}", e))?;
+        write_atomic(path, json).map_err(|e| format!("Failed to save clipper settings: {}", e))
+    }
+
+    /// The clippings folder, checked to stay inside the workspace
+    pub fn folder_path(&self) -> Result<String, String> {
+        let folder = self.folder.trim().replace('\\', "/");
+        let folder = folder.trim_matches('/');
+        if folder.is_empty() {
+            return Err("Clippings folder is required".to_string());
+        }
+
+        for component in Path::new(folder).components() {
+            match component {
+                Component::Normal(name) if !name.to_string_lossy().starts_with('.') => {}
+                _ => {
+                    return Err(format!(
+                        "Clippings folder must be a folder inside the workspace: {}",
+                        self.folder
+                    ))
+                }
+            }
+        }
+        Ok(folder.to_string())
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.folder_path().map(|_| ())
+    }
+}
+
+// ============================================================================
+// Clipping
+// ============================================================================
+
+/// Convert a selection, download its images and save it in the clippings folder
+pub async fn clip<C: HttpClient>(
+    client: &C,
+    manager: &WorkspaceManager,
+    workspace_root: &Path,
+    settings: &ClipperSettings,
+    request: &ClipRequest,
+) -> Result<ClipResult, String> {
+    let source = Url::parse(request.url.trim()).map_err(|e| format!("Invalid URL: {}", e))?;
+    if !matches!(source.scheme(), "http" | "https") {
+        return Err(format!("Unsupported URL scheme: {}", source.scheme()));
+    }
+    let folder = settings.folder_path()?;
+
+    let markdown = html_to_markdown(&request.html, &source);
+    if markdown.is_empty() {
+        return Err("Nothing to clip: the selection has no text or images".to_string());
+    }
+
+    let images = ImageManager::new(workspace_root);
+    images
+        .init()
+        .await
+        .map_err(|e| format!("Failed to create image folder: {}", e))?;
+    let (markdown, images_saved, images_failed) = download_images(client, &images, &markdown).await;
+
+    let title = request
+        .title
+        .as_deref()
+        .map(str::trim)
+        .filter(|title| !title.is_empty())
+        .map(str::to_string)
+        .or_else(|| {
+            markdown
+                .lines()
+                .find_map(|line| line.strip_prefix("# "))
+                .map(|heading| heading.trim().to_string())
+        })
+        .unwrap_or_else(|| source.host_str().unwrap_or("Clipping").to_string());
+
+    let path = unique_path(workspace_root, &folder, &file_name(&title));
+    let document = format!(
+        "# {}\n\nSource: {}\nClipped: {}\n\n{}",
+        title,
+        source,
+        Utc::now().format("%Y-%m-%d %H:%M UTC"),
+        markdown
+    );
+
+    let result = manager
+        .import_markdown(&path, &document, TRIGGER)
+        .await
+        .map_err(|e| format!("Failed to save clipping: {}", e))?;
+    if !result.success {
+        return Err(format!(
+            "Failed to save clipping: {}",
+            result.error.unwrap_or_default()
+        ));
+    }
+
+    Ok(ClipResult {
+        path,
+        title,
+        images_saved,
+        images_failed,
+    })
+}
+
+/// Store every image on its own line in the workspace and point the line at
+/// the stored copy. Returns the new Markdown and how many images were saved
+/// and how many could not be.
+async fn download_images<C: HttpClient>(
+    client: &C,
+    images: &ImageManager,
+    markdown: &str,
+) -> (String, usize, usize) {
+    let mut stored: HashMap<String, Option<String>> = HashMap::new();
+    let mut saved = 0;
+    let mut failed = 0;
+    let mut lines = Vec::new();
+
+    for line in markdown.lines() {
+        let Some(image) = IMAGE_LINE.captures(line) else {
+            lines.push(line.to_string());
+            continue;
+        };
+        let (alt, src) = (&image[1], &image[2]);
+
+        if !stored.contains_key(src) {
+            if stored.len() >= MAX_IMAGES {
+                lines.push(line.to_string());
+                continue;
+            }
+            let reference = match download_image(client, images, src).await {
+                Ok(reference) => {
+                    saved += 1;
+                    Some(reference)
+                }
+                Err(e) => {
+                    debug!("Keeping remote image {}: {}", src, e);
+                    failed += 1;
+                    None
+                }
+            };
+            stored.insert(src.to_string(), reference);
+        }
+
+        match &stored[src] {
+            Some(reference) => lines.push(format!("![{}]({})", alt, reference)),
+            None => lines.push(line.to_string()),
+        }
+    }
+
+    (lines.join("\n"), saved, failed)
+}
+
+/// Download an image, or decode a data URL, into the workspace image store.
+/// Returns the stored image's reference.
+pub(crate) async fn download_image<C: HttpClient>(
+    client: &C,
+    images: &ImageManager,
+    src: &str,
+) -> Result<String, String> {
+    if src.starts_with("data:image/") {
+        return images
+            .store_image(src, None)
+            .await
+            .map_err(|e| e.to_string());
+    }
+
+    let url = validate_url(src)?;
+    let mut headers = HashMap::new();
+    headers.insert("Accept".to_string(), "image/*".to_string());
+    let response = client
+        .get_with_headers(url.as_str(), &headers)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if !response.is_success() {
+        return Err(format!("HTTP {}", response.status));
+    }
+    if response.body.len() > MAX_IMAGE_BYTES {
+        return Err(format!(
+            "image is too large ({} bytes)",
+            response.body.len()
+        ));
+    }
+    let mime = response
+        .headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("content-type"))
+        .and_then(|(_, v)| v.split(';').next())
+        .map(|v| v.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+    if !mime.starts_with("image/") {
+        return Err(format!("not an image: {}", mime));
+    }
+
+    images
+        .store_image_bytes(&response.body, &mime)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// A file name for a clipping title, without characters file systems refuse
+fn file_name(title: &str) -> String {
+    let cleaned: String = title
+        .chars()
+        .map(|c| {
+            if c.is_control() || matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') {
+                ' '
+            } else {
+                c
+            }
+        })
+        .collect();
+    let cleaned = collapse_spaces(&cleaned);
+    let name: String = cleaned.chars().take(MAX_TITLE_CHARS).collect();
+    let name = name.trim().trim_matches('.').trim();
+
+    if name.is_empty() {
+        "Clipping".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// `folder/name.midlight`, numbered when a document by that name exists
+fn unique_path(workspace_root: &Path, folder: &str, name: &str) -> String {
+    let mut path = format!("{}/{}.midlight", folder, name);
+    let mut number = 2;
+    while workspace_root.join(&path).exists() {
+        path = format!("{}/{} {}.midlight", folder, name, number);
+        number += 1;
+    }
+    path
+}
+
+// ============================================================================
+// HTML to Markdown
+// ============================================================================
+
+/// Convert an HTML selection to Markdown. Links and images are made absolute
+/// against the page address; images are put on lines of their own.
+pub fn html_to_markdown(html: &str, base: &Url) -> String {
+    let mut blocks = Vec::new();
+    let markdown = convert(html, base, &mut blocks);
+    let markdown = normalize_lines(&markdown);
+
+    // Put the preformatted blocks back, untouched by the clean-up above
+    markdown
+        .lines()
+        .map(|line| {
+            line.strip_prefix(PLACEHOLDER)
+                .and_then(|rest| rest.strip_suffix(PLACEHOLDER))
+                .and_then(|index| index.parse::<usize>().ok())
+                .and_then(|index| blocks.get(index).cloned())
+                .unwrap_or_else(|| line.to_string())
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn convert(html: &str, base: &Url, blocks: &mut Vec<String>) -> String {
+    let mut html = COMMENT.replace_all(html, "").into_owned();
+    for element in SKIPPED.iter() {
+        html = element.replace_all(&html, "").into_owned();
+    }
+
+    let html = PRE
+        .replace_all(&html, |caps: &Captures| {
+            let code = decode_entities(&TAG.replace_all(&caps[1], ""));
+            let code = code.trim_matches('\n').trim_end();
+            placeholder(blocks, format!("```\n{}\n```", code))
+        })
+        .into_owned();
+
+    let html = BLOCKQUOTE
+        .replace_all(&html, |caps: &Captures| {
+            let mut inner_blocks = Vec::new();
+            let inner = normalize_lines(&convert(&caps[1], base, &mut inner_blocks));
+            let quoted: Vec<String> = inner
+                .lines()
+                .map(|line| {
+                    if line.is_empty() {
+                        ">".to_string()
+                    } else {
+                        format!("> {}", line)
+                    }
+                })
+                .collect();
+            placeholder(blocks, quoted.join("\n"))
+        })
+        .into_owned();
+
+    let html = IMG.replace_all(&html, |caps: &Captures| {
+        let attributes = attributes(&caps[1]);
+        match attributes
+            .get("src")
+            .and_then(|src| absolute_url(base, src))
+        {
+            Some(src) => {
+                let alt = collapse_spaces(&decode_entities(
+                    attributes.get("alt").map(String::as_str).unwrap_or(""),
+                ))
+                .replace(['[', ']'], "");
+                format!("\n\n![{}]({})\n\n", alt, src)
+            }
+            None => String::new(),
+        }
+    });
+
+    let html = wrap_inline(&STRONG, &html, "**");
+    let html = wrap_inline(&EMPHASIS, &html, "*");
+    let html = wrap_inline(&CODE, &html, "`");
+
+    let html = LINK.replace_all(&html, |caps: &Captures| {
+        let inner = caps[2].to_string();
+        let text = collapse_spaces(&TAG.replace_all(&inner, ""));
+        let href = attributes(&caps[1])
+            .get("href")
+            .filter(|href| !href.starts_with('#'))
+            .and_then(|href| absolute_url(base, href));
+
+        match href {
+            // Linked images keep the image, not the link
+            Some(href) if !text.is_empty() && !inner.contains("![") => {
+                format!("[{}]({})", text, href)
+            }
+            _ => inner,
+        }
+    });
+
+    let html = HEADING.replace_all(&html, |caps: &Captures| {
+        let level: usize = caps[1].parse().unwrap_or(1);
+        let text = collapse_spaces(&TAG.replace_all(&caps[2], ""));
+        if text.is_empty() {
+            String::new()
+        } else {
+            format!("\n\n{} {}\n\n", "#".repeat(level), text)
+        }
+    });
+
+    let html = LIST_ITEM.replace_all(&html, "\n- ");
+    let html = LINE_BREAK.replace_all(&html, "\n");
+    let html = BLOCK.replace_all(&html, "\n\n");
+    let html = CELL.replace_all(&html, " ");
+    let html = TAG.replace_all(&html, "");
+    decode_entities(&html)
+}
+
+/// Keep a finished block aside and return the line that stands in for it
+fn placeholder(blocks: &mut Vec<String>, block: String) -> String {
+    blocks.push(block);
+    format!("\n\n{}{}{}\n\n", PLACEHOLDER, blocks.len() - 1, PLACEHOLDER)
+}
+
+fn wrap_inline(re: &Regex, html: &str, marker: &str) -> String {
+    re.replace_all(html, |caps: &Captures| {
+        let inner = caps[1].trim();
+        if inner.is_empty() {
+            String::new()
+        } else {
+            format!("{}{}{}", marker, inner, marker)
+        }
+    })
+    .into_owned()
+}
+
+/// Collapse spaces, drop empty list items and keep at most one blank line
+/// between paragraphs
+fn normalize_lines(text: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let line = collapse_spaces(line);
+        if line.is_empty() {
+            if lines.last().is_some_and(|l| !l.is_empty()) {
+                lines.push(String::new());
+            }
+        } else if line != "-" {
+            lines.push(line);
+        }
+    }
+    while lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+    lines.join("\n")
+}
+
+fn attributes(tag: &str) -> HashMap<String, String> {
+    ATTRIBUTE
+        .captures_iter(tag)
+        .map(|caps| {
+            let value = caps
+                .get(2)
+                .or_else(|| caps.get(3))
+                .or_else(|| caps.get(4))
+                .map(|m| decode_entities(m.as_str()))
+                .unwrap_or_default();
+            (caps[1].to_ascii_lowercase(), value)
+        })
+        .collect()
+}
+
+/// An address resolved against the page, for http(s) and inline images only
+fn absolute_url(base: &Url, href: &str) -> Option<String> {
+    let href = href.trim();
+    if href.starts_with("data:image/") {
+        return Some(href.to_string());
+    }
+    let url = base.join(href).ok()?;
+    matches!(url.scheme(), "http" | "https" | "mailto").then(|| url.to_string())
+}
+
+fn collapse_spaces(text: &str) -> String {
+    SPACES.replace_all(text, " ").trim().to_string()
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::http_client::HttpResponse;
+    use crate::traits::MockHttpClient;
+    use tempfile::TempDir;
+
+    fn base() -> Url {
+        Url::parse("https://example.com/blog/post.html").unwrap()
+    }
+
+    #[test]
+    fn test_html_to_markdown() {
+        let html = r#"
+            <script>alert(1)</script>
+            <h2>Why <em>it</em> matters</h2>
+            <p>Read the <a href="/docs">docs</a> &amp; <strong>notes</strong>.</p>
+            <ul><li>One</li><li><code>two</code></li></ul>
+            <figure><img src="../img/chart.png" alt="A [big] chart"></figure>
+            <pre><code>fn main() {
+    println!("&lt;hi&gt;");
+}</code></pre>
+            <blockquote><p>Quoted</p><p>Twice</p></blockquote>
+        "#;
+
+        assert_eq!(
+            html_to_markdown(html, &base()),
+            "## Why *it* matters\n\
+             \n\
+             Read the [docs](https://example.com/docs) & **notes**.\n\
+             \n\
+             - One\n\
+             - `two`\n\
+             \n\
+             ![A big chart](https://example.com/img/chart.png)\n\
+             \n\
+             ```\nfn main() {\n    println!(\"<hi>\");\n}\n```\n\
+             \n\
+             > Quoted\n>\n> Twice"
+        );
+    }
+
+    #[test]
+    fn test_file_names_and_folders() {
+        assert_eq!(file_name("  A/B: \"c\"?  "), "A B c");
+        assert_eq!(file_name("..."), "Clipping");
+
+        let folder = |folder: &str| {
+            ClipperSettings {
+                folder: folder.to_string(),
+            }
+            .folder_path()
+        };
+        assert_eq!(folder("/Web/Clips/").unwrap(), "Web/Clips");
+        assert!(folder("../outside").is_err());
+        assert!(folder(".midlight").is_err());
+        assert!(folder(" ").is_err());
+
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("settings").join("clipper.json");
+        assert_eq!(ClipperSettings::load(&path).folder, "Clippings");
+        ClipperSettings {
+            folder: "Inbox".to_string(),
+        }
+        .save(&path)
+        .unwrap();
+        assert_eq!(ClipperSettings::load(&path).folder, "Inbox");
+    }
+
+    #[tokio::test]
+    async fn test_clip_downloads_images() {
+        let temp = TempDir::new().unwrap();
+        let manager = WorkspaceManager::new(temp.path());
+        manager.init().await.unwrap();
+
+        let client = MockHttpClient::new()
+            .queue_response(
+                HttpResponse::new(200, vec![0x89, b'P', b'N', b'G'])
+                    .with_header("Content-Type", "image/png"),
+            )
+            .queue_response(HttpResponse::new(404, "missing"));
+
+        let request = ClipRequest {
+            url: "https://example.com/blog/post.html".to_string(),
+            title: Some("Post: Part 1".to_string()),
+            html: r#"<p>Hello</p><img src="/a.png" alt="A"><img src="/b.png"><img src="/a.png">"#
+                .to_string(),
+        };
+        let settings = ClipperSettings::default();
+        let result = clip(&client, &manager, temp.path(), &settings, &request)
+            .await
+            .unwrap();
+
+        assert_eq!(result.path, "Clippings/Post Part 1.midlight");
+        assert_eq!(result.images_saved, 1);
+        assert_eq!(result.images_failed, 1);
+        assert_eq!(client.get_requests().len(), 2);
+
+        let markdown = manager.export_markdown(&result.path).await.unwrap();
+        assert!(markdown.starts_with(
+            "# Post: Part 1\n\nSource: https://example.com/blog/post.html\nClipped: "
+        ));
+        assert!(markdown.contains("![A](midlight://img-"));
+        assert!(markdown.contains("![](https://example.com/b.png)"));
+        // The repeated image is downloaded once and used twice
+        assert_eq!(markdown.matches("(midlight://img-").count(), 2);
+
+        // A second clipping of the same page gets its own document
+        let client = MockHttpClient::new();
+        let request = ClipRequest {
+            html: "<p>Again</p>".to_string(),
+            ..request
+        };
+        let again = clip(&client, &manager, temp.path(), &settings, &request)
+            .await
+            .unwrap();
+        assert_eq!(again.path, "Clippings/Post Part 1 2.midlight");
+    }
+}
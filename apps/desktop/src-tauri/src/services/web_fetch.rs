@@ -0,0 +1,522 @@
+// Web Fetch - Download a web page and reduce it to readable text
+//
+// Backs the agent's fetch_url tool. Requests go through the HttpClient trait
+// so tests can supply canned pages. HTML is cut down to the page's main text:
+// scripts, styles and navigation chrome are dropped, block elements become
+// line breaks and entities are decoded. Only public http(s) addresses can be
+// fetched - loopback, private and link-local hosts are refused, including as
+// redirect targets, so page content can't steer the agent at the local network.
+// Host names are checked again once resolved, by the client's own resolver, so
+// the addresses checked are the ones connected to. Behind a proxy the proxy
+// does the lookup, and only the names are checked.
+
+use crate::services::network_config::{configure_client, current_settings, validate_proxy_url};
+use crate::traits::http_client::{HttpClient, ReqwestHttpClient};
+use regex::Regex;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use url::{Host, Url};
+
+/// Characters of text returned when the caller doesn't ask for a limit
+pub const DEFAULT_MAX_CHARS: usize = 20_000;
+
+/// Upper bound on the requested limit
+pub const MAX_CHARS_LIMIT: usize = 100_000;
+
+/// Pages larger than this are refused before text extraction
+const MAX_BODY_BYTES: usize = 5 * 1024 * 1024;
+
+/// Downloads through [`client`] stop once they pass this. It's the largest
+/// response any caller accepts: an image saved by the clipper or an import.
+pub const MAX_RESPONSE_BYTES: usize = 10 * 1024 * 1024;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(20);
+const MAX_REDIRECTS: usize = 5;
+
+/// Elements whose content is never part of the readable text
+const SKIPPED_ELEMENTS: &[&str] = &[
+    "script", "style", "noscript", "svg", "nav", "header", "footer", "aside", "form", "iframe",
+    "template",
+];
+
+lazy_static::lazy_static! {
+    static ref COMMENT: Regex = Regex::new(r"(?s)<!--.*?-->").unwrap();
+    static ref SKIPPED: Vec<Regex> = SKIPPED_ELEMENTS
+        .iter()
+        .map(|tag| Regex::new(&format!(r"(?is)<{tag}\b[^>]*>.*?</{tag}\s*>")).unwrap())
+        .collect();
+    static ref TITLE: Regex = Regex::new(r"(?is)<title\b[^>]*>(.*?)</title\s*>").unwrap();
+    static ref ARTICLE: Regex = Regex::new(r"(?is)<article\b[^>]*>(.*?)</article\s*>").unwrap();
+    static ref MAIN: Regex = Regex::new(r"(?is)<main\b[^>]*>(.*?)</main\s*>").unwrap();
+    static ref BODY: Regex = Regex::new(r"(?is)<body\b[^>]*>(.*)</body\s*>").unwrap();
+    static ref LIST_ITEM: Regex = Regex::new(r"(?i)<li\b[^>]*>").unwrap();
+    static ref BLOCK: Regex = Regex::new(
+        r"(?i)</?(?:p|div|br|hr|h[1-6]|ul|ol|li|tr|table|section|article|main|blockquote|pre|dl|dt|dd|figure|figcaption)\b[^>]*>"
+    )
+    .unwrap();
+    static ref TAG: Regex = Regex::new(r"<[^>]*>").unwrap();
+    static ref ENTITY: Regex = Regex::new(r"&(#[0-9]+|#[xX][0-9a-fA-F]+|[a-zA-Z]+);").unwrap();
+    static ref SPACES: Regex = Regex::new(r"[ \t\u{a0}]+").unwrap();
+}
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FetchedPage {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    pub text: String,
+    /// Length of the extracted text before truncation, in characters
+    pub total_chars: usize,
+    pub truncated: bool,
+}
+
+// ============================================================================
+// Fetching
+// ============================================================================
+
+/// HTTP client for page fetches: honours the network settings, times out,
+/// refuses redirects to blocked hosts and stops reading oversized responses
+pub fn client() -> Result<ReqwestHttpClient, String> {
+    let redirect = reqwest::redirect::Policy::custom(|attempt| {
+        if attempt.previous().len() >= MAX_REDIRECTS {
+            attempt.error("too many redirects")
+        } else if validate_url(attempt.url().as_str()).is_err() {
+            attempt.error("redirected to a blocked address")
+        } else {
+            attempt.follow()
+        }
+    });
+
+    let proxy_host = current_settings()
+        .effective()
+        .proxy_url
+        .and_then(|url| validate_proxy_url(&url).ok())
+        .and_then(|url| url.host_str().map(str::to_ascii_lowercase));
+
+    let client = configure_client(reqwest::Client::builder())
+        .timeout(FETCH_TIMEOUT)
+        .redirect(redirect)
+        .dns_resolver(Arc::new(PublicResolver { proxy_host }))
+        .user_agent(concat!("Midlight/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    Ok(ReqwestHttpClient::with_client(client).with_body_limit(MAX_RESPONSE_BYTES))
+}
+
+/// Check that a URL is http(s) and doesn't point at this machine or the
+/// local network. Host names are checked as written here; [`client`] checks
+/// what they resolve to.
+pub fn validate_url(url: &str) -> Result<Url, String> {
+    let parsed = Url::parse(url.trim()).map_err(|e| format!("Invalid URL: {}", e))?;
+
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("Unsupported URL scheme: {}", parsed.scheme()));
+    }
+
+    let blocked = match parsed.host() {
+        None => true,
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+            domain == "localhost" || domain.ends_with(".localhost") || domain.ends_with(".local")
+        }
+        Some(Host::Ipv4(ip)) => is_private_ip(IpAddr::V4(ip)),
+        Some(Host::Ipv6(ip)) => is_private_ip(IpAddr::V6(ip)),
+    };
+    if blocked {
+        return Err(format!(
+            "Fetching local or private addresses is not allowed: {}",
+            parsed.host_str().unwrap_or("")
+        ));
+    }
+
+    Ok(parsed)
+}
+
+/// Resolves host names for [`client`], failing the lookup if any address is
+/// local or private. Connections go to the addresses it returns, so a second
+/// lookup can't swap in a private one.
+struct PublicResolver {
+    /// The configured proxy, which is allowed to be on the local network
+    proxy_host: Option<String>,
+}
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_ascii_lowercase();
+        let is_proxy = self.proxy_host.as_deref() == Some(host.as_str());
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> =
+                tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            if !is_proxy {
+                check_resolved(&host, &addrs)?;
+            }
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// Refuse a host that resolved to nothing or to any local or private address
+fn check_resolved(host: &str, addrs: &[SocketAddr]) -> Result<(), String> {
+    if addrs.is_empty() {
+        return Err(format!("No addresses found for {}", host));
+    }
+    if addrs.iter().any(|addr| is_private_ip(addr.ip())) {
+        return Err(format!(
+            "Fetching local or private addresses is not allowed: {}",
+            host
+        ));
+    }
+    Ok(())
+}
+
+fn is_private_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                // Carrier-grade NAT (100.64.0.0/10)
+                || (ip.octets()[0] == 100 && (ip.octets()[1] & 0xc0) == 64)
+        }
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+            ip.is_loopback()
+                || ip.is_unspecified()
+                // Unique local (fc00::/7) and link-local (fe80::/10)
+                || (segments[0] & 0xfe00) == 0xfc00
+                || (segments[0] & 0xffc0) == 0xfe80
+                || ip.to_ipv4_mapped().is_some_and(|v4| is_private_ip(IpAddr::V4(v4)))
+        }
+    }
+}
+
+/// Download a page and return its readable text, truncated to `max_chars`
+pub async fn fetch_page<C: HttpClient>(
+    client: &C,
+    url: &str,
+    max_chars: usize,
+) -> Result<FetchedPage, String> {
+    let parsed = validate_url(url)?;
+
+    let mut headers = HashMap::new();
+    headers.insert(
+        "Accept".to_string(),
+        "text/html,application/xhtml+xml,text/plain;q=0.9,*/*;q=0.5".to_string(),
+    );
+    let response = client
+        .get_with_headers(parsed.as_str(), &headers)
+        .await
+        .map_err(|e| format!("Failed to fetch {}: {}", parsed, e))?;
+
+    if !response.is_success() {
+        return Err(format!(
+            "Failed to fetch {}: HTTP {}",
+            parsed, response.status
+        ));
+    }
+    if response.body.len() > MAX_BODY_BYTES {
+        return Err(format!(
+            "Page is too large ({} bytes, limit {})",
+            response.body.len(),
+            MAX_BODY_BYTES
+        ));
+    }
+
+    let content_type = response
+        .headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("content-type"))
+        .map(|(_, v)| v.to_ascii_lowercase());
+    let mime = content_type
+        .as_deref()
+        .and_then(|ct| ct.split(';').next())
+        .map(str::trim)
+        .unwrap_or("");
+
+    let is_html = mime.is_empty() || mime == "text/html" || mime == "application/xhtml+xml";
+    let is_text =
+        mime.starts_with("text/") || mime == "application/json" || mime.ends_with("+json");
+    if !is_html && !is_text {
+        return Err(format!("Unsupported content type: {}", mime));
+    }
+
+    let body = String::from_utf8_lossy(&response.body);
+    let (title, text) = if is_html {
+        html_to_text(&body)
+    } else {
+        (None, body.trim().to_string())
+    };
+
+    let total_chars = text.chars().count();
+    let (text, truncated) = truncate_chars(&text, max_chars.clamp(1, MAX_CHARS_LIMIT));
+
+    Ok(FetchedPage {
+        url: parsed.to_string(),
+        title,
+        content_type,
+        text,
+        total_chars,
+        truncated,
+    })
+}
+
+// ============================================================================
+// Text Extraction
+// ============================================================================
+
+/// Extract the title and readable text from an HTML page
+pub fn html_to_text(html: &str) -> (Option<String>, String) {
+    let title = TITLE
+        .captures(html)
+        .and_then(|c| c.get(1))
+        .map(|m| collapse_spaces(&decode_entities(&TAG.replace_all(m.as_str(), ""))))
+        .filter(|t| !t.is_empty());
+
+    let mut cleaned = COMMENT.replace_all(html, "").into_owned();
+    for element in SKIPPED.iter() {
+        cleaned = element.replace_all(&cleaned, "\n").into_owned();
+    }
+
+    // Prefer the page's main content when it is marked up
+    let content = [&*ARTICLE, &*MAIN, &*BODY]
+        .iter()
+        .find_map(|re| re.captures(&cleaned).and_then(|c| c.get(1)))
+        .map(|m| m.as_str())
+        .unwrap_or(&cleaned);
+
+    let text = LIST_ITEM.replace_all(content, "\n- ");
+    let text = BLOCK.replace_all(&text, "\n");
+    let text = TAG.replace_all(&text, "");
+    let text = decode_entities(&text);
+
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let line = collapse_spaces(line);
+        if line.is_empty() {
+            // Keep at most one blank line between paragraphs
+            if lines.last().is_some_and(|l| !l.is_empty()) {
+                lines.push(String::new());
+            }
+        } else if line != "-" {
+            lines.push(line);
+        }
+    }
+    while lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+
+    (title, lines.join("\n"))
+}
+
+fn collapse_spaces(text: &str) -> String {
+    SPACES.replace_all(text, " ").trim().to_string()
+}
+
+/// Decode HTML character references and the common named entities
+pub fn decode_entities(text: &str) -> String {
+    ENTITY
+        .replace_all(text, |caps: &regex::Captures| {
+            let entity = &caps[1];
+            let decoded = if let Some(hex) = entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+            {
+                u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+            } else if let Some(dec) = entity.strip_prefix('#') {
+                dec.parse().ok().and_then(char::from_u32)
+            } else {
+                match entity {
+                    "amp" => Some('&'),
+                    "lt" => Some('<'),
+                    "gt" => Some('>'),
+                    "quot" => Some('"'),
+                    "apos" => Some('\''),
+                    "nbsp" => Some(' '),
+                    "ndash" => Some('–'),
+                    "mdash" => Some('—'),
+                    "hellip" => Some('…'),
+                    "lsquo" => Some('‘'),
+                    "rsquo" => Some('’'),
+                    "ldquo" => Some('“'),
+                    "rdquo" => Some('”'),
+                    "copy" => Some('©'),
+                    _ => None,
+                }
+            };
+            decoded.map_or_else(|| caps[0].to_string(), String::from)
+        })
+        .into_owned()
+}
+
+/// Cut text to at most `max_chars` characters, preferring a line break
+fn truncate_chars(text: &str, max_chars: usize) -> (String, bool) {
+    let Some((byte_index, _)) = text.char_indices().nth(max_chars) else {
+        return (text.to_string(), false);
+    };
+
+    let cut = &text[..byte_index];
+    let cut = match cut.rfind('\n') {
+        Some(newline) if newline > cut.len() / 2 => &cut[..newline],
+        _ => cut,
+    };
+    (cut.trim_end().to_string(), true)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::http_client::HttpResponse;
+    use crate::traits::MockHttpClient;
+
+    const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head><title>Rust &amp; Tauri</title><style>body { color: red; }</style></head>
+<body>
+  <nav><a href="/">Home</a></nav>
+  <article>
+    <h1>Getting started</h1>
+    <p>Install the   <b>CLI</b> first.</p>
+    <script>trackVisit();</script>
+    <ul><li>One</li><li>Two &lt;3</li></ul>
+  </article>
+  <footer>Copyright</footer>
+</body>
+</html>"#;
+
+    #[test]
+    fn test_html_to_text() {
+        let (title, text) = html_to_text(PAGE);
+        assert_eq!(title.as_deref(), Some("Rust & Tauri"));
+        assert_eq!(
+            text,
+            "Getting started\n\nInstall the CLI first.\n\n- One\n\n- Two <3"
+        );
+        assert!(!text.contains("trackVisit"));
+        assert!(!text.contains("Home"));
+        assert!(!text.contains("Copyright"));
+    }
+
+    #[test]
+    fn test_validate_url() {
+        assert!(validate_url("https://example.com/page").is_ok());
+        assert!(validate_url("ftp://example.com").is_err());
+        assert!(validate_url("file:///etc/passwd").is_err());
+        assert!(validate_url("not a url").is_err());
+
+        for blocked in [
+            "http://localhost:1420",
+            "http://127.0.0.1/",
+            "http://192.168.1.1/admin",
+            "http://10.0.0.5",
+            "http://169.254.169.254/latest/meta-data",
+            "http://[::1]/",
+            "http://[fd00::1]/",
+            "http://printer.local/",
+        ] {
+            assert!(
+                validate_url(blocked).is_err(),
+                "{} should be blocked",
+                blocked
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_resolved() {
+        let addr = |ip: &str| SocketAddr::new(ip.parse().unwrap(), 0);
+
+        assert!(check_resolved("example.com", &[addr("93.184.215.14")]).is_ok());
+        assert!(check_resolved("example.com", &[]).is_err());
+        // One private address among public ones is enough to refuse
+        assert!(check_resolved(
+            "rebind.example",
+            &[addr("93.184.215.14"), addr("127.0.0.1")]
+        )
+        .is_err());
+        assert!(check_resolved("rebind.example", &[addr("::ffff:10.0.0.1")]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resolver_refuses_loopback_names() {
+        let resolver = PublicResolver { proxy_host: None };
+        let name: Name = "localhost".parse().unwrap();
+        assert!(resolver.resolve(name).await.is_err());
+
+        // The configured proxy may be local
+        let resolver = PublicResolver {
+            proxy_host: Some("localhost".to_string()),
+        };
+        let name: Name = "localhost".parse().unwrap();
+        assert!(resolver.resolve(name).await.is_ok());
+    }
+
+    #[test]
+    fn test_truncate_chars() {
+        assert_eq!(truncate_chars("short", 10), ("short".to_string(), false));
+        let (text, truncated) = truncate_chars("line one\nline two\nline three", 20);
+        assert!(truncated);
+        assert_eq!(text, "line one\nline two");
+        // Counts characters, not bytes
+        assert_eq!(truncate_chars("ééééé", 3), ("ééé".to_string(), true));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_page() {
+        let client = MockHttpClient::new().queue_response(
+            HttpResponse::new(200, PAGE).with_header("content-type", "text/html; charset=utf-8"),
+        );
+
+        let page = fetch_page(&client, "https://example.com/guide", 15)
+            .await
+            .unwrap();
+        assert_eq!(page.title.as_deref(), Some("Rust & Tauri"));
+        assert_eq!(page.text, "Getting started");
+        assert!(page.truncated);
+        assert!(page.total_chars > 15);
+        assert_eq!(
+            client.last_request().unwrap().url,
+            "https://example.com/guide"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_page_errors() {
+        let client = MockHttpClient::new()
+            .queue_error_response(404, "Not found")
+            .queue_response(
+                HttpResponse::new(200, vec![0u8, 1, 2]).with_header("content-type", "image/png"),
+            );
+
+        let err = fetch_page(&client, "https://example.com/missing", 100)
+            .await
+            .unwrap_err();
+        assert!(err.contains("HTTP 404"));
+
+        let err = fetch_page(&client, "https://example.com/logo.png", 100)
+            .await
+            .unwrap_err();
+        assert!(err.contains("Unsupported content type"));
+
+        // Blocked before any request is made
+        assert!(fetch_page(&client, "http://127.0.0.1", 100).await.is_err());
+        assert_eq!(client.get_requests().len(), 2);
+    }
+}
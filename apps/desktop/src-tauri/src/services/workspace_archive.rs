@@ -0,0 +1,520 @@
+// Workspace Archive - A whole workspace in one portable file
+//
+// A .midlightpkg holds a workspace's documents and, optionally, its images,
+// checkpoints, settings and indexes, for moving to another machine or for a
+// backup before turning on sync. Importing one unpacks it into a new folder
+// that opens as an ordinary workspace.
+//
+// Settings include metadata.db: pins, goals and writing history are kept
+// there alongside rebuildable data. Indexes (the file tree cache, extracted
+// PDF text) are left out by default since they are rebuilt on demand.
+// Recovery files, the trash and logs are never archived.
+//
+// Format: a zip with manifest.json at the top and the workspace's files
+// under workspace/, by workspace-relative path.
+
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+use super::document_chunks;
+use super::export_filter::{ExportFilter, ExportSelection};
+use super::object_store::ObjectStore;
+use super::operations::OperationHandle;
+use super::workspace_paths::document_key;
+
+pub const ARCHIVE_EXTENSION: &str = "midlightpkg";
+
+/// Newest archive version this build can import
+const ARCHIVE_VERSION: u32 = 1;
+
+const ARCHIVE_FORMAT: &str = "midlightpkg";
+const MANIFEST_NAME: &str = "manifest.json";
+const FILES_PREFIX: &str = "workspace/";
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// What goes into an archive besides the documents
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ArchiveContents {
+    /// Stored images and audio
+    pub images: bool,
+    pub checkpoints: bool,
+    /// Workspace configuration, pins, goals, conversations and the like
+    pub settings: bool,
+    /// Rebuildable caches
+    pub indexes: bool,
+}
+
+impl Default for ArchiveContents {
+    fn default() -> Self {
+        Self {
+            images: true,
+            checkpoints: true,
+            settings: true,
+            indexes: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveManifest {
+    pub format: String,
+    pub version: u32,
+    /// Version of Midlight that wrote the archive
+    pub app_version: String,
+    pub created_at: DateTime<Utc>,
+    /// Name of the workspace folder
+    pub workspace_name: String,
+    pub contents: ArchiveContents,
+    pub files: usize,
+    /// Uncompressed size of the files
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Category {
+    Document,
+    Images,
+    Checkpoints,
+    Settings,
+    Indexes,
+}
+
+/// What a workspace-relative path is, or None if it's never archived
+fn category(key: &str) -> Option<Category> {
+    let Some(inner) = key.strip_prefix(".midlight/") else {
+        return Some(Category::Document);
+    };
+    let top = inner.split('/').next().unwrap_or(inner);
+    if top.ends_with("-wal") || top.ends_with("-shm") || top.ends_with(".tmp") {
+        return None;
+    }
+    match top {
+        "recovery" | "recovery.json" | "trash" | "watcher.json" | "agent-audit.jsonl" => None,
+        "images" | "audio" | "image-refs.json" => Some(Category::Images),
+        "checkpoints" | "objects" => Some(Category::Checkpoints),
+        "tree-cache.db" | "pdf" => Some(Category::Indexes),
+        _ => Some(Category::Settings),
+    }
+}
+
+impl ArchiveContents {
+    fn includes(&self, category: Category) -> bool {
+        match category {
+            Category::Document => true,
+            Category::Images => self.images,
+            Category::Checkpoints => self.checkpoints,
+            Category::Settings => self.settings,
+            Category::Indexes => self.indexes,
+        }
+    }
+}
+
+// ============================================================================
+// Export
+// ============================================================================
+
+/// Write a workspace to an archive at `output`. An archive narrowed by
+/// `filter` leaves out checkpoints, settings and indexes, which hold every
+/// document's history and metadata.
+pub fn export_archive(
+    workspace_root: &Path,
+    output: &Path,
+    contents: ArchiveContents,
+    filter: &ExportFilter,
+    operation: &OperationHandle,
+) -> Result<ArchiveManifest, String> {
+    if !workspace_root.is_dir() {
+        return Err(format!("Workspace not found: {}", workspace_root.display()));
+    }
+    if output.starts_with(workspace_root) {
+        return Err("Can't save the archive inside the workspace".to_string());
+    }
+
+    let selection = filter.is_active().then(|| filter.select(workspace_root));
+    let contents = match selection {
+        Some(_) => ArchiveContents {
+            checkpoints: false,
+            settings: false,
+            indexes: false,
+            ..contents
+        },
+        None => contents,
+    };
+
+    // Without checkpoints, objects are still needed for documents stored in
+    // sections
+    let sections = if contents.checkpoints {
+        BTreeSet::new()
+    } else {
+        section_objects(workspace_root, selection.as_ref())
+    };
+
+    let mut files = Vec::new();
+    let walker = WalkDir::new(workspace_root)
+        .min_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file());
+    for entry in walker {
+        let Ok(relative) = entry.path().strip_prefix(workspace_root) else {
+            continue;
+        };
+        let key = document_key(&relative.to_string_lossy());
+        let Some(category) = category(&key) else {
+            continue;
+        };
+        let selected = selection
+            .as_ref()
+            .map_or(true, |selection| is_selected(selection, &key, category));
+        if (contents.includes(category) && selected) || sections.contains(&key) {
+            files.push((key, entry.into_path()));
+        }
+    }
+
+    for (key, path) in &files {
+        if key.ends_with(".db") {
+            checkpoint_database(path);
+        }
+    }
+
+    let mut manifest = ArchiveManifest {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: Utc::now(),
+        workspace_name: workspace_root
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Workspace".to_string()),
+        contents,
+        files: files.len(),
+        bytes: 0,
+    };
+
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    let temp_path = output.with_extension(format!("{}.tmp", ARCHIVE_EXTENSION));
+    let result = write_archive(&temp_path, &files, &mut manifest, operation)
+        .and_then(|_| fs::rename(&temp_path, output).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp_path);
+        return Err(format!("Failed to write archive: {}", e));
+    }
+    Ok(manifest)
+}
+
+fn write_archive(
+    path: &Path,
+    files: &[(String, PathBuf)],
+    manifest: &mut ArchiveManifest,
+    operation: &OperationHandle,
+) -> Result<(), String> {
+    let mut zip = ZipWriter::new(File::create(path).map_err(|e| e.to_string())?);
+    let options = SimpleFileOptions::default().large_file(true);
+    for (i, (key, source)) in files.iter().enumerate() {
+        operation.check_cancelled()?;
+        operation.progress("writing", i, files.len(), Some(key.clone()));
+        let mut file = File::open(source).map_err(|e| format!("{}: {}", key, e))?;
+        zip.start_file(format!("{}{}", FILES_PREFIX, key), options)
+            .map_err(|e| format!("{}: {}", key, e))?;
+        manifest.bytes += io::copy(&mut file, &mut zip).map_err(|e| format!("{}: {}", key, e))?;
+    }
+
+    // Written last so it has the totals; readers look it up by name
+    let json = serde_json::to_vec_pretty(manifest).map_err(|e| e.to_string())?;
+    zip.start_file(MANIFEST_NAME, SimpleFileOptions::default())
+        .and_then(|_| zip.write_all(&json).map_err(Into::into))
+        .map_err(|e| e.to_string())?;
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Whether an export filter's selection takes a file. Image references
+/// name every document, so they're left out.
+fn is_selected(selection: &ExportSelection, key: &str, category: Category) -> bool {
+    match category {
+        Category::Document => selection.files.contains(key),
+        Category::Images => match key.strip_prefix(".midlight/images/") {
+            Some(image) => selection.includes_image(image.rsplit('/').next().unwrap_or(image)),
+            None => key != ".midlight/image-refs.json",
+        },
+        _ => true,
+    }
+}
+
+/// Object store paths of the sections of documents stored in sections, of
+/// the selected documents only if there's a selection
+fn section_objects(workspace_root: &Path, selection: Option<&ExportSelection>) -> BTreeSet<String> {
+    let store = ObjectStore::new(workspace_root);
+    WalkDir::new(workspace_root)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|e| !e.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().and_then(|x| x.to_str()) == Some("midlight"))
+        .filter(|e| {
+            selection.map_or(true, |selection| {
+                e.path().strip_prefix(workspace_root).is_ok_and(|relative| {
+                    selection
+                        .files
+                        .contains(&document_key(&relative.to_string_lossy()))
+                })
+            })
+        })
+        .filter_map(|e| fs::read_to_string(e.path()).ok())
+        .filter_map(|json| serde_json::from_str(&json).ok())
+        .filter_map(|document| document_chunks::manifest(&document))
+        .flat_map(|manifest| manifest.sections)
+        .filter_map(|section| {
+            let path = store.get_object_path(&section.hash);
+            let relative = path.strip_prefix(workspace_root).ok()?;
+            Some(document_key(&relative.to_string_lossy()))
+        })
+        .collect()
+}
+
+/// Move a SQLite database's write-ahead log into the main file, so copying
+/// the file alone copies everything
+fn checkpoint_database(path: &Path) {
+    let result = Connection::open(path)
+        .and_then(|conn| conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);"));
+    if let Err(e) = result {
+        tracing::warn!("Failed to checkpoint {}: {}", path.display(), e);
+    }
+}
+
+// ============================================================================
+// Import
+// ============================================================================
+
+/// The manifest of an archive, without unpacking it
+pub fn read_manifest(archive: &Path) -> Result<ArchiveManifest, String> {
+    let file = File::open(archive).map_err(|e| format!("Failed to open archive: {}", e))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| format!("Not a Midlight archive: {}", e))?;
+    manifest_of(&mut zip)
+}
+
+fn manifest_of(zip: &mut ZipArchive<File>) -> Result<ArchiveManifest, String> {
+    let mut json = String::new();
+    zip.by_name(MANIFEST_NAME)
+        .map_err(|_| "Not a Midlight archive: it has no manifest".to_string())?
+        .read_to_string(&mut json)
+        .map_err(|e| format!("Failed to read manifest: {}", e))?;
+    let manifest: ArchiveManifest =
+        serde_json::from_str(&json).map_err(|e| format!("Failed to parse manifest: {}", e))?;
+    if manifest.format != ARCHIVE_FORMAT {
+        return Err("Not a Midlight archive".to_string());
+    }
+    if manifest.version > ARCHIVE_VERSION {
+        return Err(format!(
+            "This archive was made by a newer version of Midlight ({}); update to import it",
+            manifest.app_version
+        ));
+    }
+    Ok(manifest)
+}
+
+/// Unpack an archive into `target`, which must not exist or be empty
+pub fn import_archive(
+    archive: &Path,
+    target: &Path,
+    operation: &OperationHandle,
+) -> Result<ArchiveManifest, String> {
+    let file = File::open(archive).map_err(|e| format!("Failed to open archive: {}", e))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| format!("Not a Midlight archive: {}", e))?;
+    let manifest = manifest_of(&mut zip)?;
+
+    let existed = target.exists();
+    if existed {
+        let empty = fs::read_dir(target)
+            .map_err(|e| format!("Failed to read {}: {}", target.display(), e))?
+            .next()
+            .is_none();
+        if !empty {
+            return Err(format!("{} is not empty", target.display()));
+        }
+    }
+    fs::create_dir_all(target).map_err(|e| format!("Failed to create workspace: {}", e))?;
+
+    let result = unpack(&mut zip, target, operation);
+    if result.is_err() {
+        if existed {
+            let entries = fs::read_dir(target).into_iter().flatten().flatten();
+            for entry in entries {
+                let _ = fs::remove_dir_all(entry.path()).or_else(|_| fs::remove_file(entry.path()));
+            }
+        } else {
+            let _ = fs::remove_dir_all(target);
+        }
+    }
+    result.map(|_| manifest)
+}
+
+fn unpack(
+    zip: &mut ZipArchive<File>,
+    target: &Path,
+    operation: &OperationHandle,
+) -> Result<(), String> {
+    let total = zip.len();
+    for i in 0..total {
+        operation.check_cancelled()?;
+        let mut entry = zip
+            .by_index(i)
+            .map_err(|e| format!("Failed to read archive: {}", e))?;
+        let name = entry.name().to_string();
+        let Some(name) = name.strip_prefix(FILES_PREFIX) else {
+            continue;
+        };
+        let relative = Path::new(name);
+        let inside = relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)));
+        if !inside || entry.is_dir() {
+            if !inside {
+                tracing::warn!("Skipping archive entry outside the workspace: {}", name);
+            }
+            continue;
+        }
+
+        operation.progress("unpacking", i, total, Some(name.to_string()));
+        let path = target.join(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+        }
+        let mut out =
+            File::create(&path).map_err(|e| format!("Failed to write {}: {}", name, e))?;
+        io::copy(&mut entry, &mut out).map_err(|e| format!("Failed to write {}: {}", name, e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::operations::OperationKind;
+    use tempfile::TempDir;
+
+    fn write(root: &Path, key: &str, content: &str) {
+        let path = root.join(key);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_export_and_import() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("Notes");
+        write(
+            &root,
+            "a.midlight",
+            r#"{"version":1,"content":{"type":"doc"}}"#,
+        );
+        write(&root, "Ideas/b.md", "# B");
+        write(&root, ".midlight/workspace.config.json", "{}");
+        write(&root, ".midlight/images/3f2a.png", "png");
+        write(&root, ".midlight/checkpoints/a.json", "[]");
+        write(&root, ".midlight/objects/ab/cdef", "old version");
+        write(&root, ".midlight/tree-cache.db", "cache");
+        write(&root, ".midlight/recovery/a.wal", "unsaved");
+
+        let archive = temp.path().join("Notes.midlightpkg");
+        let contents = ArchiveContents {
+            checkpoints: false,
+            ..Default::default()
+        };
+        let operation = OperationHandle::detached(OperationKind::Export);
+        let manifest = export_archive(
+            &root,
+            &archive,
+            contents,
+            &ExportFilter::default(),
+            &operation,
+        )
+        .unwrap();
+        assert_eq!(manifest.files, 4);
+        assert_eq!(manifest.workspace_name, "Notes");
+        assert_eq!(read_manifest(&archive).unwrap().contents, contents);
+
+        let target = temp.path().join("Restored");
+        import_archive(&archive, &target, &operation).unwrap();
+        assert_eq!(
+            fs::read_to_string(target.join("Ideas/b.md")).unwrap(),
+            "# B"
+        );
+        assert!(target.join(".midlight/images/3f2a.png").exists());
+        assert!(!target.join(".midlight/objects").exists());
+        assert!(!target.join(".midlight/tree-cache.db").exists());
+        assert!(!target.join(".midlight/recovery").exists());
+
+        // Won't unpack over an existing workspace
+        assert!(import_archive(&archive, &root, &operation).is_err());
+        assert!(root.join("a.midlight").exists());
+
+        // A cancelled import leaves nothing behind
+        operation.cancel_token().cancel();
+        let target = temp.path().join("Cancelled");
+        assert!(import_archive(&archive, &target, &operation).is_err());
+        assert!(!target.exists());
+    }
+
+    #[test]
+    fn test_filtered_export() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("Notes");
+        write(
+            &root,
+            "Work/plan.md",
+            "![](midlight://img-3f2a3f2a3f2a3f2a)",
+        );
+        write(&root, "Home/diary.md", "Dear diary");
+        write(&root, ".midlight/images/3f2a3f2a3f2a3f2a.png", "png");
+        write(&root, ".midlight/images/9b9b9b9b9b9b9b9b.png", "png");
+        write(&root, ".midlight/image-refs.json", "{}");
+        write(&root, ".midlight/checkpoints/a.json", "[]");
+        write(&root, ".midlight/workspace.config.json", "{}");
+
+        let archive = temp.path().join("Work.midlightpkg");
+        let filter = ExportFilter {
+            folders: vec!["Work".to_string()],
+            referenced_assets_only: true,
+            ..Default::default()
+        };
+        let operation = OperationHandle::detached(OperationKind::Export);
+        let manifest = export_archive(
+            &root,
+            &archive,
+            ArchiveContents::default(),
+            &filter,
+            &operation,
+        )
+        .unwrap();
+        assert_eq!(manifest.files, 2);
+        assert!(!manifest.contents.checkpoints && !manifest.contents.settings);
+
+        let target = temp.path().join("Restored");
+        import_archive(&archive, &target, &operation).unwrap();
+        assert!(target.join("Work/plan.md").exists());
+        assert!(target
+            .join(".midlight/images/3f2a3f2a3f2a3f2a.png")
+            .exists());
+        assert!(!target.join("Home").exists());
+        assert!(!target
+            .join(".midlight/images/9b9b9b9b9b9b9b9b.png")
+            .exists());
+    }
+}
@@ -2,17 +2,32 @@
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
-use super::checkpoint_manager::{Checkpoint, CheckpointManager};
-use super::error::Result;
+use super::atomic_write::write_atomic;
+use super::change_staging::{diff_hunks, line_diff, DiffOp};
+use super::checkpoint_manager::{
+    Checkpoint, CheckpointAttribution, CheckpointManager, DocumentVariant,
+};
+use super::document_chunks::{self, SectionEntry, Sections};
+use super::document_lock;
+use super::error::{MidlightError, Result};
+use super::image_manager::ImageManager;
+use super::image_refs::{self, ImageRefStore};
+use super::link_graph::{self, LinkGraph, LinkGraphStore};
+use super::markdown_convert::{self, MarkdownOptions};
 use super::object_store::ObjectStore;
-use crate::commands::versions::DiffResult;
+use super::rag_indexer::RAG_INDEXER;
+use super::task_index::TaskIndex;
+use super::workspace_paths::document_key;
+use super::writing_stats::WritingStats;
+use crate::commands::fs::refresh_tree_cache;
+use crate::commands::versions::{DiffResult, VariantMergePreview};
 use crate::commands::workspace::{LoadedDocument, SaveResult};
 
 /// Project context settings stored in .project.midlight
@@ -208,7 +223,11 @@ impl WorkspaceManager {
         }
 
         let content = fs::read_to_string(full_path)?;
-        let midlight_doc: Value = serde_json::from_str(&content)?;
+        let mut midlight_doc: Value = serde_json::from_str(&content)?;
+        if let Some(manifest) = document_chunks::manifest(&midlight_doc) {
+            midlight_doc["content"] = document_chunks::load_content(&self.object_store, &manifest)
+                .map_err(MidlightError::Internal)?;
+        }
 
         // Extract content (Tiptap JSON)
         let json = midlight_doc.get("content").cloned().unwrap_or_else(|| {
@@ -311,7 +330,7 @@ impl WorkspaceManager {
             "images": images
         });
 
-        fs::write(&midlight_path, serde_json::to_string_pretty(&midlight_doc)?)?;
+        write_atomic(&midlight_path, serde_json::to_string_pretty(&midlight_doc)?)?;
         tracing::info!("Migrated {} to {}", file_path, midlight_path.display());
 
         // Delete original .md and .sidecar.json files after successful migration
@@ -339,16 +358,90 @@ impl WorkspaceManager {
         json: Value,
         trigger: &str,
     ) -> Result<SaveResult> {
-        // Determine the .midlight file path
-        let midlight_path = if file_path.ends_with(".midlight") {
+        self.save_document_with(file_path, json, trigger, None).await
+    }
+
+    /// Save a document an AI edit produced. The document as it was is
+    /// checkpointed first, and both checkpoints are credited to the edit.
+    pub async fn save_ai_edit(
+        &self,
+        file_path: &str,
+        json: Value,
+        attribution: &CheckpointAttribution,
+    ) -> Result<SaveResult> {
+        let midlight_path = Self::midlight_path(file_path);
+        if let Some(before) = self.read_midlight(&self.workspace_root.join(&midlight_path)) {
+            self.checkpoint_manager
+                .write()
+                .await
+                .create_attributed_checkpoint(
+                    &midlight_path,
+                    &serde_json::to_string(&before)?,
+                    "{}",
+                    "before_ai_edit",
+                    attribution,
+                )
+                .await?;
+        }
+        self.save_document_with(file_path, json, "ai_edit", Some(attribution)).await
+    }
+
+    /// Checkpoint an AI edit that's already been written: the .midlight
+    /// document as it was before, if known, then as it is now
+    pub async fn checkpoint_ai_edit(
+        &self,
+        file_path: &str,
+        before: Option<&Value>,
+        attribution: &CheckpointAttribution,
+    ) -> Result<()> {
+        let mut cm = self.checkpoint_manager.write().await;
+        if let Some(before) = before {
+            cm.create_attributed_checkpoint(
+                file_path,
+                &serde_json::to_string(before)?,
+                "{}",
+                "before_ai_edit",
+                attribution,
+            )
+            .await?;
+        }
+        if let Some(after) = self.read_midlight(&self.workspace_root.join(file_path)) {
+            cm.create_attributed_checkpoint(
+                file_path,
+                &serde_json::to_string(&after)?,
+                "{}",
+                "ai_edit",
+                attribution,
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// The .midlight file a document is saved to
+    fn midlight_path(file_path: &str) -> String {
+        if file_path.ends_with(".midlight") {
             file_path.to_string()
         } else if file_path.ends_with(".md") {
             file_path.replace(".md", ".midlight")
         } else {
             format!("{}.midlight", file_path)
-        };
+        }
+    }
+
+    async fn save_document_with(
+        &self,
+        file_path: &str,
+        json: Value,
+        trigger: &str,
+        attribution: Option<&CheckpointAttribution>,
+    ) -> Result<SaveResult> {
+        let midlight_path = Self::midlight_path(file_path);
 
         let full_path = self.workspace_root.join(&midlight_path);
+        if document_lock::is_locked(&full_path) {
+            return Err(MidlightError::DocumentLocked(midlight_path));
+        }
 
         // Ensure parent directory exists
         if let Some(parent) = full_path.parent() {
@@ -356,10 +449,8 @@ impl WorkspaceManager {
         }
 
         // Read existing document to preserve meta.created
-        let (created, existing_images) = if full_path.exists() {
-            let existing = fs::read_to_string(&full_path)
-                .ok()
-                .and_then(|s| serde_json::from_str::<Value>(&s).ok());
+        let (created, existing_images, previous_content) = if full_path.exists() {
+            let existing = self.read_midlight(&full_path);
             let created = existing
                 .as_ref()
                 .and_then(|d| d.get("meta"))
@@ -367,9 +458,10 @@ impl WorkspaceManager {
                 .and_then(|c| c.as_str())
                 .map(|s| s.to_string());
             let images = existing.as_ref().and_then(|d| d.get("images")).cloned();
-            (created, images)
+            let content = existing.as_ref().and_then(|d| d.get("content")).cloned();
+            (created, images, content)
         } else {
-            (None, None)
+            (None, None, None)
         };
 
         let now = chrono::Utc::now().to_rfc3339();
@@ -390,25 +482,51 @@ impl WorkspaceManager {
         });
 
         // Write the .midlight file
-        fs::write(&full_path, serde_json::to_string_pretty(&midlight_doc)?)?;
+        self.write_midlight(&full_path, &midlight_doc)?;
+
+        // Queue the document for background search indexing
+        RAG_INDEXER.file_changed(full_path.clone(), false);
+        // The watcher ignores the app's own saves
+        refresh_tree_cache(&full_path);
+
+        self.update_image_refs(&midlight_path, &midlight_doc["content"]).await;
+        self.update_link_graph(&midlight_path, &midlight_doc["content"]);
+        self.update_task_index(&midlight_path);
+        // Text converted from a Markdown file wasn't written just now
+        let previous = previous_content.as_ref().or_else(|| {
+            (!file_path.ends_with(".midlight")).then_some(&midlight_doc["content"])
+        });
+        self.update_writing_stats(&midlight_path, previous, &midlight_doc["content"]);
 
         // For checkpoint, we store the full midlight document content
         let content_for_checkpoint = serde_json::to_string(&midlight_doc)?;
         let sidecar_placeholder = "{}"; // Sidecar info is now part of the midlight doc
 
-        let checkpoint = self
-            .checkpoint_manager
-            .write()
-            .await
-            .create_checkpoint(
-                &midlight_path,
-                &content_for_checkpoint,
-                sidecar_placeholder,
-                trigger,
-                None,
-                None,
-            )
-            .await?;
+        let mut cm = self.checkpoint_manager.write().await;
+        let checkpoint = match attribution {
+            Some(attribution) => {
+                cm.create_attributed_checkpoint(
+                    &midlight_path,
+                    &content_for_checkpoint,
+                    sidecar_placeholder,
+                    trigger,
+                    attribution,
+                )
+                .await?
+            }
+            None => {
+                cm.create_checkpoint(
+                    &midlight_path,
+                    &content_for_checkpoint,
+                    sidecar_placeholder,
+                    trigger,
+                    None,
+                    None,
+                )
+                .await?
+            }
+        };
+        drop(cm);
 
         // Clear recovery file
         let recovery_path = self.midlight_dir.join("recovery").join(format!(
@@ -430,6 +548,233 @@ impl WorkspaceManager {
         })
     }
 
+    /// A document's content as Markdown, for editing outside the app
+    pub async fn export_markdown(&self, file_path: &str) -> Result<String> {
+        let loaded = self.load_document(file_path).await?;
+        Ok(self.tiptap_to_markdown(&loaded.json))
+    }
+
+    /// Replace a document's content with Markdown edited outside the app
+    pub async fn import_markdown(
+        &self,
+        file_path: &str,
+        markdown: &str,
+        trigger: &str,
+    ) -> Result<SaveResult> {
+        let json = self.markdown_to_tiptap(markdown);
+        self.save_document(file_path, json, trigger).await
+    }
+
+    /// Add Markdown to the end of a document, creating it if it doesn't exist
+    pub async fn append_markdown(
+        &self,
+        file_path: &str,
+        markdown: &str,
+        trigger: &str,
+    ) -> Result<SaveResult> {
+        let mut json = self.load_document(file_path).await?.json;
+        let appended = self.markdown_to_tiptap(markdown);
+
+        if let Some(content) = json.get_mut("content").and_then(Value::as_array_mut) {
+            // A new document starts with one empty paragraph
+            if content.len() == 1 && content[0].get("content").is_none() {
+                content.clear();
+            }
+            if let Some(nodes) = appended.get("content").and_then(Value::as_array) {
+                content.extend(nodes.iter().cloned());
+            }
+        }
+        self.save_document(file_path, json, trigger).await
+    }
+
+    /// Read a .midlight file, with its content put back together if it's
+    /// stored in sections
+    fn read_midlight(&self, full_path: &Path) -> Option<Value> {
+        let mut document: Value = serde_json::from_str(&fs::read_to_string(full_path).ok()?).ok()?;
+        if let Some(manifest) = document_chunks::manifest(&document) {
+            document["content"] =
+                document_chunks::load_content(&self.object_store, &manifest).ok()?;
+        }
+        Some(document)
+    }
+
+    /// Write a .midlight file, storing its content in sections if it's long.
+    /// Sections that haven't changed since the last save are already stored.
+    fn write_midlight(&self, full_path: &Path, midlight_doc: &Value) -> Result<()> {
+        let sections = Sections::split(&midlight_doc["content"], &self.object_store);
+        if !sections.should_chunk() {
+            write_atomic(full_path, serde_json::to_string_pretty(midlight_doc)?)?;
+            return Ok(());
+        }
+
+        let written = sections
+            .store(&self.object_store)
+            .map_err(MidlightError::Internal)?;
+        tracing::debug!(
+            "Stored {} of {} sections of {}",
+            written,
+            sections.manifest.sections.len(),
+            full_path.display()
+        );
+        let mut chunked = serde_json::Map::new();
+        for (key, value) in midlight_doc.as_object().into_iter().flatten() {
+            if key != "content" {
+                chunked.insert(key.clone(), value.clone());
+            }
+        }
+        chunked.insert("chunks".to_string(), serde_json::to_value(&sections.manifest)?);
+        write_atomic(full_path, serde_json::to_string_pretty(&chunked)?)?;
+        Ok(())
+    }
+
+    /// A document's sections, so a long document can be loaded a section at
+    /// a time. Documents not stored in sections are split as they are.
+    pub fn document_sections(&self, file_path: &str) -> Result<Vec<SectionEntry>> {
+        let full_path = self.workspace_root.join(file_path);
+        let document: Value = serde_json::from_str(&fs::read_to_string(&full_path)?)?;
+        Ok(match document_chunks::manifest(&document) {
+            Some(manifest) => manifest.sections,
+            None => {
+                let content = document.get("content").unwrap_or(&Value::Null);
+                Sections::split(content, &self.object_store).manifest.sections
+            }
+        })
+    }
+
+    /// The blocks of one section of a document
+    pub fn load_section(&self, file_path: &str, index: usize) -> Result<Vec<Value>> {
+        let full_path = self.workspace_root.join(file_path);
+        let document: Value = serde_json::from_str(&fs::read_to_string(&full_path)?)?;
+        if let Some(manifest) = document_chunks::manifest(&document) {
+            let entry = manifest
+                .sections
+                .get(index)
+                .ok_or_else(|| MidlightError::NotFound(format!("Section {}", index)))?;
+            return document_chunks::load_section(&self.object_store, entry)
+                .map_err(MidlightError::Internal);
+        }
+
+        let content = document.get("content").unwrap_or(&Value::Null);
+        Sections::split(content, &self.object_store)
+            .blocks(index)
+            .ok_or_else(|| MidlightError::NotFound(format!("Section {}", index)))
+    }
+
+    /// Replace one section of a document. `base_hash` is the hash the
+    /// section had when it was loaded; if it's changed since, nothing is
+    /// saved. Only the sections that changed are written.
+    pub async fn save_section(
+        &self,
+        file_path: &str,
+        index: usize,
+        base_hash: &str,
+        blocks: Vec<Value>,
+        trigger: &str,
+    ) -> Result<SaveResult> {
+        let full_path = self.workspace_root.join(file_path);
+        let document = self
+            .read_midlight(&full_path)
+            .ok_or_else(|| MidlightError::DocumentNotFound(file_path.to_string()))?;
+        let sections = Sections::split(&document["content"], &self.object_store);
+        let Some(entry) = sections.manifest.sections.get(index) else {
+            return Err(MidlightError::NotFound(format!("Section {}", index)));
+        };
+        if entry.hash != base_hash {
+            return Err(MidlightError::InvalidInput(format!(
+                "Section {} of {} has changed since it was loaded",
+                index, file_path
+            )));
+        }
+
+        let start: usize = sections.manifest.sections[..index]
+            .iter()
+            .map(|s| s.blocks)
+            .sum();
+        let mut content = document["content"].clone();
+        if let Some(children) = content.get_mut("content").and_then(Value::as_array_mut) {
+            children.splice(start..start + entry.blocks, blocks);
+        }
+        self.save_document(file_path, content, trigger).await
+    }
+
+    /// Record which images a saved document uses, then delete images that
+    /// have gone unreferenced for longer than the grace period
+    async fn update_image_refs(&self, midlight_path: &str, content: &Value) {
+        let store = ImageRefStore::new(&self.workspace_root);
+        let refs = image_refs::extract_refs(&content.to_string());
+        if let Err(e) = store.update_document(&document_key(midlight_path), refs) {
+            tracing::warn!("Failed to update image references: {}", e);
+            return;
+        }
+
+        let expired = match store.take_expired_orphans() {
+            Ok(expired) => expired,
+            Err(e) => {
+                tracing::warn!("Failed to read image references: {}", e);
+                return;
+            }
+        };
+        let images = ImageManager::new(&self.workspace_root);
+        for hash in expired {
+            if let Err(e) = images.delete(&hash).await {
+                tracing::debug!("Failed to delete unreferenced image {}: {}", hash, e);
+            }
+        }
+    }
+
+    /// Record the links and tags of a saved document in the link graph
+    fn update_link_graph(&self, midlight_path: &str, content: &Value) {
+        let key = document_key(midlight_path);
+        let links = link_graph::midlight_links(&key, content);
+        let tags = link_graph::midlight_tags(content);
+        if let Err(e) =
+            LinkGraphStore::new(&self.workspace_root).update_document(&key, links, tags)
+        {
+            tracing::warn!("Failed to update link graph: {}", e);
+        }
+    }
+
+    /// Re-read the tasks of a saved document into the task index
+    fn update_task_index(&self, midlight_path: &str) {
+        if let Err(e) = TaskIndex::new(&self.workspace_root).update_document(midlight_path) {
+            tracing::warn!("Failed to update task index: {}", e);
+        }
+    }
+
+    /// Count the words a save added and removed towards today's writing
+    fn update_writing_stats(&self, midlight_path: &str, previous: Option<&Value>, content: &Value) {
+        let today = chrono::Local::now().date_naive();
+        if let Err(e) = WritingStats::new(&self.workspace_root).record_save(
+            midlight_path,
+            previous,
+            content,
+            today,
+        ) {
+            tracing::warn!("Failed to update writing stats: {}", e);
+        }
+    }
+
+    /// Documents and the links between them
+    pub fn link_graph(&self) -> Result<LinkGraph> {
+        LinkGraphStore::new(&self.workspace_root)
+            .graph()
+            .map_err(MidlightError::Internal)
+    }
+
+    /// Documents linking to a document
+    pub fn backlinks(&self, file_path: &str) -> Result<Vec<String>> {
+        LinkGraphStore::new(&self.workspace_root)
+            .backlinks(file_path)
+            .map_err(MidlightError::Internal)
+    }
+
+    /// Tags in the workspace, each with the documents that have it
+    pub fn tags(&self) -> Result<BTreeMap<String, Vec<String>>> {
+        LinkGraphStore::new(&self.workspace_root)
+            .tags()
+            .map_err(MidlightError::Internal)
+    }
+
     /// Get checkpoints for a file
     pub async fn get_checkpoints(&self, file_path: &str) -> Result<Vec<Checkpoint>> {
         self.checkpoint_manager
@@ -481,6 +826,9 @@ impl WorkspaceManager {
         };
 
         let full_path = self.workspace_root.join(&midlight_path);
+        if document_lock::is_locked(&full_path) {
+            return Err(MidlightError::DocumentLocked(midlight_path));
+        }
 
         // Ensure parent directory exists
         if let Some(parent) = full_path.parent() {
@@ -488,10 +836,8 @@ impl WorkspaceManager {
         }
 
         // Read existing document to preserve meta.created
-        let (created, existing_images) = if full_path.exists() {
-            let existing = fs::read_to_string(&full_path)
-                .ok()
-                .and_then(|s| serde_json::from_str::<Value>(&s).ok());
+        let (created, existing_images, previous_content) = if full_path.exists() {
+            let existing = self.read_midlight(&full_path);
             let created = existing
                 .as_ref()
                 .and_then(|d| d.get("meta"))
@@ -499,9 +845,10 @@ impl WorkspaceManager {
                 .and_then(|c| c.as_str())
                 .map(|s| s.to_string());
             let images = existing.as_ref().and_then(|d| d.get("images")).cloned();
-            (created, images)
+            let content = existing.as_ref().and_then(|d| d.get("content")).cloned();
+            (created, images, content)
         } else {
-            (None, None)
+            (None, None, None)
         };
 
         let now = chrono::Utc::now().to_rfc3339();
@@ -522,7 +869,12 @@ impl WorkspaceManager {
         });
 
         // Write the .midlight file
-        fs::write(&full_path, serde_json::to_string_pretty(&midlight_doc)?)?;
+        self.write_midlight(&full_path, &midlight_doc)?;
+        self.update_writing_stats(
+            &midlight_path,
+            previous_content.as_ref(),
+            &midlight_doc["content"],
+        );
 
         // For checkpoint, store the full midlight document
         let content_for_checkpoint = serde_json::to_string(&midlight_doc)?;
@@ -549,6 +901,29 @@ impl WorkspaceManager {
         })
     }
 
+    /// Bookmark a document's current on-disk content without rewriting it.
+    /// Returns None if the file doesn't exist.
+    pub async fn checkpoint_file(
+        &self,
+        file_path: &str,
+        label: &str,
+        description: Option<&str>,
+    ) -> Result<Option<Checkpoint>> {
+        let full_path = self.workspace_root.join(file_path);
+        if !full_path.is_file() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&full_path)?;
+
+        let checkpoint = self
+            .checkpoint_manager
+            .write()
+            .await
+            .create_checkpoint(file_path, &content, "{}", "bookmark", Some(label), description)
+            .await?;
+        Ok(Some(checkpoint))
+    }
+
     /// Compare two checkpoints
     pub async fn compare_checkpoints(
         &self,
@@ -570,6 +945,120 @@ impl WorkspaceManager {
         })
     }
 
+    // ============================================
+    // Document Variants
+    // ============================================
+
+    /// A document's current content, which variants are copies of
+    fn variant_source(&self, file_path: &str) -> Result<Value> {
+        if !file_path.ends_with(".midlight") {
+            return Err(MidlightError::InvalidInput(format!(
+                "Variants need a .midlight document: {}",
+                file_path
+            )));
+        }
+        self.read_midlight(&self.workspace_root.join(file_path))
+            .map(|document| document["content"].clone())
+            .ok_or_else(|| MidlightError::DocumentNotFound(file_path.to_string()))
+    }
+
+    /// A variant's content and the content it started from
+    async fn variant_contents(&self, file_path: &str, name: &str) -> Result<(Value, Value)> {
+        let mut cm = self.checkpoint_manager.write().await;
+        let variant = cm.get_variant(file_path, name).await?;
+        let (content, base) = cm.get_variant_content(&variant).await?;
+        Ok((serde_json::from_str(&content)?, serde_json::from_str(&base)?))
+    }
+
+    /// Start a named variant of a document from its current content
+    pub async fn create_variant(&self, file_path: &str, name: &str) -> Result<DocumentVariant> {
+        let content = serde_json::to_string(&self.variant_source(file_path)?)?;
+        self.checkpoint_manager
+            .write()
+            .await
+            .create_variant(file_path, name, &content)
+            .await
+    }
+
+    pub async fn get_variants(&self, file_path: &str) -> Result<Vec<DocumentVariant>> {
+        self.checkpoint_manager
+            .write()
+            .await
+            .get_variants(file_path)
+            .await
+    }
+
+    /// A variant's content, for editing
+    pub async fn load_variant(&self, file_path: &str, name: &str) -> Result<Value> {
+        Ok(self.variant_contents(file_path, name).await?.0)
+    }
+
+    pub async fn save_variant(
+        &self,
+        file_path: &str,
+        name: &str,
+        json: Value,
+    ) -> Result<DocumentVariant> {
+        let content = serde_json::to_string(&json)?;
+        self.checkpoint_manager
+            .write()
+            .await
+            .update_variant(file_path, name, &content)
+            .await
+    }
+
+    /// What merging a variant would change in its document, with `context`
+    /// unchanged lines around each change
+    pub async fn preview_variant_merge(
+        &self,
+        file_path: &str,
+        name: &str,
+        context: usize,
+    ) -> Result<VariantMergePreview> {
+        let current = self.variant_source(file_path)?;
+        let (variant, base) = self.variant_contents(file_path, name).await?;
+        let diff = line_diff(
+            &self.tiptap_to_markdown(&current),
+            &self.tiptap_to_markdown(&variant),
+        );
+        let count = |op| diff.iter().filter(|l| l.op == op).count();
+
+        Ok(VariantMergePreview {
+            insertions: count(DiffOp::Added),
+            deletions: count(DiffOp::Removed),
+            hunks: diff_hunks(&diff, context),
+            document_changed: current != base,
+        })
+    }
+
+    /// Replace a document's content with a variant's and drop the variant.
+    /// The document as it was is bookmarked first, so edits made to it
+    /// since the variant was created can be restored.
+    pub async fn merge_variant(&self, file_path: &str, name: &str) -> Result<SaveResult> {
+        let current = self.variant_source(file_path)?;
+        let (variant, _) = self.variant_contents(file_path, name).await?;
+
+        self.create_bookmark(
+            file_path,
+            current,
+            &format!("Before merging \"{}\"", name),
+            None,
+        )
+        .await?;
+        let result = self.save_document(file_path, variant, "variant-merge").await?;
+        self.discard_variant(file_path, name).await?;
+        Ok(result)
+    }
+
+    pub async fn discard_variant(&self, file_path: &str, name: &str) -> Result<()> {
+        self.checkpoint_manager
+            .write()
+            .await
+            .remove_variant(file_path, name)
+            .await?;
+        Ok(())
+    }
+
     // ============================================
     // Project and Context Methods
     // ============================================
@@ -964,136 +1453,19 @@ impl WorkspaceManager {
         })
     }
 
-    /// Simple markdown to Tiptap JSON conversion
-    /// Full conversion is done in TypeScript for accuracy
+    /// Markdown documents are stored one block per line, so blank lines
+    /// round-trip as empty paragraphs
     fn markdown_to_tiptap(&self, markdown: &str) -> Value {
-        let mut content = Vec::new();
-
-        for line in markdown.lines() {
-            if line.starts_with("# ") {
-                content.push(serde_json::json!({
-                    "type": "heading",
-                    "attrs": { "level": 1 },
-                    "content": [{ "type": "text", "text": &line[2..] }]
-                }));
-            } else if line.starts_with("## ") {
-                content.push(serde_json::json!({
-                    "type": "heading",
-                    "attrs": { "level": 2 },
-                    "content": [{ "type": "text", "text": &line[3..] }]
-                }));
-            } else if line.starts_with("### ") {
-                content.push(serde_json::json!({
-                    "type": "heading",
-                    "attrs": { "level": 3 },
-                    "content": [{ "type": "text", "text": &line[4..] }]
-                }));
-            } else if !line.is_empty() {
-                content.push(serde_json::json!({
-                    "type": "paragraph",
-                    "content": [{ "type": "text", "text": line }]
-                }));
-            } else {
-                content.push(serde_json::json!({
-                    "type": "paragraph"
-                }));
-            }
-        }
-
-        if content.is_empty() {
-            content.push(serde_json::json!({
-                "type": "paragraph"
-            }));
-        }
-
-        serde_json::json!({
-            "type": "doc",
-            "content": content
-        })
+        markdown_convert::markdown_to_tiptap(markdown, MarkdownOptions::workspace())
     }
 
-    /// Simple Tiptap JSON to markdown conversion
-    #[allow(dead_code)]
     fn tiptap_to_markdown(&self, json: &Value) -> String {
-        let mut lines = Vec::new();
-
-        if let Some(content) = json.get("content").and_then(|c| c.as_array()) {
-            for node in content {
-                let node_type = node.get("type").and_then(|t| t.as_str()).unwrap_or("");
-
-                match node_type {
-                    "heading" => {
-                        let level = node
-                            .get("attrs")
-                            .and_then(|a| a.get("level"))
-                            .and_then(|l| l.as_u64())
-                            .unwrap_or(1) as usize;
-                        let text = self.extract_text_content(node);
-                        lines.push(format!("{} {}", "#".repeat(level), text));
-                    }
-                    "paragraph" => {
-                        let text = self.extract_text_content(node);
-                        lines.push(text);
-                    }
-                    "bulletList" => {
-                        if let Some(items) = node.get("content").and_then(|c| c.as_array()) {
-                            for item in items {
-                                let text = self.extract_text_content(item);
-                                lines.push(format!("- {}", text));
-                            }
-                        }
-                    }
-                    "orderedList" => {
-                        if let Some(items) = node.get("content").and_then(|c| c.as_array()) {
-                            for (i, item) in items.iter().enumerate() {
-                                let text = self.extract_text_content(item);
-                                lines.push(format!("{}. {}", i + 1, text));
-                            }
-                        }
-                    }
-                    "blockquote" => {
-                        let text = self.extract_text_content(node);
-                        for line in text.lines() {
-                            lines.push(format!("> {}", line));
-                        }
-                    }
-                    "codeBlock" => {
-                        let lang = node
-                            .get("attrs")
-                            .and_then(|a| a.get("language"))
-                            .and_then(|l| l.as_str())
-                            .unwrap_or("");
-                        let text = self.extract_text_content(node);
-                        lines.push(format!("```{}", lang));
-                        lines.push(text);
-                        lines.push("```".to_string());
-                    }
-                    "horizontalRule" => {
-                        lines.push("---".to_string());
-                    }
-                    _ => {}
-                }
-            }
-        }
-
-        lines.join("\n")
+        markdown_convert::tiptap_to_markdown(json, MarkdownOptions::workspace())
     }
 
-    #[allow(dead_code)]
+    #[cfg(test)]
     fn extract_text_content(&self, node: &Value) -> String {
-        if let Some(text) = node.get("text").and_then(|t| t.as_str()) {
-            return text.to_string();
-        }
-
-        if let Some(content) = node.get("content").and_then(|c| c.as_array()) {
-            return content
-                .iter()
-                .map(|n| self.extract_text_content(n))
-                .collect::<Vec<_>>()
-                .join("");
-        }
-
-        String::new()
+        markdown_convert::extract_text_content(node)
     }
 
     #[allow(dead_code)]
@@ -1134,6 +1506,13 @@ impl WorkspaceManagerRegistry {
         Ok(manager)
     }
 
+    /// Roots of the open workspaces, sorted
+    pub fn workspace_roots(&self) -> Vec<String> {
+        let mut roots: Vec<String> = self.managers.keys().cloned().collect();
+        roots.sort();
+        roots
+    }
+
     /// Remove a workspace manager
     pub fn remove(&mut self, workspace_root: &str) {
         self.managers.remove(workspace_root);
@@ -1294,6 +1673,30 @@ mod tests {
         assert!(temp.path().join("test.md.backup").exists());
     }
 
+    #[tokio::test]
+    async fn test_append_markdown() {
+        let temp = TempDir::new().unwrap();
+        let manager = WorkspaceManager::new(temp.path());
+        manager.init().await.unwrap();
+
+        manager
+            .append_markdown("inbox.midlight", "First clipping", "api")
+            .await
+            .unwrap();
+        manager
+            .append_markdown("inbox.midlight", "## Second\n\nMore text", "api")
+            .await
+            .unwrap();
+
+        let loaded = manager.load_document("inbox.midlight").await.unwrap();
+        let content = loaded.json["content"].as_array().unwrap();
+        // The blank line is kept as an empty paragraph
+        assert_eq!(content.len(), 4);
+        assert_eq!(content[0]["content"][0]["text"], "First clipping");
+        assert_eq!(content[1]["type"], "heading");
+        assert_eq!(content[3]["content"][0]["text"], "More text");
+    }
+
     #[tokio::test]
     async fn test_load_unsupported_format() {
         let temp = TempDir::new().unwrap();
@@ -1364,6 +1767,68 @@ mod tests {
         assert!(temp.path().join("test.midlight").exists());
     }
 
+    #[tokio::test]
+    async fn test_save_long_document_in_sections() {
+        let temp = TempDir::new().unwrap();
+        let manager = WorkspaceManager::new(temp.path());
+        manager.init().await.unwrap();
+
+        let sentence = "All work and no play makes a long manuscript. ".repeat(24);
+        let mut blocks = Vec::new();
+        for chapter in 0..4 {
+            blocks.push(serde_json::json!({
+                "type": "heading",
+                "attrs": { "level": 1 },
+                "content": [{ "type": "text", "text": format!("Chapter {}", chapter) }]
+            }));
+            for _ in 0..300 {
+                blocks.push(serde_json::json!({
+                    "type": "paragraph",
+                    "content": [{ "type": "text", "text": sentence }]
+                }));
+            }
+        }
+        let json = serde_json::json!({ "type": "doc", "content": blocks });
+        manager
+            .save_document("novel.midlight", json.clone(), "manual")
+            .await
+            .unwrap();
+
+        let saved: Value = serde_json::from_str(
+            &fs::read_to_string(temp.path().join("novel.midlight")).unwrap(),
+        )
+        .unwrap();
+        assert!(saved.get("content").is_none());
+        assert!(saved["meta"]["created"].is_string());
+        assert_eq!(manager.load_document("novel.midlight").await.unwrap().json, json);
+
+        let sections = manager.document_sections("novel.midlight").unwrap();
+        assert_eq!(sections.len(), 4);
+        assert_eq!(sections[2].title.as_deref(), Some("Chapter 2"));
+        assert_eq!(manager.load_section("novel.midlight", 2).unwrap()[0], json["content"][602]);
+
+        // Saving one section leaves the others as they were
+        let edited = vec![serde_json::json!({
+            "type": "heading",
+            "attrs": { "level": 1 },
+            "content": [{ "type": "text", "text": "Chapter two, rewritten" }]
+        })];
+        manager
+            .save_section("novel.midlight", 2, &sections[2].hash, edited.clone(), "manual")
+            .await
+            .unwrap();
+        let resaved = manager.document_sections("novel.midlight").unwrap();
+        assert_eq!(resaved[1], sections[1]);
+        assert_eq!(resaved[3], sections[3]);
+        assert_eq!(manager.load_section("novel.midlight", 2).unwrap(), edited);
+
+        // A stale section is refused
+        let stale = manager
+            .save_section("novel.midlight", 2, &sections[2].hash, edited, "manual")
+            .await;
+        assert!(stale.is_err());
+    }
+
     #[tokio::test]
     async fn test_save_document_converts_md_path() {
         let temp = TempDir::new().unwrap();
@@ -1478,6 +1943,120 @@ mod tests {
     // Checkpoint operations tests
     // ============================================
 
+    #[tokio::test]
+    async fn test_variant_merge() {
+        let temp = TempDir::new().unwrap();
+        let manager = WorkspaceManager::new(temp.path());
+        manager.init().await.unwrap();
+        let paragraph = |text: &str| {
+            serde_json::json!({
+                "type": "doc",
+                "content": [{
+                    "type": "paragraph",
+                    "content": [{ "type": "text", "text": text }]
+                }]
+            })
+        };
+
+        manager
+            .save_document("story.midlight", paragraph("They parted."), "manual")
+            .await
+            .unwrap();
+        manager
+            .create_variant("story.midlight", "Happier ending")
+            .await
+            .unwrap();
+        manager
+            .save_variant("story.midlight", "Happier ending", paragraph("They stayed."))
+            .await
+            .unwrap();
+        assert_eq!(
+            manager
+                .load_document("story.midlight")
+                .await
+                .unwrap()
+                .json,
+            paragraph("They parted.")
+        );
+
+        let preview = manager
+            .preview_variant_merge("story.midlight", "Happier ending", 3)
+            .await
+            .unwrap();
+        assert_eq!((preview.insertions, preview.deletions), (1, 1));
+        assert_eq!(preview.hunks.len(), 1);
+        assert!(!preview.document_changed);
+
+        manager
+            .merge_variant("story.midlight", "Happier ending")
+            .await
+            .unwrap();
+        assert_eq!(
+            manager
+                .load_document("story.midlight")
+                .await
+                .unwrap()
+                .json,
+            paragraph("They stayed.")
+        );
+        assert!(manager
+            .get_variants("story.midlight")
+            .await
+            .unwrap()
+            .is_empty());
+        let checkpoints = manager.get_checkpoints("story.midlight").await.unwrap();
+        assert!(checkpoints
+            .iter()
+            .any(|c| c.label.as_deref() == Some("Before merging \"Happier ending\"")));
+    }
+
+    #[tokio::test]
+    async fn test_save_ai_edit_checkpoints_with_attribution() {
+        let temp = TempDir::new().unwrap();
+        let manager = WorkspaceManager::new(temp.path());
+        manager.init().await.unwrap();
+        let paragraph = |text: &str| {
+            serde_json::json!({
+                "type": "doc",
+                "content": [{
+                    "type": "paragraph",
+                    "content": [{ "type": "text", "text": text }]
+                }]
+            })
+        };
+        let attribution = CheckpointAttribution {
+            conversation_id: Some("conv-1".to_string()),
+            tool: Some("edit_document".to_string()),
+            change_id: Some("change-1".to_string()),
+        };
+
+        manager
+            .save_document("notes.midlight", paragraph("Draft"), "manual")
+            .await
+            .unwrap();
+        manager
+            .save_ai_edit("notes.midlight", paragraph("Polished draft"), &attribution)
+            .await
+            .unwrap();
+
+        let checkpoints = manager.get_checkpoints("notes.midlight").await.unwrap();
+        let triggers = |attributed: bool| {
+            let mut triggers: Vec<_> = checkpoints
+                .iter()
+                .filter(|c| c.attribution.is_some() == attributed)
+                .map(|c| c.trigger.as_str())
+                .collect();
+            triggers.sort();
+            triggers
+        };
+        assert_eq!(triggers(true), vec!["ai_edit", "before_ai_edit"]);
+        assert_eq!(triggers(false), vec!["manual"]);
+        assert!(checkpoints
+            .iter()
+            .filter_map(|c| c.attribution.as_ref())
+            .all(|a| a == &attribution));
+    }
+
     #[tokio::test]
     async fn test_get_checkpoints_empty() {
         let temp = TempDir::new().unwrap();
@@ -1567,6 +2146,21 @@ mod tests {
     // Markdown to Tiptap conversion tests
     // ============================================
 
+    #[tokio::test]
+    async fn test_markdown_image_lines() {
+        let temp = TempDir::new().unwrap();
+        let manager = WorkspaceManager::new(temp.path());
+
+        let markdown = "Intro\n![A chart](midlight://img-0123456789abcdef)";
+        let json = manager.markdown_to_tiptap(markdown);
+        let image = &json["content"][1];
+        assert_eq!(image["type"], "image");
+        assert_eq!(image["attrs"]["src"], "midlight://img-0123456789abcdef");
+        assert_eq!(image["attrs"]["alt"], "A chart");
+
+        assert_eq!(manager.tiptap_to_markdown(&json), markdown);
+    }
+
     #[tokio::test]
     async fn test_markdown_to_tiptap_headings() {
         let temp = TempDir::new().unwrap();
@@ -0,0 +1,45 @@
+// Workspace Paths - Finding a path's workspace and naming documents in it
+//
+// A workspace is a folder with a .midlight directory. Services that keep
+// per-document data key it by the document's workspace-relative path with
+// forward slashes, so the same keys work on every platform.
+
+use std::path::{Path, PathBuf};
+
+/// The workspace a path belongs to: the closest ancestor with a .midlight
+/// directory
+pub fn find_workspace_root(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .skip(1)
+        .find(|dir| dir.join(".midlight").is_dir())
+        .map(Path::to_path_buf)
+}
+
+/// Documents are keyed by their workspace-relative path with forward slashes
+pub fn document_key(relative: &str) -> String {
+    relative.replace('\\', "/")
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find_workspace_root() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join(".midlight")).unwrap();
+        fs::create_dir_all(temp.path().join("notes")).unwrap();
+
+        assert_eq!(
+            find_workspace_root(&temp.path().join("notes").join("a.midlight")),
+            Some(temp.path().to_path_buf())
+        );
+        assert_eq!(document_key("notes\\a.midlight"), "notes/a.midlight");
+    }
+}
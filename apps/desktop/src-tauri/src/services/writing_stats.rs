@@ -0,0 +1,430 @@
+// Writing Stats - Words written per document per day, with goals and streaks
+//
+// Every save compares the document's words with the version it replaces and
+// adds the difference to the day's tally for that document. Words are
+// compared as a multiset, so text moved around a document isn't counted
+// twice and fixing a typo counts one word removed and one added. Days are in
+// local time, so a late-night session counts where the writer expects.
+//
+// Goals are either a number of words to add each day, or a length for the
+// document (or, for the project, all documents together) to reach. A
+// streak is the run of days up to today on which the daily goal was met, or
+// on which anything was written if there is no daily goal.
+//
+// Stats are stored in the workspace metadata store (.midlight/metadata.db)
+
+use crate::services::document_stats::midlight_blocks;
+use crate::services::metadata_store::{self, WritingRecords};
+use crate::services::workspace_paths::document_key;
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GoalPeriod {
+    /// Words to add each day
+    Daily,
+    /// Length to reach
+    Total,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WritingGoal {
+    pub words: u64,
+    pub period: GoalPeriod,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WordCount {
+    pub added: u64,
+    pub removed: u64,
+}
+
+impl WordCount {
+    fn add(&mut self, other: WordCount) {
+        self.added += other.added;
+        self.removed += other.removed;
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Goals {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<WritingGoal>,
+    #[serde(default)]
+    pub documents: BTreeMap<String, WritingGoal>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DayStats {
+    pub date: NaiveDate,
+    #[serde(flatten)]
+    pub count: WordCount,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WritingProgress {
+    /// Document the progress is for, or None for the whole project
+    pub document: Option<String>,
+    /// Current length in words
+    pub words: u64,
+    pub today: WordCount,
+    pub goal: Option<WritingGoal>,
+    /// Share of the goal reached, from 0 to 1
+    pub progress: Option<f64>,
+    /// Days in a row, up to today, the goal was met
+    pub current_streak: u32,
+    pub longest_streak: u32,
+}
+
+// ============================================================================
+// Store
+// ============================================================================
+
+pub struct WritingStats {
+    workspace_root: PathBuf,
+}
+
+impl WritingStats {
+    pub fn new(workspace_root: &Path) -> Self {
+        Self {
+            workspace_root: workspace_root.to_path_buf(),
+        }
+    }
+
+    /// Count the words a save added to and removed from a document.
+    /// `previous` is the content it replaced, if the document existed.
+    pub fn record_save(
+        &self,
+        relative: &str,
+        previous: Option<&Value>,
+        current: &Value,
+        date: NaiveDate,
+    ) -> Result<WordCount, String> {
+        let key = document_key(relative);
+
+        let current_words = words(current);
+        let count = match previous {
+            Some(previous) => word_changes(&words(previous), &current_words),
+            None => WordCount {
+                added: current_words.len() as u64,
+                removed: 0,
+            },
+        };
+        metadata_store::open(&self.workspace_root)?.record_writing(
+            &key,
+            current_words.len() as u64,
+            date,
+            count,
+        )?;
+        Ok(count)
+    }
+
+    /// Set or clear the goal of a document, or of the project if no
+    /// document is given
+    pub fn set_goal(
+        &self,
+        document: Option<&str>,
+        goal: Option<WritingGoal>,
+    ) -> Result<(), String> {
+        if goal.is_some_and(|g| g.words == 0) {
+            return Err("A goal needs at least one word".to_string());
+        }
+        let key = document.map(document_key);
+        metadata_store::open(&self.workspace_root)?.set_goal(key.as_deref(), goal)
+    }
+
+    pub fn goals(&self) -> Goals {
+        self.load().goals
+    }
+
+    /// Words added and removed on each day from `from` to `to`, for a
+    /// document or the whole project. Days without writing are included.
+    pub fn history(&self, document: Option<&str>, from: NaiveDate, to: NaiveDate) -> Vec<DayStats> {
+        let stats = self.load();
+        let key = document.map(document_key);
+        from.iter_days()
+            .take_while(|date| *date <= to)
+            .map(|date| DayStats {
+                date,
+                count: day_count(&stats, key.as_deref(), date),
+            })
+            .collect()
+    }
+
+    /// Today's writing, goal and streaks for a document or the whole project
+    pub fn progress(&self, document: Option<&str>, today: NaiveDate) -> WritingProgress {
+        let stats = self.load();
+        let key = document.map(document_key);
+        let goal = match &key {
+            Some(key) => stats.goals.documents.get(key).copied(),
+            None => stats.goals.project,
+        };
+        let words = match &key {
+            Some(key) => stats.words.get(key).copied().unwrap_or(0),
+            None => stats.words.values().sum(),
+        };
+        let today_count = day_count(&stats, key.as_deref(), today);
+
+        let progress = goal.map(|goal| {
+            let done = match goal.period {
+                GoalPeriod::Daily => today_count.added,
+                GoalPeriod::Total => words,
+            };
+            (done as f64 / goal.words as f64).min(1.0)
+        });
+
+        // Days with writing, and whether each met the daily goal
+        let daily = goal
+            .filter(|g| g.period == GoalPeriod::Daily)
+            .map_or(1, |g| g.words);
+        let met: Vec<NaiveDate> = stats
+            .days
+            .keys()
+            .copied()
+            .filter(|date| *date <= today)
+            .filter(|date| day_count(&stats, key.as_deref(), *date).added >= daily)
+            .collect();
+        let (current_streak, longest_streak) = streaks(&met, today);
+
+        WritingProgress {
+            document: key,
+            words,
+            today: today_count,
+            goal,
+            progress,
+            current_streak,
+            longest_streak,
+        }
+    }
+
+    fn load(&self) -> WritingRecords {
+        metadata_store::open(&self.workspace_root)
+            .and_then(|store| store.writing_records())
+            .unwrap_or_else(|e| {
+                tracing::warn!("Failed to read writing stats: {}", e);
+                WritingRecords::default()
+            })
+    }
+}
+
+fn day_count(stats: &WritingRecords, document: Option<&str>, date: NaiveDate) -> WordCount {
+    let mut total = WordCount::default();
+    if let Some(day) = stats.days.get(&date) {
+        match document {
+            Some(key) => total.add(day.get(key).copied().unwrap_or_default()),
+            None => day.values().for_each(|count| total.add(*count)),
+        }
+    }
+    total
+}
+
+/// The current streak (ending today, or yesterday if today has no writing
+/// yet) and the longest streak, from the sorted days a goal was met
+fn streaks(met: &[NaiveDate], today: NaiveDate) -> (u32, u32) {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for date in met {
+        run = match previous {
+            Some(p) if *date - p == Duration::days(1) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(*date);
+    }
+
+    let current = match previous {
+        Some(last) if last == today || last == today - Duration::days(1) => run,
+        _ => 0,
+    };
+    (current, longest)
+}
+
+// ============================================================================
+// Word counting
+// ============================================================================
+
+fn words(content: &Value) -> Vec<String> {
+    midlight_blocks(content)
+        .iter()
+        .flat_map(|block| {
+            block
+                .split_whitespace()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Words in `current` that aren't in `previous` and the other way round,
+/// counting repeats
+fn word_changes(previous: &[String], current: &[String]) -> WordCount {
+    let mut balance: HashMap<&str, i64> = HashMap::new();
+    for word in previous {
+        *balance.entry(word).or_default() -= 1;
+    }
+    for word in current {
+        *balance.entry(word).or_default() += 1;
+    }
+
+    let mut count = WordCount::default();
+    for difference in balance.values() {
+        if *difference > 0 {
+            count.added += *difference as u64;
+        } else {
+            count.removed += difference.unsigned_abs();
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn doc(text: &str) -> Value {
+        json!({ "type": "doc", "content": [
+            { "type": "paragraph", "content": [{ "type": "text", "text": text }] }
+        ]})
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, day).unwrap()
+    }
+
+    #[test]
+    fn test_record_save() {
+        let temp = TempDir::new().unwrap();
+        let stats = WritingStats::new(temp.path());
+
+        let first = stats
+            .record_save("Notes/a.midlight", None, &doc("one two three"), date(1))
+            .unwrap();
+        assert_eq!(
+            first,
+            WordCount {
+                added: 3,
+                removed: 0
+            }
+        );
+
+        // Moving words around is free; replacing one is a removal and an addition
+        let count = stats
+            .record_save(
+                "Notes\\a.midlight",
+                Some(&doc("one two three")),
+                &doc("three two one four"),
+                date(1),
+            )
+            .unwrap();
+        assert_eq!(
+            count,
+            WordCount {
+                added: 1,
+                removed: 0
+            }
+        );
+        let count = stats
+            .record_save(
+                "Notes/a.midlight",
+                Some(&doc("three two one four")),
+                &doc("three too one four"),
+                date(2),
+            )
+            .unwrap();
+        assert_eq!(
+            count,
+            WordCount {
+                added: 1,
+                removed: 1
+            }
+        );
+
+        let history = stats.history(Some("Notes/a.midlight"), date(1), date(3));
+        let added: Vec<u64> = history.iter().map(|d| d.count.added).collect();
+        assert_eq!(added, vec![4, 1, 0]);
+    }
+
+    #[test]
+    fn test_progress_and_streaks() {
+        let temp = TempDir::new().unwrap();
+        let stats = WritingStats::new(temp.path());
+        let ten = doc("a b c d e f g h i j");
+        let twenty = doc("a b c d e f g h i j k l m n o p q r s t");
+
+        stats
+            .record_save("a.midlight", None, &ten, date(1))
+            .unwrap();
+        stats
+            .record_save("a.midlight", Some(&ten), &twenty, date(2))
+            .unwrap();
+        stats
+            .record_save("b.midlight", None, &ten, date(4))
+            .unwrap();
+        // Only deleting doesn't extend a streak
+        stats
+            .record_save("a.midlight", Some(&twenty), &ten, date(5))
+            .unwrap();
+
+        let project = stats.progress(None, date(5));
+        assert_eq!((project.current_streak, project.longest_streak), (1, 2));
+        assert_eq!(project.words, 20);
+        assert_eq!(
+            project.today,
+            WordCount {
+                added: 0,
+                removed: 10
+            }
+        );
+
+        stats
+            .set_goal(
+                None,
+                Some(WritingGoal {
+                    words: 20,
+                    period: GoalPeriod::Daily,
+                }),
+            )
+            .unwrap();
+        let project = stats.progress(None, date(6));
+        assert_eq!(project.progress, Some(0.0));
+        assert_eq!((project.current_streak, project.longest_streak), (0, 0));
+
+        stats
+            .set_goal(
+                Some("a.midlight"),
+                Some(WritingGoal {
+                    words: 40,
+                    period: GoalPeriod::Total,
+                }),
+            )
+            .unwrap();
+        let document = stats.progress(Some("a.midlight"), date(5));
+        assert_eq!(document.words, 10);
+        assert_eq!(document.progress, Some(0.25));
+        assert!(stats
+            .set_goal(
+                None,
+                Some(WritingGoal {
+                    words: 0,
+                    period: GoalPeriod::Daily
+                })
+            )
+            .is_err());
+    }
+}
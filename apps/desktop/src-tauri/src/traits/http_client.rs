@@ -58,6 +58,9 @@ pub enum HttpError {
 
     #[error("Serialization error: {0}")]
     SerializationError(String),
+
+    #[error("Response body exceeds {0} bytes")]
+    BodyTooLarge(usize),
 }
 
 /// Result type for HTTP operations.
@@ -111,17 +114,55 @@ pub trait HttpClient: Send + Sync {
 #[derive(Debug, Clone)]
 pub struct ReqwestHttpClient {
     client: reqwest::Client,
+    /// Responses larger than this are refused while they download
+    max_body_bytes: Option<usize>,
 }
 
 impl ReqwestHttpClient {
     pub fn new() -> Self {
+        Self::with_client(reqwest::Client::new())
+    }
+
+    pub fn with_client(client: reqwest::Client) -> Self {
         Self {
-            client: reqwest::Client::new(),
+            client,
+            max_body_bytes: None,
         }
     }
 
-    pub fn with_client(client: reqwest::Client) -> Self {
-        Self { client }
+    /// Refuse response bodies over `limit` bytes, without reading past it
+    pub fn with_body_limit(mut self, limit: usize) -> Self {
+        self.max_body_bytes = Some(limit);
+        self
+    }
+
+    async fn read_body(&self, mut response: reqwest::Response) -> HttpResult<Vec<u8>> {
+        let Some(limit) = self.max_body_bytes else {
+            return response
+                .bytes()
+                .await
+                .map(|body| body.to_vec())
+                .map_err(|e| HttpError::RequestFailed(e.to_string()));
+        };
+
+        if response
+            .content_length()
+            .is_some_and(|length| length > limit as u64)
+        {
+            return Err(HttpError::BodyTooLarge(limit));
+        }
+        let mut body = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| HttpError::RequestFailed(e.to_string()))?
+        {
+            if body.len() + chunk.len() > limit {
+                return Err(HttpError::BodyTooLarge(limit));
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
     }
 }
 
@@ -147,11 +188,7 @@ impl HttpClient for ReqwestHttpClient {
             .iter()
             .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
             .collect();
-        let body = response
-            .bytes()
-            .await
-            .map_err(|e| HttpError::RequestFailed(e.to_string()))?
-            .to_vec();
+        let body = self.read_body(response).await?;
 
         Ok(HttpResponse {
             status,
@@ -181,11 +218,7 @@ impl HttpClient for ReqwestHttpClient {
             .iter()
             .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
             .collect();
-        let body = response
-            .bytes()
-            .await
-            .map_err(|e| HttpError::RequestFailed(e.to_string()))?
-            .to_vec();
+        let body = self.read_body(response).await?;
 
         Ok(HttpResponse {
             status,
@@ -213,11 +246,7 @@ impl HttpClient for ReqwestHttpClient {
             .iter()
             .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
             .collect();
-        let body = response
-            .bytes()
-            .await
-            .map_err(|e| HttpError::RequestFailed(e.to_string()))?
-            .to_vec();
+        let body = self.read_body(response).await?;
 
         Ok(HttpResponse {
             status,
@@ -248,11 +277,7 @@ impl HttpClient for ReqwestHttpClient {
             .iter()
             .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
             .collect();
-        let body = response
-            .bytes()
-            .await
-            .map_err(|e| HttpError::RequestFailed(e.to_string()))?
-            .to_vec();
+        let body = self.read_body(response).await?;
 
         Ok(HttpResponse {
             status,
@@ -280,11 +305,7 @@ impl HttpClient for ReqwestHttpClient {
             .iter()
             .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
             .collect();
-        let body = response
-            .bytes()
-            .await
-            .map_err(|e| HttpError::RequestFailed(e.to_string()))?
-            .to_vec();
+        let body = self.read_body(response).await?;
 
         Ok(HttpResponse {
             status,
@@ -315,11 +336,7 @@ impl HttpClient for ReqwestHttpClient {
             .iter()
             .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
             .collect();
-        let body = response
-            .bytes()
-            .await
-            .map_err(|e| HttpError::RequestFailed(e.to_string()))?
-            .to_vec();
+        let body = self.read_body(response).await?;
 
         Ok(HttpResponse {
             status,
@@ -484,6 +501,9 @@ mod mock {
 mod tests {
     use super::*;
     use serde_json::json;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
 
     #[tokio::test]
     async fn test_mock_http_client_get() {
@@ -534,4 +554,37 @@ mod tests {
         assert_eq!(resp2.status, 201);
         assert_eq!(resp2.text().unwrap(), "Second");
     }
+
+    /// Serve one response with the given head, then stream chunks of `x`
+    /// for as long as the client keeps reading
+    async fn endless_server(head: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 1024];
+            let _ = socket.read(&mut request).await;
+            socket.write_all(head.as_bytes()).await.unwrap();
+            let chunk = format!("400\r\n{}\r\n", "x".repeat(0x400));
+            while socket.write_all(chunk.as_bytes()).await.is_ok() {}
+        });
+        format!("http://{}/", addr)
+    }
+
+    #[tokio::test]
+    async fn test_body_limit_stops_reading_oversized_responses() {
+        let client = ReqwestHttpClient::new().with_body_limit(64 * 1024);
+
+        let url = endless_server("HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n").await;
+        let result = tokio::time::timeout(Duration::from_secs(10), client.get(&url))
+            .await
+            .expect("the download should stop at the limit");
+        assert!(matches!(result, Err(HttpError::BodyTooLarge(_))));
+
+        let url = endless_server("HTTP/1.1 200 OK\r\nContent-Length: 1000000000\r\n\r\n").await;
+        let result = tokio::time::timeout(Duration::from_secs(10), client.get(&url))
+            .await
+            .expect("a declared length over the limit should be refused");
+        assert!(matches!(result, Err(HttpError::BodyTooLarge(_))));
+    }
 }
@@ -0,0 +1,199 @@
+// System tray icon
+// The tray menu reopens recent documents, starts a new note, shows whether
+// requests queued while offline are still waiting (or paused for battery
+// saver or a metered connection), and pauses or resumes background indexing.
+// Services call refresh_tray when any of that changes; the menu is only
+// rebuilt when what it shows is different.
+
+use crate::commands::deep_link::{open_document, OpenDocumentEvent};
+use crate::services::power_state::{PauseReason, POWER_MONITOR};
+use crate::services::rag_indexer::RAG_INDEXER;
+use crate::services::recent_documents::{RecentDocument, RECENT_DOCUMENTS};
+use crate::services::request_queue::REQUEST_QUEUE;
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+use tauri::{
+    menu::{Menu, MenuBuilder, MenuItemBuilder, SubmenuBuilder},
+    tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
+    App, AppHandle, Emitter, Manager, Runtime,
+};
+use tracing::warn;
+
+const TRAY_ID: &str = "main";
+
+/// Prefix of the ids of recent document items, followed by the index
+const RECENT_PREFIX: &str = "recent:";
+
+/// Everything the tray menu shows that can change
+#[derive(Debug, Clone, PartialEq)]
+struct TrayMenuState {
+    recent: Vec<RecentDocument>,
+    pending_requests: usize,
+    indexing_paused: bool,
+    /// Why background work is paused, if it is
+    paused_for: Option<PauseReason>,
+}
+
+impl TrayMenuState {
+    fn current() -> Self {
+        Self {
+            recent: RECENT_DOCUMENTS.list(),
+            pending_requests: REQUEST_QUEUE.status().pending,
+            indexing_paused: RAG_INDEXER.is_paused(),
+            paused_for: POWER_MONITOR.pause_reasons().first().copied(),
+        }
+    }
+
+    fn sync_label(&self) -> String {
+        match (self.pending_requests, self.paused_for) {
+            (0, _) => "Sync: Up to date".to_string(),
+            (_, Some(PauseReason::BatterySaver)) => "Sync: Paused for battery saver".to_string(),
+            (_, Some(PauseReason::MeteredConnection)) => {
+                "Sync: Paused on metered connection".to_string()
+            }
+            (1, None) => "Sync: 1 request waiting".to_string(),
+            (n, None) => format!("Sync: {} requests waiting", n),
+        }
+    }
+}
+
+lazy_static! {
+    /// What the tray menu currently shows
+    static ref SHOWN: Mutex<Option<TrayMenuState>> = Mutex::new(None);
+}
+
+/// Create the tray icon and its menu
+pub fn create_tray<R: Runtime>(app: &App<R>) -> Result<(), tauri::Error> {
+    let state = TrayMenuState::current();
+    let menu = build_menu(app.handle(), &state)?;
+    *SHOWN.lock().unwrap() = Some(state);
+
+    TrayIconBuilder::with_id(TRAY_ID)
+        .icon(app.default_window_icon().unwrap().clone())
+        .icon_as_template(true)
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(|app, event| handle_tray_menu_event(app, event.id().as_ref()))
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                show_main_window(tray.app_handle());
+            }
+        })
+        .build(app)?;
+    Ok(())
+}
+
+/// Rebuild the tray menu if anything it shows has changed
+pub fn refresh_tray<R: Runtime>(app: &AppHandle<R>) {
+    let state = TrayMenuState::current();
+    let mut shown = SHOWN.lock().unwrap();
+    if shown.as_ref() == Some(&state) {
+        return;
+    }
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+
+    match build_menu(app, &state) {
+        Ok(menu) => {
+            if let Err(e) = tray.set_menu(Some(menu)) {
+                warn!("Failed to update tray menu: {}", e);
+                return;
+            }
+            *shown = Some(state);
+        }
+        Err(e) => warn!("Failed to build tray menu: {}", e),
+    }
+}
+
+fn build_menu<R: Runtime>(
+    app: &AppHandle<R>,
+    state: &TrayMenuState,
+) -> Result<Menu<R>, tauri::Error> {
+    let mut recent = SubmenuBuilder::new(app, "Open Recent");
+    if state.recent.is_empty() {
+        recent = recent.item(
+            &MenuItemBuilder::with_id("no_recent", "No Recent Documents")
+                .enabled(false)
+                .build(app)?,
+        );
+    }
+    for (index, document) in state.recent.iter().enumerate() {
+        recent = recent.item(
+            &MenuItemBuilder::with_id(format!("{}{}", RECENT_PREFIX, index), document.title())
+                .build(app)?,
+        );
+    }
+
+    let indexing_label = if state.indexing_paused {
+        "Resume AI Indexing"
+    } else {
+        "Pause AI Indexing"
+    };
+
+    MenuBuilder::new(app)
+        .item(&MenuItemBuilder::with_id("show", "Show Midlight").build(app)?)
+        .item(&MenuItemBuilder::with_id("new_note", "New Note").build(app)?)
+        .item(&recent.build()?)
+        .separator()
+        .item(
+            &MenuItemBuilder::with_id("sync_status", state.sync_label())
+                .enabled(false)
+                .build(app)?,
+        )
+        .item(&MenuItemBuilder::with_id("toggle_indexing", indexing_label).build(app)?)
+        .separator()
+        .item(&MenuItemBuilder::with_id("quit", "Quit").build(app)?)
+        .build()
+}
+
+fn handle_tray_menu_event<R: Runtime>(app: &AppHandle<R>, event_id: &str) {
+    match event_id {
+        "show" => show_main_window(app),
+        "new_note" => {
+            show_main_window(app);
+            let _ = app.emit("menu:new-document", ());
+        }
+        "toggle_indexing" => {
+            RAG_INDEXER.set_paused(!RAG_INDEXER.is_paused());
+            refresh_tray(app);
+        }
+        "quit" => app.exit(0),
+        id => {
+            let Some(index) = id
+                .strip_prefix(RECENT_PREFIX)
+                .and_then(|index| index.parse::<usize>().ok())
+            else {
+                return;
+            };
+            // Open what the menu showed, even if the list has changed since
+            let document = SHOWN
+                .lock()
+                .unwrap()
+                .as_ref()
+                .and_then(|shown| shown.recent.get(index).cloned());
+            if let Some(document) = document {
+                show_main_window(app);
+                open_document(
+                    app,
+                    OpenDocumentEvent {
+                        path: document.path,
+                        workspace_root: Some(document.workspace_root),
+                    },
+                );
+            }
+        }
+    }
+}
+
+fn show_main_window<R: Runtime>(app: &AppHandle<R>) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
@@ -40,6 +40,23 @@ export interface TiptapMark {
   attrs?: Record<string, unknown>;
 }
 
+/**
+ * How a document is laid out for PDF export. Header and footer templates
+ * fill in {title}, {author}, {date}, {page} and {pages}; `|` separates
+ * their left, center and right parts.
+ */
+export interface PrintLayoutOptions {
+  title?: string;
+  author?: string;
+  header?: string;
+  footer?: string;
+  tableOfContents?: boolean;
+  /** Deepest heading level listed in the table of contents */
+  tocDepth?: number;
+  /** Start a new page at \pagebreak / \newpage markers */
+  honorPageBreaks?: boolean;
+}
+
 // ============================================================================
 // Export Client
 // ============================================================================
@@ -65,12 +82,49 @@ class ExportClient {
     });
   }
 
+  /**
+   * Lays the document out for printing, returning HTML with its own stylesheet
+   */
+  async getPrintLayout(
+    content: TiptapDocument,
+    options?: PrintLayoutOptions,
+    workspaceRoot?: string
+  ): Promise<string> {
+    return invoke<string>('export_print_layout', { content, options, workspaceRoot });
+  }
+
   /**
    * Exports the document to PDF using the system print dialog
-   * This uses the webview's native print functionality
+   * This uses the webview's native print functionality. Given the document,
+   * its print layout is printed in place of the editor.
    */
-  async exportToPdf(): Promise<boolean> {
-    return invoke<boolean>('export_pdf');
+  async exportToPdf(
+    content?: TiptapDocument,
+    options?: PrintLayoutOptions,
+    workspaceRoot?: string
+  ): Promise<boolean> {
+    if (!content) {
+      return invoke<boolean>('export_pdf');
+    }
+
+    const container = document.createElement('div');
+    container.className = 'print-layout';
+    container.innerHTML = await this.getPrintLayout(content, options, workspaceRoot);
+    document.body.appendChild(container);
+    document.body.classList.add('printing-layout');
+
+    const cleanUp = () => {
+      document.body.classList.remove('printing-layout');
+      container.remove();
+    };
+    window.addEventListener('afterprint', cleanUp, { once: true });
+    try {
+      return await invoke<boolean>('export_pdf');
+    } catch (e) {
+      window.removeEventListener('afterprint', cleanUp);
+      cleanUp();
+      throw e;
+    }
   }
 
   /**
@@ -89,12 +143,13 @@ class ExportClient {
     content: TiptapDocument,
     documentName: string,
     exportType: ExportType,
-    onProgress?: (progress: ExportProgress) => void
+    onProgress?: (progress: ExportProgress) => void,
+    pdfOptions?: PrintLayoutOptions
   ): Promise<ExportResult> {
     // For PDF, use the print dialog
     if (exportType === 'pdf') {
       try {
-        await this.exportToPdf();
+        await this.exportToPdf(content, { title: documentName, ...pdfOptions });
         return {
           success: true,
           path: null,
@@ -22,6 +22,15 @@ export interface ImportFileInfo {
   hasFrontMatter: boolean;
   hasCallouts: boolean;
   hasDataview: boolean;
+  /** Why the front matter couldn't be read */
+  frontMatterError?: YamlError;
+}
+
+export interface YamlError {
+  kind: 'syntax' | 'tooLarge' | 'tooDeep' | 'tooManyAliases' | 'tooManyNodes';
+  message: string;
+  line: number | null;
+  column: number | null;
 }
 
 export interface AccessWarning {
@@ -56,6 +65,8 @@ export interface ImportOptions {
   preserveFolderStructure: boolean;
   skipEmptyPages: boolean;
   createMidlightFiles: boolean;
+  /** Download images linked from the web and point the links at the copies */
+  fetchRemoteImages: boolean;
 }
 
 export type UntitledHandling = 'number' | 'keep' | 'prompt';
@@ -63,6 +74,8 @@ export type UntitledHandling = 'number' | 'keep' | 'prompt';
 export interface NotionImportOptions extends ImportOptions {
   removeUuids: boolean;
   convertCsvToTables: boolean;
+  /** Import databases as sortable table documents instead of Markdown tables */
+  csvAsTableDocuments: boolean;
   untitledHandling: UntitledHandling;
 }
 
@@ -94,6 +107,16 @@ export interface ImportResult {
   attachmentsCopied: number;
   errors: ImportErrorInfo[];
   warnings: ImportWarningInfo[];
+  /** Manifest the import can be repeated from */
+  manifestPath?: string;
+}
+
+export interface ImportManifestInfo {
+  path: string;
+  sourceType: ImportSourceType;
+  sourcePath: string;
+  importedAt: string;
+  files: number;
 }
 
 // ============================================================================
@@ -155,12 +178,14 @@ export const defaultImportOptions: ImportOptions = {
   preserveFolderStructure: true,
   skipEmptyPages: true,
   createMidlightFiles: true,
+  fetchRemoteImages: false,
 };
 
 export const defaultNotionOptions: NotionImportOptions = {
   ...defaultImportOptions,
   removeUuids: true,
   convertCsvToTables: true,
+  csvAsTableDocuments: false,
   untitledHandling: 'number',
 };
 
@@ -227,6 +252,20 @@ class ImportClient {
     });
   }
 
+  /**
+   * Re-run an earlier import, importing only new or changed source files
+   */
+  async repeatImport(manifestPath: string): Promise<ImportResult> {
+    return invoke<ImportResult>('import_repeat', { manifestPath });
+  }
+
+  /**
+   * Earlier imports into a folder, most recent first
+   */
+  async listManifests(destPath: string): Promise<ImportManifestInfo[]> {
+    return invoke<ImportManifestInfo[]>('import_list_manifests', { destPath });
+  }
+
   /**
    * Cancel an active import
    */
@@ -9,6 +9,7 @@ import type {
   LoadedDocument,
   SaveResult,
   CheckpointTrigger,
+  CheckpointAttribution,
 } from '@midlight/core/types';
 
 export class TauriStorageAdapter implements StorageAdapter {
@@ -63,13 +64,15 @@ export class TauriStorageAdapter implements StorageAdapter {
     workspaceRoot: string,
     filePath: string,
     json: TiptapDocument,
-    trigger: CheckpointTrigger
+    trigger: CheckpointTrigger,
+    attribution?: CheckpointAttribution
   ): Promise<SaveResult> {
     return await invoke('workspace_save_document', {
       workspaceRoot,
       filePath,
       json,
       trigger,
+      attribution,
     });
   }
 
@@ -97,3 +100,288 @@ export class TauriStorageAdapter implements StorageAdapter {
     });
   }
 }
+
+export interface FileChunk {
+  data: string;
+  offset: number;
+  nextOffset: number;
+  size: number;
+  modified: number | null;
+  done: boolean;
+}
+
+/**
+ * Read a file a chunk at a time. The next chunk is only requested once
+ * `onChunk` has finished with the last, so a large file never crosses IPC
+ * in one message.
+ */
+export async function readFileChunks(
+  path: string,
+  encoding: 'utf8' | 'base64',
+  onChunk: (chunk: FileChunk) => void | Promise<void>
+): Promise<void> {
+  let offset = 0;
+  for (;;) {
+    const chunk = await invoke<FileChunk>('read_file_chunk', { path, offset, encoding });
+    await onChunk(chunk);
+    if (chunk.done) return;
+    offset = chunk.nextOffset;
+  }
+}
+
+/** Read a file as base64 in chunks */
+export async function readFileBase64(path: string): Promise<string> {
+  const parts: string[] = [];
+  await readFileChunks(path, 'base64', (chunk) => {
+    parts.push(chunk.data);
+  });
+  return parts.join('');
+}
+
+/** A file or folder from the workspace's tree cache */
+export interface CachedEntry {
+  /** Path relative to the workspace root, with forward slashes */
+  path: string;
+  name: string;
+  isDir: boolean;
+  size: number;
+  modified: number;
+  title: string | null;
+  wordCount: number | null;
+}
+
+/**
+ * List a workspace's files from its tree cache, or the children of `parent`.
+ * The cache is caught up with the disk in the background and
+ * `fs:tree-cache-updated` fires if anything changed.
+ */
+export async function cachedTree(workspaceRoot: string, parent?: string): Promise<CachedEntry[]> {
+  return await invoke('fs_cached_tree', { workspaceRoot, parent });
+}
+
+/** What a workspace archive holds besides the documents */
+export interface ArchiveContents {
+  images: boolean;
+  checkpoints: boolean;
+  settings: boolean;
+  indexes: boolean;
+}
+
+export interface ArchiveManifest {
+  format: string;
+  version: number;
+  appVersion: string;
+  createdAt: string;
+  workspaceName: string;
+  contents: ArchiveContents;
+  files: number;
+  bytes: number;
+}
+
+/** Which documents a workspace export takes; leave it out to export everything */
+export interface ExportFilter {
+  /** Workspace-relative folders; empty for all of them */
+  folders?: string[];
+  /** Only documents with one of these tags; empty for any */
+  tags?: string[];
+  /** Leave out documents with `private: true` or the #private tag */
+  excludePrivate?: boolean;
+  /** Only files and images the exported documents link to or embed */
+  referencedAssetsOnly?: boolean;
+}
+
+/**
+ * Pack a workspace into a .midlightpkg archive. A filtered archive has no
+ * checkpoints, settings or indexes.
+ */
+export async function exportWorkspaceArchive(
+  workspaceRoot: string,
+  outputPath: string,
+  contents?: Partial<ArchiveContents>,
+  filter?: ExportFilter
+): Promise<ArchiveManifest> {
+  return await invoke('workspace_export_archive', {
+    workspaceRoot,
+    outputPath,
+    contents,
+    filter,
+  });
+}
+
+/** Unpack a .midlightpkg archive into a new or empty folder */
+export async function importWorkspaceArchive(
+  archivePath: string,
+  targetPath: string
+): Promise<ArchiveManifest> {
+  return await invoke('workspace_import_archive', { archivePath, targetPath });
+}
+
+export interface ObsidianExportResult {
+  vaultPath: string;
+  documents: number;
+  filesCopied: number;
+  attachments: number;
+  linksConverted: number;
+  /** Documents the export filter left out */
+  excluded: number;
+  warnings: string[];
+}
+
+/** Write the workspace out as an Obsidian vault in a new or empty folder */
+export async function exportObsidianVault(
+  workspaceRoot: string,
+  outputPath: string,
+  filter?: ExportFilter
+): Promise<ObsidianExportResult> {
+  return await invoke('export_to_obsidian', { workspaceRoot, outputPath, filter });
+}
+
+export interface OperationProgress {
+  id: number;
+  kind: 'import' | 'export' | 'indexing' | 'sync';
+  label: string;
+  phase: string;
+  current: number;
+  total: number;
+  message: string | null;
+  state: 'running' | 'completed' | 'failed' | 'cancelled';
+  error: string | null;
+}
+
+/** Long-running operations in progress; updates arrive as "operation:progress" events */
+export async function listOperations(): Promise<OperationProgress[]> {
+  return await invoke('operation_list');
+}
+
+/** Cancel a running import, export or indexing operation */
+export async function cancelOperation(id: number): Promise<void> {
+  return await invoke('operation_cancel', { id });
+}
+
+export interface TableColumn {
+  name: string;
+  type: 'text' | 'number' | 'checkbox' | 'date' | 'url';
+}
+
+export type TableCell = string | number | boolean | null;
+
+export interface TableQuery {
+  sortColumn?: number;
+  descending?: boolean;
+  search?: string;
+  offset?: number;
+  limit?: number;
+}
+
+export interface TablePage {
+  title: string;
+  columns: TableColumn[];
+  rows: TableCell[][];
+  total: number;
+}
+
+/** A page of a .midtable document's rows, sorted and filtered by the backend */
+export async function queryTable(path: string, query?: TableQuery): Promise<TablePage> {
+  return await invoke('table_query', { path, query });
+}
+
+export interface DocumentVariant {
+  name: string;
+  baseCheckpointId: string | null;
+  baseHash: string;
+  contentHash: string;
+  created: string;
+  modified: string;
+}
+
+export interface VariantDiffLine {
+  op: 'equal' | 'added' | 'removed';
+  text: string;
+}
+
+export interface VariantMergePreview {
+  insertions: number;
+  deletions: number;
+  hunks: { oldStart: number; newStart: number; lines: VariantDiffLine[] }[];
+  /** The document was edited after the variant was created */
+  documentChanged: boolean;
+}
+
+/** Start a named variant of a document ("try a different ending") */
+export async function createVariant(
+  workspaceRoot: string,
+  filePath: string,
+  name: string
+): Promise<DocumentVariant> {
+  return await invoke('create_variant', { workspaceRoot, filePath, name });
+}
+
+export async function getVariants(
+  workspaceRoot: string,
+  filePath: string
+): Promise<DocumentVariant[]> {
+  return await invoke('get_variants', { workspaceRoot, filePath });
+}
+
+export async function loadVariant(
+  workspaceRoot: string,
+  filePath: string,
+  name: string
+): Promise<TiptapDocument> {
+  return await invoke('load_variant', { workspaceRoot, filePath, name });
+}
+
+export async function saveVariant(
+  workspaceRoot: string,
+  filePath: string,
+  name: string,
+  json: TiptapDocument
+): Promise<DocumentVariant> {
+  return await invoke('save_variant', { workspaceRoot, filePath, name, json });
+}
+
+/** What merging a variant would change in its document */
+export async function previewVariantMerge(
+  workspaceRoot: string,
+  filePath: string,
+  name: string,
+  context?: number
+): Promise<VariantMergePreview> {
+  return await invoke('preview_variant_merge', { workspaceRoot, filePath, name, context });
+}
+
+/** Replace the document with the variant; the document is bookmarked first */
+export async function mergeVariant(
+  workspaceRoot: string,
+  filePath: string,
+  name: string
+): Promise<SaveResult> {
+  return await invoke('merge_variant', { workspaceRoot, filePath, name });
+}
+
+export async function discardVariant(
+  workspaceRoot: string,
+  filePath: string,
+  name: string
+): Promise<void> {
+  return await invoke('discard_variant', { workspaceRoot, filePath, name });
+}
+
+export interface EditingSession {
+  started: string;
+  ended: string;
+  durationSeconds: number;
+  /** Words at the end of the session less words at its start */
+  wordDelta: number;
+  wordCount: number;
+  checkpoints: Checkpoint[];
+}
+
+/** A document's checkpoints grouped into editing sessions, oldest first */
+export async function getTimeline(
+  workspaceRoot: string,
+  filePath: string,
+  gapMinutes?: number
+): Promise<EditingSession[]> {
+  return await invoke('versions_get_timeline', { workspaceRoot, filePath, gapMinutes });
+}
@@ -129,6 +129,14 @@ export interface Checkpoint {
   description?: string;
   stats: CheckpointStats;
   trigger: CheckpointTrigger;
+  /** Set when the checkpoint was taken around an AI edit */
+  attribution?: CheckpointAttribution;
+}
+
+export interface CheckpointAttribution {
+  conversationId?: string;
+  tool?: string;
+  changeId?: string;
 }
 
 export interface CheckpointStats {
@@ -144,6 +152,8 @@ export type CheckpointTrigger =
   | 'file_close'
   | 'bookmark'
   | 'before_restore'
+  | 'before_ai_edit'
+  | 'ai_edit'
   | 'manual';
 
 export interface CheckpointHistory {
@@ -279,7 +289,8 @@ export interface StorageAdapter {
     workspaceRoot: string,
     filePath: string,
     json: TiptapDocument,
-    trigger: CheckpointTrigger
+    trigger: CheckpointTrigger,
+    attribution?: CheckpointAttribution
   ): Promise<SaveResult>;
 
   // Workspace operations
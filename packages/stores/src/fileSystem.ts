@@ -651,12 +651,17 @@ function createFileSystemStore() {
       if (!staged || !storageAdapter || !state.rootDir) return null;
 
       try {
-        // Write the staged content to disk
+        // Write the staged content to disk, checkpointed as the AI's edit
         await storageAdapter.saveDocument(
           state.rootDir,
           staged.path,
           staged.stagedTiptapJson,
-          'manual'
+          'ai_edit',
+          {
+            conversationId: staged.conversationId,
+            tool: 'edit_document',
+            changeId: staged.changeId,
+          }
         );
 
         // Capture staged info before clearing (for annotation application)